@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS user_api_tokens_user_idx;
+DROP TABLE IF EXISTS user_api_tokens;
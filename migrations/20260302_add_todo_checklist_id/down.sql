@@ -0,0 +1,2 @@
+-- SQLite down migration intentionally left as no-op for additive checklist_id column.
+SELECT 1;
@@ -0,0 +1 @@
+ALTER TABLE todo_items ADD COLUMN checklist_id INTEGER;
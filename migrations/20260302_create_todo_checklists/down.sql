@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS todo_checklist_runs_checklist_idx;
+DROP TABLE IF EXISTS todo_checklist_runs;
+DROP INDEX IF EXISTS todo_checklists_next_reset_idx;
+DROP TABLE IF EXISTS todo_checklists;
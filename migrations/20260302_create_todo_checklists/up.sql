@@ -0,0 +1,28 @@
+CREATE TABLE IF NOT EXISTS todo_checklists (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    items_json TEXT NOT NULL,
+    schedule TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    last_reset_at INTEGER,
+    next_reset_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS todo_checklists_next_reset_idx
+ON todo_checklists (next_reset_at);
+
+CREATE TABLE IF NOT EXISTS todo_checklist_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    checklist_id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    reset_at INTEGER NOT NULL,
+    total_items INTEGER NOT NULL,
+    completed_items INTEGER NOT NULL,
+    completed_titles TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS todo_checklist_runs_checklist_idx
+ON todo_checklist_runs (checklist_id, reset_at);
@@ -0,0 +1,2 @@
+-- SQLite down migration leaves the additive target_ref column in place.
+DROP INDEX IF EXISTS idx_reminders_user_target;
@@ -0,0 +1,4 @@
+ALTER TABLE reminders ADD COLUMN target_ref TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_reminders_user_target
+    ON reminders(user_id, target_ref);
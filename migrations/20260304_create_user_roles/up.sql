@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    role TEXT NOT NULL,
+    granted_by TEXT,
+    updated_at BIGINT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- SQLite down migration intentionally left as no-op for additive delivery window columns.
+SELECT 1;
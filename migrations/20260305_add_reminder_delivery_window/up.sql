@@ -0,0 +1,2 @@
+ALTER TABLE reminders ADD COLUMN delivery_window TEXT;
+ALTER TABLE reminders ADD COLUMN held_until BIGINT;
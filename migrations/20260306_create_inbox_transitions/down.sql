@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS inbox_transitions_user_created_idx;
+DROP TABLE IF EXISTS inbox_transitions;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS inbox_transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    origin_ref TEXT NOT NULL,
+    from_status TEXT NOT NULL,
+    to_status TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS inbox_transitions_user_created_idx
+ON inbox_transitions (user_id, created_at);
@@ -0,0 +1,4 @@
+DROP INDEX IF EXISTS audit_events_origin_created_idx;
+DROP INDEX IF EXISTS audit_events_type_created_idx;
+DROP INDEX IF EXISTS audit_events_user_created_idx;
+DROP TABLE IF EXISTS audit_events;
@@ -0,0 +1,21 @@
+CREATE TABLE IF NOT EXISTS audit_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    actor TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    status TEXT NOT NULL,
+    origin_ref TEXT,
+    severity TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_user_created_idx
+ON audit_events (user_id, created_at);
+
+CREATE INDEX IF NOT EXISTS audit_events_type_created_idx
+ON audit_events (event_type, created_at);
+
+CREATE INDEX IF NOT EXISTS audit_events_origin_created_idx
+ON audit_events (origin_ref, created_at);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS plan_step_dependencies_trash;
+DROP TABLE IF EXISTS plans_trash;
+DROP TABLE IF EXISTS scheduled_tasks_trash;
+DROP TABLE IF EXISTS reminders_trash;
+DROP TABLE IF EXISTS todo_items_trash;
@@ -0,0 +1,90 @@
+CREATE TABLE IF NOT EXISTS todo_items_trash (
+    id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    notes TEXT,
+    position INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    completed_at INTEGER,
+    t_shirt_size TEXT,
+    story_points INTEGER,
+    estimate_optimistic_minutes INTEGER,
+    estimate_likely_minutes INTEGER,
+    estimate_pessimistic_minutes INTEGER,
+    dependency_refs TEXT,
+    checklist_id INTEGER,
+    trash_batch TEXT NOT NULL,
+    trashed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS todo_items_trash_batch_idx
+ON todo_items_trash (user_id, trash_batch);
+
+CREATE TABLE IF NOT EXISTS reminders_trash (
+    id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    due_at BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    completed_at BIGINT,
+    fired_at BIGINT,
+    target_ref TEXT,
+    delivery_window TEXT,
+    held_until BIGINT,
+    trash_batch TEXT NOT NULL,
+    trashed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS reminders_trash_batch_idx
+ON reminders_trash (user_id, trash_batch);
+
+CREATE TABLE IF NOT EXISTS scheduled_tasks_trash (
+    id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    run_at INTEGER NOT NULL,
+    interval_minutes INTEGER,
+    enabled BOOLEAN NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    last_run_at INTEGER,
+    next_run_at INTEGER NOT NULL,
+    trash_batch TEXT NOT NULL,
+    trashed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_tasks_trash_batch_idx
+ON scheduled_tasks_trash (user_id, trash_batch);
+
+CREATE TABLE IF NOT EXISTS plans_trash (
+    id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    goal TEXT NOT NULL,
+    steps_json TEXT,
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    trash_batch TEXT NOT NULL,
+    trashed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS plans_trash_batch_idx
+ON plans_trash (user_id, trash_batch);
+
+CREATE TABLE IF NOT EXISTS plan_step_dependencies_trash (
+    id INTEGER NOT NULL,
+    plan_id INTEGER NOT NULL,
+    user_id TEXT NOT NULL,
+    step_ref TEXT NOT NULL,
+    depends_on_ref TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    trash_batch TEXT NOT NULL,
+    trashed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS plan_step_dependencies_trash_batch_idx
+ON plan_step_dependencies_trash (user_id, trash_batch);
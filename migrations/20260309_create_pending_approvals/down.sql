@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS pending_approvals_user_status_idx;
+DROP TABLE IF EXISTS pending_approvals;
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS pending_approvals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    capability TEXT NOT NULL,
+    args TEXT NOT NULL,
+    status TEXT NOT NULL,
+    result TEXT,
+    created_at INTEGER NOT NULL,
+    decided_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS pending_approvals_user_status_idx
+ON pending_approvals (user_id, status, created_at);
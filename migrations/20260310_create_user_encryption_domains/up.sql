@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS user_encryption_domains (
+    user_id TEXT PRIMARY KEY NOT NULL,
+    wrapped_key BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,7 @@
+DROP INDEX IF EXISTS plan_labels_label_idx;
+DROP INDEX IF EXISTS reminder_labels_label_idx;
+DROP INDEX IF EXISTS todo_labels_label_idx;
+DROP TABLE IF EXISTS plan_labels;
+DROP TABLE IF EXISTS reminder_labels;
+DROP TABLE IF EXISTS todo_labels;
+DROP TABLE IF EXISTS labels;
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS labels (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS todo_labels (
+    todo_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    PRIMARY KEY (todo_id, label_id)
+);
+
+CREATE TABLE IF NOT EXISTS reminder_labels (
+    reminder_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    PRIMARY KEY (reminder_id, label_id)
+);
+
+CREATE TABLE IF NOT EXISTS plan_labels (
+    plan_id INTEGER NOT NULL,
+    label_id INTEGER NOT NULL,
+    PRIMARY KEY (plan_id, label_id)
+);
+
+CREATE INDEX IF NOT EXISTS todo_labels_label_idx ON todo_labels (label_id);
+CREATE INDEX IF NOT EXISTS reminder_labels_label_idx ON reminder_labels (label_id);
+CREATE INDEX IF NOT EXISTS plan_labels_label_idx ON plan_labels (label_id);
@@ -0,0 +1,9 @@
+CREATE VIRTUAL TABLE IF NOT EXISTS work_search USING fts5(
+    title,
+    body,
+    user_id UNINDEXED,
+    kind UNINDEXED,
+    item_id UNINDEXED,
+    updated_at UNINDEXED,
+    digest UNINDEXED
+);
@@ -0,0 +1,2 @@
+ALTER TABLE reminders ADD COLUMN escalation_level INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE reminders ADD COLUMN escalated_at BIGINT;
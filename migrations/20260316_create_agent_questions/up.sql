@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS agent_questions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    question TEXT NOT NULL,
+    blocks_ref TEXT,
+    status TEXT NOT NULL DEFAULT 'open',
+    answer TEXT,
+    awaiting_reply INTEGER NOT NULL DEFAULT 1,
+    asked_at BIGINT NOT NULL,
+    seen_at BIGINT,
+    answered_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_agent_questions_user_status ON agent_questions(user_id, status);
@@ -0,0 +1,14 @@
+CREATE TABLE IF NOT EXISTS external_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    origin_ref TEXT NOT NULL,
+    source TEXT NOT NULL,
+    title TEXT NOT NULL,
+    details TEXT,
+    url TEXT,
+    priority TEXT NOT NULL DEFAULT 'normal',
+    status TEXT NOT NULL DEFAULT 'open',
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_external_items_ref ON external_items(user_id, origin_ref);
@@ -0,0 +1,2 @@
+-- SQLite down migration intentionally left as no-op for additive origin_ref column.
+DROP INDEX IF EXISTS idx_todo_items_origin_ref;
@@ -0,0 +1,3 @@
+ALTER TABLE todo_items ADD COLUMN origin_ref TEXT;
+ALTER TABLE todo_items_trash ADD COLUMN origin_ref TEXT;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_todo_items_origin_ref ON todo_items(user_id, origin_ref);
@@ -0,0 +1,19 @@
+CREATE TABLE IF NOT EXISTS approval_steps (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    approval_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    approvers TEXT NOT NULL,
+    status TEXT NOT NULL,
+    timeout_secs BIGINT,
+    on_timeout TEXT NOT NULL,
+    due_at BIGINT,
+    announced_at BIGINT,
+    decided_by TEXT,
+    decided_at BIGINT
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS approval_steps_position_idx
+ON approval_steps (approval_id, position);
+
+CREATE INDEX IF NOT EXISTS approval_steps_status_due_idx
+ON approval_steps (status, due_at);
@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS todo_estimate_samples_category_idx;
+DROP INDEX IF EXISTS todo_estimate_samples_todo_idx;
+DROP TABLE IF EXISTS todo_estimate_samples;
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS todo_estimate_samples (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    todo_id INTEGER NOT NULL,
+    category TEXT NOT NULL,
+    baseline_minutes INTEGER NOT NULL,
+    estimated_minutes INTEGER NOT NULL,
+    actual_minutes INTEGER NOT NULL,
+    completed_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS todo_estimate_samples_todo_idx
+ON todo_estimate_samples (user_id, todo_id);
+
+CREATE INDEX IF NOT EXISTS todo_estimate_samples_category_idx
+ON todo_estimate_samples (user_id, category, completed_at);
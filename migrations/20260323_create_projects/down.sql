@@ -0,0 +1,6 @@
+-- SQLite down migration leaves the additive project_id columns in place.
+DROP INDEX IF EXISTS idx_reminders_project;
+DROP INDEX IF EXISTS idx_scheduled_tasks_project;
+DROP INDEX IF EXISTS idx_plans_project;
+DROP INDEX IF EXISTS idx_todo_items_project;
+DROP TABLE IF EXISTS projects;
@@ -0,0 +1,23 @@
+CREATE TABLE IF NOT EXISTS projects (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    UNIQUE (user_id, name)
+);
+
+ALTER TABLE todo_items ADD COLUMN project_id INTEGER;
+ALTER TABLE todo_items_trash ADD COLUMN project_id INTEGER;
+ALTER TABLE plans ADD COLUMN project_id INTEGER;
+ALTER TABLE plans_trash ADD COLUMN project_id INTEGER;
+ALTER TABLE scheduled_tasks ADD COLUMN project_id INTEGER;
+ALTER TABLE scheduled_tasks_trash ADD COLUMN project_id INTEGER;
+ALTER TABLE reminders ADD COLUMN project_id INTEGER;
+ALTER TABLE reminders_trash ADD COLUMN project_id INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_todo_items_project ON todo_items (user_id, project_id);
+CREATE INDEX IF NOT EXISTS idx_plans_project ON plans (user_id, project_id);
+CREATE INDEX IF NOT EXISTS idx_scheduled_tasks_project ON scheduled_tasks (user_id, project_id);
+CREATE INDEX IF NOT EXISTS idx_reminders_project ON reminders (user_id, project_id);
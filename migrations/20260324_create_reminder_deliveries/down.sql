@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS idx_reminder_dead_letters_user;
+DROP TABLE IF EXISTS reminder_dead_letters;
+DROP INDEX IF EXISTS idx_reminder_deliveries_due;
+DROP INDEX IF EXISTS idx_reminder_deliveries_channel;
+DROP TABLE IF EXISTS reminder_deliveries;
@@ -0,0 +1,29 @@
+CREATE TABLE IF NOT EXISTS reminder_deliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    reminder_id INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    title TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at BIGINT NOT NULL,
+    last_error TEXT,
+    created_at BIGINT NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_reminder_deliveries_channel
+    ON reminder_deliveries(reminder_id, channel);
+CREATE INDEX IF NOT EXISTS idx_reminder_deliveries_due
+    ON reminder_deliveries(next_attempt_at);
+
+CREATE TABLE IF NOT EXISTS reminder_dead_letters (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    reminder_id INTEGER NOT NULL,
+    channel TEXT NOT NULL,
+    title TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    last_error TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    failed_at BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_reminder_dead_letters_user
+    ON reminder_dead_letters(user_id, failed_at);
@@ -0,0 +1,2 @@
+-- SQLite down migration leaves the additive priority columns in place.
+DROP INDEX IF EXISTS idx_reminders_fired_priority;
@@ -0,0 +1,3 @@
+ALTER TABLE reminders ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';
+ALTER TABLE reminders_trash ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal';
+CREATE INDEX IF NOT EXISTS idx_reminders_fired_priority ON reminders (priority, fired_at);
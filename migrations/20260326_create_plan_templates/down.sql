@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_plan_templates_user_name;
+DROP TABLE IF EXISTS plan_templates;
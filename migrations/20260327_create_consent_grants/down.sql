@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_consent_grants_user_capability;
+DROP TABLE IF EXISTS consent_grants;
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS consent_grants (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    tool TEXT NOT NULL,
+    capability TEXT NOT NULL,
+    scope TEXT NOT NULL,
+    status TEXT NOT NULL,
+    requested_at BIGINT NOT NULL,
+    decided_at BIGINT,
+    expires_at BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_consent_grants_user_capability
+    ON consent_grants(user_id, capability, scope);
@@ -0,0 +1,2 @@
+-- SQLite down migration leaves the additive run option columns in place.
+SELECT 1;
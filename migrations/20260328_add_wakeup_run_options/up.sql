@@ -0,0 +1,4 @@
+ALTER TABLE wakeup_tasks ADD COLUMN jitter_seconds INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE wakeup_tasks ADD COLUMN max_concurrency INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE wakeup_tasks ADD COLUMN max_backoff_minutes INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE wakeup_tasks ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS scheduler_jobs (
+    name TEXT PRIMARY KEY NOT NULL,
+    last_run_at BIGINT,
+    next_run_at BIGINT NOT NULL,
+    last_error TEXT,
+    run_count BIGINT NOT NULL DEFAULT 0,
+    updated_at BIGINT NOT NULL
+);
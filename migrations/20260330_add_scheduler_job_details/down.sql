@@ -0,0 +1,2 @@
+-- SQLite down migration leaves the additive job detail columns in place.
+SELECT 1;
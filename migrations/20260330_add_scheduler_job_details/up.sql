@@ -0,0 +1,3 @@
+ALTER TABLE scheduler_jobs ADD COLUMN interval_seconds BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE scheduler_jobs ADD COLUMN last_duration_ms BIGINT;
+ALTER TABLE scheduler_jobs ADD COLUMN registered_at BIGINT NOT NULL DEFAULT 0;
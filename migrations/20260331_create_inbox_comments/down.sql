@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS inbox_comments_user_ref_idx;
+DROP TABLE IF EXISTS inbox_comments;
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS inbox_comments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    origin_ref TEXT NOT NULL,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS inbox_comments_user_ref_idx
+ON inbox_comments (user_id, origin_ref, created_at);
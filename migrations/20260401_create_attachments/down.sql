@@ -0,0 +1,3 @@
+DROP INDEX IF EXISTS attachments_sha256_idx;
+DROP INDEX IF EXISTS attachments_user_ref_idx;
+DROP TABLE IF EXISTS attachments;
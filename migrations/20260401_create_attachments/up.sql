@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id TEXT NOT NULL,
+    origin_ref TEXT NOT NULL,
+    name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS attachments_user_ref_idx
+ON attachments (user_id, origin_ref);
+
+CREATE INDEX IF NOT EXISTS attachments_sha256_idx
+ON attachments (sha256);
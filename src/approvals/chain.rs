@@ -0,0 +1,567 @@
+//! Approval chains: a parked call that needs several people, one step at
+//! a time.
+//!
+//! Each step lists who may approve it and any one of them is enough. Only
+//! the current step can be decided, nobody approves two steps of the same
+//! chain, and a rejection anywhere ends it. A step left undecided past its
+//! timeout is rejected or, with [`TimeoutAction::Skip`], handed on to the
+//! next step; the last step is never skipped.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use super::schema::{approval_steps, pending_approvals};
+use super::{map_row, ApprovalStatus, ApprovalStore, PendingApproval, PendingApprovalRow};
+use crate::error::{ButterflyBotError, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    /// Behind an earlier step that is still undecided.
+    Waiting,
+    Pending,
+    Approved,
+    Rejected,
+    TimedOut,
+}
+
+impl StepStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StepStatus::Waiting => "waiting",
+            StepStatus::Pending => "pending",
+            StepStatus::Approved => "approved",
+            StepStatus::Rejected => "rejected",
+            StepStatus::TimedOut => "timed_out",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "waiting" => Some(StepStatus::Waiting),
+            "pending" => Some(StepStatus::Pending),
+            "approved" => Some(StepStatus::Approved),
+            "rejected" => Some(StepStatus::Rejected),
+            "timed_out" => Some(StepStatus::TimedOut),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    #[default]
+    Reject,
+    /// Move on to the next step; rejects when there is none.
+    Skip,
+}
+
+impl TimeoutAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimeoutAction::Reject => "reject",
+            TimeoutAction::Skip => "skip",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(TimeoutAction::Reject),
+            "skip" => Some(TimeoutAction::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// One step of a chain as requested when parking a call.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChainStep {
+    pub approvers: Vec<String>,
+    pub timeout_secs: Option<i64>,
+    pub on_timeout: TimeoutAction,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ApprovalStep {
+    pub id: i32,
+    pub approval_id: i32,
+    pub position: i32,
+    pub approvers: Vec<String>,
+    pub status: StepStatus,
+    pub timeout_secs: Option<i64>,
+    pub on_timeout: TimeoutAction,
+    /// When the pending step times out.
+    pub due_at: Option<i64>,
+    /// When its approvers were told about it.
+    pub announced_at: Option<i64>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<i64>,
+}
+
+/// What deciding (or timing out) the current step did to the chain.
+#[derive(Clone, Debug, PartialEq)]
+pub enum StepOutcome {
+    /// The next step is now pending.
+    Advanced(ApprovalStep),
+    /// The last step was approved; the call may run.
+    Completed,
+    /// The chain is over and the call must not run.
+    Rejected,
+    /// The actor may not decide the current step.
+    NotAnApprover(ApprovalStep),
+    /// No step is pending, e.g. someone else decided first.
+    Closed,
+}
+
+#[derive(Queryable)]
+struct ApprovalStepRow {
+    id: i32,
+    approval_id: i32,
+    position: i32,
+    approvers: String,
+    status: String,
+    timeout_secs: Option<i64>,
+    on_timeout: String,
+    due_at: Option<i64>,
+    announced_at: Option<i64>,
+    decided_by: Option<String>,
+    decided_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = approval_steps)]
+struct NewApprovalStep<'a> {
+    approval_id: i32,
+    position: i32,
+    approvers: String,
+    status: &'a str,
+    timeout_secs: Option<i64>,
+    on_timeout: &'a str,
+    due_at: Option<i64>,
+}
+
+impl ApprovalStore {
+    /// Parks a call behind `steps`, the first of which is pending at once.
+    pub async fn park_chain(
+        &self,
+        user_id: &str,
+        tool: &str,
+        capability: &str,
+        args: &serde_json::Value,
+        steps: &[ChainStep],
+    ) -> Result<PendingApproval> {
+        let approval = self.park(user_id, tool, capability, args).await?;
+        let now = self.clock.now();
+        let rows: Vec<NewApprovalStep<'_>> = steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| NewApprovalStep {
+                approval_id: approval.id,
+                position: index as i32,
+                approvers: serde_json::to_string(&step.approvers).unwrap_or_default(),
+                status: if index == 0 {
+                    StepStatus::Pending.as_str()
+                } else {
+                    StepStatus::Waiting.as_str()
+                },
+                timeout_secs: step.timeout_secs,
+                on_timeout: step.on_timeout.as_str(),
+                due_at: (index == 0)
+                    .then_some(step.timeout_secs)
+                    .flatten()
+                    .map(|secs| now + secs),
+            })
+            .collect();
+        if !rows.is_empty() {
+            let mut conn = self.conn().await?;
+            diesel::insert_into(approval_steps::table)
+                .values(&rows)
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+        Ok(approval)
+    }
+
+    /// The chain behind `approval_id`, first step first; empty for a plain
+    /// approval.
+    pub async fn steps(&self, approval_id: i32) -> Result<Vec<ApprovalStep>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ApprovalStepRow> = approval_steps::table
+            .filter(approval_steps::approval_id.eq(approval_id))
+            .order(approval_steps::position.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_step).collect())
+    }
+
+    /// An approval `actor` may see: their own, or one they are asked to
+    /// approve somewhere in its chain.
+    pub async fn get_for_actor(&self, actor: &str, id: i32) -> Result<Option<PendingApproval>> {
+        let Some(approval) = self.find(id).await? else {
+            return Ok(None);
+        };
+        if approval.user_id == actor
+            || self
+                .steps(id)
+                .await?
+                .iter()
+                .any(|step| step.approvers.iter().any(|approver| approver == actor))
+        {
+            return Ok(Some(approval));
+        }
+        Ok(None)
+    }
+
+    /// Other users' undecided calls whose current step lists `approver`,
+    /// oldest first, with that step.
+    pub async fn list_awaiting(
+        &self,
+        approver: &str,
+        limit: usize,
+    ) -> Result<Vec<(PendingApproval, ApprovalStep)>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ApprovalStepRow> = approval_steps::table
+            .filter(approval_steps::status.eq(StepStatus::Pending.as_str()))
+            .order(approval_steps::approval_id.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let steps: Vec<ApprovalStep> = rows
+            .into_iter()
+            .map(map_step)
+            .filter(|step| step.approvers.iter().any(|name| name == approver))
+            .collect();
+        let ids: Vec<i32> = steps.iter().map(|step| step.approval_id).collect();
+        let approvals: Vec<PendingApprovalRow> = pending_approvals::table
+            .filter(pending_approvals::id.eq_any(&ids))
+            .filter(pending_approvals::user_id.ne(approver))
+            .filter(pending_approvals::status.eq(ApprovalStatus::Pending.as_str()))
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let mut approvals: Vec<PendingApproval> = approvals.into_iter().map(map_row).collect();
+        approvals.sort_by_key(|approval| approval.id);
+        Ok(approvals
+            .into_iter()
+            .filter_map(|approval| {
+                let step = steps
+                    .iter()
+                    .find(|step| step.approval_id == approval.id)?
+                    .clone();
+                Some((approval, step))
+            })
+            .take(limit)
+            .collect())
+    }
+
+    /// Decides the current step of `approval`'s chain as `actor`. The
+    /// requester may also withdraw their own call at any step.
+    pub async fn decide_step(
+        &self,
+        approval: &PendingApproval,
+        actor: &str,
+        approve: bool,
+    ) -> Result<StepOutcome> {
+        let steps = self.steps(approval.id).await?;
+        let Some(current) = steps.iter().find(|step| step.status == StepStatus::Pending) else {
+            return Ok(StepOutcome::Closed);
+        };
+        let listed = current.approvers.iter().any(|approver| approver == actor);
+        let already_approved = steps.iter().any(|step| {
+            step.status == StepStatus::Approved && step.decided_by.as_deref() == Some(actor)
+        });
+        let withdrawing = !approve && approval.user_id == actor;
+        if !withdrawing && (!listed || already_approved) {
+            return Ok(StepOutcome::NotAnApprover(current.clone()));
+        }
+        let status = if approve {
+            StepStatus::Approved
+        } else {
+            StepStatus::Rejected
+        };
+        if !self.close_step(current.id, status, Some(actor)).await? {
+            return Ok(StepOutcome::Closed);
+        }
+        if !approve {
+            return Ok(StepOutcome::Rejected);
+        }
+        self.open_next(&steps, current.position).await
+    }
+
+    /// Times out every pending step due by `now`, returning each approval
+    /// with the step that lapsed and what became of the chain.
+    pub async fn expire_due(
+        &self,
+        now: i64,
+    ) -> Result<Vec<(PendingApproval, ApprovalStep, StepOutcome)>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ApprovalStepRow> = approval_steps::table
+            .filter(approval_steps::status.eq(StepStatus::Pending.as_str()))
+            .filter(approval_steps::due_at.le(now))
+            .order(approval_steps::due_at.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let mut expired = Vec::new();
+        for lapsed in rows.into_iter().map(map_step) {
+            let Some(approval) = self.find(lapsed.approval_id).await? else {
+                continue;
+            };
+            if !self
+                .close_step(lapsed.id, StepStatus::TimedOut, None)
+                .await?
+            {
+                continue;
+            }
+            let outcome = match lapsed.on_timeout {
+                TimeoutAction::Skip => {
+                    let steps = self.steps(approval.id).await?;
+                    match self.open_next(&steps, lapsed.position).await? {
+                        StepOutcome::Completed => StepOutcome::Rejected,
+                        outcome => outcome,
+                    }
+                }
+                TimeoutAction::Reject => StepOutcome::Rejected,
+            };
+            expired.push((approval, lapsed, outcome));
+        }
+        Ok(expired)
+    }
+
+    /// Pending steps nobody has been told about yet, marking them told.
+    pub async fn take_unannounced(&self) -> Result<Vec<(PendingApproval, ApprovalStep)>> {
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        let rows: Vec<ApprovalStepRow> = approval_steps::table
+            .filter(approval_steps::status.eq(StepStatus::Pending.as_str()))
+            .filter(approval_steps::announced_at.is_null())
+            .order(approval_steps::id.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let mut announced = Vec::new();
+        for mut step in rows.into_iter().map(map_step) {
+            let mut conn = self.conn().await?;
+            let updated = diesel::update(
+                approval_steps::table
+                    .filter(approval_steps::id.eq(step.id))
+                    .filter(approval_steps::announced_at.is_null()),
+            )
+            .set(approval_steps::announced_at.eq(Some(now)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            drop(conn);
+            if updated == 0 {
+                continue;
+            }
+            step.announced_at = Some(now);
+            if let Some(approval) = self.find(step.approval_id).await? {
+                announced.push((approval, step));
+            }
+        }
+        Ok(announced)
+    }
+
+    async fn find(&self, id: i32) -> Result<Option<PendingApproval>> {
+        let mut conn = self.conn().await?;
+        let row: Option<PendingApprovalRow> = pending_approvals::table
+            .filter(pending_approvals::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    /// Moves a pending step to `status`; `false` when it was no longer
+    /// pending.
+    async fn close_step(&self, id: i32, status: StepStatus, actor: Option<&str>) -> Result<bool> {
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        let updated = diesel::update(
+            approval_steps::table
+                .filter(approval_steps::id.eq(id))
+                .filter(approval_steps::status.eq(StepStatus::Pending.as_str())),
+        )
+        .set((
+            approval_steps::status.eq(status.as_str()),
+            approval_steps::decided_by.eq(actor),
+            approval_steps::decided_at.eq(Some(now)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Opens the step after `position`, or reports the chain complete.
+    async fn open_next(&self, steps: &[ApprovalStep], position: i32) -> Result<StepOutcome> {
+        let Some(next) = steps.iter().find(|step| step.position == position + 1) else {
+            return Ok(StepOutcome::Completed);
+        };
+        let now = self.clock.now();
+        let due_at = next.timeout_secs.map(|secs| now + secs);
+        let mut conn = self.conn().await?;
+        diesel::update(approval_steps::table.filter(approval_steps::id.eq(next.id)))
+            .set((
+                approval_steps::status.eq(StepStatus::Pending.as_str()),
+                approval_steps::due_at.eq(due_at),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(StepOutcome::Advanced(ApprovalStep {
+            status: StepStatus::Pending,
+            due_at,
+            ..next.clone()
+        }))
+    }
+}
+
+fn map_step(row: ApprovalStepRow) -> ApprovalStep {
+    ApprovalStep {
+        id: row.id,
+        approval_id: row.approval_id,
+        position: row.position,
+        approvers: serde_json::from_str(&row.approvers).unwrap_or_default(),
+        status: StepStatus::parse(&row.status).unwrap_or(StepStatus::Waiting),
+        timeout_secs: row.timeout_secs,
+        on_timeout: TimeoutAction::parse(&row.on_timeout).unwrap_or_default(),
+        due_at: row.due_at,
+        announced_at: row.announced_at,
+        decided_by: row.decided_by,
+        decided_at: row.decided_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    fn step(approvers: &[&str], timeout_secs: Option<i64>, on_timeout: TimeoutAction) -> ChainStep {
+        ChainStep {
+            approvers: approvers.iter().map(|name| name.to_string()).collect(),
+            timeout_secs,
+            on_timeout,
+        }
+    }
+
+    #[tokio::test]
+    async fn chains_advance_one_approver_at_a_time_and_time_out() {
+        let temp = tempdir().unwrap();
+        let db_path = temp.path().join("chains.db");
+        let clock = ManualClock::new(1_000);
+        let store = ApprovalStore::new(db_path.to_string_lossy())
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+        let args = json!({"user_id": "alice", "amount_sol": 5});
+
+        let approval = store
+            .park_chain(
+                "alice",
+                "solana",
+                "solana.transfer",
+                &args,
+                &[
+                    step(&["alice"], None, TimeoutAction::Reject),
+                    step(&["alice", "bob"], Some(600), TimeoutAction::Reject),
+                ],
+            )
+            .await
+            .unwrap();
+        assert!(store
+            .get_for_actor("bob", approval.id)
+            .await
+            .unwrap()
+            .is_some());
+        assert!(store
+            .get_for_actor("eve", approval.id)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(store.list_awaiting("bob", 10).await.unwrap().is_empty());
+        let announced = store.take_unannounced().await.unwrap();
+        assert_eq!(announced.len(), 1);
+        assert!(store.take_unannounced().await.unwrap().is_empty());
+
+        assert!(matches!(
+            store.decide_step(&approval, "bob", true).await.unwrap(),
+            StepOutcome::NotAnApprover(current) if current.position == 0
+        ));
+        let StepOutcome::Advanced(second) =
+            store.decide_step(&approval, "alice", true).await.unwrap()
+        else {
+            panic!("expected the chain to advance");
+        };
+        assert_eq!(second.due_at, Some(1_600));
+        // Alice already approved a step, so the second one needs Bob.
+        assert!(matches!(
+            store.decide_step(&approval, "alice", true).await.unwrap(),
+            StepOutcome::NotAnApprover(_)
+        ));
+        let awaiting = store.list_awaiting("bob", 10).await.unwrap();
+        assert_eq!(awaiting.len(), 1);
+        assert_eq!(awaiting[0].1.position, 1);
+        assert_eq!(
+            store.decide_step(&approval, "bob", true).await.unwrap(),
+            StepOutcome::Completed
+        );
+        assert_eq!(
+            store.decide_step(&approval, "bob", true).await.unwrap(),
+            StepOutcome::Closed
+        );
+        let steps = store.steps(approval.id).await.unwrap();
+        assert_eq!(steps[1].decided_by.as_deref(), Some("bob"));
+
+        let skipping = store
+            .park_chain(
+                "alice",
+                "solana",
+                "solana.transfer",
+                &args,
+                &[
+                    step(&["carol"], Some(60), TimeoutAction::Skip),
+                    step(&["bob"], Some(60), TimeoutAction::Skip),
+                ],
+            )
+            .await
+            .unwrap();
+        let now = clock.advance(61);
+        let expired = store.expire_due(now).await.unwrap();
+        assert_eq!(expired.len(), 1);
+        assert!(matches!(&expired[0].2, StepOutcome::Advanced(next) if next.approvers == ["bob"]));
+        let now = clock.advance(61);
+        let expired = store.expire_due(now).await.unwrap();
+        assert_eq!(expired[0].0.id, skipping.id);
+        assert_eq!(expired[0].1.status, StepStatus::Pending);
+        assert_eq!(expired[0].2, StepOutcome::Rejected);
+
+        let withdrawn = store
+            .park_chain(
+                "alice",
+                "solana",
+                "solana.transfer",
+                &args,
+                &[step(&["bob"], None, TimeoutAction::Reject)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            store.decide_step(&withdrawn, "alice", false).await.unwrap(),
+            StepOutcome::Rejected
+        );
+    }
+}
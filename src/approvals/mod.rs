@@ -0,0 +1,360 @@
+//! Capability calls parked until a human approves them.
+//!
+//! When the confirmation guardrail flags a capability, the tool registry
+//! stores the call here instead of running it. The daemon lists pending rows
+//! in the inbox and replays the call once the user approves it. Calls the
+//! policy marks as high-risk wait on an approval [`chain`] instead, which
+//! can involve other people.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+pub mod chain;
+mod schema;
+pub use chain::{ApprovalStep, ChainStep, StepOutcome, StepStatus, TimeoutAction};
+use schema::pending_approvals;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const PENDING_APPROVALS_UP_SQL: &str =
+    include_str!("../../migrations/20260309_create_pending_approvals/up.sql");
+const APPROVAL_STEPS_UP_SQL: &str =
+    include_str!("../../migrations/20260321_create_approval_steps/up.sql");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    Pending,
+    /// Approved and handed to the registry; replaced by the outcome.
+    Approved,
+    Executed,
+    Failed,
+    Rejected,
+}
+
+impl ApprovalStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApprovalStatus::Pending => "pending",
+            ApprovalStatus::Approved => "approved",
+            ApprovalStatus::Executed => "executed",
+            ApprovalStatus::Failed => "failed",
+            ApprovalStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pending" => Some(ApprovalStatus::Pending),
+            "approved" => Some(ApprovalStatus::Approved),
+            "executed" => Some(ApprovalStatus::Executed),
+            "failed" => Some(ApprovalStatus::Failed),
+            "rejected" => Some(ApprovalStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PendingApproval {
+    pub id: i32,
+    pub user_id: String,
+    pub tool: String,
+    pub capability: String,
+    pub args: Value,
+    pub status: ApprovalStatus,
+    pub result: Option<Value>,
+    pub created_at: i64,
+    pub decided_at: Option<i64>,
+}
+
+impl PendingApproval {
+    pub fn origin_ref(&self) -> String {
+        format!("approval:{}", self.id)
+    }
+}
+
+#[derive(Queryable)]
+struct PendingApprovalRow {
+    id: i32,
+    user_id: String,
+    tool: String,
+    capability: String,
+    args: String,
+    status: String,
+    result: Option<String>,
+    created_at: i64,
+    decided_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = pending_approvals)]
+struct NewPendingApproval<'a> {
+    user_id: &'a str,
+    tool: &'a str,
+    capability: &'a str,
+    args: String,
+    status: &'a str,
+    created_at: i64,
+}
+
+pub struct ApprovalStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl ApprovalStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_pending_approvals_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Records a call that must wait for the user.
+    pub async fn park(
+        &self,
+        user_id: &str,
+        tool: &str,
+        capability: &str,
+        args: &Value,
+    ) -> Result<PendingApproval> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let row = NewPendingApproval {
+            user_id,
+            tool,
+            capability,
+            args: args.to_string(),
+            status: ApprovalStatus::Pending.as_str(),
+            created_at: now,
+        };
+        diesel::insert_into(pending_approvals::table)
+            .values(&row)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let row: PendingApprovalRow = pending_approvals::table
+            .filter(pending_approvals::user_id.eq(user_id))
+            .order(pending_approvals::id.desc())
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(map_row(row))
+    }
+
+    pub async fn get(&self, user_id: &str, id: i32) -> Result<Option<PendingApproval>> {
+        let mut conn = self.conn().await?;
+        let row: Option<PendingApprovalRow> = pending_approvals::table
+            .filter(pending_approvals::id.eq(id))
+            .filter(pending_approvals::user_id.eq(user_id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    /// The user's undecided calls, oldest first.
+    pub async fn list_pending(&self, user_id: &str, limit: usize) -> Result<Vec<PendingApproval>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<PendingApprovalRow> = pending_approvals::table
+            .filter(pending_approvals::user_id.eq(user_id))
+            .filter(pending_approvals::status.eq(ApprovalStatus::Pending.as_str()))
+            .order(pending_approvals::id.asc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Moves a pending call to `status`. Returns `false` when the call was
+    /// already decided, so two approvals can't both run it.
+    pub async fn decide(&self, user_id: &str, id: i32, status: ApprovalStatus) -> Result<bool> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            pending_approvals::table
+                .filter(pending_approvals::id.eq(id))
+                .filter(pending_approvals::user_id.eq(user_id))
+                .filter(pending_approvals::status.eq(ApprovalStatus::Pending.as_str())),
+        )
+        .set((
+            pending_approvals::status.eq(status.as_str()),
+            pending_approvals::decided_at.eq(Some(now)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Stores what running an approved call produced.
+    pub async fn record_outcome(
+        &self,
+        id: i32,
+        status: ApprovalStatus,
+        result: &Value,
+    ) -> Result<()> {
+        let mut conn = self.write_conn().await?;
+        diesel::update(pending_approvals::table.filter(pending_approvals::id.eq(id)))
+            .set((
+                pending_approvals::status.eq(status.as_str()),
+                pending_approvals::result.eq(Some(result.to_string())),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// Parses an inbox origin ref of the form `approval:<id>`.
+pub fn parse_origin_ref(origin_ref: &str) -> Option<i32> {
+    origin_ref.strip_prefix("approval:")?.parse().ok()
+}
+
+fn map_row(row: PendingApprovalRow) -> PendingApproval {
+    PendingApproval {
+        id: row.id,
+        user_id: row.user_id,
+        tool: row.tool,
+        capability: row.capability,
+        args: serde_json::from_str(&row.args).unwrap_or(Value::Null),
+        status: ApprovalStatus::parse(&row.status).unwrap_or(ApprovalStatus::Pending),
+        result: row
+            .result
+            .as_deref()
+            .and_then(|result| serde_json::from_str(result).ok()),
+        created_at: row.created_at,
+        decided_at: row.decided_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_pending_approvals_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+
+        for (table, up_sql) in [
+            ("pending_approvals", PENDING_APPROVALS_UP_SQL),
+            ("approval_steps", APPROVAL_STEPS_UP_SQL),
+        ] {
+            let check = diesel::connection::SimpleConnection::batch_execute(
+                &mut conn,
+                &format!("SELECT 1 FROM {table} LIMIT 1"),
+            );
+            if let Err(err) = check {
+                let message = err.to_string();
+                if message.contains("no such table") {
+                    diesel::connection::SimpleConnection::batch_execute(&mut conn, up_sql)
+                        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                } else {
+                    return Err(ButterflyBotError::Runtime(message));
+                }
+            }
+        }
+
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn parked_calls_are_decided_exactly_once() {
+        let temp = tempdir().unwrap();
+        let db_path = temp.path().join("approvals.db");
+        let clock = ManualClock::new(1_000);
+        let store = ApprovalStore::new(db_path.to_string_lossy())
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let parked = store
+            .park(
+                "alice",
+                "todo",
+                "kv.sqlite.todo.clear",
+                &json!({"status": "all"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(parked.status, ApprovalStatus::Pending);
+        assert_eq!(parse_origin_ref(&parked.origin_ref()), Some(parked.id));
+        assert!(store.get("bob", parked.id).await.unwrap().is_none());
+        assert_eq!(store.list_pending("alice", 10).await.unwrap().len(), 1);
+
+        clock.advance(30);
+        assert!(store
+            .decide("alice", parked.id, ApprovalStatus::Approved)
+            .await
+            .unwrap());
+        assert!(!store
+            .decide("alice", parked.id, ApprovalStatus::Rejected)
+            .await
+            .unwrap());
+        store
+            .record_outcome(parked.id, ApprovalStatus::Executed, &json!({"deleted": 3}))
+            .await
+            .unwrap();
+
+        let decided = store.get("alice", parked.id).await.unwrap().unwrap();
+        assert_eq!(decided.status, ApprovalStatus::Executed);
+        assert_eq!(decided.decided_at, Some(1_030));
+        assert_eq!(decided.result, Some(json!({"deleted": 3})));
+        assert!(store.list_pending("alice", 10).await.unwrap().is_empty());
+    }
+}
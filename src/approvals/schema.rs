@@ -0,0 +1,29 @@
+diesel::table! {
+    pending_approvals (id) {
+        id -> Integer,
+        user_id -> Text,
+        tool -> Text,
+        capability -> Text,
+        args -> Text,
+        status -> Text,
+        result -> Nullable<Text>,
+        created_at -> BigInt,
+        decided_at -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    approval_steps (id) {
+        id -> Integer,
+        approval_id -> Integer,
+        position -> Integer,
+        approvers -> Text,
+        status -> Text,
+        timeout_secs -> Nullable<BigInt>,
+        on_timeout -> Text,
+        due_at -> Nullable<BigInt>,
+        announced_at -> Nullable<BigInt>,
+        decided_by -> Nullable<Text>,
+        decided_at -> Nullable<BigInt>,
+    }
+}
@@ -0,0 +1,735 @@
+//! Files attached to todos and inbox items.
+//!
+//! Rows in `attachments` hold the metadata and name their content by its
+//! SHA-256, so attaching the same screenshot twice stores it once. The
+//! content lives under `attachments/<aa>/<sha256>` next to the database,
+//! sealed with the database key so a blob is no more readable on disk than
+//! the row that points at it; names and content types are sealed under the
+//! owner's encryption domain like other user content. Blobs are written
+//! and removed only during the database's write turn, which keeps a
+//! cleanup from removing content an attach is about to point at.
+
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use cocoon::Cocoon;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
+
+mod schema;
+use schema::attachments;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const ATTACHMENTS_UP_SQL: &str =
+    include_str!("../../migrations/20260401_create_attachments/up.sql");
+
+pub const DEFAULT_MAX_FILE_MB: u64 = 10;
+pub const DEFAULT_QUOTA_MB: u64 = 500;
+/// Largest upload the daemon reads, whatever the config allows.
+pub const MAX_FILE_BYTES_CEILING: usize = 100 * 1024 * 1024;
+const MB: u64 = 1024 * 1024;
+const MAX_NAME_LEN: usize = 200;
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+const PARTIAL_SUFFIX: &str = ".partial";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentConfig {
+    pub max_file_bytes: u64,
+    /// Combined size of one user's attachments.
+    pub quota_bytes: u64,
+    /// `None` for `attachments/` next to the database.
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: DEFAULT_MAX_FILE_MB * MB,
+            quota_bytes: DEFAULT_QUOTA_MB * MB,
+            directory: None,
+        }
+    }
+}
+
+impl AttachmentConfig {
+    /// Reads `tools.settings.attachments`: `max_file_mb`, `quota_mb` and
+    /// `directory`.
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("attachments"))
+        else {
+            return Self::default();
+        };
+        let megabytes = |key: &str, default: u64| {
+            section
+                .get(key)
+                .and_then(Value::as_u64)
+                .unwrap_or(default)
+                .max(1)
+                .saturating_mul(MB)
+        };
+        Self {
+            max_file_bytes: megabytes("max_file_mb", DEFAULT_MAX_FILE_MB)
+                .min(MAX_FILE_BYTES_CEILING as u64),
+            quota_bytes: megabytes("quota_mb", DEFAULT_QUOTA_MB),
+            directory: section
+                .get("directory")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+
+    pub fn directory_for(&self, db_path: &str) -> PathBuf {
+        self.directory.clone().unwrap_or_else(|| {
+            Path::new(db_path)
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join("attachments")
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Queryable)]
+pub struct Attachment {
+    pub id: i32,
+    pub origin_ref: String,
+    pub name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    pub created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = attachments)]
+struct NewAttachment<'a> {
+    user_id: &'a str,
+    origin_ref: &'a str,
+    name: &'a str,
+    content_type: &'a str,
+    size_bytes: i64,
+    sha256: &'a str,
+    created_at: i64,
+}
+
+/// Blobs a cleanup removed because no attachment pointed at them.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct OrphanSweep {
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+pub struct AttachmentStore {
+    db: DbHandle,
+    blobs: PathBuf,
+    config: AttachmentConfig,
+    clock: SharedClock,
+}
+
+impl AttachmentStore {
+    pub async fn new(sqlite_path: impl AsRef<str>, config: AttachmentConfig) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?, config).await
+    }
+
+    pub async fn from_db(db: DbHandle, config: AttachmentConfig) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_attachments_table(sqlite_path).await?;
+        Ok(Self {
+            blobs: config.directory_for(sqlite_path),
+            db,
+            config,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn config(&self) -> &AttachmentConfig {
+        &self.config
+    }
+
+    /// Stores `bytes` as a file called `name` on the item `origin_ref`.
+    /// Refuses empty files, files over the size limit and uploads that
+    /// would take the user past their quota.
+    pub async fn attach(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+        name: &str,
+        content_type: Option<&str>,
+        bytes: &[u8],
+    ) -> Result<Attachment> {
+        let origin_ref = origin_ref.trim();
+        if origin_ref.is_empty() {
+            return Err(ButterflyBotError::Config(
+                "Attachments need an item origin_ref".to_string(),
+            ));
+        }
+        let name = clean_name(name)?;
+        if bytes.is_empty() {
+            return Err(ButterflyBotError::Config("Attachment is empty".to_string()));
+        }
+        let size = bytes.len() as u64;
+        if size > self.config.max_file_bytes {
+            return Err(ButterflyBotError::Config(format!(
+                "Attachments are limited to {} MB",
+                self.config.max_file_bytes / MB
+            )));
+        }
+        let content_type = content_type
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .unwrap_or(DEFAULT_CONTENT_TYPE);
+        let sealed_name = user_domains::seal(user_id, "attachment.name", &name)?;
+        let sealed_type = user_domains::seal(user_id, "attachment.content_type", content_type)?;
+        let sha256 = hex_digest(bytes);
+        let path = self.blob_path(&sha256);
+        // Sealing is the slow part; do it before waiting for the write turn.
+        let mut sealed = if tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            None
+        } else {
+            Some(seal_blocking(bytes.to_vec()).await?)
+        };
+
+        let mut conn = self.write_conn().await?;
+        let used: i64 = attachments::table
+            .filter(attachments::user_id.eq(user_id))
+            .select(attachments::size_bytes)
+            .load::<i64>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            .into_iter()
+            .sum();
+        if used.max(0) as u64 + size > self.config.quota_bytes {
+            return Err(ButterflyBotError::Config(format!(
+                "Attachment quota of {} MB is used up",
+                self.config.quota_bytes / MB
+            )));
+        }
+        if !tokio::fs::try_exists(&path).await.unwrap_or(false) {
+            let sealed = match sealed.take() {
+                Some(sealed) => sealed,
+                None => seal_blocking(bytes.to_vec()).await?,
+            };
+            write_blob(&path, &sealed).await?;
+        }
+        diesel::insert_into(attachments::table)
+            .values(&NewAttachment {
+                user_id,
+                origin_ref,
+                name: &sealed_name,
+                content_type: &sealed_type,
+                size_bytes: size as i64,
+                sha256: &sha256,
+                created_at: self.clock.now(),
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let mut attachment: Attachment = attachments::table
+            .filter(attachments::user_id.eq(user_id))
+            .order(attachments::id.desc())
+            .select((
+                attachments::id,
+                attachments::origin_ref,
+                attachments::name,
+                attachments::content_type,
+                attachments::size_bytes,
+                attachments::sha256,
+                attachments::created_at,
+            ))
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        attachment.name = name;
+        attachment.content_type = content_type.to_string();
+        Ok(attachment)
+    }
+
+    /// The item's attachments, oldest first.
+    pub async fn list(&self, user_id: &str, origin_ref: &str) -> Result<Vec<Attachment>> {
+        let mut grouped = self.list_for(user_id, &[origin_ref.to_string()]).await?;
+        Ok(grouped.remove(origin_ref).unwrap_or_default())
+    }
+
+    /// Attachments for several items at once, keyed by origin ref. Items
+    /// without attachments are left out.
+    pub async fn list_for(
+        &self,
+        user_id: &str,
+        origin_refs: &[String],
+    ) -> Result<HashMap<String, Vec<Attachment>>> {
+        if origin_refs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut conn = self.conn().await?;
+        let rows: Vec<Attachment> = attachments::table
+            .filter(attachments::user_id.eq(user_id))
+            .filter(attachments::origin_ref.eq_any(origin_refs))
+            .order((attachments::created_at.asc(), attachments::id.asc()))
+            .select((
+                attachments::id,
+                attachments::origin_ref,
+                attachments::name,
+                attachments::content_type,
+                attachments::size_bytes,
+                attachments::sha256,
+                attachments::created_at,
+            ))
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let mut grouped: HashMap<String, Vec<Attachment>> = HashMap::new();
+        for attachment in rows {
+            let attachment = open_metadata(user_id, attachment);
+            grouped
+                .entry(attachment.origin_ref.clone())
+                .or_default()
+                .push(attachment);
+        }
+        Ok(grouped)
+    }
+
+    pub async fn get(&self, user_id: &str, id: i32) -> Result<Option<Attachment>> {
+        let mut conn = self.conn().await?;
+        let attachment = attachments::table
+            .filter(attachments::user_id.eq(user_id))
+            .filter(attachments::id.eq(id))
+            .select((
+                attachments::id,
+                attachments::origin_ref,
+                attachments::name,
+                attachments::content_type,
+                attachments::size_bytes,
+                attachments::sha256,
+                attachments::created_at,
+            ))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(attachment.map(|attachment| open_metadata(user_id, attachment)))
+    }
+
+    /// The attachment with its content, checked against the stored digest.
+    pub async fn read(&self, user_id: &str, id: i32) -> Result<Option<(Attachment, Vec<u8>)>> {
+        let Some(attachment) = self.get(user_id, id).await? else {
+            return Ok(None);
+        };
+        let sealed = tokio::fs::read(self.blob_path(&attachment.sha256))
+            .await
+            .map_err(|e| {
+                ButterflyBotError::Runtime(format!("Attachment {id} content is missing: {e}"))
+            })?;
+        let bytes = tokio::task::spawn_blocking(move || open(&sealed))
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+        if hex_digest(&bytes) != attachment.sha256 {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Attachment {id} content does not match its digest"
+            )));
+        }
+        Ok(Some((attachment, bytes)))
+    }
+
+    /// Removes the attachment, and its blob when nothing else points at it.
+    pub async fn delete(&self, user_id: &str, id: i32) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let sha256: Option<String> = attachments::table
+            .filter(attachments::user_id.eq(user_id))
+            .filter(attachments::id.eq(id))
+            .select(attachments::sha256)
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let Some(sha256) = sha256 else {
+            return Ok(false);
+        };
+        diesel::delete(
+            attachments::table
+                .filter(attachments::user_id.eq(user_id))
+                .filter(attachments::id.eq(id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let still_used = attachments::table
+            .filter(attachments::sha256.eq(&sha256))
+            .select(attachments::id)
+            .first::<i32>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            .is_some();
+        if !still_used {
+            match tokio::fs::remove_file(self.blob_path(&sha256)).await {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                Err(err) => return Err(ButterflyBotError::Runtime(err.to_string())),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Deletes every attachment the user has, then their unused blobs.
+    pub async fn clear(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        let deleted = diesel::delete(attachments::table.filter(attachments::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        self.sweep_locked(&mut conn).await?;
+        Ok(deleted)
+    }
+
+    /// Removes blobs no attachment points at, such as content left behind
+    /// by a crash between writing a blob and recording it.
+    pub async fn sweep_orphans(&self) -> Result<OrphanSweep> {
+        let mut conn = self.write_conn().await?;
+        self.sweep_locked(&mut conn).await
+    }
+
+    /// Must run during the write turn `conn` holds.
+    async fn sweep_locked(&self, conn: &mut WriteConn<'_>) -> Result<OrphanSweep> {
+        let referenced: HashSet<String> = attachments::table
+            .select(attachments::sha256)
+            .distinct()
+            .load::<String>(conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            .into_iter()
+            .collect();
+        let blobs = self.blobs.clone();
+        tokio::task::spawn_blocking(move || sweep_dir(&blobs, &referenced))
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+    }
+
+    fn blob_path(&self, sha256: &str) -> PathBuf {
+        self.blobs.join(&sha256[..2]).join(sha256)
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn open_metadata(user_id: &str, attachment: Attachment) -> Attachment {
+    Attachment {
+        name: user_domains::open(user_id, "attachment.name", attachment.name),
+        content_type: user_domains::open(
+            user_id,
+            "attachment.content_type",
+            attachment.content_type,
+        ),
+        ..attachment
+    }
+}
+
+/// The last path component of `name`, without control characters.
+fn clean_name(name: &str) -> Result<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LEN)
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        return Err(ButterflyBotError::Config(
+            "Attachments need a file name".to_string(),
+        ));
+    }
+    Ok(cleaned.to_string())
+}
+
+fn hex_digest(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+async fn seal_blocking(plaintext: Vec<u8>) -> Result<Vec<u8>> {
+    tokio::task::spawn_blocking(move || seal(&plaintext))
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+}
+
+fn seal(plaintext: &[u8]) -> Result<Vec<u8>> {
+    let key = crate::db::get_sqlcipher_key()?;
+    let mut cocoon = Cocoon::new(key.as_bytes());
+    let mut sealed = Vec::new();
+    cocoon.dump(plaintext.to_vec(), &mut sealed).map_err(|e| {
+        ButterflyBotError::SecurityStorage(format!("failed to seal attachment: {e:?}"))
+    })?;
+    Ok(sealed)
+}
+
+fn open(sealed: &[u8]) -> Result<Vec<u8>> {
+    let key = crate::db::get_sqlcipher_key()?;
+    Cocoon::new(key.as_bytes())
+        .parse(&mut Cursor::new(sealed))
+        .map_err(|e| {
+            ButterflyBotError::SecurityStorage(format!("failed to open attachment: {e:?}"))
+        })
+}
+
+/// Writes next to the final path and renames, so a crash never leaves a
+/// truncated blob under a digest name.
+async fn write_blob(path: &Path, sealed: &[u8]) -> Result<()> {
+    let io_error = |e: std::io::Error| ButterflyBotError::Runtime(e.to_string());
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(io_error)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(PARTIAL_SUFFIX);
+    tokio::fs::write(&partial, sealed).await.map_err(io_error)?;
+    tokio::fs::rename(&partial, path).await.map_err(io_error)
+}
+
+fn sweep_dir(blobs: &Path, referenced: &HashSet<String>) -> Result<OrphanSweep> {
+    let mut sweep = OrphanSweep::default();
+    let shards = match std::fs::read_dir(blobs) {
+        Ok(shards) => shards,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(sweep),
+        Err(err) => return Err(ButterflyBotError::Runtime(err.to_string())),
+    };
+    for shard in shards.flatten() {
+        let Ok(files) = std::fs::read_dir(shard.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let name = file.file_name().to_string_lossy().to_string();
+            if referenced.contains(&name) {
+                continue;
+            }
+            let bytes = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+            if std::fs::remove_file(file.path()).is_ok() {
+                sweep.removed += 1;
+                sweep.bytes += bytes;
+            }
+        }
+        // Only succeeds once the shard is empty.
+        let _ = std::fs::remove_dir(shard.path());
+    }
+    Ok(sweep)
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_attachments_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM attachments LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                conn.run_pending_migrations(MIGRATIONS)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, ATTACHMENTS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{AttachmentConfig, AttachmentStore, MB};
+
+    #[tokio::test]
+    async fn attachments_share_sealed_blobs_and_sweep_orphans() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("bot.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let config = AttachmentConfig {
+            max_file_bytes: MB,
+            quota_bytes: 3 * MB / 2,
+            directory: None,
+        };
+        let store = AttachmentStore::new(&db_path, config).await.expect("store");
+        let blobs = dir.path().join("attachments");
+
+        let screenshot = b"PNG screenshot bytes".to_vec();
+        let first = store
+            .attach(
+                "u1",
+                "todo:4",
+                "C:\\shots\\error.png",
+                Some("image/png"),
+                &screenshot,
+            )
+            .await
+            .expect("attach");
+        assert_eq!(first.name, "error.png");
+        assert_eq!(first.size_bytes, screenshot.len() as i64);
+        let again = store
+            .attach("u1", "inbox:9", "copy.png", None, &screenshot)
+            .await
+            .expect("attach again");
+        assert_eq!(again.sha256, first.sha256);
+        assert_eq!(again.content_type, "application/octet-stream");
+
+        let blob = blobs.join(&first.sha256[..2]).join(&first.sha256);
+        let on_disk = std::fs::read(&blob).expect("blob");
+        assert!(!on_disk
+            .windows(screenshot.len())
+            .any(|window| window == screenshot.as_slice()));
+        let (read, bytes) = store.read("u1", first.id).await.unwrap().unwrap();
+        assert_eq!((read, bytes), (first.clone(), screenshot.clone()));
+        assert!(store.read("u2", first.id).await.unwrap().is_none());
+
+        // Size limit, quota and names.
+        let big = vec![7u8; MB as usize + 1];
+        assert!(store
+            .attach("u1", "todo:4", "big.bin", None, &big)
+            .await
+            .is_err());
+        let most = vec![8u8; MB as usize];
+        store
+            .attach("u1", "todo:4", "most.bin", None, &most)
+            .await
+            .expect("within quota");
+        assert!(store
+            .attach("u1", "todo:4", "over.bin", None, &most[..MB as usize / 2])
+            .await
+            .is_err());
+        assert!(store
+            .attach("u1", "todo:4", "../", None, b"x")
+            .await
+            .is_err());
+        assert!(store
+            .attach("u1", "todo:4", "empty", None, b"")
+            .await
+            .is_err());
+
+        // A blob stays while another attachment points at it.
+        assert!(store.delete("u1", first.id).await.unwrap());
+        assert!(!store.delete("u1", first.id).await.unwrap());
+        assert!(blob.exists());
+        assert!(store.delete("u1", again.id).await.unwrap());
+        assert!(!blob.exists());
+
+        let stray = blobs.join("ab").join("ab".repeat(32));
+        std::fs::create_dir_all(stray.parent().unwrap()).unwrap();
+        std::fs::write(&stray, b"left by a crash").unwrap();
+        let sweep = store.sweep_orphans().await.expect("sweep");
+        assert_eq!((sweep.removed, sweep.bytes), (1, 15));
+        assert!(!stray.exists());
+
+        let listed = store.list("u1", "todo:4").await.expect("list");
+        assert_eq!(listed.len(), 1);
+        assert_eq!(store.clear("u1").await.expect("clear"), 1);
+        assert_eq!(std::fs::read_dir(&blobs).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn names_are_sealed_for_enrolled_users() {
+        use diesel::prelude::*;
+        use diesel_async::RunQueryDsl;
+
+        use super::attachments;
+        use crate::security::user_domains::UserDomainStore;
+
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("bot.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = AttachmentStore::new(&db_path, AttachmentConfig::default())
+            .await
+            .expect("store");
+        UserDomainStore::new(&db_path)
+            .await
+            .unwrap()
+            .enroll("attach-sealed-user", "correct horse battery")
+            .await
+            .unwrap();
+
+        let attached = store
+            .attach(
+                "attach-sealed-user",
+                "todo:1",
+                "diagnosis.pdf",
+                Some("application/pdf"),
+                b"%PDF",
+            )
+            .await
+            .expect("attach");
+        assert_eq!(attached.name, "diagnosis.pdf");
+        let (name, content_type): (String, String) = attachments::table
+            .filter(attachments::id.eq(attached.id))
+            .select((attachments::name, attachments::content_type))
+            .first(&mut store.conn().await.unwrap())
+            .await
+            .unwrap();
+        assert!(name.starts_with("ud1:") && content_type.starts_with("ud1:"));
+        let listed = store.list("attach-sealed-user", "todo:1").await.unwrap();
+        assert_eq!(listed, vec![attached]);
+    }
+
+    #[test]
+    fn config_reads_megabytes_and_caps_the_file_limit() {
+        let config = AttachmentConfig::from_tools(Some(&json!({
+            "settings": {"attachments": {
+                "max_file_mb": 5000,
+                "quota_mb": 50,
+                "directory": "/srv/files"
+            }}
+        })));
+        assert_eq!(config.max_file_bytes, super::MAX_FILE_BYTES_CEILING as u64);
+        assert_eq!(config.quota_bytes, 50 * MB);
+        assert_eq!(
+            config.directory_for("/data/bot.db"),
+            std::path::PathBuf::from("/srv/files")
+        );
+        assert_eq!(
+            AttachmentConfig::default().directory_for("/data/bot.db"),
+            std::path::PathBuf::from("/data/attachments")
+        );
+    }
+}
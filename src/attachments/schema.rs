@@ -0,0 +1,12 @@
+diesel::table! {
+    attachments (id) {
+        id -> Integer,
+        user_id -> Text,
+        origin_ref -> Text,
+        name -> Text,
+        content_type -> Text,
+        size_bytes -> BigInt,
+        sha256 -> Text,
+        created_at -> BigInt,
+    }
+}
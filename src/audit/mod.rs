@@ -0,0 +1,585 @@
+//! Persistent audit trail of agent, tool and inbox events.
+//!
+//! Every `UiEvent` broadcast by the daemon is stored here with the fields the
+//! Audit tab filters on pulled out of the payload, so queries by time range,
+//! event type or work item hit an index instead of scanning a log file.
+
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use crate::services::agent::UiEvent;
+
+mod schema;
+use schema::audit_events;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const AUDIT_EVENTS_UP_SQL: &str =
+    include_str!("../../migrations/20260307_create_audit_events/up.sql");
+
+/// Users whose events are visible to everyone, next to the caller's own.
+const SHARED_USERS: &[&str] = &["system", "daemon"];
+/// Event types that concern every user regardless of who triggered them.
+const SHARED_EVENT_TYPES: &[&str] = &["boot", "autonomy"];
+
+pub const DEFAULT_PAGE_SIZE: usize = 200;
+pub const MAX_PAGE_SIZE: usize = 2000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl AuditSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditSeverity::Info => "info",
+            AuditSeverity::Warning => "warning",
+            AuditSeverity::Error => "error",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "info" => Some(AuditSeverity::Info),
+            "warning" | "warn" => Some(AuditSeverity::Warning),
+            "error" => Some(AuditSeverity::Error),
+            _ => None,
+        }
+    }
+
+    /// Severity implied by an event status when the payload doesn't set one.
+    pub fn from_status(status: &str) -> Self {
+        match status.trim().to_ascii_lowercase().as_str() {
+            "error" | "failed" | "failure" | "denied" | "rejected" | "blocked" => {
+                AuditSeverity::Error
+            }
+            "held" | "skipped" | "timeout" | "retry" | "warning" | "cancelled" => {
+                AuditSeverity::Warning
+            }
+            _ => AuditSeverity::Info,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AuditEvent {
+    pub id: i32,
+    pub timestamp: i64,
+    pub user_id: String,
+    pub actor: String,
+    pub event_type: String,
+    pub tool: String,
+    pub status: String,
+    pub origin_ref: Option<String>,
+    pub severity: AuditSeverity,
+    pub payload: Value,
+}
+
+/// Filters for [`AuditStore::query`]. Unset fields match everything.
+#[derive(Clone, Debug, Default)]
+pub struct AuditQuery {
+    /// The caller's own events plus system-wide ones.
+    pub user_id: Option<String>,
+    pub event_types: Vec<String>,
+    pub origin_ref: Option<String>,
+    pub severity: Option<AuditSeverity>,
+    /// Inclusive lower bound on the event timestamp.
+    pub since: Option<i64>,
+    /// Exclusive upper bound on the event timestamp.
+    pub until: Option<i64>,
+    /// Cursor from a previous page; only older events are returned.
+    pub before_id: Option<i32>,
+    pub limit: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditPage {
+    /// Oldest first, matching the order events happened in.
+    pub events: Vec<AuditEvent>,
+    /// Pass as `before_id` to fetch the next older page; `None` at the end.
+    pub next_before_id: Option<i32>,
+}
+
+#[derive(Queryable)]
+struct AuditEventRow {
+    id: i32,
+    user_id: String,
+    actor: String,
+    event_type: String,
+    tool: String,
+    status: String,
+    origin_ref: Option<String>,
+    severity: String,
+    payload: String,
+    created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = audit_events)]
+struct NewAuditEvent<'a> {
+    user_id: &'a str,
+    actor: &'a str,
+    event_type: &'a str,
+    tool: &'a str,
+    status: &'a str,
+    origin_ref: Option<&'a str>,
+    severity: &'a str,
+    payload: String,
+    created_at: i64,
+}
+
+pub struct AuditStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl AuditStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_audit_events_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for events recorded without a timestamp.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Stores a broadcast event. Actor, origin and severity are read from the
+    /// payload when present and derived from the user and status otherwise.
+    pub async fn record(&self, event: &UiEvent) -> Result<()> {
+        let timestamp = if event.timestamp > 0 {
+            event.timestamp
+        } else {
+            self.clock.now()
+        };
+        self.insert(&[(event, timestamp)]).await
+    }
+
+    /// Copies events from the JSON-lines log the daemon wrote before this
+    /// store existed. Only runs against an empty table, so calling it on
+    /// every start is safe; returns how many events were imported.
+    pub async fn import_legacy_log(&self, path: &str) -> Result<usize> {
+        if self.count().await? > 0 {
+            return Ok(0);
+        }
+        let content = match tokio::fs::read_to_string(path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(ButterflyBotError::Runtime(err.to_string())),
+        };
+        let events = content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+            .map(|value| {
+                let field = |key: &str| {
+                    value
+                        .get(key)
+                        .and_then(|v| v.as_str())
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                UiEvent {
+                    event_type: field("event_type"),
+                    user_id: field("user_id"),
+                    tool: field("tool"),
+                    status: field("status"),
+                    payload: value.get("payload").cloned().unwrap_or(Value::Null),
+                    timestamp: value.get("timestamp").and_then(|v| v.as_i64()).unwrap_or(0),
+                }
+            })
+            .collect::<Vec<_>>();
+        let rows = events
+            .iter()
+            .map(|event| (event, event.timestamp))
+            .collect::<Vec<_>>();
+        for chunk in rows.chunks(500) {
+            self.insert(chunk).await?;
+        }
+        Ok(rows.len())
+    }
+
+    pub async fn count(&self) -> Result<i64> {
+        let mut conn = self.conn().await?;
+        audit_events::table
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// One page of events matching `query`, newest page first.
+    pub async fn query(&self, query: &AuditQuery) -> Result<AuditPage> {
+        let limit = query.limit.clamp(1, MAX_PAGE_SIZE);
+        let mut conn = self.conn().await?;
+        let mut sql = audit_events::table.into_boxed();
+        if let Some(user_id) = query.user_id.as_deref() {
+            sql = sql.filter(
+                audit_events::user_id
+                    .eq(user_id.to_string())
+                    .or(audit_events::user_id.eq_any(SHARED_USERS))
+                    .or(audit_events::event_type.eq_any(SHARED_EVENT_TYPES)),
+            );
+        }
+        if !query.event_types.is_empty() {
+            sql = sql.filter(audit_events::event_type.eq_any(query.event_types.clone()));
+        }
+        if let Some(origin_ref) = query.origin_ref.as_deref() {
+            sql = sql.filter(audit_events::origin_ref.eq(origin_ref.to_string()));
+        }
+        if let Some(severity) = query.severity {
+            sql = sql.filter(audit_events::severity.eq(severity.as_str()));
+        }
+        if let Some(since) = query.since {
+            sql = sql.filter(audit_events::created_at.ge(since));
+        }
+        if let Some(until) = query.until {
+            sql = sql.filter(audit_events::created_at.lt(until));
+        }
+        if let Some(before_id) = query.before_id {
+            sql = sql.filter(audit_events::id.lt(before_id));
+        }
+
+        let mut rows: Vec<AuditEventRow> = sql
+            .order(audit_events::id.desc())
+            .limit(limit as i64 + 1)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let next_before_id = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(|row| row.id)
+        } else {
+            None
+        };
+        rows.reverse();
+        Ok(AuditPage {
+            events: rows.into_iter().map(map_row).collect(),
+            next_before_id,
+        })
+    }
+
+    /// When each tool last ran, successfully or not, keyed by tool name.
+    pub async fn last_tool_use(&self) -> Result<HashMap<String, i64>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<(String, Option<i64>)> = audit_events::table
+            .filter(audit_events::event_type.eq("tool"))
+            .filter(audit_events::status.eq_any(["success", "error"]))
+            .group_by(audit_events::tool)
+            .select((
+                audit_events::tool,
+                diesel::dsl::max(audit_events::created_at),
+            ))
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|(tool, at)| at.map(|at| (tool, at)))
+            .collect())
+    }
+
+    async fn insert(&self, events: &[(&UiEvent, i64)]) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let derived = events
+            .iter()
+            .map(|(event, timestamp)| {
+                (
+                    *event,
+                    *timestamp,
+                    derive_actor(event),
+                    derive_severity(event),
+                )
+            })
+            .collect::<Vec<_>>();
+        let rows = derived
+            .iter()
+            .map(|(event, timestamp, actor, severity)| NewAuditEvent {
+                user_id: &event.user_id,
+                actor,
+                event_type: &event.event_type,
+                tool: &event.tool,
+                status: &event.status,
+                origin_ref: payload_str(&event.payload, "origin_ref"),
+                severity: severity.as_str(),
+                payload: crate::redaction::redact_json(&event.payload).to_string(),
+                created_at: *timestamp,
+            })
+            .collect::<Vec<_>>();
+
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(audit_events::table)
+            .values(&rows)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn payload_str<'a>(payload: &'a Value, key: &str) -> Option<&'a str> {
+    payload
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+fn derive_actor(event: &UiEvent) -> String {
+    if let Some(actor) = payload_str(&event.payload, "actor") {
+        return actor.to_string();
+    }
+    if SHARED_USERS.contains(&event.user_id.as_str()) {
+        "system".to_string()
+    } else {
+        "agent".to_string()
+    }
+}
+
+fn derive_severity(event: &UiEvent) -> AuditSeverity {
+    payload_str(&event.payload, "severity")
+        .and_then(AuditSeverity::parse)
+        .unwrap_or_else(|| AuditSeverity::from_status(&event.status))
+}
+
+fn map_row(row: AuditEventRow) -> AuditEvent {
+    AuditEvent {
+        id: row.id,
+        timestamp: row.created_at,
+        user_id: row.user_id,
+        actor: row.actor,
+        event_type: row.event_type,
+        tool: row.tool,
+        status: row.status,
+        origin_ref: row.origin_ref,
+        severity: AuditSeverity::parse(&row.severity).unwrap_or(AuditSeverity::Info),
+        payload: serde_json::from_str(&row.payload).unwrap_or(Value::Null),
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_audit_events_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM audit_events LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                conn.run_pending_migrations(MIGRATIONS)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, AUDIT_EVENTS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(user_id: &str, event_type: &str, status: &str, payload: Value, ts: i64) -> UiEvent {
+        UiEvent {
+            event_type: event_type.to_string(),
+            user_id: user_id.to_string(),
+            tool: "inbox".to_string(),
+            status: status.to_string(),
+            payload,
+            timestamp: ts,
+        }
+    }
+
+    #[tokio::test]
+    async fn records_typed_fields_and_filters_by_type_origin_and_time() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("audit.db").to_string_lossy().to_string();
+        let store = AuditStore::new(&db_path).await.expect("store");
+
+        store
+            .record(&event(
+                "u1",
+                "inbox_transition",
+                "done",
+                json!({"origin_ref": "todo:1", "actor": "user"}),
+                100,
+            ))
+            .await
+            .unwrap();
+        store
+            .record(&event("u1", "tool", "error", json!({}), 200))
+            .await
+            .unwrap();
+        store
+            .record(&event("u2", "tool", "ok", json!({}), 300))
+            .await
+            .unwrap();
+        store
+            .record(&event("system", "boot", "ok", json!({}), 50))
+            .await
+            .unwrap();
+
+        let mine = store
+            .query(&AuditQuery {
+                user_id: Some("u1".to_string()),
+                limit: 10,
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(mine.events.len(), 3);
+        assert_eq!(mine.events[0].event_type, "inbox_transition");
+        assert_eq!(mine.events[0].actor, "user");
+        assert_eq!(mine.events[0].origin_ref.as_deref(), Some("todo:1"));
+        assert_eq!(mine.events[1].severity, AuditSeverity::Error);
+        assert_eq!(mine.events[2].actor, "system");
+
+        let by_origin = store
+            .query(&AuditQuery {
+                origin_ref: Some("todo:1".to_string()),
+                limit: 10,
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(by_origin.events.len(), 1);
+
+        let windowed = store
+            .query(&AuditQuery {
+                event_types: vec!["tool".to_string()],
+                since: Some(150),
+                until: Some(300),
+                limit: 10,
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(windowed.events.len(), 1);
+        assert_eq!(windowed.events[0].user_id, "u1");
+    }
+
+    #[tokio::test]
+    async fn masks_secrets_before_storing_payloads() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("audit.db").to_string_lossy().to_string();
+        let store = AuditStore::new(&db_path).await.expect("store");
+        crate::redaction::remember_secret("audit_test_key", "audit-test-secret-value");
+
+        store
+            .record(&event(
+                "u1",
+                "tool",
+                "ok",
+                json!({"result": {"echo": "got audit-test-secret-value back"}}),
+                10,
+            ))
+            .await
+            .unwrap();
+
+        let stored = store
+            .query(&AuditQuery {
+                user_id: Some("u1".to_string()),
+                limit: 10,
+                ..AuditQuery::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            stored.events[0].payload["result"]["echo"],
+            json!("got [REDACTED:audit_test_key] back")
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_backwards_with_a_cursor() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("audit.db").to_string_lossy().to_string();
+        let store = AuditStore::new(&db_path).await.expect("store");
+        for ts in 1..=5 {
+            store
+                .record(&event("u1", "tool", "ok", json!({}), ts))
+                .await
+                .unwrap();
+        }
+
+        let mut query = AuditQuery {
+            user_id: Some("u1".to_string()),
+            limit: 2,
+            ..AuditQuery::default()
+        };
+        let first = store.query(&query).await.unwrap();
+        let stamps = first.events.iter().map(|e| e.timestamp).collect::<Vec<_>>();
+        assert_eq!(stamps, vec![4, 5]);
+
+        query.before_id = first.next_before_id;
+        let second = store.query(&query).await.unwrap();
+        let stamps = second
+            .events
+            .iter()
+            .map(|e| e.timestamp)
+            .collect::<Vec<_>>();
+        assert_eq!(stamps, vec![2, 3]);
+
+        query.before_id = second.next_before_id;
+        let last = store.query(&query).await.unwrap();
+        assert_eq!(last.events.len(), 1);
+        assert_eq!(last.next_before_id, None);
+    }
+}
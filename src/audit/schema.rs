@@ -0,0 +1,14 @@
+diesel::table! {
+    audit_events (id) {
+        id -> Integer,
+        user_id -> Text,
+        actor -> Text,
+        event_type -> Text,
+        tool -> Text,
+        status -> Text,
+        origin_ref -> Nullable<Text>,
+        severity -> Text,
+        payload -> Text,
+        created_at -> BigInt,
+    }
+}
@@ -0,0 +1,503 @@
+//! Scheduled snapshots of the database.
+//!
+//! Configured under `tools.settings.backups`:
+//!
+//! ```json
+//! {"enabled": true, "interval_hours": 24, "keep": 7,
+//!  "directory": "/mnt/backups/butterfly"}
+//! ```
+//!
+//! Each snapshot is an online copy made with `sqlcipher_export`, so it is
+//! encrypted with the same key as the live database and is taken without
+//! stopping the daemon. Snapshots land in `directory` (default: `backups/`
+//! next to the database) and, when remote storage is configured with
+//! `backups` on, are sealed and uploaded there too. Only the newest `keep`
+//! are kept in either place.
+//!
+//! `butterfly-bot restore --from <snapshot>` puts one back with [`restore`]
+//! after the daemon has stopped.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use diesel::connection::SimpleConnection;
+use diesel::sql_types::Text;
+use diesel::{QueryableByName, RunQueryDsl};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::remote_storage::{Category, RemoteUploader};
+
+pub const DEFAULT_INTERVAL_HOURS: u64 = 24;
+pub const DEFAULT_KEEP: usize = 7;
+const SNAPSHOT_PREFIX: &str = "butterfly-bot-";
+const SNAPSHOT_SUFFIX: &str = ".db";
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    pub interval_hours: u64,
+    pub keep: usize,
+    /// `None` for `backups/` next to the database.
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for BackupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_hours: DEFAULT_INTERVAL_HOURS,
+            keep: DEFAULT_KEEP,
+            directory: None,
+        }
+    }
+}
+
+impl BackupConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("backups"))
+        else {
+            return Self::default();
+        };
+        let defaults = Self::default();
+        Self {
+            enabled: section
+                .get("enabled")
+                .and_then(Value::as_bool)
+                .unwrap_or(defaults.enabled),
+            interval_hours: section
+                .get("interval_hours")
+                .and_then(Value::as_u64)
+                .unwrap_or(defaults.interval_hours)
+                .max(1),
+            keep: section
+                .get("keep")
+                .and_then(Value::as_u64)
+                .map(|keep| keep as usize)
+                .unwrap_or(defaults.keep)
+                .max(1),
+            directory: section
+                .get("directory")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+
+    pub fn directory_for(&self, db_path: &str) -> PathBuf {
+        self.directory.clone().unwrap_or_else(|| {
+            Path::new(db_path)
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join("backups")
+        })
+    }
+
+    /// True when the newest snapshot is older than the interval.
+    pub fn is_due(&self, newest: Option<&Snapshot>, now: i64) -> bool {
+        newest.is_none_or(|snapshot| {
+            now.saturating_sub(snapshot.created_at) >= (self.interval_hours * 3600) as i64
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Snapshot {
+    pub name: String,
+    pub path: String,
+    pub bytes: u64,
+    pub created_at: i64,
+}
+
+/// Outcome of one backup pass.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct BackupRun {
+    pub at: i64,
+    pub snapshot: Option<String>,
+    pub bytes: u64,
+    pub remote_key: Option<String>,
+    pub pruned: usize,
+    pub error: Option<String>,
+}
+
+fn last_run_slot() -> &'static Mutex<Option<BackupRun>> {
+    static LAST_RUN: OnceLock<Mutex<Option<BackupRun>>> = OnceLock::new();
+    LAST_RUN.get_or_init(|| Mutex::new(None))
+}
+
+/// The most recent pass since the daemon started, failed or not.
+pub fn last_run() -> Option<BackupRun> {
+    last_run_slot()
+        .lock()
+        .map(|slot| slot.clone())
+        .unwrap_or(None)
+}
+
+fn record(run: &BackupRun) {
+    if let Ok(mut slot) = last_run_slot().lock() {
+        *slot = Some(run.clone());
+    }
+}
+
+pub fn snapshot_name(now: i64) -> String {
+    let stamp = Utc
+        .timestamp_opt(now, 0)
+        .single()
+        .unwrap_or_default()
+        .format(STAMP_FORMAT);
+    format!("{SNAPSHOT_PREFIX}{stamp}{SNAPSHOT_SUFFIX}")
+}
+
+fn snapshot_time(name: &str) -> Option<i64> {
+    let stamp = name
+        .strip_prefix(SNAPSHOT_PREFIX)?
+        .strip_suffix(SNAPSHOT_SUFFIX)?;
+    NaiveDateTime::parse_from_str(stamp, STAMP_FORMAT)
+        .ok()
+        .map(|time| time.and_utc().timestamp())
+}
+
+/// Snapshots in `dir`, oldest first. A missing directory has none.
+pub fn list_snapshots(dir: &Path) -> Result<Vec<Snapshot>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(ButterflyBotError::Runtime(format!(
+                "{}: {err}",
+                dir.display()
+            )))
+        }
+    };
+    let mut snapshots = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let created_at = snapshot_time(&name)?;
+            Some(Snapshot {
+                path: entry.path().to_string_lossy().to_string(),
+                bytes: entry.metadata().map(|meta| meta.len()).unwrap_or(0),
+                name,
+                created_at,
+            })
+        })
+        .collect::<Vec<_>>();
+    snapshots.sort_by_key(|snapshot| snapshot.created_at);
+    Ok(snapshots)
+}
+
+/// Copies the live database to `dest` while it stays in use. The copy is
+/// keyed like the original and only appears under `dest` once complete.
+pub fn snapshot(db_path: &str, dest: &Path) -> Result<()> {
+    let mut conn = crate::db::open_sqlcipher_connection_sync(db_path)?;
+    // Opening resolves which key the database actually uses.
+    let key = crate::db::get_sqlcipher_key()?;
+    let partial = dest.with_extension("partial");
+    let _ = std::fs::remove_file(&partial);
+    let attach = format!(
+        "ATTACH DATABASE '{}' AS backup KEY '{}';\n\
+         SELECT sqlcipher_export('backup');\n\
+         DETACH DATABASE backup;",
+        partial.to_string_lossy().replace('\'', "''"),
+        key.replace('\'', "''")
+    );
+    if let Err(err) = conn.batch_execute(&attach) {
+        let _ = conn.batch_execute("DETACH DATABASE backup;");
+        let _ = std::fs::remove_file(&partial);
+        return Err(ButterflyBotError::Runtime(format!(
+            "Snapshot failed: {err}"
+        )));
+    }
+    std::fs::rename(&partial, dest)
+        .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", dest.display())))
+}
+
+/// Deletes all but the newest `keep` snapshots; returns how many went.
+pub fn prune(dir: &Path, keep: usize) -> Result<usize> {
+    let snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    for snapshot in &snapshots[..excess] {
+        std::fs::remove_file(&snapshot.path)
+            .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", snapshot.path)))?;
+    }
+    Ok(excess)
+}
+
+/// Takes a snapshot, uploads it when a remote target wants backups, and
+/// applies retention in both places. The outcome is kept for
+/// [`last_run`] whether or not it succeeded.
+pub async fn run_backup(
+    db_path: &str,
+    config: &BackupConfig,
+    uploader: Option<&RemoteUploader>,
+    now: i64,
+) -> Result<BackupRun> {
+    let result = back_up(db_path, config, uploader, now).await;
+    let run = match &result {
+        Ok(run) => run.clone(),
+        Err(err) => BackupRun {
+            at: now,
+            error: Some(err.to_string()),
+            ..Default::default()
+        },
+    };
+    record(&run);
+    result
+}
+
+async fn back_up(
+    db_path: &str,
+    config: &BackupConfig,
+    uploader: Option<&RemoteUploader>,
+    now: i64,
+) -> Result<BackupRun> {
+    let dir = config.directory_for(db_path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", dir.display())))?;
+    let name = snapshot_name(now);
+    let dest = dir.join(&name);
+    {
+        let db_path = db_path.to_string();
+        let dest = dest.clone();
+        tokio::task::spawn_blocking(move || snapshot(&db_path, &dest))
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    }
+    let bytes = std::fs::read(&dest)
+        .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", dest.display())))?;
+    let mut pruned = prune(&dir, config.keep)?;
+
+    let mut remote_key = None;
+    if let Some(uploader) = uploader {
+        remote_key = uploader.upload(Category::Backups, &name, &bytes).await?;
+        if remote_key.is_some() {
+            let keys = uploader.list(Category::Backups).await?;
+            let excess = keys.len().saturating_sub(config.keep);
+            for key in &keys[..excess] {
+                uploader.delete(key).await?;
+            }
+            pruned += excess;
+        }
+    }
+
+    Ok(BackupRun {
+        at: now,
+        snapshot: Some(dest.to_string_lossy().to_string()),
+        bytes: bytes.len() as u64,
+        remote_key,
+        pruned,
+        error: None,
+    })
+}
+
+/// Outcome of [`restore`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct RestoreReport {
+    pub snapshot: PathBuf,
+    /// Copy of the database as it was before the swap, if there was one.
+    pub previous: Option<PathBuf>,
+}
+
+#[derive(QueryableByName)]
+struct IntegrityRow {
+    #[diesel(sql_type = Text)]
+    integrity_check: String,
+}
+
+/// Finds the snapshot `from` names: a file path, a snapshot name in `dir`,
+/// or `latest`.
+pub fn resolve_snapshot(dir: &Path, from: &str) -> Result<PathBuf> {
+    let from = from.trim();
+    if from == "latest" {
+        return list_snapshots(dir)?
+            .pop()
+            .map(|snapshot| PathBuf::from(snapshot.path))
+            .ok_or_else(|| {
+                ButterflyBotError::Config(format!("No snapshots in {}", dir.display()))
+            });
+    }
+    let path = Path::new(from);
+    if path.is_file() {
+        return Ok(path.to_path_buf());
+    }
+    let named = dir.join(from);
+    if named.is_file() {
+        return Ok(named);
+    }
+    Err(ButterflyBotError::Config(format!(
+        "No snapshot {from} (looked in {})",
+        dir.display()
+    )))
+}
+
+/// Checks that `path` decrypts with the current key and passes SQLite's
+/// `integrity_check`.
+pub fn validate_snapshot(path: &Path) -> Result<()> {
+    let path = path.to_string_lossy();
+    let mut conn = crate::db::open_existing_sqlcipher_connection_sync(&path)
+        .map_err(|e| ButterflyBotError::Config(format!("{path} is not readable: {e}")))?;
+    let rows = diesel::sql_query("PRAGMA integrity_check")
+        .load::<IntegrityRow>(&mut conn)
+        .map_err(|e| ButterflyBotError::Config(format!("{path}: {e}")))?;
+    let problems = rows
+        .into_iter()
+        .map(|row| row.integrity_check)
+        .filter(|result| result != "ok")
+        .collect::<Vec<_>>();
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ButterflyBotError::Config(format!(
+            "{path} failed the integrity check: {}",
+            problems.join("; ")
+        )))
+    }
+}
+
+/// Replaces the database at `db_path` with `snapshot`. Nothing may have the
+/// database open. The current file is kept as `<db>.pre-restore-<stamp>`,
+/// the snapshot is staged next to it and renamed into place, and the result
+/// is checked again; if that fails the previous database is put back.
+pub fn restore(db_path: &str, snapshot: &Path, now: i64) -> Result<RestoreReport> {
+    validate_snapshot(snapshot)?;
+    let db = Path::new(db_path);
+    let file_name = db
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| ButterflyBotError::Config(format!("{db_path} is not a file path")))?;
+    let io_error = |path: &Path, err: std::io::Error| {
+        ButterflyBotError::Runtime(format!("{}: {err}", path.display()))
+    };
+
+    let previous = if db.exists() {
+        // Fold the WAL in so the kept copy is complete on its own.
+        if let Ok(mut conn) = crate::db::open_existing_sqlcipher_connection_sync(db_path) {
+            let _ = conn.batch_execute("PRAGMA wal_checkpoint(TRUNCATE);");
+        }
+        let stamp = snapshot_name(now)
+            .trim_start_matches(SNAPSHOT_PREFIX)
+            .trim_end_matches(SNAPSHOT_SUFFIX)
+            .to_string();
+        let previous = db.with_file_name(format!("{file_name}.pre-restore-{stamp}"));
+        std::fs::copy(db, &previous).map_err(|e| io_error(&previous, e))?;
+        Some(previous)
+    } else {
+        None
+    };
+
+    // Staged in the same directory so the final rename cannot cross
+    // filesystems and is atomic.
+    let staging = db.with_file_name(format!("{file_name}.restoring"));
+    let swap_in = |source: &Path| -> Result<()> {
+        std::fs::copy(source, &staging).map_err(|e| io_error(&staging, e))?;
+        std::fs::File::open(&staging)
+            .and_then(|file| file.sync_all())
+            .map_err(|e| io_error(&staging, e))?;
+        // A leftover journal belongs to the old file and would be replayed
+        // onto the new one.
+        for suffix in ["-wal", "-shm"] {
+            let _ = std::fs::remove_file(db.with_file_name(format!("{file_name}{suffix}")));
+        }
+        std::fs::rename(&staging, db).map_err(|e| io_error(db, e))
+    };
+    swap_in(snapshot)?;
+
+    if let Err(err) = validate_snapshot(db) {
+        if let Some(previous) = &previous {
+            swap_in(previous)?;
+        }
+        return Err(ButterflyBotError::Runtime(format!(
+            "Restored database failed its check, previous database put back: {err}"
+        )));
+    }
+    Ok(RestoreReport {
+        snapshot: snapshot.to_path_buf(),
+        previous,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(QueryableByName)]
+    struct Note {
+        #[diesel(sql_type = Text)]
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_readable_copies_pruned_to_keep() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("live.db").to_string_lossy().to_string();
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        conn.batch_execute("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('hi');")
+            .unwrap();
+
+        let tools = json!({"settings": {"backups": {"keep": 2}}});
+        let config = BackupConfig::from_tools(Some(&tools));
+        assert_eq!(config.directory_for(&db_path), dir.path().join("backups"));
+        for hour in 0..3 {
+            run_backup(&db_path, &config, None, 1_700_000_000 + hour * 3600)
+                .await
+                .unwrap();
+        }
+
+        let snapshots = list_snapshots(&dir.path().join("backups")).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].created_at, 1_700_007_200);
+        assert_eq!(last_run().unwrap().pruned, 1);
+        assert!(!config.is_due(snapshots.last(), 1_700_010_000));
+        assert!(config.is_due(snapshots.last(), 1_700_007_200 + 86_400));
+
+        let mut copy = crate::db::open_sqlcipher_connection_sync(&snapshots[1].path).unwrap();
+        let notes = diesel::sql_query("SELECT body FROM notes")
+            .load::<Note>(&mut copy)
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].body, "hi");
+    }
+
+    #[test]
+    fn restore_swaps_in_a_valid_snapshot_and_keeps_the_old_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("live.db").to_string_lossy().to_string();
+        let backups = dir.path().join("backups");
+        std::fs::create_dir_all(&backups).unwrap();
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        conn.batch_execute("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('kept');")
+            .unwrap();
+        snapshot(&db_path, &backups.join(snapshot_name(1_700_000_000))).unwrap();
+        conn.batch_execute("DELETE FROM notes;").unwrap();
+        drop(conn);
+
+        let garbage = dir.path().join("garbage.db");
+        std::fs::write(&garbage, b"not a database at all").unwrap();
+        assert!(validate_snapshot(&garbage).is_err());
+        assert!(restore(&db_path, &garbage, 1_700_000_100).is_err());
+        assert!(resolve_snapshot(&backups, "butterfly-bot-19990101T000000Z.db").is_err());
+
+        let latest = resolve_snapshot(&backups, "latest").unwrap();
+        assert_eq!(
+            resolve_snapshot(&backups, "butterfly-bot-20231114T221320Z.db").unwrap(),
+            latest
+        );
+        let report = restore(&db_path, &latest, 1_700_000_200).unwrap();
+        assert!(report.previous.as_ref().unwrap().exists());
+        assert!(!dir.path().join("live.db.restoring").exists());
+
+        let mut restored = crate::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        let notes = diesel::sql_query("SELECT body FROM notes")
+            .load::<Note>(&mut restored)
+            .unwrap();
+        assert_eq!(notes.len(), 1);
+        assert_eq!(notes[0].body, "kept");
+    }
+}
@@ -14,6 +14,11 @@ struct Cli {
 
     #[arg(long, default_value_t = butterfly_bot::runtime_paths::default_db_path())]
     db: String,
+
+    /// Development checkout: rebuild the WASM tools from ./wasm-tool into
+    /// ./wasm (same as `butterfly-bot tools build`) before starting.
+    #[arg(long)]
+    dev: bool,
 }
 
 #[tokio::main]
@@ -22,5 +27,18 @@ async fn main() -> Result<()> {
     let cli = Cli::parse();
     let token = butterfly_bot::vault::ensure_daemon_auth_token()?;
 
+    if cli.dev {
+        let options = butterfly_bot::wasm_build::BuildOptions::for_repo(".");
+        let out_dir = std::fs::canonicalize(".")
+            .map(|root| root.join("wasm"))
+            .unwrap_or_else(|_| options.out_dir.clone());
+        let manifest =
+            tokio::task::spawn_blocking(move || butterfly_bot::wasm_build::build_tools(&options))
+                .await
+                .map_err(|e| butterfly_bot::ButterflyBotError::Runtime(e.to_string()))??;
+        tracing::info!(modules = manifest.modules.len(), "Rebuilt WASM tools");
+        std::env::set_var(butterfly_bot::wasm_bundle::DEV_WASM_DIR_ENV, &out_dir);
+    }
+
     daemon::run(&cli.host, cli.port, &cli.db, &token).await
 }
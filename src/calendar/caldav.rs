@@ -0,0 +1,131 @@
+//! CalDAV collections, read with a single `calendar-query` REPORT.
+
+use chrono::{TimeZone, Utc};
+use regex::Regex;
+use reqwest::Method;
+
+use crate::error::{ButterflyBotError, Result};
+
+/// `VCALENDAR` documents for events overlapping `[from, to)`.
+pub async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+    from: i64,
+    to: i64,
+) -> Result<Vec<String>> {
+    let method =
+        Method::from_bytes(b"REPORT").map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    let mut request = client
+        .request(method, url)
+        .header("Depth", "1")
+        .header("Content-Type", "application/xml; charset=utf-8")
+        .body(query_body(from, to));
+    if let Some(username) = username {
+        request = request.basic_auth(username, password);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ButterflyBotError::Http(format!(
+            "CalDAV REPORT on {url} failed with HTTP {status}"
+        )));
+    }
+    let body = response
+        .text()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    Ok(calendar_data(&body))
+}
+
+fn query_body(from: i64, to: i64) -> String {
+    let stamp = |ts: i64| {
+        Utc.timestamp_opt(ts, 0)
+            .single()
+            .unwrap_or_default()
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{}" end="{}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+        stamp(from),
+        stamp(to)
+    )
+}
+
+/// The `calendar-data` payloads of a multistatus response, whatever
+/// namespace prefix the server picked.
+fn calendar_data(body: &str) -> Vec<String> {
+    let pattern = Regex::new(
+        r"(?s)<(?:[A-Za-z0-9_-]+:)?calendar-data[^>]*>(.*?)</(?:[A-Za-z0-9_-]+:)?calendar-data>",
+    )
+    .expect("calendar-data pattern");
+    pattern
+        .captures_iter(body)
+        .map(|captures| {
+            let raw = captures[1].trim();
+            match raw
+                .strip_prefix("<![CDATA[")
+                .and_then(|rest| rest.strip_suffix("]]>"))
+            {
+                Some(cdata) => cdata.to_string(),
+                None => xml_unescape(raw),
+            }
+        })
+        .filter(|data| data.contains("BEGIN:VCALENDAR"))
+        .collect()
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_escaped_and_cdata_calendar_data() {
+        let body = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response><d:propstat><d:prop>
+    <cal:calendar-data>BEGIN:VCALENDAR&#13;
+SUMMARY:R&amp;D sync&#13;
+END:VCALENDAR</cal:calendar-data>
+  </d:prop></d:propstat></d:response>
+  <d:response><d:propstat><d:prop>
+    <C:calendar-data xmlns:C="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR
+SUMMARY:<Lunch>
+END:VCALENDAR]]></C:calendar-data>
+  </d:prop></d:propstat></d:response>
+</d:multistatus>"#;
+        let data = calendar_data(body);
+        assert_eq!(data.len(), 2);
+        assert!(data[0].contains("SUMMARY:R&D sync\r\n"));
+        assert!(data[1].contains("SUMMARY:<Lunch>"));
+        assert!(
+            query_body(0, 86_400).contains(r#"start="19700101T000000Z" end="19700102T000000Z""#)
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! Just enough iCalendar (RFC 5545) to read events and write a feed.
+//!
+//! Recurrence rules are not expanded: a recurring series shows up as its
+//! first occurrence plus any overridden instances (`RECURRENCE-ID`).
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::reminders::ReminderItem;
+
+/// How long a reminder lasts in the exported feed.
+const FEED_EVENT_MINUTES: i64 = 15;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct IcsEvent {
+    /// `UID`, suffixed with the recurrence id for overridden instances.
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub all_day: bool,
+}
+
+/// Events in an iCalendar document. Floating times and dates are read in
+/// `zone`, or the host's zone when `None`. Cancelled and undated events
+/// are skipped.
+pub fn parse_events(text: &str, zone: Option<Tz>) -> Vec<IcsEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<Vec<Property>> = None;
+    for line in unfold(text) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => current = Some(Vec::new()),
+            ("END", "VEVENT") => {
+                if let Some(event) = current.take().and_then(|props| build_event(&props, zone)) {
+                    events.push(event);
+                }
+            }
+            _ => {
+                if let Some(props) = current.as_mut() {
+                    props.push(property);
+                }
+            }
+        }
+    }
+    events
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside a quoted parameter.
+        let mut quoted = false;
+        let split = line.char_indices().find_map(|(index, ch)| match ch {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..split], &line[split + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((
+                    key.trim().to_ascii_uppercase(),
+                    value.trim().trim_matches('"').to_string(),
+                ))
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, key: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+fn build_event(props: &[Property], zone: Option<Tz>) -> Option<IcsEvent> {
+    // Only the event's own properties, not those of nested components.
+    let mut depth = 0;
+    let own: Vec<&Property> = props
+        .iter()
+        .filter(|prop| match prop.name.as_str() {
+            "BEGIN" => {
+                depth += 1;
+                false
+            }
+            "END" => {
+                depth -= 1;
+                false
+            }
+            _ => depth == 0,
+        })
+        .collect();
+    let get = |name: &str| own.iter().copied().find(|prop| prop.name == name);
+    if get("STATUS").is_some_and(|status| status.value.eq_ignore_ascii_case("CANCELLED")) {
+        return None;
+    }
+
+    let start = get("DTSTART")?;
+    let (starts_at, all_day) = parse_time(start, zone)?;
+    let ends_at = match (get("DTEND"), get("DURATION")) {
+        (Some(end), _) => parse_time(end, zone)?.0,
+        (None, Some(duration)) => starts_at + parse_duration(&duration.value)?,
+        (None, None) if all_day => starts_at + 24 * 60 * 60,
+        (None, None) => starts_at,
+    };
+    let mut uid = get("UID")
+        .map(|uid| uid.value.trim().to_string())
+        .unwrap_or_else(|| format!("dtstart-{starts_at}"));
+    if let Some(recurrence) = get("RECURRENCE-ID").and_then(|prop| parse_time(prop, zone)) {
+        uid.push_str(&format!("@{}", recurrence.0));
+    }
+    let text = |name: &str| {
+        get(name)
+            .map(|prop| unescape(&prop.value))
+            .filter(|value| !value.trim().is_empty())
+    };
+    Some(IcsEvent {
+        uid,
+        summary: text("SUMMARY").unwrap_or_else(|| "(busy)".to_string()),
+        description: text("DESCRIPTION"),
+        location: text("LOCATION"),
+        starts_at,
+        ends_at: ends_at.max(starts_at),
+        all_day,
+    })
+}
+
+/// Epoch seconds and whether the value was a bare date.
+fn parse_time(prop: &Property, zone: Option<Tz>) -> Option<(i64, bool)> {
+    let value = prop.value.trim();
+    if prop.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((local_timestamp(date.and_time(NaiveTime::MIN), zone)?, true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&naive).timestamp(), false));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = prop
+        .param("TZID")
+        .and_then(|tzid| tzid.trim_start_matches('/').parse::<Tz>().ok())
+        .or(zone);
+    Some((local_timestamp(naive, zone)?, false))
+}
+
+fn local_timestamp(naive: NaiveDateTime, zone: Option<Tz>) -> Option<i64> {
+    match zone {
+        Some(tz) => tz
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.timestamp()),
+        None => chrono::Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.timestamp()),
+    }
+}
+
+/// `P1D`, `PT1H30M`, `-PT15M`, `P2W` in seconds.
+fn parse_duration(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let mut total = 0i64;
+    let mut number = String::new();
+    for ch in rest.chars() {
+        match ch {
+            '0'..='9' => number.push(ch),
+            'T' => {}
+            unit => {
+                let amount: i64 = number.parse().ok()?;
+                number.clear();
+                total += amount
+                    * match unit {
+                        'W' => 7 * 24 * 3600,
+                        'D' => 24 * 3600,
+                        'H' => 3600,
+                        'M' => 60,
+                        'S' => 1,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+    Some(sign * total)
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Folds a content line at 75 octets without splitting a character.
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(ch);
+        width += ch.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+fn utc_stamp(ts: i64) -> String {
+    Utc.timestamp_opt(ts, 0)
+        .single()
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// An iCalendar feed of open reminders, each as a short event with an
+/// alarm at its due time.
+pub fn reminders_feed(name: &str, reminders: &[ReminderItem], now: i64) -> String {
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//butterfly-bot//reminders//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ] {
+        out.push_str(&fold(&line));
+    }
+    for reminder in reminders.iter().filter(|r| r.completed_at.is_none()) {
+        let end = reminder.due_at + Duration::minutes(FEED_EVENT_MINUTES).num_seconds();
+        for line in [
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:reminder-{}@butterfly-bot", reminder.id),
+            format!("DTSTAMP:{}", utc_stamp(now)),
+            format!("DTSTART:{}", utc_stamp(reminder.due_at)),
+            format!("DTEND:{}", utc_stamp(end)),
+            format!("SUMMARY:{}", escape(&reminder.title)),
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape(&reminder.title)),
+            "TRIGGER:PT0M".to_string(),
+            "END:VALARM".to_string(),
+            "END:VEVENT".to_string(),
+        ] {
+            out.push_str(&fold(&line));
+        }
+    }
+    out.push_str(&fold("END:VCALENDAR"));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:standup@example\r\n\
+DTSTART;TZID=Europe/Berlin:20260302T090000\r\n\
+DTEND;TZID=Europe/Berlin:20260302T091500\r\n\
+SUMMARY:Stand-up\\, team\r\n\
+DESCRIPTION:Line one\\nline \r\n two\r\n\
+BEGIN:VALARM\r\n\
+DESCRIPTION:alarm text\r\n\
+TRIGGER:-PT5M\r\n\
+END:VALARM\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:holiday@example\r\n\
+DTSTART;VALUE=DATE:20260305\r\n\
+SUMMARY:Holiday\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:call@example\r\n\
+DTSTART:20260303T140000Z\r\n\
+DURATION:PT1H30M\r\n\
+SUMMARY:Call\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:gone@example\r\n\
+DTSTART:20260303T140000Z\r\n\
+STATUS:CANCELLED\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[test]
+    fn parses_zoned_all_day_and_duration_events() {
+        let berlin: Tz = "Europe/Berlin".parse().unwrap();
+        let events = parse_events(SAMPLE, Some(berlin));
+        assert_eq!(events.len(), 3);
+
+        let standup = &events[0];
+        assert_eq!(standup.summary, "Stand-up, team");
+        assert_eq!(standup.description.as_deref(), Some("Line one\nline two"));
+        // 09:00 CET is 08:00 UTC.
+        assert_eq!(
+            standup.starts_at,
+            Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+        assert_eq!(standup.ends_at - standup.starts_at, 15 * 60);
+
+        let holiday = &events[1];
+        assert!(holiday.all_day);
+        assert_eq!(holiday.ends_at - holiday.starts_at, 24 * 3600);
+
+        assert_eq!(events[2].ends_at - events[2].starts_at, 90 * 60);
+        assert_eq!(parse_duration("-P1W"), Some(-7 * 24 * 3600));
+    }
+
+    #[test]
+    fn feed_escapes_folds_and_skips_completed_reminders() {
+        let reminder = |id: i32, title: &str, completed_at: Option<i64>| ReminderItem {
+            id,
+            title: title.to_string(),
+            due_at: 1_772_438_400,
+            created_at: 0,
+            completed_at,
+            fired_at: None,
+            target_ref: None,
+            delivery_window: None,
+            held_until: None,
+            escalation_level: 0,
+            escalated_at: None,
+            priority: "normal".to_string(),
+        };
+        let long = "x".repeat(100);
+        let feed = reminders_feed(
+            "Reminders",
+            &[
+                reminder(1, "Pay rent; call, bank", None),
+                reminder(2, &long, None),
+                reminder(3, "Done", Some(1)),
+            ],
+            1_772_400_000,
+        );
+        assert!(feed.contains("SUMMARY:Pay rent\\; call\\, bank\r\n"));
+        assert!(feed.contains("UID:reminder-2@butterfly-bot"));
+        assert!(!feed.contains("reminder-3@"));
+        assert!(feed.lines().all(|line| line.len() <= 75));
+
+        let parsed = parse_events(&feed, None);
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].summary, "Pay rent; call, bank");
+        assert_eq!(parsed[1].summary, long);
+        assert_eq!(parsed[0].ends_at - parsed[0].starts_at, 15 * 60);
+    }
+}
@@ -0,0 +1,575 @@
+//! Calendar events imported from ICS feeds and CalDAV accounts.
+//!
+//! Sources are configured under `tools.settings.calendar`:
+//!
+//! ```json
+//! {"sources": [
+//!    {"name": "work", "user_id": "alice", "ics_url": "https://…/basic.ics"},
+//!    {"name": "home", "user_id": "alice", "caldav_url": "https://dav.example/cal/",
+//!     "username": "alice", "password_secret": "caldav_home_password"}],
+//!  "past_days": 7, "window_days": 60, "poll_minutes": 30, "feed": true}
+//! ```
+//!
+//! Each sync replaces a source's events wholesale, so the table is a
+//! read-only mirror: edits happen in the calendar, never here. With
+//! `feed` on, reminders can also be subscribed to as an ICS feed.
+
+pub mod caldav;
+pub mod ics;
+
+use std::collections::HashSet;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use ics::IcsEvent;
+
+mod schema;
+use schema::calendar_events;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const CALENDAR_EVENTS_UP_SQL: &str =
+    include_str!("../../migrations/20260319_create_calendar_events/up.sql");
+
+pub const SOURCE_TYPE: &str = "calendar";
+const DEFAULT_PAST_DAYS: i64 = 7;
+const DEFAULT_WINDOW_DAYS: i64 = 60;
+const DEFAULT_POLL_MINUTES: u64 = 30;
+const FEED_TOKEN_PREFIX: &str = "calendar_feed_token:";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceKind {
+    Ics {
+        url: String,
+    },
+    CalDav {
+        url: String,
+        username: Option<String>,
+        /// Vault entry holding the password.
+        password_secret: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarSource {
+    pub name: String,
+    pub user_id: String,
+    pub kind: SourceKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarConfig {
+    pub sources: Vec<CalendarSource>,
+    pub past_days: i64,
+    pub window_days: i64,
+    pub poll_minutes: u64,
+    /// Whether reminders may be exported as a subscribable feed.
+    pub feed: bool,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            sources: Vec::new(),
+            past_days: DEFAULT_PAST_DAYS,
+            window_days: DEFAULT_WINDOW_DAYS,
+            poll_minutes: DEFAULT_POLL_MINUTES,
+            feed: false,
+        }
+    }
+}
+
+impl CalendarConfig {
+    /// Sources without a name, user or URL are skipped.
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("calendar"))
+        else {
+            return Self::default();
+        };
+        let sources = section
+            .get("sources")
+            .and_then(Value::as_array)
+            .map(|sources| sources.iter().filter_map(parse_source).collect())
+            .unwrap_or_default();
+        let days = |key: &str, default: i64| {
+            section
+                .get(key)
+                .and_then(Value::as_i64)
+                .filter(|days| *days >= 0)
+                .unwrap_or(default)
+        };
+        Self {
+            sources,
+            past_days: days("past_days", DEFAULT_PAST_DAYS),
+            window_days: days("window_days", DEFAULT_WINDOW_DAYS),
+            poll_minutes: section
+                .get("poll_minutes")
+                .and_then(Value::as_u64)
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(DEFAULT_POLL_MINUTES),
+            feed: section
+                .get("feed")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        }
+    }
+}
+
+fn parse_source(value: &Value) -> Option<CalendarSource> {
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let kind = match (text("ics_url"), text("caldav_url")) {
+        (Some(url), _) => SourceKind::Ics { url },
+        (None, Some(url)) => SourceKind::CalDav {
+            url,
+            username: text("username"),
+            password_secret: text("password_secret"),
+        },
+        (None, None) => return None,
+    };
+    Some(CalendarSource {
+        name: text("name")?,
+        user_id: text("user_id")?,
+        kind,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CalendarEvent {
+    pub id: i32,
+    pub user_id: String,
+    pub source: String,
+    pub uid: String,
+    pub title: String,
+    pub location: Option<String>,
+    pub description: Option<String>,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub all_day: bool,
+    pub synced_at: i64,
+}
+
+impl CalendarEvent {
+    /// `calendar:<source>:<uid>`, the event's inbox origin ref.
+    pub fn origin_ref(&self) -> String {
+        format!("{SOURCE_TYPE}:{}:{}", self.source, self.uid)
+    }
+}
+
+#[derive(Queryable)]
+struct CalendarEventRow {
+    id: i32,
+    user_id: String,
+    source: String,
+    uid: String,
+    title: String,
+    location: Option<String>,
+    description: Option<String>,
+    starts_at: i64,
+    ends_at: i64,
+    all_day: bool,
+    synced_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = calendar_events)]
+struct NewCalendarEvent<'a> {
+    user_id: &'a str,
+    source: &'a str,
+    uid: &'a str,
+    title: &'a str,
+    location: Option<&'a str>,
+    description: Option<&'a str>,
+    starts_at: i64,
+    ends_at: i64,
+    all_day: bool,
+    synced_at: i64,
+}
+
+/// Outcome of syncing one source.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SyncReport {
+    pub source: String,
+    pub user_id: String,
+    pub events: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct CalendarStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl CalendarStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_calendar_events_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Swaps the stored events of `source` for `events`. Duplicate UIDs
+    /// keep their first occurrence.
+    pub async fn replace_source(
+        &self,
+        user_id: &str,
+        source: &str,
+        events: &[IcsEvent],
+    ) -> Result<usize> {
+        let now = self.clock.now();
+        let mut seen = HashSet::new();
+        let rows: Vec<NewCalendarEvent<'_>> = events
+            .iter()
+            .filter(|event| seen.insert(event.uid.as_str()))
+            .map(|event| NewCalendarEvent {
+                user_id,
+                source,
+                uid: &event.uid,
+                title: &event.summary,
+                location: event.location.as_deref(),
+                description: event.description.as_deref(),
+                starts_at: event.starts_at,
+                ends_at: event.ends_at,
+                all_day: event.all_day,
+                synced_at: now,
+            })
+            .collect();
+        let mut conn = self.write_conn().await?;
+        diesel::delete(
+            calendar_events::table
+                .filter(calendar_events::user_id.eq(user_id))
+                .filter(calendar_events::source.eq(source)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        diesel::insert_into(calendar_events::table)
+            .values(&rows)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Events overlapping `[from, to)`, earliest first.
+    pub async fn list(&self, user_id: &str, from: i64, to: i64) -> Result<Vec<CalendarEvent>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<CalendarEventRow> = calendar_events::table
+            .filter(calendar_events::user_id.eq(user_id))
+            .filter(calendar_events::ends_at.ge(from))
+            .filter(calendar_events::starts_at.lt(to))
+            .order(calendar_events::starts_at.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(calendar_events::table.filter(calendar_events::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// Fetches every configured source (or only `user_id`'s) and stores the
+/// events inside the sync window. A failing source keeps its previous
+/// events and reports the error.
+pub async fn sync(
+    store: &CalendarStore,
+    config: &CalendarConfig,
+    user_id: Option<&str>,
+) -> Vec<SyncReport> {
+    let now = store.clock().now();
+    let from = now - config.past_days * 86_400;
+    let to = now + config.window_days * 86_400;
+    let client = match reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            return config
+                .sources
+                .iter()
+                .map(|source| report(source, Err(ButterflyBotError::Runtime(err.to_string()))))
+                .collect()
+        }
+    };
+    let mut reports = Vec::new();
+    for source in &config.sources {
+        if user_id.is_some_and(|user_id| user_id != source.user_id) {
+            continue;
+        }
+        let result = match fetch_source(&client, source, from, to).await {
+            Ok(events) => {
+                store
+                    .replace_source(&source.user_id, &source.name, &events)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+        reports.push(report(source, result));
+    }
+    reports
+}
+
+fn report(source: &CalendarSource, result: Result<usize>) -> SyncReport {
+    let (events, error) = match result {
+        Ok(events) => (events, None),
+        Err(err) => (0, Some(err.to_string())),
+    };
+    SyncReport {
+        source: source.name.clone(),
+        user_id: source.user_id.clone(),
+        events,
+        error,
+    }
+}
+
+async fn fetch_source(
+    client: &reqwest::Client,
+    source: &CalendarSource,
+    from: i64,
+    to: i64,
+) -> Result<Vec<IcsEvent>> {
+    let zone = crate::timezones::zone_for(&source.user_id);
+    let documents = match &source.kind {
+        SourceKind::Ics { url } => {
+            // Phones hand out `webcal://` links for the same HTTPS feed.
+            let url = match url.strip_prefix("webcal://") {
+                Some(rest) => format!("https://{rest}"),
+                None => url.clone(),
+            };
+            let response = client
+                .get(&url)
+                .send()
+                .await
+                .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+            let status = response.status();
+            if !status.is_success() {
+                return Err(ButterflyBotError::Http(format!(
+                    "Calendar feed {url} returned HTTP {status}"
+                )));
+            }
+            vec![response
+                .text()
+                .await
+                .map_err(|e| ButterflyBotError::Http(e.to_string()))?]
+        }
+        SourceKind::CalDav {
+            url,
+            username,
+            password_secret,
+        } => {
+            let password = match password_secret {
+                Some(name) => crate::vault::get_secret(name)?,
+                None => None,
+            };
+            caldav::fetch_range(
+                client,
+                url,
+                username.as_deref(),
+                password.as_deref(),
+                from,
+                to,
+            )
+            .await?
+        }
+    };
+    Ok(documents
+        .iter()
+        .flat_map(|document| ics::parse_events(document, zone))
+        .filter(|event| event.ends_at >= from && event.starts_at < to)
+        .collect())
+}
+
+/// The secret in `user_id`'s feed URL, minted on first use. `rotate`
+/// replaces it, which breaks every existing subscription.
+pub fn feed_token(user_id: &str, rotate: bool) -> Result<String> {
+    let name = format!("{FEED_TOKEN_PREFIX}{user_id}");
+    if !rotate {
+        if let Some(token) = crate::vault::get_secret(&name)?.filter(|token| !token.is_empty()) {
+            return Ok(token);
+        }
+    }
+    use rand::TryRng;
+    let mut bytes = [0u8; 24];
+    rand::rngs::SysRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    let token = hex::encode(bytes);
+    crate::vault::set_secret(&name, &token)?;
+    Ok(token)
+}
+
+/// Whether `token` is `user_id`'s current feed token. Users who never
+/// asked for a feed have none, so nothing matches.
+pub fn verify_feed_token(user_id: &str, token: &str) -> Result<bool> {
+    let Some(expected) = crate::vault::get_secret(&format!("{FEED_TOKEN_PREFIX}{user_id}"))?
+        .filter(|expected| !expected.is_empty())
+    else {
+        return Ok(false);
+    };
+    Ok(crate::security::tokens::tokens_match(&expected, token))
+}
+
+fn map_row(row: CalendarEventRow) -> CalendarEvent {
+    CalendarEvent {
+        id: row.id,
+        user_id: row.user_id,
+        source: row.source,
+        uid: row.uid,
+        title: row.title,
+        location: row.location,
+        description: row.description,
+        starts_at: row.starts_at,
+        ends_at: row.ends_at,
+        all_day: row.all_day,
+        synced_at: row.synced_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_calendar_events_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM calendar_events LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    CALENDAR_EVENTS_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    const FEED: &str = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+UID:a\r\n\
+DTSTART:20260302T090000Z\r\n\
+DTEND:20260302T100000Z\r\n\
+SUMMARY:Planning\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:old\r\n\
+DTSTART:20250101T090000Z\r\n\
+SUMMARY:Long ago\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+    #[tokio::test]
+    async fn sync_replaces_source_events_inside_the_window() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method("GET").path("/cal.ics");
+                then.status(200).body(FEED);
+            })
+            .await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calendar.db");
+        let clock = crate::clock::ManualClock::new(1_772_400_000);
+        let store = CalendarStore::new(path.to_str().unwrap())
+            .await
+            .unwrap()
+            .with_clock(clock);
+        let tools = json!({"settings": {"calendar": {"sources": [
+            {"name": "work", "user_id": "u", "ics_url": server.url("/cal.ics")},
+            {"name": "broken", "user_id": "u", "ics_url": server.url("/missing.ics")},
+            {"name": "nameless", "ics_url": "https://example.invalid"}
+        ]}}});
+        let config = CalendarConfig::from_tools(Some(&tools));
+        assert_eq!(config.sources.len(), 2);
+
+        // Pretend an earlier sync left an event that has since been deleted.
+        let stale = ics::parse_events(&FEED.replace("UID:a", "UID:stale"), None);
+        store.replace_source("u", "work", &stale).await.unwrap();
+
+        let reports = sync(&store, &config, Some("u")).await;
+        assert_eq!(reports[0].events, 1);
+        assert!(reports[0].error.is_none());
+        assert!(reports[1].error.as_deref().unwrap().contains("404"));
+
+        let events = store.list("u", 0, i64::MAX).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Planning");
+        assert_eq!(events[0].origin_ref(), "calendar:work:a");
+        assert_eq!(store.clear_user("u").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,15 @@
+diesel::table! {
+    calendar_events (id) {
+        id -> Integer,
+        user_id -> Text,
+        source -> Text,
+        uid -> Text,
+        title -> Text,
+        location -> Nullable<Text>,
+        description -> Nullable<Text>,
+        starts_at -> BigInt,
+        ends_at -> BigInt,
+        all_day -> Bool,
+        synced_at -> BigInt,
+    }
+}
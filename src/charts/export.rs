@@ -0,0 +1,729 @@
+//! Shareable snapshots of the Gantt and Dependencies views.
+//!
+//! The UI hands over the rows it is currently showing, so filters and
+//! presentation-mode redaction carry into the file. Both views are laid out
+//! once as a list of shapes and then written as SVG, PNG or a single-page
+//! PDF; none of the three needs anything beyond what `charts` already does.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+
+use super::{hex, png, xml_escape, BACKGROUND, GRID, INK};
+use crate::error::{ButterflyBotError, Result};
+
+const GANTT_WIDTH: f64 = 960.0;
+const LABEL_WIDTH: f64 = 240.0;
+const HEADER_HEIGHT: f64 = 56.0;
+const ROW_HEIGHT: f64 = 26.0;
+const AXIS_HEIGHT: f64 = 36.0;
+const MARGIN: f64 = 16.0;
+const MAX_AXIS_TICKS: i64 = 8;
+const MAX_LABEL_CHARS: usize = 28;
+
+const NODE_WIDTH: f64 = 200.0;
+const NODE_HEIGHT: f64 = 28.0;
+const COLUMN_GAP: f64 = 72.0;
+const NODE_GAP: f64 = 14.0;
+
+const OPEN: [u8; 3] = [0x6c, 0x8c, 0xff];
+const DONE: [u8; 3] = [0x5c, 0xd6, 0xa0];
+const BLOCKED: [u8; 3] = [0xff, 0x6c, 0x6c];
+const MISSING: [u8; 3] = [0xf2, 0xc9, 0x4c];
+const CRITICAL: [u8; 3] = [0xff, 0xa8, 0x3c];
+const PALETTE: [[u8; 3]; 8] = [
+    BACKGROUND, GRID, INK, OPEN, DONE, BLOCKED, MISSING, CRITICAL,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    Svg,
+    Pdf,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "png" => Some(ExportFormat::Png),
+            "svg" => Some(ExportFormat::Svg),
+            "pdf" => Some(ExportFormat::Pdf),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Svg => "svg",
+            ExportFormat::Pdf => "pdf",
+        }
+    }
+}
+
+/// Colour of a bar or node, matching the tab's legend.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportTone {
+    #[default]
+    Open,
+    Done,
+    Blocked,
+    /// A dependency that isn't in the current view.
+    Missing,
+    /// Open work on the Gantt critical path.
+    Critical,
+}
+
+impl ExportTone {
+    fn color(self) -> u8 {
+        match self {
+            ExportTone::Open => 3,
+            ExportTone::Done => 4,
+            ExportTone::Blocked => 5,
+            ExportTone::Missing => 6,
+            ExportTone::Critical => 7,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GanttBar {
+    pub label: String,
+    pub start: i64,
+    pub end: i64,
+    #[serde(default)]
+    pub tone: ExportTone,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GanttExport {
+    pub title: String,
+    pub bars: Vec<GanttBar>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DependencyNode {
+    pub id: String,
+    pub label: String,
+    #[serde(default)]
+    pub tone: ExportTone,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DependencyExport {
+    pub title: String,
+    pub nodes: Vec<DependencyNode>,
+    /// `(dependent, dependency)` pairs of node ids.
+    pub edges: Vec<(String, String)>,
+}
+
+pub fn exports_dir() -> PathBuf {
+    super::charts_dir().join("exports")
+}
+
+pub fn render_gantt(export: &GanttExport, format: ExportFormat) -> Result<Vec<u8>> {
+    if export.bars.is_empty() {
+        return Err(ButterflyBotError::Runtime(
+            "Nothing in the Gantt view to export".to_string(),
+        ));
+    }
+    Ok(gantt_scene(export).render(format))
+}
+
+pub fn render_dependencies(export: &DependencyExport, format: ExportFormat) -> Result<Vec<u8>> {
+    if export.edges.is_empty() {
+        return Err(ButterflyBotError::Runtime(
+            "No dependency edges to export".to_string(),
+        ));
+    }
+    Ok(dependency_scene(export).render(format))
+}
+
+/// Writes `bytes` to `<dir>/<stem>-<timestamp>.<ext>` and returns the path.
+pub fn write_export(
+    dir: &Path,
+    stem: &str,
+    format: ExportFormat,
+    bytes: &[u8],
+    now: i64,
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    let stamp = Local
+        .timestamp_opt(now, 0)
+        .single()
+        .map(|time| time.format("%Y%m%d-%H%M%S").to_string())
+        .unwrap_or_else(|| now.to_string());
+    let path = dir.join(format!("{stem}-{stamp}.{}", format.extension()));
+    std::fs::write(&path, bytes).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(path)
+}
+
+fn truncate_label(value: &str) -> String {
+    if value.chars().count() <= MAX_LABEL_CHARS {
+        return value.to_string();
+    }
+    let mut short: String = value.chars().take(MAX_LABEL_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+fn gantt_scene(export: &GanttExport) -> Scene {
+    let height = HEADER_HEIGHT + ROW_HEIGHT * export.bars.len() as f64 + AXIS_HEIGHT;
+    let mut scene = Scene::new(GANTT_WIDTH, height);
+    scene.text(MARGIN, 28.0, 15.0, &export.title, 2, Anchor::Start);
+
+    let start = export.bars.iter().map(|bar| bar.start).min().unwrap_or(0);
+    let end = export
+        .bars
+        .iter()
+        .map(|bar| bar.end)
+        .max()
+        .unwrap_or(start)
+        .max(start + 3600);
+    let left = LABEL_WIDTH;
+    let right = GANTT_WIDTH - MARGIN;
+    let x_at = |ts: i64| left + (ts - start) as f64 / (end - start) as f64 * (right - left);
+    let bottom = HEADER_HEIGHT + ROW_HEIGHT * export.bars.len() as f64;
+
+    // Short spans get evenly spaced time ticks; longer ones tick on day
+    // boundaries, a day or more apart.
+    let span = end - start;
+    let (mut tick, tick_step, format) = if span <= 2 * 86_400 {
+        (start, (span / MAX_AXIS_TICKS).max(60), "%m-%d %H:%M")
+    } else {
+        let days = (span + 86_399) / 86_400;
+        let step = (days + MAX_AXIS_TICKS - 1) / MAX_AXIS_TICKS * 86_400;
+        ((start / 86_400 + 1) * 86_400, step, "%m-%d")
+    };
+    while tick <= end {
+        let x = x_at(tick);
+        scene.line(x, HEADER_HEIGHT - 6.0, x, bottom, 1, false);
+        if let Some(time) = Local.timestamp_opt(tick, 0).single() {
+            scene.text(
+                x,
+                bottom + 20.0,
+                11.0,
+                &time.format(format).to_string(),
+                2,
+                Anchor::Middle,
+            );
+        }
+        tick += tick_step;
+    }
+
+    for (index, bar) in export.bars.iter().enumerate() {
+        let y = HEADER_HEIGHT + ROW_HEIGHT * index as f64;
+        scene.text(
+            MARGIN,
+            y + ROW_HEIGHT / 2.0 + 4.0,
+            12.0,
+            &truncate_label(&bar.label),
+            2,
+            Anchor::Start,
+        );
+        let x0 = x_at(bar.start.max(start));
+        let x1 = x_at(bar.end.min(end)).max(x0 + 4.0);
+        scene.rect(x0, y + 6.0, x1 - x0, ROW_HEIGHT - 12.0, bar.tone.color());
+    }
+    scene.line(left, bottom, right, bottom, 2, false);
+    scene
+}
+
+/// Dependencies sit left of the items that wait on them: each node's column
+/// is the length of its longest dependency chain. Cycles are cut off after
+/// one pass per node so they can't push columns out indefinitely.
+fn dependency_columns(export: &DependencyExport) -> HashMap<&str, usize> {
+    let mut depth: HashMap<&str, usize> = export
+        .nodes
+        .iter()
+        .map(|node| (node.id.as_str(), 0))
+        .collect();
+    for _ in 0..export.nodes.len() {
+        let mut changed = false;
+        for (dependent, dependency) in &export.edges {
+            let needed = depth.get(dependency.as_str()).copied().unwrap_or(0) + 1;
+            let current = depth.entry(dependent.as_str()).or_insert(0);
+            if *current < needed {
+                *current = needed;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    depth
+}
+
+fn dependency_scene(export: &DependencyExport) -> Scene {
+    let columns = dependency_columns(export);
+    let column_count = columns.values().max().copied().unwrap_or(0) + 1;
+    let mut per_column: Vec<Vec<&DependencyNode>> = vec![Vec::new(); column_count];
+    for node in &export.nodes {
+        let column = columns.get(node.id.as_str()).copied().unwrap_or(0);
+        per_column[column].push(node);
+    }
+    let tallest = per_column.iter().map(Vec::len).max().unwrap_or(1).max(1);
+    let width = MARGIN * 2.0 + column_count as f64 * (NODE_WIDTH + COLUMN_GAP) - COLUMN_GAP;
+    let height = HEADER_HEIGHT + tallest as f64 * (NODE_HEIGHT + NODE_GAP) + MARGIN;
+    let mut scene = Scene::new(width.max(480.0), height);
+    scene.text(MARGIN, 28.0, 15.0, &export.title, 2, Anchor::Start);
+
+    let mut positions: HashMap<&str, (f64, f64)> = HashMap::new();
+    for (column, nodes) in per_column.iter().enumerate() {
+        for (row, node) in nodes.iter().enumerate() {
+            let x = MARGIN + column as f64 * (NODE_WIDTH + COLUMN_GAP);
+            let y = HEADER_HEIGHT + row as f64 * (NODE_HEIGHT + NODE_GAP);
+            positions.insert(node.id.as_str(), (x, y));
+        }
+    }
+
+    for (dependent, dependency) in &export.edges {
+        let (Some(&(tx, ty)), Some(&(fx, fy))) = (
+            positions.get(dependent.as_str()),
+            positions.get(dependency.as_str()),
+        ) else {
+            continue;
+        };
+        let from = (fx + NODE_WIDTH, fy + NODE_HEIGHT / 2.0);
+        let to = (tx, ty + NODE_HEIGHT / 2.0);
+        // Back edges from a cycle run right to left; dash them.
+        let backwards = to.0 <= from.0;
+        scene.line(from.0, from.1, to.0, to.1, 2, backwards);
+        scene.line(to.0 - 6.0, to.1 - 4.0, to.0, to.1, 2, false);
+        scene.line(to.0 - 6.0, to.1 + 4.0, to.0, to.1, 2, false);
+    }
+
+    for node in &export.nodes {
+        let Some(&(x, y)) = positions.get(node.id.as_str()) else {
+            continue;
+        };
+        scene.rect(x, y, NODE_WIDTH, NODE_HEIGHT, 1);
+        scene.rect(x, y, 5.0, NODE_HEIGHT, node.tone.color());
+        scene.text(
+            x + 12.0,
+            y + NODE_HEIGHT / 2.0 + 4.0,
+            12.0,
+            &truncate_label(&node.label),
+            2,
+            Anchor::Start,
+        );
+    }
+    scene
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Anchor {
+    Start,
+    Middle,
+}
+
+/// Shapes in top-left coordinates; colours index into `PALETTE`.
+enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        color: u8,
+    },
+    Line {
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        color: u8,
+        dashed: bool,
+    },
+    /// `y` is the text baseline.
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        value: String,
+        color: u8,
+        anchor: Anchor,
+    },
+}
+
+struct Scene {
+    width: f64,
+    height: f64,
+    shapes: Vec<Shape>,
+}
+
+impl Scene {
+    fn new(width: f64, height: f64) -> Self {
+        Self {
+            width: width.ceil(),
+            height: height.ceil(),
+            shapes: Vec::new(),
+        }
+    }
+
+    fn rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: u8) {
+        self.shapes.push(Shape::Rect {
+            x,
+            y,
+            width,
+            height,
+            color,
+        });
+    }
+
+    fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: u8, dashed: bool) {
+        self.shapes.push(Shape::Line {
+            x0,
+            y0,
+            x1,
+            y1,
+            color,
+            dashed,
+        });
+    }
+
+    fn text(&mut self, x: f64, y: f64, size: f64, value: &str, color: u8, anchor: Anchor) {
+        self.shapes.push(Shape::Text {
+            x,
+            y,
+            size,
+            value: value.to_string(),
+            color,
+            anchor,
+        });
+    }
+
+    fn render(&self, format: ExportFormat) -> Vec<u8> {
+        match format {
+            ExportFormat::Svg => self.svg().into_bytes(),
+            ExportFormat::Png => self.png(),
+            ExportFormat::Pdf => self.pdf(),
+        }
+    }
+
+    fn svg(&self) -> String {
+        let (width, height) = (self.width, self.height);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\" font-family=\"sans-serif\">\n<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+            hex(BACKGROUND)
+        );
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => svg.push_str(&format!(
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{height:.1}\" fill=\"{}\"/>\n",
+                    hex(PALETTE[*color as usize])
+                )),
+                Shape::Line {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    color,
+                    dashed,
+                } => svg.push_str(&format!(
+                    "<line x1=\"{x0:.1}\" y1=\"{y0:.1}\" x2=\"{x1:.1}\" y2=\"{y1:.1}\" stroke=\"{}\"{}/>\n",
+                    hex(PALETTE[*color as usize]),
+                    if *dashed { " stroke-dasharray=\"6 4\"" } else { "" }
+                )),
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    value,
+                    color,
+                    anchor,
+                } => svg.push_str(&format!(
+                    "<text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"{size}\" text-anchor=\"{}\" fill=\"{}\">{}</text>\n",
+                    if *anchor == Anchor::Middle { "middle" } else { "start" },
+                    hex(PALETTE[*color as usize]),
+                    xml_escape(value)
+                )),
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// Text goes through the chart bitmap font, so labels come out in
+    /// capitals and characters outside it are skipped.
+    fn png(&self) -> Vec<u8> {
+        let (width, height) = (self.width as u32, self.height as u32);
+        let mut canvas = png::Canvas::new(width, height, 0);
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width,
+                    height,
+                    color,
+                } => canvas.fill_rect(*x, *y, *width, *height, *color),
+                Shape::Line {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    color,
+                    dashed,
+                } => canvas.line(*x0, *y0, *x1, *y1, *color, *dashed),
+                Shape::Text {
+                    x,
+                    y,
+                    value,
+                    color,
+                    anchor,
+                    ..
+                } => {
+                    let top = y - 10.0;
+                    match anchor {
+                        Anchor::Start => canvas.text(*x, top, value, *color),
+                        Anchor::Middle => canvas.text_centered(*x, top, value, *color),
+                    }
+                }
+            }
+        }
+        png::encode_indexed(width, height, &PALETTE, canvas.pixels())
+    }
+
+    /// A one-page PDF with the built-in Helvetica font. Text outside
+    /// Latin-1 is replaced, since the standard fonts carry no other glyphs.
+    fn pdf(&self) -> Vec<u8> {
+        let (width, height) = (self.width, self.height);
+        let mut content = String::new();
+        let fill = |color: u8| {
+            let [r, g, b] = PALETTE[color as usize];
+            format!(
+                "{:.3} {:.3} {:.3}",
+                r as f64 / 255.0,
+                g as f64 / 255.0,
+                b as f64 / 255.0
+            )
+        };
+        content.push_str(&format!("{} rg 0 0 {width} {height} re f\n", fill(0)));
+        for shape in &self.shapes {
+            match shape {
+                Shape::Rect {
+                    x,
+                    y,
+                    width: w,
+                    height: h,
+                    color,
+                } => content.push_str(&format!(
+                    "{} rg {x:.1} {:.1} {w:.1} {h:.1} re f\n",
+                    fill(*color),
+                    height - y - h
+                )),
+                Shape::Line {
+                    x0,
+                    y0,
+                    x1,
+                    y1,
+                    color,
+                    dashed,
+                } => content.push_str(&format!(
+                    "{} RG {} d {x0:.1} {:.1} m {x1:.1} {:.1} l S\n",
+                    fill(*color),
+                    if *dashed { "[6 4] 0" } else { "[] 0" },
+                    height - y0,
+                    height - y1
+                )),
+                Shape::Text {
+                    x,
+                    y,
+                    size,
+                    value,
+                    color,
+                    anchor,
+                } => {
+                    // Helvetica averages a little over half an em per glyph.
+                    let shift = if *anchor == Anchor::Middle {
+                        value.chars().count() as f64 * size * 0.28
+                    } else {
+                        0.0
+                    };
+                    content.push_str(&format!(
+                        "BT {} rg /F1 {size} Tf {:.1} {:.1} Td ({}) Tj ET\n",
+                        fill(*color),
+                        x - shift,
+                        height - y,
+                        pdf_string(value)
+                    ));
+                }
+            }
+        }
+
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width} {height}] /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R >>"
+            ),
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        ];
+        let mut out = b"%PDF-1.4\n".to_vec();
+        let mut offsets = Vec::new();
+        for (index, body) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{body}\nendobj\n", index + 1).as_bytes());
+        }
+        let stream = latin1(&content);
+        offsets.push(out.len());
+        out.extend_from_slice(
+            format!("5 0 obj\n<< /Length {} >>\nstream\n", stream.len()).as_bytes(),
+        );
+        out.extend_from_slice(&stream);
+        out.extend_from_slice(b"\nendstream\nendobj\n");
+
+        let xref = out.len();
+        out.extend_from_slice(
+            format!("xref\n0 {}\n0000000000 65535 f \n", offsets.len() + 1).as_bytes(),
+        );
+        for offset in &offsets {
+            out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+        }
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+                offsets.len() + 1
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+fn pdf_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '(' | ')' | '\\' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '…' => escaped.push_str("..."),
+            '→' => escaped.push_str("->"),
+            ch if (ch as u32) < 0x20 => escaped.push(' '),
+            ch if (ch as u32) <= 0xff => escaped.push(ch),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+fn latin1(value: &str) -> Vec<u8> {
+    value
+        .chars()
+        .map(|ch| u8::try_from(ch as u32).unwrap_or(b'?'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gantt() -> GanttExport {
+        GanttExport {
+            title: "Launch (Q4)".to_string(),
+            bars: vec![
+                GanttBar {
+                    label: "Book venue".to_string(),
+                    start: 1_760_000_000,
+                    end: 1_760_086_400,
+                    tone: ExportTone::Done,
+                },
+                GanttBar {
+                    label: "Send invites <all>".to_string(),
+                    start: 1_760_086_400,
+                    end: 1_760_300_000,
+                    tone: ExportTone::Blocked,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn gantt_exports_in_each_format() {
+        let svg = String::from_utf8(render_gantt(&gantt(), ExportFormat::Svg).unwrap()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Send invites &lt;all&gt;"));
+        assert!(svg.contains(&hex(BLOCKED)));
+
+        let png = render_gantt(&gantt(), ExportFormat::Png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(
+            u32::from_be_bytes(png[16..20].try_into().unwrap()),
+            GANTT_WIDTH as u32
+        );
+
+        let pdf = render_gantt(&gantt(), ExportFormat::Pdf).unwrap();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("(Launch \\(Q4\\)) Tj"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .and_then(|line| line.parse().ok())
+            .unwrap();
+        assert!(text[startxref..].starts_with("xref"));
+
+        let empty = GanttExport {
+            title: "Empty".to_string(),
+            bars: Vec::new(),
+        };
+        assert!(render_gantt(&empty, ExportFormat::Png).is_err());
+    }
+
+    #[test]
+    fn dependencies_place_each_node_right_of_its_dependencies() {
+        let node = |id: &str, tone| DependencyNode {
+            id: id.to_string(),
+            label: id.to_string(),
+            tone,
+        };
+        let export = DependencyExport {
+            title: "Dependencies".to_string(),
+            nodes: vec![
+                node("c", ExportTone::Open),
+                node("b", ExportTone::Blocked),
+                node("a", ExportTone::Done),
+                node("x", ExportTone::Missing),
+            ],
+            edges: vec![
+                ("c".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+                ("c".to_string(), "x".to_string()),
+            ],
+        };
+        let columns = dependency_columns(&export);
+        assert_eq!(columns["a"], 0);
+        assert_eq!(columns["x"], 0);
+        assert_eq!(columns["b"], 1);
+        assert_eq!(columns["c"], 2);
+
+        let looped = DependencyExport {
+            title: "Loop".to_string(),
+            nodes: vec![node("a", ExportTone::Open), node("b", ExportTone::Open)],
+            edges: vec![
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "a".to_string()),
+            ],
+        };
+        let svg =
+            String::from_utf8(render_dependencies(&looped, ExportFormat::Svg).unwrap()).unwrap();
+        assert!(svg.contains("stroke-dasharray"));
+    }
+}
@@ -0,0 +1,724 @@
+//! Charts for the `chart.render` capability.
+//!
+//! A chart spec is plain JSON (kind, optional title, one or more series of
+//! `{x, y}` points) so WASM tools can forward whatever the model assembled.
+//! Rendered files land in a content-addressed cache under the app root:
+//! asking for the same chart twice returns the file that already exists.
+
+pub mod export;
+mod png;
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ButterflyBotError, Result};
+
+pub const WIDTH: u32 = 640;
+pub const HEIGHT: u32 = 360;
+
+const PLOT_LEFT: f64 = 64.0;
+const PLOT_RIGHT: f64 = WIDTH as f64 - 16.0;
+const PLOT_TOP: f64 = 40.0;
+const PLOT_BOTTOM: f64 = HEIGHT as f64 - 44.0;
+const MAX_X_LABELS: usize = 8;
+const MAX_POINTS: usize = 500;
+
+const BACKGROUND: [u8; 3] = [0x16, 0x1a, 0x23];
+const GRID: [u8; 3] = [0x2a, 0x30, 0x40];
+const INK: [u8; 3] = [0xc8, 0xce, 0xdb];
+const SERIES_COLORS: [[u8; 3]; 5] = [
+    [0x6c, 0x8c, 0xff],
+    [0xff, 0x8c, 0x6c],
+    [0x5c, 0xd6, 0xa0],
+    [0xf2, 0xc9, 0x4c],
+    [0xc7, 0x7d, 0xff],
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChartKind {
+    #[default]
+    Line,
+    Bar,
+    /// Remaining work per day, with a dashed ideal line down to zero.
+    Burndown,
+    /// Money out per period, drawn as bars.
+    Spending,
+    /// Account balance over time, drawn as a line.
+    Balance,
+}
+
+impl ChartKind {
+    fn draws_bars(self) -> bool {
+        matches!(self, ChartKind::Bar | ChartKind::Spending)
+    }
+
+    fn anchors_at_zero(self) -> bool {
+        matches!(
+            self,
+            ChartKind::Bar | ChartKind::Spending | ChartKind::Burndown
+        )
+    }
+
+    fn default_title(self) -> &'static str {
+        match self {
+            ChartKind::Line | ChartKind::Bar => "Chart",
+            ChartKind::Burndown => "Burndown",
+            ChartKind::Spending => "Spending over time",
+            ChartKind::Balance => "Balance history",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChartFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+impl ChartFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ChartFormat::Png => "png",
+            ChartFormat::Svg => "svg",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChartPoint {
+    #[serde(deserialize_with = "label_from_any")]
+    pub x: String,
+    pub y: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChartSeries {
+    #[serde(default)]
+    pub name: String,
+    pub points: Vec<ChartPoint>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChartSpec {
+    #[serde(default)]
+    pub kind: ChartKind,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub y_label: Option<String>,
+    pub series: Vec<ChartSeries>,
+}
+
+impl ChartSpec {
+    pub fn title(&self) -> String {
+        self.title
+            .as_deref()
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .unwrap_or(self.kind.default_title())
+            .to_string()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.series.is_empty() || self.series.iter().all(|s| s.points.is_empty()) {
+            return Err(ButterflyBotError::Runtime(
+                "Chart needs at least one series with points".to_string(),
+            ));
+        }
+        let total: usize = self.series.iter().map(|s| s.points.len()).sum();
+        if total > MAX_POINTS {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Chart has {total} points; at most {MAX_POINTS} are supported"
+            )));
+        }
+        if self
+            .series
+            .iter()
+            .flat_map(|s| s.points.iter())
+            .any(|p| !p.y.is_finite())
+        {
+            return Err(ButterflyBotError::Runtime(
+                "Chart values must be finite numbers".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedChart {
+    pub path: PathBuf,
+    pub format: ChartFormat,
+    pub cached: bool,
+}
+
+/// Dates and counters arrive as strings or numbers depending on the model.
+fn label_from_any<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(value) => Ok(value),
+        Value::Number(value) => Ok(value.to_string()),
+        other => Err(serde::de::Error::custom(format!(
+            "chart x must be a string or number, got {other}"
+        ))),
+    }
+}
+
+pub fn charts_dir() -> PathBuf {
+    crate::runtime_paths::app_root().join("charts")
+}
+
+pub fn render(spec: &ChartSpec, format: ChartFormat) -> Result<Vec<u8>> {
+    spec.validate()?;
+    let layout = Layout::new(spec);
+    Ok(match format {
+        ChartFormat::Svg => render_svg(spec, &layout).into_bytes(),
+        ChartFormat::Png => render_png(&layout),
+    })
+}
+
+/// Renders into `dir` unless a chart with the same spec and format is
+/// already there.
+pub fn render_cached(spec: &ChartSpec, format: ChartFormat, dir: &Path) -> Result<RenderedChart> {
+    spec.validate()?;
+    let key_source = serde_json::to_vec(&(spec, format))
+        .map_err(|e| ButterflyBotError::Serialization(e.to_string()))?;
+    let digest = Sha256::digest(&key_source);
+    let key: String = digest[..16]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let path = dir.join(format!("{key}.{}", format.extension()));
+    if path.exists() {
+        return Ok(RenderedChart {
+            path,
+            format,
+            cached: true,
+        });
+    }
+    let bytes = render(spec, format)?;
+    std::fs::create_dir_all(dir).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    std::fs::write(&path, bytes).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(RenderedChart {
+        path,
+        format,
+        cached: false,
+    })
+}
+
+/// Entry point shared by the `chart.render` capability and the native
+/// tools' `chart` action. Accepts the spec either inline or under `chart`.
+pub fn execute_render(args: &Value) -> Result<Value> {
+    execute_render_in(args, &charts_dir())
+}
+
+pub fn execute_render_in(args: &Value, dir: &Path) -> Result<Value> {
+    let spec_value = args
+        .get("chart")
+        .filter(|value| value.is_object())
+        .unwrap_or(args);
+    let spec: ChartSpec = serde_json::from_value(spec_value.clone())
+        .map_err(|e| ButterflyBotError::Runtime(format!("Invalid chart spec: {e}")))?;
+    let format = match args
+        .get("format")
+        .or_else(|| spec_value.get("format"))
+        .and_then(|value| value.as_str())
+    {
+        Some(value) => serde_json::from_value(json!(value.to_ascii_lowercase())).map_err(|_| {
+            ButterflyBotError::Runtime(format!("Unsupported chart format '{value}'"))
+        })?,
+        None => ChartFormat::default(),
+    };
+    let rendered = render_cached(&spec, format, dir)?;
+    let title = spec.title();
+    let path = rendered.path.to_string_lossy().to_string();
+    let legend: Vec<Value> = spec
+        .series
+        .iter()
+        .enumerate()
+        .map(|(index, series)| {
+            json!({
+                "name": series.name,
+                "color": hex(SERIES_COLORS[index % SERIES_COLORS.len()]),
+            })
+        })
+        .collect();
+    Ok(json!({
+        "status": "ok",
+        "kind": spec.kind,
+        "format": rendered.format,
+        "path": path,
+        "cached": rendered.cached,
+        "title": title,
+        "legend": legend,
+        "markdown": format!("![{title}]({path})"),
+    }))
+}
+
+/// JSON schema fragment for the `chart` parameter on tools that expose a
+/// `chart` action.
+pub fn chart_parameter_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Chart spec for action=chart. Reply with the returned `markdown` so the chart shows inline.",
+        "properties": {
+            "kind": { "type": "string", "enum": ["line", "bar", "burndown", "spending", "balance"] },
+            "title": { "type": "string" },
+            "y_label": { "type": "string" },
+            "format": { "type": "string", "enum": ["png", "svg"] },
+            "series": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "points": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "x": { "type": ["string", "number"] },
+                                    "y": { "type": "number" }
+                                },
+                                "required": ["x", "y"]
+                            }
+                        }
+                    },
+                    "required": ["points"]
+                }
+            }
+        },
+        "required": ["series"]
+    })
+}
+
+/// Local chart files referenced as markdown images, in order of appearance.
+/// Only paths inside the chart cache are returned.
+pub fn chart_paths_in_markdown(text: &str, dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("![") {
+        rest = &rest[start + 2..];
+        let Some(close) = rest.find("](") else {
+            break;
+        };
+        rest = &rest[close + 2..];
+        let Some(end) = rest.find(')') else {
+            break;
+        };
+        let path = PathBuf::from(rest[..end].trim());
+        rest = &rest[end..];
+        if path.starts_with(dir) && !paths.contains(&path) {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+struct Layout {
+    labels: Vec<String>,
+    series: Vec<Vec<Option<f64>>>,
+    bars: bool,
+    ideal_start: Option<f64>,
+    y_min: f64,
+    y_max: f64,
+    ticks: Vec<f64>,
+}
+
+impl Layout {
+    fn new(spec: &ChartSpec) -> Self {
+        let mut labels: Vec<String> = Vec::new();
+        for point in spec.series.iter().flat_map(|s| s.points.iter()) {
+            if !labels.contains(&point.x) {
+                labels.push(point.x.clone());
+            }
+        }
+        let series: Vec<Vec<Option<f64>>> = spec
+            .series
+            .iter()
+            .map(|s| {
+                labels
+                    .iter()
+                    .map(|label| s.points.iter().find(|p| &p.x == label).map(|p| p.y))
+                    .collect()
+            })
+            .collect();
+
+        let ideal_start = (spec.kind == ChartKind::Burndown)
+            .then(|| {
+                series
+                    .first()
+                    .and_then(|values| values.iter().flatten().next().copied())
+            })
+            .flatten();
+
+        let values = series.iter().flatten().flatten().copied();
+        let (mut lo, mut hi) =
+            values.fold((f64::MAX, f64::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)));
+        if spec.kind.anchors_at_zero() {
+            lo = lo.min(0.0);
+            hi = hi.max(0.0);
+        }
+        if (hi - lo).abs() < f64::EPSILON {
+            hi = lo + 1.0;
+        }
+        let (y_min, y_max, step) = nice_range(lo, hi);
+        let mut ticks = Vec::new();
+        let mut tick = y_min;
+        while tick <= y_max + step * 0.5 {
+            ticks.push(tick);
+            tick += step;
+        }
+
+        Self {
+            labels,
+            series,
+            bars: spec.kind.draws_bars(),
+            ideal_start,
+            y_min,
+            y_max,
+            ticks,
+        }
+    }
+
+    fn slot_width(&self) -> f64 {
+        (PLOT_RIGHT - PLOT_LEFT) / self.labels.len().max(1) as f64
+    }
+
+    fn x_at(&self, index: usize) -> f64 {
+        PLOT_LEFT + self.slot_width() * (index as f64 + 0.5)
+    }
+
+    fn y_at(&self, value: f64) -> f64 {
+        let span = self.y_max - self.y_min;
+        PLOT_BOTTOM - (value - self.y_min) / span * (PLOT_BOTTOM - PLOT_TOP)
+    }
+
+    fn label_stride(&self) -> usize {
+        self.labels.len().div_ceil(MAX_X_LABELS).max(1)
+    }
+
+    /// Rectangles `(x, y, width, height, series_index)` for bar charts.
+    fn bar_rects(&self) -> Vec<(f64, f64, f64, f64, usize)> {
+        let groups = self.series.len().max(1) as f64;
+        let group_width = self.slot_width() * 0.7;
+        let bar_width = group_width / groups;
+        let zero = self.y_at(0.0_f64.clamp(self.y_min, self.y_max));
+        let mut rects = Vec::new();
+        for (series_index, values) in self.series.iter().enumerate() {
+            for (index, value) in values.iter().enumerate() {
+                let Some(value) = value else {
+                    continue;
+                };
+                let x = self.x_at(index) - group_width / 2.0 + bar_width * series_index as f64;
+                let y = self.y_at(*value);
+                rects.push((x, y.min(zero), bar_width, (zero - y).abs(), series_index));
+            }
+        }
+        rects
+    }
+
+    /// Polyline segments per series; gaps split a line.
+    fn line_runs(&self, values: &[Option<f64>]) -> Vec<Vec<(f64, f64)>> {
+        let mut runs = vec![Vec::new()];
+        for (index, value) in values.iter().enumerate() {
+            match value {
+                Some(value) => runs
+                    .last_mut()
+                    .expect("runs is never empty")
+                    .push((self.x_at(index), self.y_at(*value))),
+                None => runs.push(Vec::new()),
+            }
+        }
+        runs.retain(|run| !run.is_empty());
+        runs
+    }
+
+    fn ideal_line(&self) -> Option<((f64, f64), (f64, f64))> {
+        let start = self.ideal_start?;
+        let last = self.labels.len().checked_sub(1)?;
+        Some((
+            (self.x_at(0), self.y_at(start)),
+            (self.x_at(last), self.y_at(0.0)),
+        ))
+    }
+}
+
+fn nice_range(lo: f64, hi: f64) -> (f64, f64, f64) {
+    let raw = (hi - lo) / 4.0;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let normalized = raw / magnitude;
+    let step = if normalized <= 1.0 {
+        1.0
+    } else if normalized <= 2.0 {
+        2.0
+    } else if normalized <= 5.0 {
+        5.0
+    } else {
+        10.0
+    } * magnitude;
+    ((lo / step).floor() * step, (hi / step).ceil() * step, step)
+}
+
+fn format_tick(value: f64) -> String {
+    let (scaled, suffix) = if value.abs() >= 1_000_000.0 {
+        (value / 1_000_000.0, "M")
+    } else if value.abs() >= 1_000.0 {
+        (value / 1_000.0, "K")
+    } else {
+        (value, "")
+    };
+    let mut text = format!("{scaled:.2}");
+    while text.contains('.') && (text.ends_with('0') || text.ends_with('.')) {
+        text.pop();
+    }
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    format!("{text}{suffix}")
+}
+
+fn hex(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn render_svg(spec: &ChartSpec, layout: &Layout) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" viewBox=\"0 0 {WIDTH} {HEIGHT}\" font-family=\"sans-serif\">\n<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n<text x=\"{PLOT_LEFT}\" y=\"24\" font-size=\"15\" fill=\"{}\">{}</text>\n",
+        hex(BACKGROUND),
+        hex(INK),
+        xml_escape(&spec.title())
+    );
+    for tick in &layout.ticks {
+        let y = layout.y_at(*tick);
+        svg.push_str(&format!(
+            "<line x1=\"{PLOT_LEFT}\" y1=\"{y:.1}\" x2=\"{PLOT_RIGHT}\" y2=\"{y:.1}\" stroke=\"{}\"/>\n<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" text-anchor=\"end\" fill=\"{}\">{}</text>\n",
+            hex(GRID),
+            PLOT_LEFT - 6.0,
+            y + 4.0,
+            hex(INK),
+            format_tick(*tick)
+        ));
+    }
+    for (index, label) in layout.labels.iter().enumerate() {
+        if index % layout.label_stride() != 0 {
+            continue;
+        }
+        svg.push_str(&format!(
+            "<text x=\"{:.1}\" y=\"{:.1}\" font-size=\"11\" text-anchor=\"middle\" fill=\"{}\">{}</text>\n",
+            layout.x_at(index),
+            PLOT_BOTTOM + 16.0,
+            hex(INK),
+            xml_escape(label)
+        ));
+    }
+    if let Some(y_label) = spec.y_label.as_deref() {
+        svg.push_str(&format!(
+            "<text x=\"14\" y=\"{:.1}\" font-size=\"11\" text-anchor=\"middle\" fill=\"{}\" transform=\"rotate(-90 14 {:.1})\">{}</text>\n",
+            (PLOT_TOP + PLOT_BOTTOM) / 2.0,
+            hex(INK),
+            (PLOT_TOP + PLOT_BOTTOM) / 2.0,
+            xml_escape(y_label)
+        ));
+    }
+    if layout.bars {
+        for (x, y, width, height, series_index) in layout.bar_rects() {
+            svg.push_str(&format!(
+                "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{width:.1}\" height=\"{height:.1}\" fill=\"{}\"/>\n",
+                hex(SERIES_COLORS[series_index % SERIES_COLORS.len()])
+            ));
+        }
+    } else {
+        if let Some(((x1, y1), (x2, y2))) = layout.ideal_line() {
+            svg.push_str(&format!(
+                "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" stroke=\"{}\" stroke-dasharray=\"6 4\"/>\n",
+                hex(INK)
+            ));
+        }
+        for (series_index, values) in layout.series.iter().enumerate() {
+            for run in layout.line_runs(values) {
+                let points: Vec<String> =
+                    run.iter().map(|(x, y)| format!("{x:.1},{y:.1}")).collect();
+                svg.push_str(&format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+                    points.join(" "),
+                    hex(SERIES_COLORS[series_index % SERIES_COLORS.len()])
+                ));
+            }
+        }
+    }
+    let named: Vec<(usize, &ChartSeries)> = spec
+        .series
+        .iter()
+        .enumerate()
+        .filter(|(_, series)| !series.name.trim().is_empty())
+        .collect();
+    let mut legend_x = PLOT_RIGHT;
+    for (series_index, series) in named.iter().rev() {
+        legend_x -= 14.0 + series.name.chars().count() as f64 * 7.0;
+        svg.push_str(&format!(
+            "<rect x=\"{legend_x:.1}\" y=\"14\" width=\"10\" height=\"10\" fill=\"{}\"/>\n<text x=\"{:.1}\" y=\"23\" font-size=\"11\" fill=\"{}\">{}</text>\n",
+            hex(SERIES_COLORS[series_index % SERIES_COLORS.len()]),
+            legend_x + 14.0,
+            hex(INK),
+            xml_escape(&series.name)
+        ));
+        legend_x -= 10.0;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// PNG output for inline display. Axis ticks and x labels are drawn with a
+/// tiny bitmap font; the title and legend travel alongside in the markdown
+/// and `legend` fields instead.
+fn render_png(layout: &Layout) -> Vec<u8> {
+    let mut palette = vec![BACKGROUND, GRID, INK];
+    palette.extend_from_slice(&SERIES_COLORS);
+    let mut canvas = png::Canvas::new(WIDTH, HEIGHT, 0);
+    for tick in &layout.ticks {
+        let y = layout.y_at(*tick);
+        canvas.line(PLOT_LEFT, y, PLOT_RIGHT, y, 1, false);
+        let label = format_tick(*tick);
+        canvas.text_right(PLOT_LEFT - 6.0, y - 5.0, &label, 2);
+    }
+    canvas.line(PLOT_LEFT, PLOT_BOTTOM, PLOT_RIGHT, PLOT_BOTTOM, 2, false);
+    for (index, label) in layout.labels.iter().enumerate() {
+        if index % layout.label_stride() != 0 {
+            continue;
+        }
+        canvas.text_centered(layout.x_at(index), PLOT_BOTTOM + 8.0, label, 2);
+    }
+    if layout.bars {
+        for (x, y, width, height, series_index) in layout.bar_rects() {
+            canvas.fill_rect(
+                x,
+                y,
+                width,
+                height,
+                3 + (series_index % SERIES_COLORS.len()) as u8,
+            );
+        }
+    } else {
+        if let Some(((x1, y1), (x2, y2))) = layout.ideal_line() {
+            canvas.line(x1, y1, x2, y2, 2, true);
+        }
+        for (series_index, values) in layout.series.iter().enumerate() {
+            let color = 3 + (series_index % SERIES_COLORS.len()) as u8;
+            for run in layout.line_runs(values) {
+                if run.len() == 1 {
+                    let (x, y) = run[0];
+                    canvas.fill_rect(x - 2.0, y - 2.0, 4.0, 4.0, color);
+                }
+                for pair in run.windows(2) {
+                    canvas.thick_line(pair[0].0, pair[0].1, pair[1].0, pair[1].1, color);
+                }
+            }
+        }
+    }
+    for series_index in 0..layout.series.len().min(SERIES_COLORS.len()) {
+        let x = PLOT_RIGHT - 14.0 * (layout.series.len() - series_index) as f64;
+        canvas.fill_rect(x, 14.0, 10.0, 10.0, 3 + series_index as u8);
+    }
+    png::encode_indexed(WIDTH, HEIGHT, &palette, canvas.pixels())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn burndown() -> Value {
+        json!({
+            "kind": "burndown",
+            "title": "Sprint <12>",
+            "series": [{
+                "name": "Remaining",
+                "points": [
+                    {"x": "2026-10-01", "y": 20},
+                    {"x": "2026-10-02", "y": 14},
+                    {"x": "2026-10-03", "y": 9}
+                ]
+            }]
+        })
+    }
+
+    #[test]
+    fn svg_includes_escaped_title_ideal_line_and_series() {
+        let spec: ChartSpec = serde_json::from_value(burndown()).unwrap();
+        let svg = String::from_utf8(render(&spec, ChartFormat::Svg).unwrap()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Sprint &lt;12&gt;"));
+        assert!(svg.contains("stroke-dasharray"));
+        assert!(svg.contains("<polyline"));
+        assert!(svg.contains(">Remaining</text>"));
+    }
+
+    #[test]
+    fn png_has_signature_and_dimensions() {
+        let spec: ChartSpec = serde_json::from_value(json!({
+            "kind": "spending",
+            "series": [{"name": "Food", "points": [{"x": 1, "y": 1200.5}, {"x": 2, "y": -40}]}]
+        }))
+        .unwrap();
+        let bytes = render(&spec, ChartFormat::Png).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(bytes[16..20].try_into().unwrap()), WIDTH);
+        assert_eq!(
+            u32::from_be_bytes(bytes[20..24].try_into().unwrap()),
+            HEIGHT
+        );
+        assert!(bytes.ends_with(b"IEND\xae\x42\x60\x82"));
+    }
+
+    #[test]
+    fn render_is_cached_by_spec_and_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = execute_render_in(&json!({"chart": burndown()}), dir.path()).unwrap();
+        let second = execute_render_in(&json!({"chart": burndown()}), dir.path()).unwrap();
+        let svg =
+            execute_render_in(&json!({"chart": burndown(), "format": "SVG"}), dir.path()).unwrap();
+        assert_eq!(first["cached"], json!(false));
+        assert_eq!(second["cached"], json!(true));
+        assert_eq!(first["path"], second["path"]);
+        assert_ne!(first["path"], svg["path"]);
+        assert!(svg["path"].as_str().unwrap().ends_with(".svg"));
+
+        let markdown = first["markdown"].as_str().unwrap();
+        let paths = chart_paths_in_markdown(
+            &format!("Here you go:\n\n{markdown}\n\n![other](/tmp/elsewhere.png)"),
+            dir.path(),
+        );
+        assert_eq!(paths, vec![PathBuf::from(first["path"].as_str().unwrap())]);
+    }
+
+    #[test]
+    fn rejects_empty_specs_and_unknown_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(execute_render_in(&json!({"series": []}), dir.path()).is_err());
+        assert!(execute_render_in(
+            &json!({"series": [{"points": [{"x": "a", "y": 1}]}], "format": "gif"}),
+            dir.path()
+        )
+        .is_err());
+        assert_eq!(format_tick(1500.0), "1.5K");
+        assert_eq!(format_tick(-0.0), "0");
+        assert_eq!(format_tick(2_000_000.0), "2M");
+    }
+}
@@ -0,0 +1,254 @@
+//! Minimal indexed-colour rasterizer and PNG encoder.
+//!
+//! Charts only need rectangles, lines and a handful of glyphs, so this avoids
+//! pulling an imaging stack into the daemon. Image data is written as stored
+//! (uncompressed) deflate blocks; a 640x360 chart is about 230 KB.
+
+/// 3x5 glyphs, one row per byte, high bit on the left.
+fn glyph(ch: char) -> Option<[u8; 5]> {
+    Some(match ch {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '(' => [0b010, 0b100, 0b100, 0b100, 0b010],
+        ')' => [0b010, 0b001, 0b001, 0b001, 0b010],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        '<' => [0b001, 0b010, 0b100, 0b010, 0b001],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        _ => return None,
+    })
+}
+
+const GLYPH_SCALE: i64 = 2;
+const GLYPH_ADVANCE: f64 = 8.0;
+
+pub(super) struct Canvas {
+    width: i64,
+    height: i64,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub(super) fn new(width: u32, height: u32, background: u8) -> Self {
+        Self {
+            width: width as i64,
+            height: height as i64,
+            pixels: vec![background; (width * height) as usize],
+        }
+    }
+
+    pub(super) fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn put(&mut self, x: i64, y: i64, color: u8) {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = color;
+        }
+    }
+
+    pub(super) fn fill_rect(&mut self, x: f64, y: f64, width: f64, height: f64, color: u8) {
+        let x0 = x.round() as i64;
+        let y0 = y.round() as i64;
+        let x1 = (x + width).round().max(x0 as f64 + 1.0) as i64;
+        let y1 = (y + height).round().max(y0 as f64 + 1.0) as i64;
+        for py in y0..y1 {
+            for px in x0..x1 {
+                self.put(px, py, color);
+            }
+        }
+    }
+
+    /// Bresenham line, optionally dashed in 6px on / 4px off steps.
+    pub(super) fn line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: u8, dashed: bool) {
+        let (mut x, mut y) = (x0.round() as i64, y0.round() as i64);
+        let (x1, y1) = (x1.round() as i64, y1.round() as i64);
+        let dx = (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let mut step = 0u32;
+        loop {
+            if !dashed || step % 10 < 6 {
+                self.put(x, y, color);
+            }
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * err;
+            if doubled >= dy {
+                err += dy;
+                x += sx;
+            }
+            if doubled <= dx {
+                err += dx;
+                y += sy;
+            }
+            step += 1;
+        }
+    }
+
+    pub(super) fn thick_line(&mut self, x0: f64, y0: f64, x1: f64, y1: f64, color: u8) {
+        for (ox, oy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            self.line(x0 + ox, y0 + oy, x1 + ox, y1 + oy, color, false);
+        }
+    }
+
+    pub(super) fn text(&mut self, x: f64, y: f64, value: &str, color: u8) {
+        let (x, y) = (x.round() as i64, y.round() as i64);
+        for (index, ch) in value.chars().enumerate() {
+            let Some(rows) = glyph(ch.to_ascii_uppercase()) else {
+                continue;
+            };
+            let origin = x + (index as f64 * GLYPH_ADVANCE) as i64;
+            for (row, bits) in rows.iter().enumerate() {
+                for col in 0..3 {
+                    if bits & (0b100 >> col) == 0 {
+                        continue;
+                    }
+                    for sy in 0..GLYPH_SCALE {
+                        for sx in 0..GLYPH_SCALE {
+                            self.put(
+                                origin + col * GLYPH_SCALE + sx,
+                                y + row as i64 * GLYPH_SCALE + sy,
+                                color,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn text_width(value: &str) -> f64 {
+        value.chars().count() as f64 * GLYPH_ADVANCE - 2.0
+    }
+
+    pub(super) fn text_right(&mut self, right: f64, y: f64, value: &str, color: u8) {
+        self.text(right - Self::text_width(value), y, value, color);
+    }
+
+    pub(super) fn text_centered(&mut self, center: f64, y: f64, value: &str, color: u8) {
+        self.text(center - Self::text_width(value) / 2.0, y, value, color);
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in bytes.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+pub(super) fn encode_indexed(
+    width: u32,
+    height: u32,
+    palette: &[[u8; 3]],
+    pixels: &[u8],
+) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(((width + 1) * height) as usize);
+    for row in pixels.chunks(width as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    let mut zlib = vec![0x78, 0x01];
+    let blocks: Vec<&[u8]> = raw.chunks(u16::MAX as usize).collect();
+    for (index, block) in blocks.iter().enumerate() {
+        zlib.push(u8::from(index + 1 == blocks.len()));
+        let len = block.len() as u16;
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 3, 0, 0, 0]);
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"PLTE", &palette.concat());
+    push_chunk(&mut out, b"IDAT", &zlib);
+    push_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_reference_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+}
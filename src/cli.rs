@@ -0,0 +1,582 @@
+//! Launcher subcommands that talk to a running daemon.
+
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use chrono::{Local, TimeZone};
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{ButterflyBotError, Result};
+use crate::interfaces::providers::MemoryHit;
+use crate::providers::retention::RetentionReport;
+
+pub struct DaemonClient {
+    base_url: String,
+    token: Option<String>,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct MemorySearchResponse {
+    results: Vec<MemoryHit>,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+#[derive(Deserialize)]
+struct ChatHistoryResponse {
+    history: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct InboxResponse {
+    items: Vec<InboxEntry>,
+}
+
+/// The parts of an inbox item the terminal shows.
+#[derive(Clone, Debug, Deserialize)]
+pub struct InboxEntry {
+    pub source_type: String,
+    pub title: String,
+    pub status: String,
+    pub priority: String,
+    pub due_at: Option<i64>,
+    pub origin_ref: String,
+}
+
+/// Thread the terminal chat shares with the desktop chat tab.
+const CHAT_THREAD: &str = "default";
+const HISTORY_LIMIT: usize = 20;
+/// Long enough for an in-flight backup or sync to wrap up.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(120);
+
+impl DaemonClient {
+    pub fn new(base_url: impl Into<String>, token: Option<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: token.filter(|token| !token.trim().is_empty()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn memory_search(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+        rerank: bool,
+    ) -> Result<Vec<MemoryHit>> {
+        let body = json!({
+            "user_id": user_id,
+            "query": query,
+            "limit": limit,
+            "rerank": rerank,
+        });
+        self.post::<MemorySearchResponse>("/memory/search", &body, "Memory search")
+            .await
+            .map(|response| response.results)
+    }
+
+    pub async fn memory_retention(&self, user_id: &str, dry_run: bool) -> Result<RetentionReport> {
+        let body = json!({ "user_id": user_id, "dry_run": dry_run });
+        self.post("/memory/retention", &body, "Memory retention")
+            .await
+    }
+
+    pub async fn chat_history(&self, user_id: &str, limit: usize) -> Result<Vec<String>> {
+        let query = [
+            ("user_id", user_id.to_string()),
+            ("limit", limit.to_string()),
+        ];
+        self.get::<ChatHistoryResponse>("/chat_history", &query, "Chat history")
+            .await
+            .map(|response| response.history)
+    }
+
+    /// Open inbox items, e.g. todos and reminders.
+    pub async fn inbox(&self, user_id: &str) -> Result<Vec<InboxEntry>> {
+        let query = [
+            ("user_id", user_id.to_string()),
+            ("include_done", "false".to_string()),
+        ];
+        self.get::<InboxResponse>("/inbox", &query, "Inbox")
+            .await
+            .map(|response| response.items)
+    }
+
+    /// Sends a prompt and hands each reply token to `on_token` as the daemon
+    /// streams it.
+    pub async fn stream_prompt(
+        &self,
+        user_id: &str,
+        text: &str,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<()> {
+        let body = json!({
+            "user_id": user_id,
+            "text": text,
+            "source": "cli",
+            "priority": "interactive",
+            "thread_id": CHAT_THREAD,
+        });
+        let request = self
+            .client
+            .post(format!("{}/process_text/stream", self.base_url))
+            .json(&body);
+        let response = self.send(self.authorized(request)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(ButterflyBotError::Http(format!(
+                "Chat failed ({status}): {}",
+                error_message(body)
+            )));
+        }
+
+        let mut body = response.bytes_stream();
+        let mut buffer = String::new();
+        while let Some(chunk) = body.next().await {
+            let bytes = chunk.map_err(|e| ButterflyBotError::Http(format!("Stream error: {e}")))?;
+            buffer.push_str(&String::from_utf8_lossy(&bytes));
+            for (event, data) in take_sse_frames(&mut buffer) {
+                match event.as_str() {
+                    "token" => {
+                        if let Some(token) = data.get("text").and_then(Value::as_str) {
+                            on_token(token);
+                        }
+                    }
+                    "done" => return Ok(()),
+                    "error" => {
+                        let error = data
+                            .get("error")
+                            .and_then(Value::as_str)
+                            .unwrap_or("Unknown stream error");
+                        return Err(ButterflyBotError::Http(error.to_string()));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(ButterflyBotError::Http(
+            "Response stream ended before the reply finished".to_string(),
+        ))
+    }
+
+    pub async fn is_running(&self) -> bool {
+        self.client
+            .get(format!("{}/health", self.base_url))
+            .timeout(Duration::from_secs(2))
+            .send()
+            .await
+            .is_ok_and(|response| response.status().is_success())
+    }
+
+    /// Asks the daemon to exit and waits until it stops answering. Running
+    /// scheduled jobs finish first. Returns false when it was not running.
+    pub async fn shutdown(&self) -> Result<bool> {
+        if !self.is_running().await {
+            return Ok(false);
+        }
+        let _: Value = self.post("/shutdown", &json!({}), "Shutdown").await?;
+        let deadline = tokio::time::Instant::now() + SHUTDOWN_TIMEOUT;
+        while self.is_running().await {
+            if tokio::time::Instant::now() >= deadline {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Daemon at {} did not stop within {}s",
+                    self.base_url,
+                    SHUTDOWN_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+        Ok(true)
+    }
+
+    fn authorized(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.token {
+            Some(token) => request.header("authorization", format!("Bearer {token}")),
+            None => request,
+        }
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        request
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(format!("Daemon unreachable: {e}")))
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        what: &str,
+    ) -> Result<T> {
+        let request = self
+            .client
+            .get(format!("{}{path}", self.base_url))
+            .query(query);
+        self.read_json(self.authorized(request), what).await
+    }
+
+    async fn post<T: DeserializeOwned>(&self, path: &str, body: &Value, what: &str) -> Result<T> {
+        let request = self
+            .client
+            .post(format!("{}{path}", self.base_url))
+            .json(body);
+        self.read_json(self.authorized(request), what).await
+    }
+
+    async fn read_json<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+        what: &str,
+    ) -> Result<T> {
+        let response = self.send(request).await?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if !status.is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "{what} failed ({status}): {}",
+                error_message(body)
+            )));
+        }
+        serde_json::from_str::<T>(&body)
+            .map_err(|e| ButterflyBotError::Serialization(e.to_string()))
+    }
+}
+
+fn error_message(body: String) -> String {
+    serde_json::from_str::<ErrorBody>(&body)
+        .map(|body| body.error)
+        .unwrap_or(body)
+}
+
+/// Removes every complete `event:`/`data:` frame from `buffer`, leaving a
+/// partial frame for the next chunk.
+fn take_sse_frames(buffer: &mut String) -> Vec<(String, Value)> {
+    let mut frames = Vec::new();
+    while let Some(end) = buffer.find("\n\n") {
+        let frame = buffer[..end].to_string();
+        buffer.drain(..end + 2);
+        let mut event = "message".to_string();
+        let mut data = String::new();
+        for line in frame.lines() {
+            if let Some(value) = line.strip_prefix("event:") {
+                event = value.trim().to_string();
+            } else if let Some(value) = line.strip_prefix("data:") {
+                data.push_str(value.trim_start());
+            }
+        }
+        if let Ok(data) = serde_json::from_str(&data) {
+            frames.push((event, data));
+        }
+    }
+    frames
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ChatInput {
+    Prompt(String),
+    Todos,
+    Reminders,
+    History(usize),
+    Help,
+    Quit,
+    Empty,
+}
+
+pub fn parse_chat_input(line: &str) -> ChatInput {
+    let line = line.trim();
+    if line.is_empty() {
+        return ChatInput::Empty;
+    }
+    // `//text` goes to the daemon as typed; it hands `/text` to the model.
+    let Some(command) = line.strip_prefix('/').filter(|rest| !rest.starts_with('/')) else {
+        return ChatInput::Prompt(line.to_string());
+    };
+    let mut parts = command.split_whitespace();
+    match parts.next().unwrap_or_default() {
+        "todos" => ChatInput::Todos,
+        "reminders" => ChatInput::Reminders,
+        "history" => ChatInput::History(
+            parts
+                .next()
+                .and_then(|count| count.parse().ok())
+                .unwrap_or(HISTORY_LIMIT),
+        ),
+        "help" | "?" => ChatInput::Help,
+        "quit" | "exit" => ChatInput::Quit,
+        // `/todo`, `/remind`, `/done` and the rest are the daemon's.
+        _ => ChatInput::Prompt(line.to_string()),
+    }
+}
+
+const CHAT_HELP: &str = "Type a message and press Enter.
+  /todos          open todos
+  /reminders      upcoming reminders
+  /history [n]    last n chat turns (default 20)
+  /todo <title>   add a todo; /remind and /done work too
+  /quit           leave (Ctrl-D works too)
+  //text          send text that starts with a slash";
+
+/// Open items of one `source_type`, soonest due first.
+pub fn format_inbox_entries(entries: &[InboxEntry], source_type: &str, empty: &str) -> String {
+    let mut matching = entries
+        .iter()
+        .filter(|entry| entry.source_type == source_type)
+        .collect::<Vec<_>>();
+    if matching.is_empty() {
+        return empty.to_string();
+    }
+    matching.sort_by_key(|entry| entry.due_at.unwrap_or(i64::MAX));
+    matching
+        .iter()
+        .map(|entry| {
+            let due = entry
+                .due_at
+                .and_then(|due| Local.timestamp_opt(due, 0).single())
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "no due date".to_string());
+            format!(
+                "- {}  [{} • {} • {due}]  {}",
+                entry.title, entry.status, entry.priority, entry.origin_ref
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Line-based chat against the daemon for terminals without the desktop UI.
+/// Replies print as they stream in.
+pub fn run_chat(
+    runtime: &tokio::runtime::Runtime,
+    client: &DaemonClient,
+    user_id: &str,
+) -> Result<()> {
+    println!("Butterfly Bot chat as {user_id}. /help lists commands.");
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(());
+        };
+        let line = line.map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let outcome = match parse_chat_input(&line) {
+            ChatInput::Empty => Ok(()),
+            ChatInput::Quit => return Ok(()),
+            ChatInput::Help => {
+                println!("{CHAT_HELP}");
+                Ok(())
+            }
+            ChatInput::History(limit) => {
+                runtime
+                    .block_on(client.chat_history(user_id, limit))
+                    .map(|history| {
+                        if history.is_empty() {
+                            println!("No chat history yet.");
+                        }
+                        for line in history {
+                            println!("{line}");
+                        }
+                    })
+            }
+            ChatInput::Todos => runtime.block_on(client.inbox(user_id)).map(|items| {
+                println!("{}", format_inbox_entries(&items, "todo", "No open todos."));
+            }),
+            ChatInput::Reminders => runtime.block_on(client.inbox(user_id)).map(|items| {
+                println!(
+                    "{}",
+                    format_inbox_entries(&items, "reminder", "No pending reminders.")
+                );
+            }),
+            ChatInput::Prompt(prompt) => {
+                let result = runtime.block_on(client.stream_prompt(user_id, &prompt, |token| {
+                    print!("{token}");
+                    let _ = std::io::stdout().flush();
+                }));
+                println!();
+                result
+            }
+        };
+        if let Err(err) = outcome {
+            eprintln!("error: {err}");
+        }
+    }
+}
+
+/// One line per hit: score, local time, then the snippet.
+pub fn format_memory_hits(hits: &[MemoryHit]) -> String {
+    if hits.is_empty() {
+        return "No matching memories.".to_string();
+    }
+    hits.iter()
+        .map(|hit| {
+            let when = Local
+                .timestamp_opt(hit.timestamp, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| hit.timestamp.to_string());
+            let snippet = hit.content.split_whitespace().collect::<Vec<_>>().join(" ");
+            let marker = if hit.reranked { "*" } else { " " };
+            format!("{:.3}{marker} {when}  {snippet}", hit.score)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_days(days: Option<u32>) -> String {
+    days.map(|days| format!("{days}d"))
+        .unwrap_or_else(|| "off".to_string())
+}
+
+pub fn format_retention_report(report: &RetentionReport) -> String {
+    let policy = &report.policy;
+    let verb = |dry: &'static str, done: &'static str| if report.dry_run { dry } else { done };
+    let mut lines = vec![
+        format!(
+            "Retention {} for {}",
+            verb("dry run", "pass"),
+            report.user_id
+        ),
+        format!(
+            "Policy: summarize after {}, delete after {}, drop summaries after {}, {} turns per summary",
+            format_days(policy.summarize_after_days),
+            format_days(policy.delete_after_days),
+            format_days(policy.summary_max_age_days),
+            policy.batch_size
+        ),
+    ];
+    if policy.summarize_after_days.is_some() && !report.summarizer_available {
+        lines.push("No summary model configured; old turns are kept until one is.".to_string());
+    }
+    lines.push(format!(
+        "{} {} turns into {} summaries",
+        verb("Would summarize", "Summarized"),
+        report.turns_to_summarize,
+        if report.dry_run {
+            report.summary_batches
+        } else {
+            report.summaries_written
+        }
+    ));
+    lines.push(format!(
+        "{} {} raw turns ({} held until summarized)",
+        verb("Would delete", "Deleted"),
+        report.turns_to_delete,
+        report.turns_awaiting_summary
+    ));
+    lines.push(format!(
+        "{} {} summary memories",
+        verb("Would delete", "Deleted"),
+        report.summaries_to_delete
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_hits_with_scores_and_flattened_snippets() {
+        let hits = vec![MemoryHit {
+            content: "Dentist moved to\nThursday".to_string(),
+            timestamp: 0,
+            score: 0.8765,
+            reranked: true,
+        }];
+        let output = format_memory_hits(&hits);
+        assert!(output.starts_with("0.877*"));
+        assert!(output.ends_with("Dentist moved to Thursday"));
+        assert_eq!(format_memory_hits(&[]), "No matching memories.");
+    }
+
+    #[test]
+    fn parses_slash_commands_and_sse_frames() {
+        assert_eq!(parse_chat_input("  "), ChatInput::Empty);
+        assert_eq!(
+            parse_chat_input("what's due?"),
+            ChatInput::Prompt("what's due?".to_string())
+        );
+        assert_eq!(parse_chat_input("/todos"), ChatInput::Todos);
+        assert_eq!(parse_chat_input("/history 5"), ChatInput::History(5));
+        assert_eq!(parse_chat_input("/history"), ChatInput::History(20));
+        assert_eq!(
+            parse_chat_input("//etc/hosts is broken"),
+            ChatInput::Prompt("//etc/hosts is broken".to_string())
+        );
+        assert_eq!(
+            parse_chat_input("/todo buy milk"),
+            ChatInput::Prompt("/todo buy milk".to_string())
+        );
+
+        let mut buffer = "event: token\ndata: {\"text\":\"Hi\"}\n\nevent: done\nda".to_string();
+        let frames = take_sse_frames(&mut buffer);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].0, "token");
+        assert_eq!(frames[0].1["text"], "Hi");
+        assert_eq!(buffer, "event: done\nda");
+    }
+
+    #[test]
+    fn lists_one_kind_of_inbox_item_soonest_first() {
+        let entry = |source_type: &str, title: &str, due_at: Option<i64>| InboxEntry {
+            source_type: source_type.to_string(),
+            title: title.to_string(),
+            status: "open".to_string(),
+            priority: "normal".to_string(),
+            due_at,
+            origin_ref: format!("{source_type}:1"),
+        };
+        let items = vec![
+            entry("todo", "File taxes", None),
+            entry("reminder", "Call the bank", Some(200)),
+            entry("todo", "Buy milk", Some(100)),
+        ];
+        let output = format_inbox_entries(&items, "todo", "No open todos.");
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("- Buy milk"));
+        assert!(lines[1].contains("no due date"));
+        assert_eq!(format_inbox_entries(&items, "task", "Nothing."), "Nothing.");
+    }
+
+    #[test]
+    fn retention_report_reads_as_a_forecast_when_dry() {
+        let mut report = RetentionReport {
+            user_id: "user".to_string(),
+            dry_run: true,
+            turns_to_summarize: 45,
+            summary_batches: 2,
+            turns_to_delete: 30,
+            turns_awaiting_summary: 5,
+            ..Default::default()
+        };
+        report.policy.summarize_after_days = Some(14);
+        let output = format_retention_report(&report);
+        assert!(output.contains("summarize after 14d, delete after off"));
+        assert!(output.contains("No summary model configured"));
+        assert!(output.contains("Would summarize 45 turns into 2 summaries"));
+        assert!(output.contains("Would delete 30 raw turns (5 held until summarized)"));
+
+        report.dry_run = false;
+        report.summarizer_available = true;
+        report.summaries_written = 2;
+        let output = format_retention_report(&report);
+        assert!(output.starts_with("Retention pass for user"));
+        assert!(output.contains("Summarized 45 turns into 2 summaries"));
+    }
+}
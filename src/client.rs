@@ -6,6 +6,8 @@ use crate::config::Config;
 use crate::error::{ButterflyBotError, Result};
 use crate::factories::agent_factory::ButterflyBotFactory;
 use crate::interfaces::plugins::Tool;
+use crate::interfaces::providers::MemoryHit;
+use crate::providers::retention::RetentionReport;
 use crate::services::agent::UiEvent;
 use crate::services::query::{ProcessOptions, ProcessResult, QueryService, UserInput};
 use tokio::sync::broadcast;
@@ -30,7 +32,7 @@ impl ButterflyBot {
     }
 
     pub async fn from_store(db_path: &str) -> Result<Self> {
-        let config = Config::from_store(db_path)?.resolve_vault()?;
+        let config = Config::load(db_path)?.resolve_vault()?;
         let agent = Self::from_config(config).await?;
         Ok(agent)
     }
@@ -39,7 +41,7 @@ impl ButterflyBot {
         db_path: &str,
         ui_event_tx: Option<broadcast::Sender<UiEvent>>,
     ) -> Result<Self> {
-        let config = Config::from_store(db_path)?.resolve_vault()?;
+        let config = Config::load(db_path)?.resolve_vault()?;
         let agent = Self::from_config_with_events(config, ui_event_tx).await?;
         Ok(agent)
     }
@@ -71,6 +73,12 @@ impl ButterflyBot {
         self.query_service.get_user_history(user_id, limit).await
     }
 
+    pub async fn post_assistant_message(&self, user_id: &str, content: &str) -> Result<()> {
+        self.query_service
+            .append_assistant_message(user_id, content)
+            .await
+    }
+
     pub async fn search_memory(
         &self,
         user_id: &str,
@@ -82,6 +90,28 @@ impl ButterflyBot {
             .await
     }
 
+    pub async fn semantic_search_memory(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+        rerank: bool,
+    ) -> Result<Vec<MemoryHit>> {
+        self.query_service
+            .semantic_search_memory(user_id, query, limit, rerank)
+            .await
+    }
+
+    pub async fn enforce_memory_retention(
+        &self,
+        user_id: &str,
+        dry_run: bool,
+    ) -> Result<RetentionReport> {
+        self.query_service
+            .enforce_memory_retention(user_id, dry_run)
+            .await
+    }
+
     pub async fn preload_context(&self, user_id: &str) -> Result<()> {
         self.query_service.preload_context(user_id).await
     }
@@ -115,6 +145,57 @@ impl ButterflyBot {
         Ok(true)
     }
 
+    /// Asks the agent for a JSON answer matching `schema` outside the
+    /// conversation: nothing is read from or written to chat history.
+    pub async fn structured_answer(
+        &self,
+        user_id: &str,
+        prompt: &str,
+        schema: serde_json::Value,
+    ) -> Result<serde_json::Value> {
+        let agent_service = self.query_service.agent_service();
+        agent_service
+            .generate_structured_response(user_id, prompt, "", None, schema)
+            .await
+    }
+
+    pub async fn describe_tools(&self) -> Vec<crate::plugins::registry::ToolDescriptor> {
+        let agent_service = self.query_service.agent_service();
+        agent_service.tool_registry.describe_tools().await
+    }
+
+    pub async fn tool_postures(
+        &self,
+        last_used: &std::collections::HashMap<String, i64>,
+    ) -> Vec<crate::plugins::posture::ToolPosture> {
+        let agent_service = self.query_service.agent_service();
+        agent_service.tool_registry.tool_postures(last_used).await
+    }
+
+    /// Runs a capability call the user approved from the inbox.
+    pub async fn execute_approved(
+        &self,
+        approval: &crate::approvals::PendingApproval,
+    ) -> Result<serde_json::Value> {
+        let agent_service = self.query_service.agent_service();
+        agent_service.tool_registry.execute_approved(approval).await
+    }
+
+    /// Policy checks for an action the daemon runs without a tool; see
+    /// [`crate::plugins::registry::ToolRegistry::admit_direct_call`].
+    pub async fn admit_direct_call(
+        &self,
+        tool_name: &str,
+        capability: &str,
+        args: &serde_json::Value,
+    ) -> Result<Option<serde_json::Value>> {
+        let agent_service = self.query_service.agent_service();
+        agent_service
+            .tool_registry
+            .admit_direct_call(tool_name, capability, args)
+            .await
+    }
+
     pub async fn brain_tick(&self) {
         let agent_service = self.query_service.agent_service();
         agent_service.dispatch_brain_tick().await;
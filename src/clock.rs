@@ -0,0 +1,107 @@
+//! Source of "now" for stores, scheduled jobs and UI timing rules.
+//!
+//! Production code uses [`SystemClock`]; tests hand a [`ManualClock`] to a
+//! store with `with_clock` and move time explicitly instead of sleeping.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Local, TimeZone};
+
+pub trait Clock: Send + Sync {
+    /// Unix seconds.
+    fn now(&self) -> i64;
+
+    fn now_local(&self) -> DateTime<Local> {
+        Local
+            .timestamp_opt(self.now(), 0)
+            .single()
+            .unwrap_or_else(Local::now)
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64
+    }
+}
+
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now: i64) -> Arc<Self> {
+        Arc::new(Self {
+            now: AtomicI64::new(now),
+        })
+    }
+
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: i64) -> i64 {
+        self.now.fetch_add(seconds, Ordering::SeqCst) + seconds
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Whether local minute-of-day `current` falls in `[start, end)`, wrapping
+/// past midnight when `start > end`. An empty range is never quiet.
+pub fn in_minute_window(current: u16, start: u16, end: u16) -> bool {
+    if start == end {
+        return false;
+    }
+    if start < end {
+        current >= start && current < end
+    } else {
+        current >= start || current < end
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_moves_only_when_told() {
+        let clock = ManualClock::new(1_000);
+        let shared: SharedClock = clock.clone();
+        assert_eq!(shared.now(), 1_000);
+        assert_eq!(clock.advance(60), 1_060);
+        assert_eq!(shared.now(), 1_060);
+        clock.set(5);
+        assert_eq!(shared.now(), 5);
+    }
+
+    #[test]
+    fn minute_windows_wrap_midnight() {
+        let (start, end) = (22 * 60, 7 * 60);
+        assert!(in_minute_window(23 * 60, start, end));
+        assert!(in_minute_window(6 * 60 + 59, start, end));
+        assert!(!in_minute_window(7 * 60, start, end));
+        assert!(in_minute_window(9 * 60, 9 * 60, 17 * 60));
+        assert!(!in_minute_window(9 * 60, 9 * 60, 9 * 60));
+    }
+}
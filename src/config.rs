@@ -1,6 +1,8 @@
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::{Map, Value};
+use std::collections::HashMap;
 use std::fs;
+use std::sync::{OnceLock, RwLock};
 
 use crate::error::{ButterflyBotError, Result};
 
@@ -23,6 +25,61 @@ pub struct ProviderConfig {
     pub runtime: RuntimeProvider,
 }
 
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProviderKind {
+    #[default]
+    Openai,
+    Ollama,
+    Anthropic,
+}
+
+impl LlmProviderKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Openai => "openai",
+            Self::Ollama => "ollama",
+            Self::Anthropic => "anthropic",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "openai" => Some(Self::Openai),
+            "ollama" => Some(Self::Ollama),
+            "anthropic" => Some(Self::Anthropic),
+            _ => None,
+        }
+    }
+}
+
+/// Chat backend selection. Unset fields fall back to the `openai` section
+/// for OpenAI-compatible backends.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LlmConfig {
+    #[serde(default)]
+    pub provider: LlmProviderKind,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub max_tokens: Option<u32>,
+    /// Backends tried in order when the primary keeps failing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<LlmConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failover: Option<FailoverConfig>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct FailoverConfig {
+    /// Consecutive failures before a backend is skipped.
+    pub failure_threshold: Option<u32>,
+    /// How long a tripped backend is skipped before it is tried again.
+    pub cooldown_seconds: Option<u64>,
+    /// Per-call timeout; a timeout counts as a failure.
+    pub timeout_seconds: Option<u64>,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct MemoryConfig {
     pub enabled: Option<bool>,
@@ -34,6 +91,25 @@ pub struct MemoryConfig {
     pub context_embed_enabled: Option<bool>,
     pub summary_threshold: Option<usize>,
     pub retention_days: Option<u32>,
+    #[serde(default)]
+    pub retention: Option<MemoryRetentionConfig>,
+}
+
+/// History retention knobs. Every field is optional; see
+/// `providers::retention::RetentionPolicy` for the defaults.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct MemoryRetentionConfig {
+    /// Chat turns older than this are folded into summary memories.
+    pub summarize_after_days: Option<u32>,
+    /// Raw turns older than this are deleted once summarized. Falls back to
+    /// `memory.retention_days`.
+    pub delete_after_days: Option<u32>,
+    /// Summary memories older than this are deleted. Unset keeps them.
+    pub summary_max_age_days: Option<u32>,
+    /// Turns folded into a single summary memory.
+    pub batch_size: Option<usize>,
+    /// Minimum gap between automatic passes for one user.
+    pub interval_minutes: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq, Eq)]
@@ -182,6 +258,8 @@ fn is_legacy_ollama_base_url(base_url: &str) -> bool {
 pub struct Config {
     pub provider: Option<ProviderConfig>,
     pub openai: Option<OpenAiConfig>,
+    #[serde(default)]
+    pub llm: Option<LlmConfig>,
     #[serde(
         default = "default_heartbeat_source",
         alias = "heartbeat_file",
@@ -321,6 +399,7 @@ impl Config {
                 model: Some(router_model),
                 base_url: Some("https://api.openai.com/v1".to_string()),
             }),
+            llm: None,
             heartbeat_source: default_heartbeat_source(),
             prompt_source: default_prompt_source(),
             memory: Some(MemoryConfig {
@@ -333,6 +412,11 @@ impl Config {
                 context_embed_enabled: Some(false),
                 summary_threshold: None,
                 retention_days: None,
+                retention: Some(MemoryRetentionConfig {
+                    summarize_after_days: Some(14),
+                    delete_after_days: Some(90),
+                    ..Default::default()
+                }),
             }),
             tools: Some(Value::Object(Map::new())),
             brains: None,
@@ -359,6 +443,60 @@ impl Config {
         }
     }
 
+    /// The stored config with `${env:VAR}` and `${file:/path}` references
+    /// resolved, for running the bot. Settings editors use
+    /// [`Config::from_store`] instead so references are saved back as
+    /// written.
+    ///
+    /// Once a config has loaded, a later failure (an unset variable, a
+    /// deleted file) returns that last good config rather than an error, so
+    /// callers never fall back to defaults and drop guardrails, rate limits
+    /// or roles. Use [`Config::reload`] to see the failure.
+    pub fn load(db_path: &str) -> Result<Self> {
+        let err = match Self::reload(db_path) {
+            Ok(config) => return Ok(config),
+            Err(err) => err,
+        };
+        let cached = last_good()
+            .read()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .get(db_path)
+            .cloned();
+        match cached {
+            Some(config) => {
+                tracing::warn!(db_path, error = %err, "Keeping the last good config");
+                Ok(config)
+            }
+            None => Err(err),
+        }
+    }
+
+    /// [`Config::load`] without the fallback, for startup and explicit
+    /// reloads that must report a broken config.
+    pub fn reload(db_path: &str) -> Result<Self> {
+        let config = Self::from_store(db_path)?.resolve_references()?;
+        last_good()
+            .write()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .insert(db_path.to_string(), config.clone());
+        Ok(config)
+    }
+
+    /// Resolves `${env:VAR}` and `${file:/path}` references in every config
+    /// string except the heartbeat and prompt markdown.
+    pub fn resolve_references(self) -> Result<Self> {
+        let mut value =
+            serde_json::to_value(&self).map_err(|e| ButterflyBotError::Config(e.to_string()))?;
+        if let Value::Object(fields) = &mut value {
+            for (key, field) in fields.iter_mut() {
+                if key != "heartbeat_source" && key != "prompt_source" {
+                    interpolate_references(field)?;
+                }
+            }
+        }
+        serde_json::from_value(value).map_err(|e| ButterflyBotError::Config(e.to_string()))
+    }
+
     pub fn resolve_vault(mut self) -> Result<Self> {
         if let Some(openai) = &mut self.openai {
             if openai.api_key.is_none() {
@@ -370,6 +508,16 @@ impl Config {
                 }
             }
         }
+        if let Some(llm) = &mut self.llm {
+            if llm.provider == LlmProviderKind::Anthropic && llm.api_key.is_none() {
+                if let Some(secret) = crate::vault::get_secret_required("anthropic_api_key")? {
+                    let trimmed = secret.trim();
+                    if !trimmed.is_empty() {
+                        llm.api_key = Some(trimmed.to_string());
+                    }
+                }
+            }
+        }
         if let Some(memory) = &mut self.memory {
             if let Some(openai) = &mut memory.openai {
                 if openai.api_key.is_none() {
@@ -388,6 +536,82 @@ impl Config {
     }
 }
 
+/// The last config per database that [`Config::reload`] resolved.
+fn last_good() -> &'static RwLock<HashMap<String, Config>> {
+    static LAST_GOOD: OnceLock<RwLock<HashMap<String, Config>>> = OnceLock::new();
+    LAST_GOOD.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Replaces `${env:VAR}` with the environment variable and `${file:/path}`
+/// with the file's contents (minus a trailing newline) in every string in
+/// `value`. `$${` is a literal `${`; other `${...}` text is left alone.
+/// A reference that cannot be read is an error naming it, so a missing
+/// secret fails at load time rather than reaching a provider as text.
+pub fn interpolate_references(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(text) => {
+            if text.contains("${") {
+                *text = interpolate_str(text)?;
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                interpolate_references(item)?;
+            }
+        }
+        Value::Object(fields) => {
+            for field in fields.values_mut() {
+                interpolate_references(field)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn interpolate_str(text: &str) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            out.push_str(&rest[..start - 1]);
+            out.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        out.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            out.push_str(&rest[start..]);
+            return Ok(out);
+        };
+        let reference = &rest[start + 2..start + len];
+        match reference.split_once(':') {
+            Some(("env", name)) => {
+                let name = name.trim();
+                let resolved = std::env::var(name).map_err(|_| {
+                    ButterflyBotError::Config(format!(
+                        "Config references ${{env:{name}}}, which is not set"
+                    ))
+                })?;
+                out.push_str(&resolved);
+            }
+            Some(("file", path)) => {
+                let path = path.trim();
+                let contents = fs::read_to_string(path).map_err(|e| {
+                    ButterflyBotError::Config(format!(
+                        "Config references ${{file:{path}}}, which cannot be read: {e}"
+                    ))
+                })?;
+                out.push_str(contents.trim_end_matches(['\n', '\r']));
+            }
+            _ => out.push_str(&rest[start..start + len + 1]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -571,4 +795,61 @@ mod tests {
             .and_then(|memory| memory.openai.as_ref())
             .is_none());
     }
+
+    #[test]
+    fn env_and_file_references_resolve_at_load() {
+        let temp = tempfile::tempdir().unwrap();
+        let key_file = temp.path().join("openai_key");
+        std::fs::write(&key_file, "sk-from-file\n").unwrap();
+        std::env::set_var("BUTTERFLY_TEST_RPC_TOKEN", "tok123");
+
+        let mut config = Config::convention_defaults(":memory:");
+        config.openai.as_mut().unwrap().api_key = Some(format!("${{file:{}}}", key_file.display()));
+        config.tools = Some(json!({"settings": {"solana": {"rpc": {
+            "endpoint": "https://rpc.example/${env:BUTTERFLY_TEST_RPC_TOKEN}",
+            "note": "$${env:NOT_A_REF} and ${other}"
+        }}}}));
+        let resolved = config.clone().resolve_references().unwrap();
+        assert_eq!(
+            resolved.openai.unwrap().api_key.as_deref(),
+            Some("sk-from-file")
+        );
+        let rpc = &resolved.tools.unwrap()["settings"]["solana"]["rpc"];
+        assert_eq!(rpc["endpoint"], "https://rpc.example/tok123");
+        assert_eq!(rpc["note"], "${env:NOT_A_REF} and ${other}");
+
+        config.openai.as_mut().unwrap().api_key =
+            Some("${env:BUTTERFLY_TEST_MISSING_VAR}".to_string());
+        let err = config.resolve_references().unwrap_err().to_string();
+        assert!(err.contains("BUTTERFLY_TEST_MISSING_VAR"), "{err}");
+    }
+
+    #[test]
+    fn load_keeps_the_last_good_config_when_a_reference_breaks() {
+        let temp = tempfile::tempdir().unwrap();
+        let db_path = temp.path().join("config.db").display().to_string();
+        let mut config = Config::convention_defaults(&db_path);
+        config.tools = Some(json!({"settings": {"roles": {
+            "default_role": "${env:BUTTERFLY_TEST_DEFAULT_ROLE}"
+        }}}));
+        crate::config_store::save_config(&db_path, &config).unwrap();
+
+        std::env::remove_var("BUTTERFLY_TEST_DEFAULT_ROLE");
+        assert!(Config::load(&db_path).is_err());
+
+        std::env::set_var("BUTTERFLY_TEST_DEFAULT_ROLE", "readonly");
+        let loaded = Config::load(&db_path).unwrap();
+        assert_eq!(
+            loaded.tools.unwrap()["settings"]["roles"]["default_role"],
+            "readonly"
+        );
+
+        std::env::remove_var("BUTTERFLY_TEST_DEFAULT_ROLE");
+        assert!(Config::reload(&db_path).is_err());
+        let kept = Config::load(&db_path).unwrap();
+        assert_eq!(
+            kept.tools.unwrap()["settings"]["roles"]["default_role"],
+            "readonly"
+        );
+    }
 }
@@ -0,0 +1,497 @@
+//! Standing consent for sensitive capabilities.
+//!
+//! The first time the agent reaches for a capability the consent guardrail
+//! covers (see [`crate::guardrails::consent`]), the tool registry files a
+//! request here and refuses the call. The request shows up in the inbox as
+//! `consent:<id>`; once the user allows it, the grant covers every later
+//! call with the same capability and scope until it expires or is revoked.
+//! Rows are never deleted, so the table doubles as the user's consent log.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::consent_grants;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const CONSENT_GRANTS_UP_SQL: &str =
+    include_str!("../../migrations/20260327_create_consent_grants/up.sql");
+
+const REF_PREFIX: &str = "consent:";
+/// Scope of a grant that covers every use of its capability.
+pub const ANY_SCOPE: &str = "*";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsentStatus {
+    Pending,
+    Granted,
+    Denied,
+    Revoked,
+}
+
+impl ConsentStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConsentStatus::Pending => "pending",
+            ConsentStatus::Granted => "granted",
+            ConsentStatus::Denied => "denied",
+            ConsentStatus::Revoked => "revoked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "pending" => Some(ConsentStatus::Pending),
+            "granted" => Some(ConsentStatus::Granted),
+            "denied" => Some(ConsentStatus::Denied),
+            "revoked" => Some(ConsentStatus::Revoked),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConsentRecord {
+    pub id: i32,
+    pub user_id: String,
+    /// The tool whose call asked first.
+    pub tool: String,
+    pub capability: String,
+    /// What the grant covers, e.g. a domain for `http.request`, or
+    /// [`ANY_SCOPE`].
+    pub scope: String,
+    pub status: ConsentStatus,
+    pub requested_at: i64,
+    pub decided_at: Option<i64>,
+    /// No expiry when unset.
+    pub expires_at: Option<i64>,
+}
+
+impl ConsentRecord {
+    pub fn origin_ref(&self) -> String {
+        format!("{REF_PREFIX}{}", self.id)
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        self.status == ConsentStatus::Granted && self.expires_at.is_none_or(|at| at > now)
+    }
+
+    /// What the record means, in a sentence for the consent log.
+    pub fn summary(&self, now: i64) -> String {
+        let target = if self.scope == ANY_SCOPE {
+            format!("'{}'", self.capability)
+        } else {
+            format!("'{}' for {}", self.capability, self.scope)
+        };
+        match self.status {
+            ConsentStatus::Pending => format!("Asked to allow {target}"),
+            ConsentStatus::Denied => format!("Denied {target}"),
+            ConsentStatus::Revoked => format!("Revoked {target}"),
+            ConsentStatus::Granted => match self.expires_at {
+                None => format!("Allowed {target} with no expiry"),
+                Some(at) if at > now => format!("Allowed {target} until {}", format_day(at)),
+                Some(at) => format!("Allowed {target}; expired {}", format_day(at)),
+            },
+        }
+    }
+}
+
+/// Where a user stands on one capability and scope.
+#[derive(Clone, Debug)]
+pub enum ConsentState {
+    /// Never asked, or the last answer expired or was revoked.
+    Missing,
+    Pending(ConsentRecord),
+    Granted(ConsentRecord),
+    Denied(ConsentRecord),
+}
+
+#[derive(Queryable)]
+struct ConsentRow {
+    id: i32,
+    user_id: String,
+    tool: String,
+    capability: String,
+    scope: String,
+    status: String,
+    requested_at: i64,
+    decided_at: Option<i64>,
+    expires_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = consent_grants)]
+struct NewConsent<'a> {
+    user_id: &'a str,
+    tool: &'a str,
+    capability: &'a str,
+    scope: &'a str,
+    status: &'a str,
+    requested_at: i64,
+}
+
+pub struct ConsentStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl ConsentStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_consent_grants_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    pub async fn state(
+        &self,
+        user_id: &str,
+        capability: &str,
+        scope: &str,
+    ) -> Result<ConsentState> {
+        let mut conn = self.conn().await?;
+        let row: Option<ConsentRow> = consent_grants::table
+            .filter(consent_grants::user_id.eq(user_id))
+            .filter(consent_grants::capability.eq(capability))
+            .filter(consent_grants::scope.eq(scope))
+            .order(consent_grants::id.desc())
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let Some(record) = row.map(map_row) else {
+            return Ok(ConsentState::Missing);
+        };
+        Ok(match record.status {
+            ConsentStatus::Pending => ConsentState::Pending(record),
+            ConsentStatus::Denied => ConsentState::Denied(record),
+            ConsentStatus::Granted if record.is_active(self.clock.now()) => {
+                ConsentState::Granted(record)
+            }
+            ConsentStatus::Granted | ConsentStatus::Revoked => ConsentState::Missing,
+        })
+    }
+
+    /// Files a consent request, or returns the one already waiting.
+    pub async fn request(
+        &self,
+        user_id: &str,
+        tool: &str,
+        capability: &str,
+        scope: &str,
+    ) -> Result<ConsentRecord> {
+        if let ConsentState::Pending(record) = self.state(user_id, capability, scope).await? {
+            return Ok(record);
+        }
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(consent_grants::table)
+            .values(&NewConsent {
+                user_id,
+                tool,
+                capability,
+                scope,
+                status: ConsentStatus::Pending.as_str(),
+                requested_at: self.clock.now(),
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let row: ConsentRow = consent_grants::table
+            .filter(consent_grants::user_id.eq(user_id))
+            .order(consent_grants::id.desc())
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(map_row(row))
+    }
+
+    pub async fn get(&self, user_id: &str, id: i32) -> Result<Option<ConsentRecord>> {
+        let mut conn = self.conn().await?;
+        let row: Option<ConsentRow> = consent_grants::table
+            .filter(consent_grants::user_id.eq(user_id))
+            .filter(consent_grants::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    /// The consent log, newest first.
+    pub async fn list(&self, user_id: &str, limit: usize) -> Result<Vec<ConsentRecord>> {
+        self.load(user_id, None, limit).await
+    }
+
+    pub async fn list_pending(&self, user_id: &str, limit: usize) -> Result<Vec<ConsentRecord>> {
+        self.load(user_id, Some(ConsentStatus::Pending), limit)
+            .await
+    }
+
+    /// Answers a pending request. A grant lasts `ttl_secs`, or until revoked
+    /// when `None`. Returns the record when it was still pending.
+    pub async fn decide(
+        &self,
+        user_id: &str,
+        id: i32,
+        grant: bool,
+        ttl_secs: Option<i64>,
+    ) -> Result<Option<ConsentRecord>> {
+        let now = self.clock.now();
+        let (status, expires_at) = if grant {
+            (ConsentStatus::Granted, ttl_secs.map(|ttl| now + ttl))
+        } else {
+            (ConsentStatus::Denied, None)
+        };
+        self.update(user_id, id, &[ConsentStatus::Pending], status, expires_at)
+            .await
+    }
+
+    /// Withdraws a decision, so the next call asks again.
+    pub async fn revoke(&self, user_id: &str, id: i32) -> Result<Option<ConsentRecord>> {
+        self.update(
+            user_id,
+            id,
+            &[ConsentStatus::Granted, ConsentStatus::Denied],
+            ConsentStatus::Revoked,
+            None,
+        )
+        .await
+    }
+
+    async fn update(
+        &self,
+        user_id: &str,
+        id: i32,
+        from: &[ConsentStatus],
+        to: ConsentStatus,
+        expires_at: Option<i64>,
+    ) -> Result<Option<ConsentRecord>> {
+        let from: Vec<&str> = from.iter().map(|status| status.as_str()).collect();
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            consent_grants::table
+                .filter(consent_grants::user_id.eq(user_id))
+                .filter(consent_grants::id.eq(id))
+                .filter(consent_grants::status.eq_any(from)),
+        )
+        .set((
+            consent_grants::status.eq(to.as_str()),
+            consent_grants::decided_at.eq(Some(self.clock.now())),
+            consent_grants::expires_at.eq(expires_at),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.get(user_id, id).await
+    }
+
+    async fn load(
+        &self,
+        user_id: &str,
+        status: Option<ConsentStatus>,
+        limit: usize,
+    ) -> Result<Vec<ConsentRecord>> {
+        let mut conn = self.conn().await?;
+        let mut query = consent_grants::table
+            .filter(consent_grants::user_id.eq(user_id))
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(consent_grants::status.eq(status.as_str()));
+        }
+        if limit > 0 {
+            query = query.limit(limit as i64);
+        }
+        let rows: Vec<ConsentRow> = query
+            .order(consent_grants::id.desc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// Parses an inbox origin ref of the form `consent:<id>`.
+pub fn parse_origin_ref(origin_ref: &str) -> Option<i32> {
+    origin_ref.trim().strip_prefix(REF_PREFIX)?.parse().ok()
+}
+
+fn format_day(ts: i64) -> String {
+    OffsetDateTime::from_unix_timestamp(ts)
+        .ok()
+        .and_then(|at| at.format(format_description!("[year]-[month]-[day]")).ok())
+        .unwrap_or_else(|| ts.to_string())
+}
+
+fn map_row(row: ConsentRow) -> ConsentRecord {
+    ConsentRecord {
+        id: row.id,
+        user_id: row.user_id,
+        tool: row.tool,
+        capability: row.capability,
+        scope: row.scope,
+        status: ConsentStatus::parse(&row.status).unwrap_or(ConsentStatus::Pending),
+        requested_at: row.requested_at,
+        decided_at: row.decided_at,
+        expires_at: row.expires_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_consent_grants_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM consent_grants LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    CONSENT_GRANTS_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+
+    #[tokio::test]
+    async fn grants_cover_their_scope_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("consent.db");
+        let clock = ManualClock::new(1_700_000_000);
+        let store = ConsentStore::new(path.to_string_lossy())
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let asked = store
+            .request("u", "fetch", "http.request", "api.example.com")
+            .await
+            .unwrap();
+        assert_eq!(parse_origin_ref(&asked.origin_ref()), Some(asked.id));
+        let again = store
+            .request("u", "fetch", "http.request", "api.example.com")
+            .await
+            .unwrap();
+        assert_eq!(again.id, asked.id);
+        assert_eq!(store.list_pending("u", 10).await.unwrap().len(), 1);
+
+        let granted = store
+            .decide("u", asked.id, true, Some(86_400))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            granted.summary(clock.now()),
+            "Allowed 'http.request' for api.example.com until 2023-11-15"
+        );
+        assert!(store
+            .decide("u", asked.id, false, None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            store
+                .state("u", "http.request", "api.example.com")
+                .await
+                .unwrap(),
+            ConsentState::Granted(_)
+        ));
+        assert!(matches!(
+            store
+                .state("u", "http.request", "evil.example")
+                .await
+                .unwrap(),
+            ConsentState::Missing
+        ));
+
+        clock.advance(86_400);
+        assert!(matches!(
+            store
+                .state("u", "http.request", "api.example.com")
+                .await
+                .unwrap(),
+            ConsentState::Missing
+        ));
+
+        let asked = store
+            .request("u", "wallet", "solana.transfer", ANY_SCOPE)
+            .await
+            .unwrap();
+        store.decide("u", asked.id, false, None).await.unwrap();
+        assert!(matches!(
+            store
+                .state("u", "solana.transfer", ANY_SCOPE)
+                .await
+                .unwrap(),
+            ConsentState::Denied(_)
+        ));
+        let revoked = store.revoke("u", asked.id).await.unwrap().unwrap();
+        assert_eq!(revoked.summary(clock.now()), "Revoked 'solana.transfer'");
+        assert_eq!(store.list("u", 10).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,13 @@
+diesel::table! {
+    consent_grants (id) {
+        id -> Integer,
+        user_id -> Text,
+        tool -> Text,
+        capability -> Text,
+        scope -> Text,
+        status -> Text,
+        requested_at -> BigInt,
+        decided_at -> Nullable<BigInt>,
+        expires_at -> Nullable<BigInt>,
+    }
+}
@@ -14,8 +14,7 @@ use axum::{
     Router,
 };
 use bytes::Bytes;
-use chrono::{DateTime, Local, TimeZone};
-use chrono_english::{parse_date_string, Dialect};
+use chrono::{Local, TimeZone};
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
 use futures::StreamExt;
@@ -24,28 +23,77 @@ use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use time::{Date, PrimitiveDateTime, Time, UtcOffset};
 
+use crate::approvals::{
+    ApprovalStatus, ApprovalStep, ApprovalStore, PendingApproval, StepOutcome, StepStatus,
+};
+use crate::attachments::{Attachment, AttachmentConfig, AttachmentStore};
+use crate::audit::{AuditEvent, AuditQuery, AuditSeverity, AuditStore};
+use crate::backup::{self, BackupConfig, BackupRun, Snapshot};
+use crate::calendar::{CalendarConfig, CalendarStore, SyncReport};
 use crate::client::ButterflyBot;
-use crate::config::Config;
+use crate::config::{Config, LlmProviderKind};
 use crate::config_store;
+use crate::consent::{ConsentRecord, ConsentStore};
+use crate::dashboard::{self, DashboardConfig, DashboardItem, DashboardSummary};
+use crate::db::DbHandle;
+use crate::digest::{self, Digest, DigestChannel, DigestConfig, DigestItem, DigestSchedule};
+use crate::email::{EmailConfig, IngestReport};
 use crate::error::{ButterflyBotError, Result};
+use crate::external_items::github::{self, GithubAction, GithubWebhookConfig};
+use crate::external_items::zapier::{self, ZapierAction, ZapierInbound};
+use crate::external_items::{self, ExternalItem, ExternalItemStore, ExternalUpdate};
 use crate::factories::agent_factory::load_markdown_content;
+use crate::guardrails::consent::ConsentPolicy;
 use crate::inbox_fsm::{InboxAction, InboxState};
-use crate::inbox_state::InboxStateStore;
+use crate::inbox_rules::{InboxRules, RuleSubject};
+use crate::inbox_state::{InboxComment, InboxStateStore, AUTHOR_HUMAN};
+use crate::inbox_sweep::{self, SweepBatch, SweepConfig, SweepItem};
 use crate::interfaces::scheduler::ScheduledJob;
+use crate::labels::{LabelStore, LabelTarget};
+use crate::matrix::MatrixConfig;
+use crate::outbox::{OutboxConfig, OutboxDelivery, OutboxStore};
 use crate::planning::{resolve_plan_db_path, PlanStore};
-use crate::reminders::{resolve_reminder_db_path, ReminderStore};
-use crate::sandbox::{SandboxSettings, ToolRuntime};
+use crate::plugins::posture::ToolPosture;
+use crate::plugins::registry::ToolDescriptor;
+use crate::privacy_lock;
+use crate::projects::{ProjectStore, ProjectSummary, ProjectTarget};
+use crate::prompt_queue::{PromptPriority, PromptQueueConfig, TicketStatus};
+use crate::questions::{self, QuestionStore};
+use crate::redaction::redact_json;
+use crate::refs::{self, RefResolver};
+use crate::reminders::{
+    reminders_complete_on_fire, resolve_reminder_db_path, DeliveryChannel, DeliveryConfig,
+    DeliveryFailure, DeliveryWindows, EscalationPolicy, EscalationStep, ReminderDelivery,
+    ReminderStore,
+};
+use crate::remote_storage::{Category as RemoteCategory, RemoteUploader};
+use crate::replication::{self, ReplicaTarget, ReplicationConfig, ReplicationRun, Replicator};
+use crate::roles::{Role, RolePolicy, RoleStore, UserRole};
+use crate::sandbox::coverage::{CapabilityCoverage, CoverageReport};
+use crate::sandbox::{SandboxSettings, ToolRuntime, WasmRuntime};
+use crate::scheduler::catch_up::{CatchUpConfig, CatchUpPolicy};
+use crate::scheduler::state::JobStateStore;
 use crate::scheduler::Scheduler;
+use crate::search::{self, SearchHit, SearchIndex, SearchKind, SearchSources};
 use crate::security::policy::SigningIntent;
 use crate::security::signer_daemon::{SignerRequest, SignerService};
 use crate::security::solana_rpc_policy::SolanaRpcExecutionPolicy;
+use crate::security::user_domains::{self, DomainStatus, UserDomainStore};
 use crate::security::x402::canonicalize_payment_required;
-use crate::services::agent::UiEvent;
+use crate::services::agent::{with_pinned_llm, UiEvent};
 use crate::services::query::{OutputFormat, ProcessOptions, ProcessResult, UserInput};
+use crate::sessions::{SessionStore, UserToken};
+use crate::shutdown::ShutdownConfig;
+use crate::slash_commands::{self, SlashCommand};
+use crate::smart_lists::SmartList;
 use crate::tasks::{resolve_task_db_path, TaskStore};
-use crate::todo::{resolve_todo_db_path, TodoStore};
+use crate::templates::{self, ImportSummary, ImportTargets, PromptTemplateStore, TemplateBundle};
+use crate::threads::{self, ChatThread, ThreadPin, ThreadStore};
+use crate::todo::{resolve_todo_db_path, EstimateAccuracy, TodoStore};
+use crate::trash::{TrashBatch, TrashConfig};
 use crate::vault;
-use crate::wakeup::WakeupStore;
+use crate::voice::VoiceConfig;
+use crate::wakeup::{RunningWakeups, WakeupStore};
 use tokio::sync::{broadcast, RwLock};
 
 #[derive(Clone)]
@@ -56,6 +104,10 @@ pub struct AppState {
     pub token: String,
     pub ui_event_tx: broadcast::Sender<UiEvent>,
     pub db_path: String,
+    pub session_store: Arc<SessionStore>,
+    pub role_store: Arc<RoleStore>,
+    /// `tools.settings.roles`, read at startup and on each config reload.
+    pub role_policy: Arc<RwLock<RolePolicy>>,
 }
 
 static AUTONOMY_LAST_RUN_TS: AtomicI64 = AtomicI64::new(0);
@@ -110,6 +162,10 @@ impl ScheduledJob for BrainTickJob {
 struct WakeupJob {
     agent: Arc<RwLock<Arc<ButterflyBot>>>,
     store: Arc<WakeupStore>,
+    /// Runs still executing; a wakeup at its `max_concurrency` is skipped.
+    running: RunningWakeups,
+    /// Applied to wakeups that missed slots while the daemon was down.
+    catch_up: CatchUpConfig,
     interval: Duration,
     ui_event_tx: broadcast::Sender<UiEvent>,
     audit_log_path: Option<String>,
@@ -120,6 +176,8 @@ struct WakeupJob {
 struct ScheduledTasksJob {
     agent: Arc<RwLock<Arc<ButterflyBot>>>,
     store: Arc<TaskStore>,
+    /// Applied to recurring tasks that missed slots while the daemon was down.
+    catch_up: CatchUpConfig,
     interval: Duration,
     ui_event_tx: broadcast::Sender<UiEvent>,
     audit_log_path: Option<String>,
@@ -130,12 +188,32 @@ struct ReminderDispatchJob {
     interval: Duration,
     ui_event_tx: broadcast::Sender<UiEvent>,
     audit_log_path: Option<String>,
+    delivery_windows: DeliveryWindows,
+    escalation: EscalationPolicy,
+    agent: Arc<RwLock<Arc<ButterflyBot>>>,
+    delivery: DeliveryConfig,
+}
+
+struct ReminderEscalationJob {
+    agent: Arc<RwLock<Arc<ButterflyBot>>>,
+    store: Arc<ReminderStore>,
+    db_path: String,
+    interval: Duration,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    audit_log_path: Option<String>,
+    escalation: EscalationPolicy,
+}
+
+struct ChecklistResetJob {
+    store: Arc<TodoStore>,
+    interval: Duration,
+    ui_event_tx: broadcast::Sender<UiEvent>,
 }
 
 #[async_trait::async_trait]
-impl ScheduledJob for ScheduledTasksJob {
+impl ScheduledJob for ChecklistResetJob {
     fn name(&self) -> &str {
-        "scheduled_tasks"
+        "checklist_reset"
     }
 
     fn interval(&self) -> Duration {
@@ -143,3432 +221,3317 @@ impl ScheduledJob for ScheduledTasksJob {
     }
 
     async fn run(&self) -> Result<()> {
-        let now = now_ts();
-        let tasks = self.store.list_due(now, 32).await?;
-        for task in tasks {
-            let agent = self.agent.read().await.clone();
-            let run_at = now_ts();
-            let next_run_at = if let Some(interval) = task.interval_minutes {
-                run_at + interval.max(1) * 60
-            } else {
-                run_at
+        let now = self.store.clock().now();
+        let due = self.store.due_checklists(now, 32).await?;
+        for checklist in due {
+            let Some((run, items)) = self
+                .store
+                .reset_checklist(&checklist.user_id, checklist.id)
+                .await?
+            else {
+                continue;
             };
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "todo".to_string(),
+                user_id: checklist.user_id.clone(),
+                tool: "todo".to_string(),
+                status: "checklist_reset".to_string(),
+                payload: json!({
+                    "checklist_id": checklist.id,
+                    "name": checklist.name,
+                    "completed_items": run.completed_items,
+                    "total_items": run.total_items,
+                    "items": items.len(),
+                }),
+                timestamp: now,
+            });
+        }
+        Ok(())
+    }
+}
 
-            if task.interval_minutes.is_some() {
-                let _ = self.store.mark_run(task.id, run_at, next_run_at).await;
-            } else {
-                let _ = self.store.complete_one_shot(task.id).await;
-            }
+struct TrashPurgeJob {
+    db_path: String,
+    config: TrashConfig,
+    clock: crate::clock::SharedClock,
+}
 
-            let options = ProcessOptions {
-                prompt: None,
-                images: Vec::new(),
-                output_format: OutputFormat::Text,
-                image_detail: "auto".to_string(),
-                json_schema: None,
-            };
-            let input = format!("Scheduled task '{}': {}", task.name, task.prompt);
-            let result = agent
-                .process(&task.user_id, UserInput::Text(input), options)
-                .await;
+#[async_trait::async_trait]
+impl ScheduledJob for TrashPurgeJob {
+    fn name(&self) -> &str {
+        "trash_purge"
+    }
 
-            let (status, payload): (String, serde_json::Value) = match result {
-                Ok(ProcessResult::Text(text)) => (
-                    "ok".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "output": text}),
-                ),
-                Ok(other) => (
-                    "ok".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "output": format!("{other:?}")}),
-                ),
-                Err(err) => (
-                    "error".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "error": err.to_string()}),
-                ),
-            };
+    fn interval(&self) -> Duration {
+        Duration::from_secs(3600)
+    }
 
-            let event = UiEvent {
-                event_type: "tasks".to_string(),
-                user_id: task.user_id.clone(),
-                tool: "tasks".to_string(),
-                status: status.clone(),
-                payload: payload.clone(),
-                timestamp: run_at,
-            };
-            let _ = self.ui_event_tx.send(event);
-            let _ = write_tasks_audit_log(
-                self.audit_log_path.as_deref(),
-                run_at,
-                &task,
-                status.as_str(),
-                payload,
-            );
+    async fn run(&self) -> Result<()> {
+        let cutoff = self.config.purge_cutoff(self.clock.now());
+        let db_path = self.db_path.as_str();
+        let purged = TodoStore::new(db_path).await?.purge_trash(cutoff).await?
+            + ReminderStore::new(db_path)
+                .await?
+                .purge_trash(cutoff)
+                .await?
+            + TaskStore::new(db_path).await?.purge_trash(cutoff).await?
+            + PlanStore::new(db_path).await?.purge_trash(cutoff).await?;
+        if purged > 0 {
+            tracing::info!(purged, cutoff, "Purged expired trash");
         }
         Ok(())
     }
 }
 
+/// Removes attachment blobs nothing points at any more.
+struct AttachmentSweepJob {
+    db_path: String,
+}
+
 #[async_trait::async_trait]
-impl ScheduledJob for ReminderDispatchJob {
+impl ScheduledJob for AttachmentSweepJob {
     fn name(&self) -> &str {
-        "reminder_dispatch"
+        "attachment_sweep"
     }
 
     fn interval(&self) -> Duration {
-        self.interval
+        Duration::from_secs(86_400)
     }
 
     async fn run(&self) -> Result<()> {
-        let now = now_ts();
-        let due = self.store.peek_due_reminders_all(now, 32).await?;
-        for reminder in due {
-            let title = reminder.item.title.clone();
-            let base_payload = json!({
-                "id": reminder.item.id,
-                "title": title,
-                "due_at": reminder.item.due_at,
-            });
-
-            let _ = self.ui_event_tx.send(UiEvent {
-                event_type: "reminder_delivery".to_string(),
-                user_id: reminder.user_id.clone(),
-                tool: "reminders".to_string(),
-                status: "queued".to_string(),
-                payload: base_payload.clone(),
-                timestamp: now,
-            });
-            let _ = write_reminder_audit_log(
-                self.audit_log_path.as_deref(),
-                now,
-                &reminder.user_id,
-                reminder.item.id,
-                "queued",
-                base_payload.clone(),
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let sweep =
+            AttachmentStore::new(&self.db_path, AttachmentConfig::from_tools(tools.as_ref()))
+                .await?
+                .sweep_orphans()
+                .await?;
+        if sweep.removed > 0 {
+            tracing::info!(
+                removed = sweep.removed,
+                bytes = sweep.bytes,
+                "Removed orphaned attachment blobs"
             );
+        }
+        Ok(())
+    }
+}
 
-            let _ = self.ui_event_tx.send(UiEvent {
-                event_type: "reminder".to_string(),
-                user_id: reminder.user_id.clone(),
-                tool: "reminders".to_string(),
-                status: "due".to_string(),
-                payload: base_payload.clone(),
-                timestamp: now,
-            });
+/// Moves blocked inbox items back to new once everything they depend on is
+/// done, for dependencies completed outside the inbox (tools, the agent).
+struct DependencyUnblockJob {
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+}
 
-            let _ = self.ui_event_tx.send(UiEvent {
-                event_type: "reminder_delivery".to_string(),
-                user_id: reminder.user_id.clone(),
-                tool: "reminders".to_string(),
-                status: "delivery_attempted".to_string(),
-                payload: base_payload.clone(),
-                timestamp: now,
-            });
-            let _ = write_reminder_audit_log(
-                self.audit_log_path.as_deref(),
-                now,
-                &reminder.user_id,
-                reminder.item.id,
-                "delivery_attempted",
-                base_payload.clone(),
-            );
+#[async_trait::async_trait]
+impl ScheduledJob for DependencyUnblockJob {
+    fn name(&self) -> &str {
+        "dependency_unblock"
+    }
 
-            let delivered = send_desktop_notification("Butterfly Bot reminder", &title);
-            if delivered {
-                let _ = self
-                    .store
-                    .mark_fired_reminder(&reminder.user_id, reminder.item.id, now)
-                    .await;
-                let _ = self.ui_event_tx.send(UiEvent {
-                    event_type: "reminder_delivery".to_string(),
-                    user_id: reminder.user_id.clone(),
-                    tool: "reminders".to_string(),
-                    status: "delivered".to_string(),
-                    payload: base_payload.clone(),
-                    timestamp: now,
-                });
-                let _ = write_reminder_audit_log(
-                    self.audit_log_path.as_deref(),
-                    now,
-                    &reminder.user_id,
-                    reminder.item.id,
-                    "delivered",
-                    base_payload.clone(),
-                );
-            } else {
-                let _ = self.ui_event_tx.send(UiEvent {
-                    event_type: "reminder_delivery".to_string(),
-                    user_id: reminder.user_id.clone(),
-                    tool: "reminders".to_string(),
-                    status: "delivery_failed".to_string(),
-                    payload: base_payload,
-                    timestamp: now,
-                });
-                let _ = write_reminder_audit_log(
-                    self.audit_log_path.as_deref(),
-                    now,
-                    &reminder.user_id,
-                    reminder.item.id,
-                    "delivery_failed",
-                    json!({
-                        "id": reminder.item.id,
-                        "title": reminder.item.title,
-                        "due_at": reminder.item.due_at,
-                    }),
-                );
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
+
+    async fn run(&self) -> Result<()> {
+        let users = InboxStateStore::new(&self.db_path)
+            .await?
+            .users_with_status("blocked")
+            .await?;
+        for user_id in users {
+            if let Err(err) = unblock_ready_items(&self.db_path, &self.ui_event_tx, &user_id).await
+            {
+                tracing::warn!(user_id, error = %err, "Dependency unblocking failed");
             }
         }
         Ok(())
     }
 }
 
+/// Moves the user's blocked inbox items whose dependencies are all done to
+/// new, with an `unblock` transition event each. An item with no
+/// dependencies, or with one this daemon can't see the status of, stays
+/// blocked. Returns the refs it moved.
+async fn unblock_ready_items(
+    db_path: &str,
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+    user_id: &str,
+) -> Result<Vec<String>> {
+    let states = InboxStateStore::new(db_path).await?;
+    let statuses = states.list_statuses(user_id, 5000).await?;
+    let mut blocked = statuses
+        .iter()
+        .filter(|(_, status)| status.as_str() == "blocked")
+        .map(|(origin_ref, _)| origin_ref.clone())
+        .collect::<Vec<_>>();
+    if blocked.is_empty() {
+        return Ok(Vec::new());
+    }
+    blocked.sort();
+
+    let config_json = Config::load(db_path)
+        .ok()
+        .and_then(|cfg| cfg.tools)
+        .unwrap_or(Value::Null);
+    let resolver = RefResolver::load(&config_json, db_path, user_id).await?;
+    // The inbox shows a persisted status over the store's own.
+    let is_done = |origin_ref: &str| match statuses.get(origin_ref) {
+        Some(status) => status == "done",
+        None => resolver.resolve(origin_ref).is_ok_and(|item| item.done),
+    };
+
+    let mut unblocked = Vec::new();
+    for origin_ref in blocked {
+        let Ok(item) = resolver.resolve(&origin_ref) else {
+            continue;
+        };
+        if item.dependency_refs.is_empty()
+            || !item
+                .dependency_refs
+                .iter()
+                .all(|dep_ref| refs::is_tracked(dep_ref) && is_done(dep_ref))
+        {
+            continue;
+        }
+        states.set_status(user_id, &origin_ref, "new").await?;
+        states
+            .record_transition(user_id, &origin_ref, "blocked", "new")
+            .await?;
+        if let Some(refs::OriginRef::PlanStep { plan_id, index }) =
+            refs::OriginRef::parse(&origin_ref)
+        {
+            let plan_store = PlanStore::new(
+                resolve_plan_db_path(&config_json).unwrap_or_else(|| db_path.to_string()),
+            )
+            .await?;
+            let step_blocked = plan_store
+                .get_plan(plan_id)
+                .await?
+                .steps
+                .as_ref()
+                .and_then(|steps| steps.get(index))
+                .and_then(|step| step.get("status"))
+                .and_then(Value::as_str)
+                == Some("blocked");
+            if step_blocked {
+                plan_store
+                    .set_step_status(user_id, plan_id, index, "new")
+                    .await?;
+            }
+        }
+        let _ = ui_event_tx.send(UiEvent {
+            event_type: "inbox_transition".to_string(),
+            user_id: user_id.to_string(),
+            tool: "inbox".to_string(),
+            status: "new".to_string(),
+            payload: json!({
+                "origin_ref": origin_ref,
+                "source_type": item.kind,
+                "action": "unblock",
+                "actor": "system",
+                "reason": "All dependencies are done",
+                "from": "blocked",
+                "to": "new",
+                "previous_status": "blocked",
+                "next_status": "new",
+                "dependency_refs": item.dependency_refs,
+            }),
+            timestamp: now_ts(),
+        });
+        unblocked.push(origin_ref);
+    }
+    Ok(unblocked)
+}
+
+struct OutboxDeliveryJob {
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    client: reqwest::Client,
+}
+
 #[async_trait::async_trait]
-impl ScheduledJob for WakeupJob {
+impl ScheduledJob for OutboxDeliveryJob {
     fn name(&self) -> &str {
-        "wakeup"
+        "outbox_delivery"
     }
 
     fn interval(&self) -> Duration {
-        self.interval
+        Duration::from_secs(15)
     }
 
     async fn run(&self) -> Result<()> {
-        let now = now_ts();
-        let dynamic_source = Config::from_store(&self.db_path)
-            .ok()
-            .map(|cfg| cfg.heartbeat_source)
-            .unwrap_or_else(|| self.heartbeat_source.clone());
-        let prompt_source = Config::from_store(&self.db_path)
+        let tools = Config::load(&self.db_path)
             .ok()
-            .map(|cfg| cfg.prompt_source);
-
-        match load_markdown_content(&dynamic_source).await {
-            Ok(markdown) => {
-                let agent = self.agent.read().await.clone();
-                agent.set_heartbeat_markdown(markdown.clone()).await;
-                let status = if markdown
-                    .as_ref()
-                    .map(|m| !m.trim().is_empty())
-                    .unwrap_or(false)
-                {
-                    "ok"
-                } else {
-                    "empty"
-                };
-                let event = UiEvent {
-                    event_type: "wakeup".to_string(),
-                    user_id: "system".to_string(),
-                    tool: "heartbeat".to_string(),
-                    status: status.to_string(),
-                    payload: json!({"source": dynamic_source}),
-                    timestamp: now_ts(),
-                };
-                let _ = self.ui_event_tx.send(event);
-            }
-            Err(err) => {
-                let event = UiEvent {
-                    event_type: "wakeup".to_string(),
-                    user_id: "system".to_string(),
-                    tool: "heartbeat".to_string(),
-                    status: "error".to_string(),
-                    payload: json!({"source": dynamic_source, "error": err.to_string()}),
-                    timestamp: now_ts(),
-                };
-                let _ = self.ui_event_tx.send(event);
-            }
+            .and_then(|config| config.tools);
+        let config = OutboxConfig::from_tools(tools.as_ref());
+        let store = OutboxStore::new(&self.db_path).await?;
+        let outcomes = store
+            .deliver_due(&config, &self.client, 25, |name| {
+                crate::vault::get_secret(name).ok().flatten()
+            })
+            .await?;
+        for outcome in outcomes {
+            let delivery = outcome.delivery;
+            let status = match outcome.status {
+                crate::outbox::STATUS_PENDING => "retrying",
+                other => other,
+            };
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "outbox".to_string(),
+                user_id: delivery.user_id,
+                tool: delivery.event_type,
+                status: status.to_string(),
+                payload: json!({
+                    "delivery_id": delivery.id,
+                    "event_id": delivery.event_id,
+                    "endpoint_url": delivery.endpoint_url,
+                    "attempt": delivery.attempts + 1,
+                }),
+                timestamp: now_ts(),
+            });
         }
+        Ok(())
+    }
+}
 
-        if let Some(source) = &prompt_source {
-            match load_markdown_content(source).await {
-                Ok(markdown) => {
-                    let agent = self.agent.read().await.clone();
-                    agent.set_prompt_markdown(markdown.clone()).await;
-                    let status = if markdown
-                        .as_ref()
-                        .map(|m| !m.trim().is_empty())
-                        .unwrap_or(false)
-                    {
-                        "ok"
-                    } else {
-                        "empty"
-                    };
-                    let event = UiEvent {
-                        event_type: "wakeup".to_string(),
-                        user_id: "system".to_string(),
-                        tool: "prompt".to_string(),
-                        status: status.to_string(),
-                        payload: json!({"source": source}),
-                        timestamp: now_ts(),
-                    };
-                    let _ = self.ui_event_tx.send(event);
-                }
-                Err(err) => {
-                    let event = UiEvent {
-                        event_type: "wakeup".to_string(),
-                        user_id: "system".to_string(),
-                        tool: "prompt".to_string(),
-                        status: "error".to_string(),
-                        payload: json!({"source": source, "error": err.to_string()}),
-                        timestamp: now_ts(),
-                    };
-                    let _ = self.ui_event_tx.send(event);
-                }
-            }
-        }
-
-        // Autonomous heartbeat processing
-        {
-            let agent = self.agent.read().await.clone();
-            let ui_event_tx = self.ui_event_tx.clone();
-            tokio::spawn(async move {
-                run_autonomy_tick(agent, ui_event_tx, "system".to_string(), "wakeup").await;
-            });
-        }
-
-        let tasks = self.store.list_due(now, 32).await?;
-        for task in tasks {
-            let agent = self.agent.read().await.clone();
-            let run_at = now_ts();
-            let next_run_at = run_at + task.interval_minutes.max(1) * 60;
-            let _ = self.store.mark_run(task.id, run_at, next_run_at).await;
+/// Checks often and snapshots once the newest backup is older than the
+/// configured interval, so restarts don't push backups back.
+struct BackupJob {
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    clock: crate::clock::SharedClock,
+}
 
-            let options = ProcessOptions {
-                prompt: None,
-                images: Vec::new(),
-                output_format: OutputFormat::Text,
-                image_detail: "auto".to_string(),
-                json_schema: None,
-            };
-            let input = format!("Wakeup task '{}': {}", task.name, task.prompt);
-            let result = agent
-                .process(&task.user_id, UserInput::Text(input), options)
-                .await;
+#[async_trait::async_trait]
+impl ScheduledJob for BackupJob {
+    fn name(&self) -> &str {
+        "backup"
+    }
 
-            let (status, payload): (String, Value) = match result {
-                Ok(ProcessResult::Text(text)) => (
-                    "ok".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "output": text}),
-                ),
-                Ok(other) => (
-                    "ok".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "output": format!("{other:?}")}),
-                ),
-                Err(err) => (
-                    "error".to_string(),
-                    json!({"task_id": task.id, "name": task.name, "error": err.to_string()}),
-                ),
-            };
+    fn interval(&self) -> Duration {
+        Duration::from_secs(15 * 60)
+    }
 
-            let event = UiEvent {
-                event_type: "wakeup".to_string(),
-                user_id: task.user_id.clone(),
-                tool: "wakeup".to_string(),
-                status: status.clone(),
-                payload: payload.clone(),
-                timestamp: run_at,
-            };
-            let _ = self.ui_event_tx.send(event);
-            let _ = write_wakeup_audit_log(
-                self.audit_log_path.as_deref(),
-                run_at,
-                &task,
-                status.as_str(),
-                payload.clone(),
-            );
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let config = BackupConfig::from_tools(tools.as_ref());
+        if !config.enabled {
+            return Ok(());
         }
+        let now = self.clock.now();
+        let snapshots = backup::list_snapshots(&config.directory_for(&self.db_path))?;
+        if !config.is_due(snapshots.last(), now) {
+            return Ok(());
+        }
+        run_backup_now(
+            &self.db_path,
+            &config,
+            tools.as_ref(),
+            now,
+            &self.ui_event_tx,
+        )
+        .await;
         Ok(())
     }
 }
 
-#[derive(Serialize)]
-struct HealthResponse {
-    status: String,
+/// One backup pass, reported as a `backup` event either way.
+async fn run_backup_now(
+    db_path: &str,
+    config: &BackupConfig,
+    tools: Option<&Value>,
+    now: i64,
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+) -> BackupRun {
+    let result = match RemoteUploader::from_tools(tools) {
+        Ok(uploader) => backup::run_backup(db_path, config, uploader.as_ref(), now).await,
+        // A broken remote setup still gets a local snapshot.
+        Err(err) => {
+            tracing::warn!(error = %err, "Backing up locally only; remote storage unavailable");
+            backup::run_backup(db_path, config, None, now).await
+        }
+    };
+    let run = result.unwrap_or_else(|err| {
+        tracing::warn!(error = %err, "Backup failed");
+        backup::last_run().unwrap_or_default()
+    });
+    let _ = ui_event_tx.send(UiEvent {
+        event_type: "backup".to_string(),
+        user_id: "system".to_string(),
+        tool: "backup".to_string(),
+        status: if run.error.is_some() {
+            "error"
+        } else {
+            "success"
+        }
+        .to_string(),
+        payload: json!(run),
+        timestamp: now,
+    });
+    run
 }
 
-#[derive(Deserialize)]
-struct ProcessTextRequest {
-    user_id: String,
-    text: String,
-    prompt: Option<String>,
+/// Ships newly committed log frames to the replica each pass. Keeps its
+/// replicator between passes so a generation carries on instead of
+/// starting over.
+struct ReplicationJob {
+    db_path: String,
+    interval: Duration,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    clock: crate::clock::SharedClock,
+    replicator: tokio::sync::Mutex<Option<Replicator>>,
 }
 
-#[derive(Serialize)]
-struct ProcessTextResponse {
-    text: String,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for ReplicationJob {
+    fn name(&self) -> &str {
+        "replication"
+    }
 
-#[derive(Deserialize)]
-struct MemorySearchRequest {
-    user_id: String,
-    query: String,
-    limit: Option<usize>,
-}
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-#[derive(Deserialize)]
-struct ChatHistoryQuery {
-    user_id: String,
-    limit: Option<usize>,
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let config = ReplicationConfig::from_tools(tools.as_ref());
+        let mut replicator = self.replicator.lock().await;
+        if !config.enabled {
+            // Its read transaction would otherwise keep the log growing.
+            *replicator = None;
+            return Ok(());
+        }
+        let now = self.clock.now();
+        let result = match ReplicaTarget::from_tools(&config, tools.as_ref()) {
+            Ok(target) => {
+                replication::run_pass(&mut replicator, &self.db_path, &config, &target, now).await
+            }
+            Err(err) => Err(err),
+        };
+        // Routine passes stay quiet; new generations and failures are
+        // worth a `replication` event.
+        let (status, payload) = match result {
+            Ok(run) if !run.new_generation => return Ok(()),
+            Ok(run) => ("success", json!(run)),
+            Err(err) => {
+                tracing::warn!(error = %err, "Replication pass failed");
+                ("error", json!({"error": err.to_string()}))
+            }
+        };
+        let _ = self.ui_event_tx.send(UiEvent {
+            event_type: "replication".to_string(),
+            user_id: "system".to_string(),
+            tool: "replication".to_string(),
+            status: status.to_string(),
+            payload,
+            timestamp: now,
+        });
+        Ok(())
+    }
 }
 
-#[derive(Deserialize)]
-struct ClearHistoryRequest {
-    user_id: String,
+struct CalendarSyncJob {
+    db_path: String,
+    interval: Duration,
+    ui_event_tx: broadcast::Sender<UiEvent>,
 }
 
-#[derive(Deserialize)]
-struct ClearUserDataRequest {
-    user_id: String,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for CalendarSyncJob {
+    fn name(&self) -> &str {
+        "calendar_sync"
+    }
 
-#[derive(Deserialize)]
-struct PreloadBootRequest {
-    user_id: String,
-}
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-#[derive(Serialize)]
-struct PreloadBootResponse {
-    context_status: String,
-    heartbeat_status: String,
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let config = CalendarConfig::from_tools(tools.as_ref());
+        if config.sources.is_empty() {
+            return Ok(());
+        }
+        let store = CalendarStore::new(&self.db_path).await?;
+        for report in crate::calendar::sync(&store, &config, None).await {
+            if let Some(error) = &report.error {
+                tracing::warn!(source = %report.source, error = %error, "Calendar sync failed");
+            }
+            let _ = self.ui_event_tx.send(calendar_sync_event(&report));
+        }
+        Ok(())
+    }
 }
 
-#[derive(Deserialize)]
-struct ReminderStreamQuery {
-    user_id: String,
+fn calendar_sync_event(report: &SyncReport) -> UiEvent {
+    UiEvent {
+        event_type: "calendar".to_string(),
+        user_id: report.user_id.clone(),
+        tool: report.source.clone(),
+        status: if report.error.is_some() {
+            "error"
+        } else {
+            "synced"
+        }
+        .to_string(),
+        payload: json!(report),
+        timestamp: now_ts(),
+    }
 }
 
-#[derive(Deserialize)]
-struct UiEventStreamQuery {
-    user_id: Option<String>,
+struct EmailIngestJob {
+    db_path: String,
+    store: Arc<TodoStore>,
+    interval: Duration,
+    ui_event_tx: broadcast::Sender<UiEvent>,
 }
 
-#[derive(Deserialize)]
-struct ReminderDeliveryEventsQuery {
-    user_id: Option<String>,
-    limit: Option<usize>,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for EmailIngestJob {
+    fn name(&self) -> &str {
+        "email_ingest"
+    }
 
-#[derive(Deserialize)]
-struct AuditEventsQuery {
-    user_id: Option<String>,
-    limit: Option<usize>,
-}
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-#[derive(Deserialize)]
-struct InboxQuery {
-    user_id: String,
-    limit: Option<usize>,
-    include_done: Option<bool>,
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let config = EmailConfig::from_tools(tools.as_ref());
+        if config.accounts.is_empty() {
+            return Ok(());
+        }
+        let now = self.store.clock().now();
+        for report in crate::email::poll(&self.store, &config, None, now).await {
+            if let Some(error) = &report.error {
+                tracing::warn!(account = %report.account, error = %error, "Email poll failed");
+            }
+            // Quiet polls stay quiet; only new todos and failures are news.
+            if report.error.is_some() || !report.todo_ids.is_empty() {
+                let _ = self.ui_event_tx.send(email_ingest_event(&report));
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Deserialize)]
-struct InboxTransitionRequest {
-    user_id: String,
-    origin_ref: String,
-    action: String,
+fn email_ingest_event(report: &IngestReport) -> UiEvent {
+    UiEvent {
+        event_type: "email".to_string(),
+        user_id: report.user_id.clone(),
+        tool: report.account.clone(),
+        status: if report.error.is_some() {
+            "error"
+        } else {
+            "ingested"
+        }
+        .to_string(),
+        payload: json!(report),
+        timestamp: now_ts(),
+    }
 }
 
-#[derive(Serialize)]
-struct InboxTransitionResponse {
-    status: String,
-    origin_ref: String,
-    previous_status: String,
-    next_status: String,
+/// Answers what listed senders write in their Matrix rooms. Replies reach
+/// the room through the chat mirror.
+struct MatrixSyncJob {
+    agent: Arc<RwLock<Arc<ButterflyBot>>>,
+    db_path: String,
+    interval: Duration,
+    since: tokio::sync::Mutex<Option<String>>,
+    encrypted: std::sync::Mutex<HashMap<String, bool>>,
+    ui_event_tx: broadcast::Sender<UiEvent>,
 }
 
-#[derive(Serialize, Clone)]
-struct InboxItemResponse {
-    id: String,
-    source_type: String,
-    source_id: i32,
-    title: String,
-    details: Option<String>,
-    owner: String,
-    status: String,
-    priority: String,
-    due_at: Option<i64>,
-    created_at: i64,
-    updated_at: i64,
-    requires_human_action: bool,
-    origin_ref: String,
-    dependency_refs: Vec<String>,
-    t_shirt_size: Option<String>,
-    story_points: Option<i32>,
-    estimate_optimistic_minutes: Option<i32>,
-    estimate_likely_minutes: Option<i32>,
-    estimate_pessimistic_minutes: Option<i32>,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for MatrixSyncJob {
+    fn name(&self) -> &str {
+        "matrix_sync"
+    }
 
-#[derive(Serialize)]
-struct InboxResponse {
-    items: Vec<InboxItemResponse>,
-}
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-#[derive(Serialize)]
-struct InboxActionableCountResponse {
-    actionable_count: usize,
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let Some(config) = MatrixConfig::from_tools(tools.as_ref()) else {
+            return Ok(());
+        };
+        let client = config.client()?;
+        let rooms: Vec<String> = config
+            .rooms
+            .iter()
+            .map(|room| room.room_id.clone())
+            .collect();
+        // Held for the whole run so overlapping runs can't answer twice.
+        let mut since = self.since.lock().await;
+        let batch = client.sync(since.as_deref(), &rooms).await?;
+        *since = Some(batch.next_batch);
+
+        for message in batch.messages {
+            let Some(user_id) = config.user_for(&message.room_id, &message.sender) else {
+                continue;
+            };
+            let status = match crate::matrix::room_allowed(
+                &config,
+                &self.encrypted,
+                &client,
+                &message.room_id,
+            )
+            .await
+            {
+                Ok(true) => match self.answer(user_id, message.body.clone()).await {
+                    Ok(()) => "received".to_string(),
+                    Err(err) => {
+                        tracing::warn!(user_id, error = %err, "Matrix message failed");
+                        "error".to_string()
+                    }
+                },
+                Ok(false) => "blocked".to_string(),
+                Err(err) => {
+                    tracing::warn!(room_id = %message.room_id, error = %err, "Matrix room check failed");
+                    "error".to_string()
+                }
+            };
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "matrix".to_string(),
+                user_id: user_id.to_string(),
+                tool: "matrix".to_string(),
+                status,
+                payload: json!({
+                    "room_id": message.room_id,
+                    "sender": message.sender,
+                    "event_id": message.event_id,
+                    "actor": message.sender,
+                }),
+                timestamp: now_ts(),
+            });
+        }
+        Ok(())
+    }
 }
 
-#[derive(Serialize)]
-struct ReminderDeliveryEventsResponse {
-    events: Vec<Value>,
+impl MatrixSyncJob {
+    async fn answer(&self, user_id: &str, text: String) -> Result<()> {
+        let _permit = crate::prompt_queue::shared()
+            .acquire("matrix", PromptPriority::Interactive)
+            .await?;
+        let options = ProcessOptions {
+            prompt: None,
+            images: Vec::new(),
+            output_format: OutputFormat::Text,
+            image_detail: "auto".to_string(),
+            json_schema: None,
+        };
+        let agent = self.agent.read().await.clone();
+        crate::matrix::from_matrix(agent.process(user_id, UserInput::Text(text), options))
+            .await
+            .map(|_| ())
+    }
 }
 
-#[derive(Serialize)]
-struct AuditEventsResponse {
-    events: Vec<Value>,
+/// Announces newly pending chain steps and times out overdue ones.
+struct ApprovalChainJob {
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    clock: crate::clock::SharedClock,
 }
 
-#[derive(Serialize)]
-struct MemorySearchResponse {
-    results: Vec<String>,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for ApprovalChainJob {
+    fn name(&self) -> &str {
+        "approval_chains"
+    }
 
-#[derive(Serialize)]
-struct ChatHistoryResponse {
-    history: Vec<String>,
-}
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
 
-#[derive(Serialize)]
-struct ClearHistoryResponse {
-    status: String,
-    message: String,
+    async fn run(&self) -> Result<()> {
+        let store = ApprovalStore::new(&self.db_path).await?;
+        for (approval, lapsed, outcome) in store.expire_due(self.clock.now()).await? {
+            let _ = self.ui_event_tx.send(approval_step_event(
+                &approval,
+                &lapsed,
+                StepStatus::TimedOut,
+                None,
+            ));
+            if outcome == StepOutcome::Rejected {
+                reject_timed_out_approval(&store, &self.ui_event_tx, &approval, &lapsed).await?;
+            }
+        }
+        announce_approval_steps(&store, &self.ui_event_tx).await
+    }
 }
 
-#[derive(Serialize)]
-struct ClearUserDataResponse {
-    status: String,
-    message: String,
-    cleared: Value,
+/// Closes an approval whose chain ran out of time.
+async fn reject_timed_out_approval(
+    store: &ApprovalStore,
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+    approval: &PendingApproval,
+    lapsed: &ApprovalStep,
+) -> Result<()> {
+    if !store
+        .decide(&approval.user_id, approval.id, ApprovalStatus::Rejected)
+        .await?
+    {
+        return Ok(());
+    }
+    let error = format!("Timed out at step {}", lapsed.position + 1);
+    store
+        .record_outcome(
+            approval.id,
+            ApprovalStatus::Rejected,
+            &json!({"error": error}),
+        )
+        .await?;
+    let _ = ui_event_tx.send(UiEvent {
+        event_type: "approval".to_string(),
+        user_id: approval.user_id.clone(),
+        tool: approval.tool.clone(),
+        status: ApprovalStatus::Rejected.as_str().to_string(),
+        payload: json!({
+            "origin_ref": approval.origin_ref(),
+            "capability": approval.capability,
+            "actor": "system",
+            "error": error,
+        }),
+        timestamp: now_ts(),
+    });
+    Ok(())
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
-    error: String,
+/// Tells each approver of a newly pending step that it waits on them. The
+/// `awaiting` event also goes out as an `approval.requested` webhook, so
+/// another household member's instance or phone can pick it up.
+async fn announce_approval_steps(
+    store: &ApprovalStore,
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+) -> Result<()> {
+    for (approval, step) in store.take_unannounced().await? {
+        for approver in &step.approvers {
+            let _ = ui_event_tx.send(UiEvent {
+                event_type: "approval".to_string(),
+                user_id: approver.clone(),
+                tool: approval.tool.clone(),
+                status: "awaiting".to_string(),
+                payload: json!({
+                    "origin_ref": approval.origin_ref(),
+                    "approval_id": approval.id,
+                    "capability": approval.capability,
+                    "requested_by": approval.user_id,
+                    "actor": "system",
+                    "step": step.position + 1,
+                    "approvers": step.approvers,
+                    "due_at": step.due_at,
+                }),
+                timestamp: now_ts(),
+            });
+        }
+    }
+    Ok(())
 }
 
-#[derive(Deserialize)]
-struct SignerIdRequest {
-    request_id: String,
+/// Audit trail for one decided (or lapsed) chain step, on the requester's
+/// timeline.
+fn approval_step_event(
+    approval: &PendingApproval,
+    step: &ApprovalStep,
+    status: StepStatus,
+    actor: Option<&str>,
+) -> UiEvent {
+    let severity = match status {
+        StepStatus::Rejected => "error",
+        StepStatus::TimedOut => "warning",
+        _ => "info",
+    };
+    UiEvent {
+        event_type: "approval".to_string(),
+        user_id: approval.user_id.clone(),
+        tool: approval.tool.clone(),
+        status: format!("step_{}", status.as_str()),
+        payload: json!({
+            "origin_ref": approval.origin_ref(),
+            "capability": approval.capability,
+            "actor": actor.unwrap_or("system"),
+            "step": step.position + 1,
+            "approvers": step.approvers,
+            "severity": severity,
+        }),
+        timestamp: now_ts(),
+    }
 }
 
-#[derive(Deserialize)]
-struct X402PreviewRequest {
-    request_id: String,
-    actor: String,
-    user_id: String,
-    payment_required: Value,
-    merchant_origin: Option<String>,
-    context_requires_approval: Option<bool>,
-    idempotency_key: Option<String>,
+/// Queues the public webhook event, if any, behind a daemon UI event. A
+/// plan step reaching done also completes its plan once every step is done.
+async fn enqueue_outbox_event(db_path: &str, store: &OutboxStore, event: &UiEvent) -> Result<()> {
+    let mut events = Vec::new();
+    if let Some(public) = crate::outbox::public_event(event) {
+        events.push(public);
+    }
+    if event.event_type == "inbox_transition" && event.status == "done" {
+        if let Some(origin_ref) = event.payload.get("origin_ref").and_then(Value::as_str) {
+            if let Some(data) = completed_plan_for_step(db_path, &event.user_id, origin_ref).await {
+                events.push((crate::outbox::EVENT_PLAN_COMPLETED, data));
+            }
+        }
+    }
+    if events.is_empty() {
+        return Ok(());
+    }
+    let tools = Config::load(db_path).ok().and_then(|config| config.tools);
+    let config = OutboxConfig::from_tools(tools.as_ref());
+    if config.is_empty() {
+        return Ok(());
+    }
+    for (event_type, data) in events {
+        store
+            .enqueue(&config, &event.user_id, event_type, &data)
+            .await?;
+    }
+    Ok(())
 }
 
-#[derive(Serialize)]
-struct X402PreviewResponse {
-    canonical_intent: crate::security::x402::CanonicalX402Intent,
-    signer: crate::security::signer_daemon::SignerResponse,
+async fn completed_plan_for_step(db_path: &str, user_id: &str, origin_ref: &str) -> Option<Value> {
+    let refs::OriginRef::PlanStep { plan_id, .. } = refs::OriginRef::parse(origin_ref)? else {
+        return None;
+    };
+    let items = build_inbox_items(db_path, user_id, 2000, true).await.ok()?;
+    let steps: Vec<&InboxItemResponse> = items
+        .iter()
+        .filter(|item| item.source_type == "plan_step" && item.source_id == plan_id)
+        .collect();
+    if steps.is_empty() || steps.iter().any(|step| step.status != "done") {
+        return None;
+    }
+    Some(json!({
+        "plan_id": plan_id,
+        "origin_ref": format!("plan:{plan_id}"),
+        "steps": steps.len(),
+    }))
 }
 
-#[derive(Deserialize)]
-struct SolanaWalletQuery {
-    user_id: String,
-    actor: Option<String>,
+struct DailyDigestJob {
+    agent: Arc<RwLock<Arc<ButterflyBot>>>,
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    clock: crate::clock::SharedClock,
+    last_sent: std::sync::Mutex<HashMap<String, chrono::NaiveDate>>,
 }
 
-#[derive(Serialize)]
-struct SolanaWalletResponse {
-    user_id: String,
-    actor: String,
-    address: String,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for DailyDigestJob {
+    fn name(&self) -> &str {
+        "daily_digest"
+    }
 
-#[derive(Deserialize)]
-struct SolanaBalanceQuery {
-    address: Option<String>,
-    user_id: Option<String>,
-    actor: Option<String>,
-}
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
 
-fn asks_for_wallet_address_only(text: &str) -> bool {
-    let normalized = text.to_ascii_lowercase();
-    let asks_for_address = normalized.contains("wallet address")
-        || normalized.contains("my address")
-        || (normalized.contains("wallet") && normalized.contains("address"));
+    async fn run(&self) -> Result<()> {
+        // Read fresh each minute so schedule edits apply without a restart.
+        let tools = Config::load(&self.db_path).ok().and_then(|cfg| cfg.tools);
+        let config = DigestConfig::from_tools(tools.as_ref());
+        let now = self.clock.now();
+        for (user_id, schedule) in &config.users {
+            let Some((today, minute)) = crate::timezones::local_day_minute(user_id, now) else {
+                continue;
+            };
+            let last_sent = self
+                .last_sent
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .get(user_id)
+                .copied();
+            if !schedule.is_due(today, minute, last_sent) {
+                continue;
+            }
+            // Marked before sending so a failing channel can't repeat the
+            // digest every minute.
+            self.last_sent
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .insert(user_id.clone(), today);
+
+            let digest = compose_user_digest(&self.db_path, user_id, schedule, now).await?;
+            let agent = self.agent.read().await.clone();
+            let delivered = deliver_digest(&agent, user_id, schedule, &digest).await;
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "digest".to_string(),
+                user_id: user_id.clone(),
+                tool: "digest".to_string(),
+                status: if delivered.is_empty() {
+                    "failed".to_string()
+                } else {
+                    "sent".to_string()
+                },
+                payload: json!({
+                    "channels": delivered,
+                    "counts": digest.counts.iter().cloned().collect::<HashMap<_, _>>(),
+                }),
+                timestamp: now,
+            });
+        }
+        Ok(())
+    }
+}
 
-    asks_for_address
-        && !normalized.contains("balance")
-        && !normalized.contains("transfer")
-        && !normalized.contains("send")
-        && !normalized.contains("transaction")
-        && !normalized.contains("tx")
-        && !normalized.contains("history")
+struct InboxSweepJob {
+    agent: Arc<RwLock<Arc<ButterflyBot>>>,
+    db_path: String,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    clock: crate::clock::SharedClock,
+    last_run: std::sync::Mutex<HashMap<String, chrono::NaiveDate>>,
 }
 
-fn asks_for_wallet_balance_only(text: &str) -> bool {
-    let normalized = text.to_ascii_lowercase();
-    let asks_for_balance = normalized.contains("balance") || normalized.contains("lamports");
-    let in_solana_context = normalized.contains("solana") || normalized.contains("wallet");
+#[async_trait::async_trait]
+impl ScheduledJob for InboxSweepJob {
+    fn name(&self) -> &str {
+        "inbox_sweep"
+    }
 
-    asks_for_balance
-        && in_solana_context
-        && !normalized.contains("transfer")
-        && !normalized.contains("send")
-        && !normalized.contains("transaction")
-        && !normalized.contains("tx")
-        && !normalized.contains("history")
-}
+    fn interval(&self) -> Duration {
+        Duration::from_secs(60)
+    }
 
-async fn solana_balance_line_for_user(state: &AppState, user_id: &str) -> Result<String> {
-    let policy = load_solana_rpc_policy(state)?;
-    let endpoint = require_solana_rpc_endpoint(&policy)?;
-    let address = crate::security::solana_signer::wallet_address(user_id, "agent")?;
-    let lamports = crate::solana_rpc::get_balance(&endpoint, &address, &policy.commitment).await?;
-    let sol = lamports as f64 / 1_000_000_000f64;
+    async fn run(&self) -> Result<()> {
+        let tools = Config::load(&self.db_path).ok().and_then(|cfg| cfg.tools);
+        let config = SweepConfig::from_tools(tools.as_ref());
+        let now = self.clock.now();
+        for (user_id, schedule) in &config.users {
+            let Some((today, minute)) = crate::timezones::local_day_minute(user_id, now) else {
+                continue;
+            };
+            let last_run = self
+                .last_run
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .get(user_id)
+                .copied();
+            if !schedule.is_due(today, minute, last_run) {
+                continue;
+            }
+            self.last_run
+                .lock()
+                .unwrap_or_else(|poison| poison.into_inner())
+                .insert(user_id.clone(), today);
 
-    Ok(format!(
-        "Your Solana balance is {:.9} SOL ({} lamports).",
-        sol, lamports
-    ))
+            let agent = self.agent.read().await.clone();
+            if let Err(err) =
+                propose_inbox_sweep(&agent, &self.db_path, &self.ui_event_tx, user_id, now).await
+            {
+                tracing::warn!(user_id, error = %err, "Inbox sweep failed");
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Serialize)]
-struct SolanaBalanceResponse {
-    address: String,
-    lamports: u64,
-    sol: f64,
-}
+/// Parks one approval holding a proposed disposition for every open inbox
+/// item. Returns the sweep still awaiting a decision instead when there is
+/// one, and `None` when the inbox is already empty.
+async fn propose_inbox_sweep(
+    agent: &ButterflyBot,
+    db_path: &str,
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+    user_id: &str,
+    now: i64,
+) -> Result<Option<PendingApproval>> {
+    let approvals = ApprovalStore::new(db_path).await?;
+    if let Some(pending) = approvals
+        .list_pending(user_id, 500)
+        .await?
+        .into_iter()
+        .find(|approval| approval.capability == inbox_sweep::SWEEP_CAPABILITY)
+    {
+        return Ok(Some(pending));
+    }
 
-#[derive(Deserialize)]
-struct SolanaTransferRequest {
-    request_id: String,
-    user_id: String,
-    actor: Option<String>,
-    to: String,
-    lamports: u64,
-    payee: Option<String>,
-    simulate_only: Option<bool>,
-}
+    let items = build_inbox_items(db_path, user_id, 500, false)
+        .await?
+        .into_iter()
+        .filter(|item| {
+            !matches!(item.source_type.as_str(), "approval" | "consent")
+                && !matches!(item.status.as_str(), "done" | "dismissed")
+        })
+        .map(|item| SweepItem {
+            origin_ref: item.origin_ref,
+            title: item.title,
+            source_type: item.source_type,
+            status: item.status,
+            priority: item.priority,
+            due_at: item.due_at,
+            updated_at: item.updated_at,
+        })
+        .collect::<Vec<_>>();
+    if items.is_empty() {
+        return Ok(None);
+    }
 
-#[derive(Serialize)]
-struct SolanaTransferResponse {
-    status: String,
-    request_id: String,
-    wallet_address: String,
-    signer_reason_code: String,
-    simulation: Option<Value>,
-    signature: Option<String>,
+    let answer = match agent
+        .structured_answer(
+            user_id,
+            &inbox_sweep::agent_prompt(&items, now),
+            inbox_sweep::proposal_schema(),
+        )
+        .await
+    {
+        Ok(answer) => Some(answer),
+        Err(err) => {
+            tracing::warn!(user_id, error = %err, "Inbox sweep falling back to rule proposals");
+            None
+        }
+    };
+    let trends = match build_aggregate_insights(db_path, user_id, 7, now).await {
+        Ok(insights) => insights.summary(),
+        Err(err) => {
+            tracing::warn!(user_id, error = %err, "Weekly trends unavailable");
+            None
+        }
+    };
+    let batch = SweepBatch {
+        proposals: inbox_sweep::propose(&items, answer.as_ref(), now),
+        trends,
+    };
+    let approval = approvals
+        .park(
+            user_id,
+            inbox_sweep::SWEEP_TOOL,
+            inbox_sweep::SWEEP_CAPABILITY,
+            &batch.to_args(),
+        )
+        .await?;
+    let _ = ui_event_tx.send(UiEvent {
+        event_type: "inbox_sweep".to_string(),
+        user_id: user_id.to_string(),
+        tool: inbox_sweep::SWEEP_TOOL.to_string(),
+        status: "proposed".to_string(),
+        payload: json!({
+            "origin_ref": approval.origin_ref(),
+            "summary": batch.summary(),
+            "proposals": batch.proposals.len(),
+            "trends": batch.trends,
+        }),
+        timestamp: now,
+    });
+    Ok(Some(approval))
 }
 
-#[derive(Deserialize)]
-struct SolanaTxStatusQuery {
-    signature: String,
-}
+/// Applies the accepted proposals of an approved sweep through the regular
+/// inbox transitions and logs what happened to each.
+async fn apply_inbox_sweep(
+    state: &AppState,
+    user_id: &str,
+    approval: &PendingApproval,
+    accepted: Option<&[String]>,
+) -> Value {
+    let batch = SweepBatch::from_args(&approval.args).unwrap_or_default();
+    let items = match build_inbox_items(&state.db_path, user_id, 1000, true).await {
+        Ok(items) => items,
+        Err(err) => return json!({"status": "error", "error": err.to_string()}),
+    };
 
-#[derive(Serialize)]
-struct SolanaTxStatusResponse {
-    signature: String,
-    value: Value,
-}
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    for proposal in &batch.proposals {
+        if accepted.is_some_and(|accepted| !accepted.contains(&proposal.origin_ref)) {
+            skipped.push(json!({"origin_ref": proposal.origin_ref, "reason": "not accepted"}));
+            continue;
+        }
+        let Some(item) = items
+            .iter()
+            .find(|item| item.origin_ref == proposal.origin_ref)
+        else {
+            skipped.push(
+                json!({"origin_ref": proposal.origin_ref, "reason": "no longer in the inbox"}),
+            );
+            continue;
+        };
+        // Anything already past `new` has been looked at; keeping it is
+        // all "schedule" asks for.
+        if proposal.disposition == inbox_sweep::Disposition::Schedule && item.status != "new" {
+            applied.push(json!({
+                "origin_ref": proposal.origin_ref,
+                "disposition": proposal.disposition.as_str(),
+                "next_status": item.status,
+            }));
+            continue;
+        }
+        match apply_inbox_transition(
+            state,
+            user_id,
+            item,
+            proposal.disposition.action(),
+            proposal.disposition.as_str(),
+            "human",
+            "inbox_sweep",
+        )
+        .await
+        {
+            Ok(transition) => applied.push(json!({
+                "origin_ref": proposal.origin_ref,
+                "disposition": proposal.disposition.as_str(),
+                "next_status": transition.next_status,
+            })),
+            Err((_, Json(err))) => {
+                skipped.push(json!({"origin_ref": proposal.origin_ref, "reason": err.error}))
+            }
+        }
+    }
 
-#[derive(Deserialize)]
-struct SolanaTxHistoryQuery {
-    address: Option<String>,
-    user_id: Option<String>,
-    actor: Option<String>,
-    limit: Option<usize>,
+    let outcome = json!({
+        "summary": batch.summary(),
+        "applied": applied,
+        "skipped": skipped,
+    });
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "inbox_sweep".to_string(),
+        user_id: user_id.to_string(),
+        tool: inbox_sweep::SWEEP_TOOL.to_string(),
+        status: "applied".to_string(),
+        payload: json!({
+            "origin_ref": approval.origin_ref(),
+            "applied": applied.len(),
+            "skipped": skipped.len(),
+        }),
+        timestamp: now_ts(),
+    });
+    outcome
 }
 
-#[derive(Serialize)]
-struct SolanaTxHistoryResponse {
-    address: String,
-    entries: Value,
+async fn compose_user_digest(
+    db_path: &str,
+    user_id: &str,
+    schedule: &DigestSchedule,
+    now: i64,
+) -> Result<Digest> {
+    let items = build_inbox_items(db_path, user_id, 500, false)
+        .await?
+        .into_iter()
+        .filter(|item| item.status != "done")
+        .map(|item| DigestItem {
+            is_reminder: item.source_type == "reminder",
+            blocked: item.status == "blocked",
+            title: item.title,
+            due_at: item.due_at,
+        })
+        .collect::<Vec<_>>();
+    let bounds = crate::timezones::smart_list_bounds(user_id, now);
+    Ok(digest::compose(schedule, &items, &bounds))
 }
 
-#[derive(Serialize, Clone)]
-struct DoctorCheck {
-    name: String,
-    status: String,
-    message: String,
-    fix_hint: Option<String>,
+/// Sends the digest over each configured channel and returns the ones that
+/// took it.
+async fn deliver_digest(
+    agent: &ButterflyBot,
+    user_id: &str,
+    schedule: &DigestSchedule,
+    digest: &Digest,
+) -> Vec<&'static str> {
+    let mut delivered = Vec::new();
+    for channel in &schedule.channels {
+        let ok = match channel {
+            DigestChannel::Chat => {
+                match agent.post_assistant_message(user_id, &digest.text).await {
+                    Ok(()) => true,
+                    Err(err) => {
+                        tracing::warn!(user_id, error = %err, "Digest chat delivery failed");
+                        false
+                    }
+                }
+            }
+            DigestChannel::Notification => {
+                let body = if privacy_lock::is_locked(user_id) {
+                    "Your daily agenda is ready".to_string()
+                } else {
+                    digest.text.replace("**", "")
+                };
+                send_desktop_notification("Butterfly Bot daily agenda", &body)
+            }
+            DigestChannel::Webhook => match schedule.webhook_url.as_deref() {
+                Some(url) => post_digest_webhook(url, user_id, digest).await,
+                None => {
+                    tracing::warn!(user_id, "Digest webhook channel has no webhook_url");
+                    false
+                }
+            },
+        };
+        if ok {
+            delivered.push(channel.key());
+        }
+    }
+    delivered
 }
 
-#[derive(Serialize)]
-struct DoctorResponse {
-    overall: String,
-    checks: Vec<DoctorCheck>,
+async fn post_digest_webhook(url: &str, user_id: &str, digest: &Digest) -> bool {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::warn!(error = %err, "Digest webhook client failed to build");
+            return false;
+        }
+    };
+    let payload = json!({
+        "type": "daily_digest",
+        "user_id": user_id,
+        "text": digest.text,
+        "counts": digest.counts.iter().cloned().collect::<HashMap<_, _>>(),
+    });
+    match client.post(url).json(&payload).send().await {
+        Ok(response) if response.status().is_success() => true,
+        Ok(response) => {
+            tracing::warn!(user_id, status = %response.status(), "Digest webhook rejected");
+            false
+        }
+        Err(err) => {
+            tracing::warn!(user_id, error = %err, "Digest webhook failed");
+            false
+        }
+    }
 }
 
-#[derive(Serialize, Clone)]
-struct SecurityAuditFinding {
-    id: String,
-    severity: String,
-    status: String,
-    message: String,
-    fix_hint: Option<String>,
-    auto_fixable: bool,
-}
+#[async_trait::async_trait]
+impl ScheduledJob for ScheduledTasksJob {
+    fn name(&self) -> &str {
+        "scheduled_tasks"
+    }
 
-#[derive(Serialize)]
-struct SecurityAuditResponse {
-    overall: String,
-    findings: Vec<SecurityAuditFinding>,
-}
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-#[derive(Serialize)]
-struct FactoryResetConfigResponse {
-    status: String,
-    message: String,
-    config: Value,
-}
+    async fn run(&self) -> Result<()> {
+        let now = self.store.clock().now();
+        let policy = self.catch_up.policy_for(self.name());
+        let tasks = self.store.list_due(now, 32).await?;
+        for task in tasks {
+            let agent = self.agent.read().await.clone();
+            let run_at = self.store.clock().now();
+            let next_run_at = if let Some(interval) = task.interval_minutes {
+                let plan = policy.item_plan(
+                    task.next_run_at,
+                    run_at,
+                    interval.max(1) * 60,
+                    self.catch_up.max_runs,
+                );
+                if plan.runs == 0 {
+                    let _ = self.store.reschedule(task.id, plan.next_run_at).await;
+                    announce_missed_run(
+                        &self.ui_event_tx,
+                        "tasks",
+                        &task.user_id,
+                        task.id,
+                        &task.name,
+                        plan.missed,
+                        plan.next_run_at,
+                        run_at,
+                    );
+                    continue;
+                }
+                plan.next_run_at
+            } else {
+                run_at
+            };
 
-pub fn build_router(state: AppState) -> Router {
-    Router::new()
-        .route("/health", get(health))
-        .route("/inbox", get(inbox))
-        .route("/inbox/actionable_count", get(inbox_actionable_count))
-        .route("/inbox/transition", post(inbox_transition))
-        .route("/audit/events", get(audit_events))
-        .route("/reminders/delivery_events", get(reminder_delivery_events))
-        .route("/doctor", post(doctor))
-        .route("/security_audit", post(security_audit))
-        .route("/process_text", post(process_text))
-        .route("/process_text_stream", post(process_text_stream))
-        .route("/chat_history", get(chat_history))
-        .route("/clear_user_history", post(clear_user_history))
-        .route("/clear_user_data", post(clear_user_data))
-        .route("/memory_search", post(memory_search))
-        .route("/preload_boot", post(preload_boot))
-        .route("/reminder_stream", get(reminder_stream))
-        .route("/ui_events", get(ui_events))
-        .route("/factory_reset_config", post(factory_reset_config))
-        .route("/reload_config", post(reload_config))
-        .route("/signer/preview", post(signer_preview))
-        .route("/signer/approve", post(signer_approve))
-        .route("/signer/sign", post(signer_sign))
-        .route("/signer/deny", post(signer_deny))
-        .route("/x402/preview", post(x402_preview))
-        .route("/solana/wallet", get(solana_wallet))
-        .route("/solana/balance", get(solana_balance))
-        .route("/solana/transfer", post(solana_transfer))
-        .route("/solana/simulate_transfer", post(solana_simulate_transfer))
-        .route("/solana/tx/status", get(solana_tx_status))
-        .route("/solana/tx/history", get(solana_tx_history))
-        .with_state(state)
-}
+            if task.interval_minutes.is_some() {
+                let _ = self.store.mark_run(task.id, run_at, next_run_at).await;
+            } else {
+                let _ = self.store.complete_one_shot(task.id).await;
+            }
 
-async fn inbox(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<InboxQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+            let options = ProcessOptions {
+                prompt: None,
+                images: Vec::new(),
+                output_format: OutputFormat::Text,
+                image_detail: "auto".to_string(),
+                json_schema: None,
+            };
+            let input = format!("Scheduled task '{}': {}", task.name, task.prompt);
+            let result = match crate::prompt_queue::shared()
+                .acquire("tasks", PromptPriority::Scheduled)
+                .await
+            {
+                Ok(_permit) => {
+                    agent
+                        .process(&task.user_id, UserInput::Text(input), options)
+                        .await
+                }
+                Err(err) => Err(err),
+            };
 
-    let limit = query.limit.unwrap_or(200).clamp(1, 500);
-    let include_done = query.include_done.unwrap_or(true);
-    match build_inbox_items(&state.db_path, &query.user_id, limit, include_done).await {
-        Ok(items) => (StatusCode::OK, Json(InboxResponse { items })).into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
+            let (status, payload): (String, serde_json::Value) = match result {
+                Ok(ProcessResult::Text(text)) => (
+                    "ok".to_string(),
+                    json!({"task_id": task.id, "name": task.name, "output": text}),
+                ),
+                Ok(other) => (
+                    "ok".to_string(),
+                    json!({"task_id": task.id, "name": task.name, "output": format!("{other:?}")}),
+                ),
+                Err(err) => (
+                    "error".to_string(),
+                    json!({"task_id": task.id, "name": task.name, "error": err.to_string()}),
+                ),
+            };
+
+            let event = UiEvent {
+                event_type: "tasks".to_string(),
+                user_id: task.user_id.clone(),
+                tool: "tasks".to_string(),
+                status: status.clone(),
+                payload: payload.clone(),
+                timestamp: run_at,
+            };
+            let _ = self.ui_event_tx.send(event);
+            let _ = write_tasks_audit_log(
+                self.audit_log_path.as_deref(),
+                run_at,
+                &task,
+                status.as_str(),
+                payload,
+            );
+        }
+        Ok(())
     }
 }
 
-async fn inbox_actionable_count(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<InboxQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
+#[async_trait::async_trait]
+impl ScheduledJob for ReminderDispatchJob {
+    fn name(&self) -> &str {
+        "reminder_dispatch"
     }
 
-    match build_inbox_items(&state.db_path, &query.user_id, 500, false).await {
-        Ok(items) => {
-            let actionable_count = items
-                .iter()
-                .filter(|item| {
-                    item.owner == "human"
-                        && item.requires_human_action
-                        && matches!(
-                            item.status.as_str(),
-                            "new" | "acknowledged" | "in_progress" | "blocked"
-                        )
-                })
-                .count();
-            (
-                StatusCode::OK,
-                Json(InboxActionableCountResponse { actionable_count }),
-            )
-                .into_response()
-        }
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
+    fn interval(&self) -> Duration {
+        self.interval
     }
-}
 
-fn parse_inbox_status_state(value: &str) -> Option<InboxState> {
-    match value.trim().to_ascii_lowercase().as_str() {
-        "new" => Some(InboxState::New),
-        "ack" | "acknowledged" => Some(InboxState::Acknowledged),
-        "in_progress" | "in progress" | "started" => Some(InboxState::InProgress),
-        "blocked" => Some(InboxState::Blocked),
-        "done" | "completed" | "complete" => Some(InboxState::Done),
-        "dismissed" | "archived" => Some(InboxState::Dismissed),
-        _ => None,
+    async fn run(&self) -> Result<()> {
+        let now = self.store.clock().now();
+        let due = self.store.peek_due_reminders_all(now, 32).await?;
+        for reminder in due {
+            let title = reminder.item.title.clone();
+            let base_payload = json!({
+                "id": reminder.item.id,
+                "title": title,
+                "due_at": reminder.item.due_at,
+            });
+
+            if let Some(window) = self
+                .delivery_windows
+                .resolve(reminder.item.delivery_window.as_deref())
+            {
+                if !window.contains(now) {
+                    let held_until = window.next_open_at(now);
+                    let _ = self
+                        .store
+                        .hold_reminder(&reminder.user_id, reminder.item.id, held_until)
+                        .await;
+                    let mut held_payload = base_payload.clone();
+                    held_payload["held_until"] = json!(held_until);
+                    held_payload["delivery_window"] = json!(reminder.item.delivery_window);
+                    let _ = self.ui_event_tx.send(UiEvent {
+                        event_type: "reminder_delivery".to_string(),
+                        user_id: reminder.user_id.clone(),
+                        tool: "reminders".to_string(),
+                        status: "held".to_string(),
+                        payload: held_payload.clone(),
+                        timestamp: now,
+                    });
+                    let _ = write_reminder_audit_log(
+                        self.audit_log_path.as_deref(),
+                        now,
+                        &reminder.user_id,
+                        reminder.item.id,
+                        "held",
+                        held_payload,
+                    );
+                    continue;
+                }
+            }
+
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "reminder_delivery".to_string(),
+                user_id: reminder.user_id.clone(),
+                tool: "reminders".to_string(),
+                status: "queued".to_string(),
+                payload: base_payload.clone(),
+                timestamp: now,
+            });
+            let _ = write_reminder_audit_log(
+                self.audit_log_path.as_deref(),
+                now,
+                &reminder.user_id,
+                reminder.item.id,
+                "queued",
+                base_payload.clone(),
+            );
+
+            let _ = self.ui_event_tx.send(UiEvent {
+                event_type: "reminder".to_string(),
+                user_id: reminder.user_id.clone(),
+                tool: "reminders".to_string(),
+                status: "due".to_string(),
+                payload: base_payload.clone(),
+                timestamp: now,
+            });
+
+            // From here the delivery queue owns the reminder: it fires once
+            // and each channel retries on its own.
+            if let Err(err) = self
+                .store
+                .enqueue_deliveries(
+                    &reminder.user_id,
+                    reminder.item.id,
+                    &title,
+                    &self.delivery.channels,
+                )
+                .await
+            {
+                tracing::warn!(
+                    user_id = %reminder.user_id,
+                    error = %err,
+                    "Reminder deliveries failed to queue"
+                );
+                continue;
+            }
+            let escalates = self.escalation.chain_for(&reminder.item.priority).is_some();
+            let _ = if escalates {
+                self.store
+                    .mark_fired_open(&reminder.user_id, reminder.item.id, now)
+                    .await
+            } else {
+                self.store
+                    .mark_fired_reminder(&reminder.user_id, reminder.item.id, now)
+                    .await
+            };
+        }
+        self.deliver_queued(now).await
     }
 }
 
-fn inbox_state_to_str(state: InboxState) -> &'static str {
-    match state {
-        InboxState::New => "new",
-        InboxState::Acknowledged => "acknowledged",
-        InboxState::InProgress => "in_progress",
-        InboxState::Blocked => "blocked",
-        InboxState::Done => "done",
-        InboxState::Dismissed => "dismissed",
+impl ReminderDispatchJob {
+    /// Tries every queued delivery whose attempt is due.
+    async fn deliver_queued(&self, now: i64) -> Result<()> {
+        for delivery in self.store.due_deliveries(64).await? {
+            let mut payload = json!({
+                "id": delivery.reminder_id,
+                "title": delivery.title,
+                "channel": delivery.channel,
+                "attempt": delivery.attempts + 1,
+            });
+            log_reminder_delivery(
+                &self.ui_event_tx,
+                self.audit_log_path.as_deref(),
+                &delivery.user_id,
+                delivery.reminder_id,
+                "delivery_attempted",
+                &payload,
+                now,
+            );
+            let status = match self.send(&delivery).await {
+                Ok(()) => {
+                    self.store.record_delivery_success(delivery.id).await?;
+                    "delivered"
+                }
+                Err(error) => {
+                    payload["error"] = json!(error);
+                    match self
+                        .store
+                        .record_delivery_failure(&delivery, &error, self.delivery.max_attempts)
+                        .await?
+                    {
+                        DeliveryFailure::RetryAt(retry_at) => {
+                            payload["retry_at"] = json!(retry_at);
+                            "delivery_failed"
+                        }
+                        DeliveryFailure::DeadLettered => "dead_lettered",
+                    }
+                }
+            };
+            log_reminder_delivery(
+                &self.ui_event_tx,
+                self.audit_log_path.as_deref(),
+                &delivery.user_id,
+                delivery.reminder_id,
+                status,
+                &payload,
+                now,
+            );
+        }
+        Ok(())
     }
-}
 
-fn parse_inbox_action(value: &str) -> Option<InboxAction> {
-    match value.trim().to_ascii_lowercase().as_str() {
-        "ack" | "acknowledge" => Some(InboxAction::Acknowledge),
-        "start" => Some(InboxAction::Start),
-        "block" | "blocked" => Some(InboxAction::Block),
-        "done" | "complete" => Some(InboxAction::Done),
-        "reopen" | "undo" | "undone" => Some(InboxAction::Reopen),
-        "dismiss" | "dismissed" => Some(InboxAction::Dismiss),
-        "snooze" => Some(InboxAction::Snooze),
-        _ => None,
+    async fn send(&self, delivery: &ReminderDelivery) -> std::result::Result<(), String> {
+        let user_id = delivery.user_id.as_str();
+        let body = privacy_lock::reminder_notification_body(user_id, &[delivery.title.as_str()]);
+        match DeliveryChannel::parse(&delivery.channel) {
+            Some(DeliveryChannel::Notification) => {
+                if send_desktop_notification("Butterfly Bot reminder", &body) {
+                    Ok(())
+                } else {
+                    Err("desktop notification failed".to_string())
+                }
+            }
+            Some(DeliveryChannel::Chat) => {
+                let agent = self.agent.read().await.clone();
+                agent
+                    .post_assistant_message(user_id, &format!("Reminder: {body}"))
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            Some(DeliveryChannel::Push) => {
+                let Some(url) = self.escalation.push_url.as_deref() else {
+                    return Err("no push_url configured".to_string());
+                };
+                let payload = json!({
+                    "type": "reminder",
+                    "user_id": user_id,
+                    "reminder_id": delivery.reminder_id,
+                    "text": body,
+                });
+                if post_reminder_push(url, user_id, &payload).await {
+                    Ok(())
+                } else {
+                    Err("push relay did not accept the reminder".to_string())
+                }
+            }
+            None => Err(format!("unknown channel '{}'", delivery.channel)),
+        }
     }
 }
 
-async fn inbox_transition(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<InboxTransitionRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
+#[async_trait::async_trait]
+impl ScheduledJob for ReminderEscalationJob {
+    fn name(&self) -> &str {
+        "reminder_escalation"
     }
 
-    let Some(action) = parse_inbox_action(&payload.action) else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Unsupported inbox action".to_string(),
-            }),
-        )
-            .into_response();
-    };
+    fn interval(&self) -> Duration {
+        self.interval
+    }
 
-    let items = match build_inbox_items(&state.db_path, &payload.user_id, 1000, true).await {
-        Ok(items) => items,
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
+    async fn run(&self) -> Result<()> {
+        let now = self.store.clock().now();
+        let open = self
+            .store
+            .fired_open_reminders_all(&self.escalation.priorities(), 200)
+            .await?;
+        if open.is_empty() {
+            return Ok(());
         }
-    };
-
-    let Some(item) = items
-        .iter()
-        .find(|item| item.origin_ref == payload.origin_ref)
-        .cloned()
-    else {
-        return (
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Inbox item not found".to_string(),
-            }),
-        )
-            .into_response();
-    };
-
-    let Some(previous_state) = parse_inbox_status_state(&item.status) else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Invalid current inbox status".to_string(),
-            }),
-        )
-            .into_response();
-    };
+        let inbox_states = InboxStateStore::new(&self.db_path).await?;
+        let mut statuses: HashMap<String, HashMap<String, String>> = HashMap::new();
+        for reminder in open {
+            let Some(chain) = self.escalation.chain_for(&reminder.item.priority) else {
+                continue;
+            };
+            let Some((level, step)) = chain.next_step(&reminder.item, now) else {
+                continue;
+            };
+            if !statuses.contains_key(&reminder.user_id) {
+                let loaded = inbox_states
+                    .list_statuses(&reminder.user_id, 5000)
+                    .await
+                    .unwrap_or_default();
+                statuses.insert(reminder.user_id.clone(), loaded);
+            }
+            // Any move out of `new` in the inbox counts as acknowledging it.
+            let origin_ref = format!("reminder:{}", reminder.item.id);
+            if statuses
+                .get(&reminder.user_id)
+                .and_then(|statuses| statuses.get(&origin_ref))
+                .is_some_and(|status| status != "new")
+            {
+                continue;
+            }
 
-    let Some(next_state) = crate::inbox_fsm::transition(previous_state, action) else {
-        return (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: "Invalid inbox transition".to_string(),
-            }),
-        )
-            .into_response();
-    };
+            let payload = json!({
+                "id": reminder.item.id,
+                "title": reminder.item.title,
+                "due_at": reminder.item.due_at,
+                "fired_at": reminder.item.fired_at,
+                "level": level,
+                "step": step.key(),
+            });
+            self.log_delivery(
+                &reminder.user_id,
+                reminder.item.id,
+                "escalation_attempted",
+                &payload,
+                now,
+            );
+            let delivered = self
+                .escalate(&reminder.user_id, &reminder.item, step, level)
+                .await;
+            let _ = self
+                .store
+                .record_escalation(&reminder.user_id, reminder.item.id, level, now)
+                .await;
+            let status = if delivered {
+                "escalated"
+            } else {
+                "escalation_failed"
+            };
+            self.log_delivery(&reminder.user_id, reminder.item.id, status, &payload, now);
+        }
+        Ok(())
+    }
+}
 
-    if action == InboxAction::Done {
-        match item.source_type.as_str() {
-            "reminder" => {
-                let store = ReminderStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store
-                            .complete_reminder(&payload.user_id, item.source_id)
-                            .await;
-                    }
+impl ReminderEscalationJob {
+    /// Carries out one escalation step. A failed step still counts as taken
+    /// so a dead channel can't stall the rest of the chain.
+    async fn escalate(
+        &self,
+        user_id: &str,
+        reminder: &crate::reminders::ReminderItem,
+        step: EscalationStep,
+        level: i32,
+    ) -> bool {
+        let body = privacy_lock::reminder_notification_body(user_id, &[reminder.title.as_str()]);
+        match step {
+            EscalationStep::Chat => {
+                let agent = self.agent.read().await.clone();
+                let message = format!("Reminder still waiting on you: {body}");
+                match agent.post_assistant_message(user_id, &message).await {
+                    Ok(()) => true,
                     Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
+                        tracing::warn!(user_id, error = %err, "Reminder chat escalation failed");
+                        false
                     }
                 }
             }
-            "todo" => {
-                let store = TodoStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store.set_completed(item.source_id, true).await;
-                    }
-                    Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
-                    }
-                }
+            EscalationStep::Notification => {
+                send_desktop_notification("Butterfly Bot reminder (overdue)", &body)
             }
-            "task" => {
-                let store = TaskStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store.set_enabled(item.source_id, false).await;
-                    }
-                    Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    if action == InboxAction::Reopen {
-        match item.source_type.as_str() {
-            "reminder" => {
-                let store = ReminderStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store
-                            .reopen_reminder(&payload.user_id, item.source_id)
-                            .await;
-                    }
-                    Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
-                    }
-                }
-            }
-            "todo" => {
-                let store = TodoStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store.set_completed(item.source_id, false).await;
-                    }
-                    Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
-                    }
+            EscalationStep::Push => match self.escalation.push_url.as_deref() {
+                Some(url) => {
+                    let payload = json!({
+                        "type": "reminder_escalation",
+                        "user_id": user_id,
+                        "reminder_id": reminder.id,
+                        "text": body,
+                        "due_at": reminder.due_at,
+                        "level": level,
+                    });
+                    post_reminder_push(url, user_id, &payload).await
                 }
-            }
-            "task" => {
-                let store = TaskStore::new(&state.db_path).await;
-                match store {
-                    Ok(store) => {
-                        let _ = store.set_enabled(item.source_id, true).await;
-                    }
-                    Err(err) => {
-                        return (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ErrorResponse {
-                                error: err.to_string(),
-                            }),
-                        )
-                            .into_response();
-                    }
+                None => {
+                    tracing::warn!(user_id, "Reminder push escalation has no push_url");
+                    false
                 }
-            }
-            _ => {}
-        }
-    }
-
-    if action == InboxAction::Snooze && item.source_type == "reminder" {
-        let store = ReminderStore::new(&state.db_path).await;
-        match store {
-            Ok(store) => {
-                let now = now_ts();
-                let due_at = item.due_at.unwrap_or(now).max(now) + 15 * 60;
-                let _ = store
-                    .snooze_reminder(&payload.user_id, item.source_id, due_at)
-                    .await;
-            }
-            Err(err) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response();
-            }
+            },
+            // Read back by the inbox, which ranks the reminder as critical.
+            EscalationStep::InboxCritical => true,
         }
     }
 
-    let state_store = match InboxStateStore::new(&state.db_path).await {
-        Ok(store) => store,
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response();
-        }
-    };
-    if let Err(err) = state_store
-        .set_status(
-            &payload.user_id,
-            &payload.origin_ref,
-            inbox_state_to_str(next_state),
-        )
-        .await
-    {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response();
+    fn log_delivery(
+        &self,
+        user_id: &str,
+        reminder_id: i32,
+        status: &str,
+        payload: &Value,
+        now: i64,
+    ) {
+        log_reminder_delivery(
+            &self.ui_event_tx,
+            self.audit_log_path.as_deref(),
+            user_id,
+            reminder_id,
+            status,
+            payload,
+            now,
+        );
     }
+}
 
-    let _ = state.ui_event_tx.send(UiEvent {
-        event_type: "inbox_transition".to_string(),
-        user_id: payload.user_id.clone(),
-        tool: "inbox".to_string(),
-        status: inbox_state_to_str(next_state).to_string(),
-        payload: json!({
-            "origin_ref": payload.origin_ref,
-            "source_type": item.source_type,
-            "source_id": item.source_id,
-            "action": payload.action,
-            "actor": "human",
-            "reason": "manual_transition",
-            "from": inbox_state_to_str(previous_state),
-            "to": inbox_state_to_str(next_state),
-            "previous_status": inbox_state_to_str(previous_state),
-            "next_status": inbox_state_to_str(next_state),
-        }),
-        timestamp: now_ts(),
+fn log_reminder_delivery(
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+    audit_log_path: Option<&str>,
+    user_id: &str,
+    reminder_id: i32,
+    status: &str,
+    payload: &Value,
+    now: i64,
+) {
+    let _ = ui_event_tx.send(UiEvent {
+        event_type: "reminder_delivery".to_string(),
+        user_id: user_id.to_string(),
+        tool: "reminders".to_string(),
+        status: status.to_string(),
+        payload: payload.clone(),
+        timestamp: now,
     });
-
-    (
-        StatusCode::OK,
-        Json(InboxTransitionResponse {
-            status: "ok".to_string(),
-            origin_ref: payload.origin_ref,
-            previous_status: inbox_state_to_str(previous_state).to_string(),
-            next_status: inbox_state_to_str(next_state).to_string(),
-        }),
-    )
-        .into_response()
+    let _ = write_reminder_audit_log(
+        audit_log_path,
+        now,
+        user_id,
+        reminder_id,
+        status,
+        payload.clone(),
+    );
 }
 
-async fn reminder_delivery_events(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<ReminderDeliveryEventsQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
-
-    let config = Config::from_store(&state.db_path).ok();
-    let Some(path) = reminders_audit_log_path(config.as_ref()) else {
-        return (
-            StatusCode::OK,
-            Json(ReminderDeliveryEventsResponse { events: vec![] }),
-        )
-            .into_response();
-    };
-
-    let limit = query.limit.unwrap_or(50).clamp(1, 500);
-    let content = match tokio::fs::read_to_string(&path).await {
-        Ok(content) => content,
-        Err(_) => {
-            return (
-                StatusCode::OK,
-                Json(ReminderDeliveryEventsResponse { events: vec![] }),
-            )
-                .into_response()
+async fn post_reminder_push(url: &str, user_id: &str, payload: &Value) -> bool {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::warn!(error = %err, "Reminder push client failed to build");
+            return false;
         }
     };
-
-    let mut events = content
-        .lines()
-        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-        .filter(|event| {
-            if let Some(user_id) = &query.user_id {
-                event
-                    .get("user_id")
-                    .and_then(|value| value.as_str())
-                    .map(|value| value == user_id)
-                    .unwrap_or(false)
-            } else {
-                true
-            }
-        })
-        .collect::<Vec<_>>();
-
-    if events.len() > limit {
-        let keep_from = events.len() - limit;
-        events = events.split_off(keep_from);
-    }
-
-    (
-        StatusCode::OK,
-        Json(ReminderDeliveryEventsResponse { events }),
-    )
-        .into_response()
-}
-
-async fn audit_events(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<AuditEventsQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
-
-    let config = Config::from_store(&state.db_path).ok();
-    let Some(path) = ui_event_log_path(config.as_ref()) else {
-        return (StatusCode::OK, Json(AuditEventsResponse { events: vec![] })).into_response();
-    };
-
-    let limit = query.limit.unwrap_or(200).clamp(1, 2000);
-    let content = match tokio::fs::read_to_string(&path).await {
-        Ok(content) => content,
-        Err(_) => {
-            return (StatusCode::OK, Json(AuditEventsResponse { events: vec![] })).into_response()
+    match client.post(url).json(payload).send().await {
+        Ok(response) if response.status().is_success() => true,
+        Ok(response) => {
+            tracing::warn!(user_id, status = %response.status(), "Reminder push relay rejected");
+            false
+        }
+        Err(err) => {
+            tracing::warn!(user_id, error = %err, "Reminder push relay failed");
+            false
         }
-    };
-
-    let mut events = content
-        .lines()
-        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
-        .filter(|event| {
-            if let Some(user_id) = &query.user_id {
-                let event_user = event.get("user_id").and_then(|value| value.as_str());
-                let event_type = event.get("event_type").and_then(|value| value.as_str());
-                matches!(event_user, Some("system") | Some("daemon"))
-                    || event_user == Some(user_id.as_str())
-                    || matches!(event_type, Some("boot") | Some("autonomy"))
-            } else {
-                true
-            }
-        })
-        .collect::<Vec<_>>();
-
-    if events.len() > limit {
-        let keep_from = events.len() - limit;
-        events = events.split_off(keep_from);
     }
-
-    (StatusCode::OK, Json(AuditEventsResponse { events })).into_response()
 }
 
-fn parse_plan_step_title(step: &Value) -> Option<String> {
-    if let Some(text) = step.as_str() {
-        let trimmed = text.trim();
-        if !trimmed.is_empty() {
-            return Some(trimmed.to_string());
-        }
+#[async_trait::async_trait]
+impl ScheduledJob for WakeupJob {
+    fn name(&self) -> &str {
+        "wakeup"
     }
 
-    for key in ["title", "description", "name", "text", "step"] {
-        if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
-            let trimmed = value.trim();
-            if !trimmed.is_empty() {
-                return Some(trimmed.to_string());
-            }
-        }
+    fn interval(&self) -> Duration {
+        self.interval
     }
 
-    None
-}
+    async fn run(&self) -> Result<()> {
+        let now = self.store.clock().now();
+        let dynamic_source = Config::load(&self.db_path)
+            .ok()
+            .map(|cfg| cfg.heartbeat_source)
+            .unwrap_or_else(|| self.heartbeat_source.clone());
+        let prompt_source = Config::load(&self.db_path)
+            .ok()
+            .map(|cfg| cfg.prompt_source);
 
-fn normalize_unix_timestamp(value: i64) -> Option<i64> {
-    if value <= 0 {
-        return None;
-    }
-    if value >= 1_000_000_000_000 {
-        return Some(value / 1000);
-    }
-    Some(value)
-}
-
-fn parse_yyyy_mm_dd_to_unix(value: &str) -> Option<i64> {
-    let trimmed = value.trim();
-    let normalized = trimmed.replace('/', "-");
-    let mut parts = normalized.split('-');
-    let year = parts.next()?.trim().parse::<i32>().ok()?;
-    let month = parts.next()?.trim().parse::<u8>().ok()?;
-    let day = parts.next()?.trim().parse::<u8>().ok()?;
-    if parts.next().is_some() {
-        return None;
-    }
-    let date = Date::from_calendar_date(year, time::Month::try_from(month).ok()?, day).ok()?;
-    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    Some(
-        PrimitiveDateTime::new(date, Time::MIDNIGHT)
-            .assume_offset(local_offset)
-            .unix_timestamp(),
-    )
-}
-
-fn parse_due_at_value(value: &Value) -> Option<i64> {
-    match value {
-        Value::Number(number) => number
-            .as_i64()
-            .and_then(normalize_unix_timestamp)
-            .or_else(|| {
-                number
-                    .as_u64()
-                    .and_then(|n| normalize_unix_timestamp(n as i64))
-            }),
-        Value::String(raw) => {
-            let trimmed = raw.trim();
-            if trimmed.is_empty() {
-                return None;
-            }
-            if let Ok(ts) = trimmed.parse::<i64>() {
-                return normalize_unix_timestamp(ts);
+        match load_markdown_content(&dynamic_source).await {
+            Ok(markdown) => {
+                let agent = self.agent.read().await.clone();
+                agent.set_heartbeat_markdown(markdown.clone()).await;
+                let status = if markdown
+                    .as_ref()
+                    .map(|m| !m.trim().is_empty())
+                    .unwrap_or(false)
+                {
+                    "ok"
+                } else {
+                    "empty"
+                };
+                let event = UiEvent {
+                    event_type: "wakeup".to_string(),
+                    user_id: "system".to_string(),
+                    tool: "heartbeat".to_string(),
+                    status: status.to_string(),
+                    payload: json!({"source": dynamic_source}),
+                    timestamp: self.store.clock().now(),
+                };
+                let _ = self.ui_event_tx.send(event);
             }
-            if let Some(ts) = parse_yyyy_mm_dd_to_unix(trimmed) {
-                return Some(normalize_due_year_if_stale(ts, trimmed));
+            Err(err) => {
+                let event = UiEvent {
+                    event_type: "wakeup".to_string(),
+                    user_id: "system".to_string(),
+                    tool: "heartbeat".to_string(),
+                    status: "error".to_string(),
+                    payload: json!({"source": dynamic_source, "error": err.to_string()}),
+                    timestamp: self.store.clock().now(),
+                };
+                let _ = self.ui_event_tx.send(event);
             }
-            if let Some(ts) = parse_due_at_from_text(trimmed, now_ts()) {
-                return Some(ts);
+        }
+
+        if let Some(source) = &prompt_source {
+            match load_markdown_content(source).await {
+                Ok(markdown) => {
+                    let agent = self.agent.read().await.clone();
+                    agent.set_prompt_markdown(markdown.clone()).await;
+                    let status = if markdown
+                        .as_ref()
+                        .map(|m| !m.trim().is_empty())
+                        .unwrap_or(false)
+                    {
+                        "ok"
+                    } else {
+                        "empty"
+                    };
+                    let event = UiEvent {
+                        event_type: "wakeup".to_string(),
+                        user_id: "system".to_string(),
+                        tool: "prompt".to_string(),
+                        status: status.to_string(),
+                        payload: json!({"source": source}),
+                        timestamp: self.store.clock().now(),
+                    };
+                    let _ = self.ui_event_tx.send(event);
+                }
+                Err(err) => {
+                    let event = UiEvent {
+                        event_type: "wakeup".to_string(),
+                        user_id: "system".to_string(),
+                        tool: "prompt".to_string(),
+                        status: "error".to_string(),
+                        payload: json!({"source": source, "error": err.to_string()}),
+                        timestamp: self.store.clock().now(),
+                    };
+                    let _ = self.ui_event_tx.send(event);
+                }
             }
-            static DATE_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-            let date_re = DATE_RE.get_or_init(|| Regex::new(r"(\d{4}[-/]\d{2}[-/]\d{2})").unwrap());
-            let captures = date_re.captures(trimmed)?;
-            parse_yyyy_mm_dd_to_unix(captures.get(1)?.as_str())
         }
-        _ => None,
-    }
-}
 
-fn normalize_nlp_due_text(input: &str) -> String {
-    static FROM_START_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    static FROM_NOW_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+        // Autonomous heartbeat processing
+        {
+            let agent = self.agent.read().await.clone();
+            let ui_event_tx = self.ui_event_tx.clone();
+            tokio::spawn(async move {
+                run_autonomy_tick(agent, ui_event_tx, "system".to_string(), "wakeup").await;
+            });
+        }
 
-    let from_start_re = FROM_START_RE.get_or_init(|| {
-        Regex::new(r"(?i)\b(\d+)\s*(day|days|week|weeks|month|months)\s+from\s+start\b").unwrap()
-    });
-    let from_now_re = FROM_NOW_RE.get_or_init(|| {
-        Regex::new(r"(?i)\b(\d+)\s*(day|days|week|weeks|month|months)\s+from\s+now\b").unwrap()
-    });
+        let policy = self.catch_up.policy_for(self.name());
+        let tasks = self.store.list_due(now, 32).await?;
+        for task in tasks {
+            let run_at = self.store.clock().now();
+            let plan = policy.item_plan(
+                task.next_run_at,
+                run_at,
+                task.interval_minutes.max(1) * 60,
+                self.catch_up.max_runs,
+            );
+            if plan.runs == 0 {
+                let _ = self.store.reschedule(task.id, plan.next_run_at).await;
+                announce_missed_run(
+                    &self.ui_event_tx,
+                    "wakeup",
+                    &task.user_id,
+                    task.id,
+                    &task.name,
+                    plan.missed,
+                    plan.next_run_at,
+                    run_at,
+                );
+                continue;
+            }
+            let next_run_at = if plan.missed > 1 {
+                plan.next_run_at
+            } else {
+                task.next_run_after(run_at)
+            };
+            let Some(slot) = self.running.try_start(&task) else {
+                // The previous run is still going; this occurrence is dropped
+                // rather than queued behind it.
+                let _ = self.store.reschedule(task.id, next_run_at).await;
+                let payload = json!({
+                    "task_id": task.id,
+                    "name": task.name,
+                    "running": self.running.running(task.id),
+                    "next_run_at": next_run_at,
+                });
+                let _ = self.ui_event_tx.send(UiEvent {
+                    event_type: "wakeup".to_string(),
+                    user_id: task.user_id.clone(),
+                    tool: "wakeup".to_string(),
+                    status: "skipped".to_string(),
+                    payload: payload.clone(),
+                    timestamp: run_at,
+                });
+                let _ = write_wakeup_audit_log(
+                    self.audit_log_path.as_deref(),
+                    run_at,
+                    &task,
+                    "skipped",
+                    payload,
+                );
+                continue;
+            };
+            let _ = self.store.mark_run(task.id, run_at, next_run_at).await;
 
-    let normalized = from_start_re.replace_all(input, "in $1 $2").to_string();
-    from_now_re.replace_all(&normalized, "in $1 $2").to_string()
+            let agent = self.agent.read().await.clone();
+            let store = self.store.clone();
+            let ui_event_tx = self.ui_event_tx.clone();
+            let audit_log_path = self.audit_log_path.clone();
+            let work = crate::shutdown::shared().begin(format!("wakeup {}", task.id));
+            tokio::spawn(async move {
+                let (_slot, _work) = (slot, work);
+                run_wakeup_task(agent, store, ui_event_tx, audit_log_path, task, run_at).await;
+            });
+        }
+        Ok(())
+    }
 }
 
-fn text_has_explicit_time(input: &str) -> bool {
-    static TIME_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re = TIME_RE.get_or_init(|| {
-        Regex::new(r"(?i)(\b\d{1,2}:\d{2}\b|\b\d{1,2}\s*(am|pm)\b|\bnoon\b|\bmidnight\b)").unwrap()
+/// Tells the UI a recurring item's missed slots were skipped on catch-up.
+#[allow(clippy::too_many_arguments)]
+fn announce_missed_run(
+    ui_event_tx: &broadcast::Sender<UiEvent>,
+    tool: &str,
+    user_id: &str,
+    task_id: i32,
+    name: &str,
+    missed: i64,
+    next_run_at: i64,
+    timestamp: i64,
+) {
+    let _ = ui_event_tx.send(UiEvent {
+        event_type: tool.to_string(),
+        user_id: user_id.to_string(),
+        tool: tool.to_string(),
+        status: "skipped".to_string(),
+        payload: json!({
+            "task_id": task_id,
+            "name": name,
+            "missed": missed,
+            "catch_up": CatchUpPolicy::Skip.as_str(),
+            "next_run_at": next_run_at,
+        }),
+        timestamp,
     });
-    re.is_match(input)
 }
 
-fn local_midnight_unix(ts: i64) -> i64 {
-    let Ok(utc_dt) = time::OffsetDateTime::from_unix_timestamp(ts) else {
-        return ts;
+async fn run_wakeup_task(
+    agent: Arc<ButterflyBot>,
+    store: Arc<WakeupStore>,
+    ui_event_tx: broadcast::Sender<UiEvent>,
+    audit_log_path: Option<String>,
+    task: crate::wakeup::WakeupTask,
+    run_at: i64,
+) {
+    let options = ProcessOptions {
+        prompt: None,
+        images: Vec::new(),
+        output_format: OutputFormat::Text,
+        image_detail: "auto".to_string(),
+        json_schema: None,
     };
-    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    let local_dt = utc_dt.to_offset(local_offset);
-    let date = local_dt.date();
-    PrimitiveDateTime::new(date, Time::MIDNIGHT)
-        .assume_offset(local_offset)
-        .unix_timestamp()
-}
-
-fn extract_explicit_year(input: &str) -> Option<i32> {
-    static YEAR_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re = YEAR_RE.get_or_init(|| Regex::new(r"\b(20\d{2})\b").unwrap());
-    re.captures(input)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-}
-
-fn with_local_year(ts: i64, year: i32) -> Option<i64> {
-    let utc_dt = time::OffsetDateTime::from_unix_timestamp(ts).ok()?;
-    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    let local_dt = utc_dt.to_offset(local_offset);
-    let month = local_dt.month();
-    let mut day = local_dt.day();
-
-    let date = loop {
-        if let Ok(value) = Date::from_calendar_date(year, month, day) {
-            break value;
-        }
-        if day == 1 {
-            return None;
+    let input = format!("Wakeup task '{}': {}", task.name, task.prompt);
+    let result = match crate::prompt_queue::shared()
+        .acquire("wakeup", PromptPriority::Scheduled)
+        .await
+    {
+        Ok(_permit) => {
+            agent
+                .process(&task.user_id, UserInput::Text(input), options)
+                .await
         }
-        day -= 1;
+        Err(err) => Err(err),
     };
 
-    let pdt = PrimitiveDateTime::new(
-        date,
-        Time::from_hms(local_dt.hour(), local_dt.minute(), local_dt.second()).ok()?,
-    );
-    Some(pdt.assume_offset(local_offset).unix_timestamp())
-}
-
-fn normalize_due_year_if_stale(ts: i64, input: &str) -> i64 {
-    let now = now_ts();
-    let Some(explicit_year) = extract_explicit_year(input) else {
-        return ts;
+    let (status, mut payload): (String, Value) = match result {
+        Ok(ProcessResult::Text(text)) => (
+            "ok".to_string(),
+            json!({"task_id": task.id, "name": task.name, "output": text}),
+        ),
+        Ok(other) => (
+            "ok".to_string(),
+            json!({"task_id": task.id, "name": task.name, "output": format!("{other:?}")}),
+        ),
+        Err(err) => (
+            "error".to_string(),
+            json!({"task_id": task.id, "name": task.name, "error": err.to_string()}),
+        ),
     };
+    if let Ok(updated) = store.record_outcome(task.id, run_at, status == "ok").await {
+        if updated.consecutive_failures > 0 {
+            payload["consecutive_failures"] = json!(updated.consecutive_failures);
+            payload["next_run_at"] = json!(updated.next_run_at);
+        }
+    }
 
-    let Ok(now_local) = time::OffsetDateTime::from_unix_timestamp(now) else {
-        return ts;
+    let event = UiEvent {
+        event_type: "wakeup".to_string(),
+        user_id: task.user_id.clone(),
+        tool: "wakeup".to_string(),
+        status: status.clone(),
+        payload: payload.clone(),
+        timestamp: run_at,
     };
-    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    let current_year = now_local.to_offset(local_offset).year();
+    let _ = ui_event_tx.send(event);
+    let _ = write_wakeup_audit_log(
+        audit_log_path.as_deref(),
+        run_at,
+        &task,
+        status.as_str(),
+        payload.clone(),
+    );
+}
 
-    if explicit_year >= current_year {
-        return ts;
-    }
+#[derive(Serialize)]
+struct HealthResponse {
+    status: String,
+}
 
-    if let Some(mut adjusted) = with_local_year(ts, current_year) {
-        if adjusted < now {
-            if let Some(next_year) = with_local_year(ts, current_year + 1) {
-                adjusted = next_year;
-            }
-        }
-        return adjusted;
+#[derive(Deserialize)]
+struct ProcessTextRequest {
+    user_id: String,
+    text: String,
+    prompt: Option<String>,
+    /// Queue source, e.g. `ui` or `webhook`; defaults to `ui`.
+    source: Option<String>,
+    /// `interactive`, `scheduled` or `background`; defaults by source.
+    priority: Option<String>,
+    /// Conversation thread; its pinned model, if any, answers the prompt.
+    thread_id: Option<String>,
+    /// Origin ref this message answers, e.g. `question:7` from the inbox.
+    reply_to: Option<String>,
+}
+
+impl ProcessTextRequest {
+    fn admission(&self) -> (String, PromptPriority) {
+        let source = self
+            .source
+            .as_deref()
+            .map(str::trim)
+            .filter(|source| !source.is_empty())
+            .unwrap_or("ui")
+            .to_ascii_lowercase();
+        let priority = self
+            .priority
+            .as_deref()
+            .and_then(PromptPriority::parse)
+            .unwrap_or(if source == "ui" {
+                PromptPriority::Interactive
+            } else {
+                PromptPriority::Scheduled
+            });
+        (source, priority)
     }
 
-    ts
+    /// Only messages typed by the human, in the UI or the terminal chat,
+    /// can answer questions or run slash commands.
+    fn from_human(&self) -> bool {
+        matches!(self.admission().0.as_str(), "ui" | "cli")
+    }
 }
 
-fn parse_due_at_nlp(input: &str, anchor_ts: i64) -> Option<i64> {
-    let normalized = normalize_nlp_due_text(input);
-    let anchor = Local
-        .timestamp_opt(anchor_ts, 0)
-        .single()
-        .or_else(|| Local.timestamp_opt(now_ts(), 0).single())
-        .unwrap_or_else(Local::now);
-
-    parse_date_string(&normalized, anchor, Dialect::Us)
-        .or_else(|_| parse_date_string(&normalized, anchor, Dialect::Uk))
-        .ok()
-        .map(|dt: DateTime<Local>| {
-            let ts = dt.timestamp();
-            let parsed = if text_has_explicit_time(input) {
-                ts
-            } else {
-                local_midnight_unix(ts)
-            };
-            normalize_due_year_if_stale(parsed, input)
-        })
+#[derive(Serialize)]
+struct ProcessTextResponse {
+    text: String,
 }
 
-fn parse_due_at_from_text(input: &str, anchor_ts: i64) -> Option<i64> {
-    if input.trim().is_empty() {
-        return None;
-    }
-    static DATE_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let date_re = DATE_RE.get_or_init(|| {
-        Regex::new(r"(?i)(?:due(?:\s+date)?|deadline)\s*:\s*(\d{4}[-/]\d{2}[-/]\d{2})").unwrap()
-    });
-    if let Some(captures) = date_re.captures(input) {
-        if let Some(found) = captures.get(1).map(|m| m.as_str()) {
-            if let Some(ts) = parse_yyyy_mm_dd_to_unix(found) {
-                return Some(ts);
-            }
-        }
-    }
-    let fallback_re = DATE_RE.get_or_init(|| Regex::new(r"(\d{4}[-/]\d{2}[-/]\d{2})").unwrap());
-    if let Some(captures) = fallback_re.captures(input) {
-        if let Some(ts) = parse_yyyy_mm_dd_to_unix(captures.get(1)?.as_str()) {
-            return Some(normalize_due_year_if_stale(ts, input));
-        }
-    }
+/// Upper bound on an uploaded recording; about ten minutes of 16 kHz WAV.
+const MAX_RECORDING_BYTES: usize = 25 * 1024 * 1024;
+/// Upper bound on the items in one `/inbox/bulk` request.
+const MAX_INBOX_BULK_ITEMS: usize = 500;
 
-    parse_due_at_nlp(input, anchor_ts)
+#[derive(Deserialize)]
+struct TranscribeQuery {
+    user_id: String,
+    /// Container of the upload, e.g. `wav`.
+    format: Option<String>,
 }
 
-fn parse_plan_step_due_at(step: &Value, title: &str, anchor_ts: i64) -> Option<i64> {
-    for key in [
-        "due_at",
-        "due_ts",
-        "due",
-        "due_date",
-        "due_on",
-        "deadline",
-        "target_date",
-    ] {
-        if let Some(value) = step.get(key).and_then(parse_due_at_value) {
-            return Some(value);
-        }
-    }
+#[derive(Deserialize)]
+struct MemorySearchRequest {
+    user_id: String,
+    query: String,
+    limit: Option<usize>,
+}
 
-    if let Some(description) = step.get("description").and_then(|v| v.as_str()) {
-        if let Some(ts) = parse_due_at_from_text(description, anchor_ts) {
-            return Some(ts);
-        }
-    }
+#[derive(Deserialize)]
+struct SemanticMemorySearchRequest {
+    user_id: String,
+    query: String,
+    limit: Option<usize>,
+    rerank: Option<bool>,
+}
 
-    parse_due_at_from_text(title, anchor_ts)
+#[derive(Deserialize)]
+struct MemoryRetentionRequest {
+    user_id: String,
+    dry_run: Option<bool>,
 }
 
-fn parse_plan_step_story_points(step: &Value, text: &str) -> Option<i32> {
-    for key in ["story_points", "points", "estimate_points"] {
-        if let Some(value) = step.get(key).and_then(|v| v.as_i64()) {
-            if value > 0 {
-                return Some(value as i32);
-            }
-        }
-    }
+#[derive(Deserialize)]
+struct ChatHistoryQuery {
+    user_id: String,
+    limit: Option<usize>,
+}
 
-    static STORY_POINTS_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re =
-        STORY_POINTS_RE.get_or_init(|| Regex::new(r"(?i)story\s*points?\s*:\s*(\d+)").unwrap());
-    re.captures(text)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-        .filter(|value| *value > 0)
+#[derive(Deserialize)]
+struct ClearHistoryRequest {
+    user_id: String,
 }
 
-fn parse_plan_step_t_shirt_size(step: &Value, text: &str) -> Option<String> {
-    for key in ["t_shirt_size", "tshirt_size", "shirt_size", "size"] {
-        if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
-            let normalized = value.trim().to_ascii_uppercase();
-            if ["XS", "S", "M", "L", "XL", "XXL"].contains(&normalized.as_str()) {
-                return Some(normalized);
-            }
-        }
-    }
+#[derive(Deserialize)]
+struct ClearUserDataRequest {
+    user_id: String,
+}
 
-    static TSHIRT_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re = TSHIRT_RE
-        .get_or_init(|| Regex::new(r"(?i)t-?shirt\s*size\s*:\s*(XS|S|M|L|XL|XXL)").unwrap());
-    re.captures(text)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_ascii_uppercase())
+#[derive(Deserialize)]
+struct SearchQuery {
+    user_id: String,
+    q: String,
+    /// Comma-separated kinds (`todo,reminder,plan,chat`); all by default.
+    kinds: Option<String>,
+    limit: Option<usize>,
 }
 
-fn parse_time_estimate_minutes_from_text(text: &str) -> Option<i32> {
-    static WEEK_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    static DAY_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    static HOUR_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    static MIN_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+#[derive(Deserialize)]
+struct ThreadsQuery {
+    user_id: String,
+}
 
-    let week_re =
-        WEEK_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*weeks?").unwrap());
-    if let Some(weeks) = week_re
-        .captures(text)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-    {
-        return Some(weeks.max(1) * 5 * 8 * 60);
-    }
+#[derive(Deserialize)]
+struct ThreadPinRequest {
+    user_id: String,
+    thread_id: String,
+    #[serde(flatten)]
+    pin: ThreadPin,
+}
 
-    let day_re =
-        DAY_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*days?").unwrap());
-    if let Some(days) = day_re
-        .captures(text)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-    {
-        return Some(days.max(1) * 8 * 60);
-    }
+#[derive(Deserialize)]
+struct ThreadUnpinRequest {
+    user_id: String,
+    thread_id: String,
+}
 
-    let hour_re =
-        HOUR_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*hours?").unwrap());
-    if let Some(hours) = hour_re
-        .captures(text)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-    {
-        return Some(hours.max(1) * 60);
-    }
+#[derive(Serialize)]
+struct ThreadsResponse {
+    /// `provider:model` answering threads that pin nothing.
+    default_model: String,
+    threads: Vec<ChatThread>,
+}
 
-    let min_re =
-        MIN_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*minutes?").unwrap());
-    min_re
-        .captures(text)
-        .and_then(|caps| caps.get(1))
-        .and_then(|m| m.as_str().parse::<i32>().ok())
-        .map(|minutes| minutes.max(1))
+#[derive(Deserialize)]
+struct DependencyCheckQuery {
+    user_id: String,
 }
 
-fn parse_plan_status(value: Option<&str>, default_status: &str) -> String {
-    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
-        "new" => "new".to_string(),
-        "ack" | "acknowledged" => "acknowledged".to_string(),
-        "in_progress" | "in progress" | "started" => "in_progress".to_string(),
-        "blocked" => "blocked".to_string(),
-        "done" | "completed" | "complete" => "done".to_string(),
-        "dismissed" | "archived" => "dismissed".to_string(),
-        _ => default_status.to_string(),
-    }
+#[derive(Deserialize)]
+struct TrashQuery {
+    user_id: String,
 }
 
-fn parse_plan_priority(value: Option<&str>) -> String {
-    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
-        "low" => "low".to_string(),
-        "high" => "high".to_string(),
-        "urgent" | "critical" => "urgent".to_string(),
-        _ => "normal".to_string(),
-    }
+#[derive(Deserialize)]
+struct TrashRestoreRequest {
+    user_id: String,
+    store: String,
+    batch_id: String,
 }
 
-fn parse_plan_priority_from_text(text: &str) -> Option<String> {
-    static PRIORITY_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re = PRIORITY_RE.get_or_init(|| {
-        Regex::new(r"(?i)priority\s*:\s*(low|normal|medium|high|urgent|critical)").unwrap()
-    });
-    let raw = re
-        .captures(text)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str())?;
-    Some(parse_plan_priority(Some(raw)))
+#[derive(Deserialize)]
+struct TemplateBundleRequest {
+    user_id: String,
+    bundle: Value,
+    /// Digest returned by `/templates/preview`; required to import.
+    #[serde(default)]
+    digest: Option<String>,
 }
 
-fn parse_plan_owner_from_text(text: &str) -> Option<String> {
-    static OWNER_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let re = OWNER_RE.get_or_init(|| Regex::new(r"(?i)owner\s*:\s*(human|agent)").unwrap());
-    re.captures(text)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_ascii_lowercase())
+#[derive(Deserialize)]
+struct PrivacyLockRequest {
+    user_id: String,
+    locked: bool,
 }
 
-fn build_plan_step_alias_map(plan_id: i32, steps: &[Value]) -> HashMap<String, String> {
-    let mut alias_map = HashMap::new();
-    for (index, step) in steps.iter().enumerate() {
-        let origin_ref = format!("plan_step:{plan_id}:{index}");
+#[derive(Serialize)]
+struct PrivacyLockResponse {
+    user_id: String,
+    locked: bool,
+}
 
-        alias_map.insert(index.to_string(), origin_ref.clone());
-        alias_map.insert(format!("step {index}"), origin_ref.clone());
-        alias_map.insert(format!("step {}", index + 1), origin_ref.clone());
+#[derive(Deserialize)]
+struct EncryptionQuery {
+    user_id: String,
+}
 
-        for key in ["id", "ref", "key", "code", "step_id"] {
-            if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
-                let normalized = value.trim().to_ascii_lowercase();
-                if !normalized.is_empty() {
-                    alias_map.insert(normalized, origin_ref.clone());
-                }
-            }
-        }
+#[derive(Deserialize)]
+struct EncryptionPassphraseRequest {
+    user_id: String,
+    passphrase: String,
+}
 
-        if let Some(title) = parse_plan_step_title(step) {
-            let normalized = title.trim().to_ascii_lowercase();
-            if !normalized.is_empty() {
-                alias_map.insert(normalized.clone(), origin_ref.clone());
-                let compact = normalized
-                    .chars()
-                    .map(|c| {
-                        if c.is_ascii_alphanumeric() || c.is_ascii_whitespace() {
-                            c
-                        } else {
-                            ' '
-                        }
-                    })
-                    .collect::<String>()
-                    .split_whitespace()
-                    .collect::<Vec<_>>()
-                    .join(" ");
-                if !compact.is_empty() {
-                    alias_map.insert(compact, origin_ref.clone());
-                }
-            }
-        }
-    }
-    alias_map
+#[derive(Deserialize)]
+struct EncryptionLockRequest {
+    user_id: String,
 }
 
-fn parse_plan_step_dependency_refs(
-    plan_id: i32,
-    step: &Value,
-    alias_map: &HashMap<String, String>,
-) -> Vec<String> {
-    fn resolve_alias(alias_map: &HashMap<String, String>, lower: &str) -> Option<String> {
-        if let Some(mapped) = alias_map.get(lower) {
-            return Some(mapped.clone());
-        }
-        if lower.len() < 4 {
-            return None;
-        }
-        alias_map
-            .iter()
-            .filter(|(key, _)| key.contains(lower) || lower.contains(key.as_str()))
-            .max_by_key(|(key, _)| key.len())
-            .map(|(_, value)| value.clone())
-    }
+#[derive(Deserialize)]
+struct EncryptionChangeRequest {
+    user_id: String,
+    current: String,
+    next: String,
+}
 
-    fn push_dependency_ref(
-        out: &mut Vec<String>,
-        plan_id: i32,
-        alias_map: &HashMap<String, String>,
-        value: &Value,
-    ) {
-        match value {
-            Value::Number(number) => {
-                if let Some(index) = number.as_u64() {
-                    out.push(format!("plan_step:{plan_id}:{index}"));
-                }
-            }
-            Value::String(raw) => {
-                let trimmed = raw.trim();
-                if trimmed.is_empty() {
-                    return;
-                }
-                let lower = trimmed.to_ascii_lowercase();
-                if lower.starts_with("plan_step:")
-                    || lower.starts_with("task:")
-                    || lower.starts_with("todo:")
-                    || lower.starts_with("reminder:")
-                    || lower.starts_with("plan:")
-                {
-                    out.push(lower);
-                } else if let Some(mapped) = resolve_alias(alias_map, &lower) {
-                    out.push(mapped.clone());
-                } else if trimmed.contains(',') || trimmed.contains('|') || trimmed.contains(';') {
-                    for token in trimmed.split([',', '|', ';']) {
-                        push_dependency_ref(
-                            out,
-                            plan_id,
-                            alias_map,
-                            &Value::String(token.to_string()),
-                        );
-                    }
-                } else if let Ok(index) = trimmed.parse::<u64>() {
-                    out.push(format!("plan_step:{plan_id}:{index}"));
-                } else if let Some(rest) = lower.strip_prefix("step ") {
-                    if let Ok(index) = rest.trim().parse::<u64>() {
-                        out.push(format!("plan_step:{plan_id}:{index}"));
-                    }
-                } else {
-                    out.push(lower);
-                }
-            }
-            Value::Array(values) => {
-                for entry in values {
-                    push_dependency_ref(out, plan_id, alias_map, entry);
-                }
-            }
-            Value::Object(map) => {
-                if let Some(origin_ref) = map.get("origin_ref").or_else(|| map.get("ref")) {
-                    push_dependency_ref(out, plan_id, alias_map, origin_ref);
-                } else if let Some(id) = map.get("id") {
-                    push_dependency_ref(out, plan_id, alias_map, id);
-                } else if let Some(step_index) = map
-                    .get("step_index")
-                    .or_else(|| map.get("index"))
-                    .or_else(|| map.get("step"))
-                {
-                    push_dependency_ref(out, plan_id, alias_map, step_index);
-                }
-            }
-            _ => {}
-        }
-    }
+#[derive(Serialize)]
+struct EncryptionStatusResponse {
+    user_id: String,
+    status: DomainStatus,
+}
 
-    fn push_dependency_refs_from_text(
-        out: &mut Vec<String>,
-        plan_id: i32,
-        alias_map: &HashMap<String, String>,
-        text: &str,
-    ) {
-        static DEPENDS_ON_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-        let re = DEPENDS_ON_RE.get_or_init(|| {
-            Regex::new(
-                r"(?i)(?:depends\s*on|dependencies|blocked\s*by|requires|dependency[_\s-]*refs?)\s*:\s*([^\n\r]+)",
-            )
-            .unwrap()
-        });
+#[derive(Deserialize)]
+struct ApprovalDecisionRequest {
+    user_id: String,
+    id: i32,
+    /// `approve` or `reject`.
+    decision: String,
+    /// For batch approvals such as the inbox sweep: the origin refs to
+    /// apply. Everything in the batch when omitted.
+    accepted: Option<Vec<String>>,
+}
 
-        for caps in re.captures_iter(text) {
-            if let Some(raw) = caps.get(1).map(|m| m.as_str()) {
-                for token in raw.split([',', '|', ';']) {
-                    push_dependency_ref(out, plan_id, alias_map, &Value::String(token.to_string()));
-                }
-            }
-        }
-    }
+#[derive(Deserialize)]
+struct ConsentQuery {
+    user_id: String,
+}
 
-    let mut refs = Vec::new();
-    for key in [
-        "dependency_refs",
-        "dependencyRefs",
-        "depends_on",
-        "dependsOn",
-        "dependencies",
-        "blocked_by",
-        "blockedBy",
-        "requires",
-        "prerequisite",
-        "prerequisites",
-        "after",
-    ] {
-        if let Some(value) = step.get(key) {
-            push_dependency_ref(&mut refs, plan_id, alias_map, value);
-        }
-    }
+#[derive(Deserialize)]
+struct ConsentDecisionRequest {
+    user_id: String,
+    id: i32,
+    /// `allow` or `deny`.
+    decision: String,
+    /// Overrides `tools.settings.consent.ttl_days`; `0` keeps the grant
+    /// until it is revoked.
+    ttl_days: Option<u64>,
+}
 
-    if let Some(title) = parse_plan_step_title(step) {
-        push_dependency_refs_from_text(&mut refs, plan_id, alias_map, &title);
-    }
-    if let Some(description) = step.get("description").and_then(|v| v.as_str()) {
-        push_dependency_refs_from_text(&mut refs, plan_id, alias_map, description);
-    }
+#[derive(Deserialize)]
+struct ConsentRevokeRequest {
+    user_id: String,
+    id: i32,
+}
 
-    refs.sort();
-    refs.dedup();
-    refs
+#[derive(Deserialize)]
+struct InboxSweepRequest {
+    user_id: String,
 }
 
-fn extract_plan_step_ref_from_details(details: Option<&str>) -> Option<String> {
-    static PLAN_STEP_REF_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
-    let text = details?;
-    let re = PLAN_STEP_REF_RE
-        .get_or_init(|| Regex::new(r"(?i)planstepref\s*:\s*(plan_step:\d+:\d+)").unwrap());
-    re.captures(text)
-        .and_then(|caps| caps.get(1))
-        .map(|m| m.as_str().to_ascii_lowercase())
+#[derive(Deserialize)]
+struct PreloadBootRequest {
+    user_id: String,
 }
 
-fn priority_rank(priority: &str) -> i32 {
-    match priority {
-        "urgent" => 0,
-        "high" => 1,
-        "normal" => 2,
-        _ => 3,
-    }
+#[derive(Serialize)]
+struct PreloadBootResponse {
+    context_status: String,
+    heartbeat_status: String,
 }
 
-async fn build_inbox_items(
-    db_path: &str,
-    user_id: &str,
-    limit: usize,
-    include_done: bool,
-) -> Result<Vec<InboxItemResponse>> {
-    let now = now_ts();
-    let config_json = Config::from_store(db_path)
-        .ok()
-        .and_then(|cfg| cfg.tools)
-        .unwrap_or(Value::Null);
+#[derive(Deserialize)]
+struct ReminderStreamQuery {
+    user_id: String,
+}
 
-    let reminder_db_path =
-        resolve_reminder_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
-    let todo_db_path = resolve_todo_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
-    let task_db_path = resolve_task_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
-    let plan_db_path = resolve_plan_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
+#[derive(Deserialize)]
+struct UiEventStreamQuery {
+    user_id: Option<String>,
+}
 
-    let reminder_store = ReminderStore::new(&reminder_db_path).await?;
-    let todo_store = TodoStore::new(&todo_db_path).await?;
-    let task_store = TaskStore::new(&task_db_path).await?;
-    let plan_store = PlanStore::new(&plan_db_path).await?;
+#[derive(Deserialize)]
+struct ReminderDeliveryEventsQuery {
+    user_id: Option<String>,
+    limit: Option<usize>,
+}
 
-    let reminders = reminder_store
-        .list_reminders(user_id, crate::reminders::ReminderStatus::All, limit)
-        .await?;
-    let todos = todo_store
-        .list_items(user_id, crate::todo::TodoStatus::All, limit)
-        .await?;
-    let tasks = task_store
-        .list_tasks(user_id, crate::tasks::TaskStatus::All, limit)
-        .await?;
-    let plans = plan_store.list_plans(user_id, limit).await?;
-    let plan_ids: Vec<i32> = plans.iter().map(|plan| plan.id).collect();
-    let plan_dependency_refs = plan_store
-        .list_step_dependencies_for_plans(&plan_ids)
-        .await?;
-    let status_store = InboxStateStore::new(db_path).await?;
-    let persisted_statuses = status_store.list_statuses(user_id, 2000).await?;
+#[derive(Deserialize)]
+struct DeadLettersQuery {
+    user_id: String,
+    limit: Option<usize>,
+}
 
-    let mut items = Vec::new();
+#[derive(Serialize)]
+struct DeadLettersResponse {
+    dead_letters: Vec<crate::reminders::DeadLetter>,
+}
 
-    for reminder in reminders {
-        let status = if reminder.completed_at.is_some() {
-            "done"
-        } else {
-            "new"
-        };
-        if !include_done && status == "done" {
-            continue;
-        }
-        let priority = if reminder.completed_at.is_none() && reminder.due_at <= now {
-            "high"
-        } else {
-            "normal"
-        };
-        items.push(InboxItemResponse {
-            id: format!("reminder:{}", reminder.id),
-            source_type: "reminder".to_string(),
-            source_id: reminder.id,
-            title: reminder.title,
-            details: Some("Reminder".to_string()),
-            owner: "human".to_string(),
-            status: status.to_string(),
-            priority: priority.to_string(),
-            due_at: Some(reminder.due_at),
-            created_at: reminder.created_at,
-            updated_at: reminder.completed_at.unwrap_or(reminder.created_at),
-            requires_human_action: reminder.completed_at.is_none(),
-            origin_ref: format!("reminder:{}", reminder.id),
-            dependency_refs: vec![],
-            t_shirt_size: None,
-            story_points: None,
-            estimate_optimistic_minutes: None,
-            estimate_likely_minutes: None,
-            estimate_pessimistic_minutes: None,
-        });
-    }
+#[derive(Deserialize)]
+struct DeadLetterActionRequest {
+    user_id: String,
+    id: i32,
+}
 
-    for todo in todos {
-        let status = if todo.completed_at.is_some() {
-            "done"
-        } else {
-            "new"
-        };
-        if !include_done && status == "done" {
-            continue;
-        }
-        items.push(InboxItemResponse {
-            id: format!("todo:{}", todo.id),
-            source_type: "todo".to_string(),
-            source_id: todo.id,
-            title: todo.title,
-            details: todo.notes,
-            owner: "human".to_string(),
-            status: status.to_string(),
-            priority: "normal".to_string(),
-            due_at: None,
-            created_at: todo.created_at,
-            updated_at: todo.updated_at,
-            requires_human_action: todo.completed_at.is_none(),
-            origin_ref: format!("todo:{}", todo.id),
-            dependency_refs: todo.dependency_refs,
-            t_shirt_size: todo.t_shirt_size,
-            story_points: todo.story_points,
-            estimate_optimistic_minutes: todo.estimate_optimistic_minutes,
-            estimate_likely_minutes: todo.estimate_likely_minutes,
-            estimate_pessimistic_minutes: todo.estimate_pessimistic_minutes,
-        });
-    }
-
-    for task in tasks {
-        let status = if task.enabled { "new" } else { "done" };
-        if !include_done && status == "done" {
-            continue;
-        }
-        let priority = if task.enabled && task.next_run_at <= now {
-            "high"
-        } else {
-            "normal"
-        };
-        items.push(InboxItemResponse {
-            id: format!("task:{}", task.id),
-            source_type: "task".to_string(),
-            source_id: task.id,
-            title: task.name,
-            details: Some(task.prompt),
-            owner: "human".to_string(),
-            status: status.to_string(),
-            priority: priority.to_string(),
-            due_at: Some(task.next_run_at),
-            created_at: task.created_at,
-            updated_at: task.updated_at,
-            requires_human_action: task.enabled,
-            origin_ref: format!("task:{}", task.id),
-            dependency_refs: vec![],
-            t_shirt_size: None,
-            story_points: None,
-            estimate_optimistic_minutes: None,
-            estimate_likely_minutes: None,
-            estimate_pessimistic_minutes: None,
-        });
-    }
+#[derive(Deserialize)]
+struct OutboxDeliveriesQuery {
+    user_id: String,
+    limit: Option<usize>,
+}
 
-    for plan in plans {
-        let mut emitted_step = false;
-        if let Some(steps) = plan.steps.as_ref().and_then(|value| value.as_array()) {
-            let step_alias_map = build_plan_step_alias_map(plan.id, steps);
-            for (index, step) in steps.iter().enumerate() {
-                let Some(title) = parse_plan_step_title(step) else {
-                    continue;
-                };
-                emitted_step = true;
-                let owner = step
-                    .get("owner")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("human")
-                    .to_ascii_lowercase();
-                let owner = parse_plan_owner_from_text(&title).unwrap_or(owner);
-                let status = parse_plan_status(
-                    step.get("status").and_then(|v| v.as_str()),
-                    if plan.status == "done" { "done" } else { "new" },
-                );
-                if !include_done && status == "done" {
-                    continue;
-                }
-                let due_at = parse_plan_step_due_at(step, &title, plan.created_at);
-                let priority = parse_plan_priority(step.get("priority").and_then(|v| v.as_str()));
-                let priority = parse_plan_priority_from_text(&title).unwrap_or(priority);
-                let origin_ref = format!("plan_step:{}:{}", plan.id, index);
-                let dependency_refs = plan_dependency_refs
-                    .get(&origin_ref)
-                    .cloned()
-                    .unwrap_or_else(|| {
-                        parse_plan_step_dependency_refs(plan.id, step, &step_alias_map)
-                    });
-                let t_shirt_size = parse_plan_step_t_shirt_size(step, &title);
-                let story_points = parse_plan_step_story_points(step, &title);
-                let estimate_likely_minutes = step
-                    .get("estimate_likely_minutes")
-                    .and_then(|v| v.as_i64())
-                    .map(|v| v as i32)
-                    .or_else(|| parse_time_estimate_minutes_from_text(&title));
-                let estimate_optimistic_minutes = step
-                    .get("estimate_optimistic_minutes")
-                    .and_then(|v| v.as_i64())
-                    .map(|v| v as i32)
-                    .or_else(|| {
-                        estimate_likely_minutes.map(|value| ((value as f32) * 0.70).round() as i32)
-                    });
-                let estimate_pessimistic_minutes = step
-                    .get("estimate_pessimistic_minutes")
-                    .and_then(|v| v.as_i64())
-                    .map(|v| v as i32)
-                    .or_else(|| {
-                        estimate_likely_minutes.map(|value| ((value as f32) * 1.45).round() as i32)
-                    });
+#[derive(Serialize)]
+struct OutboxDeliveriesResponse {
+    deliveries: Vec<OutboxDelivery>,
+}
 
-                items.push(InboxItemResponse {
-                    id: format!("plan_step:{}:{}", plan.id, index),
-                    source_type: "plan_step".to_string(),
-                    source_id: plan.id,
-                    title,
-                    details: Some(format!("Plan: {}", plan.title)),
-                    owner: owner.clone(),
-                    status,
-                    priority,
-                    due_at,
-                    created_at: plan.created_at,
-                    updated_at: plan.updated_at,
-                    requires_human_action: owner != "agent",
-                    origin_ref,
-                    dependency_refs,
-                    t_shirt_size,
-                    story_points,
-                    estimate_optimistic_minutes,
-                    estimate_likely_minutes,
-                    estimate_pessimistic_minutes,
-                });
-            }
-        }
+#[derive(Deserialize)]
+struct EstimateAccuracyQuery {
+    user_id: String,
+}
 
-        if !emitted_step {
-            let status = if plan.status == "done" { "done" } else { "new" };
-            if !include_done && status == "done" {
-                continue;
-            }
-            items.push(InboxItemResponse {
-                id: format!("plan:{}", plan.id),
-                source_type: "plan_step".to_string(),
-                source_id: plan.id,
-                title: format!("Review plan: {}", plan.title),
-                details: Some(plan.goal),
-                owner: "human".to_string(),
-                status: status.to_string(),
-                priority: "normal".to_string(),
-                due_at: None,
-                created_at: plan.created_at,
-                updated_at: plan.updated_at,
-                requires_human_action: true,
-                origin_ref: format!("plan:{}", plan.id),
-                dependency_refs: vec![],
-                t_shirt_size: None,
-                story_points: None,
-                estimate_optimistic_minutes: None,
-                estimate_likely_minutes: None,
-                estimate_pessimistic_minutes: None,
-            });
-        }
-    }
+#[derive(Deserialize)]
+struct ProjectsQuery {
+    user_id: String,
+}
 
-    for item in &mut items {
-        if let Some(status) = persisted_statuses.get(&item.origin_ref) {
-            item.status = status.clone();
-        }
-    }
+#[derive(Serialize)]
+struct ProjectsResponse {
+    projects: Vec<ProjectSummary>,
+}
 
-    let plan_step_refs = items
-        .iter()
-        .filter(|item| item.source_type == "plan_step")
-        .map(|item| item.origin_ref.to_ascii_lowercase())
-        .collect::<HashSet<_>>();
+#[derive(Deserialize)]
+struct DashboardQuery {
+    user_id: String,
+}
 
-    items.retain(|item| {
-        !(item.source_type == "todo"
-            && extract_plan_step_ref_from_details(item.details.as_deref())
-                .map(|origin_ref| plan_step_refs.contains(&origin_ref))
-                .unwrap_or(false))
-    });
+#[derive(Serialize)]
+struct EstimateAccuracyResponse {
+    /// Overall (`all`) first, then per category.
+    categories: Vec<EstimateAccuracy>,
+}
 
-    let mut seen_origin_refs = HashSet::new();
-    items.retain(|item| seen_origin_refs.insert(item.origin_ref.clone()));
+#[derive(Deserialize)]
+struct ArchiveAuditRequest {
+    user_id: String,
+    /// Only events at or after this timestamp; everything when absent.
+    since: Option<i64>,
+}
 
-    if !include_done {
-        items.retain(|item| item.status != "done" && item.status != "dismissed");
-    }
+#[derive(Serialize)]
+struct ArchiveAuditResponse {
+    status: String,
+    key: Option<String>,
+    events: usize,
+}
 
-    items.sort_by(|a, b| {
-        priority_rank(&a.priority)
-            .cmp(&priority_rank(&b.priority))
-            .then_with(|| {
-                a.due_at
-                    .unwrap_or(i64::MAX)
-                    .cmp(&b.due_at.unwrap_or(i64::MAX))
-            })
-            .then_with(|| b.created_at.cmp(&a.created_at))
-    });
+#[derive(Deserialize)]
+struct CalendarSyncRequest {
+    user_id: String,
+}
 
-    if items.len() > limit {
-        items.truncate(limit);
-    }
+#[derive(Serialize)]
+struct CalendarSyncResponse {
+    reports: Vec<SyncReport>,
+}
 
-    Ok(items)
+#[derive(Deserialize)]
+struct EmailPollRequest {
+    user_id: String,
 }
 
-async fn solana_wallet(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<SolanaWalletQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct EmailPollResponse {
+    reports: Vec<IngestReport>,
+}
 
-    let actor = query.actor.unwrap_or_else(|| "agent".to_string());
+#[derive(Deserialize)]
+struct CalendarFeedRequest {
+    user_id: String,
+    /// Issue a new token, cutting off existing subscribers.
+    #[serde(default)]
+    rotate: bool,
+}
 
-    match crate::security::solana_signer::wallet_address(&query.user_id, &actor) {
-        Ok(address) => (
-            StatusCode::OK,
-            Json(SolanaWalletResponse {
-                user_id: query.user_id,
-                actor,
-                address,
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct CalendarFeedResponse {
+    path: String,
 }
 
-async fn solana_balance(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<SolanaBalanceQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Deserialize)]
+struct AuditEventsQuery {
+    user_id: Option<String>,
+    limit: Option<usize>,
+    /// Comma-separated list of event types.
+    event_type: Option<String>,
+    origin_ref: Option<String>,
+    severity: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    before_id: Option<i32>,
+}
 
-    let policy = match load_solana_rpc_policy(&state) {
-        Ok(policy) => policy,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
-    let endpoint = match require_solana_rpc_endpoint(&policy) {
-        Ok(endpoint) => endpoint,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Deserialize)]
+struct DigestPreviewQuery {
+    user_id: String,
+}
 
-    let address =
-        match resolve_query_or_wallet_address(query.address, query.user_id, query.actor, "agent") {
-            Ok(address) => address,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        };
+#[derive(Serialize)]
+struct DigestPreviewResponse {
+    text: String,
+    counts: HashMap<&'static str, usize>,
+    scheduled: bool,
+}
 
-    match crate::solana_rpc::get_balance(&endpoint, &address, &policy.commitment).await {
-        Ok(lamports) => (
-            StatusCode::OK,
-            Json(SolanaBalanceResponse {
-                address,
-                lamports,
-                sol: lamports as f64 / 1_000_000_000f64,
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Deserialize)]
+struct CatchUpQuery {
+    user_id: String,
+    since: i64,
 }
 
-async fn solana_transfer(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<SolanaTransferRequest>,
-) -> impl IntoResponse {
-    execute_solana_transfer(state, headers, payload, false).await
+#[derive(Deserialize)]
+struct ActivityHeatmapQuery {
+    user_id: String,
+    days: Option<u32>,
 }
 
-async fn solana_simulate_transfer(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<SolanaTransferRequest>,
-) -> impl IntoResponse {
-    execute_solana_transfer(state, headers, payload, true).await
+#[derive(Deserialize)]
+struct InboxQuery {
+    user_id: String,
+    limit: Option<usize>,
+    include_done: Option<bool>,
+    /// Keep only todos, plans, tasks and reminders in this project.
+    project_id: Option<i32>,
 }
 
-async fn execute_solana_transfer(
-    state: AppState,
-    headers: HeaderMap,
-    payload: SolanaTransferRequest,
-    force_simulation_only: bool,
-) -> Response {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Deserialize)]
+struct IssueUserTokenRequest {
+    user_id: String,
+    label: Option<String>,
+}
 
-    let actor = payload.actor.unwrap_or_else(|| "agent".to_string());
-    let signer_preview = match state.signer_service.process(SignerRequest::Preview {
-        intent: Box::new(SigningIntent {
-            request_id: payload.request_id.clone(),
-            actor: actor.clone(),
-            user_id: payload.user_id.clone(),
-            action_type: "solana_transfer".to_string(),
-            amount_atomic: payload.lamports,
-            payee: payload
-                .payee
-                .clone()
-                .unwrap_or_else(|| "merchant.local".to_string()),
-            context_requires_approval: false,
-            scheme_id: None,
-            chain_id: Some("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp".to_string()),
-            payment_authority: None,
-            idempotency_key: Some(payload.request_id.clone()),
-        }),
-    }) {
-        Ok(response) => response,
-        Err(err) => {
-            return (
-                StatusCode::FORBIDDEN,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Deserialize)]
+struct UserTokenRequest {
+    user_id: String,
+    token_id: i32,
+}
 
-    if signer_preview.status != "approved" {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(json!({
-                "error": "transfer requires approval",
-                "signer": signer_preview,
-            })),
-        )
-            .into_response();
-    }
+#[derive(Deserialize)]
+struct UserTokensQuery {
+    user_id: String,
+    include_revoked: Option<bool>,
+}
 
-    let policy = match load_solana_rpc_policy(&state) {
-        Ok(policy) => policy,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
-    let endpoint = match require_solana_rpc_endpoint(&policy) {
-        Ok(endpoint) => endpoint,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Serialize)]
+struct UserTokensResponse {
+    tokens: Vec<UserToken>,
+}
 
-    let from_seed = match crate::security::solana_signer::signing_seed(&payload.user_id, &actor) {
-        Ok(seed) => seed,
-        Err(err) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Deserialize)]
+struct SetUserRoleRequest {
+    user_id: String,
+    role: String,
+}
 
-    let latest_blockhash =
-        match crate::solana_rpc::get_latest_blockhash(&endpoint, &policy.commitment).await {
-            Ok(hash) => hash,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_GATEWAY,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        };
+#[derive(Deserialize)]
+struct RemoveUserRoleRequest {
+    user_id: String,
+}
 
-    let probe_unit_limit = crate::solana_rpc::probe_compute_unit_limit(&policy);
-    let (probe_tx_base64, wallet_address) =
-        match crate::solana_rpc::build_transfer_transaction_base64_with_unit_limit(
-            &from_seed,
-            &payload.to,
-            payload.lamports,
-            &latest_blockhash,
-            &policy,
-            probe_unit_limit,
-        ) {
-            Ok(tuple) => tuple,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        };
+#[derive(Serialize)]
+struct UserRolesResponse {
+    default_role: Role,
+    roles: Vec<UserRole>,
+}
 
-    let probe_simulation =
-        match crate::solana_rpc::simulate_transaction(&endpoint, &probe_tx_base64, &policy).await {
-            Ok(value) => value,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_GATEWAY,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        };
+#[derive(Deserialize)]
+struct CreateSecretRequest {
+    name: String,
+    value: String,
+    description: Option<String>,
+}
 
-    let adjusted_unit_limit = crate::solana_rpc::recommended_compute_unit_limit(
-        &probe_simulation,
-        policy.compute_budget.unit_limit,
-    );
+#[derive(Deserialize)]
+struct RotateSecretRequest {
+    name: String,
+    value: String,
+}
 
-    let tx_base64 = if adjusted_unit_limit == probe_unit_limit {
-        probe_tx_base64
-    } else {
-        match crate::solana_rpc::build_transfer_transaction_base64_with_unit_limit(
-            &from_seed,
-            &payload.to,
-            payload.lamports,
-            &latest_blockhash,
-            &policy,
-            adjusted_unit_limit,
-        ) {
-            Ok((tx, _)) => tx,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        }
-    };
+#[derive(Deserialize)]
+struct DeleteSecretRequest {
+    name: String,
+}
 
-    let simulate_only = force_simulation_only || payload.simulate_only.unwrap_or(false);
-    let simulation_result = Some(probe_simulation);
+#[derive(Serialize)]
+struct SecretsResponse {
+    secrets: Vec<vault::SecretMetadata>,
+}
 
-    if simulate_only {
-        return (
-            StatusCode::OK,
-            Json(SolanaTransferResponse {
-                status: "simulated".to_string(),
-                request_id: payload.request_id,
-                wallet_address,
-                signer_reason_code: signer_preview.reason_code,
-                simulation: simulation_result,
-                signature: None,
-            }),
-        )
-            .into_response();
-    }
+#[derive(Deserialize)]
+struct InboxTransitionRequest {
+    user_id: String,
+    origin_ref: String,
+    action: String,
+}
 
-    if let Err(err) = state.signer_service.process(SignerRequest::Sign {
-        request_id: payload.request_id.clone(),
-    }) {
-        return (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response();
-    }
+#[derive(Deserialize)]
+struct InboxCommentsQuery {
+    user_id: String,
+    origin_ref: String,
+}
 
-    match crate::solana_rpc::send_transaction(&endpoint, &tx_base64, &policy).await {
-        Ok(signature) => (
-            StatusCode::OK,
-            Json(SolanaTransferResponse {
-                status: "submitted".to_string(),
-                request_id: payload.request_id,
-                wallet_address,
-                signer_reason_code: signer_preview.reason_code,
-                simulation: simulation_result,
-                signature: Some(signature),
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Deserialize)]
+struct InboxCommentRequest {
+    user_id: String,
+    origin_ref: String,
+    body: String,
 }
 
-async fn solana_tx_status(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<SolanaTxStatusQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Deserialize)]
+struct AttachmentsQuery {
+    user_id: String,
+    origin_ref: String,
+}
 
-    let policy = match load_solana_rpc_policy(&state) {
-        Ok(policy) => policy,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
-    let endpoint = match require_solana_rpc_endpoint(&policy) {
-        Ok(endpoint) => endpoint,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Deserialize)]
+struct AttachmentUploadQuery {
+    user_id: String,
+    origin_ref: String,
+    name: String,
+}
 
-    match crate::solana_rpc::get_signature_status(&endpoint, &query.signature).await {
-        Ok(value) => (
-            StatusCode::OK,
-            Json(SolanaTxStatusResponse {
-                signature: query.signature,
-                value,
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Deserialize)]
+struct AttachmentDownloadQuery {
+    user_id: String,
 }
 
-async fn solana_tx_history(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<SolanaTxHistoryQuery>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Deserialize)]
+struct DeleteAttachmentRequest {
+    user_id: String,
+    id: i32,
+}
 
-    let policy = match load_solana_rpc_policy(&state) {
-        Ok(policy) => policy,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
-    let endpoint = match require_solana_rpc_endpoint(&policy) {
-        Ok(endpoint) => endpoint,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response()
-        }
-    };
+#[derive(Serialize)]
+struct AttachmentsResponse {
+    origin_ref: String,
+    attachments: Vec<Attachment>,
+}
 
-    let address =
-        match resolve_query_or_wallet_address(query.address, query.user_id, query.actor, "agent") {
-            Ok(address) => address,
-            Err(err) => {
-                return (
-                    StatusCode::BAD_REQUEST,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response()
-            }
-        };
+#[derive(Serialize)]
+struct DeleteAttachmentResponse {
+    deleted: bool,
+}
 
-    match crate::solana_rpc::get_signatures_for_address(
-        &endpoint,
-        &address,
-        query.limit.unwrap_or(20),
-    )
-    .await
-    {
-        Ok(entries) => (
-            StatusCode::OK,
-            Json(SolanaTxHistoryResponse { address, entries }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::BAD_GATEWAY,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct InboxCommentsResponse {
+    origin_ref: String,
+    comments: Vec<InboxComment>,
 }
 
-async fn x402_preview(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<X402PreviewRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct InboxTransitionResponse {
+    status: String,
+    origin_ref: String,
+    previous_status: String,
+    next_status: String,
+}
 
-    let context_requires_approval = payload.context_requires_approval.unwrap_or(false);
+#[derive(Deserialize)]
+struct InboxBulkRequest {
+    user_id: String,
+    origin_refs: Vec<String>,
+    /// `done`, `snooze` or `acknowledge`.
+    action: String,
+}
 
-    let (canonical_intent, signing_intent) = match canonicalize_payment_required(
-        &payload.request_id,
-        &payload.actor,
-        &payload.user_id,
-        &payload.payment_required,
-        payload.merchant_origin.as_deref(),
-        context_requires_approval,
-        payload.idempotency_key.as_deref(),
-    ) {
-        Ok(value) => value,
-        Err(err) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: err.to_string(),
-                }),
-            )
-                .into_response();
-        }
-    };
-
-    match state.signer_service.process(SignerRequest::Preview {
-        intent: Box::new(signing_intent),
-    }) {
-        Ok(signer_response) => (
-            StatusCode::OK,
-            Json(X402PreviewResponse {
-                canonical_intent,
-                signer: signer_response,
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct InboxBulkSkip {
+    origin_ref: String,
+    reason: String,
 }
 
-async fn signer_preview(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(intent): Json<SigningIntent>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
-
-    match state.signer_service.process(SignerRequest::Preview {
-        intent: Box::new(intent),
-    }) {
-        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
-        Err(err) => (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct InboxBulkResponse {
+    action: String,
+    applied: Vec<InboxTransitionResponse>,
+    skipped: Vec<InboxBulkSkip>,
 }
 
-async fn signer_approve(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<SignerIdRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize, Clone)]
+struct InboxItemResponse {
+    id: String,
+    source_type: String,
+    source_id: i32,
+    title: String,
+    details: Option<String>,
+    owner: String,
+    status: String,
+    priority: String,
+    due_at: Option<i64>,
+    created_at: i64,
+    updated_at: i64,
+    requires_human_action: bool,
+    origin_ref: String,
+    dependency_refs: Vec<String>,
+    t_shirt_size: Option<String>,
+    story_points: Option<i32>,
+    estimate_optimistic_minutes: Option<i32>,
+    estimate_likely_minutes: Option<i32>,
+    estimate_pessimistic_minutes: Option<i32>,
+    linked_reminders: Vec<LinkedReminderResponse>,
+    held_until: Option<i64>,
+    labels: Vec<String>,
+    project_id: Option<i32>,
+    /// Notes from the human and the agent, oldest first.
+    comments: Vec<InboxComment>,
+    attachments: Vec<Attachment>,
+}
 
-    match state.signer_service.process(SignerRequest::Approve {
-        request_id: payload.request_id,
-    }) {
-        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
-        Err(err) => (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize, Clone)]
+struct LinkedReminderResponse {
+    id: i32,
+    title: String,
+    due_at: i64,
 }
 
-async fn signer_sign(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<SignerIdRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct CatchUpEntry {
+    origin_ref: Option<String>,
+    title: String,
+    detail: Option<String>,
+    at: i64,
+}
 
-    match state.signer_service.process(SignerRequest::Sign {
-        request_id: payload.request_id,
-    }) {
-        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
-        Err(err) => (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct CatchUpResponse {
+    since: i64,
+    until: i64,
+    /// One-line recap for banners; empty when nothing changed.
+    summary: String,
+    /// Longer recap handed to the agent as an additional prompt so it can
+    /// open the next conversation with what actually happened.
+    agent_context: String,
+    new_items: Vec<CatchUpEntry>,
+    completed_items: Vec<CatchUpEntry>,
+    fired_reminders: Vec<CatchUpEntry>,
+    agent_actions: Vec<CatchUpEntry>,
+    payments: Vec<CatchUpEntry>,
 }
 
-async fn signer_deny(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<SignerIdRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct InboxResponse {
+    items: Vec<InboxItemResponse>,
+}
 
-    match state.signer_service.process(SignerRequest::Deny {
-        request_id: payload.request_id,
-    }) {
-        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
-        Err(err) => (
-            StatusCode::FORBIDDEN,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct SmartListResponse {
+    key: String,
+    label: String,
+    items: Vec<InboxItemResponse>,
 }
 
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        status: "ok".to_string(),
-    })
+#[derive(Serialize)]
+struct SmartListsResponse {
+    lists: Vec<SmartListResponse>,
 }
 
-async fn doctor(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct InboxActionableCountResponse {
+    actionable_count: usize,
+}
 
-    let checks = run_doctor_checks(&state).await;
-    let has_fail = checks.iter().any(|check| check.status == "fail");
-    let has_warn = checks.iter().any(|check| check.status == "warn");
-    let overall = if has_fail {
-        "fail"
-    } else if has_warn {
-        "warn"
-    } else {
-        "pass"
-    };
+#[derive(Serialize)]
+struct ReminderDeliveryEventsResponse {
+    events: Vec<Value>,
+}
 
-    (
-        StatusCode::OK,
-        Json(DoctorResponse {
-            overall: overall.to_string(),
-            checks,
-        }),
-    )
-        .into_response()
+#[derive(Serialize)]
+struct AuditEventsResponse {
+    events: Vec<AuditEvent>,
+    next_before_id: Option<i32>,
 }
 
-async fn security_audit(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct MemorySearchResponse {
+    results: Vec<String>,
+}
 
-    let findings = run_security_audit_checks(&state).await;
-    let overall = highest_severity(&findings);
+#[derive(Serialize)]
+struct SemanticMemorySearchResponse {
+    results: Vec<crate::interfaces::providers::MemoryHit>,
+}
 
-    (
-        StatusCode::OK,
-        Json(SecurityAuditResponse { overall, findings }),
-    )
-        .into_response()
+#[derive(Serialize)]
+struct ChatHistoryResponse {
+    history: Vec<String>,
 }
 
-fn doctor_check(name: &str, status: &str, message: String, fix_hint: Option<&str>) -> DoctorCheck {
-    DoctorCheck {
-        name: name.to_string(),
-        status: status.to_string(),
-        message,
-        fix_hint: fix_hint.map(str::to_string),
-    }
+#[derive(Serialize)]
+struct ClearHistoryResponse {
+    status: String,
+    message: String,
 }
 
-fn security_finding(
-    id: &str,
-    severity: &str,
-    status: &str,
+#[derive(Serialize)]
+struct ClearUserDataResponse {
+    status: String,
     message: String,
-    fix_hint: Option<&str>,
-    auto_fixable: bool,
-) -> SecurityAuditFinding {
-    SecurityAuditFinding {
-        id: id.to_string(),
-        severity: severity.to_string(),
-        status: status.to_string(),
-        message,
-        fix_hint: fix_hint.map(str::to_string),
-        auto_fixable,
-    }
+    cleared: Value,
 }
 
-fn severity_rank(severity: &str) -> u8 {
-    match severity {
-        "critical" => 4,
-        "high" => 3,
-        "medium" => 2,
-        _ => 1,
+/// A consent record with its log line.
+#[derive(Serialize)]
+struct ConsentLogEntry {
+    #[serde(flatten)]
+    record: ConsentRecord,
+    summary: String,
+    active: bool,
+}
+
+impl ConsentLogEntry {
+    fn new(record: ConsentRecord, now: i64) -> Self {
+        Self {
+            summary: record.summary(now),
+            active: record.is_active(now),
+            record,
+        }
     }
 }
 
-fn highest_severity(findings: &[SecurityAuditFinding]) -> String {
-    findings
-        .iter()
-        .filter(|finding| finding.status != "pass")
-        .max_by_key(|finding| severity_rank(&finding.severity))
-        .map(|finding| finding.severity.clone())
-        .unwrap_or_else(|| "low".to_string())
+#[derive(Serialize)]
+struct ConsentLogResponse {
+    entries: Vec<ConsentLogEntry>,
 }
 
-async fn run_security_audit_checks(state: &AppState) -> Vec<SecurityAuditFinding> {
-    let mut findings = Vec::new();
+#[derive(Serialize)]
+struct TrashResponse {
+    batches: Vec<TrashBatch>,
+    retention_days: u64,
+}
 
-    if state.token.trim().is_empty() {
-        findings.push(security_finding(
-            "daemon_auth_token",
-            "medium",
-            "warn",
-            "Daemon auth token is empty; this is unexpected because token bootstrap is automatic and protected endpoints fail closed."
-                .to_string(),
-            Some("Restart the app/daemon to re-run token bootstrap and verify keyring/secret-store availability."),
-            false,
-        ));
-    } else {
-        findings.push(security_finding(
-            "daemon_auth_token",
-            "low",
-            "pass",
-            "Daemon auth token is configured.".to_string(),
-            None,
-            false,
-        ));
-    }
+#[derive(Serialize)]
+struct TrashRestoreResponse {
+    status: String,
+    store: String,
+    batch_id: String,
+    restored: usize,
+}
 
-    match Config::from_store(&state.db_path) {
-        Ok(config) => {
-            findings.push(security_finding(
-                "config_load",
-                "low",
-                "pass",
-                "Config loaded from store/keyring.".to_string(),
-                None,
-                false,
-            ));
+#[derive(Serialize)]
+struct SearchResponse {
+    query: String,
+    indexed: bool,
+    hits: Vec<SearchHit>,
+}
 
-            let root = json!({ "tools": config.tools.clone().unwrap_or(Value::Null) });
-            let sandbox = SandboxSettings::from_root_config(&root);
+#[derive(Serialize)]
+struct TemplateImportResponse {
+    status: String,
+    name: String,
+    imported: ImportSummary,
+}
 
-            let built_in_tools = [
-                "coding",
-                "mcp",
-                "http_call",
-                "github",
-                "zapier",
-                "planning",
-                "reminders",
-                "search_internet",
-                "tasks",
-                "todo",
-                "wakeup",
-            ];
-            let mut non_wasm_tools = Vec::new();
-            for tool_name in built_in_tools {
-                let plan = sandbox.execution_plan(tool_name);
-                if plan.runtime != ToolRuntime::Wasm {
-                    non_wasm_tools.push(tool_name);
-                }
-            }
+#[derive(Serialize)]
+struct ApprovalDecisionResponse {
+    status: String,
+    approval: PendingApproval,
+    /// The approval chain, when the call needed one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    steps: Vec<ApprovalStep>,
+}
 
-            if non_wasm_tools.is_empty() {
-                findings.push(security_finding(
-                    "tool_runtime_invariant",
-                    "low",
-                    "pass",
-                    "All built-in tools resolve to WASM runtime.".to_string(),
-                    None,
-                    false,
-                ));
-            } else {
-                findings.push(security_finding(
-                    "tool_runtime_invariant",
-                    "high",
-                    "fail",
-                    format!(
-                        "Non-WASM tool runtime detected for: {}.",
-                        non_wasm_tools.join(", ")
-                    ),
-                    Some(
-                        "Enforce WASM-only execution in sandbox settings and tool runtime planner.",
-                    ),
-                    false,
-                ));
-            }
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
 
-            let default_deny = config
-                .tools
-                .as_ref()
-                .and_then(|tools| tools.get("settings"))
-                .and_then(|settings| settings.get("permissions"))
-                .and_then(|permissions| permissions.get("default_deny"))
-                .and_then(|value| value.as_bool())
-                .unwrap_or(false);
+#[derive(Deserialize)]
+struct SignerIdRequest {
+    request_id: String,
+}
 
-            if default_deny {
-                findings.push(security_finding(
-                    "network_default_deny",
-                    "low",
-                    "pass",
-                    "Global tools network policy uses default_deny=true.".to_string(),
-                    None,
-                    false,
-                ));
-            } else {
-                findings.push(security_finding(
-                    "network_default_deny",
-                    "medium",
-                    "warn",
-                    "Global tools network policy default_deny is disabled or missing."
-                        .to_string(),
-                    Some("Set tools.settings.permissions.default_deny to true and allowlist required domains."),
-                    false,
-                ));
-            }
-        }
-        Err(err) => {
-            findings.push(security_finding(
-                "config_load",
-                "critical",
-                "fail",
-                format!("Config load failed: {err}"),
-                Some("Save a valid config in Config tab and rerun security audit."),
-                false,
-            ));
-        }
-    }
+#[derive(Deserialize)]
+struct X402PreviewRequest {
+    request_id: String,
+    actor: String,
+    user_id: String,
+    payment_required: Value,
+    merchant_origin: Option<String>,
+    context_requires_approval: Option<bool>,
+    idempotency_key: Option<String>,
+}
 
-    findings
+#[derive(Serialize)]
+struct X402PreviewResponse {
+    canonical_intent: crate::security::x402::CanonicalX402Intent,
+    signer: crate::security::signer_daemon::SignerResponse,
 }
 
-async fn run_doctor_checks(state: &AppState) -> Vec<DoctorCheck> {
-    let mut checks = Vec::new();
+#[derive(Deserialize)]
+struct SolanaWalletQuery {
+    user_id: String,
+    actor: Option<String>,
+}
 
-    if state.token.trim().is_empty() {
-        checks.push(doctor_check(
-            "daemon_auth_token",
-            "warn",
-            "Daemon auth token is empty; this is unexpected because token bootstrap is automatic and protected endpoints fail closed."
-                .to_string(),
-            Some("Restart the app/daemon to re-run token bootstrap and verify keyring/secret-store availability."),
-        ));
-    } else {
-        checks.push(doctor_check(
-            "daemon_auth_token",
-            "pass",
-            "Daemon auth token is configured.".to_string(),
-            None,
-        ));
-    }
+#[derive(Serialize)]
+struct SolanaWalletResponse {
+    user_id: String,
+    actor: String,
+    address: String,
+}
 
-    match Config::from_store(&state.db_path) {
-        Ok(config) => {
-            checks.push(doctor_check(
-                "config_store",
-                "pass",
-                "Config loaded from store/keyring.".to_string(),
-                None,
-            ));
+#[derive(Deserialize)]
+struct SolanaBalanceQuery {
+    address: Option<String>,
+    user_id: Option<String>,
+    actor: Option<String>,
+}
 
-            match config.clone().resolve_vault() {
-                Ok(_) => {
-                    checks.push(doctor_check(
-                        "vault_resolution",
-                        "pass",
-                        "Vault-backed secrets resolved successfully.".to_string(),
-                        None,
-                    ));
-                }
-                Err(err) => {
-                    checks.push(doctor_check(
-                        "vault_resolution",
-                        "fail",
-                        format!("Vault resolution failed: {err}"),
-                        Some("Verify OS keychain access and required secret keys."),
-                    ));
-                }
-            }
+fn asks_for_wallet_address_only(text: &str) -> bool {
+    let normalized = text.to_ascii_lowercase();
+    let asks_for_address = normalized.contains("wallet address")
+        || normalized.contains("my address")
+        || (normalized.contains("wallet") && normalized.contains("address"));
 
-            let mode = crate::security::tpm_provider::tpm_mode();
-            let tpm_available = crate::security::tpm_provider::tpm_available();
-            if tpm_available {
-                checks.push(doctor_check(
-                    "security_tpm_mode",
-                    "pass",
-                    format!("TPM capability available (mode={mode})."),
-                    None,
-                ));
-                checks.push(doctor_check(
-                    "solana_custody",
-                    "pass",
-                    "Secure Solana wallet custody/signing backend is available.".to_string(),
-                    None,
-                ));
-            } else if mode == "strict" {
-                checks.push(doctor_check(
-                    "security_tpm_mode",
-                    "fail",
-                    "TPM unavailable while strict mode is enabled.".to_string(),
-                    Some("Switch TPM mode to auto/compatible or restore TPM availability."),
-                ));
-                checks.push(doctor_check(
-                    "solana_custody",
-                    "warn",
-                    "Solana signing/custody is disabled because secure key backend is unavailable in strict mode."
-                        .to_string(),
-                    Some("Use a machine with TPM support or relax TPM mode for compatibility."),
-                ));
-            } else {
-                checks.push(doctor_check(
-                    "security_tpm_mode",
-                    "warn",
-                    format!(
-                        "TPM unavailable; running in compatibility path (mode={mode}) with degraded hardware binding."
-                    ),
-                    Some("Use strict mode on TPM-capable hardware for strongest key protection."),
-                ));
-                checks.push(doctor_check(
-                    "solana_custody",
-                    "warn",
-                    "Solana signing/custody is degraded or disabled without hardware-backed key protection."
-                        .to_string(),
-                    Some("For production custody, run on TPM-capable hardware in strict mode."),
-                ));
-            }
-        }
-        Err(err) => {
-            checks.push(doctor_check(
-                "config_store",
-                "fail",
-                format!("Config load failed: {err}"),
-                Some("Save a valid config in the Config tab and retry."),
-            ));
-            checks.push(doctor_check(
-                "vault_resolution",
-                "warn",
-                "Skipped because config could not be loaded.".to_string(),
-                Some("Fix config_store check first."),
-            ));
-            checks.push(doctor_check(
-                "security_tpm_mode",
-                "warn",
-                "Skipped because config could not be loaded.".to_string(),
-                Some("Fix config_store check first."),
-            ));
-            checks.push(doctor_check(
-                "solana_custody",
-                "warn",
-                "Skipped because config could not be loaded.".to_string(),
-                Some("Fix config_store check first."),
-            ));
-        }
-    }
+    asks_for_address
+        && !normalized.contains("balance")
+        && !normalized.contains("transfer")
+        && !normalized.contains("send")
+        && !normalized.contains("transaction")
+        && !normalized.contains("tx")
+        && !normalized.contains("history")
+}
 
-    let db_path = state.db_path.clone();
-    let db_check = tokio::task::spawn_blocking(move || -> DoctorCheck {
-        if let Err(err) = crate::config_store::ensure_parent_dir(&db_path) {
-            return doctor_check(
-                "database_access",
-                "fail",
-                format!("Database directory check failed: {err}"),
-                Some("Verify filesystem permissions for DB path."),
-            );
-        }
+fn asks_for_wallet_balance_only(text: &str) -> bool {
+    let normalized = text.to_ascii_lowercase();
+    let asks_for_balance = normalized.contains("balance") || normalized.contains("lamports");
+    let in_solana_context = normalized.contains("solana") || normalized.contains("wallet");
 
-        let mut conn = match SqliteConnection::establish(&db_path) {
-            Ok(conn) => conn,
-            Err(err) => {
-                return doctor_check(
-                    "database_access",
-                    "fail",
-                    format!("Database open failed: {err}"),
-                    Some("Verify DB path and SQLite/SQLCipher availability."),
-                )
-            }
-        };
+    asks_for_balance
+        && in_solana_context
+        && !normalized.contains("transfer")
+        && !normalized.contains("send")
+        && !normalized.contains("transaction")
+        && !normalized.contains("tx")
+        && !normalized.contains("history")
+}
 
-        if let Err(err) = crate::db::apply_sqlcipher_key_sync(&mut conn) {
-            return doctor_check(
-                "database_access",
-                "fail",
-                format!("Database key apply failed: {err}"),
-                Some("Verify secure secret storage availability for db_encryption_key."),
-            );
-        }
+async fn solana_balance_line_for_user(state: &AppState, user_id: &str) -> Result<String> {
+    let policy = load_solana_rpc_policy(state)?;
+    let endpoint = require_solana_rpc_endpoint(&policy)?;
+    let address = crate::security::solana_signer::wallet_address(user_id, "agent")?;
+    let lamports = crate::solana_rpc::get_balance(&endpoint, &address, &policy.commitment).await?;
+    let sol = lamports as f64 / 1_000_000_000f64;
 
-        let probe_result = diesel::sql_query(
-            "CREATE TABLE IF NOT EXISTS doctor_probe (id INTEGER PRIMARY KEY, ts INTEGER NOT NULL)",
-        )
-        .execute(&mut conn);
+    Ok(format!(
+        "Your Solana balance is {:.9} SOL ({} lamports).",
+        sol, lamports
+    ))
+}
 
-        match probe_result {
-            Ok(_) => doctor_check(
-                "database_access",
-                "pass",
-                "Database opened and write probe succeeded.".to_string(),
-                None,
-            ),
-            Err(err) => doctor_check(
-                "database_access",
-                "fail",
-                format!("Database write probe failed: {err}"),
-                Some("Verify DB permissions and SQLCipher key configuration."),
-            ),
-        }
-    })
-    .await;
+#[derive(Serialize)]
+struct SolanaBalanceResponse {
+    address: String,
+    lamports: u64,
+    sol: f64,
+}
 
-    match db_check {
-        Ok(check) => checks.push(check),
-        Err(err) => checks.push(doctor_check(
-            "database_access",
-            "fail",
-            format!("Database check task failed: {err}"),
-            Some("Retry diagnostics; if persistent, inspect runtime logs."),
-        )),
-    }
+#[derive(Deserialize)]
+struct SolanaTransferRequest {
+    request_id: String,
+    user_id: String,
+    actor: Option<String>,
+    to: String,
+    lamports: u64,
+    payee: Option<String>,
+    simulate_only: Option<bool>,
+}
 
-    checks
+#[derive(Serialize)]
+struct SolanaTransferResponse {
+    status: String,
+    request_id: String,
+    wallet_address: String,
+    signer_reason_code: String,
+    simulation: Option<Value>,
+    signature: Option<String>,
 }
 
-async fn process_text(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<ProcessTextRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Deserialize)]
+struct SolanaTxStatusQuery {
+    signature: String,
+}
 
-    if asks_for_wallet_address_only(&payload.text) {
-        match crate::security::solana_signer::wallet_address(&payload.user_id, "agent") {
-            Ok(address) => {
-                return (
-                    StatusCode::OK,
-                    Json(ProcessTextResponse {
-                        text: format!("Your Solana wallet address is {address}."),
-                    }),
-                )
-                    .into_response();
-            }
-            Err(err) => {
-                return (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ErrorResponse {
-                        error: err.to_string(),
-                    }),
-                )
-                    .into_response();
-            }
-        }
-    }
+#[derive(Serialize)]
+struct SolanaTxStatusResponse {
+    signature: String,
+    value: Value,
+}
 
-    if asks_for_wallet_balance_only(&payload.text) {
-        let balance_line = match solana_balance_line_for_user(&state, &payload.user_id).await {
-            Ok(line) => line,
-            Err(err) => format!("I couldn't fetch your Solana balance right now: {err}"),
-        };
+#[derive(Deserialize)]
+struct SolanaTxHistoryQuery {
+    address: Option<String>,
+    user_id: Option<String>,
+    actor: Option<String>,
+    limit: Option<usize>,
+}
 
-        return (
-            StatusCode::OK,
-            Json(ProcessTextResponse { text: balance_line }),
-        )
-            .into_response();
-    }
+#[derive(Serialize)]
+struct SolanaTxHistoryResponse {
+    address: String,
+    entries: Value,
+}
 
-    let options = ProcessOptions {
-        prompt: payload.prompt.clone(),
-        images: Vec::new(),
-        output_format: OutputFormat::Text,
-        image_detail: "auto".to_string(),
-        json_schema: None,
-    };
+#[derive(Serialize, Clone)]
+struct DoctorCheck {
+    name: String,
+    status: String,
+    message: String,
+    fix_hint: Option<String>,
+}
 
-    let agent = state.agent.read().await.clone();
-    let response = agent
-        .process(&payload.user_id, UserInput::Text(payload.text), options)
-        .await;
+#[derive(Serialize)]
+struct CapabilitiesResponse {
+    /// Capability ABI the host speaks.
+    abi_version: u32,
+    tools: Vec<ToolDescriptor>,
+}
 
-    match response {
-        Ok(ProcessResult::Text(text)) => {
-            (StatusCode::OK, Json(ProcessTextResponse { text })).into_response()
-        }
-        Ok(other) => (
-            StatusCode::BAD_REQUEST,
-            Json(ErrorResponse {
-                error: format!("Unexpected response: {other:?}"),
-            }),
-        )
-            .into_response(),
-        Err(err) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response(),
-    }
+#[derive(Serialize)]
+struct ToolPostureResponse {
+    tools: Vec<ToolPosture>,
 }
 
-async fn process_text_stream(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<ProcessTextRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
+#[derive(Serialize)]
+struct BackupStatusResponse {
+    enabled: bool,
+    interval_hours: u64,
+    keep: usize,
+    directory: String,
+    /// `None` until a pass has run since the daemon started.
+    last_run: Option<BackupRun>,
+    /// Local snapshots, newest first.
+    snapshots: Vec<Snapshot>,
+    replication_enabled: bool,
+    /// Latest replication pass, when replication has run.
+    replication: Option<ReplicationRun>,
+}
 
-    let agent = state.agent.read().await.clone();
-    let ProcessTextRequest {
-        user_id,
-        text,
-        prompt,
-    } = payload;
+#[derive(Serialize)]
+struct CapabilityReportResponse {
+    consistent: bool,
+    mismatches: Vec<String>,
+    capabilities: Vec<CapabilityCoverage>,
+}
 
-    if asks_for_wallet_address_only(&text) {
-        let wallet_line = match crate::security::solana_signer::wallet_address(&user_id, "agent") {
-            Ok(address) => format!("Your Solana wallet address is {address}."),
-            Err(err) => format!("[error] {}", err),
-        };
+#[derive(Serialize)]
+struct DoctorResponse {
+    overall: String,
+    checks: Vec<DoctorCheck>,
+}
 
-        let body = Body::from(wallet_line);
-        return Response::builder()
-            .status(StatusCode::OK)
-            .header("content-type", "text/plain; charset=utf-8")
-            .body(body)
-            .unwrap();
-    }
+#[derive(Serialize)]
+struct SchemaDriftEntry {
+    db_path: String,
+    drift: Vec<crate::schema_drift::Drift>,
+}
 
-    if asks_for_wallet_balance_only(&text) {
-        let balance_line = match solana_balance_line_for_user(&state, &user_id).await {
-            Ok(line) => line,
-            Err(err) => format!("I couldn't fetch your Solana balance right now: {err}"),
-        };
+#[derive(Serialize)]
+struct SchedulerJobEntry {
+    #[serde(flatten)]
+    job: crate::scheduler::state::JobState,
+    /// A whole interval has passed since the job was due without it running.
+    late: bool,
+}
 
-        let body = Body::from(balance_line);
-        return Response::builder()
-            .status(StatusCode::OK)
-            .header("content-type", "text/plain; charset=utf-8")
-            .body(body)
-            .unwrap();
+#[derive(Serialize)]
+struct SchedulerJobsResponse {
+    now: i64,
+    jobs: Vec<SchedulerJobEntry>,
+}
+
+#[derive(Serialize)]
+struct SchemaDriftResponse {
+    databases: Vec<SchemaDriftEntry>,
+}
+
+#[derive(Serialize)]
+struct SchemaRepairEntry {
+    db_path: String,
+    report: crate::schema_drift::RepairReport,
+}
+
+#[derive(Serialize)]
+struct SchemaRepairResponse {
+    databases: Vec<SchemaRepairEntry>,
+}
+
+#[derive(Serialize, Clone)]
+struct SecurityAuditFinding {
+    id: String,
+    severity: String,
+    status: String,
+    message: String,
+    fix_hint: Option<String>,
+    auto_fixable: bool,
+}
+
+#[derive(Serialize)]
+struct SecurityAuditResponse {
+    overall: String,
+    findings: Vec<SecurityAuditFinding>,
+}
+
+#[derive(Serialize)]
+struct FactoryResetConfigResponse {
+    status: String,
+    message: String,
+    config: Value,
+}
+
+pub fn build_router(state: AppState) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .route("/inbox", get(inbox))
+        .route("/inbox/actionable_count", get(inbox_actionable_count))
+        .route("/inbox/smart_lists", get(inbox_smart_lists))
+        .route("/inbox/transition", post(inbox_transition))
+        .route("/inbox/bulk", post(inbox_bulk))
+        .route(
+            "/inbox/comments",
+            get(inbox_comments).post(add_inbox_comment),
+        )
+        .route(
+            "/attachments",
+            get(list_attachments).post(upload_attachment).layer(
+                axum::extract::DefaultBodyLimit::max(crate::attachments::MAX_FILE_BYTES_CEILING),
+            ),
+        )
+        .route("/attachments/delete", post(delete_attachment))
+        .route("/attachments/{id}", get(download_attachment))
+        .route("/inbox/sweep", post(start_inbox_sweep))
+        .route("/dependencies/check", get(dependency_check))
+        .route("/approvals/decide", post(decide_approval))
+        .route("/consent", get(consent_log))
+        .route("/consent/decide", post(decide_consent))
+        .route("/consent/revoke", post(revoke_consent))
+        .route("/catch_up", get(catch_up))
+        .route("/digest/preview", get(digest_preview))
+        .route("/insights/heatmap", get(activity_heatmap))
+        .route("/insights/aggregate", get(aggregate_insights))
+        .route("/audit/events", get(audit_events))
+        .route("/reminders/delivery_events", get(reminder_delivery_events))
+        .route("/reminders/dead_letters", get(reminder_dead_letters))
+        .route(
+            "/reminders/dead_letters/redrive",
+            post(redrive_reminder_dead_letter),
+        )
+        .route(
+            "/reminders/dead_letters/discard",
+            post(discard_reminder_dead_letter),
+        )
+        .route("/outbox/deliveries", get(outbox_deliveries))
+        .route("/todos/estimate_accuracy", get(estimate_accuracy))
+        .route("/projects", get(list_projects))
+        .route("/dashboard", get(dashboard_page))
+        .route("/dashboard/summary", get(dashboard_summary))
+        .route("/webhooks/github", post(github_webhook))
+        .route("/webhooks/zapier/{name}", post(zapier_webhook))
+        .route("/calendar/sync", post(calendar_sync))
+        .route("/email/poll", post(email_poll))
+        .route("/calendar/feed", post(calendar_feed_link))
+        .route("/calendar/feed/{user_id}/{file}", get(calendar_feed))
+        .route("/storage/test", post(test_remote_storage))
+        .route("/storage/archive_audit", post(archive_audit_to_remote))
+        .route("/backups/status", get(backup_status))
+        .route("/backups/run", post(run_backup))
+        .route("/shutdown", post(request_shutdown))
+        .route("/doctor", post(doctor))
+        .route("/doctor/schema", get(schema_drift_report))
+        .route("/doctor/schema/repair", post(repair_schema_drift))
+        .route("/capabilities", get(capabilities))
+        .route("/capabilities/report", get(capabilities_report))
+        .route("/tools/posture", get(tool_posture))
+        .route("/security_audit", post(security_audit))
+        .route("/process_text", post(process_text))
+        .route(
+            "/transcribe",
+            post(transcribe).layer(axum::extract::DefaultBodyLimit::max(MAX_RECORDING_BYTES)),
+        )
+        .route("/process_text_stream", post(process_text_stream))
+        .route("/process_text/stream", post(process_text_events))
+        .route("/process_text/queue", get(prompt_queue_status))
+        .route("/scheduler/jobs", get(scheduler_jobs))
+        .route("/chat_history", get(chat_history))
+        .route("/clear_user_history", post(clear_user_history))
+        .route("/clear_user_data", post(clear_user_data))
+        .route("/search", get(search_work_items))
+        .route("/threads", get(list_threads))
+        .route("/threads/pin", post(pin_thread))
+        .route("/threads/unpin", post(unpin_thread))
+        .route("/trash", get(list_trash))
+        .route("/trash/restore", post(restore_trash))
+        .route("/templates/preview", post(preview_template))
+        .route("/templates/import", post(import_template))
+        .route("/privacy_lock", post(set_privacy_lock))
+        .route("/encryption/status", get(encryption_status))
+        .route("/encryption/enroll", post(enroll_encryption))
+        .route("/encryption/unlock", post(unlock_encryption))
+        .route("/encryption/lock", post(lock_encryption))
+        .route("/encryption/passphrase", post(change_encryption_passphrase))
+        .route("/memory_search", post(memory_search))
+        .route("/memory/search", post(semantic_memory_search))
+        .route("/memory/retention", post(memory_retention))
+        .route("/preload_boot", post(preload_boot))
+        .route("/reminder_stream", get(reminder_stream))
+        .route("/ui_events", get(ui_events))
+        .route("/factory_reset_config", post(factory_reset_config))
+        .route("/auth/tokens", get(list_user_tokens).post(issue_user_token))
+        .route("/auth/tokens/rotate", post(rotate_user_token))
+        .route("/auth/tokens/revoke", post(revoke_user_token))
+        .route("/auth/roles", get(list_user_roles).post(set_user_role))
+        .route("/auth/roles/remove", post(remove_user_role))
+        .route("/vault/secrets", get(list_secrets).post(create_secret))
+        .route("/vault/secrets/rotate", post(rotate_secret))
+        .route("/vault/secrets/delete", post(delete_secret))
+        .route("/reload_config", post(reload_config))
+        .route("/signer/preview", post(signer_preview))
+        .route("/signer/approve", post(signer_approve))
+        .route("/signer/sign", post(signer_sign))
+        .route("/signer/deny", post(signer_deny))
+        .route("/x402/preview", post(x402_preview))
+        .route("/solana/wallet", get(solana_wallet))
+        .route("/solana/balance", get(solana_balance))
+        .route("/solana/transfer", post(solana_transfer))
+        .route("/solana/simulate_transfer", post(solana_simulate_transfer))
+        .route("/solana/tx/status", get(solana_tx_status))
+        .route("/solana/tx/history", get(solana_tx_history))
+        .layer(axum::middleware::from_fn(shutdown_gate))
+        .with_state(state)
+}
+
+/// Refuses new requests once shutdown has started and counts the rest as
+/// in-flight work. `/health` keeps answering so callers can wait for the
+/// process to exit.
+async fn shutdown_gate(request: axum::extract::Request, next: axum::middleware::Next) -> Response {
+    let in_flight = crate::shutdown::shared();
+    if in_flight.is_stopping() && request.uri().path() != "/health" {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Daemon is shutting down".to_string(),
+            }),
+        )
+            .into_response();
     }
+    let _work = in_flight.begin(format!("{} {}", request.method(), request.uri().path()));
+    next.run(request).await
+}
 
-    let body = Body::from_stream(async_stream::stream! {
-        let mut stream = agent.process_text_stream(&user_id, &text, prompt.as_deref());
-        while let Some(item) = stream.next().await {
-            match item {
-                Ok(chunk) => {
-                    if !chunk.is_empty() {
-                        yield Ok::<Bytes, std::convert::Infallible>(Bytes::from(chunk));
-                    }
-                }
-                Err(err) => {
-                    let message = format!("\n[error] {}", err);
-                    yield Ok(Bytes::from(message));
-                    break;
-                }
+async fn inbox(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<InboxQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let limit = query.limit.unwrap_or(200).clamp(1, 500);
+    let include_done = query.include_done.unwrap_or(true);
+    // A project filter reads the whole window first so `limit` counts matches.
+    let scan = if query.project_id.is_some() {
+        500
+    } else {
+        limit
+    };
+    match build_inbox_items(&state.db_path, &query.user_id, scan, include_done).await {
+        Ok(mut items) => {
+            if let Some(project_id) = query.project_id {
+                items.retain(|item| item.project_id == Some(project_id));
+                items.truncate(limit);
             }
+            (StatusCode::OK, Json(InboxResponse { items })).into_response()
         }
-    });
-
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "text/plain; charset=utf-8")
-        .body(body)
-        .unwrap()
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
 }
 
-async fn preload_boot(
+async fn inbox_actionable_count(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<PreloadBootRequest>,
+    axum::extract::Query(query): axum::extract::Query<InboxQuery>,
 ) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
         return err.into_response();
     }
 
-    let agent = state.agent.read().await.clone();
-    let db_path = state.db_path.clone();
-    let ui_event_tx = state.ui_event_tx.clone();
-    let user_id = payload.user_id.clone();
+    match build_inbox_items(&state.db_path, &query.user_id, 500, false).await {
+        Ok(items) => {
+            let actionable_count = items.iter().filter(|item| is_actionable(item)).count();
+            (
+                StatusCode::OK,
+                Json(InboxActionableCountResponse { actionable_count }),
+            )
+                .into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
 
-    tokio::spawn(async move {
-        let quick_timeout = Duration::from_secs(2);
+/// Broken dependency refs and dependency cycles across the user's todos,
+/// reminders, tasks and plans, done items included.
+async fn dependency_check(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<DependencyCheckQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let config_json = Config::load(&state.db_path)
+        .ok()
+        .and_then(|cfg| cfg.tools)
+        .unwrap_or(Value::Null);
+    match RefResolver::load(&config_json, &state.db_path, &query.user_id).await {
+        Ok(resolver) => (StatusCode::OK, Json(resolver.report())).into_response(),
+        Err(err) => integration_error(err),
+    }
+}
 
-        let context_status =
-            match tokio::time::timeout(quick_timeout, agent.preload_context(&user_id)).await {
-                Ok(Ok(())) => "ok".to_string(),
-                Ok(Err(err)) => format!("error: {err}"),
-                Err(_) => {
-                    let agent = agent.clone();
-                    let ui_event_tx = ui_event_tx.clone();
-                    let user_id = user_id.clone();
-                    tokio::spawn(async move {
-                        let status = match agent.preload_context(&user_id).await {
-                            Ok(()) => "ok".to_string(),
-                            Err(err) => format!("error: {err}"),
-                        };
-                        let _ = ui_event_tx.send(UiEvent {
-                        event_type: "boot".to_string(),
-                        user_id: user_id.clone(),
-                        tool: "context".to_string(),
-                        status: status.clone(),
-                        payload: json!({"user_id": user_id, "status": status, "phase": "deferred"}),
-                        timestamp: now_ts(),
-                    });
-                    });
-                    "deferred".to_string()
-                }
-            };
-        let _ = ui_event_tx.send(UiEvent {
-            event_type: "boot".to_string(),
-            user_id: user_id.clone(),
-            tool: "context".to_string(),
-            status: context_status.clone(),
-            payload: json!({"user_id": user_id, "status": context_status, "phase": "quick"}),
-            timestamp: now_ts(),
-        });
+/// Waiting on the human and not yet done.
+fn is_actionable(item: &InboxItemResponse) -> bool {
+    item.owner == "human"
+        && item.requires_human_action
+        && matches!(
+            item.status.as_str(),
+            "new" | "acknowledged" | "in_progress" | "blocked"
+        )
+}
 
-        let heartbeat_status = if let Ok(config) = Config::from_store(&db_path) {
-            let source = config.heartbeat_source;
-            match tokio::time::timeout(quick_timeout, load_markdown_content(&source)).await {
-                Ok(Ok(markdown)) => {
-                    agent.set_heartbeat_markdown(markdown.clone()).await;
-                    if markdown
-                        .as_ref()
-                        .map(|m| !m.trim().is_empty())
-                        .unwrap_or(false)
-                    {
-                        "ok".to_string()
-                    } else {
-                        "empty".to_string()
-                    }
-                }
-                Ok(Err(err)) => format!("error: {err}"),
-                Err(_) => {
-                    let agent = agent.clone();
-                    let ui_event_tx = ui_event_tx.clone();
-                    let source = source.clone();
-                    tokio::spawn(async move {
-                        let status = match load_markdown_content(&source).await {
-                            Ok(markdown) => {
-                                agent.set_heartbeat_markdown(markdown.clone()).await;
-                                if markdown
-                                    .as_ref()
-                                    .map(|m| !m.trim().is_empty())
-                                    .unwrap_or(false)
-                                {
-                                    "ok".to_string()
-                                } else {
-                                    "empty".to_string()
-                                }
-                            }
-                            Err(err) => format!("error: {err}"),
-                        };
-                        let _ = ui_event_tx.send(UiEvent {
-                            event_type: "boot".to_string(),
-                            user_id: "system".to_string(),
-                            tool: "heartbeat".to_string(),
-                            status: status.clone(),
-                            payload: json!({"status": status, "phase": "deferred"}),
-                            timestamp: now_ts(),
-                        });
-                    });
-                    "deferred".to_string()
-                }
-            }
-        } else {
-            "config_error".to_string()
-        };
-
-        let _ = ui_event_tx.send(UiEvent {
-            event_type: "boot".to_string(),
-            user_id: "system".to_string(),
-            tool: "heartbeat".to_string(),
-            status: heartbeat_status.clone(),
-            payload: json!({"status": heartbeat_status, "phase": "quick"}),
-            timestamp: now_ts(),
-        });
+async fn inbox_smart_lists(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<InboxQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
 
-        let prompt_status = if let Ok(config) = Config::from_store(&db_path) {
-            let source = config.prompt_source;
-            match tokio::time::timeout(quick_timeout, load_markdown_content(&source)).await {
-                Ok(Ok(markdown)) => {
-                    agent.set_prompt_markdown(markdown.clone()).await;
-                    if markdown
-                        .as_ref()
-                        .map(|m| !m.trim().is_empty())
-                        .unwrap_or(false)
-                    {
-                        "ok".to_string()
-                    } else {
-                        "empty".to_string()
-                    }
-                }
-                Ok(Err(err)) => format!("error: {err}"),
-                Err(_) => {
-                    let agent = agent.clone();
-                    let ui_event_tx = ui_event_tx.clone();
-                    let source = source.clone();
-                    tokio::spawn(async move {
-                        let status = match load_markdown_content(&source).await {
-                            Ok(markdown) => {
-                                agent.set_prompt_markdown(markdown.clone()).await;
-                                if markdown
-                                    .as_ref()
-                                    .map(|m| !m.trim().is_empty())
-                                    .unwrap_or(false)
-                                {
-                                    "ok".to_string()
-                                } else {
-                                    "empty".to_string()
-                                }
-                            }
-                            Err(err) => format!("error: {err}"),
-                        };
-                        let _ = ui_event_tx.send(UiEvent {
-                            event_type: "boot".to_string(),
-                            user_id: "system".to_string(),
-                            tool: "prompt".to_string(),
-                            status: status.clone(),
-                            payload: json!({"status": status, "phase": "deferred"}),
-                            timestamp: now_ts(),
-                        });
-                    });
-                    "deferred".to_string()
-                }
-            }
-        } else {
-            "config_error".to_string()
-        };
+    let limit = query.limit.unwrap_or(500).clamp(1, 500);
+    match build_inbox_items(&state.db_path, &query.user_id, limit, false).await {
+        Ok(items) => (
+            StatusCode::OK,
+            Json(SmartListsResponse {
+                lists: group_smart_lists(items, now_ts(), &query.user_id),
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
 
-        let _ = ui_event_tx.send(UiEvent {
-            event_type: "boot".to_string(),
-            user_id: user_id.clone(),
-            tool: "prompt".to_string(),
-            status: prompt_status.clone(),
-            payload: json!({"status": prompt_status}),
-            timestamp: now_ts(),
-        });
+fn group_smart_lists(
+    items: Vec<InboxItemResponse>,
+    now: i64,
+    user_id: &str,
+) -> Vec<SmartListResponse> {
+    let bounds = crate::timezones::smart_list_bounds(user_id, now);
+    let mut lists = SmartList::all()
+        .into_iter()
+        .map(|list| SmartListResponse {
+            key: list.key().to_string(),
+            label: list.label().to_string(),
+            items: Vec::new(),
+        })
+        .collect::<Vec<_>>();
 
-        if (heartbeat_status == "ok"
-            || heartbeat_status == "empty"
-            || heartbeat_status == "deferred")
-            && (prompt_status == "ok" || prompt_status == "empty" || prompt_status == "deferred")
-        {
-            let agent = agent.clone();
-            let ui_event_tx = ui_event_tx.clone();
-            let user_id = user_id.clone();
-            tokio::spawn(async move {
-                run_autonomy_tick(agent, ui_event_tx, user_id, "boot").await;
-            });
+    for item in items {
+        let due_at = item
+            .due_at
+            .or_else(|| parse_due_at_from_text(&item.title, item.created_at, user_id));
+        let bucket = bounds.classify(due_at);
+        if let Some(index) = SmartList::all().iter().position(|list| *list == bucket) {
+            lists[index].items.push(item);
         }
-    });
+    }
 
-    (
-        StatusCode::OK,
-        Json(PreloadBootResponse {
-            context_status: "started".to_string(),
-            heartbeat_status: "started".to_string(),
-        }),
-    )
-        .into_response()
+    lists
 }
 
-async fn memory_search(
+fn parse_inbox_status_state(value: &str) -> Option<InboxState> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "new" => Some(InboxState::New),
+        "ack" | "acknowledged" => Some(InboxState::Acknowledged),
+        "in_progress" | "in progress" | "started" => Some(InboxState::InProgress),
+        "blocked" => Some(InboxState::Blocked),
+        "done" | "completed" | "complete" => Some(InboxState::Done),
+        "dismissed" | "archived" => Some(InboxState::Dismissed),
+        _ => None,
+    }
+}
+
+fn inbox_state_to_str(state: InboxState) -> &'static str {
+    match state {
+        InboxState::New => "new",
+        InboxState::Acknowledged => "acknowledged",
+        InboxState::InProgress => "in_progress",
+        InboxState::Blocked => "blocked",
+        InboxState::Done => "done",
+        InboxState::Dismissed => "dismissed",
+    }
+}
+
+fn parse_inbox_action(value: &str) -> Option<InboxAction> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "ack" | "acknowledge" => Some(InboxAction::Acknowledge),
+        "start" => Some(InboxAction::Start),
+        "block" | "blocked" => Some(InboxAction::Block),
+        "done" | "complete" => Some(InboxAction::Done),
+        "reopen" | "undo" | "undone" => Some(InboxAction::Reopen),
+        "dismiss" | "dismissed" => Some(InboxAction::Dismiss),
+        "snooze" => Some(InboxAction::Snooze),
+        _ => None,
+    }
+}
+
+async fn inbox_comments(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<MemorySearchRequest>,
+    axum::extract::Query(query): axum::extract::Query<InboxCommentsQuery>,
 ) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
         return err.into_response();
     }
 
-    let limit = payload.limit.unwrap_or(8);
-    let agent = state.agent.read().await.clone();
-    let response = agent
-        .search_memory(&payload.user_id, &payload.query, limit)
-        .await;
-
-    match response {
-        Ok(results) => (StatusCode::OK, Json(MemorySearchResponse { results })).into_response(),
+    let comments = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => store.comments(&query.user_id, &query.origin_ref).await,
+        Err(err) => Err(err),
+    };
+    match comments {
+        Ok(comments) => (
+            StatusCode::OK,
+            Json(InboxCommentsResponse {
+                origin_ref: query.origin_ref,
+                comments,
+            }),
+        )
+            .into_response(),
         Err(err) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
@@ -3579,21 +3542,41 @@ async fn memory_search(
     }
 }
 
-async fn chat_history(
+/// Appends the human's note to an item's thread.
+async fn add_inbox_comment(
     State(state): State<AppState>,
     headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<ChatHistoryQuery>,
+    Json(payload): Json<InboxCommentRequest>,
 ) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
         return err.into_response();
     }
 
-    let limit = query.limit.unwrap_or(40).clamp(1, 200);
-    let agent = state.agent.read().await.clone();
-    let response = agent.get_user_history(&query.user_id, limit).await;
-
-    match response {
-        Ok(history) => (StatusCode::OK, Json(ChatHistoryResponse { history })).into_response(),
+    let store = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    match store
+        .add_comment(
+            &payload.user_id,
+            &payload.origin_ref,
+            AUTHOR_HUMAN,
+            &payload.body,
+        )
+        .await
+    {
+        Ok(comment) => (StatusCode::OK, Json(comment)).into_response(),
+        Err(ButterflyBotError::Config(error)) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
         Err(err) => (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
@@ -3604,69 +3587,7122 @@ async fn chat_history(
     }
 }
 
-async fn clear_user_history(
+async fn attachment_store(state: &AppState) -> Result<AttachmentStore> {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    AttachmentStore::new(&state.db_path, AttachmentConfig::from_tools(tools.as_ref())).await
+}
+
+fn attachment_error(err: ButterflyBotError) -> Response {
+    match err {
+        ButterflyBotError::Config(error) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
+        err => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn list_attachments(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<ClearHistoryRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
+    axum::extract::Query(query): axum::extract::Query<AttachmentsQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
         return err.into_response();
     }
-
-    let agent = state.agent.read().await.clone();
-    tracing::info!(
-        "clear_user_history requested for user_id={}",
-        payload.user_id
-    );
-    match agent.delete_user_history(&payload.user_id).await {
-        Ok(()) => (
+    let attachments = match attachment_store(&state).await {
+        Ok(store) => store.list(&query.user_id, &query.origin_ref).await,
+        Err(err) => Err(err),
+    };
+    match attachments {
+        Ok(attachments) => (
             StatusCode::OK,
-            Json(ClearHistoryResponse {
-                status: "ok".to_string(),
-                message: "User history cleared".to_string(),
+            Json(AttachmentsResponse {
+                origin_ref: query.origin_ref,
+                attachments,
             }),
         )
             .into_response(),
-        Err(err) => {
-            tracing::error!(
-                "clear_user_history failed for user_id={}: {}",
-                payload.user_id,
-                err
-            );
-            (
+        Err(err) => attachment_error(err),
+    }
+}
+
+/// Stores the request body as a file on an inbox item or todo. The
+/// content type comes from the request's `content-type` header.
+async fn upload_attachment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<AttachmentUploadQuery>,
+    body: Bytes,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let owned = build_inbox_items(&state.db_path, &query.user_id, 1000, true)
+        .await
+        .map(|items| {
+            items
+                .iter()
+                .any(|item| item.origin_ref == query.origin_ref.trim())
+        });
+    match owned {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Inbox item not found".to_string(),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => return attachment_error(err),
+    }
+    let content_type = headers
+        .get("content-type")
+        .and_then(|value| value.to_str().ok());
+    let attached = match attachment_store(&state).await {
+        Ok(store) => {
+            store
+                .attach(
+                    &query.user_id,
+                    &query.origin_ref,
+                    &query.name,
+                    content_type,
+                    &body,
+                )
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    match attached {
+        Ok(attachment) => (StatusCode::OK, Json(attachment)).into_response(),
+        Err(err) => attachment_error(err),
+    }
+}
+
+async fn download_attachment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<i32>,
+    axum::extract::Query(query): axum::extract::Query<AttachmentDownloadQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let read = match attachment_store(&state).await {
+        Ok(store) => store.read(&query.user_id, id).await,
+        Err(err) => Err(err),
+    };
+    match read {
+        Ok(Some((attachment, bytes))) => {
+            // Header values must be visible ASCII; anything else becomes `_`.
+            let filename: String = attachment
+                .name
+                .chars()
+                .map(|c| {
+                    if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect();
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", attachment.content_type)
+                .header("x-content-type-options", "nosniff")
+                .header(
+                    "content-disposition",
+                    format!("attachment; filename=\"{filename}\""),
+                )
+                .body(Body::from(bytes))
+                .unwrap_or_else(|err| attachment_error(ButterflyBotError::Runtime(err.to_string())))
+        }
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Attachment not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(err) => attachment_error(err),
+    }
+}
+
+async fn delete_attachment(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DeleteAttachmentRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let deleted = match attachment_store(&state).await {
+        Ok(store) => store.delete(&payload.user_id, payload.id).await,
+        Err(err) => Err(err),
+    };
+    match deleted {
+        Ok(deleted) => (StatusCode::OK, Json(DeleteAttachmentResponse { deleted })).into_response(),
+        Err(err) => attachment_error(err),
+    }
+}
+
+async fn inbox_transition(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<InboxTransitionRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let Some(action) = parse_inbox_action(&payload.action) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Unsupported inbox action".to_string(),
+            }),
+        )
+            .into_response();
+    };
+
+    let items = match build_inbox_items(&state.db_path, &payload.user_id, 1000, true).await {
+        Ok(items) => items,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let Some(item) = items
+        .iter()
+        .find(|item| item.origin_ref == payload.origin_ref)
+        .cloned()
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Inbox item not found".to_string(),
+            }),
+        )
+            .into_response();
+    };
+
+    match apply_inbox_transition(
+        &state,
+        &payload.user_id,
+        &item,
+        action,
+        &payload.action,
+        "human",
+        "manual_transition",
+    )
+    .await
+    {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Applies one action to several inbox items. Items that cannot take the
+/// action (already done, not a reminder for snooze, ...) are skipped with a
+/// reason instead of failing the batch.
+async fn inbox_bulk(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<InboxBulkRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let action = match parse_inbox_action(&payload.action) {
+        Some(action @ (InboxAction::Done | InboxAction::Snooze | InboxAction::Acknowledge)) => {
+            action
+        }
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "Bulk actions are done, snooze and acknowledge".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    if payload.origin_refs.len() > MAX_INBOX_BULK_ITEMS {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("At most {MAX_INBOX_BULK_ITEMS} items per bulk action"),
+            }),
+        )
+            .into_response();
+    }
+
+    let items = match build_inbox_items(&state.db_path, &payload.user_id, 1000, true).await {
+        Ok(items) => items,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let mut applied = Vec::new();
+    let mut skipped = Vec::new();
+    let mut seen = HashSet::new();
+    for origin_ref in &payload.origin_refs {
+        if !seen.insert(origin_ref.as_str()) {
+            continue;
+        }
+        let Some(item) = items.iter().find(|item| &item.origin_ref == origin_ref) else {
+            skipped.push(InboxBulkSkip {
+                origin_ref: origin_ref.clone(),
+                reason: "Inbox item not found".to_string(),
+            });
+            continue;
+        };
+        // Matches the single-item UI, which only offers snooze on reminders.
+        if action == InboxAction::Snooze && item.source_type != "reminder" {
+            skipped.push(InboxBulkSkip {
+                origin_ref: origin_ref.clone(),
+                reason: "Only reminders can be snoozed".to_string(),
+            });
+            continue;
+        }
+        match apply_inbox_transition(
+            &state,
+            &payload.user_id,
+            item,
+            action,
+            &payload.action,
+            "human",
+            "bulk_transition",
+        )
+        .await
+        {
+            Ok(transition) => applied.push(transition),
+            Err((_, Json(err))) => skipped.push(InboxBulkSkip {
+                origin_ref: origin_ref.clone(),
+                reason: err.error,
+            }),
+        }
+    }
+
+    (
+        StatusCode::OK,
+        Json(InboxBulkResponse {
+            action: payload.action.trim().to_ascii_lowercase(),
+            applied,
+            skipped,
+        }),
+    )
+        .into_response()
+}
+
+/// Moves `item` through the inbox state machine, running the store side
+/// effects an action implies (completing a todo, snoozing a reminder, ...),
+/// and announces the transition on the UI event bus.
+#[allow(clippy::too_many_arguments)]
+async fn apply_inbox_transition(
+    state: &AppState,
+    user_id: &str,
+    item: &InboxItemResponse,
+    action: InboxAction,
+    action_label: &str,
+    actor: &str,
+    reason: &str,
+) -> std::result::Result<InboxTransitionResponse, (StatusCode, Json<ErrorResponse>)> {
+    if item.source_type == crate::calendar::SOURCE_TYPE {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "Calendar entries are read-only; change the event in its calendar"
+                    .to_string(),
+            }),
+        ));
+    }
+
+    let Some(previous_state) = parse_inbox_status_state(&item.status) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid current inbox status".to_string(),
+            }),
+        ));
+    };
+
+    let Some(next_state) = crate::inbox_fsm::transition(previous_state, action) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Invalid inbox transition".to_string(),
+            }),
+        ));
+    };
+
+    if action == InboxAction::Done {
+        match item.source_type.as_str() {
+            "reminder" => {
+                let store = ReminderStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.complete_reminder(user_id, item.source_id).await;
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            "todo" => {
+                let store = TodoStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.set_completed(item.source_id, true).await;
+                        if let Ok(reminders) = ReminderStore::new(&state.db_path).await {
+                            let _ = reminders
+                                .complete_linked_reminders(user_id, &item.origin_ref)
+                                .await;
+                        }
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            "task" => {
+                let store = TaskStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.set_enabled(item.source_id, false).await;
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if action == InboxAction::Reopen {
+        match item.source_type.as_str() {
+            "reminder" => {
+                let store = ReminderStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.reopen_reminder(user_id, item.source_id).await;
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            "todo" => {
+                let store = TodoStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.set_completed(item.source_id, false).await;
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            "task" => {
+                let store = TaskStore::new(&state.db_path).await;
+                match store {
+                    Ok(store) => {
+                        let _ = store.set_enabled(item.source_id, true).await;
+                    }
+                    Err(err) => {
+                        return Err((
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            Json(ErrorResponse {
+                                error: err.to_string(),
+                            }),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if action == InboxAction::Snooze && item.source_type == "reminder" {
+        let store = ReminderStore::new(&state.db_path).await;
+        match store {
+            Ok(store) => {
+                let now = now_ts();
+                let due_at = item.due_at.unwrap_or(now).max(now) + 15 * 60;
+                let _ = store.snooze_reminder(user_id, item.source_id, due_at).await;
+            }
+            Err(err) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                ));
+            }
+        }
+    }
+
+    if item.source_type == "question" {
+        let store = match QuestionStore::new(&state.db_path).await {
+            Ok(store) => store,
+            Err(err) => {
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                ));
+            }
+        };
+        // Any look at the question counts as the read receipt.
+        let _ = store.mark_seen(user_id, item.source_id).await;
+        let closed = match action {
+            InboxAction::Done => store
+                .answer(user_id, item.source_id, None)
+                .await
+                .is_ok_and(|question| question.is_some()),
+            InboxAction::Dismiss => store
+                .dismiss(user_id, item.source_id)
+                .await
+                .unwrap_or(false),
+            InboxAction::Reopen => {
+                let _ = store.reopen(user_id, item.source_id).await;
+                false
+            }
+            _ => false,
+        };
+        if closed {
+            if let Ok(reminders) = ReminderStore::new(&state.db_path).await {
+                let _ = reminders
+                    .complete_linked_reminders(user_id, &item.origin_ref)
+                    .await;
+            }
+        }
+    }
+
+    let state_store = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            ));
+        }
+    };
+    if let Err(err) = state_store
+        .set_status(user_id, &item.origin_ref, inbox_state_to_str(next_state))
+        .await
+    {
+        return Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        ));
+    }
+    let _ = state_store
+        .record_transition(
+            user_id,
+            &item.origin_ref,
+            inbox_state_to_str(previous_state),
+            inbox_state_to_str(next_state),
+        )
+        .await;
+
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "inbox_transition".to_string(),
+        user_id: user_id.to_string(),
+        tool: "inbox".to_string(),
+        status: inbox_state_to_str(next_state).to_string(),
+        payload: json!({
+            "origin_ref": item.origin_ref,
+            "source_type": item.source_type,
+            "source_id": item.source_id,
+            "action": action_label,
+            "actor": actor,
+            "reason": reason,
+            "from": inbox_state_to_str(previous_state),
+            "to": inbox_state_to_str(next_state),
+            "previous_status": inbox_state_to_str(previous_state),
+            "next_status": inbox_state_to_str(next_state),
+        }),
+        timestamp: now_ts(),
+    });
+
+    if next_state == InboxState::Done {
+        if let Err(err) = unblock_ready_items(&state.db_path, &state.ui_event_tx, user_id).await {
+            tracing::warn!(user_id, error = %err, "Dependency unblocking failed");
+        }
+    }
+
+    Ok(InboxTransitionResponse {
+        status: "ok".to_string(),
+        origin_ref: item.origin_ref.clone(),
+        previous_status: inbox_state_to_str(previous_state).to_string(),
+        next_status: inbox_state_to_str(next_state).to_string(),
+    })
+}
+
+/// Runs the inbox sweep now instead of waiting for its weekday.
+async fn start_inbox_sweep(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<InboxSweepRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let agent = state.agent.read().await.clone();
+    match propose_inbox_sweep(
+        &agent,
+        &state.db_path,
+        &state.ui_event_tx,
+        &payload.user_id,
+        now_ts(),
+    )
+    .await
+    {
+        Ok(Some(approval)) => (
+            StatusCode::OK,
+            Json(json!({
+                "status": "proposed",
+                "summary": SweepBatch::from_args(&approval.args)
+                    .unwrap_or_default()
+                    .summary(),
+                "approval": approval,
+            })),
+        )
+            .into_response(),
+        Ok(None) => (StatusCode::OK, Json(json!({"status": "empty"}))).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn decide_approval(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ApprovalDecisionRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let decision = match payload.decision.trim().to_ascii_lowercase().as_str() {
+        "approve" | "approved" | "allow" => ApprovalStatus::Approved,
+        "reject" | "rejected" | "deny" => ApprovalStatus::Rejected,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "decision must be 'approve' or 'reject'".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let store = match ApprovalStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    // Approvers in a chain decide other people's calls; the call itself
+    // still runs as, and is recorded for, the person who asked.
+    let approval = match store.get_for_actor(&payload.user_id, payload.id).await {
+        Ok(Some(approval)) => approval,
+        Ok(None) => {
+            return (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Approval not found".to_string(),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let owner = approval.user_id.clone();
+    let steps = match store.steps(approval.id).await {
+        Ok(steps) => steps,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    if !steps.is_empty() {
+        if approval.status != ApprovalStatus::Pending {
+            return (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!("Approval was already {}", approval.status.as_str()),
+                }),
+            )
+                .into_response();
+        }
+        let current = steps
+            .iter()
+            .find(|step| step.status == StepStatus::Pending)
+            .cloned();
+        let outcome = match store
+            .decide_step(
+                &approval,
+                &payload.user_id,
+                decision == ApprovalStatus::Approved,
+            )
+            .await
+        {
+            Ok(outcome) => outcome,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+        match outcome {
+            StepOutcome::NotAnApprover(step) => {
+                return (
+                    StatusCode::FORBIDDEN,
+                    Json(ErrorResponse {
+                        error: format!(
+                            "Step {} is waiting on {}",
+                            step.position + 1,
+                            step.approvers.join(" or ")
+                        ),
+                    }),
+                )
+                    .into_response()
+            }
+            StepOutcome::Closed => {
+                return (
+                    StatusCode::CONFLICT,
+                    Json(ErrorResponse {
+                        error: "This step was already decided".to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+            outcome => {
+                if let Some(step) = &current {
+                    let status = if decision == ApprovalStatus::Approved {
+                        StepStatus::Approved
+                    } else {
+                        StepStatus::Rejected
+                    };
+                    let _ = state.ui_event_tx.send(approval_step_event(
+                        &approval,
+                        step,
+                        status,
+                        Some(&payload.user_id),
+                    ));
+                }
+                if let StepOutcome::Advanced(_) = outcome {
+                    if let Err(err) = announce_approval_steps(&store, &state.ui_event_tx).await {
+                        tracing::warn!(error = %err, approval_id = approval.id, "Failed to announce approval step");
+                    }
+                    let steps = store.steps(approval.id).await.unwrap_or(steps);
+                    return (
+                        StatusCode::OK,
+                        Json(ApprovalDecisionResponse {
+                            status: ApprovalStatus::Pending.as_str().to_string(),
+                            approval,
+                            steps,
+                        }),
+                    )
+                        .into_response();
+                }
+            }
+        }
+    }
+    match store.decide(&owner, payload.id, decision).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!("Approval was already {}", approval.status.as_str()),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+
+    let status = if decision == ApprovalStatus::Rejected {
+        ApprovalStatus::Rejected
+    } else if approval.capability == inbox_sweep::SWEEP_CAPABILITY {
+        let outcome =
+            apply_inbox_sweep(&state, &owner, &approval, payload.accepted.as_deref()).await;
+        let status = if outcome.get("status").and_then(|v| v.as_str()) == Some("error") {
+            ApprovalStatus::Failed
+        } else {
+            ApprovalStatus::Executed
+        };
+        if let Err(err) = store.record_outcome(approval.id, status, &outcome).await {
+            tracing::warn!(error = %err, approval_id = approval.id, "Failed to record approval outcome");
+        }
+        status
+    } else {
+        let agent = state.agent.read().await.clone();
+        let (status, result) = match agent.execute_approved(&approval).await {
+            Ok(result) if result.get("status").and_then(|v| v.as_str()) == Some("error") => {
+                (ApprovalStatus::Failed, result)
+            }
+            Ok(result) => (ApprovalStatus::Executed, result),
+            Err(err) => (ApprovalStatus::Failed, json!({"error": err.to_string()})),
+        };
+        if let Err(err) = store.record_outcome(approval.id, status, &result).await {
+            tracing::warn!(error = %err, approval_id = approval.id, "Failed to record approval outcome");
+        }
+        status
+    };
+
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "approval".to_string(),
+        user_id: owner.clone(),
+        tool: approval.tool.clone(),
+        status: status.as_str().to_string(),
+        payload: json!({
+            "origin_ref": approval.origin_ref(),
+            "capability": approval.capability,
+            "actor": payload.user_id,
+        }),
+        timestamp: now_ts(),
+    });
+
+    let approval = match store.get(&owner, payload.id).await {
+        Ok(Some(approval)) => approval,
+        _ => approval,
+    };
+    let steps = store.steps(approval.id).await.unwrap_or(steps);
+    (
+        StatusCode::OK,
+        Json(ApprovalDecisionResponse {
+            status: status.as_str().to_string(),
+            approval,
+            steps,
+        }),
+    )
+        .into_response()
+}
+
+fn consent_event(record: &ConsentRecord, now: i64) -> UiEvent {
+    UiEvent {
+        event_type: "consent".to_string(),
+        user_id: record.user_id.clone(),
+        tool: record.tool.clone(),
+        status: record.status.as_str().to_string(),
+        payload: json!({
+            "origin_ref": record.origin_ref(),
+            "capability": record.capability,
+            "scope": record.scope,
+            "expires_at": record.expires_at,
+            "summary": record.summary(now),
+        }),
+        timestamp: now,
+    }
+}
+
+/// The user's consent log: every request, grant, denial and revocation.
+async fn consent_log(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ConsentQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let records = match ConsentStore::new(&state.db_path).await {
+        Ok(store) => store.list(&query.user_id, 500).await,
+        Err(err) => Err(err),
+    };
+    match records {
+        Ok(records) => {
+            let now = now_ts();
+            let entries = records
+                .into_iter()
+                .map(|record| ConsentLogEntry::new(record, now))
+                .collect();
+            (StatusCode::OK, Json(ConsentLogResponse { entries })).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn decide_consent(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ConsentDecisionRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let grant = match payload.decision.trim().to_ascii_lowercase().as_str() {
+        "allow" | "grant" | "approve" => true,
+        "deny" | "reject" => false,
+        _ => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: "decision must be 'allow' or 'deny'".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let ttl_secs = match payload.ttl_days {
+        Some(days) => (days > 0).then_some(days as i64 * 86_400),
+        None => match Config::load(&state.db_path) {
+            Ok(config) => {
+                let tools = config.tools.unwrap_or(Value::Null);
+                ConsentPolicy::from_root_config(&json!({ "tools": tools })).ttl_secs()
+            }
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    let store = match ConsentStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let decided = store
+        .decide(&payload.user_id, payload.id, grant, ttl_secs)
+        .await;
+    consent_outcome(&state, &store, &payload.user_id, payload.id, decided).await
+}
+
+async fn revoke_consent(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ConsentRevokeRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let store = match ConsentStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let revoked = store.revoke(&payload.user_id, payload.id).await;
+    consent_outcome(&state, &store, &payload.user_id, payload.id, revoked).await
+}
+
+/// Announces a consent change, or explains why nothing changed.
+async fn consent_outcome(
+    state: &AppState,
+    store: &ConsentStore,
+    user_id: &str,
+    id: i32,
+    changed: Result<Option<ConsentRecord>>,
+) -> axum::response::Response {
+    let now = now_ts();
+    match changed {
+        Ok(Some(record)) => {
+            let _ = state.ui_event_tx.send(consent_event(&record, now));
+            (StatusCode::OK, Json(ConsentLogEntry::new(record, now))).into_response()
+        }
+        Ok(None) => match store.get(user_id, id).await {
+            Ok(Some(record)) => (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: format!("Consent is already {}", record.status.as_str()),
+                }),
+            )
+                .into_response(),
+            Ok(None) => (
+                StatusCode::NOT_FOUND,
+                Json(ErrorResponse {
+                    error: "Consent request not found".to_string(),
+                }),
+            )
+                .into_response(),
+            Err(err) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response(),
+        },
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// The UI reports its lock state so reminder notifications can go generic.
+async fn set_privacy_lock(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<PrivacyLockRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    privacy_lock::set_locked(&payload.user_id, payload.locked);
+    (
+        StatusCode::OK,
+        Json(PrivacyLockResponse {
+            locked: privacy_lock::is_locked(&payload.user_id),
+            user_id: payload.user_id,
+        }),
+    )
+        .into_response()
+}
+
+fn encryption_status_response(user_id: &str) -> axum::response::Response {
+    (
+        StatusCode::OK,
+        Json(EncryptionStatusResponse {
+            user_id: user_id.to_string(),
+            status: user_domains::status(user_id),
+        }),
+    )
+        .into_response()
+}
+
+fn encryption_error_response(err: ButterflyBotError) -> axum::response::Response {
+    let status = match &err {
+        ButterflyBotError::Config(_) => StatusCode::BAD_REQUEST,
+        ButterflyBotError::SecurityPolicy(_) => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+async fn encryption_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<EncryptionQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    if let Err(err) = UserDomainStore::new(&state.db_path).await {
+        return encryption_error_response(err);
+    }
+    encryption_status_response(&query.user_id)
+}
+
+async fn enroll_encryption(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EncryptionPassphraseRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let result = match UserDomainStore::new(&state.db_path).await {
+        Ok(store) => store.enroll(&payload.user_id, &payload.passphrase).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => encryption_status_response(&payload.user_id),
+        Err(err) => encryption_error_response(err),
+    }
+}
+
+async fn unlock_encryption(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EncryptionPassphraseRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let result = match UserDomainStore::new(&state.db_path).await {
+        Ok(store) => store.unlock(&payload.user_id, &payload.passphrase).await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => encryption_status_response(&payload.user_id),
+        Err(err) => encryption_error_response(err),
+    }
+}
+
+async fn lock_encryption(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EncryptionLockRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    user_domains::lock(&payload.user_id);
+    encryption_status_response(&payload.user_id)
+}
+
+async fn change_encryption_passphrase(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EncryptionChangeRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let result = match UserDomainStore::new(&state.db_path).await {
+        Ok(store) => {
+            store
+                .change_passphrase(&payload.user_id, &payload.current, &payload.next)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(()) => encryption_status_response(&payload.user_id),
+        Err(err) => encryption_error_response(err),
+    }
+}
+
+async fn catch_up(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<CatchUpQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let since = normalize_unix_timestamp(query.since).unwrap_or(0);
+    match build_catch_up(&state.db_path, &query.user_id, since).await {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Composes today's digest on demand; users without a schedule get the
+/// default sections so they can see what enabling it would send.
+async fn digest_preview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<DigestPreviewQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let tools = Config::load(&state.db_path).ok().and_then(|cfg| cfg.tools);
+    let config = DigestConfig::from_tools(tools.as_ref());
+    let scheduled = config.schedule_for(&query.user_id).cloned();
+    let schedule = scheduled.clone().unwrap_or_default();
+    match compose_user_digest(&state.db_path, &query.user_id, &schedule, now_ts()).await {
+        Ok(digest) => (
+            StatusCode::OK,
+            Json(DigestPreviewResponse {
+                counts: digest.counts.into_iter().collect(),
+                text: digest.text,
+                scheduled: scheduled.is_some(),
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+const DEFAULT_HEATMAP_DAYS: u32 = 56;
+
+async fn activity_heatmap(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ActivityHeatmapQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let days = query.days.unwrap_or(DEFAULT_HEATMAP_DAYS).clamp(1, 365);
+    let since = now_ts() - days as i64 * 24 * 60 * 60;
+    let result = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => {
+            store
+                .transition_times(&query.user_id, crate::insights::WORK_STATUSES, since)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(times) => (
+            StatusCode::OK,
+            Json(crate::insights::ActivityHeatmap::from_timestamps(
+                times, days,
+            )),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+const DEFAULT_AGGREGATE_DAYS: u32 = 28;
+
+async fn aggregate_insights(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ActivityHeatmapQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let days = query.days.unwrap_or(DEFAULT_AGGREGATE_DAYS).clamp(1, 365);
+    match build_aggregate_insights(&state.db_path, &query.user_id, days, now_ts()).await {
+        Ok(insights) => (StatusCode::OK, Json(insights)).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Reduces inbox items active in the window to hashed work samples before
+/// anything is aggregated; titles and refs never reach the insights code.
+async fn build_aggregate_insights(
+    db_path: &str,
+    user_id: &str,
+    days: u32,
+    now: i64,
+) -> Result<crate::insights::AggregateInsights> {
+    let since = now - days as i64 * 24 * 60 * 60;
+    let mut moves: HashMap<String, (Option<i64>, Option<i64>)> = HashMap::new();
+    for transition in InboxStateStore::new(db_path)
+        .await?
+        .transitions_since(user_id, since)
+        .await?
+    {
+        let entry = moves.entry(transition.origin_ref).or_default();
+        match transition.to_status.as_str() {
+            "in_progress" => {
+                entry.0.get_or_insert(transition.created_at);
+            }
+            "done" => entry.1 = Some(transition.created_at),
+            _ => {}
+        }
+    }
+
+    let salt = crate::insights::insights_salt()?;
+    let samples = build_inbox_items(db_path, user_id, 2000, true)
+        .await?
+        .into_iter()
+        .filter(|item| !matches!(item.source_type.as_str(), "approval" | "consent"))
+        .filter_map(|item| {
+            let (started_at, done_at) = moves.remove(&item.origin_ref).unwrap_or_default();
+            let done_at = done_at.or_else(|| {
+                (item.status == "done" && item.updated_at >= since).then_some(item.updated_at)
+            });
+            if item.created_at < since && started_at.is_none() && done_at.is_none() {
+                return None;
+            }
+            Some(crate::insights::WorkSample {
+                item: crate::insights::anonymize(&salt, &item.origin_ref),
+                tags: item.labels,
+                created_at: item.created_at,
+                started_at,
+                done_at,
+                estimate_minutes: item.estimate_likely_minutes,
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(crate::insights::AggregateInsights::compute(
+        &samples,
+        days,
+        &crate::insights::Noise::new(salt, crate::insights::DEFAULT_EPSILON),
+    ))
+}
+
+const CATCH_UP_SECTION_LIMIT: usize = 50;
+
+async fn build_catch_up(db_path: &str, user_id: &str, since: i64) -> Result<CatchUpResponse> {
+    let until = now_ts();
+    let items = build_inbox_items(db_path, user_id, 500, true).await?;
+
+    let config = Config::load(db_path).ok();
+    let tools_json = config
+        .as_ref()
+        .and_then(|cfg| cfg.tools.clone())
+        .unwrap_or(Value::Null);
+    let reminder_db_path =
+        resolve_reminder_db_path(&tools_json).unwrap_or_else(|| db_path.to_string());
+    let reminders = ReminderStore::new(&reminder_db_path)
+        .await?
+        .list_reminders(user_id, crate::reminders::ReminderStatus::All, 500)
+        .await?;
+
+    let mut fired_reminders = Vec::new();
+    let mut fired_refs = HashSet::new();
+    for reminder in reminders {
+        let Some(fired_at) = reminder.fired_at.filter(|at| *at > since) else {
+            continue;
+        };
+        fired_refs.insert(format!("reminder:{}", reminder.id));
+        fired_reminders.push(CatchUpEntry {
+            origin_ref: Some(format!("reminder:{}", reminder.id)),
+            title: reminder.title,
+            detail: reminder.target_ref,
+            at: fired_at,
+        });
+    }
+
+    let mut new_items = Vec::new();
+    let mut completed_items = Vec::new();
+    for item in items {
+        if item.created_at > since {
+            new_items.push(CatchUpEntry {
+                origin_ref: Some(item.origin_ref.clone()),
+                title: item.title.clone(),
+                detail: Some(item.source_type.clone()),
+                at: item.created_at,
+            });
+        }
+        // Fired reminders close themselves; they are reported separately.
+        if item.status == "done" && item.updated_at > since && !fired_refs.contains(&item.id) {
+            completed_items.push(CatchUpEntry {
+                origin_ref: Some(item.origin_ref),
+                title: item.title,
+                detail: Some(item.source_type),
+                at: item.updated_at,
+            });
+        }
+    }
+
+    let mut agent_actions = Vec::new();
+    let mut payments = Vec::new();
+    let log_content = match ui_event_log_path(config.as_ref()) {
+        Some(path) => tokio::fs::read_to_string(path).await.unwrap_or_default(),
+        None => String::new(),
+    };
+    for event in log_content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+    {
+        let field = |key: &str| event.get(key).and_then(|value| value.as_str());
+        let timestamp = event
+            .get("timestamp")
+            .and_then(|value| value.as_i64())
+            .unwrap_or(0);
+        if field("user_id") != Some(user_id)
+            || timestamp <= since
+            || field("event_type") != Some("tool")
+            || field("status") != Some("success")
+        {
+            continue;
+        }
+        let tool = field("tool").unwrap_or("tool");
+        if tool == "context" {
+            continue;
+        }
+        let action = event
+            .pointer("/payload/args/action")
+            .and_then(|value| value.as_str());
+        let entry = CatchUpEntry {
+            origin_ref: None,
+            title: match action {
+                Some(action) => format!("{tool} {action}"),
+                None => tool.to_string(),
+            },
+            detail: action.map(str::to_string),
+            at: timestamp,
+        };
+        if tool == "solana" && action.is_some_and(|action| action.contains("transfer")) {
+            payments.push(entry);
+        } else {
+            agent_actions.push(entry);
+        }
+    }
+
+    let mut response = CatchUpResponse {
+        since,
+        until,
+        summary: String::new(),
+        agent_context: String::new(),
+        new_items: newest_first(new_items),
+        completed_items: newest_first(completed_items),
+        fired_reminders: newest_first(fired_reminders),
+        agent_actions: newest_first(agent_actions),
+        payments: newest_first(payments),
+    };
+    response.summary = catch_up_summary(&response);
+    response.agent_context = catch_up_agent_context(&response);
+    Ok(response)
+}
+
+fn newest_first(mut entries: Vec<CatchUpEntry>) -> Vec<CatchUpEntry> {
+    entries.sort_by(|a, b| b.at.cmp(&a.at));
+    entries.truncate(CATCH_UP_SECTION_LIMIT);
+    entries
+}
+
+fn catch_up_summary(response: &CatchUpResponse) -> String {
+    let parts = [
+        (response.new_items.len(), "new item", "new items"),
+        (response.completed_items.len(), "completed", "completed"),
+        (
+            response.fired_reminders.len(),
+            "reminder fired",
+            "reminders fired",
+        ),
+        (
+            response.agent_actions.len(),
+            "agent action",
+            "agent actions",
+        ),
+        (response.payments.len(), "payment", "payments"),
+    ]
+    .into_iter()
+    .filter(|(count, _, _)| *count > 0)
+    .map(|(count, one, many)| format!("{count} {}", if count == 1 { one } else { many }))
+    .collect::<Vec<_>>();
+    parts.join(", ")
+}
+
+fn catch_up_agent_context(response: &CatchUpResponse) -> String {
+    if response.summary.is_empty() {
+        return String::new();
+    }
+    let mut lines = vec![format!(
+        "Changes since the user last looked ({}): {}.",
+        Local
+            .timestamp_opt(response.since, 0)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|| response.since.to_string()),
+        response.summary
+    )];
+    for (label, entries) in [
+        ("New", &response.new_items),
+        ("Completed", &response.completed_items),
+        ("Reminders fired", &response.fired_reminders),
+        ("Agent actions", &response.agent_actions),
+        ("Payments", &response.payments),
+    ] {
+        if entries.is_empty() {
+            continue;
+        }
+        let titles = entries
+            .iter()
+            .take(5)
+            .map(|entry| entry.title.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        lines.push(format!("- {label}: {titles}"));
+    }
+    lines.push(
+        "If relevant, open with a brief recap of these changes before answering.".to_string(),
+    );
+    lines.join("\n")
+}
+
+/// Inbound GitHub deliveries. There is no bearer token here; the HMAC
+/// signature over the raw body is the only credential.
+async fn github_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let config = GithubWebhookConfig::from_tools(tools.as_ref());
+    let secret = match crate::vault::get_secret(&config.secret_name) {
+        Ok(Some(secret)) if !secret.is_empty() => secret,
+        Ok(_) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: format!("Vault secret '{}' is not set", config.secret_name),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let signature = headers
+        .get(github::SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    if !github::verify_signature(&secret, &body, signature) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Invalid webhook signature".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let event = headers
+        .get(github::EVENT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid JSON payload: {err}"),
+                }),
+            )
+                .into_response()
+        }
+    };
+    if event == "ping" {
+        return (StatusCode::OK, Json(json!({"status": "pong"}))).into_response();
+    }
+    if !github::repository(&payload).is_some_and(|repo| config.accepts_repo(repo)) {
+        return (
+            StatusCode::OK,
+            Json(json!({"status": "ignored", "applied": 0})),
+        )
+            .into_response();
+    }
+
+    match apply_github_actions(&state, &config, &github::actions(event, &payload)).await {
+        Ok(applied) => (
+            StatusCode::OK,
+            Json(json!({"status": "ok", "applied": applied})),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Opens and resolves external items for mapped users, keeping any inbox
+/// status override in step so a resolved item leaves the human lane.
+async fn apply_github_actions(
+    state: &AppState,
+    config: &GithubWebhookConfig,
+    actions: &[GithubAction],
+) -> Result<usize> {
+    let store = ExternalItemStore::new(&state.db_path).await?;
+    let status_store = InboxStateStore::new(&state.db_path).await?;
+    let mut applied = 0;
+    for action in actions {
+        match action {
+            GithubAction::Open { login, update } => {
+                let Some(user_id) = config.user_for(login) else {
+                    continue;
+                };
+                open_external_item(state, &store, &status_store, user_id, update).await?;
+                applied += 1;
+            }
+            GithubAction::Resolve { login, origin_ref } => {
+                let users = match login {
+                    Some(login) => config
+                        .user_for(login)
+                        .map(|user| vec![user.to_string()])
+                        .unwrap_or_default(),
+                    None => store.open_users(origin_ref).await?,
+                };
+                for user_id in users {
+                    if resolve_external_item(
+                        state,
+                        &store,
+                        &status_store,
+                        &user_id,
+                        origin_ref,
+                        github::SOURCE,
+                    )
+                    .await?
+                    {
+                        applied += 1;
+                    }
+                }
+            }
+        }
+    }
+    Ok(applied)
+}
+
+async fn open_external_item(
+    state: &AppState,
+    store: &ExternalItemStore,
+    status_store: &InboxStateStore,
+    user_id: &str,
+    update: &ExternalUpdate,
+) -> Result<ExternalItem> {
+    let (item, opened) = store.upsert(user_id, update).await?;
+    if opened {
+        status_store
+            .set_status(user_id, &item.origin_ref, "new")
+            .await?;
+    }
+    announce_external_item(state, &item, if opened { "opened" } else { "updated" });
+    Ok(item)
+}
+
+/// Resolves an open item and moves its inbox status to done; false when
+/// the user had no open item under `origin_ref`.
+async fn resolve_external_item(
+    state: &AppState,
+    store: &ExternalItemStore,
+    status_store: &InboxStateStore,
+    user_id: &str,
+    origin_ref: &str,
+    source: &str,
+) -> Result<bool> {
+    if !store.resolve(user_id, origin_ref).await? {
+        return Ok(false);
+    }
+    let previous = status_store
+        .list_statuses(user_id, 2000)
+        .await?
+        .remove(origin_ref)
+        .unwrap_or_else(|| "new".to_string());
+    status_store.set_status(user_id, origin_ref, "done").await?;
+    status_store
+        .record_transition(user_id, origin_ref, &previous, "done")
+        .await?;
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "external_item".to_string(),
+        user_id: user_id.to_string(),
+        tool: source.to_string(),
+        status: "resolved".to_string(),
+        payload: json!({"origin_ref": origin_ref}),
+        timestamp: now_ts(),
+    });
+    Ok(true)
+}
+
+/// Inbound Zapier deliveries for Zaps with an `inbound` block. The hook's
+/// vault secret, sent as a bearer token, is the only credential.
+async fn zapier_webhook(
+    State(state): State<AppState>,
+    axum::extract::Path(name): axum::extract::Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let Some(inbound) = ZapierInbound::find(tools.as_ref(), &name) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No inbound Zapier webhook named '{name}'"),
+            }),
+        )
+            .into_response();
+    };
+    let secret = match crate::vault::get_secret(&inbound.secret_name) {
+        Ok(Some(secret)) if !secret.is_empty() => secret,
+        Ok(_) => {
+            return (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: format!("Vault secret '{}' is not set", inbound.secret_name),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+    };
+    let presented = header(zapier::TOKEN_HEADER);
+    let presented = if presented.is_empty() {
+        header("authorization")
+            .strip_prefix("Bearer ")
+            .unwrap_or_default()
+    } else {
+        presented
+    };
+    if !zapier::token_matches(&secret, presented) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Invalid webhook token".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid JSON payload: {err}"),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let action = match zapier::action(&inbound, &payload, &body) {
+        Ok(action) => action,
+        Err(error) => {
+            return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
+    };
+
+    let applied = async {
+        let store = ExternalItemStore::new(&state.db_path).await?;
+        let status_store = InboxStateStore::new(&state.db_path).await?;
+        match action {
+            ZapierAction::Open(update) => {
+                let item =
+                    open_external_item(&state, &store, &status_store, &inbound.user, &update)
+                        .await?;
+                Ok::<_, ButterflyBotError>(json!({"status": "ok", "origin_ref": item.origin_ref}))
+            }
+            ZapierAction::Resolve { origin_ref } => {
+                let resolved = resolve_external_item(
+                    &state,
+                    &store,
+                    &status_store,
+                    &inbound.user,
+                    &origin_ref,
+                    zapier::SOURCE,
+                )
+                .await?;
+                Ok(json!({
+                    "status": if resolved { "ok" } else { "ignored" },
+                    "origin_ref": origin_ref,
+                }))
+            }
+        }
+    }
+    .await;
+    match applied {
+        Ok(body) => (StatusCode::OK, Json(body)).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn announce_external_item(state: &AppState, item: &ExternalItem, status: &str) {
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "external_item".to_string(),
+        user_id: item.user_id.clone(),
+        tool: item.source.clone(),
+        status: status.to_string(),
+        payload: json!({
+            "origin_ref": item.origin_ref,
+            "title": item.title,
+            "url": item.url,
+        }),
+        timestamp: now_ts(),
+    });
+}
+
+async fn outbox_deliveries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<OutboxDeliveriesQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let deliveries = match OutboxStore::new(&state.db_path).await {
+        Ok(store) => store.list(&query.user_id, limit).await,
+        Err(err) => Err(err),
+    };
+    match deliveries {
+        Ok(deliveries) => (
+            StatusCode::OK,
+            Json(OutboxDeliveriesResponse { deliveries }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// How the user's todo estimates compare with how long todos really took.
+async fn estimate_accuracy(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<EstimateAccuracyQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let todo_db_path = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| serde_json::to_value(config).ok())
+        .and_then(|value| resolve_todo_db_path(&value))
+        .unwrap_or_else(|| state.db_path.clone());
+    let categories = match TodoStore::new(&todo_db_path).await {
+        Ok(store) => store.estimate_accuracy(&query.user_id).await,
+        Err(err) => Err(err),
+    };
+    match categories {
+        Ok(categories) => (
+            StatusCode::OK,
+            Json(EstimateAccuracyResponse { categories }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn list_projects(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ProjectsQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let todo_db_path = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| serde_json::to_value(config).ok())
+        .and_then(|value| resolve_todo_db_path(&value))
+        .unwrap_or_else(|| state.db_path.clone());
+    let projects = match ProjectStore::new(&todo_db_path).await {
+        Ok(store) => store.list_projects(&query.user_id).await,
+        Err(err) => Err(err),
+    };
+    match projects {
+        Ok(projects) => (StatusCode::OK, Json(ProjectsResponse { projects })).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn dashboard_config(state: &AppState) -> DashboardConfig {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    DashboardConfig::from_tools(tools.as_ref())
+}
+
+fn dashboard_disabled() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Dashboard is disabled".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// The static dashboard page; it fetches everything it shows itself.
+async fn dashboard_page(State(state): State<AppState>) -> Response {
+    if !dashboard_config(&state).enabled {
+        return dashboard_disabled();
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/html; charset=utf-8")
+        .header("cache-control", "no-store")
+        .header(
+            "content-security-policy",
+            "default-src 'none'; script-src 'unsafe-inline'; style-src 'unsafe-inline'; \
+             connect-src 'self'; frame-ancestors 'none'",
+        )
+        .body(Body::from(dashboard::PAGE))
+        .unwrap()
+}
+
+/// Inbox status, upcoming reminders and recent audit events in one read.
+async fn dashboard_summary(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<DashboardQuery>,
+) -> Response {
+    let config = dashboard_config(&state);
+    if !config.enabled {
+        return dashboard_disabled();
+    }
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let items = match build_inbox_items(&state.db_path, &query.user_id, 500, false).await {
+        Ok(items) => items
+            .into_iter()
+            .map(|item| DashboardItem {
+                actionable: is_actionable(&item),
+                title: item.title,
+                source_type: item.source_type,
+                status: item.status,
+                priority: item.priority,
+                due_at: item.due_at,
+            })
+            .collect(),
+        Err(err) => return integration_error(err),
+    };
+    let reminders = match state
+        .reminder_store
+        .list_reminders(
+            &query.user_id,
+            crate::reminders::ReminderStatus::Open,
+            dashboard::REMINDER_LIMIT,
+        )
+        .await
+    {
+        Ok(reminders) => reminders,
+        Err(err) => return integration_error(err),
+    };
+    let events = match AuditStore::new(&state.db_path).await {
+        Ok(store) => store
+            .query(&AuditQuery {
+                user_id: Some(query.user_id.clone()),
+                limit: dashboard::EVENT_LIMIT,
+                ..AuditQuery::default()
+            })
+            .await
+            .map(|page| page.events),
+        Err(err) => Err(err),
+    };
+    let events = match events {
+        Ok(events) => events,
+        Err(err) => return integration_error(err),
+    };
+
+    let summary = DashboardSummary::build(now_ts(), &config, items, reminders, events);
+    (StatusCode::OK, Json(summary)).into_response()
+}
+
+fn remote_uploader(state: &AppState) -> Result<Option<RemoteUploader>> {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    RemoteUploader::from_tools(tools.as_ref())
+}
+
+/// Bad settings are the caller's problem, a failing remote is a bad gateway.
+fn integration_error(err: ButterflyBotError) -> Response {
+    let status = match err {
+        ButterflyBotError::Config(_) | ButterflyBotError::SecurityPolicy(_) => {
+            StatusCode::BAD_REQUEST
+        }
+        ButterflyBotError::Http(_) => StatusCode::BAD_GATEWAY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: err.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+async fn test_remote_storage(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let uploader = match remote_uploader(&state) {
+        Ok(Some(uploader)) => uploader,
+        Ok(None) => {
+            return integration_error(ButterflyBotError::Config(
+                "No remote storage target is configured".to_string(),
+            ))
+        }
+        Err(err) => return integration_error(err),
+    };
+    match uploader.test_connection().await {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(err) => integration_error(err),
+    }
+}
+
+async fn backup_status(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let config = BackupConfig::from_tools(tools.as_ref());
+    let directory = config.directory_for(&state.db_path);
+    match backup::list_snapshots(&directory) {
+        Ok(mut snapshots) => {
+            snapshots.reverse();
+            (
+                StatusCode::OK,
+                Json(BackupStatusResponse {
+                    enabled: config.enabled,
+                    interval_hours: config.interval_hours,
+                    keep: config.keep,
+                    directory: directory.to_string_lossy().to_string(),
+                    last_run: backup::last_run(),
+                    snapshots,
+                    replication_enabled: ReplicationConfig::from_tools(tools.as_ref()).enabled,
+                    replication: replication::last_run(),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => integration_error(err),
+    }
+}
+
+/// Backs up immediately, even when scheduled backups are off.
+async fn run_backup(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let config = BackupConfig::from_tools(tools.as_ref());
+    let run = run_backup_now(
+        &state.db_path,
+        &config,
+        tools.as_ref(),
+        now_ts(),
+        &state.ui_event_tx,
+    )
+    .await;
+    match &run.error {
+        None => (StatusCode::OK, Json(run)).into_response(),
+        Some(error) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: error.clone(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Uploads the user's audit trail as sealed JSON lines.
+async fn archive_audit_to_remote(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ArchiveAuditRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let uploader = match remote_uploader(&state) {
+        Ok(Some(uploader)) => uploader,
+        Ok(None) => {
+            return integration_error(ButterflyBotError::Config(
+                "No remote storage target is configured".to_string(),
+            ))
+        }
+        Err(err) => return integration_error(err),
+    };
+    if !uploader.config().enabled_for(RemoteCategory::AuditArchives) {
+        return (
+            StatusCode::OK,
+            Json(ArchiveAuditResponse {
+                status: "disabled".to_string(),
+                key: None,
+                events: 0,
+            }),
+        )
+            .into_response();
+    }
+
+    let store = match AuditStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => return integration_error(err),
+    };
+    let mut lines = Vec::new();
+    let mut before_id = None;
+    loop {
+        let page = match store
+            .query(&AuditQuery {
+                user_id: Some(payload.user_id.clone()),
+                since: payload.since,
+                before_id,
+                limit: crate::audit::MAX_PAGE_SIZE,
+                ..AuditQuery::default()
+            })
+            .await
+        {
+            Ok(page) => page,
+            Err(err) => return integration_error(err),
+        };
+        // Each page is older than the one before; keep the archive in order.
+        let mut chunk: Vec<String> = page
+            .events
+            .iter()
+            .filter_map(|event| serde_json::to_string(event).ok())
+            .collect();
+        chunk.append(&mut lines);
+        lines = chunk;
+        match page.next_before_id {
+            Some(next) => before_id = Some(next),
+            None => break,
+        }
+    }
+
+    let mut body = lines.join("\n");
+    body.push('\n');
+    let user_hash = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(
+        payload.user_id.as_bytes(),
+    ));
+    let name = format!(
+        "audit-{}-{}.jsonl",
+        user_hash.get(..12).unwrap_or_default(),
+        chrono::Utc::now().format("%Y%m%dT%H%M%SZ")
+    );
+    match uploader
+        .upload(RemoteCategory::AuditArchives, &name, body.as_bytes())
+        .await
+    {
+        Ok(key) => (
+            StatusCode::OK,
+            Json(ArchiveAuditResponse {
+                status: "ok".to_string(),
+                key,
+                events: lines.len(),
+            }),
+        )
+            .into_response(),
+        Err(err) => integration_error(err),
+    }
+}
+
+fn calendar_config(state: &AppState) -> CalendarConfig {
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    CalendarConfig::from_tools(tools.as_ref())
+}
+
+/// Syncs the user's calendar sources now instead of waiting for the poll.
+async fn calendar_sync(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CalendarSyncRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let config = calendar_config(&state);
+    let store = match CalendarStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => return integration_error(err),
+    };
+    let reports = crate::calendar::sync(&store, &config, Some(&payload.user_id)).await;
+    for report in &reports {
+        let _ = state.ui_event_tx.send(calendar_sync_event(report));
+    }
+    (StatusCode::OK, Json(CalendarSyncResponse { reports })).into_response()
+}
+
+/// Polls the user's mail accounts now instead of waiting for the job.
+async fn email_poll(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<EmailPollRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let config = Config::load(&state.db_path).ok();
+    let todo_db_path = config
+        .as_ref()
+        .and_then(|config| serde_json::to_value(config).ok())
+        .and_then(|value| resolve_todo_db_path(&value))
+        .unwrap_or_else(|| state.db_path.clone());
+    let config = EmailConfig::from_tools(config.and_then(|config| config.tools).as_ref());
+    let store = match TodoStore::new(&todo_db_path).await {
+        Ok(store) => store,
+        Err(err) => return integration_error(err),
+    };
+    let reports = crate::email::poll(&store, &config, Some(&payload.user_id), now_ts()).await;
+    for report in &reports {
+        let _ = state.ui_event_tx.send(email_ingest_event(report));
+    }
+    (StatusCode::OK, Json(EmailPollResponse { reports })).into_response()
+}
+
+/// Hands out the user's subscribable reminders feed path.
+async fn calendar_feed_link(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<CalendarFeedRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    if !calendar_config(&state).feed {
+        return integration_error(ButterflyBotError::Config(
+            "The reminders calendar feed is turned off (tools.settings.calendar.feed)".to_string(),
+        ));
+    }
+    match crate::calendar::feed_token(&payload.user_id, payload.rotate) {
+        Ok(token) => (
+            StatusCode::OK,
+            Json(CalendarFeedResponse {
+                path: format!("/calendar/feed/{}/{token}.ics", payload.user_id),
+            }),
+        )
+            .into_response(),
+        Err(err) => integration_error(err),
+    }
+}
+
+/// The reminders feed itself. Calendar apps can't send a bearer token, so
+/// the token in the path is the credential.
+async fn calendar_feed(
+    State(state): State<AppState>,
+    axum::extract::Path((user_id, file)): axum::extract::Path<(String, String)>,
+) -> Response {
+    let token = file.strip_suffix(".ics").unwrap_or(&file);
+    let authorized = calendar_config(&state).feed
+        && crate::calendar::verify_feed_token(&user_id, token).unwrap_or(false);
+    if !authorized {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Unknown calendar feed".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let reminders = match state
+        .reminder_store
+        .list_reminders(&user_id, crate::reminders::ReminderStatus::Open, 500)
+        .await
+    {
+        Ok(reminders) => reminders,
+        Err(err) => return integration_error(err),
+    };
+    let body = crate::calendar::ics::reminders_feed("Butterfly reminders", &reminders, now_ts());
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/calendar; charset=utf-8")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+async fn reminder_delivery_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(mut query): axum::extract::Query<ReminderDeliveryEventsQuery>,
+) -> impl IntoResponse {
+    match authorize_user(&state, &headers, query.user_id.as_deref()).await {
+        Ok(Some(user_id)) => query.user_id = Some(user_id),
+        Ok(None) => {}
+        Err(err) => return err.into_response(),
+    }
+
+    let config = Config::load(&state.db_path).ok();
+    let Some(path) = reminders_audit_log_path(config.as_ref()) else {
+        return (
+            StatusCode::OK,
+            Json(ReminderDeliveryEventsResponse { events: vec![] }),
+        )
+            .into_response();
+    };
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(_) => {
+            return (
+                StatusCode::OK,
+                Json(ReminderDeliveryEventsResponse { events: vec![] }),
+            )
+                .into_response()
+        }
+    };
+
+    let mut events = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<Value>(line).ok())
+        .filter(|event| {
+            if let Some(user_id) = &query.user_id {
+                event
+                    .get("user_id")
+                    .and_then(|value| value.as_str())
+                    .map(|value| value == user_id)
+                    .unwrap_or(false)
+            } else {
+                true
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if events.len() > limit {
+        let keep_from = events.len() - limit;
+        events = events.split_off(keep_from);
+    }
+
+    (
+        StatusCode::OK,
+        Json(ReminderDeliveryEventsResponse { events }),
+    )
+        .into_response()
+}
+
+async fn reminder_dead_letters(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<DeadLettersQuery>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let limit = query.limit.unwrap_or(100).clamp(1, 500);
+    match state
+        .reminder_store
+        .dead_letters(&query.user_id, limit)
+        .await
+    {
+        Ok(dead_letters) => {
+            (StatusCode::OK, Json(DeadLettersResponse { dead_letters })).into_response()
+        }
+        Err(err) => integration_error(err),
+    }
+}
+
+/// Puts a dead-lettered delivery back in the queue with fresh attempts; the
+/// dispatch job picks it up on its next tick.
+async fn redrive_reminder_dead_letter(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DeadLetterActionRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    match state
+        .reminder_store
+        .redrive_dead_letter(&payload.user_id, payload.id)
+        .await
+    {
+        Ok(Some(dead_letter)) => {
+            log_reminder_delivery(
+                &state.ui_event_tx,
+                reminders_audit_log_path(Config::load(&state.db_path).ok().as_ref()).as_deref(),
+                &payload.user_id,
+                dead_letter.reminder_id,
+                "redriven",
+                &json!({
+                    "id": dead_letter.reminder_id,
+                    "title": dead_letter.title,
+                    "channel": dead_letter.channel,
+                }),
+                now_ts(),
+            );
+            (StatusCode::OK, Json(json!({"status": "ok"}))).into_response()
+        }
+        Ok(None) => dead_letter_not_found(),
+        Err(err) => integration_error(err),
+    }
+}
+
+async fn discard_reminder_dead_letter(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DeadLetterActionRequest>,
+) -> Response {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    match state
+        .reminder_store
+        .discard_dead_letter(&payload.user_id, payload.id)
+        .await
+    {
+        Ok(true) => (StatusCode::OK, Json(json!({"status": "ok"}))).into_response(),
+        Ok(false) => dead_letter_not_found(),
+        Err(err) => integration_error(err),
+    }
+}
+
+fn dead_letter_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Dead-lettered delivery not found".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+async fn audit_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(mut query): axum::extract::Query<AuditEventsQuery>,
+) -> impl IntoResponse {
+    match authorize_user(&state, &headers, query.user_id.as_deref()).await {
+        Ok(Some(user_id)) => query.user_id = Some(user_id),
+        Ok(None) => {}
+        Err(err) => return err.into_response(),
+    }
+
+    let severity = match query.severity.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => match AuditSeverity::parse(value) {
+            Some(severity) => Some(severity),
+            None => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: format!("unknown severity '{value}'"),
+                    }),
+                )
+                    .into_response()
+            }
+        },
+    };
+
+    let store = match AuditStore::new(&state.db_path).await {
+        Ok(store) => store,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let config = Config::load(&state.db_path).ok();
+    if let Some(path) = ui_event_log_path(config.as_ref()) {
+        if let Err(err) = store.import_legacy_log(&path).await {
+            tracing::warn!(error = %err, "Failed to import legacy UI event log");
+        }
+    }
+
+    let audit_query = AuditQuery {
+        user_id: query.user_id,
+        event_types: query
+            .event_type
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+            .collect(),
+        origin_ref: query
+            .origin_ref
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty()),
+        severity,
+        since: query.since,
+        until: query.until,
+        before_id: query.before_id,
+        limit: query
+            .limit
+            .unwrap_or(crate::audit::DEFAULT_PAGE_SIZE)
+            .clamp(1, crate::audit::MAX_PAGE_SIZE),
+    };
+    match store.query(&audit_query).await {
+        Ok(page) => (
+            StatusCode::OK,
+            Json(AuditEventsResponse {
+                events: page.events,
+                next_before_id: page.next_before_id,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn parse_plan_step_title(step: &Value) -> Option<String> {
+    if let Some(text) = step.as_str() {
+        let trimmed = text.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
+        }
+    }
+
+    for key in ["title", "description", "name", "text", "step"] {
+        if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
+            let trimmed = value.trim();
+            if !trimmed.is_empty() {
+                return Some(trimmed.to_string());
+            }
+        }
+    }
+
+    None
+}
+
+fn normalize_unix_timestamp(value: i64) -> Option<i64> {
+    if value <= 0 {
+        return None;
+    }
+    if value >= 1_000_000_000_000 {
+        return Some(value / 1000);
+    }
+    Some(value)
+}
+
+fn parse_yyyy_mm_dd_to_unix(value: &str) -> Option<i64> {
+    let trimmed = value.trim();
+    let normalized = trimmed.replace('/', "-");
+    let mut parts = normalized.split('-');
+    let year = parts.next()?.trim().parse::<i32>().ok()?;
+    let month = parts.next()?.trim().parse::<u8>().ok()?;
+    let day = parts.next()?.trim().parse::<u8>().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    let date = Date::from_calendar_date(year, time::Month::try_from(month).ok()?, day).ok()?;
+    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    Some(
+        PrimitiveDateTime::new(date, Time::MIDNIGHT)
+            .assume_offset(local_offset)
+            .unix_timestamp(),
+    )
+}
+
+fn parse_due_at_value(value: &Value, user_id: &str) -> Option<i64> {
+    match value {
+        Value::Number(number) => number
+            .as_i64()
+            .and_then(normalize_unix_timestamp)
+            .or_else(|| {
+                number
+                    .as_u64()
+                    .and_then(|n| normalize_unix_timestamp(n as i64))
+            }),
+        Value::String(raw) => {
+            let trimmed = raw.trim();
+            if trimmed.is_empty() {
+                return None;
+            }
+            if let Ok(ts) = trimmed.parse::<i64>() {
+                return normalize_unix_timestamp(ts);
+            }
+            if let Some(ts) = parse_yyyy_mm_dd_to_unix(trimmed) {
+                return Some(normalize_due_year_if_stale(ts, trimmed));
+            }
+            if let Some(ts) = parse_due_at_from_text(trimmed, now_ts(), user_id) {
+                return Some(ts);
+            }
+            static DATE_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+            let date_re = DATE_RE.get_or_init(|| Regex::new(r"(\d{4}[-/]\d{2}[-/]\d{2})").unwrap());
+            let captures = date_re.captures(trimmed)?;
+            parse_yyyy_mm_dd_to_unix(captures.get(1)?.as_str())
+        }
+        _ => None,
+    }
+}
+
+fn normalize_nlp_due_text(input: &str) -> String {
+    static FROM_START_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    static FROM_NOW_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+    let from_start_re = FROM_START_RE.get_or_init(|| {
+        Regex::new(r"(?i)\b(\d+)\s*(day|days|week|weeks|month|months)\s+from\s+start\b").unwrap()
+    });
+    let from_now_re = FROM_NOW_RE.get_or_init(|| {
+        Regex::new(r"(?i)\b(\d+)\s*(day|days|week|weeks|month|months)\s+from\s+now\b").unwrap()
+    });
+
+    let normalized = from_start_re.replace_all(input, "in $1 $2").to_string();
+    from_now_re.replace_all(&normalized, "in $1 $2").to_string()
+}
+
+fn extract_explicit_year(input: &str) -> Option<i32> {
+    static YEAR_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = YEAR_RE.get_or_init(|| Regex::new(r"\b(20\d{2})\b").unwrap());
+    re.captures(input)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+}
+
+fn with_local_year(ts: i64, year: i32) -> Option<i64> {
+    let utc_dt = time::OffsetDateTime::from_unix_timestamp(ts).ok()?;
+    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let local_dt = utc_dt.to_offset(local_offset);
+    let month = local_dt.month();
+    let mut day = local_dt.day();
+
+    let date = loop {
+        if let Ok(value) = Date::from_calendar_date(year, month, day) {
+            break value;
+        }
+        if day == 1 {
+            return None;
+        }
+        day -= 1;
+    };
+
+    let pdt = PrimitiveDateTime::new(
+        date,
+        Time::from_hms(local_dt.hour(), local_dt.minute(), local_dt.second()).ok()?,
+    );
+    Some(pdt.assume_offset(local_offset).unix_timestamp())
+}
+
+fn normalize_due_year_if_stale(ts: i64, input: &str) -> i64 {
+    let now = now_ts();
+    let Some(explicit_year) = extract_explicit_year(input) else {
+        return ts;
+    };
+
+    let Ok(now_local) = time::OffsetDateTime::from_unix_timestamp(now) else {
+        return ts;
+    };
+    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
+    let current_year = now_local.to_offset(local_offset).year();
+
+    if explicit_year >= current_year {
+        return ts;
+    }
+
+    if let Some(mut adjusted) = with_local_year(ts, current_year) {
+        if adjusted < now {
+            if let Some(next_year) = with_local_year(ts, current_year + 1) {
+                adjusted = next_year;
+            }
+        }
+        return adjusted;
+    }
+
+    ts
+}
+
+fn parse_due_at_nlp(input: &str, anchor_ts: i64, user_id: &str) -> Option<i64> {
+    let normalized = normalize_nlp_due_text(input);
+    let anchor_ts = if anchor_ts > 0 { anchor_ts } else { now_ts() };
+
+    crate::timezones::parse_phrase(user_id, &normalized, anchor_ts).map(|ts| {
+        let parsed = if crate::date_phrases::has_explicit_time(input) {
+            ts
+        } else {
+            crate::timezones::midnight(user_id, ts)
+        };
+        normalize_due_year_if_stale(parsed, input)
+    })
+}
+
+fn parse_due_at_from_text(input: &str, anchor_ts: i64, user_id: &str) -> Option<i64> {
+    if input.trim().is_empty() {
+        return None;
+    }
+    static DATE_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let date_re = DATE_RE.get_or_init(|| {
+        Regex::new(r"(?i)(?:due(?:\s+date)?|deadline)\s*:\s*(\d{4}[-/]\d{2}[-/]\d{2})").unwrap()
+    });
+    if let Some(captures) = date_re.captures(input) {
+        if let Some(found) = captures.get(1).map(|m| m.as_str()) {
+            if let Some(ts) = parse_yyyy_mm_dd_to_unix(found) {
+                return Some(ts);
+            }
+        }
+    }
+    let fallback_re = DATE_RE.get_or_init(|| Regex::new(r"(\d{4}[-/]\d{2}[-/]\d{2})").unwrap());
+    if let Some(captures) = fallback_re.captures(input) {
+        if let Some(ts) = parse_yyyy_mm_dd_to_unix(captures.get(1)?.as_str()) {
+            return Some(normalize_due_year_if_stale(ts, input));
+        }
+    }
+
+    parse_due_at_nlp(input, anchor_ts, user_id)
+}
+
+fn parse_plan_step_due_at(step: &Value, title: &str, anchor_ts: i64, user_id: &str) -> Option<i64> {
+    for key in [
+        "due_at",
+        "due_ts",
+        "due",
+        "due_date",
+        "due_on",
+        "deadline",
+        "target_date",
+    ] {
+        if let Some(value) = step.get(key).and_then(|v| parse_due_at_value(v, user_id)) {
+            return Some(value);
+        }
+    }
+
+    if let Some(description) = step.get("description").and_then(|v| v.as_str()) {
+        if let Some(ts) = parse_due_at_from_text(description, anchor_ts, user_id) {
+            return Some(ts);
+        }
+    }
+
+    parse_due_at_from_text(title, anchor_ts, user_id)
+}
+
+fn parse_plan_step_story_points(step: &Value, text: &str) -> Option<i32> {
+    for key in ["story_points", "points", "estimate_points"] {
+        if let Some(value) = step.get(key).and_then(|v| v.as_i64()) {
+            if value > 0 {
+                return Some(value as i32);
+            }
+        }
+    }
+
+    static STORY_POINTS_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re =
+        STORY_POINTS_RE.get_or_init(|| Regex::new(r"(?i)story\s*points?\s*:\s*(\d+)").unwrap());
+    re.captures(text)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+        .filter(|value| *value > 0)
+}
+
+fn parse_plan_step_t_shirt_size(step: &Value, text: &str) -> Option<String> {
+    for key in ["t_shirt_size", "tshirt_size", "shirt_size", "size"] {
+        if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
+            let normalized = value.trim().to_ascii_uppercase();
+            if ["XS", "S", "M", "L", "XL", "XXL"].contains(&normalized.as_str()) {
+                return Some(normalized);
+            }
+        }
+    }
+
+    static TSHIRT_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = TSHIRT_RE
+        .get_or_init(|| Regex::new(r"(?i)t-?shirt\s*size\s*:\s*(XS|S|M|L|XL|XXL)").unwrap());
+    re.captures(text)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_ascii_uppercase())
+}
+
+fn parse_time_estimate_minutes_from_text(text: &str) -> Option<i32> {
+    static WEEK_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    static DAY_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    static HOUR_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    static MIN_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+
+    let week_re =
+        WEEK_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*weeks?").unwrap());
+    if let Some(weeks) = week_re
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+    {
+        return Some(weeks.max(1) * 5 * 8 * 60);
+    }
+
+    let day_re =
+        DAY_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*days?").unwrap());
+    if let Some(days) = day_re
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+    {
+        return Some(days.max(1) * 8 * 60);
+    }
+
+    let hour_re =
+        HOUR_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*hours?").unwrap());
+    if let Some(hours) = hour_re
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+    {
+        return Some(hours.max(1) * 60);
+    }
+
+    let min_re =
+        MIN_RE.get_or_init(|| Regex::new(r"(?i)time\s*estimate\s*:\s*(\d+)\s*minutes?").unwrap());
+    min_re
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .and_then(|m| m.as_str().parse::<i32>().ok())
+        .map(|minutes| minutes.max(1))
+}
+
+fn parse_plan_status(value: Option<&str>, default_status: &str) -> String {
+    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
+        "new" => "new".to_string(),
+        "ack" | "acknowledged" => "acknowledged".to_string(),
+        "in_progress" | "in progress" | "started" => "in_progress".to_string(),
+        "blocked" => "blocked".to_string(),
+        "done" | "completed" | "complete" => "done".to_string(),
+        "dismissed" | "archived" => "dismissed".to_string(),
+        _ => default_status.to_string(),
+    }
+}
+
+fn parse_plan_priority(value: Option<&str>) -> String {
+    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
+        "low" => "low".to_string(),
+        "high" => "high".to_string(),
+        "urgent" | "critical" => "urgent".to_string(),
+        _ => "normal".to_string(),
+    }
+}
+
+fn parse_plan_priority_from_text(text: &str) -> Option<String> {
+    static PRIORITY_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = PRIORITY_RE.get_or_init(|| {
+        Regex::new(r"(?i)priority\s*:\s*(low|normal|medium|high|urgent|critical)").unwrap()
+    });
+    let raw = re
+        .captures(text)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str())?;
+    Some(parse_plan_priority(Some(raw)))
+}
+
+fn parse_plan_owner_from_text(text: &str) -> Option<String> {
+    static OWNER_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let re = OWNER_RE.get_or_init(|| Regex::new(r"(?i)owner\s*:\s*(human|agent)").unwrap());
+    re.captures(text)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_ascii_lowercase())
+}
+
+fn build_plan_step_alias_map(plan_id: i32, steps: &[Value]) -> HashMap<String, String> {
+    let mut alias_map = HashMap::new();
+    for (index, step) in steps.iter().enumerate() {
+        let origin_ref = format!("plan_step:{plan_id}:{index}");
+
+        alias_map.insert(index.to_string(), origin_ref.clone());
+        alias_map.insert(format!("step {index}"), origin_ref.clone());
+        alias_map.insert(format!("step {}", index + 1), origin_ref.clone());
+
+        for key in ["id", "ref", "key", "code", "step_id"] {
+            if let Some(value) = step.get(key).and_then(|v| v.as_str()) {
+                let normalized = value.trim().to_ascii_lowercase();
+                if !normalized.is_empty() {
+                    alias_map.insert(normalized, origin_ref.clone());
+                }
+            }
+        }
+
+        if let Some(title) = parse_plan_step_title(step) {
+            let normalized = title.trim().to_ascii_lowercase();
+            if !normalized.is_empty() {
+                alias_map.insert(normalized.clone(), origin_ref.clone());
+                let compact = normalized
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c.is_ascii_whitespace() {
+                            c
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect::<String>()
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !compact.is_empty() {
+                    alias_map.insert(compact, origin_ref.clone());
+                }
+            }
+        }
+    }
+    alias_map
+}
+
+fn parse_plan_step_dependency_refs(
+    plan_id: i32,
+    step: &Value,
+    alias_map: &HashMap<String, String>,
+) -> Vec<String> {
+    fn resolve_alias(alias_map: &HashMap<String, String>, lower: &str) -> Option<String> {
+        if let Some(mapped) = alias_map.get(lower) {
+            return Some(mapped.clone());
+        }
+        if lower.len() < 4 {
+            return None;
+        }
+        alias_map
+            .iter()
+            .filter(|(key, _)| key.contains(lower) || lower.contains(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, value)| value.clone())
+    }
+
+    fn push_dependency_ref(
+        out: &mut Vec<String>,
+        plan_id: i32,
+        alias_map: &HashMap<String, String>,
+        value: &Value,
+    ) {
+        match value {
+            Value::Number(number) => {
+                if let Some(index) = number.as_u64() {
+                    out.push(format!("plan_step:{plan_id}:{index}"));
+                }
+            }
+            Value::String(raw) => {
+                let trimmed = raw.trim();
+                if trimmed.is_empty() {
+                    return;
+                }
+                let lower = trimmed.to_ascii_lowercase();
+                if refs::is_tracked(&lower) {
+                    out.push(refs::canonical(&lower));
+                } else if let Some(mapped) = resolve_alias(alias_map, &lower) {
+                    out.push(mapped.clone());
+                } else if trimmed.contains(',') || trimmed.contains('|') || trimmed.contains(';') {
+                    for token in trimmed.split([',', '|', ';']) {
+                        push_dependency_ref(
+                            out,
+                            plan_id,
+                            alias_map,
+                            &Value::String(token.to_string()),
+                        );
+                    }
+                } else if let Ok(index) = trimmed.parse::<u64>() {
+                    out.push(format!("plan_step:{plan_id}:{index}"));
+                } else if let Some(rest) = lower.strip_prefix("step ") {
+                    if let Ok(index) = rest.trim().parse::<u64>() {
+                        out.push(format!("plan_step:{plan_id}:{index}"));
+                    }
+                } else {
+                    out.push(lower);
+                }
+            }
+            Value::Array(values) => {
+                for entry in values {
+                    push_dependency_ref(out, plan_id, alias_map, entry);
+                }
+            }
+            Value::Object(map) => {
+                if let Some(origin_ref) = map.get("origin_ref").or_else(|| map.get("ref")) {
+                    push_dependency_ref(out, plan_id, alias_map, origin_ref);
+                } else if let Some(id) = map.get("id") {
+                    push_dependency_ref(out, plan_id, alias_map, id);
+                } else if let Some(step_index) = map
+                    .get("step_index")
+                    .or_else(|| map.get("index"))
+                    .or_else(|| map.get("step"))
+                {
+                    push_dependency_ref(out, plan_id, alias_map, step_index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_dependency_refs_from_text(
+        out: &mut Vec<String>,
+        plan_id: i32,
+        alias_map: &HashMap<String, String>,
+        text: &str,
+    ) {
+        static DEPENDS_ON_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+        let re = DEPENDS_ON_RE.get_or_init(|| {
+            Regex::new(
+                r"(?i)(?:depends\s*on|dependencies|blocked\s*by|requires|dependency[_\s-]*refs?)\s*:\s*([^\n\r]+)",
+            )
+            .unwrap()
+        });
+
+        for caps in re.captures_iter(text) {
+            if let Some(raw) = caps.get(1).map(|m| m.as_str()) {
+                for token in raw.split([',', '|', ';']) {
+                    push_dependency_ref(out, plan_id, alias_map, &Value::String(token.to_string()));
+                }
+            }
+        }
+    }
+
+    let mut refs = Vec::new();
+    for key in [
+        "dependency_refs",
+        "dependencyRefs",
+        "depends_on",
+        "dependsOn",
+        "dependencies",
+        "blocked_by",
+        "blockedBy",
+        "requires",
+        "prerequisite",
+        "prerequisites",
+        "after",
+    ] {
+        if let Some(value) = step.get(key) {
+            push_dependency_ref(&mut refs, plan_id, alias_map, value);
+        }
+    }
+
+    if let Some(title) = parse_plan_step_title(step) {
+        push_dependency_refs_from_text(&mut refs, plan_id, alias_map, &title);
+    }
+    if let Some(description) = step.get("description").and_then(|v| v.as_str()) {
+        push_dependency_refs_from_text(&mut refs, plan_id, alias_map, description);
+    }
+
+    refs.sort();
+    refs.dedup();
+    refs
+}
+
+fn extract_plan_step_ref_from_details(details: Option<&str>) -> Option<String> {
+    static PLAN_STEP_REF_RE: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let text = details?;
+    let re = PLAN_STEP_REF_RE
+        .get_or_init(|| Regex::new(r"(?i)planstepref\s*:\s*(plan_step:\d+:\d+)").unwrap());
+    re.captures(text)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str().to_ascii_lowercase())
+}
+
+fn priority_rank(priority: &str) -> i32 {
+    match priority {
+        "critical" => 0,
+        "urgent" => 1,
+        "high" => 2,
+        "normal" => 3,
+        _ => 4,
+    }
+}
+
+/// One-line summary of what an approval would run, for the inbox card.
+fn describe_approval(approval: &PendingApproval) -> String {
+    if approval.capability == inbox_sweep::SWEEP_CAPABILITY {
+        let batch = SweepBatch::from_args(&approval.args).unwrap_or_default();
+        return match &batch.trends {
+            Some(trends) => format!(
+                "Weekly inbox sweep proposes: {}. This week: {trends}",
+                batch.summary()
+            ),
+            None => format!("Weekly inbox sweep proposes: {}", batch.summary()),
+        };
+    }
+    let args = approval
+        .args
+        .as_object()
+        .map(|args| {
+            args.iter()
+                .filter(|(key, _)| key.as_str() != "user_id")
+                .map(|(key, value)| match value.as_str() {
+                    Some(text) => format!("{key}={text}"),
+                    None => format!("{key}={value}"),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_default();
+    if args.is_empty() {
+        format!("Requested by the agent through the {} tool", approval.tool)
+    } else {
+        format!(
+            "Requested by the agent through the {} tool ({args})",
+            approval.tool
+        )
+    }
+}
+
+async fn build_inbox_items(
+    db_path: &str,
+    user_id: &str,
+    limit: usize,
+    include_done: bool,
+) -> Result<Vec<InboxItemResponse>> {
+    let now = now_ts();
+    let config_json = Config::load(db_path)
+        .ok()
+        .and_then(|cfg| cfg.tools)
+        .unwrap_or(Value::Null);
+
+    let reminder_db_path =
+        resolve_reminder_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
+    let todo_db_path = resolve_todo_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
+    let task_db_path = resolve_task_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
+    let plan_db_path = resolve_plan_db_path(&config_json).unwrap_or_else(|| db_path.to_string());
+
+    let reminder_store = ReminderStore::new(&reminder_db_path).await?;
+    let todo_store = TodoStore::new(&todo_db_path).await?;
+    let task_store = TaskStore::new(&task_db_path).await?;
+    let plan_store = PlanStore::new(&plan_db_path).await?;
+    let escalation = EscalationPolicy::from_tools_config(Some(&config_json));
+
+    let reminders = reminder_store
+        .list_reminders(user_id, crate::reminders::ReminderStatus::All, limit)
+        .await?;
+    let todos = todo_store
+        .list_items(user_id, crate::todo::TodoStatus::All, limit)
+        .await?;
+    let tasks = task_store
+        .list_tasks(user_id, crate::tasks::TaskStatus::All, limit)
+        .await?;
+    let plans = plan_store.list_plans(user_id, limit).await?;
+    let plan_ids: Vec<i32> = plans.iter().map(|plan| plan.id).collect();
+    let plan_dependency_refs = plan_store
+        .list_step_dependencies_for_plans(&plan_ids)
+        .await?;
+    let status_store = InboxStateStore::new(db_path).await?;
+    let persisted_statuses = status_store.list_statuses(user_id, 2000).await?;
+    let approval_store = ApprovalStore::new(db_path).await?;
+    let mut approvals = Vec::new();
+    for approval in approval_store.list_pending(user_id, limit).await? {
+        let steps = approval_store.steps(approval.id).await?;
+        approvals.push((approval, steps));
+    }
+    let awaiting_approvals = approval_store.list_awaiting(user_id, limit).await?;
+    let consent_requests = ConsentStore::new(db_path)
+        .await?
+        .list_pending(user_id, limit)
+        .await?;
+    let questions = QuestionStore::new(&plan_db_path)
+        .await?
+        .list(user_id, include_done, limit)
+        .await?;
+    let external_items = ExternalItemStore::new(db_path)
+        .await?
+        .list(user_id, limit)
+        .await?;
+    let calendar_config = CalendarConfig::from_tools(Some(&config_json));
+    let calendar_events = CalendarStore::new(db_path)
+        .await?
+        .list(
+            user_id,
+            now - calendar_config.past_days * 86_400,
+            now + calendar_config.window_days * 86_400,
+        )
+        .await?;
+
+    let reminder_ids: Vec<i32> = reminders.iter().map(|reminder| reminder.id).collect();
+    let todo_ids: Vec<i32> = todos.iter().map(|todo| todo.id).collect();
+    let mut reminder_labels = LabelStore::new(&reminder_db_path)
+        .await?
+        .labels_for(user_id, LabelTarget::Reminder, &reminder_ids)
+        .await?;
+    let mut todo_labels = LabelStore::new(&todo_db_path)
+        .await?
+        .labels_for(user_id, LabelTarget::Todo, &todo_ids)
+        .await?;
+    let mut plan_labels = LabelStore::new(&plan_db_path)
+        .await?
+        .labels_for(user_id, LabelTarget::Plan, &plan_ids)
+        .await?;
+    let reminder_projects = ProjectStore::new(&reminder_db_path)
+        .await?
+        .assignments(user_id, ProjectTarget::Reminder)
+        .await?;
+    let todo_projects = ProjectStore::new(&todo_db_path)
+        .await?
+        .assignments(user_id, ProjectTarget::Todo)
+        .await?;
+    let task_projects = ProjectStore::new(&task_db_path)
+        .await?
+        .assignments(user_id, ProjectTarget::Task)
+        .await?;
+    let plan_projects = ProjectStore::new(&plan_db_path)
+        .await?
+        .assignments(user_id, ProjectTarget::Plan)
+        .await?;
+
+    let mut items = Vec::new();
+
+    let mut linked_reminders: HashMap<String, Vec<LinkedReminderResponse>> = HashMap::new();
+    for reminder in reminders.iter().filter(|r| r.completed_at.is_none()) {
+        if let Some(target_ref) = reminder.target_ref.as_ref() {
+            linked_reminders
+                .entry(target_ref.clone())
+                .or_default()
+                .push(LinkedReminderResponse {
+                    id: reminder.id,
+                    title: reminder.title.clone(),
+                    due_at: reminder.due_at,
+                });
+        }
+    }
+
+    for reminder in reminders {
+        let status = if reminder.completed_at.is_some() {
+            "done"
+        } else {
+            "new"
+        };
+        if !include_done && status == "done" {
+            continue;
+        }
+        let critical = reminder.completed_at.is_none()
+            && escalation
+                .chain_for(&reminder.priority)
+                .is_some_and(|chain| chain.is_critical(reminder.escalation_level));
+        let priority = if critical {
+            "critical"
+        } else if reminder.completed_at.is_none()
+            && (reminder.due_at <= now || matches!(reminder.priority.as_str(), "high" | "urgent"))
+        {
+            "high"
+        } else {
+            "normal"
+        };
+        let held_until = reminder
+            .held_until
+            .filter(|until| reminder.completed_at.is_none() && *until > now);
+        items.push(InboxItemResponse {
+            id: format!("reminder:{}", reminder.id),
+            source_type: "reminder".to_string(),
+            source_id: reminder.id,
+            title: reminder.title,
+            details: Some(match (critical, reminder.target_ref) {
+                (true, _) => "Overdue reminder, escalated without acknowledgement".to_string(),
+                (false, Some(target_ref)) => format!("Reminder for {target_ref}"),
+                (false, None) => "Reminder".to_string(),
+            }),
+            owner: "human".to_string(),
+            status: status.to_string(),
+            priority: priority.to_string(),
+            due_at: Some(reminder.due_at),
+            created_at: reminder.created_at,
+            updated_at: reminder.completed_at.unwrap_or(reminder.created_at),
+            requires_human_action: reminder.completed_at.is_none(),
+            origin_ref: format!("reminder:{}", reminder.id),
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until,
+            labels: reminder_labels.remove(&reminder.id).unwrap_or_default(),
+            project_id: reminder_projects.get(&reminder.id).copied(),
+        });
+    }
+
+    for todo in todos {
+        let status = if todo.completed_at.is_some() {
+            "done"
+        } else {
+            "new"
+        };
+        if !include_done && status == "done" {
+            continue;
+        }
+        items.push(InboxItemResponse {
+            id: format!("todo:{}", todo.id),
+            source_type: "todo".to_string(),
+            source_id: todo.id,
+            title: todo.title,
+            details: todo.notes,
+            owner: "human".to_string(),
+            status: status.to_string(),
+            priority: "normal".to_string(),
+            due_at: None,
+            created_at: todo.created_at,
+            updated_at: todo.updated_at,
+            requires_human_action: todo.completed_at.is_none(),
+            origin_ref: format!("todo:{}", todo.id),
+            dependency_refs: todo
+                .dependency_refs
+                .iter()
+                .map(|dep_ref| refs::canonical(dep_ref))
+                .collect(),
+            t_shirt_size: todo.t_shirt_size,
+            story_points: todo.story_points,
+            estimate_optimistic_minutes: todo.estimate_optimistic_minutes,
+            estimate_likely_minutes: todo.estimate_likely_minutes,
+            estimate_pessimistic_minutes: todo.estimate_pessimistic_minutes,
+            linked_reminders: linked_reminders
+                .remove(&format!("todo:{}", todo.id))
+                .unwrap_or_default(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: todo_labels.remove(&todo.id).unwrap_or_default(),
+            project_id: todo_projects.get(&todo.id).copied(),
+        });
+    }
+
+    for task in tasks {
+        let status = if task.enabled { "new" } else { "done" };
+        if !include_done && status == "done" {
+            continue;
+        }
+        let priority = if task.enabled && task.next_run_at <= now {
+            "high"
+        } else {
+            "normal"
+        };
+        items.push(InboxItemResponse {
+            id: format!("task:{}", task.id),
+            source_type: "task".to_string(),
+            source_id: task.id,
+            title: task.name,
+            details: Some(task.prompt),
+            owner: "human".to_string(),
+            status: status.to_string(),
+            priority: priority.to_string(),
+            due_at: Some(task.next_run_at),
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+            requires_human_action: task.enabled,
+            origin_ref: format!("task:{}", task.id),
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: task_projects.get(&task.id).copied(),
+        });
+    }
+
+    for plan in plans {
+        let mut emitted_step = false;
+        if let Some(steps) = plan.steps.as_ref().and_then(|value| value.as_array()) {
+            let step_alias_map = build_plan_step_alias_map(plan.id, steps);
+            for (index, step) in steps.iter().enumerate() {
+                let Some(title) = parse_plan_step_title(step) else {
+                    continue;
+                };
+                emitted_step = true;
+                let owner = step
+                    .get("owner")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("human")
+                    .to_ascii_lowercase();
+                let owner = parse_plan_owner_from_text(&title).unwrap_or(owner);
+                let status = parse_plan_status(
+                    step.get("status").and_then(|v| v.as_str()),
+                    if matches!(plan.status.as_str(), "done" | "rejected") {
+                        "done"
+                    } else {
+                        "new"
+                    },
+                );
+                if !include_done && status == "done" {
+                    continue;
+                }
+                let due_at = parse_plan_step_due_at(step, &title, plan.created_at, &plan.user_id);
+                let priority = parse_plan_priority(step.get("priority").and_then(|v| v.as_str()));
+                let priority = parse_plan_priority_from_text(&title).unwrap_or(priority);
+                let origin_ref = format!("plan_step:{}:{}", plan.id, index);
+                let dependency_refs = plan_dependency_refs
+                    .get(&origin_ref)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        parse_plan_step_dependency_refs(plan.id, step, &step_alias_map)
+                    });
+                let t_shirt_size = parse_plan_step_t_shirt_size(step, &title);
+                let story_points = parse_plan_step_story_points(step, &title);
+                let estimate_likely_minutes = step
+                    .get("estimate_likely_minutes")
+                    .and_then(|v| v.as_i64())
+                    .map(|v| v as i32)
+                    .or_else(|| parse_time_estimate_minutes_from_text(&title));
+                let estimate_optimistic_minutes = step
+                    .get("estimate_optimistic_minutes")
+                    .and_then(|v| v.as_i64())
+                    .map(|v| v as i32)
+                    .or_else(|| {
+                        estimate_likely_minutes.map(|value| ((value as f32) * 0.70).round() as i32)
+                    });
+                let estimate_pessimistic_minutes = step
+                    .get("estimate_pessimistic_minutes")
+                    .and_then(|v| v.as_i64())
+                    .map(|v| v as i32)
+                    .or_else(|| {
+                        estimate_likely_minutes.map(|value| ((value as f32) * 1.45).round() as i32)
+                    });
+
+                items.push(InboxItemResponse {
+                    id: format!("plan_step:{}:{}", plan.id, index),
+                    source_type: "plan_step".to_string(),
+                    source_id: plan.id,
+                    title,
+                    details: Some(format!("Plan: {}", plan.title)),
+                    owner: owner.clone(),
+                    status,
+                    priority,
+                    due_at,
+                    created_at: plan.created_at,
+                    updated_at: plan.updated_at,
+                    requires_human_action: owner != "agent",
+                    origin_ref,
+                    dependency_refs,
+                    t_shirt_size,
+                    story_points,
+                    estimate_optimistic_minutes,
+                    estimate_likely_minutes,
+                    estimate_pessimistic_minutes,
+                    linked_reminders: Vec::new(),
+                    comments: Vec::new(),
+                    attachments: Vec::new(),
+                    held_until: None,
+                    labels: plan_labels.get(&plan.id).cloned().unwrap_or_default(),
+                    project_id: plan_projects.get(&plan.id).copied(),
+                });
+            }
+        }
+
+        if !emitted_step {
+            let status = if matches!(plan.status.as_str(), "done" | "rejected") {
+                "done"
+            } else {
+                "new"
+            };
+            if !include_done && status == "done" {
+                continue;
+            }
+            items.push(InboxItemResponse {
+                id: format!("plan:{}", plan.id),
+                source_type: "plan_step".to_string(),
+                source_id: plan.id,
+                title: format!("Review plan: {}", plan.title),
+                details: Some(plan.goal),
+                owner: "human".to_string(),
+                status: status.to_string(),
+                priority: "normal".to_string(),
+                due_at: None,
+                created_at: plan.created_at,
+                updated_at: plan.updated_at,
+                requires_human_action: true,
+                origin_ref: format!("plan:{}", plan.id),
+                dependency_refs: vec![],
+                t_shirt_size: None,
+                story_points: None,
+                estimate_optimistic_minutes: None,
+                estimate_likely_minutes: None,
+                estimate_pessimistic_minutes: None,
+                linked_reminders: Vec::new(),
+                comments: Vec::new(),
+                attachments: Vec::new(),
+                held_until: None,
+                labels: plan_labels.remove(&plan.id).unwrap_or_default(),
+                project_id: plan_projects.get(&plan.id).copied(),
+            });
+        }
+    }
+
+    for (approval, steps) in approvals {
+        let origin_ref = approval.origin_ref();
+        let mut details = describe_approval(&approval);
+        let current = steps.iter().find(|step| step.status == StepStatus::Pending);
+        // With a chain, the requester only acts when their own step is up.
+        let requires_human_action =
+            current.is_none_or(|step| step.approvers.contains(&approval.user_id));
+        if let Some(step) = current {
+            details.push_str(&format!(
+                ". Waiting on {} (step {} of {})",
+                step.approvers.join(" or "),
+                step.position + 1,
+                steps.len()
+            ));
+        }
+        items.push(InboxItemResponse {
+            id: origin_ref.clone(),
+            source_type: "approval".to_string(),
+            source_id: approval.id,
+            title: if approval.capability == inbox_sweep::SWEEP_CAPABILITY {
+                "Review inbox sweep".to_string()
+            } else {
+                format!("Approve {}", approval.capability)
+            },
+            details: Some(details),
+            owner: "human".to_string(),
+            status: "new".to_string(),
+            priority: "high".to_string(),
+            due_at: current.and_then(|step| step.due_at),
+            created_at: approval.created_at,
+            updated_at: approval.created_at,
+            requires_human_action,
+            origin_ref,
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+        });
+    }
+
+    for (approval, step) in awaiting_approvals {
+        let origin_ref = approval.origin_ref();
+        items.push(InboxItemResponse {
+            id: origin_ref.clone(),
+            source_type: "approval".to_string(),
+            source_id: approval.id,
+            title: format!("Approve {} for {}", approval.capability, approval.user_id),
+            details: Some(format!(
+                "{}. Step {} of the approval chain",
+                describe_approval(&approval),
+                step.position + 1
+            )),
+            owner: "human".to_string(),
+            status: "new".to_string(),
+            priority: "high".to_string(),
+            due_at: step.due_at,
+            created_at: approval.created_at,
+            updated_at: approval.created_at,
+            requires_human_action: true,
+            origin_ref,
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+        });
+    }
+
+    for request in consent_requests {
+        let origin_ref = request.origin_ref();
+        let target = if request.scope == crate::consent::ANY_SCOPE {
+            String::new()
+        } else {
+            format!(" for {}", request.scope)
+        };
+        items.push(InboxItemResponse {
+            id: origin_ref.clone(),
+            source_type: "consent".to_string(),
+            source_id: request.id,
+            title: format!("Allow {}{target}?", request.capability),
+            details: Some(format!(
+                "The agent asked to use {}{target} through the {} tool. Allowing it covers later calls too, until the grant expires or you revoke it",
+                request.capability, request.tool
+            )),
+            owner: "human".to_string(),
+            status: "new".to_string(),
+            priority: "high".to_string(),
+            due_at: None,
+            created_at: request.requested_at,
+            updated_at: request.requested_at,
+            requires_human_action: true,
+            origin_ref,
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: Vec::new(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+        });
+    }
+
+    for question in questions {
+        let origin_ref = question.origin_ref();
+        let (status, details) = match question.status.as_str() {
+            questions::STATUS_OPEN => {
+                let mut details = "The agent is waiting on your answer".to_string();
+                if let Some(blocks_ref) = &question.blocks_ref {
+                    details.push_str(&format!("; it blocks {blocks_ref}"));
+                }
+                if question.seen_at.is_none() {
+                    details.push_str(" (unread)");
+                }
+                ("new", details)
+            }
+            questions::STATUS_DISMISSED => ("dismissed", "Dismissed without an answer".to_string()),
+            _ => (
+                "done",
+                format!(
+                    "Answered: {}",
+                    question.answer.as_deref().unwrap_or("(no text)")
+                ),
+            ),
+        };
+        items.push(InboxItemResponse {
+            id: origin_ref.clone(),
+            source_type: "question".to_string(),
+            source_id: question.id,
+            title: question.question,
+            details: Some(details),
+            owner: "human".to_string(),
+            status: status.to_string(),
+            priority: "high".to_string(),
+            due_at: None,
+            created_at: question.asked_at,
+            updated_at: question.answered_at.unwrap_or(question.asked_at),
+            requires_human_action: status == "new",
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: linked_reminders.remove(&origin_ref).unwrap_or_default(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+            origin_ref,
+        });
+    }
+
+    for external in external_items {
+        let open = external.status == external_items::STATUS_OPEN;
+        let details = match (&external.details, &external.url) {
+            (Some(details), Some(url)) => Some(format!("{details}\n{url}")),
+            (details, url) => details.clone().or_else(|| url.clone()),
+        };
+        items.push(InboxItemResponse {
+            id: external.origin_ref.clone(),
+            source_type: external.source,
+            source_id: external.id,
+            title: external.title,
+            details,
+            owner: "human".to_string(),
+            status: if open { "new" } else { "done" }.to_string(),
+            priority: external.priority,
+            due_at: None,
+            created_at: external.created_at,
+            updated_at: external.updated_at,
+            requires_human_action: open,
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            linked_reminders: linked_reminders
+                .remove(&external.origin_ref)
+                .unwrap_or_default(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+            origin_ref: external.origin_ref,
+        });
+    }
+
+    for item in &mut items {
+        if let Some(status) = persisted_statuses.get(&item.origin_ref) {
+            item.status = status.clone();
+        }
+    }
+
+    // Added after the overrides: calendar entries mirror the calendar and
+    // finish when the event does, whatever the inbox says.
+    for event in calendar_events {
+        let origin_ref = event.origin_ref();
+        let details = [event.location.as_deref(), event.description.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join("\n");
+        items.push(InboxItemResponse {
+            id: origin_ref.clone(),
+            source_type: crate::calendar::SOURCE_TYPE.to_string(),
+            source_id: event.id,
+            title: event.title,
+            details: (!details.is_empty()).then_some(details),
+            owner: "human".to_string(),
+            status: if event.ends_at <= now { "done" } else { "new" }.to_string(),
+            priority: "normal".to_string(),
+            due_at: Some(event.ends_at),
+            created_at: event.synced_at,
+            updated_at: event.synced_at,
+            requires_human_action: false,
+            dependency_refs: vec![],
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: i32::try_from((event.ends_at - event.starts_at) / 60)
+                .ok()
+                .map(|minutes| minutes.max(1)),
+            estimate_pessimistic_minutes: None,
+            linked_reminders: linked_reminders.remove(&origin_ref).unwrap_or_default(),
+            comments: Vec::new(),
+            attachments: Vec::new(),
+            held_until: None,
+            labels: Vec::new(),
+            project_id: None,
+            origin_ref,
+        });
+    }
+
+    let plan_step_refs = items
+        .iter()
+        .filter(|item| item.source_type == "plan_step")
+        .map(|item| item.origin_ref.to_ascii_lowercase())
+        .collect::<HashSet<_>>();
+
+    items.retain(|item| {
+        !(item.source_type == "todo"
+            && extract_plan_step_ref_from_details(item.details.as_deref())
+                .map(|origin_ref| plan_step_refs.contains(&origin_ref))
+                .unwrap_or(false))
+    });
+
+    let mut seen_origin_refs = HashSet::new();
+    items.retain(|item| seen_origin_refs.insert(item.origin_ref.clone()));
+
+    let rules = InboxRules::from_tools(Some(&config_json));
+    if !rules.is_empty() {
+        for item in &mut items {
+            let outcome = rules.evaluate(
+                &RuleSubject {
+                    source_type: &item.source_type,
+                    title: &item.title,
+                    details: item.details.as_deref(),
+                    labels: &item.labels,
+                    due_at: item.due_at,
+                },
+                now,
+            );
+            if let Some(priority) = outcome.priority {
+                item.priority = priority;
+            }
+            if let Some(owner) = outcome.owner {
+                item.owner = owner;
+            }
+            if let Some(requires_human_action) = outcome.requires_human_action {
+                item.requires_human_action = requires_human_action;
+            }
+        }
+    }
+
+    if !include_done {
+        items.retain(|item| item.status != "done" && item.status != "dismissed");
+    }
+
+    items.sort_by(|a, b| {
+        priority_rank(&a.priority)
+            .cmp(&priority_rank(&b.priority))
+            .then_with(|| {
+                a.due_at
+                    .unwrap_or(i64::MAX)
+                    .cmp(&b.due_at.unwrap_or(i64::MAX))
+            })
+            .then_with(|| b.created_at.cmp(&a.created_at))
+    });
+
+    if items.len() > limit {
+        items.truncate(limit);
+    }
+
+    let origin_refs: Vec<String> = items.iter().map(|item| item.origin_ref.clone()).collect();
+    let mut comments = status_store.comments_for(user_id, &origin_refs).await?;
+    let mut attachments =
+        AttachmentStore::new(db_path, AttachmentConfig::from_tools(Some(&config_json)))
+            .await?
+            .list_for(user_id, &origin_refs)
+            .await?;
+    for item in &mut items {
+        item.comments = comments.remove(&item.origin_ref).unwrap_or_default();
+        item.attachments = attachments.remove(&item.origin_ref).unwrap_or_default();
+    }
+
+    Ok(items)
+}
+
+async fn solana_wallet(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<SolanaWalletQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let actor = query.actor.unwrap_or_else(|| "agent".to_string());
+
+    match crate::security::solana_signer::wallet_address(&query.user_id, &actor) {
+        Ok(address) => (
+            StatusCode::OK,
+            Json(SolanaWalletResponse {
+                user_id: query.user_id,
+                actor,
+                address,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn solana_balance(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<SolanaBalanceQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let policy = match load_solana_rpc_policy(&state) {
+        Ok(policy) => policy,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let endpoint = match require_solana_rpc_endpoint(&policy) {
+        Ok(endpoint) => endpoint,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let address =
+        match resolve_query_or_wallet_address(query.address, query.user_id, query.actor, "agent") {
+            Ok(address) => address,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    match crate::solana_rpc::get_balance(&endpoint, &address, &policy.commitment).await {
+        Ok(lamports) => (
+            StatusCode::OK,
+            Json(SolanaBalanceResponse {
+                address,
+                lamports,
+                sol: lamports as f64 / 1_000_000_000f64,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn solana_transfer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SolanaTransferRequest>,
+) -> impl IntoResponse {
+    execute_solana_transfer(state, headers, payload, false).await
+}
+
+async fn solana_simulate_transfer(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SolanaTransferRequest>,
+) -> impl IntoResponse {
+    execute_solana_transfer(state, headers, payload, true).await
+}
+
+async fn execute_solana_transfer(
+    state: AppState,
+    headers: HeaderMap,
+    payload: SolanaTransferRequest,
+    force_simulation_only: bool,
+) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let actor = payload.actor.unwrap_or_else(|| "agent".to_string());
+    let signer_preview = match state.signer_service.process(SignerRequest::Preview {
+        intent: Box::new(SigningIntent {
+            request_id: payload.request_id.clone(),
+            actor: actor.clone(),
+            user_id: payload.user_id.clone(),
+            action_type: "solana_transfer".to_string(),
+            amount_atomic: payload.lamports,
+            payee: payload
+                .payee
+                .clone()
+                .unwrap_or_else(|| "merchant.local".to_string()),
+            context_requires_approval: false,
+            scheme_id: None,
+            chain_id: Some("solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp".to_string()),
+            payment_authority: None,
+            idempotency_key: Some(payload.request_id.clone()),
+        }),
+    }) {
+        Ok(response) => response,
+        Err(err) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    if signer_preview.status != "approved" {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!({
+                "error": "transfer requires approval",
+                "signer": signer_preview,
+            })),
+        )
+            .into_response();
+    }
+
+    let policy = match load_solana_rpc_policy(&state) {
+        Ok(policy) => policy,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let endpoint = match require_solana_rpc_endpoint(&policy) {
+        Ok(endpoint) => endpoint,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let from_seed = match crate::security::solana_signer::signing_seed(&payload.user_id, &actor) {
+        Ok(seed) => seed,
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let latest_blockhash =
+        match crate::solana_rpc::get_latest_blockhash(&endpoint, &policy.commitment).await {
+            Ok(hash) => hash,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    let probe_unit_limit = crate::solana_rpc::probe_compute_unit_limit(&policy);
+    let (probe_tx_base64, wallet_address) =
+        match crate::solana_rpc::build_transfer_transaction_base64_with_unit_limit(
+            &from_seed,
+            &payload.to,
+            payload.lamports,
+            &latest_blockhash,
+            &policy,
+            probe_unit_limit,
+        ) {
+            Ok(tuple) => tuple,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    let probe_simulation =
+        match crate::solana_rpc::simulate_transaction(&endpoint, &probe_tx_base64, &policy).await {
+            Ok(value) => value,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_GATEWAY,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    let adjusted_unit_limit = crate::solana_rpc::recommended_compute_unit_limit(
+        &probe_simulation,
+        policy.compute_budget.unit_limit,
+    );
+
+    let tx_base64 = if adjusted_unit_limit == probe_unit_limit {
+        probe_tx_base64
+    } else {
+        match crate::solana_rpc::build_transfer_transaction_base64_with_unit_limit(
+            &from_seed,
+            &payload.to,
+            payload.lamports,
+            &latest_blockhash,
+            &policy,
+            adjusted_unit_limit,
+        ) {
+            Ok((tx, _)) => tx,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        }
+    };
+
+    let simulate_only = force_simulation_only || payload.simulate_only.unwrap_or(false);
+    let simulation_result = Some(probe_simulation);
+
+    if simulate_only {
+        return (
+            StatusCode::OK,
+            Json(SolanaTransferResponse {
+                status: "simulated".to_string(),
+                request_id: payload.request_id,
+                wallet_address,
+                signer_reason_code: signer_preview.reason_code,
+                simulation: simulation_result,
+                signature: None,
+            }),
+        )
+            .into_response();
+    }
+
+    if let Err(err) = state.signer_service.process(SignerRequest::Sign {
+        request_id: payload.request_id.clone(),
+    }) {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    match crate::solana_rpc::send_transaction(&endpoint, &tx_base64, &policy).await {
+        Ok(signature) => {
+            let _ = state.ui_event_tx.send(UiEvent {
+                event_type: "payment".to_string(),
+                user_id: payload.user_id.clone(),
+                tool: "solana".to_string(),
+                status: "submitted".to_string(),
+                payload: json!({
+                    "request_id": payload.request_id,
+                    "to": payload.to,
+                    "lamports": payload.lamports,
+                    "signature": signature,
+                    "wallet_address": wallet_address,
+                }),
+                timestamp: now_ts(),
+            });
+            (
+                StatusCode::OK,
+                Json(SolanaTransferResponse {
+                    status: "submitted".to_string(),
+                    request_id: payload.request_id,
+                    wallet_address,
+                    signer_reason_code: signer_preview.reason_code,
+                    simulation: simulation_result,
+                    signature: Some(signature),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn solana_tx_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<SolanaTxStatusQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let policy = match load_solana_rpc_policy(&state) {
+        Ok(policy) => policy,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let endpoint = match require_solana_rpc_endpoint(&policy) {
+        Ok(endpoint) => endpoint,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    match crate::solana_rpc::get_signature_status(&endpoint, &query.signature).await {
+        Ok(value) => (
+            StatusCode::OK,
+            Json(SolanaTxStatusResponse {
+                signature: query.signature,
+                value,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn solana_tx_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<SolanaTxHistoryQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let policy = match load_solana_rpc_policy(&state) {
+        Ok(policy) => policy,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let endpoint = match require_solana_rpc_endpoint(&policy) {
+        Ok(endpoint) => endpoint,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let address =
+        match resolve_query_or_wallet_address(query.address, query.user_id, query.actor, "agent") {
+            Ok(address) => address,
+            Err(err) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    match crate::solana_rpc::get_signatures_for_address(
+        &endpoint,
+        &address,
+        query.limit.unwrap_or(20),
+    )
+    .await
+    {
+        Ok(entries) => (
+            StatusCode::OK,
+            Json(SolanaTxHistoryResponse { address, entries }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn x402_preview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<X402PreviewRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let context_requires_approval = payload.context_requires_approval.unwrap_or(false);
+
+    let (canonical_intent, signing_intent) = match canonicalize_payment_required(
+        &payload.request_id,
+        &payload.actor,
+        &payload.user_id,
+        &payload.payment_required,
+        payload.merchant_origin.as_deref(),
+        context_requires_approval,
+        payload.idempotency_key.as_deref(),
+    ) {
+        Ok(value) => value,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    match state.signer_service.process(SignerRequest::Preview {
+        intent: Box::new(signing_intent),
+    }) {
+        Ok(signer_response) => (
+            StatusCode::OK,
+            Json(X402PreviewResponse {
+                canonical_intent,
+                signer: signer_response,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn signer_preview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(intent): Json<SigningIntent>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    match state.signer_service.process(SignerRequest::Preview {
+        intent: Box::new(intent),
+    }) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn signer_approve(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SignerIdRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    match state.signer_service.process(SignerRequest::Approve {
+        request_id: payload.request_id,
+    }) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn signer_sign(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SignerIdRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    match state.signer_service.process(SignerRequest::Sign {
+        request_id: payload.request_id,
+    }) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn signer_deny(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SignerIdRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    match state.signer_service.process(SignerRequest::Deny {
+        request_id: payload.request_id,
+    }) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(err) => (
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Wakes the server's shutdown future; a permit is kept if it is not yet
+/// waiting.
+fn shutdown_requested() -> &'static tokio::sync::Notify {
+    static SHUTDOWN: std::sync::OnceLock<tokio::sync::Notify> = std::sync::OnceLock::new();
+    SHUTDOWN.get_or_init(tokio::sync::Notify::new)
+}
+
+/// Stops the daemon the way SIGTERM does (see [`crate::shutdown`]). Used by
+/// `butterfly-bot restore` before it replaces the database and by the UI's
+/// Stop button.
+async fn request_shutdown(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    tracing::info!("Shutdown requested over the API");
+    shutdown_requested().notify_one();
+    (StatusCode::ACCEPTED, Json(json!({"status": "stopping"}))).into_response()
+}
+
+async fn health() -> Json<HealthResponse> {
+    let status = if crate::shutdown::shared().is_stopping() {
+        "stopping"
+    } else {
+        "ok"
+    };
+    Json(HealthResponse {
+        status: status.to_string(),
+    })
+}
+
+async fn doctor(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let checks = run_doctor_checks(&state).await;
+    let has_fail = checks.iter().any(|check| check.status == "fail");
+    let has_warn = checks.iter().any(|check| check.status == "warn");
+    let overall = if has_fail {
+        "fail"
+    } else if has_warn {
+        "warn"
+    } else {
+        "pass"
+    };
+
+    (
+        StatusCode::OK,
+        Json(DoctorResponse {
+            overall: overall.to_string(),
+            checks,
+        }),
+    )
+        .into_response()
+}
+
+/// Every existing database file a store may live in, the main database
+/// first.
+fn store_db_paths(db_path: &str) -> Vec<String> {
+    let root = Config::load(db_path)
+        .ok()
+        .and_then(|cfg| serde_json::to_value(cfg).ok())
+        .unwrap_or(Value::Null);
+    let mut paths = vec![db_path.to_string()];
+    for path in [
+        resolve_reminder_db_path(&root),
+        resolve_todo_db_path(&root),
+        resolve_task_db_path(&root),
+        resolve_plan_db_path(&root),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if !paths.contains(&path) && std::path::Path::new(&path).exists() {
+            paths.push(path);
+        }
+    }
+    paths
+}
+
+async fn schema_drift_report(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let paths = store_db_paths(&state.db_path);
+    let result = tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .map(|db_path| {
+                let drift = crate::schema_drift::check(&db_path)?;
+                Ok(SchemaDriftEntry { db_path, drift })
+            })
+            .collect::<Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    .and_then(|result| result);
+    match result {
+        Ok(databases) => (StatusCode::OK, Json(SchemaDriftResponse { databases })).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Backs up and repairs each drifted database. Stops at the first failure;
+/// databases repaired before it stay repaired.
+async fn repair_schema_drift(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let paths = store_db_paths(&state.db_path);
+    let result = tokio::task::spawn_blocking(move || {
+        paths
+            .into_iter()
+            .map(|db_path| {
+                let report = crate::schema_drift::repair(&db_path)?;
+                Ok(SchemaRepairEntry { db_path, report })
+            })
+            .collect::<Result<Vec<_>>>()
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    .and_then(|result| result);
+    match result {
+        Ok(databases) => {
+            let _ = state.ui_event_tx.send(UiEvent {
+                event_type: "doctor".to_string(),
+                user_id: "system".to_string(),
+                tool: "schema_drift".to_string(),
+                status: "repaired".to_string(),
+                payload: json!({
+                    "databases": databases
+                        .iter()
+                        .map(|entry| json!({
+                            "db_path": entry.db_path,
+                            "backup_path": entry.report.backup_path,
+                            "applied": entry.report.applied.len(),
+                            "remaining": entry.report.remaining.len(),
+                        }))
+                        .collect::<Vec<_>>(),
+                }),
+                timestamp: now_ts(),
+            });
+            (StatusCode::OK, Json(SchemaRepairResponse { databases })).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn schema_drift_check(db_path: &str) -> DoctorCheck {
+    match crate::schema_drift::check(db_path) {
+        Ok(drift) if drift.is_empty() => doctor_check(
+            "schema_drift",
+            "pass",
+            format!("Schema of {db_path} matches the migrations."),
+            None,
+        ),
+        Ok(drift) => {
+            let mut details: Vec<String> = drift.iter().take(5).map(|d| d.detail.clone()).collect();
+            if drift.len() > details.len() {
+                details.push(format!("and {} more", drift.len() - details.len()));
+            }
+            doctor_check(
+                "schema_drift",
+                "warn",
+                format!(
+                    "{} schema difference(s) in {db_path}: {}.",
+                    drift.len(),
+                    details.join("; ")
+                ),
+                Some("Use Repair schema in Diagnostics; the database is backed up before anything changes."),
+            )
+        }
+        Err(err) => doctor_check(
+            "schema_drift",
+            "fail",
+            format!("Schema check of {db_path} failed: {err}"),
+            Some("Verify the database opens; see database_access."),
+        ),
+    }
+}
+
+async fn security_audit(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let findings = run_security_audit_checks(&state).await;
+    let overall = highest_severity(&findings);
+
+    (
+        StatusCode::OK,
+        Json(SecurityAuditResponse { overall, findings }),
+    )
+        .into_response()
+}
+
+fn capability_coverage(tools: Option<&Value>) -> CoverageReport {
+    let root = json!({ "tools": tools.cloned().unwrap_or(Value::Null) });
+    CoverageReport::build(&SandboxSettings::from_root_config(&root))
+}
+
+async fn capabilities(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let agent = state.agent.read().await.clone();
+    (
+        StatusCode::OK,
+        Json(CapabilitiesResponse {
+            abi_version: WasmRuntime::SUPPORTED_CAPABILITY_ABI_VERSION,
+            tools: agent.describe_tools().await,
+        }),
+    )
+        .into_response()
+}
+
+/// Risk labels, module identity and last use of the registered tools.
+async fn tool_posture(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    // A missing audit trail only hides last-used times.
+    let last_used = match AuditStore::new(&state.db_path).await {
+        Ok(store) => store.last_tool_use().await.unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    };
+    let agent = state.agent.read().await.clone();
+    (
+        StatusCode::OK,
+        Json(ToolPostureResponse {
+            tools: agent.tool_postures(&last_used).await,
+        }),
+    )
+        .into_response()
+}
+
+async fn capabilities_report(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+
+    let tools = Config::load(&state.db_path).ok().and_then(|cfg| cfg.tools);
+    let report = capability_coverage(tools.as_ref());
+    let mismatches = report.describe_mismatches();
+    (
+        StatusCode::OK,
+        Json(CapabilityReportResponse {
+            consistent: mismatches.is_empty(),
+            mismatches,
+            capabilities: report.capabilities,
+        }),
+    )
+        .into_response()
+}
+
+fn doctor_check(name: &str, status: &str, message: String, fix_hint: Option<&str>) -> DoctorCheck {
+    DoctorCheck {
+        name: name.to_string(),
+        status: status.to_string(),
+        message,
+        fix_hint: fix_hint.map(str::to_string),
+    }
+}
+
+fn security_finding(
+    id: &str,
+    severity: &str,
+    status: &str,
+    message: String,
+    fix_hint: Option<&str>,
+    auto_fixable: bool,
+) -> SecurityAuditFinding {
+    SecurityAuditFinding {
+        id: id.to_string(),
+        severity: severity.to_string(),
+        status: status.to_string(),
+        message,
+        fix_hint: fix_hint.map(str::to_string),
+        auto_fixable,
+    }
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "critical" => 4,
+        "high" => 3,
+        "medium" => 2,
+        _ => 1,
+    }
+}
+
+fn highest_severity(findings: &[SecurityAuditFinding]) -> String {
+    findings
+        .iter()
+        .filter(|finding| finding.status != "pass")
+        .max_by_key(|finding| severity_rank(&finding.severity))
+        .map(|finding| finding.severity.clone())
+        .unwrap_or_else(|| "low".to_string())
+}
+
+async fn run_security_audit_checks(state: &AppState) -> Vec<SecurityAuditFinding> {
+    let mut findings = Vec::new();
+
+    if state.token.trim().is_empty() {
+        findings.push(security_finding(
+            "daemon_auth_token",
+            "medium",
+            "warn",
+            "Daemon auth token is empty; this is unexpected because token bootstrap is automatic and protected endpoints fail closed."
+                .to_string(),
+            Some("Restart the app/daemon to re-run token bootstrap and verify keyring/secret-store availability."),
+            false,
+        ));
+    } else {
+        findings.push(security_finding(
+            "daemon_auth_token",
+            "low",
+            "pass",
+            "Daemon auth token is configured.".to_string(),
+            None,
+            false,
+        ));
+    }
+
+    match Config::load(&state.db_path) {
+        Ok(config) => {
+            findings.push(security_finding(
+                "config_load",
+                "low",
+                "pass",
+                "Config loaded from store/keyring.".to_string(),
+                None,
+                false,
+            ));
+
+            let root = json!({ "tools": config.tools.clone().unwrap_or(Value::Null) });
+            let sandbox = SandboxSettings::from_root_config(&root);
+
+            let built_in_tools = [
+                "coding",
+                "mcp",
+                "http_call",
+                "github",
+                "zapier",
+                "planning",
+                "reminders",
+                "search_internet",
+                "tasks",
+                "todo",
+                "wakeup",
+            ];
+            let mut non_wasm_tools = Vec::new();
+            for tool_name in built_in_tools {
+                let plan = sandbox.execution_plan(tool_name);
+                if plan.runtime != ToolRuntime::Wasm {
+                    non_wasm_tools.push(tool_name);
+                }
+            }
+
+            if non_wasm_tools.is_empty() {
+                findings.push(security_finding(
+                    "tool_runtime_invariant",
+                    "low",
+                    "pass",
+                    "All built-in tools resolve to WASM runtime.".to_string(),
+                    None,
+                    false,
+                ));
+            } else {
+                findings.push(security_finding(
+                    "tool_runtime_invariant",
+                    "high",
+                    "fail",
+                    format!(
+                        "Non-WASM tool runtime detected for: {}.",
+                        non_wasm_tools.join(", ")
+                    ),
+                    Some(
+                        "Enforce WASM-only execution in sandbox settings and tool runtime planner.",
+                    ),
+                    false,
+                ));
+            }
+
+            let default_deny = config
+                .tools
+                .as_ref()
+                .and_then(|tools| tools.get("settings"))
+                .and_then(|settings| settings.get("permissions"))
+                .and_then(|permissions| permissions.get("default_deny"))
+                .and_then(|value| value.as_bool())
+                .unwrap_or(false);
+
+            if default_deny {
+                findings.push(security_finding(
+                    "network_default_deny",
+                    "low",
+                    "pass",
+                    "Global tools network policy uses default_deny=true.".to_string(),
+                    None,
+                    false,
+                ));
+            } else {
+                findings.push(security_finding(
+                    "network_default_deny",
+                    "medium",
+                    "warn",
+                    "Global tools network policy default_deny is disabled or missing."
+                        .to_string(),
+                    Some("Set tools.settings.permissions.default_deny to true and allowlist required domains."),
+                    false,
+                ));
+            }
+        }
+        Err(err) => {
+            findings.push(security_finding(
+                "config_load",
+                "critical",
+                "fail",
+                format!("Config load failed: {err}"),
+                Some("Save a valid config in Config tab and rerun security audit."),
+                false,
+            ));
+        }
+    }
+
+    findings
+}
+
+async fn run_doctor_checks(state: &AppState) -> Vec<DoctorCheck> {
+    let mut checks = Vec::new();
+
+    if state.token.trim().is_empty() {
+        checks.push(doctor_check(
+            "daemon_auth_token",
+            "warn",
+            "Daemon auth token is empty; this is unexpected because token bootstrap is automatic and protected endpoints fail closed."
+                .to_string(),
+            Some("Restart the app/daemon to re-run token bootstrap and verify keyring/secret-store availability."),
+        ));
+    } else {
+        checks.push(doctor_check(
+            "daemon_auth_token",
+            "pass",
+            "Daemon auth token is configured.".to_string(),
+            None,
+        ));
+    }
+
+    match Config::load(&state.db_path) {
+        Ok(config) => {
+            checks.push(doctor_check(
+                "config_store",
+                "pass",
+                "Config loaded from store/keyring.".to_string(),
+                None,
+            ));
+
+            match config.clone().resolve_vault() {
+                Ok(_) => {
+                    checks.push(doctor_check(
+                        "vault_resolution",
+                        "pass",
+                        "Vault-backed secrets resolved successfully.".to_string(),
+                        None,
+                    ));
+                }
+                Err(err) => {
+                    checks.push(doctor_check(
+                        "vault_resolution",
+                        "fail",
+                        format!("Vault resolution failed: {err}"),
+                        Some("Verify OS keychain access and required secret keys."),
+                    ));
+                }
+            }
+
+            let mismatches = capability_coverage(config.tools.as_ref()).describe_mismatches();
+            if mismatches.is_empty() {
+                checks.push(doctor_check(
+                    "capability_coverage",
+                    "pass",
+                    "Every wasm tool capability is allowlisted and has a host handler.".to_string(),
+                    None,
+                ));
+            } else {
+                checks.push(doctor_check(
+                    "capability_coverage",
+                    "fail",
+                    format!("Capability mismatches: {}.", mismatches.join("; ")),
+                    Some("See GET /capabilities/report; fix the sandbox capability allowlist in config or the host handler."),
+                ));
+            }
+
+            let mode = crate::security::tpm_provider::tpm_mode();
+            let tpm_available = crate::security::tpm_provider::tpm_available();
+            if tpm_available {
+                checks.push(doctor_check(
+                    "security_tpm_mode",
+                    "pass",
+                    format!("TPM capability available (mode={mode})."),
+                    None,
+                ));
+                checks.push(doctor_check(
+                    "solana_custody",
+                    "pass",
+                    "Secure Solana wallet custody/signing backend is available.".to_string(),
+                    None,
+                ));
+            } else if mode == "strict" {
+                checks.push(doctor_check(
+                    "security_tpm_mode",
+                    "fail",
+                    "TPM unavailable while strict mode is enabled.".to_string(),
+                    Some("Switch TPM mode to auto/compatible or restore TPM availability."),
+                ));
+                checks.push(doctor_check(
+                    "solana_custody",
+                    "warn",
+                    "Solana signing/custody is disabled because secure key backend is unavailable in strict mode."
+                        .to_string(),
+                    Some("Use a machine with TPM support or relax TPM mode for compatibility."),
+                ));
+            } else {
+                checks.push(doctor_check(
+                    "security_tpm_mode",
+                    "warn",
+                    format!(
+                        "TPM unavailable; running in compatibility path (mode={mode}) with degraded hardware binding."
+                    ),
+                    Some("Use strict mode on TPM-capable hardware for strongest key protection."),
+                ));
+                checks.push(doctor_check(
+                    "solana_custody",
+                    "warn",
+                    "Solana signing/custody is degraded or disabled without hardware-backed key protection."
+                        .to_string(),
+                    Some("For production custody, run on TPM-capable hardware in strict mode."),
+                ));
+            }
+        }
+        Err(err) => {
+            checks.push(doctor_check(
+                "config_store",
+                "fail",
+                format!("Config load failed: {err}"),
+                Some("Save a valid config in the Config tab and retry."),
+            ));
+            checks.push(doctor_check(
+                "vault_resolution",
+                "warn",
+                "Skipped because config could not be loaded.".to_string(),
+                Some("Fix config_store check first."),
+            ));
+            checks.push(doctor_check(
+                "capability_coverage",
+                "warn",
+                "Skipped because config could not be loaded.".to_string(),
+                Some("Fix config_store check first."),
+            ));
+            checks.push(doctor_check(
+                "security_tpm_mode",
+                "warn",
+                "Skipped because config could not be loaded.".to_string(),
+                Some("Fix config_store check first."),
+            ));
+            checks.push(doctor_check(
+                "solana_custody",
+                "warn",
+                "Skipped because config could not be loaded.".to_string(),
+                Some("Fix config_store check first."),
+            ));
+        }
+    }
+
+    let backends = crate::llm::failover::health_snapshot();
+    if !backends.is_empty() {
+        let tripped: Vec<String> = backends
+            .iter()
+            .filter(|backend| !backend.healthy)
+            .map(
+                |backend| match (&backend.tripped_until, &backend.last_error) {
+                    (Some(until), Some(error)) => {
+                        format!("{} (skipped until {until}: {error})", backend.label)
+                    }
+                    (None, Some(error)) => format!(
+                        "{} ({} recent failures: {error})",
+                        backend.label, backend.consecutive_failures
+                    ),
+                    _ => backend.label.clone(),
+                },
+            )
+            .collect();
+        let chain = backends
+            .iter()
+            .map(|backend| backend.label.as_str())
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        if tripped.is_empty() {
+            checks.push(doctor_check(
+                "llm_failover",
+                "pass",
+                format!("All LLM backends healthy ({chain})."),
+                None,
+            ));
+        } else {
+            checks.push(doctor_check(
+                "llm_failover",
+                "warn",
+                format!("Degraded LLM backends: {}.", tripped.join("; ")),
+                Some("Check that the backend is running and reachable; requests fail over along the chain until it recovers."),
+            ));
+        }
+    }
+
+    let db_path = state.db_path.clone();
+    let db_check = tokio::task::spawn_blocking(move || -> DoctorCheck {
+        if let Err(err) = crate::config_store::ensure_parent_dir(&db_path) {
+            return doctor_check(
+                "database_access",
+                "fail",
+                format!("Database directory check failed: {err}"),
+                Some("Verify filesystem permissions for DB path."),
+            );
+        }
+
+        let mut conn = match SqliteConnection::establish(&db_path) {
+            Ok(conn) => conn,
+            Err(err) => {
+                return doctor_check(
+                    "database_access",
+                    "fail",
+                    format!("Database open failed: {err}"),
+                    Some("Verify DB path and SQLite/SQLCipher availability."),
+                )
+            }
+        };
+
+        if let Err(err) = crate::db::apply_sqlcipher_key_sync(&mut conn) {
+            return doctor_check(
+                "database_access",
+                "fail",
+                format!("Database key apply failed: {err}"),
+                Some("Verify secure secret storage availability for db_encryption_key."),
+            );
+        }
+
+        let probe_result = diesel::sql_query(
+            "CREATE TABLE IF NOT EXISTS doctor_probe (id INTEGER PRIMARY KEY, ts INTEGER NOT NULL)",
+        )
+        .execute(&mut conn);
+
+        match probe_result {
+            Ok(_) => doctor_check(
+                "database_access",
+                "pass",
+                "Database opened and write probe succeeded.".to_string(),
+                None,
+            ),
+            Err(err) => doctor_check(
+                "database_access",
+                "fail",
+                format!("Database write probe failed: {err}"),
+                Some("Verify DB permissions and SQLCipher key configuration."),
+            ),
+        }
+    })
+    .await;
+
+    match db_check {
+        Ok(check) => checks.push(check),
+        Err(err) => checks.push(doctor_check(
+            "database_access",
+            "fail",
+            format!("Database check task failed: {err}"),
+            Some("Retry diagnostics; if persistent, inspect runtime logs."),
+        )),
+    }
+
+    let paths = store_db_paths(&state.db_path);
+    match tokio::task::spawn_blocking(move || {
+        paths
+            .iter()
+            .map(|path| schema_drift_check(path))
+            .collect::<Vec<_>>()
+    })
+    .await
+    {
+        Ok(schema_checks) => checks.extend(schema_checks),
+        Err(err) => checks.push(doctor_check(
+            "schema_drift",
+            "fail",
+            format!("Schema check task failed: {err}"),
+            Some("Retry diagnostics; if persistent, inspect runtime logs."),
+        )),
+    }
+
+    checks
+}
+
+/// Closes the agent questions a chat message answers, along with their
+/// reminders, so plans waiting on them can move again.
+async fn resolve_question_answers(
+    state: &AppState,
+    user_id: &str,
+    text: &str,
+    reply_to: Option<&str>,
+) {
+    let answered = match QuestionStore::new(&state.db_path).await {
+        Ok(store) => store.resolve_from_chat(user_id, text, reply_to).await,
+        Err(err) => Err(err),
+    };
+    let answered = match answered {
+        Ok(answered) => answered,
+        Err(err) => {
+            tracing::warn!(user_id, error = %err, "Failed to match chat reply to open questions");
+            return;
+        }
+    };
+    for question in answered {
+        let origin_ref = question.origin_ref();
+        if let Ok(reminders) = ReminderStore::new(&state.db_path).await {
+            let _ = reminders
+                .complete_linked_reminders(user_id, &origin_ref)
+                .await;
+        }
+        if let Ok(states) = InboxStateStore::new(&state.db_path).await {
+            let _ = states.set_status(user_id, &origin_ref, "done").await;
+        }
+        let _ = state.ui_event_tx.send(UiEvent {
+            event_type: "question".to_string(),
+            user_id: user_id.to_string(),
+            tool: "planning".to_string(),
+            status: "answered".to_string(),
+            payload: json!({
+                "origin_ref": origin_ref,
+                "blocks_ref": question.blocks_ref,
+                "seen_at": question.seen_at,
+            }),
+            timestamp: now_ts(),
+        });
+    }
+}
+
+/// Adds the notes on the item a message answers to the prompt, so the
+/// agent replies knowing e.g. that the item is waiting on a vendor.
+async fn prompt_with_item_notes(
+    state: &AppState,
+    user_id: &str,
+    reply_to: Option<&str>,
+    prompt: Option<String>,
+) -> Option<String> {
+    let Some(origin_ref) = reply_to.map(str::trim).filter(|value| !value.is_empty()) else {
+        return prompt;
+    };
+    let comments = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => store.comments(user_id, origin_ref).await,
+        Err(err) => Err(err),
+    };
+    let comments = match comments {
+        Ok(comments) if !comments.is_empty() => comments,
+        Ok(_) => return prompt,
+        Err(err) => {
+            tracing::warn!(user_id, origin_ref, error = %err, "Failed to load inbox item notes");
+            return prompt;
+        }
+    };
+    let notes = item_notes_context(origin_ref, &comments);
+    Some(match prompt {
+        Some(prompt) if !prompt.trim().is_empty() => format!("{prompt}\n\n{notes}"),
+        _ => notes,
+    })
+}
+
+fn item_notes_context(origin_ref: &str, comments: &[InboxComment]) -> String {
+    let mut lines = vec![format!("Notes on {origin_ref}, oldest first:")];
+    for comment in comments {
+        lines.push(format!(
+            "- {} {}: {}",
+            Local
+                .timestamp_opt(comment.created_at, 0)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| comment.created_at.to_string()),
+            comment.author,
+            comment.body
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Turns a recording from the UI into text with the local transcription
+/// engine. The body is the raw audio.
+async fn transcribe(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<TranscribeQuery>,
+    body: Bytes,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let tools = Config::load(&state.db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let Some(transcriber) = VoiceConfig::from_tools(tools.as_ref()).transcriber() else {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "No transcription engine configured under tools.settings.voice".to_string(),
+            }),
+        )
+            .into_response();
+    };
+    if body.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Recording is empty".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let format = query.format.as_deref().unwrap_or("wav");
+    match transcriber.transcribe(body.to_vec(), format).await {
+        Ok(text) => (StatusCode::OK, Json(ProcessTextResponse { text })).into_response(),
+        Err(err) => (
+            StatusCode::BAD_GATEWAY,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Role, consent and rate-limit checks for `capability`; the error is the reply.
+async fn admit_slash_command(
+    state: &AppState,
+    user_id: &str,
+    tool: &str,
+    capability: &str,
+) -> std::result::Result<(), String> {
+    // The daemon's store first, so a readonly user is refused even when the
+    // registry has no role database.
+    let role = match state.role_store.role_for(user_id).await {
+        Ok(Some(role)) => role,
+        Ok(None) => state.role_policy.read().await.default_role,
+        Err(err) => return Err(format!("Couldn't check your role: {err}")),
+    };
+    if !state.role_policy.read().await.allows(role, capability) {
+        return Err(format!(
+            "Capability '{capability}' is not permitted for role '{}'",
+            role.as_str()
+        ));
+    }
+    let agent = state.agent.read().await.clone();
+    match agent
+        .admit_direct_call(tool, capability, &json!({ "user_id": user_id }))
+        .await
+    {
+        Ok(None) => Ok(()),
+        Ok(Some(refused)) => Err(refused
+            .get("error")
+            .or_else(|| refused.get("message"))
+            .and_then(Value::as_str)
+            .unwrap_or("Not permitted")
+            .to_string()),
+        Err(err) => Err(format!("Couldn't check permissions: {err}")),
+    }
+}
+
+/// What completing an inbox item from `source_type` counts as for
+/// [`admit_slash_command`].
+fn done_capability(source_type: &str) -> String {
+    match source_type {
+        "todo" => "kv.sqlite.todo.complete".to_string(),
+        "reminder" => "kv.sqlite.reminders.complete".to_string(),
+        "plan_step" => "kv.sqlite.planning.step_status".to_string(),
+        other => format!("inbox.{other}.done"),
+    }
+}
+
+/// Runs a slash command against the stores and returns the chat reply.
+async fn run_slash_command(state: &AppState, user_id: &str, command: SlashCommand) -> String {
+    let now = now_ts();
+    match command {
+        SlashCommand::Todo { title } => {
+            if let Err(refused) =
+                admit_slash_command(state, user_id, "todo", "kv.sqlite.todo.create").await
+            {
+                return refused;
+            }
+            let todo_db_path = Config::load(&state.db_path)
+                .ok()
+                .and_then(|config| serde_json::to_value(config).ok())
+                .and_then(|value| resolve_todo_db_path(&value))
+                .unwrap_or_else(|| state.db_path.clone());
+            let created = match TodoStore::new(&todo_db_path).await {
+                Ok(store) => store.create_item(user_id, &title, None, None).await,
+                Err(err) => Err(err),
+            };
+            match created {
+                Ok(item) => {
+                    let _ = state.ui_event_tx.send(UiEvent {
+                        event_type: "todo".to_string(),
+                        user_id: user_id.to_string(),
+                        tool: "todo".to_string(),
+                        status: "created".to_string(),
+                        payload: json!({"id": item.id, "title": item.title, "source": "slash"}),
+                        timestamp: now,
+                    });
+                    format!("Added todo {}: {}", item.id, item.title)
+                }
+                Err(err) => format!("Couldn't add the todo: {err}"),
+            }
+        }
+        SlashCommand::Remind { args } => {
+            let Some((due_at, title)) = slash_commands::reminder_parts(user_id, &args, now) else {
+                return "Couldn't find a time in that. Try /remind 3pm standup or \
+                        /remind call the bank tomorrow 9am."
+                    .to_string();
+            };
+            if let Err(refused) =
+                admit_slash_command(state, user_id, "reminders", "kv.sqlite.reminders.create").await
+            {
+                return refused;
+            }
+            match state
+                .reminder_store
+                .create_reminder(user_id, &title, due_at)
+                .await
+            {
+                Ok(reminder) => {
+                    let _ = state.ui_event_tx.send(UiEvent {
+                        event_type: "reminder".to_string(),
+                        user_id: user_id.to_string(),
+                        tool: "reminders".to_string(),
+                        status: "created".to_string(),
+                        payload: json!({
+                            "id": reminder.id,
+                            "title": reminder.title,
+                            "due_at": reminder.due_at,
+                            "source": "slash",
+                        }),
+                        timestamp: now,
+                    });
+                    format!(
+                        "Reminder {} set for {}: {}",
+                        reminder.id,
+                        Local
+                            .timestamp_opt(reminder.due_at, 0)
+                            .single()
+                            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                            .unwrap_or_else(|| reminder.due_at.to_string()),
+                        reminder.title
+                    )
+                }
+                Err(err) => format!("Couldn't set the reminder: {err}"),
+            }
+        }
+        SlashCommand::Done { origin_ref } => {
+            let items = match build_inbox_items(&state.db_path, user_id, 1000, true).await {
+                Ok(items) => items,
+                Err(err) => return format!("Couldn't load the inbox: {err}"),
+            };
+            let Some(item) = items.iter().find(|item| item.origin_ref == origin_ref) else {
+                return format!("No inbox item {origin_ref}");
+            };
+            let capability = done_capability(&item.source_type);
+            if let Err(refused) = admit_slash_command(state, user_id, "inbox", &capability).await {
+                return refused;
+            }
+            match apply_inbox_transition(
+                state,
+                user_id,
+                item,
+                InboxAction::Done,
+                "done",
+                "human",
+                "slash_command",
+            )
+            .await
+            {
+                Ok(_) => format!("Marked done: {}", item.title),
+                Err((_, Json(err))) => format!("Couldn't mark {origin_ref} done: {}", err.error),
+            }
+        }
+        SlashCommand::Help => slash_commands::HELP.to_string(),
+        SlashCommand::Unknown { name } => {
+            format!("Unknown command /{name}.\n{}", slash_commands::HELP)
+        }
+    }
+}
+
+/// The slash command in a human's message, if it is one. An escaped
+/// `//text` is unwrapped to `/text` for the model instead.
+fn slash_command(payload: &mut ProcessTextRequest) -> Option<SlashCommand> {
+    if !payload.from_human() {
+        return None;
+    }
+    if let Some(text) = slash_commands::unescape(&payload.text) {
+        payload.text = text.to_string();
+        return None;
+    }
+    slash_commands::parse(&payload.text)
+}
+
+async fn process_text(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ProcessTextRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    if payload.from_human() {
+        resolve_question_answers(
+            &state,
+            &payload.user_id,
+            &payload.text,
+            payload.reply_to.as_deref(),
+        )
+        .await;
+    }
+
+    if let Some(command) = slash_command(&mut payload) {
+        let text = run_slash_command(&state, &payload.user_id, command).await;
+        return (StatusCode::OK, Json(ProcessTextResponse { text })).into_response();
+    }
+
+    if asks_for_wallet_address_only(&payload.text) {
+        match crate::security::solana_signer::wallet_address(&payload.user_id, "agent") {
+            Ok(address) => {
+                return (
+                    StatusCode::OK,
+                    Json(ProcessTextResponse {
+                        text: format!("Your Solana wallet address is {address}."),
+                    }),
+                )
+                    .into_response();
+            }
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    if asks_for_wallet_balance_only(&payload.text) {
+        let balance_line = match solana_balance_line_for_user(&state, &payload.user_id).await {
+            Ok(line) => line,
+            Err(err) => format!("I couldn't fetch your Solana balance right now: {err}"),
+        };
+
+        return (
+            StatusCode::OK,
+            Json(ProcessTextResponse { text: balance_line }),
+        )
+            .into_response();
+    }
+
+    let prompt = prompt_with_item_notes(
+        &state,
+        &payload.user_id,
+        payload.reply_to.as_deref(),
+        payload.prompt.clone(),
+    )
+    .await;
+    let options = ProcessOptions {
+        prompt,
+        images: Vec::new(),
+        output_format: OutputFormat::Text,
+        image_detail: "auto".to_string(),
+        json_schema: None,
+    };
+
+    let (source, priority) = payload.admission();
+    let _permit = match crate::prompt_queue::shared()
+        .acquire(&source, priority)
+        .await
+    {
+        Ok(permit) => permit,
+        Err(err) => return queue_rejected(err).into_response(),
+    };
+    let pinned = match pinned_llm(&state, &payload.user_id, payload.thread_id.as_deref()).await {
+        Ok(pinned) => pinned,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+    let agent = state.agent.read().await.clone();
+    let response = with_pinned_llm(
+        pinned,
+        agent.process(&payload.user_id, UserInput::Text(payload.text), options),
+    )
+    .await;
+
+    match response {
+        Ok(ProcessResult::Text(text)) => {
+            (StatusCode::OK, Json(ProcessTextResponse { text })).into_response()
+        }
+        Ok(other) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Unexpected response: {other:?}"),
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn process_text_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ProcessTextRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    if payload.from_human() {
+        resolve_question_answers(
+            &state,
+            &payload.user_id,
+            &payload.text,
+            payload.reply_to.as_deref(),
+        )
+        .await;
+    }
+
+    if let Some(command) = slash_command(&mut payload) {
+        let reply = run_slash_command(&state, &payload.user_id, command).await;
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(Body::from(reply))
+            .unwrap();
+    }
+
+    let agent = state.agent.read().await.clone();
+    let (source, priority) = payload.admission();
+    let ProcessTextRequest {
+        user_id,
+        text,
+        prompt,
+        thread_id,
+        reply_to,
+        ..
+    } = payload;
+    let pinned = pinned_llm(&state, &user_id, thread_id.as_deref()).await;
+    let prompt = prompt_with_item_notes(&state, &user_id, reply_to.as_deref(), prompt).await;
+
+    if asks_for_wallet_address_only(&text) {
+        let wallet_line = match crate::security::solana_signer::wallet_address(&user_id, "agent") {
+            Ok(address) => format!("Your Solana wallet address is {address}."),
+            Err(err) => format!("[error] {}", err),
+        };
+
+        let body = Body::from(wallet_line);
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(body)
+            .unwrap();
+    }
+
+    if asks_for_wallet_balance_only(&text) {
+        let balance_line = match solana_balance_line_for_user(&state, &user_id).await {
+            Ok(line) => line,
+            Err(err) => format!("I couldn't fetch your Solana balance right now: {err}"),
+        };
+
+        let body = Body::from(balance_line);
+        return Response::builder()
+            .status(StatusCode::OK)
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(body)
+            .unwrap();
+    }
+
+    let permit = match crate::prompt_queue::shared()
+        .acquire(&source, priority)
+        .await
+    {
+        Ok(permit) => permit,
+        Err(err) => return queue_rejected(err).into_response(),
+    };
+
+    let pinned = match pinned {
+        Ok(pinned) => pinned,
+        Err(err) => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .header("content-type", "text/plain; charset=utf-8")
+                .body(Body::from(format!("[error] {err}")))
+                .unwrap();
+        }
+    };
+    let body = Body::from_stream(async_stream::stream! {
+        let _permit = permit;
+        let mut stream = agent.process_text_stream(&user_id, &text, prompt.as_deref());
+        while let Some(item) = with_pinned_llm(pinned.clone(), stream.next()).await {
+            match item {
+                Ok(chunk) => {
+                    if !chunk.is_empty() {
+                        yield Ok::<Bytes, std::convert::Infallible>(Bytes::from(chunk));
+                    }
+                }
+                Err(err) => {
+                    let message = format!("\n[error] {}", err);
+                    yield Ok(Bytes::from(message));
+                    break;
+                }
+            }
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(body)
+        .unwrap()
+}
+
+/// Server-sent events variant of `process_text_stream`. Each chunk arrives as
+/// a `token` event carrying `{"text": ..}`, followed by a single `done` or
+/// `error` event, so clients can tell a finished reply from a dropped one.
+async fn process_text_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut payload): Json<ProcessTextRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    if payload.from_human() {
+        resolve_question_answers(
+            &state,
+            &payload.user_id,
+            &payload.text,
+            payload.reply_to.as_deref(),
+        )
+        .await;
+    }
+
+    let slash = match slash_command(&mut payload) {
+        Some(command) => Some(run_slash_command(&state, &payload.user_id, command).await),
+        None => None,
+    };
+    let agent = state.agent.read().await.clone();
+    let (source, priority) = payload.admission();
+    let ProcessTextRequest {
+        user_id,
+        text,
+        prompt,
+        thread_id,
+        reply_to,
+        ..
+    } = payload;
+    let pinned = pinned_llm(&state, &user_id, thread_id.as_deref()).await;
+    let prompt = prompt_with_item_notes(&state, &user_id, reply_to.as_deref(), prompt).await;
+
+    let shortcut = if let Some(reply) = slash {
+        Some(Ok(reply))
+    } else if asks_for_wallet_address_only(&text) {
+        Some(
+            crate::security::solana_signer::wallet_address(&user_id, "agent")
+                .map(|address| format!("Your Solana wallet address is {address}."))
+                .map_err(|err| err.to_string()),
+        )
+    } else if asks_for_wallet_balance_only(&text) {
+        Some(Ok(
+            match solana_balance_line_for_user(&state, &user_id).await {
+                Ok(line) => line,
+                Err(err) => format!("I couldn't fetch your Solana balance right now: {err}"),
+            },
+        ))
+    } else {
+        None
+    };
+
+    let body = Body::from_stream(async_stream::stream! {
+        if let Some(result) = shortcut {
+            match result {
+                Ok(line) => {
+                    yield Ok::<Bytes, std::convert::Infallible>(sse_event("token", &json!({"text": line})));
+                    yield Ok(sse_event("done", &json!({})));
+                }
+                Err(err) => yield Ok(sse_event("error", &json!({"error": err}))),
+            }
+            return;
+        }
+
+        let mut ticket = match crate::prompt_queue::shared().enqueue(&source, priority) {
+            Ok(ticket) => ticket,
+            Err(err) => {
+                yield Ok(sse_event("error", &json!({"error": err.to_string()})));
+                return;
+            }
+        };
+        let mut status = ticket.status();
+        let _permit = loop {
+            match status {
+                TicketStatus::Ready => break ticket.into_permit(),
+                TicketStatus::Shed(reason) => {
+                    yield Ok(sse_event("error", &json!({"error": reason})));
+                    return;
+                }
+                TicketStatus::Queued(position) => {
+                    // Repeated while waiting so clients with read timeouts
+                    // see the connection is alive.
+                    yield Ok(sse_event("queued", &json!({"position": position})));
+                    status = tokio::time::timeout(QUEUE_KEEPALIVE, ticket.next_status())
+                        .await
+                        .unwrap_or(TicketStatus::Queued(position));
+                }
+            }
+        };
+
+        let pinned = match pinned {
+            Ok(pinned) => pinned,
+            Err(err) => {
+                yield Ok(sse_event("error", &json!({"error": err.to_string()})));
+                return;
+            }
+        };
+        let mut stream = agent.process_text_stream(&user_id, &text, prompt.as_deref());
+        while let Some(item) = with_pinned_llm(pinned.clone(), stream.next()).await {
+            match item {
+                Ok(chunk) => {
+                    if !chunk.is_empty() {
+                        yield Ok(sse_event("token", &json!({"text": chunk})));
+                    }
+                }
+                Err(err) => {
+                    yield Ok(sse_event("error", &json!({"error": err.to_string()})));
+                    return;
+                }
+            }
+        }
+        yield Ok(sse_event("done", &json!({})));
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(body)
+        .unwrap()
+}
+
+const QUEUE_KEEPALIVE: Duration = Duration::from_secs(15);
+
+fn queue_rejected(err: ButterflyBotError) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse {
+            error: err.to_string(),
+        }),
+    )
+}
+
+async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/plain; version=0.0.4; charset=utf-8")
+        .body(Body::from(crate::metrics::render_prometheus()))
+        .unwrap()
+}
+
+async fn prompt_queue_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    (
+        StatusCode::OK,
+        Json(crate::prompt_queue::shared().snapshot()),
+    )
+        .into_response()
+}
+
+/// Every job the daemon's scheduler registered at its last start, with its
+/// last run and when the next one is expected.
+async fn scheduler_jobs(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(err) = authorize(&headers, &state.token) {
+        return err.into_response();
+    }
+    let result = match JobStateStore::new(&state.db_path).await {
+        Ok(store) => store.list_registered().await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok(jobs) => {
+            let now = now_ts();
+            let jobs = jobs
+                .into_iter()
+                .map(|job| SchedulerJobEntry {
+                    late: job.is_late(now),
+                    job,
+                })
+                .collect();
+            (StatusCode::OK, Json(SchedulerJobsResponse { now, jobs })).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn sse_event(event: &str, data: &Value) -> Bytes {
+    Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
+}
+
+async fn preload_boot(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<PreloadBootRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let agent = state.agent.read().await.clone();
+    let db_path = state.db_path.clone();
+    let ui_event_tx = state.ui_event_tx.clone();
+    let user_id = payload.user_id.clone();
+
+    tokio::spawn(async move {
+        let quick_timeout = Duration::from_secs(2);
+
+        let context_status =
+            match tokio::time::timeout(quick_timeout, agent.preload_context(&user_id)).await {
+                Ok(Ok(())) => "ok".to_string(),
+                Ok(Err(err)) => format!("error: {err}"),
+                Err(_) => {
+                    let agent = agent.clone();
+                    let ui_event_tx = ui_event_tx.clone();
+                    let user_id = user_id.clone();
+                    tokio::spawn(async move {
+                        let status = match agent.preload_context(&user_id).await {
+                            Ok(()) => "ok".to_string(),
+                            Err(err) => format!("error: {err}"),
+                        };
+                        let _ = ui_event_tx.send(UiEvent {
+                        event_type: "boot".to_string(),
+                        user_id: user_id.clone(),
+                        tool: "context".to_string(),
+                        status: status.clone(),
+                        payload: json!({"user_id": user_id, "status": status, "phase": "deferred"}),
+                        timestamp: now_ts(),
+                    });
+                    });
+                    "deferred".to_string()
+                }
+            };
+        let _ = ui_event_tx.send(UiEvent {
+            event_type: "boot".to_string(),
+            user_id: user_id.clone(),
+            tool: "context".to_string(),
+            status: context_status.clone(),
+            payload: json!({"user_id": user_id, "status": context_status, "phase": "quick"}),
+            timestamp: now_ts(),
+        });
+
+        let heartbeat_status = if let Ok(config) = Config::load(&db_path) {
+            let source = config.heartbeat_source;
+            match tokio::time::timeout(quick_timeout, load_markdown_content(&source)).await {
+                Ok(Ok(markdown)) => {
+                    agent.set_heartbeat_markdown(markdown.clone()).await;
+                    if markdown
+                        .as_ref()
+                        .map(|m| !m.trim().is_empty())
+                        .unwrap_or(false)
+                    {
+                        "ok".to_string()
+                    } else {
+                        "empty".to_string()
+                    }
+                }
+                Ok(Err(err)) => format!("error: {err}"),
+                Err(_) => {
+                    let agent = agent.clone();
+                    let ui_event_tx = ui_event_tx.clone();
+                    let source = source.clone();
+                    tokio::spawn(async move {
+                        let status = match load_markdown_content(&source).await {
+                            Ok(markdown) => {
+                                agent.set_heartbeat_markdown(markdown.clone()).await;
+                                if markdown
+                                    .as_ref()
+                                    .map(|m| !m.trim().is_empty())
+                                    .unwrap_or(false)
+                                {
+                                    "ok".to_string()
+                                } else {
+                                    "empty".to_string()
+                                }
+                            }
+                            Err(err) => format!("error: {err}"),
+                        };
+                        let _ = ui_event_tx.send(UiEvent {
+                            event_type: "boot".to_string(),
+                            user_id: "system".to_string(),
+                            tool: "heartbeat".to_string(),
+                            status: status.clone(),
+                            payload: json!({"status": status, "phase": "deferred"}),
+                            timestamp: now_ts(),
+                        });
+                    });
+                    "deferred".to_string()
+                }
+            }
+        } else {
+            "config_error".to_string()
+        };
+
+        let _ = ui_event_tx.send(UiEvent {
+            event_type: "boot".to_string(),
+            user_id: "system".to_string(),
+            tool: "heartbeat".to_string(),
+            status: heartbeat_status.clone(),
+            payload: json!({"status": heartbeat_status, "phase": "quick"}),
+            timestamp: now_ts(),
+        });
+
+        let prompt_status = if let Ok(config) = Config::load(&db_path) {
+            let source = config.prompt_source;
+            match tokio::time::timeout(quick_timeout, load_markdown_content(&source)).await {
+                Ok(Ok(markdown)) => {
+                    agent.set_prompt_markdown(markdown.clone()).await;
+                    if markdown
+                        .as_ref()
+                        .map(|m| !m.trim().is_empty())
+                        .unwrap_or(false)
+                    {
+                        "ok".to_string()
+                    } else {
+                        "empty".to_string()
+                    }
+                }
+                Ok(Err(err)) => format!("error: {err}"),
+                Err(_) => {
+                    let agent = agent.clone();
+                    let ui_event_tx = ui_event_tx.clone();
+                    let source = source.clone();
+                    tokio::spawn(async move {
+                        let status = match load_markdown_content(&source).await {
+                            Ok(markdown) => {
+                                agent.set_prompt_markdown(markdown.clone()).await;
+                                if markdown
+                                    .as_ref()
+                                    .map(|m| !m.trim().is_empty())
+                                    .unwrap_or(false)
+                                {
+                                    "ok".to_string()
+                                } else {
+                                    "empty".to_string()
+                                }
+                            }
+                            Err(err) => format!("error: {err}"),
+                        };
+                        let _ = ui_event_tx.send(UiEvent {
+                            event_type: "boot".to_string(),
+                            user_id: "system".to_string(),
+                            tool: "prompt".to_string(),
+                            status: status.clone(),
+                            payload: json!({"status": status, "phase": "deferred"}),
+                            timestamp: now_ts(),
+                        });
+                    });
+                    "deferred".to_string()
+                }
+            }
+        } else {
+            "config_error".to_string()
+        };
+
+        let _ = ui_event_tx.send(UiEvent {
+            event_type: "boot".to_string(),
+            user_id: user_id.clone(),
+            tool: "prompt".to_string(),
+            status: prompt_status.clone(),
+            payload: json!({"status": prompt_status}),
+            timestamp: now_ts(),
+        });
+
+        if (heartbeat_status == "ok"
+            || heartbeat_status == "empty"
+            || heartbeat_status == "deferred")
+            && (prompt_status == "ok" || prompt_status == "empty" || prompt_status == "deferred")
+        {
+            let agent = agent.clone();
+            let ui_event_tx = ui_event_tx.clone();
+            let user_id = user_id.clone();
+            tokio::spawn(async move {
+                run_autonomy_tick(agent, ui_event_tx, user_id, "boot").await;
+            });
+        }
+    });
+
+    (
+        StatusCode::OK,
+        Json(PreloadBootResponse {
+            context_status: "started".to_string(),
+            heartbeat_status: "started".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+async fn memory_search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<MemorySearchRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let limit = payload.limit.unwrap_or(8);
+    let agent = state.agent.read().await.clone();
+    let response = agent
+        .search_memory(&payload.user_id, &payload.query, limit)
+        .await;
+
+    match response {
+        Ok(results) => (StatusCode::OK, Json(MemorySearchResponse { results })).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn semantic_memory_search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SemanticMemorySearchRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let query = payload.query.trim();
+    if query.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "query is required".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let limit = payload.limit.unwrap_or(10).clamp(1, 50);
+    let agent = state.agent.read().await.clone();
+    match agent
+        .semantic_search_memory(
+            &payload.user_id,
+            query,
+            limit,
+            payload.rerank.unwrap_or(false),
+        )
+        .await
+    {
+        Ok(results) => (
+            StatusCode::OK,
+            Json(SemanticMemorySearchResponse { results }),
+        )
+            .into_response(),
+        Err(ButterflyBotError::Config(error)) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Runs a retention pass for one user. Defaults to a dry run so the report
+/// can be reviewed before anything is summarized or deleted.
+async fn memory_retention(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<MemoryRetentionRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let agent = state.agent.read().await.clone();
+    match agent
+        .enforce_memory_retention(&payload.user_id, payload.dry_run.unwrap_or(true))
+        .await
+    {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(ButterflyBotError::Config(error)) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn chat_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ChatHistoryQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let limit = query.limit.unwrap_or(40).clamp(1, 200);
+    let agent = state.agent.read().await.clone();
+    let response = agent.get_user_history(&query.user_id, limit).await;
+
+    match response {
+        Ok(history) => (StatusCode::OK, Json(ChatHistoryResponse { history })).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn clear_user_history(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ClearHistoryRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let agent = state.agent.read().await.clone();
+    tracing::info!(
+        "clear_user_history requested for user_id={}",
+        payload.user_id
+    );
+    match agent.delete_user_history(&payload.user_id).await {
+        Ok(()) => (
+            StatusCode::OK,
+            Json(ClearHistoryResponse {
+                status: "ok".to_string(),
+                message: "User history cleared".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(err) => {
+            tracing::error!(
+                "clear_user_history failed for user_id={}: {}",
+                payload.user_id,
+                err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn trash_batches(db_path: &str, user_id: &str) -> Result<Vec<TrashBatch>> {
+    let mut batches = TodoStore::new(db_path).await?.list_trash(user_id).await?;
+    batches.extend(
+        ReminderStore::new(db_path)
+            .await?
+            .list_trash(user_id)
+            .await?,
+    );
+    batches.extend(TaskStore::new(db_path).await?.list_trash(user_id).await?);
+    batches.extend(PlanStore::new(db_path).await?.list_trash(user_id).await?);
+    batches.sort_by(|a, b| b.trashed_at.cmp(&a.trashed_at));
+    Ok(batches)
+}
+
+/// `None` when `store` names no store with a trash.
+async fn restore_trash_batch(
+    db_path: &str,
+    user_id: &str,
+    store: &str,
+    batch_id: &str,
+) -> Result<Option<usize>> {
+    let restored = match store {
+        "todo" => {
+            TodoStore::new(db_path)
+                .await?
+                .restore_trash(user_id, batch_id)
+                .await?
+        }
+        "reminders" => {
+            ReminderStore::new(db_path)
+                .await?
+                .restore_trash(user_id, batch_id)
+                .await?
+        }
+        "tasks" => {
+            TaskStore::new(db_path)
+                .await?
+                .restore_trash(user_id, batch_id)
+                .await?
+        }
+        "plans" => {
+            PlanStore::new(db_path)
+                .await?
+                .restore_trash(user_id, batch_id)
+                .await?
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(restored))
+}
+
+fn search_sources(db_path: &str) -> SearchSources {
+    let config = Config::load(db_path).ok();
+    let memory_db_path = config
+        .as_ref()
+        .and_then(|cfg| cfg.memory.as_ref())
+        .and_then(|memory| memory.sqlite_path.clone())
+        .unwrap_or_else(|| db_path.to_string());
+    let tools = config.and_then(|cfg| cfg.tools).unwrap_or(Value::Null);
+    SearchSources {
+        todo_db_path: resolve_todo_db_path(&tools).unwrap_or_else(|| db_path.to_string()),
+        reminder_db_path: resolve_reminder_db_path(&tools).unwrap_or_else(|| db_path.to_string()),
+        plan_db_path: resolve_plan_db_path(&tools).unwrap_or_else(|| db_path.to_string()),
+        memory_db_path,
+    }
+}
+
+async fn search_work_items(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<SearchQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    if query.q.trim().is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "q is required".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let kinds = SearchKind::parse_list(query.kinds.as_deref());
+    let limit = query.limit.unwrap_or(20).clamp(1, 100);
+    let outcome = match SearchIndex::new(&state.db_path).await {
+        Ok(index) => {
+            search::search_everything(
+                &index,
+                &search_sources(&state.db_path),
+                &query.user_id,
+                &query.q,
+                &kinds,
+                limit,
+            )
+            .await
+        }
+        Err(err) => Err(err),
+    };
+
+    match outcome {
+        Ok(outcome) => (
+            StatusCode::OK,
+            Json(SearchResponse {
+                query: query.q,
+                indexed: outcome.indexed,
+                hits: outcome.hits,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Chat backend pinned to the request's thread, or `None` when the thread
+/// pins nothing and the agent's configured backend applies.
+async fn pinned_llm(
+    state: &AppState,
+    user_id: &str,
+    thread_id: Option<&str>,
+) -> Result<Option<Arc<dyn crate::llm::Provider>>> {
+    let thread_id = threads::thread_or_default(thread_id);
+    let Some(thread) = ThreadStore::new(&state.db_path)
+        .await?
+        .get(user_id, thread_id)
+        .await?
+    else {
+        return Ok(None);
+    };
+    if !thread.is_pinned() {
+        return Ok(None);
+    }
+    let config = Config::load(&state.db_path)?;
+    thread.pinned_provider(&config)
+}
+
+async fn list_threads(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ThreadsQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+    let default_model = Config::load(&state.db_path)
+        .map(|config| crate::llm::primary_label(&config))
+        .unwrap_or_default();
+    let threads = match ThreadStore::new(&state.db_path).await {
+        Ok(store) => store.list(&query.user_id).await,
+        Err(err) => Err(err),
+    };
+    match threads {
+        Ok(threads) => (
+            StatusCode::OK,
+            Json(ThreadsResponse {
+                default_model,
+                threads,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn pin_thread(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ThreadPinRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    // A pin that cannot build a backend (say, Anthropic without a key) would
+    // fail every later prompt in the thread, so refuse it up front.
+    if let Ok(config) = Config::load(&state.db_path) {
+        let provider = payload
+            .pin
+            .provider
+            .as_deref()
+            .and_then(LlmProviderKind::parse);
+        if let Err(err) = crate::llm::build_pinned_provider(
+            &config,
+            provider,
+            payload.pin.model.clone(),
+            payload.pin.temperature,
+        ) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    }
+
+    let pinned = match ThreadStore::new(&state.db_path).await {
+        Ok(store) => {
+            store
+                .pin(&payload.user_id, &payload.thread_id, payload.pin)
+                .await
+        }
+        Err(err) => Err(err),
+    };
+    match pinned {
+        Ok(thread) => (StatusCode::OK, Json(thread)).into_response(),
+        Err(err @ ButterflyBotError::Config(_)) => (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn unpin_thread(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ThreadUnpinRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+    let unpinned = match ThreadStore::new(&state.db_path).await {
+        Ok(store) => store.unpin(&payload.user_id, &payload.thread_id).await,
+        Err(err) => Err(err),
+    };
+    match unpinned {
+        Ok(unpinned) => (StatusCode::OK, Json(json!({ "unpinned": unpinned }))).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn list_trash(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<TrashQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let retention_days = Config::load(&state.db_path)
+        .ok()
+        .map(|config| TrashConfig::from_tools(config.tools.as_ref()))
+        .unwrap_or_default()
+        .retention_days;
+    match trash_batches(&state.db_path, &query.user_id).await {
+        Ok(batches) => (
+            StatusCode::OK,
+            Json(TrashResponse {
+                batches,
+                retention_days,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn restore_trash(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TrashRestoreRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let restored = match restore_trash_batch(
+        &state.db_path,
+        &payload.user_id,
+        &payload.store,
+        &payload.batch_id,
+    )
+    .await
+    {
+        Ok(Some(restored)) => restored,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("unknown store '{}'", payload.store),
+                }),
+            )
+                .into_response()
+        }
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "trash".to_string(),
+        user_id: payload.user_id.clone(),
+        tool: payload.store.clone(),
+        status: "restored".to_string(),
+        payload: json!({
+            "batch_id": payload.batch_id,
+            "restored": restored,
+        }),
+        timestamp: now_ts(),
+    });
+
+    (
+        StatusCode::OK,
+        Json(TrashRestoreResponse {
+            status: "ok".to_string(),
+            store: payload.store,
+            batch_id: payload.batch_id,
+            restored,
+        }),
+    )
+        .into_response()
+}
+
+fn template_import_targets(db_path: &str) -> (ImportTargets, SandboxSettings) {
+    let config_json = Config::load(db_path)
+        .ok()
+        .and_then(|cfg| cfg.tools)
+        .unwrap_or(Value::Null);
+    let targets = ImportTargets {
+        plan_db_path: resolve_plan_db_path(&config_json).unwrap_or_else(|| db_path.to_string()),
+        todo_db_path: resolve_todo_db_path(&config_json).unwrap_or_else(|| db_path.to_string()),
+        task_db_path: resolve_task_db_path(&config_json).unwrap_or_else(|| db_path.to_string()),
+        prompt_db_path: db_path.to_string(),
+    };
+    let settings = SandboxSettings::from_root_config(&json!({ "tools": config_json }));
+    (targets, settings)
+}
+
+async fn preview_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TemplateBundleRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let bundle = match TemplateBundle::from_value(&payload.bundle) {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let (_, settings) = template_import_targets(&state.db_path);
+    let prompt_names = bundle
+        .prompt_templates
+        .iter()
+        .map(|prompt| prompt.name.trim().to_string())
+        .collect::<Vec<_>>();
+    let existing = match PromptTemplateStore::new(&state.db_path).await {
+        Ok(store) => store
+            .existing_names(&payload.user_id, &prompt_names)
+            .await
+            .unwrap_or_default(),
+        Err(_) => Vec::new(),
+    };
+
+    (
+        StatusCode::OK,
+        Json(templates::preview(&bundle, &settings, &existing)),
+    )
+        .into_response()
+}
+
+async fn import_template(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<TemplateBundleRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let bundle = match TemplateBundle::from_value(&payload.bundle) {
+        Ok(bundle) => bundle,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    };
+    let digest = payload.digest.unwrap_or_default();
+    if digest.trim() != bundle.digest() {
+        return (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: "Template bundle does not match the reviewed preview".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let (targets, _) = template_import_targets(&state.db_path);
+    let imported =
+        match templates::import_bundle(&targets, &payload.user_id, &bundle, &digest).await {
+            Ok(imported) => imported,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response()
+            }
+        };
+
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "templates".to_string(),
+        user_id: payload.user_id.clone(),
+        tool: "templates".to_string(),
+        status: "imported".to_string(),
+        payload: json!({
+            "name": bundle.name,
+            "imported": imported,
+        }),
+        timestamp: now_ts(),
+    });
+
+    (
+        StatusCode::OK,
+        Json(TemplateImportResponse {
+            status: "ok".to_string(),
+            name: bundle.name.trim().to_string(),
+            imported,
+        }),
+    )
+        .into_response()
+}
+
+async fn clear_user_data(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<ClearUserDataRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let user_id = payload.user_id;
+    let agent = state.agent.read().await.clone();
+    if let Err(err) = agent.delete_user_history(&user_id).await {
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let reminders_deleted = match ReminderStore::new(&state.db_path).await {
+        Ok(store) => match async {
+            let deleted = store.delete_all(&user_id, true).await?;
+            store.clear_deliveries(&user_id).await?;
+            Ok::<_, ButterflyBotError>(deleted)
+        }
+        .await
+        {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let todos_deleted = match TodoStore::new(&state.db_path).await {
+        Ok(store) => match store
+            .clear_items(&user_id, crate::todo::TodoStatus::All)
+            .await
+        {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let tasks_deleted = match TaskStore::new(&state.db_path).await {
+        Ok(store) => match store
+            .clear_tasks(&user_id, crate::tasks::TaskStatus::All)
+            .await
+        {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let plans_deleted = match PlanStore::new(&state.db_path).await {
+        Ok(store) => match async {
+            let deleted = store.clear_plans(&user_id).await?;
+            store.clear_templates(&user_id).await?;
+            Ok::<_, ButterflyBotError>(deleted)
+        }
+        .await
+        {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let inbox_states_deleted = match InboxStateStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_statuses(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let labels_deleted = match LabelStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let projects_deleted = match ProjectStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let prompt_templates_deleted = match PromptTemplateStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let threads_deleted = match ThreadStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: err.to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let questions_deleted = match QuestionStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
+            Ok(v) => v,
+            Err(err) => {
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: err.to_string(),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        Err(err) => {
+            return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
                     error: err.to_string(),
                 }),
             )
-                .into_response()
+                .into_response();
         }
-    }
-}
-
-async fn clear_user_data(
-    State(state): State<AppState>,
-    headers: HeaderMap,
-    Json(payload): Json<ClearUserDataRequest>,
-) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
-    }
-
-    let user_id = payload.user_id;
-    let agent = state.agent.read().await.clone();
-    if let Err(err) = agent.delete_user_history(&user_id).await {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: err.to_string(),
-            }),
-        )
-            .into_response();
-    }
+    };
 
-    let reminders_deleted = match ReminderStore::new(&state.db_path).await {
-        Ok(store) => match store.delete_all(&user_id, true).await {
+    let outbox_deliveries_deleted = match OutboxStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
             Ok(v) => v,
             Err(err) => {
                 return (
@@ -3689,11 +10725,8 @@ async fn clear_user_data(
         }
     };
 
-    let todos_deleted = match TodoStore::new(&state.db_path).await {
-        Ok(store) => match store
-            .clear_items(&user_id, crate::todo::TodoStatus::All)
-            .await
-        {
+    let external_items_deleted = match ExternalItemStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
             Ok(v) => v,
             Err(err) => {
                 return (
@@ -3716,11 +10749,8 @@ async fn clear_user_data(
         }
     };
 
-    let tasks_deleted = match TaskStore::new(&state.db_path).await {
-        Ok(store) => match store
-            .clear_tasks(&user_id, crate::tasks::TaskStatus::All)
-            .await
-        {
+    let calendar_events_deleted = match CalendarStore::new(&state.db_path).await {
+        Ok(store) => match store.clear_user(&user_id).await {
             Ok(v) => v,
             Err(err) => {
                 return (
@@ -3743,8 +10773,8 @@ async fn clear_user_data(
         }
     };
 
-    let plans_deleted = match PlanStore::new(&state.db_path).await {
-        Ok(store) => match store.clear_plans(&user_id).await {
+    let search_rows_deleted = match SearchIndex::new(&state.db_path).await {
+        Ok(index) => match index.clear_user(&user_id).await {
             Ok(v) => v,
             Err(err) => {
                 return (
@@ -3767,8 +10797,8 @@ async fn clear_user_data(
         }
     };
 
-    let inbox_states_deleted = match InboxStateStore::new(&state.db_path).await {
-        Ok(store) => match store.clear_statuses(&user_id).await {
+    let attachments_deleted = match attachment_store(&state).await {
+        Ok(store) => match store.clear(&user_id).await {
             Ok(v) => v,
             Err(err) => {
                 return (
@@ -3789,74 +10819,439 @@ async fn clear_user_data(
             )
                 .into_response();
         }
-    };
+    };
+
+    (
+        StatusCode::OK,
+        Json(ClearUserDataResponse {
+            status: "ok".to_string(),
+            message: "User work data cleared".to_string(),
+            cleared: json!({
+                "history": true,
+                "reminders": reminders_deleted,
+                "todos": todos_deleted,
+                "tasks": tasks_deleted,
+                "plans": plans_deleted,
+                "inbox_state_overrides": inbox_states_deleted,
+                "labels": labels_deleted,
+                "projects": projects_deleted,
+                "prompt_templates": prompt_templates_deleted,
+                "search_index": search_rows_deleted,
+                "chat_threads": threads_deleted,
+                "agent_questions": questions_deleted,
+                "webhook_outbox": outbox_deliveries_deleted,
+                "external_items": external_items_deleted,
+                "calendar_events": calendar_events_deleted,
+                "attachments": attachments_deleted,
+            }),
+        }),
+    )
+        .into_response()
+}
+
+async fn reminder_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<ReminderStreamQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let store = state.reminder_store.clone();
+    let user_id = query.user_id;
+    let mut tick = tokio::time::interval(Duration::from_secs(1));
+
+    let body = Body::from_stream(async_stream::stream! {
+        loop {
+            tick.tick().await;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs() as i64;
+            if let Ok(items) = store.due_reminders(&user_id, now, 10).await {
+                if cfg!(debug_assertions) && !items.is_empty() {
+                    eprintln!(
+                        "Reminder stream emit: user_id={} count={} now={}",
+                        user_id,
+                        items.len(),
+                        now
+                    );
+                }
+                for item in items {
+                    let payload = serde_json::json!({
+                        "id": item.id,
+                        "title": item.title,
+                        "due_at": item.due_at,
+                    });
+                    let line = format!("data: {}\n\n", payload);
+                    yield Ok::<Bytes, std::convert::Infallible>(Bytes::from(line));
+                }
+            }
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(body)
+        .unwrap()
+}
+
+async fn issue_user_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<IssueUserTokenRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let result = state
+        .session_store
+        .issue_token(&payload.user_id, payload.label.as_deref())
+        .await;
+    match result {
+        Ok(issued) => (StatusCode::OK, Json(issued)).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn list_user_tokens(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Query(query): axum::extract::Query<UserTokensQuery>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&query.user_id)).await {
+        return err.into_response();
+    }
+
+    let include_revoked = query.include_revoked.unwrap_or(false);
+    let result = state
+        .session_store
+        .list_tokens(&query.user_id, include_revoked)
+        .await;
+    match result {
+        Ok(tokens) => (StatusCode::OK, Json(UserTokensResponse { tokens })).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn rotate_user_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<UserTokenRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let result = state
+        .session_store
+        .rotate_token(&payload.user_id, payload.token_id)
+        .await;
+    match result {
+        Ok(Some(issued)) => (StatusCode::OK, Json(issued)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Token not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn revoke_user_token(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<UserTokenRequest>,
+) -> impl IntoResponse {
+    if let Err(err) = authorize_user(&state, &headers, Some(&payload.user_id)).await {
+        return err.into_response();
+    }
+
+    let result = state
+        .session_store
+        .revoke_token(&payload.user_id, payload.token_id)
+        .await;
+    match result {
+        Ok(revoked) => (StatusCode::OK, Json(json!({ "revoked": revoked }))).into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+fn role_policy_from(config: &Config) -> Result<RolePolicy> {
+    let root =
+        serde_json::to_value(config).map_err(|e| ButterflyBotError::Config(e.to_string()))?;
+    Ok(RolePolicy::from_root_config(&root))
+}
+
+/// Role management is limited to the daemon token and users holding the
+/// admin role. Returns the acting user, or `None` for the daemon token.
+async fn authorize_role_admin(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> std::result::Result<Option<String>, (StatusCode, Json<ErrorResponse>)> {
+    let Some(actor) = authorize_user(state, headers, None).await? else {
+        return Ok(None);
+    };
+    let role = match state.role_store.role_for(&actor).await {
+        Ok(Some(role)) => role,
+        Ok(None) => state.role_policy.read().await.default_role,
+        Err(err) => {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: format!("Could not resolve role: {err}"),
+                }),
+            ))
+        }
+    };
+    if role == Role::Admin {
+        Ok(Some(actor))
+    } else {
+        Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: "Admin role required".to_string(),
+            }),
+        ))
+    }
+}
+
+async fn list_user_roles(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize_role_admin(&state, &headers).await {
+        return err.into_response();
+    }
+
+    let result = state.role_store.list_roles().await;
+    match result {
+        Ok(roles) => (
+            StatusCode::OK,
+            Json(UserRolesResponse {
+                default_role: state.role_policy.read().await.default_role,
+                roles,
+            }),
+        )
+            .into_response(),
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn set_user_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<SetUserRoleRequest>,
+) -> impl IntoResponse {
+    let actor = match authorize_role_admin(&state, &headers).await {
+        Ok(actor) => actor,
+        Err(err) => return err.into_response(),
+    };
+    let Some(role) = Role::parse(&payload.role) else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Unsupported role (expected admin, member or readonly)".to_string(),
+            }),
+        )
+            .into_response();
+    };
+
+    let granted_by = actor.as_deref().unwrap_or("daemon");
+    let result = state
+        .role_store
+        .set_role(&payload.user_id, role, Some(granted_by))
+        .await;
+    match result {
+        Ok(assigned) => {
+            let _ = state.ui_event_tx.send(UiEvent {
+                event_type: "audit".to_string(),
+                user_id: assigned.user_id.clone(),
+                tool: "roles".to_string(),
+                status: "role_assigned".to_string(),
+                payload: json!({"role": assigned.role, "granted_by": granted_by}),
+                timestamp: assigned.updated_at,
+            });
+            (StatusCode::OK, Json(assigned)).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+async fn remove_user_role(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RemoveUserRoleRequest>,
+) -> impl IntoResponse {
+    let actor = match authorize_role_admin(&state, &headers).await {
+        Ok(actor) => actor,
+        Err(err) => return err.into_response(),
+    };
+
+    let result = state.role_store.remove_role(&payload.user_id).await;
+    match result {
+        Ok(removed) => {
+            if removed {
+                let _ = state.ui_event_tx.send(UiEvent {
+                    event_type: "audit".to_string(),
+                    user_id: payload.user_id.clone(),
+                    tool: "roles".to_string(),
+                    status: "role_removed".to_string(),
+                    payload: json!({"removed_by": actor.as_deref().unwrap_or("daemon")}),
+                    timestamp: now_ts(),
+                });
+            }
+            (StatusCode::OK, Json(json!({"removed": removed}))).into_response()
+        }
+        Err(err) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
 
-    (
-        StatusCode::OK,
-        Json(ClearUserDataResponse {
-            status: "ok".to_string(),
-            message: "User work data cleared".to_string(),
-            cleared: json!({
-                "history": true,
-                "reminders": reminders_deleted,
-                "todos": todos_deleted,
-                "tasks": tasks_deleted,
-                "plans": plans_deleted,
-                "inbox_state_overrides": inbox_states_deleted,
+fn vault_error_response(err: ButterflyBotError) -> axum::response::Response {
+    match err {
+        ButterflyBotError::Config(error) => {
+            (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+        }
+        err => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: err.to_string(),
             }),
-        }),
-    )
-        .into_response()
+        )
+            .into_response(),
+    }
 }
 
-async fn reminder_stream(
+/// Records a named-secret change; values never leave the vault.
+fn emit_secret_audit(state: &AppState, actor: Option<&str>, status: &str, name: &str) {
+    let _ = state.ui_event_tx.send(UiEvent {
+        event_type: "audit".to_string(),
+        user_id: actor.unwrap_or("daemon").to_string(),
+        tool: "vault".to_string(),
+        status: status.to_string(),
+        payload: json!({"name": name}),
+        timestamp: now_ts(),
+    });
+}
+
+/// Lists named secrets by metadata. Like the other secret routes it needs
+/// the admin role.
+async fn list_secrets(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    if let Err(err) = authorize_role_admin(&state, &headers).await {
+        return err.into_response();
+    }
+
+    match vault::list_secrets() {
+        Ok(secrets) => (StatusCode::OK, Json(SecretsResponse { secrets })).into_response(),
+        Err(err) => vault_error_response(err),
+    }
+}
+
+async fn create_secret(
     State(state): State<AppState>,
     headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<ReminderStreamQuery>,
+    Json(payload): Json<CreateSecretRequest>,
 ) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
+    let actor = match authorize_role_admin(&state, &headers).await {
+        Ok(actor) => actor,
+        Err(err) => return err.into_response(),
+    };
+
+    let name = payload.name.trim();
+    match vault::create_secret(name, &payload.value, payload.description.as_deref()) {
+        Ok(metadata) => {
+            emit_secret_audit(&state, actor.as_deref(), "secret_created", name);
+            (StatusCode::OK, Json(metadata)).into_response()
+        }
+        Err(err) => vault_error_response(err),
     }
+}
 
-    let store = state.reminder_store.clone();
-    let user_id = query.user_id;
-    let mut tick = tokio::time::interval(Duration::from_secs(1));
+async fn rotate_secret(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<RotateSecretRequest>,
+) -> impl IntoResponse {
+    let actor = match authorize_role_admin(&state, &headers).await {
+        Ok(actor) => actor,
+        Err(err) => return err.into_response(),
+    };
 
-    let body = Body::from_stream(async_stream::stream! {
-        loop {
-            tick.tick().await;
-            let now = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() as i64;
-            if let Ok(items) = store.due_reminders(&user_id, now, 10).await {
-                if cfg!(debug_assertions) && !items.is_empty() {
-                    eprintln!(
-                        "Reminder stream emit: user_id={} count={} now={}",
-                        user_id,
-                        items.len(),
-                        now
-                    );
-                }
-                for item in items {
-                    let payload = serde_json::json!({
-                        "id": item.id,
-                        "title": item.title,
-                        "due_at": item.due_at,
-                    });
-                    let line = format!("data: {}\n\n", payload);
-                    yield Ok::<Bytes, std::convert::Infallible>(Bytes::from(line));
-                }
-            }
+    let name = payload.name.trim();
+    match vault::rotate_secret(name, &payload.value) {
+        Ok(metadata) => {
+            emit_secret_audit(&state, actor.as_deref(), "secret_rotated", name);
+            (StatusCode::OK, Json(metadata)).into_response()
         }
-    });
+        Err(err) => vault_error_response(err),
+    }
+}
 
-    Response::builder()
-        .status(StatusCode::OK)
-        .header("content-type", "text/event-stream")
-        .header("cache-control", "no-cache")
-        .body(body)
-        .unwrap()
+async fn delete_secret(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(payload): Json<DeleteSecretRequest>,
+) -> impl IntoResponse {
+    let actor = match authorize_role_admin(&state, &headers).await {
+        Ok(actor) => actor,
+        Err(err) => return err.into_response(),
+    };
+
+    let name = payload.name.trim();
+    match vault::delete_secret(name) {
+        Ok(deleted) => {
+            if deleted {
+                emit_secret_audit(&state, actor.as_deref(), "secret_deleted", name);
+            }
+            (StatusCode::OK, Json(json!({"deleted": deleted}))).into_response()
+        }
+        Err(err) => vault_error_response(err),
+    }
 }
 
 async fn reload_config(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
@@ -3864,10 +11259,30 @@ async fn reload_config(State(state): State<AppState>, headers: HeaderMap) -> imp
         return err.into_response();
     }
 
+    // Checked first so a broken reference keeps the running agent and
+    // policy instead of rebuilding from a last good or default config.
+    let (config, policy) = match Config::reload(&state.db_path).and_then(|config| {
+        let policy = role_policy_from(&config)?;
+        Ok((config, policy))
+    }) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Config not reloaded: {err}"),
+                }),
+            )
+                .into_response()
+        }
+    };
     let agent =
         ButterflyBot::from_store_with_events(&state.db_path, Some(state.ui_event_tx.clone())).await;
     match agent {
         Ok(agent) => {
+            crate::prompt_queue::shared()
+                .configure(PromptQueueConfig::from_tools(config.tools.as_ref()));
+            *state.role_policy.write().await = policy;
             let mut guard = state.agent.write().await;
             *guard = Arc::new(agent);
             (
@@ -3958,10 +11373,12 @@ async fn factory_reset_config(
 async fn ui_events(
     State(state): State<AppState>,
     headers: HeaderMap,
-    axum::extract::Query(query): axum::extract::Query<UiEventStreamQuery>,
+    axum::extract::Query(mut query): axum::extract::Query<UiEventStreamQuery>,
 ) -> impl IntoResponse {
-    if let Err(err) = authorize(&headers, &state.token) {
-        return err.into_response();
+    match authorize_user(&state, &headers, query.user_id.as_deref()).await {
+        Ok(Some(user_id)) => query.user_id = Some(user_id),
+        Ok(None) => {}
+        Err(err) => return err.into_response(),
     }
 
     let mut receiver = state.ui_event_tx.subscribe();
@@ -3969,7 +11386,16 @@ async fn ui_events(
 
     let body = Body::from_stream(async_stream::stream! {
         loop {
-            match receiver.recv().await {
+            let Ok(received) = tokio::time::timeout(UI_EVENT_KEEPALIVE, receiver.recv()).await
+            else {
+                // A comment frame, so clients with read timeouts can tell a
+                // quiet daemon from a dead connection.
+                yield Ok::<Bytes, std::convert::Infallible>(Bytes::from_static(
+                    b": keepalive\n\n",
+                ));
+                continue;
+            };
+            match received {
                 Ok(event) => {
                     if let Some(filter) = &filter_user {
                         if event.user_id != *filter
@@ -3980,6 +11406,10 @@ async fn ui_events(
                             continue;
                         }
                     }
+                    let event = UiEvent {
+                        payload: redact_json(&event.payload),
+                        ..event
+                    };
                     let payload = serde_json::to_string(&event).unwrap_or_default();
                     let line = format!("data: {}\n\n", payload);
                     yield Ok::<Bytes, std::convert::Infallible>(Bytes::from(line));
@@ -4000,18 +11430,41 @@ async fn ui_events(
         .unwrap()
 }
 
+const UI_EVENT_KEEPALIVE: Duration = Duration::from_secs(15);
+
+fn presented_token(headers: &HeaderMap) -> &str {
+    let bearer = headers
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .unwrap_or_default();
+    if !bearer.is_empty() {
+        return bearer;
+    }
+    headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+fn unauthorized() -> (StatusCode, Json<ErrorResponse>) {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "Unauthorized".to_string(),
+        }),
+    )
+}
+
 fn authorize(
     headers: &HeaderMap,
     token: &str,
 ) -> std::result::Result<(), (StatusCode, Json<ErrorResponse>)> {
     let expected_token = token.trim();
     if expected_token.is_empty() {
-        return Err((
-            StatusCode::UNAUTHORIZED,
-            Json(ErrorResponse {
-                error: "Unauthorized".to_string(),
-            }),
-        ));
+        return Err(unauthorized());
     }
 
     let header = headers
@@ -4028,18 +11481,46 @@ fn authorize(
     if bearer == expected_token || api_key == expected_token {
         Ok(())
     } else {
-        Err((
-            StatusCode::UNAUTHORIZED,
+        Err(unauthorized())
+    }
+}
+
+/// Authorizes a request that acts on behalf of a user.
+///
+/// The daemon token is an admin credential: it may act for any user and yields
+/// `Ok(None)`. A per-user token yields `Ok(Some(owner))` and is refused when the
+/// request names a different user.
+async fn authorize_user(
+    state: &AppState,
+    headers: &HeaderMap,
+    requested_user_id: Option<&str>,
+) -> std::result::Result<Option<String>, (StatusCode, Json<ErrorResponse>)> {
+    if authorize(headers, &state.token).is_ok() {
+        return Ok(None);
+    }
+
+    let token = presented_token(headers);
+    if token.is_empty() {
+        return Err(unauthorized());
+    }
+    let owner = state.session_store.resolve_user(token).await.ok().flatten();
+    let Some(owner) = owner else {
+        return Err(unauthorized());
+    };
+
+    match requested_user_id {
+        Some(requested) if requested != owner => Err((
+            StatusCode::FORBIDDEN,
             Json(ErrorResponse {
-                error: "Unauthorized".to_string(),
+                error: "Token is not valid for this user".to_string(),
             }),
-        ))
+        )),
+        _ => Ok(Some(owner)),
     }
 }
 
 fn load_solana_rpc_policy(state: &AppState) -> Result<SolanaRpcExecutionPolicy> {
-    let config = Config::from_store(&state.db_path)
-        .unwrap_or_else(|_| Config::convention_defaults(&state.db_path));
+    let config = Config::load(&state.db_path)?;
     SolanaRpcExecutionPolicy::from_config(&config)
 }
 
@@ -4163,7 +11644,7 @@ fn archive_db_for_recovery(db_path: &str) -> Result<Option<String>> {
 
 fn load_or_bootstrap_config_with_recovery(db_path: &str) -> Result<Config> {
     if let Ok(config) = Config::from_store(db_path) {
-        return Ok(config);
+        return config.resolve_references();
     }
 
     tracing::warn!("No config in store; writing default config for {}", db_path);
@@ -4186,6 +11667,10 @@ fn load_or_bootstrap_config_with_recovery(db_path: &str) -> Result<Config> {
     }
 }
 
+/// How long the server and the audit recorder get to close once in-flight
+/// work has drained.
+const SHUTDOWN_CLOSE_GRACE: Duration = Duration::from_secs(5);
+
 pub async fn run(host: &str, port: u16, db_path: &str, token: &str) -> Result<()> {
     run_with_shutdown(host, port, db_path, token, futures::future::pending::<()>()).await
 }
@@ -4216,6 +11701,10 @@ where
         config.heartbeat_source
     );
 
+    for mismatch in capability_coverage(config.tools.as_ref()).describe_mismatches() {
+        tracing::warn!(%mismatch, "Capability coverage mismatch");
+    }
+
     bootstrap_solana_wallets(Some(&config))?;
 
     let tick_seconds = Some(&config)
@@ -4226,14 +11715,69 @@ where
         .unwrap_or(60);
 
     let (ui_event_tx, _) = broadcast::channel(256);
-    if let Some(path) = ui_event_log_path(Some(&config)) {
+    vault::remember_named_secrets();
+    let audit_store = AuditStore::new(db_path).await?;
+    // Registers enrolled users as locked before any store can write for them.
+    UserDomainStore::new(db_path).await?;
+    // Migrations have run by now, so anything missing is real drift.
+    let drift_path = db_path.to_string();
+    tokio::task::spawn_blocking(move || match crate::schema_drift::check(&drift_path) {
+        Ok(drift) => {
+            for item in drift {
+                tracing::warn!(db_path = %drift_path, drift = %item.detail, "Schema drift");
+            }
+        }
+        Err(err) => tracing::warn!(db_path = %drift_path, error = %err, "Schema check failed"),
+    });
+    let event_log_path = ui_event_log_path(Some(&config));
+    if let Some(path) = event_log_path.as_deref() {
+        match audit_store.import_legacy_log(path).await {
+            Ok(0) => {}
+            Ok(imported) => tracing::info!(imported, "Imported UI event log into audit store"),
+            Err(err) => tracing::warn!(error = %err, "Failed to import legacy UI event log"),
+        }
+    }
+    let (audit_flush_tx, mut audit_flush_rx) = tokio::sync::watch::channel(false);
+    let audit_recorder = {
+        let mut rx = ui_event_tx.subscribe();
+        let path = event_log_path.clone();
+        tokio::spawn(async move {
+            let record = |event: UiEvent| record_ui_event(path.as_deref(), &audit_store, event);
+            loop {
+                tokio::select! {
+                    received = rx.recv() => match received {
+                        Ok(event) => record(event).await,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = audit_flush_rx.changed() => {
+                        // Shutting down: record what is already queued.
+                        loop {
+                            match rx.try_recv() {
+                                Ok(event) => record(event).await,
+                                Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                                Err(_) => break,
+                            }
+                        }
+                        break;
+                    }
+                }
+            }
+        })
+    };
+    {
         let mut rx = ui_event_tx.subscribe();
-        let path = path.clone();
+        let outbox_store = OutboxStore::new(db_path).await?;
+        let db_path = db_path.to_string();
         tokio::spawn(async move {
             loop {
                 match rx.recv().await {
                     Ok(event) => {
-                        let _ = write_ui_event_log(&path, &event);
+                        if let Err(err) =
+                            enqueue_outbox_event(&db_path, &outbox_store, &event).await
+                        {
+                            tracing::warn!(error = %err, "Failed to queue outbox event");
+                        }
                     }
                     Err(broadcast::error::RecvError::Lagged(_)) => {
                         continue;
@@ -4246,14 +11790,34 @@ where
     let agent = Arc::new(RwLock::new(Arc::new(
         ButterflyBot::from_store_with_events(db_path, Some(ui_event_tx.clone())).await?,
     )));
-    let reminder_db_path = Some(&config)
+    let config_value = serde_json::to_value(&config).unwrap_or(Value::Null);
+    let reminder_db_path =
+        resolve_reminder_db_path(&config_value).unwrap_or_else(|| db_path.to_string());
+    let clock = crate::clock::system_clock();
+    let reminder_store = Arc::new(
+        ReminderStore::new(reminder_db_path)
+            .await?
+            .with_clock(clock.clone())
+            .with_complete_on_fire(reminders_complete_on_fire(&config_value)),
+    );
+    let todo_db_path = Some(&config)
         .and_then(|cfg| serde_json::to_value(cfg).ok())
-        .and_then(|value| resolve_reminder_db_path(&value))
+        .and_then(|value| resolve_todo_db_path(&value))
         .unwrap_or_else(|| db_path.to_string());
-    let reminder_store = Arc::new(ReminderStore::new(reminder_db_path).await?);
-    let task_store = Arc::new(TaskStore::new(db_path).await?);
-    let wakeup_store = Arc::new(WakeupStore::new(db_path).await?);
-    let mut scheduler = Scheduler::new();
+    let todo_store = Arc::new(
+        TodoStore::new(todo_db_path)
+            .await?
+            .with_clock(clock.clone()),
+    );
+    let task_store = Arc::new(TaskStore::new(db_path).await?.with_clock(clock.clone()));
+    let wakeup_store = Arc::new(WakeupStore::new(db_path).await?.with_clock(clock.clone()));
+    let catch_up = CatchUpConfig::from_tools(config.tools.as_ref());
+    let shutdown_config = ShutdownConfig::from_tools(config.tools.as_ref());
+    let mut scheduler = Scheduler::new()
+        .with_state(Arc::new(
+            JobStateStore::new(db_path).await?.with_clock(clock.clone()),
+        ))
+        .with_catch_up(catch_up.clone());
     scheduler.register_job(Arc::new(BrainTickJob {
         agent: agent.clone(),
         interval: Duration::from_secs(tick_seconds.max(1)),
@@ -4280,9 +11844,12 @@ where
         })
         .unwrap_or(60);
     set_autonomy_cooldown_seconds(autonomy_cooldown_seconds);
+    crate::prompt_queue::shared().configure(PromptQueueConfig::from_tools(config.tools.as_ref()));
     scheduler.register_job(Arc::new(WakeupJob {
         agent: agent.clone(),
         store: wakeup_store.clone(),
+        running: RunningWakeups::new(),
+        catch_up: catch_up.clone(),
         interval: Duration::from_secs(wakeup_poll_seconds.max(1)),
         ui_event_tx: ui_event_tx.clone(),
         audit_log_path: wakeup_audit_log_path(Some(&config)),
@@ -4300,6 +11867,7 @@ where
     scheduler.register_job(Arc::new(ScheduledTasksJob {
         agent: agent.clone(),
         store: task_store.clone(),
+        catch_up,
         interval: Duration::from_secs(tasks_poll_seconds.max(1)),
         ui_event_tx: ui_event_tx.clone(),
         audit_log_path: tasks_audit_log_path(Some(&config)),
@@ -4315,9 +11883,115 @@ where
         interval: Duration::from_secs(reminders_poll_seconds.max(1)),
         ui_event_tx: ui_event_tx.clone(),
         audit_log_path: reminders_audit_log_path(Some(&config)),
+        delivery_windows: DeliveryWindows::from_tools_config(config.tools.as_ref()),
+        escalation: EscalationPolicy::from_tools_config(config.tools.as_ref()),
+        agent: agent.clone(),
+        delivery: DeliveryConfig::from_tools_config(config.tools.as_ref()),
+    }));
+    let escalation = EscalationPolicy::from_tools_config(config.tools.as_ref());
+    if !escalation.is_empty() {
+        scheduler.register_job(Arc::new(ReminderEscalationJob {
+            agent: agent.clone(),
+            store: reminder_store.clone(),
+            db_path: db_path.to_string(),
+            interval: Duration::from_secs(reminders_poll_seconds.max(1)),
+            ui_event_tx: ui_event_tx.clone(),
+            audit_log_path: reminders_audit_log_path(Some(&config)),
+            escalation,
+        }));
+    }
+    let todo_poll_seconds = Some(&config)
+        .and_then(|cfg| cfg.tools.as_ref())
+        .and_then(|tools| tools.get("todo"))
+        .and_then(|todo| todo.get("poll_seconds"))
+        .and_then(|value| value.as_u64())
+        .unwrap_or(60);
+    scheduler.register_job(Arc::new(ChecklistResetJob {
+        store: todo_store.clone(),
+        interval: Duration::from_secs(todo_poll_seconds.max(1)),
+        ui_event_tx: ui_event_tx.clone(),
+    }));
+    scheduler.register_job(Arc::new(DailyDigestJob {
+        agent: agent.clone(),
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+        clock: clock.clone(),
+        last_sent: std::sync::Mutex::new(HashMap::new()),
+    }));
+    scheduler.register_job(Arc::new(InboxSweepJob {
+        agent: agent.clone(),
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+        clock: clock.clone(),
+        last_run: std::sync::Mutex::new(HashMap::new()),
+    }));
+    scheduler.register_job(Arc::new(DependencyUnblockJob {
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+    }));
+    scheduler.register_job(Arc::new(OutboxDeliveryJob {
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+        client: reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
+    }));
+    scheduler.register_job(Arc::new(CalendarSyncJob {
+        db_path: db_path.to_string(),
+        interval: Duration::from_secs(
+            CalendarConfig::from_tools(config.tools.as_ref()).poll_minutes * 60,
+        ),
+        ui_event_tx: ui_event_tx.clone(),
+    }));
+    scheduler.register_job(Arc::new(EmailIngestJob {
+        db_path: db_path.to_string(),
+        store: todo_store,
+        interval: Duration::from_secs(
+            EmailConfig::from_tools(config.tools.as_ref()).poll_minutes * 60,
+        ),
+        ui_event_tx: ui_event_tx.clone(),
+    }));
+    scheduler.register_job(Arc::new(ApprovalChainJob {
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+        clock: clock.clone(),
+    }));
+    if let Some(matrix) = MatrixConfig::from_tools(config.tools.as_ref()) {
+        scheduler.register_job(Arc::new(MatrixSyncJob {
+            agent: agent.clone(),
+            db_path: db_path.to_string(),
+            interval: Duration::from_secs(matrix.poll_seconds),
+            since: tokio::sync::Mutex::new(None),
+            encrypted: std::sync::Mutex::new(HashMap::new()),
+            ui_event_tx: ui_event_tx.clone(),
+        }));
+    }
+    scheduler.register_job(Arc::new(BackupJob {
+        db_path: db_path.to_string(),
+        ui_event_tx: ui_event_tx.clone(),
+        clock: clock.clone(),
+    }));
+    scheduler.register_job(Arc::new(ReplicationJob {
+        db_path: db_path.to_string(),
+        interval: Duration::from_secs(
+            ReplicationConfig::from_tools(config.tools.as_ref()).interval_seconds,
+        ),
+        ui_event_tx: ui_event_tx.clone(),
+        clock: clock.clone(),
+        replicator: tokio::sync::Mutex::new(None),
+    }));
+    scheduler.register_job(Arc::new(TrashPurgeJob {
+        db_path: db_path.to_string(),
+        config: TrashConfig::from_tools(config.tools.as_ref()),
+        clock,
+    }));
+    scheduler.register_job(Arc::new(AttachmentSweepJob {
+        db_path: db_path.to_string(),
     }));
     scheduler.start();
 
+    let db = DbHandle::open(db_path).await?;
     let state = AppState {
         agent,
         reminder_store,
@@ -4325,6 +11999,9 @@ where
         token: token.to_string(),
         ui_event_tx,
         db_path: db_path.to_string(),
+        session_store: Arc::new(SessionStore::from_db(db.clone()).await?),
+        role_store: Arc::new(RoleStore::from_db(db).await?),
+        role_policy: Arc::new(RwLock::new(role_policy_from(&config)?)),
     };
     let app = build_router(state);
 
@@ -4333,16 +12010,67 @@ where
         .await
         .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
     tracing::info!(address = %addr, "Daemon listener bound");
-    let shutdown = async move {
-        shutdown.await;
-        scheduler.stop().await;
-    };
+    let in_flight = crate::shutdown::shared();
+    in_flight.resume();
+    let (close_tx, close_rx) = tokio::sync::oneshot::channel::<()>();
+    let mut server = tokio::spawn(async move {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(async move {
+                let _ = close_rx.await;
+            })
+            .await
+    });
+
+    tokio::select! {
+        served = &mut server => {
+            served
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            return Ok(());
+        }
+        _ = shutdown => {}
+        _ = shutdown_requested().notified() => {}
+        _ = crate::shutdown::signal() => {}
+    }
+
+    // The listener stays open while draining so `/health` answers until the
+    // process exits; everything else is refused by `shutdown_gate`.
+    let drain = shutdown_config.drain;
+    let deadline = tokio::time::Instant::now() + drain;
+    tracing::info!(
+        drain_seconds = drain.as_secs(),
+        "Shutting down; draining in-flight work"
+    );
+    in_flight.stop();
+    if tokio::time::timeout_at(deadline, scheduler.stop())
+        .await
+        .is_err()
+    {
+        tracing::warn!("Scheduler jobs still running at the shutdown deadline");
+    }
+    let abandoned = in_flight.drain(deadline).await;
+    if !abandoned.is_empty() {
+        tracing::warn!(work = ?abandoned, "Abandoning work still running at the shutdown deadline");
+    }
 
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown)
+    let _ = close_tx.send(());
+    // Event streams stay open until the client leaves; don't wait on them.
+    let close_deadline = deadline.max(tokio::time::Instant::now() + SHUTDOWN_CLOSE_GRACE);
+    if tokio::time::timeout_at(close_deadline, &mut server)
         .await
-        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        .is_err()
+    {
+        server.abort();
+    }
 
+    let _ = audit_flush_tx.send(true);
+    if tokio::time::timeout(SHUTDOWN_CLOSE_GRACE, audit_recorder)
+        .await
+        .is_err()
+    {
+        tracing::warn!("Audit events still queued at exit were not recorded");
+    }
+    tracing::info!("Daemon stopped");
     Ok(())
 }
 
@@ -4369,6 +12097,28 @@ async fn run_autonomy_tick(
         return;
     }
 
+    let _permit = match crate::prompt_queue::shared()
+        .acquire("autonomy", PromptPriority::Background)
+        .await
+    {
+        Ok(permit) => permit,
+        Err(err) => {
+            let _ = ui_event_tx.send(UiEvent {
+                event_type: "autonomy".to_string(),
+                user_id,
+                tool: "heartbeat".to_string(),
+                status: "skipped".to_string(),
+                payload: json!({
+                    "source": source,
+                    "reason": "queue",
+                    "error": err.to_string(),
+                }),
+                timestamp: now_ts(),
+            });
+            return;
+        }
+    };
+
     let _ = ui_event_tx.send(UiEvent {
         event_type: "autonomy".to_string(),
         user_id: user_id.clone(),
@@ -4629,7 +12379,11 @@ fn ui_event_log_path(config: Option<&Config>) -> Option<String> {
 
 fn write_ui_event_log(path: &str, event: &UiEvent) -> Result<()> {
     config_store::ensure_parent_dir(path)?;
-    let payload = serde_json::to_string(event)
+    let event = UiEvent {
+        payload: redact_json(&event.payload),
+        ..event.clone()
+    };
+    let payload = serde_json::to_string(&event)
         .map_err(|e| ButterflyBotError::Serialization(e.to_string()))?;
     let mut file = std::fs::OpenOptions::new()
         .create(true)
@@ -4663,6 +12417,15 @@ fn reminders_audit_log_path(config: Option<&Config>) -> Option<String> {
         .or_else(|| Some("./data/reminders_audit.log".to_string()))
 }
 
+async fn record_ui_event(path: Option<&str>, audit_store: &AuditStore, event: UiEvent) {
+    if let Some(path) = path {
+        let _ = write_ui_event_log(path, &event);
+    }
+    if let Err(err) = audit_store.record(&event).await {
+        tracing::warn!(error = %err, "Failed to record audit event");
+    }
+}
+
 fn write_reminder_audit_log(
     path: Option<&str>,
     ts: i64,
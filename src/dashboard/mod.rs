@@ -0,0 +1,243 @@
+//! Read-only status page for checking in from a phone browser.
+//!
+//! With `settings.dashboard.enabled` the daemon serves a static page at
+//! `/dashboard` and the data it renders at `/dashboard/summary`. The page
+//! carries no data itself: it asks once for the user id and daemon token,
+//! keeps them in the browser's local storage and polls the summary with the
+//! usual bearer header, so the dashboard is exactly as private as the API.
+//! Nothing on it can change state. A phone only reaches it when the daemon
+//! listens beyond loopback (`--host`).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::audit::AuditEvent;
+use crate::reminders::ReminderItem;
+
+/// The whole dashboard; rendering happens in the browser.
+pub const PAGE: &str = include_str!("page.html");
+
+/// Actionable inbox items, upcoming reminders and audit events listed.
+pub const ITEM_LIMIT: usize = 10;
+pub const REMINDER_LIMIT: usize = 10;
+pub const EVENT_LIMIT: usize = 15;
+
+const DEFAULT_REFRESH_SECONDS: u64 = 30;
+const MIN_REFRESH_SECONDS: u64 = 10;
+const MAX_REFRESH_SECONDS: u64 = 3600;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DashboardConfig {
+    pub enabled: bool,
+    /// How often the open page polls the summary.
+    pub refresh_seconds: u64,
+}
+
+impl Default for DashboardConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_seconds: DEFAULT_REFRESH_SECONDS,
+        }
+    }
+}
+
+impl DashboardConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("dashboard"))
+        else {
+            return Self::default();
+        };
+        Self {
+            enabled: section
+                .get("enabled")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            refresh_seconds: section
+                .get("refresh_seconds")
+                .and_then(Value::as_u64)
+                .unwrap_or(DEFAULT_REFRESH_SECONDS)
+                .clamp(MIN_REFRESH_SECONDS, MAX_REFRESH_SECONDS),
+        }
+    }
+}
+
+/// An open inbox item as the dashboard shows it.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct DashboardItem {
+    pub title: String,
+    pub source_type: String,
+    pub status: String,
+    pub priority: String,
+    pub due_at: Option<i64>,
+    /// Waiting on the human rather than the agent.
+    pub actionable: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct InboxOverview {
+    pub open: usize,
+    pub actionable: usize,
+    pub overdue: usize,
+    pub by_status: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct DashboardReminder {
+    pub id: i32,
+    pub title: String,
+    pub due_at: i64,
+}
+
+impl From<ReminderItem> for DashboardReminder {
+    fn from(reminder: ReminderItem) -> Self {
+        Self {
+            id: reminder.id,
+            title: reminder.title,
+            due_at: reminder.due_at,
+        }
+    }
+}
+
+/// An audit event without its payload, which may hold tool arguments.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct DashboardEvent {
+    pub timestamp: i64,
+    pub event_type: String,
+    pub tool: String,
+    pub status: String,
+    pub severity: &'static str,
+}
+
+impl From<AuditEvent> for DashboardEvent {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            timestamp: event.timestamp,
+            event_type: event.event_type,
+            tool: event.tool,
+            status: event.status,
+            severity: event.severity.as_str(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct DashboardSummary {
+    pub generated_at: i64,
+    pub refresh_seconds: u64,
+    pub inbox: InboxOverview,
+    /// Actionable items, earliest due first.
+    pub actionable_items: Vec<DashboardItem>,
+    pub upcoming_reminders: Vec<DashboardReminder>,
+    /// Newest first.
+    pub recent_events: Vec<DashboardEvent>,
+}
+
+impl DashboardSummary {
+    /// `items` are the open inbox items; `events` come oldest first, as
+    /// the audit store pages them.
+    pub fn build(
+        now: i64,
+        config: &DashboardConfig,
+        items: Vec<DashboardItem>,
+        reminders: Vec<ReminderItem>,
+        events: Vec<AuditEvent>,
+    ) -> Self {
+        let mut inbox = InboxOverview {
+            open: items.len(),
+            ..InboxOverview::default()
+        };
+        for item in &items {
+            *inbox.by_status.entry(item.status.clone()).or_default() += 1;
+            if item.due_at.is_some_and(|due_at| due_at < now) {
+                inbox.overdue += 1;
+            }
+        }
+        let mut actionable_items = items
+            .into_iter()
+            .filter(|item| item.actionable)
+            .collect::<Vec<_>>();
+        inbox.actionable = actionable_items.len();
+        actionable_items.sort_by_key(|item| (item.due_at.is_none(), item.due_at));
+        actionable_items.truncate(ITEM_LIMIT);
+
+        let mut upcoming_reminders = reminders
+            .into_iter()
+            .map(DashboardReminder::from)
+            .collect::<Vec<_>>();
+        upcoming_reminders.sort_by_key(|reminder| reminder.due_at);
+        upcoming_reminders.truncate(REMINDER_LIMIT);
+
+        Self {
+            generated_at: now,
+            refresh_seconds: config.refresh_seconds,
+            inbox,
+            actionable_items,
+            upcoming_reminders,
+            recent_events: events
+                .into_iter()
+                .rev()
+                .take(EVENT_LIMIT)
+                .map(DashboardEvent::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DashboardConfig, DashboardItem, DashboardSummary, PAGE};
+    use serde_json::json;
+
+    fn item(title: &str, status: &str, due_at: Option<i64>, actionable: bool) -> DashboardItem {
+        DashboardItem {
+            title: title.to_string(),
+            source_type: "todo".to_string(),
+            status: status.to_string(),
+            priority: "normal".to_string(),
+            due_at,
+            actionable,
+        }
+    }
+
+    #[test]
+    fn config_is_off_unless_enabled() {
+        assert!(!DashboardConfig::from_tools(None).enabled);
+        let config = DashboardConfig::from_tools(Some(&json!({
+            "settings": {"dashboard": {"enabled": true, "refresh_seconds": 1}}
+        })));
+        assert!(config.enabled);
+        assert_eq!(config.refresh_seconds, 10);
+    }
+
+    #[test]
+    fn summary_counts_inbox_and_orders_actionable_items() {
+        let items = vec![
+            item("later", "new", None, true),
+            item("overdue", "blocked", Some(50), true),
+            item("agent work", "in_progress", Some(500), false),
+        ];
+        let summary =
+            DashboardSummary::build(100, &DashboardConfig::default(), items, vec![], vec![]);
+        assert_eq!(summary.inbox.open, 3);
+        assert_eq!(summary.inbox.actionable, 2);
+        assert_eq!(summary.inbox.overdue, 1);
+        assert_eq!(summary.inbox.by_status.get("blocked"), Some(&1));
+        let titles = summary
+            .actionable_items
+            .iter()
+            .map(|item| item.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, vec!["overdue", "later"]);
+    }
+
+    #[test]
+    fn page_only_reads_the_summary() {
+        assert!(PAGE.contains("/dashboard/summary"));
+        assert!(!PAGE.contains("method: \"POST\""));
+    }
+}
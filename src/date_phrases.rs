@@ -0,0 +1,390 @@
+//! Natural-language due dates beyond English.
+//!
+//! `chrono-english` does the actual date arithmetic. Phrases it can't read are
+//! scanned for French, German and Spanish date and time expressions, which are
+//! rewritten into the English forms it understands ("demain à 9h" becomes
+//! "tomorrow 9:00", "am Freitag" becomes "friday"). Words that aren't part of
+//! a date are dropped, so a whole captured sentence can be passed in.
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, TimeZone};
+use chrono_english::{parse_date_string, Dialect};
+use regex::{Captures, Regex};
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Weekday names per language, Monday first, without accents where a
+/// keyboard often drops them.
+const FOREIGN_WEEKDAYS: [[&str; 7]; 3] = [
+    [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    [
+        "montag",
+        "dienstag",
+        "mittwoch",
+        "donnerstag",
+        "freitag",
+        "samstag",
+        "sonntag",
+    ],
+    [
+        "lunes",
+        "martes",
+        "miercoles",
+        "jueves",
+        "viernes",
+        "sabado",
+        "domingo",
+    ],
+];
+
+const FOREIGN_MONTHS: [[&str; 12]; 3] = [
+    [
+        "janvier",
+        "fevrier",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "aout",
+        "septembre",
+        "octobre",
+        "novembre",
+        "decembre",
+    ],
+    [
+        "januar",
+        "februar",
+        "marz",
+        "april",
+        "mai",
+        "juni",
+        "juli",
+        "august",
+        "september",
+        "oktober",
+        "november",
+        "dezember",
+    ],
+    [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+];
+
+type Rewrite = fn(&Captures) -> Option<String>;
+
+struct Rule {
+    re: Regex,
+    rewrite: Rewrite,
+}
+
+/// Parses a due-date phrase relative to `anchor`, trying English first.
+/// Relative phrases ("tomorrow", "friday 9am") resolve in the anchor's zone.
+pub fn parse_phrase<Z>(input: &str, anchor: DateTime<Z>) -> Option<DateTime<Z>>
+where
+    Z: TimeZone,
+    Z::Offset: Copy,
+{
+    if let Ok(parsed) = parse_english(input, anchor) {
+        return Some(parsed);
+    }
+    let translated = translate_phrase(input)?;
+    parse_english(&translated, anchor).ok()
+}
+
+fn parse_english<Z>(
+    input: &str,
+    anchor: DateTime<Z>,
+) -> std::result::Result<DateTime<Z>, chrono_english::DateError>
+where
+    Z: TimeZone,
+    Z::Offset: Copy,
+{
+    parse_date_string(input, anchor, Dialect::Us)
+        .or_else(|_| parse_date_string(input, anchor, Dialect::Uk))
+}
+
+/// Whether the phrase names a time of day in any supported language, so the
+/// caller knows not to snap the due date to midnight.
+pub fn has_explicit_time(input: &str) -> bool {
+    static ENGLISH_TIME_RE: OnceLock<Regex> = OnceLock::new();
+    let english = ENGLISH_TIME_RE.get_or_init(|| {
+        Regex::new(r"(?i)(\b\d{1,2}:\d{2}\b|\b\d{1,2}\s*(am|pm)\b|\bnoon\b|\bmidnight\b)").unwrap()
+    });
+    if english.is_match(input) {
+        return true;
+    }
+    let folded = fold(input);
+    time_rules().iter().any(|rule| rule.re.is_match(&folded))
+}
+
+/// Rewrites the French, German and Spanish date expressions in `input` into
+/// an English phrase. Returns `None` when nothing was recognised.
+pub fn translate_phrase(input: &str) -> Option<String> {
+    let folded = fold(input);
+    let mut matches = date_rules()
+        .iter()
+        .chain(time_rules().iter())
+        .flat_map(|rule| {
+            rule.re.captures_iter(&folded).filter_map(|caps| {
+                let whole = caps.get(0)?;
+                Some((whole.start(), whole.end(), (rule.rewrite)(&caps)?))
+            })
+        })
+        .collect::<Vec<_>>();
+    // Earliest match first; of two starting together the longer one wins.
+    matches.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)));
+
+    let mut parts = Vec::new();
+    let mut cursor = 0;
+    for (start, end, text) in matches {
+        if start < cursor {
+            continue;
+        }
+        parts.push(text);
+        cursor = end;
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Lowercases and strips the accents that vary between keyboards, so the
+/// rules only need one spelling of each word.
+fn fold(input: &str) -> String {
+    input
+        .to_lowercase()
+        .chars()
+        .map(|ch| match ch {
+            'à' | 'â' | 'á' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'í' | 'î' | 'ï' => 'i',
+            'ó' | 'ô' | 'ö' => 'o',
+            'ú' | 'û' | 'ü' => 'u',
+            'ñ' => 'n',
+            'ç' => 'c',
+            '’' => '\'',
+            other => other,
+        })
+        .collect()
+}
+
+fn hh_mm(hour: &str, minute: Option<&str>) -> Option<String> {
+    let hour = hour.parse::<u32>().ok()?;
+    let minute = minute.map_or(Ok(0), str::parse::<u32>).ok()?;
+    (hour < 24 && minute < 60).then(|| format!("{hour}:{minute:02}"))
+}
+
+fn weekday_index(name: &str) -> Option<usize> {
+    FOREIGN_WEEKDAYS
+        .iter()
+        .find_map(|names| names.iter().position(|day| *day == name))
+}
+
+fn month_index(name: &str) -> Option<usize> {
+    FOREIGN_MONTHS
+        .iter()
+        .find_map(|names| names.iter().position(|month| *month == name))
+}
+
+fn unit(word: &str) -> Option<&'static str> {
+    let unit = match word {
+        w if w.starts_with("jour") || w.starts_with("tag") || w.starts_with("dia") => "days",
+        w if w.starts_with("semaine") || w.starts_with("woche") || w.starts_with("semana") => {
+            "weeks"
+        }
+        w if w.starts_with("heure") || w.starts_with("stunde") || w.starts_with("hora") => "hours",
+        w if w.starts_with("minut") => "minutes",
+        "mois" | "monat" | "monate" | "monaten" | "mes" | "meses" => "months",
+        _ => return None,
+    };
+    Some(unit)
+}
+
+fn alternation(names: impl IntoIterator<Item = &'static str>) -> String {
+    names.into_iter().collect::<Vec<_>>().join("|")
+}
+
+fn date_rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let weekdays = alternation(FOREIGN_WEEKDAYS.iter().flatten().copied());
+        let months = alternation(FOREIGN_MONTHS.iter().flatten().copied());
+        let rule = |pattern: String, rewrite: Rewrite| Rule {
+            re: Regex::new(&pattern).unwrap(),
+            rewrite,
+        };
+        vec![
+            rule(
+                r"\b(?:apres-demain|apres demain|ubermorgen|uebermorgen|pasado manana)\b".into(),
+                |_| Some("in 2 days".to_string()),
+            ),
+            rule(
+                r"\b(?:aujourd'hui|aujourd hui|heute|hoy)\b".into(),
+                |_| Some("today".to_string()),
+            ),
+            rule(r"\b(?:demain|morgen|manana)\b".into(), |_| {
+                Some("tomorrow".to_string())
+            }),
+            rule(
+                r"\b(?:dans|in|en)\s+(\d{1,3})\s+([a-z]+)\b".into(),
+                |caps| Some(format!("in {} {}", &caps[1], unit(&caps[2])?)),
+            ),
+            // "le vendredi", "vendredi prochain", "am Freitag", "nächsten
+            // Freitag", "el próximo viernes", "el viernes que viene".
+            rule(
+                format!(
+                    r"\b(?:(?:le|am|el)\s+)?(?:(naechsten|nachsten|nachste|kommenden|proximo|proxima)\s+)?({weekdays})(?:\s+(prochain|prochaine|que viene))?\b"
+                ),
+                |caps| {
+                    let day = WEEKDAYS[weekday_index(&caps[2])?];
+                    if caps.get(1).is_some() || caps.get(3).is_some() {
+                        Some(format!("next {day}"))
+                    } else {
+                        Some(day.to_string())
+                    }
+                },
+            ),
+            // "le 3 mars", "am 3. März", "el 3 de marzo".
+            rule(
+                format!(r"\b(\d{{1,2}})(?:\.|er)?\s+(?:de\s+)?({months})\b(?:\s+(?:de\s+)?(\d{{4}}))?"),
+                |caps| {
+                    let day = caps[1].parse::<u32>().ok().filter(|day| (1..=31).contains(day))?;
+                    let month = MONTHS[month_index(&caps[2])?];
+                    Some(match caps.get(3) {
+                        Some(year) => format!("{day} {month} {}", year.as_str()),
+                        None => format!("{day} {month}"),
+                    })
+                },
+            ),
+        ]
+    })
+}
+
+fn time_rules() -> &'static [Rule] {
+    static RULES: OnceLock<Vec<Rule>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        let rule = |pattern: &str, rewrite: Rewrite| Rule {
+            re: Regex::new(pattern).unwrap(),
+            rewrite,
+        };
+        vec![
+            // "à 9h", "9h30", "14 h".
+            rule(r"\b(?:a\s+)?(\d{1,2})\s*h\s*(\d{2})?\b", |caps| {
+                hh_mm(&caps[1], caps.get(2).map(|m| m.as_str()))
+            }),
+            // "um 9 Uhr", "9:30 Uhr", "9 Uhr 30".
+            rule(
+                r"\b(?:um\s+)?(\d{1,2})(?::(\d{2}))?\s*uhr(?:\s+(\d{2}))?\b",
+                |caps| {
+                    let minute = caps.get(2).or_else(|| caps.get(3)).map(|m| m.as_str());
+                    hh_mm(&caps[1], minute)
+                },
+            ),
+            // "a las 9", "a la 1", "a las 9:30".
+            rule(r"\ba\s+las?\s+(\d{1,2})(?::(\d{2}))?\b", |caps| {
+                hh_mm(&caps[1], caps.get(2).map(|m| m.as_str()))
+            }),
+            // "um 9:30", "à 9:30" where the time is already numeric.
+            rule(r"\b(?:um|a)\s+(\d{1,2}):(\d{2})\b", |caps| {
+                hh_mm(&caps[1], Some(&caps[2]))
+            }),
+            rule(
+                r"\b(?:a\s+)?midi\b|\b(?:am\s+|um\s+)?mittag\b|\b(?:al\s+)?mediodia\b",
+                |_| Some("12:00".to_string()),
+            ),
+            rule(
+                r"\b(?:a\s+)?minuit\b|\b(?:um\s+)?mitternacht\b|\b(?:a\s+la\s+)?medianoche\b",
+                |_| Some("0:00".to_string()),
+            ),
+        ]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, Local, Timelike};
+
+    #[test]
+    fn translates_french_german_and_spanish_phrases() {
+        assert_eq!(
+            translate_phrase("Appeler Marie demain à 9h").as_deref(),
+            Some("tomorrow 9:00")
+        );
+        assert_eq!(translate_phrase("am Freitag").as_deref(), Some("friday"));
+        assert_eq!(
+            translate_phrase("nächsten Montag um 14:30 Uhr").as_deref(),
+            Some("next monday 14:30")
+        );
+        assert_eq!(
+            translate_phrase("el próximo viernes a las 10").as_deref(),
+            Some("next friday 10:00")
+        );
+        assert_eq!(
+            translate_phrase("le 3 mars à 18h15").as_deref(),
+            Some("3 March 18:15")
+        );
+        assert_eq!(
+            translate_phrase("dans 3 jours").as_deref(),
+            Some("in 3 days")
+        );
+        assert_eq!(translate_phrase("buy milk"), None);
+    }
+
+    #[test]
+    fn detects_times_in_any_supported_language() {
+        assert!(has_explicit_time("tomorrow at 9am"));
+        assert!(has_explicit_time("demain à 9h"));
+        assert!(has_explicit_time("morgen um 9 Uhr"));
+        assert!(has_explicit_time("mañana a las 9"));
+        assert!(!has_explicit_time("am Freitag"));
+    }
+
+    #[test]
+    fn parses_translated_phrase_against_anchor() {
+        let anchor = Local.with_ymd_and_hms(2026, 3, 4, 10, 0, 0).unwrap();
+        let parsed = parse_phrase("demain à 9h", anchor).expect("parsed");
+        assert_eq!(parsed.day(), 5);
+        assert_eq!((parsed.hour(), parsed.minute()), (9, 0));
+    }
+}
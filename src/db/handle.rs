@@ -0,0 +1,228 @@
+//! One connection pool per database file, shared by every store that opens
+//! it.
+//!
+//! Each connection gets the SQLCipher key and the other pragmas once, when
+//! it is opened, instead of on every checkout. Stores take a [`DbHandle`]
+//! in `from_db`; their `new(path)` constructors open the shared handle for
+//! the path, so a daemon with a dozen stores on one file still holds a
+//! single pool.
+//!
+//! The file runs in WAL mode, so readers never wait on a writer. Writers
+//! still take SQLite's single write lock, and two of them starting a
+//! transaction at once can fail with `SQLITE_BUSY` without waiting out
+//! `busy_timeout`; store mutations therefore go through
+//! [`DbHandle::write_conn`], which lets one writer per file in at a time.
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use diesel::sqlite::SqliteConnection;
+use diesel::{ConnectionError, ConnectionResult};
+use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
+use diesel_async::pooled_connection::{AsyncDieselConnectionManager, ManagerConfig};
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::{AsyncConnection, SimpleAsyncConnection};
+use futures::FutureExt;
+
+use crate::error::{ButterflyBotError, Result};
+
+pub type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
+pub type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
+type SqlitePool = Pool<SqliteAsyncConn>;
+
+/// Connections per database file, across all of its stores.
+pub const POOL_SIZE: u32 = 16;
+
+struct Shared {
+    path: String,
+    pool: SqlitePool,
+    writes: tokio::sync::Mutex<()>,
+}
+
+/// A database file and its connection pool. Cheap to clone.
+#[derive(Clone)]
+pub struct DbHandle {
+    shared: Arc<Shared>,
+}
+
+impl DbHandle {
+    /// The handle for `path`, opening its pool on first use.
+    pub async fn open(path: impl AsRef<str>) -> Result<Self> {
+        let path = path.as_ref();
+        if let Some(handle) = lock_handles().get(path) {
+            return Ok(handle.clone());
+        }
+        let opened = Self::connect(path).await?;
+        // Two stores racing to open the same file keep the first pool.
+        Ok(lock_handles()
+            .entry(path.to_string())
+            .or_insert(opened)
+            .clone())
+    }
+
+    async fn connect(path: &str) -> Result<Self> {
+        ensure_parent_dir(path)?;
+        let mut config = ManagerConfig::default();
+        config.custom_setup = Box::new(|url| establish(url.to_string()).boxed());
+        let manager =
+            AsyncDieselConnectionManager::<SqliteAsyncConn>::new_with_config(path, config);
+        let pool = Pool::builder()
+            .max_size(POOL_SIZE)
+            .build(manager)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Self {
+            shared: Arc::new(Shared {
+                path: path.to_string(),
+                pool,
+                writes: tokio::sync::Mutex::new(()),
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.shared.path
+    }
+
+    pub async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.shared
+            .pool
+            .get()
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// A connection for a mutation. Waits for this file's other writers
+    /// first; the connection is taken only once it is this caller's turn,
+    /// so queued writers do not hold connections readers need.
+    pub async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        let gate = self.shared.writes.lock().await;
+        let conn = self.conn().await?;
+        Ok(WriteConn { conn, _gate: gate })
+    }
+
+    /// This file's write turn without a connection, for writes that have
+    /// to go through another driver.
+    pub async fn write_turn(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.shared.writes.lock().await
+    }
+
+    /// Whether both handles hand out connections from the same pool.
+    pub fn shares_pool_with(&self, other: &DbHandle) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+/// A pooled connection holding its file's write turn until dropped.
+pub struct WriteConn<'a> {
+    conn: SqlitePooledConn<'a>,
+    _gate: tokio::sync::MutexGuard<'a, ()>,
+}
+
+impl Deref for WriteConn<'_> {
+    type Target = SqliteAsyncConn;
+
+    fn deref(&self) -> &Self::Target {
+        &self.conn
+    }
+}
+
+impl DerefMut for WriteConn<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.conn
+    }
+}
+
+fn lock_handles() -> std::sync::MutexGuard<'static, HashMap<String, DbHandle>> {
+    static HANDLES: OnceLock<Mutex<HashMap<String, DbHandle>>> = OnceLock::new();
+    HANDLES
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+async fn establish(url: String) -> ConnectionResult<SqliteAsyncConn> {
+    let mut conn = SqliteAsyncConn::establish(&url).await?;
+    super::apply_sqlcipher_key_async(&mut conn)
+        .await
+        .map_err(|e| ConnectionError::BadConnection(e.to_string()))?;
+    // Must follow the key: SQLCipher cannot read the header before it. The
+    // mode is stored in the file, so a connection that loses the race to
+    // switch it still ends up in WAL once another one succeeds.
+    if let Err(err) = conn.batch_execute("PRAGMA journal_mode = WAL;").await {
+        tracing::warn!(db_path = %url, error = %err, "Could not enable WAL mode");
+    }
+    Ok(conn)
+}
+
+fn ensure_parent_dir(path: &str) -> Result<()> {
+    let path = Path::new(path);
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use diesel::sql_types::Text;
+    use diesel::QueryableByName;
+    use diesel_async::RunQueryDsl;
+
+    use super::DbHandle;
+
+    #[derive(QueryableByName)]
+    struct JournalMode {
+        #[diesel(sql_type = Text)]
+        journal_mode: String,
+    }
+
+    #[tokio::test]
+    async fn stores_on_one_file_share_a_pool_and_take_turns_writing() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("nested").join("bot.db");
+        let path = path.to_string_lossy();
+        let other = temp.path().join("other.db");
+
+        let first = DbHandle::open(&path).await.unwrap();
+        let second = DbHandle::open(&path).await.unwrap();
+        let elsewhere = DbHandle::open(other.to_string_lossy()).await.unwrap();
+        assert!(first.shares_pool_with(&second));
+        assert!(!first.shares_pool_with(&elsewhere));
+        assert_eq!(second.path(), path);
+
+        let mut conn = first.conn().await.unwrap();
+        diesel::sql_query("CREATE TABLE shared_pool_probe (id INTEGER)")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        let mode: JournalMode = diesel::sql_query("PRAGMA journal_mode")
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(mode.journal_mode, "wal");
+        drop(conn);
+
+        // One writer per file at a time; readers are not held up.
+        let mut writer = first.write_conn().await.unwrap();
+        let queued = tokio::time::timeout(Duration::from_millis(50), second.write_conn()).await;
+        assert!(
+            queued.is_err(),
+            "second writer got in while the first held its turn"
+        );
+        assert!(second.conn().await.is_ok());
+        diesel::sql_query("INSERT INTO shared_pool_probe (id) VALUES (1)")
+            .execute(&mut writer)
+            .await
+            .unwrap();
+        drop(writer);
+        assert!(second.write_conn().await.is_ok());
+    }
+}
@@ -12,6 +12,10 @@ use rand::TryRng;
 
 use crate::error::{ButterflyBotError, Result};
 
+mod handle;
+
+pub use handle::{DbHandle, SqliteAsyncConn, SqlitePooledConn, WriteConn, POOL_SIZE};
+
 #[cfg(not(test))]
 const DB_KEY_NAME: &str = "db_encryption_key";
 
@@ -386,6 +390,18 @@ pub fn open_sqlcipher_connection_sync(database_url: &str) -> Result<SqliteConnec
     Ok(conn)
 }
 
+/// Opens a database that must already exist and decrypt with the current
+/// key. Unlike [`open_sqlcipher_connection_sync`] it never archives or
+/// recreates an unreadable file, so it is safe for inspecting copies.
+pub fn open_existing_sqlcipher_connection_sync(database_url: &str) -> Result<SqliteConnection> {
+    if !std::path::Path::new(database_url).is_file() {
+        return Err(ButterflyBotError::Runtime(format!(
+            "{database_url}: no such database"
+        )));
+    }
+    try_open_with_key(database_url, &get_sqlcipher_key()?)
+}
+
 pub async fn apply_sqlcipher_key_async(
     conn: &mut SyncConnectionWrapper<SqliteConnection>,
 ) -> Result<()> {
@@ -0,0 +1,382 @@
+//! Morning agenda digest.
+//!
+//! Configured per user under `tools.settings.digest`; top-level keys are
+//! defaults for every entry in `users`:
+//!
+//! ```json
+//! {"send_at": "07:30", "sections": ["reminders", "overdue", "today", "blocked"],
+//!  "channels": ["chat"], "webhook_url": null,
+//!  "users": {"alice": {"send_at": "08:15", "channels": ["chat", "webhook"]}}}
+//! ```
+//!
+//! Send times are read in the user's time zone (see [`crate::timezones`]).
+//! Only users listed under `users` get a digest.
+
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde_json::Value;
+
+use crate::smart_lists::SmartListBounds;
+
+pub const DEFAULT_SEND_AT_MINUTE: u32 = 8 * 60;
+
+/// How late a digest may still go out, e.g. after the daemon was asleep at
+/// the send time. Beyond this the day is skipped rather than sent at noon.
+pub const CATCH_UP_MINUTES: u32 = 120;
+
+/// Lines shown per section before collapsing into "and N more".
+const SECTION_LIMIT: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestSection {
+    Reminders,
+    Overdue,
+    Today,
+    Blocked,
+}
+
+impl DigestSection {
+    pub fn all() -> [DigestSection; 4] {
+        [
+            DigestSection::Reminders,
+            DigestSection::Overdue,
+            DigestSection::Today,
+            DigestSection::Blocked,
+        ]
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reminders" | "due_reminders" => Some(DigestSection::Reminders),
+            "overdue" | "overdue_todos" => Some(DigestSection::Overdue),
+            "today" | "tasks" | "today_tasks" => Some(DigestSection::Today),
+            "blocked" => Some(DigestSection::Blocked),
+            _ => None,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            DigestSection::Reminders => "reminders",
+            DigestSection::Overdue => "overdue",
+            DigestSection::Today => "today",
+            DigestSection::Blocked => "blocked",
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            DigestSection::Reminders => "Reminders due",
+            DigestSection::Overdue => "Overdue",
+            DigestSection::Today => "Due today",
+            DigestSection::Blocked => "Blocked",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DigestChannel {
+    /// Appended to the user's chat as an assistant message.
+    Chat,
+    /// Desktop notification from the daemon.
+    Notification,
+    /// JSON POST to the schedule's `webhook_url`.
+    Webhook,
+}
+
+impl DigestChannel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "chat" | "proactive_chat" => Some(DigestChannel::Chat),
+            "notification" | "desktop" => Some(DigestChannel::Notification),
+            "webhook" => Some(DigestChannel::Webhook),
+            _ => None,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            DigestChannel::Chat => "chat",
+            DigestChannel::Notification => "notification",
+            DigestChannel::Webhook => "webhook",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestSchedule {
+    pub send_at_minute: u32,
+    pub sections: Vec<DigestSection>,
+    pub channels: Vec<DigestChannel>,
+    pub webhook_url: Option<String>,
+}
+
+impl Default for DigestSchedule {
+    fn default() -> Self {
+        Self {
+            send_at_minute: DEFAULT_SEND_AT_MINUTE,
+            sections: DigestSection::all().to_vec(),
+            channels: vec![DigestChannel::Chat],
+            webhook_url: None,
+        }
+    }
+}
+
+impl DigestSchedule {
+    fn overlay(&self, value: &Value) -> Self {
+        let mut schedule = self.clone();
+        if let Some(minute) = value
+            .get("send_at")
+            .and_then(|v| v.as_str())
+            .and_then(parse_send_at)
+        {
+            schedule.send_at_minute = minute;
+        }
+        if let Some(sections) = value.get("sections").and_then(|v| v.as_array()) {
+            schedule.sections = parse_list(sections, DigestSection::parse);
+        }
+        if let Some(channels) = value.get("channels").and_then(|v| v.as_array()) {
+            schedule.channels = parse_list(channels, DigestChannel::parse);
+        }
+        if let Some(url) = value.get("webhook_url") {
+            schedule.webhook_url = url
+                .as_str()
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(ToString::to_string);
+        }
+        schedule
+    }
+
+    /// Whether the digest should go out at `minute` past local midnight on
+    /// `today`, given the day it was last sent.
+    pub fn is_due(&self, today: NaiveDate, minute: u32, last_sent: Option<NaiveDate>) -> bool {
+        last_sent != Some(today)
+            && minute >= self.send_at_minute
+            && minute < self.send_at_minute + CATCH_UP_MINUTES
+    }
+}
+
+pub(crate) fn parse_send_at(value: &str) -> Option<u32> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    let hour = hour.trim().parse::<u32>().ok()?;
+    let minute = minute.trim().parse::<u32>().ok()?;
+    (hour < 24 && minute < 60).then_some(hour * 60 + minute)
+}
+
+fn parse_list<T: PartialEq>(values: &[Value], parse: fn(&str) -> Option<T>) -> Vec<T> {
+    let mut parsed = Vec::new();
+    for value in values.iter().filter_map(|v| v.as_str()).filter_map(parse) {
+        if !parsed.contains(&value) {
+            parsed.push(value);
+        }
+    }
+    parsed
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DigestConfig {
+    pub users: HashMap<String, DigestSchedule>,
+}
+
+impl DigestConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("digest"))
+        else {
+            return Self::default();
+        };
+        if section.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+            return Self::default();
+        }
+        let defaults = DigestSchedule::default().overlay(section);
+        let users = section
+            .get("users")
+            .and_then(|v| v.as_object())
+            .map(|users| {
+                users
+                    .iter()
+                    .filter(|(_, value)| {
+                        value.get("enabled").and_then(|v| v.as_bool()) != Some(false)
+                    })
+                    .map(|(user_id, value)| (user_id.clone(), defaults.overlay(value)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { users }
+    }
+
+    pub fn schedule_for(&self, user_id: &str) -> Option<&DigestSchedule> {
+        self.users.get(user_id)
+    }
+}
+
+/// The slice of an inbox item the digest looks at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DigestItem {
+    pub title: String,
+    pub is_reminder: bool,
+    pub due_at: Option<i64>,
+    pub blocked: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Digest {
+    pub text: String,
+    /// Item count per included section key.
+    pub counts: Vec<(&'static str, usize)>,
+}
+
+impl Digest {
+    pub fn is_empty(&self) -> bool {
+        self.counts.iter().all(|(_, count)| *count == 0)
+    }
+}
+
+fn in_section(section: DigestSection, item: &DigestItem, bounds: &SmartListBounds) -> bool {
+    match section {
+        DigestSection::Reminders => item
+            .due_at
+            .is_some_and(|due| item.is_reminder && due < bounds.tomorrow_start),
+        DigestSection::Overdue => item
+            .due_at
+            .is_some_and(|due| !item.is_reminder && due < bounds.now),
+        DigestSection::Today => item.due_at.is_some_and(|due| {
+            !item.is_reminder && due >= bounds.now && due < bounds.tomorrow_start
+        }),
+        DigestSection::Blocked => item.blocked,
+    }
+}
+
+/// Builds the digest text for `schedule`'s sections, earliest due first.
+pub fn compose(
+    schedule: &DigestSchedule,
+    items: &[DigestItem],
+    bounds: &SmartListBounds,
+) -> Digest {
+    let mut lines = vec!["Good morning! Here's your agenda for today.".to_string()];
+    let mut counts = Vec::new();
+    for section in &schedule.sections {
+        let mut matched = items
+            .iter()
+            .filter(|item| in_section(*section, item, bounds))
+            .collect::<Vec<_>>();
+        matched.sort_by_key(|item| item.due_at.unwrap_or(i64::MAX));
+        counts.push((section.key(), matched.len()));
+        if matched.is_empty() {
+            continue;
+        }
+        lines.push(String::new());
+        lines.push(format!("**{}** ({})", section.heading(), matched.len()));
+        for item in matched.iter().take(SECTION_LIMIT) {
+            lines.push(format!("- {}", item.title));
+        }
+        if matched.len() > SECTION_LIMIT {
+            lines.push(format!("- …and {} more", matched.len() - SECTION_LIMIT));
+        }
+    }
+    let mut digest = Digest {
+        text: String::new(),
+        counts,
+    };
+    if digest.is_empty() {
+        lines.push(String::new());
+        lines.push("Nothing due and nothing blocked.".to_string());
+    }
+    digest.text = lines.join("\n");
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(title: &str, is_reminder: bool, due_at: Option<i64>, blocked: bool) -> DigestItem {
+        DigestItem {
+            title: title.to_string(),
+            is_reminder,
+            due_at,
+            blocked,
+        }
+    }
+
+    #[test]
+    fn users_inherit_defaults_and_override_them() {
+        let config = DigestConfig::from_tools(Some(&json!({"settings": {"digest": {
+            "send_at": "07:30",
+            "channels": ["chat", "notification", "carrier pigeon"],
+            "users": {
+                "alice": {"send_at": "06:05", "sections": ["blocked", "today", "blocked"]},
+                "bob": {},
+                "carol": {"enabled": false}
+            }
+        }}})));
+        let alice = config.schedule_for("alice").unwrap();
+        assert_eq!(alice.send_at_minute, 6 * 60 + 5);
+        assert_eq!(
+            alice.sections,
+            vec![DigestSection::Blocked, DigestSection::Today]
+        );
+        assert_eq!(
+            alice.channels,
+            vec![DigestChannel::Chat, DigestChannel::Notification]
+        );
+        let bob = config.schedule_for("bob").unwrap();
+        assert_eq!(bob.send_at_minute, 7 * 60 + 30);
+        assert_eq!(bob.sections, DigestSection::all().to_vec());
+        assert!(config.schedule_for("carol").is_none());
+        assert!(DigestConfig::from_tools(None).users.is_empty());
+    }
+
+    #[test]
+    fn due_once_per_day_within_the_catch_up_window() {
+        let schedule = DigestSchedule::default();
+        let today = NaiveDate::from_ymd_opt(2026, 3, 4).unwrap();
+        let yesterday = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        assert!(!schedule.is_due(today, 7 * 60 + 59, None));
+        assert!(schedule.is_due(today, 8 * 60, Some(yesterday)));
+        assert!(!schedule.is_due(today, 8 * 60 + 5, Some(today)));
+        assert!(!schedule.is_due(today, 8 * 60 + CATCH_UP_MINUTES, None));
+    }
+
+    #[test]
+    fn compose_buckets_items_into_sections() {
+        let bounds = SmartListBounds {
+            now: 1_000,
+            tomorrow_start: 5_000,
+            next_week_start: 9_000,
+            someday_after: 100_000,
+        };
+        let items = vec![
+            item("Pay rent", true, Some(4_000), false),
+            item("Old reminder", true, Some(500), false),
+            item("Next week's reminder", true, Some(8_000), false),
+            item("File taxes", false, Some(900), false),
+            item("Ship release", false, Some(2_000), false),
+            item("Wait on legal", false, None, true),
+            item("Someday idea", false, None, false),
+        ];
+        let digest = compose(&DigestSchedule::default(), &items, &bounds);
+        assert_eq!(
+            digest.counts,
+            vec![
+                ("reminders", 2),
+                ("overdue", 1),
+                ("today", 1),
+                ("blocked", 1)
+            ]
+        );
+        assert!(digest
+            .text
+            .contains("**Reminders due** (2)\n- Old reminder\n- Pay rent"));
+        assert!(digest.text.contains("**Blocked** (1)\n- Wait on legal"));
+        assert!(!digest.text.contains("Someday idea"));
+
+        let empty = compose(&DigestSchedule::default(), &[], &bounds);
+        assert!(empty.is_empty());
+        assert!(empty.text.ends_with("Nothing due and nothing blocked."));
+    }
+}
@@ -0,0 +1,270 @@
+//! The handful of IMAP4rev1 commands the poller needs: log in, select a
+//! folder, search it and peek at messages without marking them read.
+
+use std::sync::Arc;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::TcpStream;
+
+use crate::error::{ButterflyBotError, Result};
+
+/// Upper bound on a single literal; bigger messages are refused rather
+/// than buffered.
+const MAX_LITERAL_BYTES: usize = 25 * 1024 * 1024;
+
+/// One untagged response. Literals are cut out of `text` and kept in
+/// order in `literals`.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Untagged {
+    text: String,
+    literals: Vec<Vec<u8>>,
+}
+
+/// A message as returned by `UID FETCH`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fetched {
+    pub uid: u32,
+    pub flags: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+impl Fetched {
+    pub fn is_flagged(&self) -> bool {
+        self.flags
+            .iter()
+            .any(|flag| flag.eq_ignore_ascii_case("\\Flagged"))
+    }
+}
+
+trait Stream: AsyncBufRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncBufRead + AsyncWrite + Unpin + Send> Stream for T {}
+
+pub struct Session {
+    stream: Box<dyn Stream>,
+    next_tag: u32,
+}
+
+impl Session {
+    /// Connects and reads the greeting. `tls: false` is meant for local
+    /// bridges that listen on loopback only.
+    pub async fn connect(host: &str, port: u16, tls: bool) -> Result<Self> {
+        let tcp = TcpStream::connect((host, port))
+            .await
+            .map_err(|e| ButterflyBotError::Http(format!("IMAP connect to {host}:{port}: {e}")))?;
+        let stream: Box<dyn Stream> = if tls {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+            let name = rustls::pki_types::ServerName::try_from(host.to_string())
+                .map_err(|e| ButterflyBotError::Config(format!("Bad IMAP host {host}: {e}")))?;
+            let stream = tokio_rustls::TlsConnector::from(Arc::new(config))
+                .connect(name, tcp)
+                .await
+                .map_err(|e| ButterflyBotError::Http(format!("IMAP TLS with {host}: {e}")))?;
+            Box::new(BufReader::new(stream))
+        } else {
+            Box::new(BufReader::new(tcp))
+        };
+        let mut session = Self {
+            stream,
+            next_tag: 1,
+        };
+        let greeting = session.read_line().await?;
+        if !greeting.starts_with("* OK") && !greeting.starts_with("* PREAUTH") {
+            return Err(ButterflyBotError::Http(format!(
+                "Unexpected IMAP greeting: {}",
+                greeting.trim_end()
+            )));
+        }
+        Ok(session)
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> Result<()> {
+        let command = format!("LOGIN {} {}", quote(username), quote(password));
+        self.run(&command).await.map(|_| ())
+    }
+
+    pub async fn select(&mut self, folder: &str) -> Result<()> {
+        self.run(&format!("SELECT {}", quote(folder)))
+            .await
+            .map(|_| ())
+    }
+
+    /// UIDs of messages that arrived on or after `since` (unix seconds).
+    pub async fn search_since(&mut self, since: i64) -> Result<Vec<u32>> {
+        let date = chrono::DateTime::from_timestamp(since, 0)
+            .unwrap_or_default()
+            .format("%-d-%b-%Y");
+        let responses = self.run(&format!("UID SEARCH SINCE {date}")).await?;
+        Ok(responses
+            .iter()
+            .filter_map(|response| response.text.strip_prefix("* SEARCH"))
+            .flat_map(|rest| rest.split_whitespace().filter_map(|uid| uid.parse().ok()))
+            .collect())
+    }
+
+    /// Flags and headers of `uids`.
+    pub async fn fetch_headers(&mut self, uids: &[u32]) -> Result<Vec<Fetched>> {
+        self.fetch(uids, "BODY.PEEK[HEADER]").await
+    }
+
+    /// Flags and the full message of `uids`. `PEEK` leaves `\Seen` alone.
+    pub async fn fetch_messages(&mut self, uids: &[u32]) -> Result<Vec<Fetched>> {
+        self.fetch(uids, "BODY.PEEK[]").await
+    }
+
+    pub async fn logout(mut self) -> Result<()> {
+        self.run("LOGOUT").await.map(|_| ())
+    }
+
+    async fn fetch(&mut self, uids: &[u32], item: &str) -> Result<Vec<Fetched>> {
+        if uids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let responses = self
+            .run(&format!("UID FETCH {set} (UID FLAGS {item})"))
+            .await?;
+        Ok(responses.into_iter().filter_map(parse_fetch).collect())
+    }
+
+    /// Sends a command and collects its untagged responses until the
+    /// tagged completion, which must be `OK`.
+    async fn run(&mut self, command: &str) -> Result<Vec<Untagged>> {
+        let tag = format!("a{}", self.next_tag);
+        self.next_tag += 1;
+        self.stream
+            .write_all(format!("{tag} {command}\r\n").as_bytes())
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        self.stream
+            .flush()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+
+        let mut responses = Vec::new();
+        loop {
+            let response = self.read_response().await?;
+            if let Some(status) = response.text.strip_prefix(&format!("{tag} ")) {
+                if status.starts_with("OK") {
+                    return Ok(responses);
+                }
+                let verb = command.split_whitespace().next().unwrap_or(command);
+                return Err(ButterflyBotError::Http(format!(
+                    "IMAP {verb} failed: {}",
+                    status.trim_end()
+                )));
+            }
+            responses.push(response);
+        }
+    }
+
+    async fn read_response(&mut self) -> Result<Untagged> {
+        let mut response = Untagged::default();
+        loop {
+            let line = self.read_line().await?;
+            let line = line.trim_end_matches(['\r', '\n']);
+            let Some(size) = literal_size(line) else {
+                response.text.push_str(line);
+                return Ok(response);
+            };
+            if size > MAX_LITERAL_BYTES {
+                return Err(ButterflyBotError::Http(format!(
+                    "IMAP literal of {size} bytes is too large"
+                )));
+            }
+            response.text.push_str(line);
+            let mut literal = vec![0u8; size];
+            self.stream
+                .read_exact(&mut literal)
+                .await
+                .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+            response.literals.push(literal);
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = Vec::new();
+        let read = self
+            .stream
+            .read_until(b'\n', &mut line)
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if read == 0 {
+            return Err(ButterflyBotError::Http(
+                "IMAP server closed the connection".to_string(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+/// The `n` of a trailing `{n}`, announcing a literal on the next bytes.
+fn literal_size(line: &str) -> Option<usize> {
+    let open = line.strip_suffix('}')?.rfind('{')?;
+    line[open + 1..line.len() - 1].parse().ok()
+}
+
+/// A quoted IMAP string.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+static UID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bUID (\d+)").unwrap());
+static FLAGS_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\bFLAGS \(([^)]*)\)").unwrap());
+
+fn parse_fetch(response: Untagged) -> Option<Fetched> {
+    if !response.text.starts_with("* ") || !response.text.contains(" FETCH (") {
+        return None;
+    }
+    let uid = UID_RE.captures(&response.text)?[1].parse().ok()?;
+    let flags = FLAGS_RE
+        .captures(&response.text)
+        .map(|caps| caps[1].split_whitespace().map(str::to_string).collect())
+        .unwrap_or_default();
+    Some(Fetched {
+        uid,
+        flags,
+        data: response.literals.into_iter().next().unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fetch_responses_with_literals() {
+        assert_eq!(literal_size("* 1 FETCH (UID 7 BODY[] {12}"), Some(12));
+        assert_eq!(literal_size("* OK {not a literal"), None);
+        assert_eq!(quote("pa\"ss\\"), "\"pa\\\"ss\\\\\"");
+
+        let fetched = parse_fetch(Untagged {
+            text: "* 3 FETCH (FLAGS (\\Seen \\Flagged) UID 42 BODY[HEADER] {5})".to_string(),
+            literals: vec![b"a: b\n".to_vec()],
+        })
+        .unwrap();
+        assert_eq!(fetched.uid, 42);
+        assert!(fetched.is_flagged());
+        assert_eq!(fetched.data, b"a: b\n");
+        assert!(parse_fetch(Untagged {
+            text: "* 3 EXISTS".to_string(),
+            literals: Vec::new(),
+        })
+        .is_none());
+    }
+}
@@ -0,0 +1,374 @@
+//! Just enough RFC 5322/MIME to turn an email into a todo: decoded
+//! headers and the first readable text part.
+
+use base64::Engine;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Headers {
+    /// Lowercase name and decoded value, in order.
+    fields: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub fn parse(raw: &[u8]) -> Self {
+        let text = decode_charset(raw, None);
+        let mut fields: Vec<(String, String)> = Vec::new();
+        for line in text.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with([' ', '\t']) {
+                if let Some((_, value)) = fields.last_mut() {
+                    value.push(' ');
+                    value.push_str(line.trim());
+                }
+                continue;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                fields.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+        }
+        for (_, value) in &mut fields {
+            *value = decode_words(value);
+        }
+        Self { fields }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `Message-ID` without its angle brackets.
+    pub fn message_id(&self) -> Option<String> {
+        self.get("message-id")
+            .map(|id| {
+                id.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .trim()
+            })
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// Splits a message into header bytes and body bytes.
+fn split_message(raw: &[u8]) -> (&[u8], &[u8]) {
+    for (sep, len) in [(&b"\r\n\r\n"[..], 4), (&b"\n\n"[..], 2)] {
+        if let Some(pos) = raw.windows(len).position(|window| window == sep) {
+            return (&raw[..pos + len], &raw[pos + len..]);
+        }
+    }
+    (raw, &[])
+}
+
+/// The first `text/plain` part, falling back to `text/html` with the tags
+/// stripped. Attachments are skipped.
+pub fn body_text(raw: &[u8]) -> Option<String> {
+    let (head, body) = split_message(raw);
+    let headers = Headers::parse(head);
+    let (plain, html) = find_text(&headers, body, 0);
+    plain
+        .or_else(|| html.map(|html| strip_html(&html)))
+        .map(|text| text.replace("\r\n", "\n").trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+fn find_text(headers: &Headers, body: &[u8], depth: usize) -> (Option<String>, Option<String>) {
+    let content_type = headers.get("content-type").unwrap_or("text/plain");
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let disposition = headers
+        .get("content-disposition")
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if disposition.starts_with("attachment") {
+        return (None, None);
+    }
+
+    if mime.starts_with("multipart/") {
+        let Some(boundary) = parameter(content_type, "boundary") else {
+            return (None, None);
+        };
+        if depth > 8 {
+            return (None, None);
+        }
+        let mut html = None;
+        for part in split_parts(body, &boundary) {
+            let (head, part_body) = split_message(part);
+            let (found_plain, found_html) = find_text(&Headers::parse(head), part_body, depth + 1);
+            if found_plain.is_some() {
+                return (found_plain, None);
+            }
+            html = html.or(found_html);
+        }
+        return (None, html);
+    }
+
+    let charset = parameter(content_type, "charset");
+    let decoded = decode_transfer(
+        body,
+        headers.get("content-transfer-encoding").unwrap_or("7bit"),
+    );
+    let text = decode_charset(&decoded, charset.as_deref());
+    match mime.as_str() {
+        "text/plain" => (Some(text), None),
+        "text/html" => (None, Some(text)),
+        _ => (None, None),
+    }
+}
+
+fn split_parts<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{boundary}");
+    let delimiter = delimiter.as_bytes();
+    let mut starts = Vec::new();
+    let mut index = 0;
+    while index + delimiter.len() <= body.len() {
+        let at_line_start = index == 0 || body[index - 1] == b'\n';
+        if at_line_start && &body[index..index + delimiter.len()] == delimiter {
+            starts.push(index);
+            index += delimiter.len();
+        } else {
+            index += 1;
+        }
+    }
+    starts
+        .windows(2)
+        .filter_map(|pair| {
+            let part = &body[pair[0] + delimiter.len()..pair[1]];
+            // Drop the rest of the delimiter line.
+            let start = part.iter().position(|byte| *byte == b'\n')? + 1;
+            Some(&part[start..])
+        })
+        .collect()
+}
+
+/// A `key=value` parameter of a structured header, unquoted.
+fn parameter(header: &str, key: &str) -> Option<String> {
+    header.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(key)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+fn decode_transfer(body: &[u8], encoding: &str) -> Vec<u8> {
+    match encoding.trim().to_ascii_lowercase().as_str() {
+        "base64" => {
+            let compact: Vec<u8> = body
+                .iter()
+                .copied()
+                .filter(|byte| !byte.is_ascii_whitespace())
+                .collect();
+            base64::engine::general_purpose::STANDARD
+                .decode(&compact)
+                .unwrap_or_else(|_| body.to_vec())
+        }
+        "quoted-printable" => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    }
+}
+
+/// Quoted-printable; `underscores` is the header (`Q`) variant where `_`
+/// stands for a space.
+fn decode_quoted_printable(input: &[u8], underscores: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'=' => {
+                let rest = &input[index + 1..];
+                if rest.starts_with(b"\r\n") {
+                    index += 3;
+                    continue;
+                }
+                if rest.starts_with(b"\n") {
+                    index += 2;
+                    continue;
+                }
+                let hex = rest
+                    .get(..2)
+                    .and_then(|pair| std::str::from_utf8(pair).ok())
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        out.push(byte);
+                        index += 3;
+                    }
+                    None => {
+                        out.push(b'=');
+                        index += 1;
+                    }
+                }
+            }
+            b'_' if underscores => {
+                out.push(b' ');
+                index += 1;
+            }
+            byte => {
+                out.push(byte);
+                index += 1;
+            }
+        }
+    }
+    out
+}
+
+fn decode_charset(bytes: &[u8], charset: Option<&str>) -> String {
+    match charset.map(|charset| charset.to_ascii_lowercase()) {
+        Some(charset) if charset == "iso-8859-1" || charset == "latin1" => {
+            bytes.iter().map(|byte| char::from(*byte)).collect()
+        }
+        _ => String::from_utf8_lossy(bytes).into_owned(),
+    }
+}
+
+/// Decodes RFC 2047 encoded words (`=?utf-8?B?…?=`). Whitespace between
+/// two encoded words is dropped, as the RFC asks.
+fn decode_words(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    let mut pending_space = String::new();
+    let mut last_was_word = false;
+    while !rest.is_empty() {
+        if let Some((decoded, used)) = rest.strip_prefix("=?").and_then(encoded_word) {
+            if !last_was_word {
+                out.push_str(&pending_space);
+            }
+            pending_space.clear();
+            out.push_str(&decoded);
+            rest = &rest[used + 2..];
+            last_was_word = true;
+            continue;
+        }
+        let ch = rest.chars().next().unwrap_or_default();
+        if ch.is_whitespace() {
+            pending_space.push(ch);
+        } else {
+            out.push_str(&pending_space);
+            pending_space.clear();
+            out.push(ch);
+            last_was_word = false;
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+    out.push_str(&pending_space);
+    out
+}
+
+/// Decodes `charset?enc?text?=` and returns how many bytes it used.
+fn encoded_word(input: &str) -> Option<(String, usize)> {
+    let mut pieces = input.splitn(3, '?');
+    let charset = pieces.next()?;
+    let encoding = pieces.next()?;
+    let remainder = pieces.next()?;
+    let end = remainder.find("?=")?;
+    let text = &remainder[..end];
+    let bytes = match encoding.to_ascii_uppercase().as_str() {
+        "B" => base64::engine::general_purpose::STANDARD
+            .decode(text)
+            .ok()?,
+        "Q" => decode_quoted_printable(text.as_bytes(), true),
+        _ => return None,
+    };
+    let charset = charset.split('*').next().unwrap_or(charset);
+    let used = charset.len() + 1 + encoding.len() + 1 + end + 2;
+    Some((decode_charset(&bytes, Some(charset)), used))
+}
+
+fn strip_html(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_tag = false;
+    let lower = html.to_ascii_lowercase();
+    let mut skip_until: Option<&str> = None;
+    let mut index = 0;
+    for (pos, ch) in html.char_indices() {
+        if pos < index {
+            continue;
+        }
+        if let Some(end) = skip_until {
+            if lower[pos..].starts_with(end) {
+                skip_until = None;
+                index = pos + end.len();
+            }
+            continue;
+        }
+        match ch {
+            '<' => {
+                if lower[pos..].starts_with("<style") {
+                    skip_until = Some("</style>");
+                } else if lower[pos..].starts_with("<script") {
+                    skip_until = Some("</script>");
+                } else if lower[pos..].starts_with("<br") || lower[pos..].starts_with("</p") {
+                    out.push('\n');
+                }
+                in_tag = true;
+            }
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => out.push(ch),
+            _ => {}
+        }
+    }
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_headers_and_picks_the_plain_part() {
+        let raw = b"Message-ID: <abc@mail.example>\r\n\
+Subject: =?UTF-8?B?UmU6IGludm9pY2Ug4oCU?=\r\n =?utf-8?Q?due_Friday?=\r\n\
+From: Alice <alice@example.com>\r\n\
+Content-Type: multipart/alternative; boundary=\"b1\"\r\n\
+\r\n\
+--b1\r\n\
+Content-Type: text/html; charset=utf-8\r\n\
+\r\n\
+<p>ignored</p>\r\n\
+--b1\r\n\
+Content-Type: text/plain; charset=utf-8\r\n\
+Content-Transfer-Encoding: quoted-printable\r\n\
+\r\n\
+Please pay by Friday =E2=80=94 thanks!=\r\n\
+ See attached.\r\n\
+--b1--\r\n";
+        let (head, _) = split_message(raw);
+        let headers = Headers::parse(head);
+        assert_eq!(headers.message_id().as_deref(), Some("abc@mail.example"));
+        assert_eq!(headers.get("subject"), Some("Re: invoice — due Friday"));
+        assert_eq!(
+            body_text(raw).as_deref(),
+            Some("Please pay by Friday — thanks! See attached.")
+        );
+    }
+
+    #[test]
+    fn falls_back_to_stripped_html_and_base64() {
+        let body = base64::engine::general_purpose::STANDARD
+            .encode("<html><style>p{}</style><p>Call&nbsp;Bob</p><br>tomorrow</html>");
+        let raw = format!(
+            "Subject: plain\r\nContent-Type: text/html\r\nContent-Transfer-Encoding: base64\r\n\r\n{body}\r\n"
+        );
+        assert_eq!(
+            body_text(raw.as_bytes()).as_deref(),
+            Some("Call Bob\n\ntomorrow")
+        );
+        assert_eq!(decode_words("a =?x?Z?bad?= b"), "a =?x?Z?bad?= b");
+    }
+}
@@ -0,0 +1,348 @@
+//! Turns emails into todos, so "forward it to your bot" works.
+//!
+//! Accounts are polled over IMAP and configured under
+//! `tools.settings.email`:
+//!
+//! ```json
+//! {"accounts": [
+//!    {"name": "work", "user_id": "alice", "host": "imap.example.com",
+//!     "username": "bot@example.com", "password_secret": "email_work_password",
+//!     "folder": "INBOX", "flagged": true,
+//!     "rules": [{"from": "@acme.com", "subject": "invoice"}, {"to": "bot+todo@"}]}],
+//!  "poll_minutes": 5, "lookback_days": 3}
+//! ```
+//!
+//! A message becomes a todo when it is flagged (unless `flagged` is off)
+//! or when every field of one rule appears in the matching header. Each
+//! todo carries an `email:<message-id>` origin ref, so a message is only
+//! ever imported once; the mailbox itself is never changed.
+
+pub mod imap;
+pub mod message;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::todo::TodoStore;
+use message::Headers;
+
+pub const ORIGIN_PREFIX: &str = "email:";
+const DEFAULT_FOLDER: &str = "INBOX";
+const DEFAULT_PORT: u16 = 993;
+const DEFAULT_POLL_MINUTES: u64 = 5;
+const DEFAULT_LOOKBACK_DAYS: i64 = 3;
+const MAX_NOTES_CHARS: usize = 4000;
+
+/// Header substrings a message must all contain, case-insensitively.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EmailRule {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub subject: Option<String>,
+}
+
+impl EmailRule {
+    pub fn matches(&self, headers: &Headers) -> bool {
+        let contains = |header: &str, needle: &Option<String>| match needle {
+            Some(needle) => headers
+                .get(header)
+                .is_some_and(|value| value.to_lowercase().contains(&needle.to_lowercase())),
+            None => true,
+        };
+        contains("from", &self.from)
+            && (self.to.is_none()
+                || contains("to", &self.to)
+                || contains("cc", &self.to)
+                || contains("delivered-to", &self.to))
+            && contains("subject", &self.subject)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailAccount {
+    pub name: String,
+    pub user_id: String,
+    pub host: String,
+    pub port: u16,
+    /// Plain TCP, for mail bridges listening on loopback.
+    pub tls: bool,
+    pub username: String,
+    /// Vault entry holding the password.
+    pub password_secret: Option<String>,
+    pub folder: String,
+    /// Whether flagged messages become todos.
+    pub flagged: bool,
+    pub rules: Vec<EmailRule>,
+}
+
+impl EmailAccount {
+    fn wants(&self, flagged: bool, headers: &Headers) -> bool {
+        (self.flagged && flagged) || self.rules.iter().any(|rule| rule.matches(headers))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmailConfig {
+    pub accounts: Vec<EmailAccount>,
+    pub poll_minutes: u64,
+    /// How far back each poll searches the folder.
+    pub lookback_days: i64,
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            accounts: Vec::new(),
+            poll_minutes: DEFAULT_POLL_MINUTES,
+            lookback_days: DEFAULT_LOOKBACK_DAYS,
+        }
+    }
+}
+
+impl EmailConfig {
+    /// Accounts without a name, user, host or username are skipped, as
+    /// are empty rules, which would match every message.
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("email"))
+        else {
+            return Self::default();
+        };
+        Self {
+            accounts: section
+                .get("accounts")
+                .and_then(Value::as_array)
+                .map(|accounts| accounts.iter().filter_map(parse_account).collect())
+                .unwrap_or_default(),
+            poll_minutes: section
+                .get("poll_minutes")
+                .and_then(Value::as_u64)
+                .filter(|minutes| *minutes > 0)
+                .unwrap_or(DEFAULT_POLL_MINUTES),
+            lookback_days: section
+                .get("lookback_days")
+                .and_then(Value::as_i64)
+                .filter(|days| *days > 0)
+                .unwrap_or(DEFAULT_LOOKBACK_DAYS),
+        }
+    }
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn parse_account(value: &Value) -> Option<EmailAccount> {
+    let rules = value
+        .get("rules")
+        .and_then(Value::as_array)
+        .map(|rules| {
+            rules
+                .iter()
+                .map(|rule| EmailRule {
+                    from: text(rule, "from"),
+                    to: text(rule, "to"),
+                    subject: text(rule, "subject"),
+                })
+                .filter(|rule| rule != &EmailRule::default())
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(EmailAccount {
+        name: text(value, "name")?,
+        user_id: text(value, "user_id")?,
+        host: text(value, "host")?,
+        port: value
+            .get("port")
+            .and_then(Value::as_u64)
+            .and_then(|port| u16::try_from(port).ok())
+            .unwrap_or(DEFAULT_PORT),
+        tls: value.get("tls").and_then(Value::as_bool).unwrap_or(true),
+        username: text(value, "username")?,
+        password_secret: text(value, "password_secret"),
+        folder: text(value, "folder").unwrap_or_else(|| DEFAULT_FOLDER.to_string()),
+        flagged: value
+            .get("flagged")
+            .and_then(Value::as_bool)
+            .unwrap_or(true),
+        rules,
+    })
+}
+
+/// Outcome of polling one account.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct IngestReport {
+    pub account: String,
+    pub user_id: String,
+    /// Todos created by this poll.
+    pub todo_ids: Vec<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Polls every configured account (or only `user_id`'s). A failing
+/// account reports its error without stopping the others.
+pub async fn poll(
+    store: &TodoStore,
+    config: &EmailConfig,
+    user_id: Option<&str>,
+    now: i64,
+) -> Vec<IngestReport> {
+    let mut reports = Vec::new();
+    for account in &config.accounts {
+        if user_id.is_some_and(|user_id| user_id != account.user_id) {
+            continue;
+        }
+        let (todo_ids, error) =
+            match poll_account(store, account, now - config.lookback_days * 86_400).await {
+                Ok(todo_ids) => (todo_ids, None),
+                Err(err) => (Vec::new(), Some(err.to_string())),
+            };
+        reports.push(IngestReport {
+            account: account.name.clone(),
+            user_id: account.user_id.clone(),
+            todo_ids,
+            error,
+        });
+    }
+    reports
+}
+
+async fn poll_account(store: &TodoStore, account: &EmailAccount, since: i64) -> Result<Vec<i32>> {
+    let password = match &account.password_secret {
+        Some(name) => crate::vault::get_secret(name)?.ok_or_else(|| {
+            ButterflyBotError::Config(format!("Vault has no secret named {name}"))
+        })?,
+        None => String::new(),
+    };
+    let mut session = imap::Session::connect(&account.host, account.port, account.tls).await?;
+    session.login(&account.username, &password).await?;
+    session.select(&account.folder).await?;
+    let uids = session.search_since(since).await?;
+
+    let mut wanted = Vec::new();
+    for fetched in session.fetch_headers(&uids).await? {
+        let headers = Headers::parse(&fetched.data);
+        if !account.wants(fetched.is_flagged(), &headers) {
+            continue;
+        }
+        let origin_ref = origin_ref(account, fetched.uid, &headers);
+        if store
+            .find_by_origin(&account.user_id, &origin_ref)
+            .await?
+            .is_none()
+        {
+            wanted.push(fetched.uid);
+        }
+    }
+
+    let mut todo_ids = Vec::new();
+    for fetched in session.fetch_messages(&wanted).await? {
+        let headers = Headers::parse(&fetched.data);
+        let origin_ref = origin_ref(account, fetched.uid, &headers);
+        let (title, notes) = todo_text(&headers, &fetched.data);
+        if let Some(item) = store
+            .create_item_from_origin(&account.user_id, &origin_ref, &title, Some(&notes))
+            .await?
+        {
+            todo_ids.push(item.id);
+        }
+    }
+    // The todos are in; a server that drops us on LOGOUT changes nothing.
+    let _ = session.logout().await;
+    Ok(todo_ids)
+}
+
+/// `email:<message-id>`. Messages without one get a stand-in built from
+/// the account and UID, which is stable as long as the folder is.
+fn origin_ref(account: &EmailAccount, uid: u32, headers: &Headers) -> String {
+    let id = headers.message_id().unwrap_or_else(|| {
+        format!(
+            "{uid}.{}.{}@{}",
+            account.folder, account.username, account.host
+        )
+    });
+    format!("{ORIGIN_PREFIX}{id}")
+}
+
+/// The subject as title; the sender and readable body as notes.
+fn todo_text(headers: &Headers, raw: &[u8]) -> (String, String) {
+    let subject = headers
+        .get("subject")
+        .map(str::trim)
+        .filter(|subject| !subject.is_empty())
+        .unwrap_or("(no subject)");
+    let mut notes = headers
+        .get("from")
+        .map(|from| format!("From: {from}"))
+        .unwrap_or_default();
+    if let Some(body) = message::body_text(raw) {
+        if !notes.is_empty() {
+            notes.push_str("\n\n");
+        }
+        notes.push_str(&body);
+    }
+    if notes.chars().count() > MAX_NOTES_CHARS {
+        notes = notes.chars().take(MAX_NOTES_CHARS).collect::<String>() + "…";
+    }
+    (subject.to_string(), notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_accounts_and_matches_rules() {
+        let tools = json!({"settings": {"email": {
+            "accounts": [
+                {"name": "work", "user_id": "u", "host": "imap.example.com",
+                 "username": "bot@example.com", "flagged": false,
+                 "rules": [{"from": "@ACME.com", "subject": "invoice"}, {"to": "bot+todo@"}, {}]},
+                {"name": "hostless", "user_id": "u", "username": "x"}
+            ],
+            "poll_minutes": 0
+        }}});
+        let config = EmailConfig::from_tools(Some(&tools));
+        assert_eq!(config.accounts.len(), 1);
+        assert_eq!(config.poll_minutes, DEFAULT_POLL_MINUTES);
+        let account = &config.accounts[0];
+        assert_eq!((account.port, account.tls), (993, true));
+        assert_eq!(account.folder, "INBOX");
+        assert_eq!(account.rules.len(), 2);
+
+        let headers = |raw: &str| Headers::parse(raw.as_bytes());
+        let invoice = headers("From: Billing <billing@acme.com>\r\nSubject: Invoice #12\r\n\r\n");
+        assert!(account.wants(false, &invoice));
+        assert!(!account.wants(true, &headers("From: a@acme.com\r\nSubject: lunch\r\n\r\n")));
+        assert!(account.wants(false, &headers("To: Bot <bot+todo@example.com>\r\n\r\n")));
+    }
+
+    #[test]
+    fn builds_todo_text_and_origin_refs() {
+        let raw = b"Message-ID: <m1@example.com>\r\nFrom: Bob <bob@example.com>\r\n\
+Subject:   \r\n\r\nRenew the passport\r\n";
+        let headers = Headers::parse(raw);
+        let (title, notes) = todo_text(&headers, raw);
+        assert_eq!(title, "(no subject)");
+        assert_eq!(notes, "From: Bob <bob@example.com>\n\nRenew the passport");
+
+        let account = parse_account(&json!({
+            "name": "home", "user_id": "u", "host": "h", "username": "me"
+        }))
+        .unwrap();
+        assert_eq!(origin_ref(&account, 9, &headers), "email:m1@example.com");
+        assert_eq!(
+            origin_ref(&account, 9, &Headers::default()),
+            "email:9.INBOX.me@h"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! GitHub webhooks: issue assignments, review requests and failed CI runs.
+//!
+//! Configured under `tools.settings.github_webhook`; `users` maps GitHub
+//! logins to butterfly users and events for anyone else are ignored:
+//!
+//! ```json
+//! {"users": {"octocat": "alice"}, "repos": ["acme/app"],
+//!  "secret_name": "github_webhook_secret"}
+//! ```
+//!
+//! Deliveries must carry `X-Hub-Signature-256` computed with the vault
+//! secret named by `secret_name`. Origin refs look like
+//! `github:pr:acme/app:123`, `github:issue:acme/app:45` and
+//! `github:ci:acme/app:main`, so a repeat event lands on the same item.
+
+use std::collections::HashMap;
+
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+use super::ExternalUpdate;
+
+pub const SOURCE: &str = "github";
+pub const SIGNATURE_HEADER: &str = "x-hub-signature-256";
+pub const EVENT_HEADER: &str = "x-github-event";
+pub const DEFAULT_SECRET_NAME: &str = "github_webhook_secret";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GithubWebhookConfig {
+    /// GitHub login (lowercase) to butterfly user id.
+    pub users: HashMap<String, String>,
+    /// `owner/repo` names to accept; empty accepts every repository.
+    pub repos: Vec<String>,
+    pub secret_name: String,
+}
+
+impl GithubWebhookConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let section = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("github_webhook"));
+        let users = section
+            .and_then(|section| section.get("users"))
+            .and_then(Value::as_object)
+            .map(|users| {
+                users
+                    .iter()
+                    .filter_map(|(login, user)| {
+                        let user = user.as_str()?.trim();
+                        (!user.is_empty())
+                            .then(|| (login.trim().to_ascii_lowercase(), user.to_string()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let repos = section
+            .and_then(|section| section.get("repos"))
+            .and_then(Value::as_array)
+            .map(|repos| {
+                repos
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(|repo| repo.trim().to_ascii_lowercase())
+                    .filter(|repo| !repo.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let secret_name = section
+            .and_then(|section| section.get("secret_name"))
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(DEFAULT_SECRET_NAME)
+            .to_string();
+        Self {
+            users,
+            repos,
+            secret_name,
+        }
+    }
+
+    pub fn user_for(&self, login: &str) -> Option<&str> {
+        self.users
+            .get(&login.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    pub fn accepts_repo(&self, repo: &str) -> bool {
+        self.repos.is_empty() || self.repos.contains(&repo.to_ascii_lowercase())
+    }
+}
+
+/// Checks `X-Hub-Signature-256` (`sha256=<hex>`) against the raw body.
+pub fn verify_signature(secret: &str, body: &[u8], header: &str) -> bool {
+    let Some(given) = header.trim().strip_prefix("sha256=") else {
+        return false;
+    };
+    let Ok(given) = hex::decode(given) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    // `verify_slice` compares in constant time.
+    mac.verify_slice(&given).is_ok()
+}
+
+/// What one delivery means for the inbox.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GithubAction {
+    /// Open or refresh an item for the user behind `login`.
+    Open {
+        login: String,
+        update: ExternalUpdate,
+    },
+    /// Resolve an item for `login`, or for everyone who has it open.
+    Resolve {
+        login: Option<String>,
+        origin_ref: String,
+    },
+}
+
+impl GithubAction {
+    pub fn origin_ref(&self) -> &str {
+        match self {
+            GithubAction::Open { update, .. } => &update.origin_ref,
+            GithubAction::Resolve { origin_ref, .. } => origin_ref,
+        }
+    }
+}
+
+/// Repository `owner/repo` of a delivery.
+pub fn repository(payload: &Value) -> Option<&str> {
+    payload.get("repository")?.get("full_name")?.as_str()
+}
+
+/// Inbox actions for an event; unknown events and actions yield nothing.
+pub fn actions(event: &str, payload: &Value) -> Vec<GithubAction> {
+    let Some(repo) = repository(payload) else {
+        return Vec::new();
+    };
+    let action = text(payload, &["action"]).unwrap_or_default();
+    match event {
+        "issues" => issue_actions(repo, action, payload),
+        "pull_request" => pull_request_actions(repo, action, payload),
+        "pull_request_review" if action == "submitted" => {
+            let (Some(number), Some(login)) = (
+                payload
+                    .pointer("/pull_request/number")
+                    .and_then(Value::as_u64),
+                text(payload, &["review", "user", "login"]),
+            ) else {
+                return Vec::new();
+            };
+            vec![GithubAction::Resolve {
+                login: Some(login.to_string()),
+                origin_ref: format!("github:pr:{repo}:{number}"),
+            }]
+        }
+        "workflow_run" if action == "completed" => workflow_actions(repo, payload),
+        _ => Vec::new(),
+    }
+}
+
+fn issue_actions(repo: &str, action: &str, payload: &Value) -> Vec<GithubAction> {
+    let Some(number) = payload.pointer("/issue/number").and_then(Value::as_u64) else {
+        return Vec::new();
+    };
+    let origin_ref = format!("github:issue:{repo}:{number}");
+    let assignee = text(payload, &["assignee", "login"]).map(str::to_string);
+    match action {
+        "assigned" | "reopened" => {
+            let logins = match (action, assignee) {
+                ("assigned", Some(login)) => vec![login],
+                _ => logins_at(payload, "/issue/assignees"),
+            };
+            let title = text(payload, &["issue", "title"]).unwrap_or("(untitled)");
+            logins
+                .into_iter()
+                .map(|login| GithubAction::Open {
+                    login,
+                    update: ExternalUpdate {
+                        origin_ref: origin_ref.clone(),
+                        source: SOURCE.to_string(),
+                        title: format!("Issue assigned: {title}"),
+                        details: Some(format!("{repo}#{number}")),
+                        url: text(payload, &["issue", "html_url"]).map(str::to_string),
+                        priority: "normal".to_string(),
+                    },
+                })
+                .collect()
+        }
+        "unassigned" => assignee
+            .map(|login| GithubAction::Resolve {
+                login: Some(login),
+                origin_ref,
+            })
+            .into_iter()
+            .collect(),
+        "closed" => vec![GithubAction::Resolve {
+            login: None,
+            origin_ref,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+fn pull_request_actions(repo: &str, action: &str, payload: &Value) -> Vec<GithubAction> {
+    let Some(number) = payload
+        .pointer("/pull_request/number")
+        .and_then(Value::as_u64)
+    else {
+        return Vec::new();
+    };
+    let origin_ref = format!("github:pr:{repo}:{number}");
+    // Team review requests have no individual login; they are skipped.
+    let reviewer = text(payload, &["requested_reviewer", "login"]).map(str::to_string);
+    match action {
+        "review_requested" => {
+            let Some(login) = reviewer else {
+                return Vec::new();
+            };
+            let title = text(payload, &["pull_request", "title"]).unwrap_or("(untitled)");
+            let author = text(payload, &["pull_request", "user", "login"]).unwrap_or("someone");
+            vec![GithubAction::Open {
+                login,
+                update: ExternalUpdate {
+                    origin_ref,
+                    source: SOURCE.to_string(),
+                    title: format!("Review requested: {title}"),
+                    details: Some(format!("{repo}#{number} by {author}")),
+                    url: text(payload, &["pull_request", "html_url"]).map(str::to_string),
+                    priority: "high".to_string(),
+                },
+            }]
+        }
+        "review_request_removed" => reviewer
+            .map(|login| GithubAction::Resolve {
+                login: Some(login),
+                origin_ref,
+            })
+            .into_iter()
+            .collect(),
+        "closed" => vec![GithubAction::Resolve {
+            login: None,
+            origin_ref,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+fn workflow_actions(repo: &str, payload: &Value) -> Vec<GithubAction> {
+    let Some(branch) = text(payload, &["workflow_run", "head_branch"]) else {
+        return Vec::new();
+    };
+    let origin_ref = format!("github:ci:{repo}:{branch}");
+    match text(payload, &["workflow_run", "conclusion"]).unwrap_or_default() {
+        "failure" | "timed_out" | "startup_failure" => {
+            let Some(login) = text(payload, &["workflow_run", "actor", "login"]) else {
+                return Vec::new();
+            };
+            let workflow = text(payload, &["workflow_run", "name"]).unwrap_or("CI");
+            let commit = text(payload, &["workflow_run", "head_sha"])
+                .map(|sha| sha.chars().take(7).collect::<String>())
+                .unwrap_or_default();
+            vec![GithubAction::Open {
+                login: login.to_string(),
+                update: ExternalUpdate {
+                    origin_ref,
+                    source: SOURCE.to_string(),
+                    title: format!("CI failed: {workflow} on {branch}"),
+                    details: Some(format!("{repo} at {commit}")),
+                    url: text(payload, &["workflow_run", "html_url"]).map(str::to_string),
+                    priority: "urgent".to_string(),
+                },
+            }]
+        }
+        "success" => vec![GithubAction::Resolve {
+            login: None,
+            origin_ref,
+        }],
+        _ => Vec::new(),
+    }
+}
+
+fn text<'a>(payload: &'a Value, path: &[&str]) -> Option<&'a str> {
+    path.iter()
+        .try_fold(payload, |value, key| value.get(*key))?
+        .as_str()
+}
+
+fn logins_at(payload: &Value, pointer: &str) -> Vec<String> {
+    payload
+        .pointer(pointer)
+        .and_then(Value::as_array)
+        .map(|users| {
+            users
+                .iter()
+                .filter_map(|user| user.get("login")?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn signature_matches_github_format() {
+        let body = br#"{"zen":"Keep it logically awesome."}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"It's a Secret").unwrap();
+        mac.update(body);
+        let header = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert!(verify_signature("It's a Secret", body, &header));
+        assert!(verify_signature(
+            "It's a Secret",
+            body,
+            &header.to_ascii_uppercase().replace("SHA256=", "sha256=")
+        ));
+        assert!(!verify_signature("wrong", body, &header));
+        assert!(!verify_signature("It's a Secret", b"{}", &header));
+        assert!(!verify_signature("It's a Secret", body, "sha1=abc"));
+    }
+
+    #[test]
+    fn maps_review_requests_assignments_and_ci() {
+        let repo = json!({"full_name": "acme/app"});
+        let review = actions(
+            "pull_request",
+            &json!({
+                "action": "review_requested",
+                "repository": repo,
+                "requested_reviewer": {"login": "Octocat"},
+                "pull_request": {"number": 123, "title": "Add cache", "user": {"login": "hubot"},
+                                 "html_url": "https://github.com/acme/app/pull/123"}
+            }),
+        );
+        let [GithubAction::Open { login, update }] = review.as_slice() else {
+            panic!("expected one open action, got {review:?}");
+        };
+        assert_eq!(login, "Octocat");
+        assert_eq!(update.origin_ref, "github:pr:acme/app:123");
+        assert_eq!(update.title, "Review requested: Add cache");
+        assert_eq!(update.priority, "high");
+
+        let submitted = actions(
+            "pull_request_review",
+            &json!({"action": "submitted", "repository": repo,
+                    "review": {"user": {"login": "octocat"}}, "pull_request": {"number": 123}}),
+        );
+        assert_eq!(submitted[0].origin_ref(), "github:pr:acme/app:123");
+
+        let assigned = actions(
+            "issues",
+            &json!({"action": "assigned", "repository": repo, "assignee": {"login": "octocat"},
+                    "issue": {"number": 45, "title": "Crash"}}),
+        );
+        assert_eq!(assigned[0].origin_ref(), "github:issue:acme/app:45");
+
+        let run = |conclusion: &str| {
+            actions(
+                "workflow_run",
+                &json!({"action": "completed", "repository": repo,
+                        "workflow_run": {"head_branch": "main", "conclusion": conclusion,
+                                         "name": "tests", "head_sha": "abcdef123456",
+                                         "actor": {"login": "octocat"}}}),
+            )
+        };
+        let failed = run("failure");
+        let [GithubAction::Open { update, .. }] = failed.as_slice() else {
+            panic!("expected an open action");
+        };
+        assert_eq!(update.title, "CI failed: tests on main");
+        assert_eq!(update.details.as_deref(), Some("acme/app at abcdef1"));
+        assert_eq!(
+            run("success"),
+            vec![GithubAction::Resolve {
+                login: None,
+                origin_ref: "github:ci:acme/app:main".to_string()
+            }]
+        );
+        assert!(run("cancelled").is_empty());
+        assert!(actions("star", &json!({"repository": repo})).is_empty());
+    }
+
+    #[test]
+    fn config_maps_logins_case_insensitively() {
+        let config =
+            GithubWebhookConfig::from_tools(Some(&json!({"settings": {"github_webhook": {
+                "users": {"OctoCat": "alice", "empty": " "}, "repos": ["Acme/App"]
+            }}})));
+        assert_eq!(config.user_for("octocat"), Some("alice"));
+        assert_eq!(config.user_for("empty"), None);
+        assert!(config.accepts_repo("acme/app"));
+        assert!(!config.accepts_repo("acme/other"));
+        assert_eq!(config.secret_name, DEFAULT_SECRET_NAME);
+    }
+}
@@ -0,0 +1,339 @@
+//! Inbox items that mirror work tracked somewhere else.
+//!
+//! Rows are keyed by `(user_id, origin_ref)`, so a webhook delivered twice
+//! updates the same item. The source decides when an item is resolved; the
+//! user can still move it through the inbox like any other item.
+
+pub mod github;
+pub mod zapier;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqliteAsyncConn, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::external_items;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const EXTERNAL_ITEMS_UP_SQL: &str =
+    include_str!("../../migrations/20260318_create_external_items/up.sql");
+
+pub const STATUS_OPEN: &str = "open";
+pub const STATUS_RESOLVED: &str = "resolved";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ExternalItem {
+    pub id: i32,
+    pub user_id: String,
+    pub origin_ref: String,
+    pub source: String,
+    pub title: String,
+    pub details: Option<String>,
+    pub url: Option<String>,
+    pub priority: String,
+    pub status: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// What a source reports about an item; applied with [`ExternalItemStore::upsert`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalUpdate {
+    pub origin_ref: String,
+    pub source: String,
+    pub title: String,
+    pub details: Option<String>,
+    pub url: Option<String>,
+    pub priority: String,
+}
+
+#[derive(Queryable)]
+struct ExternalItemRow {
+    id: i32,
+    user_id: String,
+    origin_ref: String,
+    source: String,
+    title: String,
+    details: Option<String>,
+    url: Option<String>,
+    priority: String,
+    status: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = external_items)]
+struct NewExternalItem<'a> {
+    user_id: &'a str,
+    origin_ref: &'a str,
+    source: &'a str,
+    title: &'a str,
+    details: Option<&'a str>,
+    url: Option<&'a str>,
+    priority: &'a str,
+    status: &'a str,
+    created_at: i64,
+    updated_at: i64,
+}
+
+pub struct ExternalItemStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl ExternalItemStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_external_items_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Creates the item or refreshes it, reopening it if it was resolved.
+    /// Returns the item and whether it is newly open.
+    pub async fn upsert(
+        &self,
+        user_id: &str,
+        update: &ExternalUpdate,
+    ) -> Result<(ExternalItem, bool)> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let existing = find_row(&mut conn, user_id, &update.origin_ref).await?;
+        let opened = match &existing {
+            Some(row) => {
+                diesel::update(external_items::table.filter(external_items::id.eq(row.id)))
+                    .set((
+                        external_items::title.eq(&update.title),
+                        external_items::details.eq(update.details.as_deref()),
+                        external_items::url.eq(update.url.as_deref()),
+                        external_items::priority.eq(&update.priority),
+                        external_items::status.eq(STATUS_OPEN),
+                        external_items::updated_at.eq(now),
+                    ))
+                    .execute(&mut conn)
+                    .await
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                row.status != STATUS_OPEN
+            }
+            None => {
+                diesel::insert_into(external_items::table)
+                    .values(&NewExternalItem {
+                        user_id,
+                        origin_ref: &update.origin_ref,
+                        source: &update.source,
+                        title: &update.title,
+                        details: update.details.as_deref(),
+                        url: update.url.as_deref(),
+                        priority: &update.priority,
+                        status: STATUS_OPEN,
+                        created_at: now,
+                        updated_at: now,
+                    })
+                    .execute(&mut conn)
+                    .await
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                true
+            }
+        };
+        let row = find_row(&mut conn, user_id, &update.origin_ref)
+            .await?
+            .ok_or_else(|| ButterflyBotError::Runtime("External item vanished".to_string()))?;
+        Ok((map_row(row), opened))
+    }
+
+    /// Marks an open item resolved. Returns `false` when there was nothing
+    /// open under `origin_ref`.
+    pub async fn resolve(&self, user_id: &str, origin_ref: &str) -> Result<bool> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            external_items::table
+                .filter(external_items::user_id.eq(user_id))
+                .filter(external_items::origin_ref.eq(origin_ref))
+                .filter(external_items::status.eq(STATUS_OPEN)),
+        )
+        .set((
+            external_items::status.eq(STATUS_RESOLVED),
+            external_items::updated_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Users with an open item under `origin_ref`.
+    pub async fn open_users(&self, origin_ref: &str) -> Result<Vec<String>> {
+        let mut conn = self.conn().await?;
+        external_items::table
+            .filter(external_items::origin_ref.eq(origin_ref))
+            .filter(external_items::status.eq(STATUS_OPEN))
+            .select(external_items::user_id)
+            .load::<String>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Most recently updated first.
+    pub async fn list(&self, user_id: &str, limit: usize) -> Result<Vec<ExternalItem>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ExternalItemRow> = external_items::table
+            .filter(external_items::user_id.eq(user_id))
+            .order(external_items::updated_at.desc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(external_items::table.filter(external_items::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+async fn find_row(
+    conn: &mut SqliteAsyncConn,
+    user_id: &str,
+    origin_ref: &str,
+) -> Result<Option<ExternalItemRow>> {
+    external_items::table
+        .filter(external_items::user_id.eq(user_id))
+        .filter(external_items::origin_ref.eq(origin_ref))
+        .first::<ExternalItemRow>(conn)
+        .await
+        .optional()
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+}
+
+fn map_row(row: ExternalItemRow) -> ExternalItem {
+    ExternalItem {
+        id: row.id,
+        user_id: row.user_id,
+        origin_ref: row.origin_ref,
+        source: row.source,
+        title: row.title,
+        details: row.details,
+        url: row.url,
+        priority: row.priority,
+        status: row.status,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_external_items_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM external_items LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    EXTERNAL_ITEMS_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(title: &str) -> ExternalUpdate {
+        ExternalUpdate {
+            origin_ref: "github:pr:acme/app:7".to_string(),
+            source: "github".to_string(),
+            title: title.to_string(),
+            details: None,
+            url: Some("https://github.com/acme/app/pull/7".to_string()),
+            priority: "high".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn upsert_is_idempotent_and_reopens_resolved_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("external.db");
+        let store = ExternalItemStore::new(path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        let (item, opened) = store.upsert("u", &update("Review")).await.unwrap();
+        assert!(opened);
+        let (again, opened) = store.upsert("u", &update("Review v2")).await.unwrap();
+        assert!(!opened);
+        assert_eq!(again.id, item.id);
+        assert_eq!(again.title, "Review v2");
+        assert_eq!(store.open_users(&item.origin_ref).await.unwrap(), vec!["u"]);
+
+        assert!(store.resolve("u", &item.origin_ref).await.unwrap());
+        assert!(!store.resolve("u", &item.origin_ref).await.unwrap());
+        assert!(store.open_users(&item.origin_ref).await.unwrap().is_empty());
+
+        let (_, opened) = store.upsert("u", &update("Review v3")).await.unwrap();
+        assert!(opened);
+        assert_eq!(store.list("u", 10).await.unwrap().len(), 1);
+        assert_eq!(store.clear_user("u").await.unwrap(), 1);
+    }
+}
@@ -0,0 +1,15 @@
+diesel::table! {
+    external_items (id) {
+        id -> Integer,
+        user_id -> Text,
+        origin_ref -> Text,
+        source -> Text,
+        title -> Text,
+        details -> Nullable<Text>,
+        url -> Nullable<Text>,
+        priority -> Text,
+        status -> Text,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
@@ -0,0 +1,174 @@
+//! Inbound Zapier webhooks: a Zap's "Webhooks by Zapier" POST step opens or
+//! resolves an inbox item.
+//!
+//! Enabled per Zap in `tools.zapier.zaps` with an `inbound` block; `url` is
+//! only needed for Zaps the bot triggers itself:
+//!
+//! ```json
+//! {"name": "support_ticket",
+//!  "inbound": {"user": "alice", "priority": "high"}}
+//! ```
+//!
+//! Deliveries go to `POST /webhooks/zapier/<name>` and must carry the vault
+//! secret `zapier_hook_<name>_secret` (or `secret_name`) as a bearer token or
+//! in `X-Butterfly-Token`. The JSON body needs a `title`; `details`, `url`,
+//! `priority` and `id` are optional. Origin refs look like
+//! `zapier:support_ticket:<id>`, so a delivery with a known `id` refreshes
+//! its item, and `"status": "resolved"` (or `done`, `closed`) resolves it.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::ExternalUpdate;
+
+pub const SOURCE: &str = "zapier";
+pub const TOKEN_HEADER: &str = "x-butterfly-token";
+const PRIORITIES: &[&str] = &["low", "normal", "high", "urgent"];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ZapierInbound {
+    pub name: String,
+    /// Butterfly user whose inbox receives the items.
+    pub user: String,
+    pub priority: String,
+    pub secret_name: String,
+}
+
+impl ZapierInbound {
+    /// Zaps in `tools.zapier.zaps` that accept deliveries.
+    pub fn from_tools(tools: Option<&Value>) -> Vec<Self> {
+        tools
+            .and_then(|tools| tools.get("zapier"))
+            .and_then(|zapier| zapier.get("zaps"))
+            .and_then(Value::as_array)
+            .map(|zaps| zaps.iter().filter_map(Self::from_value).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn find(tools: Option<&Value>, name: &str) -> Option<Self> {
+        Self::from_tools(tools)
+            .into_iter()
+            .find(|inbound| inbound.name == name)
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let text = |value: &Value, key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        let name = text(value, "name")?;
+        let inbound = value.get("inbound")?;
+        let priority = text(inbound, "priority")
+            .map(|priority| priority.to_ascii_lowercase())
+            .filter(|priority| PRIORITIES.contains(&priority.as_str()))
+            .unwrap_or_else(|| "normal".to_string());
+        Some(Self {
+            user: text(inbound, "user")?,
+            priority,
+            secret_name: text(inbound, "secret_name")
+                .unwrap_or_else(|| format!("zapier_hook_{name}_secret")),
+            name,
+        })
+    }
+}
+
+/// Whether a presented token, surrounding whitespace aside, is the stored
+/// secret.
+pub fn token_matches(secret: &str, presented: &str) -> bool {
+    crate::security::tokens::tokens_match(secret, presented.trim())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ZapierAction {
+    Open(ExternalUpdate),
+    Resolve { origin_ref: String },
+}
+
+/// What a delivery means for the inbox, or why it cannot be used.
+pub fn action(
+    inbound: &ZapierInbound,
+    payload: &Value,
+    body: &[u8],
+) -> std::result::Result<ZapierAction, String> {
+    let text = |key: &str| {
+        payload.get(key).and_then(|value| match value {
+            Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        })
+    };
+    let id = text("id");
+    let resolves = text("status").is_some_and(|status| {
+        matches!(
+            status.to_ascii_lowercase().as_str(),
+            "resolved" | "done" | "closed"
+        )
+    });
+    if resolves {
+        let id = id.ok_or_else(|| "resolving needs the item's id".to_string())?;
+        return Ok(ZapierAction::Resolve {
+            origin_ref: format!("{SOURCE}:{}:{id}", inbound.name),
+        });
+    }
+
+    let title = text("title").ok_or_else(|| "payload needs a title".to_string())?;
+    // Without an id every delivery is its own item; the body hash keeps a
+    // retried delivery from opening a second one.
+    let id = id.unwrap_or_else(|| hex::encode(Sha256::digest(body))[..16].to_string());
+    let priority = text("priority")
+        .map(|priority| priority.to_ascii_lowercase())
+        .filter(|priority| PRIORITIES.contains(&priority.as_str()))
+        .unwrap_or_else(|| inbound.priority.clone());
+    Ok(ZapierAction::Open(ExternalUpdate {
+        origin_ref: format!("{SOURCE}:{}:{id}", inbound.name),
+        source: SOURCE.to_string(),
+        title,
+        details: text("details"),
+        url: text("url"),
+        priority,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn inbound_zaps_map_deliveries_to_items() {
+        let tools = json!({"zapier": {"zaps": [
+            {"name": "tickets", "inbound": {"user": "alice", "priority": "HIGH"}},
+            {"name": "outbound_only", "url": "https://hooks.zapier.com/hooks/catch/1/a"}
+        ]}});
+        let inbound = ZapierInbound::from_tools(Some(&tools));
+        assert_eq!(inbound.len(), 1);
+        let tickets = ZapierInbound::find(Some(&tools), "tickets").expect("inbound zap");
+        assert_eq!(tickets.priority, "high");
+        assert_eq!(tickets.secret_name, "zapier_hook_tickets_secret");
+
+        let opened = action(
+            &tickets,
+            &json!({"id": 42, "title": "Refund request", "url": "https://desk/42"}),
+            b"{}",
+        );
+        let Ok(ZapierAction::Open(update)) = opened else {
+            panic!("expected an open action, got {opened:?}");
+        };
+        assert_eq!(update.origin_ref, "zapier:tickets:42");
+        assert_eq!(update.priority, "high");
+        assert_eq!(
+            action(&tickets, &json!({"id": "42", "status": "Closed"}), b"{}"),
+            Ok(ZapierAction::Resolve {
+                origin_ref: "zapier:tickets:42".to_string()
+            })
+        );
+        assert!(action(&tickets, &json!({"details": "no title"}), b"{}").is_err());
+
+        assert!(token_matches("s3cret-token", " s3cret-token"));
+        assert!(!token_matches("s3cret-token", "s3cret-tokeX"));
+    }
+}
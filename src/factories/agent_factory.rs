@@ -65,11 +65,15 @@ use crate::brain::plugins::zero_cost_reasoning::ZeroCostReasoningBrain;
 use crate::config::{Config, MarkdownSource};
 use crate::domains::agent::AIAgent;
 use crate::error::{ButterflyBotError, Result};
+use crate::guardrails::data_scope::DataScopePolicy;
 use crate::interfaces::plugins::Tool;
+use crate::matrix::MatrixMirror;
 use crate::providers::memory::InMemoryMemoryProvider;
 use crate::providers::openai::OpenAiProvider;
 use crate::providers::sqlite::{SqliteMemoryProvider, SqliteMemoryProviderConfig};
-use crate::reminders::{default_reminder_db_path, resolve_reminder_db_path, ReminderStore};
+use crate::reminders::{
+    default_reminder_db_path, reminders_complete_on_fire, resolve_reminder_db_path, ReminderStore,
+};
 use crate::services::agent::{AgentService, UiEvent};
 use crate::services::query::QueryService;
 use crate::tools::coding::CodingTool;
@@ -100,41 +104,35 @@ impl ButterflyBotFactory {
         let memory_config = config.memory.clone();
         let config_value =
             serde_json::to_value(&config).map_err(|e| ButterflyBotError::Config(e.to_string()))?;
-        let (api_key, model, base_url) = if let Some(openai) = config.openai.clone() {
-            let api_key = openai
-                .api_key
-                .filter(|key| !key.trim().is_empty())
-                .ok_or_else(|| ButterflyBotError::Config("Missing OpenAI API key".to_string()))?;
-            (api_key, openai.model, openai.base_url)
-        } else {
-            return Err(ButterflyBotError::Config(
-                "Missing openai configuration".to_string(),
-            ));
-        };
+        let llm = crate::llm::build_provider_with_events(&config, ui_event_tx.clone())?;
+        let primary_credentials = crate::llm::openai_compatible_credentials(&config)?;
 
-        let (memory_api_key, memory_model, memory_base_url) = memory_config
+        // Memory embeddings, reranking and summaries stay on an
+        // OpenAI-compatible endpoint whichever chat backend is selected.
+        let memory_credentials = memory_config
             .as_ref()
             .and_then(|mem| mem.openai.clone())
             .map(|openai| {
                 let api_key = openai
                     .api_key
                     .filter(|key| !key.trim().is_empty())
-                    .unwrap_or_else(|| api_key.clone());
+                    .or_else(|| primary_credentials.as_ref().map(|(key, _, _)| key.clone()))
+                    .ok_or_else(|| {
+                        ButterflyBotError::Config("Missing memory OpenAI API key".to_string())
+                    })?;
                 Ok::<_, ButterflyBotError>((api_key, openai.model, openai.base_url))
             })
             .transpose()?
-            .unwrap_or((api_key.clone(), model.clone(), base_url.clone()));
-
-        let llm = Arc::new(OpenAiProvider::new(
-            api_key.clone(),
-            model,
-            base_url.clone(),
-        ));
-        let llm_for_memory = Arc::new(OpenAiProvider::new(
-            memory_api_key.clone(),
-            memory_model.clone(),
-            memory_base_url.clone(),
-        ));
+            .or(primary_credentials);
+        let llm_for_memory = memory_credentials
+            .as_ref()
+            .map(|(api_key, model, base_url)| {
+                Arc::new(OpenAiProvider::new(
+                    api_key.clone(),
+                    model.clone(),
+                    base_url.clone(),
+                )) as Arc<dyn crate::interfaces::providers::LlmProvider>
+            });
 
         let context_source = config.prompt_source.clone();
         tracing::info!(
@@ -333,6 +331,7 @@ impl ButterflyBotFactory {
             ui_event_tx,
         );
 
+        crate::timezones::configure(&config_value);
         let tool_registry = agent_service.tool_registry.clone();
         tool_registry
             .configure_all_tools(config_value.clone())
@@ -427,34 +426,35 @@ impl ButterflyBotFactory {
         let memory_provider: Arc<dyn crate::interfaces::providers::MemoryProvider> =
             if let Some(memory) = memory_config {
                 if memory.enabled.unwrap_or(true) {
+                    let retention =
+                        crate::providers::retention::RetentionPolicy::from_config(&memory);
                     let sqlite_path = memory
                         .sqlite_path
                         .unwrap_or_else(crate::runtime_paths::default_db_path);
-                    let reranker = memory.rerank_model.as_ref().map(|rerank_model| {
-                        Arc::new(OpenAiProvider::new(
-                            memory_api_key.clone(),
-                            Some(rerank_model.clone()),
-                            memory_base_url.clone(),
-                        ))
-                            as Arc<dyn crate::interfaces::providers::LlmProvider>
-                    });
-                    let summarizer = memory.summary_model.as_ref().map(|summary_model| {
-                        Arc::new(OpenAiProvider::new(
-                            memory_api_key.clone(),
-                            Some(summary_model.clone()),
-                            memory_base_url.clone(),
-                        ))
-                            as Arc<dyn crate::interfaces::providers::LlmProvider>
-                    });
+                    let memory_model_provider = |model: &String| {
+                        memory_credentials.as_ref().map(|(api_key, _, base_url)| {
+                            Arc::new(OpenAiProvider::new(
+                                api_key.clone(),
+                                Some(model.clone()),
+                                base_url.clone(),
+                            ))
+                                as Arc<dyn crate::interfaces::providers::LlmProvider>
+                        })
+                    };
+                    let reranker = memory.rerank_model.as_ref().and_then(memory_model_provider);
+                    let summarizer = memory
+                        .summary_model
+                        .as_ref()
+                        .and_then(memory_model_provider);
                     let mut memory_provider_config = SqliteMemoryProviderConfig::new(sqlite_path);
-                    memory_provider_config.embedder = Some(llm_for_memory.clone());
+                    memory_provider_config.embedder = llm_for_memory.clone();
                     memory_provider_config.embedding_model = memory.embedding_model.clone();
                     memory_provider_config.reranker = reranker;
                     memory_provider_config.summarizer = summarizer;
                     memory_provider_config.context_embed_enabled =
                         memory.context_embed_enabled.unwrap_or(false);
                     memory_provider_config.summary_threshold = memory.summary_threshold;
-                    memory_provider_config.retention_days = memory.retention_days;
+                    memory_provider_config.retention = retention;
                     Arc::new(SqliteMemoryProvider::new(memory_provider_config).await?)
                         as Arc<dyn crate::interfaces::providers::MemoryProvider>
                 } else {
@@ -469,16 +469,24 @@ impl ButterflyBotFactory {
         let reminder_store = if registered_tools.iter().any(|name| name == "reminders") {
             let path =
                 resolve_reminder_db_path(&config_value).unwrap_or_else(default_reminder_db_path);
-            Some(Arc::new(ReminderStore::new(path).await?))
+            Some(Arc::new(
+                ReminderStore::new(path)
+                    .await?
+                    .with_complete_on_fire(reminders_complete_on_fire(&config_value)),
+            ))
         } else {
             None
         };
 
-        Ok(QueryService::new(
-            agent_service,
-            Some(memory_provider),
-            reminder_store,
-        ))
+        let data_scope = DataScopePolicy::from_tools(config.tools.as_ref())
+            .map(|policy| policy.scope_for(&crate::llm::chat_backends(&config)))
+            .unwrap_or_default();
+
+        Ok(
+            QueryService::new(agent_service, Some(memory_provider), reminder_store)
+                .with_data_scope(data_scope)
+                .with_matrix_mirror(MatrixMirror::from_tools(config.tools.as_ref())),
+        )
     }
 }
 
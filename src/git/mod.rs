@@ -0,0 +1,442 @@
+//! Git operations for coding workflows.
+//!
+//! The `git.*` capabilities work on repositories whose root is one of the
+//! coding tool's `filesystem.allow` workspaces, through the `git` binary.
+//! Repository hooks never run. Commits are authored by the bot and refused
+//! on protected branches, so generated work lands on a working branch for a
+//! human to review. `git.push` always waits for human approval (see
+//! [`crate::guardrails::confirmation`]).
+
+use std::path::Path;
+use std::process::Stdio;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::process::Command;
+
+use crate::error::ButterflyBotError;
+use crate::patch::Workspace;
+use crate::Result;
+
+const DEFAULT_AUTHOR_NAME: &str = "Butterfly Bot";
+const DEFAULT_AUTHOR_EMAIL: &str = "butterfly-bot@localhost";
+const DEFAULT_PROTECTED_BRANCHES: &[&str] = &["main", "master"];
+const DEFAULT_MAX_DIFF_BYTES: usize = 256 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GitPolicy {
+    pub author_name: String,
+    pub author_email: String,
+    /// Branches the bot may not commit to or push.
+    pub protected_branches: Vec<String>,
+    pub max_diff_bytes: usize,
+}
+
+impl Default for GitPolicy {
+    fn default() -> Self {
+        Self {
+            author_name: DEFAULT_AUTHOR_NAME.to_string(),
+            author_email: DEFAULT_AUTHOR_EMAIL.to_string(),
+            protected_branches: DEFAULT_PROTECTED_BRANCHES
+                .iter()
+                .map(|branch| branch.to_string())
+                .collect(),
+            max_diff_bytes: DEFAULT_MAX_DIFF_BYTES,
+        }
+    }
+}
+
+impl GitPolicy {
+    /// Reads the `git` section of the coding tool config.
+    pub fn from_tool_config(tool_cfg: Option<&Value>) -> Self {
+        let mut policy = Self::default();
+        let Some(section) = tool_cfg.and_then(|cfg| cfg.get("git")) else {
+            return policy;
+        };
+        let text = |key: &str| {
+            section
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        if let Some(name) = text("author_name") {
+            policy.author_name = name;
+        }
+        if let Some(email) = text("author_email") {
+            policy.author_email = email;
+        }
+        if let Some(branches) = section.get("protected_branches").and_then(Value::as_array) {
+            policy.protected_branches = branches
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|branch| !branch.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(bytes) = section.get("max_diff_bytes").and_then(Value::as_u64) {
+            policy.max_diff_bytes = bytes as usize;
+        }
+        policy
+    }
+
+    pub fn is_protected(&self, branch: &str) -> bool {
+        self.protected_branches.iter().any(|known| known == branch)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct StatusEntry {
+    pub path: String,
+    /// Porcelain status letters for the index and the worktree.
+    pub index: String,
+    pub worktree: String,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct GitStatus {
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub clean: bool,
+    pub entries: Vec<StatusEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct GitDiff {
+    pub diff: String,
+    pub truncated: bool,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct GitCommit {
+    pub sha: String,
+    pub branch: String,
+    pub author: String,
+    pub files: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct GitBranches {
+    pub current: Option<String>,
+    pub branches: Vec<String>,
+}
+
+/// A git repository rooted at an allowlisted workspace.
+#[derive(Clone, Debug)]
+pub struct Repository {
+    workspace: Workspace,
+}
+
+impl Repository {
+    pub async fn open(requested: Option<&str>, allow: &[String]) -> Result<Self> {
+        let repo = Self {
+            workspace: Workspace::open(requested, allow)?,
+        };
+        let toplevel = repo.git(&["rev-parse", "--show-toplevel"]).await?;
+        let toplevel = std::fs::canonicalize(toplevel.trim())
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        if toplevel != repo.root() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Workspace {} is not the root of a git repository",
+                repo.root().display()
+            )));
+        }
+        Ok(repo)
+    }
+
+    pub fn root(&self) -> &Path {
+        self.workspace.root()
+    }
+
+    pub async fn status(&self) -> Result<GitStatus> {
+        let output = self
+            .git(&[
+                "status",
+                "--porcelain=v1",
+                "--branch",
+                "--untracked-files=all",
+            ])
+            .await?;
+        Ok(parse_status(&output))
+    }
+
+    /// Unstaged changes, or staged ones with `staged`, optionally limited
+    /// to one path.
+    pub async fn diff(
+        &self,
+        staged: bool,
+        path: Option<&str>,
+        policy: &GitPolicy,
+    ) -> Result<GitDiff> {
+        let mut args = vec!["diff", "--no-ext-diff"];
+        if staged {
+            args.push("--cached");
+        }
+        if let Some(path) = path {
+            self.workspace.resolve(path)?;
+            args.extend(["--", path]);
+        }
+        let mut diff = self.git(&args).await?;
+        let truncated = diff.len() > policy.max_diff_bytes;
+        if truncated {
+            let mut end = policy.max_diff_bytes;
+            while !diff.is_char_boundary(end) {
+                end -= 1;
+            }
+            diff.truncate(end);
+        }
+        Ok(GitDiff { diff, truncated })
+    }
+
+    /// Stages `paths` (everything when empty) and commits them as the bot.
+    pub async fn commit(
+        &self,
+        message: &str,
+        paths: &[String],
+        policy: &GitPolicy,
+    ) -> Result<GitCommit> {
+        let message = message.trim();
+        if message.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "Missing commit message".to_string(),
+            ));
+        }
+        let branch = self.current_branch().await?.ok_or_else(|| {
+            ButterflyBotError::Runtime("Cannot commit on a detached HEAD".to_string())
+        })?;
+        if policy.is_protected(&branch) {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Branch {branch} is protected; create a working branch with git.branch first"
+            )));
+        }
+
+        if paths.is_empty() {
+            self.git(&["add", "--all"]).await?;
+        } else {
+            for path in paths {
+                self.workspace.resolve(path)?;
+            }
+            let mut args = vec!["add", "--all", "--"];
+            args.extend(paths.iter().map(String::as_str));
+            self.git(&args).await?;
+        }
+        let staged = self.git(&["diff", "--cached", "--name-only"]).await?;
+        let files = staged
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        if files.is_empty() {
+            return Err(ButterflyBotError::Runtime("Nothing to commit".to_string()));
+        }
+
+        let author = format!("{} <{}>", policy.author_name, policy.author_email);
+        let message = format!("{message}\n\nCommitted-by: {author}");
+        let identity = [
+            ("GIT_AUTHOR_NAME", policy.author_name.as_str()),
+            ("GIT_AUTHOR_EMAIL", policy.author_email.as_str()),
+            ("GIT_COMMITTER_NAME", policy.author_name.as_str()),
+            ("GIT_COMMITTER_EMAIL", policy.author_email.as_str()),
+        ];
+        self.git_with_env(
+            &["commit", "--no-verify", "--quiet", "-m", &message],
+            &identity,
+        )
+        .await?;
+        let sha = self.git(&["rev-parse", "HEAD"]).await?.trim().to_string();
+        Ok(GitCommit {
+            sha,
+            branch,
+            author,
+            files,
+        })
+    }
+
+    pub async fn branches(&self) -> Result<GitBranches> {
+        let output = self
+            .git(&["branch", "--list", "--format=%(refname:short)"])
+            .await?;
+        Ok(GitBranches {
+            current: self.current_branch().await?,
+            branches: output
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+        })
+    }
+
+    /// Creates `name` from the current commit, or switches to it when it
+    /// already exists.
+    pub async fn switch_branch(&self, name: &str, create: bool) -> Result<GitBranches> {
+        self.git(&["check-ref-format", "--branch", name]).await?;
+        if create {
+            self.git(&["switch", "--create", name]).await?;
+        } else {
+            self.git(&["switch", name]).await?;
+        }
+        self.branches().await
+    }
+
+    /// Pushes a working branch. Only reachable once a human approved the
+    /// `git.push` call.
+    pub async fn push(&self, remote: &str, branch: &str, policy: &GitPolicy) -> Result<String> {
+        if policy.is_protected(branch) {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Branch {branch} is protected and cannot be pushed by the bot"
+            )));
+        }
+        let remotes = self.git(&["remote"]).await?;
+        if !remotes.lines().any(|known| known == remote) {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Remote {remote} is not configured in this repository"
+            )));
+        }
+        self.git(&["check-ref-format", "--branch", branch]).await?;
+        self.git(&["push", "--porcelain", remote, &format!("{branch}:{branch}")])
+            .await
+    }
+
+    async fn current_branch(&self) -> Result<Option<String>> {
+        let output = self
+            .git(&["symbolic-ref", "--quiet", "--short", "HEAD"])
+            .await;
+        Ok(output
+            .ok()
+            .map(|branch| branch.trim().to_string())
+            .filter(|branch| !branch.is_empty()))
+    }
+
+    async fn git(&self, args: &[&str]) -> Result<String> {
+        self.git_with_env(args, &[]).await
+    }
+
+    async fn git_with_env(&self, args: &[&str], env: &[(&str, &str)]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(self.root())
+            .args(["-c", "core.hooksPath=/dev/null", "-c", "color.ui=false"])
+            // Repository config must not be able to run commands.
+            .args(["-c", "core.fsmonitor=false"])
+            .args(["-c", "protocol.ext.allow=never"])
+            .args(["-c", "core.sshCommand=ssh"])
+            .args(args)
+            .envs(env.iter().copied())
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(format!("Failed to run git: {e}")))?;
+        if !output.status.success() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus {
+        branch: None,
+        upstream: None,
+        clean: true,
+        entries: Vec::new(),
+    };
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("## ") {
+            let header = header.strip_prefix("No commits yet on ").unwrap_or(header);
+            let head = header.split(' ').next().unwrap_or_default();
+            let (branch, upstream) = match head.split_once("...") {
+                Some((branch, upstream)) => (branch, Some(upstream.to_string())),
+                None => (head, None),
+            };
+            if !branch.starts_with("HEAD") {
+                status.branch = Some(branch.to_string());
+            }
+            status.upstream = upstream;
+        } else if line.len() > 3 {
+            status.entries.push(StatusEntry {
+                index: line[..1].to_string(),
+                worktree: line[1..2].to_string(),
+                path: line[3..].to_string(),
+            });
+        }
+    }
+    status.clean = status.entries.is_empty();
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{parse_status, GitPolicy, Repository};
+
+    #[test]
+    fn parses_porcelain_status() {
+        let status =
+            parse_status("## bot/work...origin/bot/work [ahead 1]\n M src/lib.rs\n?? notes.md\n");
+        assert_eq!(status.branch.as_deref(), Some("bot/work"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/bot/work"));
+        assert!(!status.clean);
+        assert_eq!(status.entries[0].worktree, "M");
+        assert_eq!(status.entries[1].path, "notes.md");
+        assert!(parse_status("## No commits yet on main\n").clean);
+    }
+
+    #[tokio::test]
+    async fn commits_only_on_working_branches_as_the_bot() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().to_path_buf();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&root)
+                .args([
+                    "-c",
+                    "user.name=Tester",
+                    "-c",
+                    "user.email=tester@localhost",
+                ])
+                .args(args)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "--quiet", "--initial-branch=main"]);
+        fs::write(root.join("README.md"), "hello\n").unwrap();
+        git(&["add", "README.md"]);
+        git(&["commit", "--quiet", "-m", "Initial commit"]);
+
+        let allow = vec![root.to_string_lossy().to_string()];
+        let repo = Repository::open(None, &allow).await.unwrap();
+        let policy = GitPolicy::default();
+
+        fs::write(root.join("lib.rs"), "fn main() {}\n").unwrap();
+        assert!(repo.commit("Add lib", &[], &policy).await.is_err());
+
+        let branches = repo.switch_branch("bot/lib", true).await.unwrap();
+        assert_eq!(branches.current.as_deref(), Some("bot/lib"));
+        assert_eq!(repo.status().await.unwrap().entries.len(), 1);
+
+        let commit = repo.commit("Add lib", &[], &policy).await.unwrap();
+        assert_eq!(commit.branch, "bot/lib");
+        assert_eq!(commit.files, vec!["lib.rs".to_string()]);
+        assert_eq!(commit.author, "Butterfly Bot <butterfly-bot@localhost>");
+        assert!(repo.status().await.unwrap().clean);
+
+        fs::write(root.join("lib.rs"), "fn main() { run(); }\n").unwrap();
+        let diff = repo.diff(false, Some("lib.rs"), &policy).await.unwrap();
+        assert!(diff.diff.contains("+fn main() { run(); }"));
+        assert!(repo.diff(false, Some("../x"), &policy).await.is_err());
+        assert!(repo.push("origin", "main", &policy).await.is_err());
+        assert!(repo.push("origin", "bot/lib", &policy).await.is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use serde_json::Value;
+
+use crate::approvals::{ChainStep, TimeoutAction};
+
+/// Capabilities that wait for a human whatever the config says.
+pub const ALWAYS_CONFIRM: &[&str] = &["git.push"];
+
+/// Capabilities the agent may request but not run until a human approves.
+///
+/// Configured under `tools.settings.confirmation.require` as a list of
+/// capability patterns, where `*` matches any run of characters:
+/// `["solana.transfer", "kv.sqlite.*.clear"]`. Empty by default, so apart
+/// from [`ALWAYS_CONFIRM`] nothing is held back unless the operator opts in.
+/// Calls matching one of `confirmation.chains` wait on an [`ApprovalChain`]
+/// instead.
+#[derive(Clone, Debug, Default)]
+pub struct ConfirmationPolicy {
+    pub require: Vec<String>,
+    pub chains: Vec<ApprovalChain>,
+}
+
+/// Several approvals in a row for high-risk calls:
+///
+/// ```json
+/// {"capability": "solana.transfer", "arg": "amount_sol", "over": 1.0,
+///  "steps": [["self"], ["bob", "carol"]],
+///  "timeout_minutes": 120, "on_timeout": "reject"}
+/// ```
+///
+/// Each step lists who may approve it, `self` being whoever made the call.
+/// With `arg` and `over` the chain only covers calls where that argument
+/// exceeds the threshold; a call where it is missing or not a number is
+/// covered too, so a renamed argument can't slip past.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ApprovalChain {
+    pub capability: String,
+    pub arg: Option<String>,
+    pub over: Option<f64>,
+    pub steps: Vec<Vec<String>>,
+    /// Per step; no timeout when unset.
+    pub timeout_minutes: Option<u64>,
+    pub on_timeout: TimeoutAction,
+}
+
+impl ApprovalChain {
+    fn from_value(value: &Value) -> Option<Self> {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        let steps: Vec<Vec<String>> = value
+            .get("steps")?
+            .as_array()?
+            .iter()
+            .filter_map(|step| {
+                let approvers: Vec<String> = step
+                    .as_array()?
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|approver| !approver.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                (!approvers.is_empty()).then_some(approvers)
+            })
+            .collect();
+        if steps.is_empty() {
+            return None;
+        }
+        Some(Self {
+            capability: text("capability")?,
+            arg: text("arg"),
+            over: value.get("over").and_then(Value::as_f64),
+            steps,
+            timeout_minutes: value
+                .get("timeout_minutes")
+                .and_then(Value::as_u64)
+                .filter(|minutes| *minutes > 0),
+            on_timeout: text("on_timeout")
+                .and_then(|action| TimeoutAction::parse(&action))
+                .unwrap_or_default(),
+        })
+    }
+
+    pub fn applies_to(&self, capability: &str, args: &Value) -> bool {
+        if !pattern_matches(&self.capability, capability) {
+            return false;
+        }
+        let (Some(arg), Some(over)) = (&self.arg, self.over) else {
+            return true;
+        };
+        let amount = args.get(arg).and_then(|value| match value {
+            Value::String(text) => text.trim().parse::<f64>().ok(),
+            value => value.as_f64(),
+        });
+        amount.is_none_or(|amount| amount > over)
+    }
+
+    /// The chain's steps with `self` resolved to `requester`.
+    pub fn steps_for(&self, requester: &str) -> Vec<ChainStep> {
+        self.steps
+            .iter()
+            .map(|approvers| {
+                let mut resolved: Vec<String> = Vec::new();
+                for approver in approvers {
+                    let approver = if approver == "self" {
+                        requester
+                    } else {
+                        approver.as_str()
+                    };
+                    if !resolved.iter().any(|known| known == approver) {
+                        resolved.push(approver.to_string());
+                    }
+                }
+                ChainStep {
+                    approvers: resolved,
+                    timeout_secs: self.timeout_minutes.map(|minutes| minutes as i64 * 60),
+                    on_timeout: self.on_timeout,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ConfirmationPolicy {
+    pub fn from_root_config(config: &serde_json::Value) -> Self {
+        let section = config
+            .get("tools")
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("confirmation"));
+        let require = section
+            .and_then(|confirmation| confirmation.get("require"))
+            .and_then(|value| value.as_array())
+            .map(|list| {
+                list.iter()
+                    .filter_map(|value| value.as_str())
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let chains = section
+            .and_then(|confirmation| confirmation.get("chains"))
+            .and_then(|value| value.as_array())
+            .map(|list| list.iter().filter_map(ApprovalChain::from_value).collect())
+            .unwrap_or_default();
+        Self { require, chains }
+    }
+
+    pub fn requires_confirmation(&self, capability: &str) -> bool {
+        ALWAYS_CONFIRM.contains(&capability)
+            || self
+                .require
+                .iter()
+                .any(|pattern| pattern_matches(pattern, capability))
+    }
+
+    /// The first chain covering this call, if any.
+    pub fn chain_for(&self, capability: &str, args: &Value) -> Option<&ApprovalChain> {
+        self.chains
+            .iter()
+            .find(|chain| chain.applies_to(capability, args))
+    }
+}
+
+pub(crate) fn pattern_matches(pattern: &str, capability: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = capability.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patterns_match_whole_capabilities_with_wildcards() {
+        let policy = ConfirmationPolicy::from_root_config(&json!({
+            "tools": {"settings": {"confirmation": {
+                "require": ["solana.transfer", "kv.sqlite.*.clear", " "]
+            }}}
+        }));
+        assert_eq!(policy.require.len(), 2);
+        assert!(policy.requires_confirmation("solana.transfer"));
+        assert!(!policy.requires_confirmation("solana.transfer_all"));
+        assert!(policy.requires_confirmation("kv.sqlite.todo.clear"));
+        assert!(policy.requires_confirmation("kv.sqlite.planning.clear"));
+        assert!(!policy.requires_confirmation("kv.sqlite.todo.create"));
+        assert!(ConfirmationPolicy::default().requires_confirmation("git.push"));
+        assert!(!ConfirmationPolicy::default().requires_confirmation("git.commit"));
+        assert!(!ConfirmationPolicy::default().requires_confirmation("solana.transfer"));
+    }
+
+    #[test]
+    fn chains_cover_calls_over_their_threshold() {
+        let policy = ConfirmationPolicy::from_root_config(&json!({
+            "tools": {"settings": {"confirmation": {"chains": [
+                {"capability": "solana.transfer", "arg": "amount_sol", "over": 1.0,
+                 "steps": [["self"], ["bob", "self", "bob"], []],
+                 "timeout_minutes": 30, "on_timeout": "skip"},
+                {"capability": "solana.*", "steps": []}
+            ]}}}
+        }));
+        assert_eq!(policy.chains.len(), 1);
+        let chain = &policy.chains[0];
+        assert!(policy
+            .chain_for("solana.transfer", &json!({"amount_sol": 2.5}))
+            .is_some());
+        assert!(chain.applies_to("solana.transfer", &json!({"amount_sol": "1.5"})));
+        assert!(!chain.applies_to("solana.transfer", &json!({"amount_sol": 0.5})));
+        assert!(chain.applies_to("solana.transfer", &json!({"lamports": 10})));
+        assert!(!chain.applies_to("solana.balance", &json!({})));
+
+        let steps = chain.steps_for("alice");
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].approvers, vec!["alice"]);
+        assert_eq!(steps[1].approvers, vec!["bob", "alice"]);
+        assert_eq!(steps[1].timeout_secs, Some(1800));
+        assert_eq!(steps[1].on_timeout, TimeoutAction::Skip);
+    }
+}
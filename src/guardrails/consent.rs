@@ -0,0 +1,131 @@
+use serde_json::Value;
+
+use crate::consent::ANY_SCOPE;
+
+/// Capabilities the user has to allow once before the agent may use them.
+pub const DEFAULT_CAPABILITIES: &[&str] = &["solana.transfer", "github.call_tool", "http.request"];
+const DEFAULT_TTL_DAYS: u64 = 30;
+
+/// Sensitive capabilities that need the user's standing consent.
+///
+/// Configured under `tools.settings.consent`:
+/// `{"capabilities": ["solana.transfer", "http.*"], "ttl_days": 30}`.
+/// Capabilities are patterns where `*` matches any run of characters and
+/// default to [`DEFAULT_CAPABILITIES`]; an empty list turns consent off.
+/// Grants last `ttl_days`, or until revoked when it is `0`. HTTP calls are
+/// consented per domain, or per configured server; everything else per
+/// capability.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConsentPolicy {
+    pub capabilities: Vec<String>,
+    pub ttl_days: Option<u64>,
+}
+
+impl Default for ConsentPolicy {
+    fn default() -> Self {
+        Self {
+            capabilities: DEFAULT_CAPABILITIES
+                .iter()
+                .map(|capability| capability.to_string())
+                .collect(),
+            ttl_days: Some(DEFAULT_TTL_DAYS),
+        }
+    }
+}
+
+impl ConsentPolicy {
+    pub fn from_root_config(config: &Value) -> Self {
+        let mut policy = Self::default();
+        let Some(section) = config
+            .get("tools")
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("consent"))
+        else {
+            return policy;
+        };
+        if let Some(capabilities) = section.get("capabilities").and_then(Value::as_array) {
+            policy.capabilities = capabilities
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|capability| !capability.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(days) = section.get("ttl_days").and_then(Value::as_u64) {
+            policy.ttl_days = (days > 0).then_some(days);
+        }
+        policy
+    }
+
+    pub fn requires_consent(&self, capability: &str) -> bool {
+        self.capabilities
+            .iter()
+            .any(|pattern| super::confirmation::pattern_matches(pattern, capability))
+    }
+
+    pub fn ttl_secs(&self) -> Option<i64> {
+        self.ttl_days.map(|days| days as i64 * 86_400)
+    }
+}
+
+/// What a grant for this call has to cover: the host for HTTP calls to a
+/// URL, the server name for calls to a configured server, otherwise the
+/// whole capability.
+pub fn scope_for(capability: &str, args: &Value) -> String {
+    if !capability.starts_with("http.") {
+        return ANY_SCOPE.to_string();
+    }
+    let text = |key: &str| {
+        args.get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    if let Some(url) = text("url") {
+        return reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+            .unwrap_or_else(|| url.to_string());
+    }
+    match text("server") {
+        Some(server) => format!("server {server}"),
+        None => ANY_SCOPE.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{scope_for, ConsentPolicy};
+
+    #[test]
+    fn policy_defaults_and_http_scopes() {
+        let policy = ConsentPolicy::default();
+        assert!(policy.requires_consent("solana.transfer"));
+        assert!(policy.requires_consent("http.request"));
+        assert!(!policy.requires_consent("solana.balance"));
+        assert_eq!(policy.ttl_secs(), Some(30 * 86_400));
+
+        let policy = ConsentPolicy::from_root_config(&json!({
+            "tools": {"settings": {"consent": {"capabilities": ["mcp.*"], "ttl_days": 0}}}
+        }));
+        assert!(policy.requires_consent("mcp.call"));
+        assert!(!policy.requires_consent("solana.transfer"));
+        assert_eq!(policy.ttl_secs(), None);
+
+        assert_eq!(
+            scope_for(
+                "http.request",
+                &json!({"url": "https://API.example.com/v1?q=1"})
+            ),
+            "api.example.com"
+        );
+        assert_eq!(
+            scope_for("http.request", &json!({"server": "crm"})),
+            "server crm"
+        );
+        assert_eq!(scope_for("solana.transfer", &json!({"to": "w"})), "*");
+    }
+}
@@ -0,0 +1,371 @@
+//! Data scopes: which kinds of personal data may reach which chat backend.
+//!
+//! Configured under `tools.settings.data_scope`:
+//!
+//! ```json
+//! {"cloud": ["memories"],
+//!  "local": ["memories", "financial", "people"],
+//!  "providers": {"anthropic": []},
+//!  "people": ["Grace", "Ada Lovelace"]}
+//! ```
+//!
+//! `cloud` and `local` default to the lists above and a `providers` entry
+//! overrides them for one provider. A failover chain gets the strictest
+//! scope of its backends, since any of them may end up answering. `people`
+//! is the registry of names to watch for and falls back to
+//! `tools.settings.presentation.names`.
+//!
+//! Without the section nothing is filtered. With it, context assembly drops
+//! whatever the backend may not see and reports each drop for the audit log.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::LlmProviderKind;
+use crate::presentation::{names_from_tools, Redactor};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataCategory {
+    /// Conversation history, recalled memories and the context document.
+    Memories,
+    /// Wallet addresses, amounts and account details.
+    Financial,
+    /// Anyone in the people registry.
+    People,
+}
+
+impl DataCategory {
+    pub const ALL: [DataCategory; 3] = [
+        DataCategory::Memories,
+        DataCategory::Financial,
+        DataCategory::People,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DataCategory::Memories => "memories",
+            DataCategory::Financial => "financial",
+            DataCategory::People => "people",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "memories" | "memory" => Some(DataCategory::Memories),
+            "financial" | "finance" => Some(DataCategory::Financial),
+            "people" | "persons" => Some(DataCategory::People),
+            _ => None,
+        }
+    }
+}
+
+/// Words that mark a line as financial even without a figure in it.
+const FINANCIAL_TERMS: &[&str] = &[
+    "iban",
+    "routing number",
+    "account number",
+    "sort code",
+    "credit card",
+    "card number",
+    "seed phrase",
+    "private key",
+    "salary",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataScopePolicy {
+    pub cloud: Vec<DataCategory>,
+    pub local: Vec<DataCategory>,
+    /// Per-provider overrides, keyed by provider name.
+    pub providers: HashMap<String, Vec<DataCategory>>,
+    pub people: Vec<String>,
+}
+
+impl DataScopePolicy {
+    /// `None` when no policy is configured or it is switched off.
+    pub fn from_tools(tools: Option<&Value>) -> Option<Self> {
+        let section = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("data_scope"))
+            .filter(|section| section.get("enabled").and_then(Value::as_bool) != Some(false))?;
+        let people = section
+            .get("people")
+            .and_then(Value::as_array)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|names| !names.is_empty())
+            .unwrap_or_else(|| names_from_tools(tools));
+        Some(Self {
+            cloud: section
+                .get("cloud")
+                .map(parse_categories)
+                .unwrap_or_else(|| vec![DataCategory::Memories]),
+            local: section
+                .get("local")
+                .map(parse_categories)
+                .unwrap_or_else(|| DataCategory::ALL.to_vec()),
+            providers: section
+                .get("providers")
+                .and_then(Value::as_object)
+                .map(|providers| {
+                    providers
+                        .iter()
+                        .map(|(name, categories)| {
+                            (
+                                name.trim().to_ascii_lowercase(),
+                                parse_categories(categories),
+                            )
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            people,
+        })
+    }
+
+    /// Categories one backend may see.
+    pub fn allowed(&self, provider: LlmProviderKind, local: bool) -> &[DataCategory] {
+        match self.providers.get(provider.as_str()) {
+            Some(categories) => categories,
+            None if local => &self.local,
+            None => &self.cloud,
+        }
+    }
+
+    /// The scope for a chain of `(provider, runs_locally)` backends.
+    pub fn scope_for(&self, backends: &[(LlmProviderKind, bool)]) -> DataScope {
+        let allowed = DataCategory::ALL
+            .into_iter()
+            .filter(|category| {
+                backends
+                    .iter()
+                    .all(|(provider, local)| self.allowed(*provider, *local).contains(category))
+            })
+            .collect();
+        let target = backends
+            .iter()
+            .map(|(provider, local)| {
+                format!(
+                    "{}({})",
+                    provider.as_str(),
+                    if *local { "local" } else { "cloud" }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        DataScope {
+            allowed: Some(allowed),
+            target,
+            people: Redactor::new(&self.people),
+        }
+    }
+}
+
+fn parse_categories(value: &Value) -> Vec<DataCategory> {
+    let mut categories = Vec::new();
+    for name in value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        match DataCategory::parse(name) {
+            Some(category) if !categories.contains(&category) => categories.push(category),
+            Some(_) => {}
+            None => tracing::warn!(category = name, "Ignoring unknown data scope category"),
+        }
+    }
+    categories
+}
+
+/// Context that was kept out of a prompt.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ScopeViolation {
+    pub category: DataCategory,
+    /// Which part of the context it came from, e.g. `history`.
+    pub source: String,
+    pub lines: usize,
+}
+
+/// What the configured chat backends may see. The default lets everything
+/// through.
+#[derive(Clone, Debug, Default)]
+pub struct DataScope {
+    allowed: Option<Vec<DataCategory>>,
+    target: String,
+    people: Redactor,
+}
+
+impl DataScope {
+    /// The backends this scope was built for, for logs.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn allows(&self, category: DataCategory) -> bool {
+        self.allowed
+            .as_ref()
+            .is_none_or(|allowed| allowed.contains(&category))
+    }
+
+    /// Keeps the lines of `text` the backends may see. `category` is what
+    /// the whole source counts as, if anything; every line is also checked
+    /// for financial details and registered names.
+    pub fn filter(
+        &self,
+        source: &str,
+        category: Option<DataCategory>,
+        text: &str,
+        violations: &mut Vec<ScopeViolation>,
+    ) -> String {
+        if self.allowed.is_none() {
+            return text.to_string();
+        }
+        let mut kept = Vec::new();
+        for line in text.lines() {
+            match self.blocked_category(category, line) {
+                Some(blocked) => record(violations, source, blocked),
+                None => kept.push(line),
+            }
+        }
+        kept.join("\n")
+    }
+
+    fn blocked_category(&self, category: Option<DataCategory>, line: &str) -> Option<DataCategory> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        if let Some(category) = category.filter(|category| !self.allows(*category)) {
+            return Some(category);
+        }
+        if !self.allows(DataCategory::Financial) && is_financial(&self.people, line) {
+            return Some(DataCategory::Financial);
+        }
+        if !self.allows(DataCategory::People) && self.people.mentions_name(line) {
+            return Some(DataCategory::People);
+        }
+        None
+    }
+}
+
+fn is_financial(redactor: &Redactor, line: &str) -> bool {
+    let lower = line.to_lowercase();
+    redactor.mentions_money(line) || FINANCIAL_TERMS.iter().any(|term| lower.contains(term))
+}
+
+fn record(violations: &mut Vec<ScopeViolation>, source: &str, category: DataCategory) {
+    match violations
+        .iter_mut()
+        .find(|violation| violation.source == source && violation.category == category)
+    {
+        Some(violation) => violation.lines += 1,
+        None => violations.push(ScopeViolation {
+            category,
+            source: source.to_string(),
+            lines: 1,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn chains_get_the_strictest_scope() {
+        let tools = json!({"settings": {
+            "data_scope": {"providers": {"anthropic": ["memories", "people"]}},
+            "presentation": {"names": ["Grace"]}
+        }});
+        let policy = DataScopePolicy::from_tools(Some(&tools)).unwrap();
+        assert_eq!(policy.people, vec!["Grace".to_string()]);
+        assert_eq!(
+            policy.allowed(LlmProviderKind::Ollama, true),
+            DataCategory::ALL
+        );
+        assert_eq!(
+            policy.allowed(LlmProviderKind::Openai, false),
+            [DataCategory::Memories]
+        );
+
+        let scope = policy.scope_for(&[
+            (LlmProviderKind::Ollama, true),
+            (LlmProviderKind::Anthropic, false),
+        ]);
+        assert_eq!(scope.target(), "ollama(local), anthropic(cloud)");
+        assert!(scope.allows(DataCategory::People));
+        assert!(!scope.allows(DataCategory::Financial));
+
+        assert!(DataScopePolicy::from_tools(None).is_none());
+        let off = json!({"settings": {"data_scope": {"enabled": false}}});
+        assert!(DataScopePolicy::from_tools(Some(&off)).is_none());
+    }
+
+    #[test]
+    fn filters_blocked_lines_and_counts_them() {
+        let tools = json!({"settings": {"data_scope": {
+            "cloud": ["memories"], "people": ["Grace Hopper"]
+        }}});
+        let scope = DataScopePolicy::from_tools(Some(&tools))
+            .unwrap()
+            .scope_for(&[(LlmProviderKind::Openai, false)]);
+        let mut violations = Vec::new();
+        let history = "user: plan the offsite\n\
+                       user: I paid $1,200 rent\n\
+                       assistant: Grace Hopper called\n\
+                       user: my IBAN is on file";
+        assert_eq!(
+            scope.filter("history", None, history, &mut violations),
+            "user: plan the offsite"
+        );
+        assert_eq!(
+            violations,
+            vec![
+                ScopeViolation {
+                    category: DataCategory::Financial,
+                    source: "history".to_string(),
+                    lines: 2,
+                },
+                ScopeViolation {
+                    category: DataCategory::People,
+                    source: "history".to_string(),
+                    lines: 1,
+                },
+            ]
+        );
+
+        let strict = DataScopePolicy::from_tools(Some(&json!({"settings": {"data_scope": {
+            "cloud": []
+        }}})))
+        .unwrap()
+        .scope_for(&[(LlmProviderKind::Anthropic, false)]);
+        let mut violations = Vec::new();
+        assert_eq!(
+            strict.filter(
+                "memory",
+                Some(DataCategory::Memories),
+                "likes tea",
+                &mut violations
+            ),
+            ""
+        );
+        assert_eq!(violations[0].category, DataCategory::Memories);
+
+        let open = DataScope::default();
+        assert_eq!(
+            open.filter("history", None, history, &mut violations),
+            history
+        );
+    }
+}
@@ -1 +1,6 @@
+pub mod confirmation;
+pub mod consent;
+pub mod data_scope;
+pub mod injection;
 pub mod pii;
+pub mod rate_limit;
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Per-user token buckets for capability calls.
+///
+/// Configured under `tools.settings.rate_limits`, keyed by capability prefix:
+/// `{"http.request": {"max": 30, "per": "minute"}, "solana.transfer": {"max":
+/// 3, "per": "hour"}}`. `per` also accepts `second`, `day` or a number of
+/// seconds. The longest matching prefix decides, and each user gets their
+/// own bucket per prefix. Nothing is limited unless configured.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RateLimitPolicy {
+    pub rules: Vec<RateLimitRule>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimitRule {
+    pub prefix: String,
+    pub max: u32,
+    pub per_seconds: u64,
+}
+
+/// Why a call was refused; `retry_after_seconds` is when one token is back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLimited {
+    pub rule: RateLimitRule,
+    pub retry_after_seconds: u64,
+}
+
+impl RateLimitPolicy {
+    pub fn from_root_config(config: &serde_json::Value) -> Self {
+        let Some(limits) = config
+            .get("tools")
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("rate_limits"))
+            .and_then(|value| value.as_object())
+        else {
+            return Self::default();
+        };
+        let mut rules = limits
+            .iter()
+            .filter_map(|(prefix, rule)| {
+                let prefix = prefix.trim();
+                let max = rule.get("max").and_then(|v| v.as_u64())?;
+                let per_seconds = rule.get("per").and_then(parse_period)?;
+                (!prefix.is_empty() && max > 0 && per_seconds > 0).then(|| RateLimitRule {
+                    prefix: prefix.to_string(),
+                    max: max.min(u32::MAX as u64) as u32,
+                    per_seconds,
+                })
+            })
+            .collect::<Vec<_>>();
+        rules.sort_by(|a, b| b.prefix.len().cmp(&a.prefix.len()));
+        Self { rules }
+    }
+
+    pub fn rule_for(&self, capability: &str) -> Option<&RateLimitRule> {
+        self.rules
+            .iter()
+            .find(|rule| capability.starts_with(rule.prefix.as_str()))
+    }
+}
+
+fn parse_period(value: &serde_json::Value) -> Option<u64> {
+    if let Some(seconds) = value.as_u64() {
+        return Some(seconds);
+    }
+    match value.as_str()?.trim().to_ascii_lowercase().as_str() {
+        "second" | "sec" | "s" => Some(1),
+        "minute" | "min" | "m" => Some(60),
+        "hour" | "h" => Some(3_600),
+        "day" | "d" => Some(86_400),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at_ms: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<(String, String), Bucket>>,
+}
+
+impl RateLimiter {
+    /// Takes one token from the user's bucket for `capability`, refilling it
+    /// for the time elapsed since it was last touched. Buckets start full.
+    pub fn try_acquire(
+        &self,
+        policy: &RateLimitPolicy,
+        user_id: &str,
+        capability: &str,
+        now_ms: i64,
+    ) -> Result<(), RateLimited> {
+        let Some(rule) = policy.rule_for(capability) else {
+            return Ok(());
+        };
+        let capacity = rule.max as f64;
+        let refill_per_ms = capacity / (rule.per_seconds as f64 * 1_000.0);
+
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        let bucket = buckets
+            .entry((user_id.to_string(), rule.prefix.clone()))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated_at_ms: now_ms,
+            });
+        let elapsed_ms = now_ms.saturating_sub(bucket.updated_at_ms).max(0) as f64;
+        bucket.tokens = (bucket.tokens + elapsed_ms * refill_per_ms).min(capacity);
+        bucket.updated_at_ms = now_ms.max(bucket.updated_at_ms);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        let missing_ms = (1.0 - bucket.tokens) / refill_per_ms;
+        Err(RateLimited {
+            rule: rule.clone(),
+            retry_after_seconds: (missing_ms / 1_000.0).ceil().max(1.0) as u64,
+        })
+    }
+
+    /// Drops buckets for prefixes the policy no longer limits.
+    pub fn retain_policy(&self, policy: &RateLimitPolicy) {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+        buckets.retain(|(_, prefix), _| policy.rules.iter().any(|rule| &rule.prefix == prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy() -> RateLimitPolicy {
+        RateLimitPolicy::from_root_config(&json!({
+            "tools": {"settings": {"rate_limits": {
+                "http.": {"max": 2, "per": "minute"},
+                "http.request": {"max": 1, "per": 10},
+                "solana.transfer": {"max": 0, "per": "hour"},
+                "broken": {"max": 1, "per": "fortnight"}
+            }}}
+        }))
+    }
+
+    #[test]
+    fn longest_prefix_wins_and_invalid_rules_are_dropped() {
+        let policy = policy();
+        assert_eq!(policy.rules.len(), 2);
+        assert_eq!(policy.rule_for("http.request").unwrap().per_seconds, 10);
+        assert_eq!(policy.rule_for("http.head").unwrap().max, 2);
+        assert!(policy.rule_for("solana.transfer").is_none());
+        assert!(RateLimitPolicy::default().rule_for("http.request").is_none());
+    }
+
+    #[test]
+    fn buckets_refill_over_time_per_user() {
+        let policy = policy();
+        let limiter = RateLimiter::default();
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 0).is_ok());
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 0).is_ok());
+        let limited = limiter
+            .try_acquire(&policy, "a", "http.head", 0)
+            .unwrap_err();
+        assert_eq!(limited.rule.prefix, "http.");
+        assert_eq!(limited.retry_after_seconds, 30);
+        assert!(limiter.try_acquire(&policy, "b", "http.head", 0).is_ok());
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 29_000).is_err());
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 31_000).is_ok());
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 31_000).is_err());
+
+        limiter.retain_policy(&RateLimitPolicy::default());
+        assert!(limiter.try_acquire(&policy, "a", "http.head", 31_000).is_ok());
+    }
+}
@@ -1,9 +1,8 @@
 use ::time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
-use chrono::{DateTime, Local, TimeZone};
-use chrono_english::{parse_date_string, Dialect};
+use chrono::{DateTime, Local, TimeZone, Timelike};
 use iced::widget::{
-    button, column, container, image, markdown, row, scrollable, text, text_editor, text_input,
-    Id as WidgetId, Space,
+    button, checkbox, column, container, image, markdown, mouse_area, pick_list, row, scrollable,
+    text, text_editor, text_input, Id as WidgetId, Space,
 };
 use iced::{
     application, time, Background, Border, Color, Element, Length, Shadow, Size, Subscription,
@@ -18,7 +17,16 @@ use std::process::{Child, Command, Stdio};
 use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
 
+use crate::charts::export::{
+    self as view_export, DependencyExport, DependencyNode, ExportFormat, ExportTone, GanttBar,
+    GanttExport,
+};
+use crate::clock::{system_clock, SharedClock};
 use crate::inbox_fsm::InboxState as InboxStatus;
+use crate::insights::{format_hours, weekday_label, ActivityHeatmap, AggregateInsights};
+use crate::interfaces::providers::MemoryHit;
+use crate::scheduling;
+use crate::smart_lists::{SmartList, SmartListBounds};
 
 const BUTTERFLY_BOT_LOGO_BYTES: &[u8] =
     include_bytes!("../assets/icons/hicolor/512x512/apps/butterfly-bot.png");
@@ -42,6 +50,65 @@ struct ProcessTextRequest {
     user_id: String,
     text: String,
     prompt: Option<String>,
+    thread_id: Option<String>,
+}
+
+const DEFAULT_CHAT_THREAD: &str = "default";
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChatThreadsApiResponse {
+    #[serde(default)]
+    default_model: String,
+    #[serde(default)]
+    threads: Vec<ChatThreadApiItem>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ChatThreadApiItem {
+    thread_id: String,
+    provider: Option<String>,
+    model: Option<String>,
+    temperature: Option<f32>,
+}
+
+/// Model answering the chat, as shown in the chat header.
+#[derive(Clone, Debug, Default)]
+struct ChatModelInfo {
+    label: String,
+    temperature: Option<f32>,
+    pinned: bool,
+}
+
+impl ChatModelInfo {
+    fn from_threads(response: ChatThreadsApiResponse) -> Self {
+        let Some(thread) = response.threads.into_iter().find(|thread| {
+            thread.thread_id == DEFAULT_CHAT_THREAD
+                && (thread.provider.is_some()
+                    || thread.model.is_some()
+                    || thread.temperature.is_some())
+        }) else {
+            return Self {
+                label: response.default_model,
+                temperature: None,
+                pinned: false,
+            };
+        };
+        let (default_provider, default_model) = response
+            .default_model
+            .split_once(':')
+            .map(|(provider, model)| (provider.to_string(), Some(model.to_string())))
+            .unwrap_or((response.default_model.clone(), None));
+        let provider = thread.provider.unwrap_or(default_provider);
+        let label = match thread.model.or(default_model) {
+            Some(model) => format!("{provider}:{model}"),
+            None => provider,
+        };
+        Self {
+            label,
+            temperature: thread.temperature,
+            pinned: true,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -52,12 +119,36 @@ struct DoctorCheckResponse {
     fix_hint: Option<String>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct RemoteStorageReport {
+    target: String,
+    round_trip_ms: u64,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct DoctorResponse {
     overall: String,
     checks: Vec<DoctorCheckResponse>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct SchemaRepairReport {
+    backup_path: Option<String>,
+    applied: Vec<Value>,
+    remaining: Vec<Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SchemaRepairEntry {
+    db_path: String,
+    report: SchemaRepairReport,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SchemaRepairResponse {
+    databases: Vec<SchemaRepairEntry>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct SecurityAuditFindingResponse {
     id: String,
@@ -74,6 +165,48 @@ struct SecurityAuditResponse {
     findings: Vec<SecurityAuditFindingResponse>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct ToolPostureRow {
+    name: String,
+    version: Option<String>,
+    module_sha256: Option<String>,
+    capabilities: Vec<String>,
+    areas: Vec<String>,
+    risk: String,
+    last_used_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ToolPostureApiResponse {
+    tools: Vec<ToolPostureRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SecretRow {
+    name: String,
+    description: Option<String>,
+    rotated_at: i64,
+    version: u32,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SecretsApiResponse {
+    secrets: Vec<SecretRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ConsentLogRow {
+    id: i32,
+    status: String,
+    summary: String,
+    requested_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ConsentLogApiResponse {
+    entries: Vec<ConsentLogRow>,
+}
+
 #[derive(Clone, Debug, Deserialize)]
 struct SolanaWalletUiResponse {
     address: String,
@@ -105,11 +238,154 @@ struct InboxApiItem {
     estimate_optimistic_minutes: Option<i32>,
     estimate_likely_minutes: Option<i32>,
     estimate_pessimistic_minutes: Option<i32>,
+    #[serde(default)]
+    linked_reminders: Vec<InboxLinkedReminder>,
+    held_until: Option<i64>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    project_id: Option<i32>,
+    #[serde(default)]
+    comments: Vec<InboxNote>,
+    #[serde(default)]
+    attachments: Vec<InboxAttachment>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct InboxLinkedReminder {
+    due_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct InboxNote {
+    author: String,
+    body: String,
+    created_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct InboxAttachment {
+    name: String,
+    size_bytes: i64,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 struct AuditEventsApiResponse {
     events: Vec<Value>,
+    #[serde(default)]
+    next_before_id: Option<i32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ProjectsApiResponse {
+    projects: Vec<ProjectRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct ProjectRow {
+    id: i32,
+    name: String,
+}
+
+/// Entry of the header project selector; `id: None` shows every project.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProjectChoice {
+    id: Option<i32>,
+    name: String,
+}
+
+impl std::fmt::Display for ProjectChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TrashApiResponse {
+    batches: Vec<TrashBatchRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TrashBatchRow {
+    store: String,
+    batch_id: String,
+    count: i64,
+    trashed_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SearchApiResponse {
+    indexed: bool,
+    hits: Vec<SearchHitRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SearchHitRow {
+    kind: String,
+    origin_ref: String,
+    title: String,
+    snippet: String,
+    updated_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TemplatePreviewEntry {
+    kind: String,
+    name: String,
+    detail: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TemplateRuleCapabilities {
+    rule: String,
+    tool: String,
+    capabilities: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct TemplatePreview {
+    name: String,
+    description: Option<String>,
+    author: Option<String>,
+    digest: String,
+    creates: Vec<TemplatePreviewEntry>,
+    #[serde(default)]
+    capabilities: Vec<TemplateRuleCapabilities>,
+    #[serde(default)]
+    warnings: Vec<String>,
+}
+
+/// A bundle read from disk together with the daemon's preview of it; the
+/// same bundle is sent back on import.
+#[derive(Clone, Debug)]
+struct TemplateReview {
+    bundle: Value,
+    preview: TemplatePreview,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BackupRunRow {
+    at: i64,
+    bytes: u64,
+    remote_key: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BackupSnapshotRow {
+    name: String,
+    bytes: u64,
+    created_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BackupStatusApiResponse {
+    enabled: bool,
+    interval_hours: u64,
+    keep: usize,
+    directory: String,
+    last_run: Option<BackupRunRow>,
+    snapshots: Vec<BackupSnapshotRow>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -117,14 +393,86 @@ struct ReminderDeliveryEventsApiResponse {
     events: Vec<Value>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct EstimateAccuracyRow {
+    category: String,
+    samples: usize,
+    median_ratio: f64,
+    mean_absolute_error_pct: f64,
+    within_25_pct: f64,
+    multiplier: f64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct EstimateAccuracyApiResponse {
+    categories: Vec<EstimateAccuracyRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SchedulerJobRow {
+    name: String,
+    interval_seconds: i64,
+    last_run_at: Option<i64>,
+    last_duration_ms: Option<i64>,
+    last_error: Option<String>,
+    next_run_at: i64,
+    late: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct SchedulerJobsApiResponse {
+    jobs: Vec<SchedulerJobRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DeadLetterRow {
+    id: i32,
+    channel: String,
+    title: String,
+    attempts: i32,
+    last_error: String,
+    failed_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DeadLettersApiResponse {
+    dead_letters: Vec<DeadLetterRow>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BrokenRefRow {
+    from: String,
+    to: String,
+    reason: String,
+}
+
+/// The daemon's check of every dependency ref, done items included.
+#[derive(Clone, Debug, Deserialize)]
+struct DependencyCheckRow {
+    checked: usize,
+    broken: Vec<BrokenRefRow>,
+    cycles: Vec<Vec<String>>,
+}
+
 #[derive(Clone, Debug)]
 struct AuditEventRow {
+    id: i32,
     timestamp: i64,
     event_type: String,
     status: String,
     actor: Option<String>,
     line: String,
     origin_ref: Option<String>,
+    /// The reminder or item title carried in the payload, if any.
+    title: Option<String>,
+}
+
+#[derive(Clone, Debug)]
+struct AuditEventsPage {
+    rows: Vec<AuditEventRow>,
+    next_before_id: Option<i32>,
+    /// Set when this page continues an earlier one rather than replacing it.
+    older: bool,
 }
 
 #[derive(Clone)]
@@ -143,6 +491,18 @@ enum MessageRole {
     System,
 }
 
+const HEATMAP_REFRESH_SECONDS: i64 = 10 * 60;
+/// Inbox and audit polling; stretched while the daemon pushes its events.
+const POLL_REFRESH_SECONDS: i64 = 15;
+const STREAMED_POLL_REFRESH_SECONDS: i64 = 2 * 60;
+const EVENT_STREAM_MIN_BACKOFF: Duration = Duration::from_secs(1);
+const EVENT_STREAM_MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Well past the daemon's keepalive, so only a dead connection times out.
+const EVENT_STREAM_READ_TIMEOUT: Duration = Duration::from_secs(45);
+const AUDIT_PAGE_SIZE: usize = 200;
+/// How long the inbox keeps offering to undo a clear.
+const TRASH_UNDO_WINDOW_SECONDS: i64 = 10 * 60;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum UiTab {
     Inbox,
@@ -152,6 +512,7 @@ enum UiTab {
     Audit,
     Chat,
     Activity,
+    Insights,
     Settings,
     Diagnostics,
     Context,
@@ -159,7 +520,7 @@ enum UiTab {
 }
 
 impl UiTab {
-    fn all() -> [UiTab; 11] {
+    fn all() -> [UiTab; 12] {
         [
             UiTab::Inbox,
             UiTab::Kanban,
@@ -168,6 +529,7 @@ impl UiTab {
             UiTab::Audit,
             UiTab::Chat,
             UiTab::Activity,
+            UiTab::Insights,
             UiTab::Settings,
             UiTab::Diagnostics,
             UiTab::Context,
@@ -184,6 +546,7 @@ impl UiTab {
             UiTab::Audit => "Audit",
             UiTab::Chat => "Chat",
             UiTab::Activity => "Activity",
+            UiTab::Insights => "Insights",
             UiTab::Settings => "Config",
             UiTab::Diagnostics => "Diagnostics",
             UiTab::Context => "Context",
@@ -198,6 +561,14 @@ enum InboxSourceType {
     Todo,
     Task,
     PlanStep,
+    Approval,
+    /// Standing consent the agent asked for.
+    Consent,
+    Question,
+    /// Mirrored from another system, e.g. GitHub.
+    External,
+    /// Read-only event from a synced calendar.
+    Calendar,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -228,6 +599,58 @@ struct InboxItem {
     estimate_optimistic_minutes: Option<i32>,
     estimate_likely_minutes: Option<i32>,
     estimate_pessimistic_minutes: Option<i32>,
+    linked_reminders: Vec<InboxLinkedReminder>,
+    held_until: Option<i64>,
+    labels: Vec<String>,
+    project_id: Option<i32>,
+    comments: Vec<InboxNote>,
+    attachments: Vec<InboxAttachment>,
+}
+
+/// Board columns; dropping a card on one runs the matching inbox action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KanbanColumn {
+    New,
+    Seen,
+    InProgress,
+    Blocked,
+    Done,
+}
+
+impl KanbanColumn {
+    fn title(self) -> &'static str {
+        match self {
+            KanbanColumn::New => "NEW",
+            KanbanColumn::Seen => "SEEN",
+            KanbanColumn::InProgress => "IN PROGRESS",
+            KanbanColumn::Blocked => "BLOCKED",
+            KanbanColumn::Done => "DONE",
+        }
+    }
+
+    fn of(status: InboxStatus) -> Self {
+        match status {
+            InboxStatus::New => KanbanColumn::New,
+            InboxStatus::Acknowledged => KanbanColumn::Seen,
+            InboxStatus::InProgress => KanbanColumn::InProgress,
+            InboxStatus::Blocked => KanbanColumn::Blocked,
+            InboxStatus::Done | InboxStatus::Dismissed => KanbanColumn::Done,
+        }
+    }
+
+    /// The action that moves a card in `status` here, if the inbox state
+    /// machine allows it. Nothing moves back to New.
+    fn action_from(self, status: InboxStatus) -> Option<InboxActionKind> {
+        let action = match (self, status) {
+            (KanbanColumn::New, _) => return None,
+            (KanbanColumn::Seen, _) => InboxActionKind::Acknowledge,
+            (KanbanColumn::InProgress, InboxStatus::Done) => InboxActionKind::Reopen,
+            (KanbanColumn::InProgress, _) => InboxActionKind::Start,
+            (KanbanColumn::Blocked, _) => InboxActionKind::Block,
+            (KanbanColumn::Done, _) => InboxActionKind::Done,
+        };
+        crate::inbox_fsm::transition(status, fsm_action(action)).map(|_| action)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -240,14 +663,111 @@ enum InboxActionKind {
     Snooze,
 }
 
+/// One `tools.settings.inbox_rules` entry as edited in the Config tab;
+/// list fields are comma-separated.
+#[derive(Clone, Debug, Default)]
+struct UiInboxRuleRow {
+    name: String,
+    source_types: String,
+    keywords: String,
+    due_within_hours: String,
+    labels: String,
+    priority: String,
+    owner: String,
+    /// `None` leaves the item's own value.
+    requires_human_action: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum InboxRuleField {
+    Name,
+    SourceTypes,
+    Keywords,
+    DueWithinHours,
+    Labels,
+    Priority,
+    Owner,
+}
+
+impl UiInboxRuleRow {
+    fn from_rule(rule: crate::inbox_rules::InboxRule) -> Self {
+        Self {
+            name: rule.name,
+            source_types: rule.source_types.join(", "),
+            keywords: rule.keywords.join(", "),
+            due_within_hours: rule
+                .due_within_hours
+                .map(|hours| hours.to_string())
+                .unwrap_or_default(),
+            labels: rule.labels.join(", "),
+            priority: rule.priority.unwrap_or_default(),
+            owner: rule.owner.unwrap_or_default(),
+            requires_human_action: rule.requires_human_action,
+        }
+    }
+
+    /// `Ok(None)` for a row left completely blank.
+    fn to_rule(&self) -> Result<Option<crate::inbox_rules::InboxRule>, String> {
+        let list = |value: &str| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let optional =
+            |value: &str| Some(value.trim().to_ascii_lowercase()).filter(|value| !value.is_empty());
+        let due_within_hours = match self.due_within_hours.trim() {
+            "" => None,
+            hours => Some(
+                hours
+                    .parse::<u64>()
+                    .map_err(|_| format!("\"{hours}\" is not a number of hours"))?,
+            ),
+        };
+        let rule = crate::inbox_rules::InboxRule {
+            name: self.name.trim().to_string(),
+            source_types: list(&self.source_types),
+            keywords: list(&self.keywords),
+            due_within_hours,
+            labels: list(&self.labels),
+            priority: optional(&self.priority),
+            owner: optional(&self.owner),
+            requires_human_action: self.requires_human_action,
+        };
+        if rule == crate::inbox_rules::InboxRule::default() {
+            return Ok(None);
+        }
+        match rule.problem() {
+            Some(problem) => Err(problem),
+            None => Ok(Some(rule)),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 struct UiServerRow {
     name: String,
     url: String,
+    /// Local command line for stdio MCP servers; the header fields then
+    /// hold an environment variable instead.
+    command: String,
     header_key: String,
     header_value: String,
 }
 
+/// One `tools.zapier.zaps` entry. `fields` reads like
+/// `email:string, amount:number?`; a Zap with an inbound user also opens
+/// inbox items from `POST /webhooks/zapier/<name>`.
+#[derive(Clone, Debug, Default)]
+struct UiZapRow {
+    name: String,
+    url: String,
+    fields: String,
+    inbound_user: String,
+}
+
 #[derive(Clone, Debug)]
 struct SettingsForm {
     github_pat: String,
@@ -263,8 +783,29 @@ struct SettingsForm {
     proactive_chat_severity: String,
     proactive_chat_quiet_start_hhmm: String,
     proactive_chat_quiet_end_hhmm: String,
+    speak_chat_replies: bool,
+    speak_inbox: bool,
+    privacy_lock_idle_minutes: String,
+    /// Only sent when non-empty; the stored verifier is never read back.
+    privacy_lock_passphrase: String,
+    /// Comma-separated names masked in presentation mode.
+    presentation_names: String,
+    /// `s3`, `webdav` or `off`.
+    remote_storage_kind: String,
+    /// S3 endpoint or WebDAV collection URL.
+    remote_storage_location: String,
+    remote_storage_bucket: String,
+    remote_storage_region: String,
+    /// S3 access key id or WebDAV username.
+    remote_storage_account: String,
+    remote_storage_secret: String,
+    remote_storage_prefix: String,
+    /// Only sent when non-empty, like the lock passphrase.
+    remote_storage_passphrase: String,
     mcp_servers: Vec<UiServerRow>,
     http_call_servers: Vec<UiServerRow>,
+    zaps: Vec<UiZapRow>,
+    inbox_rules: Vec<UiInboxRuleRow>,
     prompt_text: String,
     heartbeat_text: String,
 }
@@ -285,8 +826,23 @@ impl Default for SettingsForm {
             proactive_chat_severity: "blocked_or_overdue".to_string(),
             proactive_chat_quiet_start_hhmm: String::new(),
             proactive_chat_quiet_end_hhmm: String::new(),
+            speak_chat_replies: false,
+            speak_inbox: false,
+            privacy_lock_idle_minutes: "0".to_string(),
+            privacy_lock_passphrase: String::new(),
+            presentation_names: String::new(),
+            remote_storage_kind: "off".to_string(),
+            remote_storage_location: String::new(),
+            remote_storage_bucket: String::new(),
+            remote_storage_region: String::new(),
+            remote_storage_account: String::new(),
+            remote_storage_secret: String::new(),
+            remote_storage_prefix: String::new(),
+            remote_storage_passphrase: String::new(),
             mcp_servers: vec![],
+            inbox_rules: vec![],
             http_call_servers: vec![],
+            zaps: vec![],
             prompt_text: String::new(),
             heartbeat_text: String::new(),
         }
@@ -299,6 +855,7 @@ struct LoadedSettings {
 }
 
 struct ButterflyIcedApp {
+    clock: SharedClock,
     daemon_url: String,
     user_id: String,
     db_path: String,
@@ -306,10 +863,18 @@ struct ButterflyIcedApp {
     active_tab: UiTab,
     composer: String,
     busy: bool,
+    streaming_message_id: Option<u64>,
+    prompt_queue_position: Option<usize>,
+    catch_up: Option<CatchUp>,
+    chat_model: Option<ChatModelInfo>,
+    /// Recap handed to the agent with the next prompt, then cleared.
+    catch_up_context: Option<String>,
     error: String,
     daemon_running: bool,
     daemon_starting: bool,
     daemon_status: String,
+    /// Whether `/ui_events` is connected and pushing updates.
+    event_stream_connected: bool,
     next_id: u64,
     history_loaded: bool,
     chat_messages: Vec<ChatMessage>,
@@ -319,15 +884,56 @@ struct ButterflyIcedApp {
     inbox_error: String,
     inbox_refresh_in_flight: bool,
     inbox_action_origin_ref_in_flight: Option<String>,
+    /// Origin refs ticked for a bulk action.
+    inbox_selected: HashSet<String>,
+    inbox_bulk_in_flight: bool,
+    /// Card whose note composer is open, and the note being written.
+    inbox_note_origin_ref: Option<String>,
+    inbox_note_draft: String,
+    /// Card whose attach field is open, and the file path typed into it.
+    inbox_attach_origin_ref: Option<String>,
+    inbox_attach_path: String,
+    /// Card being dragged on the Kanban board.
+    kanban_dragging: Option<String>,
+    kanban_hover: Option<KanbanColumn>,
+    /// Status and update time to put back if a dropped move fails.
+    kanban_rollback: HashMap<String, (InboxStatus, i64)>,
+    kanban_status: String,
     inbox_last_refresh_ts: i64,
+    /// A pushed event arrived while a refresh was already running.
+    inbox_stale: bool,
+    inbox_group_by_smart_list: bool,
+    inbox_collapsed_smart_lists: HashSet<SmartList>,
+    trash_batches: Vec<TrashBatchRow>,
+    trash_restore_in_flight: Option<String>,
+    projects: Vec<ProjectRow>,
+    /// Project the inbox and boards are narrowed to; `None` shows all.
+    selected_project: Option<i32>,
+    search_query: String,
+    search_hits: Vec<SearchHitRow>,
+    search_status: String,
+    search_in_flight: bool,
+    template_path: String,
+    template_review: Option<TemplateReview>,
+    template_status: String,
+    template_error: String,
+    template_in_flight: bool,
+    view_export_status: String,
     last_badge_actionable_count: Option<usize>,
     audit_events: Vec<AuditEventRow>,
     audit_status: String,
     audit_error: String,
     audit_refresh_in_flight: bool,
     audit_last_refresh_ts: i64,
+    audit_stale: bool,
     audit_last_activity_bridge_ts: i64,
     audit_origin_filter: Option<String>,
+    audit_next_before_id: Option<i32>,
+    activity_heatmap: Option<ActivityHeatmap>,
+    aggregate_insights: Option<AggregateInsights>,
+    heatmap_error: String,
+    heatmap_refresh_in_flight: bool,
+    heatmap_last_refresh_ts: i64,
     timeline_focus_origin_ref: Option<String>,
     chat_origin_anchor: Option<String>,
     chat_anchor_message_id: Option<u64>,
@@ -337,6 +943,7 @@ struct ButterflyIcedApp {
     settings: SettingsForm,
     settings_status: String,
     settings_error: String,
+    remote_storage_status: String,
     doctor_status: String,
     doctor_error: String,
     doctor_overall: String,
@@ -345,9 +952,33 @@ struct ButterflyIcedApp {
     security_error: String,
     security_overall: String,
     security_findings: Vec<SecurityAuditFindingResponse>,
+    tool_postures: Vec<ToolPostureRow>,
+    tool_posture_status: String,
+    tool_posture_in_flight: bool,
+    secrets: Vec<SecretRow>,
+    secrets_status: String,
+    secrets_in_flight: bool,
+    consent_log: Vec<ConsentLogRow>,
+    consent_status: String,
+    consent_in_flight: bool,
+    secret_name: String,
+    secret_value: String,
+    secret_description: String,
     reminder_delivery_status: String,
     reminder_delivery_error: String,
     reminder_delivery_events: Vec<String>,
+    backup_status: String,
+    backup_error: String,
+    backup_snapshots: Vec<String>,
+    backup_in_flight: bool,
+    estimate_accuracy_status: String,
+    estimate_accuracy_lines: Vec<String>,
+    scheduler_jobs_status: String,
+    scheduler_jobs_lines: Vec<String>,
+    dead_letters: Vec<DeadLetterRow>,
+    dead_letters_status: String,
+    dependency_check: Option<DependencyCheckRow>,
+    dependency_check_status: String,
     solana_wallet_address: Option<String>,
     solana_wallet_status: String,
     solana_wallet_fetch_in_flight: bool,
@@ -356,8 +987,25 @@ struct ButterflyIcedApp {
     heartbeat_preview_items: Vec<markdown::Item>,
     context_editor: text_editor::Content,
     heartbeat_editor: text_editor::Content,
+    memory_query: String,
+    memory_rerank: bool,
+    memory_hits: Vec<MemoryHit>,
+    memory_search_status: String,
+    memory_search_in_flight: bool,
     manage_local_daemon: bool,
     daemon_autostart_attempted: bool,
+    privacy_locked: bool,
+    last_interaction_ts: i64,
+    unlock_input: String,
+    unlock_error: String,
+    unlock_in_flight: bool,
+    presentation_mode: bool,
+    redactor: crate::presentation::Redactor,
+    voice_recording: Option<crate::voice::capture::Recording>,
+    voice_transcribing: bool,
+    voice_status: String,
+    /// Running while any tab speaks; rebuilt whenever settings load.
+    speaker: Option<crate::voice::speech::Speaker>,
 }
 
 #[derive(Clone, Debug)]
@@ -367,13 +1015,50 @@ struct DaemonHealth {
     switched: bool,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+struct CatchUp {
+    since: i64,
+    summary: String,
+    #[serde(default)]
+    agent_context: String,
+}
+
+/// Where the UI event subscription connects; a change reconnects it.
+#[derive(Clone, Debug, Hash)]
+struct DaemonEventSource {
+    daemon_url: String,
+    token: String,
+    user_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct DaemonEventFrame {
+    event_type: String,
+}
+
+#[derive(Clone, Debug)]
+enum PromptStreamEvent {
+    Queued(usize),
+    Token(String),
+    Done,
+}
+
 #[derive(Clone, Debug)]
 enum Message {
     Tick,
+    DaemonEventStreamChanged(bool),
+    DaemonEvent(DaemonEventFrame),
     TabSelected(UiTab),
     ComposerChanged(String),
     SendPressed,
-    ResponseReady(Result<String, String>),
+    VoicePressed,
+    VoiceTranscribed(Result<String, String>),
+    ResponseStream(Result<PromptStreamEvent, String>),
+    CatchUpLoaded(Result<CatchUp, String>),
+    ChatThreadsLoaded(Result<ChatThreadsApiResponse, String>),
+    HeatmapLoaded(Result<ActivityHeatmap, String>),
+    AggregateInsightsLoaded(Result<AggregateInsights, String>),
+    DismissCatchUp,
     HealthChecked(DaemonHealth),
     StartDaemonPressed,
     StopDaemonPressed,
@@ -399,24 +1084,57 @@ enum Message {
     ProactiveChatSeverityChanged(String),
     ProactiveChatQuietStartChanged(String),
     ProactiveChatQuietEndChanged(String),
+    ToggleSpeakChatReplies,
+    ToggleSpeakInbox,
     AddMcpServer,
     RemoveMcpServer(usize),
     McpServerNameChanged(usize, String),
     McpServerUrlChanged(usize, String),
+    McpServerCommandChanged(usize, String),
     McpServerHeaderKeyChanged(usize, String),
     McpServerHeaderValueChanged(usize, String),
     AddHttpServer,
+    AddInboxRule,
+    RemoveInboxRule(usize),
+    InboxRuleChanged(usize, InboxRuleField, String),
+    InboxRuleHumanActionCycled(usize),
     RemoveHttpServer(usize),
     HttpServerNameChanged(usize, String),
     HttpServerUrlChanged(usize, String),
     HttpServerHeaderKeyChanged(usize, String),
     HttpServerHeaderValueChanged(usize, String),
+    AddZap,
+    RemoveZap(usize),
+    ZapNameChanged(usize, String),
+    ZapUrlChanged(usize, String),
+    ZapFieldsChanged(usize, String),
+    ZapInboundUserChanged(usize, String),
     MarkdownLinkClicked(String),
     ContextEdited(text_editor::Action),
+    MemoryQueryChanged(String),
+    MemoryRerankToggled,
+    MemorySearchPressed,
+    MemorySearchFinished(Result<Vec<MemoryHit>, String>),
     HeartbeatEdited(text_editor::Action),
     RunDoctorPressed,
     DoctorFinished(Result<DoctorResponse, String>),
+    RepairSchemaPressed,
+    SchemaRepairFinished(Result<SchemaRepairResponse, String>),
     SecurityFinished(Result<SecurityAuditResponse, String>),
+    ToolPostureRefresh,
+    ToolPostureLoaded(Result<Vec<ToolPostureRow>, String>),
+    SecretsRefresh,
+    SecretsLoaded(Result<Vec<SecretRow>, String>),
+    SecretNameChanged(String),
+    SecretValueChanged(String),
+    SecretDescriptionChanged(String),
+    SaveSecretPressed,
+    DeleteSecretPressed(String),
+    SecretChanged(Result<String, String>),
+    ConsentRefresh,
+    ConsentLoaded(Result<Vec<ConsentLogRow>, String>),
+    RevokeConsentPressed(i32),
+    ConsentChanged(Result<String, String>),
     RefreshSolanaWallet,
     SolanaWalletLoaded(Result<Option<String>, String>),
     CopyToClipboard(String),
@@ -429,16 +1147,90 @@ enum Message {
     InboxReopen(String),
     InboxSnooze(String),
     InboxActionFinished(Result<String, String>),
+    InboxDecideApproval(String, bool),
+    InboxDecideConsent(String, bool),
+    InboxSelectToggled(String),
+    InboxNoteToggled(String),
+    InboxNoteDraftChanged(String),
+    InboxNoteSubmitted,
+    InboxAttachToggled(String),
+    InboxAttachPathChanged(String),
+    InboxAttachSubmitted,
+    KanbanDragStarted(String),
+    KanbanHovered(KanbanColumn),
+    KanbanHoverEnded(KanbanColumn),
+    KanbanDropped(KanbanColumn),
+    KanbanDragCancelled,
+    KanbanMoveFinished(String, Result<String, String>),
+    InboxSelectionCleared,
+    InboxBulkAction(InboxActionKind),
+    InboxBulkFinished(Result<String, String>),
+    InboxToggleSmartListGrouping,
+    InboxToggleSmartList(SmartList),
+    TrashLoaded(Result<Vec<TrashBatchRow>, String>),
+    ProjectsLoaded(Result<Vec<ProjectRow>, String>),
+    ProjectSelected(ProjectChoice),
+    TrashRestore(TrashBatchRow),
+    TrashRestoreFinished(Result<String, String>),
+    SearchQueryChanged(String),
+    SearchSubmitted,
+    SearchFinished(Result<SearchApiResponse, String>),
+    SearchCleared,
+    SearchOpenHit(SearchHitRow),
+    TemplatePathChanged(String),
+    TemplateReviewPressed,
+    TemplateReviewLoaded(Box<Result<TemplateReview, String>>),
+    TemplateImportPressed,
+    TemplateImportFinished(Result<String, String>),
+    TemplateReviewDismissed,
+    ExportViewPressed(UiTab, ExportFormat),
+    ExportViewFinished(Result<String, String>),
     RefreshReminderDeliveryEvents,
+    RefreshBackupStatus,
+    BackupStatusLoaded(Result<BackupStatusApiResponse, String>),
+    BackupNowPressed,
+    BackupFinished(Result<String, String>),
+    RefreshEstimateAccuracy,
+    EstimateAccuracyLoaded(Result<Vec<String>, String>),
+    RefreshSchedulerJobs,
+    SchedulerJobsLoaded(Result<Vec<String>, String>),
+    RefreshDeadLetters,
+    DeadLettersLoaded(Result<Vec<DeadLetterRow>, String>),
+    DeadLetterRedrive(i32),
+    DeadLetterDiscard(i32),
+    DeadLetterActionDone(Result<(), String>),
+    RefreshDependencyCheck,
+    DependencyCheckLoaded(Result<DependencyCheckRow, String>),
     ReminderDeliveryEventsLoaded(Result<Vec<String>, String>),
     AuditRefreshRequested,
-    AuditEventsLoaded(Result<Vec<AuditEventRow>, String>),
+    AuditEventsLoaded(Result<AuditEventsPage, String>),
+    AuditLoadOlder,
     TimelineOpenItem(String),
     TimelineOpenAudit(String),
     OpenChatWithContext(String),
     OpenChatAtEvent(String, i64),
     ChatClearAnchor,
     AuditClearFilter,
+    UserActivity,
+    PrivacyLockIdleMinutesChanged(String),
+    PrivacyLockPassphraseChanged(String),
+    LockNowPressed,
+    TogglePresentationMode,
+    PresentationNamesChanged(String),
+    RemoteStorageKindChanged(String),
+    RemoteStorageLocationChanged(String),
+    RemoteStorageBucketChanged(String),
+    RemoteStorageRegionChanged(String),
+    RemoteStorageAccountChanged(String),
+    RemoteStorageSecretChanged(String),
+    RemoteStoragePrefixChanged(String),
+    RemoteStoragePassphraseChanged(String),
+    TestRemoteStoragePressed,
+    RemoteStorageTestFinished(Result<RemoteStorageReport, String>),
+    UnlockInputChanged(String),
+    UnlockPressed,
+    UnlockFinished(Result<bool, String>),
+    PrivacyLockReported(Result<(), String>),
 }
 
 pub fn launch_ui(config: IcedUiLaunchConfig) -> iced::Result {
@@ -463,15 +1255,9 @@ pub fn launch_ui(config: IcedUiLaunchConfig) -> iced::Result {
                     ),
                     Message::InboxLoaded,
                 ),
-                Task::perform(
-                    fetch_audit_events(
-                        state.daemon_url.clone(),
-                        state.token.clone(),
-                        state.user_id.clone(),
-                        200,
-                    ),
-                    Message::AuditEventsLoaded,
-                ),
+                state.audit_fetch_task(None),
+                // A previous session may have exited while locked.
+                report_privacy_lock_task(&state, false),
                 Task::perform(load_settings(state.db_path.clone()), |result| {
                     Message::SettingsLoaded(Box::new(result))
                 }),
@@ -508,14 +1294,46 @@ fn app_theme(_state: &ButterflyIcedApp) -> Theme {
     Theme::Dark
 }
 
-fn subscription(_state: &ButterflyIcedApp) -> Subscription<Message> {
-    time::every(Duration::from_secs(2)).map(|_| Message::Tick)
+fn subscription(state: &ButterflyIcedApp) -> Subscription<Message> {
+    let mut subscriptions = vec![
+        time::every(Duration::from_secs(2)).map(|_| Message::Tick),
+        iced::event::listen_with(user_activity_event),
+    ];
+    if state.daemon_running && !state.privacy_locked {
+        subscriptions.push(Subscription::run_with(
+            DaemonEventSource {
+                daemon_url: state.daemon_url.clone(),
+                token: state.token.clone(),
+                user_id: state.user_id.clone(),
+            },
+            daemon_event_stream,
+        ));
+    }
+    Subscription::batch(subscriptions)
+}
+
+/// Key presses, clicks and scrolling count as activity for the idle lock;
+/// pointer movement alone does not.
+fn user_activity_event(
+    event: iced::Event,
+    _status: iced::event::Status,
+    _window: iced::window::Id,
+) -> Option<Message> {
+    match event {
+        iced::Event::Keyboard(iced::keyboard::Event::KeyPressed { .. })
+        | iced::Event::Mouse(iced::mouse::Event::ButtonPressed(_))
+        | iced::Event::Mouse(iced::mouse::Event::WheelScrolled { .. }) => {
+            Some(Message::UserActivity)
+        }
+        _ => None,
+    }
 }
 
 impl ButterflyIcedApp {
     fn new(flags: IcedUiLaunchConfig) -> Self {
         let manage_local_daemon = env_flag_enabled("BUTTERFLY_UI_MANAGE_DAEMON", true);
         Self {
+            clock: system_clock(),
             daemon_url: normalize_daemon_url(&flags.daemon_url),
             user_id: flags.user_id,
             db_path: flags.db_path,
@@ -523,9 +1341,15 @@ impl ButterflyIcedApp {
             active_tab: UiTab::Chat,
             composer: String::new(),
             busy: false,
+            streaming_message_id: None,
+            prompt_queue_position: None,
+            catch_up: None,
+            chat_model: None,
+            catch_up_context: None,
             error: String::new(),
             daemon_running: false,
             daemon_starting: false,
+            event_stream_connected: false,
             daemon_status: if manage_local_daemon {
                 "Local daemon control enabled".to_string()
             } else {
@@ -540,15 +1364,49 @@ impl ButterflyIcedApp {
             inbox_error: String::new(),
             inbox_refresh_in_flight: true,
             inbox_action_origin_ref_in_flight: None,
+            inbox_selected: HashSet::new(),
+            inbox_bulk_in_flight: false,
+            inbox_note_origin_ref: None,
+            inbox_note_draft: String::new(),
+            inbox_attach_origin_ref: None,
+            inbox_attach_path: String::new(),
+            kanban_dragging: None,
+            kanban_hover: None,
+            kanban_rollback: HashMap::new(),
+            kanban_status: String::new(),
             inbox_last_refresh_ts: 0,
+            inbox_stale: false,
+            inbox_group_by_smart_list: false,
+            inbox_collapsed_smart_lists: HashSet::from([SmartList::Later, SmartList::Someday]),
+            trash_batches: vec![],
+            trash_restore_in_flight: None,
+            projects: vec![],
+            selected_project: None,
+            search_query: String::new(),
+            search_hits: vec![],
+            search_status: String::new(),
+            search_in_flight: false,
+            template_path: String::new(),
+            template_review: None,
+            template_status: String::new(),
+            template_error: String::new(),
+            view_export_status: String::new(),
+            template_in_flight: false,
             last_badge_actionable_count: None,
             audit_events: vec![],
             audit_status: "Loading audit events...".to_string(),
             audit_error: String::new(),
             audit_refresh_in_flight: true,
             audit_last_refresh_ts: 0,
+            audit_stale: false,
             audit_last_activity_bridge_ts: 0,
             audit_origin_filter: None,
+            audit_next_before_id: None,
+            activity_heatmap: None,
+            aggregate_insights: None,
+            heatmap_error: String::new(),
+            heatmap_refresh_in_flight: false,
+            heatmap_last_refresh_ts: 0,
             timeline_focus_origin_ref: None,
             chat_origin_anchor: None,
             chat_anchor_message_id: None,
@@ -558,6 +1416,7 @@ impl ButterflyIcedApp {
             settings: SettingsForm::default(),
             settings_status: String::new(),
             settings_error: String::new(),
+            remote_storage_status: String::new(),
             doctor_status: String::new(),
             doctor_error: String::new(),
             doctor_overall: String::new(),
@@ -566,9 +1425,33 @@ impl ButterflyIcedApp {
             security_error: String::new(),
             security_overall: String::new(),
             security_findings: vec![],
+            tool_postures: vec![],
+            tool_posture_status: String::new(),
+            tool_posture_in_flight: false,
+            secrets: vec![],
+            secrets_status: String::new(),
+            secrets_in_flight: false,
+            consent_log: vec![],
+            consent_status: String::new(),
+            consent_in_flight: false,
+            secret_name: String::new(),
+            secret_value: String::new(),
+            secret_description: String::new(),
             reminder_delivery_status: String::new(),
             reminder_delivery_error: String::new(),
             reminder_delivery_events: vec![],
+            backup_status: String::new(),
+            backup_error: String::new(),
+            backup_snapshots: vec![],
+            backup_in_flight: false,
+            estimate_accuracy_status: String::new(),
+            estimate_accuracy_lines: vec![],
+            scheduler_jobs_status: String::new(),
+            scheduler_jobs_lines: vec![],
+            dead_letters: vec![],
+            dead_letters_status: String::new(),
+            dependency_check: None,
+            dependency_check_status: String::new(),
             solana_wallet_address: None,
             solana_wallet_status: String::new(),
             solana_wallet_fetch_in_flight: false,
@@ -576,9 +1459,25 @@ impl ButterflyIcedApp {
             context_preview_items: vec![],
             heartbeat_preview_items: vec![],
             context_editor: text_editor::Content::new(),
+            memory_query: String::new(),
+            memory_rerank: false,
+            memory_hits: Vec::new(),
+            memory_search_status: String::new(),
+            memory_search_in_flight: false,
             heartbeat_editor: text_editor::Content::new(),
             manage_local_daemon,
             daemon_autostart_attempted: false,
+            privacy_locked: false,
+            last_interaction_ts: now_unix_ts(),
+            unlock_input: String::new(),
+            unlock_error: String::new(),
+            unlock_in_flight: false,
+            presentation_mode: false,
+            redactor: crate::presentation::Redactor::default(),
+            voice_recording: None,
+            voice_transcribing: false,
+            voice_status: String::new(),
+            speaker: None,
         }
     }
 
@@ -598,6 +1497,103 @@ impl ButterflyIcedApp {
         }
     }
 
+    fn append_streaming_reply(&mut self, chunk: &str) {
+        let streaming = self.streaming_message_id.and_then(|id| {
+            self.chat_messages
+                .iter_mut()
+                .find(|message| message.id == id)
+        });
+        match streaming {
+            Some(message) => {
+                message.text.push_str(chunk);
+                message.markdown_items = parse_markdown_items(&message.text);
+            }
+            None => {
+                self.push_chat(MessageRole::Bot, chunk.to_string());
+                self.streaming_message_id = self.chat_messages.last().map(|message| message.id);
+            }
+        }
+    }
+
+    /// Ends the in-flight reply, dropping its bubble if nothing ever arrived.
+    fn finish_streaming_reply(&mut self) {
+        self.busy = false;
+        self.prompt_queue_position = None;
+        if let Some(id) = self.streaming_message_id.take() {
+            self.chat_messages
+                .retain(|message| message.id != id || !message.text.trim().is_empty());
+            if let Some(reply) = self.chat_messages.iter().find(|message| message.id == id) {
+                let reply = reply.text.clone();
+                self.speak(UiTab::Chat, &reply);
+            }
+        }
+    }
+
+    /// Reads `text` aloud when `tab` is set to speak. Nothing is said while
+    /// locked, and presentation mode masks names as it does on screen.
+    fn speak(&self, tab: UiTab, text: &str) {
+        let enabled = match tab {
+            UiTab::Chat => self.settings.speak_chat_replies,
+            UiTab::Inbox => self.settings.speak_inbox,
+            _ => false,
+        };
+        if !enabled || self.privacy_locked {
+            return;
+        }
+        if let Some(speaker) = &self.speaker {
+            speaker.say(&shown(self, text));
+        }
+    }
+
+    fn restart_speaker(&mut self) {
+        self.speaker = None;
+        if !self.settings.speak_chat_replies && !self.settings.speak_inbox {
+            return;
+        }
+        let tools = crate::config::Config::from_store(&self.db_path)
+            .ok()
+            .and_then(|config| config.tools);
+        let speech = crate::voice::VoiceConfig::from_tools(tools.as_ref()).speech;
+        self.speaker = Some(crate::voice::speech::Speaker::start(speech));
+    }
+
+    /// Fetches the newest audit page, or the page before `before_id`, with
+    /// the current origin filter applied by the daemon.
+    fn audit_fetch_task(&self, before_id: Option<i32>) -> Task<Message> {
+        Task::perform(
+            fetch_audit_events(
+                self.daemon_url.clone(),
+                self.token.clone(),
+                self.user_id.clone(),
+                self.audit_origin_filter.clone(),
+                before_id,
+            ),
+            Message::AuditEventsLoaded,
+        )
+    }
+
+    /// The heatmap and the anonymized trends refresh together.
+    fn insights_fetch_task(&self) -> Task<Message> {
+        Task::batch(vec![
+            Task::perform(
+                fetch_activity_heatmap(
+                    self.daemon_url.clone(),
+                    self.token.clone(),
+                    self.user_id.clone(),
+                ),
+                Message::HeatmapLoaded,
+            ),
+            Task::perform(
+                fetch_aggregate_insights(
+                    self.daemon_url.clone(),
+                    self.token.clone(),
+                    self.user_id.clone(),
+                ),
+                Message::AggregateInsightsLoaded,
+            ),
+        ])
+    }
+
     fn push_activity(&mut self, text: String) {
         let markdown_items = parse_markdown_items(&text);
         self.activity_messages.push(ChatMessage {
@@ -615,6 +1611,64 @@ impl ButterflyIcedApp {
     }
 }
 
+impl ButterflyIcedApp {
+    /// Pushed events only count while the subscription is running.
+    fn streaming_events(&self) -> bool {
+        self.event_stream_connected && self.daemon_running && !self.privacy_locked
+    }
+
+    fn idle_lock_due(&self, now: i64) -> bool {
+        let idle_minutes = self
+            .settings
+            .privacy_lock_idle_minutes
+            .trim()
+            .parse::<i64>()
+            .unwrap_or(0);
+        idle_minutes > 0
+            && now.saturating_sub(self.last_interaction_ts) >= idle_minutes.saturating_mul(60)
+    }
+
+    /// Hides the UI until the passphrase is entered. Without a passphrase
+    /// there would be no way back in, so locking is refused.
+    fn engage_privacy_lock(&mut self) -> Task<Message> {
+        if !crate::privacy_lock::has_passphrase() {
+            self.settings_error = "Set a lock passphrase in Config before locking".to_string();
+            // Don't retry every tick until the user is active again.
+            self.last_interaction_ts = now_unix_ts();
+            return Task::none();
+        }
+        self.privacy_locked = true;
+        self.unlock_input.clear();
+        self.unlock_error.clear();
+        report_privacy_lock_task(self, true)
+    }
+}
+
+fn presentation_redactor(names: &str) -> crate::presentation::Redactor {
+    crate::presentation::Redactor::new(names.split(','))
+}
+
+/// How user data is drawn: as-is, or masked while presentation mode is on.
+fn shown(state: &ButterflyIcedApp, value: &str) -> String {
+    if state.presentation_mode {
+        state.redactor.redact(value).into_owned()
+    } else {
+        value.to_string()
+    }
+}
+
+fn report_privacy_lock_task(state: &ButterflyIcedApp, locked: bool) -> Task<Message> {
+    Task::perform(
+        report_privacy_lock(
+            state.daemon_url.clone(),
+            state.token.clone(),
+            state.user_id.clone(),
+            locked,
+        ),
+        Message::PrivacyLockReported,
+    )
+}
+
 impl Drop for ButterflyIcedApp {
     fn drop(&mut self) {
         let _ = set_macos_dock_badge(0);
@@ -636,9 +1690,19 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
 
             let now = now_unix_ts();
+            if !state.privacy_locked && state.idle_lock_due(now) {
+                tasks.push(state.engage_privacy_lock());
+            }
+            let poll_seconds = if state.streaming_events() {
+                STREAMED_POLL_REFRESH_SECONDS
+            } else {
+                POLL_REFRESH_SECONDS
+            };
             if !state.inbox_refresh_in_flight
-                && now.saturating_sub(state.inbox_last_refresh_ts) >= 15
+                && (state.inbox_stale
+                    || now.saturating_sub(state.inbox_last_refresh_ts) >= poll_seconds)
             {
+                state.inbox_stale = false;
                 state.inbox_refresh_in_flight = true;
                 tasks.push(Task::perform(
                     load_inbox_items(
@@ -651,18 +1715,19 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
 
             if !state.audit_refresh_in_flight
-                && now.saturating_sub(state.audit_last_refresh_ts) >= 15
+                && (state.audit_stale
+                    || now.saturating_sub(state.audit_last_refresh_ts) >= poll_seconds)
             {
+                state.audit_stale = false;
                 state.audit_refresh_in_flight = true;
-                tasks.push(Task::perform(
-                    fetch_audit_events(
-                        state.daemon_url.clone(),
-                        state.token.clone(),
-                        state.user_id.clone(),
-                        200,
-                    ),
-                    Message::AuditEventsLoaded,
-                ));
+                tasks.push(state.audit_fetch_task(None));
+            }
+
+            if !state.heatmap_refresh_in_flight
+                && now.saturating_sub(state.heatmap_last_refresh_ts) >= HEATMAP_REFRESH_SECONDS
+            {
+                state.heatmap_refresh_in_flight = true;
+                tasks.push(state.insights_fetch_task());
             }
 
             if tasks.is_empty() {
@@ -671,8 +1736,41 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                 Task::batch(tasks)
             }
         }
+        Message::DaemonEventStreamChanged(connected) => {
+            if connected != state.event_stream_connected {
+                state.event_stream_connected = connected;
+                state.push_activity(if connected {
+                    "live updates connected".to_string()
+                } else {
+                    "live updates disconnected; polling".to_string()
+                });
+            }
+            Task::none()
+        }
+        Message::DaemonEvent(event) => {
+            // Every event lands in the audit log, but it is written after the
+            // broadcast, so the audit fetch waits for the next tick.
+            state.audit_stale = true;
+            if !daemon_event_touches_inbox(&event.event_type) {
+                return Task::none();
+            }
+            if state.inbox_refresh_in_flight {
+                state.inbox_stale = true;
+                return Task::none();
+            }
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            )
+        }
         Message::TabSelected(tab) => {
             state.active_tab = tab;
+            state.kanban_dragging = None;
             if tab == UiTab::Inbox && !state.inbox_refresh_in_flight {
                 state.inbox_refresh_in_flight = true;
                 return Task::perform(
@@ -686,15 +1784,66 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             if tab == UiTab::Audit && !state.audit_refresh_in_flight {
                 state.audit_refresh_in_flight = true;
-                return Task::perform(
-                    fetch_audit_events(
-                        state.daemon_url.clone(),
-                        state.token.clone(),
-                        state.user_id.clone(),
-                        200,
+                return state.audit_fetch_task(None);
+            }
+            if tab == UiTab::Insights && !state.heatmap_refresh_in_flight {
+                state.heatmap_refresh_in_flight = true;
+                return state.insights_fetch_task();
+            }
+            if tab == UiTab::Dependencies {
+                return update(state, Message::RefreshDependencyCheck);
+            }
+            if tab == UiTab::Diagnostics && state.daemon_running && !state.backup_in_flight {
+                state.backup_in_flight = true;
+                return Task::batch([
+                    Task::perform(
+                        load_backup_status(state.daemon_url.clone(), state.token.clone()),
+                        Message::BackupStatusLoaded,
                     ),
-                    Message::AuditEventsLoaded,
-                );
+                    Task::perform(
+                        fetch_estimate_accuracy(
+                            state.daemon_url.clone(),
+                            state.token.clone(),
+                            state.user_id.clone(),
+                        ),
+                        Message::EstimateAccuracyLoaded,
+                    ),
+                    Task::perform(
+                        fetch_scheduler_jobs(state.daemon_url.clone(), state.token.clone()),
+                        Message::SchedulerJobsLoaded,
+                    ),
+                    Task::perform(
+                        fetch_dead_letters(
+                            state.daemon_url.clone(),
+                            state.token.clone(),
+                            state.user_id.clone(),
+                        ),
+                        Message::DeadLettersLoaded,
+                    ),
+                ]);
+            }
+            if tab == UiTab::Settings && state.daemon_running && !state.tool_posture_in_flight {
+                state.tool_posture_in_flight = true;
+                state.secrets_in_flight = true;
+                state.consent_in_flight = true;
+                return Task::batch(vec![
+                    Task::perform(
+                        load_tool_posture(state.daemon_url.clone(), state.token.clone()),
+                        Message::ToolPostureLoaded,
+                    ),
+                    Task::perform(
+                        load_secrets(state.daemon_url.clone(), state.token.clone()),
+                        Message::SecretsLoaded,
+                    ),
+                    Task::perform(
+                        load_consent_log(
+                            state.daemon_url.clone(),
+                            state.token.clone(),
+                            state.user_id.clone(),
+                        ),
+                        Message::ConsentLoaded,
+                    ),
+                ]);
             }
             Task::none()
         }
@@ -717,19 +1866,13 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
         }
         Message::TimelineOpenAudit(origin_ref) => {
             state.audit_origin_filter = Some(origin_ref.clone());
+            state.audit_events.clear();
+            state.audit_next_before_id = None;
             state.active_tab = UiTab::Audit;
             state.push_activity(format!("timeline drilldown → audit ({origin_ref})"));
             if !state.audit_refresh_in_flight {
                 state.audit_refresh_in_flight = true;
-                return Task::perform(
-                    fetch_audit_events(
-                        state.daemon_url.clone(),
-                        state.token.clone(),
-                        state.user_id.clone(),
-                        200,
-                    ),
-                    Message::AuditEventsLoaded,
-                );
+                return state.audit_fetch_task(None);
             }
             Task::none()
         }
@@ -766,12 +1909,79 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
         }
         Message::AuditClearFilter => {
             state.audit_origin_filter = None;
+            state.audit_events.clear();
+            state.audit_next_before_id = None;
+            if state.audit_refresh_in_flight {
+                return Task::none();
+            }
+            state.audit_refresh_in_flight = true;
+            state.audit_fetch_task(None)
+        }
+        Message::InboxToggleSmartListGrouping => {
+            state.inbox_group_by_smart_list = !state.inbox_group_by_smart_list;
+            Task::none()
+        }
+        Message::InboxToggleSmartList(list) => {
+            if !state.inbox_collapsed_smart_lists.remove(&list) {
+                state.inbox_collapsed_smart_lists.insert(list);
+            }
             Task::none()
         }
         Message::ComposerChanged(value) => {
             state.composer = value;
             Task::none()
         }
+        Message::VoicePressed => {
+            if state.voice_transcribing {
+                return Task::none();
+            }
+            if let Some(recording) = state.voice_recording.take() {
+                state.voice_transcribing = true;
+                state.voice_status = "Transcribing...".to_string();
+                return Task::perform(
+                    transcribe_recording(
+                        state.daemon_url.clone(),
+                        state.token.clone(),
+                        state.user_id.clone(),
+                        recording,
+                    ),
+                    Message::VoiceTranscribed,
+                );
+            }
+            if !state.daemon_running {
+                state.voice_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            let tools = crate::config::Config::from_store(&state.db_path)
+                .ok()
+                .and_then(|config| config.tools);
+            let voice = crate::voice::VoiceConfig::from_tools(tools.as_ref());
+            match crate::voice::capture::Recording::start(&voice.capture) {
+                Ok(recording) => {
+                    state.voice_recording = Some(recording);
+                    state.voice_status = "Listening... press Stop when done".to_string();
+                }
+                Err(err) => state.voice_status = err.to_string(),
+            }
+            Task::none()
+        }
+        Message::VoiceTranscribed(result) => {
+            state.voice_transcribing = false;
+            match result {
+                Ok(text) if text.trim().is_empty() => {
+                    state.voice_status = "Heard nothing".to_string();
+                }
+                Ok(text) => {
+                    if !state.composer.trim().is_empty() {
+                        state.composer.push(' ');
+                    }
+                    state.composer.push_str(text.trim());
+                    state.voice_status.clear();
+                }
+                Err(err) => state.voice_status = format!("Voice input failed: {err}"),
+            }
+            Task::none()
+        }
         Message::SendPressed => {
             if state.busy || state.composer.trim().is_empty() {
                 return Task::none();
@@ -787,21 +1997,75 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             state.composer.clear();
             state.busy = true;
             state.error.clear();
+            state.push_chat(MessageRole::Bot, String::new());
+            state.streaming_message_id = state.chat_messages.last().map(|message| message.id);
 
             let daemon_url = state.daemon_url.clone();
             let user_id = state.user_id.clone();
             let token = state.token.clone();
+            let context = state.catch_up_context.take();
 
-            Task::perform(
-                send_prompt(daemon_url, user_id, token, prompt),
-                Message::ResponseReady,
+            Task::run(
+                send_prompt(daemon_url, user_id, token, prompt, context),
+                Message::ResponseStream,
             )
         }
-        Message::ResponseReady(result) => {
-            state.busy = false;
+        Message::CatchUpLoaded(result) => {
+            match result {
+                Ok(catch_up) if !catch_up.summary.is_empty() => {
+                    state.push_activity(format!("while you were away: {}", catch_up.summary));
+                    if !catch_up.agent_context.is_empty() {
+                        state.catch_up_context = Some(catch_up.agent_context.clone());
+                    }
+                    state.catch_up = Some(catch_up);
+                }
+                Ok(_) => {}
+                Err(err) => state.push_activity(format!("catch-up unavailable: {err}")),
+            }
+            Task::none()
+        }
+        Message::ChatThreadsLoaded(result) => {
             match result {
-                Ok(reply) => state.push_chat(MessageRole::Bot, reply),
+                Ok(response) => state.chat_model = Some(ChatModelInfo::from_threads(response)),
+                Err(err) => state.push_activity(format!("chat model unavailable: {err}")),
+            }
+            Task::none()
+        }
+        Message::HeatmapLoaded(result) => {
+            state.heatmap_refresh_in_flight = false;
+            state.heatmap_last_refresh_ts = now_unix_ts();
+            match result {
+                Ok(heatmap) => {
+                    state.heatmap_error.clear();
+                    state.activity_heatmap = Some(heatmap);
+                }
+                Err(err) => state.heatmap_error = err,
+            }
+            Task::none()
+        }
+        Message::AggregateInsightsLoaded(result) => {
+            match result {
+                Ok(insights) => state.aggregate_insights = Some(insights),
+                Err(err) => state.push_activity(format!("trends unavailable: {err}")),
+            }
+            Task::none()
+        }
+        Message::DismissCatchUp => {
+            state.catch_up = None;
+            Task::none()
+        }
+        Message::ResponseStream(event) => {
+            match event {
+                Ok(PromptStreamEvent::Queued(position)) => {
+                    state.prompt_queue_position = Some(position);
+                }
+                Ok(PromptStreamEvent::Token(chunk)) => {
+                    state.prompt_queue_position = None;
+                    state.append_streaming_reply(&chunk);
+                }
+                Ok(PromptStreamEvent::Done) => state.finish_streaming_reply(),
                 Err(err) => {
+                    state.finish_streaming_reply();
                     state.error = err.clone();
                     state.push_activity(format!("chat error: {err}"));
                 }
@@ -832,15 +2096,25 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                 }
                 if !state.history_loaded {
                     state.history_loaded = true;
-                    return Task::perform(
-                        run_chat_history_request(
-                            state.daemon_url.clone(),
-                            state.token.clone(),
-                            state.user_id.clone(),
-                            60,
+                    return Task::batch([
+                        Task::perform(
+                            run_chat_history_request(
+                                state.daemon_url.clone(),
+                                state.token.clone(),
+                                state.user_id.clone(),
+                                60,
+                            ),
+                            Message::HistoryLoaded,
                         ),
-                        Message::HistoryLoaded,
-                    );
+                        Task::perform(
+                            fetch_chat_threads(
+                                state.daemon_url.clone(),
+                                state.token.clone(),
+                                state.user_id.clone(),
+                            ),
+                            Message::ChatThreadsLoaded,
+                        ),
+                    ]);
                 }
             } else if state.manage_local_daemon
                 && !state.daemon_autostart_attempted
@@ -886,7 +2160,7 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             state.daemon_starting = true;
             if state.manage_local_daemon {
                 return Task::perform(
-                    stop_daemon_by_url(state.daemon_url.clone()),
+                    stop_daemon_by_url(state.daemon_url.clone(), state.token.clone()),
                     Message::DaemonStopFinished,
                 );
             }
@@ -957,7 +2231,26 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                             find_latest_chat_message_id(&state.chat_messages, origin, None)
                         });
                     state.push_activity("chat history loaded".to_string());
-                    return scroll_chat_to_anchor_task(state);
+                    let last_seen = state
+                        .chat_messages
+                        .iter()
+                        .map(|message| message.timestamp)
+                        .max();
+                    let Some(since) = last_seen else {
+                        return scroll_chat_to_anchor_task(state);
+                    };
+                    return Task::batch([
+                        scroll_chat_to_anchor_task(state),
+                        Task::perform(
+                            fetch_catch_up(
+                                state.daemon_url.clone(),
+                                state.token.clone(),
+                                state.user_id.clone(),
+                                since,
+                            ),
+                            Message::CatchUpLoaded,
+                        ),
+                    ]);
                 }
                 Err(err) => {
                     state.error = format!("History load failed: {err}");
@@ -991,15 +2284,7 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                             ),
                             Message::InboxLoaded,
                         ),
-                        Task::perform(
-                            fetch_audit_events(
-                                state.daemon_url.clone(),
-                                state.token.clone(),
-                                state.user_id.clone(),
-                                200,
-                            ),
-                            Message::AuditEventsLoaded,
-                        ),
+                        state.audit_fetch_task(None),
                     ]);
                 }
                 Err(err) => state.error = format!("Clear data failed: {err}"),
@@ -1014,7 +2299,9 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
         Message::SettingsLoaded(result) => {
             match *result {
                 Ok(loaded) => {
+                    state.redactor = presentation_redactor(&loaded.form.presentation_names);
                     state.settings = loaded.form;
+                    state.restart_speaker();
                     state.context_editor =
                         text_editor::Content::with_text(&state.settings.prompt_text);
                     state.heartbeat_editor =
@@ -1058,6 +2345,8 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                 Ok(status) => {
                     state.settings_status = status.clone();
                     state.settings_error.clear();
+                    state.settings.privacy_lock_passphrase.clear();
+                    state.restart_speaker();
                     state.push_activity(status);
                     state.solana_wallet_refresh_pending = true;
                     if state.daemon_running && !state.solana_wallet_fetch_in_flight {
@@ -1132,6 +2421,129 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             state.settings.proactive_chat_quiet_end_hhmm = value;
             Task::none()
         }
+        Message::ToggleSpeakChatReplies => {
+            state.settings.speak_chat_replies = !state.settings.speak_chat_replies;
+            Task::none()
+        }
+        Message::ToggleSpeakInbox => {
+            state.settings.speak_inbox = !state.settings.speak_inbox;
+            Task::none()
+        }
+        Message::PrivacyLockIdleMinutesChanged(value) => {
+            state.settings.privacy_lock_idle_minutes = value;
+            Task::none()
+        }
+        Message::PrivacyLockPassphraseChanged(value) => {
+            state.settings.privacy_lock_passphrase = value;
+            Task::none()
+        }
+        Message::UserActivity => {
+            if !state.privacy_locked {
+                state.last_interaction_ts = now_unix_ts();
+            }
+            Task::none()
+        }
+        Message::LockNowPressed => state.engage_privacy_lock(),
+        Message::TogglePresentationMode => {
+            state.presentation_mode = !state.presentation_mode;
+            Task::none()
+        }
+        Message::PresentationNamesChanged(value) => {
+            state.redactor = presentation_redactor(&value);
+            state.settings.presentation_names = value;
+            Task::none()
+        }
+        Message::RemoteStorageKindChanged(value) => {
+            state.settings.remote_storage_kind = value;
+            Task::none()
+        }
+        Message::RemoteStorageLocationChanged(value) => {
+            state.settings.remote_storage_location = value;
+            Task::none()
+        }
+        Message::RemoteStorageBucketChanged(value) => {
+            state.settings.remote_storage_bucket = value;
+            Task::none()
+        }
+        Message::RemoteStorageRegionChanged(value) => {
+            state.settings.remote_storage_region = value;
+            Task::none()
+        }
+        Message::RemoteStorageAccountChanged(value) => {
+            state.settings.remote_storage_account = value;
+            Task::none()
+        }
+        Message::RemoteStorageSecretChanged(value) => {
+            state.settings.remote_storage_secret = value;
+            Task::none()
+        }
+        Message::RemoteStoragePrefixChanged(value) => {
+            state.settings.remote_storage_prefix = value;
+            Task::none()
+        }
+        Message::RemoteStoragePassphraseChanged(value) => {
+            state.settings.remote_storage_passphrase = value;
+            Task::none()
+        }
+        Message::TestRemoteStoragePressed => {
+            if !state.daemon_running {
+                state.remote_storage_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.remote_storage_status = "Testing remote storage...".to_string();
+            Task::perform(
+                test_remote_storage_request(state.daemon_url.clone(), state.token.clone()),
+                Message::RemoteStorageTestFinished,
+            )
+        }
+        Message::RemoteStorageTestFinished(result) => {
+            state.remote_storage_status = match result {
+                Ok(report) => format!(
+                    "Connected to {} ({} ms round trip)",
+                    report.target, report.round_trip_ms
+                ),
+                Err(err) => format!("Connection failed: {err}"),
+            };
+            Task::none()
+        }
+        Message::UnlockInputChanged(value) => {
+            state.unlock_input = value;
+            state.unlock_error.clear();
+            Task::none()
+        }
+        Message::UnlockPressed => {
+            if state.unlock_in_flight || state.unlock_input.is_empty() {
+                return Task::none();
+            }
+            state.unlock_in_flight = true;
+            let passphrase = std::mem::take(&mut state.unlock_input);
+            Task::perform(verify_lock_passphrase(passphrase), Message::UnlockFinished)
+        }
+        Message::UnlockFinished(result) => {
+            state.unlock_in_flight = false;
+            match result {
+                Ok(true) => {
+                    state.privacy_locked = false;
+                    state.unlock_error.clear();
+                    state.last_interaction_ts = now_unix_ts();
+                    report_privacy_lock_task(state, false)
+                }
+                Ok(false) => {
+                    state.unlock_error = "Wrong passphrase".to_string();
+                    Task::none()
+                }
+                Err(err) => {
+                    state.unlock_error = err;
+                    Task::none()
+                }
+            }
+        }
+        Message::PrivacyLockReported(result) => {
+            if let Err(err) = result {
+                state.push_activity(format!("privacy lock state not sent to daemon: {err}"));
+            }
+            Task::none()
+        }
         Message::AddMcpServer => {
             state.settings.mcp_servers.push(UiServerRow::default());
             Task::none()
@@ -1154,6 +2566,12 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             Task::none()
         }
+        Message::McpServerCommandChanged(index, value) => {
+            if let Some(row) = state.settings.mcp_servers.get_mut(index) {
+                row.command = value;
+            }
+            Task::none()
+        }
         Message::McpServerHeaderKeyChanged(index, value) => {
             if let Some(row) = state.settings.mcp_servers.get_mut(index) {
                 row.header_key = value;
@@ -1166,6 +2584,41 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             Task::none()
         }
+        Message::AddInboxRule => {
+            state.settings.inbox_rules.push(UiInboxRuleRow::default());
+            Task::none()
+        }
+        Message::RemoveInboxRule(index) => {
+            if index < state.settings.inbox_rules.len() {
+                state.settings.inbox_rules.remove(index);
+            }
+            Task::none()
+        }
+        Message::InboxRuleChanged(index, field, value) => {
+            if let Some(rule) = state.settings.inbox_rules.get_mut(index) {
+                let target = match field {
+                    InboxRuleField::Name => &mut rule.name,
+                    InboxRuleField::SourceTypes => &mut rule.source_types,
+                    InboxRuleField::Keywords => &mut rule.keywords,
+                    InboxRuleField::DueWithinHours => &mut rule.due_within_hours,
+                    InboxRuleField::Labels => &mut rule.labels,
+                    InboxRuleField::Priority => &mut rule.priority,
+                    InboxRuleField::Owner => &mut rule.owner,
+                };
+                *target = value;
+            }
+            Task::none()
+        }
+        Message::InboxRuleHumanActionCycled(index) => {
+            if let Some(rule) = state.settings.inbox_rules.get_mut(index) {
+                rule.requires_human_action = match rule.requires_human_action {
+                    None => Some(true),
+                    Some(true) => Some(false),
+                    Some(false) => None,
+                };
+            }
+            Task::none()
+        }
         Message::AddHttpServer => {
             state
                 .settings
@@ -1203,17 +2656,289 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             Task::none()
         }
-        Message::MarkdownLinkClicked(uri) => {
-            state.push_activity(format!("link clicked: {uri}"));
-            let _ = open_uri_best_effort(&uri);
+        Message::AddZap => {
+            state.settings.zaps.push(UiZapRow::default());
             Task::none()
         }
-        Message::ContextEdited(action) => {
-            state.context_editor.perform(action);
+        Message::RemoveZap(index) => {
+            if index < state.settings.zaps.len() {
+                state.settings.zaps.remove(index);
+            }
+            Task::none()
+        }
+        Message::ZapNameChanged(index, value) => {
+            if let Some(row) = state.settings.zaps.get_mut(index) {
+                row.name = value;
+            }
+            Task::none()
+        }
+        Message::ZapUrlChanged(index, value) => {
+            if let Some(row) = state.settings.zaps.get_mut(index) {
+                row.url = value;
+            }
+            Task::none()
+        }
+        Message::ZapFieldsChanged(index, value) => {
+            if let Some(row) = state.settings.zaps.get_mut(index) {
+                row.fields = value;
+            }
+            Task::none()
+        }
+        Message::ZapInboundUserChanged(index, value) => {
+            if let Some(row) = state.settings.zaps.get_mut(index) {
+                row.inbound_user = value;
+            }
+            Task::none()
+        }
+        Message::MarkdownLinkClicked(uri) => {
+            state.push_activity(format!("link clicked: {uri}"));
+            let _ = open_uri_best_effort(&uri);
+            Task::none()
+        }
+        Message::ContextEdited(action) => {
+            state.context_editor.perform(action);
             state.settings.prompt_text = state.context_editor.text();
             state.context_preview_items = parse_markdown_items(&state.settings.prompt_text);
             Task::none()
         }
+        Message::MemoryQueryChanged(query) => {
+            state.memory_query = query;
+            Task::none()
+        }
+        Message::MemoryRerankToggled => {
+            state.memory_rerank = !state.memory_rerank;
+            Task::none()
+        }
+        Message::SearchQueryChanged(value) => {
+            state.search_query = value;
+            Task::none()
+        }
+        Message::SearchSubmitted => {
+            let query = state.search_query.trim().to_string();
+            if query.is_empty() || state.search_in_flight {
+                return Task::none();
+            }
+            state.search_in_flight = true;
+            state.search_status = "Searching...".to_string();
+            Task::perform(
+                search_work_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    query,
+                ),
+                Message::SearchFinished,
+            )
+        }
+        Message::SearchFinished(result) => {
+            state.search_in_flight = false;
+            match result {
+                Ok(response) => {
+                    state.search_status = match (response.hits.len(), response.indexed) {
+                        (0, true) => "No matches".to_string(),
+                        (0, false) => {
+                            "No matches (encrypted items are only searchable while unlocked)"
+                                .to_string()
+                        }
+                        (count, _) => {
+                            format!("{count} match{}", if count == 1 { "" } else { "es" })
+                        }
+                    };
+                    state.search_hits = response.hits;
+                }
+                Err(err) => {
+                    state.search_status = format!("Search failed: {err}");
+                    state.search_hits.clear();
+                }
+            }
+            Task::none()
+        }
+        Message::SearchCleared => {
+            state.search_query.clear();
+            state.search_hits.clear();
+            state.search_status.clear();
+            Task::none()
+        }
+        Message::SearchOpenHit(hit) => {
+            if hit.kind == "chat" {
+                state.active_tab = UiTab::Chat;
+                state.push_activity(format!("search → chat ({})", hit.origin_ref));
+                return Task::none();
+            }
+            update(state, Message::TimelineOpenItem(hit.origin_ref))
+        }
+        Message::TemplatePathChanged(value) => {
+            state.template_path = value;
+            Task::none()
+        }
+        Message::TemplateReviewPressed => {
+            let path = state.template_path.trim().to_string();
+            if path.is_empty() || state.template_in_flight {
+                return Task::none();
+            }
+            state.template_in_flight = true;
+            state.template_review = None;
+            state.template_error.clear();
+            state.template_status = "Reading template...".to_string();
+            Task::perform(
+                review_template(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    path,
+                ),
+                |result| Message::TemplateReviewLoaded(Box::new(result)),
+            )
+        }
+        Message::TemplateReviewLoaded(result) => {
+            state.template_in_flight = false;
+            match *result {
+                Ok(review) => {
+                    state.template_status = format!(
+                        "Review {} item{} before importing",
+                        review.preview.creates.len(),
+                        if review.preview.creates.len() == 1 {
+                            ""
+                        } else {
+                            "s"
+                        }
+                    );
+                    state.template_review = Some(review);
+                }
+                Err(err) => {
+                    state.template_status.clear();
+                    state.template_error = err;
+                }
+            }
+            Task::none()
+        }
+        Message::TemplateImportPressed => {
+            let Some(review) = state.template_review.clone() else {
+                return Task::none();
+            };
+            if state.template_in_flight {
+                return Task::none();
+            }
+            state.template_in_flight = true;
+            state.template_status = format!("Importing {}...", review.preview.name);
+            Task::perform(
+                import_template(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    review,
+                ),
+                Message::TemplateImportFinished,
+            )
+        }
+        Message::TemplateImportFinished(result) => {
+            state.template_in_flight = false;
+            match result {
+                Ok(status) => {
+                    state.push_activity(status.clone());
+                    state.template_status = status;
+                    state.template_error.clear();
+                    state.template_review = None;
+                    state.template_path.clear();
+                }
+                Err(err) => {
+                    state.template_status.clear();
+                    state.template_error = err;
+                    return Task::none();
+                }
+            }
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            )
+        }
+        Message::ExportViewPressed(tab, format) => {
+            let rendered = match tab {
+                UiTab::Gantt => view_export::render_gantt(&gantt_export(state), format)
+                    .map(|bytes| ("gantt", bytes)),
+                UiTab::Dependencies => {
+                    view_export::render_dependencies(&dependency_export(state), format)
+                        .map(|bytes| ("dependencies", bytes))
+                }
+                _ => return Task::none(),
+            };
+            let now = now_unix_ts();
+            let db_path = state.db_path.clone();
+            state.view_export_status = format!("Exporting {}...", format.extension());
+            Task::perform(
+                async move {
+                    let (stem, bytes) = rendered.map_err(|err| err.to_string())?;
+                    let path = view_export::write_export(
+                        &view_export::exports_dir(),
+                        stem,
+                        format,
+                        &bytes,
+                        now,
+                    )
+                    .map_err(|err| err.to_string())?;
+                    let status = format!("Exported to {}", path.display());
+                    Ok(match upload_export_copy(&db_path, &path, &bytes).await {
+                        Ok(Some(key)) => format!("{status}; remote copy at {key}"),
+                        Ok(None) => status,
+                        Err(err) => format!("{status}; remote copy failed: {err}"),
+                    })
+                },
+                Message::ExportViewFinished,
+            )
+        }
+        Message::ExportViewFinished(result) => {
+            match result {
+                Ok(status) => {
+                    state.push_activity(status.clone());
+                    state.view_export_status = status;
+                }
+                Err(err) => state.view_export_status = format!("Export failed: {err}"),
+            }
+            Task::none()
+        }
+        Message::TemplateReviewDismissed => {
+            state.template_review = None;
+            state.template_status.clear();
+            state.template_error.clear();
+            Task::none()
+        }
+        Message::MemorySearchPressed => {
+            let query = state.memory_query.trim().to_string();
+            if query.is_empty() || state.memory_search_in_flight {
+                return Task::none();
+            }
+            state.memory_search_in_flight = true;
+            state.memory_search_status = "Searching memory...".to_string();
+            Task::perform(
+                run_memory_search(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    query,
+                    state.memory_rerank,
+                ),
+                Message::MemorySearchFinished,
+            )
+        }
+        Message::MemorySearchFinished(result) => {
+            state.memory_search_in_flight = false;
+            match result {
+                Ok(hits) => {
+                    state.memory_search_status = format!("{} matching memories", hits.len());
+                    state.memory_hits = hits;
+                }
+                Err(err) => {
+                    state.memory_search_status = format!("Memory search failed: {err}");
+                    state.memory_hits.clear();
+                }
+            }
+            Task::none()
+        }
         Message::HeartbeatEdited(action) => {
             state.heartbeat_editor.perform(action);
             state.settings.heartbeat_text = state.heartbeat_editor.text();
@@ -1284,6 +3009,64 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             Task::none()
         }
+        Message::RepairSchemaPressed => {
+            if !state.daemon_running {
+                state.doctor_error = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.doctor_status = "Backing up and repairing database schema...".to_string();
+            state.doctor_error.clear();
+            Task::perform(
+                repair_schema_request(state.daemon_url.clone(), state.token.clone()),
+                Message::SchemaRepairFinished,
+            )
+        }
+        Message::SchemaRepairFinished(result) => {
+            match result {
+                Ok(response) => {
+                    let applied: usize = response
+                        .databases
+                        .iter()
+                        .map(|entry| entry.report.applied.len())
+                        .sum();
+                    let remaining: usize = response
+                        .databases
+                        .iter()
+                        .map(|entry| entry.report.remaining.len())
+                        .sum();
+                    let backups = response
+                        .databases
+                        .iter()
+                        .filter_map(|entry| {
+                            entry
+                                .report
+                                .backup_path
+                                .as_ref()
+                                .map(|backup| format!("{} → {backup}", entry.db_path))
+                        })
+                        .collect::<Vec<_>>();
+                    state.doctor_status = if backups.is_empty() {
+                        "Schema already matches; nothing to repair".to_string()
+                    } else {
+                        format!(
+                            "Repaired {applied} schema difference(s), {remaining} left. Backup: {}",
+                            backups.join(", ")
+                        )
+                    };
+                    state.push_activity(state.doctor_status.clone());
+                    return Task::perform(
+                        run_doctor_request(state.daemon_url.clone(), state.token.clone()),
+                        Message::DoctorFinished,
+                    );
+                }
+                Err(err) => {
+                    state.doctor_error = format!("Schema repair failed: {err}");
+                    state.doctor_status.clear();
+                    state.push_activity(state.doctor_error.clone());
+                }
+            }
+            Task::none()
+        }
         Message::SecurityFinished(result) => {
             match result {
                 Ok(report) => {
@@ -1310,107 +3093,393 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
             }
             Task::none()
         }
-        Message::RefreshSolanaWallet => {
-            state.solana_wallet_refresh_pending = true;
-            if state.daemon_running && !state.solana_wallet_fetch_in_flight {
-                state.solana_wallet_fetch_in_flight = true;
-                Task::perform(
-                    fetch_solana_wallet_address(
-                        state.daemon_url.clone(),
-                        state.token.clone(),
-                        state.user_id.clone(),
-                    ),
-                    Message::SolanaWalletLoaded,
-                )
-            } else {
-                Task::none()
+        Message::ToolPostureRefresh => {
+            if !state.daemon_running {
+                state.tool_posture_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            if state.tool_posture_in_flight {
+                return Task::none();
             }
+            state.tool_posture_in_flight = true;
+            Task::perform(
+                load_tool_posture(state.daemon_url.clone(), state.token.clone()),
+                Message::ToolPostureLoaded,
+            )
         }
-        Message::SolanaWalletLoaded(result) => {
-            state.solana_wallet_fetch_in_flight = false;
-            state.solana_wallet_refresh_pending = false;
+        Message::ToolPostureLoaded(result) => {
+            state.tool_posture_in_flight = false;
             match result {
-                Ok(Some(address)) => {
-                    state.solana_wallet_address = Some(address);
-                    state.solana_wallet_status = "Butterfly Bot Wallet detected".to_string();
-                }
-                Ok(None) => {
-                    state.solana_wallet_address = None;
-                    state.solana_wallet_status = "No Butterfly Bot Wallet available".to_string();
+                Ok(tools) => {
+                    let high = tools.iter().filter(|tool| tool.risk == "high").count();
+                    state.tool_posture_status =
+                        format!("{} tool(s), {high} high-risk", tools.len());
+                    state.tool_postures = tools;
                 }
                 Err(err) => {
-                    state.solana_wallet_address = None;
-                    state.solana_wallet_status = format!("Solana wallet unavailable: {err}");
+                    state.tool_posture_status = format!("Tool posture failed: {err}");
                 }
             }
             Task::none()
         }
-        Message::CopyToClipboard(value) => {
-            state.push_activity("copied to clipboard".to_string());
-            iced::clipboard::write(value)
-        }
-        Message::InboxRefreshRequested => {
-            if state.inbox_refresh_in_flight {
+        Message::SecretsRefresh => {
+            if !state.daemon_running {
+                state.secrets_status = "Daemon is not running".to_string();
                 return Task::none();
             }
-            state.inbox_refresh_in_flight = true;
-            state.inbox_error.clear();
+            if state.secrets_in_flight {
+                return Task::none();
+            }
+            state.secrets_in_flight = true;
             Task::perform(
-                load_inbox_items(
-                    state.daemon_url.clone(),
-                    state.token.clone(),
-                    state.user_id.clone(),
-                ),
-                Message::InboxLoaded,
+                load_secrets(state.daemon_url.clone(), state.token.clone()),
+                Message::SecretsLoaded,
             )
         }
-        Message::InboxLoaded(result) => {
-            state.inbox_refresh_in_flight = false;
-            state.inbox_action_origin_ref_in_flight = None;
-            state.inbox_last_refresh_ts = now_unix_ts();
+        Message::SecretsLoaded(result) => {
+            state.secrets_in_flight = false;
             match result {
-                Ok(items) => {
-                    state.inbox_items = items;
-                    state.inbox_error.clear();
-                    state.inbox_status =
-                        format!("Inbox synced ({} items)", state.inbox_items.len());
-                    sync_actionable_badge(state);
-                    maybe_emit_proactive_chat_nudge(state);
+                Ok(secrets) => {
+                    state.secrets = secrets;
                 }
                 Err(err) => {
-                    state.inbox_error = err;
-                    state.inbox_status.clear();
+                    state.secrets_status = format!("Secrets failed to load: {err}");
                 }
             }
             Task::none()
         }
-        Message::InboxAcknowledge(origin_ref) => {
-            let Some(item) = state
-                .inbox_items
-                .iter()
-                .find(|item| item.origin_ref == origin_ref)
-                .cloned()
-            else {
+        Message::SecretNameChanged(value) => {
+            state.secret_name = value;
+            Task::none()
+        }
+        Message::SecretValueChanged(value) => {
+            state.secret_value = value;
+            Task::none()
+        }
+        Message::SecretDescriptionChanged(value) => {
+            state.secret_description = value;
+            Task::none()
+        }
+        Message::SaveSecretPressed => {
+            if !state.daemon_running {
+                state.secrets_status = "Daemon is not running".to_string();
                 return Task::none();
-            };
-
-            optimistic_inbox_transition(state, &origin_ref, InboxActionKind::Acknowledge);
-            state.inbox_action_origin_ref_in_flight = Some(origin_ref.clone());
-            state.inbox_refresh_in_flight = true;
+            }
+            let name = state.secret_name.trim().to_string();
+            if name.is_empty() || state.secret_value.is_empty() {
+                state.secrets_status = "Enter a name and a value".to_string();
+                return Task::none();
+            }
+            // An existing name rotates the value; a new one creates it.
+            let rotate = state.secrets.iter().any(|secret| secret.name == name);
+            let value = std::mem::take(&mut state.secret_value);
             Task::perform(
-                apply_inbox_action(
+                save_secret_request(
                     state.daemon_url.clone(),
                     state.token.clone(),
-                    state.user_id.clone(),
-                    item,
-                    InboxActionKind::Acknowledge,
+                    name,
+                    value,
+                    state.secret_description.clone(),
+                    rotate,
                 ),
-                Message::InboxActionFinished,
+                Message::SecretChanged,
             )
         }
-        Message::InboxStart(origin_ref) => {
-            let Some(item) = state
-                .inbox_items
+        Message::DeleteSecretPressed(name) => {
+            if !state.daemon_running {
+                state.secrets_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            Task::perform(
+                delete_secret_request(state.daemon_url.clone(), state.token.clone(), name),
+                Message::SecretChanged,
+            )
+        }
+        Message::SecretChanged(result) => {
+            match result {
+                Ok(message) => {
+                    state.secrets_status = message;
+                    state.secret_name.clear();
+                    state.secret_description.clear();
+                }
+                Err(err) => {
+                    state.secrets_status = format!("Secret change failed: {err}");
+                }
+            }
+            if state.secrets_in_flight {
+                return Task::none();
+            }
+            state.secrets_in_flight = true;
+            Task::perform(
+                load_secrets(state.daemon_url.clone(), state.token.clone()),
+                Message::SecretsLoaded,
+            )
+        }
+        Message::ConsentRefresh => {
+            if !state.daemon_running {
+                state.consent_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            if state.consent_in_flight {
+                return Task::none();
+            }
+            state.consent_in_flight = true;
+            Task::perform(
+                load_consent_log(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::ConsentLoaded,
+            )
+        }
+        Message::ConsentLoaded(result) => {
+            state.consent_in_flight = false;
+            match result {
+                Ok(entries) => {
+                    state.consent_log = entries;
+                }
+                Err(err) => {
+                    state.consent_status = format!("Consent log failed to load: {err}");
+                }
+            }
+            Task::none()
+        }
+        Message::RevokeConsentPressed(id) => {
+            if !state.daemon_running {
+                state.consent_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            Task::perform(
+                revoke_consent_request(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    id,
+                ),
+                Message::ConsentChanged,
+            )
+        }
+        Message::ConsentChanged(result) => {
+            state.consent_status = match result {
+                Ok(message) => message,
+                Err(err) => format!("Consent change failed: {err}"),
+            };
+            if state.consent_in_flight {
+                return Task::none();
+            }
+            state.consent_in_flight = true;
+            Task::perform(
+                load_consent_log(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::ConsentLoaded,
+            )
+        }
+        Message::RefreshSolanaWallet => {
+            state.solana_wallet_refresh_pending = true;
+            if state.daemon_running && !state.solana_wallet_fetch_in_flight {
+                state.solana_wallet_fetch_in_flight = true;
+                Task::perform(
+                    fetch_solana_wallet_address(
+                        state.daemon_url.clone(),
+                        state.token.clone(),
+                        state.user_id.clone(),
+                    ),
+                    Message::SolanaWalletLoaded,
+                )
+            } else {
+                Task::none()
+            }
+        }
+        Message::SolanaWalletLoaded(result) => {
+            state.solana_wallet_fetch_in_flight = false;
+            state.solana_wallet_refresh_pending = false;
+            match result {
+                Ok(Some(address)) => {
+                    state.solana_wallet_address = Some(address);
+                    state.solana_wallet_status = "Butterfly Bot Wallet detected".to_string();
+                }
+                Ok(None) => {
+                    state.solana_wallet_address = None;
+                    state.solana_wallet_status = "No Butterfly Bot Wallet available".to_string();
+                }
+                Err(err) => {
+                    state.solana_wallet_address = None;
+                    state.solana_wallet_status = format!("Solana wallet unavailable: {err}");
+                }
+            }
+            Task::none()
+        }
+        Message::CopyToClipboard(value) => {
+            state.push_activity("copied to clipboard".to_string());
+            iced::clipboard::write(value)
+        }
+        Message::InboxRefreshRequested => {
+            if state.inbox_refresh_in_flight {
+                return Task::none();
+            }
+            state.inbox_refresh_in_flight = true;
+            state.inbox_error.clear();
+            Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            )
+        }
+        Message::InboxLoaded(result) => {
+            state.inbox_refresh_in_flight = false;
+            state.inbox_action_origin_ref_in_flight = None;
+            state.inbox_last_refresh_ts = now_unix_ts();
+            match result {
+                Ok(mut items) => {
+                    if let Some(project_id) = state.selected_project {
+                        items.retain(|item| item.project_id == Some(project_id));
+                    }
+                    state.inbox_items = items;
+                    let items = &state.inbox_items;
+                    state.inbox_selected.retain(|origin_ref| {
+                        items.iter().any(|item| &item.origin_ref == origin_ref)
+                    });
+                    state.inbox_error.clear();
+                    state.inbox_status =
+                        format!("Inbox synced ({} items)", state.inbox_items.len());
+                    sync_actionable_badge(state);
+                    maybe_emit_proactive_chat_nudge(state);
+                }
+                Err(err) => {
+                    state.inbox_error = err;
+                    state.inbox_status.clear();
+                    return Task::none();
+                }
+            }
+            Task::batch([
+                Task::perform(
+                    load_trash_batches(
+                        state.daemon_url.clone(),
+                        state.token.clone(),
+                        state.user_id.clone(),
+                    ),
+                    Message::TrashLoaded,
+                ),
+                Task::perform(
+                    load_projects(
+                        state.daemon_url.clone(),
+                        state.token.clone(),
+                        state.user_id.clone(),
+                    ),
+                    Message::ProjectsLoaded,
+                ),
+            ])
+        }
+        Message::TrashLoaded(result) => {
+            match result {
+                Ok(batches) => state.trash_batches = batches,
+                Err(err) => state.push_activity(format!("trash refresh failed: {err}")),
+            }
+            Task::none()
+        }
+        Message::ProjectsLoaded(result) => {
+            match result {
+                Ok(projects) => {
+                    state.projects = projects;
+                    let deleted = state.selected_project.is_some_and(|selected| {
+                        !state.projects.iter().any(|project| project.id == selected)
+                    });
+                    if deleted {
+                        state.selected_project = None;
+                        return update(state, Message::InboxRefreshRequested);
+                    }
+                }
+                Err(err) => state.push_activity(format!("project refresh failed: {err}")),
+            }
+            Task::none()
+        }
+        Message::ProjectSelected(choice) => {
+            if state.selected_project == choice.id {
+                return Task::none();
+            }
+            state.selected_project = choice.id;
+            state.push_activity(format!("showing project: {}", choice.name));
+            // Filtered-out items were dropped, so switching needs a fresh load.
+            update(state, Message::InboxRefreshRequested)
+        }
+        Message::TrashRestore(batch) => {
+            if state.trash_restore_in_flight.is_some() {
+                return Task::none();
+            }
+            state.trash_restore_in_flight = Some(batch.batch_id.clone());
+            Task::perform(
+                restore_trash_batch(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    batch,
+                ),
+                Message::TrashRestoreFinished,
+            )
+        }
+        Message::TrashRestoreFinished(result) => {
+            if let Some(batch_id) = state.trash_restore_in_flight.take() {
+                if result.is_ok() {
+                    state
+                        .trash_batches
+                        .retain(|batch| batch.batch_id != batch_id);
+                }
+            }
+            match result {
+                Ok(status) => {
+                    state.push_activity(status.clone());
+                    state.inbox_status = status;
+                    state.inbox_error.clear();
+                }
+                Err(err) => {
+                    state.inbox_error = err;
+                    return Task::none();
+                }
+            }
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            )
+        }
+        Message::InboxAcknowledge(origin_ref) => {
+            let Some(item) = state
+                .inbox_items
+                .iter()
+                .find(|item| item.origin_ref == origin_ref)
+                .cloned()
+            else {
+                return Task::none();
+            };
+
+            optimistic_inbox_transition(state, &origin_ref, InboxActionKind::Acknowledge);
+            state.inbox_action_origin_ref_in_flight = Some(origin_ref.clone());
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                apply_inbox_action(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    item,
+                    InboxActionKind::Acknowledge,
+                ),
+                Message::InboxActionFinished,
+            )
+        }
+        Message::InboxStart(origin_ref) => {
+            let Some(item) = state
+                .inbox_items
                 .iter()
                 .find(|item| item.origin_ref == origin_ref)
                 .cloned()
@@ -1528,256 +3597,838 @@ fn update(state: &mut ButterflyIcedApp, message: Message) -> Task<Message> {
                 Message::InboxActionFinished,
             )
         }
-        Message::InboxActionFinished(result) => {
-            let mut tasks = Vec::new();
-            state.inbox_action_origin_ref_in_flight = None;
-            match result {
-                Ok(status) => {
-                    state.push_activity(status.clone());
-                    state.inbox_status = status;
-                    state.inbox_error.clear();
-                }
-                Err(err) => {
-                    state.inbox_error = err;
-                    state.inbox_refresh_in_flight = false;
-                    return Task::none();
-                }
+        Message::KanbanDragStarted(origin_ref) => {
+            state.kanban_dragging = Some(origin_ref);
+            Task::none()
+        }
+        Message::KanbanHovered(column) => {
+            state.kanban_hover = Some(column);
+            Task::none()
+        }
+        Message::KanbanHoverEnded(column) => {
+            if state.kanban_hover == Some(column) {
+                state.kanban_hover = None;
             }
-
-            tasks.push(Task::perform(
-                load_inbox_items(
-                    state.daemon_url.clone(),
-                    state.token.clone(),
-                    state.user_id.clone(),
-                ),
-                Message::InboxLoaded,
-            ));
-
-            Task::batch(tasks)
+            Task::none()
         }
-        Message::RefreshReminderDeliveryEvents => {
-            if !state.daemon_running {
-                state.reminder_delivery_error = "Daemon is not running".to_string();
+        Message::KanbanDragCancelled => {
+            state.kanban_dragging = None;
+            Task::none()
+        }
+        Message::KanbanDropped(column) => {
+            let Some(origin_ref) = state.kanban_dragging.take() else {
                 return Task::none();
-            }
-            state.reminder_delivery_status = "Loading reminder delivery diagnostics...".to_string();
-            state.reminder_delivery_error.clear();
-            Task::perform(
-                fetch_reminder_delivery_events(
+            };
+            let Some(item) = state
+                .inbox_items
+                .iter()
+                .find(|item| item.origin_ref == origin_ref)
+                .cloned()
+            else {
+                return Task::none();
+            };
+            let from = KanbanColumn::of(item.status);
+            if from == column {
+                return Task::none();
+            }
+            let Some(action) = column.action_from(item.status) else {
+                state.kanban_status = format!(
+                    "Cards can't move from {} to {}",
+                    from.title(),
+                    column.title()
+                );
+                return Task::none();
+            };
+
+            state
+                .kanban_rollback
+                .insert(origin_ref.clone(), (item.status, item.updated_at));
+            optimistic_inbox_transition(state, &origin_ref, action);
+            state.kanban_status = format!("Moving to {}...", column.title());
+            Task::perform(
+                apply_inbox_action(
                     state.daemon_url.clone(),
                     state.token.clone(),
                     state.user_id.clone(),
-                    60,
+                    item,
+                    action,
                 ),
-                Message::ReminderDeliveryEventsLoaded,
+                move |result| Message::KanbanMoveFinished(origin_ref, result),
             )
         }
-        Message::ReminderDeliveryEventsLoaded(result) => {
+        Message::KanbanMoveFinished(origin_ref, result) => {
+            let rollback = state.kanban_rollback.remove(&origin_ref);
             match result {
-                Ok(events) => {
-                    state.reminder_delivery_events = events;
-                    state.reminder_delivery_error.clear();
-                    state.reminder_delivery_status = format!(
-                        "Reminder delivery diagnostics loaded ({})",
-                        state.reminder_delivery_events.len()
-                    );
+                Ok(status) => {
+                    state.push_activity(status.clone());
+                    state.kanban_status = status;
+                    if state.inbox_refresh_in_flight {
+                        return Task::none();
+                    }
+                    state.inbox_refresh_in_flight = true;
+                    Task::perform(
+                        load_inbox_items(
+                            state.daemon_url.clone(),
+                            state.token.clone(),
+                            state.user_id.clone(),
+                        ),
+                        Message::InboxLoaded,
+                    )
                 }
                 Err(err) => {
-                    state.reminder_delivery_error = err;
-                    state.reminder_delivery_status.clear();
+                    if let (Some((status, updated_at)), Some(item)) = (
+                        rollback,
+                        state
+                            .inbox_items
+                            .iter_mut()
+                            .find(|item| item.origin_ref == origin_ref),
+                    ) {
+                        item.status = status;
+                        item.updated_at = updated_at;
+                    }
+                    state.kanban_status = format!("Move undone: {err}");
+                    Task::none()
                 }
             }
+        }
+        Message::InboxSelectToggled(origin_ref) => {
+            if !state.inbox_selected.remove(&origin_ref) {
+                state.inbox_selected.insert(origin_ref);
+            }
             Task::none()
         }
-        Message::AuditRefreshRequested => {
-            if state.audit_refresh_in_flight {
+        Message::InboxSelectionCleared => {
+            state.inbox_selected.clear();
+            Task::none()
+        }
+        Message::InboxNoteToggled(origin_ref) => {
+            if state.inbox_note_origin_ref.as_deref() == Some(origin_ref.as_str()) {
+                state.inbox_note_origin_ref = None;
+            } else {
+                state.inbox_note_origin_ref = Some(origin_ref);
+                state.inbox_attach_origin_ref = None;
+            }
+            state.inbox_note_draft.clear();
+            Task::none()
+        }
+        Message::InboxNoteDraftChanged(value) => {
+            state.inbox_note_draft = value;
+            Task::none()
+        }
+        Message::InboxNoteSubmitted => {
+            let body = state.inbox_note_draft.trim().to_string();
+            if body.is_empty() || state.inbox_action_origin_ref_in_flight.is_some() {
                 return Task::none();
             }
-            state.audit_refresh_in_flight = true;
-            state.audit_error.clear();
+            let Some(origin_ref) = state.inbox_note_origin_ref.take() else {
+                return Task::none();
+            };
+            state.inbox_note_draft.clear();
+            state.inbox_action_origin_ref_in_flight = Some(origin_ref.clone());
+            state.inbox_refresh_in_flight = true;
             Task::perform(
-                fetch_audit_events(
+                add_inbox_note(
                     state.daemon_url.clone(),
                     state.token.clone(),
                     state.user_id.clone(),
-                    200,
+                    origin_ref,
+                    body,
                 ),
-                Message::AuditEventsLoaded,
+                Message::InboxActionFinished,
             )
         }
-        Message::AuditEventsLoaded(result) => {
-            state.audit_refresh_in_flight = false;
-            state.audit_last_refresh_ts = now_unix_ts();
+        Message::InboxAttachToggled(origin_ref) => {
+            if state.inbox_attach_origin_ref.as_deref() == Some(origin_ref.as_str()) {
+                state.inbox_attach_origin_ref = None;
+            } else {
+                state.inbox_attach_origin_ref = Some(origin_ref);
+                state.inbox_note_origin_ref = None;
+            }
+            state.inbox_attach_path.clear();
+            Task::none()
+        }
+        Message::InboxAttachPathChanged(value) => {
+            state.inbox_attach_path = value;
+            Task::none()
+        }
+        Message::InboxAttachSubmitted => {
+            let path = state.inbox_attach_path.trim().to_string();
+            if path.is_empty() || state.inbox_action_origin_ref_in_flight.is_some() {
+                return Task::none();
+            }
+            let Some(origin_ref) = state.inbox_attach_origin_ref.take() else {
+                return Task::none();
+            };
+            state.inbox_attach_path.clear();
+            state.inbox_action_origin_ref_in_flight = Some(origin_ref.clone());
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                upload_inbox_attachment(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    origin_ref,
+                    path,
+                ),
+                Message::InboxActionFinished,
+            )
+        }
+        Message::InboxBulkAction(action) => {
+            if state.inbox_bulk_in_flight || state.inbox_selected.is_empty() {
+                return Task::none();
+            }
+            let origin_refs = state.inbox_selected.iter().cloned().collect::<Vec<_>>();
+            for origin_ref in &origin_refs {
+                optimistic_inbox_transition(state, origin_ref, action);
+            }
+            state.inbox_bulk_in_flight = true;
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                apply_inbox_bulk_action(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    origin_refs,
+                    action,
+                ),
+                Message::InboxBulkFinished,
+            )
+        }
+        Message::InboxBulkFinished(result) => {
+            state.inbox_bulk_in_flight = false;
             match result {
-                Ok(events) => {
-                    let mut bridged = 0usize;
-                    let bridge_after_ts = state.audit_last_activity_bridge_ts;
-                    for event in events
-                        .iter()
-                        .filter(|event| event.timestamp > bridge_after_ts)
-                    {
-                        let should_bridge = matches!(
-                            event.event_type.as_str(),
-                            "inbox_transition" | "reminder_delivery"
-                        );
-                        if should_bridge {
-                            let origin = event.origin_ref.as_deref().unwrap_or("-");
-                            let actor = event.actor.as_deref().unwrap_or("system");
-                            state.push_activity(format!(
-                                "Ops update ({actor}) • {} • {} • origin:{}",
-                                event.event_type, event.status, origin
-                            ));
-                            bridged += 1;
-                        }
-                    }
-                    if let Some(max_ts) = events.iter().map(|e| e.timestamp).max() {
-                        state.audit_last_activity_bridge_ts =
-                            state.audit_last_activity_bridge_ts.max(max_ts);
-                    }
-                    state.audit_events = events;
-                    state.audit_error.clear();
-                    state.audit_status = format!("Audit synced ({})", state.audit_events.len());
-                    if bridged > 0 {
-                        state.push_activity(format!("audit→activity bridge: {bridged} updates"));
-                    }
+                Ok(status) => {
+                    state.inbox_selected.clear();
+                    state.push_activity(status.clone());
+                    state.inbox_status = status;
+                    state.inbox_error.clear();
                 }
                 Err(err) => {
-                    state.audit_error = err;
-                    state.audit_status.clear();
+                    state.inbox_error = err;
+                    state.inbox_refresh_in_flight = false;
+                    return Task::none();
                 }
             }
-            Task::none()
+            Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            )
         }
-    }
-}
-
-fn view(state: &ButterflyIcedApp) -> Element<'_, Message> {
-    let tabs_row = UiTab::all()
-        .into_iter()
-        .fold(row!().spacing(8), |row, tab| {
-            let active = tab == state.active_tab;
-            row.push(
-                button(text(tab.label()).size(14))
-                    .padding([8, 14])
-                    .style(if active {
-                        rounded_primary_button
-                    } else {
-                        rounded_secondary_button
-                    })
-                    .on_press(Message::TabSelected(tab)),
+        Message::InboxDecideApproval(origin_ref, approve) => {
+            let Some(id) = crate::approvals::parse_origin_ref(&origin_ref) else {
+                return Task::none();
+            };
+            state.inbox_action_origin_ref_in_flight = Some(origin_ref);
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                decide_approval(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    id,
+                    approve,
+                ),
+                Message::InboxActionFinished,
             )
-        });
-
-    let daemon_controls = row![
-        button(if state.daemon_starting { "⏳" } else { "▶" })
-            .padding([8, 12])
-            .style(rounded_primary_button)
-            .on_press_maybe(
-                (!state.daemon_starting && !state.daemon_running)
-                    .then_some(Message::StartDaemonPressed)
-            ),
-        button("⏹")
-            .padding([8, 12])
-            .style(rounded_primary_button)
-            .on_press_maybe(
-                (!state.daemon_starting && state.daemon_running)
-                    .then_some(Message::StopDaemonPressed)
-            ),
-        button("🗑")
-            .padding([8, 12])
-            .style(rounded_danger_button)
-            .on_press(Message::ClearHistoryPressed),
-    ]
-    .spacing(8)
-    .align_y(iced::Alignment::Center);
+        }
+        Message::InboxDecideConsent(origin_ref, allow) => {
+            let Some(id) = crate::consent::parse_origin_ref(&origin_ref) else {
+                return Task::none();
+            };
+            state.inbox_action_origin_ref_in_flight = Some(origin_ref);
+            state.inbox_refresh_in_flight = true;
+            Task::perform(
+                decide_consent(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    id,
+                    allow,
+                ),
+                Message::InboxActionFinished,
+            )
+        }
+        Message::InboxActionFinished(result) => {
+            let mut tasks = Vec::new();
+            state.inbox_action_origin_ref_in_flight = None;
+            match result {
+                Ok(status) => {
+                    state.push_activity(status.clone());
+                    state.inbox_status = status;
+                    state.inbox_error.clear();
+                }
+                Err(err) => {
+                    state.inbox_error = err;
+                    state.inbox_refresh_in_flight = false;
+                    return Task::none();
+                }
+            }
 
-    let nav_bar = row![
-        container(
-            scrollable(tabs_row).direction(iced::widget::scrollable::Direction::Horizontal(
-                iced::widget::scrollable::Scrollbar::default(),
-            ))
-        )
-        .width(Length::Fill)
-        .padding([2, 0]),
-        daemon_controls,
-    ]
-    .spacing(10)
-    .align_y(iced::Alignment::Center)
-    .width(Length::Fill);
+            tasks.push(Task::perform(
+                load_inbox_items(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::InboxLoaded,
+            ));
 
-    let body = container(match state.active_tab {
-        UiTab::Inbox => view_inbox_tab(state),
-        UiTab::Kanban => view_kanban_tab(state),
-        UiTab::Dependencies => view_dependencies_tab(state),
-        UiTab::Gantt => view_gantt_tab(state),
-        UiTab::Audit => view_audit_tab(state),
-        UiTab::Chat => view_chat_tab(state),
-        UiTab::Activity => view_activity_tab(state),
-        UiTab::Settings => view_settings_tab(state),
-        UiTab::Diagnostics => view_diagnostics_tab(state),
-        UiTab::Context => view_context_tab(state),
-        UiTab::Heartbeat => view_heartbeat_tab(state),
-    })
-    .width(Length::Fill)
-    .height(Length::Fill);
-
-    let logo: Element<'_, Message> = image::<image::Handle>(butterfly_bot_logo_handle())
-        .width(40)
-        .height(40)
-        .into();
-
-    let actionable_count = count_actionable_human_items(&state.inbox_items);
-
-    let content = column![
-        row![
-            logo,
-            column![
-                text("Butterfly Bot").size(30),
-                text("Personal-ops assistant").size(14),
-                text(build_stamp()).size(12)
-            ]
-            .spacing(2),
-            Space::new().width(Length::Fill),
-            text(format!("Actionable now: {actionable_count}")).size(14),
-            text(state.daemon_status.clone()).size(14)
-        ]
-        .spacing(16)
-        .width(Length::Fill)
-        .align_y(iced::Alignment::Center),
-        container(nav_bar).padding(8).style(glass_panel),
-        body
-    ]
-    .spacing(12)
-    .padding(16)
-    .height(Length::Fill);
-
-    container(container(content).height(Length::Fill).style(glass_shell))
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .center_x(Length::Fill)
-        .into()
+            Task::batch(tasks)
+        }
+        Message::RefreshReminderDeliveryEvents => {
+            if !state.daemon_running {
+                state.reminder_delivery_error = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.reminder_delivery_status = "Loading reminder delivery diagnostics...".to_string();
+            state.reminder_delivery_error.clear();
+            Task::perform(
+                fetch_reminder_delivery_events(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                    60,
+                ),
+                Message::ReminderDeliveryEventsLoaded,
+            )
+        }
+        Message::RefreshBackupStatus => {
+            if !state.daemon_running {
+                state.backup_error = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            if state.backup_in_flight {
+                return Task::none();
+            }
+            state.backup_in_flight = true;
+            state.backup_error.clear();
+            Task::perform(
+                load_backup_status(state.daemon_url.clone(), state.token.clone()),
+                Message::BackupStatusLoaded,
+            )
+        }
+        Message::BackupStatusLoaded(result) => {
+            state.backup_in_flight = false;
+            match result {
+                Ok(status) => {
+                    let (summary, snapshots) = backup_summary(&status);
+                    state.backup_status = summary;
+                    state.backup_snapshots = snapshots;
+                    state.backup_error = status
+                        .last_run
+                        .and_then(|run| run.error)
+                        .map(|error| format!("Last backup failed: {error}"))
+                        .unwrap_or_default();
+                }
+                Err(err) => state.backup_error = err,
+            }
+            Task::none()
+        }
+        Message::BackupNowPressed => {
+            if !state.daemon_running {
+                state.backup_error = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            if state.backup_in_flight {
+                return Task::none();
+            }
+            state.backup_in_flight = true;
+            state.backup_error.clear();
+            state.backup_status = "Backing up...".to_string();
+            Task::perform(
+                run_backup_request(state.daemon_url.clone(), state.token.clone()),
+                Message::BackupFinished,
+            )
+        }
+        Message::BackupFinished(result) => {
+            match result {
+                Ok(status) => state.push_activity(status),
+                Err(err) => state.push_activity(format!("backup failed: {err}")),
+            }
+            Task::perform(
+                load_backup_status(state.daemon_url.clone(), state.token.clone()),
+                Message::BackupStatusLoaded,
+            )
+        }
+        Message::RefreshEstimateAccuracy => {
+            if !state.daemon_running {
+                state.estimate_accuracy_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.estimate_accuracy_status = "Loading estimate accuracy...".to_string();
+            Task::perform(
+                fetch_estimate_accuracy(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::EstimateAccuracyLoaded,
+            )
+        }
+        Message::EstimateAccuracyLoaded(result) => {
+            match result {
+                Ok(lines) if lines.is_empty() => {
+                    state.estimate_accuracy_status =
+                        "No completed todos yet; estimates are uncalibrated.".to_string();
+                    state.estimate_accuracy_lines.clear();
+                }
+                Ok(lines) => {
+                    state.estimate_accuracy_status =
+                        "Actual time vs. estimate for completed todos; new estimates use the multiplier."
+                            .to_string();
+                    state.estimate_accuracy_lines = lines;
+                }
+                Err(err) => state.estimate_accuracy_status = err,
+            }
+            Task::none()
+        }
+        Message::RefreshSchedulerJobs => {
+            if !state.daemon_running {
+                state.scheduler_jobs_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.scheduler_jobs_status = "Loading scheduler jobs...".to_string();
+            Task::perform(
+                fetch_scheduler_jobs(state.daemon_url.clone(), state.token.clone()),
+                Message::SchedulerJobsLoaded,
+            )
+        }
+        Message::SchedulerJobsLoaded(result) => {
+            match result {
+                Ok(lines) if lines.is_empty() => {
+                    state.scheduler_jobs_status =
+                        "No jobs registered; the scheduler has not started yet.".to_string();
+                    state.scheduler_jobs_lines.clear();
+                }
+                Ok(lines) => {
+                    state.scheduler_jobs_status =
+                        "Background jobs the daemon runs; late ones missed a whole interval."
+                            .to_string();
+                    state.scheduler_jobs_lines = lines;
+                }
+                Err(err) => state.scheduler_jobs_status = err,
+            }
+            Task::none()
+        }
+        Message::RefreshDeadLetters => {
+            if !state.daemon_running {
+                state.dead_letters_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.dead_letters_status = "Loading failed deliveries...".to_string();
+            Task::perform(
+                fetch_dead_letters(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::DeadLettersLoaded,
+            )
+        }
+        Message::RefreshDependencyCheck => {
+            if !state.daemon_running {
+                state.dependency_check_status = "Daemon is not running".to_string();
+                return Task::none();
+            }
+            state.dependency_check_status = "Checking references...".to_string();
+            Task::perform(
+                fetch_dependency_check(
+                    state.daemon_url.clone(),
+                    state.token.clone(),
+                    state.user_id.clone(),
+                ),
+                Message::DependencyCheckLoaded,
+            )
+        }
+        Message::DependencyCheckLoaded(result) => {
+            match result {
+                Ok(report) => {
+                    state.dependency_check_status = format!(
+                        "{} dependency refs checked, {} broken, {} cycles.",
+                        report.checked,
+                        report.broken.len(),
+                        report.cycles.len()
+                    );
+                    state.dependency_check = Some(report);
+                }
+                Err(err) => state.dependency_check_status = err,
+            }
+            Task::none()
+        }
+        Message::DeadLettersLoaded(result) => {
+            match result {
+                Ok(rows) => {
+                    state.dead_letters_status = if rows.is_empty() {
+                        "No failed reminder deliveries.".to_string()
+                    } else {
+                        "These deliveries ran out of retries. Retry re-queues one with fresh attempts."
+                            .to_string()
+                    };
+                    state.dead_letters = rows;
+                }
+                Err(err) => state.dead_letters_status = err,
+            }
+            Task::none()
+        }
+        Message::DeadLetterRedrive(id) => Task::perform(
+            dead_letter_action(
+                state.daemon_url.clone(),
+                state.token.clone(),
+                state.user_id.clone(),
+                "redrive",
+                id,
+            ),
+            Message::DeadLetterActionDone,
+        ),
+        Message::DeadLetterDiscard(id) => Task::perform(
+            dead_letter_action(
+                state.daemon_url.clone(),
+                state.token.clone(),
+                state.user_id.clone(),
+                "discard",
+                id,
+            ),
+            Message::DeadLetterActionDone,
+        ),
+        Message::DeadLetterActionDone(result) => {
+            if let Err(err) = result {
+                state.dead_letters_status = err;
+                return Task::none();
+            }
+            update(state, Message::RefreshDeadLetters)
+        }
+        Message::ReminderDeliveryEventsLoaded(result) => {
+            match result {
+                Ok(events) => {
+                    state.reminder_delivery_events = events;
+                    state.reminder_delivery_error.clear();
+                    state.reminder_delivery_status = format!(
+                        "Reminder delivery diagnostics loaded ({})",
+                        state.reminder_delivery_events.len()
+                    );
+                }
+                Err(err) => {
+                    state.reminder_delivery_error = err;
+                    state.reminder_delivery_status.clear();
+                }
+            }
+            Task::none()
+        }
+        Message::AuditRefreshRequested => {
+            if state.audit_refresh_in_flight {
+                return Task::none();
+            }
+            state.audit_refresh_in_flight = true;
+            state.audit_error.clear();
+            state.audit_fetch_task(None)
+        }
+        Message::AuditLoadOlder => {
+            let Some(before_id) = state.audit_next_before_id else {
+                return Task::none();
+            };
+            if state.audit_refresh_in_flight {
+                return Task::none();
+            }
+            state.audit_refresh_in_flight = true;
+            state.audit_error.clear();
+            state.audit_fetch_task(Some(before_id))
+        }
+        Message::AuditEventsLoaded(result) => {
+            state.audit_refresh_in_flight = false;
+            match result {
+                Ok(page) if page.older => {
+                    let mut rows = page.rows;
+                    rows.append(&mut state.audit_events);
+                    state.audit_events = rows;
+                    state.audit_next_before_id = page.next_before_id;
+                    state.audit_error.clear();
+                    state.audit_status = format!("Audit loaded ({})", state.audit_events.len());
+                }
+                Ok(page) => {
+                    state.audit_last_refresh_ts = now_unix_ts();
+                    let events = page.rows;
+                    let mut bridged = 0usize;
+                    let bridge_after_ts = state.audit_last_activity_bridge_ts;
+                    for event in events
+                        .iter()
+                        .filter(|event| event.timestamp > bridge_after_ts)
+                    {
+                        let should_bridge = matches!(
+                            event.event_type.as_str(),
+                            "inbox_transition" | "reminder_delivery"
+                        );
+                        if should_bridge {
+                            let origin = event.origin_ref.as_deref().unwrap_or("-");
+                            let actor = event.actor.as_deref().unwrap_or("system");
+                            state.push_activity(format!(
+                                "Ops update ({actor}) • {} • {} • origin:{}",
+                                event.event_type, event.status, origin
+                            ));
+                            bridged += 1;
+                        }
+                        // The first page is history, not news.
+                        let reminder_fired = bridge_after_ts > 0
+                            && event.event_type == "reminder_delivery"
+                            && event.status == "delivered";
+                        if let Some(title) = event.title.as_deref().filter(|_| reminder_fired) {
+                            state.speak(UiTab::Inbox, &format!("Reminder: {title}"));
+                        }
+                    }
+                    if let Some(max_ts) = events.iter().map(|e| e.timestamp).max() {
+                        state.audit_last_activity_bridge_ts =
+                            state.audit_last_activity_bridge_ts.max(max_ts);
+                    }
+                    // Keep pages the user already scrolled back through when
+                    // the newest page still overlaps them.
+                    let oldest_new_id = events.first().map(|event| event.id);
+                    let kept_older = match oldest_new_id {
+                        Some(oldest) if state.audit_events.iter().any(|e| e.id >= oldest) => state
+                            .audit_events
+                            .iter()
+                            .filter(|event| event.id < oldest)
+                            .cloned()
+                            .collect::<Vec<_>>(),
+                        _ => Vec::new(),
+                    };
+                    if kept_older.is_empty() {
+                        state.audit_next_before_id = page.next_before_id;
+                    }
+                    state.audit_events = kept_older;
+                    state.audit_events.extend(events);
+                    state.audit_error.clear();
+                    state.audit_status = format!("Audit synced ({})", state.audit_events.len());
+                    if bridged > 0 {
+                        state.push_activity(format!("audit→activity bridge: {bridged} updates"));
+                    }
+                }
+                Err(err) => {
+                    state.audit_last_refresh_ts = now_unix_ts();
+                    state.audit_error = err;
+                    state.audit_status.clear();
+                }
+            }
+            Task::none()
+        }
+    }
 }
 
-fn glass_shell(_theme: &Theme) -> iced::widget::container::Style {
-    iced::widget::container::Style {
-        text_color: None,
-        background: Some(Background::Color(Color::from_rgba(0.07, 0.10, 0.18, 0.65))),
-        border: Border {
-            radius: 18.0.into(),
-            width: 1.0,
-            color: Color::from_rgba(1.0, 1.0, 1.0, 0.10),
-        },
-        shadow: Shadow::default(),
-        snap: false,
+fn view(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    if state.privacy_locked {
+        return view_privacy_lock(state);
     }
-}
 
-fn glass_panel(_theme: &Theme) -> iced::widget::container::Style {
-    iced::widget::container::Style {
-        text_color: None,
-        background: Some(Background::Color(Color::from_rgba(0.10, 0.14, 0.24, 0.58))),
-        border: Border {
-            radius: 16.0.into(),
+    let tabs_row = UiTab::all()
+        .into_iter()
+        .fold(row!().spacing(8), |row, tab| {
+            let active = tab == state.active_tab;
+            row.push(
+                button(text(tab.label()).size(14))
+                    .padding([8, 14])
+                    .style(if active {
+                        rounded_primary_button
+                    } else {
+                        rounded_secondary_button
+                    })
+                    .on_press(Message::TabSelected(tab)),
+            )
+        });
+
+    let daemon_controls = row![
+        button(if state.daemon_starting { "⏳" } else { "▶" })
+            .padding([8, 12])
+            .style(rounded_primary_button)
+            .on_press_maybe(
+                (!state.daemon_starting && !state.daemon_running)
+                    .then_some(Message::StartDaemonPressed)
+            ),
+        button("⏹")
+            .padding([8, 12])
+            .style(rounded_primary_button)
+            .on_press_maybe(
+                (!state.daemon_starting && state.daemon_running)
+                    .then_some(Message::StopDaemonPressed)
+            ),
+        button("🗑")
+            .padding([8, 12])
+            .style(rounded_danger_button)
+            .on_press(Message::ClearHistoryPressed),
+        button("🔒")
+            .padding([8, 12])
+            .style(rounded_secondary_button)
+            .on_press(Message::LockNowPressed),
+        button(if state.presentation_mode {
+            "🙈"
+        } else {
+            "👁"
+        })
+        .padding([8, 12])
+        .style(if state.presentation_mode {
+            rounded_primary_button
+        } else {
+            rounded_secondary_button
+        })
+        .on_press(Message::TogglePresentationMode),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center);
+
+    let nav_bar = row![
+        container(
+            scrollable(tabs_row).direction(iced::widget::scrollable::Direction::Horizontal(
+                iced::widget::scrollable::Scrollbar::default(),
+            ))
+        )
+        .width(Length::Fill)
+        .padding([2, 0]),
+        daemon_controls,
+    ]
+    .spacing(10)
+    .align_y(iced::Alignment::Center)
+    .width(Length::Fill);
+
+    let body = container(match state.active_tab {
+        UiTab::Inbox => view_inbox_tab(state),
+        UiTab::Kanban => view_kanban_tab(state),
+        UiTab::Dependencies => view_dependencies_tab(state),
+        UiTab::Gantt => view_gantt_tab(state),
+        UiTab::Audit => view_audit_tab(state),
+        UiTab::Chat => view_chat_tab(state),
+        UiTab::Activity => view_activity_tab(state),
+        UiTab::Insights => view_insights_tab(state),
+        UiTab::Settings => view_settings_tab(state),
+        UiTab::Diagnostics => view_diagnostics_tab(state),
+        UiTab::Context => view_context_tab(state),
+        UiTab::Heartbeat => view_heartbeat_tab(state),
+    })
+    .width(Length::Fill)
+    .height(Length::Fill);
+
+    let logo: Element<'_, Message> = image::<image::Handle>(butterfly_bot_logo_handle())
+        .width(40)
+        .height(40)
+        .into();
+
+    let actionable_count = count_actionable_human_items(&state.inbox_items);
+
+    let all_projects = ProjectChoice {
+        id: None,
+        name: "All projects".to_string(),
+    };
+    let project_choices = std::iter::once(all_projects.clone())
+        .chain(state.projects.iter().map(|project| ProjectChoice {
+            id: Some(project.id),
+            name: project.name.clone(),
+        }))
+        .collect::<Vec<_>>();
+    let selected_project = project_choices
+        .iter()
+        .find(|choice| choice.id == state.selected_project)
+        .cloned()
+        .unwrap_or(all_projects);
+    let project_selector = pick_list(
+        project_choices,
+        Some(selected_project),
+        Message::ProjectSelected,
+    )
+    .text_size(14)
+    .padding([6, 10]);
+
+    let content = column![
+        row![
+            logo,
+            column![
+                text("Butterfly Bot").size(30),
+                text("Personal-ops assistant").size(14),
+                text(build_stamp()).size(12)
+            ]
+            .spacing(2),
+            Space::new().width(Length::Fill),
+            project_selector,
+            text(format!("Actionable now: {actionable_count}")).size(14),
+            text(state.daemon_status.clone()).size(14)
+        ]
+        .spacing(16)
+        .width(Length::Fill)
+        .align_y(iced::Alignment::Center),
+        container(nav_bar).padding(8).style(glass_panel),
+        body
+    ]
+    .spacing(12)
+    .padding(16)
+    .height(Length::Fill);
+
+    container(container(content).height(Length::Fill).style(glass_shell))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+}
+
+fn view_privacy_lock(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let logo: Element<'_, Message> = image::<image::Handle>(butterfly_bot_logo_handle())
+        .width(64)
+        .height(64)
+        .into();
+    let mut panel = column![
+        logo,
+        text("Butterfly Bot is locked").size(22),
+        text_input("Passphrase", &state.unlock_input)
+            .secure(true)
+            .on_input(Message::UnlockInputChanged)
+            .on_submit(Message::UnlockPressed)
+            .padding(8)
+            .width(320),
+        button(if state.unlock_in_flight {
+            "Unlocking..."
+        } else {
+            "Unlock"
+        })
+        .padding([8, 14])
+        .style(rounded_primary_button)
+        .on_press_maybe(
+            (!state.unlock_in_flight && !state.unlock_input.is_empty())
+                .then_some(Message::UnlockPressed)
+        ),
+    ]
+    .spacing(12)
+    .align_x(iced::Alignment::Center);
+    if !state.unlock_error.is_empty() {
+        panel = panel.push(text(state.unlock_error.clone()).size(13));
+    }
+
+    container(container(panel).padding(24).style(glass_panel))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .style(glass_shell)
+        .into()
+}
+
+fn glass_shell(_theme: &Theme) -> iced::widget::container::Style {
+    iced::widget::container::Style {
+        text_color: None,
+        background: Some(Background::Color(Color::from_rgba(0.07, 0.10, 0.18, 0.65))),
+        border: Border {
+            radius: 18.0.into(),
+            width: 1.0,
+            color: Color::from_rgba(1.0, 1.0, 1.0, 0.10),
+        },
+        shadow: Shadow::default(),
+        snap: false,
+    }
+}
+
+fn glass_panel(_theme: &Theme) -> iced::widget::container::Style {
+    iced::widget::container::Style {
+        text_color: None,
+        background: Some(Background::Color(Color::from_rgba(0.10, 0.14, 0.24, 0.58))),
+        border: Border {
+            radius: 16.0.into(),
             width: 1.0,
             color: Color::from_rgba(1.0, 1.0, 1.0, 0.12),
         },
@@ -1978,6 +4629,112 @@ fn view_inbox_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     sort_section_by_due(&mut blocked_items);
     sort_section_by_due(&mut done_items);
 
+    let sections: Element<'_, Message> = if state.inbox_group_by_smart_list {
+        let bounds = SmartListBounds::at(now);
+        let mut grouped: HashMap<SmartList, Vec<&InboxItem>> = HashMap::new();
+        for item in &state.inbox_items {
+            if !item.status.is_actionable() {
+                continue;
+            }
+            let due_at = item.due_at.or_else(|| infer_due_at_from_item_text(item));
+            grouped
+                .entry(bounds.classify(due_at))
+                .or_default()
+                .push(item);
+        }
+        let mut smart_column = column!().spacing(10);
+        for list in SmartList::all() {
+            let mut items = grouped.remove(&list).unwrap_or_default();
+            sort_section_by_due(&mut items);
+            smart_column = smart_column.push(smart_list_section(
+                state,
+                list,
+                &items,
+                state.inbox_collapsed_smart_lists.contains(&list),
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ));
+        }
+        smart_column
+            .push(inbox_section(
+                state,
+                "Done",
+                &done_items,
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ))
+            .into()
+    } else {
+        column![
+            inbox_section(
+                state,
+                "Needs Action",
+                &needs_action_items,
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ),
+            inbox_section(
+                state,
+                "In progress",
+                &in_progress_items,
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ),
+            inbox_section(
+                state,
+                "Blocked",
+                &blocked_items,
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ),
+            inbox_section(
+                state,
+                "Done",
+                &done_items,
+                state.timeline_focus_origin_ref.as_deref(),
+                state.inbox_action_origin_ref_in_flight.as_deref(),
+            ),
+        ]
+        .spacing(10)
+        .into()
+    };
+
+    let mut undo_banner = column!().spacing(6);
+    for batch in state
+        .trash_batches
+        .iter()
+        .filter(|batch| now.saturating_sub(batch.trashed_at) < TRASH_UNDO_WINDOW_SECONDS)
+    {
+        let restoring = state.trash_restore_in_flight.as_deref() == Some(batch.batch_id.as_str());
+        undo_banner = undo_banner.push(
+            container(
+                row![
+                    text(format!(
+                        "Cleared {} {} item{}",
+                        batch.count,
+                        batch.store,
+                        if batch.count == 1 { "" } else { "s" }
+                    ))
+                    .size(13),
+                    Space::new().width(Length::Fill),
+                    button(if restoring { "Restoring..." } else { "Undo" })
+                        .padding([6, 12])
+                        .style(rounded_primary_button)
+                        .on_press_maybe(
+                            state
+                                .trash_restore_in_flight
+                                .is_none()
+                                .then(|| Message::TrashRestore(batch.clone())),
+                        ),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+            )
+            .padding([6, 10])
+            .style(glass_accent_panel),
+        );
+    }
+
     let content = column![
         row![
             inbox_chip("Actionable now", actionable_now),
@@ -1985,6 +4742,14 @@ fn view_inbox_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             inbox_chip("Blocked", blocked),
             inbox_chip("Awaiting human", awaiting_human),
             Space::new().width(Length::Fill),
+            button(if state.inbox_group_by_smart_list {
+                "Group by status"
+            } else {
+                "Smart lists"
+            })
+            .padding([8, 12])
+            .style(rounded_secondary_button)
+            .on_press(Message::InboxToggleSmartListGrouping),
             button("Refresh")
                 .padding([8, 12])
                 .style(rounded_primary_button)
@@ -1992,6 +4757,7 @@ fn view_inbox_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         ]
         .spacing(8)
         .align_y(iced::Alignment::Center),
+        search_panel(state),
         container(
             text("Action semantics: Seen = reviewed • Start = in progress • Block = waiting/dependency • Done = completed • Undo = reopen if DoD not met • Snooze = remind later")
                 .size(13)
@@ -2003,30 +4769,9 @@ fn view_inbox_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         } else {
             text(state.inbox_error.clone()).color([0.95, 0.45, 0.45])
         },
-        inbox_section(
-            "Needs Action",
-            &needs_action_items,
-            state.timeline_focus_origin_ref.as_deref(),
-            state.inbox_action_origin_ref_in_flight.as_deref(),
-        ),
-        inbox_section(
-            "In progress",
-            &in_progress_items,
-            state.timeline_focus_origin_ref.as_deref(),
-            state.inbox_action_origin_ref_in_flight.as_deref(),
-        ),
-        inbox_section(
-            "Blocked",
-            &blocked_items,
-            state.timeline_focus_origin_ref.as_deref(),
-            state.inbox_action_origin_ref_in_flight.as_deref(),
-        ),
-        inbox_section(
-            "Done",
-            &done_items,
-            state.timeline_focus_origin_ref.as_deref(),
-            state.inbox_action_origin_ref_in_flight.as_deref(),
-        ),
+        undo_banner,
+        inbox_bulk_bar(state),
+        sections,
     ]
     .spacing(10)
     .width(Length::Fill);
@@ -2043,34 +4788,179 @@ fn view_inbox_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     .into()
 }
 
-fn inbox_chip<'a>(label: &'a str, value: usize) -> Element<'a, Message> {
-    container(text(format!("{label}: {value}")).size(13))
-        .padding([6, 10])
-        .style(glass_panel)
-        .into()
+/// Shown while items are ticked: applies one action to all of them.
+fn inbox_bulk_bar(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    if state.inbox_selected.is_empty() {
+        return column!().into();
+    }
+    let idle = !state.inbox_bulk_in_flight;
+    container(
+        row![
+            text(if state.inbox_bulk_in_flight {
+                format!("Updating {} selected...", state.inbox_selected.len())
+            } else {
+                format!("{} selected", state.inbox_selected.len())
+            })
+            .size(13),
+            Space::new().width(Length::Fill),
+            button("Seen")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press_maybe(
+                    idle.then_some(Message::InboxBulkAction(InboxActionKind::Acknowledge))
+                ),
+            button("Done")
+                .padding([6, 10])
+                .style(rounded_success_button)
+                .on_press_maybe(idle.then_some(Message::InboxBulkAction(InboxActionKind::Done))),
+            button("Snooze")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press_maybe(idle.then_some(Message::InboxBulkAction(InboxActionKind::Snooze))),
+            button("Clear")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press_maybe(idle.then_some(Message::InboxSelectionCleared)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+    )
+    .padding([6, 10])
+    .style(glass_accent_panel)
+    .into()
 }
 
-#[derive(Clone, Copy)]
-enum BadgeTone {
-    Neutral,
-    Info,
-    Success,
-    Warning,
-    Danger,
-    Muted,
-}
+/// Search box over every store plus chat; results open the item in the
+/// inbox or jump to the chat tab.
+fn search_panel(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut panel = column![row![
+        text_input(
+            "Search todos, reminders, plans and chat…",
+            &state.search_query
+        )
+        .on_input(Message::SearchQueryChanged)
+        .on_submit(Message::SearchSubmitted)
+        .padding(8)
+        .width(Length::Fill),
+        button("Search")
+            .padding([8, 12])
+            .style(rounded_primary_button)
+            .on_press_maybe(
+                (!state.search_in_flight && !state.search_query.trim().is_empty())
+                    .then_some(Message::SearchSubmitted)
+            ),
+        button("Clear")
+            .padding([8, 12])
+            .style(rounded_secondary_button)
+            .on_press_maybe(
+                (!state.search_query.is_empty() || !state.search_hits.is_empty())
+                    .then_some(Message::SearchCleared)
+            ),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center)]
+    .spacing(6);
 
-fn metric_badge(label: &str, value: impl Into<String>) -> Element<'static, Message> {
-    metric_badge_tone(label, value, BadgeTone::Neutral)
+    if !state.search_status.is_empty() {
+        panel = panel.push(text(state.search_status.clone()).size(12));
+    }
+    for hit in &state.search_hits {
+        panel = panel.push(
+            button(
+                column![
+                    row![
+                        container(text(hit.kind.clone()).size(11))
+                            .padding([2, 6])
+                            .style(glass_accent_panel),
+                        text(shown(state, &hit.title)).size(14),
+                        Space::new().width(Length::Fill),
+                        text(format_local_time(hit.updated_at)).size(11),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                    text(shown(state, &hit.snippet)).size(12),
+                ]
+                .spacing(4),
+            )
+            .width(Length::Fill)
+            .padding([6, 10])
+            .style(rounded_secondary_button)
+            .on_press(Message::SearchOpenHit(hit.clone())),
+        );
+    }
+    panel.into()
 }
 
-fn metric_badge_tone(
-    label: &str,
-    value: impl Into<String>,
-    tone: BadgeTone,
-) -> Element<'static, Message> {
-    let style = match tone {
-        BadgeTone::Neutral => glass_panel,
+fn smart_list_section<'a>(
+    state: &ButterflyIcedApp,
+    list: SmartList,
+    items: &[&InboxItem],
+    collapsed: bool,
+    focused_origin_ref: Option<&str>,
+    action_in_flight_origin_ref: Option<&str>,
+) -> Element<'a, Message> {
+    if !collapsed {
+        return column![
+            button(text(format!("▾ {}", list.label())).size(13))
+                .padding([4, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::InboxToggleSmartList(list)),
+            inbox_section(
+                state,
+                list.label(),
+                items,
+                focused_origin_ref,
+                action_in_flight_origin_ref,
+            ),
+        ]
+        .spacing(6)
+        .into();
+    }
+
+    container(
+        row![
+            button(text(format!("▸ {}", list.label())).size(13))
+                .padding([4, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::InboxToggleSmartList(list)),
+            Space::new().width(Length::Fill),
+            inbox_chip("items", items.len()),
+        ]
+        .align_y(iced::Alignment::Center),
+    )
+    .padding(8)
+    .style(glass_panel)
+    .into()
+}
+
+fn inbox_chip<'a>(label: &'a str, value: usize) -> Element<'a, Message> {
+    container(text(format!("{label}: {value}")).size(13))
+        .padding([6, 10])
+        .style(glass_panel)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum BadgeTone {
+    Neutral,
+    Info,
+    Success,
+    Warning,
+    Danger,
+    Muted,
+}
+
+fn metric_badge(label: &str, value: impl Into<String>) -> Element<'static, Message> {
+    metric_badge_tone(label, value, BadgeTone::Neutral)
+}
+
+fn metric_badge_tone(
+    label: &str,
+    value: impl Into<String>,
+    tone: BadgeTone,
+) -> Element<'static, Message> {
+    let style = match tone {
+        BadgeTone::Neutral => glass_panel,
         BadgeTone::Info => glass_accent_panel,
         BadgeTone::Success => glass_success_panel,
         BadgeTone::Warning => glass_warning_panel,
@@ -2084,6 +4974,13 @@ fn metric_badge_tone(
         .into()
 }
 
+fn label_chip(name: String) -> Element<'static, Message> {
+    container(text(format!("#{name}")).size(11))
+        .padding([4, 8])
+        .style(glass_muted_panel)
+        .into()
+}
+
 fn format_minutes_short(minutes: i32) -> String {
     let minutes = minutes.max(1);
     if minutes >= 8 * 60 {
@@ -2098,6 +4995,7 @@ fn format_minutes_short(minutes: i32) -> String {
 }
 
 fn inbox_section<'a>(
+    state: &ButterflyIcedApp,
     title: &'a str,
     items: &[&InboxItem],
     focused_origin_ref: Option<&str>,
@@ -2118,6 +5016,11 @@ fn inbox_section<'a>(
                 InboxSourceType::Todo => "todo",
                 InboxSourceType::Task => "task",
                 InboxSourceType::PlanStep => "plan",
+                InboxSourceType::Approval => "approval",
+                InboxSourceType::Consent => "consent",
+                InboxSourceType::Question => "question",
+                InboxSourceType::External => "external",
+                InboxSourceType::Calendar => "calendar",
             };
             let due_ts = item.due_at.or_else(|| infer_due_at_from_item_text(item));
             let due = due_ts
@@ -2190,6 +5093,28 @@ fn inbox_section<'a>(
                     BadgeTone::Danger,
                 ));
             }
+            if let Some(next) = item.linked_reminders.iter().map(|r| r.due_at).min() {
+                let label = if item.linked_reminders.len() > 1 {
+                    format!(
+                        "{} (+{})",
+                        format_due_badge_time(next),
+                        item.linked_reminders.len() - 1
+                    )
+                } else {
+                    format_due_badge_time(next)
+                };
+                meta_badges = meta_badges.push(metric_badge_tone("Remind", label, BadgeTone::Info));
+            }
+            if let Some(until) = item.held_until {
+                meta_badges = meta_badges.push(metric_badge_tone(
+                    "Held until",
+                    format_due_badge_time(until),
+                    BadgeTone::Warning,
+                ));
+            }
+            for label in &item.labels {
+                meta_badges = meta_badges.push(label_chip(shown(state, label)));
+            }
 
             let row_in_flight = action_in_flight_origin_ref == Some(item.origin_ref.as_str());
             let can_transition = item.status.is_actionable() && !row_in_flight;
@@ -2222,50 +5147,193 @@ fn inbox_section<'a>(
                         | InboxStatus::InProgress
                         | InboxStatus::Blocked
                 );
-            let action_row = row![
-                button("Seen")
-                    .padding([6, 10])
-                    .style(rounded_secondary_button)
-                    .on_press_maybe(
-                        can_seen.then_some(Message::InboxAcknowledge(item.origin_ref.clone()))
-                    ),
-                button("Start")
-                    .padding([6, 10])
-                    .style(rounded_primary_button)
-                    .on_press_maybe(
-                        can_start.then_some(Message::InboxStart(item.origin_ref.clone()))
-                    ),
-                button("Blocked")
+            let action_row = if item.source_type == InboxSourceType::Approval {
+                row![
+                    button("Approve")
+                        .padding([6, 10])
+                        .style(rounded_success_button)
+                        .on_press_maybe((!row_in_flight).then(|| {
+                            Message::InboxDecideApproval(item.origin_ref.clone(), true)
+                        })),
+                    button("Reject")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe((!row_in_flight).then(|| {
+                            Message::InboxDecideApproval(item.origin_ref.clone(), false)
+                        })),
+                ]
+                .spacing(8)
+            } else if item.source_type == InboxSourceType::Consent {
+                row![
+                    button("Allow")
+                        .padding([6, 10])
+                        .style(rounded_success_button)
+                        .on_press_maybe((!row_in_flight).then(|| {
+                            Message::InboxDecideConsent(item.origin_ref.clone(), true)
+                        })),
+                    button("Deny")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe((!row_in_flight).then(|| {
+                            Message::InboxDecideConsent(item.origin_ref.clone(), false)
+                        })),
+                ]
+                .spacing(8)
+            } else if item.source_type == InboxSourceType::Calendar {
+                row![text("Synced from your calendar").size(12)].spacing(8)
+            } else {
+                row![
+                    button("Seen")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe(
+                            can_seen.then_some(Message::InboxAcknowledge(item.origin_ref.clone()))
+                        ),
+                    button("Start")
+                        .padding([6, 10])
+                        .style(rounded_primary_button)
+                        .on_press_maybe(
+                            can_start.then_some(Message::InboxStart(item.origin_ref.clone()))
+                        ),
+                    button("Blocked")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe(
+                            can_block.then_some(Message::InboxBlock(item.origin_ref.clone()))
+                        ),
+                    button("Done")
+                        .padding([6, 10])
+                        .style(rounded_success_button)
+                        .on_press_maybe(
+                            can_done.then_some(Message::InboxDone(item.origin_ref.clone()))
+                        ),
+                    button("Undo")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe(
+                            can_reopen.then_some(Message::InboxReopen(item.origin_ref.clone()))
+                        ),
+                    button("Snooze")
+                        .padding([6, 10])
+                        .style(rounded_secondary_button)
+                        .on_press_maybe(
+                            can_snooze.then_some(Message::InboxSnooze(item.origin_ref.clone()))
+                        ),
+                ]
+                .spacing(8)
+            };
+            let action_row = action_row.push(
+                button("Note")
                     .padding([6, 10])
                     .style(rounded_secondary_button)
                     .on_press_maybe(
-                        can_block.then_some(Message::InboxBlock(item.origin_ref.clone()))
+                        (!row_in_flight)
+                            .then(|| Message::InboxNoteToggled(item.origin_ref.clone())),
                     ),
-                button("Done")
-                    .padding([6, 10])
-                    .style(rounded_success_button)
-                    .on_press_maybe(
-                        can_done.then_some(Message::InboxDone(item.origin_ref.clone()))
-                    ),
-                button("Undo")
+            );
+            let action_row = action_row.push(
+                button("Attach")
                     .padding([6, 10])
                     .style(rounded_secondary_button)
                     .on_press_maybe(
-                        can_reopen.then_some(Message::InboxReopen(item.origin_ref.clone()))
+                        (!row_in_flight)
+                            .then(|| Message::InboxAttachToggled(item.origin_ref.clone())),
                     ),
-                button("Snooze")
-                    .padding([6, 10])
-                    .style(rounded_secondary_button)
-                    .on_press_maybe(
-                        can_snooze.then_some(Message::InboxSnooze(item.origin_ref.clone()))
+            );
+
+            let mut thread = column![].spacing(2);
+            for note in &item.comments {
+                thread = thread.push(
+                    text(format!(
+                        "{} • {}: {}",
+                        format_local_time(note.created_at),
+                        note.author,
+                        shown(state, &note.body)
+                    ))
+                    .size(12),
+                );
+            }
+            for attachment in &item.attachments {
+                thread = thread.push(
+                    text(format!(
+                        "Attachment: {} ({:.1} KB)",
+                        shown(state, &attachment.name),
+                        attachment.size_bytes as f64 / 1024.0
+                    ))
+                    .size(12),
+                );
+            }
+            let composer: Element<'a, Message> = if state.inbox_note_origin_ref.as_deref()
+                == Some(item.origin_ref.as_str())
+            {
+                row![
+                    text_input(
+                        "Add a note, e.g. waiting on vendor reply",
+                        &state.inbox_note_draft
+                    )
+                    .on_input(Message::InboxNoteDraftChanged)
+                    .on_submit(Message::InboxNoteSubmitted)
+                    .padding(6)
+                    .width(Length::Fill),
+                    button("Add")
+                        .padding([6, 10])
+                        .style(rounded_primary_button)
+                        .on_press_maybe(
+                            (!state.inbox_note_draft.trim().is_empty())
+                                .then_some(Message::InboxNoteSubmitted)
+                        ),
+                ]
+                .spacing(8)
+                .into()
+            } else if state.inbox_attach_origin_ref.as_deref() == Some(item.origin_ref.as_str()) {
+                row![
+                    text_input(
+                        "Path to a file, e.g. ~/Pictures/screenshot.png",
+                        &state.inbox_attach_path
+                    )
+                    .on_input(Message::InboxAttachPathChanged)
+                    .on_submit(Message::InboxAttachSubmitted)
+                    .padding(6)
+                    .width(Length::Fill),
+                    button("Upload")
+                        .padding([6, 10])
+                        .style(rounded_primary_button)
+                        .on_press_maybe(
+                            (!state.inbox_attach_path.trim().is_empty())
+                                .then_some(Message::InboxAttachSubmitted)
+                        ),
+                ]
+                .spacing(8)
+                .into()
+            } else {
+                Space::new().width(0).into()
+            };
+
+            // Approvals, consent requests and calendar entries have their
+            // own flows.
+            let selectable = !matches!(
+                item.source_type,
+                InboxSourceType::Approval | InboxSourceType::Consent | InboxSourceType::Calendar
+            );
+            let select_box: Element<'a, Message> = if selectable {
+                let origin_ref = item.origin_ref.clone();
+                row![
+                    checkbox(state.inbox_selected.contains(&item.origin_ref)).on_toggle_maybe(
+                        (!state.inbox_bulk_in_flight)
+                            .then(|| move |_| Message::InboxSelectToggled(origin_ref.clone())),
                     ),
-            ]
-            .spacing(8);
+                    Space::new().width(6),
+                ]
+                .into()
+            } else {
+                Space::new().width(0).into()
+            };
 
             col.push(
                 container(
                     column![
                         row![
+                            select_box,
                             text(if item.status == InboxStatus::New {
                                 "●"
                             } else {
@@ -2280,16 +5348,17 @@ fn inbox_section<'a>(
                                 }
                             ),
                             Space::new().width(8),
-                            text(item.title.clone()).size(16),
+                            text(shown(state, &item.title)).size(16),
                             Space::new().width(Length::Fill),
                             text(inbox_status_label(item.status)).size(12)
                         ]
                         .align_y(iced::Alignment::Center),
                         if let Some(details) = &item.details {
-                            text(details.clone()).size(13)
+                            text(shown(state, details)).size(13)
                         } else {
                             text("").size(1)
                         },
+                        thread,
                         scrollable(meta_badges)
                             .direction(iced::widget::scrollable::Direction::Horizontal(
                                 iced::widget::scrollable::Scrollbar::default(),
@@ -2303,6 +5372,7 @@ fn inbox_section<'a>(
                         ))
                         .size(12),
                         action_row,
+                        composer,
                         if row_in_flight {
                             text("Updating...").size(12).color([0.70, 0.86, 1.0])
                         } else {
@@ -2427,7 +5497,7 @@ fn view_timeline_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                         column![
                             row![
                                 text(if unresolved { "⚠" } else { "⛓" }).size(15),
-                                text(chain).size(13),
+                                text(shown(state, &chain)).size(13),
                             ]
                             .spacing(8)
                             .align_y(iced::Alignment::Center),
@@ -2523,7 +5593,7 @@ fn view_timeline_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                     container(
                         column![
                             row![
-                                text(item.title.clone()).size(15),
+                                text(shown(state, &item.title)).size(15),
                                 Space::new().width(Length::Fill),
                                 text("●").size(13).color(status_color),
                                 text(status).size(12).color(status_color)
@@ -2752,7 +5822,7 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                 col.push(
                     container(
                         column![
-                            text(chain).size(12),
+                            text(shown(state, &chain)).size(12),
                             row![
                                 Space::new().width(Length::Fill),
                                 button("Inbox")
@@ -2781,7 +5851,8 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             .into()
     };
 
-    let column_view = |title: &'static str, items: Vec<&InboxItem>| {
+    let dragging = state.kanban_dragging.as_deref();
+    let column_view = |board_column: KanbanColumn, items: Vec<&InboxItem>| {
         let max_cards = 4usize;
         let item_count = items.len();
         let cards = if items.is_empty() {
@@ -2804,6 +5875,11 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                             InboxSourceType::Todo => "todo",
                             InboxSourceType::Task => "task",
                             InboxSourceType::PlanStep => "plan",
+                            InboxSourceType::Approval => "approval",
+                            InboxSourceType::Consent => "consent",
+                            InboxSourceType::Question => "question",
+                            InboxSourceType::External => "external",
+                            InboxSourceType::Calendar => "calendar",
                         };
                         let size = item
                             .t_shirt_size
@@ -2816,29 +5892,50 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                             .map(format_minutes_short)
                             .unwrap_or_else(|| "-".to_string());
 
-                        col.push(
-                            container(
-                                column![
-                                    text(item.title.clone()).size(14),
-                                    text(format!("{source} • {}", item.owner)).size(12),
-                                    text(format!(
+                        let card = container(
+                            column![
+                                text(shown(state, &item.title)).size(14),
+                                text(shown(state, &format!("{source} • {}", item.owner))).size(12),
+                                text(format!(
                                     "due: {due} • size: {size} • sp: {points} • likely: {likely}"
                                 ))
-                                    .size(11),
-                                    text(item.origin_ref.to_string()).size(11),
-                                ]
-                                .spacing(4),
-                            )
-                            .padding([8, 10])
-                            .width(Length::Fill)
-                            .style(
-                                if item.status == InboxStatus::Blocked {
-                                    glass_alert_panel
-                                } else {
-                                    glass_panel
-                                },
-                            ),
+                                .size(11),
+                                text(item.origin_ref.to_string()).size(11),
+                            ]
+                            .spacing(4),
                         )
+                        .padding([8, 10])
+                        .width(Length::Fill)
+                        .style(
+                            if dragging == Some(item.origin_ref.as_str()) {
+                                glass_accent_panel
+                            } else if item.status == InboxStatus::Blocked {
+                                glass_alert_panel
+                            } else {
+                                glass_panel
+                            },
+                        );
+                        // Approvals, consent requests and calendar entries
+                        // are decided elsewhere.
+                        let draggable = !matches!(
+                            item.source_type,
+                            InboxSourceType::Approval
+                                | InboxSourceType::Consent
+                                | InboxSourceType::Calendar
+                        );
+                        let card: Element<'_, Message> = if draggable {
+                            mouse_area(card)
+                                .on_press(Message::KanbanDragStarted(item.origin_ref.clone()))
+                                .interaction(if dragging.is_some() {
+                                    iced::mouse::Interaction::Grabbing
+                                } else {
+                                    iced::mouse::Interaction::Grab
+                                })
+                                .into()
+                        } else {
+                            card.into()
+                        };
+                        col.push(card)
                     });
 
             let overflow = item_count.saturating_sub(max_cards);
@@ -2858,29 +5955,40 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             cards
         };
 
-        container(
-            column![
-                row![
-                    text(title).size(18),
-                    Space::new().width(Length::Fill),
-                    inbox_chip("items", item_count),
+        mouse_area(
+            container(
+                column![
+                    row![
+                        text(board_column.title()).size(18),
+                        Space::new().width(Length::Fill),
+                        inbox_chip("items", item_count),
+                    ]
+                    .align_y(iced::Alignment::Center),
+                    cards
                 ]
-                .align_y(iced::Alignment::Center),
-                cards
-            ]
-            .spacing(8),
+                .spacing(8),
+            )
+            .padding(8)
+            .style(
+                if dragging.is_some() && state.kanban_hover == Some(board_column) {
+                    glass_accent_panel
+                } else {
+                    glass_panel
+                },
+            )
+            .width(Length::FillPortion(1)),
         )
-        .padding(8)
-        .style(glass_panel)
-        .width(Length::FillPortion(1))
+        .on_release(Message::KanbanDropped(board_column))
+        .on_enter(Message::KanbanHovered(board_column))
+        .on_exit(Message::KanbanHoverEnded(board_column))
     };
 
     let board = row![
-        column_view("NEW", new_items),
-        column_view("SEEN", acknowledged_items),
-        column_view("IN PROGRESS", in_progress_items),
-        column_view("BLOCKED", blocked_items),
-        column_view("DONE", done_items),
+        column_view(KanbanColumn::New, new_items),
+        column_view(KanbanColumn::Seen, acknowledged_items),
+        column_view(KanbanColumn::InProgress, in_progress_items),
+        column_view(KanbanColumn::Blocked, blocked_items),
+        column_view(KanbanColumn::Done, done_items),
     ]
     .spacing(10)
     .height(Length::Fill)
@@ -2891,7 +5999,12 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             row![
                 text("Kanban board + delivery metrics").size(14),
                 Space::new().width(Length::Fill),
-                text("No inline edits in this view").size(12),
+                text(if state.kanban_status.is_empty() {
+                    "Drag cards between columns to move them"
+                } else {
+                    state.kanban_status.as_str()
+                })
+                .size(12),
             ]
             .align_y(iced::Alignment::Center),
         )
@@ -2934,59 +6047,209 @@ fn view_kanban_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     .spacing(10)
     .height(Length::Fill);
 
-    container(
-        scrollable(container(content).padding([4, 14]))
-            .height(Length::Fill)
-            .width(Length::Fill),
+    // A release anywhere on the tab ends the drag; over a column, that
+    // column's drop is handled first.
+    mouse_area(
+        container(
+            scrollable(container(content).padding([4, 14]))
+                .height(Length::Fill)
+                .width(Length::Fill),
+        )
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill),
     )
-    .padding(10)
-    .style(glass_panel)
-    .width(Length::Fill)
-    .height(Length::Fill)
+    .on_release(Message::KanbanDragCancelled)
     .into()
 }
 
-fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+fn export_tone(status: InboxStatus) -> ExportTone {
+    match status {
+        InboxStatus::Done | InboxStatus::Dismissed => ExportTone::Done,
+        InboxStatus::Blocked => ExportTone::Blocked,
+        _ => ExportTone::Open,
+    }
+}
+
+fn gantt_export(state: &ButterflyIcedApp) -> GanttExport {
+    GanttExport {
+        title: "Gantt".to_string(),
+        bars: gantt_rows(state)
+            .into_iter()
+            .map(|row| GanttBar {
+                label: shown(state, &row.item.title),
+                start: row.start,
+                end: row.end,
+                tone: match export_tone(row.item.status) {
+                    ExportTone::Open if row.critical => ExportTone::Critical,
+                    tone => tone,
+                },
+            })
+            .collect(),
+    }
+}
+
+/// Every item on either end of a dependency edge, plus placeholders for
+/// dependencies that aren't loaded.
+fn dependency_export(state: &ButterflyIcedApp) -> DependencyExport {
     let item_index: HashMap<&str, &InboxItem> = state
         .inbox_items
         .iter()
         .map(|item| (item.origin_ref.as_str(), item))
         .collect();
+    let mut nodes: Vec<DependencyNode> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for item in &state.inbox_items {
+        for dep_ref in &item.dependency_refs {
+            edges.push((item.origin_ref.clone(), dep_ref.clone()));
+            for origin_ref in [&item.origin_ref, dep_ref] {
+                if !seen.insert(origin_ref.clone()) {
+                    continue;
+                }
+                nodes.push(match item_index.get(origin_ref.as_str()) {
+                    Some(node) => DependencyNode {
+                        id: origin_ref.clone(),
+                        label: shown(state, &node.title),
+                        tone: export_tone(node.status),
+                    },
+                    None => DependencyNode {
+                        id: origin_ref.clone(),
+                        label: format!("missing ({origin_ref})"),
+                        tone: ExportTone::Missing,
+                    },
+                });
+            }
+        }
+    }
+    DependencyExport {
+        title: "Dependencies".to_string(),
+        nodes,
+        edges,
+    }
+}
 
-    let mut edges = state
-        .inbox_items
-        .iter()
-        .flat_map(|item| {
-            item.dependency_refs.iter().map(|dep_ref| {
-                let dep_item = item_index.get(dep_ref.as_str()).copied();
-                (
-                    item.origin_ref.clone(),
-                    item.title.clone(),
-                    item.owner.clone(),
-                    item.status,
-                    dep_ref.clone(),
-                    dep_item.map(|dep| dep.title.clone()),
-                    dep_item.map(|dep| dep.owner.clone()),
-                    dep_item.is_some(),
-                )
-            })
-        })
-        .collect::<Vec<_>>();
-
-    edges.sort_by(|a, b| {
-        let a_unresolved = !a.7;
-        let b_unresolved = !b.7;
-        a_unresolved
-            .cmp(&b_unresolved)
-            .reverse()
-            .then_with(|| (a.3 != InboxStatus::Blocked).cmp(&(b.3 != InboxStatus::Blocked)))
-            .then_with(|| a.1.cmp(&b.1))
-    });
-
-    let total_edges = edges.len();
-    let unresolved_edges = edges.iter().filter(|edge| !edge.7).count();
-    let blocked_edges = edges
-        .iter()
+/// Broken refs and cycles from the daemon, which sees done and unloaded
+/// items the inbox list leaves out.
+fn view_dependency_check<'a>(
+    state: &'a ButterflyIcedApp,
+    item_index: &HashMap<&str, &InboxItem>,
+) -> Element<'a, Message> {
+    let title_of = |origin_ref: &str| {
+        item_index
+            .get(origin_ref)
+            .map(|item| shown(state, &item.title))
+            .unwrap_or_else(|| origin_ref.to_string())
+    };
+    let mut rows = column!().spacing(6);
+    if let Some(report) = &state.dependency_check {
+        for broken in report.broken.iter().take(10) {
+            rows = rows.push(
+                row![
+                    text(format!("{} → {}", title_of(&broken.from), broken.to)).size(12),
+                    Space::new().width(Length::Fill),
+                    metric_badge_tone("Broken", broken.reason.replace('_', " "), BadgeTone::Danger),
+                ]
+                .spacing(6)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        for cycle in report.cycles.iter().take(10) {
+            let mut path = cycle
+                .iter()
+                .map(|origin_ref| title_of(origin_ref))
+                .collect::<Vec<_>>();
+            if let Some(first) = path.first().cloned() {
+                path.push(first);
+            }
+            rows = rows.push(
+                row![
+                    text(path.join(" → ")).size(12),
+                    Space::new().width(Length::Fill),
+                    metric_badge_tone("Cycle", cycle.len().to_string(), BadgeTone::Warning),
+                ]
+                .spacing(6)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+    }
+    container(
+        column![
+            row![
+                text("Reference check").size(15),
+                Space::new().width(Length::Fill),
+                button("Refresh")
+                    .padding([6, 10])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::RefreshDependencyCheck),
+            ]
+            .align_y(iced::Alignment::Center),
+            text(state.dependency_check_status.clone()).size(12),
+            rows,
+        ]
+        .spacing(8),
+    )
+    .padding(8)
+    .style(glass_panel)
+    .into()
+}
+
+fn view_export_buttons(tab: UiTab) -> Element<'static, Message> {
+    [ExportFormat::Png, ExportFormat::Svg, ExportFormat::Pdf]
+        .into_iter()
+        .fold(row![].spacing(6), |row, format| {
+            row.push(
+                button(text(format.extension().to_ascii_uppercase()).size(12))
+                    .padding([4, 8])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::ExportViewPressed(tab, format)),
+            )
+        })
+        .into()
+}
+
+fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let item_index: HashMap<&str, &InboxItem> = state
+        .inbox_items
+        .iter()
+        .map(|item| (item.origin_ref.as_str(), item))
+        .collect();
+
+    let mut edges = state
+        .inbox_items
+        .iter()
+        .flat_map(|item| {
+            item.dependency_refs.iter().map(|dep_ref| {
+                let dep_item = item_index.get(dep_ref.as_str()).copied();
+                (
+                    item.origin_ref.clone(),
+                    item.title.clone(),
+                    item.owner.clone(),
+                    item.status,
+                    dep_ref.clone(),
+                    dep_item.map(|dep| dep.title.clone()),
+                    dep_item.map(|dep| dep.owner.clone()),
+                    dep_item.is_some(),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    edges.sort_by(|a, b| {
+        let a_unresolved = !a.7;
+        let b_unresolved = !b.7;
+        a_unresolved
+            .cmp(&b_unresolved)
+            .reverse()
+            .then_with(|| (a.3 != InboxStatus::Blocked).cmp(&(b.3 != InboxStatus::Blocked)))
+            .then_with(|| a.1.cmp(&b.1))
+    });
+
+    let total_edges = edges.len();
+    let unresolved_edges = edges.iter().filter(|edge| !edge.7).count();
+    let blocked_edges = edges
+        .iter()
         .filter(|edge| edge.3 == InboxStatus::Blocked)
         .count();
     let cross_owner_edges = edges
@@ -3084,14 +6347,14 @@ fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                         column![
                             row![
                                 text(if unresolved { "⚠" } else { "⛓" }).size(14),
-                                text(format!("{from_title} → {dep_title}")).size(13),
+                                text(shown(state, &format!("{from_title} → {dep_title}"))).size(13),
                                 Space::new().width(Length::Fill),
                                 text(inbox_status_label(status)).size(12),
                             ]
                             .spacing(8)
                             .align_y(iced::Alignment::Center),
                             row![
-                                text(owner_pair).size(11),
+                                text(shown(state, &owner_pair)).size(11),
                                 Space::new().width(Length::Fill),
                                 button("Inbox")
                                     .padding([4, 8])
@@ -3138,7 +6401,7 @@ fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                     col.push(
                         container(
                             row![
-                                text(pair).size(12),
+                                text(shown(state, &pair)).size(12),
                                 Space::new().width(Length::Fill),
                                 metric_badge("Edges", total.to_string()),
                                 metric_badge_tone(
@@ -3178,18 +6441,28 @@ fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                 text("Dependency graph").size(14),
                 Space::new().width(Length::Fill),
                 text("Edges across todos/plans/tasks/reminders").size(12),
+                view_export_buttons(UiTab::Dependencies),
             ]
+            .spacing(8)
             .align_y(iced::Alignment::Center),
         )
         .padding([8, 10])
         .style(glass_accent_panel),
+        text(state.view_export_status.clone()).size(12),
         row![
             inbox_chip("Total edges", total_edges),
             inbox_chip("Unresolved", unresolved_edges),
             inbox_chip("Blocked edges", blocked_edges),
             inbox_chip("Cross-owner", cross_owner_edges),
             inbox_chip("Cross-owner blocked", cross_owner_blocked_edges),
-            inbox_chip("2-node cycles", cycle_count),
+            inbox_chip(
+                "Cycles",
+                state
+                    .dependency_check
+                    .as_ref()
+                    .map(|report| report.cycles.len())
+                    .unwrap_or(cycle_count)
+            ),
         ]
         .spacing(8)
         .align_y(iced::Alignment::Center),
@@ -3207,6 +6480,7 @@ fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         )
         .padding(8)
         .style(glass_panel),
+        view_dependency_check(state, &item_index),
         graph_rows,
     ]
     .spacing(10)
@@ -3224,7 +6498,19 @@ fn view_dependencies_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     .into()
 }
 
-fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+/// A Gantt bar placed by the forward scheduling pass.
+struct GanttRow<'a> {
+    item: &'a InboxItem,
+    start: i64,
+    end: i64,
+    critical: bool,
+    late: bool,
+}
+
+/// Rows the Gantt tab draws, scheduled from their estimates, dependencies
+/// and due dates, in display order. The export uses the same rows so the
+/// file matches the screen.
+fn gantt_rows(state: &ButterflyIcedApp) -> Vec<GanttRow<'_>> {
     let extract_plan_step_ref = |details: Option<&String>| -> Option<String> {
         static PLAN_STEP_REF_RE: OnceLock<Regex> = OnceLock::new();
         let text = details?.as_str();
@@ -3248,8 +6534,11 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .filter(|item| {
             matches!(
                 item.source_type,
-                InboxSourceType::Todo | InboxSourceType::PlanStep
-            ) && (item.due_at.is_some() || item.estimate_likely_minutes.is_some())
+                InboxSourceType::Todo | InboxSourceType::PlanStep | InboxSourceType::Calendar
+            ) && (item.due_at.is_some()
+                || item.estimate_optimistic_minutes.is_some()
+                || item.estimate_likely_minutes.is_some()
+                || item.estimate_pessimistic_minutes.is_some())
                 && !(item.source_type == InboxSourceType::Todo
                     && extract_plan_step_ref(item.details.as_ref())
                         .map(|origin_ref| plan_step_refs_in_view.contains(&origin_ref))
@@ -3257,12 +6546,6 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         })
         .collect::<Vec<_>>();
 
-    let item_index: HashMap<String, &InboxItem> = state
-        .inbox_items
-        .iter()
-        .map(|item| (item.origin_ref.to_ascii_lowercase(), item))
-        .collect();
-
     let mut seen_fingerprints = HashSet::new();
     gantt_rows.retain(|item| {
         let due_bucket = item
@@ -3288,49 +6571,58 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         seen_fingerprints.insert(fingerprint)
     });
 
-    let gantt_start_end = |item: &InboxItem| -> (i64, i64) {
-        let likely_seconds = (item.estimate_likely_minutes.unwrap_or(60).max(1) as i64) * 60;
-        let end_ts = item
-            .due_at
-            .unwrap_or_else(|| item.created_at + likely_seconds)
-            .max(item.created_at + 60);
-
-        let latest_dep_end = item
-            .dependency_refs
-            .iter()
-            .filter_map(|dep_ref| item_index.get(&dep_ref.to_ascii_lowercase()).copied())
-            .map(|dep| {
-                dep.due_at.unwrap_or_else(|| {
-                    dep.created_at + (dep.estimate_likely_minutes.unwrap_or(60).max(1) as i64 * 60)
-                })
-            })
-            .max();
+    // Done items and calendar events are pinned where they finished or
+    // happen; everything else is scheduled from now.
+    let tasks = gantt_rows
+        .iter()
+        .map(|item| scheduling::Task {
+            id: item.origin_ref.to_ascii_lowercase(),
+            optimistic_minutes: item.estimate_optimistic_minutes,
+            likely_minutes: item.estimate_likely_minutes,
+            pessimistic_minutes: item.estimate_pessimistic_minutes,
+            depends_on: item
+                .dependency_refs
+                .iter()
+                .map(|dep_ref| dep_ref.to_ascii_lowercase())
+                .collect(),
+            due_at: item.due_at,
+            fixed_end: if matches!(item.status, InboxStatus::Done | InboxStatus::Dismissed) {
+                Some(item.updated_at)
+            } else if item.source_type == InboxSourceType::Calendar {
+                item.due_at
+            } else {
+                None
+            },
+        })
+        .collect::<Vec<_>>();
+    let plan = scheduling::schedule(&tasks, now_unix_ts());
 
-        let start_from_due = item
-            .due_at
-            .map(|due| (due - likely_seconds).max(0))
-            .unwrap_or(item.created_at);
-
-        let start_ts = latest_dep_end
-            .filter(|dep_end| *dep_end < end_ts)
-            .unwrap_or(start_from_due)
-            .min(end_ts - 60);
-        (start_ts, end_ts)
-    };
+    let mut rows = gantt_rows
+        .into_iter()
+        .zip(plan)
+        .map(|(item, scheduled)| GanttRow {
+            item,
+            start: scheduled.start,
+            end: scheduled.end,
+            critical: scheduled.critical,
+            late: scheduled.late,
+        })
+        .collect::<Vec<_>>();
+    rows.sort_by_key(|row| (row.start, row.end, row.item.created_at));
+    rows
+}
 
-    gantt_rows.sort_by_key(|item| {
-        let (start_ts, end_ts) = gantt_start_end(item);
-        (start_ts, end_ts, item.created_at)
-    });
+fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let gantt_rows = gantt_rows(state);
 
     let time_window_start = gantt_rows
         .iter()
-        .map(|item| gantt_start_end(item).0)
+        .map(|row| row.start)
         .min()
         .unwrap_or_else(now_unix_ts);
     let time_window_end = gantt_rows
         .iter()
-        .map(|item| gantt_start_end(item).1)
+        .map(|row| row.end)
         .max()
         .unwrap_or(time_window_start + 3600)
         .max(time_window_start + 3600);
@@ -3343,9 +6635,14 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     } else {
         gantt_rows
             .into_iter()
-            .fold(column!().spacing(10), |col, item| {
-                let (start_ts, end_ts) = gantt_start_end(item);
-
+            .fold(column!().spacing(10), |col, row| {
+                let GanttRow {
+                    item,
+                    start: start_ts,
+                    end: end_ts,
+                    critical,
+                    late,
+                } = row;
                 let offset_px =
                     (((start_ts - time_window_start).max(0) as f32) / total_window_seconds * 560.0)
                         .clamp(0.0, 560.0);
@@ -3354,7 +6651,11 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
 
                 let size = item.t_shirt_size.clone().unwrap_or_else(|| "-".to_string());
                 let points = item.story_points.unwrap_or(0).max(0);
-                let likely_minutes = item.estimate_likely_minutes.unwrap_or(60).max(1);
+                let expected_minutes = scheduling::expected_minutes(
+                    item.estimate_optimistic_minutes,
+                    item.estimate_likely_minutes,
+                    item.estimate_pessimistic_minutes,
+                );
                 let due_label = item
                     .due_at
                     .map(format_local_time)
@@ -3364,7 +6665,7 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                     container(
                         column![
                             row![
-                                text(item.title.clone()).size(14),
+                                text(shown(state, &item.title)).size(14),
                                 Space::new().width(Length::Fill),
                                 text(format!("size: {size} • points: {points}")).size(12),
                             ]
@@ -3379,6 +6680,8 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                                         glass_success_panel
                                     } else if item.status == InboxStatus::Blocked {
                                         glass_alert_panel
+                                    } else if critical {
+                                        glass_warning_panel
                                     } else {
                                         glass_accent_panel
                                     }
@@ -3388,10 +6691,12 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                             .spacing(0)
                             .align_y(iced::Alignment::Center),
                             text(format!(
-                                "start {} • due {} • likely {} • ref {}",
+                                "start {} • end {} • due {}{} • expected {} • ref {}",
                                 format_local_time(start_ts),
+                                format_local_time(end_ts),
                                 due_label,
-                                format_minutes_short(likely_minutes),
+                                if late { " (late)" } else { "" },
+                                format_minutes_short(expected_minutes as i32),
                                 item.origin_ref
                             ))
                             .size(11),
@@ -3407,14 +6712,17 @@ fn view_gantt_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     let content = column![
         container(
             row![
-                text("Read-only Gantt").size(14),
+                text("Scheduled Gantt").size(14),
                 Space::new().width(Length::Fill),
-                text("Blue=open • Green=done • Red=blocked (due-date aligned)").size(12),
+                text("Blue=open • Amber=critical path • Green=done • Red=blocked").size(12),
+                view_export_buttons(UiTab::Gantt),
             ]
+            .spacing(8)
             .align_y(iced::Alignment::Center),
         )
         .padding([8, 10])
         .style(glass_accent_panel),
+        text(state.view_export_status.clone()).size(12),
         rows,
     ]
     .spacing(10)
@@ -3593,20 +6901,8 @@ fn view_burndown_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
 }
 
 fn view_audit_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
-    let filtered_events = state
+    let lines = state
         .audit_events
-        .iter()
-        .filter(|event| {
-            if let Some(origin_ref) = state.audit_origin_filter.as_ref() {
-                event.origin_ref.as_deref() == Some(origin_ref.as_str())
-            } else {
-                true
-            }
-        })
-        .cloned()
-        .collect::<Vec<_>>();
-
-    let lines = filtered_events
         .iter()
         .fold(column!().spacing(6), |col, event| {
             let (icon, tone, panel) = if event.line.contains("error")
@@ -3657,7 +6953,7 @@ fn view_audit_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                 container(
                     row![
                         text(icon).size(16).color(tone),
-                        text(event.line.clone()).size(13),
+                        text(shown(state, &event.line)).size(13),
                         Space::new().width(Length::Fill),
                         open_item_button,
                     ]
@@ -3714,11 +7010,21 @@ fn view_audit_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                 .into()
         };
 
+    let load_older: Element<'_, Message> = if state.audit_next_before_id.is_some() {
+        button("Load older")
+            .padding([4, 10])
+            .style(rounded_secondary_button)
+            .on_press_maybe((!state.audit_refresh_in_flight).then_some(Message::AuditLoadOlder))
+            .into()
+    } else {
+        Space::new().height(0).into()
+    };
+
     let content = column![
         row![
             text("Audit trail").size(18),
             Space::new().width(Length::Fill),
-            inbox_chip("Events", filtered_events.len()),
+            inbox_chip("Events", state.audit_events.len()),
             text(last_sync).size(12),
             Space::new().width(Length::Fill),
             button("Refresh")
@@ -3730,6 +7036,7 @@ fn view_audit_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .align_y(iced::Alignment::Center),
         status_banner,
         filter_bar,
+        load_older,
         lines,
     ]
     .spacing(10);
@@ -3831,8 +7138,21 @@ fn view_chat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                             .on_press(Message::CopyToClipboard(msg.text.clone()))
                     ]
                     .align_y(iced::Alignment::Center),
-                    markdown::view(msg.markdown_items.iter(), markdown_render_settings())
-                        .map(Message::MarkdownLinkClicked)
+                    if msg.text.is_empty() && state.streaming_message_id == Some(msg.id) {
+                        Element::from(text("…").size(14))
+                    } else if state.presentation_mode {
+                        // Parsed markdown carries the raw text, so masked
+                        // messages are drawn plain.
+                        Element::from(text(shown(state, &msg.text)).size(14))
+                    } else {
+                        markdown::view(msg.markdown_items.iter(), markdown_render_settings())
+                            .map(Message::MarkdownLinkClicked)
+                    },
+                    if state.presentation_mode {
+                        Element::from(Space::new().height(0))
+                    } else {
+                        view_inline_charts(&msg.text)
+                    }
                 ]
                 .spacing(6),
             )
@@ -3863,15 +7183,64 @@ fn view_chat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             .on_submit(Message::SendPressed)
             .padding(12)
             .width(Length::Fill),
-        button(if state.busy { "Sending..." } else { "Send" })
-            .padding([10, 16])
-            .style(rounded_primary_button)
-            .on_press_maybe((!state.busy).then_some(Message::SendPressed)),
+        button(text(if state.voice_transcribing {
+            "..."
+        } else if state.voice_recording.is_some() {
+            "Stop"
+        } else {
+            "Mic"
+        }))
+        .padding([10, 14])
+        .style(if state.voice_recording.is_some() {
+            rounded_danger_button
+        } else {
+            rounded_secondary_button
+        })
+        .on_press_maybe((!state.voice_transcribing).then_some(Message::VoicePressed)),
+        button(text(match (state.busy, state.prompt_queue_position) {
+            (true, Some(position)) => format!("Queued #{position}"),
+            (true, None) => "Sending...".to_string(),
+            (false, _) => "Send".to_string(),
+        }))
+        .padding([10, 16])
+        .style(rounded_primary_button)
+        .on_press_maybe((!state.busy).then_some(Message::SendPressed)),
     ]
     .spacing(10)
     .align_y(iced::Alignment::Center);
 
+    let catch_up_banner: Element<'_, Message> = if let Some(catch_up) = state.catch_up.as_ref() {
+        container(
+            row![
+                column![
+                    text("While you were away").size(14),
+                    text(format!(
+                        "Since {}: {}",
+                        format_local_time(catch_up.since),
+                        catch_up.summary
+                    ))
+                    .size(12),
+                ]
+                .spacing(2),
+                Space::new().width(Length::Fill),
+                button("Dismiss")
+                    .padding([4, 10])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::DismissCatchUp),
+            ]
+            .align_y(iced::Alignment::Center),
+        )
+        .padding([8, 10])
+        .style(glass_accent_panel)
+        .width(Length::Fill)
+        .into()
+    } else {
+        Space::new().height(0).into()
+    };
+
     column![
+        chat_model_header(state),
+        catch_up_banner,
         container(
             scrollable(container(list).padding([0, 14]).width(Length::Fill))
                 .id(state.chat_scroll_id.clone())
@@ -3890,6 +7259,11 @@ fn view_chat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             text(state.error.clone()).color([0.95, 0.45, 0.45])
         },
         anchor_banner,
+        if state.voice_status.is_empty() {
+            text("")
+        } else {
+            text(state.voice_status.clone()).size(12)
+        },
         composer
     ]
     .spacing(10)
@@ -3897,6 +7271,40 @@ fn view_chat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     .into()
 }
 
+fn chat_model_header(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let Some(model) = state
+        .chat_model
+        .as_ref()
+        .filter(|model| !model.label.is_empty())
+    else {
+        return Space::new().height(0).into();
+    };
+    let mut header = row![text(format!("Model: {}", model.label)).size(12)]
+        .spacing(8)
+        .align_y(iced::Alignment::Center);
+    if let Some(temperature) = model.temperature {
+        header = header.push(text(format!("temperature {temperature:.1}")).size(12));
+    }
+    header = header.push(Space::new().width(Length::Fill));
+    header = header.push(
+        container(
+            text(if model.pinned {
+                "pinned to this thread"
+            } else {
+                "global default"
+            })
+            .size(11),
+        )
+        .padding([4, 8])
+        .style(glass_muted_panel),
+    );
+    container(header)
+        .padding([6, 10])
+        .style(glass_panel)
+        .width(Length::Fill)
+        .into()
+}
+
 fn view_activity_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     let list = state
         .activity_messages
@@ -3908,7 +7316,7 @@ fn view_activity_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                         text(format!(
                             "[{}] {}",
                             format_local_time(msg.timestamp),
-                            msg.text
+                            shown(state, &msg.text)
                         )),
                         Space::new().width(Length::Fill),
                         button(text("📋").size(14))
@@ -3933,10 +7341,185 @@ fn view_activity_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .into()
 }
 
+fn heatmap_cell_style(warmth: f32) -> impl Fn(&Theme) -> iced::widget::container::Style {
+    move |_theme| iced::widget::container::Style {
+        text_color: None,
+        background: Some(Background::Color(if warmth > 0.0 {
+            Color::from_rgba(0.39, 0.40, 0.95, 0.15 + 0.80 * warmth)
+        } else {
+            Color::from_rgba(1.0, 1.0, 1.0, 0.04)
+        })),
+        border: Border {
+            radius: 3.0.into(),
+            width: 0.0,
+            color: Color::TRANSPARENT,
+        },
+        shadow: Shadow::default(),
+        snap: false,
+    }
+}
+
+fn view_insights_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let Some(heatmap) = state.activity_heatmap.as_ref() else {
+        let status = if state.heatmap_error.is_empty() {
+            "Loading activity heatmap...".to_string()
+        } else {
+            format!("Heatmap unavailable: {}", state.heatmap_error)
+        };
+        return container(text(status))
+            .padding(16)
+            .style(glass_panel)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into();
+    };
+
+    let max = heatmap.max_count().max(1) as f32;
+    let hours_header = (0..24).fold(row![Space::new().width(44)].spacing(3), |header, hour| {
+        header.push(
+            container(
+                text(if hour % 3 == 0 {
+                    format!("{hour:02}")
+                } else {
+                    String::new()
+                })
+                .size(11),
+            )
+            .width(22),
+        )
+    });
+    let grid = (0..7).fold(column![hours_header].spacing(3), |grid, weekday| {
+        let cells = (0..24).fold(
+            row![container(text(weekday_label(weekday)).size(12)).width(44)].spacing(3),
+            |cells, hour| {
+                let count = heatmap.count(weekday, hour);
+                cells.push(
+                    container(Space::new())
+                        .width(22)
+                        .height(22)
+                        .style(heatmap_cell_style(count as f32 / max)),
+                )
+            },
+        );
+        grid.push(cells.align_y(iced::Alignment::Center))
+    });
+
+    let peaks = heatmap
+        .peak_slots(3)
+        .into_iter()
+        .map(|(weekday, hour, count)| format!("{} {hour:02}:00 ({count})", weekday_label(weekday)))
+        .collect::<Vec<_>>();
+    let summary = if heatmap.total == 0 {
+        "No items started or finished yet. Move inbox items to In Progress or Done to build your rhythm.".to_string()
+    } else {
+        format!(
+            "{} items started or finished in the last {} days. Busiest: {}.",
+            heatmap.total,
+            heatmap.window_days,
+            peaks.join(", ")
+        )
+    };
+    let nudge_note = if heatmap.is_confident() {
+        "Proactive nudges are held during hours you rarely act on items."
+    } else {
+        "Nudges aren't timed by this yet; it needs a few more weeks of activity."
+    };
+
+    container(
+        scrollable(
+            column![
+                text("When you get things done").size(22),
+                text(summary).size(14),
+                text(nudge_note).size(13),
+                grid,
+                view_trends(state.aggregate_insights.as_ref()),
+            ]
+            .spacing(12),
+        )
+        .height(Length::Fill)
+        .width(Length::Fill),
+    )
+    .padding(16)
+    .style(glass_panel)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
+
+fn view_trends(insights: Option<&AggregateInsights>) -> Element<'_, Message> {
+    let title = text("Trends").size(22);
+    let note = text(
+        "Figures are computed from hashed items with a little noise added; groups under five items are left out.",
+    )
+    .size(13);
+    let Some((insights, overall)) =
+        insights.and_then(|insights| insights.overall.as_ref().map(|overall| (insights, overall)))
+    else {
+        return column![
+            title,
+            note,
+            text("Not enough activity in the last four weeks yet.").size(14),
+        ]
+        .spacing(6)
+        .into();
+    };
+
+    let cycle = |hours: Option<f64>| hours.map(format_hours).unwrap_or_else(|| "–".to_string());
+    let mut headline = format!(
+        "~{} items in the last {} days, {:.0}% done, average cycle {}.",
+        overall.items,
+        insights.window_days,
+        overall.completion_rate * 100.0,
+        cycle(overall.avg_cycle_hours)
+    );
+    if let Some(accuracy) = &insights.estimate_accuracy {
+        headline.push_str(&format!(
+            " Estimates: {:.0}% on target, work takes {:.1}× the estimate on average.",
+            accuracy.on_target_rate * 100.0,
+            accuracy.mean_ratio
+        ));
+    }
+    let tags = insights
+        .by_tag
+        .iter()
+        .fold(column![].spacing(4), |col, tag| {
+            col.push(
+                row![
+                    text(tag.tag.clone()).size(13).width(Length::FillPortion(2)),
+                    text(format!("~{} items", tag.stats.items))
+                        .size(13)
+                        .width(Length::FillPortion(1)),
+                    text(format!("{:.0}% done", tag.stats.completion_rate * 100.0))
+                        .size(13)
+                        .width(Length::FillPortion(1)),
+                    text(format!("cycle {}", cycle(tag.stats.avg_cycle_hours)))
+                        .size(13)
+                        .width(Length::FillPortion(1)),
+                ]
+                .spacing(8),
+            )
+        });
+    let suppressed = if insights.suppressed_tags > 0 {
+        format!("{} smaller tags not shown.", insights.suppressed_tags)
+    } else {
+        String::new()
+    };
+    column![
+        title,
+        note,
+        text(headline).size(14),
+        tags,
+        text(suppressed).size(12)
+    ]
+    .spacing(6)
+    .into()
+}
+
 fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     let mcp_rows = state.settings.mcp_servers.iter().enumerate().fold(
         column!().spacing(8),
         |col, (index, server)| {
+            let stdio = server.url.trim().is_empty() && !server.command.trim().is_empty();
             col.push(
                 column![
                     row![
@@ -3955,12 +7538,32 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
                     ]
                     .spacing(8)
                     .align_y(iced::Alignment::Center),
+                    text_input(
+                        "Or a local command (stdio), e.g. npx -y @modelcontextprotocol/server-memory",
+                        &server.command
+                    )
+                    .on_input(move |value| Message::McpServerCommandChanged(index, value))
+                    .padding(8),
                     row![
-                        text_input("Header key (optional)", &server.header_key)
-                            .on_input(move |value| Message::McpServerHeaderKeyChanged(index, value))
-                            .padding(8)
-                            .width(Length::FillPortion(1)),
-                        text_input("Header value (optional)", &server.header_value)
+                        text_input(
+                            if stdio {
+                                "Env var name (optional)"
+                            } else {
+                                "Header key (optional)"
+                            },
+                            &server.header_key
+                        )
+                        .on_input(move |value| Message::McpServerHeaderKeyChanged(index, value))
+                        .padding(8)
+                        .width(Length::FillPortion(1)),
+                        text_input(
+                            if stdio {
+                                "Env var value (optional)"
+                            } else {
+                                "Header value (optional)"
+                            },
+                            &server.header_value
+                        )
                             .on_input(move |value| Message::McpServerHeaderValueChanged(
                                 index, value
                             ))
@@ -3975,9 +7578,70 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         },
     );
 
-    let http_rows = state.settings.http_call_servers.iter().enumerate().fold(
+    let inbox_rule_rows = state.settings.inbox_rules.iter().enumerate().fold(
         column!().spacing(8),
-        |col, (index, server)| {
+        |col, (index, rule)| {
+            let field = move |field: InboxRuleField| {
+                move |value| Message::InboxRuleChanged(index, field, value)
+            };
+            col.push(
+                column![
+                    row![
+                        text_input("Rule name", &rule.name)
+                            .on_input(field(InboxRuleField::Name))
+                            .padding(8)
+                            .width(Length::FillPortion(2)),
+                        text_input("Priority (low…critical)", &rule.priority)
+                            .on_input(field(InboxRuleField::Priority))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        text_input("Owner (human|agent)", &rule.owner)
+                            .on_input(field(InboxRuleField::Owner))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        button(match rule.requires_human_action {
+                            None => "Needs human: as is",
+                            Some(true) => "Needs human: yes",
+                            Some(false) => "Needs human: no",
+                        })
+                        .padding([8, 10])
+                        .style(rounded_secondary_button)
+                        .on_press(Message::InboxRuleHumanActionCycled(index)),
+                        button("Remove")
+                            .padding([8, 10])
+                            .style(rounded_danger_button)
+                            .on_press(Message::RemoveInboxRule(index)),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center),
+                    row![
+                        text_input("Sources (reminder, todo, …)", &rule.source_types)
+                            .on_input(field(InboxRuleField::SourceTypes))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        text_input("Keywords", &rule.keywords)
+                            .on_input(field(InboxRuleField::Keywords))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        text_input("Labels", &rule.labels)
+                            .on_input(field(InboxRuleField::Labels))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        text_input("Due within hours", &rule.due_within_hours)
+                            .on_input(field(InboxRuleField::DueWithinHours))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                    ]
+                    .spacing(8),
+                ]
+                .spacing(8),
+            )
+        },
+    );
+
+    let http_rows = state.settings.http_call_servers.iter().enumerate().fold(
+        column!().spacing(8),
+        |col, (index, server)| {
             col.push(
                 column![
                     row![
@@ -4018,6 +7682,55 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         },
     );
 
+    let zap_rows =
+        state
+            .settings
+            .zaps
+            .iter()
+            .enumerate()
+            .fold(column!().spacing(8), |col, (index, zap)| {
+                col.push(
+                    column![
+                        row![
+                            text_input("Zap name", &zap.name)
+                                .on_input(move |value| Message::ZapNameChanged(index, value))
+                                .padding(8)
+                                .width(Length::FillPortion(2)),
+                            text_input(
+                                "https://hooks.zapier.com/hooks/catch/... (optional)",
+                                &zap.url
+                            )
+                            .on_input(move |value| Message::ZapUrlChanged(index, value))
+                            .secure(true)
+                            .padding(8)
+                            .width(Length::FillPortion(3)),
+                            button("Remove")
+                                .padding([8, 10])
+                                .style(rounded_danger_button)
+                                .on_press(Message::RemoveZap(index)),
+                        ]
+                        .spacing(8)
+                        .align_y(iced::Alignment::Center),
+                        row![
+                            text_input("Fields, e.g. email:string, amount:number?", &zap.fields)
+                                .on_input(move |value| Message::ZapFieldsChanged(index, value))
+                                .padding(8)
+                                .width(Length::FillPortion(1)),
+                            text_input(
+                                "Inbox user for inbound webhook (optional)",
+                                &zap.inbound_user
+                            )
+                            .on_input(move |value| Message::ZapInboundUserChanged(index, value))
+                            .padding(8)
+                            .width(Length::FillPortion(1)),
+                        ]
+                        .spacing(8)
+                        .align_y(iced::Alignment::Center),
+                    ]
+                    .spacing(8),
+                )
+            });
+
     let form = column![
             row![
                 text("Provider: OpenAI (fixed)").size(14),
@@ -4111,6 +7824,134 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .spacing(6))
         .padding(10)
         .style(glass_panel),
+        container(column![
+            text("Read aloud").size(16),
+            text("Spoken by the local engine in tools.settings.voice.speech (piper by default), one at a time.").size(13),
+            row![
+                text("Chat replies").size(13),
+                Space::new().width(Length::Fill),
+                button(if state.settings.speak_chat_replies {
+                    "On"
+                } else {
+                    "Off"
+                })
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::ToggleSpeakChatReplies),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+            row![
+                text("Inbox reminders and nudges").size(13),
+                Space::new().width(Length::Fill),
+                button(if state.settings.speak_inbox { "On" } else { "Off" })
+                    .padding([6, 10])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::ToggleSpeakInbox),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        ]
+        .spacing(6))
+        .padding(10)
+        .style(glass_panel),
+        container(column![
+            text("Privacy lock").size(16),
+            text("Locking hides everything until the passphrase is entered; reminder notifications only show a count meanwhile.").size(13),
+            text_input(
+                "Lock after idle minutes (0 = manual only)",
+                &state.settings.privacy_lock_idle_minutes
+            )
+            .on_input(Message::PrivacyLockIdleMinutesChanged)
+            .padding(8),
+            text_input(
+                "New lock passphrase (leave empty to keep the current one)",
+                &state.settings.privacy_lock_passphrase
+            )
+            .secure(true)
+            .on_input(Message::PrivacyLockPassphraseChanged)
+            .padding(8),
+        ]
+        .spacing(8))
+        .padding(10)
+        .style(glass_panel),
+        container(column![
+            text("Presentation mode").size(16),
+            text("The 👁 button masks wallet addresses, amounts, hosts in links and these names on every tab. Stored data is untouched.").size(13),
+            text_input(
+                "Names to mask, comma-separated",
+                &state.settings.presentation_names
+            )
+            .on_input(Message::PresentationNamesChanged)
+            .padding(8),
+        ]
+        .spacing(8))
+        .padding(10)
+        .style(glass_panel),
+        container(column![
+            text("Remote storage").size(16),
+            text("Backups, chart exports and audit archives are encrypted with the passphrase before upload. Save before testing.").size(13),
+            text_input("Kind (s3|webdav|off)", &state.settings.remote_storage_kind)
+                .on_input(Message::RemoteStorageKindChanged)
+                .padding(8),
+            text_input(
+                "S3 endpoint or WebDAV URL",
+                &state.settings.remote_storage_location
+            )
+            .on_input(Message::RemoteStorageLocationChanged)
+            .padding(8),
+            row![
+                text_input("Bucket (S3)", &state.settings.remote_storage_bucket)
+                    .on_input(Message::RemoteStorageBucketChanged)
+                    .padding(8)
+                    .width(Length::FillPortion(1)),
+                text_input("Region (S3)", &state.settings.remote_storage_region)
+                    .on_input(Message::RemoteStorageRegionChanged)
+                    .padding(8)
+                    .width(Length::FillPortion(1)),
+            ]
+            .spacing(8),
+            row![
+                text_input(
+                    "Access key id or username",
+                    &state.settings.remote_storage_account
+                )
+                .on_input(Message::RemoteStorageAccountChanged)
+                .padding(8)
+                .width(Length::FillPortion(1)),
+                text_input(
+                    "Secret key or password",
+                    &state.settings.remote_storage_secret
+                )
+                .secure(true)
+                .on_input(Message::RemoteStorageSecretChanged)
+                .padding(8)
+                .width(Length::FillPortion(1)),
+            ]
+            .spacing(8),
+            text_input("Folder prefix (optional)", &state.settings.remote_storage_prefix)
+                .on_input(Message::RemoteStoragePrefixChanged)
+                .padding(8),
+            text_input(
+                "New encryption passphrase (leave empty to keep the current one)",
+                &state.settings.remote_storage_passphrase
+            )
+            .secure(true)
+            .on_input(Message::RemoteStoragePassphraseChanged)
+            .padding(8),
+            row![
+                button("Test connection")
+                    .padding([8, 12])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::TestRemoteStoragePressed),
+                text(state.remote_storage_status.clone()).size(13),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        ]
+        .spacing(8))
+        .padding(10)
+        .style(glass_panel),
         container(column![
             text("MCP Servers").size(16),
             mcp_rows,
@@ -4122,6 +7963,18 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .spacing(8))
         .padding(10)
         .style(glass_panel),
+        container(column![
+            text("Inbox rules").size(16),
+            text("Applied in order when the inbox is built; later matches win. Every filled-in condition must hold.").size(13),
+            inbox_rule_rows,
+            button("+ Add rule")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::AddInboxRule),
+        ]
+        .spacing(8))
+        .padding(10)
+        .style(glass_panel),
         container(column![
             text("HTTP Call Servers").size(16),
             http_rows,
@@ -4133,6 +7986,18 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
         .spacing(8))
         .padding(10)
         .style(glass_panel),
+        container(column![
+            text("Zaps").size(16),
+            text("Webhook URLs the agent may trigger, and Zaps that post to /webhooks/zapier/<name> with the vault secret zapier_hook_<name>_secret.").size(13),
+            zap_rows,
+            button("+ Add Zap")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::AddZap),
+        ]
+        .spacing(8))
+        .padding(10)
+        .style(glass_panel),
         container(column![
             text("Network + system").size(16),
             text("Search default deny: enabled (fixed)").size(14),
@@ -4158,7 +8023,7 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             if let Some(address) = &state.solana_wallet_address {
                 row![
                     text("Butterfly Bot Wallet address:").size(14),
-                    text(address.clone()).size(14),
+                    text(shown(state, address)).size(14),
                     Space::new().width(Length::Fill),
                     button(text("📋").size(14))
                         .padding(6)
@@ -4184,12 +8049,16 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
             if state.solana_wallet_status.is_empty() {
                 text("")
             } else {
-                text(state.solana_wallet_status.clone()).size(13)
+                text(shown(state, &state.solana_wallet_status)).size(13)
             }
         ]
         .spacing(8))
         .padding(10)
         .style(glass_panel),
+        tool_posture_panel(state),
+        secrets_panel(state),
+        consent_panel(state),
+        template_import_panel(state),
         if state.settings_error.is_empty() {
             text(state.settings_status.clone()).color([0.55, 0.9, 0.65])
         } else {
@@ -4198,623 +8067,2243 @@ fn view_settings_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
     ]
     .spacing(12);
 
-    container(
-        scrollable(container(form).width(Length::Fill).padding([0, 16]))
-            .height(Length::Fill)
-            .width(Length::Fill),
-    )
-    .padding(10)
-    .style(glass_panel)
-    .width(Length::Fill)
-    .height(Length::Fill)
-    .into()
+    container(
+        scrollable(container(form).width(Length::Fill).padding([0, 16]))
+            .height(Length::Fill)
+            .width(Length::Fill),
+    )
+    .padding(10)
+    .style(glass_panel)
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
+
+/// Settings panel listing each registered tool with its risk label, what its
+/// capabilities reach, the module build it runs and when it was last used.
+fn tool_posture_panel(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut panel = column![row![
+        text("Tool security").size(16),
+        Space::new().width(Length::Fill),
+        button(if state.tool_posture_in_flight {
+            "Loading..."
+        } else {
+            "Refresh"
+        })
+        .padding([6, 10])
+        .style(rounded_secondary_button)
+        .on_press_maybe((!state.tool_posture_in_flight).then_some(Message::ToolPostureRefresh)),
+    ]
+    .spacing(8)
+    .align_y(iced::Alignment::Center)]
+    .spacing(8);
+
+    for tool in &state.tool_postures {
+        let tone = match tool.risk.as_str() {
+            "high" => BadgeTone::Danger,
+            "medium" => BadgeTone::Warning,
+            _ => BadgeTone::Success,
+        };
+        let areas = if tool.areas.is_empty() {
+            "local data only".to_string()
+        } else {
+            tool.areas.join(", ")
+        };
+        let build = match (&tool.version, &tool.module_sha256) {
+            (Some(version), Some(digest)) => {
+                format!("v{version} · {}", &digest[..digest.len().min(12)])
+            }
+            (None, Some(digest)) => format!("custom · {}", &digest[..digest.len().min(12)]),
+            _ => "module not found".to_string(),
+        };
+        let last_used = tool
+            .last_used_at
+            .map(|ts| format!("last used {}", format_local_time(ts)))
+            .unwrap_or_else(|| "never used".to_string());
+        panel = panel.push(
+            column![
+                row![
+                    text(tool.name.clone()).size(14),
+                    metric_badge_tone("risk", tool.risk.clone(), tone),
+                    text(areas).size(12),
+                    Space::new().width(Length::Fill),
+                    text(last_used).size(11),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+                text(format!(
+                    "{build} · {} capabilit{}",
+                    tool.capabilities.len(),
+                    if tool.capabilities.len() == 1 {
+                        "y"
+                    } else {
+                        "ies"
+                    }
+                ))
+                .size(11),
+            ]
+            .spacing(2),
+        );
+    }
+    if !state.tool_posture_status.is_empty() {
+        panel = panel.push(text(state.tool_posture_status.clone()).size(13));
+    }
+
+    container(panel).padding(10).style(glass_panel).into()
+}
+
+/// Settings panel for named vault secrets. Values go straight to the daemon
+/// and are never shown again; the list only carries metadata.
+fn secrets_panel(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut panel = column![
+        row![
+            text("Secrets").size(16),
+            Space::new().width(Length::Fill),
+            button(if state.secrets_in_flight {
+                "Loading..."
+            } else {
+                "Refresh"
+            })
+            .padding([6, 10])
+            .style(rounded_secondary_button)
+            .on_press_maybe((!state.secrets_in_flight).then_some(Message::SecretsRefresh)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text("API keys for integrations live in the OS keychain. Reference them by name in tool config instead of pasting them into the JSON.").size(13),
+    ]
+    .spacing(8);
+
+    for secret in &state.secrets {
+        panel = panel.push(
+            row![
+                text(secret.name.clone()).size(14),
+                text(secret.description.clone().unwrap_or_default()).size(12),
+                Space::new().width(Length::Fill),
+                text(format!(
+                    "v{} · rotated {}",
+                    secret.version,
+                    format_local_time(secret.rotated_at)
+                ))
+                .size(11),
+                button("Delete")
+                    .padding([4, 8])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::DeleteSecretPressed(secret.name.clone())),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        );
+    }
+
+    let rotating = state
+        .secrets
+        .iter()
+        .any(|secret| secret.name == state.secret_name.trim());
+    panel = panel.push(
+        row![
+            text_input("Name", &state.secret_name)
+                .on_input(Message::SecretNameChanged)
+                .padding(8)
+                .width(Length::FillPortion(2)),
+            text_input("Value", &state.secret_value)
+                .secure(true)
+                .on_input(Message::SecretValueChanged)
+                .padding(8)
+                .width(Length::FillPortion(3)),
+            text_input("Description (optional)", &state.secret_description)
+                .on_input(Message::SecretDescriptionChanged)
+                .padding(8)
+                .width(Length::FillPortion(3)),
+            button(if rotating { "Rotate" } else { "Add" })
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::SaveSecretPressed),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+    );
+    if !state.secrets_status.is_empty() {
+        panel = panel.push(text(state.secrets_status.clone()).size(13));
+    }
+
+    container(panel).padding(10).style(glass_panel).into()
+}
+
+/// Settings panel listing what the user allowed or denied the agent, with
+/// a way to take a decision back.
+fn consent_panel(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut panel = column![
+        row![
+            text("Consent log").size(16),
+            Space::new().width(Length::Fill),
+            button(if state.consent_in_flight {
+                "Loading..."
+            } else {
+                "Refresh"
+            })
+            .padding([6, 10])
+            .style(rounded_secondary_button)
+            .on_press_maybe((!state.consent_in_flight).then_some(Message::ConsentRefresh)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text("Transfers, GitHub calls and requests to new domains ask once in the inbox. Revoke a decision to be asked again.").size(13),
+    ]
+    .spacing(8);
+
+    if state.consent_log.is_empty() {
+        panel = panel.push(text("Nothing asked yet").size(12));
+    }
+    for entry in &state.consent_log {
+        let revocable = matches!(entry.status.as_str(), "granted" | "denied");
+        panel = panel.push(
+            row![
+                text(entry.summary.clone()).size(13),
+                Space::new().width(Length::Fill),
+                text(format!("asked {}", format_local_time(entry.requested_at))).size(11),
+                button("Revoke")
+                    .padding([4, 8])
+                    .style(rounded_secondary_button)
+                    .on_press_maybe(revocable.then_some(Message::RevokeConsentPressed(entry.id))),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        );
+    }
+    if !state.consent_status.is_empty() {
+        panel = panel.push(text(state.consent_status.clone()).size(13));
+    }
+
+    container(panel).padding(10).style(glass_panel).into()
+}
+
+/// Settings panel that previews a `.butterfly-template.json` bundle and
+/// imports it only after the user has seen what it creates.
+fn template_import_panel(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut panel = column![
+        text("Templates").size(16),
+        text("Import a shared .butterfly-template.json bundle. Nothing is created until you review it.")
+            .size(13),
+        row![
+            text_input("Path to .butterfly-template.json", &state.template_path)
+                .on_input(Message::TemplatePathChanged)
+                .on_submit(Message::TemplateReviewPressed)
+                .padding(8)
+                .width(Length::Fill),
+            button("Review")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press_maybe(
+                    (!state.template_in_flight && !state.template_path.trim().is_empty())
+                        .then_some(Message::TemplateReviewPressed)
+                ),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+    ]
+    .spacing(8);
+
+    if let Some(review) = &state.template_review {
+        let preview = &review.preview;
+        let mut byline = preview.name.clone();
+        if let Some(author) = preview.author.as_deref() {
+            byline.push_str(&format!(" by {author}"));
+        }
+        panel = panel.push(text(byline).size(15));
+        if let Some(description) = preview.description.as_deref() {
+            panel = panel.push(text(description.to_string()).size(13));
+        }
+        panel = panel.push(text("Will create").size(14));
+        for entry in &preview.creates {
+            panel = panel.push(
+                row![
+                    container(text(entry.kind.replace('_', " ")).size(11))
+                        .padding([4, 8])
+                        .style(glass_accent_panel),
+                    text(entry.name.clone()).size(13),
+                    text(entry.detail.clone()).size(12),
+                ]
+                .spacing(8)
+                .align_y(iced::Alignment::Center),
+            );
+        }
+        if !preview.capabilities.is_empty() {
+            panel = panel.push(text("Capabilities the rules can use").size(14));
+            for rule in &preview.capabilities {
+                panel = panel.push(
+                    text(format!(
+                        "{} → {}: {}",
+                        rule.rule,
+                        rule.tool,
+                        rule.capabilities.join(", ")
+                    ))
+                    .size(12),
+                );
+            }
+        }
+        for warning in &preview.warnings {
+            panel = panel.push(
+                container(text(warning.clone()).size(12))
+                    .padding([4, 8])
+                    .style(glass_warning_panel),
+            );
+        }
+        panel = panel.push(
+            row![
+                text(format!(
+                    "Digest {}",
+                    &preview.digest[..preview.digest.len().min(12)]
+                ))
+                .size(11),
+                Space::new().width(Length::Fill),
+                button("Cancel")
+                    .padding([8, 12])
+                    .style(rounded_secondary_button)
+                    .on_press(Message::TemplateReviewDismissed),
+                button(if state.template_in_flight {
+                    "Importing..."
+                } else {
+                    "Import"
+                })
+                .padding([8, 12])
+                .style(rounded_success_button)
+                .on_press_maybe(
+                    (!state.template_in_flight).then_some(Message::TemplateImportPressed)
+                ),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        );
+    }
+
+    panel = panel.push(if state.template_error.is_empty() {
+        text(state.template_status.clone()).size(13)
+    } else {
+        text(state.template_error.clone())
+            .size(13)
+            .color([0.95, 0.45, 0.45])
+    });
+
+    container(panel).padding(10).style(glass_panel).into()
+}
+
+fn view_diagnostics_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let schema_drift_found = state
+        .doctor_checks
+        .iter()
+        .any(|check| check.name == "schema_drift" && check.status == "warn");
+    let doctor_lines = state
+        .doctor_checks
+        .iter()
+        .fold(column!().spacing(6), |col, check| {
+            let mut line = format!("{} [{}] — {}", check.name, check.status, check.message);
+            if let Some(hint) = &check.fix_hint {
+                line.push_str(&format!(" ({hint})"));
+            }
+            col.push(text(shown(state, &line)))
+        });
+
+    let security_lines =
+        state
+            .security_findings
+            .iter()
+            .fold(column!().spacing(6), |col, finding| {
+                let mut line = format!(
+                    "{} [{} / {}] — {}{}",
+                    finding.id,
+                    finding.severity,
+                    finding.status,
+                    finding.message,
+                    if finding.auto_fixable {
+                        " (auto-fixable)"
+                    } else {
+                        ""
+                    }
+                );
+                if let Some(hint) = &finding.fix_hint {
+                    line.push_str(&format!(" ({hint})"));
+                }
+                col.push(text(shown(state, &line)))
+            });
+
+    let content = column![
+        row![
+            button("Run security doctor")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::RunDoctorPressed),
+            button("Refresh reminder delivery")
+                .padding([8, 12])
+                .style(rounded_secondary_button)
+                .on_press(Message::RefreshReminderDeliveryEvents),
+            button("Repair schema")
+                .padding([8, 12])
+                .style(rounded_secondary_button)
+                .on_press_maybe(schema_drift_found.then_some(Message::RepairSchemaPressed)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text(state.doctor_status.clone()),
+        if state.doctor_error.is_empty() {
+            text("")
+        } else {
+            text(state.doctor_error.clone()).color([0.95, 0.45, 0.45])
+        },
+        text(format!(
+            "Doctor overall: {}",
+            display_posture_level(&state.doctor_overall)
+        )),
+        container(doctor_lines).padding(8).style(glass_panel),
+        text(""),
+        text(state.security_status.clone()),
+        if state.security_error.is_empty() {
+            text("")
+        } else {
+            text(state.security_error.clone()).color([0.95, 0.45, 0.45])
+        },
+        text(format!(
+            "Security overall: {}",
+            display_posture_level(&state.security_overall)
+        )),
+        container(security_lines).padding(8).style(glass_panel),
+        text(""),
+        text(state.reminder_delivery_status.clone()),
+        if state.reminder_delivery_error.is_empty() {
+            text("")
+        } else {
+            text(state.reminder_delivery_error.clone()).color([0.95, 0.45, 0.45])
+        },
+        container(
+            state
+                .reminder_delivery_events
+                .iter()
+                .fold(column!().spacing(6), |col, line| col
+                    .push(text(shown(state, line))))
+        )
+        .padding(8)
+        .style(glass_panel),
+        text(""),
+        row![
+            text("Backups").size(16),
+            Space::new().width(Length::Fill),
+            button("Refresh")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press_maybe((!state.backup_in_flight).then_some(Message::RefreshBackupStatus)),
+            button("Back up now")
+                .padding([6, 10])
+                .style(rounded_primary_button)
+                .on_press_maybe((!state.backup_in_flight).then_some(Message::BackupNowPressed)),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text(state.backup_status.clone()),
+        if state.backup_error.is_empty() {
+            text("")
+        } else {
+            text(state.backup_error.clone()).color([0.95, 0.45, 0.45])
+        },
+        container(
+            state
+                .backup_snapshots
+                .iter()
+                .fold(column!().spacing(6), |col, line| col
+                    .push(text(line.clone())))
+        )
+        .padding(8)
+        .style(glass_panel),
+        text(""),
+        row![
+            text("Estimate accuracy").size(16),
+            Space::new().width(Length::Fill),
+            button("Refresh")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::RefreshEstimateAccuracy),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text(state.estimate_accuracy_status.clone()),
+        container(
+            state
+                .estimate_accuracy_lines
+                .iter()
+                .fold(column!().spacing(6), |col, line| col
+                    .push(text(line.clone())))
+        )
+        .padding(8)
+        .style(glass_panel),
+        text(""),
+        row![
+            text("Scheduler jobs").size(16),
+            Space::new().width(Length::Fill),
+            button("Refresh")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::RefreshSchedulerJobs),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text(state.scheduler_jobs_status.clone()),
+        container(
+            state
+                .scheduler_jobs_lines
+                .iter()
+                .fold(column!().spacing(6), |col, line| col
+                    .push(text(shown(state, line))))
+        )
+        .padding(8)
+        .style(glass_panel),
+        text(""),
+        row![
+            text("Failed reminder deliveries").size(16),
+            Space::new().width(Length::Fill),
+            button("Refresh")
+                .padding([6, 10])
+                .style(rounded_secondary_button)
+                .on_press(Message::RefreshDeadLetters),
+        ]
+        .spacing(8)
+        .align_y(iced::Alignment::Center),
+        text(state.dead_letters_status.clone()),
+        container(
+            state
+                .dead_letters
+                .iter()
+                .fold(column!().spacing(6), |col, dead_letter| col.push(
+                    row![
+                        column![
+                            text(dead_letter.title.clone()),
+                            text(format!(
+                                "{} • {} attempts • failed {} • {}",
+                                dead_letter.channel,
+                                dead_letter.attempts,
+                                format_local_time(dead_letter.failed_at),
+                                dead_letter.last_error
+                            ))
+                            .size(12),
+                        ]
+                        .spacing(2)
+                        .width(Length::Fill),
+                        button("Retry")
+                            .padding([6, 10])
+                            .style(rounded_secondary_button)
+                            .on_press(Message::DeadLetterRedrive(dead_letter.id)),
+                        button("Dismiss")
+                            .padding([6, 10])
+                            .style(rounded_secondary_button)
+                            .on_press(Message::DeadLetterDiscard(dead_letter.id)),
+                    ]
+                    .spacing(8)
+                    .align_y(iced::Alignment::Center)
+                ))
+        )
+        .padding(8)
+        .style(glass_panel),
+    ]
+    .spacing(10);
+
+    container(scrollable(content).height(Length::Fill).width(Length::Fill))
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn view_context_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let content = column![
+        text("Context prompt").size(22),
+        text("Paste markdown text or a URL.").size(14),
+        text_editor(&state.context_editor)
+            .height(160)
+            .padding(10)
+            .on_action(Message::ContextEdited),
+        row![
+            button("Save Context")
+                .padding([8, 12])
+                .style(rounded_success_button)
+                .on_press(Message::SaveSettingsPressed),
+            button("Reload")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::LoadSettingsPressed),
+        ]
+        .spacing(10),
+        view_memory_search(state),
+        text("Preview").size(14),
+        container(
+            scrollable(
+                markdown::view(
+                    state.context_preview_items.iter(),
+                    markdown_render_settings()
+                )
+                .map(Message::MarkdownLinkClicked)
+            )
+            .height(Length::Fill)
+            .width(Length::Fill)
+        )
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill),
+        if state.settings_error.is_empty() {
+            text(state.settings_status.clone()).color([0.55, 0.9, 0.65])
+        } else {
+            text(state.settings_error.clone()).color([0.95, 0.45, 0.45])
+        }
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .height(Length::Fill);
+
+    container(content)
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn view_memory_search(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let mut search = button(if state.memory_search_in_flight {
+        "Searching..."
+    } else {
+        "Search"
+    })
+    .padding([8, 12])
+    .style(rounded_primary_button);
+    if !state.memory_search_in_flight && !state.memory_query.trim().is_empty() {
+        search = search.on_press(Message::MemorySearchPressed);
+    }
+
+    let hits = state
+        .memory_hits
+        .iter()
+        .fold(column![].spacing(6), |col, hit| {
+            col.push(
+                row![
+                    text(format!("{:.2}", hit.score))
+                        .size(13)
+                        .color([0.55, 0.9, 0.65])
+                        .width(Length::Fixed(44.0)),
+                    text(format_local_time(hit.timestamp))
+                        .size(13)
+                        .width(Length::Fixed(150.0)),
+                    text(shown(state, &hit.content))
+                        .size(13)
+                        .width(Length::Fill),
+                ]
+                .spacing(8),
+            )
+        });
+
+    column![
+        text("Memory search").size(14),
+        row![
+            text_input("What do you remember about...", &state.memory_query)
+                .on_input(Message::MemoryQueryChanged)
+                .on_submit(Message::MemorySearchPressed)
+                .padding(8)
+                .width(Length::Fill),
+            button(if state.memory_rerank {
+                "Rerank: on"
+            } else {
+                "Rerank: off"
+            })
+            .padding([8, 12])
+            .style(rounded_primary_button)
+            .on_press(Message::MemoryRerankToggled),
+            search,
+        ]
+        .spacing(10),
+        text(state.memory_search_status.clone()).size(13),
+        scrollable(hits).height(Length::Fixed(160.0)),
+    ]
+    .spacing(8)
+    .into()
+}
+
+fn view_heartbeat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
+    let content = column![
+        text("Heartbeat").size(22),
+        text("Paste markdown text or a URL.").size(14),
+        text_editor(&state.heartbeat_editor)
+            .height(160)
+            .padding(10)
+            .on_action(Message::HeartbeatEdited),
+        row![
+            button("Save Heartbeat")
+                .padding([8, 12])
+                .style(rounded_success_button)
+                .on_press(Message::SaveSettingsPressed),
+            button("Reload")
+                .padding([8, 12])
+                .style(rounded_primary_button)
+                .on_press(Message::LoadSettingsPressed),
+        ]
+        .spacing(10),
+        text("Preview").size(14),
+        container(
+            scrollable(
+                markdown::view(
+                    state.heartbeat_preview_items.iter(),
+                    markdown_render_settings()
+                )
+                .map(Message::MarkdownLinkClicked)
+            )
+            .height(Length::Fill)
+            .width(Length::Fill)
+        )
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill),
+        if state.settings_error.is_empty() {
+            text(state.settings_status.clone()).color([0.55, 0.9, 0.65])
+        } else {
+            text(state.settings_error.clone()).color([0.95, 0.45, 0.45])
+        }
+    ]
+    .spacing(10)
+    .width(Length::Fill)
+    .height(Length::Fill);
+
+    container(content)
+        .padding(10)
+        .style(glass_panel)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+}
+
+fn count_actionable_human_items(items: &[InboxItem]) -> usize {
+    items
+        .iter()
+        .filter(|item| item.requires_human_action && item.status.is_actionable())
+        .count()
+}
+
+fn sync_actionable_badge(state: &mut ButterflyIcedApp) {
+    let actionable = count_actionable_human_items(&state.inbox_items);
+    if state.last_badge_actionable_count == Some(actionable) {
+        return;
+    }
+
+    if let Err(err) = set_macos_dock_badge(actionable) {
+        state.push_activity(format!("dock badge update failed: {err}"));
+    }
+    state.last_badge_actionable_count = Some(actionable);
+}
+
+fn set_macos_dock_badge(count: usize) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    {
+        let label = if count == 0 {
+            "".to_string()
+        } else {
+            count.to_string()
+        };
+        let script = format!(
+            "ObjC.import('AppKit'); $.NSApplication.sharedApplication.dockTile.badgeLabel = '{}';",
+            label.replace('\'', "\\'")
+        );
+        let _status = Command::new("osascript")
+            .args(["-l", "JavaScript", "-e", &script])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        return Ok(());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = count;
+        Ok(())
+    }
+}
+
+fn parse_inbox_status(value: Option<&str>, default: InboxStatus) -> InboxStatus {
+    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
+        "new" => InboxStatus::New,
+        "ack" | "acknowledged" => InboxStatus::Acknowledged,
+        "in_progress" | "in progress" | "started" => InboxStatus::InProgress,
+        "blocked" => InboxStatus::Blocked,
+        "done" | "completed" | "complete" => InboxStatus::Done,
+        "dismissed" | "archived" => InboxStatus::Dismissed,
+        _ => default,
+    }
+}
+
+fn parse_inbox_priority(value: Option<&str>, default: InboxPriority) -> InboxPriority {
+    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
+        "low" => InboxPriority::Low,
+        "high" => InboxPriority::High,
+        "urgent" | "critical" => InboxPriority::Urgent,
+        "normal" | "medium" => InboxPriority::Normal,
+        _ => default,
+    }
+}
+
+fn priority_rank(priority: InboxPriority) -> i32 {
+    match priority {
+        InboxPriority::Urgent => 0,
+        InboxPriority::High => 1,
+        InboxPriority::Normal => 2,
+        InboxPriority::Low => 3,
+    }
+}
+
+async fn load_inbox_items(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<Vec<InboxItem>, String> {
+    let extract_plan_step_ref = |details: Option<&String>| -> Option<String> {
+        static PLAN_STEP_REF_RE: OnceLock<Regex> = OnceLock::new();
+        let text = details?.as_str();
+        let re = PLAN_STEP_REF_RE
+            .get_or_init(|| Regex::new(r"(?i)planstepref\s*:\s*(plan_step:\d+:\d+)").unwrap());
+        re.captures(text)
+            .and_then(|caps| caps.get(1))
+            .map(|m| m.as_str().to_ascii_lowercase())
+    };
+
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/inbox?user_id={}&limit=250&include_done=true",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Inbox request failed: HTTP {status}: {body}"));
+    }
+
+    let parsed = response
+        .json::<InboxApiResponse>()
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut items = parsed
+        .items
+        .into_iter()
+        .map(|item| {
+            let source_type = match item.source_type.as_str() {
+                "reminder" => InboxSourceType::Reminder,
+                "todo" => InboxSourceType::Todo,
+                "task" => InboxSourceType::Task,
+                "approval" => InboxSourceType::Approval,
+                "consent" => InboxSourceType::Consent,
+                "question" => InboxSourceType::Question,
+                "github" | "zapier" => InboxSourceType::External,
+                "calendar" => InboxSourceType::Calendar,
+                _ => InboxSourceType::PlanStep,
+            };
+            let default_status = parse_inbox_status(Some(&item.status), InboxStatus::New);
+            let status = default_status;
+            let priority = parse_inbox_priority(Some(&item.priority), InboxPriority::Normal);
+
+            InboxItem {
+                id: item.id,
+                source_type,
+                owner: item.owner,
+                title: item.title,
+                details: item.details,
+                status,
+                priority,
+                due_at: item.due_at,
+                created_at: item.created_at,
+                updated_at: item.updated_at,
+                requires_human_action: item.requires_human_action,
+                origin_ref: item.origin_ref,
+                dependency_refs: item.dependency_refs,
+                t_shirt_size: item.t_shirt_size,
+                story_points: item.story_points,
+                estimate_optimistic_minutes: item.estimate_optimistic_minutes,
+                estimate_likely_minutes: item.estimate_likely_minutes,
+                estimate_pessimistic_minutes: item.estimate_pessimistic_minutes,
+                linked_reminders: item.linked_reminders,
+                held_until: item.held_until,
+                labels: item.labels,
+                project_id: item.project_id,
+                comments: item.comments,
+                attachments: item.attachments,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let plan_step_refs_in_view = items
+        .iter()
+        .filter(|item| item.source_type == InboxSourceType::PlanStep)
+        .map(|item| item.origin_ref.to_ascii_lowercase())
+        .collect::<HashSet<_>>();
+
+    items.retain(|item| {
+        !(item.source_type == InboxSourceType::Todo
+            && extract_plan_step_ref(item.details.as_ref())
+                .map(|origin_ref| plan_step_refs_in_view.contains(&origin_ref))
+                .unwrap_or(false))
+    });
+
+    let mut seen_origin_refs = HashSet::new();
+    items.retain(|item| seen_origin_refs.insert(item.origin_ref.clone()));
+
+    items.sort_by(|a, b| {
+        priority_rank(a.priority)
+            .cmp(&priority_rank(b.priority))
+            .then_with(|| {
+                a.due_at
+                    .unwrap_or(i64::MAX)
+                    .cmp(&b.due_at.unwrap_or(i64::MAX))
+            })
+            .then_with(|| b.created_at.cmp(&a.created_at))
+    });
+
+    Ok(items)
+}
+
+async fn apply_inbox_action(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    item: InboxItem,
+    action: InboxActionKind,
+) -> Result<String, String> {
+    let action_name = match action {
+        InboxActionKind::Acknowledge => "acknowledge",
+        InboxActionKind::Start => "start",
+        InboxActionKind::Block => "block",
+        InboxActionKind::Done => "done",
+        InboxActionKind::Reopen => "reopen",
+        InboxActionKind::Snooze => "snooze",
+    };
+
+    let client = daemon_request_client();
+    let url = format!("{}/inbox/transition", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "origin_ref": item.origin_ref,
+        "action": action_name,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Inbox action failed: HTTP {status}: {body}"));
+    }
+
+    Ok(format!("Inbox action applied: {}", action_name))
+}
+
+async fn add_inbox_note(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    origin_ref: String,
+    body: String,
+) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/inbox/comments", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "origin_ref": origin_ref,
+        "body": body,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Adding note failed: HTTP {status}: {body}"));
+    }
+
+    Ok(format!("Note added to {origin_ref}"))
+}
+
+async fn upload_inbox_attachment(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    origin_ref: String,
+    path: String,
+) -> Result<String, String> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => std::env::var("HOME")
+            .map(|home| format!("{home}/{rest}"))
+            .unwrap_or(path),
+        None => path,
+    };
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|err| format!("Unable to read {path}: {err}"))?;
+    let path = std::path::Path::new(&path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let client = daemon_request_client();
+    let url = format!("{}/attachments", daemon_url.trim_end_matches('/'));
+    let mut request = client
+        .post(url)
+        .query(&[
+            ("user_id", user_id.as_str()),
+            ("origin_ref", origin_ref.as_str()),
+            ("name", name.as_str()),
+        ])
+        .header("content-type", attachment_content_type(path))
+        .body(bytes);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Attaching file failed: HTTP {status}: {body}"));
+    }
+
+    Ok(format!("Attached {name} to {origin_ref}"))
+}
+
+/// A content type for the file types people usually attach; anything else
+/// is stored as plain bytes.
+fn attachment_content_type(path: &std::path::Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "txt" | "md" | "log" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+async fn apply_inbox_bulk_action(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    origin_refs: Vec<String>,
+    action: InboxActionKind,
+) -> Result<String, String> {
+    let (action_name, label) = match action {
+        InboxActionKind::Acknowledge => ("acknowledge", "Marked seen"),
+        InboxActionKind::Done => ("done", "Marked done"),
+        InboxActionKind::Snooze => ("snooze", "Snoozed"),
+        other => return Err(format!("{other:?} is not a bulk action")),
+    };
+
+    let client = daemon_request_client();
+    let url = format!("{}/inbox/bulk", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "origin_refs": origin_refs,
+        "action": action_name,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Bulk action failed: HTTP {status}: {body}"));
+    }
+    let outcome: serde_json::Value = serde_json::from_str(&body).map_err(|err| err.to_string())?;
+    let count = |key: &str| outcome[key].as_array().map(Vec::len).unwrap_or(0);
+    let mut summary = format!("{label}: {} item(s)", count("applied"));
+    if count("skipped") > 0 {
+        summary.push_str(&format!(", {} skipped", count("skipped")));
+        if let Some(reason) = outcome["skipped"][0]["reason"].as_str() {
+            summary.push_str(&format!(" ({reason})"));
+        }
+    }
+    Ok(summary)
+}
+
+async fn verify_lock_passphrase(passphrase: String) -> Result<bool, String> {
+    tokio::task::spawn_blocking(move || crate::privacy_lock::verify_passphrase(&passphrase))
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
+}
+
+async fn report_privacy_lock(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    locked: bool,
+) -> Result<(), String> {
+    let client = daemon_request_client();
+    let url = format!("{}/privacy_lock", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "locked": locked,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("HTTP {status}"));
+    }
+    Ok(())
+}
+
+async fn decide_approval(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    id: i32,
+    approve: bool,
+) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/approvals/decide", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "id": id,
+        "decision": if approve { "approve" } else { "reject" },
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Approval failed: HTTP {status}: {body}"));
+    }
+    let outcome = serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|value| {
+            value
+                .get("status")
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "decided".to_string());
+
+    Ok(format!("Approval {id}: {outcome}"))
+}
+
+async fn decide_consent(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    id: i32,
+    allow: bool,
+) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/consent/decide", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "id": id,
+        "decision": if allow { "allow" } else { "deny" },
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Consent failed: HTTP {status}: {body}"));
+    }
+    Ok(serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|value| value.get("summary")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("Consent {id}: decided")))
 }
 
-fn view_diagnostics_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
-    let doctor_lines = state
-        .doctor_checks
-        .iter()
-        .fold(column!().spacing(6), |col, check| {
-            let mut line = format!("{} [{}] — {}", check.name, check.status, check.message);
-            if let Some(hint) = &check.fix_hint {
-                line.push_str(&format!(" ({hint})"));
+async fn load_trash_batches(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<Vec<TrashBatchRow>, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/trash?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Trash request failed: HTTP {status}: {body}"));
+    }
+
+    response
+        .json::<TrashApiResponse>()
+        .await
+        .map(|parsed| parsed.batches)
+        .map_err(|err| err.to_string())
+}
+
+async fn load_projects(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<Vec<ProjectRow>, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/projects?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Projects request failed: HTTP {status}: {body}"));
+    }
+
+    response
+        .json::<ProjectsApiResponse>()
+        .await
+        .map(|parsed| parsed.projects)
+        .map_err(|err| err.to_string())
+}
+
+async fn restore_trash_batch(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    batch: TrashBatchRow,
+) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/trash/restore", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "store": batch.store,
+        "batch_id": batch.batch_id,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Restore failed: HTTP {status}: {body}"));
+    }
+
+    Ok(format!("Restored {} {} items", batch.count, batch.store))
+}
+
+async fn search_work_items(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    query: String,
+) -> Result<SearchApiResponse, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/search", daemon_url.trim_end_matches('/'));
+    let mut request = client
+        .get(url)
+        .query(&[("user_id", user_id.as_str()), ("q", query.as_str())]);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Search request failed: HTTP {status}: {body}"));
+    }
+
+    response
+        .json::<SearchApiResponse>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn review_template(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    path: String,
+) -> Result<TemplateReview, String> {
+    let contents =
+        std::fs::read_to_string(&path).map_err(|err| format!("Unable to read {path}: {err}"))?;
+    let bundle: Value = serde_json::from_str(&contents)
+        .map_err(|err| format!("{path} is not valid JSON: {err}"))?;
+
+    let client = daemon_request_client();
+    let url = format!("{}/templates/preview", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "bundle": bundle,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Template preview failed: HTTP {status}: {body}"));
+    }
+    let preview = response
+        .json::<TemplatePreview>()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(TemplateReview { bundle, preview })
+}
+
+async fn import_template(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    review: TemplateReview,
+) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/templates/import", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({
+        "user_id": user_id,
+        "bundle": review.bundle,
+        "digest": review.preview.digest,
+    }));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|err| err.to_string())?;
+    if !status.is_success() {
+        return Err(format!("Template import failed: HTTP {status}: {body}"));
+    }
+
+    Ok(format!(
+        "Imported {} ({} items)",
+        review.preview.name,
+        review.preview.creates.len()
+    ))
+}
+
+/// Posts the prompt to `/process_text/stream` and yields each `token` event
+/// as it arrives, ending with `Done` once the daemon reports completion.
+fn send_prompt(
+    daemon_url: String,
+    user_id: String,
+    token: String,
+    prompt: String,
+    context: Option<String>,
+) -> impl futures::Stream<Item = Result<PromptStreamEvent, String>> {
+    async_stream::stream! {
+        let client = match reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(2))
+            .read_timeout(Duration::from_secs(60))
+            .build()
+        {
+            Ok(client) => client,
+            Err(err) => {
+                yield Err(err.to_string());
+                return;
             }
-            col.push(text(line))
+        };
+        let url = format!("{}/process_text/stream", daemon_url.trim_end_matches('/'));
+        let mut request = client.post(url).json(&ProcessTextRequest {
+            user_id,
+            text: prompt,
+            prompt: context,
+            thread_id: Some(DEFAULT_CHAT_THREAD.to_string()),
         });
+        if !token.trim().is_empty() {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
 
-    let security_lines =
-        state
-            .security_findings
-            .iter()
-            .fold(column!().spacing(6), |col, finding| {
-                let mut line = format!(
-                    "{} [{} / {}] — {}{}",
-                    finding.id,
-                    finding.severity,
-                    finding.status,
-                    finding.message,
-                    if finding.auto_fixable {
-                        " (auto-fixable)"
-                    } else {
-                        ""
-                    }
-                );
-                if let Some(hint) = &finding.fix_hint {
-                    line.push_str(&format!(" ({hint})"));
-                }
-                col.push(text(line))
-            });
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(err) => {
+                yield Err(err.to_string());
+                return;
+            }
+        };
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            yield Err(format!("HTTP {status}: {body}"));
+            return;
+        }
 
-    let content = column![
-        row![
-            button("Run security doctor")
-                .padding([8, 12])
-                .style(rounded_primary_button)
-                .on_press(Message::RunDoctorPressed),
-            button("Refresh reminder delivery")
-                .padding([8, 12])
-                .style(rounded_secondary_button)
-                .on_press(Message::RefreshReminderDeliveryEvents),
-        ]
-        .spacing(8)
-        .align_y(iced::Alignment::Center),
-        text(state.doctor_status.clone()),
-        if state.doctor_error.is_empty() {
-            text("")
-        } else {
-            text(state.doctor_error.clone()).color([0.95, 0.45, 0.45])
-        },
-        text(format!(
-            "Doctor overall: {}",
-            display_posture_level(&state.doctor_overall)
-        )),
-        container(doctor_lines).padding(8).style(glass_panel),
-        text(""),
-        text(state.security_status.clone()),
-        if state.security_error.is_empty() {
-            text("")
-        } else {
-            text(state.security_error.clone()).color([0.95, 0.45, 0.45])
-        },
-        text(format!(
-            "Security overall: {}",
-            display_posture_level(&state.security_overall)
-        )),
-        container(security_lines).padding(8).style(glass_panel),
-        text(""),
-        text(state.reminder_delivery_status.clone()),
-        if state.reminder_delivery_error.is_empty() {
-            text("")
-        } else {
-            text(state.reminder_delivery_error.clone()).color([0.95, 0.45, 0.45])
-        },
-        container(
-            state
-                .reminder_delivery_events
-                .iter()
-                .fold(column!().spacing(6), |col, line| col
-                    .push(text(line.clone())))
-        )
-        .padding(8)
-        .style(glass_panel),
-    ]
-    .spacing(10);
+        let mut body = response.bytes_stream();
+        let mut buffer = String::new();
+        while let Some(chunk) = futures::StreamExt::next(&mut body).await {
+            let bytes = match chunk {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    yield Err(format!("Stream error: {err}"));
+                    return;
+                }
+            };
+            buffer.push_str(&String::from_utf8_lossy(&bytes));
+            while let Some(end) = buffer.find("\n\n") {
+                let frame = buffer[..end].to_string();
+                buffer.drain(..end + 2);
+                let Some((event, data)) = parse_sse_frame(&frame) else {
+                    continue;
+                };
+                match event.as_str() {
+                    "queued" => {
+                        if let Some(position) = data.get("position").and_then(|v| v.as_u64()) {
+                            yield Ok(PromptStreamEvent::Queued(position as usize));
+                        }
+                    }
+                    "token" => {
+                        if let Some(text) = data.get("text").and_then(|v| v.as_str()) {
+                            yield Ok(PromptStreamEvent::Token(text.to_string()));
+                        }
+                    }
+                    "done" => {
+                        yield Ok(PromptStreamEvent::Done);
+                        return;
+                    }
+                    "error" => {
+                        let error = data
+                            .get("error")
+                            .and_then(|v| v.as_str())
+                            .unwrap_or("Unknown stream error");
+                        yield Err(error.to_string());
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        yield Err("Response stream ended before the reply finished.".to_string());
+    }
+}
 
-    container(scrollable(content).height(Length::Fill).width(Length::Fill))
-        .padding(10)
-        .style(glass_panel)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+/// Event types that can add, remove or move inbox items.
+fn daemon_event_touches_inbox(event_type: &str) -> bool {
+    matches!(
+        event_type,
+        "inbox_transition"
+            | "inbox_sweep"
+            | "reminder"
+            | "reminder_delivery"
+            | "todo"
+            | "tasks"
+            | "tool"
+            | "approval"
+            | "consent"
+            | "question"
+            | "external_item"
+            | "calendar"
+            | "email"
+            | "trash"
+            | "templates"
+    )
 }
 
-fn view_context_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
-    let content = column![
-        text("Context prompt").size(22),
-        text("Paste markdown text or a URL.").size(14),
-        text_editor(&state.context_editor)
-            .height(160)
-            .padding(10)
-            .on_action(Message::ContextEdited),
-        row![
-            button("Save Context")
-                .padding([8, 12])
-                .style(rounded_success_button)
-                .on_press(Message::SaveSettingsPressed),
-            button("Reload")
-                .padding([8, 12])
-                .style(rounded_primary_button)
-                .on_press(Message::LoadSettingsPressed),
-        ]
-        .spacing(10),
-        text("Preview").size(14),
-        container(
-            scrollable(
-                markdown::view(
-                    state.context_preview_items.iter(),
-                    markdown_render_settings()
-                )
-                .map(Message::MarkdownLinkClicked)
-            )
-            .height(Length::Fill)
-            .width(Length::Fill)
-        )
-        .padding(10)
-        .style(glass_panel)
-        .width(Length::Fill)
-        .height(Length::Fill),
-        if state.settings_error.is_empty() {
-            text(state.settings_status.clone()).color([0.55, 0.9, 0.65])
-        } else {
-            text(state.settings_error.clone()).color([0.95, 0.45, 0.45])
+/// The daemon's UI events as they happen, reconnecting with backoff.
+fn daemon_event_stream(source: &DaemonEventSource) -> impl futures::Stream<Item = Message> {
+    let source = source.clone();
+    async_stream::stream! {
+        let mut backoff = EVENT_STREAM_MIN_BACKOFF;
+        loop {
+            if let Ok(response) = open_daemon_event_stream(&source).await {
+                backoff = EVENT_STREAM_MIN_BACKOFF;
+                yield Message::DaemonEventStreamChanged(true);
+                let mut body = response.bytes_stream();
+                let mut buffer = String::new();
+                while let Some(Ok(bytes)) = futures::StreamExt::next(&mut body).await {
+                    buffer.push_str(&String::from_utf8_lossy(&bytes));
+                    while let Some(end) = buffer.find("\n\n") {
+                        let frame = buffer[..end].to_string();
+                        buffer.drain(..end + 2);
+                        // Keepalive comments carry no data and parse to nothing.
+                        let Some((_, data)) = parse_sse_frame(&frame) else {
+                            continue;
+                        };
+                        if let Ok(event) = serde_json::from_value::<DaemonEventFrame>(data) {
+                            yield Message::DaemonEvent(event);
+                        }
+                    }
+                }
+                yield Message::DaemonEventStreamChanged(false);
+            }
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(EVENT_STREAM_MAX_BACKOFF);
         }
-    ]
-    .spacing(10)
-    .width(Length::Fill)
-    .height(Length::Fill);
+    }
+}
 
-    container(content)
-        .padding(10)
-        .style(glass_panel)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+async fn open_daemon_event_stream(source: &DaemonEventSource) -> Result<reqwest::Response, String> {
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(2))
+        .read_timeout(EVENT_STREAM_READ_TIMEOUT)
+        .build()
+        .map_err(|err| err.to_string())?;
+    let url = format!(
+        "{}/ui_events?user_id={}",
+        source.daemon_url.trim_end_matches('/'),
+        source.user_id
+    );
+    let mut request = client.get(url);
+    if !source.token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {}", source.token));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("UI events request failed: HTTP {status}"));
+    }
+    Ok(response)
 }
 
-fn view_heartbeat_tab(state: &ButterflyIcedApp) -> Element<'_, Message> {
-    let content = column![
-        text("Heartbeat").size(22),
-        text("Paste markdown text or a URL.").size(14),
-        text_editor(&state.heartbeat_editor)
-            .height(160)
-            .padding(10)
-            .on_action(Message::HeartbeatEdited),
-        row![
-            button("Save Heartbeat")
-                .padding([8, 12])
-                .style(rounded_success_button)
-                .on_press(Message::SaveSettingsPressed),
-            button("Reload")
-                .padding([8, 12])
-                .style(rounded_primary_button)
-                .on_press(Message::LoadSettingsPressed),
-        ]
-        .spacing(10),
-        text("Preview").size(14),
-        container(
-            scrollable(
-                markdown::view(
-                    state.heartbeat_preview_items.iter(),
-                    markdown_render_settings()
-                )
-                .map(Message::MarkdownLinkClicked)
-            )
-            .height(Length::Fill)
-            .width(Length::Fill)
-        )
-        .padding(10)
-        .style(glass_panel)
-        .width(Length::Fill)
-        .height(Length::Fill),
-        if state.settings_error.is_empty() {
-            text(state.settings_status.clone()).color([0.55, 0.9, 0.65])
-        } else {
-            text(state.settings_error.clone()).color([0.95, 0.45, 0.45])
+fn parse_sse_frame(frame: &str) -> Option<(String, Value)> {
+    let mut event = "message".to_string();
+    let mut data = String::new();
+    for line in frame.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            event = value.trim().to_string();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push_str(value.trim_start());
         }
-    ]
-    .spacing(10)
-    .width(Length::Fill)
-    .height(Length::Fill);
-
-    container(content)
-        .padding(10)
-        .style(glass_panel)
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .into()
+    }
+    let data = serde_json::from_str(&data).ok()?;
+    Some((event, data))
 }
 
-fn count_actionable_human_items(items: &[InboxItem]) -> usize {
-    items
-        .iter()
-        .filter(|item| item.requires_human_action && item.status.is_actionable())
-        .count()
+fn daemon_request_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(2))
+        .timeout(Duration::from_secs(60))
+        .build()
+        .expect("request client")
 }
 
-fn sync_actionable_badge(state: &mut ButterflyIcedApp) {
-    let actionable = count_actionable_human_items(&state.inbox_items);
-    if state.last_badge_actionable_count == Some(actionable) {
-        return;
+async fn check_daemon_health(daemon_url: String) -> DaemonHealth {
+    let normalized = normalize_daemon_url(&daemon_url);
+    if check_daemon_health_once(&normalized).await {
+        return DaemonHealth {
+            daemon_url: normalized,
+            healthy: true,
+            switched: false,
+        };
     }
 
-    if let Err(err) = set_macos_dock_badge(actionable) {
-        state.push_activity(format!("dock badge update failed: {err}"));
+    if running_from_macos_app_bundle() {
+        return DaemonHealth {
+            daemon_url: normalized,
+            healthy: false,
+            switched: false,
+        };
     }
-    state.last_badge_actionable_count = Some(actionable);
-}
 
-fn set_macos_dock_badge(count: usize) -> std::io::Result<()> {
-    #[cfg(target_os = "macos")]
-    {
-        let label = if count == 0 {
-            "".to_string()
+    let (host, port) = parse_daemon_address(&normalized);
+    if port == 7878 && (host == "127.0.0.1" || host == "localhost") {
+        let scheme = if normalized.starts_with("https://") {
+            "https"
         } else {
-            count.to_string()
+            "http"
         };
-        let script = format!(
-            "ObjC.import('AppKit'); $.NSApplication.sharedApplication.dockTile.badgeLabel = '{}';",
-            label.replace('\'', "\\'")
-        );
-        let _status = Command::new("osascript")
-            .args(["-l", "JavaScript", "-e", &script])
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()?;
-        return Ok(());
+        let fallback = format!("{scheme}://{host}:7979");
+        if check_daemon_health_once(&fallback).await {
+            return DaemonHealth {
+                daemon_url: fallback,
+                healthy: true,
+                switched: true,
+            };
+        }
     }
 
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = count;
-        Ok(())
+    DaemonHealth {
+        daemon_url: normalized,
+        healthy: false,
+        switched: false,
+    }
+}
+
+async fn check_daemon_health_once(daemon_url: &str) -> bool {
+    let client = daemon_request_client();
+    let url = format!("{}/health", daemon_url.trim_end_matches('/'));
+    match client.get(url).send().await {
+        Ok(resp) => resp.status().is_success(),
+        Err(_) => false,
+    }
+}
+
+/// Sends a finished export to the remote target, if one takes exports.
+async fn upload_export_copy(
+    db_path: &str,
+    path: &std::path::Path,
+    bytes: &[u8],
+) -> Result<Option<String>, String> {
+    let tools = crate::config::Config::from_store(db_path)
+        .ok()
+        .and_then(|config| config.tools);
+    let Some(uploader) = crate::remote_storage::RemoteUploader::from_tools(tools.as_ref())
+        .map_err(|err| err.to_string())?
+    else {
+        return Ok(None);
+    };
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "export".to_string());
+    uploader
+        .upload(crate::remote_storage::Category::Exports, &name, bytes)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn test_remote_storage_request(
+    daemon_url: String,
+    token: String,
+) -> Result<RemoteStorageReport, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/storage/test", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
+    }
+    response
+        .json::<RemoteStorageReport>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Stops `recording` and has the daemon's transcription engine turn it
+/// into text for the composer.
+async fn transcribe_recording(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    recording: crate::voice::capture::Recording,
+) -> Result<String, String> {
+    let audio = tokio::task::spawn_blocking(move || recording.stop())
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())?;
+    let client = daemon_request_client();
+    let url = format!("{}/transcribe", daemon_url.trim_end_matches('/'));
+    let mut request = client
+        .post(url)
+        .query(&[("user_id", user_id.as_str()), ("format", "wav")])
+        .header("content-type", "audio/wav")
+        .body(audio);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
+    response
+        .json::<Value>()
+        .await
+        .map_err(|err| err.to_string())?
+        .get("text")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "Transcription response has no text".to_string())
 }
 
-fn parse_inbox_status(value: Option<&str>, default: InboxStatus) -> InboxStatus {
-    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
-        "new" => InboxStatus::New,
-        "ack" | "acknowledged" => InboxStatus::Acknowledged,
-        "in_progress" | "in progress" | "started" => InboxStatus::InProgress,
-        "blocked" => InboxStatus::Blocked,
-        "done" | "completed" | "complete" => InboxStatus::Done,
-        "dismissed" | "archived" => InboxStatus::Dismissed,
-        _ => default,
-    }
+/// The status line and one line per local snapshot, newest first.
+fn backup_summary(status: &BackupStatusApiResponse) -> (String, Vec<String>) {
+    let size = |bytes: u64| format!("{:.1} MB", bytes as f64 / 1_048_576.0);
+    let schedule = if status.enabled {
+        format!(
+            "Every {}h, keeping {} in {}",
+            status.interval_hours, status.keep, status.directory
+        )
+    } else {
+        format!(
+            "Scheduled backups are off; snapshots go to {}",
+            status.directory
+        )
+    };
+    let last = match (&status.last_run, status.snapshots.first()) {
+        (Some(run), _) if run.error.is_some() => {
+            format!("last attempt {} failed", format_local_time(run.at))
+        }
+        (Some(run), _) => format!(
+            "last backup {} ({}{})",
+            format_local_time(run.at),
+            size(run.bytes),
+            if run.remote_key.is_some() {
+                ", uploaded"
+            } else {
+                ""
+            }
+        ),
+        (None, Some(newest)) => format!("newest snapshot {}", format_local_time(newest.created_at)),
+        (None, None) => "no backups yet".to_string(),
+    };
+    let snapshots = status
+        .snapshots
+        .iter()
+        .map(|snapshot| {
+            format!(
+                "{} • {} • {}",
+                format_local_time(snapshot.created_at),
+                snapshot.name,
+                size(snapshot.bytes)
+            )
+        })
+        .collect();
+    (format!("{schedule} — {last}"), snapshots)
 }
 
-fn parse_inbox_priority(value: Option<&str>, default: InboxPriority) -> InboxPriority {
-    match value.unwrap_or("").trim().to_ascii_lowercase().as_str() {
-        "low" => InboxPriority::Low,
-        "high" => InboxPriority::High,
-        "urgent" | "critical" => InboxPriority::Urgent,
-        "normal" | "medium" => InboxPriority::Normal,
-        _ => default,
+async fn load_backup_status(
+    daemon_url: String,
+    token: String,
+) -> Result<BackupStatusApiResponse, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/backups/status", daemon_url.trim_end_matches('/'));
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
+    response
+        .json::<BackupStatusApiResponse>()
+        .await
+        .map_err(|err| err.to_string())
 }
 
-fn priority_rank(priority: InboxPriority) -> i32 {
-    match priority {
-        InboxPriority::Urgent => 0,
-        InboxPriority::High => 1,
-        InboxPriority::Normal => 2,
-        InboxPriority::Low => 3,
+async fn run_backup_request(daemon_url: String, token: String) -> Result<String, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/backups/run", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
+    let run = response
+        .json::<BackupRunRow>()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(format!(
+        "backup written ({} bytes{})",
+        run.bytes,
+        if run.remote_key.is_some() {
+            ", uploaded"
+        } else {
+            ""
+        }
+    ))
 }
 
-async fn load_inbox_items(
+async fn load_tool_posture(
     daemon_url: String,
     token: String,
-    user_id: String,
-) -> Result<Vec<InboxItem>, String> {
-    let extract_plan_step_ref = |details: Option<&String>| -> Option<String> {
-        static PLAN_STEP_REF_RE: OnceLock<Regex> = OnceLock::new();
-        let text = details?.as_str();
-        let re = PLAN_STEP_REF_RE
-            .get_or_init(|| Regex::new(r"(?i)planstepref\s*:\s*(plan_step:\d+:\d+)").unwrap());
-        re.captures(text)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().to_ascii_lowercase())
-    };
-
+) -> Result<Vec<ToolPostureRow>, String> {
     let client = daemon_request_client();
-    let url = format!(
-        "{}/inbox?user_id={}&limit=250&include_done=true",
-        daemon_url.trim_end_matches('/'),
-        user_id
-    );
+    let url = format!("{}/tools/posture", daemon_url.trim_end_matches('/'));
     let mut request = client.get(url);
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
-
     let response = request.send().await.map_err(|err| err.to_string())?;
     if !response.status().is_success() {
         let status = response.status();
-        let body = response
+        let text = response
             .text()
             .await
             .unwrap_or_else(|_| "Unable to read response body".to_string());
-        return Err(format!("Inbox request failed: HTTP {status}: {body}"));
+        return Err(format!("HTTP {status}: {text}"));
     }
-
-    let parsed = response
-        .json::<InboxApiResponse>()
+    response
+        .json::<ToolPostureApiResponse>()
         .await
-        .map_err(|err| err.to_string())?;
-
-    let mut items = parsed
-        .items
-        .into_iter()
-        .map(|item| {
-            let source_type = match item.source_type.as_str() {
-                "reminder" => InboxSourceType::Reminder,
-                "todo" => InboxSourceType::Todo,
-                "task" => InboxSourceType::Task,
-                _ => InboxSourceType::PlanStep,
-            };
-            let default_status = parse_inbox_status(Some(&item.status), InboxStatus::New);
-            let status = default_status;
-            let priority = parse_inbox_priority(Some(&item.priority), InboxPriority::Normal);
-
-            InboxItem {
-                id: item.id,
-                source_type,
-                owner: item.owner,
-                title: item.title,
-                details: item.details,
-                status,
-                priority,
-                due_at: item.due_at,
-                created_at: item.created_at,
-                updated_at: item.updated_at,
-                requires_human_action: item.requires_human_action,
-                origin_ref: item.origin_ref,
-                dependency_refs: item.dependency_refs,
-                t_shirt_size: item.t_shirt_size,
-                story_points: item.story_points,
-                estimate_optimistic_minutes: item.estimate_optimistic_minutes,
-                estimate_likely_minutes: item.estimate_likely_minutes,
-                estimate_pessimistic_minutes: item.estimate_pessimistic_minutes,
-            }
-        })
-        .collect::<Vec<_>>();
-
-    let plan_step_refs_in_view = items
-        .iter()
-        .filter(|item| item.source_type == InboxSourceType::PlanStep)
-        .map(|item| item.origin_ref.to_ascii_lowercase())
-        .collect::<HashSet<_>>();
-
-    items.retain(|item| {
-        !(item.source_type == InboxSourceType::Todo
-            && extract_plan_step_ref(item.details.as_ref())
-                .map(|origin_ref| plan_step_refs_in_view.contains(&origin_ref))
-                .unwrap_or(false))
-    });
-
-    let mut seen_origin_refs = HashSet::new();
-    items.retain(|item| seen_origin_refs.insert(item.origin_ref.clone()));
-
-    items.sort_by(|a, b| {
-        priority_rank(a.priority)
-            .cmp(&priority_rank(b.priority))
-            .then_with(|| {
-                a.due_at
-                    .unwrap_or(i64::MAX)
-                    .cmp(&b.due_at.unwrap_or(i64::MAX))
-            })
-            .then_with(|| b.created_at.cmp(&a.created_at))
-    });
+        .map(|response| response.tools)
+        .map_err(|err| err.to_string())
+}
 
-    Ok(items)
+async fn load_secrets(daemon_url: String, token: String) -> Result<Vec<SecretRow>, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/vault/secrets", daemon_url.trim_end_matches('/'));
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
+    }
+    response
+        .json::<SecretsApiResponse>()
+        .await
+        .map(|response| response.secrets)
+        .map_err(|err| err.to_string())
 }
 
-async fn apply_inbox_action(
+async fn save_secret_request(
     daemon_url: String,
     token: String,
-    user_id: String,
-    item: InboxItem,
-    action: InboxActionKind,
+    name: String,
+    value: String,
+    description: String,
+    rotate: bool,
 ) -> Result<String, String> {
-    let action_name = match action {
-        InboxActionKind::Acknowledge => "acknowledge",
-        InboxActionKind::Start => "start",
-        InboxActionKind::Block => "block",
-        InboxActionKind::Done => "done",
-        InboxActionKind::Reopen => "reopen",
-        InboxActionKind::Snooze => "snooze",
+    let client = daemon_request_client();
+    let base = daemon_url.trim_end_matches('/');
+    let mut request = if rotate {
+        client
+            .post(format!("{base}/vault/secrets/rotate"))
+            .json(&serde_json::json!({"name": name, "value": value}))
+    } else {
+        client
+            .post(format!("{base}/vault/secrets"))
+            .json(&serde_json::json!({
+                "name": name,
+                "value": value,
+                "description": description
+            }))
     };
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
+    }
+    Ok(if rotate {
+        format!("Rotated {name}")
+    } else {
+        format!("Stored {name}")
+    })
+}
 
+async fn delete_secret_request(
+    daemon_url: String,
+    token: String,
+    name: String,
+) -> Result<String, String> {
     let client = daemon_request_client();
-    let url = format!("{}/inbox/transition", daemon_url.trim_end_matches('/'));
-    let mut request = client.post(url).json(&serde_json::json!({
-        "user_id": user_id,
-        "origin_ref": item.origin_ref,
-        "action": action_name,
-    }));
+    let url = format!("{}/vault/secrets/delete", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url).json(&serde_json::json!({"name": name}));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
+    }
+    Ok(format!("Deleted {name}"))
+}
+
+async fn load_consent_log(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<Vec<ConsentLogRow>, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/consent", daemon_url.trim_end_matches('/'));
+    let mut request = client.get(url).query(&[("user_id", user_id)]);
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
-
     let response = request.send().await.map_err(|err| err.to_string())?;
-    let status = response.status();
-    let body = response.text().await.map_err(|err| err.to_string())?;
-    if !status.is_success() {
-        return Err(format!("Inbox action failed: HTTP {status}: {body}"));
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
-
-    Ok(format!("Inbox action applied: {}", action_name))
+    response
+        .json::<ConsentLogApiResponse>()
+        .await
+        .map(|response| response.entries)
+        .map_err(|err| err.to_string())
 }
 
-async fn send_prompt(
+async fn revoke_consent_request(
     daemon_url: String,
-    user_id: String,
     token: String,
-    prompt: String,
+    user_id: String,
+    id: i32,
 ) -> Result<String, String> {
     let client = daemon_request_client();
-    let url = format!("{}/process_text", daemon_url.trim_end_matches('/'));
-    let mut request = client.post(url).json(&ProcessTextRequest {
-        user_id,
-        text: prompt,
-        prompt: None,
-    });
+    let url = format!("{}/consent/revoke", daemon_url.trim_end_matches('/'));
+    let mut request = client
+        .post(url)
+        .json(&serde_json::json!({"user_id": user_id, "id": id}));
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
-
-    let response = request.send().await.map_err(|e| e.to_string())?;
+    let response = request.send().await.map_err(|err| err.to_string())?;
     let status = response.status();
-    let body = response.text().await.map_err(|e| e.to_string())?;
+    let body = response.text().await.map_err(|err| err.to_string())?;
     if !status.is_success() {
         return Err(format!("HTTP {status}: {body}"));
     }
-
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&body) {
-        if let Some(text) = extract_assistant_text(&json) {
-            return Ok(text);
-        }
-    }
-
-    Ok(body)
+    Ok(serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|value| value.get("summary")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("Revoked consent {id}")))
 }
 
-fn extract_assistant_text(value: &serde_json::Value) -> Option<String> {
-    if let Some(text) = value.as_str() {
-        return Some(text.to_string());
+async fn run_doctor_request(daemon_url: String, token: String) -> Result<DoctorResponse, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/doctor", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
     }
-
-    for key in ["response", "output", "text", "message", "content", "answer"] {
-        if let Some(text) = value.get(key).and_then(|v| v.as_str()) {
-            return Some(text.to_string());
-        }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
+    response
+        .json::<DoctorResponse>()
+        .await
+        .map_err(|err| err.to_string())
+}
 
-    if let Some(message) = value.get("message") {
-        if let Some(text) = message.get("content").and_then(|v| v.as_str()) {
-            return Some(text.to_string());
-        }
-        if let Some(text) = message.get("text").and_then(|v| v.as_str()) {
-            return Some(text.to_string());
-        }
+async fn repair_schema_request(
+    daemon_url: String,
+    token: String,
+) -> Result<SchemaRepairResponse, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/doctor/schema/repair", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
     }
-
-    if let Some(choice) = value
-        .get("choices")
-        .and_then(|v| v.as_array())
-        .and_then(|arr| arr.first())
-    {
-        if let Some(text) = choice
-            .get("message")
-            .and_then(|m| m.get("content"))
-            .and_then(|v| v.as_str())
-        {
-            return Some(text.to_string());
-        }
-        if let Some(text) = choice.get("text").and_then(|v| v.as_str()) {
-            return Some(text.to_string());
-        }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
     }
-
-    None
+    response
+        .json::<SchemaRepairResponse>()
+        .await
+        .map_err(|err| err.to_string())
 }
 
-fn daemon_request_client() -> reqwest::Client {
-    reqwest::Client::builder()
-        .connect_timeout(Duration::from_secs(2))
-        .timeout(Duration::from_secs(60))
-        .build()
-        .expect("request client")
+async fn run_security_audit_request(
+    daemon_url: String,
+    token: String,
+) -> Result<SecurityAuditResponse, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/security_audit", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("HTTP {status}: {text}"));
+    }
+    response
+        .json::<SecurityAuditResponse>()
+        .await
+        .map_err(|err| err.to_string())
 }
 
-async fn check_daemon_health(daemon_url: String) -> DaemonHealth {
-    let normalized = normalize_daemon_url(&daemon_url);
-    if check_daemon_health_once(&normalized).await {
-        return DaemonHealth {
-            daemon_url: normalized,
-            healthy: true,
-            switched: false,
-        };
+async fn fetch_estimate_accuracy(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<Vec<String>, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/todos/estimate_accuracy?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
     }
-
-    if running_from_macos_app_bundle() {
-        return DaemonHealth {
-            daemon_url: normalized,
-            healthy: false,
-            switched: false,
-        };
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Estimate accuracy failed: HTTP {status}: {body}"));
     }
+    let parsed = response
+        .json::<EstimateAccuracyApiResponse>()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(parsed
+        .categories
+        .into_iter()
+        .map(|row| {
+            format!(
+                "{} • {} done • took {:.2}× the estimate (median) • off by {:.0}% on average • {:.0}% within 25% • multiplier {:.2}×",
+                row.category,
+                row.samples,
+                row.median_ratio,
+                row.mean_absolute_error_pct,
+                row.within_25_pct * 100.0,
+                row.multiplier
+            )
+        })
+        .collect())
+}
 
-    let (host, port) = parse_daemon_address(&normalized);
-    if port == 7878 && (host == "127.0.0.1" || host == "localhost") {
-        let scheme = if normalized.starts_with("https://") {
-            "https"
-        } else {
-            "http"
-        };
-        let fallback = format!("{scheme}://{host}:7979");
-        if check_daemon_health_once(&fallback).await {
-            return DaemonHealth {
-                daemon_url: fallback,
-                healthy: true,
-                switched: true,
-            };
-        }
+async fn fetch_scheduler_jobs(daemon_url: String, token: String) -> Result<Vec<String>, String> {
+    let client = daemon_request_client();
+    let url = format!("{}/scheduler/jobs", daemon_url.trim_end_matches('/'));
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
     }
-
-    DaemonHealth {
-        daemon_url: normalized,
-        healthy: false,
-        switched: false,
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Scheduler jobs failed: HTTP {status}: {body}"));
     }
+    let parsed = response
+        .json::<SchedulerJobsApiResponse>()
+        .await
+        .map_err(|err| err.to_string())?;
+    Ok(parsed
+        .jobs
+        .into_iter()
+        .map(|job| {
+            let last_run = match (job.last_run_at, job.last_duration_ms) {
+                (Some(at), Some(ms)) => format!("last ran {} for {ms} ms", format_local_time(at)),
+                (Some(at), None) => format!("last ran {}", format_local_time(at)),
+                _ => "never run".to_string(),
+            };
+            let mut line = format!(
+                "{}{} • every {} • {} • next {}",
+                job.name,
+                if job.late { " [late]" } else { "" },
+                format_interval_seconds(job.interval_seconds),
+                last_run,
+                format_local_time(job.next_run_at)
+            );
+            if let Some(error) = job.last_error {
+                line.push_str(&format!(" • last error: {error}"));
+            }
+            line
+        })
+        .collect())
 }
 
-async fn check_daemon_health_once(daemon_url: &str) -> bool {
-    let client = daemon_request_client();
-    let url = format!("{}/health", daemon_url.trim_end_matches('/'));
-    match client.get(url).send().await {
-        Ok(resp) => resp.status().is_success(),
-        Err(_) => false,
+fn format_interval_seconds(seconds: i64) -> String {
+    match seconds {
+        s if s <= 0 => "unknown interval".to_string(),
+        s if s % 3600 == 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 => format!("{}m", s / 60),
+        s => format!("{s}s"),
     }
 }
 
-async fn run_doctor_request(daemon_url: String, token: String) -> Result<DoctorResponse, String> {
+async fn fetch_dependency_check(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<DependencyCheckRow, String> {
     let client = daemon_request_client();
-    let url = format!("{}/doctor", daemon_url.trim_end_matches('/'));
-    let mut request = client.post(url);
+    let url = format!(
+        "{}/dependencies/check?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
     let response = request.send().await.map_err(|err| err.to_string())?;
     if !response.status().is_success() {
         let status = response.status();
-        let text = response
+        let body = response
             .text()
             .await
             .unwrap_or_else(|_| "Unable to read response body".to_string());
-        return Err(format!("HTTP {status}: {text}"));
+        return Err(format!("Reference check failed: HTTP {status}: {body}"));
     }
     response
-        .json::<DoctorResponse>()
+        .json::<DependencyCheckRow>()
         .await
         .map_err(|err| err.to_string())
 }
 
-async fn run_security_audit_request(
+async fn fetch_dead_letters(
     daemon_url: String,
     token: String,
-) -> Result<SecurityAuditResponse, String> {
+    user_id: String,
+) -> Result<Vec<DeadLetterRow>, String> {
     let client = daemon_request_client();
-    let url = format!("{}/security_audit", daemon_url.trim_end_matches('/'));
-    let mut request = client.post(url);
+    let url = format!(
+        "{}/reminders/dead_letters?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
     let response = request.send().await.map_err(|err| err.to_string())?;
     if !response.status().is_success() {
         let status = response.status();
-        let text = response
+        let body = response
             .text()
             .await
             .unwrap_or_else(|_| "Unable to read response body".to_string());
-        return Err(format!("HTTP {status}: {text}"));
+        return Err(format!("Failed deliveries failed: HTTP {status}: {body}"));
     }
     response
-        .json::<SecurityAuditResponse>()
+        .json::<DeadLettersApiResponse>()
         .await
+        .map(|parsed| parsed.dead_letters)
         .map_err(|err| err.to_string())
 }
 
+/// `action` is `redrive` or `discard`.
+async fn dead_letter_action(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    action: &'static str,
+    id: i32,
+) -> Result<(), String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/reminders/dead_letters/{action}",
+        daemon_url.trim_end_matches('/')
+    );
+    let mut request = client
+        .post(url)
+        .json(&serde_json::json!({"user_id": user_id, "id": id}));
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unable to read response body".to_string());
+        return Err(format!("Delivery {action} failed: HTTP {status}: {body}"));
+    }
+    Ok(())
+}
+
 async fn fetch_reminder_delivery_events(
     daemon_url: String,
     token: String,
@@ -4868,27 +10357,44 @@ async fn fetch_reminder_delivery_events(
                 .and_then(|v| v.get("title"))
                 .and_then(|v| v.as_str())
                 .unwrap_or("(untitled)");
-            format!("[{ts}] {status} — {title}")
+            match event
+                .get("payload")
+                .and_then(|v| v.get("held_until"))
+                .and_then(|v| v.as_i64())
+            {
+                Some(until) => format!(
+                    "[{ts}] {status} — {title} (until {})",
+                    format_local_time(until)
+                ),
+                None => format!("[{ts}] {status} — {title}"),
+            }
         })
         .collect::<Vec<_>>();
 
-    Ok(lines)
+    Ok(AuditEventsPage {
+        rows,
+        next_before_id,
+        older: before_id.is_some(),
+    })
 }
 
 async fn fetch_audit_events(
     daemon_url: String,
     token: String,
     user_id: String,
-    limit: usize,
-) -> Result<Vec<AuditEventRow>, String> {
+    origin_ref: Option<String>,
+    before_id: Option<i32>,
+) -> Result<AuditEventsPage, String> {
     let client = daemon_request_client();
-    let url = format!(
-        "{}/audit/events?user_id={}&limit={}",
-        daemon_url.trim_end_matches('/'),
-        user_id,
-        limit
-    );
-    let mut request = client.get(url);
+    let url = format!("{}/audit/events", daemon_url.trim_end_matches('/'));
+    let mut query = vec![("user_id", user_id), ("limit", AUDIT_PAGE_SIZE.to_string())];
+    if let Some(origin_ref) = origin_ref {
+        query.push(("origin_ref", origin_ref));
+    }
+    if let Some(before_id) = before_id {
+        query.push(("before_id", before_id.to_string()));
+    }
+    let mut request = client.get(url).query(&query);
     if !token.trim().is_empty() {
         request = request.header("authorization", format!("Bearer {token}"));
     }
@@ -4910,10 +10416,16 @@ async fn fetch_audit_events(
         .await
         .map_err(|err| err.to_string())?;
 
-    let lines = parsed
+    let next_before_id = parsed.next_before_id;
+    let rows = parsed
         .events
         .into_iter()
         .map(|event| {
+            let id = event
+                .get("id")
+                .and_then(|v| v.as_i64())
+                .and_then(|v| i32::try_from(v).ok())
+                .unwrap_or(0);
             let ts = event.get("timestamp").and_then(|v| v.as_i64()).unwrap_or(0);
             let ts_label = if ts > 0 {
                 format_local_time(ts)
@@ -4937,16 +10449,15 @@ async fn fetch_audit_events(
                 .to_string();
             let user = event.get("user_id").and_then(|v| v.as_str()).unwrap_or("-");
             let actor = event
-                .get("payload")
-                .and_then(|v| v.get("actor"))
+                .get("actor")
                 .and_then(|v| v.as_str())
                 .map(|v| v.to_string());
             let origin_ref = event
-                .get("payload")
-                .and_then(|v| v.get("origin_ref"))
+                .get("origin_ref")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string());
             AuditEventRow {
+                id,
                 timestamp: ts,
                 event_type: event_type.clone(),
                 status: status.clone(),
@@ -4956,6 +10467,10 @@ async fn fetch_audit_events(
                     origin_ref.clone().unwrap_or_else(|| "-".to_string())
                 ),
                 origin_ref,
+                title: event
+                    .pointer("/payload/title")
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string()),
             }
         })
         .collect::<Vec<_>>();
@@ -4997,6 +10512,129 @@ async fn fetch_solana_wallet_address(
     }
 }
 
+async fn fetch_chat_threads(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<ChatThreadsApiResponse, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/threads?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("HTTP {status}: {body}"));
+    }
+    response
+        .json::<ChatThreadsApiResponse>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn fetch_catch_up(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    since: i64,
+) -> Result<CatchUp, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/catch_up?user_id={}&since={}",
+        daemon_url.trim_end_matches('/'),
+        user_id,
+        since
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("HTTP {status}: {body}"));
+    }
+    response
+        .json::<CatchUp>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn fetch_aggregate_insights(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<AggregateInsights, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/insights/aggregate?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("HTTP {status}: {body}"));
+    }
+    response
+        .json::<AggregateInsights>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn fetch_activity_heatmap(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+) -> Result<ActivityHeatmap, String> {
+    let client = daemon_request_client();
+    let url = format!(
+        "{}/insights/heatmap?user_id={}",
+        daemon_url.trim_end_matches('/'),
+        user_id
+    );
+    let mut request = client.get(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    let response = request.send().await.map_err(|err| err.to_string())?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("HTTP {status}: {body}"));
+    }
+    response
+        .json::<ActivityHeatmap>()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+async fn run_memory_search(
+    daemon_url: String,
+    token: String,
+    user_id: String,
+    query: String,
+    rerank: bool,
+) -> Result<Vec<MemoryHit>, String> {
+    crate::cli::DaemonClient::new(daemon_url, Some(token))
+        .memory_search(&user_id, &query, 10, rerank)
+        .await
+        .map_err(|err| err.to_string())
+}
+
 async fn run_chat_history_request(
     daemon_url: String,
     token: String,
@@ -5083,19 +10721,22 @@ fn optimistic_inbox_transition(
     let Some(previous) = parse_inbox_status_state(item.status) else {
         return;
     };
-    let action = match action {
+    let Some(next) = crate::inbox_fsm::transition(previous, fsm_action(action)) else {
+        return;
+    };
+    item.status = next;
+    item.updated_at = now_unix_ts();
+}
+
+fn fsm_action(action: InboxActionKind) -> crate::inbox_fsm::InboxAction {
+    match action {
         InboxActionKind::Acknowledge => crate::inbox_fsm::InboxAction::Acknowledge,
         InboxActionKind::Start => crate::inbox_fsm::InboxAction::Start,
         InboxActionKind::Block => crate::inbox_fsm::InboxAction::Block,
         InboxActionKind::Done => crate::inbox_fsm::InboxAction::Done,
         InboxActionKind::Reopen => crate::inbox_fsm::InboxAction::Reopen,
         InboxActionKind::Snooze => crate::inbox_fsm::InboxAction::Snooze,
-    };
-    let Some(next) = crate::inbox_fsm::transition(previous, action) else {
-        return;
-    };
-    item.status = next;
-    item.updated_at = now_unix_ts();
+    }
 }
 
 fn parse_inbox_status_state(status: InboxStatus) -> Option<crate::inbox_fsm::InboxState> {
@@ -5199,14 +10840,26 @@ fn maybe_emit_proactive_chat_nudge(state: &mut ButterflyIcedApp) {
         return;
     }
 
+    let now_local = state.clock.now_local();
     if in_quiet_hours(
+        &now_local,
         &state.settings.proactive_chat_quiet_start_hhmm,
         &state.settings.proactive_chat_quiet_end_hhmm,
     ) {
         return;
     }
 
-    let now = now_unix_ts();
+    // Hold nudges for hours the user historically doesn't act on; the
+    // heatmap only answers false once it has enough history to be trusted.
+    if state
+        .activity_heatmap
+        .as_ref()
+        .is_some_and(|heatmap| !heatmap.is_active_at(&now_local))
+    {
+        return;
+    }
+
+    let now = state.clock.now();
     let min_interval_seconds = state
         .settings
         .proactive_chat_min_interval_seconds
@@ -5290,6 +10943,7 @@ fn maybe_emit_proactive_chat_nudge(state: &mut ButterflyIcedApp) {
             ask
         ),
     );
+    state.speak(UiTab::Inbox, &format!("Heads up: {}. {}", item.title, ask));
     state
         .proactive_notified_origin_refs
         .insert(item.origin_ref.clone());
@@ -5314,7 +10968,7 @@ fn parse_hhmm_to_minutes(value: &str) -> Option<u16> {
     Some(hour * 60 + minute)
 }
 
-fn in_quiet_hours(start_hhmm: &str, end_hhmm: &str) -> bool {
+fn in_quiet_hours(now_local: &DateTime<Local>, start_hhmm: &str, end_hhmm: &str) -> bool {
     let Some(start) = parse_hhmm_to_minutes(start_hhmm) else {
         return false;
     };
@@ -5322,18 +10976,8 @@ fn in_quiet_hours(start_hhmm: &str, end_hhmm: &str) -> bool {
         return false;
     };
 
-    let local_offset = UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC);
-    let now_local = OffsetDateTime::now_utc().to_offset(local_offset);
     let current = now_local.hour() as u16 * 60 + now_local.minute() as u16;
-
-    if start == end {
-        return false;
-    }
-    if start < end {
-        current >= start && current < end
-    } else {
-        current >= start || current < end
-    }
+    crate::clock::in_minute_window(current, start, end)
 }
 
 fn scroll_chat_to_anchor_task(state: &ButterflyIcedApp) -> Task<Message> {
@@ -5420,14 +11064,6 @@ fn normalize_nlp_due_text(input: &str) -> String {
     from_now_re.replace_all(&normalized, "in $1 $2").to_string()
 }
 
-fn text_has_explicit_time(input: &str) -> bool {
-    static TIME_RE: OnceLock<Regex> = OnceLock::new();
-    let re = TIME_RE.get_or_init(|| {
-        Regex::new(r"(?i)(\b\d{1,2}:\d{2}\b|\b\d{1,2}\s*(am|pm)\b|\bnoon\b|\bmidnight\b)").unwrap()
-    });
-    re.is_match(input)
-}
-
 fn local_midnight_unix(ts: i64) -> i64 {
     let Ok(utc_dt) = OffsetDateTime::from_unix_timestamp(ts) else {
         return ts;
@@ -5538,18 +11174,15 @@ fn infer_due_at_from_text(text: &str, anchor_ts: i64) -> Option<i64> {
         .or_else(|| Local.timestamp_opt(now_unix_ts(), 0).single())
         .unwrap_or_else(Local::now);
 
-    parse_date_string(&normalized, anchor, Dialect::Us)
-        .or_else(|_| parse_date_string(&normalized, anchor, Dialect::Uk))
-        .ok()
-        .map(|dt: DateTime<Local>| {
-            let ts = dt.timestamp();
-            let parsed = if text_has_explicit_time(trimmed) {
-                ts
-            } else {
-                local_midnight_unix(ts)
-            };
-            normalize_due_year_if_stale(parsed, trimmed)
-        })
+    crate::date_phrases::parse_phrase(&normalized, anchor).map(|dt: DateTime<Local>| {
+        let ts = dt.timestamp();
+        let parsed = if crate::date_phrases::has_explicit_time(trimmed) {
+            ts
+        } else {
+            local_midnight_unix(ts)
+        };
+        normalize_due_year_if_stale(parsed, trimmed)
+    })
 }
 
 fn infer_due_at_from_item_text(item: &InboxItem) -> Option<i64> {
@@ -5619,6 +11252,25 @@ fn derive_doctor_overall(checks: &[DoctorCheckResponse]) -> &'static str {
     }
 }
 
+/// Charts rendered by `chart.render` are referenced as markdown images;
+/// show them under the bubble text at their native size.
+fn view_inline_charts(message_text: &str) -> Element<'static, Message> {
+    if message_text.is_empty() {
+        return Space::new().height(0).into();
+    }
+    crate::charts::chart_paths_in_markdown(message_text, &crate::charts::charts_dir())
+        .into_iter()
+        .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("png"))
+        .fold(column!().spacing(6), |col, path| {
+            col.push(
+                image::<image::Handle>(image::Handle::from_path(path))
+                    .width(Length::Fixed(crate::charts::WIDTH as f32))
+                    .height(Length::Fixed(crate::charts::HEIGHT as f32)),
+            )
+        })
+        .into()
+}
+
 fn parse_markdown_items(input: &str) -> Vec<markdown::Item> {
     markdown::parse(input).collect()
 }
@@ -5832,7 +11484,38 @@ async fn stop_local_daemon() -> Result<String, String> {
     }
 }
 
-async fn stop_daemon_by_url(daemon_url: String) -> Result<String, String> {
+/// Longer than the daemon's default drain so a clean stop is not cut short.
+const DAEMON_SHUTDOWN_WAIT: Duration = Duration::from_secs(45);
+
+/// Asks the daemon to shut down the way SIGTERM does, letting in-flight work
+/// finish, and waits until it stops answering.
+async fn request_daemon_shutdown(daemon_url: &str, token: &str) -> bool {
+    let client = daemon_request_client();
+    let url = format!("{}/shutdown", daemon_url.trim_end_matches('/'));
+    let mut request = client.post(url);
+    if !token.trim().is_empty() {
+        request = request.header("authorization", format!("Bearer {token}"));
+    }
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {}
+        _ => return false,
+    }
+    let deadline = std::time::Instant::now() + DAEMON_SHUTDOWN_WAIT;
+    while check_daemon_health_once(daemon_url).await {
+        if std::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+    true
+}
+
+async fn stop_daemon_by_url(daemon_url: String, token: String) -> Result<String, String> {
+    if request_daemon_shutdown(&daemon_url, &token).await {
+        // Reaps the exited child, if this UI started it.
+        let _ = stop_local_daemon().await;
+        return Ok("Daemon stopped".to_string());
+    }
     match stop_local_daemon().await {
         Ok(status) if status == "Daemon stopped" => Ok(status),
         Ok(_) | Err(_) => {
@@ -5958,11 +11641,28 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                                 .unwrap_or_default()
                                 .trim()
                                 .to_string();
-                            if name.is_empty() && url.is_empty() {
+                            let command = entry
+                                .get("command")
+                                .and_then(|v| v.as_str())
+                                .map(|command| {
+                                    let args: Vec<&str> = entry
+                                        .get("args")
+                                        .and_then(|v| v.as_array())
+                                        .map(|args| {
+                                            args.iter().filter_map(|v| v.as_str()).collect()
+                                        })
+                                        .unwrap_or_default();
+                                    crate::tools::mcp::join_command_line(
+                                        std::iter::once(command.trim()).chain(args),
+                                    )
+                                })
+                                .unwrap_or_default();
+                            if name.is_empty() && url.is_empty() && command.is_empty() {
                                 return None;
                             }
+                            let pairs = if command.is_empty() { "headers" } else { "env" };
                             let (header_key, header_value) = entry
-                                .get("headers")
+                                .get(pairs)
                                 .and_then(|v| v.as_object())
                                 .and_then(|map| {
                                     map.iter().find_map(|(k, v)| {
@@ -5975,6 +11675,7 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                             Some(UiServerRow {
                                 name,
                                 url,
+                                command,
                                 header_key,
                                 header_value,
                             })
@@ -5984,6 +11685,52 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                 .unwrap_or_default()
         }
 
+        fn parse_zap_rows(value: Option<&Value>) -> Vec<UiZapRow> {
+            let text = |entry: &Value, key: &str| {
+                entry
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            };
+            value
+                .and_then(|v| v.as_array())
+                .map(|zaps| {
+                    zaps.iter()
+                        .filter_map(|entry| {
+                            let name = text(entry, "name");
+                            if name.is_empty() {
+                                return None;
+                            }
+                            let fields = entry
+                                .get("fields")
+                                .and_then(|v| v.as_object())
+                                .map(|fields| {
+                                    fields
+                                        .iter()
+                                        .map(|(field, kind)| {
+                                            format!("{field}:{}", kind.as_str().unwrap_or("string"))
+                                        })
+                                        .collect::<Vec<_>>()
+                                        .join(", ")
+                                })
+                                .unwrap_or_default();
+                            Some(UiZapRow {
+                                url: text(entry, "url"),
+                                fields,
+                                inbound_user: entry
+                                    .get("inbound")
+                                    .map(|inbound| text(inbound, "user"))
+                                    .unwrap_or_default(),
+                                name,
+                            })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        }
+
         let mut config = crate::config::Config::from_store(&db_path)
             .map_err(|err| format!("Failed to load config: {err}"))?;
 
@@ -6029,6 +11776,12 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
             .and_then(|v| v.as_str())
             .unwrap_or("auto")
             .to_string();
+        let speech_tabs = get_path(tools, &["settings", "voice", "speech", "tabs"])
+            .and_then(|v| v.as_array())
+            .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let speak_chat_replies = speech_tabs.contains(&"chat");
+        let speak_inbox = speech_tabs.contains(&"inbox");
         let proactive_chat_enabled = get_path(tools, &["settings", "proactive_chat", "enabled"])
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
@@ -6053,8 +11806,48 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                 .and_then(|v| v.as_str())
                 .unwrap_or_default()
                 .to_string();
+        let privacy_lock_idle_minutes =
+            crate::privacy_lock::idle_minutes_from_tools(Some(tools)).to_string();
+        let presentation_names = crate::presentation::names_from_tools(Some(tools)).join(", ");
+        let remote_storage = |key: &str| {
+            get_path(tools, &["settings", "remote_storage", key])
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+        let remote_storage_kind = Some(remote_storage("kind"))
+            .filter(|kind| !kind.is_empty())
+            .unwrap_or_else(|| "off".to_string());
+        let (remote_storage_location, remote_storage_account) =
+            if remote_storage_kind.eq_ignore_ascii_case("webdav") {
+                (remote_storage("url"), remote_storage("username"))
+            } else {
+                (remote_storage("endpoint"), remote_storage("access_key_id"))
+            };
+        let remote_storage_bucket = remote_storage("bucket");
+        let remote_storage_region = remote_storage("region");
+        let remote_storage_prefix = remote_storage("prefix");
+        let remote_storage_secret =
+            crate::vault::get_secret_required(crate::remote_storage::VAULT_SECRET)
+                .map_err(|err| err.to_string())?
+                .unwrap_or_default();
         let mut mcp_servers = parse_server_rows(get_path(tools, &["mcp", "servers"]));
         let mut http_call_servers = parse_server_rows(get_path(tools, &["http_call", "servers"]));
+        let zaps = parse_zap_rows(get_path(tools, &["zapier", "zaps"]));
+        // Shows every stored rule, including ones the daemon currently
+        // ignores, so they can be fixed here.
+        let inbox_rules = get_path(tools, &["settings", "inbox_rules"])
+            .and_then(Value::as_array)
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter_map(|entry| {
+                        serde_json::from_value::<crate::inbox_rules::InboxRule>(entry.clone()).ok()
+                    })
+                    .map(UiInboxRuleRow::from_rule)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
 
         if http_call_servers.is_empty() {
             let shared_header = get_path(tools, &["http_call", "custom_headers"])
@@ -6079,6 +11872,7 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                                 url: trimmed.to_string(),
                                 header_key: shared_header.0.clone(),
                                 header_value: shared_header.1.clone(),
+                                ..UiServerRow::default()
                             });
                         }
                     }
@@ -6110,8 +11904,23 @@ async fn load_settings(db_path: String) -> Result<LoadedSettings, String> {
                 proactive_chat_severity,
                 proactive_chat_quiet_start_hhmm,
                 proactive_chat_quiet_end_hhmm,
+                speak_chat_replies,
+                speak_inbox,
+                privacy_lock_idle_minutes,
+                privacy_lock_passphrase: String::new(),
+                presentation_names,
+                remote_storage_kind,
+                remote_storage_location,
+                remote_storage_bucket,
+                remote_storage_region,
+                remote_storage_account,
+                remote_storage_secret,
+                remote_storage_prefix,
+                remote_storage_passphrase: String::new(),
                 mcp_servers: std::mem::take(&mut mcp_servers),
                 http_call_servers: std::mem::take(&mut http_call_servers),
+                zaps,
+                inbox_rules,
                 prompt_text,
                 heartbeat_text,
             },
@@ -6157,6 +11966,7 @@ async fn save_settings(
                 context_embed_enabled: Some(false),
                 summary_threshold: None,
                 retention_days: None,
+                retention: None,
             });
             memory.enabled = Some(true);
             memory.summary_model = Some("gpt-4.1-mini".to_string());
@@ -6175,6 +11985,22 @@ async fn save_settings(
                 .map_err(|err| format!("Failed to store OpenAI API key: {err}"))?;
             crate::vault::set_secret_required("search_internet_grok_api_key", &form.grok_api_key)
                 .map_err(|err| format!("Failed to store search API key: {err}"))?;
+            if !form.privacy_lock_passphrase.is_empty() {
+                crate::privacy_lock::set_passphrase(&form.privacy_lock_passphrase)
+                    .map_err(|err| format!("Failed to store lock passphrase: {err}"))?;
+            }
+            crate::vault::set_secret_required(
+                crate::remote_storage::VAULT_SECRET,
+                &form.remote_storage_secret,
+            )
+            .map_err(|err| format!("Failed to store remote storage secret: {err}"))?;
+            if !form.remote_storage_passphrase.is_empty() {
+                crate::vault::set_secret_required(
+                    crate::remote_storage::VAULT_PASSPHRASE,
+                    &form.remote_storage_passphrase,
+                )
+                .map_err(|err| format!("Failed to store remote storage passphrase: {err}"))?;
+            }
 
             let tools = config
                 .tools
@@ -6246,6 +12072,92 @@ async fn save_settings(
                     Value::String(form.proactive_chat_quiet_end_hhmm.trim().to_string()),
                 );
 
+                let voice = settings_obj
+                    .entry("voice")
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+                let speech = voice
+                    .as_object_mut()
+                    .ok_or_else(|| "tools.settings.voice must be an object".to_string())?
+                    .entry("speech")
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+                let speech_obj = speech
+                    .as_object_mut()
+                    .ok_or_else(|| "tools.settings.voice.speech must be an object".to_string())?;
+                let speech_tabs = [
+                    (form.speak_chat_replies, "chat"),
+                    (form.speak_inbox, "inbox"),
+                ]
+                .into_iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, tab)| Value::String(tab.to_string()))
+                .collect::<Vec<_>>();
+                speech_obj.insert("tabs".to_string(), Value::Array(speech_tabs));
+
+                let privacy_lock = settings_obj
+                    .entry("privacy_lock")
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+                let privacy_lock_obj = privacy_lock
+                    .as_object_mut()
+                    .ok_or_else(|| "tools.settings.privacy_lock must be an object".to_string())?;
+                let idle_minutes = form
+                    .privacy_lock_idle_minutes
+                    .trim()
+                    .parse::<u64>()
+                    .unwrap_or(0);
+                privacy_lock_obj.insert(
+                    "idle_minutes".to_string(),
+                    Value::Number(serde_json::Number::from(idle_minutes)),
+                );
+
+                let presentation = settings_obj
+                    .entry("presentation")
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+                let presentation_obj = presentation
+                    .as_object_mut()
+                    .ok_or_else(|| "tools.settings.presentation must be an object".to_string())?;
+                let names = form
+                    .presentation_names
+                    .split(',')
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .map(|s| Value::String(s.to_string()))
+                    .collect::<Vec<_>>();
+                presentation_obj.insert("names".to_string(), Value::Array(names));
+
+                let mut inbox_rules = Vec::new();
+                for (index, row) in form.inbox_rules.iter().enumerate() {
+                    let rule = row
+                        .to_rule()
+                        .map_err(|problem| format!("Inbox rule {}: {problem}", index + 1))?;
+                    if let Some(rule) = rule {
+                        inbox_rules.push(serde_json::to_value(rule).map_err(|e| e.to_string())?);
+                    }
+                }
+                settings_obj.insert("inbox_rules".to_string(), Value::Array(inbox_rules));
+
+                let remote_storage = settings_obj
+                    .entry("remote_storage")
+                    .or_insert_with(|| Value::Object(serde_json::Map::new()));
+                let remote_storage_obj = remote_storage
+                    .as_object_mut()
+                    .ok_or_else(|| "tools.settings.remote_storage must be an object".to_string())?;
+                let kind = form.remote_storage_kind.trim().to_ascii_lowercase();
+                let (location_key, account_key) = if kind == "webdav" {
+                    ("url", "username")
+                } else {
+                    ("endpoint", "access_key_id")
+                };
+                for (key, value) in [
+                    ("kind", kind.as_str()),
+                    (location_key, form.remote_storage_location.trim()),
+                    (account_key, form.remote_storage_account.trim()),
+                    ("bucket", form.remote_storage_bucket.trim()),
+                    ("region", form.remote_storage_region.trim()),
+                    ("prefix", form.remote_storage_prefix.trim()),
+                ] {
+                    remote_storage_obj.insert(key.to_string(), Value::String(value.to_string()));
+                }
+
                 let solana = settings_obj
                     .entry("solana")
                     .or_insert_with(|| Value::Object(serde_json::Map::new()));
@@ -6303,12 +12215,24 @@ async fn save_settings(
                 .filter_map(|entry| {
                     let name = entry.name.trim();
                     let url = entry.url.trim();
-                    if name.is_empty() || url.is_empty() {
+                    let mut command = crate::tools::mcp::split_command_line(&entry.command);
+                    if name.is_empty() || (url.is_empty() && command.is_empty()) {
                         return None;
                     }
                     let mut server = serde_json::Map::new();
                     server.insert("name".to_string(), Value::String(name.to_string()));
-                    server.insert("url".to_string(), Value::String(url.to_string()));
+                    let pairs = if url.is_empty() {
+                        let args = command.split_off(1);
+                        server.insert("command".to_string(), Value::String(command.remove(0)));
+                        server.insert(
+                            "args".to_string(),
+                            Value::Array(args.into_iter().map(Value::String).collect()),
+                        );
+                        "env"
+                    } else {
+                        server.insert("url".to_string(), Value::String(url.to_string()));
+                        "headers"
+                    };
                     let header_key = entry.header_key.trim();
                     let header_value = entry.header_value.trim();
                     if !header_key.is_empty() && !header_value.is_empty() {
@@ -6317,7 +12241,7 @@ async fn save_settings(
                             header_key.to_string(),
                             Value::String(header_value.to_string()),
                         );
-                        server.insert("headers".to_string(), Value::Object(headers));
+                        server.insert(pairs.to_string(), Value::Object(headers));
                     }
                     Some(Value::Object(server))
                 })
@@ -6351,12 +12275,24 @@ async fn save_settings(
                 .filter_map(|entry| {
                     let name = entry.name.trim();
                     let url = entry.url.trim();
-                    if name.is_empty() || url.is_empty() {
+                    let mut command = crate::tools::mcp::split_command_line(&entry.command);
+                    if name.is_empty() || (url.is_empty() && command.is_empty()) {
                         return None;
                     }
                     let mut server = serde_json::Map::new();
                     server.insert("name".to_string(), Value::String(name.to_string()));
-                    server.insert("url".to_string(), Value::String(url.to_string()));
+                    let pairs = if url.is_empty() {
+                        let args = command.split_off(1);
+                        server.insert("command".to_string(), Value::String(command.remove(0)));
+                        server.insert(
+                            "args".to_string(),
+                            Value::Array(args.into_iter().map(Value::String).collect()),
+                        );
+                        "env"
+                    } else {
+                        server.insert("url".to_string(), Value::String(url.to_string()));
+                        "headers"
+                    };
                     let header_key = entry.header_key.trim();
                     let header_value = entry.header_value.trim();
                     if !header_key.is_empty() && !header_value.is_empty() {
@@ -6365,7 +12301,7 @@ async fn save_settings(
                             header_key.to_string(),
                             Value::String(header_value.to_string()),
                         );
-                        server.insert("headers".to_string(), Value::Object(headers));
+                        server.insert(pairs.to_string(), Value::Object(headers));
                     }
                     Some(Value::Object(server))
                 })
@@ -6383,6 +12319,72 @@ async fn save_settings(
                 .collect::<Vec<_>>();
             http_call_obj.insert("base_urls".to_string(), Value::Array(base_urls));
 
+            let zapier = tools_obj
+                .entry("zapier")
+                .or_insert_with(|| Value::Object(serde_json::Map::new()));
+            let zapier_obj = zapier
+                .as_object_mut()
+                .ok_or_else(|| "tools.zapier must be an object".to_string())?;
+            // Keys the form does not show (description, inbound priority or
+            // secret name) survive from the stored entry of the same name.
+            let previous = zapier_obj
+                .get("zaps")
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default();
+            let zaps = form
+                .zaps
+                .iter()
+                .filter_map(|entry| {
+                    let name = entry.name.trim();
+                    let url = entry.url.trim();
+                    let user = entry.inbound_user.trim();
+                    if name.is_empty() || (url.is_empty() && user.is_empty()) {
+                        return None;
+                    }
+                    let mut zap = previous
+                        .iter()
+                        .find(|zap| zap.get("name").and_then(|v| v.as_str()) == Some(name))
+                        .and_then(|zap| zap.as_object().cloned())
+                        .unwrap_or_default();
+                    zap.insert("name".to_string(), Value::String(name.to_string()));
+                    if url.is_empty() {
+                        zap.remove("url");
+                    } else {
+                        zap.insert("url".to_string(), Value::String(url.to_string()));
+                    }
+                    let fields = entry
+                        .fields
+                        .split(',')
+                        .filter_map(|field| {
+                            let (field, kind) = field.split_once(':').unwrap_or((field, "string"));
+                            let (field, kind) = (field.trim(), kind.trim());
+                            (!field.is_empty()).then(|| {
+                                let kind = if kind.is_empty() { "string" } else { kind };
+                                (field.to_string(), Value::String(kind.to_string()))
+                            })
+                        })
+                        .collect::<serde_json::Map<_, _>>();
+                    if fields.is_empty() {
+                        zap.remove("fields");
+                    } else {
+                        zap.insert("fields".to_string(), Value::Object(fields));
+                    }
+                    if user.is_empty() {
+                        zap.remove("inbound");
+                    } else {
+                        let mut inbound = zap
+                            .get("inbound")
+                            .and_then(|v| v.as_object().cloned())
+                            .unwrap_or_default();
+                        inbound.insert("user".to_string(), Value::String(user.to_string()));
+                        zap.insert("inbound".to_string(), Value::Object(inbound));
+                    }
+                    Some(Value::Object(zap))
+                })
+                .collect::<Vec<_>>();
+            zapier_obj.insert("zaps".to_string(), Value::Array(zaps));
+
             crate::config_store::save_config(&db_path, &config)
                 .map_err(|err| format!("Failed to save config: {err}"))?;
 
@@ -0,0 +1,259 @@
+//! User-defined inbox rules.
+//!
+//! Rules run in order each time the daemon builds the inbox, after the
+//! built-in inference, and can override an item's priority, owner and
+//! whether it waits on a human. Configured under
+//! `tools.settings.inbox_rules`:
+//!
+//! ```json
+//! [{"name": "Bills first", "source_types": ["todo", "external"],
+//!   "keywords": ["invoice", "payment"], "due_within_hours": 48,
+//!   "priority": "urgent"},
+//!  {"name": "Bot chores", "labels": ["chore"], "owner": "agent",
+//!   "requires_human_action": false}]
+//! ```
+//!
+//! Every condition a rule lists must hold; within a list any entry will do.
+//! Keywords match the title or details case-insensitively and
+//! `due_within_hours` includes overdue items. Rules without a condition or
+//! without an effect are ignored. When several rules match, later ones win.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const PRIORITIES: [&str; 5] = ["low", "normal", "high", "urgent", "critical"];
+pub const OWNERS: [&str; 2] = ["human", "agent"];
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InboxRule {
+    pub name: String,
+    pub source_types: Vec<String>,
+    pub keywords: Vec<String>,
+    pub due_within_hours: Option<u64>,
+    pub labels: Vec<String>,
+    pub priority: Option<String>,
+    pub owner: Option<String>,
+    pub requires_human_action: Option<bool>,
+}
+
+/// The parts of an inbox item rules look at.
+pub struct RuleSubject<'a> {
+    pub source_type: &'a str,
+    pub title: &'a str,
+    pub details: Option<&'a str>,
+    pub labels: &'a [String],
+    pub due_at: Option<i64>,
+}
+
+/// What the matching rules decided; `None` leaves the field alone.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RuleOutcome {
+    pub priority: Option<String>,
+    pub owner: Option<String>,
+    pub requires_human_action: Option<bool>,
+    /// Names of the rules that matched, in order.
+    pub matched: Vec<String>,
+}
+
+impl InboxRule {
+    pub fn has_conditions(&self) -> bool {
+        !self.source_types.is_empty()
+            || !self.keywords.is_empty()
+            || self.due_within_hours.is_some()
+            || !self.labels.is_empty()
+    }
+
+    pub fn has_effects(&self) -> bool {
+        self.priority.is_some() || self.owner.is_some() || self.requires_human_action.is_some()
+    }
+
+    /// Why the rule would be ignored, if it would be.
+    pub fn problem(&self) -> Option<String> {
+        if !self.has_conditions() {
+            return Some("it has no conditions".to_string());
+        }
+        if !self.has_effects() {
+            return Some("it changes nothing".to_string());
+        }
+        if let Some(priority) = self
+            .priority
+            .as_deref()
+            .filter(|priority| !PRIORITIES.contains(priority))
+        {
+            return Some(format!("unknown priority {priority}"));
+        }
+        if let Some(owner) = self
+            .owner
+            .as_deref()
+            .filter(|owner| !OWNERS.contains(owner))
+        {
+            return Some(format!("unknown owner {owner}"));
+        }
+        None
+    }
+
+    pub fn matches(&self, subject: &RuleSubject<'_>, now: i64) -> bool {
+        if !self.source_types.is_empty()
+            && !self
+                .source_types
+                .iter()
+                .any(|source| source.eq_ignore_ascii_case(subject.source_type))
+        {
+            return false;
+        }
+        if !self.keywords.is_empty() {
+            let haystack =
+                format!("{} {}", subject.title, subject.details.unwrap_or_default()).to_lowercase();
+            if !self
+                .keywords
+                .iter()
+                .any(|keyword| haystack.contains(&keyword.to_lowercase()))
+            {
+                return false;
+            }
+        }
+        if let Some(hours) = self.due_within_hours {
+            let window = i64::try_from(hours.saturating_mul(3600)).unwrap_or(i64::MAX);
+            if !subject
+                .due_at
+                .is_some_and(|due| due.saturating_sub(now) <= window)
+            {
+                return false;
+            }
+        }
+        if !self.labels.is_empty()
+            && !self.labels.iter().any(|label| {
+                subject
+                    .labels
+                    .iter()
+                    .any(|item_label| item_label.eq_ignore_ascii_case(label))
+            })
+        {
+            return false;
+        }
+        true
+    }
+
+    fn normalized(mut self) -> Self {
+        let clean = |values: Vec<String>| {
+            values
+                .into_iter()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect::<Vec<_>>()
+        };
+        let lower = |value: Option<String>| {
+            value
+                .map(|value| value.trim().to_ascii_lowercase())
+                .filter(|value| !value.is_empty())
+        };
+        self.name = self.name.trim().to_string();
+        self.source_types = clean(self.source_types);
+        self.keywords = clean(self.keywords);
+        self.labels = clean(self.labels);
+        self.priority = lower(self.priority);
+        self.owner = lower(self.owner);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InboxRules {
+    rules: Vec<InboxRule>,
+}
+
+impl InboxRules {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(entries) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("inbox_rules"))
+            .and_then(Value::as_array)
+        else {
+            return Self::default();
+        };
+        let rules = entries
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entry)| {
+                let rule = match serde_json::from_value::<InboxRule>(entry.clone()) {
+                    Ok(rule) => rule.normalized(),
+                    Err(err) => {
+                        tracing::warn!(index, error = %err, "Ignoring malformed inbox rule");
+                        return None;
+                    }
+                };
+                if let Some(problem) = rule.problem() {
+                    tracing::warn!(index, name = %rule.name, %problem, "Ignoring inbox rule");
+                    return None;
+                }
+                Some(rule)
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn evaluate(&self, subject: &RuleSubject<'_>, now: i64) -> RuleOutcome {
+        let mut outcome = RuleOutcome::default();
+        for rule in self.rules.iter().filter(|rule| rule.matches(subject, now)) {
+            if rule.priority.is_some() {
+                outcome.priority = rule.priority.clone();
+            }
+            if rule.owner.is_some() {
+                outcome.owner = rule.owner.clone();
+            }
+            if rule.requires_human_action.is_some() {
+                outcome.requires_human_action = rule.requires_human_action;
+            }
+            outcome.matched.push(rule.name.clone());
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn later_matching_rules_override_earlier_ones() {
+        let tools = json!({"settings": {"inbox_rules": [
+            {"name": "Bills", "keywords": ["Invoice"], "due_within_hours": 48,
+             "priority": "Urgent"},
+            {"name": "Finance chores", "labels": ["finance"], "source_types": ["todo"],
+             "priority": "low", "owner": "agent", "requires_human_action": false},
+            {"name": "No conditions", "priority": "critical"},
+            {"name": "Bad priority", "keywords": ["x"], "priority": "asap"},
+            {"name": "Malformed", "keywords": "invoice"}
+        ]}});
+        let rules = InboxRules::from_tools(Some(&tools));
+        let now = 1_700_000_000;
+        let labels = vec!["Finance".to_string()];
+        let subject = |source_type, due_at| RuleSubject {
+            source_type,
+            title: "Pay the invoice",
+            details: None,
+            labels: &labels,
+            due_at,
+        };
+
+        let reminder = rules.evaluate(&subject("reminder", Some(now - 60)), now);
+        assert_eq!(reminder.priority.as_deref(), Some("urgent"));
+        assert_eq!(reminder.owner, None);
+        assert_eq!(reminder.matched, vec!["Bills".to_string()]);
+
+        let todo = rules.evaluate(&subject("todo", Some(now + 3600)), now);
+        assert_eq!(todo.priority.as_deref(), Some("low"));
+        assert_eq!(todo.owner.as_deref(), Some("agent"));
+        assert_eq!(todo.requires_human_action, Some(false));
+        assert_eq!(todo.matched.len(), 2);
+
+        let undated = rules.evaluate(&subject("reminder", None), now);
+        assert_eq!(undated, RuleOutcome::default());
+    }
+}
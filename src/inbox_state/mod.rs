@@ -1,27 +1,30 @@
 use std::collections::HashMap;
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::{Deserialize, Serialize};
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
 
 mod schema;
-use schema::inbox_item_states;
+use schema::{inbox_comments, inbox_item_states, inbox_transitions};
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const INBOX_STATES_UP_SQL: &str =
     include_str!("../../migrations/20260221_create_inbox_item_states/up.sql");
+const INBOX_TRANSITIONS_UP_SQL: &str =
+    include_str!("../../migrations/20260306_create_inbox_transitions/up.sql");
+const INBOX_COMMENTS_UP_SQL: &str =
+    include_str!("../../migrations/20260331_create_inbox_comments/up.sql");
 
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
+/// Who wrote an inbox comment.
+pub const AUTHOR_HUMAN: &str = "human";
+pub const AUTHOR_AGENT: &str = "agent";
+const MAX_COMMENT_LEN: usize = 4000;
 
 #[derive(Insertable)]
 #[diesel(table_name = inbox_item_states)]
@@ -33,28 +36,76 @@ struct NewInboxItemState<'a> {
     updated_at: i64,
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = inbox_transitions)]
+struct NewInboxTransition<'a> {
+    user_id: &'a str,
+    origin_ref: &'a str,
+    from_status: &'a str,
+    to_status: &'a str,
+    created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = inbox_comments)]
+struct NewInboxComment<'a> {
+    user_id: &'a str,
+    origin_ref: &'a str,
+    author: &'a str,
+    body: &'a str,
+    created_at: i64,
+}
+
+/// A timestamped note on an inbox item, e.g. "waiting on vendor reply".
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, Queryable)]
+pub struct InboxComment {
+    pub id: i32,
+    pub origin_ref: String,
+    pub author: String,
+    pub body: String,
+    pub created_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InboxTransition {
+    pub origin_ref: String,
+    pub to_status: String,
+    pub created_at: i64,
+}
+
 pub struct InboxStateStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
 }
 
 impl InboxStateStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_inbox_states_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn set_status(&self, user_id: &str, origin_ref: &str, status: &str) -> Result<()> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
 
         let existing = inbox_item_states::table
             .filter(inbox_item_states::user_id.eq(user_id))
@@ -120,33 +171,241 @@ impl InboxStateStore {
         Ok(map)
     }
 
-    pub async fn clear_statuses(&self, user_id: &str) -> Result<usize> {
+    /// Users with at least one item persisted in `status`.
+    pub async fn users_with_status(&self, status: &str) -> Result<Vec<String>> {
         let mut conn = self.conn().await?;
+        inbox_item_states::table
+            .filter(inbox_item_states::status.eq(status))
+            .select(inbox_item_states::user_id)
+            .distinct()
+            .order(inbox_item_states::user_id.asc())
+            .load::<String>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    pub async fn clear_statuses(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
         let deleted =
             diesel::delete(inbox_item_states::table.filter(inbox_item_states::user_id.eq(user_id)))
                 .execute(&mut conn)
                 .await
                 .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::delete(inbox_transitions::table.filter(inbox_transitions::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::delete(inbox_comments::table.filter(inbox_comments::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
         Ok(deleted)
     }
 
-    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
+    /// Appends a note to the item's thread. `author` is [`AUTHOR_HUMAN`] or
+    /// [`AUTHOR_AGENT`].
+    pub async fn add_comment(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+        author: &str,
+        body: &str,
+    ) -> Result<InboxComment> {
+        let origin_ref = origin_ref.trim();
+        let body = body.trim();
+        if origin_ref.is_empty() {
+            return Err(ButterflyBotError::Config(
+                "Comments need an item origin_ref".to_string(),
+            ));
+        }
+        if body.is_empty() {
+            return Err(ButterflyBotError::Config(
+                "Comment text is required".to_string(),
+            ));
+        }
+        if body.chars().count() > MAX_COMMENT_LEN {
+            return Err(ButterflyBotError::Config(format!(
+                "Comments are limited to {MAX_COMMENT_LEN} characters"
+            )));
+        }
+        if author != AUTHOR_HUMAN && author != AUTHOR_AGENT {
+            return Err(ButterflyBotError::Config(format!(
+                "Unknown comment author: {author}"
+            )));
+        }
+        let sealed_body = user_domains::seal(user_id, "inbox_comment.body", body)?;
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(inbox_comments::table)
+            .values(&NewInboxComment {
+                user_id,
+                origin_ref,
+                author,
+                body: &sealed_body,
+                created_at: self.clock.now(),
+            })
+            .execute(&mut conn)
             .await
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        let mut comment: InboxComment = inbox_comments::table
+            .filter(inbox_comments::user_id.eq(user_id))
+            .order(inbox_comments::id.desc())
+            .select((
+                inbox_comments::id,
+                inbox_comments::origin_ref,
+                inbox_comments::author,
+                inbox_comments::body,
+                inbox_comments::created_at,
+            ))
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        comment.body = body.to_string();
+        Ok(comment)
     }
-}
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    /// The item's thread, oldest first.
+    pub async fn comments(&self, user_id: &str, origin_ref: &str) -> Result<Vec<InboxComment>> {
+        let mut grouped = self
+            .comments_for(user_id, &[origin_ref.to_string()])
+            .await?;
+        Ok(grouped.remove(origin_ref).unwrap_or_default())
+    }
+
+    /// Threads for several items at once, keyed by origin ref. Items
+    /// without comments are left out.
+    pub async fn comments_for(
+        &self,
+        user_id: &str,
+        origin_refs: &[String],
+    ) -> Result<HashMap<String, Vec<InboxComment>>> {
+        if origin_refs.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut conn = self.conn().await?;
+        let rows: Vec<InboxComment> = inbox_comments::table
+            .filter(inbox_comments::user_id.eq(user_id))
+            .filter(inbox_comments::origin_ref.eq_any(origin_refs))
+            .order((inbox_comments::created_at.asc(), inbox_comments::id.asc()))
+            .select((
+                inbox_comments::id,
+                inbox_comments::origin_ref,
+                inbox_comments::author,
+                inbox_comments::body,
+                inbox_comments::created_at,
+            ))
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let mut grouped: HashMap<String, Vec<InboxComment>> = HashMap::new();
+        for mut comment in rows {
+            comment.body = user_domains::open(user_id, "inbox_comment.body", comment.body);
+            grouped
+                .entry(comment.origin_ref.clone())
+                .or_default()
+                .push(comment);
+        }
+        Ok(grouped)
+    }
+
+    pub async fn record_transition(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+        from_status: &str,
+        to_status: &str,
+    ) -> Result<()> {
+        self.record_transition_at(
+            user_id,
+            origin_ref,
+            from_status,
+            to_status,
+            self.clock.now(),
+        )
+        .await
+    }
+
+    pub async fn record_transition_at(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+        from_status: &str,
+        to_status: &str,
+        created_at: i64,
+    ) -> Result<()> {
+        let mut conn = self.write_conn().await?;
+        let new_row = NewInboxTransition {
+            user_id,
+            origin_ref,
+            from_status,
+            to_status,
+            created_at,
+        };
+        diesel::insert_into(inbox_transitions::table)
+            .values(&new_row)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Timestamps of transitions into any of `to_statuses` at or after `since`.
+    pub async fn transition_times(
+        &self,
+        user_id: &str,
+        to_statuses: &[&str],
+        since: i64,
+    ) -> Result<Vec<i64>> {
+        let mut conn = self.conn().await?;
+        inbox_transitions::table
+            .filter(inbox_transitions::user_id.eq(user_id))
+            .filter(inbox_transitions::to_status.eq_any(to_statuses))
+            .filter(inbox_transitions::created_at.ge(since))
+            .select(inbox_transitions::created_at)
+            .load::<i64>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Transitions at or after `since`, oldest first.
+    pub async fn transitions_since(
+        &self,
+        user_id: &str,
+        since: i64,
+    ) -> Result<Vec<InboxTransition>> {
+        let mut conn = self.conn().await?;
+        inbox_transitions::table
+            .filter(inbox_transitions::user_id.eq(user_id))
+            .filter(inbox_transitions::created_at.ge(since))
+            .order((
+                inbox_transitions::created_at.asc(),
+                inbox_transitions::id.asc(),
+            ))
+            .select((
+                inbox_transitions::origin_ref,
+                inbox_transitions::to_status,
+                inbox_transitions::created_at,
+            ))
+            .load::<(String, String, i64)>(&mut conn)
+            .await
+            .map(|rows| {
+                rows.into_iter()
+                    .map(|(origin_ref, to_status, created_at)| InboxTransition {
+                        origin_ref,
+                        to_status,
+                        created_at,
+                    })
+                    .collect()
+            })
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
-    Ok(())
 }
 
 async fn run_migrations(database_url: &str) -> Result<()> {
@@ -167,19 +426,25 @@ async fn ensure_inbox_states_table(database_url: &str) -> Result<()> {
     tokio::task::spawn_blocking(move || {
         let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
 
-        let check = diesel::connection::SimpleConnection::batch_execute(
-            &mut conn,
-            "SELECT 1 FROM inbox_item_states LIMIT 1",
-        );
-        if let Err(err) = check {
-            let message = err.to_string();
-            if message.contains("no such table") {
-                conn.run_pending_migrations(MIGRATIONS)
-                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-                diesel::connection::SimpleConnection::batch_execute(&mut conn, INBOX_STATES_UP_SQL)
-                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-            } else {
-                return Err(ButterflyBotError::Runtime(message));
+        for (table, up_sql) in [
+            ("inbox_item_states", INBOX_STATES_UP_SQL),
+            ("inbox_transitions", INBOX_TRANSITIONS_UP_SQL),
+            ("inbox_comments", INBOX_COMMENTS_UP_SQL),
+        ] {
+            let check = diesel::connection::SimpleConnection::batch_execute(
+                &mut conn,
+                &format!("SELECT 1 FROM {table} LIMIT 1"),
+            );
+            if let Err(err) = check {
+                let message = err.to_string();
+                if message.contains("no such table") {
+                    conn.run_pending_migrations(MIGRATIONS)
+                        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                    diesel::connection::SimpleConnection::batch_execute(&mut conn, up_sql)
+                        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                } else {
+                    return Err(ButterflyBotError::Runtime(message));
+                }
             }
         }
 
@@ -189,10 +454,3 @@ async fn ensure_inbox_states_table(database_url: &str) -> Result<()> {
     .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
     Ok(())
 }
-
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
-}
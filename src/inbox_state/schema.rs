@@ -8,3 +8,25 @@ diesel::table! {
         updated_at -> BigInt,
     }
 }
+
+diesel::table! {
+    inbox_transitions (id) {
+        id -> Integer,
+        user_id -> Text,
+        origin_ref -> Text,
+        from_status -> Text,
+        to_status -> Text,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    inbox_comments (id) {
+        id -> Integer,
+        user_id -> Text,
+        origin_ref -> Text,
+        author -> Text,
+        body -> Text,
+        created_at -> BigInt,
+    }
+}
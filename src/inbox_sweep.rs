@@ -0,0 +1,441 @@
+//! Weekly "inbox zero" sweep.
+//!
+//! Once a week the agent walks every open inbox item and proposes a
+//! disposition for each. The proposals are parked as a single approval; the
+//! user accepts all of them or a subset, and the daemon applies the accepted
+//! ones through the normal inbox transitions. Configured per user under
+//! `tools.settings.inbox_sweep`, with top-level keys as defaults:
+//!
+//! ```json
+//! {"weekday": "sunday", "run_at": "17:00",
+//!  "users": {"alice": {"weekday": "friday", "run_at": "16:00"}}}
+//! ```
+//!
+//! Run times are read in the user's time zone. Only users listed under
+//! `users` are swept.
+
+use std::collections::HashMap;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::digest::{parse_send_at, CATCH_UP_MINUTES};
+use crate::inbox_fsm::InboxAction;
+
+/// Tool and capability names the sweep approval is parked under.
+pub const SWEEP_TOOL: &str = "inbox";
+pub const SWEEP_CAPABILITY: &str = "inbox.sweep";
+
+pub const DEFAULT_RUN_AT_MINUTE: u32 = 17 * 60;
+
+/// Items sent to the agent in one sweep; the rest get heuristic proposals.
+const AGENT_ITEM_LIMIT: usize = 100;
+const SOON_SECS: i64 = 2 * 24 * 60 * 60;
+const STALE_SECS: i64 = 30 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    DoNow,
+    Schedule,
+    Delegate,
+    Drop,
+}
+
+impl Disposition {
+    pub fn all() -> [Disposition; 4] {
+        [
+            Disposition::DoNow,
+            Disposition::Schedule,
+            Disposition::Delegate,
+            Disposition::Drop,
+        ]
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value
+            .trim()
+            .to_ascii_lowercase()
+            .replace([' ', '-'], "_")
+            .as_str()
+        {
+            "do_now" | "now" | "do" => Some(Disposition::DoNow),
+            "schedule" | "later" | "defer" => Some(Disposition::Schedule),
+            "delegate" | "waiting" => Some(Disposition::Delegate),
+            "drop" | "dismiss" => Some(Disposition::Drop),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Disposition::DoNow => "do_now",
+            Disposition::Schedule => "schedule",
+            Disposition::Delegate => "delegate",
+            Disposition::Drop => "drop",
+        }
+    }
+
+    /// The inbox transition that carries the disposition out. Delegated
+    /// items are blocked on someone else; scheduled ones stay open but are
+    /// marked as seen.
+    pub fn action(self) -> InboxAction {
+        match self {
+            Disposition::DoNow => InboxAction::Start,
+            Disposition::Schedule => InboxAction::Acknowledge,
+            Disposition::Delegate => InboxAction::Block,
+            Disposition::Drop => InboxAction::Dismiss,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepSchedule {
+    pub weekday: Weekday,
+    pub run_at_minute: u32,
+}
+
+impl Default for SweepSchedule {
+    fn default() -> Self {
+        Self {
+            weekday: Weekday::Sun,
+            run_at_minute: DEFAULT_RUN_AT_MINUTE,
+        }
+    }
+}
+
+impl SweepSchedule {
+    fn overlay(&self, value: &Value) -> Self {
+        let mut schedule = self.clone();
+        if let Some(weekday) = value
+            .get("weekday")
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.trim().parse::<Weekday>().ok())
+        {
+            schedule.weekday = weekday;
+        }
+        if let Some(minute) = value
+            .get("run_at")
+            .and_then(|v| v.as_str())
+            .and_then(parse_send_at)
+        {
+            schedule.run_at_minute = minute;
+        }
+        schedule
+    }
+
+    /// Whether the sweep should run at `minute` past local midnight on
+    /// `today`, given the day it last ran.
+    pub fn is_due(&self, today: NaiveDate, minute: u32, last_run: Option<NaiveDate>) -> bool {
+        today.weekday() == self.weekday
+            && last_run != Some(today)
+            && minute >= self.run_at_minute
+            && minute < self.run_at_minute + CATCH_UP_MINUTES
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SweepConfig {
+    pub users: HashMap<String, SweepSchedule>,
+}
+
+impl SweepConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("inbox_sweep"))
+        else {
+            return Self::default();
+        };
+        if section.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+            return Self::default();
+        }
+        let defaults = SweepSchedule::default().overlay(section);
+        let users = section
+            .get("users")
+            .and_then(|v| v.as_object())
+            .map(|users| {
+                users
+                    .iter()
+                    .filter(|(_, value)| {
+                        value.get("enabled").and_then(|v| v.as_bool()) != Some(false)
+                    })
+                    .map(|(user_id, value)| (user_id.clone(), defaults.overlay(value)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { users }
+    }
+}
+
+/// The slice of an open inbox item the sweep looks at.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepItem {
+    pub origin_ref: String,
+    pub title: String,
+    pub source_type: String,
+    pub status: String,
+    pub priority: String,
+    pub due_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Proposal {
+    pub origin_ref: String,
+    pub title: String,
+    pub disposition: Disposition,
+    pub reason: String,
+    /// `agent` when the model proposed it, `rule` for the fallback.
+    pub proposed_by: String,
+}
+
+/// The proposals parked as one approval.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SweepBatch {
+    pub proposals: Vec<Proposal>,
+    /// Anonymized trend line from [`crate::insights::AggregateInsights`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trends: Option<String>,
+}
+
+impl SweepBatch {
+    pub fn from_args(args: &Value) -> Option<Self> {
+        serde_json::from_value(args.clone()).ok()
+    }
+
+    pub fn to_args(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_else(|_| json!({"proposals": []}))
+    }
+
+    /// Counts per disposition, e.g. "3 do now, 1 drop".
+    pub fn summary(&self) -> String {
+        let parts = Disposition::all()
+            .into_iter()
+            .filter_map(|disposition| {
+                let count = self
+                    .proposals
+                    .iter()
+                    .filter(|proposal| proposal.disposition == disposition)
+                    .count();
+                (count > 0).then(|| format!("{count} {}", disposition.as_str().replace('_', " ")))
+            })
+            .collect::<Vec<_>>();
+        if parts.is_empty() {
+            "nothing to sweep".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Instructions for the agent; the answer must match [`proposal_schema`].
+pub fn agent_prompt(items: &[SweepItem], now: i64) -> String {
+    let mut prompt = String::from(
+        "Weekly inbox-zero sweep. For EVERY open item below, propose exactly one disposition:\n\
+         - do_now: worth starting this week\n\
+         - schedule: keep, but not now\n\
+         - delegate: waiting on or belongs to someone else\n\
+         - drop: no longer worth doing\n\
+         Give a short reason for each. Use the item refs verbatim.\n\nITEMS:\n",
+    );
+    for item in items.iter().take(AGENT_ITEM_LIMIT) {
+        let due = match item.due_at {
+            Some(due_at) if due_at < now => format!(", overdue by {}d", (now - due_at) / 86_400),
+            Some(due_at) => format!(", due in {}d", (due_at - now) / 86_400),
+            None => String::new(),
+        };
+        prompt.push_str(&format!(
+            "- ref={} | {} | {} | status={} | priority={}{due} | untouched {}d\n",
+            item.origin_ref,
+            item.source_type,
+            item.title,
+            item.status,
+            item.priority,
+            (now - item.updated_at).max(0) / 86_400,
+        ));
+    }
+    prompt
+}
+
+pub fn proposal_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "proposals": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "ref": {"type": "string"},
+                        "disposition": {
+                            "type": "string",
+                            "enum": Disposition::all().map(Disposition::as_str),
+                        },
+                        "reason": {"type": "string"}
+                    },
+                    "required": ["ref", "disposition", "reason"],
+                    "additionalProperties": false
+                }
+            }
+        },
+        "required": ["proposals"],
+        "additionalProperties": false
+    })
+}
+
+/// One proposal per item, in item order. The agent's answer wins where it
+/// names a known item with a valid disposition; anything it skipped or
+/// garbled falls back to [`rule_disposition`].
+pub fn propose(items: &[SweepItem], agent_answer: Option<&Value>, now: i64) -> Vec<Proposal> {
+    let mut from_agent: HashMap<&str, (Disposition, String)> = HashMap::new();
+    let answers = agent_answer
+        .and_then(|answer| answer.get("proposals"))
+        .and_then(|proposals| proposals.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for answer in answers {
+        let Some(origin_ref) = answer.get("ref").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        let Some(disposition) = answer
+            .get("disposition")
+            .and_then(|v| v.as_str())
+            .and_then(Disposition::parse)
+        else {
+            continue;
+        };
+        let reason = answer
+            .get("reason")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        from_agent.insert(origin_ref.trim(), (disposition, reason));
+    }
+
+    items
+        .iter()
+        .map(|item| {
+            let (disposition, reason, proposed_by) = match from_agent.get(item.origin_ref.as_str())
+            {
+                Some((disposition, reason)) => (*disposition, reason.clone(), "agent"),
+                None => {
+                    let (disposition, reason) = rule_disposition(item, now);
+                    (disposition, reason, "rule")
+                }
+            };
+            Proposal {
+                origin_ref: item.origin_ref.clone(),
+                title: item.title.clone(),
+                disposition,
+                reason,
+                proposed_by: proposed_by.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Fallback proposal from due date, status and age alone.
+pub fn rule_disposition(item: &SweepItem, now: i64) -> (Disposition, String) {
+    if item.status == "blocked" {
+        return (
+            Disposition::Delegate,
+            "Blocked; waiting on someone else".to_string(),
+        );
+    }
+    match item.due_at {
+        Some(due_at) if due_at < now => (Disposition::DoNow, "Overdue".to_string()),
+        Some(due_at) if due_at - now <= SOON_SECS => {
+            (Disposition::DoNow, "Due within two days".to_string())
+        }
+        None if now - item.updated_at > STALE_SECS => (
+            Disposition::Drop,
+            format!(
+                "No due date and untouched for {} days",
+                (now - item.updated_at) / 86_400
+            ),
+        ),
+        _ => (
+            Disposition::Schedule,
+            "Nothing pressing; keep it for later".to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: i64 = 86_400;
+
+    fn item(origin_ref: &str, status: &str, due_at: Option<i64>, updated_at: i64) -> SweepItem {
+        SweepItem {
+            origin_ref: origin_ref.to_string(),
+            title: origin_ref.to_string(),
+            source_type: "todo".to_string(),
+            status: status.to_string(),
+            priority: "medium".to_string(),
+            due_at,
+            updated_at,
+        }
+    }
+
+    #[test]
+    fn schedule_runs_once_on_its_weekday() {
+        let config = SweepConfig::from_tools(Some(&json!({
+            "settings": {"inbox_sweep": {
+                "run_at": "18:00",
+                "users": {"alice": {"weekday": "friday"}, "bob": {"enabled": false}}
+            }}
+        })));
+        assert_eq!(config.users.len(), 1);
+        let schedule = &config.users["alice"];
+        assert_eq!(schedule.weekday, Weekday::Fri);
+
+        let friday = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        let thursday = friday.pred_opt().unwrap();
+        assert!(schedule.is_due(friday, 18 * 60 + 5, None));
+        assert!(!schedule.is_due(friday, 18 * 60 + 5, Some(friday)));
+        assert!(!schedule.is_due(friday, 17 * 60, None));
+        assert!(!schedule.is_due(thursday, 18 * 60 + 5, None));
+    }
+
+    #[test]
+    fn agent_answers_win_and_gaps_fall_back_to_rules() {
+        let now = 100 * DAY;
+        let items = vec![
+            item("todo:1", "new", Some(now - DAY), now),
+            item("todo:2", "blocked", None, now),
+            item("todo:3", "new", None, now - 40 * DAY),
+            item("todo:4", "acknowledged", Some(now + 10 * DAY), now),
+        ];
+        let answer = json!({"proposals": [
+            {"ref": "todo:1", "disposition": "drop", "reason": "Superseded"},
+            {"ref": "todo:9", "disposition": "do_now", "reason": "Unknown item"},
+            {"ref": "todo:4", "disposition": "sometime", "reason": "Garbled"}
+        ]});
+
+        let proposals = propose(&items, Some(&answer), now);
+        let dispositions = proposals
+            .iter()
+            .map(|proposal| (proposal.disposition, proposal.proposed_by.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            dispositions,
+            vec![
+                (Disposition::Drop, "agent"),
+                (Disposition::Delegate, "rule"),
+                (Disposition::Drop, "rule"),
+                (Disposition::Schedule, "rule"),
+            ]
+        );
+        let batch = SweepBatch {
+            proposals,
+            trends: None,
+        };
+        assert_eq!(batch.summary(), "1 schedule, 1 delegate, 2 drop");
+        assert_eq!(SweepBatch::from_args(&batch.to_args()), Some(batch));
+    }
+}
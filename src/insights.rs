@@ -0,0 +1,480 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::Result;
+
+/// Inbox statuses that mean the user actually picked something up or
+/// finished it; acknowledging or dismissing doesn't count as doing work.
+pub const WORK_STATUSES: &[&str] = &["in_progress", "done"];
+
+/// Below this many transitions the heatmap is shown but not trusted to hold
+/// nudges back.
+pub const MIN_CONFIDENT_SAMPLES: u32 = 20;
+
+/// Slots whose warmth falls below this share of the busiest slot are cold.
+const COLD_WARMTH: f32 = 0.15;
+
+const WEEKDAY_LABELS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+pub fn weekday_label(weekday: usize) -> &'static str {
+    WEEKDAY_LABELS.get(weekday).copied().unwrap_or("?")
+}
+
+/// Weekday × hour counts of work transitions in local time. Rows start on
+/// Monday, columns are hours 0-23.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActivityHeatmap {
+    pub cells: Vec<Vec<u32>>,
+    pub total: u32,
+    pub window_days: u32,
+}
+
+impl ActivityHeatmap {
+    pub fn empty(window_days: u32) -> Self {
+        Self {
+            cells: vec![vec![0; 24]; 7],
+            total: 0,
+            window_days,
+        }
+    }
+
+    pub fn from_timestamps(timestamps: impl IntoIterator<Item = i64>, window_days: u32) -> Self {
+        Self::from_slots(
+            timestamps.into_iter().filter_map(|ts| {
+                let local = Local.timestamp_opt(ts, 0).single()?;
+                Some(slot_of(&local))
+            }),
+            window_days,
+        )
+    }
+
+    pub fn from_slots(slots: impl IntoIterator<Item = (usize, usize)>, window_days: u32) -> Self {
+        let mut heatmap = Self::empty(window_days);
+        for (weekday, hour) in slots {
+            if weekday < 7 && hour < 24 {
+                heatmap.cells[weekday][hour] += 1;
+                heatmap.total += 1;
+            }
+        }
+        heatmap
+    }
+
+    pub fn count(&self, weekday: usize, hour: usize) -> u32 {
+        self.cells
+            .get(weekday)
+            .and_then(|row| row.get(hour))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn max_count(&self) -> u32 {
+        self.cells.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    pub fn is_confident(&self) -> bool {
+        self.total >= MIN_CONFIDENT_SAMPLES
+    }
+
+    /// Activity around a slot relative to the busiest one, in `0.0..=1.0`.
+    /// Adjacent hours count half, so a quiet hour inside a working stretch
+    /// still reads as warm.
+    pub fn warmth(&self, weekday: usize, hour: usize) -> f32 {
+        let max = self.max_count();
+        if max == 0 {
+            return 0.0;
+        }
+        let prev = self.count(weekday, (hour + 23) % 24) as f32;
+        let next = self.count(weekday, (hour + 1) % 24) as f32;
+        let smoothed = self.count(weekday, hour) as f32 + (prev + next) / 2.0;
+        (smoothed / max as f32).min(1.0)
+    }
+
+    /// Whether a nudge at this slot is likely to be acted on. Without enough
+    /// history every slot counts as active.
+    pub fn is_active_slot(&self, weekday: usize, hour: usize) -> bool {
+        !self.is_confident() || self.warmth(weekday, hour) >= COLD_WARMTH
+    }
+
+    pub fn is_active_at(&self, at: &DateTime<Local>) -> bool {
+        let (weekday, hour) = slot_of(at);
+        self.is_active_slot(weekday, hour)
+    }
+
+    /// Busiest slots first, as `(weekday, hour, count)`; empty slots are skipped.
+    pub fn peak_slots(&self, limit: usize) -> Vec<(usize, usize, u32)> {
+        let mut slots = self
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(weekday, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(hour, count)| (weekday, hour, *count))
+            })
+            .filter(|(_, _, count)| *count > 0)
+            .collect::<Vec<_>>();
+        slots.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+        slots.truncate(limit);
+        slots
+    }
+}
+
+fn slot_of(at: &DateTime<Local>) -> (usize, usize) {
+    (
+        at.weekday().num_days_from_monday() as usize,
+        at.hour() as usize,
+    )
+}
+
+/// Groups with fewer items than this are left out of aggregate insights, so
+/// a figure can't be read back to one or two specific items.
+pub const MIN_GROUP_SIZE: usize = 5;
+
+/// Privacy budget spent on each reported figure; smaller is noisier.
+pub const DEFAULT_EPSILON: f64 = 1.0;
+
+/// Vault entry holding the per-install salt for item hashes.
+const SALT_SECRET: &str = "insights_salt";
+
+/// Cycle times are clamped to this before averaging so one forgotten item
+/// can't move the mean by more than the noise covers.
+const MAX_CYCLE_HOURS: f64 = 30.0 * 24.0;
+
+/// Actual/estimated ratios are clamped to this for the same reason.
+const MAX_ESTIMATE_RATIO: f64 = 4.0;
+
+/// Actual time within this share of the estimate counts as on target.
+const ON_TARGET_TOLERANCE: f64 = 0.25;
+
+/// The install's insights salt, created on first use.
+pub fn insights_salt() -> Result<String> {
+    if let Some(salt) = crate::vault::get_secret(SALT_SECRET)?.filter(|salt| !salt.is_empty()) {
+        return Ok(salt);
+    }
+    use rand::TryRng;
+    let mut bytes = [0u8; 16];
+    rand::rngs::SysRng
+        .try_fill_bytes(&mut bytes)
+        .map_err(|e| crate::error::ButterflyBotError::Runtime(e.to_string()))?;
+    let salt = hex::encode(bytes);
+    crate::vault::set_secret(SALT_SECRET, &salt)?;
+    Ok(salt)
+}
+
+/// Salted, truncated hash standing in for an origin ref.
+pub fn anonymize(salt: &str, value: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update([0])
+        .chain_update(value.as_bytes())
+        .finalize();
+    hex::encode(&digest[..8])
+}
+
+/// What aggregates are computed from: one inbox item, known only by its
+/// hash, its labels and when it moved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkSample {
+    pub item: String,
+    pub tags: Vec<String>,
+    pub created_at: i64,
+    /// First move to in progress.
+    pub started_at: Option<i64>,
+    pub done_at: Option<i64>,
+    pub estimate_minutes: Option<i32>,
+}
+
+impl WorkSample {
+    fn cycle_hours(&self) -> Option<f64> {
+        let done = self.done_at?;
+        let start = self.started_at.unwrap_or(self.created_at);
+        Some(((done - start).max(0) as f64 / 3600.0).min(MAX_CYCLE_HOURS))
+    }
+
+    /// Actual over estimated minutes; needs both a start and an estimate.
+    fn estimate_ratio(&self) -> Option<f64> {
+        let estimate = self.estimate_minutes.filter(|minutes| *minutes > 0)?;
+        let worked = (self.done_at? - self.started_at?).max(0) as f64 / 60.0;
+        Some((worked / estimate as f64).min(MAX_ESTIMATE_RATIO))
+    }
+}
+
+/// Laplace noise keyed by a salt, so asking twice returns the same figure
+/// instead of letting repeated queries average the noise away.
+#[derive(Clone, Debug)]
+pub struct Noise {
+    salt: String,
+    epsilon: f64,
+}
+
+impl Noise {
+    pub fn new(salt: impl Into<String>, epsilon: f64) -> Self {
+        Self {
+            salt: salt.into(),
+            epsilon,
+        }
+    }
+
+    /// No noise at all; for tests and local-only views.
+    pub fn exact() -> Self {
+        Self::new("", f64::INFINITY)
+    }
+
+    fn laplace(&self, key: &str, sensitivity: f64) -> f64 {
+        let scale = sensitivity / self.epsilon;
+        if !scale.is_finite() || scale <= 0.0 {
+            return 0.0;
+        }
+        let digest = Sha256::new()
+            .chain_update(self.salt.as_bytes())
+            .chain_update([0])
+            .chain_update(key.as_bytes())
+            .finalize();
+        let mut raw = [0u8; 8];
+        raw.copy_from_slice(&digest[..8]);
+        // Uniform in (-0.5, 0.5), then the inverse Laplace CDF.
+        let u = (u64::from_be_bytes(raw) as f64 + 0.5) / 18_446_744_073_709_551_616.0 - 0.5;
+        -scale * u.signum() * (1.0 - 2.0 * u.abs()).ln()
+    }
+
+    fn count(&self, key: &str, value: usize) -> u32 {
+        (value as f64 + self.laplace(key, 1.0)).round().max(0.0) as u32
+    }
+
+    fn rate(&self, key: &str, value: f64, n: usize) -> f64 {
+        round2((value + self.laplace(key, 1.0 / n as f64)).clamp(0.0, 1.0))
+    }
+
+    fn mean(&self, key: &str, value: f64, bound: f64, n: usize) -> f64 {
+        round2((value + self.laplace(key, bound / n as f64)).clamp(0.0, bound))
+    }
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroupInsight {
+    /// Approximate number of items in the group.
+    pub items: u32,
+    pub completion_rate: f64,
+    /// `None` when too few items in the group were finished.
+    pub avg_cycle_hours: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TagInsight {
+    pub tag: String,
+    #[serde(flatten)]
+    pub stats: GroupInsight,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EstimateAccuracy {
+    pub samples: u32,
+    /// Actual over estimated time; above 1.0 means work ran long.
+    pub mean_ratio: f64,
+    /// Share of items finished within 25% of their estimate.
+    pub on_target_rate: f64,
+}
+
+/// Trends over a window that never name an item. Every figure is noised and
+/// groups under [`MIN_GROUP_SIZE`] are dropped.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AggregateInsights {
+    pub window_days: u32,
+    pub overall: Option<GroupInsight>,
+    pub by_tag: Vec<TagInsight>,
+    pub estimate_accuracy: Option<EstimateAccuracy>,
+    /// Tags left out because they had too few items.
+    pub suppressed_tags: u32,
+}
+
+impl AggregateInsights {
+    pub fn compute(samples: &[WorkSample], window_days: u32, noise: &Noise) -> Self {
+        let mut tagged: BTreeMap<&str, Vec<&WorkSample>> = BTreeMap::new();
+        for sample in samples {
+            for tag in &sample.tags {
+                tagged.entry(tag.as_str()).or_default().push(sample);
+            }
+        }
+        let mut suppressed_tags = 0;
+        let mut by_tag = Vec::new();
+        for (tag, group) in &tagged {
+            match group_insight(&format!("tag:{tag}"), group, noise) {
+                Some(stats) => by_tag.push(TagInsight {
+                    tag: tag.to_string(),
+                    stats,
+                }),
+                None => suppressed_tags += 1,
+            }
+        }
+        by_tag.sort_by(|a, b| b.stats.items.cmp(&a.stats.items).then(a.tag.cmp(&b.tag)));
+
+        let ratios = samples
+            .iter()
+            .filter_map(WorkSample::estimate_ratio)
+            .collect::<Vec<_>>();
+        let estimate_accuracy = (ratios.len() >= MIN_GROUP_SIZE).then(|| {
+            let n = ratios.len();
+            let mean = ratios.iter().sum::<f64>() / n as f64;
+            let on_target = ratios
+                .iter()
+                .filter(|ratio| (**ratio - 1.0).abs() <= ON_TARGET_TOLERANCE)
+                .count() as f64
+                / n as f64;
+            EstimateAccuracy {
+                samples: noise.count("estimate:samples", n),
+                mean_ratio: noise.mean("estimate:ratio", mean, MAX_ESTIMATE_RATIO, n),
+                on_target_rate: noise.rate("estimate:on_target", on_target, n),
+            }
+        });
+
+        Self {
+            window_days,
+            overall: group_insight("all", &samples.iter().collect::<Vec<_>>(), noise),
+            by_tag,
+            estimate_accuracy,
+            suppressed_tags,
+        }
+    }
+
+    /// One line for the weekly review, or `None` without enough history.
+    pub fn summary(&self) -> Option<String> {
+        let overall = self.overall.as_ref()?;
+        let mut parts = vec![format!(
+            "{:.0}% of ~{} items done",
+            overall.completion_rate * 100.0,
+            overall.items
+        )];
+        if let Some(hours) = overall.avg_cycle_hours {
+            parts.push(format!("avg cycle {}", format_hours(hours)));
+        }
+        if let Some(accuracy) = &self.estimate_accuracy {
+            parts.push(format!(
+                "estimates {:.0}% on target",
+                accuracy.on_target_rate * 100.0
+            ));
+        }
+        Some(parts.join(", "))
+    }
+}
+
+pub fn format_hours(hours: f64) -> String {
+    if hours >= 48.0 {
+        format!("{:.1}d", hours / 24.0)
+    } else {
+        format!("{hours:.1}h")
+    }
+}
+
+fn group_insight(key: &str, group: &[&WorkSample], noise: &Noise) -> Option<GroupInsight> {
+    let n = group.len();
+    if n < MIN_GROUP_SIZE {
+        return None;
+    }
+    let done = group
+        .iter()
+        .filter(|sample| sample.done_at.is_some())
+        .count();
+    let cycles = group
+        .iter()
+        .filter_map(|sample| sample.cycle_hours())
+        .collect::<Vec<_>>();
+    let avg_cycle_hours = (cycles.len() >= MIN_GROUP_SIZE).then(|| {
+        let mean = cycles.iter().sum::<f64>() / cycles.len() as f64;
+        noise.mean(&format!("{key}:cycle"), mean, MAX_CYCLE_HOURS, cycles.len())
+    });
+    Some(GroupInsight {
+        items: noise.count(&format!("{key}:items"), n),
+        completion_rate: noise.rate(&format!("{key}:done"), done as f64 / n as f64, n),
+        avg_cycle_hours,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_slots_and_ranks_peaks() {
+        let heatmap = ActivityHeatmap::from_slots(
+            [(0, 9), (0, 9), (0, 9), (2, 14), (2, 14), (6, 23), (7, 0)],
+            28,
+        );
+        assert_eq!(heatmap.total, 6);
+        assert_eq!(heatmap.count(0, 9), 3);
+        assert_eq!(heatmap.peak_slots(2), vec![(0, 9, 3), (2, 14, 2)]);
+        assert!(!heatmap.is_confident());
+        assert!(heatmap.is_active_slot(4, 3));
+    }
+
+    #[test]
+    fn confident_heatmap_marks_cold_hours_but_smooths_neighbours() {
+        let mut slots = vec![(1, 10); 20];
+        slots.extend(vec![(1, 12); 20]);
+        let heatmap = ActivityHeatmap::from_slots(slots, 56);
+        assert!(heatmap.is_confident());
+        assert!(heatmap.is_active_slot(1, 10));
+        assert!(heatmap.is_active_slot(1, 11));
+        assert!(!heatmap.is_active_slot(1, 3));
+        assert!(!heatmap.is_active_slot(5, 10));
+    }
+
+    fn sample(item: &str, tags: &[&str], done_hours: Option<i64>) -> WorkSample {
+        WorkSample {
+            item: anonymize("salt", item),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            created_at: 0,
+            started_at: Some(3600),
+            done_at: done_hours.map(|hours| 3600 + hours * 3600),
+            estimate_minutes: Some(120),
+        }
+    }
+
+    #[test]
+    fn aggregates_suppress_small_groups() {
+        let mut samples = (0..6)
+            .map(|i| sample(&format!("todo:{i}"), &["work"], Some(2)))
+            .collect::<Vec<_>>();
+        samples.extend((0..4).map(|i| sample(&format!("task:{i}"), &["home"], None)));
+
+        let insights = AggregateInsights::compute(&samples, 7, &Noise::exact());
+        let overall = insights.overall.clone().unwrap();
+        assert_eq!(overall.items, 10);
+        assert_eq!(overall.completion_rate, 0.6);
+        assert_eq!(overall.avg_cycle_hours, Some(2.0));
+        assert_eq!(insights.by_tag.len(), 1);
+        assert_eq!(insights.by_tag[0].tag, "work");
+        assert_eq!(insights.suppressed_tags, 1);
+        let accuracy = insights.estimate_accuracy.clone().unwrap();
+        assert_eq!(accuracy.mean_ratio, 1.0);
+        assert_eq!(accuracy.on_target_rate, 1.0);
+        assert_eq!(
+            insights.summary().unwrap(),
+            "60% of ~10 items done, avg cycle 2.0h, estimates 100% on target"
+        );
+
+        let sparse = AggregateInsights::compute(&samples[..4], 7, &Noise::exact());
+        assert!(sparse.overall.is_none());
+        assert!(sparse.summary().is_none());
+    }
+
+    #[test]
+    fn noise_is_stable_per_salt_and_stays_in_range() {
+        let samples = (0..8)
+            .map(|i| sample(&format!("todo:{i}"), &[], Some(i)))
+            .collect::<Vec<_>>();
+        let noise = Noise::new("salt", 0.5);
+        let first = AggregateInsights::compute(&samples, 7, &noise);
+        assert_eq!(first, AggregateInsights::compute(&samples, 7, &noise));
+        let overall = first.overall.unwrap();
+        assert!((0.0..=1.0).contains(&overall.completion_rate));
+        assert_ne!(anonymize("salt", "todo:1"), anonymize("other", "todo:1"));
+        assert!(!anonymize("salt", "todo:1").contains("todo"));
+    }
+}
@@ -3,7 +3,8 @@ use futures::stream::BoxStream;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::error::Result;
+use crate::error::{ButterflyBotError, Result};
+use crate::providers::retention::RetentionReport;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -27,6 +28,16 @@ pub struct ChatEvent {
     pub error: Option<String>,
 }
 
+/// A memory matched by embedding similarity. `score` is cosine similarity
+/// (1.0 is identical); `reranked` hits are ordered by the rerank model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryHit {
+    pub content: String,
+    pub timestamp: i64,
+    pub score: f32,
+    pub reranked: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct ImageInput {
     pub data: ImageData,
@@ -84,6 +95,13 @@ pub trait LlmProvider: Send + Sync {
     async fn embed(&self, inputs: Vec<String>, model: Option<&str>) -> Result<Vec<Vec<f32>>>;
 }
 
+/// Speech-to-text that runs apart from the chat backend, e.g. a local
+/// Whisper server.
+#[async_trait]
+pub trait TranscriptionProvider: Send + Sync {
+    async fn transcribe(&self, audio_bytes: Vec<u8>, input_format: &str) -> Result<String>;
+}
+
 #[async_trait]
 pub trait MemoryProvider: Send + Sync {
     async fn append_message(&self, user_id: &str, role: &str, content: &str) -> Result<()>;
@@ -125,4 +143,22 @@ pub trait MemoryProvider: Send + Sync {
     async fn search(&self, _user_id: &str, _query: &str, _limit: usize) -> Result<Vec<String>> {
         Ok(Vec::new())
     }
+
+    async fn semantic_search(
+        &self,
+        _user_id: &str,
+        _query: &str,
+        _limit: usize,
+        _rerank: bool,
+    ) -> Result<Vec<MemoryHit>> {
+        Ok(Vec::new())
+    }
+
+    /// Runs one retention pass now, ignoring the policy interval. With
+    /// `dry_run` the report says what would happen and nothing is changed.
+    async fn enforce_retention(&self, _user_id: &str, _dry_run: bool) -> Result<RetentionReport> {
+        Err(ButterflyBotError::Config(
+            "Memory retention needs the sqlite memory provider".to_string(),
+        ))
+    }
 }
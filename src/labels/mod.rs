@@ -0,0 +1,407 @@
+//! Labels shared by todos, reminders and plans.
+//!
+//! A user's label names live once in `labels`; each store has its own join
+//! table keyed by item id. Item ids are never reused (the stores use
+//! AUTOINCREMENT), so join rows left behind by a delete are inert, and an
+//! item restored from the trash keeps its labels.
+
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Text};
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::labels;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const LABELS_UP_SQL: &str = include_str!("../../migrations/20260311_create_labels/up.sql");
+const MAX_LABEL_LEN: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelTarget {
+    Todo,
+    Reminder,
+    Plan,
+}
+
+impl LabelTarget {
+    fn join_table(self) -> &'static str {
+        match self {
+            Self::Todo => "todo_labels",
+            Self::Reminder => "reminder_labels",
+            Self::Plan => "plan_labels",
+        }
+    }
+
+    fn item_column(self) -> &'static str {
+        match self {
+            Self::Todo => "todo_id",
+            Self::Reminder => "reminder_id",
+            Self::Plan => "plan_id",
+        }
+    }
+}
+
+/// Lowercases and trims a label, dropping a leading `#`. `None` for empty or
+/// overlong names.
+pub fn normalize(name: &str) -> Option<String> {
+    let name = name.trim().trim_start_matches('#').trim().to_lowercase();
+    if name.is_empty() || name.chars().count() > MAX_LABEL_LEN {
+        return None;
+    }
+    Some(name.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Accepts an array of names or a comma-separated string.
+pub fn parse_labels(value: Option<&Value>) -> Vec<String> {
+    let raw: Vec<&str> = match value {
+        Some(Value::Array(items)) => items.iter().filter_map(|item| item.as_str()).collect(),
+        Some(Value::String(list)) => list.split(',').collect(),
+        _ => Vec::new(),
+    };
+    let mut names = Vec::new();
+    for name in raw.into_iter().filter_map(normalize) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// `labels_any` / `labels_all` arguments of the list capabilities.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LabelFilter {
+    pub any: Vec<String>,
+    pub all: Vec<String>,
+}
+
+impl LabelFilter {
+    pub fn from_args(args: &Value) -> Self {
+        Self {
+            any: parse_labels(args.get("labels_any")),
+            all: parse_labels(args.get("labels_all")),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.any.is_empty() && self.all.is_empty()
+    }
+
+    pub fn matches(&self, labels: &[String]) -> bool {
+        (self.any.is_empty() || self.any.iter().any(|name| labels.contains(name)))
+            && self.all.iter().all(|name| labels.contains(name))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LabelCount {
+    pub name: String,
+    pub items: i64,
+}
+
+#[derive(QueryableByName)]
+struct ItemLabelRow {
+    #[diesel(sql_type = Integer)]
+    item_id: i32,
+    #[diesel(sql_type = Text)]
+    name: String,
+}
+
+#[derive(QueryableByName)]
+struct LabelCountRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = BigInt)]
+    items: i64,
+}
+
+pub struct LabelStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl LabelStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_labels_tables(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for timestamps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Replaces the item's labels with `names` and returns what it now carries.
+    /// Callers check that the item belongs to `user_id`.
+    pub async fn set_labels(
+        &self,
+        user_id: &str,
+        target: LabelTarget,
+        item_id: i32,
+        names: &[String],
+    ) -> Result<Vec<String>> {
+        let now = self.clock.now();
+        let names = names
+            .iter()
+            .filter_map(|name| normalize(name))
+            .collect::<Vec<_>>();
+        let mut conn = self.write_conn().await?;
+
+        diesel::sql_query(format!(
+            "DELETE FROM {table} WHERE {column} = ? \
+             AND label_id IN (SELECT id FROM labels WHERE user_id = ?)",
+            table = target.join_table(),
+            column = target.item_column(),
+        ))
+        .bind::<Integer, _>(item_id)
+        .bind::<Text, _>(user_id)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        for name in &names {
+            diesel::sql_query(
+                "INSERT OR IGNORE INTO labels (user_id, name, created_at) VALUES (?, ?, ?)",
+            )
+            .bind::<Text, _>(user_id)
+            .bind::<Text, _>(name)
+            .bind::<BigInt, _>(now)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            diesel::sql_query(format!(
+                "INSERT OR IGNORE INTO {table} ({column}, label_id) \
+                 SELECT ?, id FROM labels WHERE user_id = ? AND name = ?",
+                table = target.join_table(),
+                column = target.item_column(),
+            ))
+            .bind::<Integer, _>(item_id)
+            .bind::<Text, _>(user_id)
+            .bind::<Text, _>(name)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+
+        let mut current = names;
+        current.sort();
+        current.dedup();
+        Ok(current)
+    }
+
+    /// Labels per item id, sorted by name; items without labels are absent.
+    pub async fn labels_for(
+        &self,
+        user_id: &str,
+        target: LabelTarget,
+        item_ids: &[i32],
+    ) -> Result<HashMap<i32, Vec<String>>> {
+        if item_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut conn = self.conn().await?;
+        let rows: Vec<ItemLabelRow> = diesel::sql_query(format!(
+            "SELECT j.{column} AS item_id, l.name AS name FROM {table} j \
+             JOIN labels l ON l.id = j.label_id WHERE l.user_id = ? ORDER BY l.name",
+            table = target.join_table(),
+            column = target.item_column(),
+        ))
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let mut map: HashMap<i32, Vec<String>> = HashMap::new();
+        for row in rows {
+            if item_ids.contains(&row.item_id) {
+                map.entry(row.item_id).or_default().push(row.name);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Every label the user has, with how many todos, reminders and plans
+    /// carry it.
+    pub async fn list_labels(&self, user_id: &str) -> Result<Vec<LabelCount>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<LabelCountRow> = diesel::sql_query(
+            "SELECT l.name AS name, \
+               (SELECT COUNT(*) FROM todo_labels WHERE label_id = l.id) \
+               + (SELECT COUNT(*) FROM reminder_labels WHERE label_id = l.id) \
+               + (SELECT COUNT(*) FROM plan_labels WHERE label_id = l.id) AS items \
+             FROM labels l WHERE l.user_id = ? ORDER BY l.name",
+        )
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| LabelCount {
+                name: row.name,
+                items: row.items,
+            })
+            .collect())
+    }
+
+    /// Removes the user's labels and every join row pointing at them.
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        for target in [LabelTarget::Todo, LabelTarget::Reminder, LabelTarget::Plan] {
+            diesel::sql_query(format!(
+                "DELETE FROM {table} WHERE label_id IN (SELECT id FROM labels WHERE user_id = ?)",
+                table = target.join_table(),
+            ))
+            .bind::<Text, _>(user_id)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+        diesel::delete(labels::table.filter(labels::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Attaches a `labels` field to each serialized item and keeps the first
+    /// `limit` that pass `filter`.
+    pub async fn annotate<T: Serialize>(
+        &self,
+        user_id: &str,
+        target: LabelTarget,
+        items: Vec<T>,
+        item_id: impl Fn(&T) -> i32,
+        filter: &LabelFilter,
+        limit: usize,
+    ) -> Result<Vec<Value>> {
+        let ids = items.iter().map(&item_id).collect::<Vec<_>>();
+        let mut labels = self.labels_for(user_id, target, &ids).await?;
+        let mut annotated = Vec::new();
+        for item in items {
+            if annotated.len() >= limit {
+                break;
+            }
+            let names = labels.remove(&item_id(&item)).unwrap_or_default();
+            if !filter.matches(&names) {
+                continue;
+            }
+            let mut value = serde_json::to_value(&item)
+                .map_err(|e| ButterflyBotError::Serialization(e.to_string()))?;
+            if let Some(object) = value.as_object_mut() {
+                object.insert("labels".to_string(), Value::from(names));
+            }
+            annotated.push(value);
+        }
+        Ok(annotated)
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// How many rows a filtered list reads before applying the label filter, so
+/// `limit` still counts matching items.
+pub const FILTER_SCAN_LIMIT: usize = 1000;
+
+/// The row limit to request from a store for a list call.
+pub fn scan_limit(filter: &LabelFilter, limit: usize) -> usize {
+    if filter.is_empty() {
+        limit
+    } else {
+        limit.max(FILTER_SCAN_LIMIT)
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_labels_tables(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM labels LIMIT 1; SELECT 1 FROM plan_labels LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, LABELS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn labels_are_normalized_and_filtered() {
+        assert_eq!(
+            parse_labels(Some(&json!(["#Work", " errand ", "work", "", 3]))),
+            vec!["work".to_string(), "errand".to_string()]
+        );
+        assert_eq!(
+            parse_labels(Some(&json!("Deep  Focus, home"))),
+            vec!["deep focus".to_string(), "home".to_string()]
+        );
+
+        let filter = LabelFilter::from_args(&json!({
+            "labels_any": ["work", "home"],
+            "labels_all": "urgent"
+        }));
+        let labels = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert!(filter.matches(&labels(&["home", "urgent"])));
+        assert!(!filter.matches(&labels(&["home"])));
+        assert!(!filter.matches(&labels(&["errand", "urgent"])));
+        assert!(LabelFilter::default().matches(&[]));
+        assert_eq!(scan_limit(&LabelFilter::default(), 20), 20);
+        assert_eq!(scan_limit(&filter, 20), FILTER_SCAN_LIMIT);
+    }
+}
@@ -0,0 +1,8 @@
+diesel::table! {
+    labels (id) {
+        id -> Integer,
+        user_id -> Text,
+        name -> Text,
+        created_at -> BigInt,
+    }
+}
@@ -1,34 +1,83 @@
+pub mod approvals;
+pub mod attachments;
+pub mod audit;
+pub mod backup;
 pub mod brain;
+pub mod calendar;
+pub mod charts;
+pub mod cli;
 pub mod client;
+pub mod clock;
 pub mod config;
 pub mod config_store;
+pub mod consent;
 pub mod daemon;
+pub mod dashboard;
+pub mod date_phrases;
 pub mod db;
+pub mod digest;
 pub mod domains;
+pub mod email;
 pub mod error;
+pub mod external_items;
 pub mod factories;
+pub mod git;
 pub mod iced_ui;
 pub mod inbox_fsm;
+pub mod inbox_rules;
 pub mod inbox_state;
+pub mod inbox_sweep;
+pub mod insights;
 pub mod interfaces;
+pub mod labels;
+pub mod llm;
 pub mod logging;
+pub mod matrix;
+pub mod metrics;
+pub mod outbox;
+pub mod patch;
 pub mod planning;
 pub mod plugins;
+pub mod presentation;
+pub mod privacy_lock;
+pub mod projects;
+pub mod prompt_queue;
 pub mod providers;
+pub mod questions;
+pub mod redaction;
+pub mod refs;
 pub mod reminders;
+pub mod remote_storage;
+pub mod replication;
+pub mod roles;
+pub mod runner;
 pub mod runtime_paths;
 pub mod sandbox;
 pub mod scheduler;
+pub mod scheduling;
+pub mod schema_drift;
+pub mod search;
 pub mod security;
+pub mod service;
 pub mod services;
+pub mod sessions;
+pub mod shutdown;
+pub mod slash_commands;
+pub mod smart_lists;
 pub mod solana_rpc;
 pub mod tasks;
+pub mod templates;
+pub mod threads;
+pub mod timezones;
 pub mod todo;
 pub mod tools;
+pub mod trash;
 #[path = "ui_iced.rs"]
 pub mod ui;
 pub mod vault;
+pub mod voice;
 pub mod wakeup;
+pub mod wasm_build;
 pub mod wasm_bundle;
 
 pub type Result<T> = std::result::Result<T, error::ButterflyBotError>;
@@ -0,0 +1,390 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use futures::stream::BoxStream;
+use serde_json::{json, Value};
+
+use crate::error::{ButterflyBotError, Result};
+use crate::interfaces::providers::{
+    ChatEvent, ImageData, ImageInput, LlmProvider, LlmResponse, ToolCall,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+const API_VERSION: &str = "2023-06-01";
+
+/// Anthropic Messages API backend.
+#[derive(Clone)]
+pub struct AnthropicProvider {
+    api_key: String,
+    model: String,
+    base_url: String,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    client: reqwest::Client,
+}
+
+impl AnthropicProvider {
+    pub fn new(
+        api_key: String,
+        model: Option<String>,
+        base_url: Option<String>,
+        max_tokens: Option<u32>,
+    ) -> Self {
+        Self {
+            api_key,
+            model: model.unwrap_or_else(|| DEFAULT_MODEL.to_string()),
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            temperature: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    async fn create_message(
+        &self,
+        system_prompt: &str,
+        messages: Vec<Value>,
+        tools: Option<Vec<Value>>,
+    ) -> Result<Value> {
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": self.max_tokens,
+            "messages": messages,
+        });
+        if !system_prompt.trim().is_empty() {
+            body["system"] = json!(system_prompt);
+        }
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        let tools = tools.map(Self::convert_tools).unwrap_or_default();
+        if !tools.is_empty() {
+            body["tools"] = Value::Array(tools);
+        }
+
+        let url = format!("{}/messages", self.base_url.trim_end_matches('/'));
+        let response = self
+            .client
+            .post(url)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(format!("Anthropic transport failed: {e}")))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(format!("Anthropic read failed: {e}")))?;
+        if !status.is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "Anthropic request failed ({status}): {text}"
+            )));
+        }
+        serde_json::from_str(&text)
+            .map_err(|e| ButterflyBotError::Serialization(format!("Anthropic decode failed: {e}")))
+    }
+
+    /// Accepts the OpenAI-style tool definitions the agent builds and maps
+    /// them to Anthropic's `{name, description, input_schema}` shape.
+    fn convert_tools(tools: Vec<Value>) -> Vec<Value> {
+        tools
+            .into_iter()
+            .filter_map(|tool| {
+                let function = tool.get("function").cloned().unwrap_or(tool);
+                let name = function.get("name")?.as_str()?.trim().to_string();
+                if name.is_empty() {
+                    return None;
+                }
+                let input_schema = function
+                    .get("parameters")
+                    .cloned()
+                    .filter(|value| value.is_object())
+                    .unwrap_or_else(|| json!({"type": "object", "properties": {}}));
+                let mut converted = json!({"name": name, "input_schema": input_schema});
+                if let Some(description) = function.get("description").and_then(|v| v.as_str()) {
+                    converted["description"] = json!(description);
+                }
+                Some(converted)
+            })
+            .collect()
+    }
+
+    fn extract_text(response: &Value) -> String {
+        response
+            .get("content")
+            .and_then(|v| v.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|block| block.get("type").and_then(|v| v.as_str()) == Some("text"))
+                    .filter_map(|block| block.get("text").and_then(|v| v.as_str()))
+                    .collect::<Vec<_>>()
+                    .join("")
+            })
+            .unwrap_or_default()
+    }
+
+    fn extract_tool_calls(response: &Value) -> Vec<ToolCall> {
+        response
+            .get("content")
+            .and_then(|v| v.as_array())
+            .map(|blocks| {
+                blocks
+                    .iter()
+                    .filter(|block| block.get("type").and_then(|v| v.as_str()) == Some("tool_use"))
+                    .filter_map(|block| {
+                        Some(ToolCall {
+                            name: block.get("name")?.as_str()?.to_string(),
+                            arguments: block.get("input").cloned().unwrap_or(Value::Null),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn image_block(image: ImageInput) -> Value {
+        match image.data {
+            ImageData::Url(url) => json!({
+                "type": "image",
+                "source": {"type": "url", "url": url}
+            }),
+            ImageData::Bytes(bytes) => json!({
+                "type": "image",
+                "source": {
+                    "type": "base64",
+                    "media_type": Self::sniff_media_type(&bytes),
+                    "data": general_purpose::STANDARD.encode(&bytes)
+                }
+            }),
+        }
+    }
+
+    fn sniff_media_type(bytes: &[u8]) -> &'static str {
+        if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            "image/jpeg"
+        } else if bytes.starts_with(b"GIF8") {
+            "image/gif"
+        } else if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+            "image/webp"
+        } else {
+            "image/png"
+        }
+    }
+
+    /// Pulls the JSON document out of a reply, tolerating a Markdown fence.
+    fn parse_json_reply(text: &str) -> Result<Value> {
+        let trimmed = text.trim();
+        let unfenced = trimmed
+            .strip_prefix("```json")
+            .or_else(|| trimmed.strip_prefix("```"))
+            .and_then(|rest| rest.strip_suffix("```"))
+            .unwrap_or(trimmed);
+        serde_json::from_str(unfenced.trim())
+            .map_err(|e| ButterflyBotError::Serialization(e.to_string()))
+    }
+
+    fn unsupported(feature: &str) -> ButterflyBotError {
+        ButterflyBotError::Config(format!("Anthropic provider does not support {feature}"))
+    }
+}
+
+#[async_trait]
+impl LlmProvider for AnthropicProvider {
+    async fn generate_text(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        tools: Option<Vec<Value>>,
+    ) -> Result<String> {
+        let messages = vec![json!({"role": "user", "content": prompt})];
+        let response = self.create_message(system_prompt, messages, tools).await?;
+        let text = Self::extract_text(&response);
+        if text.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "Empty chat response".to_string(),
+            ));
+        }
+        Ok(text)
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        tools: Vec<Value>,
+    ) -> Result<LlmResponse> {
+        let messages = vec![json!({"role": "user", "content": prompt})];
+        let response = self
+            .create_message(system_prompt, messages, Some(tools))
+            .await?;
+        Ok(LlmResponse {
+            text: Self::extract_text(&response),
+            tool_calls: Self::extract_tool_calls(&response),
+        })
+    }
+
+    fn chat_stream(
+        &self,
+        messages: Vec<Value>,
+        tools: Option<Vec<Value>>,
+    ) -> BoxStream<'static, Result<ChatEvent>> {
+        let provider = self.clone();
+
+        Box::pin(try_stream! {
+            let mut system_parts = Vec::new();
+            let mut request_messages = Vec::new();
+            for message in messages {
+                let role = message.get("role").and_then(|v| v.as_str()).unwrap_or("user");
+                let content = message.get("content").and_then(|v| v.as_str()).unwrap_or("");
+                match role {
+                    "system" => system_parts.push(content.to_string()),
+                    "assistant" => {
+                        request_messages.push(json!({"role": "assistant", "content": content}))
+                    }
+                    _ => request_messages.push(json!({"role": "user", "content": content})),
+                }
+            }
+
+            let response = provider
+                .create_message(&system_parts.join("\n\n"), request_messages, tools)
+                .await?;
+            let content = AnthropicProvider::extract_text(&response);
+            if !content.is_empty() {
+                yield ChatEvent {
+                    event_type: "content".to_string(),
+                    delta: Some(content),
+                    name: None,
+                    arguments_delta: None,
+                    finish_reason: None,
+                    error: None,
+                };
+            }
+
+            yield ChatEvent {
+                event_type: "message_end".to_string(),
+                delta: None,
+                name: None,
+                arguments_delta: None,
+                finish_reason: response
+                    .get("stop_reason")
+                    .and_then(|v| v.as_str())
+                    .map(|reason| reason.to_string())
+                    .or_else(|| Some("stop".to_string())),
+                error: None,
+            };
+        })
+    }
+
+    async fn parse_structured_output(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        json_schema: Value,
+        tools: Option<Vec<Value>>,
+    ) -> Result<Value> {
+        // The Messages API has no response_format, so the schema travels in
+        // the prompt and the reply is parsed as JSON.
+        let structured_prompt = format!(
+            "{prompt}\n\nRespond ONLY with a JSON document matching this JSON Schema. Do not include markdown or extra text.\n{json_schema}"
+        );
+        let messages = vec![json!({"role": "user", "content": structured_prompt})];
+        let response = self.create_message(system_prompt, messages, tools).await?;
+        Self::parse_json_reply(&Self::extract_text(&response))
+    }
+
+    async fn tts(&self, _text: &str, _voice: &str, _response_format: &str) -> Result<Vec<u8>> {
+        Err(Self::unsupported("text-to-speech"))
+    }
+
+    async fn transcribe_audio(&self, _audio_bytes: Vec<u8>, _input_format: &str) -> Result<String> {
+        Err(Self::unsupported("audio transcription"))
+    }
+
+    async fn generate_text_with_images(
+        &self,
+        prompt: &str,
+        images: Vec<ImageInput>,
+        system_prompt: &str,
+        _detail: &str,
+        tools: Option<Vec<Value>>,
+    ) -> Result<String> {
+        let mut content: Vec<Value> = images.into_iter().map(Self::image_block).collect();
+        content.push(json!({"type": "text", "text": prompt}));
+        let messages = vec![json!({"role": "user", "content": content})];
+        let response = self.create_message(system_prompt, messages, tools).await?;
+        let text = Self::extract_text(&response);
+        if text.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "Empty chat response".to_string(),
+            ));
+        }
+        Ok(text)
+    }
+
+    async fn embed(&self, _inputs: Vec<String>, _model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        Err(Self::unsupported("embeddings"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_tools_maps_openai_functions_to_input_schema() {
+        let tools = AnthropicProvider::convert_tools(vec![
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "todo",
+                    "description": "Manage todos",
+                    "parameters": {"type": "object", "properties": {"action": {"type": "string"}}}
+                }
+            }),
+            json!({"type": "function", "name": "tasks"}),
+        ]);
+        assert_eq!(tools.len(), 2);
+        assert_eq!(tools[0]["name"], json!("todo"));
+        assert_eq!(tools[0]["description"], json!("Manage todos"));
+        assert_eq!(
+            tools[0]["input_schema"]["properties"]["action"]["type"],
+            json!("string")
+        );
+        assert_eq!(tools[1]["input_schema"]["type"], json!("object"));
+    }
+
+    #[test]
+    fn extracts_text_and_tool_use_blocks() {
+        let response = json!({
+            "content": [
+                {"type": "text", "text": "Adding that now."},
+                {"type": "tool_use", "id": "toolu_1", "name": "todo", "input": {"action": "create"}}
+            ],
+            "stop_reason": "tool_use"
+        });
+        assert_eq!(
+            AnthropicProvider::extract_text(&response),
+            "Adding that now."
+        );
+        let calls = AnthropicProvider::extract_tool_calls(&response);
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "todo");
+        assert_eq!(calls[0].arguments, json!({"action": "create"}));
+    }
+
+    #[test]
+    fn parses_fenced_json_replies() {
+        let value = AnthropicProvider::parse_json_reply("```json\n{\"ok\": true}\n```").unwrap();
+        assert_eq!(value, json!({"ok": true}));
+    }
+}
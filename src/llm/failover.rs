@@ -0,0 +1,588 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::broadcast;
+
+use crate::config::FailoverConfig;
+use crate::error::{ButterflyBotError, Result};
+use crate::interfaces::providers::{ChatEvent, ImageInput, LlmProvider, LlmResponse};
+use crate::services::agent::UiEvent;
+
+const DEFAULT_FAILURE_THRESHOLD: u32 = 3;
+const DEFAULT_COOLDOWN_SECONDS: u64 = 300;
+const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderHealth {
+    pub label: String,
+    pub primary: bool,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    /// While set and in the future the backend is skipped.
+    pub tripped_until: Option<i64>,
+    pub last_error: Option<String>,
+}
+
+/// Health of the chain built for the running agent, for diagnostics.
+/// Empty when no fallbacks are configured.
+pub fn health_snapshot() -> Vec<ProviderHealth> {
+    health_registry()
+        .lock()
+        .map(|health| health.clone())
+        .unwrap_or_default()
+}
+
+fn health_registry() -> &'static Mutex<Vec<ProviderHealth>> {
+    static HEALTH: OnceLock<Mutex<Vec<ProviderHealth>>> = OnceLock::new();
+    HEALTH.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+#[derive(Default)]
+struct BackendState {
+    consecutive_failures: u32,
+    tripped_until: Option<i64>,
+    last_error: Option<String>,
+}
+
+struct Backend {
+    label: String,
+    provider: Arc<dyn LlmProvider>,
+    state: Mutex<BackendState>,
+}
+
+/// Tries each backend in order. A backend that fails `failure_threshold`
+/// times in a row is skipped until its cooldown passes, so a flaky local
+/// model doesn't add a timeout to every request.
+pub struct FailoverProvider {
+    backends: Vec<Backend>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    timeout: Duration,
+    ui_event_tx: Option<broadcast::Sender<UiEvent>>,
+}
+
+impl FailoverProvider {
+    pub fn new(
+        chain: Vec<(String, Arc<dyn LlmProvider>)>,
+        config: FailoverConfig,
+        ui_event_tx: Option<broadcast::Sender<UiEvent>>,
+    ) -> Self {
+        let provider = Self {
+            backends: chain
+                .into_iter()
+                .map(|(label, provider)| Backend {
+                    label,
+                    provider,
+                    state: Mutex::new(BackendState::default()),
+                })
+                .collect(),
+            failure_threshold: config
+                .failure_threshold
+                .unwrap_or(DEFAULT_FAILURE_THRESHOLD)
+                .max(1),
+            cooldown: Duration::from_secs(
+                config.cooldown_seconds.unwrap_or(DEFAULT_COOLDOWN_SECONDS),
+            ),
+            timeout: Duration::from_secs(
+                config
+                    .timeout_seconds
+                    .unwrap_or(DEFAULT_TIMEOUT_SECONDS)
+                    .max(1),
+            ),
+            ui_event_tx,
+        };
+        provider.publish_health();
+        provider
+    }
+
+    pub fn health(&self) -> Vec<ProviderHealth> {
+        let now = now_ts();
+        self.backends
+            .iter()
+            .enumerate()
+            .map(|(index, backend)| {
+                let state = backend.state.lock().unwrap_or_else(|e| e.into_inner());
+                let tripped = state.tripped_until.is_some_and(|until| until > now);
+                ProviderHealth {
+                    label: backend.label.clone(),
+                    primary: index == 0,
+                    healthy: !tripped && state.consecutive_failures == 0,
+                    consecutive_failures: state.consecutive_failures,
+                    tripped_until: state.tripped_until.filter(|until| *until > now),
+                    last_error: state.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Backends in chain order, skipping tripped ones. If every backend is
+    /// tripped the whole chain is tried rather than failing outright.
+    fn candidates(&self) -> Vec<usize> {
+        let now = now_ts();
+        let available: Vec<usize> = self
+            .backends
+            .iter()
+            .enumerate()
+            .filter(|(_, backend)| {
+                backend
+                    .state
+                    .lock()
+                    .map(|state| state.tripped_until.is_none_or(|until| until <= now))
+                    .unwrap_or(true)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if available.is_empty() {
+            (0..self.backends.len()).collect()
+        } else {
+            available
+        }
+    }
+
+    async fn run<T, F, Fut>(&self, operation: &str, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LlmProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let candidates = self.candidates();
+        let mut last_error = None;
+        for (position, index) in candidates.iter().copied().enumerate() {
+            let backend = &self.backends[index];
+            let outcome =
+                match tokio::time::timeout(self.timeout, call(backend.provider.clone())).await {
+                    Ok(outcome) => outcome,
+                    Err(_) => Err(ButterflyBotError::Http(format!(
+                        "{} timed out after {}s",
+                        backend.label,
+                        self.timeout.as_secs()
+                    ))),
+                };
+            match outcome {
+                Ok(value) => {
+                    self.record_success(index, operation);
+                    return Ok(value);
+                }
+                Err(err) => {
+                    let next = candidates.get(position + 1).copied();
+                    self.record_failure(index, next, operation, &err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| ButterflyBotError::Runtime("No LLM backend configured".to_string())))
+    }
+
+    /// For capabilities some backends simply lack (speech, embeddings): use
+    /// the first backend that succeeds without touching failover state.
+    async fn first_supported<T, F, Fut>(&self, call: F) -> Result<T>
+    where
+        F: Fn(Arc<dyn LlmProvider>) -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_error = None;
+        for backend in &self.backends {
+            match call(backend.provider.clone()).await {
+                Ok(value) => return Ok(value),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error
+            .unwrap_or_else(|| ButterflyBotError::Runtime("No LLM backend configured".to_string())))
+    }
+
+    fn record_success(&self, index: usize, operation: &str) {
+        let backend = &self.backends[index];
+        let recovered = {
+            let mut state = backend.state.lock().unwrap_or_else(|e| e.into_inner());
+            let recovered = state.consecutive_failures > 0;
+            *state = BackendState::default();
+            recovered
+        };
+        if recovered {
+            self.emit(
+                "recovered",
+                json!({"backend": backend.label, "operation": operation}),
+            );
+            self.publish_health();
+        }
+    }
+
+    fn record_failure(
+        &self,
+        index: usize,
+        next: Option<usize>,
+        operation: &str,
+        err: &ButterflyBotError,
+    ) {
+        let backend = &self.backends[index];
+        let now = now_ts();
+        let tripped_until = {
+            let mut state = backend.state.lock().unwrap_or_else(|e| e.into_inner());
+            state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+            state.last_error = Some(err.to_string());
+            if state.consecutive_failures >= self.failure_threshold
+                && state.tripped_until.is_none_or(|until| until <= now)
+            {
+                let until = now + self.cooldown.as_secs() as i64;
+                state.tripped_until = Some(until);
+                Some(until)
+            } else {
+                None
+            }
+        };
+
+        if let Some(until) = tripped_until {
+            self.emit(
+                "tripped",
+                json!({
+                    "backend": backend.label,
+                    "operation": operation,
+                    "tripped_until": until,
+                    "error": err.to_string(),
+                }),
+            );
+        }
+        if let Some(next) = next {
+            self.emit(
+                "failover",
+                json!({
+                    "from": backend.label,
+                    "to": self.backends[next].label,
+                    "operation": operation,
+                    "error": err.to_string(),
+                }),
+            );
+        }
+        self.publish_health();
+    }
+
+    fn emit(&self, status: &str, payload: Value) {
+        let Some(sender) = &self.ui_event_tx else {
+            return;
+        };
+        let _ = sender.send(UiEvent {
+            event_type: "llm_failover".to_string(),
+            user_id: "system".to_string(),
+            tool: "llm".to_string(),
+            status: status.to_string(),
+            payload,
+            timestamp: now_ts(),
+        });
+    }
+
+    fn publish_health(&self) {
+        if let Ok(mut health) = health_registry().lock() {
+            *health = self.health();
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FailoverProvider {
+    async fn generate_text(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        tools: Option<Vec<Value>>,
+    ) -> Result<String> {
+        self.run("generate_text", |provider| {
+            let tools = tools.clone();
+            async move { provider.generate_text(prompt, system_prompt, tools).await }
+        })
+        .await
+    }
+
+    async fn generate_with_tools(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        tools: Vec<Value>,
+    ) -> Result<LlmResponse> {
+        self.run("generate_with_tools", |provider| {
+            let tools = tools.clone();
+            async move {
+                provider
+                    .generate_with_tools(prompt, system_prompt, tools)
+                    .await
+            }
+        })
+        .await
+    }
+
+    fn chat_stream(
+        &self,
+        messages: Vec<Value>,
+        tools: Option<Vec<Value>>,
+    ) -> BoxStream<'static, Result<ChatEvent>> {
+        // Failover only happens before the first event; once a backend has
+        // started answering, switching mid-reply would garble the output.
+        let backends: Vec<(String, Arc<dyn LlmProvider>)> = self
+            .candidates()
+            .into_iter()
+            .map(|index| {
+                let backend = &self.backends[index];
+                (backend.label.clone(), backend.provider.clone())
+            })
+            .collect();
+        let timeout = self.timeout;
+        Box::pin(async_stream::stream! {
+            let mut last_error = None;
+            for (label, provider) in backends {
+                let mut stream = provider.chat_stream(messages.clone(), tools.clone());
+                let first = match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(first) => first,
+                    Err(_) => Some(Err(ButterflyBotError::Http(format!(
+                        "{label} timed out after {}s",
+                        timeout.as_secs()
+                    )))),
+                };
+                match first {
+                    Some(Err(err)) => {
+                        last_error = Some(err);
+                        continue;
+                    }
+                    Some(Ok(event)) => yield Ok(event),
+                    None => {}
+                }
+                while let Some(event) = stream.next().await {
+                    yield event;
+                }
+                return;
+            }
+            if let Some(err) = last_error {
+                yield Err(err);
+            }
+        })
+    }
+
+    async fn parse_structured_output(
+        &self,
+        prompt: &str,
+        system_prompt: &str,
+        json_schema: Value,
+        tools: Option<Vec<Value>>,
+    ) -> Result<Value> {
+        self.run("parse_structured_output", |provider| {
+            let json_schema = json_schema.clone();
+            let tools = tools.clone();
+            async move {
+                provider
+                    .parse_structured_output(prompt, system_prompt, json_schema, tools)
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn tts(&self, text: &str, voice: &str, response_format: &str) -> Result<Vec<u8>> {
+        self.first_supported(
+            |provider| async move { provider.tts(text, voice, response_format).await },
+        )
+        .await
+    }
+
+    async fn transcribe_audio(&self, audio_bytes: Vec<u8>, input_format: &str) -> Result<String> {
+        self.first_supported(|provider| {
+            let audio_bytes = audio_bytes.clone();
+            async move { provider.transcribe_audio(audio_bytes, input_format).await }
+        })
+        .await
+    }
+
+    async fn generate_text_with_images(
+        &self,
+        prompt: &str,
+        images: Vec<ImageInput>,
+        system_prompt: &str,
+        detail: &str,
+        tools: Option<Vec<Value>>,
+    ) -> Result<String> {
+        self.run("generate_text_with_images", |provider| {
+            let images = images.clone();
+            let tools = tools.clone();
+            async move {
+                provider
+                    .generate_text_with_images(prompt, images, system_prompt, detail, tools)
+                    .await
+            }
+        })
+        .await
+    }
+
+    async fn embed(&self, inputs: Vec<String>, model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+        self.first_supported(|provider| {
+            let inputs = inputs.clone();
+            async move { provider.embed(inputs, model).await }
+        })
+        .await
+    }
+}
+
+fn now_ts() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct ScriptedProvider {
+        reply: Option<&'static str>,
+        calls: AtomicUsize,
+    }
+
+    impl ScriptedProvider {
+        fn new(reply: Option<&'static str>) -> Arc<Self> {
+            Arc::new(Self {
+                reply,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for ScriptedProvider {
+        async fn generate_text(
+            &self,
+            _prompt: &str,
+            _system_prompt: &str,
+            _tools: Option<Vec<Value>>,
+        ) -> Result<String> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.reply
+                .map(str::to_string)
+                .ok_or_else(|| ButterflyBotError::Http("connection refused".to_string()))
+        }
+
+        async fn generate_with_tools(
+            &self,
+            prompt: &str,
+            system_prompt: &str,
+            _tools: Vec<Value>,
+        ) -> Result<LlmResponse> {
+            let text = self.generate_text(prompt, system_prompt, None).await?;
+            Ok(LlmResponse {
+                text,
+                tool_calls: Vec::new(),
+            })
+        }
+
+        fn chat_stream(
+            &self,
+            _messages: Vec<Value>,
+            _tools: Option<Vec<Value>>,
+        ) -> BoxStream<'static, Result<ChatEvent>> {
+            Box::pin(futures::stream::empty())
+        }
+
+        async fn parse_structured_output(
+            &self,
+            _prompt: &str,
+            _system_prompt: &str,
+            _json_schema: Value,
+            _tools: Option<Vec<Value>>,
+        ) -> Result<Value> {
+            Ok(Value::Null)
+        }
+
+        async fn tts(&self, _text: &str, _voice: &str, _format: &str) -> Result<Vec<u8>> {
+            Ok(Vec::new())
+        }
+
+        async fn transcribe_audio(&self, _audio: Vec<u8>, _format: &str) -> Result<String> {
+            Ok(String::new())
+        }
+
+        async fn generate_text_with_images(
+            &self,
+            prompt: &str,
+            _images: Vec<ImageInput>,
+            system_prompt: &str,
+            _detail: &str,
+            _tools: Option<Vec<Value>>,
+        ) -> Result<String> {
+            self.generate_text(prompt, system_prompt, None).await
+        }
+
+        async fn embed(&self, _inputs: Vec<String>, _model: Option<&str>) -> Result<Vec<Vec<f32>>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_and_skips_tripped_primary() {
+        let primary = ScriptedProvider::new(None);
+        let secondary = ScriptedProvider::new(Some("from secondary"));
+        let (tx, mut rx) = broadcast::channel(16);
+        let failover = FailoverProvider::new(
+            vec![
+                (
+                    "ollama:llama3.1".to_string(),
+                    primary.clone() as Arc<dyn LlmProvider>,
+                ),
+                (
+                    "openai:gpt-4.1-mini".to_string(),
+                    secondary.clone() as Arc<dyn LlmProvider>,
+                ),
+            ],
+            FailoverConfig {
+                failure_threshold: Some(2),
+                cooldown_seconds: Some(600),
+                timeout_seconds: Some(5),
+            },
+            Some(tx),
+        );
+
+        for _ in 0..3 {
+            let reply = failover.generate_text("hi", "", None).await.unwrap();
+            assert_eq!(reply, "from secondary");
+        }
+        // Two failures trip the primary; the third call goes straight to
+        // the secondary.
+        assert_eq!(primary.calls.load(Ordering::SeqCst), 2);
+        assert_eq!(secondary.calls.load(Ordering::SeqCst), 3);
+
+        let health = failover.health();
+        assert!(!health[0].healthy);
+        assert!(health[0].tripped_until.is_some());
+        assert!(health[1].healthy);
+
+        let mut statuses = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            assert_eq!(event.event_type, "llm_failover");
+            statuses.push(event.status);
+        }
+        assert_eq!(statuses, vec!["failover", "tripped", "failover"]);
+    }
+
+    #[tokio::test]
+    async fn surfaces_last_error_when_every_backend_fails() {
+        let failover = FailoverProvider::new(
+            vec![
+                (
+                    "a".to_string(),
+                    ScriptedProvider::new(None) as Arc<dyn LlmProvider>,
+                ),
+                (
+                    "b".to_string(),
+                    ScriptedProvider::new(None) as Arc<dyn LlmProvider>,
+                ),
+            ],
+            FailoverConfig::default(),
+            None,
+        );
+        let err = failover.generate_text("hi", "", None).await.unwrap_err();
+        assert!(err.to_string().contains("connection refused"));
+    }
+}
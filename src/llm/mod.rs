@@ -0,0 +1,361 @@
+//! Chat backend selection.
+//!
+//! Every backend implements [`Provider`]; `build_provider` picks one from the
+//! `llm` config section, falling back to the legacy `openai` section when
+//! `llm` is absent. Configured `llm.fallbacks` wrap the chain in a
+//! [`FailoverProvider`].
+
+pub mod anthropic;
+pub mod failover;
+
+use std::sync::Arc;
+
+use tokio::sync::broadcast;
+
+use crate::config::{Config, LlmConfig, LlmProviderKind, OpenAiConfig};
+use crate::error::{ButterflyBotError, Result};
+use crate::providers::openai::OpenAiProvider;
+use crate::services::agent::UiEvent;
+
+pub use crate::interfaces::providers::LlmProvider as Provider;
+pub use anthropic::AnthropicProvider;
+pub use failover::{FailoverProvider, ProviderHealth};
+
+const OLLAMA_BASE_URL: &str = "http://localhost:11434/v1";
+const OLLAMA_DEFAULT_MODEL: &str = "llama3.1";
+
+/// Credentials for an OpenAI-compatible endpoint: key, model and base URL.
+pub type OpenAiCredentials = (String, Option<String>, Option<String>);
+
+pub fn provider_kind(config: &Config) -> LlmProviderKind {
+    config
+        .llm
+        .as_ref()
+        .map(|llm| llm.provider)
+        .unwrap_or_default()
+}
+
+pub fn build_provider(config: &Config) -> Result<Arc<dyn Provider>> {
+    build_provider_with_events(config, None)
+}
+
+pub fn build_provider_with_events(
+    config: &Config,
+    ui_event_tx: Option<broadcast::Sender<UiEvent>>,
+) -> Result<Arc<dyn Provider>> {
+    let llm = config.llm.clone().unwrap_or_default();
+    let openai = config.openai.as_ref();
+    let primary = build_backend(&llm, config.llm.is_some(), openai, None)?;
+    if llm.fallbacks.is_empty() {
+        return Ok(primary);
+    }
+
+    let mut chain = vec![(backend_label(&llm, openai), primary)];
+    for fallback in &llm.fallbacks {
+        chain.push((
+            backend_label(fallback, openai),
+            build_backend(fallback, true, openai, None)?,
+        ));
+    }
+    Ok(Arc::new(FailoverProvider::new(
+        chain,
+        llm.failover.unwrap_or_default(),
+        ui_event_tx,
+    )))
+}
+
+/// Chat backend for a conversation thread pinned to its own provider, model
+/// or temperature. Credentials come from the primary backend when the kind
+/// matches, otherwise from the first fallback of that kind. Pinned backends
+/// never fail over: the user asked for that model specifically.
+pub fn build_pinned_provider(
+    config: &Config,
+    provider: Option<LlmProviderKind>,
+    model: Option<String>,
+    temperature: Option<f32>,
+) -> Result<Arc<dyn Provider>> {
+    let llm = config.llm.clone().unwrap_or_default();
+    let kind = provider.unwrap_or(llm.provider);
+    let (mut pinned, explicit) = if kind == llm.provider {
+        (llm.clone(), config.llm.is_some())
+    } else {
+        let fallback = llm
+            .fallbacks
+            .iter()
+            .find(|fallback| fallback.provider == kind)
+            .cloned()
+            .unwrap_or(LlmConfig {
+                provider: kind,
+                ..LlmConfig::default()
+            });
+        (fallback, true)
+    };
+    pinned.fallbacks.clear();
+    if model.is_some() {
+        pinned.model = model;
+    }
+    build_backend(&pinned, explicit, config.openai.as_ref(), temperature)
+}
+
+/// Credentials for the OpenAI-compatible endpoint behind the chat backend,
+/// or `None` when the backend is not OpenAI-compatible and no `openai`
+/// section is configured. Memory embeddings, reranking and summaries use
+/// these regardless of the chat backend.
+pub fn openai_compatible_credentials(config: &Config) -> Result<Option<OpenAiCredentials>> {
+    let llm = config.llm.clone().unwrap_or_default();
+    backend_credentials(&llm, config.llm.is_some(), config.openai.as_ref())
+}
+
+/// `provider:model` of the primary backend, for display.
+pub fn primary_label(config: &Config) -> String {
+    let llm = config.llm.clone().unwrap_or_default();
+    backend_label(&llm, config.openai.as_ref())
+}
+
+/// Every chat backend a prompt may reach, primary first, with whether it
+/// runs on this machine. A backend counts as local only when its base URL
+/// points at a loopback host.
+pub fn chat_backends(config: &Config) -> Vec<(LlmProviderKind, bool)> {
+    let llm = config.llm.clone().unwrap_or_default();
+    let openai = config.openai.as_ref();
+    std::iter::once(&llm)
+        .chain(llm.fallbacks.iter())
+        .map(|backend| (backend.provider, is_local_backend(backend, openai)))
+        .collect()
+}
+
+fn is_local_backend(llm: &LlmConfig, openai: Option<&OpenAiConfig>) -> bool {
+    let base_url = match llm.provider {
+        LlmProviderKind::Openai => non_empty(llm.base_url.clone())
+            .or_else(|| openai.and_then(|cfg| non_empty(cfg.base_url.clone()))),
+        LlmProviderKind::Ollama => ollama_credentials(llm).2,
+        LlmProviderKind::Anthropic => non_empty(llm.base_url.clone()),
+    };
+    let Some(url) = base_url.and_then(|url| reqwest::Url::parse(&url).ok()) else {
+        return false;
+    };
+    let host = url.host_str().unwrap_or_default();
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .trim_matches(['[', ']'])
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+fn build_backend(
+    llm: &LlmConfig,
+    explicit: bool,
+    openai: Option<&OpenAiConfig>,
+    temperature: Option<f32>,
+) -> Result<Arc<dyn Provider>> {
+    match llm.provider {
+        LlmProviderKind::Openai | LlmProviderKind::Ollama => {
+            let (api_key, model, base_url) = backend_credentials(llm, explicit, openai)?
+                .ok_or_else(|| {
+                    ButterflyBotError::Config("Missing openai configuration".to_string())
+                })?;
+            Ok(Arc::new(
+                OpenAiProvider::new(api_key, model, base_url).with_temperature(temperature),
+            ))
+        }
+        LlmProviderKind::Anthropic => {
+            let api_key = non_empty(llm.api_key.clone()).ok_or_else(|| {
+                ButterflyBotError::Config("Missing Anthropic API key".to_string())
+            })?;
+            Ok(Arc::new(
+                AnthropicProvider::new(
+                    api_key,
+                    llm.model.clone(),
+                    llm.base_url.clone(),
+                    llm.max_tokens,
+                )
+                .with_temperature(temperature),
+            ))
+        }
+    }
+}
+
+fn backend_credentials(
+    llm: &LlmConfig,
+    explicit: bool,
+    openai: Option<&OpenAiConfig>,
+) -> Result<Option<OpenAiCredentials>> {
+    match llm.provider {
+        LlmProviderKind::Openai => {
+            if !explicit && openai.is_none() {
+                return Ok(None);
+            }
+            let api_key = non_empty(llm.api_key.clone())
+                .or_else(|| openai.and_then(|cfg| non_empty(cfg.api_key.clone())))
+                .ok_or_else(|| ButterflyBotError::Config("Missing OpenAI API key".to_string()))?;
+            Ok(Some((
+                api_key,
+                llm.model
+                    .clone()
+                    .or_else(|| openai.and_then(|cfg| cfg.model.clone())),
+                llm.base_url
+                    .clone()
+                    .or_else(|| openai.and_then(|cfg| cfg.base_url.clone())),
+            )))
+        }
+        LlmProviderKind::Ollama => Ok(Some(ollama_credentials(llm))),
+        LlmProviderKind::Anthropic => Ok(openai.and_then(|cfg| {
+            let api_key = non_empty(cfg.api_key.clone())?;
+            Some((api_key, cfg.model.clone(), cfg.base_url.clone()))
+        })),
+    }
+}
+
+fn backend_label(llm: &LlmConfig, openai: Option<&OpenAiConfig>) -> String {
+    let model = match llm.provider {
+        LlmProviderKind::Openai => llm
+            .model
+            .clone()
+            .or_else(|| openai.and_then(|cfg| cfg.model.clone())),
+        LlmProviderKind::Ollama => Some(
+            llm.model
+                .clone()
+                .unwrap_or_else(|| OLLAMA_DEFAULT_MODEL.to_string()),
+        ),
+        LlmProviderKind::Anthropic => llm.model.clone(),
+    };
+    match model {
+        Some(model) => format!("{}:{model}", llm.provider.as_str()),
+        None => llm.provider.as_str().to_string(),
+    }
+}
+
+fn ollama_credentials(llm: &LlmConfig) -> OpenAiCredentials {
+    // Ollama ignores the key, but the OpenAI client insists on one.
+    (
+        non_empty(llm.api_key.clone()).unwrap_or_else(|| "ollama".to_string()),
+        Some(
+            llm.model
+                .clone()
+                .unwrap_or_else(|| OLLAMA_DEFAULT_MODEL.to_string()),
+        ),
+        Some(
+            llm.base_url
+                .clone()
+                .unwrap_or_else(|| OLLAMA_BASE_URL.to_string()),
+        ),
+    )
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OpenAiConfig;
+
+    fn config_with(llm: Option<LlmConfig>, openai: Option<OpenAiConfig>) -> Config {
+        let mut config = Config::convention_defaults(":memory:");
+        config.llm = llm;
+        config.openai = openai;
+        config
+    }
+
+    #[test]
+    fn legacy_openai_section_still_selects_openai() {
+        let config = config_with(
+            None,
+            Some(OpenAiConfig {
+                api_key: Some("sk-test".to_string()),
+                model: Some("gpt-4.1-mini".to_string()),
+                base_url: None,
+            }),
+        );
+        assert_eq!(provider_kind(&config), LlmProviderKind::Openai);
+        let (key, model, _) = openai_compatible_credentials(&config).unwrap().unwrap();
+        assert_eq!(key, "sk-test");
+        assert_eq!(model.as_deref(), Some("gpt-4.1-mini"));
+        assert!(build_provider(&config).is_ok());
+    }
+
+    #[test]
+    fn ollama_needs_no_key_and_defaults_to_localhost() {
+        let config = config_with(
+            Some(LlmConfig {
+                provider: LlmProviderKind::Ollama,
+                ..LlmConfig::default()
+            }),
+            None,
+        );
+        let (_, model, base_url) = openai_compatible_credentials(&config).unwrap().unwrap();
+        assert_eq!(model.as_deref(), Some(OLLAMA_DEFAULT_MODEL));
+        assert_eq!(base_url.as_deref(), Some(OLLAMA_BASE_URL));
+    }
+
+    #[test]
+    fn only_loopback_backends_count_as_local() {
+        let config = config_with(
+            Some(LlmConfig {
+                provider: LlmProviderKind::Ollama,
+                fallbacks: vec![
+                    LlmConfig {
+                        provider: LlmProviderKind::Openai,
+                        base_url: Some("http://127.0.0.1:8080/v1".to_string()),
+                        ..LlmConfig::default()
+                    },
+                    LlmConfig {
+                        provider: LlmProviderKind::Ollama,
+                        base_url: Some("http://gpu-box.lan:11434/v1".to_string()),
+                        ..LlmConfig::default()
+                    },
+                    LlmConfig {
+                        provider: LlmProviderKind::Anthropic,
+                        ..LlmConfig::default()
+                    },
+                ],
+                ..LlmConfig::default()
+            }),
+            None,
+        );
+        assert_eq!(
+            chat_backends(&config),
+            vec![
+                (LlmProviderKind::Ollama, true),
+                (LlmProviderKind::Openai, true),
+                (LlmProviderKind::Ollama, false),
+                (LlmProviderKind::Anthropic, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn anthropic_requires_key_and_leaves_memory_on_openai() {
+        let missing_key = config_with(
+            Some(LlmConfig {
+                provider: LlmProviderKind::Anthropic,
+                ..LlmConfig::default()
+            }),
+            None,
+        );
+        assert!(matches!(
+            build_provider(&missing_key),
+            Err(ButterflyBotError::Config(_))
+        ));
+        assert!(openai_compatible_credentials(&missing_key)
+            .unwrap()
+            .is_none());
+
+        let config = config_with(
+            Some(LlmConfig {
+                provider: LlmProviderKind::Anthropic,
+                api_key: Some("sk-ant".to_string()),
+                ..LlmConfig::default()
+            }),
+            Some(OpenAiConfig {
+                api_key: Some("sk-openai".to_string()),
+                model: None,
+                base_url: None,
+            }),
+        );
+        assert!(build_provider(&config).is_ok());
+        let (key, _, _) = openai_compatible_credentials(&config).unwrap().unwrap();
+        assert_eq!(key, "sk-openai");
+    }
+}
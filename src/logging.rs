@@ -1,6 +1,37 @@
+use std::io::Write;
+
 use tracing_subscriber::fmt::format::FmtSpan;
 use tracing_subscriber::EnvFilter;
 
+/// Buffers one formatted event and writes it to stdout with secrets masked
+/// (see [`crate::redaction`]).
+#[derive(Default)]
+struct RedactingWriter {
+    buf: Vec<u8>,
+}
+
+impl Write for RedactingWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        self.buf.extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let line = String::from_utf8_lossy(&self.buf).into_owned();
+        self.buf.clear();
+        std::io::stdout().write_all(crate::redaction::redact_text(&line).as_bytes())
+    }
+}
+
+impl Drop for RedactingWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
 pub fn init_tracing(component: &str) {
     let default_filter = format!("info,butterfly_bot=debug,{component}=debug");
 
@@ -12,6 +43,7 @@ pub fn init_tracing(component: &str) {
 
     let _ = tracing_subscriber::fmt()
         .with_env_filter(filter)
+        .with_writer(RedactingWriter::default)
         .with_target(true)
         .with_file(true)
         .with_line_number(true)
@@ -1,5 +1,5 @@
 #[cfg(not(test))]
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[cfg(not(test))]
 use butterfly_bot::config::Config;
@@ -23,6 +23,92 @@ struct Cli {
 
     #[arg(long, default_value = "user")]
     user_id: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[cfg(not(test))]
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Chat with the agent from the terminal through the running daemon.
+    Chat,
+    /// Query the agent's memory through the running daemon.
+    Memory {
+        #[command(subcommand)]
+        command: MemoryCommand,
+    },
+    /// Replace the database with a backup snapshot. A running daemon is
+    /// stopped first and started again afterwards.
+    Restore {
+        /// Snapshot file, snapshot name in the backups directory, `latest`,
+        /// or `replica` to rebuild the newest replicated generation.
+        #[arg(long)]
+        from: String,
+    },
+    /// Run the daemon as a systemd user unit (Linux) or launchd agent (macOS).
+    Service {
+        #[command(subcommand)]
+        command: ServiceCommand,
+    },
+    /// Developer tasks for the WASM tool modules.
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
+}
+
+#[cfg(not(test))]
+#[derive(Subcommand, Debug)]
+enum ServiceCommand {
+    /// Write the unit or plist for this install and enable it at login.
+    Install,
+    Start,
+    Stop,
+    Status,
+}
+
+#[cfg(not(test))]
+#[derive(Subcommand, Debug)]
+enum ToolsCommand {
+    /// Compile each feature-gated module in wasm-tool/, check it, and install
+    /// it with a hash manifest.
+    Build {
+        /// Only build these tools (repeatable); defaults to all of them.
+        #[arg(long = "tool")]
+        tools: Vec<String>,
+
+        /// Checkout root containing wasm-tool/.
+        #[arg(long, default_value = ".")]
+        repo: String,
+
+        /// Install directory; defaults to <repo>/wasm.
+        #[arg(long)]
+        out: Option<String>,
+    },
+}
+
+#[cfg(not(test))]
+#[derive(Subcommand, Debug)]
+enum MemoryCommand {
+    /// Semantic search over stored memories.
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+
+        /// Reorder results with the configured rerank model.
+        #[arg(long)]
+        rerank: bool,
+    },
+    /// Report what the history retention policy would summarize and delete.
+    Retention {
+        /// Run the pass for real instead of reporting a dry run.
+        #[arg(long)]
+        apply: bool,
+    },
 }
 
 #[cfg(not(test))]
@@ -38,6 +124,10 @@ fn main() -> Result<()> {
         std::env::set_var("BUTTERFLY_BOT_TOKEN", token);
     }
 
+    if let Some(command) = cli.command {
+        return run_command(&cli.daemon, &cli.db, &cli.user_id, command);
+    }
+
     ensure_default_config(&cli.db)?;
     butterfly_bot::iced_ui::launch_ui(butterfly_bot::iced_ui::IcedUiLaunchConfig {
         daemon_url: cli.daemon,
@@ -48,6 +138,122 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+#[cfg(not(test))]
+fn run_command(daemon_url: &str, db_path: &str, user_id: &str, command: Command) -> Result<()> {
+    if let Command::Tools {
+        command: ToolsCommand::Build { tools, repo, out },
+    } = command
+    {
+        let mut options = butterfly_bot::wasm_build::BuildOptions::for_repo(repo);
+        if let Some(out) = out {
+            options.out_dir = out.into();
+        }
+        options.tools = tools;
+        let manifest = butterfly_bot::wasm_build::build_tools(&options)?;
+        println!(
+            "{}",
+            butterfly_bot::wasm_build::format_manifest(&manifest, &options.out_dir)
+        );
+        return Ok(());
+    }
+    if let Command::Service { command } = command {
+        use butterfly_bot::service;
+        let manager = service::ServiceManager::detect()?;
+        let report = match command {
+            ServiceCommand::Install => {
+                let spec = service::ServiceSpec::for_current_install(daemon_url, db_path)?;
+                service::install(manager, &spec)?
+            }
+            ServiceCommand::Start => service::start(manager)?,
+            ServiceCommand::Stop => service::stop(manager)?,
+            ServiceCommand::Status => service::status(manager)?,
+        };
+        println!("{report}");
+        return Ok(());
+    }
+
+    let client = butterfly_bot::cli::DaemonClient::new(
+        daemon_url,
+        std::env::var("BUTTERFLY_BOT_TOKEN").ok(),
+    );
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| butterfly_bot::ButterflyBotError::Runtime(e.to_string()))?;
+    match command {
+        Command::Chat => butterfly_bot::cli::run_chat(&runtime, &client, user_id)?,
+        Command::Memory {
+            command:
+                MemoryCommand::Search {
+                    query,
+                    limit,
+                    rerank,
+                },
+        } => {
+            let hits =
+                runtime.block_on(client.memory_search(user_id, &query.join(" "), limit, rerank))?;
+            println!("{}", butterfly_bot::cli::format_memory_hits(&hits));
+        }
+        Command::Memory {
+            command: MemoryCommand::Retention { apply },
+        } => {
+            let report = runtime.block_on(client.memory_retention(user_id, !apply))?;
+            println!("{}", butterfly_bot::cli::format_retention_report(&report));
+        }
+        Command::Restore { from } => {
+            println!("{}", restore(&runtime, &client, db_path, &from)?);
+        }
+        Command::Tools { .. } | Command::Service { .. } => {
+            unreachable!("handled before connecting to the daemon")
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(test))]
+fn restore(
+    runtime: &tokio::runtime::Runtime,
+    client: &butterfly_bot::cli::DaemonClient,
+    db_path: &str,
+    from: &str,
+) -> Result<String> {
+    use butterfly_bot::{backup, replication, service};
+
+    let tools = Config::load(db_path).ok().and_then(|config| config.tools);
+    let dir = backup::BackupConfig::from_tools(tools.as_ref()).directory_for(db_path);
+    let snapshot = if from.trim() == "replica" {
+        let config = replication::ReplicationConfig::from_tools(tools.as_ref());
+        let target = replication::ReplicaTarget::from_tools(&config, tools.as_ref())?;
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| butterfly_bot::ButterflyBotError::Runtime(e.to_string()))?;
+        let rebuilt = dir.join("replica.db");
+        runtime.block_on(replication::materialize(&target, None, &rebuilt))?;
+        rebuilt
+    } else {
+        backup::resolve_snapshot(&dir, from)?
+    };
+    backup::validate_snapshot(&snapshot)?;
+
+    let was_running = runtime.block_on(client.shutdown())?;
+    let report = backup::restore(db_path, &snapshot, chrono::Utc::now().timestamp())?;
+    let mut lines = vec![format!(
+        "Restored {} from {}",
+        db_path,
+        report.snapshot.display()
+    )];
+    if let Some(previous) = &report.previous {
+        lines.push(format!("Previous database kept at {}", previous.display()));
+    }
+    if was_running {
+        let managed = service::ServiceManager::detect()
+            .ok()
+            .filter(|manager| manager.definition_path().is_ok_and(|path| path.exists()));
+        match managed {
+            Some(manager) => lines.push(service::start(manager)?),
+            None => lines.push("Start the app again to bring the daemon back".to_string()),
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
 #[cfg(not(test))]
 fn ensure_default_config(db_path: &str) -> Result<Config> {
     match Config::from_store(db_path) {
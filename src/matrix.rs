@@ -0,0 +1,558 @@
+//! Matrix as a chat channel, for people who would rather not route their
+//! assistant through a centralized messenger.
+//!
+//! Each configured room belongs to one user. Exchanges with the agent and
+//! proactive messages (digests, reminder nudges) are mirrored into it, and
+//! messages a listed sender writes there are answered like chat. Configured
+//! under `tools.settings.matrix`:
+//!
+//! ```json
+//! {"homeserver": "http://localhost:8009",
+//!  "access_token_secret": "matrix_access_token",
+//!  "rooms": [{"user_id": "alice", "room_id": "!abc:example.org",
+//!             "senders": ["@alice:example.org"]}],
+//!  "require_encryption": true, "poll_seconds": 10}
+//! ```
+//!
+//! The bot speaks the plain client-server API; for end-to-end encrypted
+//! rooms point `homeserver` at a local pantalaimon proxy, which encrypts and
+//! decrypts on its behalf. With `require_encryption` (the default) nothing
+//! is sent to or read from a room that has encryption turned off.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::{Method, StatusCode, Url};
+use serde_json::{json, Value};
+
+use crate::error::{ButterflyBotError, Result};
+
+const DEFAULT_TOKEN_SECRET: &str = "matrix_access_token";
+const DEFAULT_POLL_SECONDS: u64 = 10;
+const MAX_MESSAGE_CHARS: usize = 16_000;
+
+static TXN_SEQ: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    /// Set while answering a message that came in over Matrix, so the
+    /// question isn't echoed back into the room it came from.
+    static FROM_MATRIX: ();
+}
+
+/// Runs `fut` as the answer to a message received over Matrix.
+pub async fn from_matrix<F: Future>(fut: F) -> F::Output {
+    FROM_MATRIX.scope((), fut).await
+}
+
+fn answering_matrix() -> bool {
+    FROM_MATRIX.try_with(|_| ()).is_ok()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatrixRoom {
+    pub user_id: String,
+    pub room_id: String,
+    /// Matrix IDs whose messages in the room are treated as the user's.
+    pub senders: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatrixConfig {
+    pub homeserver: String,
+    /// Vault entry holding the bot account's access token.
+    pub access_token_secret: String,
+    pub rooms: Vec<MatrixRoom>,
+    pub require_encryption: bool,
+    pub poll_seconds: u64,
+}
+
+impl MatrixConfig {
+    /// `None` without a homeserver or a usable room.
+    pub fn from_tools(tools: Option<&Value>) -> Option<Self> {
+        let section = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("matrix"))
+            .filter(|section| section.get("enabled").and_then(Value::as_bool) != Some(false))?;
+        let rooms: Vec<MatrixRoom> = section
+            .get("rooms")
+            .and_then(Value::as_array)
+            .map(|rooms| rooms.iter().filter_map(parse_room).collect())
+            .unwrap_or_default();
+        if rooms.is_empty() {
+            return None;
+        }
+        Some(Self {
+            homeserver: text(section, "homeserver")?
+                .trim_end_matches('/')
+                .to_string(),
+            access_token_secret: text(section, "access_token_secret")
+                .unwrap_or_else(|| DEFAULT_TOKEN_SECRET.to_string()),
+            rooms,
+            require_encryption: section
+                .get("require_encryption")
+                .and_then(Value::as_bool)
+                .unwrap_or(true),
+            poll_seconds: section
+                .get("poll_seconds")
+                .and_then(Value::as_u64)
+                .filter(|seconds| *seconds > 0)
+                .unwrap_or(DEFAULT_POLL_SECONDS),
+        })
+    }
+
+    pub fn room_for(&self, user_id: &str) -> Option<&MatrixRoom> {
+        self.rooms.iter().find(|room| room.user_id == user_id)
+    }
+
+    /// The room's owner, when `sender` may speak for them there.
+    pub fn user_for(&self, room_id: &str, sender: &str) -> Option<&str> {
+        self.rooms
+            .iter()
+            .find(|room| room.room_id == room_id && room.senders.iter().any(|s| s == sender))
+            .map(|room| room.user_id.as_str())
+    }
+
+    /// The client, with the access token read from the vault.
+    pub fn client(&self) -> Result<MatrixClient> {
+        let token = crate::vault::get_secret(&self.access_token_secret)?
+            .filter(|token| !token.trim().is_empty())
+            .ok_or_else(|| {
+                ButterflyBotError::Config(format!(
+                    "Vault has no secret named {}",
+                    self.access_token_secret
+                ))
+            })?;
+        MatrixClient::new(&self.homeserver, token.trim())
+    }
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn parse_room(value: &Value) -> Option<MatrixRoom> {
+    Some(MatrixRoom {
+        user_id: text(value, "user_id")?,
+        room_id: text(value, "room_id")?,
+        senders: value
+            .get("senders")
+            .and_then(Value::as_array)
+            .map(|senders| {
+                senders
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::trim)
+                    .filter(|sender| !sender.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+    })
+}
+
+/// A text message someone posted in a room.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InboundMessage {
+    pub room_id: String,
+    pub event_id: String,
+    pub sender: String,
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SyncBatch {
+    pub next_batch: String,
+    pub messages: Vec<InboundMessage>,
+}
+
+pub struct MatrixClient {
+    homeserver: Url,
+    access_token: String,
+    http: reqwest::Client,
+}
+
+impl MatrixClient {
+    pub fn new(homeserver: &str, access_token: &str) -> Result<Self> {
+        let homeserver = Url::parse(homeserver).map_err(|e| {
+            ButterflyBotError::Config(format!("Bad Matrix homeserver {homeserver}: {e}"))
+        })?;
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Self {
+            homeserver,
+            access_token: access_token.to_string(),
+            http,
+        })
+    }
+
+    /// Posts `body` to the room; `notice` marks it as a bot notice, which
+    /// clients don't treat as something to answer.
+    pub async fn send(&self, room_id: &str, body: &str, notice: bool) -> Result<String> {
+        let txn = format!(
+            "bb{}-{}",
+            chrono::Utc::now().timestamp_millis(),
+            TXN_SEQ.fetch_add(1, Ordering::Relaxed)
+        );
+        let body: String = if body.chars().count() > MAX_MESSAGE_CHARS {
+            body.chars().take(MAX_MESSAGE_CHARS).collect::<String>() + "…"
+        } else {
+            body.to_string()
+        };
+        let content = json!({
+            "msgtype": if notice { "m.notice" } else { "m.text" },
+            "body": body,
+        });
+        let response = self
+            .request(
+                Method::PUT,
+                &["rooms", room_id, "send", "m.room.message", &txn],
+                &[],
+                Some(&content),
+            )
+            .await?;
+        Ok(response
+            .get("event_id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string())
+    }
+
+    /// Whether the room has end-to-end encryption turned on.
+    pub async fn is_encrypted(&self, room_id: &str) -> Result<bool> {
+        match self
+            .request(
+                Method::GET,
+                &["rooms", room_id, "state", "m.room.encryption", ""],
+                &[],
+                None,
+            )
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(ButterflyBotError::Http(message)) if message.contains("404") => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// New text messages in `rooms` since `since`. Without `since` only the
+    /// position is returned, so history is never replayed.
+    pub async fn sync(&self, since: Option<&str>, rooms: &[String]) -> Result<SyncBatch> {
+        let filter = json!({
+            "presence": {"types": []},
+            "account_data": {"types": []},
+            "room": {
+                "rooms": rooms,
+                "state": {"types": []},
+                "ephemeral": {"types": []},
+                "account_data": {"types": []},
+                "timeline": {"types": ["m.room.message"], "limit": 50},
+            },
+        })
+        .to_string();
+        let mut query = vec![("timeout", "0".to_string()), ("filter", filter)];
+        if let Some(since) = since {
+            query.push(("since", since.to_string()));
+        }
+        let response = self.request(Method::GET, &["sync"], &query, None).await?;
+        let mut batch = parse_sync(&response);
+        if since.is_none() {
+            batch.messages.clear();
+        }
+        Ok(batch)
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        path: &[&str],
+        query: &[(&str, String)],
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let mut url = self.homeserver.clone();
+        url.path_segments_mut()
+            .map_err(|_| ButterflyBotError::Config("Matrix homeserver URL has no path".into()))?
+            .pop_if_empty()
+            .extend(["_matrix", "client", "v3"])
+            .extend(path);
+        let mut request = self
+            .http
+            .request(method, url)
+            .bearer_auth(&self.access_token)
+            .query(query);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(format!("Matrix request failed: {e}")))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if status == StatusCode::UNAUTHORIZED {
+            return Err(ButterflyBotError::Config(
+                "Matrix rejected the access token".to_string(),
+            ));
+        }
+        if !status.is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "Matrix returned {}: {}",
+                status.as_u16(),
+                text.chars().take(200).collect::<String>()
+            )));
+        }
+        serde_json::from_str(&text).map_err(|e| ButterflyBotError::Http(e.to_string()))
+    }
+}
+
+fn parse_sync(response: &Value) -> SyncBatch {
+    let mut messages = Vec::new();
+    let joined = response
+        .pointer("/rooms/join")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten();
+    for (room_id, room) in joined {
+        let events = room
+            .pointer("/timeline/events")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten();
+        for event in events {
+            if event.get("type").and_then(Value::as_str) != Some("m.room.message")
+                || event.pointer("/content/msgtype").and_then(Value::as_str) != Some("m.text")
+            {
+                continue;
+            }
+            let (Some(event_id), Some(sender), Some(body)) = (
+                event.get("event_id").and_then(Value::as_str),
+                event.get("sender").and_then(Value::as_str),
+                event.pointer("/content/body").and_then(Value::as_str),
+            ) else {
+                continue;
+            };
+            messages.push(InboundMessage {
+                room_id: room_id.clone(),
+                event_id: event_id.to_string(),
+                sender: sender.to_string(),
+                body: body.to_string(),
+            });
+        }
+    }
+    SyncBatch {
+        next_batch: response
+            .get("next_batch")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        messages,
+    }
+}
+
+/// Copies chat into each user's room. Sends happen in the background, so a
+/// slow or unreachable homeserver never holds up a reply.
+pub struct MatrixMirror {
+    config: MatrixConfig,
+    /// Encryption state per room, checked once.
+    encrypted: Mutex<HashMap<String, bool>>,
+}
+
+impl MatrixMirror {
+    pub fn from_tools(tools: Option<&Value>) -> Option<Arc<Self>> {
+        MatrixConfig::from_tools(tools).map(|config| {
+            Arc::new(Self {
+                config,
+                encrypted: Mutex::new(HashMap::new()),
+            })
+        })
+    }
+
+    /// Mirrors a question and its answer. A question that arrived over
+    /// Matrix is already in the room.
+    pub fn mirror_exchange(self: &Arc<Self>, user_id: &str, query: &str, response: &str) {
+        let mut messages = Vec::new();
+        if !answering_matrix() {
+            messages.push((format!("You: {query}"), true));
+        }
+        if !response.trim().is_empty() {
+            messages.push((response.to_string(), false));
+        }
+        self.spawn_send(user_id, messages);
+    }
+
+    /// Mirrors something the agent said on its own, e.g. a digest.
+    pub fn mirror_message(self: &Arc<Self>, user_id: &str, text: &str) {
+        self.spawn_send(user_id, vec![(text.to_string(), false)]);
+    }
+
+    fn spawn_send(self: &Arc<Self>, user_id: &str, messages: Vec<(String, bool)>) {
+        if messages.is_empty() || self.config.room_for(user_id).is_none() {
+            return;
+        }
+        let mirror = self.clone();
+        let user_id = user_id.to_string();
+        tokio::spawn(async move {
+            if let Err(err) = mirror.send(&user_id, &messages).await {
+                tracing::warn!(user_id, error = %err, "Matrix mirror failed");
+            }
+        });
+    }
+
+    async fn send(&self, user_id: &str, messages: &[(String, bool)]) -> Result<()> {
+        let Some(room) = self.config.room_for(user_id) else {
+            return Ok(());
+        };
+        let client = self.config.client()?;
+        if !room_allowed(&self.config, &self.encrypted, &client, &room.room_id).await? {
+            return Err(ButterflyBotError::Config(format!(
+                "Matrix room {} is not encrypted",
+                room.room_id
+            )));
+        }
+        for (body, notice) in messages {
+            client.send(&room.room_id, body, *notice).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether the bot may use the room: always, unless encryption is required
+/// and the room doesn't have it. Answers are cached in `known`.
+pub async fn room_allowed(
+    config: &MatrixConfig,
+    known: &Mutex<HashMap<String, bool>>,
+    client: &MatrixClient,
+    room_id: &str,
+) -> Result<bool> {
+    if !config.require_encryption {
+        return Ok(true);
+    }
+    if let Some(encrypted) = known
+        .lock()
+        .ok()
+        .and_then(|known| known.get(room_id).copied())
+    {
+        return Ok(encrypted);
+    }
+    let encrypted = client.is_encrypted(room_id).await?;
+    if let Ok(mut known) = known.lock() {
+        known.insert(room_id.to_string(), encrypted);
+    }
+    Ok(encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[test]
+    fn parses_rooms_and_maps_senders_to_users() {
+        let tools = json!({"settings": {"matrix": {
+            "homeserver": "https://matrix.example.org/",
+            "rooms": [
+                {"user_id": "alice", "room_id": "!a:example.org",
+                 "senders": ["@alice:example.org", " "]},
+                {"room_id": "!ownerless:example.org"}
+            ]
+        }}});
+        let config = MatrixConfig::from_tools(Some(&tools)).unwrap();
+        assert_eq!(config.homeserver, "https://matrix.example.org");
+        assert_eq!(config.access_token_secret, DEFAULT_TOKEN_SECRET);
+        assert!(config.require_encryption);
+        assert_eq!(config.rooms.len(), 1);
+        assert_eq!(
+            config.user_for("!a:example.org", "@alice:example.org"),
+            Some("alice")
+        );
+        assert_eq!(
+            config.user_for("!a:example.org", "@mallory:example.org"),
+            None
+        );
+
+        let roomless = json!({"settings": {"matrix": {"homeserver": "https://m.org"}}});
+        assert!(MatrixConfig::from_tools(Some(&roomless)).is_none());
+    }
+
+    #[test]
+    fn sync_keeps_only_text_messages() {
+        let batch = parse_sync(&json!({
+            "next_batch": "s2",
+            "rooms": {"join": {"!a:example.org": {"timeline": {"events": [
+                {"type": "m.room.message", "event_id": "$1", "sender": "@alice:example.org",
+                 "content": {"msgtype": "m.text", "body": "what's on today?"}},
+                {"type": "m.room.message", "event_id": "$2", "sender": "@bot:example.org",
+                 "content": {"msgtype": "m.notice", "body": "You: hi"}},
+                {"type": "m.room.encrypted", "event_id": "$3", "sender": "@alice:example.org",
+                 "content": {"ciphertext": "..."}}
+            ]}}}}
+        }));
+        assert_eq!(batch.next_batch, "s2");
+        assert_eq!(batch.messages.len(), 1);
+        assert_eq!(batch.messages[0].body, "what's on today?");
+    }
+
+    #[tokio::test]
+    async fn sends_only_to_encrypted_rooms_when_required() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method("GET")
+                    .path("/_matrix/client/v3/rooms/!enc:example.org/state/m.room.encryption/");
+                then.status(200)
+                    .json_body(json!({"algorithm": "m.megolm.v1.aes-sha2"}));
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method("GET")
+                    .path("/_matrix/client/v3/rooms/!open:example.org/state/m.room.encryption/");
+                then.status(404)
+                    .json_body(json!({"errcode": "M_NOT_FOUND"}));
+            })
+            .await;
+        let sent = server
+            .mock_async(|when, then| {
+                when.method("PUT")
+                    .path_includes("/rooms/!enc:example.org/send/m.room.message/")
+                    .header("authorization", "Bearer tok")
+                    .json_body_includes(r#"{"msgtype": "m.notice", "body": "You: hi"}"#);
+                then.status(200).json_body(json!({"event_id": "$9"}));
+            })
+            .await;
+
+        let config = MatrixConfig::from_tools(Some(&json!({"settings": {"matrix": {
+            "homeserver": server.base_url(),
+            "rooms": [{"user_id": "u", "room_id": "!enc:example.org"}]
+        }}})))
+        .unwrap();
+        let client = MatrixClient::new(&config.homeserver, "tok").unwrap();
+        let known = Mutex::new(HashMap::new());
+        assert!(room_allowed(&config, &known, &client, "!enc:example.org")
+            .await
+            .unwrap());
+        assert!(!room_allowed(&config, &known, &client, "!open:example.org")
+            .await
+            .unwrap());
+        assert_eq!(
+            client
+                .send("!enc:example.org", "You: hi", true)
+                .await
+                .unwrap(),
+            "$9"
+        );
+        sent.assert_async().await;
+    }
+}
@@ -0,0 +1,270 @@
+//! In-process metrics for tool execution, rendered in the Prometheus text
+//! format by the daemon's `/metrics` endpoint.
+//!
+//! Everything is counted per tool (and per capability for host calls); error
+//! rates come from the `outcome` label rather than separate series.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the WASM execution duration buckets.
+const DURATION_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    Ok,
+    Error,
+    /// Refused by sandbox or role policy before running.
+    Denied,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Ok => "ok",
+            Outcome::Error => "error",
+            Outcome::Denied => "denied",
+        }
+    }
+
+    /// Classifies a tool or capability result; tools report their own
+    /// failures as `{"status": "error"}` rather than `Err`.
+    pub fn of<E>(result: &Result<serde_json::Value, E>) -> Self {
+        let Ok(value) = result else {
+            return Outcome::Error;
+        };
+        if value.get("status").and_then(|status| status.as_str()) != Some("error") {
+            return Outcome::Ok;
+        }
+        match value.get("code").and_then(|code| code.as_str()) {
+            Some("forbidden") => Outcome::Denied,
+            _ => Outcome::Error,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    tool_invocations: BTreeMap<(String, Outcome), u64>,
+    capability_calls: BTreeMap<(String, String, Outcome), u64>,
+    wasm_durations: BTreeMap<(String, Outcome), Histogram>,
+    wasm_fuel: BTreeMap<String, u64>,
+}
+
+fn registry() -> std::sync::MutexGuard<'static, Registry> {
+    static REGISTRY: OnceLock<Mutex<Registry>> = OnceLock::new();
+    REGISTRY
+        .get_or_init(|| Mutex::new(Registry::default()))
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+pub fn record_tool_invocation(tool: &str, outcome: Outcome) {
+    *registry()
+        .tool_invocations
+        .entry((tool.to_string(), outcome))
+        .or_default() += 1;
+}
+
+pub fn record_capability_call(tool: &str, capability: &str, outcome: Outcome) {
+    *registry()
+        .capability_calls
+        .entry((tool.to_string(), capability.to_string(), outcome))
+        .or_default() += 1;
+}
+
+pub fn record_wasm_execution(tool: &str, elapsed: Duration, outcome: Outcome) {
+    registry()
+        .wasm_durations
+        .entry((tool.to_string(), outcome))
+        .or_default()
+        .observe(elapsed.as_secs_f64());
+}
+
+pub fn record_wasm_fuel(tool: &str, consumed: u64) {
+    *registry().wasm_fuel.entry(tool.to_string()).or_default() += consumed;
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+/// Renders every series in the Prometheus text exposition format.
+pub fn render_prometheus() -> String {
+    let registry = registry();
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "butterfly_tool_invocations_total",
+        "counter",
+        "Tool invocations by outcome.",
+    );
+    for ((tool, outcome), count) in &registry.tool_invocations {
+        let _ = writeln!(
+            out,
+            "butterfly_tool_invocations_total{{tool=\"{}\",outcome=\"{}\"}} {count}",
+            escape_label(tool),
+            outcome.label()
+        );
+    }
+
+    header(
+        &mut out,
+        "butterfly_capability_calls_total",
+        "counter",
+        "Host capability calls made by WASM tools, by outcome.",
+    );
+    for ((tool, capability, outcome), count) in &registry.capability_calls {
+        let _ = writeln!(
+            out,
+            "butterfly_capability_calls_total{{tool=\"{}\",capability=\"{}\",outcome=\"{}\"}} {count}",
+            escape_label(tool),
+            escape_label(capability),
+            outcome.label()
+        );
+    }
+
+    header(
+        &mut out,
+        "butterfly_wasm_execution_seconds",
+        "histogram",
+        "Wall-clock time spent running WASM tool modules.",
+    );
+    for ((tool, outcome), histogram) in &registry.wasm_durations {
+        let labels = format!(
+            "tool=\"{}\",outcome=\"{}\"",
+            escape_label(tool),
+            outcome.label()
+        );
+        for (bound, count) in DURATION_BUCKETS.iter().zip(histogram.buckets) {
+            let _ = writeln!(
+                out,
+                "butterfly_wasm_execution_seconds_bucket{{{labels},le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "butterfly_wasm_execution_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+            histogram.count
+        );
+        let _ = writeln!(
+            out,
+            "butterfly_wasm_execution_seconds_sum{{{labels}}} {}",
+            histogram.sum
+        );
+        let _ = writeln!(
+            out,
+            "butterfly_wasm_execution_seconds_count{{{labels}}} {}",
+            histogram.count
+        );
+    }
+
+    header(
+        &mut out,
+        "butterfly_wasm_fuel_consumed_total",
+        "counter",
+        "WASM fuel consumed by tools running with a fuel limit.",
+    );
+    for (tool, fuel) in &registry.wasm_fuel {
+        let _ = writeln!(
+            out,
+            "butterfly_wasm_fuel_consumed_total{{tool=\"{}\"}} {fuel}",
+            escape_label(tool)
+        );
+    }
+    drop(registry);
+
+    let queue = crate::prompt_queue::shared().snapshot();
+    header(
+        &mut out,
+        "butterfly_prompt_queue_running",
+        "gauge",
+        "Prompts currently being processed.",
+    );
+    let _ = writeln!(out, "butterfly_prompt_queue_running {}", queue.running);
+    header(
+        &mut out,
+        "butterfly_prompt_queue_waiting",
+        "gauge",
+        "Prompts waiting for a processing slot.",
+    );
+    let _ = writeln!(out, "butterfly_prompt_queue_waiting {}", queue.queued.len());
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classifies_reported_errors_and_denials() {
+        assert_eq!(Outcome::of::<()>(&Ok(json!({"status": "ok"}))), Outcome::Ok);
+        assert_eq!(
+            Outcome::of::<()>(&Ok(json!({"status": "error", "code": "forbidden"}))),
+            Outcome::Denied
+        );
+        assert_eq!(
+            Outcome::of::<()>(&Ok(json!({"status": "error"}))),
+            Outcome::Error
+        );
+        assert_eq!(Outcome::of(&Err(())), Outcome::Error);
+    }
+
+    #[test]
+    fn renders_cumulative_histogram_buckets() {
+        let tool = "metrics_test_tool";
+        record_tool_invocation(tool, Outcome::Ok);
+        record_capability_call(tool, "clock.now_unix", Outcome::Ok);
+        record_wasm_execution(tool, Duration::from_millis(30), Outcome::Ok);
+        record_wasm_fuel(tool, 1_500);
+
+        let text = render_prometheus();
+        assert!(text.contains(
+            "butterfly_tool_invocations_total{tool=\"metrics_test_tool\",outcome=\"ok\"} 1"
+        ));
+        assert!(text.contains(
+            "butterfly_wasm_execution_seconds_bucket{tool=\"metrics_test_tool\",outcome=\"ok\",le=\"0.025\"} 0"
+        ));
+        assert!(text.contains(
+            "butterfly_wasm_execution_seconds_bucket{tool=\"metrics_test_tool\",outcome=\"ok\",le=\"0.05\"} 1"
+        ));
+        assert!(
+            text.contains("butterfly_wasm_fuel_consumed_total{tool=\"metrics_test_tool\"} 1500")
+        );
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -0,0 +1,848 @@
+//! Outgoing webhooks.
+//!
+//! Selected daemon events (an item done, a plan completed, a payment made,
+//! an approval step waiting on someone) are written to `webhook_outbox` once per subscribed endpoint and then
+//! delivered by a scheduled job. Each row keeps the exact body that was
+//! signed, so a retry sends byte-for-byte the same request, and records
+//! attempts, the last response and the last error as its delivery log.
+//!
+//! Endpoints come from `tools.settings.outbox`:
+//!
+//! ```json
+//! {"max_attempts": 8,
+//!  "endpoints": [{"url": "https://dash.example/hooks/butterfly",
+//!                 "events": ["item.done", "plan.completed"],
+//!                 "secret_name": "outbox_dash_secret"}]}
+//! ```
+//!
+//! Requests carry `X-Butterfly-Signature: t=<unix>,v1=<hex>`, an
+//! HMAC-SHA256 over `<unix>.<body>` keyed with the endpoint's vault secret.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use crate::services::agent::UiEvent;
+
+mod schema;
+use schema::webhook_outbox;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const WEBHOOK_OUTBOX_UP_SQL: &str =
+    include_str!("../../migrations/20260317_create_webhook_outbox/up.sql");
+
+pub const EVENT_ITEM_DONE: &str = "item.done";
+pub const EVENT_PLAN_COMPLETED: &str = "plan.completed";
+pub const EVENT_PAYMENT_MADE: &str = "payment.made";
+/// An approval chain step now waits on the event's user.
+pub const EVENT_APPROVAL_REQUESTED: &str = "approval.requested";
+pub const EVENT_TYPES: &[&str] = &[
+    EVENT_ITEM_DONE,
+    EVENT_PLAN_COMPLETED,
+    EVENT_PAYMENT_MADE,
+    EVENT_APPROVAL_REQUESTED,
+];
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_DELIVERED: &str = "delivered";
+/// Gave up after `max_attempts`.
+pub const STATUS_DEAD: &str = "dead";
+
+pub const SIGNATURE_HEADER: &str = "X-Butterfly-Signature";
+const DEFAULT_MAX_ATTEMPTS: i32 = 8;
+const BASE_BACKOFF_SECONDS: i64 = 30;
+const MAX_BACKOFF_SECONDS: i64 = 3600;
+const MAX_ERROR_LEN: usize = 500;
+
+static EVENT_SEQ: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutboxEndpoint {
+    pub url: String,
+    /// Event types to send; empty means all of them.
+    pub events: Vec<String>,
+    /// Vault secret used to sign requests; unsigned when absent.
+    pub secret_name: Option<String>,
+    /// Only events for this user, when set.
+    pub user_id: Option<String>,
+}
+
+impl OutboxEndpoint {
+    pub fn wants(&self, user_id: &str, event_type: &str) -> bool {
+        self.user_id.as_deref().is_none_or(|user| user == user_id)
+            && (self.events.is_empty() || self.events.iter().any(|event| event == event_type))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutboxConfig {
+    pub endpoints: Vec<OutboxEndpoint>,
+    pub max_attempts: i32,
+}
+
+impl OutboxConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("outbox"))
+            .filter(|section| section.get("enabled").and_then(Value::as_bool) != Some(false))
+        else {
+            return Self::default();
+        };
+        let endpoints = section
+            .get("endpoints")
+            .and_then(Value::as_array)
+            .map(|endpoints| endpoints.iter().filter_map(parse_endpoint).collect())
+            .unwrap_or_default();
+        let max_attempts = section
+            .get("max_attempts")
+            .and_then(Value::as_i64)
+            .map(|value| value.clamp(1, 50) as i32)
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        Self {
+            endpoints,
+            max_attempts,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn endpoint(&self, url: &str) -> Option<&OutboxEndpoint> {
+        self.endpoints.iter().find(|endpoint| endpoint.url == url)
+    }
+}
+
+fn parse_endpoint(value: &Value) -> Option<OutboxEndpoint> {
+    let url = value.get("url")?.as_str()?.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        tracing::warn!(url, "Ignoring outbox endpoint without an http(s) URL");
+        return None;
+    }
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let events = value
+        .get("events")
+        .and_then(Value::as_array)
+        .map(|events| {
+            events
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|event| event.trim().to_ascii_lowercase())
+                .filter(|event| !event.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(OutboxEndpoint {
+        url: url.to_string(),
+        events,
+        secret_name: text("secret_name"),
+        user_id: text("user_id"),
+    })
+}
+
+/// The public event, if any, that a daemon UI event stands for.
+pub fn public_event(event: &UiEvent) -> Option<(&'static str, Value)> {
+    let payload = &event.payload;
+    match (
+        event.event_type.as_str(),
+        event.tool.as_str(),
+        event.status.as_str(),
+    ) {
+        ("inbox_transition", _, "done") => {
+            let origin_ref = payload.get("origin_ref")?.as_str()?;
+            if let Some(plan_id) = origin_ref.strip_prefix("plan:") {
+                return Some((
+                    EVENT_PLAN_COMPLETED,
+                    json!({"plan_id": plan_id.parse::<i64>().ok(), "origin_ref": origin_ref}),
+                ));
+            }
+            Some((
+                EVENT_ITEM_DONE,
+                json!({
+                    "origin_ref": origin_ref,
+                    "source_type": payload.get("source_type"),
+                    "source_id": payload.get("source_id"),
+                    "actor": payload.get("actor"),
+                }),
+            ))
+        }
+        ("payment", _, "submitted") => Some((EVENT_PAYMENT_MADE, payload.clone())),
+        ("approval", _, "awaiting") => Some((EVENT_APPROVAL_REQUESTED, payload.clone())),
+        ("tool", "solana", "success") => {
+            let args = payload.get("args")?;
+            let action = args.get("action")?.as_str()?;
+            if !matches!(action, "transfer" | "send" | "send_transfer") {
+                return None;
+            }
+            let result = payload.get("result").cloned().unwrap_or(Value::Null);
+            Some((
+                EVENT_PAYMENT_MADE,
+                json!({
+                    "to": args.get("to"),
+                    "lamports": args.get("lamports"),
+                    "amount_sol": args.get("amount_sol"),
+                    "mint": args.get("mint"),
+                    "amount_atomic": args.get("amount_atomic"),
+                    "signature": find_key(&result, "signature"),
+                }),
+            ))
+        }
+        ("tool", "planning", "success") => {
+            let args = payload.get("args")?;
+            let status = args.get("status")?.as_str()?.to_ascii_lowercase();
+            if args.get("action")?.as_str()? != "update"
+                || !matches!(status.as_str(), "done" | "completed")
+            {
+                return None;
+            }
+            let plan_id = args.get("id")?;
+            Some((
+                EVENT_PLAN_COMPLETED,
+                json!({"plan_id": plan_id, "origin_ref": format!("plan:{plan_id}")}),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Tool results nest the useful part under `capability_result`; look for
+/// `key` at any depth.
+fn find_key(value: &Value, key: &str) -> Option<Value> {
+    match value {
+        Value::Object(map) => map
+            .get(key)
+            .cloned()
+            .or_else(|| map.values().find_map(|child| find_key(child, key))),
+        Value::Array(items) => items.iter().find_map(|item| find_key(item, key)),
+        _ => None,
+    }
+}
+
+/// Value for [`SIGNATURE_HEADER`].
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    let v1 = hex::encode(mac.finalize().into_bytes());
+    format!("t={timestamp},v1={v1}")
+}
+
+/// Seconds to wait after the `attempts`-th failure: 30s doubling to an hour.
+pub fn backoff_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    (BASE_BACKOFF_SECONDS << exponent).min(MAX_BACKOFF_SECONDS)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OutboxDelivery {
+    pub id: i32,
+    pub event_id: String,
+    pub user_id: String,
+    pub event_type: String,
+    pub endpoint_url: String,
+    pub body: String,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: i64,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub delivered_at: Option<i64>,
+}
+
+/// A delivery as it was before an attempt, with the status it ended in.
+#[derive(Clone, Debug)]
+pub struct DeliveryOutcome {
+    pub delivery: OutboxDelivery,
+    pub status: &'static str,
+}
+
+#[derive(Queryable)]
+struct OutboxRow {
+    id: i32,
+    event_id: String,
+    user_id: String,
+    event_type: String,
+    endpoint_url: String,
+    body: String,
+    status: String,
+    attempts: i32,
+    next_attempt_at: i64,
+    last_status_code: Option<i32>,
+    last_error: Option<String>,
+    created_at: i64,
+    delivered_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_outbox)]
+struct NewDelivery<'a> {
+    event_id: &'a str,
+    user_id: &'a str,
+    event_type: &'a str,
+    endpoint_url: &'a str,
+    body: &'a str,
+    status: &'a str,
+    attempts: i32,
+    next_attempt_at: i64,
+    created_at: i64,
+}
+
+pub struct OutboxStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl OutboxStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_webhook_outbox_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Queues `event_type` for every endpoint in `config` that wants it and
+    /// returns the number of deliveries created.
+    pub async fn enqueue(
+        &self,
+        config: &OutboxConfig,
+        user_id: &str,
+        event_type: &str,
+        data: &Value,
+    ) -> Result<usize> {
+        let endpoints: Vec<&OutboxEndpoint> = config
+            .endpoints
+            .iter()
+            .filter(|endpoint| endpoint.wants(user_id, event_type))
+            .collect();
+        if endpoints.is_empty() {
+            return Ok(0);
+        }
+        let now = self.clock.now();
+        let body = json!({
+            "type": event_type,
+            "user_id": user_id,
+            "created_at": now,
+            "data": data,
+        });
+        let seed = format!(
+            "{body}|{}|{}",
+            EVENT_SEQ.fetch_add(1, Ordering::Relaxed),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos())
+                .unwrap_or_default()
+        );
+        let event_id = format!(
+            "evt_{}",
+            Sha256::digest(seed.as_bytes())[..12]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        );
+        let mut body = body;
+        body["id"] = Value::String(event_id.clone());
+        let body = body.to_string();
+
+        let rows: Vec<NewDelivery<'_>> = endpoints
+            .iter()
+            .map(|endpoint| NewDelivery {
+                event_id: &event_id,
+                user_id,
+                event_type,
+                endpoint_url: &endpoint.url,
+                body: &body,
+                status: STATUS_PENDING,
+                attempts: 0,
+                next_attempt_at: now,
+                created_at: now,
+            })
+            .collect();
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(webhook_outbox::table)
+            .values(&rows)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Pending deliveries whose next attempt is due, oldest first.
+    pub async fn due(&self, limit: usize) -> Result<Vec<OutboxDelivery>> {
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        let rows: Vec<OutboxRow> = webhook_outbox::table
+            .filter(webhook_outbox::status.eq(STATUS_PENDING))
+            .filter(webhook_outbox::next_attempt_at.le(now))
+            .order(webhook_outbox::id.asc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    pub async fn record_success(&self, id: i32, status_code: i32) -> Result<()> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::update(webhook_outbox::table.filter(webhook_outbox::id.eq(id)))
+            .set((
+                webhook_outbox::status.eq(STATUS_DELIVERED),
+                webhook_outbox::attempts.eq(webhook_outbox::attempts + 1),
+                webhook_outbox::last_status_code.eq(Some(status_code)),
+                webhook_outbox::last_error.eq(None::<String>),
+                webhook_outbox::delivered_at.eq(Some(now)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Records a failed attempt and schedules the next one, or marks the
+    /// delivery dead once `max_attempts` is used up. Returns the new status.
+    pub async fn record_failure(
+        &self,
+        delivery: &OutboxDelivery,
+        status_code: Option<i32>,
+        error: &str,
+        max_attempts: i32,
+    ) -> Result<&'static str> {
+        let attempts = delivery.attempts + 1;
+        let status = if attempts >= max_attempts {
+            STATUS_DEAD
+        } else {
+            STATUS_PENDING
+        };
+        let error: String = error.chars().take(MAX_ERROR_LEN).collect();
+        let mut conn = self.write_conn().await?;
+        diesel::update(webhook_outbox::table.filter(webhook_outbox::id.eq(delivery.id)))
+            .set((
+                webhook_outbox::status.eq(status),
+                webhook_outbox::attempts.eq(attempts),
+                webhook_outbox::next_attempt_at.eq(self.clock.now() + backoff_seconds(attempts)),
+                webhook_outbox::last_status_code.eq(status_code),
+                webhook_outbox::last_error.eq(Some(error)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(status)
+    }
+
+    /// Sends every due delivery once and records the outcome. `secret_for`
+    /// resolves an endpoint's `secret_name`; deliveries to endpoints that
+    /// have since been removed from `config` are given up on.
+    pub async fn deliver_due(
+        &self,
+        config: &OutboxConfig,
+        client: &reqwest::Client,
+        limit: usize,
+        secret_for: impl Fn(&str) -> Option<String>,
+    ) -> Result<Vec<DeliveryOutcome>> {
+        let mut outcomes = Vec::new();
+        for delivery in self.due(limit).await? {
+            let Some(endpoint) = config.endpoint(&delivery.endpoint_url) else {
+                let status = self
+                    .record_failure(&delivery, None, "endpoint no longer configured", 0)
+                    .await?;
+                outcomes.push(DeliveryOutcome { delivery, status });
+                continue;
+            };
+            let mut request = client
+                .post(&endpoint.url)
+                .header("Content-Type", "application/json")
+                .header("X-Butterfly-Event", &delivery.event_type)
+                .header("X-Butterfly-Delivery", &delivery.event_id);
+            if let Some(name) = endpoint.secret_name.as_deref() {
+                match secret_for(name) {
+                    Some(secret) => {
+                        request = request.header(
+                            SIGNATURE_HEADER,
+                            signature(&secret, self.clock.now(), &delivery.body),
+                        );
+                    }
+                    None => {
+                        let status = self
+                            .record_failure(
+                                &delivery,
+                                None,
+                                &format!("signing secret '{name}' is not in the vault"),
+                                config.max_attempts,
+                            )
+                            .await?;
+                        outcomes.push(DeliveryOutcome { delivery, status });
+                        continue;
+                    }
+                }
+            }
+            let status = match request.body(delivery.body.clone()).send().await {
+                Ok(response) if response.status().is_success() => {
+                    self.record_success(delivery.id, i32::from(response.status().as_u16()))
+                        .await?;
+                    STATUS_DELIVERED
+                }
+                Ok(response) => {
+                    let code = response.status();
+                    self.record_failure(
+                        &delivery,
+                        Some(i32::from(code.as_u16())),
+                        &format!("endpoint answered {code}"),
+                        config.max_attempts,
+                    )
+                    .await?
+                }
+                Err(err) => {
+                    self.record_failure(&delivery, None, &err.to_string(), config.max_attempts)
+                        .await?
+                }
+            };
+            outcomes.push(DeliveryOutcome { delivery, status });
+        }
+        Ok(outcomes)
+    }
+
+    /// Delivery log for a user, newest first.
+    pub async fn list(&self, user_id: &str, limit: usize) -> Result<Vec<OutboxDelivery>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<OutboxRow> = webhook_outbox::table
+            .filter(webhook_outbox::user_id.eq(user_id))
+            .order(webhook_outbox::id.desc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(webhook_outbox::table.filter(webhook_outbox::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn map_row(row: OutboxRow) -> OutboxDelivery {
+    OutboxDelivery {
+        id: row.id,
+        event_id: row.event_id,
+        user_id: row.user_id,
+        event_type: row.event_type,
+        endpoint_url: row.endpoint_url,
+        body: row.body,
+        status: row.status,
+        attempts: row.attempts,
+        next_attempt_at: row.next_attempt_at,
+        last_status_code: row.last_status_code,
+        last_error: row.last_error,
+        created_at: row.created_at,
+        delivered_at: row.delivered_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_webhook_outbox_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM webhook_outbox LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    WEBHOOK_OUTBOX_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> OutboxConfig {
+        OutboxConfig::from_tools(Some(&json!({"settings": {"outbox": {
+            "max_attempts": 2,
+            "endpoints": [
+                {"url": "https://dash.example/hook", "events": ["item.done"], "secret_name": "dash"},
+                {"url": "https://all.example/hook"},
+                {"url": "ftp://nope.example"}
+            ]
+        }}})))
+    }
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signed = signature("s", 1_700_000_000, "{}");
+        assert!(signed.starts_with("t=1700000000,v1="));
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s").unwrap();
+        mac.update(b"1700000000.{}");
+        assert_eq!(
+            &signed["t=1700000000,v1=".len()..],
+            hex::encode(mac.finalize().into_bytes())
+        );
+        assert_ne!(signed, signature("s", 1_700_000_001, "{}"));
+        assert_eq!(backoff_seconds(1), 30);
+        assert_eq!(backoff_seconds(3), 120);
+        assert_eq!(backoff_seconds(40), MAX_BACKOFF_SECONDS);
+    }
+
+    #[tokio::test]
+    async fn deliveries_fan_out_retry_and_give_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.db");
+        let store = OutboxStore::new(path.to_str().unwrap()).await.unwrap();
+        let config = config();
+        assert_eq!(config.endpoints.len(), 2);
+
+        let queued = store
+            .enqueue(
+                &config,
+                "u",
+                EVENT_ITEM_DONE,
+                &json!({"origin_ref": "todo:1"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(queued, 2);
+        assert_eq!(
+            store
+                .enqueue(&config, "u", EVENT_PAYMENT_MADE, &json!({}))
+                .await
+                .unwrap(),
+            1
+        );
+
+        let due = store.due(10).await.unwrap();
+        assert_eq!(due.len(), 3);
+        assert_eq!(due[0].event_id, due[1].event_id);
+        let body: Value = serde_json::from_str(&due[0].body).unwrap();
+        assert_eq!(body["type"], EVENT_ITEM_DONE);
+        assert_eq!(body["id"], due[0].event_id.as_str());
+
+        store.record_success(due[0].id, 200).await.unwrap();
+        let status = store
+            .record_failure(&due[1], Some(503), "unavailable", config.max_attempts)
+            .await
+            .unwrap();
+        assert_eq!(status, STATUS_PENDING);
+        // Backed off, so only the untouched payment delivery is due now.
+        assert_eq!(store.due(10).await.unwrap().len(), 1);
+
+        let retried = store.list("u", 10).await.unwrap();
+        let retried = retried.iter().find(|d| d.id == due[1].id).unwrap();
+        let status = store
+            .record_failure(retried, None, "timed out", config.max_attempts)
+            .await
+            .unwrap();
+        assert_eq!(status, STATUS_DEAD);
+
+        let log = store.list("u", 10).await.unwrap();
+        let dead = log.iter().find(|d| d.id == due[1].id).unwrap();
+        assert_eq!(dead.attempts, 2);
+        assert_eq!(dead.last_error.as_deref(), Some("timed out"));
+        let delivered = log.iter().find(|d| d.id == due[0].id).unwrap();
+        assert_eq!(delivered.status, STATUS_DELIVERED);
+        assert_eq!(delivered.last_status_code, Some(200));
+    }
+
+    #[tokio::test]
+    async fn deliver_due_signs_requests_and_logs_failures() {
+        let server = httpmock::MockServer::start_async().await;
+        let ok = server
+            .mock_async(|when, then| {
+                when.method("POST")
+                    .path("/ok")
+                    .header("x-butterfly-event", EVENT_PLAN_COMPLETED)
+                    .header_exists(SIGNATURE_HEADER)
+                    .body_includes("\"type\":\"plan.completed\"");
+                then.status(204);
+            })
+            .await;
+        let down = server
+            .mock_async(|when, then| {
+                when.method("POST").path("/down");
+                then.status(500);
+            })
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("outbox.db");
+        let store = OutboxStore::new(path.to_str().unwrap()).await.unwrap();
+        let config = OutboxConfig::from_tools(Some(&json!({"settings": {"outbox": {
+            "endpoints": [
+                {"url": server.url("/ok"), "secret_name": "hook"},
+                {"url": server.url("/down")}
+            ]
+        }}})));
+        store
+            .enqueue(&config, "u", EVENT_PLAN_COMPLETED, &json!({"plan_id": 3}))
+            .await
+            .unwrap();
+
+        let outcomes = store
+            .deliver_due(&config, &reqwest::Client::new(), 10, |name| {
+                (name == "hook").then(|| "s3cret".to_string())
+            })
+            .await
+            .unwrap();
+        let statuses: Vec<&str> = outcomes.iter().map(|outcome| outcome.status).collect();
+        assert_eq!(statuses, vec![STATUS_DELIVERED, STATUS_PENDING]);
+        ok.assert_async().await;
+        down.assert_async().await;
+
+        let log = store.list("u", 10).await.unwrap();
+        let failed = log
+            .iter()
+            .find(|delivery| delivery.endpoint_url.ends_with("/down"))
+            .unwrap();
+        assert_eq!(failed.last_status_code, Some(500));
+        assert_eq!(failed.attempts, 1);
+        assert!(failed.next_attempt_at > failed.created_at);
+
+        // Removing an endpoint from config gives up on what is queued for it.
+        let trimmed = OutboxConfig::from_tools(Some(&json!({"settings": {"outbox": {
+            "endpoints": [{"url": server.url("/ok")}]
+        }}})));
+        let store = store.with_clock(crate::clock::ManualClock::new(failed.next_attempt_at));
+        let outcomes = store
+            .deliver_due(&trimmed, &reqwest::Client::new(), 10, |_| None)
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, STATUS_DEAD);
+    }
+
+    #[test]
+    fn public_events_come_from_done_transitions_and_payments() {
+        let event = |event_type: &str, tool: &str, status: &str, payload: Value| UiEvent {
+            event_type: event_type.to_string(),
+            user_id: "u".to_string(),
+            tool: tool.to_string(),
+            status: status.to_string(),
+            payload,
+            timestamp: 0,
+        };
+        let (kind, data) = public_event(&event(
+            "inbox_transition",
+            "inbox",
+            "done",
+            json!({"origin_ref": "todo:4", "source_type": "todo", "source_id": 4}),
+        ))
+        .unwrap();
+        assert_eq!(kind, EVENT_ITEM_DONE);
+        assert_eq!(data["origin_ref"], "todo:4");
+
+        let (kind, data) = public_event(&event(
+            "inbox_transition",
+            "inbox",
+            "done",
+            json!({"origin_ref": "plan:9"}),
+        ))
+        .unwrap();
+        assert_eq!(kind, EVENT_PLAN_COMPLETED);
+        assert_eq!(data["plan_id"], 9);
+
+        let (kind, data) = public_event(&event(
+            "tool",
+            "solana",
+            "success",
+            json!({"args": {"action": "transfer", "to": "abc", "lamports": 5},
+                   "result": {"capability_result": {"result": {"signature": "sig1"}}}}),
+        ))
+        .unwrap();
+        assert_eq!(kind, EVENT_PAYMENT_MADE);
+        assert_eq!(data["signature"], "sig1");
+
+        let (kind, data) = public_event(&event(
+            "approval",
+            "solana",
+            "awaiting",
+            json!({"origin_ref": "approval:3", "requested_by": "alice"}),
+        ))
+        .unwrap();
+        assert_eq!(kind, EVENT_APPROVAL_REQUESTED);
+        assert_eq!(data["requested_by"], "alice");
+
+        assert!(public_event(&event(
+            "tool",
+            "solana",
+            "success",
+            json!({"args": {"action": "balance"}})
+        ))
+        .is_none());
+        assert!(public_event(&event(
+            "inbox_transition",
+            "inbox",
+            "in_progress",
+            json!({})
+        ))
+        .is_none());
+    }
+}
@@ -0,0 +1,17 @@
+diesel::table! {
+    webhook_outbox (id) {
+        id -> Integer,
+        event_id -> Text,
+        user_id -> Text,
+        event_type -> Text,
+        endpoint_url -> Text,
+        body -> Text,
+        status -> Text,
+        attempts -> Integer,
+        next_attempt_at -> BigInt,
+        last_status_code -> Nullable<Integer>,
+        last_error -> Nullable<Text>,
+        created_at -> BigInt,
+        delivered_at -> Nullable<BigInt>,
+    }
+}
@@ -0,0 +1,662 @@
+//! Applying unified diffs to an allowlisted workspace.
+//!
+//! `coding.apply_patch` writes the diffs a coding model produces into one of
+//! the directories listed in the coding tool's `filesystem.allow` policy.
+//! Every file is patched in memory first, so a hunk that does not match
+//! fails the whole patch before anything is written. Files are copied to a
+//! timestamped backup directory before they change; a dry run reports the
+//! same changes without touching the disk.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::error::ButterflyBotError;
+use crate::Result;
+
+/// How far from its stated line a hunk may be found when the file drifted.
+const MAX_HUNK_OFFSET: usize = 200;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    fn old_side(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_side(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(text) | HunkLine::Add(text) => Some(text.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// The changes to one file. A missing `old_path` creates the file, a
+/// missing `new_path` deletes it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+    /// The patched file ends without a trailing newline.
+    pub missing_newline: bool,
+}
+
+impl FilePatch {
+    pub fn path(&self) -> &str {
+        self.new_path
+            .as_deref()
+            .or(self.old_path.as_deref())
+            .unwrap_or_default()
+    }
+
+    fn counts(&self) -> (usize, usize) {
+        let mut added = 0;
+        let mut removed = 0;
+        for line in self.hunks.iter().flat_map(|hunk| &hunk.lines) {
+            match line {
+                HunkLine::Add(_) => added += 1,
+                HunkLine::Remove(_) => removed += 1,
+                HunkLine::Context(_) => {}
+            }
+        }
+        (added, removed)
+    }
+}
+
+/// Parses a unified diff, as produced by `diff -u` or `git diff`. Headers
+/// other than `---`/`+++` (such as `diff --git` and `index`) are skipped.
+pub fn parse(diff: &str) -> Result<Vec<FilePatch>> {
+    let lines = diff.lines().collect::<Vec<_>>();
+    let mut patches = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let Some(old_header) = lines[index].strip_prefix("--- ") else {
+            index += 1;
+            continue;
+        };
+        let new_header = lines
+            .get(index + 1)
+            .and_then(|line| line.strip_prefix("+++ "))
+            .ok_or_else(|| {
+                ButterflyBotError::Runtime(format!(
+                    "Patch line {}: expected '+++' after '---'",
+                    index + 2
+                ))
+            })?;
+        let mut patch = FilePatch {
+            old_path: header_path(old_header),
+            new_path: header_path(new_header),
+            hunks: Vec::new(),
+            missing_newline: false,
+        };
+        if patch.old_path.is_none() && patch.new_path.is_none() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Patch line {}: both sides are /dev/null",
+                index + 1
+            )));
+        }
+        index += 2;
+        while let Some(header) = lines.get(index).filter(|line| line.starts_with("@@")) {
+            let mut hunk = parse_hunk_header(header).ok_or_else(|| {
+                ButterflyBotError::Runtime(format!(
+                    "Patch line {}: malformed hunk header",
+                    index + 1
+                ))
+            })?;
+            index += 1;
+            let (mut old_seen, mut new_seen) = (0, 0);
+            while old_seen < hunk.old_lines || new_seen < hunk.new_lines {
+                let Some(line) = lines.get(index) else {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Patch for {} ends inside a hunk",
+                        patch.path()
+                    )));
+                };
+                let text = line.get(1..).unwrap_or_default().to_string();
+                let parsed = match line.chars().next() {
+                    Some(' ') | None => HunkLine::Context(text),
+                    Some('-') => HunkLine::Remove(text),
+                    Some('+') => HunkLine::Add(text),
+                    Some('\\') => {
+                        index += 1;
+                        continue;
+                    }
+                    _ => {
+                        return Err(ButterflyBotError::Runtime(format!(
+                            "Patch line {}: unexpected line inside a hunk",
+                            index + 1
+                        )))
+                    }
+                };
+                match parsed {
+                    HunkLine::Context(_) => {
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                    HunkLine::Remove(_) => old_seen += 1,
+                    HunkLine::Add(_) => new_seen += 1,
+                }
+                hunk.lines.push(parsed);
+                index += 1;
+            }
+            if old_seen != hunk.old_lines || new_seen != hunk.new_lines {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Hunk line counts in the patch for {} do not match its header",
+                    patch.path()
+                )));
+            }
+            if lines.get(index).is_some_and(|line| line.starts_with('\\')) {
+                if !matches!(hunk.lines.last(), Some(HunkLine::Remove(_))) {
+                    patch.missing_newline = true;
+                }
+                index += 1;
+            }
+            patch.hunks.push(hunk);
+        }
+        if patch.hunks.is_empty() && patch.new_path.is_some() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Patch for {} has no hunks",
+                patch.path()
+            )));
+        }
+        patches.push(patch);
+    }
+    if patches.is_empty() {
+        return Err(ButterflyBotError::Runtime(
+            "Patch contains no file changes".to_string(),
+        ));
+    }
+    Ok(patches)
+}
+
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or("").trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+fn parse_hunk_header(header: &str) -> Option<Hunk> {
+    let body = header.strip_prefix("@@ ")?;
+    let (ranges, _) = body.split_once(" @@")?;
+    let (old, new) = ranges.split_once(' ')?;
+    let range = |raw: &str| -> Option<(usize, usize)> {
+        match raw.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((raw.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(old.strip_prefix('-')?)?;
+    let (new_start, new_lines) = range(new.strip_prefix('+')?)?;
+    Some(Hunk {
+        old_start,
+        old_lines,
+        new_start,
+        new_lines,
+        lines: Vec::new(),
+    })
+}
+
+/// Applies `patch` to `original`. Hunks are tried at their stated line
+/// first, then at the nearest offset where their old side matches.
+pub fn apply_to(original: &str, patch: &FilePatch) -> Result<String> {
+    let source = original.lines().collect::<Vec<_>>();
+    let mut output: Vec<&str> = Vec::with_capacity(source.len());
+    let mut cursor = 0;
+    for (number, hunk) in patch.hunks.iter().enumerate() {
+        let old = hunk.old_side();
+        let expected = if hunk.old_lines == 0 {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let position = find_hunk(&source, &old, expected.max(cursor), cursor).ok_or_else(|| {
+            ButterflyBotError::Runtime(format!(
+                "Hunk {} of the patch for {} does not match near line {}",
+                number + 1,
+                patch.path(),
+                hunk.old_start
+            ))
+        })?;
+        output.extend_from_slice(&source[cursor..position]);
+        output.extend(hunk.new_side());
+        cursor = position + old.len();
+    }
+    output.extend_from_slice(&source[cursor..]);
+
+    let mut patched = output.join("\n");
+    let keeps_newline = original.is_empty() || original.ends_with('\n');
+    if !patched.is_empty() && keeps_newline && !patch.missing_newline {
+        patched.push('\n');
+    }
+    Ok(patched)
+}
+
+fn find_hunk(source: &[&str], old: &[&str], expected: usize, floor: usize) -> Option<usize> {
+    let matches_at = |start: usize| {
+        start >= floor
+            && start + old.len() <= source.len()
+            && source[start..start + old.len()] == *old
+    };
+    (0..=MAX_HUNK_OFFSET).find_map(|offset| {
+        [expected.checked_add(offset), expected.checked_sub(offset)]
+            .into_iter()
+            .flatten()
+            .find(|start| matches_at(*start))
+    })
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Modified => "modified",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct FileChange {
+    /// Relative to the workspace root.
+    pub path: String,
+    pub change: ChangeKind,
+    pub added: usize,
+    pub removed: usize,
+    /// Copy of the file as it was before the patch.
+    pub backup: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct PatchReport {
+    pub workspace: String,
+    pub dry_run: bool,
+    pub files: Vec<FileChange>,
+    pub backup_dir: Option<String>,
+}
+
+/// A directory patches may write into.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    root: PathBuf,
+}
+
+impl Workspace {
+    /// Opens `requested`, or the only allowlisted directory when none is
+    /// given. The directory must exist and sit inside one of `allow`.
+    pub fn open(requested: Option<&str>, allow: &[String]) -> Result<Self> {
+        let allowed = allow
+            .iter()
+            .filter_map(|entry| fs::canonicalize(expand_home(entry)).ok())
+            .collect::<Vec<_>>();
+        if allowed.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "No workspace is allowlisted for the coding tool (sandbox filesystem.allow)"
+                    .to_string(),
+            ));
+        }
+        let root = match requested.map(str::trim).filter(|value| !value.is_empty()) {
+            Some(requested) => fs::canonicalize(expand_home(requested)).map_err(|e| {
+                ButterflyBotError::Runtime(format!("Workspace {requested} is not available: {e}"))
+            })?,
+            None if allowed.len() == 1 => allowed[0].clone(),
+            None => {
+                return Err(ButterflyBotError::Runtime(
+                    "Several workspaces are allowlisted; pass workspace".to_string(),
+                ))
+            }
+        };
+        if !root.is_dir() || !allowed.iter().any(|allowed| root.starts_with(allowed)) {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Workspace {} is not allowlisted for the coding tool",
+                root.display()
+            )));
+        }
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolves a patch path inside the workspace, refusing absolute paths,
+    /// `..` and symlinks anywhere along the way. Nothing under a `.git`
+    /// directory may be patched: its config can make git run commands.
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf> {
+        let relative_path = Path::new(relative);
+        let plain = !relative.trim().is_empty()
+            && relative_path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+        if !plain {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Patch path {relative} must stay inside the workspace"
+            )));
+        }
+        let in_git_dir = relative_path.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.eq_ignore_ascii_case(".git"))
+        });
+        if in_git_dir {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Patch path {relative} is inside .git"
+            )));
+        }
+        // `exists` and `canonicalize` follow links, so a dangling link would
+        // slip past them and the write would land wherever it points.
+        let mut path = self.root.clone();
+        for component in relative_path.components() {
+            path.push(component);
+            match fs::symlink_metadata(&path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Patch path {relative} goes through a symlink"
+                    )))
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => break,
+                Err(err) => {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Cannot resolve {relative}: {err}"
+                    )))
+                }
+            }
+        }
+        Ok(self.root.join(relative_path))
+    }
+
+    /// Applies `patches`, or only checks that they apply when `dry_run`.
+    /// Originals are copied under `backup_root/<millis>/` first.
+    pub fn apply(
+        &self,
+        patches: &[FilePatch],
+        dry_run: bool,
+        backup_root: &Path,
+    ) -> Result<PatchReport> {
+        let mut seen = HashSet::new();
+        let mut planned = Vec::with_capacity(patches.len());
+        for patch in patches {
+            if let (Some(old), Some(new)) = (&patch.old_path, &patch.new_path) {
+                if old != new {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Renaming {old} to {new} is not supported"
+                    )));
+                }
+            }
+            let relative = patch.path().to_string();
+            if !seen.insert(relative.clone()) {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Patch touches {relative} more than once"
+                )));
+            }
+            let path = self.resolve(&relative)?;
+            let exists = path.is_file();
+            let (change, contents) = match (&patch.old_path, &patch.new_path) {
+                (None, _) if exists => {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Patch creates {relative}, which already exists"
+                    )))
+                }
+                (None, _) => (ChangeKind::Created, Some(apply_to("", patch)?)),
+                (_, _) if !exists => {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Patch changes {relative}, which does not exist"
+                    )))
+                }
+                (Some(_), None) => (ChangeKind::Deleted, None),
+                (Some(_), Some(_)) => {
+                    let original = fs::read_to_string(&path).map_err(|e| {
+                        ButterflyBotError::Runtime(format!("Failed to read {relative}: {e}"))
+                    })?;
+                    (ChangeKind::Modified, Some(apply_to(&original, patch)?))
+                }
+            };
+            planned.push((relative, path, change, contents, patch.counts()));
+        }
+
+        let backup_dir = (!dry_run).then(|| {
+            let millis = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis())
+                .unwrap_or_default();
+            backup_root.join(millis.to_string())
+        });
+        let mut files = Vec::with_capacity(planned.len());
+        for (relative, path, change, contents, (added, removed)) in planned {
+            let mut backup = None;
+            if let Some(backup_dir) = &backup_dir {
+                if change != ChangeKind::Created {
+                    let copy = backup_dir.join(&relative);
+                    write_parent(&copy)?;
+                    fs::copy(&path, &copy).map_err(|e| {
+                        ButterflyBotError::Runtime(format!("Failed to back up {relative}: {e}"))
+                    })?;
+                    backup = Some(copy.to_string_lossy().to_string());
+                }
+                match &contents {
+                    Some(contents) => {
+                        write_parent(&path)?;
+                        fs::write(&path, contents)
+                    }
+                    None => fs::remove_file(&path),
+                }
+                .map_err(|e| {
+                    ButterflyBotError::Runtime(format!("Failed to write {relative}: {e}"))
+                })?;
+            }
+            files.push(FileChange {
+                path: relative,
+                change,
+                added,
+                removed,
+                backup,
+            });
+        }
+
+        Ok(PatchReport {
+            workspace: self.root.to_string_lossy().to_string(),
+            dry_run,
+            files,
+            backup_dir: backup_dir.map(|dir| dir.to_string_lossy().to_string()),
+        })
+    }
+}
+
+fn write_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::tempdir;
+
+    use super::{apply_to, parse, ChangeKind, HunkLine, Workspace};
+
+    const EDIT: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,3 @@
+ fn main() {
+-    println!(\"hi\");
++    println!(\"hello\");
+ }
+";
+
+    #[test]
+    fn parses_git_style_diffs() {
+        let patches = parse(EDIT).unwrap();
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path(), "src/lib.rs");
+        assert_eq!(patches[0].hunks[0].lines.len(), 4);
+        assert_eq!(
+            patches[0].hunks[0].lines[2],
+            HunkLine::Add("    println!(\"hello\");".to_string())
+        );
+        assert!(parse("not a diff").is_err());
+    }
+
+    #[test]
+    fn applies_hunks_that_drifted() {
+        let patch = &parse(EDIT).unwrap()[0];
+        let original = "// header\n\nfn main() {\n    println!(\"hi\");\n}\n";
+        assert_eq!(
+            apply_to(original, patch).unwrap(),
+            "// header\n\nfn main() {\n    println!(\"hello\");\n}\n"
+        );
+        assert!(apply_to("fn other() {}\n", patch).is_err());
+    }
+
+    #[test]
+    fn workspace_refuses_paths_outside_it() {
+        let dir = tempdir().unwrap();
+        let allow = vec![dir.path().to_string_lossy().to_string()];
+        let workspace = Workspace::open(None, &allow).unwrap();
+        assert!(workspace.resolve("src/lib.rs").is_ok());
+        assert!(workspace.resolve("../escape.rs").is_err());
+        assert!(workspace.resolve("/etc/passwd").is_err());
+        assert!(Workspace::open(Some("/"), &allow).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workspace_refuses_writes_through_symlinks() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(&root).unwrap();
+        let outside = dir.path().join("outside");
+        std::os::unix::fs::symlink(&outside, root.join("x")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), root.join("up")).unwrap();
+        let allow = vec![root.to_string_lossy().to_string()];
+        let workspace = Workspace::open(None, &allow).unwrap();
+
+        // `x` dangles, so nothing along the path "exists".
+        assert!(workspace.resolve("x").is_err());
+        assert!(workspace.resolve("up/outside").is_err());
+        let patches = parse("--- /dev/null\n+++ b/x\n@@ -0,0 +1 @@\n+escaped\n").unwrap();
+        assert!(workspace
+            .apply(&patches, false, &dir.path().join("backups"))
+            .is_err());
+        assert!(!outside.exists());
+    }
+
+    #[test]
+    fn dry_run_leaves_files_alone_and_apply_backs_them_up() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "fn main() {\n    println!(\"hi\");\n}\n",
+        )
+        .unwrap();
+        let allow = vec![root.to_string_lossy().to_string()];
+        let workspace = Workspace::open(None, &allow).unwrap();
+        let patches = parse(EDIT).unwrap();
+        let backups = dir.path().join("backups");
+
+        let report = workspace.apply(&patches, true, &backups).unwrap();
+        assert_eq!(report.files[0].change, ChangeKind::Modified);
+        assert!(report.backup_dir.is_none());
+        assert!(fs::read_to_string(root.join("src/lib.rs"))
+            .unwrap()
+            .contains("\"hi\""));
+
+        let report = workspace.apply(&patches, false, &backups).unwrap();
+        assert_eq!((report.files[0].added, report.files[0].removed), (1, 1));
+        assert!(fs::read_to_string(root.join("src/lib.rs"))
+            .unwrap()
+            .contains("\"hello\""));
+        let backup = report.files[0].backup.as_ref().unwrap();
+        assert!(fs::read_to_string(backup).unwrap().contains("\"hi\""));
+    }
+
+    #[test]
+    fn git_metadata_cannot_be_patched() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        let config = "[core]\n\tbare = false\n";
+        fs::write(root.join(".git/config"), config).unwrap();
+        let allow = vec![root.to_string_lossy().to_string()];
+        let workspace = Workspace::open(None, &allow).unwrap();
+        let patches = parse(
+            "\
+--- a/.git/config
++++ b/.git/config
+@@ -1,2 +1,3 @@
+ [core]
+ \tbare = false
++\tfsmonitor = touch /tmp/pwned
+",
+        )
+        .unwrap();
+
+        let err = workspace
+            .apply(&patches, false, &dir.path().join("backups"))
+            .unwrap_err();
+        assert!(err.to_string().contains(".git"), "{err}");
+        assert_eq!(
+            fs::read_to_string(root.join(".git/config")).unwrap(),
+            config
+        );
+        assert!(workspace
+            .resolve("vendor/lib/.GIT/hooks/pre-commit")
+            .is_err());
+        assert!(workspace.resolve(".gitignore").is_ok());
+    }
+}
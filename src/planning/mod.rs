@@ -1,30 +1,38 @@
 use std::collections::HashMap;
-use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use serde::Serialize;
 use serde_json::Value;
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqliteAsyncConn, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
+use crate::refs;
+use crate::security::user_domains;
+use crate::trash::{self, TrashBatch, TrashReceipt, TrashTable};
 
+pub mod negotiation;
 mod schema;
+pub mod templates;
 use schema::{plan_step_dependencies, plans};
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const PLANS_UP_SQL: &str = include_str!("../../migrations/20260202_create_plans/up.sql");
 const PLAN_STEP_DEP_UP_SQL: &str =
     include_str!("../../migrations/20260222_create_plan_step_dependencies/up.sql");
-
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
+const PLAN_TEMPLATES_UP_SQL: &str =
+    include_str!("../../migrations/20260326_create_plan_templates/up.sql");
+const PLAN_TRASH: TrashTable = TrashTable {
+    name: "plans",
+    columns: "id, user_id, title, goal, steps_json, status, created_at, updated_at, project_id",
+};
+const PLAN_STEP_DEP_TRASH: TrashTable = TrashTable {
+    name: "plan_step_dependencies",
+    columns: "id, plan_id, user_id, step_ref, depends_on_ref, created_at, updated_at",
+};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct PlanItem {
@@ -85,22 +93,34 @@ struct NewPlanStepDependency {
 }
 
 pub struct PlanStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
 }
 
 impl PlanStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_plans_table(sqlite_path).await?;
+        trash::ensure_trash_tables(sqlite_path, &[&PLAN_TRASH, &PLAN_STEP_DEP_TRASH]).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn create_plan(
@@ -111,20 +131,22 @@ impl PlanStore {
         steps: Option<&Value>,
         status: Option<&str>,
     ) -> Result<PlanItem> {
-        let now = now_ts();
+        let now = self.clock.now();
         let steps_json = steps.map(|value| value.to_string());
         let status = status.unwrap_or("draft");
+        let title = user_domains::seal(user_id, "plan.title", title)?;
+        let goal = user_domains::seal(user_id, "plan.goal", goal)?;
         let new = NewPlan {
             user_id,
-            title,
-            goal,
+            title: &title,
+            goal: &goal,
             steps_json: steps_json.as_deref(),
             status,
             created_at: now,
             updated_at: now,
         };
 
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         diesel::insert_into(plans::table)
             .values(&new)
             .execute(&mut conn)
@@ -171,10 +193,17 @@ impl PlanStore {
         steps: Option<&Value>,
         status: Option<&str>,
     ) -> Result<PlanItem> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let user: String = plans::table
+            .filter(plans::id.eq(id))
+            .select(plans::user_id)
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
 
         if let Some(title) = title {
+            let title = user_domains::seal(&user, "plan.title", title)?;
             diesel::update(plans::table.filter(plans::id.eq(id)))
                 .set((plans::title.eq(title), plans::updated_at.eq(now)))
                 .execute(&mut conn)
@@ -182,6 +211,7 @@ impl PlanStore {
                 .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
         }
         if let Some(goal) = goal {
+            let goal = user_domains::seal(&user, "plan.goal", goal)?;
             diesel::update(plans::table.filter(plans::id.eq(id)))
                 .set((plans::goal.eq(goal), plans::updated_at.eq(now)))
                 .execute(&mut conn)
@@ -197,12 +227,6 @@ impl PlanStore {
                 .execute(&mut conn)
                 .await
                 .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-            let user: String = plans::table
-                .filter(plans::id.eq(id))
-                .select(plans::user_id)
-                .first(&mut conn)
-                .await
-                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
             sync_plan_step_dependencies(&mut conn, id, &user, Some(steps)).await?;
         }
         if let Some(status) = status {
@@ -221,8 +245,51 @@ impl PlanStore {
         Ok(map_row(row))
     }
 
+    /// Sets `status` on one step of the user's plan, leaving the other steps
+    /// untouched. A bare string step becomes `{"title": ...}` so it can carry
+    /// the status. Returns the plan and the step's previous status.
+    pub async fn set_step_status(
+        &self,
+        user_id: &str,
+        plan_id: i32,
+        index: usize,
+        status: &str,
+    ) -> Result<(PlanItem, Option<String>)> {
+        let status = parse_step_status(status)
+            .ok_or_else(|| ButterflyBotError::Runtime(format!("Unknown step status {status}")))?;
+        let plan = self
+            .get_plan(plan_id)
+            .await
+            .ok()
+            .filter(|plan| plan.user_id == user_id)
+            .ok_or_else(|| ButterflyBotError::Runtime(format!("Plan {plan_id} not found")))?;
+        let mut steps = plan.steps.unwrap_or(Value::Array(Vec::new()));
+        let step = steps
+            .as_array_mut()
+            .and_then(|steps| steps.get_mut(index))
+            .ok_or_else(|| {
+                ButterflyBotError::Runtime(format!("Plan {plan_id} has no step {index}"))
+            })?;
+        if let Value::String(title) = step {
+            *step = serde_json::json!({ "title": title });
+        }
+        let Some(step) = step.as_object_mut() else {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Plan {plan_id} step {index} is not an object"
+            )));
+        };
+        let previous = step
+            .insert("status".to_string(), Value::String(status.to_string()))
+            .and_then(|value| value.as_str().map(str::to_string));
+
+        let plan = self
+            .update_plan(plan_id, None, None, Some(&steps), None)
+            .await?;
+        Ok((plan, previous))
+    }
+
     pub async fn delete_plan(&self, id: i32) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let count = diesel::delete(plans::table.filter(plans::id.eq(id)))
             .execute(&mut conn)
             .await
@@ -231,27 +298,48 @@ impl PlanStore {
     }
 
     pub async fn clear_plans(&self, user_id: &str) -> Result<usize> {
+        Ok(self.clear_plans_to_trash(user_id).await?.count)
+    }
+
+    /// Moves the user's plans, and the step dependencies hanging off them,
+    /// to the trash as one batch.
+    pub async fn clear_plans_to_trash(&self, user_id: &str) -> Result<TrashReceipt> {
+        let now = self.clock.now();
+        let batch_id = trash::new_batch_id(now)?;
+        let mut conn = self.write_conn().await?;
+        trash::move_rows(
+            &mut conn,
+            &PLAN_STEP_DEP_TRASH,
+            user_id,
+            " AND plan_id IN (SELECT id FROM plans WHERE user_id = ?1)",
+            &batch_id,
+            now,
+        )
+        .await?;
+        let count = trash::move_rows(&mut conn, &PLAN_TRASH, user_id, "", &batch_id, now).await?;
+        Ok(TrashReceipt {
+            store: "plans",
+            batch_id,
+            count,
+        })
+    }
+
+    pub async fn list_trash(&self, user_id: &str) -> Result<Vec<TrashBatch>> {
         let mut conn = self.conn().await?;
-        let plan_ids: Vec<i32> = plans::table
-            .filter(plans::user_id.eq(user_id))
-            .select(plans::id)
-            .load(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        if !plan_ids.is_empty() {
-            diesel::delete(
-                plan_step_dependencies::table
-                    .filter(plan_step_dependencies::plan_id.eq_any(&plan_ids)),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        }
-        let deleted = diesel::delete(plans::table.filter(plans::user_id.eq(user_id)))
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(deleted)
+        trash::list_batches(&mut conn, &PLAN_TRASH, "plans", user_id).await
+    }
+
+    pub async fn restore_trash(&self, user_id: &str, batch_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        let restored = trash::restore_rows(&mut conn, &PLAN_TRASH, user_id, batch_id).await?;
+        trash::restore_rows(&mut conn, &PLAN_STEP_DEP_TRASH, user_id, batch_id).await?;
+        Ok(restored)
+    }
+
+    pub async fn purge_trash(&self, cutoff: i64) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::purge_before(&mut conn, &PLAN_STEP_DEP_TRASH, cutoff).await?;
+        trash::purge_before(&mut conn, &PLAN_TRASH, cutoff).await
     }
 
     pub async fn list_step_dependencies_for_plans(
@@ -294,13 +382,11 @@ impl PlanStore {
     }
 
     async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 }
 
@@ -318,14 +404,6 @@ pub fn default_plan_db_path() -> String {
     crate::runtime_paths::default_db_path()
 }
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-    }
-    Ok(())
-}
-
 async fn run_migrations(database_url: &str) -> Result<()> {
     let database_url = database_url.to_string();
     tokio::task::spawn_blocking(move || {
@@ -362,6 +440,8 @@ async fn ensure_plans_table(database_url: &str) -> Result<()> {
 
         diesel::connection::SimpleConnection::batch_execute(&mut conn, PLAN_STEP_DEP_UP_SQL)
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::connection::SimpleConnection::batch_execute(&mut conn, PLAN_TEMPLATES_UP_SQL)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
 
         Ok::<_, ButterflyBotError>(())
     })
@@ -371,11 +451,13 @@ async fn ensure_plans_table(database_url: &str) -> Result<()> {
 }
 
 fn map_row(row: PlanRow) -> PlanItem {
+    let title = user_domains::open(&row.user_id, "plan.title", row.title);
+    let goal = user_domains::open(&row.user_id, "plan.goal", row.goal);
     PlanItem {
         id: row.id,
         user_id: row.user_id,
-        title: row.title,
-        goal: row.goal,
+        title,
+        goal,
         steps: row
             .steps_json
             .and_then(|value| serde_json::from_str(&value).ok()),
@@ -466,13 +548,8 @@ fn parse_step_dependency_refs_with_aliases(
                     return;
                 }
                 let normalized = trimmed.to_ascii_lowercase();
-                if normalized.starts_with("plan_step:")
-                    || normalized.starts_with("todo:")
-                    || normalized.starts_with("task:")
-                    || normalized.starts_with("reminder:")
-                    || normalized.starts_with("plan:")
-                {
-                    out.push(normalized);
+                if refs::is_tracked(&normalized) {
+                    out.push(refs::canonical(&normalized));
                 } else if let Some(mapped) = resolve_alias(alias_map, &normalized) {
                     out.push(mapped.clone());
                 } else if trimmed.contains(',') || trimmed.contains('|') || trimmed.contains(';') {
@@ -560,8 +637,19 @@ fn parse_step_dependency_refs_with_aliases(
     refs
 }
 
+/// Normalizes a step status to the inbox vocabulary the step surfaces in.
+pub fn parse_step_status(value: &str) -> Option<&'static str> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "new" | "todo" | "open" => Some("new"),
+        "in_progress" | "in progress" | "started" => Some("in_progress"),
+        "blocked" => Some("blocked"),
+        "done" | "completed" | "complete" => Some("done"),
+        _ => None,
+    }
+}
+
 async fn sync_plan_step_dependencies(
-    conn: &mut SqlitePooledConn<'_>,
+    conn: &mut SqliteAsyncConn,
     plan_id: i32,
     user_id: &str,
     steps: Option<&Value>,
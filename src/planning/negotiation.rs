@@ -0,0 +1,423 @@
+//! Agenda negotiation: when urgent work lands on an agenda that is already
+//! full, propose what to defer or shrink instead of quietly overcommitting.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::PlanItem;
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+const DEFAULT_MINUTES_PER_DAY: i32 = 360;
+const DEFAULT_HORIZON_DAYS: i64 = 1;
+
+/// Plan status used for trade-off proposals awaiting the user's decision.
+pub const PROPOSAL_STATUS: &str = "proposed";
+
+/// Focused minutes available per day, and how many days ahead count as
+/// "already committed". Read from `tools.planning.capacity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityModel {
+    pub minutes_per_day: i32,
+    pub horizon_days: i64,
+}
+
+impl Default for CapacityModel {
+    fn default() -> Self {
+        Self {
+            minutes_per_day: DEFAULT_MINUTES_PER_DAY,
+            horizon_days: DEFAULT_HORIZON_DAYS,
+        }
+    }
+}
+
+impl CapacityModel {
+    pub fn from_tools_config(tools: Option<&Value>) -> Self {
+        let capacity = tools
+            .and_then(|tools| tools.get("planning"))
+            .and_then(|planning| planning.get("capacity"));
+        let defaults = Self::default();
+        Self {
+            minutes_per_day: capacity
+                .and_then(|value| value.get("minutes_per_day"))
+                .and_then(|value| value.as_i64())
+                .filter(|value| *value > 0)
+                .map(|value| value as i32)
+                .unwrap_or(defaults.minutes_per_day),
+            horizon_days: capacity
+                .and_then(|value| value.get("horizon_days"))
+                .and_then(|value| value.as_i64())
+                .filter(|value| *value > 0)
+                .unwrap_or(defaults.horizon_days),
+        }
+    }
+
+    pub fn capacity_minutes(&self) -> i32 {
+        self.minutes_per_day * self.horizon_days as i32
+    }
+
+    pub fn horizon_end(&self, now: i64) -> i64 {
+        now + self.horizon_days * DAY_SECONDS
+    }
+}
+
+/// One unit of scheduled work, as seen by the negotiator.
+#[derive(Debug, Clone)]
+pub struct WorkItem {
+    pub origin_ref: String,
+    pub title: String,
+    pub priority: String,
+    pub due_at: Option<i64>,
+    pub likely_minutes: i32,
+    pub optimistic_minutes: Option<i32>,
+}
+
+impl WorkItem {
+    /// Work with no due date isn't pinned to the horizon; it only counts
+    /// once it has one.
+    fn committed_within(&self, horizon_end: i64) -> bool {
+        self.due_at.is_some_and(|due_at| due_at < horizon_end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeOffKind {
+    Defer,
+    Shrink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeOff {
+    pub kind: TradeOffKind,
+    pub target_ref: String,
+    pub title: String,
+    pub minutes_freed: i32,
+    pub defer_until: Option<i64>,
+    pub target_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgendaProposal {
+    pub trigger_refs: Vec<String>,
+    pub capacity_minutes: i32,
+    pub committed_minutes: i32,
+    pub overcommit_minutes: i32,
+    pub trade_offs: Vec<TradeOff>,
+    /// False when even every available trade-off leaves the agenda over
+    /// capacity; the proposal then says so instead of pretending it fits.
+    pub resolves_overcommit: bool,
+}
+
+/// Proposes trade-offs that make room for `incoming` within the capacity
+/// horizon, or `None` when everything already fits. Urgent work is never
+/// deferred or shrunk; lower-priority and later-due items go first, normal
+/// and low items are deferred past the horizon, high items are cut to their
+/// optimistic estimate.
+pub fn propose(
+    incoming: &[WorkItem],
+    agenda: &[WorkItem],
+    capacity: CapacityModel,
+    now: i64,
+) -> Option<AgendaProposal> {
+    let horizon_end = capacity.horizon_end(now);
+    let incoming_minutes: i32 = incoming.iter().map(|item| item.likely_minutes).sum();
+    let committed: Vec<&WorkItem> = agenda
+        .iter()
+        .filter(|item| item.committed_within(horizon_end))
+        .filter(|item| {
+            !incoming
+                .iter()
+                .any(|incoming| incoming.origin_ref == item.origin_ref)
+        })
+        .collect();
+    let committed_minutes = incoming_minutes
+        + committed
+            .iter()
+            .map(|item| item.likely_minutes)
+            .sum::<i32>();
+    let overcommit_minutes = committed_minutes - capacity.capacity_minutes();
+    if incoming.is_empty() || overcommit_minutes <= 0 {
+        return None;
+    }
+
+    let mut candidates: Vec<&WorkItem> = committed
+        .into_iter()
+        .filter(|item| item.priority != "urgent" && item.likely_minutes > 0)
+        .collect();
+    candidates.sort_by(|a, b| {
+        priority_rank(&b.priority)
+            .cmp(&priority_rank(&a.priority))
+            .then_with(|| b.due_at.cmp(&a.due_at))
+    });
+
+    let mut freed = 0;
+    let mut trade_offs = Vec::new();
+    for item in candidates {
+        if freed >= overcommit_minutes {
+            break;
+        }
+        let shrink_to = item
+            .optimistic_minutes
+            .filter(|minutes| *minutes > 0 && *minutes < item.likely_minutes);
+        let trade_off = match (item.priority.as_str(), shrink_to) {
+            ("high", Some(target)) => TradeOff {
+                kind: TradeOffKind::Shrink,
+                target_ref: item.origin_ref.clone(),
+                title: item.title.clone(),
+                minutes_freed: item.likely_minutes - target,
+                defer_until: None,
+                target_minutes: Some(target),
+            },
+            _ => TradeOff {
+                kind: TradeOffKind::Defer,
+                target_ref: item.origin_ref.clone(),
+                title: item.title.clone(),
+                minutes_freed: item.likely_minutes,
+                defer_until: Some(horizon_end),
+                target_minutes: None,
+            },
+        };
+        freed += trade_off.minutes_freed;
+        trade_offs.push(trade_off);
+    }
+
+    Some(AgendaProposal {
+        trigger_refs: incoming
+            .iter()
+            .map(|item| item.origin_ref.clone())
+            .collect(),
+        capacity_minutes: capacity.capacity_minutes(),
+        committed_minutes,
+        overcommit_minutes,
+        trade_offs,
+        resolves_overcommit: freed >= overcommit_minutes,
+    })
+}
+
+impl AgendaProposal {
+    pub fn plan_title(&self, incoming_title: &str) -> String {
+        format!("Make room for: {incoming_title}")
+    }
+
+    /// Plan goal. Names the trigger refs so saving the same urgent work
+    /// again finds the pending proposal instead of stacking another.
+    pub fn plan_goal(&self) -> String {
+        let mut goal = format!(
+            "Agenda is {} minutes over capacity ({} committed vs {} available) {}.",
+            self.overcommit_minutes,
+            self.committed_minutes,
+            self.capacity_minutes,
+            trigger_phrase(&self.trigger_refs)
+        );
+        if !self.resolves_overcommit {
+            goal.push_str(" These trade-offs do not free enough time on their own.");
+        }
+        goal
+    }
+
+    /// One approvable step per trade-off. Steps carry the machine-readable
+    /// action so approving the plan can apply it.
+    pub fn plan_steps(&self) -> Value {
+        Value::Array(
+            self.trade_offs
+                .iter()
+                .map(|trade_off| {
+                    let title = match trade_off.kind {
+                        TradeOffKind::Defer => format!(
+                            "Defer \"{}\" (frees {} min)",
+                            trade_off.title, trade_off.minutes_freed
+                        ),
+                        TradeOffKind::Shrink => format!(
+                            "Shrink \"{}\" to {} min",
+                            trade_off.title,
+                            trade_off.target_minutes.unwrap_or_default()
+                        ),
+                    };
+                    json!({
+                        "title": title,
+                        "owner": "human",
+                        "priority": "urgent",
+                        "action": trade_off.kind,
+                        "target_ref": trade_off.target_ref,
+                        "defer_until": trade_off.defer_until,
+                        "target_minutes": trade_off.target_minutes,
+                        "minutes_freed": trade_off.minutes_freed,
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Whether `plan` is a pending proposal made for exactly `trigger_refs`.
+pub fn is_pending_proposal_for(plan: &PlanItem, trigger_refs: &[String]) -> bool {
+    plan.status == PROPOSAL_STATUS && plan.goal.contains(&trigger_phrase(trigger_refs))
+}
+
+fn trigger_phrase(trigger_refs: &[String]) -> String {
+    format!("once {} lands", trigger_refs.join(", "))
+}
+
+/// Open work from the user's active plans. Proposals and closed plans are
+/// left out so a proposal never negotiates against itself.
+pub fn plan_work_items(plans: &[PlanItem]) -> Vec<WorkItem> {
+    plans
+        .iter()
+        .filter(|plan| is_active_plan(&plan.status))
+        .flat_map(|plan| {
+            plan.steps
+                .as_ref()
+                .and_then(|steps| steps.as_array())
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter_map(move |(index, step)| step_work_item(plan, index, step))
+        })
+        .collect()
+}
+
+pub fn is_active_plan(status: &str) -> bool {
+    !matches!(status, "done" | "rejected" | PROPOSAL_STATUS)
+}
+
+fn step_work_item(plan: &PlanItem, index: usize, step: &Value) -> Option<WorkItem> {
+    if step.get("status").and_then(|v| v.as_str()) == Some("done") {
+        return None;
+    }
+    let title = step
+        .get("title")
+        .and_then(|v| v.as_str())
+        .or_else(|| step.as_str())?
+        .to_string();
+    let likely_minutes = step
+        .get("estimate_likely_minutes")
+        .and_then(|v| v.as_i64())
+        .unwrap_or(0) as i32;
+    Some(WorkItem {
+        origin_ref: format!("plan_step:{}:{index}", plan.id),
+        title,
+        priority: step
+            .get("priority")
+            .and_then(|v| v.as_str())
+            .unwrap_or("normal")
+            .to_string(),
+        due_at: step_due_at(step),
+        likely_minutes,
+        optimistic_minutes: step
+            .get("estimate_optimistic_minutes")
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32),
+    })
+}
+
+fn step_due_at(step: &Value) -> Option<i64> {
+    if let Some(due_at) = step.get("due_at") {
+        if let Some(ts) = due_at.as_i64() {
+            return Some(ts);
+        }
+        if let Some(ts) = due_at.as_str().and_then(|raw| raw.trim().parse().ok()) {
+            return Some(ts);
+        }
+    }
+    let due_date = step.get("due_date").and_then(|v| v.as_str())?;
+    let date = chrono::NaiveDate::parse_from_str(due_date.trim(), "%Y-%m-%d").ok()?;
+    let end_of_day = date.and_hms_opt(23, 59, 59)?;
+    chrono::TimeZone::from_local_datetime(&chrono::Local, &end_of_day)
+        .earliest()
+        .map(|dt| dt.timestamp())
+}
+
+fn priority_rank(priority: &str) -> i32 {
+    match priority {
+        "urgent" => 0,
+        "high" => 1,
+        "normal" => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_800_000_000;
+
+    fn item(origin_ref: &str, priority: &str, likely: i32, optimistic: Option<i32>) -> WorkItem {
+        WorkItem {
+            origin_ref: origin_ref.to_string(),
+            title: origin_ref.to_string(),
+            priority: priority.to_string(),
+            due_at: Some(NOW + 3600),
+            likely_minutes: likely,
+            optimistic_minutes: optimistic,
+        }
+    }
+
+    #[test]
+    fn fits_within_capacity_without_a_proposal() {
+        let capacity = CapacityModel::default();
+        let incoming = [item("plan_step:2:0", "urgent", 60, None)];
+        let agenda = [item("plan_step:1:0", "normal", 120, None)];
+        assert!(propose(&incoming, &agenda, capacity, NOW).is_none());
+    }
+
+    #[test]
+    fn defers_low_priority_before_shrinking_high_and_never_touches_urgent() {
+        let capacity = CapacityModel {
+            minutes_per_day: 240,
+            horizon_days: 1,
+        };
+        let incoming = [item("plan_step:9:0", "urgent", 120, None)];
+        let agenda = [
+            item("plan_step:1:0", "urgent", 60, None),
+            item("plan_step:1:1", "high", 120, Some(60)),
+            item("plan_step:1:2", "low", 30, None),
+        ];
+
+        let proposal = propose(&incoming, &agenda, capacity, NOW).expect("overcommitted");
+        assert_eq!(proposal.committed_minutes, 330);
+        assert_eq!(proposal.overcommit_minutes, 90);
+        assert!(proposal.resolves_overcommit);
+        let kinds: Vec<_> = proposal
+            .trade_offs
+            .iter()
+            .map(|t| (t.kind, t.target_ref.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TradeOffKind::Defer, "plan_step:1:2"),
+                (TradeOffKind::Shrink, "plan_step:1:1"),
+            ]
+        );
+        assert_eq!(
+            proposal.trade_offs[0].defer_until,
+            Some(capacity.horizon_end(NOW))
+        );
+    }
+
+    #[test]
+    fn reports_when_trade_offs_cannot_cover_the_overcommit() {
+        let capacity = CapacityModel {
+            minutes_per_day: 60,
+            horizon_days: 1,
+        };
+        let incoming = [item("plan_step:9:0", "urgent", 90, None)];
+        let agenda = [item("plan_step:1:0", "urgent", 60, None)];
+        let proposal = propose(&incoming, &agenda, capacity, NOW).expect("overcommitted");
+        assert!(proposal.trade_offs.is_empty());
+        assert!(!proposal.resolves_overcommit);
+        assert!(proposal.plan_goal().contains("do not free enough"));
+    }
+
+    #[test]
+    fn capacity_reads_tools_config() {
+        let tools = json!({"planning": {"capacity": {"minutes_per_day": 300, "horizon_days": 2}}});
+        let capacity = CapacityModel::from_tools_config(Some(&tools));
+        assert_eq!(capacity.capacity_minutes(), 600);
+        assert_eq!(
+            CapacityModel::from_tools_config(None),
+            CapacityModel::default()
+        );
+    }
+}
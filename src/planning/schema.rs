@@ -22,3 +22,16 @@ diesel::table! {
         updated_at -> BigInt,
     }
 }
+
+diesel::table! {
+    plan_templates (id) {
+        id -> Integer,
+        user_id -> Text,
+        name -> Text,
+        title -> Text,
+        goal -> Text,
+        steps_json -> Nullable<Text>,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
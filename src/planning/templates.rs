@@ -0,0 +1,426 @@
+//! Reusable plan templates.
+//!
+//! A template is a plan skeleton saved under a name ("onboard new client",
+//! "weekly review"). Its title, goal and step text may carry `{{name}}`
+//! placeholders; instantiating the template fills them from a map of values
+//! and creates an ordinary plan, so repeated workflows keep their steps
+//! without the model writing them out again.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::schema::plan_templates;
+use super::PlanStore;
+use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanTemplate {
+    pub id: i32,
+    pub user_id: String,
+    pub name: String,
+    pub title: String,
+    pub goal: String,
+    pub steps: Option<Value>,
+    /// Placeholder names the template needs values for, in first-use order.
+    pub placeholders: Vec<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// The plan a template produces once its placeholders are filled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedPlan {
+    pub title: String,
+    pub goal: String,
+    pub steps: Option<Value>,
+}
+
+#[derive(Queryable)]
+struct PlanTemplateRow {
+    id: i32,
+    user_id: String,
+    name: String,
+    title: String,
+    goal: String,
+    steps_json: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = plan_templates)]
+struct NewPlanTemplate<'a> {
+    user_id: &'a str,
+    name: &'a str,
+    title: &'a str,
+    goal: &'a str,
+    steps_json: Option<&'a str>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl PlanTemplate {
+    /// Fills every placeholder from `values`. Strings are inserted as-is and
+    /// other JSON values in their compact form; a placeholder without a
+    /// value is an error naming all that are missing.
+    pub fn render(&self, values: &Map<String, Value>) -> Result<RenderedPlan> {
+        let missing = self
+            .placeholders
+            .iter()
+            .filter(|name| !values.contains_key(name.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Missing template values: {}",
+                missing.join(", ")
+            )));
+        }
+        Ok(RenderedPlan {
+            title: fill_placeholders(&self.title, values),
+            goal: fill_placeholders(&self.goal, values),
+            steps: self.steps.as_ref().map(|steps| fill_value(steps, values)),
+        })
+    }
+}
+
+impl PlanStore {
+    pub async fn create_template(
+        &self,
+        user_id: &str,
+        name: &str,
+        title: &str,
+        goal: &str,
+        steps: Option<&Value>,
+    ) -> Result<PlanTemplate> {
+        let name = normalize_template_name(name)?;
+        let now = self.clock.now();
+        let steps_json = steps.map(|value| value.to_string());
+        let title = user_domains::seal(user_id, "plan_template.title", title)?;
+        let goal = user_domains::seal(user_id, "plan_template.goal", goal)?;
+        let mut conn = self.conn().await?;
+        diesel::insert_into(plan_templates::table)
+            .values(&NewPlanTemplate {
+                user_id,
+                name: &name,
+                title: &title,
+                goal: &goal,
+                steps_json: steps_json.as_deref(),
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| {
+                if e.to_string().contains("UNIQUE") {
+                    ButterflyBotError::Runtime(format!("A template named {name} already exists"))
+                } else {
+                    ButterflyBotError::Runtime(e.to_string())
+                }
+            })?;
+
+        let row: PlanTemplateRow = plan_templates::table
+            .filter(plan_templates::user_id.eq(user_id))
+            .filter(plan_templates::name.eq(&name))
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(map_template_row(row))
+    }
+
+    pub async fn list_templates(&self, user_id: &str, limit: usize) -> Result<Vec<PlanTemplate>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<PlanTemplateRow> = plan_templates::table
+            .filter(plan_templates::user_id.eq(user_id))
+            .order(plan_templates::name.asc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_template_row).collect())
+    }
+
+    pub async fn get_template(&self, user_id: &str, id: i32) -> Result<Option<PlanTemplate>> {
+        let mut conn = self.conn().await?;
+        let row: Option<PlanTemplateRow> = plan_templates::table
+            .filter(plan_templates::user_id.eq(user_id))
+            .filter(plan_templates::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_template_row))
+    }
+
+    /// Looks a template up by name, ignoring case and surrounding spaces.
+    pub async fn find_template(&self, user_id: &str, name: &str) -> Result<Option<PlanTemplate>> {
+        let name = normalize_template_name(name)?;
+        let mut conn = self.conn().await?;
+        let row: Option<PlanTemplateRow> = plan_templates::table
+            .filter(plan_templates::user_id.eq(user_id))
+            .filter(plan_templates::name.eq(&name))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_template_row))
+    }
+
+    /// Changes the given fields of the user's template; `None` when there is
+    /// no such template.
+    pub async fn update_template(
+        &self,
+        user_id: &str,
+        id: i32,
+        name: Option<&str>,
+        title: Option<&str>,
+        goal: Option<&str>,
+        steps: Option<&Value>,
+    ) -> Result<Option<PlanTemplate>> {
+        let Some(existing) = self.get_template(user_id, id).await? else {
+            return Ok(None);
+        };
+        let name = match name {
+            Some(name) => normalize_template_name(name)?,
+            None => existing.name,
+        };
+        let title = user_domains::seal(
+            user_id,
+            "plan_template.title",
+            title.unwrap_or(&existing.title),
+        )?;
+        let goal = user_domains::seal(
+            user_id,
+            "plan_template.goal",
+            goal.unwrap_or(&existing.goal),
+        )?;
+        let steps_json = steps
+            .or(existing.steps.as_ref())
+            .map(|value| value.to_string());
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        diesel::update(
+            plan_templates::table
+                .filter(plan_templates::user_id.eq(user_id))
+                .filter(plan_templates::id.eq(id)),
+        )
+        .set((
+            plan_templates::name.eq(&name),
+            plan_templates::title.eq(title),
+            plan_templates::goal.eq(goal),
+            plan_templates::steps_json.eq(steps_json),
+            plan_templates::updated_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| {
+            if e.to_string().contains("UNIQUE") {
+                ButterflyBotError::Runtime(format!("A template named {name} already exists"))
+            } else {
+                ButterflyBotError::Runtime(e.to_string())
+            }
+        })?;
+        drop(conn);
+        self.get_template(user_id, id).await
+    }
+
+    pub async fn delete_template(&self, user_id: &str, id: i32) -> Result<bool> {
+        let mut conn = self.conn().await?;
+        let count = diesel::delete(
+            plan_templates::table
+                .filter(plan_templates::user_id.eq(user_id))
+                .filter(plan_templates::id.eq(id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(count > 0)
+    }
+
+    pub async fn clear_templates(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.conn().await?;
+        diesel::delete(plan_templates::table.filter(plan_templates::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+}
+
+fn normalize_template_name(name: &str) -> Result<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err(ButterflyBotError::Runtime(
+            "Template name cannot be empty".to_string(),
+        ));
+    }
+    Ok(name.to_lowercase())
+}
+
+fn map_template_row(row: PlanTemplateRow) -> PlanTemplate {
+    let title = user_domains::open(&row.user_id, "plan_template.title", row.title);
+    let goal = user_domains::open(&row.user_id, "plan_template.goal", row.goal);
+    let steps = row
+        .steps_json
+        .and_then(|value| serde_json::from_str::<Value>(&value).ok());
+    let mut placeholders = Vec::new();
+    collect_placeholders(&title, &mut placeholders);
+    collect_placeholders(&goal, &mut placeholders);
+    if let Some(steps) = &steps {
+        collect_value_placeholders(steps, &mut placeholders);
+    }
+    PlanTemplate {
+        id: row.id,
+        user_id: row.user_id,
+        name: row.name,
+        title,
+        goal,
+        steps,
+        placeholders,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+/// Yields `(start, end, name)` for every `{{name}}` in `text`, where
+/// `start..end` spans the braces.
+fn placeholder_spans(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut spans = Vec::new();
+    let mut offset = 0;
+    while let Some(open) = text[offset..].find("{{") {
+        let start = offset + open;
+        let Some(close) = text[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let name = text[start + 2..end - 2].trim();
+        if !name.is_empty() && !name.contains('{') {
+            spans.push((start, end, name));
+            offset = end;
+        } else {
+            offset = start + 2;
+        }
+    }
+    spans
+}
+
+fn collect_placeholders(text: &str, out: &mut Vec<String>) {
+    for (_, _, name) in placeholder_spans(text) {
+        if !out.iter().any(|known| known == name) {
+            out.push(name.to_string());
+        }
+    }
+}
+
+fn collect_value_placeholders(value: &Value, out: &mut Vec<String>) {
+    match value {
+        Value::String(text) => collect_placeholders(text, out),
+        Value::Array(items) => {
+            for item in items {
+                collect_value_placeholders(item, out);
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values() {
+                collect_value_placeholders(item, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn fill_placeholders(text: &str, values: &Map<String, Value>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, name) in placeholder_spans(text) {
+        out.push_str(&text[last..start]);
+        match values.get(name) {
+            Some(Value::String(value)) => out.push_str(value),
+            Some(value) => out.push_str(&value.to_string()),
+            None => out.push_str(&text[start..end]),
+        }
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+fn fill_value(value: &Value, values: &Map<String, Value>) -> Value {
+    match value {
+        Value::String(text) => Value::String(fill_placeholders(text, values)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| fill_value(v, values)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, v)| (key.clone(), fill_value(v, values)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn template(title: &str, goal: &str, steps: Value) -> PlanTemplate {
+        let mut placeholders = Vec::new();
+        collect_placeholders(title, &mut placeholders);
+        collect_placeholders(goal, &mut placeholders);
+        collect_value_placeholders(&steps, &mut placeholders);
+        PlanTemplate {
+            id: 1,
+            user_id: "u1".to_string(),
+            name: "onboard new client".to_string(),
+            title: title.to_string(),
+            goal: goal.to_string(),
+            steps: Some(steps),
+            placeholders,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn renders_placeholders_in_title_goal_and_steps() {
+        let template = template(
+            "Onboard {{client}}",
+            "Get {{ client }} live by {{date}}",
+            json!([{"title": "Kickoff call with {{client}}", "due_date": "{{date}}"}, "Send invoice"]),
+        );
+        assert_eq!(template.placeholders, vec!["client", "date"]);
+
+        let values = json!({"client": "Acme", "date": "2026-11-02"});
+        let rendered = template
+            .render(values.as_object().unwrap())
+            .expect("rendered");
+        assert_eq!(rendered.title, "Onboard Acme");
+        assert_eq!(rendered.goal, "Get Acme live by 2026-11-02");
+        assert_eq!(
+            rendered.steps,
+            Some(json!([
+                {"title": "Kickoff call with Acme", "due_date": "2026-11-02"},
+                "Send invoice"
+            ]))
+        );
+    }
+
+    #[test]
+    fn rendering_names_every_missing_value() {
+        let template = template("Review {{week}}", "Close {{quarter}}", json!([]));
+        let err = template
+            .render(&Map::new())
+            .expect_err("values are missing");
+        assert!(err.to_string().contains("week, quarter"));
+    }
+
+    #[test]
+    fn unterminated_braces_are_left_alone() {
+        let values = json!({"a": 1});
+        let values = values.as_object().unwrap();
+        assert_eq!(fill_placeholders("{{a}} and {{b", values), "1 and {{b");
+        assert_eq!(fill_placeholders("{{}} {{a}}", values), "{{}} 1");
+    }
+}
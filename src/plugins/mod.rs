@@ -1,2 +1,4 @@
 pub mod manager;
+pub mod posture;
 pub mod registry;
+pub mod schema;
@@ -0,0 +1,150 @@
+//! Security posture of the registered tools: what their capabilities let
+//! them reach, how risky that makes them, which module build is loaded and
+//! when each was last used.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::sandbox::ToolSandboxConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskArea {
+    /// Reaches hosts outside the machine.
+    Network,
+    /// Reads or writes files beyond the bot's own databases.
+    Filesystem,
+    /// Touches wallets or moves money.
+    Financial,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskLevel {
+    Low,
+    Medium,
+    High,
+}
+
+/// Capabilities that make a tool high-risk on their own.
+const HIGH_RISK_CAPABILITIES: &[&str] = &["solana.transfer", "secrets.get"];
+
+const NETWORK_PREFIXES: &[&str] = &[
+    "http.", "search.", "coding.", "git.", "mcp.", "github.", "zapier.", "solana.",
+];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolPosture {
+    pub name: String,
+    pub description: String,
+    pub runtime: String,
+    pub abi_version: u32,
+    /// The daemon's version for bundled modules; `None` for modules
+    /// installed from elsewhere.
+    pub version: Option<String>,
+    /// SHA-256 of the module that will run, when it can be read.
+    pub module_sha256: Option<String>,
+    pub capabilities: Vec<String>,
+    pub areas: Vec<RiskArea>,
+    pub risk: RiskLevel,
+    pub last_used_at: Option<i64>,
+}
+
+/// What `capabilities` and the sandbox policy let a tool reach.
+pub fn risk_areas(capabilities: &[String], config: &ToolSandboxConfig) -> Vec<RiskArea> {
+    let mut areas = Vec::new();
+    if !config.network.allow.is_empty()
+        || capabilities.iter().any(|capability| {
+            NETWORK_PREFIXES
+                .iter()
+                .any(|prefix| capability.starts_with(prefix))
+        })
+    {
+        areas.push(RiskArea::Network);
+    }
+    if !config.filesystem.allow.is_empty() {
+        areas.push(RiskArea::Filesystem);
+    }
+    if capabilities
+        .iter()
+        .any(|capability| capability.starts_with("solana."))
+    {
+        areas.push(RiskArea::Financial);
+    }
+    areas
+}
+
+/// High for tools that can move money or read secrets, medium for anything
+/// reaching the network or the filesystem, low otherwise.
+pub fn risk_level(capabilities: &[String], areas: &[RiskArea]) -> RiskLevel {
+    if capabilities
+        .iter()
+        .any(|capability| HIGH_RISK_CAPABILITIES.contains(&capability.as_str()))
+    {
+        RiskLevel::High
+    } else if areas.is_empty() {
+        RiskLevel::Low
+    } else {
+        RiskLevel::Medium
+    }
+}
+
+/// Version and digest of the module at `path`.
+pub fn module_identity(path: &str) -> (Option<String>, Option<String>) {
+    let Ok(bytes) = std::fs::read(path) else {
+        return (None, None);
+    };
+    let digest = Sha256::digest(&bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let version = crate::wasm_bundle::is_bundled_module(&bytes)
+        .then(|| env!("CARGO_PKG_VERSION").to_string());
+    (version, Some(digest))
+}
+
+/// Riskiest tools first, then by name.
+pub fn sort_postures(postures: &mut [ToolPosture]) {
+    postures.sort_by(|a, b| b.risk.cmp(&a.risk).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// Fills in `last_used_at` from `(tool, timestamp)` pairs.
+pub fn apply_last_used(postures: &mut [ToolPosture], last_used: &HashMap<String, i64>) {
+    for posture in postures {
+        posture.last_used_at = last_used.get(&posture.name).copied();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capabilities(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn classifies_capabilities_into_areas_and_levels() {
+        let config = ToolSandboxConfig::default();
+
+        let todo = capabilities(&["kv.sqlite.todo.create", "clock.now_unix"]);
+        assert!(risk_areas(&todo, &config).is_empty());
+        assert_eq!(risk_level(&todo, &[]), RiskLevel::Low);
+
+        let http = capabilities(&["http.request"]);
+        let areas = risk_areas(&http, &config);
+        assert_eq!(areas, vec![RiskArea::Network]);
+        assert_eq!(risk_level(&http, &areas), RiskLevel::Medium);
+
+        let solana = capabilities(&["solana.balance", "solana.transfer"]);
+        let areas = risk_areas(&solana, &config);
+        assert_eq!(areas, vec![RiskArea::Network, RiskArea::Financial]);
+        assert_eq!(risk_level(&solana, &areas), RiskLevel::High);
+
+        let mut files = ToolSandboxConfig::default();
+        files.filesystem.allow = vec!["~/Documents".to_string()];
+        assert_eq!(risk_areas(&todo, &files), vec![RiskArea::Filesystem]);
+    }
+}
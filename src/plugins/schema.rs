@@ -0,0 +1,349 @@
+//! Host-side validation of tool input.
+//!
+//! Every tool already publishes a JSON Schema through `Tool::parameters` for
+//! the model's function-calling spec. `SchemaRegistry` keeps those schemas
+//! and `ToolRegistry` checks arguments against them before the WASM module
+//! runs, so a bad call comes back as an `invalid_args` result listing every
+//! problem with its JSON pointer rather than whatever the guest's first
+//! hand-rolled check happened to trip on.
+//!
+//! Only the keywords tool schemas use are understood: `type`, `properties`,
+//! `required`, `additionalProperties`, `enum`, `items`, `anyOf`/`oneOf`, and
+//! the numeric, string and array bounds. Anything else is ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Arguments the host adds to every call; schemas need not declare them.
+pub const HOST_SUPPLIED_KEYS: &[&str] = &["user_id"];
+
+const MAX_VIOLATIONS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value; empty for the input itself.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "input {}", self.message)
+        } else {
+            write!(f, "{} {}", self.path, self.message)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SchemaRegistry {
+    schemas: HashMap<String, Value>,
+}
+
+impl SchemaRegistry {
+    pub fn register(&mut self, tool_name: &str, schema: Value) {
+        self.schemas.insert(tool_name.to_string(), schema);
+    }
+
+    pub fn schema(&self, tool_name: &str) -> Option<&Value> {
+        self.schemas.get(tool_name)
+    }
+
+    /// Violations in `params` for `tool_name`; tools without a schema
+    /// accept anything.
+    pub fn validate(&self, tool_name: &str, params: &Value) -> Vec<SchemaViolation> {
+        match self.schemas.get(tool_name) {
+            Some(schema) => validate_input(schema, params),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Validates tool input, leaving out host-supplied keys the schema doesn't
+/// mention so `additionalProperties: false` doesn't reject them.
+pub fn validate_input(schema: &Value, params: &Value) -> Vec<SchemaViolation> {
+    let declared = schema.get("properties").and_then(Value::as_object);
+    let mut input = params.clone();
+    if let Value::Object(map) = &mut input {
+        for key in HOST_SUPPLIED_KEYS {
+            if !declared.is_some_and(|props| props.contains_key(*key)) {
+                map.remove(*key);
+            }
+        }
+    }
+    validate(schema, &input)
+}
+
+pub fn validate(schema: &Value, value: &Value) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    validate_at(schema, value, "", &mut violations);
+    violations.truncate(MAX_VIOLATIONS);
+    violations
+}
+
+/// The result a tool call gets when its input fails validation. Matches the
+/// guest's own `invalid_args` shape so the agent reports it back to the model.
+pub fn invalid_args(tool_name: &str, violations: &[SchemaViolation]) -> Value {
+    let summary = violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ");
+    json!({
+        "status": "error",
+        "code": "invalid_args",
+        "error": format!("Invalid arguments for {tool_name}: {summary}"),
+        "errors": violations,
+    })
+}
+
+fn push(violations: &mut Vec<SchemaViolation>, path: &str, message: String) {
+    violations.push(SchemaViolation {
+        path: path.to_string(),
+        message,
+    });
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_i64() || number.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(expected: &str, value: &Value) -> bool {
+    match expected {
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "number" => value.is_number(),
+        other => type_name(value) == other,
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, violations: &mut Vec<SchemaViolation>) {
+    let Some(schema) = schema.as_object() else {
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| matches_type(name, value)) {
+            push(
+                violations,
+                path,
+                format!("must be {}, got {}", allowed.join(" or "), type_name(value)),
+            );
+            // Nothing below makes sense against the wrong type.
+            return;
+        }
+    }
+
+    if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+        if !options.contains(value) {
+            let listed = options
+                .iter()
+                .map(Value::to_string)
+                .collect::<Vec<_>>()
+                .join(", ");
+            push(violations, path, format!("must be one of {listed}"));
+        }
+    }
+
+    for keyword in ["anyOf", "oneOf"] {
+        if let Some(branches) = schema.get(keyword).and_then(Value::as_array) {
+            let fits = branches.iter().any(|branch| {
+                let mut scratch = Vec::new();
+                validate_at(branch, value, path, &mut scratch);
+                scratch.is_empty()
+            });
+            if !fits {
+                push(
+                    violations,
+                    path,
+                    "does not match any of the allowed shapes".to_string(),
+                );
+            }
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            if let Some(required) = schema.get("required").and_then(Value::as_array) {
+                for key in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(key) {
+                        push(
+                            violations,
+                            &format!("{path}/{}", escape_pointer(key)),
+                            "is required".to_string(),
+                        );
+                    }
+                }
+            }
+            for (key, child) in map {
+                let child_path = format!("{path}/{}", escape_pointer(key));
+                match properties.and_then(|props| props.get(key)) {
+                    Some(child_schema) => validate_at(child_schema, child, &child_path, violations),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            let allowed = properties
+                                .map(|props| props.keys().cloned().collect::<Vec<_>>().join(", "))
+                                .unwrap_or_default();
+                            push(
+                                violations,
+                                &child_path,
+                                format!("is not an accepted argument (accepted: {allowed})"),
+                            );
+                        }
+                        Some(extra) if extra.is_object() => {
+                            validate_at(extra, child, &child_path, violations)
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    push(violations, path, format!("must have at least {min} items"));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    push(violations, path, format!("must have at most {max} items"));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{path}/{index}"), violations);
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if length < min {
+                    push(
+                        violations,
+                        path,
+                        format!("must be at least {min} characters"),
+                    );
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if length > max {
+                    push(
+                        violations,
+                        path,
+                        format!("must be at most {max} characters"),
+                    );
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < min {
+                    push(violations, path, format!("must be >= {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > max {
+                    push(violations, path, format!("must be <= {max}"));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn todo_like() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": {"type": "string", "enum": ["create", "list"]},
+                "limit": {"type": "integer", "minimum": 1},
+                "ordered_ids": {"type": "array", "items": {"type": "integer"}},
+                "x": {"type": ["string", "number"]}
+            },
+            "required": ["action"],
+            "additionalProperties": false
+        })
+    }
+
+    #[test]
+    fn reports_every_violation_with_its_pointer() {
+        let violations = validate_input(
+            &todo_like(),
+            &json!({
+                "action": "remove",
+                "limit": "10",
+                "ordered_ids": [1, "two"],
+                "x": true,
+                "colour": "red",
+                "user_id": "u"
+            }),
+        );
+        let rendered: Vec<String> = violations.iter().map(ToString::to_string).collect();
+        assert!(rendered.contains(&"/action must be one of \"create\", \"list\"".to_string()));
+        assert!(rendered.contains(&"/limit must be integer, got string".to_string()));
+        assert!(rendered.contains(&"/ordered_ids/1 must be integer, got string".to_string()));
+        assert!(rendered.contains(&"/x must be string or number, got boolean".to_string()));
+        assert!(rendered
+            .iter()
+            .any(|line| line.starts_with("/colour is not an accepted argument")));
+        // The host adds user_id itself, so it never counts against the tool.
+        assert!(!rendered.iter().any(|line| line.starts_with("/user_id")));
+        assert_eq!(violations.len(), 5);
+
+        let missing = validate(&todo_like(), &json!({"limit": 0}));
+        let rendered: Vec<String> = missing.iter().map(ToString::to_string).collect();
+        assert_eq!(rendered, vec!["/action is required", "/limit must be >= 1"]);
+
+        assert!(validate(&todo_like(), &json!({"action": "list", "limit": 5.0})).is_empty());
+        assert_eq!(
+            validate(&todo_like(), &json!("list"))[0].to_string(),
+            "input must be object, got string"
+        );
+    }
+
+    #[test]
+    fn invalid_args_payload_matches_guest_shape() {
+        let mut registry = SchemaRegistry::default();
+        registry.register("todo", todo_like());
+        assert!(registry.validate("unknown", &json!(42)).is_empty());
+
+        let violations = registry.validate("todo", &json!({}));
+        let payload = invalid_args("todo", &violations);
+        assert_eq!(payload["status"], "error");
+        assert_eq!(payload["code"], "invalid_args");
+        assert_eq!(
+            payload["error"],
+            "Invalid arguments for todo: /action is required"
+        );
+        assert_eq!(payload["errors"][0]["path"], "/action");
+    }
+}
@@ -0,0 +1,158 @@
+//! Presentation mode: masks sensitive text at render time for screen sharing.
+//!
+//! Nothing in the stores or UI state is changed; the desktop UI passes the
+//! strings it is about to draw through [`Redactor::redact`] while the mode is
+//! on. Wallet addresses, amounts, hosts in URLs and the names listed under
+//! `tools.settings.presentation.names` are replaced with placeholders.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use regex::Regex;
+use serde_json::Value;
+
+const WALLET_MASK: &str = "[wallet]";
+const AMOUNT_MASK: &str = "[amount]";
+const NAME_MASK: &str = "[name]";
+/// Keeps the scheme so a masked link still reads as a link.
+const URL_HOST_MASK: &str = "${scheme}://[host]";
+
+/// Reads `tools.settings.presentation.names`, a list of people to mask.
+pub fn names_from_tools(tools: Option<&Value>) -> Vec<String> {
+    tools
+        .and_then(|tools| tools.get("settings"))
+        .and_then(|settings| settings.get("presentation"))
+        .and_then(|presentation| presentation.get("names"))
+        .and_then(|names| names.as_array())
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(|name| name.as_str())
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Redactor {
+    names: Option<Regex>,
+}
+
+impl Redactor {
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut names = names
+            .into_iter()
+            .map(|name| name.as_ref().trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        // Longest first so "Ada Lovelace" wins over "Ada".
+        names.sort_by(|a, b| b.len().cmp(&a.len()));
+        names.dedup();
+        if names.is_empty() {
+            return Self { names: None };
+        }
+        let alternation = names
+            .iter()
+            .map(|name| regex::escape(name))
+            .collect::<Vec<_>>()
+            .join("|");
+        Self {
+            names: Regex::new(&format!(r"(?i)\b(?:{alternation})\b")).ok(),
+        }
+    }
+
+    pub fn redact<'a>(&self, input: &'a str) -> Cow<'a, str> {
+        let patterns = patterns();
+        let mut output = Cow::Borrowed(input);
+        for (re, replacement) in [
+            (&patterns.url_host, URL_HOST_MASK),
+            (&patterns.evm_wallet, WALLET_MASK),
+            (&patterns.base58_wallet, WALLET_MASK),
+            (&patterns.amount, AMOUNT_MASK),
+        ] {
+            if re.is_match(&output) {
+                output = Cow::Owned(re.replace_all(&output, replacement).into_owned());
+            }
+        }
+        if let Some(names) = &self.names {
+            if names.is_match(&output) {
+                output = Cow::Owned(names.replace_all(&output, NAME_MASK).into_owned());
+            }
+        }
+        output
+    }
+
+    /// Whether `input` names one of the listed people.
+    pub fn mentions_name(&self, input: &str) -> bool {
+        self.names
+            .as_ref()
+            .is_some_and(|names| names.is_match(input))
+    }
+
+    /// Whether `input` holds a wallet address or an amount of money.
+    pub fn mentions_money(&self, input: &str) -> bool {
+        let patterns = patterns();
+        patterns.evm_wallet.is_match(input)
+            || patterns.base58_wallet.is_match(input)
+            || patterns.amount.is_match(input)
+    }
+}
+
+struct Patterns {
+    url_host: Regex,
+    evm_wallet: Regex,
+    base58_wallet: Regex,
+    amount: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        url_host: Regex::new(r"(?i)\b(?P<scheme>https?|wss?)://[^\s/?#]+").unwrap(),
+        evm_wallet: Regex::new(r"\b0x[0-9a-fA-F]{40}\b").unwrap(),
+        base58_wallet: Regex::new(r"\b[1-9A-HJ-NP-Za-km-z]{32,44}\b").unwrap(),
+        amount: Regex::new(
+            r"(?i)[$€£]\s?\d[\d,]*(?:\.\d+)?|\b\d[\d,]*(?:\.\d+)?\s?(?:sol|usdc|usdt|usd|eur|gbp|lamports)\b",
+        )
+        .unwrap(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn masks_wallets_amounts_hosts_and_names() {
+        let redactor = Redactor::new(["Ada", "Ada Lovelace"]);
+        let input = "Send 2.5 SOL ($310.20) to 7EcDhSYGxXyscszYEp35KHN8vvw3svAuLKTzXwCFLtV \
+                     for Ada Lovelace via https://api.mainnet-beta.solana.com/rpc";
+        assert_eq!(
+            redactor.redact(input),
+            "Send [amount] ([amount]) to [wallet] for [name] via https://[host]/rpc"
+        );
+        assert_eq!(
+            redactor.redact("0x52908400098527886E0F7030069857D2E4169EE7 belongs to ada"),
+            "[wallet] belongs to [name]"
+        );
+    }
+
+    #[test]
+    fn leaves_ordinary_text_borrowed() {
+        let redactor = Redactor::default();
+        let input = "Review 3 pull requests before the standup";
+        assert!(matches!(redactor.redact(input), Cow::Borrowed(_)));
+
+        let tools = json!({"settings": {"presentation": {"names": ["Grace", " ", 7]}}});
+        assert_eq!(names_from_tools(Some(&tools)), vec!["Grace".to_string()]);
+        assert!(names_from_tools(None).is_empty());
+    }
+}
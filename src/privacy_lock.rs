@@ -0,0 +1,146 @@
+//! App lock for shared screens.
+//!
+//! The desktop UI hides its content after an idle period or on demand and
+//! asks for the lock passphrase before showing it again. The passphrase is
+//! never stored: the vault keeps a small Cocoon container sealed with it, and
+//! unlocking succeeds when that container opens. While a user is locked the
+//! daemon's desktop notifications say only how many items are due.
+
+use std::collections::HashSet;
+use std::sync::{OnceLock, RwLock};
+
+use base64::{engine::general_purpose, Engine as _};
+use cocoon::Cocoon;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::vault;
+
+const VERIFIER_SECRET: &str = "ui_lock_verifier";
+const VERIFIER_MARKER: &[u8] = b"butterfly-bot ui lock";
+
+pub const DEFAULT_IDLE_MINUTES: u64 = 0;
+
+/// Reads `tools.settings.privacy_lock.idle_minutes`; zero turns the idle
+/// lock off and leaves only the manual one.
+pub fn idle_minutes_from_tools(tools: Option<&Value>) -> u64 {
+    tools
+        .and_then(|tools| tools.get("settings"))
+        .and_then(|settings| settings.get("privacy_lock"))
+        .and_then(|lock| lock.get("idle_minutes"))
+        .and_then(|value| value.as_u64())
+        .unwrap_or(DEFAULT_IDLE_MINUTES)
+}
+
+pub fn set_passphrase(passphrase: &str) -> Result<()> {
+    let verifier = seal_verifier(passphrase)?;
+    vault::set_secret(VERIFIER_SECRET, &verifier)
+}
+
+pub fn has_passphrase() -> bool {
+    matches!(vault::get_secret(VERIFIER_SECRET), Ok(Some(value)) if !value.is_empty())
+}
+
+/// Slow on purpose (Cocoon's key derivation); call it off the UI thread.
+pub fn verify_passphrase(passphrase: &str) -> Result<bool> {
+    let verifier = vault::get_secret(VERIFIER_SECRET)?
+        .ok_or_else(|| ButterflyBotError::Config("No lock passphrase is set".to_string()))?;
+    Ok(verifier_matches(&verifier, passphrase))
+}
+
+fn seal_verifier(passphrase: &str) -> Result<String> {
+    if passphrase.chars().count() < 4 {
+        return Err(ButterflyBotError::Config(
+            "Lock passphrase must be at least 4 characters".to_string(),
+        ));
+    }
+    let mut cocoon = Cocoon::new(passphrase.as_bytes());
+    let sealed = cocoon.wrap(VERIFIER_MARKER).map_err(|e| {
+        ButterflyBotError::SecurityStorage(format!("failed to seal lock verifier: {e:?}"))
+    })?;
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+fn verifier_matches(verifier: &str, passphrase: &str) -> bool {
+    let Ok(sealed) = general_purpose::STANDARD.decode(verifier.trim()) else {
+        return false;
+    };
+    Cocoon::new(passphrase.as_bytes())
+        .unwrap(&sealed)
+        .map(|marker| marker == VERIFIER_MARKER)
+        .unwrap_or(false)
+}
+
+fn locked_users() -> &'static RwLock<HashSet<String>> {
+    static LOCKED: OnceLock<RwLock<HashSet<String>>> = OnceLock::new();
+    LOCKED.get_or_init(|| RwLock::new(HashSet::new()))
+}
+
+pub fn set_locked(user_id: &str, locked: bool) {
+    let mut guard = locked_users()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    if locked {
+        guard.insert(user_id.to_string());
+    } else {
+        guard.remove(user_id);
+    }
+}
+
+pub fn is_locked(user_id: &str) -> bool {
+    locked_users()
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .contains(user_id)
+}
+
+/// Notification body for due reminders: their titles normally, only a count
+/// while the user's UI is locked.
+pub fn reminder_notification_body(user_id: &str, titles: &[&str]) -> String {
+    if is_locked(user_id) {
+        return match titles.len() {
+            1 => "1 reminder due".to_string(),
+            count => format!("{count} reminders due"),
+        };
+    }
+    titles.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn verifier_opens_only_with_the_passphrase() {
+        let verifier = seal_verifier("open sesame").unwrap();
+        assert!(verifier_matches(&verifier, "open sesame"));
+        assert!(!verifier_matches(&verifier, "open sesame!"));
+        assert!(!verifier_matches("not base64", "open sesame"));
+        assert!(seal_verifier("abc").is_err());
+    }
+
+    #[test]
+    fn locked_users_get_generic_notifications() {
+        let user = "privacy-lock-test-user";
+        assert_eq!(
+            reminder_notification_body(user, &["Call the bank"]),
+            "Call the bank"
+        );
+        set_locked(user, true);
+        assert_eq!(
+            reminder_notification_body(user, &["Call the bank"]),
+            "1 reminder due"
+        );
+        assert_eq!(
+            reminder_notification_body(user, &["a", "b"]),
+            "2 reminders due"
+        );
+        set_locked(user, false);
+        assert!(!is_locked(user));
+
+        assert_eq!(idle_minutes_from_tools(None), 0);
+        let tools = json!({"settings": {"privacy_lock": {"idle_minutes": 5}}});
+        assert_eq!(idle_minutes_from_tools(Some(&tools)), 5);
+    }
+}
@@ -0,0 +1,615 @@
+//! Projects that group todos, plans, scheduled tasks and reminders.
+//!
+//! A user's projects live in `projects`; each store's table carries a
+//! nullable `project_id`. Items are assigned after they are created, so the
+//! stores themselves never need to know about projects, and the column rides
+//! along into the trash and back. Deleting a project detaches its items
+//! rather than deleting them.
+
+use std::collections::HashMap;
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::projects;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const PROJECTS_UP_SQL: &str = include_str!("../../migrations/20260323_create_projects/up.sql");
+const MAX_NAME_LEN: usize = 64;
+/// How many rows a project-filtered list reads before filtering, so `limit`
+/// still counts matching items.
+pub const FILTER_SCAN_LIMIT: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectTarget {
+    Todo,
+    Plan,
+    Task,
+    Reminder,
+}
+
+impl ProjectTarget {
+    pub const ALL: [Self; 4] = [Self::Todo, Self::Plan, Self::Task, Self::Reminder];
+
+    fn table(self) -> &'static str {
+        match self {
+            Self::Todo => "todo_items",
+            Self::Plan => "plans",
+            Self::Task => "scheduled_tasks",
+            Self::Reminder => "reminders",
+        }
+    }
+
+    fn noun(self) -> &'static str {
+        match self {
+            Self::Todo => "todo",
+            Self::Plan => "plan",
+            Self::Task => "task",
+            Self::Reminder => "reminder",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Queryable)]
+pub struct Project {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// A project with how many items of each kind it holds.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, QueryableByName)]
+pub struct ProjectSummary {
+    #[diesel(sql_type = Integer)]
+    pub id: i32,
+    #[diesel(sql_type = Text)]
+    pub name: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    pub description: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    pub todos: i64,
+    #[diesel(sql_type = BigInt)]
+    pub plans: i64,
+    #[diesel(sql_type = BigInt)]
+    pub tasks: i64,
+    #[diesel(sql_type = BigInt)]
+    pub reminders: i64,
+}
+
+#[derive(QueryableByName)]
+struct AssignmentRow {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    #[diesel(sql_type = Integer)]
+    project_id: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = projects)]
+struct NewProject<'a> {
+    user_id: &'a str,
+    name: &'a str,
+    description: Option<&'a str>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// Trims a project name and collapses its whitespace. `None` for empty or
+/// overlong names.
+pub fn normalize_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() || name.chars().count() > MAX_NAME_LEN {
+        return None;
+    }
+    Some(name)
+}
+
+/// The `project` argument of a list call, with what is needed to filter by
+/// it and to tag results with their project.
+pub struct ProjectScope {
+    pub project: Option<Project>,
+    assignments: HashMap<i32, i32>,
+}
+
+impl ProjectScope {
+    pub fn project_of(&self, item_id: i32) -> Option<i32> {
+        self.assignments.get(&item_id).copied()
+    }
+
+    /// The row limit to request from a store, so `limit` still counts
+    /// matching items once the filter drops the rest.
+    pub fn scan_limit(&self, scan: usize) -> usize {
+        match self.project {
+            Some(_) => scan.max(FILTER_SCAN_LIMIT),
+            None => scan,
+        }
+    }
+
+    /// Keeps the items in the requested project; all of them without one.
+    pub fn retain<T>(&self, items: &mut Vec<T>, item_id: impl Fn(&T) -> i32) {
+        if let Some(project) = &self.project {
+            items.retain(|item| self.project_of(item_id(item)) == Some(project.id));
+        }
+    }
+
+    /// Adds a `project_id` field to each serialized item that belongs to a
+    /// project, keyed by the item's `id`.
+    pub fn attach(&self, items: &mut [Value]) {
+        for item in items {
+            let project_id = item
+                .get("id")
+                .and_then(|id| id.as_i64())
+                .and_then(|id| self.project_of(id as i32));
+            if let (Some(project_id), Some(object)) = (project_id, item.as_object_mut()) {
+                object.insert("project_id".to_string(), Value::from(project_id));
+            }
+        }
+    }
+}
+
+pub struct ProjectStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl ProjectStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_projects_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for timestamps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn create_project(
+        &self,
+        user_id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Project> {
+        let name = normalize_name(name)
+            .ok_or_else(|| ButterflyBotError::Runtime("Invalid project name".to_string()))?;
+        if self.find_by_name(user_id, &name).await?.is_some() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Project '{name}' already exists"
+            )));
+        }
+        let now = self.clock.now();
+        let description = description.map(str::trim).filter(|text| !text.is_empty());
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(projects::table)
+            .values(&NewProject {
+                user_id,
+                name: &name,
+                description,
+                created_at: now,
+                updated_at: now,
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+        self.find_by_name(user_id, &name)
+            .await?
+            .ok_or_else(|| ButterflyBotError::Runtime("Project was not created".to_string()))
+    }
+
+    /// The user's projects by name, with item counts.
+    pub async fn list_projects(&self, user_id: &str) -> Result<Vec<ProjectSummary>> {
+        let mut conn = self.conn().await?;
+        diesel::sql_query(
+            "SELECT p.id AS id, p.name AS name, p.description AS description, \
+               (SELECT COUNT(*) FROM todo_items WHERE user_id = p.user_id AND project_id = p.id) AS todos, \
+               (SELECT COUNT(*) FROM plans WHERE user_id = p.user_id AND project_id = p.id) AS plans, \
+               (SELECT COUNT(*) FROM scheduled_tasks WHERE user_id = p.user_id AND project_id = p.id) AS tasks, \
+               (SELECT COUNT(*) FROM reminders WHERE user_id = p.user_id AND project_id = p.id) AS reminders \
+             FROM projects p WHERE p.user_id = ? ORDER BY p.name COLLATE NOCASE",
+        )
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Renames a project and/or replaces its description; an empty
+    /// description clears it.
+    pub async fn update_project(
+        &self,
+        user_id: &str,
+        project_id: i32,
+        name: Option<&str>,
+        description: Option<&str>,
+    ) -> Result<Project> {
+        let current = self
+            .get_project(user_id, project_id)
+            .await?
+            .ok_or_else(|| ButterflyBotError::Runtime(format!("Project {project_id} not found")))?;
+        let name = match name {
+            Some(name) => normalize_name(name)
+                .ok_or_else(|| ButterflyBotError::Runtime("Invalid project name".to_string()))?,
+            None => current.name.clone(),
+        };
+        if let Some(existing) = self.find_by_name(user_id, &name).await? {
+            if existing.id != project_id {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Project '{name}' already exists"
+                )));
+            }
+        }
+        let description = match description {
+            Some(text) => Some(text.trim()).filter(|text| !text.is_empty()),
+            None => current.description.as_deref(),
+        };
+        let mut conn = self.write_conn().await?;
+        diesel::update(
+            projects::table
+                .filter(projects::user_id.eq(user_id))
+                .filter(projects::id.eq(project_id)),
+        )
+        .set((
+            projects::name.eq(&name),
+            projects::description.eq(description),
+            projects::updated_at.eq(self.clock.now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+        self.get_project(user_id, project_id)
+            .await?
+            .ok_or_else(|| ButterflyBotError::Runtime(format!("Project {project_id} not found")))
+    }
+
+    /// Deletes a project and detaches its items, trashed ones included.
+    /// Returns how many live items were detached.
+    pub async fn delete_project(&self, user_id: &str, project_id: i32) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        let mut detached = 0;
+        for target in ProjectTarget::ALL {
+            detached += diesel::sql_query(format!(
+                "UPDATE {table} SET project_id = NULL WHERE user_id = ? AND project_id = ?",
+                table = target.table(),
+            ))
+            .bind::<Text, _>(user_id)
+            .bind::<Integer, _>(project_id)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            diesel::sql_query(format!(
+                "UPDATE {table}_trash SET project_id = NULL WHERE user_id = ? AND project_id = ?",
+                table = target.table(),
+            ))
+            .bind::<Text, _>(user_id)
+            .bind::<Integer, _>(project_id)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+        let deleted = diesel::delete(
+            projects::table
+                .filter(projects::user_id.eq(user_id))
+                .filter(projects::id.eq(project_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        if deleted == 0 {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Project {project_id} not found"
+            )));
+        }
+        Ok(detached)
+    }
+
+    pub async fn get_project(&self, user_id: &str, project_id: i32) -> Result<Option<Project>> {
+        let mut conn = self.conn().await?;
+        projects::table
+            .filter(projects::user_id.eq(user_id))
+            .filter(projects::id.eq(project_id))
+            .select((
+                projects::id,
+                projects::name,
+                projects::description,
+                projects::created_at,
+                projects::updated_at,
+            ))
+            .first::<Project>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Case-insensitive lookup by name.
+    pub async fn find_by_name(&self, user_id: &str, name: &str) -> Result<Option<Project>> {
+        let Some(name) = normalize_name(name) else {
+            return Ok(None);
+        };
+        let mut conn = self.conn().await?;
+        let rows = projects::table
+            .filter(projects::user_id.eq(user_id))
+            .select((
+                projects::id,
+                projects::name,
+                projects::description,
+                projects::created_at,
+                projects::updated_at,
+            ))
+            .load::<Project>(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .find(|project| project.name.eq_ignore_ascii_case(&name)))
+    }
+
+    /// Resolves a `project` argument given as an id or a name. Missing, null
+    /// and empty values mean no project; anything else must exist.
+    pub async fn resolve(&self, user_id: &str, value: Option<&Value>) -> Result<Option<Project>> {
+        let found = match value {
+            None | Some(Value::Null) => return Ok(None),
+            Some(Value::String(name)) if name.trim().is_empty() => return Ok(None),
+            Some(Value::Number(id)) => match id.as_i64() {
+                Some(id) => self.get_project(user_id, id as i32).await?,
+                None => None,
+            },
+            Some(Value::String(name)) => match self.find_by_name(user_id, name).await? {
+                Some(project) => Some(project),
+                None => match name.trim().parse::<i32>() {
+                    Ok(id) => self.get_project(user_id, id).await?,
+                    Err(_) => None,
+                },
+            },
+            Some(_) => None,
+        };
+        match found {
+            Some(project) => Ok(Some(project)),
+            None => Err(ButterflyBotError::Runtime(format!(
+                "Unknown project {}",
+                value.map(Value::to_string).unwrap_or_default()
+            ))),
+        }
+    }
+
+    /// Moves an item into `project_id`, or out of any project with `None`.
+    /// Returns false when the user has no such item.
+    pub async fn assign(
+        &self,
+        user_id: &str,
+        target: ProjectTarget,
+        item_id: i32,
+        project_id: Option<i32>,
+    ) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::sql_query(format!(
+            "UPDATE {table} SET project_id = ? WHERE id = ? AND user_id = ?",
+            table = target.table(),
+        ))
+        .bind::<Nullable<Integer>, _>(project_id)
+        .bind::<Integer, _>(item_id)
+        .bind::<Text, _>(user_id)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Moves an item into the project `value` names, or out of any project
+    /// when it names none. Errors when the user has no such item.
+    pub async fn move_item(
+        &self,
+        user_id: &str,
+        target: ProjectTarget,
+        item_id: i32,
+        value: Option<&Value>,
+    ) -> Result<Option<Project>> {
+        let project = self.resolve(user_id, value).await?;
+        let project_id = project.as_ref().map(|project| project.id);
+        if !self.assign(user_id, target, item_id, project_id).await? {
+            return Err(ButterflyBotError::Runtime(format!(
+                "No {} with id {item_id}",
+                target.noun()
+            )));
+        }
+        Ok(project)
+    }
+
+    /// Project id per item id for the user's items that belong to a project.
+    pub async fn assignments(
+        &self,
+        user_id: &str,
+        target: ProjectTarget,
+    ) -> Result<HashMap<i32, i32>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<AssignmentRow> = diesel::sql_query(format!(
+            "SELECT id, project_id FROM {table} WHERE user_id = ? AND project_id IS NOT NULL",
+            table = target.table(),
+        ))
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.id, row.project_id))
+            .collect())
+    }
+
+    /// Resolves a list call's `project` argument against `target`.
+    pub async fn scope(
+        &self,
+        user_id: &str,
+        target: ProjectTarget,
+        value: Option<&Value>,
+    ) -> Result<ProjectScope> {
+        Ok(ProjectScope {
+            project: self.resolve(user_id, value).await?,
+            assignments: self.assignments(user_id, target).await?,
+        })
+    }
+
+    /// Removes the user's projects. Their items are cleared by the stores.
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(projects::table.filter(projects::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_projects_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM projects LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, PROJECTS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::todo::TodoStore;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn projects_group_items_and_detach_on_delete() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("projects.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ProjectStore::new(&db_path)
+            .await
+            .expect("store")
+            .with_clock(ManualClock::new(1_767_268_800));
+        let todos = TodoStore::new(&db_path).await.expect("todos");
+
+        let launch = store
+            .create_project("u1", "  Launch   Site ", Some("Q3"))
+            .await
+            .expect("create");
+        assert_eq!(launch.name, "Launch Site");
+        assert!(store
+            .create_project("u1", "launch site", None)
+            .await
+            .is_err());
+        assert!(store
+            .create_project("u2", "Launch Site", None)
+            .await
+            .is_ok());
+
+        let todo = todos
+            .create_item("u1", "Write copy", None, None)
+            .await
+            .unwrap();
+        let resolved = store
+            .resolve("u1", Some(&json!("LAUNCH SITE")))
+            .await
+            .unwrap()
+            .expect("project");
+        assert_eq!(resolved.id, launch.id);
+        assert!(store
+            .assign("u1", ProjectTarget::Todo, todo.id, Some(launch.id))
+            .await
+            .unwrap());
+        assert!(!store
+            .assign("u2", ProjectTarget::Todo, todo.id, Some(launch.id))
+            .await
+            .unwrap());
+        assert!(store.resolve("u1", Some(&json!("nope"))).await.is_err());
+        assert_eq!(store.resolve("u1", Some(&json!(""))).await.unwrap(), None);
+
+        let summary = store.list_projects("u1").await.unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].todos, 1);
+
+        let scope = store
+            .scope("u1", ProjectTarget::Todo, Some(&json!(launch.id)))
+            .await
+            .unwrap();
+        let mut ids = vec![todo.id, todo.id + 1];
+        scope.retain(&mut ids, |id| *id);
+        assert_eq!(ids, vec![todo.id]);
+        assert_eq!(scope.scan_limit(20), FILTER_SCAN_LIMIT);
+        let mut items = vec![json!({"id": todo.id}), json!({"id": todo.id + 1})];
+        scope.attach(&mut items);
+        assert_eq!(items[0]["project_id"], json!(launch.id));
+        assert!(items[1].get("project_id").is_none());
+
+        let renamed = store
+            .update_project("u1", launch.id, Some("Launch"), Some(""))
+            .await
+            .unwrap();
+        assert_eq!(
+            (renamed.name.as_str(), renamed.description),
+            ("Launch", None)
+        );
+
+        assert_eq!(store.delete_project("u1", launch.id).await.unwrap(), 1);
+        assert!(store
+            .assignments("u1", ProjectTarget::Todo)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(store.delete_project("u1", launch.id).await.is_err());
+    }
+}
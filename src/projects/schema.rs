@@ -0,0 +1,10 @@
+diesel::table! {
+    projects (id) {
+        id -> Integer,
+        user_id -> Text,
+        name -> Text,
+        description -> Nullable<Text>,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
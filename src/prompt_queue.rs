@@ -0,0 +1,517 @@
+//! Admission queue in front of the agent.
+//!
+//! The UI, the scheduler and webhook callers all end up in
+//! `ButterflyBot::process`, and a local model falls over when they pile up.
+//! Every prompt takes a ticket here first: tickets are granted by priority
+//! (interactive, then scheduled, then background) within a global and a
+//! per-source concurrency limit, and background tickets that wait too long
+//! are shed instead of running against stale state.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::error::{ButterflyBotError, Result};
+
+const DEFAULT_MAX_CONCURRENT: usize = 2;
+const DEFAULT_PER_SOURCE_LIMIT: usize = 1;
+const DEFAULT_MAX_QUEUED: usize = 32;
+const DEFAULT_BACKGROUND_MAX_WAIT_SECONDS: u64 = 300;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptPriority {
+    Interactive,
+    Scheduled,
+    Background,
+}
+
+impl PromptPriority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "interactive" | "ui" | "high" => Some(Self::Interactive),
+            "scheduled" | "normal" => Some(Self::Scheduled),
+            "background" | "low" => Some(Self::Background),
+            _ => None,
+        }
+    }
+}
+
+/// `tools.settings.prompt_queue` in the config.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct PromptQueueConfig {
+    pub max_concurrent: Option<usize>,
+    /// Concurrency per source (`ui`, `tasks`, `wakeup`, `autonomy`, ...).
+    /// Sources not listed get `default_per_source`.
+    #[serde(default)]
+    pub per_source: HashMap<String, usize>,
+    pub default_per_source: Option<usize>,
+    pub max_queued: Option<usize>,
+    pub background_max_wait_seconds: Option<u64>,
+}
+
+impl PromptQueueConfig {
+    pub fn from_tools(tools: Option<&serde_json::Value>) -> Self {
+        tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("prompt_queue"))
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    fn max_concurrent(&self) -> usize {
+        self.max_concurrent.unwrap_or(DEFAULT_MAX_CONCURRENT).max(1)
+    }
+
+    fn source_limit(&self, source: &str) -> usize {
+        self.per_source
+            .get(source)
+            .copied()
+            .or(self.default_per_source)
+            .unwrap_or(DEFAULT_PER_SOURCE_LIMIT)
+            .max(1)
+    }
+
+    fn max_queued(&self) -> usize {
+        self.max_queued.unwrap_or(DEFAULT_MAX_QUEUED).max(1)
+    }
+
+    fn background_max_wait(&self) -> Duration {
+        Duration::from_secs(
+            self.background_max_wait_seconds
+                .unwrap_or(DEFAULT_BACKGROUND_MAX_WAIT_SECONDS),
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TicketStatus {
+    /// 1-based position among waiting tickets.
+    Queued(usize),
+    Ready,
+    Shed(String),
+}
+
+struct Waiter {
+    seq: u64,
+    source: String,
+    priority: PromptPriority,
+    enqueued_at: Instant,
+    status: watch::Sender<TicketStatus>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    config: PromptQueueConfig,
+    running: usize,
+    running_by_source: HashMap<String, usize>,
+    waiting: Vec<Waiter>,
+    next_seq: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct QueuedEntry {
+    pub source: String,
+    pub priority: PromptPriority,
+    pub position: usize,
+    pub waited_seconds: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct QueueSnapshot {
+    pub running: usize,
+    pub max_concurrent: usize,
+    pub running_by_source: HashMap<String, usize>,
+    pub queued: Vec<QueuedEntry>,
+}
+
+#[derive(Default)]
+pub struct PromptQueue {
+    state: Mutex<QueueState>,
+}
+
+/// The daemon-wide queue.
+pub fn shared() -> Arc<PromptQueue> {
+    static QUEUE: OnceLock<Arc<PromptQueue>> = OnceLock::new();
+    QUEUE
+        .get_or_init(|| Arc::new(PromptQueue::default()))
+        .clone()
+}
+
+impl PromptQueue {
+    pub fn new(config: PromptQueueConfig) -> Arc<Self> {
+        let queue = Self::default();
+        queue.lock().config = config;
+        Arc::new(queue)
+    }
+
+    /// Applies new limits; raised limits take effect for waiting tickets
+    /// right away, lowered ones as running prompts finish.
+    pub fn configure(&self, config: PromptQueueConfig) {
+        let mut state = self.lock();
+        state.config = config;
+        dispatch(&mut state);
+    }
+
+    pub fn enqueue(
+        self: &Arc<Self>,
+        source: &str,
+        priority: PromptPriority,
+    ) -> Result<QueueTicket> {
+        let mut state = self.lock();
+        shed_stale(&mut state);
+        if state.waiting.len() >= state.config.max_queued() {
+            let oldest_background = state
+                .waiting
+                .iter()
+                .filter(|waiter| waiter.priority == PromptPriority::Background)
+                .min_by_key(|waiter| waiter.seq)
+                .map(|waiter| waiter.seq);
+            match oldest_background {
+                Some(seq) if priority < PromptPriority::Background => {
+                    shed(&mut state, seq, "displaced by a higher-priority prompt");
+                }
+                _ => {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Prompt queue is full ({} waiting); try again shortly",
+                        state.waiting.len()
+                    )));
+                }
+            }
+        }
+
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        let (status, rx) = watch::channel(TicketStatus::Queued(state.waiting.len() + 1));
+        state.waiting.push(Waiter {
+            seq,
+            source: source.to_string(),
+            priority,
+            enqueued_at: Instant::now(),
+            status,
+        });
+        let max_wait =
+            (priority == PromptPriority::Background).then(|| state.config.background_max_wait());
+        dispatch(&mut state);
+        Ok(QueueTicket {
+            queue: self.clone(),
+            seq,
+            source: source.to_string(),
+            max_wait,
+            rx,
+            settled: false,
+        })
+    }
+
+    /// Waits for a slot without reporting progress.
+    pub async fn acquire(
+        self: &Arc<Self>,
+        source: &str,
+        priority: PromptPriority,
+    ) -> Result<PromptPermit> {
+        let mut ticket = self.enqueue(source, priority)?;
+        loop {
+            match ticket.next_status().await {
+                TicketStatus::Queued(_) => continue,
+                TicketStatus::Ready => return Ok(ticket.into_permit()),
+                TicketStatus::Shed(reason) => return Err(ButterflyBotError::Runtime(reason)),
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> QueueSnapshot {
+        let state = self.lock();
+        let now = Instant::now();
+        QueueSnapshot {
+            running: state.running,
+            max_concurrent: state.config.max_concurrent(),
+            running_by_source: state.running_by_source.clone(),
+            queued: ordered(&state.waiting)
+                .into_iter()
+                .enumerate()
+                .map(|(index, waiter)| QueuedEntry {
+                    source: waiter.source.clone(),
+                    priority: waiter.priority,
+                    position: index + 1,
+                    waited_seconds: now.duration_since(waiter.enqueued_at).as_secs(),
+                })
+                .collect(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn release(&self, source: &str) {
+        let mut state = self.lock();
+        state.running = state.running.saturating_sub(1);
+        if let Some(count) = state.running_by_source.get_mut(source) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                state.running_by_source.remove(source);
+            }
+        }
+        dispatch(&mut state);
+    }
+
+    fn withdraw(&self, seq: u64) -> bool {
+        let mut state = self.lock();
+        let before = state.waiting.len();
+        state.waiting.retain(|waiter| waiter.seq != seq);
+        let removed = state.waiting.len() != before;
+        if removed {
+            dispatch(&mut state);
+        }
+        removed
+    }
+
+    fn expire(&self) {
+        let mut state = self.lock();
+        shed_stale(&mut state);
+        dispatch(&mut state);
+    }
+}
+
+fn ordered(waiting: &[Waiter]) -> Vec<&Waiter> {
+    let mut ordered = waiting.iter().collect::<Vec<_>>();
+    ordered.sort_by_key(|waiter| (waiter.priority, waiter.seq));
+    ordered
+}
+
+fn shed(state: &mut QueueState, seq: u64, reason: &str) {
+    if let Some(index) = state.waiting.iter().position(|waiter| waiter.seq == seq) {
+        let waiter = state.waiting.remove(index);
+        let _ = waiter.status.send(TicketStatus::Shed(format!(
+            "Background prompt from {} dropped: {reason}",
+            waiter.source
+        )));
+    }
+}
+
+fn shed_stale(state: &mut QueueState) {
+    let max_wait = state.config.background_max_wait();
+    let stale = state
+        .waiting
+        .iter()
+        .filter(|waiter| {
+            waiter.priority == PromptPriority::Background
+                && waiter.enqueued_at.elapsed() >= max_wait
+        })
+        .map(|waiter| waiter.seq)
+        .collect::<Vec<_>>();
+    for seq in stale {
+        shed(state, seq, "waited too long in the queue");
+    }
+}
+
+/// Grants slots to the best waiting tickets whose source still has room,
+/// then refreshes everyone else's position.
+fn dispatch(state: &mut QueueState) {
+    while state.running < state.config.max_concurrent() {
+        let next = ordered(&state.waiting)
+            .into_iter()
+            .find(|waiter| {
+                state
+                    .running_by_source
+                    .get(&waiter.source)
+                    .copied()
+                    .unwrap_or(0)
+                    < state.config.source_limit(&waiter.source)
+            })
+            .map(|waiter| waiter.seq);
+        let Some(seq) = next else {
+            break;
+        };
+        let index = state
+            .waiting
+            .iter()
+            .position(|waiter| waiter.seq == seq)
+            .expect("selected waiter is queued");
+        let waiter = state.waiting.remove(index);
+        state.running += 1;
+        *state
+            .running_by_source
+            .entry(waiter.source.clone())
+            .or_insert(0) += 1;
+        let _ = waiter.status.send(TicketStatus::Ready);
+    }
+
+    for (index, waiter) in ordered(&state.waiting).into_iter().enumerate() {
+        let position = TicketStatus::Queued(index + 1);
+        waiter.status.send_if_modified(|status| {
+            if *status == position {
+                false
+            } else {
+                *status = position;
+                true
+            }
+        });
+    }
+}
+
+/// A place in the queue. Dropping it gives the place up, including a slot
+/// that was granted but never turned into a permit.
+pub struct QueueTicket {
+    queue: Arc<PromptQueue>,
+    seq: u64,
+    source: String,
+    max_wait: Option<Duration>,
+    rx: watch::Receiver<TicketStatus>,
+    settled: bool,
+}
+
+impl QueueTicket {
+    pub fn status(&self) -> TicketStatus {
+        self.rx.borrow().clone()
+    }
+
+    /// Returns immediately when the ticket is already ready or shed,
+    /// otherwise waits for the next change.
+    pub async fn next_status(&mut self) -> TicketStatus {
+        let current = self.status();
+        if !matches!(current, TicketStatus::Queued(_)) {
+            return current;
+        }
+        let changed = match self.max_wait {
+            Some(max_wait) => match tokio::time::timeout(max_wait, self.rx.changed()).await {
+                Ok(changed) => changed,
+                Err(_) => {
+                    self.queue.expire();
+                    Ok(())
+                }
+            },
+            None => self.rx.changed().await,
+        };
+        if changed.is_err() {
+            return TicketStatus::Shed("Prompt queue closed".to_string());
+        }
+        self.status()
+    }
+
+    /// Converts a ready ticket into a running permit.
+    pub fn into_permit(mut self) -> PromptPermit {
+        self.settled = true;
+        PromptPermit {
+            queue: self.queue.clone(),
+            source: std::mem::take(&mut self.source),
+        }
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        if self.settled {
+            return;
+        }
+        if !self.queue.withdraw(self.seq) && self.status() == TicketStatus::Ready {
+            self.queue.release(&self.source);
+        }
+    }
+}
+
+/// A running slot, released on drop.
+pub struct PromptPermit {
+    queue: Arc<PromptQueue>,
+    source: String,
+}
+
+impl Drop for PromptPermit {
+    fn drop(&mut self) {
+        self.queue.release(&self.source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_concurrent: usize) -> PromptQueueConfig {
+        PromptQueueConfig {
+            max_concurrent: Some(max_concurrent),
+            default_per_source: Some(4),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn interactive_jumps_ahead_of_waiting_background_work() {
+        let queue = PromptQueue::new(config(1));
+        let running = queue
+            .acquire("tasks", PromptPriority::Scheduled)
+            .await
+            .unwrap();
+        let background = queue
+            .enqueue("autonomy", PromptPriority::Background)
+            .unwrap();
+        let interactive = queue.enqueue("ui", PromptPriority::Interactive).unwrap();
+        assert_eq!(interactive.status(), TicketStatus::Queued(1));
+        assert_eq!(background.status(), TicketStatus::Queued(2));
+
+        drop(running);
+        assert_eq!(interactive.status(), TicketStatus::Ready);
+        assert_eq!(background.status(), TicketStatus::Queued(1));
+
+        let permit = interactive.into_permit();
+        drop(permit);
+        assert_eq!(background.status(), TicketStatus::Ready);
+        drop(background);
+        assert_eq!(queue.snapshot().running, 0);
+    }
+
+    #[tokio::test]
+    async fn per_source_limit_lets_other_sources_through() {
+        let queue = PromptQueue::new(PromptQueueConfig {
+            max_concurrent: Some(3),
+            per_source: HashMap::from([("wakeup".to_string(), 1)]),
+            ..Default::default()
+        });
+        let _first = queue
+            .acquire("wakeup", PromptPriority::Scheduled)
+            .await
+            .unwrap();
+        let second = queue.enqueue("wakeup", PromptPriority::Scheduled).unwrap();
+        let ui = queue.enqueue("ui", PromptPriority::Interactive).unwrap();
+        assert_eq!(second.status(), TicketStatus::Queued(1));
+        assert_eq!(ui.status(), TicketStatus::Ready);
+    }
+
+    #[tokio::test]
+    async fn stale_or_displaced_background_prompts_are_shed() {
+        let queue = PromptQueue::new(PromptQueueConfig {
+            max_concurrent: Some(1),
+            max_queued: Some(1),
+            background_max_wait_seconds: Some(0),
+            ..Default::default()
+        });
+        let _running = queue
+            .acquire("ui", PromptPriority::Interactive)
+            .await
+            .unwrap();
+        let mut background = queue
+            .enqueue("autonomy", PromptPriority::Background)
+            .unwrap();
+        assert!(matches!(
+            background.next_status().await,
+            TicketStatus::Shed(_)
+        ));
+
+        queue.configure(PromptQueueConfig {
+            max_concurrent: Some(1),
+            max_queued: Some(1),
+            ..Default::default()
+        });
+        let background = queue
+            .enqueue("autonomy", PromptPriority::Background)
+            .unwrap();
+        let scheduled = queue.enqueue("tasks", PromptPriority::Scheduled).unwrap();
+        assert!(matches!(background.status(), TicketStatus::Shed(_)));
+        assert_eq!(scheduled.status(), TicketStatus::Queued(1));
+        assert!(queue.enqueue("webhook", PromptPriority::Scheduled).is_err());
+    }
+}
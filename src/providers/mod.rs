@@ -1,3 +1,4 @@
 pub mod memory;
 pub mod openai;
+pub mod retention;
 pub mod sqlite;
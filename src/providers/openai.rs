@@ -45,6 +45,7 @@ pub struct OpenAiProvider {
     client: Client<OpenAIConfig>,
     api_key: String,
     base_url: String,
+    temperature: Option<f32>,
 }
 
 impl OpenAiProvider {
@@ -69,9 +70,17 @@ impl OpenAiProvider {
             client: Client::with_config(config),
             api_key,
             base_url,
+            temperature: None,
         }
     }
 
+    /// Sampling temperature sent with every chat completion; the endpoint
+    /// default applies when unset.
+    pub fn with_temperature(mut self, temperature: Option<f32>) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
     async fn raw_chat_completion(
         &self,
         request: &async_openai::types::chat::CreateChatCompletionRequest,
@@ -519,6 +528,9 @@ impl LlmProvider for OpenAiProvider {
 
         let mut builder = CreateChatCompletionRequestArgs::default();
         builder.model(self.model.clone());
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
         builder.messages(messages);
 
         if let Some(tools) = tools {
@@ -588,6 +600,9 @@ impl LlmProvider for OpenAiProvider {
         let tools = Self::convert_tools(tools);
         let mut builder = CreateChatCompletionRequestArgs::default();
         builder.model(self.model.clone());
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
         builder.messages(messages);
         if !tools.is_empty() {
             builder.tools(tools);
@@ -643,6 +658,9 @@ impl LlmProvider for OpenAiProvider {
 
             let mut builder = CreateChatCompletionRequestArgs::default();
             builder.model(provider.model.clone());
+            if let Some(temperature) = provider.temperature {
+                builder.temperature(temperature);
+            }
             builder.messages(request_messages);
 
             if let Some(tools) = tools {
@@ -717,6 +735,9 @@ impl LlmProvider for OpenAiProvider {
 
         let mut builder = CreateChatCompletionRequestArgs::default();
         builder.model(self.model.clone());
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
         builder.messages(messages);
         builder.response_format(response_format);
 
@@ -804,6 +825,9 @@ impl LlmProvider for OpenAiProvider {
 
         let mut builder = CreateChatCompletionRequestArgs::default();
         builder.model(self.model.clone());
+        if let Some(temperature) = self.temperature {
+            builder.temperature(temperature);
+        }
         builder.messages(messages);
 
         if let Some(tools) = tools {
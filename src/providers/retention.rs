@@ -0,0 +1,176 @@
+//! Retention policy for chat history.
+//!
+//! Old turns are folded into summary memories with `memory.summary_model`
+//! before their raw rows are deleted, so long-running installs keep what was
+//! said without keeping every message. While summarization is configured,
+//! deletion never passes the summarized watermark: a missing or failing
+//! summarizer holds raw turns back instead of losing them.
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::MemoryConfig;
+
+const DAY_SECONDS: i64 = 24 * 60 * 60;
+pub const DEFAULT_BATCH_SIZE: usize = 40;
+pub const DEFAULT_INTERVAL_MINUTES: u64 = 360;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub summarize_after_days: Option<u32>,
+    pub delete_after_days: Option<u32>,
+    pub summary_max_age_days: Option<u32>,
+    pub batch_size: usize,
+    pub interval_minutes: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            summarize_after_days: None,
+            delete_after_days: None,
+            summary_max_age_days: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            interval_minutes: DEFAULT_INTERVAL_MINUTES,
+        }
+    }
+}
+
+fn cutoff(days: Option<u32>, now: i64) -> Option<i64> {
+    days.map(|days| now - days as i64 * DAY_SECONDS)
+}
+
+impl RetentionPolicy {
+    pub fn from_config(config: &MemoryConfig) -> Self {
+        let retention = config.retention.clone().unwrap_or_default();
+        Self {
+            summarize_after_days: retention.summarize_after_days,
+            delete_after_days: retention.delete_after_days.or(config.retention_days),
+            summary_max_age_days: retention.summary_max_age_days,
+            batch_size: retention
+                .batch_size
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_BATCH_SIZE),
+            interval_minutes: retention
+                .interval_minutes
+                .unwrap_or(DEFAULT_INTERVAL_MINUTES),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.summarize_after_days.is_some()
+            || self.delete_after_days.is_some()
+            || self.summary_max_age_days.is_some()
+    }
+
+    /// Turns strictly older than this are summarized.
+    pub fn summarize_cutoff(&self, now: i64) -> Option<i64> {
+        cutoff(self.summarize_after_days, now)
+    }
+
+    /// Turns strictly older than this are deleted, subject to the watermark.
+    pub fn delete_cutoff(&self, now: i64) -> Option<i64> {
+        cutoff(self.delete_after_days, now)
+    }
+
+    /// Summary memories strictly older than this are deleted.
+    pub fn summary_cutoff(&self, now: i64) -> Option<i64> {
+        cutoff(self.summary_max_age_days, now)
+    }
+
+    pub fn holds_unsummarized(&self) -> bool {
+        self.summarize_after_days.is_some()
+    }
+
+    pub fn is_due(&self, last_run_at: Option<i64>, now: i64) -> bool {
+        match last_run_at {
+            Some(last) => now - last >= self.interval_minutes as i64 * 60,
+            None => true,
+        }
+    }
+
+    pub fn batch_count(&self, turns: u64) -> u64 {
+        turns.div_ceil(self.batch_size.max(1) as u64)
+    }
+}
+
+/// Outcome of one retention pass. With `dry_run` set nothing is written and
+/// the counts describe what a real pass would do right now.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub user_id: String,
+    pub dry_run: bool,
+    pub generated_at: i64,
+    pub policy: RetentionPolicy,
+    pub summarizer_available: bool,
+    pub turns_to_summarize: u64,
+    pub summary_batches: u64,
+    pub summaries_written: u64,
+    pub turns_to_delete: u64,
+    /// Turns past the delete age that stay until they are summarized.
+    pub turns_awaiting_summary: u64,
+    pub summaries_to_delete: u64,
+    /// Highest message id already folded into a summary.
+    pub summarized_through_id: i64,
+    pub last_run_at: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MemoryRetentionConfig;
+
+    fn memory_config(
+        retention_days: Option<u32>,
+        retention: Option<MemoryRetentionConfig>,
+    ) -> MemoryConfig {
+        MemoryConfig {
+            enabled: Some(true),
+            sqlite_path: None,
+            summary_model: None,
+            embedding_model: None,
+            rerank_model: None,
+            openai: None,
+            context_embed_enabled: None,
+            summary_threshold: None,
+            retention_days,
+            retention,
+        }
+    }
+
+    #[test]
+    fn legacy_retention_days_only_drives_deletion() {
+        let policy = RetentionPolicy::from_config(&memory_config(Some(30), None));
+        assert!(policy.is_enabled());
+        assert!(!policy.holds_unsummarized());
+        assert_eq!(
+            policy.delete_cutoff(100 * DAY_SECONDS),
+            Some(70 * DAY_SECONDS)
+        );
+        assert_eq!(policy.summarize_cutoff(100 * DAY_SECONDS), None);
+
+        let disabled = RetentionPolicy::from_config(&memory_config(None, None));
+        assert!(!disabled.is_enabled());
+    }
+
+    #[test]
+    fn explicit_policy_overrides_legacy_and_schedules_batches() {
+        let policy = RetentionPolicy::from_config(&memory_config(
+            Some(30),
+            Some(MemoryRetentionConfig {
+                summarize_after_days: Some(7),
+                delete_after_days: Some(60),
+                summary_max_age_days: None,
+                batch_size: Some(0),
+                interval_minutes: Some(10),
+            }),
+        ));
+        assert_eq!(policy.delete_after_days, Some(60));
+        assert!(policy.holds_unsummarized());
+        assert_eq!(policy.batch_size, DEFAULT_BATCH_SIZE);
+        assert_eq!(policy.batch_count(0), 0);
+        assert_eq!(policy.batch_count(41), 2);
+        assert!(policy.is_due(None, 0));
+        assert!(!policy.is_due(Some(1_000), 1_000 + 9 * 60));
+        assert!(policy.is_due(Some(1_000), 1_000 + 10 * 60));
+    }
+}
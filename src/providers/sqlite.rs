@@ -16,9 +16,6 @@ use deadpool_sqlite::{
 use diesel::prelude::*;
 use diesel::sql_types::{BigInt, Text};
 use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use lru::LruCache;
@@ -27,8 +24,11 @@ use sqlite_vec::sqlite3_vec_init;
 use time::{macros::format_description, OffsetDateTime};
 use tracing::{info, warn};
 
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
-use crate::interfaces::providers::{LlmProvider, MemoryProvider};
+use crate::interfaces::providers::{LlmProvider, MemoryHit, MemoryProvider};
+use crate::providers::retention::{RetentionPolicy, RetentionReport};
+use crate::security::user_domains::{self, DomainStatus};
 
 mod schema;
 use schema::messages;
@@ -39,10 +39,6 @@ const CLEAR_HISTORY_MAX_ATTEMPTS: usize = 6;
 const CLEAR_HISTORY_RETRY_BASE_MS: u64 = 100;
 const MESSAGE_VECTOR_SCHEMA_VERSION: i64 = 1;
 
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
-
 #[derive(Queryable)]
 struct MessageRow {
     role: String,
@@ -84,6 +80,14 @@ struct HistoryResetRow {
     reset_at: i64,
 }
 
+#[derive(QueryableByName)]
+struct RetentionStateRow {
+    #[diesel(sql_type = BigInt)]
+    summarized_through_id: i64,
+    #[diesel(sql_type = diesel::sql_types::Nullable<BigInt>)]
+    last_run_at: Option<i64>,
+}
+
 #[derive(Insertable)]
 #[diesel(table_name = messages)]
 struct NewMessage<'a> {
@@ -149,15 +153,14 @@ struct NewMemoryLink<'a> {
 
 pub struct SqliteMemoryProvider {
     sqlite_path: String,
-    pool: SqlitePool,
+    db: DbHandle,
     deadpool: DeadpoolSqlitePool,
-    write_gate: Arc<tokio::sync::Mutex<()>>,
     embedder: Option<Arc<dyn LlmProvider>>,
     embedding_model: Option<String>,
     reranker: Option<Arc<dyn LlmProvider>>,
     summarizer: Option<Arc<dyn LlmProvider>>,
     summary_threshold: usize,
-    retention_days: Option<u32>,
+    retention: RetentionPolicy,
     context_embed_enabled: bool,
     embedding_cache: Arc<tokio::sync::Mutex<LruCache<String, Vec<f32>>>>,
     vector_store_enabled: Arc<AtomicBool>,
@@ -167,15 +170,14 @@ impl Clone for SqliteMemoryProvider {
     fn clone(&self) -> Self {
         Self {
             sqlite_path: self.sqlite_path.clone(),
-            pool: self.pool.clone(),
+            db: self.db.clone(),
             deadpool: self.deadpool.clone(),
-            write_gate: Arc::clone(&self.write_gate),
             embedder: self.embedder.clone(),
             embedding_model: self.embedding_model.clone(),
             reranker: self.reranker.clone(),
             summarizer: self.summarizer.clone(),
             summary_threshold: self.summary_threshold,
-            retention_days: self.retention_days,
+            retention: self.retention.clone(),
             context_embed_enabled: self.context_embed_enabled,
             embedding_cache: Arc::clone(&self.embedding_cache),
             vector_store_enabled: Arc::clone(&self.vector_store_enabled),
@@ -191,7 +193,7 @@ pub struct SqliteMemoryProviderConfig {
     pub summarizer: Option<Arc<dyn LlmProvider>>,
     pub context_embed_enabled: bool,
     pub summary_threshold: Option<usize>,
-    pub retention_days: Option<u32>,
+    pub retention: RetentionPolicy,
 }
 
 impl SqliteMemoryProviderConfig {
@@ -204,7 +206,7 @@ impl SqliteMemoryProviderConfig {
             summarizer: None,
             context_embed_enabled: false,
             summary_threshold: None,
-            retention_days: None,
+            retention: RetentionPolicy::default(),
         }
     }
 }
@@ -216,13 +218,10 @@ impl SqliteMemoryProvider {
         run_migrations(&config.sqlite_path).await?;
         ensure_memory_tables(&config.sqlite_path).await?;
 
-        let manager =
-            AsyncDieselConnectionManager::<SqliteAsyncConn>::new(config.sqlite_path.as_str());
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-
+        let db = DbHandle::open(&config.sqlite_path).await?;
+        // sqlite-vec is registered as an auto extension, so it only loads
+        // into connections opened after registration; the shared pool may
+        // predate it. Vector writes use this pool under the db's write turn.
         let deadpool_cfg = DeadpoolSqliteConfig::new(config.sqlite_path.clone());
         let deadpool = deadpool_cfg
             .create_pool(DeadpoolRuntime::Tokio1)
@@ -230,15 +229,14 @@ impl SqliteMemoryProvider {
 
         Ok(Self {
             sqlite_path: config.sqlite_path,
-            pool,
+            db,
             deadpool,
-            write_gate: Arc::new(tokio::sync::Mutex::new(())),
             embedder: config.embedder,
             embedding_model: config.embedding_model,
             reranker: config.reranker,
             summarizer: config.summarizer,
             summary_threshold: config.summary_threshold.unwrap_or(12),
-            retention_days: config.retention_days,
+            retention: config.retention,
             context_embed_enabled: config.context_embed_enabled,
             embedding_cache: Arc::new(tokio::sync::Mutex::new(LruCache::new(
                 NonZeroUsize::new(256).unwrap(),
@@ -248,13 +246,11 @@ impl SqliteMemoryProvider {
     }
 
     async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 }
 
@@ -344,6 +340,11 @@ async fn ensure_memory_tables(database_url: &str) -> Result<()> {
             "CREATE TABLE IF NOT EXISTS history_resets (
                 user_id TEXT PRIMARY KEY,
                 reset_at BIGINT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS memory_retention_state (
+                user_id TEXT PRIMARY KEY,
+                summarized_through_id BIGINT NOT NULL DEFAULT 0,
+                last_run_at BIGINT
             );",
         )
         .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
@@ -493,6 +494,13 @@ fn is_sqlite_locked_error(message: &str) -> bool {
                 || lower.contains("sqlite_busy")))
 }
 
+fn now_ts() -> Result<i64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+        .as_secs() as i64)
+}
+
 async fn get_history_reset_ts(provider: &SqliteMemoryProvider, user_id: &str) -> Result<i64> {
     let mut conn = provider.conn().await?;
     let row = diesel::sql_query("SELECT reset_at FROM history_resets WHERE user_id = ?1 LIMIT 1")
@@ -523,15 +531,17 @@ impl MemoryProvider for SqliteMemoryProvider {
             .duration_since(UNIX_EPOCH)
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
             .as_secs() as i64;
+        let sealed = user_domains::seal(user_id, "message.content", content)?;
+        // Embeddings and summaries would keep a readable copy of sealed chat.
+        let in_domain = user_domains::status(user_id) != DomainStatus::None;
         let row_id = {
-            let _write_guard = self.write_gate.lock().await;
-            let mut conn = self.conn().await?;
+            let mut conn = self.write_conn().await?;
 
             diesel::insert_into(messages::table)
                 .values(NewMessage {
                     user_id,
                     role,
-                    content,
+                    content: &sealed,
                     timestamp: ts,
                 })
                 .execute(&mut conn)
@@ -554,7 +564,7 @@ impl MemoryProvider for SqliteMemoryProvider {
             inserted_id
         };
 
-        if self.vector_store_enabled.as_ref().load(Ordering::Relaxed) {
+        if !in_domain && self.vector_store_enabled.as_ref().load(Ordering::Relaxed) {
             if let Some(embedder) = &self.embedder {
                 let provider = self.clone();
                 let embedder = embedder.clone();
@@ -611,7 +621,7 @@ impl MemoryProvider for SqliteMemoryProvider {
             }
         }
 
-        if role == "assistant" {
+        if role == "assistant" && !in_domain {
             let provider = self.clone();
             let user_id = user_id.to_string();
             tokio::spawn(async move {
@@ -619,11 +629,13 @@ impl MemoryProvider for SqliteMemoryProvider {
             });
         }
 
-        if let Some(days) = self.retention_days {
+        if self.retention.is_enabled() {
             let provider = self.clone();
             let user_id = user_id.to_string();
             tokio::spawn(async move {
-                let _ = provider.apply_retention(&user_id, days).await;
+                if let Err(err) = provider.enforce_retention_if_due(&user_id).await {
+                    warn!("memory retention pass failed: {}", err);
+                }
             });
         }
         Ok(())
@@ -662,7 +674,7 @@ impl MemoryProvider for SqliteMemoryProvider {
                     "[{}] {}: {}",
                     format_timestamp(row.timestamp),
                     row.role,
-                    row.content
+                    user_domains::open(user_id, "message.content", row.content)
                 )
             })
             .collect())
@@ -674,7 +686,7 @@ impl MemoryProvider for SqliteMemoryProvider {
             .duration_since(UNIX_EPOCH)
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
             .as_secs() as i64;
-        let _write_guard = self.write_gate.lock().await;
+        let _write_turn = self.db.write_turn().await;
 
         for attempt in 1..=CLEAR_HISTORY_MAX_ATTEMPTS {
             let sqlite_result = self.clear_history_with_deadpool(user_id, reset_at).await;
@@ -767,6 +779,64 @@ impl MemoryProvider for SqliteMemoryProvider {
 
         Ok(merged.into_iter().take(limit.max(1)).collect())
     }
+
+    async fn semantic_search(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+        rerank: bool,
+    ) -> Result<Vec<MemoryHit>> {
+        if self.embedder.is_none() {
+            return Err(ButterflyBotError::Config(
+                "Semantic memory search needs memory.embedding_model to be configured".to_string(),
+            ));
+        }
+        let limit = limit.max(1);
+        let reranker = self.reranker.as_ref().filter(|_| rerank);
+        let reset_ts = get_history_reset_ts(self, user_id).await?;
+        let Some(vector) = self.embed_query(query).await? else {
+            return Ok(Vec::new());
+        };
+        // Give the reranker a wider pool than we return.
+        let fetch = if reranker.is_some() { limit * 3 } else { limit };
+        let mut hits: Vec<MemoryHit> = self
+            .search_vector_hits(user_id, reset_ts, &vector, fetch)
+            .await?
+            .into_iter()
+            .map(|(content, timestamp, distance)| MemoryHit {
+                content,
+                timestamp,
+                score: (1.0 - distance) as f32,
+                reranked: false,
+            })
+            .collect();
+
+        if let Some(reranker) = reranker {
+            if hits.len() > 1 {
+                let candidates: Vec<String> = hits.iter().map(|hit| hit.content.clone()).collect();
+                let order = self
+                    .rerank_with_model(reranker, query, &candidates, limit)
+                    .await?;
+                let mut reranked = Vec::with_capacity(order.len());
+                for content in order {
+                    if let Some(pos) = hits.iter().position(|hit| hit.content == content) {
+                        let mut hit = hits.remove(pos);
+                        hit.reranked = true;
+                        reranked.push(hit);
+                    }
+                }
+                hits = reranked;
+            }
+        }
+
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    async fn enforce_retention(&self, user_id: &str, dry_run: bool) -> Result<RetentionReport> {
+        self.retention_pass(user_id, dry_run, now_ts()?).await
+    }
 }
 
 impl SqliteMemoryProvider {
@@ -906,6 +976,7 @@ impl SqliteMemoryProvider {
         let vector_dim = vector.len() as i64;
         let vector_blob = encode_f32_blob(&vector);
 
+        let _write_turn = self.db.write_turn().await;
         let conn = self
             .deadpool
             .get()
@@ -1054,13 +1125,14 @@ impl SqliteMemoryProvider {
         op_result.map_err(ButterflyBotError::Runtime)
     }
 
-    async fn search_vector_rows(
+    /// Nearest stored messages as `(content, timestamp, cosine distance)`.
+    async fn search_vector_hits(
         &self,
         user_id: &str,
         reset_ts: i64,
         vector: &[f32],
         limit: usize,
-    ) -> Result<Vec<String>> {
+    ) -> Result<Vec<(String, i64, f64)>> {
         let key = crate::db::get_sqlcipher_key()?;
         let user_id = user_id.to_string();
         let query_blob = encode_f32_blob(vector);
@@ -1073,7 +1145,7 @@ impl SqliteMemoryProvider {
 
         let rows = conn
             .interact(
-                move |conn| -> std::result::Result<Vec<(String, i64)>, String> {
+                move |conn| -> std::result::Result<Vec<(String, i64, f64)>, String> {
                     conn.execute_batch("PRAGMA busy_timeout = 5000;")
                         .map_err(|e| {
                             format!("search_vector step=pragma_busy_timeout failed: {e}")
@@ -1085,11 +1157,12 @@ impl SqliteMemoryProvider {
                     let _ = conn.execute_batch("PRAGMA cipher_log_level = ERROR;");
 
                     let mut stmt = match conn.prepare(
-                        "SELECT content, timestamp
+                        "SELECT content, timestamp,
+                            vec_distance_cosine(embedding, vec_f32(?3)) AS distance
                      FROM message_vectors
                      WHERE user_id = ?1
                        AND timestamp > ?2
-                     ORDER BY vec_distance_cosine(embedding, vec_f32(?3)) ASC
+                     ORDER BY distance ASC
                      LIMIT ?4",
                     ) {
                         Ok(stmt) => stmt,
@@ -1108,7 +1181,8 @@ impl SqliteMemoryProvider {
                             |row| {
                                 let content: String = row.get(0)?;
                                 let timestamp: i64 = row.get(1)?;
-                                Ok((content, timestamp))
+                                let distance: f64 = row.get(2)?;
+                                Ok((content, timestamp, distance))
                             },
                         )
                         .map_err(|e| format!("search_vector step=query_map failed: {e}"))?;
@@ -1126,10 +1200,7 @@ impl SqliteMemoryProvider {
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
             .map_err(ButterflyBotError::Runtime)?;
 
-        Ok(rows
-            .into_iter()
-            .map(|(content, timestamp)| format!("[{}] {}", format_timestamp(timestamp), content))
-            .collect())
+        Ok(rows)
     }
 
     fn sanitize_fts_query(query: &str) -> Option<String> {
@@ -1173,32 +1244,42 @@ impl SqliteMemoryProvider {
 
     async fn search_vector(&self, user_id: &str, query: &str, limit: usize) -> Result<Vec<String>> {
         let reset_ts = get_history_reset_ts(self, user_id).await?;
-        let Some(embedder) = &self.embedder else {
+        let Some(vector) = self.embed_query(query).await? else {
             return Ok(Vec::new());
         };
 
+        Ok(self
+            .search_vector_hits(user_id, reset_ts, &vector, limit.max(1))
+            .await?
+            .into_iter()
+            .map(|(content, timestamp, _)| format!("[{}] {}", format_timestamp(timestamp), content))
+            .collect())
+    }
+
+    /// Query embedding, cached per model. `None` without an embedder.
+    async fn embed_query(&self, query: &str) -> Result<Option<Vec<f32>>> {
+        let Some(embedder) = &self.embedder else {
+            return Ok(None);
+        };
+
         let model_key = self.embedding_model.as_deref().unwrap_or("default");
         let cache_key = format!("{model_key}:{query}");
         let cached = {
             let mut cache = self.embedding_cache.lock().await;
             cache.get(&cache_key).cloned()
         };
-        let vector = if let Some(vector) = cached {
-            vector
-        } else {
-            let vectors = embedder
-                .embed(vec![query.to_string()], self.embedding_model.as_deref())
-                .await?;
-            let Some(vector) = vectors.into_iter().next() else {
-                return Ok(Vec::new());
-            };
-            let mut cache = self.embedding_cache.lock().await;
-            cache.put(cache_key, vector.clone());
-            vector
+        if let Some(vector) = cached {
+            return Ok(Some(vector));
+        }
+        let vectors = embedder
+            .embed(vec![query.to_string()], self.embedding_model.as_deref())
+            .await?;
+        let Some(vector) = vectors.into_iter().next() else {
+            return Ok(None);
         };
-
-        self.search_vector_rows(user_id, reset_ts, &vector, limit.max(1))
-            .await
+        let mut cache = self.embedding_cache.lock().await;
+        cache.put(cache_key, vector.clone());
+        Ok(Some(vector))
     }
 
     async fn rerank_with_model(
@@ -1298,6 +1379,19 @@ impl SqliteMemoryProvider {
 
         let mut rows = rows;
         rows.sort_by_key(|row| row.timestamp);
+        self.summarize_rows(user_id, summarizer, rows).await
+    }
+
+    /// Folds `rows` into one summary memory and links its entities and facts.
+    async fn summarize_rows(
+        &self,
+        user_id: &str,
+        summarizer: &Arc<dyn LlmProvider>,
+        rows: Vec<MessageRow>,
+    ) -> Result<()> {
+        if user_domains::status(user_id) != DomainStatus::None {
+            return Ok(());
+        }
         let transcript = rows
             .into_iter()
             .map(|row| {
@@ -1366,8 +1460,7 @@ impl SqliteMemoryProvider {
             salience: None,
             created_at: now,
         };
-        let _write_guard = self.write_gate.lock().await;
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         diesel::insert_into(crate::providers::sqlite::schema::memories::table)
             .values(&new_memory)
             .execute(&mut conn)
@@ -1497,35 +1590,202 @@ impl SqliteMemoryProvider {
         Ok(())
     }
 
-    async fn apply_retention(&self, user_id: &str, days: u32) -> Result<()> {
-        let cutoff = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
-            .as_secs() as i64
-            - (days as i64 * 24 * 60 * 60);
-
-        let _write_guard = self.write_gate.lock().await;
+    async fn load_retention_state(&self, user_id: &str) -> Result<(i64, Option<i64>)> {
         let mut conn = self.conn().await?;
-        diesel::delete(
-            messages::table.filter(
-                messages::user_id
-                    .eq(user_id)
-                    .and(messages::timestamp.lt(cutoff)),
-            ),
+        let row = diesel::sql_query(
+            "SELECT summarized_through_id, last_run_at FROM memory_retention_state WHERE user_id = ?1",
         )
-        .execute(&mut conn)
-        .await
-        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        diesel::delete(
-            crate::providers::sqlite::schema::memories::table.filter(
-                crate::providers::sqlite::schema::memories::user_id
-                    .eq(user_id)
-                    .and(crate::providers::sqlite::schema::memories::created_at.lt(cutoff)),
-            ),
+        .bind::<Text, _>(user_id)
+        .get_result::<RetentionStateRow>(&mut conn)
+        .await;
+        let row = diesel::OptionalExtension::optional(row)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row
+            .map(|row| (row.summarized_through_id, row.last_run_at))
+            .unwrap_or((0, None)))
+    }
+
+    async fn save_retention_state(
+        &self,
+        user_id: &str,
+        summarized_through_id: i64,
+        last_run_at: Option<i64>,
+    ) -> Result<()> {
+        let mut conn = self.write_conn().await?;
+        diesel::sql_query(
+            "INSERT OR REPLACE INTO memory_retention_state (user_id, summarized_through_id, last_run_at)
+             VALUES (?1, ?2, ?3)",
         )
+        .bind::<Text, _>(user_id)
+        .bind::<BigInt, _>(summarized_through_id)
+        .bind::<diesel::sql_types::Nullable<BigInt>, _>(last_run_at)
         .execute(&mut conn)
         .await
         .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
         Ok(())
     }
+
+    async fn enforce_retention_if_due(&self, user_id: &str) -> Result<()> {
+        let now = now_ts()?;
+        let (_, last_run_at) = self.load_retention_state(user_id).await?;
+        if !self.retention.is_due(last_run_at, now) {
+            return Ok(());
+        }
+        self.retention_pass(user_id, false, now).await.map(|_| ())
+    }
+
+    /// One retention pass evaluated at `now`; the trait method and the
+    /// background trigger pass the current time.
+    pub async fn retention_pass(
+        &self,
+        user_id: &str,
+        dry_run: bool,
+        now: i64,
+    ) -> Result<RetentionReport> {
+        let policy = self.retention.clone();
+        let (mut summarized_through_id, last_run_at) = self.load_retention_state(user_id).await?;
+        let mut report = RetentionReport {
+            user_id: user_id.to_string(),
+            dry_run,
+            generated_at: now,
+            policy: policy.clone(),
+            summarizer_available: self.summarizer.is_some(),
+            last_run_at,
+            ..Default::default()
+        };
+
+        if let (Some(cutoff), Some(summarizer)) = (policy.summarize_cutoff(now), &self.summarizer) {
+            let rows: Vec<MessageHistoryRow> = {
+                let mut conn = self.conn().await?;
+                messages::table
+                    .filter(messages::user_id.eq(user_id))
+                    .filter(messages::role.eq_any(["user", "assistant"]))
+                    .filter(messages::id.gt(summarized_through_id as i32))
+                    .filter(messages::timestamp.lt(cutoff))
+                    .order(messages::id.asc())
+                    .select((
+                        messages::id,
+                        messages::role,
+                        messages::content,
+                        messages::timestamp,
+                    ))
+                    .load(&mut conn)
+                    .await
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            };
+            report.turns_to_summarize = rows.len() as u64;
+            report.summary_batches = policy.batch_count(rows.len() as u64);
+            if dry_run {
+                if let Some(last) = rows.last() {
+                    summarized_through_id = last.id as i64;
+                }
+            } else {
+                for batch in rows.chunks(policy.batch_size) {
+                    let batch_rows = batch
+                        .iter()
+                        .map(|row| MessageRow {
+                            role: row.role.clone(),
+                            content: row.content.clone(),
+                            timestamp: row.timestamp,
+                        })
+                        .collect();
+                    self.summarize_rows(user_id, summarizer, batch_rows).await?;
+                    summarized_through_id = batch.last().map(|row| row.id as i64).unwrap_or(0);
+                    self.save_retention_state(user_id, summarized_through_id, last_run_at)
+                        .await?;
+                    report.summaries_written += 1;
+                }
+            }
+        }
+        report.summarized_through_id = summarized_through_id;
+
+        if let Some(cutoff) = policy.delete_cutoff(now) {
+            // Context rows are never summarized, so the watermark only
+            // guards user and assistant turns.
+            let deletable_filter = "user_id = ?1 AND timestamp < ?2
+                 AND (?3 = 0 OR id <= ?4 OR role NOT IN ('user', 'assistant'))";
+            let hold = i64::from(policy.holds_unsummarized());
+            let older: CountRow = {
+                let mut conn = self.conn().await?;
+                diesel::sql_query(
+                    "SELECT COUNT(*) as count FROM messages WHERE user_id = ?1 AND timestamp < ?2",
+                )
+                .bind::<Text, _>(user_id)
+                .bind::<BigInt, _>(cutoff)
+                .get_result(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            };
+            let deletable: CountRow = {
+                let mut conn = self.conn().await?;
+                diesel::sql_query(format!(
+                    "SELECT COUNT(*) as count FROM messages WHERE {deletable_filter}"
+                ))
+                .bind::<Text, _>(user_id)
+                .bind::<BigInt, _>(cutoff)
+                .bind::<BigInt, _>(hold)
+                .bind::<BigInt, _>(summarized_through_id)
+                .get_result(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            };
+            report.turns_to_delete = deletable.count as u64;
+            report.turns_awaiting_summary = (older.count - deletable.count).max(0) as u64;
+
+            if !dry_run && deletable.count > 0 {
+                let mut conn = self.write_conn().await?;
+                diesel::sql_query(format!("DELETE FROM messages WHERE {deletable_filter}"))
+                    .bind::<Text, _>(user_id)
+                    .bind::<BigInt, _>(cutoff)
+                    .bind::<BigInt, _>(hold)
+                    .bind::<BigInt, _>(summarized_through_id)
+                    .execute(&mut conn)
+                    .await
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                diesel::sql_query(
+                    "DELETE FROM message_vectors
+                     WHERE user_id = ?1 AND timestamp < ?2
+                       AND message_id NOT IN (SELECT id FROM messages WHERE user_id = ?1)",
+                )
+                .bind::<Text, _>(user_id)
+                .bind::<BigInt, _>(cutoff)
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            }
+        }
+
+        if let Some(cutoff) = policy.summary_cutoff(now) {
+            use crate::providers::sqlite::schema::memories;
+            let count: i64 = {
+                let mut conn = self.conn().await?;
+                memories::table
+                    .filter(memories::user_id.eq(user_id))
+                    .filter(memories::created_at.lt(cutoff))
+                    .count()
+                    .get_result(&mut conn)
+                    .await
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            };
+            report.summaries_to_delete = count as u64;
+            if !dry_run && count > 0 {
+                let mut conn = self.write_conn().await?;
+                diesel::delete(
+                    memories::table
+                        .filter(memories::user_id.eq(user_id))
+                        .filter(memories::created_at.lt(cutoff)),
+                )
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            }
+        }
+
+        if !dry_run {
+            self.save_retention_state(user_id, summarized_through_id, Some(now))
+                .await?;
+            report.last_run_at = Some(now);
+        }
+        Ok(report)
+    }
 }
@@ -0,0 +1,477 @@
+//! Questions the agent is blocked on.
+//!
+//! When the agent needs the human before it can continue, it files the
+//! question here instead of just asking in chat. Open questions show up in
+//! the inbox as `question:<id>` with a linked reminder, record when the
+//! human first saw them, and are resolved by the next chat message that
+//! answers them: one naming the ref explicitly, or the first reply after the
+//! turn that asked, when that turn asked exactly one question.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::agent_questions;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const AGENT_QUESTIONS_UP_SQL: &str =
+    include_str!("../../migrations/20260316_create_agent_questions/up.sql");
+
+pub const STATUS_OPEN: &str = "open";
+pub const STATUS_ANSWERED: &str = "answered";
+pub const STATUS_DISMISSED: &str = "dismissed";
+const REF_PREFIX: &str = "question:";
+const MAX_QUESTION_LEN: usize = 2000;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AgentQuestion {
+    pub id: i32,
+    pub user_id: String,
+    pub question: String,
+    /// Origin ref of the work waiting on the answer, e.g. `plan_step:4:2`.
+    pub blocks_ref: Option<String>,
+    pub status: String,
+    pub answer: Option<String>,
+    pub asked_at: i64,
+    /// Read receipt: when the human first acknowledged the question.
+    pub seen_at: Option<i64>,
+    pub answered_at: Option<i64>,
+}
+
+impl AgentQuestion {
+    pub fn origin_ref(&self) -> String {
+        format!("{REF_PREFIX}{}", self.id)
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.status == STATUS_OPEN
+    }
+}
+
+#[derive(Queryable)]
+struct QuestionRow {
+    id: i32,
+    user_id: String,
+    question: String,
+    blocks_ref: Option<String>,
+    status: String,
+    answer: Option<String>,
+    #[allow(dead_code)]
+    awaiting_reply: bool,
+    asked_at: i64,
+    seen_at: Option<i64>,
+    answered_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = agent_questions)]
+struct NewQuestion<'a> {
+    user_id: &'a str,
+    question: &'a str,
+    blocks_ref: Option<&'a str>,
+    status: &'a str,
+    awaiting_reply: bool,
+    asked_at: i64,
+}
+
+pub struct QuestionStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl QuestionStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_agent_questions_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    pub async fn ask(
+        &self,
+        user_id: &str,
+        question: &str,
+        blocks_ref: Option<&str>,
+    ) -> Result<AgentQuestion> {
+        let question = question.trim();
+        if question.is_empty() {
+            return Err(ButterflyBotError::Config(
+                "Question text is required".to_string(),
+            ));
+        }
+        if question.chars().count() > MAX_QUESTION_LEN {
+            return Err(ButterflyBotError::Config(format!(
+                "Questions are limited to {MAX_QUESTION_LEN} characters"
+            )));
+        }
+        let blocks_ref = blocks_ref.map(str::trim).filter(|value| !value.is_empty());
+        let mut conn = self.write_conn().await?;
+        diesel::insert_into(agent_questions::table)
+            .values(&NewQuestion {
+                user_id,
+                question,
+                blocks_ref,
+                status: STATUS_OPEN,
+                awaiting_reply: true,
+                asked_at: self.clock.now(),
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let row: QuestionRow = agent_questions::table
+            .filter(agent_questions::user_id.eq(user_id))
+            .order(agent_questions::id.desc())
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(map_row(row))
+    }
+
+    pub async fn get(&self, user_id: &str, id: i32) -> Result<Option<AgentQuestion>> {
+        let mut conn = self.conn().await?;
+        let row: Option<QuestionRow> = agent_questions::table
+            .filter(agent_questions::user_id.eq(user_id))
+            .filter(agent_questions::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    /// Newest first. Closed questions are included only when asked for.
+    pub async fn list(
+        &self,
+        user_id: &str,
+        include_closed: bool,
+        limit: usize,
+    ) -> Result<Vec<AgentQuestion>> {
+        let mut conn = self.conn().await?;
+        let mut query = agent_questions::table
+            .filter(agent_questions::user_id.eq(user_id))
+            .into_boxed();
+        if !include_closed {
+            query = query.filter(agent_questions::status.eq(STATUS_OPEN));
+        }
+        if limit > 0 {
+            query = query.limit(limit as i64);
+        }
+        let rows: Vec<QuestionRow> = query
+            .order(agent_questions::asked_at.desc())
+            .then_order_by(agent_questions::id.desc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Records the read receipt. Only the first call stamps it.
+    pub async fn mark_seen(&self, user_id: &str, id: i32) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            agent_questions::table
+                .filter(agent_questions::user_id.eq(user_id))
+                .filter(agent_questions::id.eq(id))
+                .filter(agent_questions::seen_at.is_null()),
+        )
+        .set(agent_questions::seen_at.eq(Some(self.clock.now())))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Closes an open question with `answer`, returning it when it was open.
+    pub async fn answer(
+        &self,
+        user_id: &str,
+        id: i32,
+        answer: Option<&str>,
+    ) -> Result<Option<AgentQuestion>> {
+        self.close(user_id, id, STATUS_ANSWERED, answer).await
+    }
+
+    pub async fn dismiss(&self, user_id: &str, id: i32) -> Result<bool> {
+        Ok(self
+            .close(user_id, id, STATUS_DISMISSED, None)
+            .await?
+            .is_some())
+    }
+
+    pub async fn reopen(&self, user_id: &str, id: i32) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            agent_questions::table
+                .filter(agent_questions::user_id.eq(user_id))
+                .filter(agent_questions::id.eq(id)),
+        )
+        .set((
+            agent_questions::status.eq(STATUS_OPEN),
+            agent_questions::answer.eq(None::<String>),
+            agent_questions::answered_at.eq(None::<i64>),
+            agent_questions::awaiting_reply.eq(false),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Resolves the questions a chat message answers and returns them.
+    ///
+    /// Questions named by `reply_to` or mentioned as `question:<id>` in the
+    /// text are always answered. Otherwise the message answers the question
+    /// asked in the previous turn, if that turn asked exactly one. Either
+    /// way, once the human has spoken, earlier questions stop waiting for
+    /// an implicit reply.
+    pub async fn resolve_from_chat(
+        &self,
+        user_id: &str,
+        text: &str,
+        reply_to: Option<&str>,
+    ) -> Result<Vec<AgentQuestion>> {
+        let mut ids = referenced_ids(text);
+        if let Some(id) = reply_to.and_then(parse_ref) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+
+        let mut conn = self.write_conn().await?;
+        if ids.is_empty() {
+            let waiting: Vec<i32> = agent_questions::table
+                .filter(agent_questions::user_id.eq(user_id))
+                .filter(agent_questions::status.eq(STATUS_OPEN))
+                .filter(agent_questions::awaiting_reply.eq(true))
+                .select(agent_questions::id)
+                .load(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            if let [only] = waiting.as_slice() {
+                ids.push(*only);
+            }
+        }
+        diesel::update(
+            agent_questions::table
+                .filter(agent_questions::user_id.eq(user_id))
+                .filter(agent_questions::awaiting_reply.eq(true)),
+        )
+        .set(agent_questions::awaiting_reply.eq(false))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let answer = text.trim();
+        let mut answered = Vec::new();
+        for id in ids {
+            if let Some(question) = self
+                .answer(user_id, id, Some(answer).filter(|a| !a.is_empty()))
+                .await?
+            {
+                answered.push(question);
+            }
+        }
+        Ok(answered)
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(agent_questions::table.filter(agent_questions::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn close(
+        &self,
+        user_id: &str,
+        id: i32,
+        status: &str,
+        answer: Option<&str>,
+    ) -> Result<Option<AgentQuestion>> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            agent_questions::table
+                .filter(agent_questions::user_id.eq(user_id))
+                .filter(agent_questions::id.eq(id))
+                .filter(agent_questions::status.eq(STATUS_OPEN)),
+        )
+        .set((
+            agent_questions::status.eq(status),
+            agent_questions::answer.eq(answer),
+            agent_questions::answered_at.eq(Some(self.clock.now())),
+            agent_questions::awaiting_reply.eq(false),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+        if updated == 0 {
+            return Ok(None);
+        }
+        self.get(user_id, id).await
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// The id in a `question:<id>` origin ref.
+pub fn parse_ref(origin_ref: &str) -> Option<i32> {
+    origin_ref.trim().strip_prefix(REF_PREFIX)?.parse().ok()
+}
+
+/// Ids of every `question:<id>` mentioned in free text.
+pub fn referenced_ids(text: &str) -> Vec<i32> {
+    let mut ids = Vec::new();
+    for (start, _) in text.match_indices(REF_PREFIX) {
+        let digits: String = text[start + REF_PREFIX.len()..]
+            .chars()
+            .take_while(char::is_ascii_digit)
+            .collect();
+        if let Ok(id) = digits.parse::<i32>() {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+fn map_row(row: QuestionRow) -> AgentQuestion {
+    AgentQuestion {
+        id: row.id,
+        user_id: row.user_id,
+        question: row.question,
+        blocks_ref: row.blocks_ref,
+        status: row.status,
+        answer: row.answer,
+        asked_at: row.asked_at,
+        seen_at: row.seen_at,
+        answered_at: row.answered_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_agent_questions_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM agent_questions LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    AGENT_QUESTIONS_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn chat_replies_answer_the_question_they_follow_or_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("questions.db");
+        let store = QuestionStore::new(path.to_str().unwrap()).await.unwrap();
+
+        let budget = store
+            .ask("u", "What's the budget ceiling?", Some("plan_step:3:1"))
+            .await
+            .unwrap();
+        assert_eq!(budget.origin_ref(), format!("question:{}", budget.id));
+        assert!(store.mark_seen("u", budget.id).await.unwrap());
+        assert!(!store.mark_seen("u", budget.id).await.unwrap());
+
+        // The first reply after a single question answers it.
+        let answered = store
+            .resolve_from_chat("u", "About 2k", None)
+            .await
+            .unwrap();
+        assert_eq!(answered.len(), 1);
+        assert_eq!(answered[0].answer.as_deref(), Some("About 2k"));
+        assert!(answered[0].seen_at.is_some());
+
+        // Two questions in one turn are ambiguous, so only refs resolve them.
+        let venue = store.ask("u", "Which venue?", None).await.unwrap();
+        let date = store.ask("u", "Which date?", None).await.unwrap();
+        assert!(store
+            .resolve_from_chat("u", "Not sure yet", None)
+            .await
+            .unwrap()
+            .is_empty());
+        let answered = store
+            .resolve_from_chat(
+                "u",
+                &format!("question:{} the old hall", venue.id),
+                Some(&date.origin_ref()),
+            )
+            .await
+            .unwrap();
+        let mut ids = answered.iter().map(|q| q.id).collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec![venue.id, date.id]);
+        assert!(store.list("u", false, 10).await.unwrap().is_empty());
+        assert_eq!(store.list("u", true, 10).await.unwrap().len(), 3);
+
+        assert!(matches!(
+            store.ask("u", "   ", None).await,
+            Err(ButterflyBotError::Config(_))
+        ));
+    }
+}
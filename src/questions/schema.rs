@@ -0,0 +1,14 @@
+diesel::table! {
+    agent_questions (id) {
+        id -> Integer,
+        user_id -> Text,
+        question -> Text,
+        blocks_ref -> Nullable<Text>,
+        status -> Text,
+        answer -> Nullable<Text>,
+        awaiting_reply -> Bool,
+        asked_at -> BigInt,
+        seen_at -> Nullable<BigInt>,
+        answered_at -> Nullable<BigInt>,
+    }
+}
@@ -0,0 +1,266 @@
+//! Secret redaction for logs, audit events and tool results.
+//!
+//! Anything persisted or shown in chat passes through [`redact_text`] or
+//! [`redact_json`] first. Three kinds of text are replaced:
+//!
+//! - values the vault has handed out or stored this run, as
+//!   `[REDACTED:<secret name>]`; the vault registers them through
+//!   [`remember_secret`], so a key read by a tool is masked from then on;
+//! - bearer credentials and well-known API key prefixes (`sk-`, `ghp_`, ...);
+//! - long high-entropy tokens that mix letters and digits, as
+//!   `[REDACTED:token]`. Hex digests and Solana addresses or signatures are
+//!   left alone since they are public and the UI links them.
+//!
+//! [`redact_value`] additionally blanks object fields whose key looks like a
+//! credential (`authorization`, `api_key`, `password`, ...), for logs where
+//! losing a field is cheaper than leaking it.
+
+use std::borrow::Cow;
+use std::sync::{OnceLock, RwLock};
+
+use regex::{Captures, Regex};
+use serde_json::Value;
+
+/// Shorter vault values (PINs, flags) would mask ordinary words.
+const MIN_SECRET_LEN: usize = 8;
+const MIN_TOKEN_LEN: usize = 32;
+/// Bits per character; random base64 sits near 5, prose and identifiers
+/// well below.
+const MIN_TOKEN_ENTROPY: f64 = 4.2;
+
+struct Patterns {
+    bearer: Regex,
+    api_key: Regex,
+    token: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        bearer: Regex::new(r"(?i)\bbearer\s+[^\s\x22\x27]+").expect("bearer regex"),
+        api_key: Regex::new(
+            r"(?i)\b(sk-|xai-|github_pat_|ghp_|gho_|ghu_|ghs_|ghr_)[A-Za-z0-9_\-]+",
+        )
+        .expect("api key regex"),
+        token: Regex::new(r"[A-Za-z0-9+/_\-]{32,}={0,2}").expect("token regex"),
+    })
+}
+
+fn known_secrets() -> &'static RwLock<Vec<(String, String)>> {
+    static KNOWN: OnceLock<RwLock<Vec<(String, String)>>> = OnceLock::new();
+    KNOWN.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Masks `value` as `[REDACTED:<name>]` from now on. Called by the vault
+/// whenever a secret is read or written.
+pub fn remember_secret(name: &str, value: &str) {
+    let value = value.trim();
+    if value.len() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut known = match known_secrets().write() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    known.retain(|(known_name, _)| known_name != name);
+    known.push((name.to_string(), value.to_string()));
+    // Longest first so a secret containing another is masked whole.
+    known.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+}
+
+pub fn forget_secret(name: &str) {
+    let mut known = match known_secrets().write() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    known.retain(|(known_name, _)| known_name != name);
+}
+
+pub fn redact_text(input: &str) -> Cow<'_, str> {
+    let mut output = Cow::Borrowed(input);
+    {
+        let known = match known_secrets().read() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        for (name, value) in known.iter() {
+            if output.contains(value.as_str()) {
+                let placeholder = format!("[REDACTED:{name}]");
+                output = Cow::Owned(output.replace(value.as_str(), &placeholder));
+            }
+        }
+    }
+
+    let patterns = patterns();
+    if patterns.bearer.is_match(&output) {
+        output = Cow::Owned(
+            patterns
+                .bearer
+                .replace_all(&output, "Bearer [REDACTED]")
+                .into_owned(),
+        );
+    }
+    if patterns.api_key.is_match(&output) {
+        output = Cow::Owned(
+            patterns
+                .api_key
+                .replace_all(&output, "$1[REDACTED]")
+                .into_owned(),
+        );
+    }
+    if patterns
+        .token
+        .find_iter(&output)
+        .any(|m| looks_like_secret(m.as_str()))
+    {
+        output = Cow::Owned(
+            patterns
+                .token
+                .replace_all(&output, |caps: &Captures| {
+                    let token = &caps[0];
+                    if looks_like_secret(token) {
+                        "[REDACTED:token]".to_string()
+                    } else {
+                        token.to_string()
+                    }
+                })
+                .into_owned(),
+        );
+    }
+    output
+}
+
+/// Redacts every string in `value`, keeping its shape.
+pub fn redact_json(value: &Value) -> Value {
+    match value {
+        Value::String(text) => Value::String(redact_text(text).into_owned()),
+        Value::Array(items) => Value::Array(items.iter().map(redact_json).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), redact_json(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Like [`redact_json`], and also blanks fields with credential-like keys.
+pub fn redact_value(value: &Value) -> Value {
+    match value {
+        Value::Array(items) => Value::Array(items.iter().map(redact_value).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if is_sensitive_key(key) {
+                        Value::String("[REDACTED]".to_string())
+                    } else {
+                        redact_value(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect(),
+        ),
+        other => redact_json(other),
+    }
+}
+
+fn is_sensitive_key(key: &str) -> bool {
+    let lower = key.to_ascii_lowercase();
+    lower.contains("authorization")
+        || lower.contains("api_key")
+        || lower.contains("apikey")
+        || lower.contains("token")
+        || lower.contains("secret")
+        || lower.contains("password")
+        || lower.contains("pat")
+}
+
+fn looks_like_secret(token: &str) -> bool {
+    let token = token.trim_end_matches('=');
+    if token.len() < MIN_TOKEN_LEN {
+        return false;
+    }
+    let has_digit = token.bytes().any(|b| b.is_ascii_digit());
+    let has_upper = token.bytes().any(|b| b.is_ascii_uppercase());
+    let has_lower = token.bytes().any(|b| b.is_ascii_lowercase());
+    if !(has_digit && has_upper && has_lower) {
+        return false;
+    }
+    if matches!(
+        bs58::decode(token).into_vec().map(|bytes| bytes.len()),
+        Ok(32 | 64)
+    ) {
+        return false;
+    }
+    shannon_entropy(token) >= MIN_TOKEN_ENTROPY
+}
+
+fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in text.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = text.len() as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{forget_secret, redact_json, redact_text, redact_value, remember_secret};
+
+    #[test]
+    fn masks_known_secrets_until_forgotten() {
+        remember_secret("crm_api_key", "crm-live-value-1");
+        assert_eq!(
+            redact_text("key=crm-live-value-1 ok"),
+            "key=[REDACTED:crm_api_key] ok"
+        );
+        forget_secret("crm_api_key");
+        assert_eq!(
+            redact_text("key=crm-live-value-1 ok"),
+            "key=crm-live-value-1 ok"
+        );
+    }
+
+    #[test]
+    fn masks_credentials_and_high_entropy_tokens() {
+        assert_eq!(
+            redact_text("Authorization: Bearer abc.def"),
+            "Authorization: Bearer [REDACTED]"
+        );
+        assert_eq!(redact_text("sk-abc123"), "sk-[REDACTED]");
+        assert_eq!(
+            redact_text("aws=Xq7vP2mK9tR4wZ8nB3cF6hJ1sD5gL0yA end"),
+            "aws=[REDACTED:token] end"
+        );
+
+        let digest = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let wallet = "4Nd1mBQtrMJVYVfKf2PJy9NZUZdTAsp7D4xWLs4gDB4T";
+        let path = "/home/user/projects/butterfly-bot/src/tools/mod.rs";
+        for text in [digest, wallet, path] {
+            assert_eq!(redact_text(text), text);
+        }
+    }
+
+    #[test]
+    fn redacts_json_by_content_and_value_by_key() {
+        let value = json!({"path": "src", "note": "Bearer abc", "n": 3});
+        assert_eq!(
+            redact_json(&value),
+            json!({"path": "src", "note": "Bearer [REDACTED]", "n": 3})
+        );
+        assert_eq!(
+            redact_value(&json!({"api_key": "short", "note": "hi"})),
+            json!({"api_key": "[REDACTED]", "note": "hi"})
+        );
+    }
+}
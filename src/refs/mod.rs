@@ -0,0 +1,556 @@
+//! Resolving origin refs across stores.
+//!
+//! Work items point at each other with refs such as `todo:12`,
+//! `reminder:5`, `task:3`, `plan:4` and `plan_step:4:1`. [`RefResolver`]
+//! indexes a user's todos, reminders, tasks, plans and plan steps once and
+//! answers what a ref points at, which dependency refs are broken and which
+//! dependencies form cycles. Refs to other sources (questions, approvals,
+//! email) are left alone: they resolve elsewhere.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::Result;
+use crate::planning::{parse_step_status, resolve_plan_db_path, PlanItem, PlanStore};
+use crate::reminders::{resolve_reminder_db_path, ReminderItem, ReminderStatus, ReminderStore};
+use crate::tasks::{resolve_task_db_path, ScheduledTask, TaskStatus, TaskStore};
+use crate::todo::{resolve_todo_db_path, TodoItem, TodoStatus, TodoStore};
+
+/// Items loaded per store when building an index from the database.
+pub const REF_SCAN_LIMIT: usize = 5000;
+
+/// Ref prefixes this module resolves.
+pub const TRACKED_KINDS: [&str; 5] = ["todo", "reminder", "task", "plan", "plan_step"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum OriginRef {
+    Todo(i32),
+    Reminder(i32),
+    Task(i32),
+    Plan(i32),
+    PlanStep { plan_id: i32, index: usize },
+}
+
+impl OriginRef {
+    /// Parses a tracked ref, ignoring case and spaces around the parts.
+    pub fn parse(value: &str) -> Option<Self> {
+        let lower = value.trim().to_ascii_lowercase();
+        let (kind, rest) = lower.split_once(':')?;
+        let id = |raw: &str| raw.trim().parse::<i32>().ok().filter(|id| *id > 0);
+        match kind.trim() {
+            "todo" => id(rest).map(OriginRef::Todo),
+            "reminder" => id(rest).map(OriginRef::Reminder),
+            "task" => id(rest).map(OriginRef::Task),
+            "plan" => id(rest).map(OriginRef::Plan),
+            "plan_step" => {
+                let (plan_id, index) = rest.split_once(':')?;
+                Some(OriginRef::PlanStep {
+                    plan_id: id(plan_id)?,
+                    index: index.trim().parse().ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OriginRef::Todo(_) => "todo",
+            OriginRef::Reminder(_) => "reminder",
+            OriginRef::Task(_) => "task",
+            OriginRef::Plan(_) => "plan",
+            OriginRef::PlanStep { .. } => "plan_step",
+        }
+    }
+}
+
+impl fmt::Display for OriginRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OriginRef::Todo(id)
+            | OriginRef::Reminder(id)
+            | OriginRef::Task(id)
+            | OriginRef::Plan(id) => write!(f, "{}:{id}", self.kind()),
+            OriginRef::PlanStep { plan_id, index } => write!(f, "plan_step:{plan_id}:{index}"),
+        }
+    }
+}
+
+/// Whether `value` claims one of the [`TRACKED_KINDS`], well-formed or not.
+pub fn is_tracked(value: &str) -> bool {
+    let lower = value.trim().to_ascii_lowercase();
+    lower
+        .split_once(':')
+        .is_some_and(|(kind, _)| TRACKED_KINDS.contains(&kind.trim()))
+}
+
+/// The canonical spelling of a tracked ref; anything else is only trimmed,
+/// since external refs may be case-sensitive.
+pub fn canonical(value: &str) -> String {
+    match OriginRef::parse(value) {
+        Some(origin_ref) => origin_ref.to_string(),
+        None => value.trim().to_string(),
+    }
+}
+
+/// What a ref resolves to.
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct WorkItem {
+    pub origin_ref: String,
+    pub kind: &'static str,
+    pub title: String,
+    pub done: bool,
+    /// Canonical refs of the items this one waits on.
+    pub dependency_refs: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BrokenReason {
+    /// Names a tracked kind but doesn't parse, e.g. `todo:abc`.
+    Malformed,
+    /// Well-formed, but nothing with that ref exists for the user.
+    Missing,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct BrokenRef {
+    /// The item holding the dependency.
+    pub from: String,
+    /// The dependency ref as written.
+    pub to: String,
+    pub reason: BrokenReason,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct DependencyReport {
+    /// Dependency edges looked at.
+    pub checked: usize,
+    pub broken: Vec<BrokenRef>,
+    /// Each cycle lists the refs caught in it, starting from the smallest.
+    pub cycles: Vec<Vec<String>>,
+}
+
+impl DependencyReport {
+    pub fn is_clean(&self) -> bool {
+        self.broken.is_empty() && self.cycles.is_empty()
+    }
+}
+
+pub struct RefResolver {
+    items: HashMap<String, WorkItem>,
+}
+
+impl RefResolver {
+    /// Indexes already loaded items. `plan_step_dependencies` maps step refs
+    /// to their dependency refs, as `PlanStore::list_step_dependencies_for_plans`
+    /// returns them.
+    pub fn index(
+        todos: &[TodoItem],
+        reminders: &[ReminderItem],
+        tasks: &[ScheduledTask],
+        plans: &[PlanItem],
+        plan_step_dependencies: &HashMap<String, Vec<String>>,
+    ) -> Self {
+        let mut items = HashMap::new();
+        let mut add = |item: WorkItem| {
+            items.insert(item.origin_ref.clone(), item);
+        };
+        for todo in todos {
+            add(WorkItem {
+                origin_ref: OriginRef::Todo(todo.id).to_string(),
+                kind: "todo",
+                title: todo.title.clone(),
+                done: todo.completed_at.is_some(),
+                dependency_refs: todo.dependency_refs.iter().map(|r| canonical(r)).collect(),
+            });
+        }
+        for reminder in reminders {
+            add(WorkItem {
+                origin_ref: OriginRef::Reminder(reminder.id).to_string(),
+                kind: "reminder",
+                title: reminder.title.clone(),
+                done: reminder.completed_at.is_some(),
+                dependency_refs: Vec::new(),
+            });
+        }
+        for task in tasks {
+            add(WorkItem {
+                origin_ref: OriginRef::Task(task.id).to_string(),
+                kind: "task",
+                title: task.name.clone(),
+                done: !task.enabled,
+                dependency_refs: Vec::new(),
+            });
+        }
+        for plan in plans {
+            add(WorkItem {
+                origin_ref: OriginRef::Plan(plan.id).to_string(),
+                kind: "plan",
+                title: plan.title.clone(),
+                done: matches!(plan.status.as_str(), "done" | "completed"),
+                dependency_refs: Vec::new(),
+            });
+            let steps = plan.steps.as_ref().and_then(Value::as_array);
+            for (index, step) in steps.into_iter().flatten().enumerate() {
+                let origin_ref = OriginRef::PlanStep {
+                    plan_id: plan.id,
+                    index,
+                }
+                .to_string();
+                let dependency_refs = plan_step_dependencies
+                    .get(&origin_ref)
+                    .map(|refs| refs.iter().map(|r| canonical(r)).collect())
+                    .unwrap_or_default();
+                add(WorkItem {
+                    title: step_title(step).unwrap_or_else(|| format!("Step {}", index + 1)),
+                    kind: "plan_step",
+                    done: step
+                        .get("status")
+                        .and_then(Value::as_str)
+                        .and_then(parse_step_status)
+                        == Some("done"),
+                    dependency_refs,
+                    origin_ref,
+                });
+            }
+        }
+        Self { items }
+    }
+
+    /// Loads and indexes the user's items. Store paths come from `config`
+    /// the same way the inbox resolves them, falling back to `db_path`.
+    pub async fn load(config: &Value, db_path: &str, user_id: &str) -> Result<Self> {
+        let path = |resolved: Option<String>| resolved.unwrap_or_else(|| db_path.to_string());
+        let todos = TodoStore::new(path(resolve_todo_db_path(config)))
+            .await?
+            .list_items(user_id, TodoStatus::All, REF_SCAN_LIMIT)
+            .await?;
+        let reminders = ReminderStore::new(path(resolve_reminder_db_path(config)))
+            .await?
+            .list_reminders(user_id, ReminderStatus::All, REF_SCAN_LIMIT)
+            .await?;
+        let tasks = TaskStore::new(path(resolve_task_db_path(config)))
+            .await?
+            .list_tasks(user_id, TaskStatus::All, REF_SCAN_LIMIT)
+            .await?;
+        let plan_store = PlanStore::new(path(resolve_plan_db_path(config))).await?;
+        let plans = plan_store.list_plans(user_id, REF_SCAN_LIMIT).await?;
+        let plan_ids = plans.iter().map(|plan| plan.id).collect::<Vec<_>>();
+        let plan_step_dependencies = plan_store
+            .list_step_dependencies_for_plans(&plan_ids)
+            .await?;
+        Ok(Self::index(
+            &todos,
+            &reminders,
+            &tasks,
+            &plans,
+            &plan_step_dependencies,
+        ))
+    }
+
+    /// What `origin_ref` points at, or why it points nowhere.
+    pub fn resolve(&self, origin_ref: &str) -> std::result::Result<&WorkItem, BrokenReason> {
+        let parsed = OriginRef::parse(origin_ref).ok_or(BrokenReason::Malformed)?;
+        self.items
+            .get(&parsed.to_string())
+            .ok_or(BrokenReason::Missing)
+    }
+
+    /// Checks every indexed item's dependencies.
+    pub fn report(&self) -> DependencyReport {
+        self.report_where(|_| true)
+    }
+
+    /// Checks the dependencies of the items `include` picks; a cycle is
+    /// reported when any of its members is picked.
+    pub fn report_where(&self, include: impl Fn(&WorkItem) -> bool) -> DependencyReport {
+        let mut report = DependencyReport::default();
+        let mut origins = self
+            .items
+            .values()
+            .filter(|item| include(item))
+            .collect::<Vec<_>>();
+        origins.sort_by(|a, b| a.origin_ref.cmp(&b.origin_ref));
+        for item in &origins {
+            for dep_ref in &item.dependency_refs {
+                if !is_tracked(dep_ref) {
+                    continue;
+                }
+                report.checked += 1;
+                if let Err(reason) = self.resolve(dep_ref) {
+                    report.broken.push(BrokenRef {
+                        from: item.origin_ref.clone(),
+                        to: dep_ref.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+
+        let picked = origins
+            .iter()
+            .map(|item| item.origin_ref.as_str())
+            .collect::<HashSet<_>>();
+        let edges = self
+            .items
+            .values()
+            .flat_map(|item| {
+                item.dependency_refs
+                    .iter()
+                    .filter(|dep_ref| self.items.contains_key(dep_ref.as_str()))
+                    .map(|dep_ref| (item.origin_ref.clone(), dep_ref.clone()))
+            })
+            .collect::<Vec<_>>();
+        report.cycles = find_cycles(&edges)
+            .into_iter()
+            .filter(|cycle| cycle.iter().any(|node| picked.contains(node.as_str())))
+            .collect();
+        report
+    }
+}
+
+fn step_title(step: &Value) -> Option<String> {
+    if let Some(title) = step.as_str() {
+        return Some(title.trim().to_string()).filter(|title| !title.is_empty());
+    }
+    ["title", "name", "step", "description", "text"]
+        .iter()
+        .filter_map(|key| step.get(key).and_then(Value::as_str))
+        .map(|title| title.trim().to_string())
+        .find(|title| !title.is_empty())
+}
+
+/// Groups of refs that depend on each other in a loop, including an item
+/// that depends on itself. Each cycle starts at its smallest ref and the
+/// list is sorted, so the result is stable. A simple loop comes out in
+/// dependency order.
+pub fn find_cycles(edges: &[(String, String)]) -> Vec<Vec<String>> {
+    let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (from, to) in edges {
+        graph.entry(from.as_str()).or_default().push(to.as_str());
+        graph.entry(to.as_str()).or_default();
+    }
+    for targets in graph.values_mut() {
+        targets.sort_unstable();
+        targets.dedup();
+    }
+    let mut nodes = graph.keys().copied().collect::<Vec<_>>();
+    nodes.sort_unstable();
+
+    // Tarjan's strongly connected components.
+    struct Walk<'a> {
+        graph: &'a HashMap<&'a str, Vec<&'a str>>,
+        next_index: usize,
+        index: HashMap<&'a str, usize>,
+        low: HashMap<&'a str, usize>,
+        stack: Vec<&'a str>,
+        on_stack: HashSet<&'a str>,
+        components: Vec<Vec<&'a str>>,
+    }
+
+    impl<'a> Walk<'a> {
+        fn visit(&mut self, node: &'a str) {
+            self.index.insert(node, self.next_index);
+            self.low.insert(node, self.next_index);
+            self.next_index += 1;
+            self.stack.push(node);
+            self.on_stack.insert(node);
+            let graph = self.graph;
+            for &next in &graph[node] {
+                if !self.index.contains_key(next) {
+                    self.visit(next);
+                    let low = self.low[node].min(self.low[next]);
+                    self.low.insert(node, low);
+                } else if self.on_stack.contains(next) {
+                    let low = self.low[node].min(self.index[next]);
+                    self.low.insert(node, low);
+                }
+            }
+            if self.low[node] == self.index[node] {
+                let mut component = Vec::new();
+                while let Some(member) = self.stack.pop() {
+                    self.on_stack.remove(member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                self.components.push(component);
+            }
+        }
+    }
+
+    let mut walk = Walk {
+        graph: &graph,
+        next_index: 0,
+        index: HashMap::new(),
+        low: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for node in nodes {
+        if !walk.index.contains_key(node) {
+            walk.visit(node);
+        }
+    }
+
+    let mut cycles = walk
+        .components
+        .into_iter()
+        .filter(|component| component.len() > 1 || graph[component[0]].contains(&component[0]))
+        .map(|mut component| {
+            // Popping the stack yields members against dependency order.
+            component.reverse();
+            let start = (0..component.len())
+                .min_by_key(|&i| component[i])
+                .unwrap_or(0);
+            component.rotate_left(start);
+            component
+                .into_iter()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    cycles.sort();
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn todo(id: i32, dependency_refs: &[&str]) -> TodoItem {
+        TodoItem {
+            id,
+            user_id: "u1".to_string(),
+            title: format!("todo {id}"),
+            notes: None,
+            position: id,
+            created_at: 0,
+            updated_at: 0,
+            completed_at: None,
+            t_shirt_size: None,
+            story_points: None,
+            estimate_optimistic_minutes: None,
+            estimate_likely_minutes: None,
+            estimate_pessimistic_minutes: None,
+            dependency_refs: dependency_refs.iter().map(|r| r.to_string()).collect(),
+            checklist_id: None,
+            origin_ref: None,
+        }
+    }
+
+    fn plan(id: i32, steps: Value) -> PlanItem {
+        PlanItem {
+            id,
+            user_id: "u1".to_string(),
+            title: format!("plan {id}"),
+            goal: String::new(),
+            steps: Some(steps),
+            status: "active".to_string(),
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn parses_and_canonicalizes_tracked_refs() {
+        assert_eq!(OriginRef::parse(" Todo: 12 "), Some(OriginRef::Todo(12)));
+        assert_eq!(
+            OriginRef::parse("plan_step:4:0"),
+            Some(OriginRef::PlanStep {
+                plan_id: 4,
+                index: 0
+            })
+        );
+        assert_eq!(OriginRef::parse("todo:abc"), None);
+        assert_eq!(OriginRef::parse("question:3"), None);
+        assert_eq!(canonical("REMINDER:5"), "reminder:5");
+        assert!(is_tracked("todo:abc"));
+        assert!(!is_tracked("email:abc@example.com"));
+    }
+
+    #[test]
+    fn resolves_items_and_reports_broken_refs() {
+        let mut deps = HashMap::new();
+        deps.insert(
+            "plan_step:4:1".to_string(),
+            vec!["plan_step:4:0".to_string()],
+        );
+        let resolver = RefResolver::index(
+            &[todo(
+                1,
+                &["plan_step:4:1", "todo:99", "todo:x", "question:2"],
+            )],
+            &[],
+            &[],
+            &[plan(
+                4,
+                json!([{"title": "Draft", "status": "done"}, "Review"]),
+            )],
+            &deps,
+        );
+
+        let step = resolver.resolve("plan_step:4:0").expect("step resolves");
+        assert_eq!(step.title, "Draft");
+        assert!(step.done);
+        assert_eq!(resolver.resolve("plan:4").map(|item| item.kind), Ok("plan"));
+        assert_eq!(resolver.resolve("todo:2"), Err(BrokenReason::Missing));
+
+        let report = resolver.report();
+        assert_eq!(report.checked, 4);
+        assert_eq!(
+            report
+                .broken
+                .iter()
+                .map(|broken| (broken.to.as_str(), broken.reason))
+                .collect::<Vec<_>>(),
+            vec![
+                ("todo:99", BrokenReason::Missing),
+                ("todo:x", BrokenReason::Malformed)
+            ]
+        );
+        assert!(report.cycles.is_empty());
+    }
+
+    #[test]
+    fn finds_cycles_including_self_dependencies() {
+        let edge = |from: &str, to: &str| (from.to_string(), to.to_string());
+        let cycles = find_cycles(&[
+            edge("todo:3", "todo:1"),
+            edge("todo:1", "todo:2"),
+            edge("todo:2", "todo:3"),
+            edge("todo:4", "todo:1"),
+            edge("todo:5", "todo:5"),
+        ]);
+        assert_eq!(
+            cycles,
+            vec![vec!["todo:1", "todo:2", "todo:3"], vec!["todo:5"]]
+        );
+    }
+
+    #[test]
+    fn report_keeps_cycles_touching_the_picked_items() {
+        let resolver = RefResolver::index(
+            &[
+                todo(1, &["todo:2"]),
+                todo(2, &["todo:1"]),
+                todo(3, &["todo:1"]),
+            ],
+            &[],
+            &[],
+            &[],
+            &HashMap::new(),
+        );
+        assert_eq!(resolver.report().cycles.len(), 1);
+        let only_third = resolver.report_where(|item| item.origin_ref == "todo:3");
+        assert_eq!(only_third.checked, 1);
+        assert!(only_third.cycles.is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+//! Reminder delivery with retries.
+//!
+//! A reminder that comes due gets one `reminder_deliveries` row per
+//! configured channel. The dispatch job tries every row whose attempt is
+//! due; a failure backs off exponentially, and once `max_attempts` is used
+//! up the row moves to `reminder_dead_letters`, where it waits for someone
+//! to re-drive or discard it. Channels come from `tools.reminders.delivery`:
+//!
+//! ```json
+//! {"channels": ["notification", "chat"], "max_attempts": 6}
+//! ```
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::schema::{reminder_dead_letters, reminder_deliveries};
+use super::ReminderStore;
+use crate::error::{ButterflyBotError, Result};
+
+const DEFAULT_MAX_ATTEMPTS: i32 = 6;
+const BASE_BACKOFF_SECONDS: i64 = 15;
+/// Reminders are time-sensitive, so retries never wait long.
+const MAX_BACKOFF_SECONDS: i64 = 15 * 60;
+const MAX_ERROR_LEN: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryChannel {
+    /// Desktop notification.
+    Notification,
+    /// Assistant message in the chat thread.
+    Chat,
+    /// POST to the push relay configured for escalation.
+    Push,
+}
+
+impl DeliveryChannel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "notification" | "desktop" => Some(DeliveryChannel::Notification),
+            "chat" => Some(DeliveryChannel::Chat),
+            "push" | "push_relay" => Some(DeliveryChannel::Push),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            DeliveryChannel::Notification => "notification",
+            DeliveryChannel::Chat => "chat",
+            DeliveryChannel::Push => "push",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeliveryConfig {
+    pub channels: Vec<DeliveryChannel>,
+    pub max_attempts: i32,
+}
+
+impl Default for DeliveryConfig {
+    fn default() -> Self {
+        Self {
+            channels: vec![DeliveryChannel::Notification],
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+}
+
+impl DeliveryConfig {
+    /// Unknown channels are dropped; no usable channel keeps the default.
+    pub fn from_tools_config(tools: Option<&Value>) -> Self {
+        let Some(delivery) = tools
+            .and_then(|tools| tools.get("reminders"))
+            .and_then(|reminders| reminders.get("delivery"))
+        else {
+            return Self::default();
+        };
+        let mut channels = Vec::new();
+        for channel in delivery
+            .get("channels")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str().and_then(DeliveryChannel::parse))
+        {
+            if !channels.contains(&channel) {
+                channels.push(channel);
+            }
+        }
+        if channels.is_empty() {
+            channels = Self::default().channels;
+        }
+        let max_attempts = delivery
+            .get("max_attempts")
+            .and_then(Value::as_i64)
+            .map(|value| value.clamp(1, 20) as i32)
+            .unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        Self {
+            channels,
+            max_attempts,
+        }
+    }
+}
+
+/// Seconds to wait after the `attempts`-th failure: 15s doubling to 15 minutes.
+pub fn delivery_backoff_seconds(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    (BASE_BACKOFF_SECONDS << exponent).min(MAX_BACKOFF_SECONDS)
+}
+
+#[derive(Clone, Debug, Serialize, Queryable)]
+pub struct ReminderDelivery {
+    pub id: i32,
+    pub user_id: String,
+    pub reminder_id: i32,
+    pub channel: String,
+    pub title: String,
+    pub attempts: i32,
+    pub next_attempt_at: i64,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+}
+
+/// A delivery that used up its attempts.
+#[derive(Clone, Debug, Serialize, Queryable)]
+pub struct DeadLetter {
+    pub id: i32,
+    pub user_id: String,
+    pub reminder_id: i32,
+    pub channel: String,
+    pub title: String,
+    pub attempts: i32,
+    pub last_error: String,
+    /// When the reminder first went out on this channel.
+    pub created_at: i64,
+    pub failed_at: i64,
+}
+
+/// Where a failed attempt left the delivery.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeliveryFailure {
+    RetryAt(i64),
+    DeadLettered,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = reminder_deliveries)]
+struct NewDelivery<'a> {
+    user_id: &'a str,
+    reminder_id: i32,
+    channel: &'a str,
+    title: &'a str,
+    attempts: i32,
+    next_attempt_at: i64,
+    created_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = reminder_dead_letters)]
+struct NewDeadLetter<'a> {
+    user_id: &'a str,
+    reminder_id: i32,
+    channel: &'a str,
+    title: &'a str,
+    attempts: i32,
+    last_error: &'a str,
+    created_at: i64,
+    failed_at: i64,
+}
+
+impl ReminderStore {
+    /// Queues a reminder on each channel, due now. A channel it is already
+    /// queued on is left alone.
+    pub async fn enqueue_deliveries(
+        &self,
+        user_id: &str,
+        reminder_id: i32,
+        title: &str,
+        channels: &[DeliveryChannel],
+    ) -> Result<usize> {
+        let now = self.clock.now();
+        let rows = channels
+            .iter()
+            .map(|channel| NewDelivery {
+                user_id,
+                reminder_id,
+                channel: channel.key(),
+                title,
+                attempts: 0,
+                next_attempt_at: now,
+                created_at: now,
+            })
+            .collect::<Vec<_>>();
+        let mut conn = self.conn().await?;
+        diesel::insert_or_ignore_into(reminder_deliveries::table)
+            .values(&rows)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Deliveries whose next attempt is due, across users, oldest first.
+    /// Deliveries of reminders that have since been deleted are dropped.
+    pub async fn due_deliveries(&self, limit: usize) -> Result<Vec<ReminderDelivery>> {
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        diesel::sql_query(
+            "DELETE FROM reminder_deliveries WHERE reminder_id NOT IN (SELECT id FROM reminders)",
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        reminder_deliveries::table
+            .filter(reminder_deliveries::next_attempt_at.le(now))
+            .order(reminder_deliveries::id.asc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    pub async fn record_delivery_success(&self, delivery_id: i32) -> Result<()> {
+        let mut conn = self.conn().await?;
+        diesel::delete(reminder_deliveries::table.filter(reminder_deliveries::id.eq(delivery_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Schedules the next attempt, or dead-letters the delivery once
+    /// `max_attempts` is used up.
+    pub async fn record_delivery_failure(
+        &self,
+        delivery: &ReminderDelivery,
+        error: &str,
+        max_attempts: i32,
+    ) -> Result<DeliveryFailure> {
+        let now = self.clock.now();
+        let attempts = delivery.attempts + 1;
+        let error: String = error.chars().take(MAX_ERROR_LEN).collect();
+        let mut conn = self.conn().await?;
+        if attempts < max_attempts {
+            let retry_at = now + delivery_backoff_seconds(attempts);
+            diesel::update(
+                reminder_deliveries::table.filter(reminder_deliveries::id.eq(delivery.id)),
+            )
+            .set((
+                reminder_deliveries::attempts.eq(attempts),
+                reminder_deliveries::next_attempt_at.eq(retry_at),
+                reminder_deliveries::last_error.eq(Some(error)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            return Ok(DeliveryFailure::RetryAt(retry_at));
+        }
+        diesel::insert_into(reminder_dead_letters::table)
+            .values(&NewDeadLetter {
+                user_id: &delivery.user_id,
+                reminder_id: delivery.reminder_id,
+                channel: &delivery.channel,
+                title: &delivery.title,
+                attempts,
+                last_error: &error,
+                created_at: delivery.created_at,
+                failed_at: now,
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::delete(reminder_deliveries::table.filter(reminder_deliveries::id.eq(delivery.id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(DeliveryFailure::DeadLettered)
+    }
+
+    /// The user's dead letters, most recent failure first.
+    pub async fn dead_letters(&self, user_id: &str, limit: usize) -> Result<Vec<DeadLetter>> {
+        let mut conn = self.conn().await?;
+        reminder_dead_letters::table
+            .filter(reminder_dead_letters::user_id.eq(user_id))
+            .order((
+                reminder_dead_letters::failed_at.desc(),
+                reminder_dead_letters::id.desc(),
+            ))
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    /// Puts a dead letter back in the queue with fresh attempts, due now.
+    pub async fn redrive_dead_letter(&self, user_id: &str, id: i32) -> Result<Option<DeadLetter>> {
+        let Some(letter) = self.take_dead_letter(user_id, id).await? else {
+            return Ok(None);
+        };
+        let now = self.clock.now();
+        let mut conn = self.conn().await?;
+        diesel::replace_into(reminder_deliveries::table)
+            .values(&NewDelivery {
+                user_id: &letter.user_id,
+                reminder_id: letter.reminder_id,
+                channel: &letter.channel,
+                title: &letter.title,
+                attempts: 0,
+                next_attempt_at: now,
+                created_at: now,
+            })
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Some(letter))
+    }
+
+    /// Gives up on a dead letter for good.
+    pub async fn discard_dead_letter(&self, user_id: &str, id: i32) -> Result<bool> {
+        Ok(self.take_dead_letter(user_id, id).await?.is_some())
+    }
+
+    /// Drops everything queued or dead-lettered for the user.
+    pub async fn clear_deliveries(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.conn().await?;
+        let queued = diesel::delete(
+            reminder_deliveries::table.filter(reminder_deliveries::user_id.eq(user_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let dead = diesel::delete(
+            reminder_dead_letters::table.filter(reminder_dead_letters::user_id.eq(user_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(queued + dead)
+    }
+
+    async fn take_dead_letter(&self, user_id: &str, id: i32) -> Result<Option<DeadLetter>> {
+        let mut conn = self.conn().await?;
+        let letter: Option<DeadLetter> = reminder_dead_letters::table
+            .filter(reminder_dead_letters::user_id.eq(user_id))
+            .filter(reminder_dead_letters::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        if letter.is_some() {
+            diesel::delete(reminder_dead_letters::table.filter(reminder_dead_letters::id.eq(id)))
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+        Ok(letter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{delivery_backoff_seconds, DeliveryChannel, DeliveryConfig, DeliveryFailure};
+    use crate::clock::{Clock, ManualClock};
+    use crate::reminders::ReminderStore;
+    use serde_json::json;
+
+    #[test]
+    fn config_keeps_known_channels_once() {
+        assert_eq!(
+            DeliveryConfig::from_tools_config(None).channels,
+            vec![DeliveryChannel::Notification]
+        );
+        let config = DeliveryConfig::from_tools_config(Some(&json!({
+            "reminders": {"delivery": {
+                "channels": ["chat", "pager", "desktop", "chat"],
+                "max_attempts": 3
+            }}
+        })));
+        assert_eq!(
+            config.channels,
+            vec![DeliveryChannel::Chat, DeliveryChannel::Notification]
+        );
+        assert_eq!(config.max_attempts, 3);
+        assert_eq!(delivery_backoff_seconds(1), 15);
+        assert_eq!(delivery_backoff_seconds(3), 60);
+        assert_eq!(delivery_backoff_seconds(30), 15 * 60);
+    }
+
+    #[tokio::test]
+    async fn failed_deliveries_back_off_then_dead_letter_and_redrive() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let clock = ManualClock::new(1_767_268_800);
+        let store = ReminderStore::new(&db_path)
+            .await
+            .expect("store")
+            .with_clock(clock.clone());
+        let reminder = store
+            .create_reminder("u1", "Call the dentist", clock.now())
+            .await
+            .expect("create");
+
+        let channels = [DeliveryChannel::Notification, DeliveryChannel::Chat];
+        assert_eq!(
+            store
+                .enqueue_deliveries("u1", reminder.id, &reminder.title, &channels)
+                .await
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            store
+                .enqueue_deliveries("u1", reminder.id, &reminder.title, &channels)
+                .await
+                .unwrap(),
+            0
+        );
+
+        let due = store.due_deliveries(10).await.expect("due");
+        assert_eq!(due.len(), 2);
+        store.record_delivery_success(due[1].id).await.unwrap();
+        let failure = store
+            .record_delivery_failure(&due[0], "no notification daemon", 2)
+            .await
+            .unwrap();
+        assert_eq!(failure, DeliveryFailure::RetryAt(clock.now() + 15));
+        assert!(store.due_deliveries(10).await.unwrap().is_empty());
+
+        clock.advance(15);
+        let retry = store.due_deliveries(10).await.expect("retry");
+        assert_eq!(retry[0].attempts, 1);
+        let failure = store
+            .record_delivery_failure(&retry[0], "still failing", 2)
+            .await
+            .unwrap();
+        assert_eq!(failure, DeliveryFailure::DeadLettered);
+
+        let letters = store.dead_letters("u1", 10).await.expect("dead letters");
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].channel, "notification");
+        assert_eq!(letters[0].last_error, "still failing");
+        assert!(store.dead_letters("u2", 10).await.unwrap().is_empty());
+        assert!(store
+            .redrive_dead_letter("u2", letters[0].id)
+            .await
+            .unwrap()
+            .is_none());
+
+        store
+            .redrive_dead_letter("u1", letters[0].id)
+            .await
+            .unwrap()
+            .expect("redriven");
+        assert!(store.dead_letters("u1", 10).await.unwrap().is_empty());
+        let requeued = store.due_deliveries(10).await.expect("requeued");
+        assert_eq!(requeued.len(), 1);
+        assert_eq!(requeued[0].attempts, 0);
+    }
+}
@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, Local, NaiveTime, TimeZone, Timelike};
+
+/// Local-time span during which a fired reminder may actually be delivered,
+/// e.g. `09:00-17:00 mon-fri`. Windows whose end is before their start run
+/// overnight (`22:00-06:00`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeliveryWindow {
+    start_minute: u32,
+    end_minute: u32,
+    /// Indexed from Monday.
+    days: [bool; 7],
+}
+
+impl DeliveryWindow {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut parts = spec.split_whitespace();
+        let range = parts.next()?;
+        let (start, end) = range.split_once('-')?;
+        let start_minute = parse_clock(start)?;
+        let end_minute = parse_clock(end)?;
+        let days = match parts.next() {
+            Some(days) => parse_days(days)?,
+            None => [true; 7],
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            start_minute,
+            end_minute,
+            days,
+        })
+    }
+
+    pub fn contains(&self, ts: i64) -> bool {
+        let Some(local) = Local.timestamp_opt(ts, 0).single() else {
+            return true;
+        };
+        let minute = local.hour() * 60 + local.minute();
+        let day = local.weekday().num_days_from_monday() as usize;
+        let previous_day = (day + 6) % 7;
+
+        if self.start_minute == self.end_minute {
+            self.days[day]
+        } else if self.start_minute < self.end_minute {
+            self.days[day] && minute >= self.start_minute && minute < self.end_minute
+        } else {
+            (self.days[day] && minute >= self.start_minute)
+                || (self.days[previous_day] && minute < self.end_minute)
+        }
+    }
+
+    /// `ts` itself when the window is open, otherwise the next time it opens.
+    pub fn next_open_at(&self, ts: i64) -> i64 {
+        if self.contains(ts) {
+            return ts;
+        }
+        let Some(local) = Local.timestamp_opt(ts, 0).single() else {
+            return ts;
+        };
+        let start = NaiveTime::from_hms_opt(self.start_minute / 60, self.start_minute % 60, 0)
+            .unwrap_or(NaiveTime::MIN);
+        for offset in 0..=7 {
+            let date = local.date_naive() + Duration::days(offset);
+            if !self.days[date.weekday().num_days_from_monday() as usize] {
+                continue;
+            }
+            let Some(opens) = Local
+                .from_local_datetime(&date.and_time(start))
+                .earliest()
+                .map(|dt| dt.timestamp())
+            else {
+                continue;
+            };
+            if opens > ts {
+                return opens;
+            }
+        }
+        ts
+    }
+}
+
+/// Delivery windows from `tools.reminders`: named windows under
+/// `delivery_windows` and an optional `default_delivery_window`, which may be
+/// either a name or a literal spec.
+#[derive(Clone, Debug, Default)]
+pub struct DeliveryWindows {
+    named: HashMap<String, DeliveryWindow>,
+    default: Option<DeliveryWindow>,
+}
+
+impl DeliveryWindows {
+    pub fn from_tools_config(tools: Option<&serde_json::Value>) -> Self {
+        let Some(reminders) = tools.and_then(|tools| tools.get("reminders")) else {
+            return Self::default();
+        };
+        let named = reminders
+            .get("delivery_windows")
+            .and_then(|value| value.as_object())
+            .map(|windows| {
+                windows
+                    .iter()
+                    .filter_map(|(name, spec)| {
+                        let window = DeliveryWindow::parse(spec.as_str()?)?;
+                        Some((name.trim().to_ascii_lowercase(), window))
+                    })
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default();
+        let mut windows = Self {
+            named,
+            default: None,
+        };
+        windows.default = reminders
+            .get("default_delivery_window")
+            .and_then(|value| value.as_str())
+            .and_then(|spec| windows.lookup(spec));
+        windows
+    }
+
+    /// Resolves a reminder's own window (by name or literal spec), falling
+    /// back to the configured default when it has none or it no longer
+    /// resolves. `None` means deliver any time.
+    pub fn resolve(&self, spec: Option<&str>) -> Option<DeliveryWindow> {
+        spec.map(str::trim)
+            .filter(|spec| !spec.is_empty())
+            .and_then(|spec| self.lookup(spec))
+            .or(self.default)
+    }
+
+    /// Whether `spec` names a configured window or parses as a literal one.
+    pub fn recognizes(&self, spec: &str) -> bool {
+        self.lookup(spec).is_some()
+    }
+
+    fn lookup(&self, spec: &str) -> Option<DeliveryWindow> {
+        self.named
+            .get(&spec.trim().to_ascii_lowercase())
+            .copied()
+            .or_else(|| DeliveryWindow::parse(spec))
+    }
+}
+
+fn parse_clock(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':').unwrap_or((value.trim(), "0"));
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if hours > 24 || minutes > 59 || (hours == 24 && minutes > 0) {
+        return None;
+    }
+    Some((hours * 60 + minutes) % (24 * 60))
+}
+
+fn parse_days(value: &str) -> Option<[bool; 7]> {
+    let mut days = [false; 7];
+    match value.to_ascii_lowercase().as_str() {
+        "daily" | "everyday" => return Some([true; 7]),
+        "weekdays" => return Some([true, true, true, true, true, false, false]),
+        "weekends" => return Some([false, false, false, false, false, true, true]),
+        _ => {}
+    }
+    for part in value.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = parse_weekday(from)?;
+                let to = parse_weekday(to)?;
+                let mut day = from;
+                loop {
+                    days[day] = true;
+                    if day == to {
+                        break;
+                    }
+                    day = (day + 1) % 7;
+                }
+            }
+            None => days[parse_weekday(part)?] = true,
+        }
+    }
+    Some(days)
+}
+
+fn parse_weekday(value: &str) -> Option<usize> {
+    let value = value.trim().to_ascii_lowercase();
+    let index = match value.get(..3)? {
+        "mon" => 0,
+        "tue" => 1,
+        "wed" => 2,
+        "thu" => 3,
+        "fri" => 4,
+        "sat" => 5,
+        "sun" => 6,
+        _ => return None,
+    };
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn local_ts(y: i32, m: u32, d: u32, h: u32, min: u32) -> i64 {
+        Local
+            .with_ymd_and_hms(y, m, d, h, min, 0)
+            .earliest()
+            .unwrap()
+            .timestamp()
+    }
+
+    #[test]
+    fn work_hours_hold_until_next_weekday_morning() {
+        let window = DeliveryWindow::parse("09:00-17:00 mon-fri").unwrap();
+        // 2026-03-06 is a Friday.
+        assert!(window.contains(local_ts(2026, 3, 6, 10, 0)));
+        assert!(!window.contains(local_ts(2026, 3, 6, 3, 0)));
+        assert_eq!(
+            window.next_open_at(local_ts(2026, 3, 6, 3, 0)),
+            local_ts(2026, 3, 6, 9, 0)
+        );
+        assert_eq!(
+            window.next_open_at(local_ts(2026, 3, 6, 18, 0)),
+            local_ts(2026, 3, 9, 9, 0)
+        );
+    }
+
+    #[test]
+    fn overnight_windows_wrap_midnight() {
+        let window = DeliveryWindow::parse("22:00-06:00").unwrap();
+        assert!(window.contains(local_ts(2026, 3, 6, 23, 30)));
+        assert!(window.contains(local_ts(2026, 3, 7, 5, 59)));
+        assert!(!window.contains(local_ts(2026, 3, 7, 12, 0)));
+        assert_eq!(
+            window.next_open_at(local_ts(2026, 3, 7, 12, 0)),
+            local_ts(2026, 3, 7, 22, 0)
+        );
+    }
+
+    #[test]
+    fn named_windows_and_default_resolve_from_config() {
+        let tools = json!({"reminders": {
+            "delivery_windows": {"work": "09:00-17:00 weekdays"},
+            "default_delivery_window": "08:00-21:00"
+        }});
+        let windows = DeliveryWindows::from_tools_config(Some(&tools));
+        assert_eq!(
+            windows.resolve(Some("Work")),
+            DeliveryWindow::parse("09:00-17:00 weekdays")
+        );
+        assert_eq!(windows.resolve(None), DeliveryWindow::parse("08:00-21:00"));
+        assert_eq!(windows.resolve(Some("unknown")), windows.resolve(None));
+        assert!(DeliveryWindow::parse("25:00-26:00").is_none());
+    }
+}
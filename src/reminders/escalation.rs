@@ -0,0 +1,254 @@
+use std::collections::HashMap;
+
+use super::ReminderItem;
+
+/// One rung of an escalation chain, taken when a fired reminder has gone
+/// unacknowledged for the chain's window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscalationStep {
+    /// Proactive nudge in the chat thread.
+    Chat,
+    /// Another desktop notification.
+    Notification,
+    /// POST to the configured push relay, e.g. a phone push gateway.
+    Push,
+    /// Flag the reminder as overdue-critical in the inbox.
+    InboxCritical,
+}
+
+impl EscalationStep {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "chat" => Some(EscalationStep::Chat),
+            "notification" | "desktop" => Some(EscalationStep::Notification),
+            "push" | "push_relay" => Some(EscalationStep::Push),
+            "inbox_critical" | "critical" => Some(EscalationStep::InboxCritical),
+            _ => None,
+        }
+    }
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            EscalationStep::Chat => "chat",
+            EscalationStep::Notification => "notification",
+            EscalationStep::Push => "push",
+            EscalationStep::InboxCritical => "inbox_critical",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscalationChain {
+    /// Minutes without acknowledgement before each next step.
+    pub after_minutes: i64,
+    pub steps: Vec<EscalationStep>,
+}
+
+impl EscalationChain {
+    fn parse(value: &serde_json::Value) -> Option<Self> {
+        let steps = value
+            .get("steps")?
+            .as_array()?
+            .iter()
+            .filter_map(|step| step.as_str().and_then(EscalationStep::parse))
+            .collect::<Vec<_>>();
+        if steps.is_empty() {
+            return None;
+        }
+        let after_minutes = value
+            .get("after_minutes")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(15)
+            .max(1);
+        Some(Self {
+            after_minutes,
+            steps,
+        })
+    }
+
+    /// The step due for `reminder` at `now`, with the level it brings the
+    /// reminder to. Each step waits a full window after the previous one,
+    /// the first after the reminder fired.
+    pub fn next_step(&self, reminder: &ReminderItem, now: i64) -> Option<(i32, EscalationStep)> {
+        let taken = usize::try_from(reminder.escalation_level).unwrap_or(0);
+        let step = *self.steps.get(taken)?;
+        let since = reminder.escalated_at.or(reminder.fired_at)?;
+        (now - since >= self.after_minutes * 60).then_some((taken as i32 + 1, step))
+    }
+
+    /// Whether a reminder at `level` has passed the inbox-critical step.
+    pub fn is_critical(&self, level: i32) -> bool {
+        let taken = usize::try_from(level).unwrap_or(0).min(self.steps.len());
+        self.steps[..taken].contains(&EscalationStep::InboxCritical)
+    }
+}
+
+/// Per-priority chains from `tools.reminders.escalation`:
+///
+/// ```json
+/// {"push_url": "https://relay.example/push",
+///  "chains": {"high": {"after_minutes": 10,
+///                      "steps": ["chat", "notification", "push", "inbox_critical"]},
+///             "normal": {"after_minutes": 60, "steps": ["chat", "inbox_critical"]}}}
+/// ```
+///
+/// Without `chains`, high and urgent reminders repeat the notification and
+/// then move to chat (see `default_chains`); `"chains": {}` turns
+/// escalation off. Priorities without a chain never escalate.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EscalationPolicy {
+    chains: HashMap<String, EscalationChain>,
+    pub push_url: Option<String>,
+}
+
+impl EscalationPolicy {
+    pub fn from_tools_config(tools: Option<&serde_json::Value>) -> Self {
+        let escalation = tools
+            .and_then(|tools| tools.get("reminders"))
+            .and_then(|reminders| reminders.get("escalation"));
+        let chains = escalation
+            .and_then(|escalation| escalation.get("chains"))
+            .and_then(|v| v.as_object())
+            .map(|chains| {
+                chains
+                    .iter()
+                    .filter_map(|(priority, chain)| {
+                        Some((
+                            priority.trim().to_ascii_lowercase(),
+                            EscalationChain::parse(chain)?,
+                        ))
+                    })
+                    .collect()
+            })
+            .unwrap_or_else(default_chains);
+        let push_url = escalation
+            .and_then(|escalation| escalation.get("push_url"))
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(str::to_string);
+        Self { chains, push_url }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chains.is_empty()
+    }
+
+    pub fn chain_for(&self, priority: &str) -> Option<&EscalationChain> {
+        self.chains.get(&priority.trim().to_ascii_lowercase())
+    }
+
+    /// Priorities that have a chain.
+    pub fn priorities(&self) -> Vec<&str> {
+        self.chains.keys().map(String::as_str).collect()
+    }
+}
+
+/// A second notification, another one a window later, then a nudge in chat.
+/// Urgent reminders move through it faster.
+fn default_chains() -> HashMap<String, EscalationChain> {
+    let steps = vec![
+        EscalationStep::Notification,
+        EscalationStep::Notification,
+        EscalationStep::Chat,
+    ];
+    HashMap::from([
+        (
+            "high".to_string(),
+            EscalationChain {
+                after_minutes: 15,
+                steps: steps.clone(),
+            },
+        ),
+        (
+            "urgent".to_string(),
+            EscalationChain {
+                after_minutes: 5,
+                steps,
+            },
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn fired(level: i32, fired_at: i64, escalated_at: Option<i64>) -> ReminderItem {
+        ReminderItem {
+            id: 1,
+            title: "Pay rent".to_string(),
+            due_at: fired_at,
+            created_at: 0,
+            completed_at: None,
+            fired_at: Some(fired_at),
+            target_ref: None,
+            delivery_window: None,
+            held_until: None,
+            escalation_level: level,
+            escalated_at,
+            priority: "high".to_string(),
+        }
+    }
+
+    #[test]
+    fn escalation_chain_steps_after_each_window_and_marks_critical() {
+        let tools = json!({"reminders": {"escalation": {
+            "push_url": " https://relay.example/push ",
+            "chains": {
+                "High": {"after_minutes": 10, "steps": ["chat", "bogus", "push", "inbox_critical"]},
+                "low": {"steps": []}
+            }
+        }}});
+        let policy = EscalationPolicy::from_tools_config(Some(&tools));
+        assert_eq!(
+            policy.push_url.as_deref(),
+            Some("https://relay.example/push")
+        );
+        assert!(policy.chain_for("low").is_none());
+        let chain = policy.chain_for("high").unwrap();
+        assert_eq!(chain.steps.len(), 3);
+
+        assert_eq!(chain.next_step(&fired(0, 1_000, None), 1_000 + 599), None);
+        assert_eq!(
+            chain.next_step(&fired(0, 1_000, None), 1_000 + 600),
+            Some((1, EscalationStep::Chat))
+        );
+        // The window restarts from the last escalation, not the fire time.
+        assert_eq!(
+            chain.next_step(&fired(1, 1_000, Some(5_000)), 5_000 + 300),
+            None
+        );
+        assert_eq!(
+            chain.next_step(&fired(2, 1_000, Some(5_000)), 5_000 + 600),
+            Some((3, EscalationStep::InboxCritical))
+        );
+        assert_eq!(chain.next_step(&fired(3, 1_000, Some(5_000)), 99_999), None);
+
+        assert!(!chain.is_critical(2));
+        assert!(chain.is_critical(3));
+    }
+
+    #[test]
+    fn high_and_urgent_escalate_by_default_unless_chains_are_set() {
+        let policy = EscalationPolicy::from_tools_config(None);
+        let mut priorities = policy.priorities();
+        priorities.sort();
+        assert_eq!(priorities, vec!["high", "urgent"]);
+        let high = policy.chain_for("high").unwrap();
+        assert_eq!(high.steps.last(), Some(&EscalationStep::Chat));
+        assert!(policy.chain_for("urgent").unwrap().after_minutes < high.after_minutes);
+
+        let tools = json!({"reminders": {"escalation": {"push_url": "https://relay.example"}}});
+        assert_eq!(
+            EscalationPolicy::from_tools_config(Some(&tools))
+                .priorities()
+                .len(),
+            2
+        );
+        let tools = json!({"reminders": {"escalation": {"chains": {}}}});
+        assert!(EscalationPolicy::from_tools_config(Some(&tools)).is_empty());
+    }
+}
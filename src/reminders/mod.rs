@@ -1,26 +1,36 @@
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use serde::Serialize;
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
+use crate::trash::{self, TrashBatch, TrashReceipt, TrashTable};
 
+mod delivery;
+mod delivery_window;
+pub mod escalation;
 mod schema;
+pub use delivery::{
+    delivery_backoff_seconds, DeadLetter, DeliveryChannel, DeliveryConfig, DeliveryFailure,
+    ReminderDelivery,
+};
+pub use delivery_window::{DeliveryWindow, DeliveryWindows};
+pub use escalation::{EscalationChain, EscalationPolicy, EscalationStep};
 use schema::reminders;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const REMINDERS_UP_SQL: &str = include_str!("../../migrations/20260130_create_reminders/up.sql");
+const DELIVERIES_UP_SQL: &str =
+    include_str!("../../migrations/20260324_create_reminder_deliveries/up.sql");
+const REMINDER_TRASH: TrashTable = TrashTable {
+    name: "reminders",
+    columns: "id, user_id, title, due_at, created_at, completed_at, fired_at, target_ref, \
+              delivery_window, held_until, project_id, priority",
+};
 
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
 const CREATE_DEDUP_DUE_AT_WINDOW_SECONDS: i64 = 2;
 
 #[derive(Debug, Clone, Serialize)]
@@ -31,6 +41,17 @@ pub struct ReminderItem {
     pub created_at: i64,
     pub completed_at: Option<i64>,
     pub fired_at: Option<i64>,
+    /// Origin ref of the item this reminder is about, e.g. `todo:12`.
+    pub target_ref: Option<String>,
+    /// Window name or spec (`09:00-17:00 mon-fri`) that gates delivery.
+    pub delivery_window: Option<String>,
+    /// Set while a fired reminder waits for its delivery window to open.
+    pub held_until: Option<i64>,
+    /// Escalation steps already taken since the reminder fired.
+    pub escalation_level: i32,
+    pub escalated_at: Option<i64>,
+    /// `low`, `normal`, `high` or `urgent`; picks the escalation chain.
+    pub priority: String,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -48,6 +69,12 @@ struct ReminderRow {
     created_at: i64,
     completed_at: Option<i64>,
     fired_at: Option<i64>,
+    target_ref: Option<String>,
+    delivery_window: Option<String>,
+    held_until: Option<i64>,
+    escalation_level: i32,
+    escalated_at: Option<i64>,
+    priority: String,
 }
 
 #[derive(Insertable)]
@@ -59,25 +86,49 @@ struct NewReminder<'a> {
     created_at: i64,
     completed_at: Option<i64>,
     fired_at: Option<i64>,
+    target_ref: Option<&'a str>,
+    priority: &'a str,
 }
 
 pub struct ReminderStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
+    complete_on_fire: bool,
 }
 
 impl ReminderStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_reminders_table(sqlite_path).await?;
+        trash::ensure_trash_tables(sqlite_path, &[&REMINDER_TRASH]).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+            complete_on_fire: false,
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Completes reminders as soon as they fire instead of leaving them open
+    /// until someone marks them done. This was the behavior before firing
+    /// and completing were separated; see [`reminders_complete_on_fire`].
+    pub fn with_complete_on_fire(mut self, complete_on_fire: bool) -> Self {
+        self.complete_on_fire = complete_on_fire;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn create_reminder(
@@ -86,33 +137,56 @@ impl ReminderStore {
         title: &str,
         due_at: i64,
     ) -> Result<ReminderItem> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        self.create_linked_reminder(user_id, title, due_at, None)
+            .await
+    }
+
+    /// Creates a reminder that points at another inbox item (usually a todo),
+    /// so it can be surfaced on that item and cleared when it is completed.
+    pub async fn create_linked_reminder(
+        &self,
+        user_id: &str,
+        title: &str,
+        due_at: i64,
+        target_ref: Option<&str>,
+    ) -> Result<ReminderItem> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
 
-        let existing = reminders::table
+        // Titles may be sealed, so candidates are compared after opening.
+        let mut existing_query = reminders::table
             .filter(reminders::user_id.eq(user_id))
-            .filter(reminders::title.eq(title))
             .filter(reminders::completed_at.is_null())
             .filter(reminders::fired_at.is_null())
             .filter(reminders::due_at.ge(due_at - CREATE_DEDUP_DUE_AT_WINDOW_SECONDS))
             .filter(reminders::due_at.le(due_at + CREATE_DEDUP_DUE_AT_WINDOW_SECONDS))
+            .into_boxed();
+        if let Some(target_ref) = target_ref {
+            existing_query = existing_query.filter(reminders::target_ref.eq(target_ref));
+        }
+        let existing = existing_query
             .order(reminders::id.desc())
-            .first::<ReminderRow>(&mut conn)
+            .load::<ReminderRow>(&mut conn)
             .await
-            .optional()
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            .into_iter()
+            .map(map_row)
+            .find(|item| item.title == title);
 
-        if let Some(row) = existing {
-            return Ok(map_row(row));
+        if let Some(item) = existing {
+            return Ok(item);
         }
 
+        let sealed_title = user_domains::seal(user_id, "reminder.title", title)?;
         let new = NewReminder {
             user_id,
-            title,
+            title: &sealed_title,
             due_at,
             created_at: now,
             completed_at: None,
             fired_at: None,
+            target_ref,
+            priority: priority_from_title(title).unwrap_or("normal"),
         };
 
         diesel::insert_into(reminders::table)
@@ -163,9 +237,104 @@ impl ReminderStore {
         Ok(rows.into_iter().map(map_row).collect())
     }
 
-    pub async fn complete_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
-        let now = now_ts();
+    /// Open reminders linked to `target_ref`, soonest first.
+    pub async fn list_linked_reminders(
+        &self,
+        user_id: &str,
+        target_ref: &str,
+    ) -> Result<Vec<ReminderItem>> {
         let mut conn = self.conn().await?;
+        let rows: Vec<ReminderRow> = reminders::table
+            .filter(reminders::user_id.eq(user_id))
+            .filter(reminders::target_ref.eq(target_ref))
+            .filter(reminders::completed_at.is_null())
+            .order(reminders::due_at.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Marks every open reminder linked to `target_ref` as completed and
+    /// returns how many were cleared.
+    pub async fn complete_linked_reminders(
+        &self,
+        user_id: &str,
+        target_ref: &str,
+    ) -> Result<usize> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::target_ref.eq(target_ref))
+                .filter(reminders::completed_at.is_null()),
+        )
+        .set(reminders::completed_at.eq(Some(now)))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    pub async fn set_delivery_window(
+        &self,
+        user_id: &str,
+        id: i32,
+        delivery_window: Option<&str>,
+    ) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::id.eq(id)),
+        )
+        .set((
+            reminders::delivery_window.eq(delivery_window),
+            reminders::held_until.eq::<Option<i64>>(None),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Holds a due reminder until its delivery window opens. Unlike snoozing,
+    /// `due_at` is left untouched so the reminder still reads as overdue.
+    pub async fn hold_reminder(&self, user_id: &str, id: i32, until: i64) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::id.eq(id))
+                .filter(reminders::completed_at.is_null()),
+        )
+        .set(reminders::held_until.eq(Some(until)))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    pub async fn set_priority(&self, user_id: &str, id: i32, priority: &str) -> Result<bool> {
+        let priority = parse_reminder_priority(priority).ok_or_else(|| {
+            ButterflyBotError::Runtime(format!("Unknown reminder priority '{priority}'"))
+        })?;
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::id.eq(id)),
+        )
+        .set(reminders::priority.eq(priority))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    pub async fn complete_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         let updated = diesel::update(
             reminders::table
                 .filter(reminders::user_id.eq(user_id))
@@ -178,8 +347,21 @@ impl ReminderStore {
         Ok(updated > 0)
     }
 
-    pub async fn reopen_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
+    pub async fn owns_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
         let mut conn = self.conn().await?;
+        let found = reminders::table
+            .filter(reminders::user_id.eq(user_id))
+            .filter(reminders::id.eq(id))
+            .select(reminders::id)
+            .first::<i32>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(found.is_some())
+    }
+
+    pub async fn reopen_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
         let updated = diesel::update(
             reminders::table
                 .filter(reminders::user_id.eq(user_id))
@@ -193,7 +375,7 @@ impl ReminderStore {
     }
 
     pub async fn delete_reminder(&self, user_id: &str, id: i32) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let deleted = diesel::delete(
             reminders::table
                 .filter(reminders::user_id.eq(user_id))
@@ -206,27 +388,49 @@ impl ReminderStore {
     }
 
     pub async fn delete_all(&self, user_id: &str, include_completed: bool) -> Result<usize> {
-        let mut conn = self.conn().await?;
-        let deleted = if include_completed {
-            diesel::delete(reminders::table.filter(reminders::user_id.eq(user_id)))
-                .execute(&mut conn)
-                .await
-                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+        Ok(self.clear_to_trash(user_id, include_completed).await?.count)
+    }
+
+    /// Moves the user's reminders to the trash so the clear can be undone.
+    pub async fn clear_to_trash(
+        &self,
+        user_id: &str,
+        include_completed: bool,
+    ) -> Result<TrashReceipt> {
+        let now = self.clock.now();
+        let batch_id = trash::new_batch_id(now)?;
+        let filter = if include_completed {
+            ""
         } else {
-            diesel::delete(
-                reminders::table
-                    .filter(reminders::user_id.eq(user_id))
-                    .filter(reminders::completed_at.is_null()),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
+            " AND completed_at IS NULL"
         };
-        Ok(deleted)
+        let mut conn = self.write_conn().await?;
+        let count =
+            trash::move_rows(&mut conn, &REMINDER_TRASH, user_id, filter, &batch_id, now).await?;
+        Ok(TrashReceipt {
+            store: "reminders",
+            batch_id,
+            count,
+        })
     }
 
-    pub async fn snooze_reminder(&self, user_id: &str, id: i32, due_at: i64) -> Result<bool> {
+    pub async fn list_trash(&self, user_id: &str) -> Result<Vec<TrashBatch>> {
         let mut conn = self.conn().await?;
+        trash::list_batches(&mut conn, &REMINDER_TRASH, "reminders", user_id).await
+    }
+
+    pub async fn restore_trash(&self, user_id: &str, batch_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::restore_rows(&mut conn, &REMINDER_TRASH, user_id, batch_id).await
+    }
+
+    pub async fn purge_trash(&self, cutoff: i64) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::purge_before(&mut conn, &REMINDER_TRASH, cutoff).await
+    }
+
+    pub async fn snooze_reminder(&self, user_id: &str, id: i32, due_at: i64) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
         let updated = diesel::update(
             reminders::table
                 .filter(reminders::user_id.eq(user_id))
@@ -235,6 +439,9 @@ impl ReminderStore {
         .set((
             reminders::due_at.eq(due_at),
             reminders::fired_at.eq::<Option<i64>>(None),
+            reminders::held_until.eq::<Option<i64>>(None),
+            reminders::escalation_level.eq(0),
+            reminders::escalated_at.eq::<Option<i64>>(None),
         ))
         .execute(&mut conn)
         .await
@@ -248,12 +455,17 @@ impl ReminderStore {
         now: i64,
         limit: usize,
     ) -> Result<Vec<ReminderItem>> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let mut query = reminders::table
             .filter(reminders::user_id.eq(user_id))
             .filter(reminders::completed_at.is_null())
             .filter(reminders::due_at.le(now))
             .filter(reminders::fired_at.is_null())
+            .filter(
+                reminders::held_until
+                    .is_null()
+                    .or(reminders::held_until.le(now)),
+            )
             .into_boxed();
         if limit > 0 {
             query = query.limit(limit as i64);
@@ -273,7 +485,7 @@ impl ReminderStore {
             )
             .set((
                 reminders::fired_at.eq(Some(now)),
-                reminders::completed_at.eq(Some(now)),
+                reminders::completed_at.eq(self.completed_on_fire(now)),
             ))
             .execute(&mut conn)
             .await
@@ -288,11 +500,11 @@ impl ReminderStore {
 
         if !rows.is_empty() {
             let ids: Vec<i32> = rows.iter().map(|row| row.id).collect();
-            let mut conn = self.conn().await?;
+            let mut conn = self.write_conn().await?;
             diesel::update(reminders::table.filter(reminders::id.eq_any(&ids)))
                 .set((
                     reminders::fired_at.eq(Some(now)),
-                    reminders::completed_at.eq(Some(now)),
+                    reminders::completed_at.eq(self.completed_on_fire(now)),
                 ))
                 .execute(&mut conn)
                 .await
@@ -307,8 +519,10 @@ impl ReminderStore {
         Ok(rows.into_iter().map(map_due_row).collect())
     }
 
+    /// Records that the reminder fired. It stays open until someone marks it
+    /// done, unless the store completes reminders on fire.
     pub async fn mark_fired_reminder(&self, user_id: &str, id: i32, now: i64) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let updated = diesel::update(
             reminders::table
                 .filter(reminders::user_id.eq(user_id))
@@ -318,7 +532,77 @@ impl ReminderStore {
         )
         .set((
             reminders::fired_at.eq(Some(now)),
-            reminders::completed_at.eq(Some(now)),
+            reminders::completed_at.eq(self.completed_on_fire(now)),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Records delivery without completing the reminder, whatever the store's
+    /// setting, and restarts its escalation chain.
+    pub async fn mark_fired_open(&self, user_id: &str, id: i32, now: i64) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::id.eq(id))
+                .filter(reminders::completed_at.is_null())
+                .filter(reminders::fired_at.is_null()),
+        )
+        .set((
+            reminders::fired_at.eq(Some(now)),
+            reminders::escalation_level.eq(0),
+            reminders::escalated_at.eq::<Option<i64>>(None),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    /// Fired reminders at one of `priorities` that nobody has completed yet,
+    /// oldest first.
+    pub async fn fired_open_reminders_all(
+        &self,
+        priorities: &[&str],
+        limit: usize,
+    ) -> Result<Vec<DueReminder>> {
+        let mut conn = self.conn().await?;
+        let mut query = reminders::table
+            .filter(reminders::completed_at.is_null())
+            .filter(reminders::fired_at.is_not_null())
+            .filter(reminders::priority.eq_any(priorities))
+            .into_boxed();
+        if limit > 0 {
+            query = query.limit(limit as i64);
+        }
+        let rows: Vec<ReminderRow> = query
+            .order(reminders::fired_at.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_due_row).collect())
+    }
+
+    pub async fn record_escalation(
+        &self,
+        user_id: &str,
+        id: i32,
+        level: i32,
+        now: i64,
+    ) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            reminders::table
+                .filter(reminders::user_id.eq(user_id))
+                .filter(reminders::id.eq(id))
+                .filter(reminders::completed_at.is_null()),
+        )
+        .set((
+            reminders::escalation_level.eq(level),
+            reminders::escalated_at.eq(Some(now)),
         ))
         .execute(&mut conn)
         .await
@@ -338,6 +622,11 @@ impl ReminderStore {
             .filter(reminders::completed_at.is_null())
             .filter(reminders::due_at.le(now))
             .filter(reminders::fired_at.is_null())
+            .filter(
+                reminders::held_until
+                    .is_null()
+                    .or(reminders::held_until.le(now)),
+            )
             .into_boxed();
         if limit > 0 {
             query = query.limit(limit as i64);
@@ -350,14 +639,17 @@ impl ReminderStore {
         Ok(rows.into_iter().map(map_row).collect())
     }
 
+    /// What firing writes to `completed_at`.
+    fn completed_on_fire(&self, now: i64) -> Option<i64> {
+        self.complete_on_fire.then_some(now)
+    }
+
     async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 
     async fn peek_due_reminders_all_rows(
@@ -370,6 +662,11 @@ impl ReminderStore {
             .filter(reminders::completed_at.is_null())
             .filter(reminders::due_at.le(now))
             .filter(reminders::fired_at.is_null())
+            .filter(
+                reminders::held_until
+                    .is_null()
+                    .or(reminders::held_until.le(now)),
+            )
             .into_boxed();
         if limit > 0 {
             query = query.limit(limit as i64);
@@ -412,11 +709,17 @@ impl std::str::FromStr for ReminderStatus {
 fn map_row(row: ReminderRow) -> ReminderItem {
     ReminderItem {
         id: row.id,
-        title: row.title,
+        title: user_domains::open(&row.user_id, "reminder.title", row.title),
         due_at: row.due_at,
         created_at: row.created_at,
         completed_at: row.completed_at,
         fired_at: row.fired_at,
+        target_ref: row.target_ref,
+        delivery_window: row.delivery_window,
+        held_until: row.held_until,
+        escalation_level: row.escalation_level,
+        escalated_at: row.escalated_at,
+        priority: row.priority,
     }
 }
 
@@ -428,21 +731,6 @@ fn map_due_row(row: ReminderRow) -> DueReminder {
     }
 }
 
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
-}
-
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-    }
-    Ok(())
-}
-
 async fn run_migrations(database_url: &str) -> Result<()> {
     let database_url = database_url.to_string();
     tokio::task::spawn_blocking(move || {
@@ -477,6 +765,32 @@ async fn ensure_reminders_table(database_url: &str) -> Result<()> {
             }
         }
 
+        for statement in [
+            "ALTER TABLE reminders ADD COLUMN target_ref TEXT",
+            "ALTER TABLE reminders ADD COLUMN delivery_window TEXT",
+            "ALTER TABLE reminders ADD COLUMN held_until BIGINT",
+            "ALTER TABLE reminders ADD COLUMN escalation_level INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE reminders ADD COLUMN escalated_at BIGINT",
+            "ALTER TABLE reminders ADD COLUMN project_id INTEGER",
+            "ALTER TABLE reminders ADD COLUMN priority TEXT NOT NULL DEFAULT 'normal'",
+        ] {
+            if let Err(err) =
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, statement)
+            {
+                let message = err.to_string().to_ascii_lowercase();
+                if !message.contains("duplicate column name") {
+                    return Err(ButterflyBotError::Runtime(err.to_string()));
+                }
+            }
+        }
+        diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "CREATE INDEX IF NOT EXISTS idx_reminders_user_target ON reminders(user_id, target_ref)",
+        )
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::connection::SimpleConnection::batch_execute(&mut conn, DELIVERIES_UP_SQL)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
         Ok::<_, ButterflyBotError>(())
     })
     .await
@@ -509,6 +823,43 @@ pub fn resolve_reminder_db_path(config: &serde_json::Value) -> Option<String> {
     None
 }
 
+/// Normalizes a reminder priority; `medium` and `critical` are accepted as
+/// `normal` and `urgent`.
+pub fn parse_reminder_priority(value: &str) -> Option<&'static str> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "low" => Some("low"),
+        "normal" | "medium" => Some("normal"),
+        "high" => Some("high"),
+        "urgent" | "critical" => Some("urgent"),
+        _ => None,
+    }
+}
+
+/// A `priority: high` tag in the title, which is how reminders were
+/// prioritized before they had a field for it. New reminders start there.
+pub fn priority_from_title(title: &str) -> Option<&'static str> {
+    let lower = title.to_ascii_lowercase();
+    let (_, rest) = lower.split_once("priority")?;
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let word = rest
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("");
+    parse_reminder_priority(word)
+}
+
+/// `tools.reminders.complete_on_fire`: keeps the old behavior of completing
+/// a reminder the moment it fires. Off by default, so a missed notification
+/// no longer loses the reminder.
+pub fn reminders_complete_on_fire(config: &serde_json::Value) -> bool {
+    config
+        .get("tools")
+        .and_then(|v| v.get("reminders"))
+        .and_then(|v| v.get("complete_on_fire"))
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
 pub fn default_reminder_db_path() -> String {
     crate::runtime_paths::default_db_path()
 }
@@ -516,6 +867,46 @@ pub fn default_reminder_db_path() -> String {
 #[cfg(test)]
 mod tests {
     use super::{ReminderStatus, ReminderStore};
+    use crate::clock::ManualClock;
+
+    #[tokio::test]
+    async fn reminder_due_checks_follow_the_injected_clock() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let clock = ManualClock::new(1_771_000_000);
+        let store = ReminderStore::new(&db_path)
+            .await
+            .expect("store")
+            .with_clock(clock.clone());
+
+        let created = store
+            .create_reminder("u1", "Stretch", 1_771_000_060)
+            .await
+            .expect("create");
+        assert_eq!(created.created_at, 1_771_000_000);
+        let now = store.clock().now();
+        assert!(store
+            .peek_due_reminders_all(now, 10)
+            .await
+            .expect("peek")
+            .is_empty());
+
+        let now = clock.advance(60);
+        let due = store.peek_due_reminders_all(now, 10).await.expect("peek");
+        assert_eq!(due.len(), 1);
+
+        clock.advance(5);
+        assert!(store
+            .complete_reminder("u1", created.id)
+            .await
+            .expect("complete"));
+        let done = store
+            .list_reminders("u1", ReminderStatus::Completed, 10)
+            .await
+            .expect("list");
+        assert_eq!(done[0].completed_at, Some(1_771_000_065));
+    }
 
     #[tokio::test]
     async fn reminder_create_deduplicates_near_identical_open_reminders() {
@@ -568,12 +959,160 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn due_reminders_are_auto_completed_when_fired() {
+    async fn fired_open_reminders_track_escalation_until_snoozed() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ReminderStore::new(&db_path).await.expect("store");
+
+        let now = 1_771_147_543_i64;
+        let created = store
+            .create_reminder("u1", "Renew the lease", now - 5)
+            .await
+            .expect("create reminder");
+        assert!(store
+            .mark_fired_open("u1", created.id, now)
+            .await
+            .expect("fire"));
+        assert!(store
+            .peek_due_reminders_all(now, 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let open = store
+            .fired_open_reminders_all(&["normal"], 10)
+            .await
+            .expect("open");
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].item.completed_at, None);
+        assert_eq!(open[0].item.escalation_level, 0);
+
+        store
+            .record_escalation("u1", created.id, 2, now + 600)
+            .await
+            .expect("escalate");
+        let open = store
+            .fired_open_reminders_all(&["normal"], 10)
+            .await
+            .expect("open");
+        assert_eq!(open[0].item.escalation_level, 2);
+        assert_eq!(open[0].item.escalated_at, Some(now + 600));
+
+        store
+            .snooze_reminder("u1", created.id, now + 3600)
+            .await
+            .expect("snooze");
+        assert!(store
+            .fired_open_reminders_all(&["normal"], 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let items = store
+            .list_reminders("u1", ReminderStatus::Open, 10)
+            .await
+            .expect("list");
+        assert_eq!(items[0].escalation_level, 0);
+        assert_eq!(items[0].escalated_at, None);
+    }
+
+    #[tokio::test]
+    async fn priority_comes_from_the_title_tag_and_filters_escalation() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ReminderStore::new(&db_path).await.expect("store");
+
+        let now = 1_771_147_543_i64;
+        let tagged = store
+            .create_reminder("u1", "Pay rent (priority: critical)", now - 5)
+            .await
+            .expect("create tagged");
+        assert_eq!(tagged.priority, "urgent");
+        let plain = store
+            .create_reminder("u1", "Water plants", now - 5)
+            .await
+            .expect("create plain");
+        assert_eq!(plain.priority, "normal");
+        assert!(store
+            .set_priority("u1", plain.id, "High")
+            .await
+            .expect("set priority"));
+        assert!(store
+            .set_priority("u1", plain.id, "whenever")
+            .await
+            .is_err());
+
+        for id in [tagged.id, plain.id] {
+            store.mark_fired_open("u1", id, now).await.expect("fire");
+        }
+        let high = store
+            .fired_open_reminders_all(&["high"], 10)
+            .await
+            .expect("high");
+        assert_eq!(high.len(), 1);
+        assert_eq!(high[0].item.id, plain.id);
+        assert!(store
+            .fired_open_reminders_all(&["low"], 10)
+            .await
+            .expect("low")
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn fired_reminders_stay_open_until_completed() {
         let dir = tempfile::tempdir().expect("temp dir");
         let db_path = dir.path().join("reminders.db");
         let db_path = db_path.to_string_lossy().to_string();
         let store = ReminderStore::new(&db_path).await.expect("store");
 
+        let now = 1_771_147_543_i64;
+        let created = store
+            .create_reminder("u1", "Feed the dogs", now - 5)
+            .await
+            .expect("create reminder");
+
+        let fired = store
+            .due_reminders("u1", now, 10)
+            .await
+            .expect("due reminders");
+        assert_eq!(fired.len(), 1);
+        let again = store
+            .due_reminders("u1", now + 1, 10)
+            .await
+            .expect("due reminders again");
+        assert!(again.is_empty());
+
+        let open = store
+            .list_reminders("u1", ReminderStatus::Open, 10)
+            .await
+            .expect("open reminders");
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].id, created.id);
+        assert!(open[0].fired_at.is_some());
+        assert!(open[0].completed_at.is_none());
+
+        assert!(store
+            .complete_reminder("u1", created.id)
+            .await
+            .expect("complete"));
+        let open = store
+            .list_reminders("u1", ReminderStatus::Open, 10)
+            .await
+            .expect("open reminders");
+        assert!(open.is_empty());
+    }
+
+    #[tokio::test]
+    async fn due_reminders_are_auto_completed_when_configured() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ReminderStore::new(&db_path)
+            .await
+            .expect("store")
+            .with_complete_on_fire(true);
+
         let now = 1_771_147_543_i64;
         let created = store
             .create_reminder("u1", "Feed the dogs", now - 5)
@@ -634,4 +1173,87 @@ mod tests {
             .expect("due reminders all second call");
         assert!(second.is_empty());
     }
+
+    #[tokio::test]
+    async fn linked_reminders_are_listed_and_cleared_by_target() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ReminderStore::new(&db_path).await.expect("store");
+
+        let now = 1_771_147_543_i64;
+        let linked = store
+            .create_linked_reminder("u1", "Pay rent", now + 60, Some("todo:7"))
+            .await
+            .expect("linked reminder");
+        store
+            .create_reminder("u1", "Water plants", now + 120)
+            .await
+            .expect("plain reminder");
+
+        let found = store
+            .list_linked_reminders("u1", "todo:7")
+            .await
+            .expect("linked list");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, linked.id);
+        assert_eq!(found[0].target_ref.as_deref(), Some("todo:7"));
+
+        let cleared = store
+            .complete_linked_reminders("u1", "todo:7")
+            .await
+            .expect("clear linked");
+        assert_eq!(cleared, 1);
+        assert!(store
+            .list_linked_reminders("u1", "todo:7")
+            .await
+            .expect("linked list after clear")
+            .is_empty());
+
+        let open = store
+            .list_reminders("u1", ReminderStatus::Open, 10)
+            .await
+            .expect("open reminders");
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].title, "Water plants");
+    }
+
+    #[tokio::test]
+    async fn held_reminders_wait_until_hold_expires() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("reminders.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = ReminderStore::new(&db_path).await.expect("store");
+
+        let now = 1_771_147_543_i64;
+        let created = store
+            .create_reminder("u1", "Call the bank", now - 60)
+            .await
+            .expect("create reminder");
+        assert!(store
+            .set_delivery_window("u1", created.id, Some("09:00-17:00 weekdays"))
+            .await
+            .expect("set window"));
+        assert!(store
+            .hold_reminder("u1", created.id, now + 3_600)
+            .await
+            .expect("hold"));
+
+        assert!(store
+            .peek_due_reminders_all(now, 10)
+            .await
+            .expect("peek while held")
+            .is_empty());
+
+        let released = store
+            .peek_due_reminders_all(now + 3_600, 10)
+            .await
+            .expect("peek after hold");
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].item.due_at, now - 60);
+        assert_eq!(
+            released[0].item.delivery_window.as_deref(),
+            Some("09:00-17:00 weekdays")
+        );
+    }
 }
@@ -7,5 +7,39 @@ diesel::table! {
         created_at -> BigInt,
         completed_at -> Nullable<BigInt>,
         fired_at -> Nullable<BigInt>,
+        target_ref -> Nullable<Text>,
+        delivery_window -> Nullable<Text>,
+        held_until -> Nullable<BigInt>,
+        escalation_level -> Integer,
+        escalated_at -> Nullable<BigInt>,
+        priority -> Text,
+    }
+}
+
+diesel::table! {
+    reminder_deliveries (id) {
+        id -> Integer,
+        user_id -> Text,
+        reminder_id -> Integer,
+        channel -> Text,
+        title -> Text,
+        attempts -> Integer,
+        next_attempt_at -> BigInt,
+        last_error -> Nullable<Text>,
+        created_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    reminder_dead_letters (id) {
+        id -> Integer,
+        user_id -> Text,
+        reminder_id -> Integer,
+        channel -> Text,
+        title -> Text,
+        attempts -> Integer,
+        last_error -> Text,
+        created_at -> BigInt,
+        failed_at -> BigInt,
     }
 }
@@ -0,0 +1,468 @@
+//! Off-device copies of backups, data exports, audit archives and database
+//! replicas.
+//!
+//! A single target is configured under `tools.settings.remote_storage`:
+//!
+//! ```json
+//! {"kind": "s3", "endpoint": "https://s3.eu-west-1.amazonaws.com",
+//!  "bucket": "my-bucket", "region": "eu-west-1", "access_key_id": "AKIA…",
+//!  "prefix": "butterfly", "exports": true, "audit": true, "backups": true,
+//!  "replication": true}
+//! {"kind": "webdav", "url": "https://dav.example/files/me", "username": "me"}
+//! ```
+//!
+//! The S3 secret key or WebDAV password lives in the vault as
+//! [`VAULT_SECRET`]. Every object is sealed with the [`VAULT_PASSPHRASE`]
+//! before it leaves the machine, so the provider only ever stores Cocoon
+//! containers; without a passphrase nothing is uploaded.
+
+pub mod s3;
+pub mod webdav;
+
+use std::io::Cursor;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use cocoon::Cocoon;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+
+pub const VAULT_SECRET: &str = "remote_storage_secret";
+pub const VAULT_PASSPHRASE: &str = "remote_storage_passphrase";
+pub const SEALED_SUFFIX: &str = ".cocoon";
+const PROBE_BODY: &[u8] = b"butterfly-bot remote storage probe";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Backups,
+    Exports,
+    AuditArchives,
+    Replicas,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Backups => "backups",
+            Category::Exports => "exports",
+            Category::AuditArchives => "audit",
+            Category::Replicas => "replicas",
+        }
+    }
+}
+
+#[async_trait]
+pub trait RemoteStorage: Send + Sync {
+    /// Short human-readable target, e.g. `s3://bucket` or the WebDAV URL.
+    fn describe(&self) -> String;
+    async fn put(&self, key: &str, body: Vec<u8>) -> Result<()>;
+    async fn get(&self, key: &str) -> Result<Vec<u8>>;
+    async fn delete(&self, key: &str) -> Result<()>;
+    /// Keys under `prefix`, in no particular order.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoteTarget {
+    S3(s3::S3Target),
+    WebDav(webdav::WebDavTarget),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RemoteStorageConfig {
+    pub target: RemoteTarget,
+    /// Folder every key is placed under; empty for the root.
+    pub prefix: String,
+    pub backups: bool,
+    pub exports: bool,
+    pub audit: bool,
+    pub replication: bool,
+}
+
+impl RemoteStorageConfig {
+    /// `None` when no target is configured or `kind` is `off`.
+    pub fn from_tools(tools: Option<&Value>) -> Result<Option<Self>> {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("remote_storage"))
+        else {
+            return Ok(None);
+        };
+        let text = |key: &str| {
+            section
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let flag = |key: &str| section.get(key).and_then(Value::as_bool).unwrap_or(true);
+        let target = match text("kind").to_ascii_lowercase().as_str() {
+            "" | "off" | "none" => return Ok(None),
+            "s3" => RemoteTarget::S3(s3::S3Target {
+                endpoint: required(&text("endpoint"), "endpoint")?,
+                bucket: required(&text("bucket"), "bucket")?,
+                region: Some(text("region"))
+                    .filter(|region| !region.is_empty())
+                    .unwrap_or_else(|| "us-east-1".to_string()),
+                access_key_id: required(&text("access_key_id"), "access_key_id")?,
+                path_style: section
+                    .get("path_style")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true),
+            }),
+            "webdav" => RemoteTarget::WebDav(webdav::WebDavTarget {
+                url: required(&text("url"), "url")?,
+                username: Some(text("username")).filter(|name| !name.is_empty()),
+            }),
+            other => {
+                return Err(ButterflyBotError::Config(format!(
+                    "Unknown remote storage kind '{other}' (expected s3, webdav or off)"
+                )))
+            }
+        };
+        Ok(Some(Self {
+            target,
+            prefix: text("prefix").trim_matches('/').to_string(),
+            backups: flag("backups"),
+            exports: flag("exports"),
+            audit: flag("audit"),
+            replication: flag("replication"),
+        }))
+    }
+
+    pub fn enabled_for(&self, category: Category) -> bool {
+        match category {
+            Category::Backups => self.backups,
+            Category::Exports => self.exports,
+            Category::AuditArchives => self.audit,
+            Category::Replicas => self.replication,
+        }
+    }
+
+    pub fn key(&self, category: Category, name: &str) -> String {
+        let name = name.trim_matches('/');
+        if self.prefix.is_empty() {
+            format!("{}/{name}", category.as_str())
+        } else {
+            format!("{}/{}/{name}", self.prefix, category.as_str())
+        }
+    }
+
+    pub fn connect(&self, secret: Option<String>) -> Result<Box<dyn RemoteStorage>> {
+        Ok(match &self.target {
+            RemoteTarget::S3(target) => {
+                let secret = secret.ok_or_else(|| {
+                    ButterflyBotError::Config(format!(
+                        "Remote storage secret key missing; store it in the vault as {VAULT_SECRET}"
+                    ))
+                })?;
+                Box::new(s3::S3Storage::new(target.clone(), secret)?)
+            }
+            RemoteTarget::WebDav(target) => {
+                Box::new(webdav::WebDavStorage::new(target.clone(), secret)?)
+            }
+        })
+    }
+}
+
+fn required(value: &str, field: &str) -> Result<String> {
+    if value.is_empty() {
+        return Err(ButterflyBotError::Config(format!(
+            "Remote storage requires {field}"
+        )));
+    }
+    Ok(value.to_string())
+}
+
+pub fn seal(passphrase: &str, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut cocoon = Cocoon::new(passphrase.as_bytes());
+    let mut sealed = Vec::new();
+    cocoon
+        .dump(plaintext.to_vec(), &mut sealed)
+        .map_err(|e| ButterflyBotError::SecurityStorage(format!("failed to seal upload: {e:?}")))?;
+    Ok(sealed)
+}
+
+pub fn open(passphrase: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+    Cocoon::new(passphrase.as_bytes())
+        .parse(&mut Cursor::new(sealed))
+        .map_err(|e| {
+            ButterflyBotError::SecurityStorage(format!(
+                "failed to open remote object (wrong passphrase?): {e:?}"
+            ))
+        })
+}
+
+/// A connected target plus the passphrase uploads are sealed with.
+pub struct RemoteUploader {
+    config: RemoteStorageConfig,
+    storage: Box<dyn RemoteStorage>,
+    passphrase: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionReport {
+    pub target: String,
+    pub round_trip_ms: u64,
+}
+
+impl RemoteUploader {
+    pub fn new(
+        config: RemoteStorageConfig,
+        storage: Box<dyn RemoteStorage>,
+        passphrase: String,
+    ) -> Result<Self> {
+        if passphrase.is_empty() {
+            return Err(ButterflyBotError::SecurityPolicy(format!(
+                "Remote uploads are always encrypted; store a passphrase as {VAULT_PASSPHRASE}"
+            )));
+        }
+        Ok(Self {
+            config,
+            storage,
+            passphrase,
+        })
+    }
+
+    /// Builds the uploader from config and vault secrets; `None` when no
+    /// target is configured.
+    pub fn from_tools(tools: Option<&Value>) -> Result<Option<Self>> {
+        let Some(config) = RemoteStorageConfig::from_tools(tools)? else {
+            return Ok(None);
+        };
+        let secret = crate::vault::get_secret(VAULT_SECRET)?.filter(|value| !value.is_empty());
+        let passphrase = crate::vault::get_secret(VAULT_PASSPHRASE)?.unwrap_or_default();
+        let storage = config.connect(secret)?;
+        Self::new(config, storage, passphrase).map(Some)
+    }
+
+    pub fn config(&self) -> &RemoteStorageConfig {
+        &self.config
+    }
+
+    pub fn describe(&self) -> String {
+        self.storage.describe()
+    }
+
+    /// Seals and uploads `bytes`, returning the object key, or `None` when
+    /// the category is switched off for this target.
+    pub async fn upload(
+        &self,
+        category: Category,
+        name: &str,
+        bytes: &[u8],
+    ) -> Result<Option<String>> {
+        if !self.config.enabled_for(category) {
+            return Ok(None);
+        }
+        let key = self.sealed_key(category, name);
+        self.storage
+            .put(&key, seal(&self.passphrase, bytes)?)
+            .await?;
+        Ok(Some(key))
+    }
+
+    /// Where [`upload`](Self::upload) puts `name`.
+    pub fn sealed_key(&self, category: Category, name: &str) -> String {
+        self.config.key(category, &format!("{name}{SEALED_SUFFIX}"))
+    }
+
+    pub async fn download(&self, key: &str) -> Result<Vec<u8>> {
+        open(&self.passphrase, &self.storage.get(key).await?)
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        self.storage.delete(key).await
+    }
+
+    /// Keys of sealed objects in a category, sorted.
+    pub async fn list(&self, category: Category) -> Result<Vec<String>> {
+        let mut keys: Vec<String> = self
+            .storage
+            .list(&self.config.key(category, ""))
+            .await?
+            .into_iter()
+            .filter(|key| key.ends_with(SEALED_SUFFIX))
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Writes, reads back and deletes a sealed probe object.
+    pub async fn test_connection(&self) -> Result<ConnectionReport> {
+        let started = Instant::now();
+        let key = if self.config.prefix.is_empty() {
+            format!(".butterfly-probe-{}", crate::clock::system_clock().now())
+        } else {
+            format!(
+                "{}/.butterfly-probe-{}",
+                self.config.prefix,
+                crate::clock::system_clock().now()
+            )
+        };
+        self.storage
+            .put(&key, seal(&self.passphrase, PROBE_BODY)?)
+            .await?;
+        let echoed = open(&self.passphrase, &self.storage.get(&key).await?);
+        let deleted = self.storage.delete(&key).await;
+        if echoed? != PROBE_BODY {
+            return Err(ButterflyBotError::Runtime(
+                "Remote storage returned a different object than was written".to_string(),
+            ));
+        }
+        deleted?;
+        Ok(ConnectionReport {
+            target: self.storage.describe(),
+            round_trip_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStorage {
+        objects: Mutex<HashMap<String, Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl RemoteStorage for MemoryStorage {
+        fn describe(&self) -> String {
+            "memory".to_string()
+        }
+
+        async fn put(&self, key: &str, body: Vec<u8>) -> Result<()> {
+            self.objects.lock().unwrap().insert(key.to_string(), body);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> Result<Vec<u8>> {
+            self.objects
+                .lock()
+                .unwrap()
+                .get(key)
+                .cloned()
+                .ok_or_else(|| ButterflyBotError::Runtime(format!("no object {key}")))
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.objects.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+            Ok(self
+                .objects
+                .lock()
+                .unwrap()
+                .keys()
+                .filter(|key| key.starts_with(prefix))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn webdav_config(extra: Value) -> RemoteStorageConfig {
+        let mut section =
+            json!({"kind": "webdav", "url": "https://dav.example/me", "prefix": "/bb/"});
+        for (key, value) in extra.as_object().unwrap() {
+            section[key] = value.clone();
+        }
+        RemoteStorageConfig::from_tools(Some(&json!({"settings": {"remote_storage": section}})))
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn config_parses_targets_and_rejects_incomplete_ones() {
+        assert!(RemoteStorageConfig::from_tools(None).unwrap().is_none());
+        assert!(RemoteStorageConfig::from_tools(Some(
+            &json!({"settings": {"remote_storage": {"kind": "off"}}})
+        ))
+        .unwrap()
+        .is_none());
+
+        let config = webdav_config(json!({"exports": false}));
+        assert_eq!(config.prefix, "bb");
+        assert!(!config.enabled_for(Category::Exports));
+        assert!(config.enabled_for(Category::Backups));
+        assert_eq!(
+            config.key(Category::AuditArchives, "a.jsonl"),
+            "bb/audit/a.jsonl"
+        );
+
+        let s3 = RemoteStorageConfig::from_tools(Some(&json!({"settings": {"remote_storage": {
+            "kind": "s3", "endpoint": "http://localhost:9000", "bucket": "b",
+            "access_key_id": "AK"
+        }}})))
+        .unwrap()
+        .unwrap();
+        match s3.target {
+            RemoteTarget::S3(target) => {
+                assert_eq!(target.region, "us-east-1");
+                assert!(target.path_style);
+            }
+            other => panic!("expected s3 target, got {other:?}"),
+        }
+        assert!(s3.connect(None).is_err());
+
+        for bad in [
+            json!({"kind": "s3", "bucket": "b"}),
+            json!({"kind": "ftp"}),
+            json!({"kind": "webdav"}),
+        ] {
+            assert!(RemoteStorageConfig::from_tools(Some(
+                &json!({"settings": {"remote_storage": bad}})
+            ))
+            .is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn uploads_are_sealed_and_round_trip() {
+        let uploader = RemoteUploader::new(
+            webdav_config(json!({"audit": false})),
+            Box::new(MemoryStorage::default()),
+            "correct horse".to_string(),
+        )
+        .unwrap();
+
+        let key = uploader
+            .upload(Category::Exports, "gantt.svg", b"<svg/>")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(key, "bb/exports/gantt.svg.cocoon");
+        let stored = uploader.storage.get(&key).await.unwrap();
+        assert!(!stored.windows(6).any(|window| window == b"<svg/>"));
+        assert_eq!(uploader.download(&key).await.unwrap(), b"<svg/>");
+        assert!(open("wrong", &stored).is_err());
+
+        assert!(uploader
+            .upload(Category::AuditArchives, "log.jsonl", b"{}")
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(uploader.list(Category::Exports).await.unwrap(), vec![key]);
+
+        let report = uploader.test_connection().await.unwrap();
+        assert_eq!(report.target, "memory");
+        // The probe cleans up after itself.
+        assert_eq!(uploader.storage.list("bb/.").await.unwrap().len(), 0);
+
+        assert!(RemoteUploader::new(
+            webdav_config(json!({})),
+            Box::new(MemoryStorage::default()),
+            String::new()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,265 @@
+//! WebDAV servers (Nextcloud, ownCloud, Apache mod_dav, …) with basic auth.
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{Method, StatusCode, Url};
+
+use super::RemoteStorage;
+use crate::error::{ButterflyBotError, Result};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct WebDavTarget {
+    /// Collection everything is stored under.
+    pub url: String,
+    pub username: Option<String>,
+}
+
+pub struct WebDavStorage {
+    base: Url,
+    username: Option<String>,
+    password: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WebDavStorage {
+    pub fn new(target: WebDavTarget, password: Option<String>) -> Result<Self> {
+        let mut base = Url::parse(&target.url).map_err(|e| {
+            ButterflyBotError::Config(format!("Invalid WebDAV URL '{}': {e}", target.url))
+        })?;
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(120))
+            .build()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Self {
+            base,
+            username: target.username,
+            password,
+            client,
+        })
+    }
+
+    fn url(&self, key: &str) -> Result<Url> {
+        self.base
+            .join(key.trim_start_matches('/'))
+            .map_err(|e| ButterflyBotError::Config(e.to_string()))
+    }
+
+    fn request(&self, method: Method, url: Url) -> reqwest::RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_deref()),
+            None => request,
+        }
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+        what: &str,
+    ) -> Result<reqwest::Response> {
+        let response = request
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(ButterflyBotError::Http(format!(
+            "WebDAV {what} failed with HTTP {}",
+            response.status()
+        )))
+    }
+
+    /// Creates the collections above `key`, one level at a time. Servers
+    /// answer 405 for collections that already exist.
+    async fn ensure_collections(&self, key: &str) -> Result<()> {
+        let mut path = String::new();
+        let parents: Vec<&str> = key.split('/').filter(|part| !part.is_empty()).collect();
+        for part in parents.iter().take(parents.len().saturating_sub(1)) {
+            path.push_str(part);
+            path.push('/');
+            let mkcol = Method::from_bytes(b"MKCOL")
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            let response = self
+                .request(mkcol, self.url(&path)?)
+                .send()
+                .await
+                .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+            let status = response.status();
+            if !(status.is_success() || status == StatusCode::METHOD_NOT_ALLOWED) {
+                return Err(ButterflyBotError::Http(format!(
+                    "WebDAV could not create collection {path}: HTTP {status}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl RemoteStorage for WebDavStorage {
+    fn describe(&self) -> String {
+        self.base.to_string()
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>) -> Result<()> {
+        self.ensure_collections(key).await?;
+        self.send(
+            self.request(Method::PUT, self.url(key)?).body(body),
+            "upload",
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Vec<u8>> {
+        let response = self
+            .send(self.request(Method::GET, self.url(key)?), "download")
+            .await?;
+        response
+            .bytes()
+            .await
+            .map(|bytes| bytes.to_vec())
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.send(self.request(Method::DELETE, self.url(key)?), "delete")
+            .await?;
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let collection = match prefix.rfind('/') {
+            Some(index) => &prefix[..=index],
+            None => "",
+        };
+        let propfind = Method::from_bytes(b"PROPFIND")
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let response = self
+            .request(propfind, self.url(collection)?)
+            .header("Depth", "1")
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !response.status().is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "WebDAV listing failed with HTTP {}",
+                response.status()
+            )));
+        }
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        let href_re = Regex::new(r"(?i)<(?:[a-z0-9]+:)?href>([^<]+)</(?:[a-z0-9]+:)?href>")
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let base_path = self.base.path().to_string();
+        Ok(href_re
+            .captures_iter(&body)
+            .filter_map(|c| {
+                let href = c[1].trim();
+                // Hrefs may be absolute URLs or absolute paths.
+                let path = Url::parse(href)
+                    .map(|url| url.path().to_string())
+                    .unwrap_or_else(|_| href.to_string());
+                let key = percent_decode(path.strip_prefix(&base_path)?);
+                (!key.is_empty() && !key.ends_with('/') && key.starts_with(prefix)).then_some(key)
+            })
+            .collect())
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).ok();
+            if let Some(byte) = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                out.push(byte);
+                index += 3;
+                continue;
+            }
+        }
+        out.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+
+    #[tokio::test]
+    async fn put_creates_collections_and_list_reads_propfind() {
+        let server = MockServer::start_async().await;
+        let mkcol = server
+            .mock_async(|when, then| {
+                when.method("MKCOL").path("/dav/bb/");
+                then.status(405);
+            })
+            .await;
+        let mkcol_exports = server
+            .mock_async(|when, then| {
+                when.method("MKCOL").path("/dav/bb/exports/");
+                then.status(201);
+            })
+            .await;
+        let put = server
+            .mock_async(|when, then| {
+                when.method("PUT")
+                    .path("/dav/bb/exports/ab.svg.cocoon")
+                    .header_exists("authorization")
+                    .body("sealed");
+                then.status(201);
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.method("PROPFIND").path("/dav/bb/exports/");
+                then.status(207).body(
+                    r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response><d:href>/dav/bb/exports/</d:href></d:response>
+  <d:response><d:href>/dav/bb/exports/a%20b.svg.cocoon</d:href></d:response>
+  <d:response><d:href>http://elsewhere/dav/bb/exports/c.pdf.cocoon</d:href></d:response>
+</d:multistatus>"#,
+                );
+            })
+            .await;
+
+        let storage = WebDavStorage::new(
+            WebDavTarget {
+                url: server.url("/dav"),
+                username: Some("me".to_string()),
+            },
+            Some("pw".to_string()),
+        )
+        .unwrap();
+        storage
+            .put("bb/exports/ab.svg.cocoon", b"sealed".to_vec())
+            .await
+            .unwrap();
+        mkcol.assert_async().await;
+        mkcol_exports.assert_async().await;
+        put.assert_async().await;
+
+        let mut keys = storage.list("bb/exports/").await.unwrap();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["bb/exports/a b.svg.cocoon", "bb/exports/c.pdf.cocoon"]
+        );
+    }
+}
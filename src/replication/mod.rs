@@ -0,0 +1,753 @@
+//! Continuous replication of the database's write-ahead log.
+//!
+//! Configured under `tools.settings.replication`:
+//!
+//! ```json
+//! {"enabled": true, "interval_seconds": 10, "generation_hours": 24,
+//!  "keep_generations": 2, "directory": "/mnt/replica/butterfly"}
+//! ```
+//!
+//! A generation starts with a raw copy of the database file. After that
+//! each pass ships only the log frames committed since the previous one,
+//! so the replica trails the live database by about one interval instead
+//! of a full snapshot. Pages are copied as SQLCipher wrote them and stay
+//! encrypted with the database key. Without `directory` generations go to
+//! the configured remote storage (with `replication` on there), sealed
+//! like every other upload.
+//!
+//! Between passes the [`Replicator`] holds a read transaction so SQLite
+//! cannot restart the log over frames that have not been shipped, and each
+//! pass reads the log with writers held off. When continuity cannot be
+//! shown — the daemon restarted, a pass failed, or the log was restarted
+//! in a way that may have dropped frames — the next pass starts a new
+//! generation. A new generation also starts every `generation_hours` to
+//! bound how much log a restore has to replay.
+//!
+//! `butterfly-bot restore --from replica` rebuilds the newest generation
+//! with [`materialize`] and swaps it in like a snapshot.
+
+mod wal;
+
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use chrono::{TimeZone, Utc};
+use diesel::connection::SimpleConnection;
+use diesel::sqlite::SqliteConnection;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::remote_storage::{Category, RemoteUploader, SEALED_SUFFIX};
+use wal::{Checksum, WalHeader, HEADER_LEN};
+
+pub const DEFAULT_INTERVAL_SECONDS: u64 = 10;
+pub const DEFAULT_GENERATION_HOURS: u64 = 24;
+pub const DEFAULT_KEEP_GENERATIONS: usize = 2;
+const BASE_NAME: &str = "base.db";
+const SEGMENT_PREFIX: &str = "wal-";
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Log size, in pages, past which a pass checkpoints so the log can
+/// restart; SQLite's own automatic checkpoint uses the same figure.
+const CHECKPOINT_PAGES: usize = 1000;
+const PIN: &str = "BEGIN; SELECT count(*) FROM sqlite_master;";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicationConfig {
+    pub enabled: bool,
+    pub interval_seconds: u64,
+    pub generation_hours: u64,
+    pub keep_generations: usize,
+    /// `None` to replicate to the configured remote storage.
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: DEFAULT_INTERVAL_SECONDS,
+            generation_hours: DEFAULT_GENERATION_HOURS,
+            keep_generations: DEFAULT_KEEP_GENERATIONS,
+            directory: None,
+        }
+    }
+}
+
+impl ReplicationConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("replication"))
+        else {
+            return Self::default();
+        };
+        let defaults = Self::default();
+        let number = |key: &str, default: u64| {
+            section
+                .get(key)
+                .and_then(Value::as_u64)
+                .unwrap_or(default)
+                .max(1)
+        };
+        Self {
+            enabled: section
+                .get("enabled")
+                .and_then(Value::as_bool)
+                .unwrap_or(defaults.enabled),
+            interval_seconds: number("interval_seconds", defaults.interval_seconds),
+            generation_hours: number("generation_hours", defaults.generation_hours),
+            keep_generations: number("keep_generations", defaults.keep_generations as u64) as usize,
+            directory: section
+                .get("directory")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        }
+    }
+}
+
+/// Where generations are kept: `<generation>/base.db` plus numbered log
+/// segments beside it.
+pub enum ReplicaTarget {
+    Directory(PathBuf),
+    Remote(RemoteUploader),
+}
+
+impl ReplicaTarget {
+    pub fn from_tools(config: &ReplicationConfig, tools: Option<&Value>) -> Result<Self> {
+        if let Some(directory) = &config.directory {
+            return Ok(Self::Directory(directory.clone()));
+        }
+        let uploader = RemoteUploader::from_tools(tools)?.ok_or_else(|| {
+            ButterflyBotError::Config(
+                "Replication needs a directory or a remote storage target".to_string(),
+            )
+        })?;
+        if !uploader.config().enabled_for(Category::Replicas) {
+            return Err(ButterflyBotError::Config(
+                "Remote storage has replication switched off".to_string(),
+            ));
+        }
+        Ok(Self::Remote(uploader))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Directory(directory) => directory.to_string_lossy().to_string(),
+            Self::Remote(uploader) => uploader.describe(),
+        }
+    }
+
+    async fn put(&self, name: &str, bytes: &[u8]) -> Result<()> {
+        match self {
+            Self::Directory(directory) => {
+                let path = directory.join(name);
+                let io_error = |e: std::io::Error| {
+                    ButterflyBotError::Runtime(format!("{}: {e}", path.display()))
+                };
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent).map_err(io_error)?;
+                }
+                let partial = path.with_extension("partial");
+                std::fs::write(&partial, bytes).map_err(io_error)?;
+                std::fs::rename(&partial, &path).map_err(io_error)
+            }
+            Self::Remote(uploader) => uploader
+                .upload(Category::Replicas, name, bytes)
+                .await
+                .map(|_| ()),
+        }
+    }
+
+    async fn get(&self, name: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::Directory(directory) => {
+                let path = directory.join(name);
+                std::fs::read(&path)
+                    .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", path.display())))
+            }
+            Self::Remote(uploader) => {
+                uploader
+                    .download(&uploader.sealed_key(Category::Replicas, name))
+                    .await
+            }
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        match self {
+            Self::Directory(directory) => {
+                let path = directory.join(name);
+                std::fs::remove_file(&path)
+                    .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", path.display())))
+            }
+            Self::Remote(uploader) => {
+                uploader
+                    .delete(&uploader.sealed_key(Category::Replicas, name))
+                    .await
+            }
+        }
+    }
+
+    /// Every stored object as `<generation>/<file>`, sorted.
+    async fn names(&self) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        match self {
+            Self::Directory(directory) => {
+                let read_dir = |dir: &Path| match std::fs::read_dir(dir) {
+                    Ok(entries) => Ok(entries.filter_map(|entry| entry.ok()).collect::<Vec<_>>()),
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+                    Err(err) => Err(ButterflyBotError::Runtime(format!(
+                        "{}: {err}",
+                        dir.display()
+                    ))),
+                };
+                for generation in read_dir(directory)? {
+                    if !generation.path().is_dir() {
+                        continue;
+                    }
+                    let generation_name = generation.file_name().to_string_lossy().to_string();
+                    for file in read_dir(&generation.path())? {
+                        let file_name = file.file_name().to_string_lossy().to_string();
+                        if !file_name.ends_with(".partial") {
+                            names.push(format!("{generation_name}/{file_name}"));
+                        }
+                    }
+                }
+            }
+            Self::Remote(uploader) => {
+                let root = uploader.config().key(Category::Replicas, "");
+                names.extend(
+                    uploader
+                        .list(Category::Replicas)
+                        .await?
+                        .iter()
+                        .filter_map(|key| key.strip_prefix(&root)?.strip_suffix(SEALED_SUFFIX))
+                        .map(str::to_string),
+                );
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Generation names, oldest first.
+    pub async fn generations(&self) -> Result<Vec<String>> {
+        let mut generations = self
+            .names()
+            .await?
+            .into_iter()
+            .filter_map(|name| Some(name.split_once('/')?.0.to_string()))
+            .collect::<Vec<_>>();
+        generations.dedup();
+        Ok(generations)
+    }
+
+    async fn delete_generation(&self, generation: &str) -> Result<()> {
+        let prefix = format!("{generation}/");
+        for name in self.names().await? {
+            if name.starts_with(&prefix) {
+                self.delete(&name).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one replication pass.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ReplicationRun {
+    pub at: i64,
+    pub generation: Option<String>,
+    /// True when this pass copied the whole database to start a generation.
+    pub new_generation: bool,
+    pub bytes: u64,
+    pub pruned: usize,
+    pub error: Option<String>,
+}
+
+fn last_run_slot() -> &'static Mutex<Option<ReplicationRun>> {
+    static LAST_RUN: OnceLock<Mutex<Option<ReplicationRun>>> = OnceLock::new();
+    LAST_RUN.get_or_init(|| Mutex::new(None))
+}
+
+/// The most recent pass since the daemon started, failed or not.
+pub fn last_run() -> Option<ReplicationRun> {
+    last_run_slot()
+        .lock()
+        .map(|slot| slot.clone())
+        .unwrap_or(None)
+}
+
+fn record(run: &ReplicationRun) {
+    if let Ok(mut slot) = last_run_slot().lock() {
+        *slot = Some(run.clone());
+    }
+}
+
+pub fn generation_name(now: i64) -> String {
+    Utc.timestamp_opt(now, 0)
+        .single()
+        .unwrap_or_default()
+        .format(STAMP_FORMAT)
+        .to_string()
+}
+
+/// Segments are numbered in shipping order and named after the log offset
+/// they start at; offset 0 means the segment opens a new log, header and
+/// all.
+fn segment_name(generation: &str, index: u64, start: usize) -> String {
+    format!("{generation}/{SEGMENT_PREFIX}{index:08}-{start:012}")
+}
+
+fn segment_start(file_name: &str) -> Option<usize> {
+    file_name
+        .strip_prefix(SEGMENT_PREFIX)?
+        .split_once('-')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// How far into which log a generation has been shipped. `end` is 0 until
+/// something from the log has gone out, so the first segment of every log
+/// carries its header.
+#[derive(Clone, Copy, Debug)]
+struct Cursor {
+    log: WalHeader,
+    end: usize,
+    checksum: Checksum,
+}
+
+impl Cursor {
+    fn start(log: WalHeader) -> Self {
+        Self {
+            log,
+            end: 0,
+            checksum: log.checksum,
+        }
+    }
+
+    /// Whether a restart of this cursor's log, now holding `restarted`,
+    /// provably lost nothing: the new log has not yet reached where this
+    /// one stopped, and nothing of the old log follows that point.
+    fn restart_lost_nothing(&self, restarted: &WalHeader, log: &[u8]) -> bool {
+        let (reached, _) = restarted.committed_end(log, HEADER_LEN, restarted.checksum);
+        reached <= self.end
+            && log.len() >= self.end
+            && self
+                .log
+                .frame_at(log, self.end.max(HEADER_LEN), self.checksum)
+                .is_none()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Position {
+    generation: String,
+    started_at: i64,
+    /// `None` while the log has had no header since the generation began.
+    cursor: Option<Cursor>,
+    next_segment: u64,
+}
+
+impl Position {
+    /// Where to carry on in the current log, or `None` when frames may
+    /// have been lost and a new generation has to start.
+    fn resume(&self, header: Option<WalHeader>, log: &[u8]) -> Option<Option<Cursor>> {
+        match (self.cursor, header) {
+            (None, None) => Some(None),
+            (Some(cursor), Some(header)) if header.salt == cursor.log.salt => Some(Some(cursor)),
+            (Some(cursor), Some(header))
+                if header.salt.0 == cursor.log.salt.0.wrapping_add(1)
+                    && cursor.restart_lost_nothing(&header, log) =>
+            {
+                Some(Some(Cursor::start(header)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// What a pass read while writers were held off.
+struct Capture {
+    position: Position,
+    /// The database file, when this pass starts a generation.
+    base: Option<Vec<u8>>,
+    segment: Option<(String, Vec<u8>)>,
+}
+
+/// The connections replication keeps open between passes: `pin` holds a
+/// read transaction so the log cannot restart over unshipped frames, and
+/// `lock` takes the write lock while a pass reads the log.
+pub struct Replicator {
+    db_path: String,
+    pin: SqliteConnection,
+    lock: SqliteConnection,
+    position: Option<Position>,
+}
+
+impl Replicator {
+    pub fn open(db_path: &str) -> Result<Self> {
+        let mut pin = crate::db::open_existing_sqlcipher_connection_sync(db_path)?;
+        pin.batch_execute(PIN)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Self {
+            db_path: db_path.to_string(),
+            pin,
+            lock: crate::db::open_existing_sqlcipher_connection_sync(db_path)?,
+            position: None,
+        })
+    }
+
+    fn capture(&mut self, config: &ReplicationConfig, now: i64) -> Result<Capture> {
+        self.lock
+            .batch_execute("BEGIN IMMEDIATE;")
+            .map_err(|e| ButterflyBotError::Runtime(format!("Replication lock: {e}")))?;
+        let captured = self.capture_locked(config, now);
+        let released = self.lock.batch_execute("COMMIT;");
+        let capture = captured?;
+        released.map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(capture)
+    }
+
+    fn capture_locked(&mut self, config: &ReplicationConfig, now: i64) -> Result<Capture> {
+        let wal_path = format!("{}-wal", self.db_path);
+        let log = match std::fs::read(&wal_path) {
+            Ok(log) => log,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(ButterflyBotError::Runtime(format!("{wal_path}: {err}"))),
+        };
+        let header = WalHeader::parse(&log);
+        let resumed = self.position.as_ref().and_then(|position| {
+            let age = now.saturating_sub(position.started_at);
+            if age >= (config.generation_hours * 3600) as i64 {
+                return None;
+            }
+            Some((position.clone(), position.resume(header, &log)?))
+        });
+        let (mut position, cursor, base) = match resumed {
+            Some((position, cursor)) => (position, cursor, None),
+            None => {
+                let base = std::fs::read(&self.db_path)
+                    .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", self.db_path)))?;
+                let position = Position {
+                    generation: generation_name(now),
+                    started_at: now,
+                    cursor: None,
+                    next_segment: 0,
+                };
+                (position, header.map(Cursor::start), Some(base))
+            }
+        };
+
+        let mut segment = None;
+        if let Some(mut cursor) = cursor {
+            let from = cursor.end.max(HEADER_LEN);
+            let (end, checksum) = cursor.log.committed_end(&log, from, cursor.checksum);
+            if end > from {
+                segment = Some((
+                    segment_name(&position.generation, position.next_segment, cursor.end),
+                    log[cursor.end..end].to_vec(),
+                ));
+                position.next_segment += 1;
+                cursor.end = end;
+                cursor.checksum = checksum;
+            }
+            position.cursor = Some(cursor);
+        }
+
+        // Everything up to here is captured and writers are still held
+        // off, so a checkpoint cannot fold in anything unshipped. Pinning
+        // again at the new end lets later checkpoints reach it.
+        let mut repin = String::from("COMMIT;");
+        let shipped = position.cursor.map_or(0, |cursor| cursor.end);
+        if header.is_some_and(|header| shipped >= CHECKPOINT_PAGES * header.frame_len()) {
+            repin.push_str("PRAGMA wal_checkpoint(PASSIVE);");
+        }
+        repin.push_str(PIN);
+        self.pin
+            .batch_execute(&repin)
+            .map_err(|e| ButterflyBotError::Runtime(format!("Replication pin: {e}")))?;
+
+        Ok(Capture {
+            position,
+            base,
+            segment,
+        })
+    }
+}
+
+/// One replication pass. The replicator in `slot` is opened on first use
+/// and dropped after a failure, so the next pass starts a new generation.
+/// The outcome is kept for [`last_run`] whether or not it succeeded.
+pub async fn run_pass(
+    slot: &mut Option<Replicator>,
+    db_path: &str,
+    config: &ReplicationConfig,
+    target: &ReplicaTarget,
+    now: i64,
+) -> Result<ReplicationRun> {
+    let result = replicate(slot, db_path, config, target, now).await;
+    let run = match &result {
+        Ok(run) => run.clone(),
+        Err(err) => ReplicationRun {
+            at: now,
+            error: Some(err.to_string()),
+            ..Default::default()
+        },
+    };
+    record(&run);
+    result
+}
+
+async fn replicate(
+    slot: &mut Option<Replicator>,
+    db_path: &str,
+    config: &ReplicationConfig,
+    target: &ReplicaTarget,
+    now: i64,
+) -> Result<ReplicationRun> {
+    let replicator = slot.take();
+    let (mut replicator, capture) = {
+        let db_path = db_path.to_string();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut replicator = match replicator {
+                Some(replicator) => replicator,
+                None => Replicator::open(&db_path)?,
+            };
+            let capture = replicator.capture(&config, now)?;
+            Ok::<_, ButterflyBotError>((replicator, capture))
+        })
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??
+    };
+
+    let generation = capture.position.generation.clone();
+    let mut run = ReplicationRun {
+        at: now,
+        generation: Some(generation.clone()),
+        new_generation: capture.base.is_some(),
+        ..Default::default()
+    };
+    if let Some(base) = &capture.base {
+        // Two generations started within a second share a name; the newer
+        // one must not inherit the older one's segments.
+        target.delete_generation(&generation).await?;
+        target
+            .put(&format!("{generation}/{BASE_NAME}"), base)
+            .await?;
+        run.bytes += base.len() as u64;
+    }
+    if let Some((name, bytes)) = &capture.segment {
+        target.put(name, bytes).await?;
+        run.bytes += bytes.len() as u64;
+    }
+    if capture.base.is_some() {
+        let generations = target.generations().await?;
+        let excess = generations.len().saturating_sub(config.keep_generations);
+        for old in &generations[..excess] {
+            target.delete_generation(old).await?;
+        }
+        run.pruned = excess;
+    }
+
+    replicator.position = Some(capture.position);
+    *slot = Some(replicator);
+    Ok(run)
+}
+
+/// Rebuilds `generation` (the newest when `None`) from `target` into a
+/// database file at `dest` and returns the generation's name. The result
+/// still needs [`crate::backup::validate_snapshot`] before it is trusted.
+pub async fn materialize(
+    target: &ReplicaTarget,
+    generation: Option<&str>,
+    dest: &Path,
+) -> Result<String> {
+    let generations = target.generations().await?;
+    let generation = match generation {
+        Some(wanted) => generations.into_iter().find(|name| name == wanted),
+        None => generations.into_iter().last(),
+    }
+    .ok_or_else(|| {
+        ButterflyBotError::Config(format!("No replica generation in {}", target.describe()))
+    })?;
+    let io_error = |path: &Path, e: std::io::Error| {
+        ButterflyBotError::Runtime(format!("{}: {e}", path.display()))
+    };
+
+    let partial = dest.with_extension("partial");
+    let base = target.get(&format!("{generation}/{BASE_NAME}")).await?;
+    std::fs::write(&partial, base).map_err(|e| io_error(&partial, e))?;
+    let mut db = std::fs::OpenOptions::new()
+        .write(true)
+        .open(&partial)
+        .map_err(|e| io_error(&partial, e))?;
+
+    // Segments of one log are contiguous; each log is replayed whole once
+    // the next one starts.
+    let prefix = format!("{generation}/");
+    let mut log = Vec::new();
+    for name in target.names().await? {
+        let Some(start) = name.strip_prefix(&prefix).and_then(segment_start) else {
+            continue;
+        };
+        let bytes = target.get(&name).await?;
+        if start == 0 {
+            if !log.is_empty() {
+                wal::apply(&mut db, &log)?;
+            }
+            log = bytes;
+        } else if start == log.len() {
+            log.extend_from_slice(&bytes);
+        } else {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Replica {generation} is missing log data before {name}"
+            )));
+        }
+    }
+    if !log.is_empty() {
+        wal::apply(&mut db, &log)?;
+    }
+    db.sync_all().map_err(|e| io_error(&partial, e))?;
+    drop(db);
+    std::fs::rename(&partial, dest).map_err(|e| io_error(dest, e))?;
+    Ok(generation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::wal::tests::build_log;
+    use super::*;
+    use diesel::sql_types::Integer;
+    use diesel::{QueryableByName, RunQueryDsl};
+    use serde_json::json;
+
+    #[derive(QueryableByName)]
+    struct Count {
+        #[diesel(sql_type = Integer)]
+        n: i32,
+    }
+
+    fn count(path: &str) -> i32 {
+        let mut conn = crate::db::open_existing_sqlcipher_connection_sync(path).unwrap();
+        diesel::sql_query("SELECT count(*) AS n FROM notes")
+            .get_result::<Count>(&mut conn)
+            .unwrap()
+            .n
+    }
+
+    #[tokio::test]
+    async fn replica_follows_the_log_and_rebuilds_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("live.db").to_string_lossy().to_string();
+        let mut writer = crate::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        writer
+            .batch_execute(
+                "PRAGMA journal_mode = WAL; CREATE TABLE notes (body TEXT);
+                 INSERT INTO notes VALUES ('one');",
+            )
+            .unwrap();
+
+        let tools = json!({"settings": {"replication": {
+            "enabled": true, "keep_generations": 1,
+            "directory": dir.path().join("replica").to_string_lossy()
+        }}});
+        let config = ReplicationConfig::from_tools(Some(&tools));
+        assert!(config.enabled);
+        assert_eq!(config.interval_seconds, DEFAULT_INTERVAL_SECONDS);
+        let target = ReplicaTarget::from_tools(&config, Some(&tools)).unwrap();
+        let mut slot = None;
+        let now = 1_700_000_000;
+
+        let first = run_pass(&mut slot, &db_path, &config, &target, now)
+            .await
+            .unwrap();
+        assert!(first.new_generation);
+
+        // Later passes ship only what was committed since.
+        writer
+            .batch_execute("INSERT INTO notes VALUES ('two'); INSERT INTO notes VALUES ('three');")
+            .unwrap();
+        let second = run_pass(&mut slot, &db_path, &config, &target, now + 10)
+            .await
+            .unwrap();
+        assert!(!second.new_generation);
+        assert!(second.bytes > 0);
+        let idle = run_pass(&mut slot, &db_path, &config, &target, now + 20)
+            .await
+            .unwrap();
+        assert_eq!(idle.bytes, 0);
+
+        let rebuilt = dir.path().join("rebuilt.db");
+        let generation = materialize(&target, None, &rebuilt).await.unwrap();
+        assert_eq!(generation, generation_name(now));
+        crate::backup::validate_snapshot(&rebuilt).unwrap();
+        assert_eq!(count(&rebuilt.to_string_lossy()), 3);
+
+        // A new generation replaces the old one once `keep` is reached.
+        let due = now + (config.generation_hours * 3600) as i64;
+        let renewed = run_pass(&mut slot, &db_path, &config, &target, due)
+            .await
+            .unwrap();
+        assert!(renewed.new_generation);
+        assert_eq!(renewed.pruned, 1);
+        assert_eq!(
+            target.generations().await.unwrap(),
+            vec![generation_name(due)]
+        );
+        assert!(materialize(&target, Some(&generation), &rebuilt)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn a_restart_counts_as_continuous_only_when_nothing_followed_the_cursor() {
+        let old = build_log(512, (1, 7), &[(1, 1, 1), (2, 2, 2), (3, 3, 3)]);
+        let old_header = WalHeader::parse(&old).unwrap();
+        let position = |frames: usize| {
+            let (end, checksum) = old_header.committed_end(
+                &old[..HEADER_LEN + frames * old_header.frame_len()],
+                HEADER_LEN,
+                old_header.checksum,
+            );
+            Position {
+                generation: generation_name(0),
+                started_at: 0,
+                cursor: Some(Cursor {
+                    log: old_header,
+                    end,
+                    checksum,
+                }),
+                next_segment: 1,
+            }
+        };
+        // SQLite restarts a log by writing over it from the top.
+        let overwrite = |new: Vec<u8>| {
+            let mut file = old.clone();
+            file[..new.len()].copy_from_slice(&new);
+            file
+        };
+
+        let restarted = overwrite(build_log(512, (2, 5), &[(1, 1, 9)]));
+        let header = WalHeader::parse(&restarted);
+        assert!(position(3).resume(header, &restarted).is_some());
+        // The old log went on past the cursor before it restarted.
+        assert!(position(2).resume(header, &restarted).is_none());
+        // The new log has already overwritten where the old one stopped.
+        let further = overwrite(build_log(512, (2, 5), &[(1, 0, 9), (2, 2, 9)]));
+        assert!(position(1)
+            .resume(WalHeader::parse(&further), &further)
+            .is_none());
+        // Two restarts since the last pass.
+        let twice = overwrite(build_log(512, (3, 5), &[(1, 1, 9)]));
+        assert!(position(3)
+            .resume(WalHeader::parse(&twice), &twice)
+            .is_none());
+        assert!(position(3).resume(None, &[]).is_none());
+    }
+}
@@ -0,0 +1,251 @@
+//! The parts of SQLite's write-ahead log format the replicator needs:
+//! the header, the frame checksum chain, and replaying committed frames
+//! onto a copy of the database file.
+//!
+//! A log is a 32-byte header followed by frames of a 24-byte frame header
+//! and one page. A frame belongs to the current log only if it carries the
+//! header's salts and continues the checksum chain; a transaction is
+//! complete once its last frame records the database size ("commit
+//! frame"). Frames past the last commit frame are ignored, as SQLite does.
+
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::error::{ButterflyBotError, Result};
+
+pub const HEADER_LEN: usize = 32;
+pub const FRAME_HEADER_LEN: usize = 24;
+const MAGIC_LITTLE_ENDIAN: u32 = 0x377f_0682;
+const MAGIC_BIG_ENDIAN: u32 = 0x377f_0683;
+
+pub type Checksum = (u32, u32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WalHeader {
+    pub page_size: u32,
+    /// Changes every time SQLite restarts the log; the first salt goes up
+    /// by exactly one per restart.
+    pub salt: (u32, u32),
+    /// Where the frame checksum chain starts.
+    pub checksum: Checksum,
+    big_endian: bool,
+}
+
+impl WalHeader {
+    /// `None` for a missing, short or corrupt header.
+    pub fn parse(log: &[u8]) -> Option<Self> {
+        let header = log.get(..HEADER_LEN)?;
+        let big_endian = match read_u32(header, 0) {
+            MAGIC_LITTLE_ENDIAN => false,
+            MAGIC_BIG_ENDIAN => true,
+            _ => return None,
+        };
+        let page_size = read_u32(header, 8);
+        if !(512..=65536).contains(&page_size) || !page_size.is_power_of_two() {
+            return None;
+        }
+        let checksum = (read_u32(header, 24), read_u32(header, 28));
+        if checksum_bytes(big_endian, (0, 0), &header[..24]) != checksum {
+            return None;
+        }
+        Some(Self {
+            page_size,
+            salt: (read_u32(header, 16), read_u32(header, 20)),
+            checksum,
+            big_endian,
+        })
+    }
+
+    pub fn frame_len(&self) -> usize {
+        FRAME_HEADER_LEN + self.page_size as usize
+    }
+
+    /// The frame at `offset` if it is whole and belongs to this log, given
+    /// the checksum of everything before it.
+    pub fn frame_at<'a>(&self, log: &'a [u8], offset: usize, prior: Checksum) -> Option<Frame<'a>> {
+        let bytes = log.get(offset..offset.checked_add(self.frame_len())?)?;
+        let (header, page) = bytes.split_at(FRAME_HEADER_LEN);
+        if (read_u32(header, 8), read_u32(header, 12)) != self.salt {
+            return None;
+        }
+        let checksum = checksum_bytes(
+            self.big_endian,
+            checksum_bytes(self.big_endian, prior, &header[..8]),
+            page,
+        );
+        if checksum != (read_u32(header, 16), read_u32(header, 20)) {
+            return None;
+        }
+        Some(Frame {
+            page_number: read_u32(header, 0),
+            commit_size: read_u32(header, 4),
+            page,
+            checksum,
+        })
+    }
+
+    /// Walks frames from `offset` and returns the end of the last complete
+    /// transaction with the checksum there; `(offset, prior)` when no
+    /// transaction completes.
+    pub fn committed_end(&self, log: &[u8], offset: usize, prior: Checksum) -> (usize, Checksum) {
+        let mut committed = (offset, prior);
+        let (mut at, mut checksum) = (offset, prior);
+        while let Some(frame) = self.frame_at(log, at, checksum) {
+            at += self.frame_len();
+            checksum = frame.checksum;
+            if frame.is_commit() {
+                committed = (at, checksum);
+            }
+        }
+        committed
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Frame<'a> {
+    pub page_number: u32,
+    /// Database size in pages after this transaction; zero unless this is
+    /// the transaction's last frame.
+    pub commit_size: u32,
+    pub page: &'a [u8],
+    checksum: Checksum,
+}
+
+impl Frame<'_> {
+    pub fn is_commit(&self) -> bool {
+        self.commit_size != 0
+    }
+}
+
+/// Writes every committed transaction in `log` into `db`, in order, and
+/// truncates it to the size the last one recorded. Returns the number of
+/// frames applied.
+pub fn apply(db: &mut File, log: &[u8]) -> Result<usize> {
+    let header = WalHeader::parse(log)
+        .ok_or_else(|| ButterflyBotError::Runtime("Replica log has no valid header".to_string()))?;
+    let (end, _) = header.committed_end(log, HEADER_LEN, header.checksum);
+    let io_error = |e: std::io::Error| ButterflyBotError::Runtime(e.to_string());
+    let (mut at, mut checksum) = (HEADER_LEN, header.checksum);
+    let mut size = None;
+    let mut applied = 0;
+    while at < end {
+        let frame = header
+            .frame_at(log, at, checksum)
+            .ok_or_else(|| ButterflyBotError::Runtime(format!("Replica log breaks at {at}")))?;
+        let position = u64::from(frame.page_number.saturating_sub(1)) * u64::from(header.page_size);
+        db.seek(SeekFrom::Start(position)).map_err(io_error)?;
+        db.write_all(frame.page).map_err(io_error)?;
+        if frame.is_commit() {
+            size = Some(u64::from(frame.commit_size) * u64::from(header.page_size));
+        }
+        at += header.frame_len();
+        checksum = frame.checksum;
+        applied += 1;
+    }
+    if let Some(size) = size {
+        db.set_len(size).map_err(io_error)?;
+    }
+    Ok(applied)
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// SQLite's log checksum: pairs of 32-bit words, in the byte order the
+/// log's magic names, folded into two running sums.
+fn checksum_bytes(big_endian: bool, (mut s0, mut s1): Checksum, bytes: &[u8]) -> Checksum {
+    for pair in bytes.chunks_exact(8) {
+        let word = |at: usize| {
+            let raw = [pair[at], pair[at + 1], pair[at + 2], pair[at + 3]];
+            if big_endian {
+                u32::from_be_bytes(raw)
+            } else {
+                u32::from_le_bytes(raw)
+            }
+        };
+        s0 = s0.wrapping_add(word(0)).wrapping_add(s1);
+        s1 = s1.wrapping_add(word(4)).wrapping_add(s0);
+    }
+    (s0, s1)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A log with the given `(page_number, commit_size, fill)` frames.
+    pub(crate) fn build_log(
+        page_size: u32,
+        salt: (u32, u32),
+        frames: &[(u32, u32, u8)],
+    ) -> Vec<u8> {
+        let mut log = Vec::new();
+        for word in [MAGIC_BIG_ENDIAN, 3_007_000, page_size, 0, salt.0, salt.1] {
+            log.extend_from_slice(&word.to_be_bytes());
+        }
+        let mut checksum = checksum_bytes(true, (0, 0), &log);
+        log.extend_from_slice(&checksum.0.to_be_bytes());
+        log.extend_from_slice(&checksum.1.to_be_bytes());
+        for &(page_number, commit_size, fill) in frames {
+            let mut header = Vec::new();
+            for word in [page_number, commit_size, salt.0, salt.1] {
+                header.extend_from_slice(&word.to_be_bytes());
+            }
+            let page = vec![fill; page_size as usize];
+            checksum = checksum_bytes(true, checksum_bytes(true, checksum, &header[..8]), &page);
+            header.extend_from_slice(&checksum.0.to_be_bytes());
+            header.extend_from_slice(&checksum.1.to_be_bytes());
+            log.extend_from_slice(&header);
+            log.extend_from_slice(&page);
+        }
+        log
+    }
+
+    #[test]
+    fn only_committed_frames_of_this_log_count() {
+        let log = build_log(512, (7, 9), &[(1, 0, 1), (2, 2, 2), (3, 0, 3)]);
+        let header = WalHeader::parse(&log).unwrap();
+        assert_eq!(header.salt, (7, 9));
+        let frame_len = header.frame_len();
+
+        // The third frame has no commit after it.
+        let (end, checksum) = header.committed_end(&log, HEADER_LEN, header.checksum);
+        assert_eq!(end, HEADER_LEN + 2 * frame_len);
+        assert_eq!(header.committed_end(&log, end, checksum), (end, checksum));
+
+        // A torn or foreign frame stops the walk.
+        let mut torn = log.clone();
+        torn[HEADER_LEN + frame_len + FRAME_HEADER_LEN] ^= 0xff;
+        assert_eq!(
+            header.committed_end(&torn, HEADER_LEN, header.checksum).0,
+            HEADER_LEN
+        );
+        let restarted = build_log(512, (8, 1), &[(1, 1, 4)]);
+        assert!(header
+            .frame_at(&restarted, HEADER_LEN, header.checksum)
+            .is_none());
+
+        let mut corrupt = log.clone();
+        corrupt[10] ^= 1;
+        assert!(WalHeader::parse(&corrupt).is_none());
+        assert!(WalHeader::parse(&log[..HEADER_LEN - 1]).is_none());
+    }
+
+    #[test]
+    fn apply_replays_committed_pages_and_sets_the_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db");
+        std::fs::write(&path, vec![0u8; 512 * 4]).unwrap();
+        let log = build_log(512, (1, 1), &[(2, 0, 5), (1, 3, 6), (3, 0, 7)]);
+
+        let mut db = std::fs::OpenOptions::new().write(true).open(&path).unwrap();
+        assert_eq!(apply(&mut db, &log).unwrap(), 2);
+        drop(db);
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 512 * 3);
+        assert!(bytes[..512].iter().all(|&b| b == 6));
+        assert!(bytes[512..1024].iter().all(|&b| b == 5));
+        assert!(bytes[1024..].iter().all(|&b| b == 0));
+    }
+}
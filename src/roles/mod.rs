@@ -0,0 +1,258 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod policy;
+mod schema;
+pub use policy::{capability_access, tool_call_access, CapabilityAccess, Role, RolePolicy};
+use schema::user_roles;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const USER_ROLES_UP_SQL: &str = include_str!("../../migrations/20260304_create_user_roles/up.sql");
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserRole {
+    pub user_id: String,
+    pub role: Role,
+    pub granted_by: Option<String>,
+    pub updated_at: i64,
+}
+
+#[derive(Queryable)]
+struct UserRoleRow {
+    user_id: String,
+    role: String,
+    granted_by: Option<String>,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_roles)]
+struct NewUserRole<'a> {
+    user_id: &'a str,
+    role: &'a str,
+    granted_by: Option<&'a str>,
+    updated_at: i64,
+}
+
+pub struct RoleStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl RoleStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_user_roles_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for timestamps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn set_role(
+        &self,
+        user_id: &str,
+        role: Role,
+        granted_by: Option<&str>,
+    ) -> Result<UserRole> {
+        let user_id = user_id.trim();
+        if user_id.is_empty() {
+            return Err(ButterflyBotError::Runtime("Missing user_id".to_string()));
+        }
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+
+        let existing = user_roles::table
+            .filter(user_roles::user_id.eq(user_id))
+            .select(user_roles::user_id)
+            .first::<String>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        if existing.is_some() {
+            diesel::update(user_roles::table.filter(user_roles::user_id.eq(user_id)))
+                .set((
+                    user_roles::role.eq(role.as_str()),
+                    user_roles::granted_by.eq(granted_by),
+                    user_roles::updated_at.eq(now),
+                ))
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        } else {
+            let new_row = NewUserRole {
+                user_id,
+                role: role.as_str(),
+                granted_by,
+                updated_at: now,
+            };
+            diesel::insert_into(user_roles::table)
+                .values(&new_row)
+                .execute(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        }
+
+        Ok(UserRole {
+            user_id: user_id.to_string(),
+            role,
+            granted_by: granted_by.map(str::to_string),
+            updated_at: now,
+        })
+    }
+
+    /// The explicitly assigned role, if any. Callers fall back to
+    /// `RolePolicy::default_role` when this is `None`.
+    pub async fn role_for(&self, user_id: &str) -> Result<Option<Role>> {
+        let mut conn = self.conn().await?;
+        let role = user_roles::table
+            .filter(user_roles::user_id.eq(user_id))
+            .select(user_roles::role)
+            .first::<String>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(role.as_deref().and_then(Role::parse))
+    }
+
+    pub async fn list_roles(&self) -> Result<Vec<UserRole>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<UserRoleRow> = user_roles::table
+            .order(user_roles::user_id.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().filter_map(map_row).collect())
+    }
+
+    pub async fn remove_role(&self, user_id: &str) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let deleted = diesel::delete(user_roles::table.filter(user_roles::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(deleted > 0)
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// Roles live alongside the agent's memory database.
+pub fn resolve_roles_db_path(config: &serde_json::Value) -> Option<String> {
+    config
+        .get("memory")
+        .and_then(|v| v.get("sqlite_path"))
+        .and_then(|v| v.as_str())
+        .map(|v| v.trim().to_string())
+        .filter(|path| !path.is_empty())
+}
+
+fn map_row(row: UserRoleRow) -> Option<UserRole> {
+    Some(UserRole {
+        role: Role::parse(&row.role)?,
+        user_id: row.user_id,
+        granted_by: row.granted_by,
+        updated_at: row.updated_at,
+    })
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_user_roles_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM user_roles LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                conn.run_pending_migrations(MIGRATIONS)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, USER_ROLES_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn roles_can_be_assigned_updated_and_removed() {
+        let temp = tempdir().unwrap();
+        let db_path = temp.path().join("roles.db");
+        let clock = ManualClock::new(1_000);
+        let store = RoleStore::new(db_path.to_string_lossy())
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        assert!(store.role_for("alice").await.unwrap().is_none());
+        store
+            .set_role("alice", Role::Readonly, Some("admin"))
+            .await
+            .unwrap();
+        assert_eq!(store.role_for("alice").await.unwrap(), Some(Role::Readonly));
+
+        clock.advance(60);
+        store.set_role("alice", Role::Member, None).await.unwrap();
+        let roles = store.list_roles().await.unwrap();
+        assert_eq!(roles.len(), 1);
+        assert_eq!(roles[0].role, Role::Member);
+        assert!(roles[0].granted_by.is_none());
+        assert_eq!(roles[0].updated_at, 1_060);
+
+        assert!(store.remove_role("alice").await.unwrap());
+        assert!(store.role_for("alice").await.unwrap().is_none());
+    }
+}
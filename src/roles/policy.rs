@@ -0,0 +1,206 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Access level a user holds when the agent dispatches capabilities on their
+/// behalf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Member,
+    Readonly,
+}
+
+impl Role {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "admin" | "owner" => Some(Role::Admin),
+            "member" | "user" => Some(Role::Member),
+            "readonly" | "read_only" | "read-only" | "viewer" => Some(Role::Readonly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Member => "member",
+            Role::Readonly => "readonly",
+        }
+    }
+}
+
+/// Whether a capability only observes state or can change it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CapabilityAccess {
+    Read,
+    Write,
+}
+
+/// Classifies a capability by its final verb. Anything not recognised as a
+/// read is treated as a write so new capabilities fail closed for readonly
+/// users.
+pub fn capability_access(capability: &str) -> CapabilityAccess {
+    const READ_CAPABILITIES: &[&str] = &[
+        "clock.now_unix",
+        "log.emit",
+        "chart.render",
+        "coding.generate",
+        "git.status",
+        "git.diff",
+        "search.internet",
+        "search.fetch_and_summarize",
+        "solana.wallet",
+        "solana.balance",
+        "solana.simulate_transfer",
+        "solana.tx_status",
+        "solana.tx_history",
+    ];
+    const READ_VERBS: &[&str] = &[
+        "list",
+        "get",
+        "list_tools",
+        "list_zaps",
+        "list_checklists",
+        "checklist_history",
+    ];
+
+    if READ_CAPABILITIES.contains(&capability) {
+        return CapabilityAccess::Read;
+    }
+    let verb = capability.rsplit('.').next().unwrap_or(capability);
+    if READ_VERBS.contains(&verb) {
+        CapabilityAccess::Read
+    } else {
+        CapabilityAccess::Write
+    }
+}
+
+/// Classifies a model tool call as `<tool>.<action>`. Calls without an
+/// `action` argument, such as `http_call`, count as writes.
+pub fn tool_call_access(tool: &str, args: &Value) -> CapabilityAccess {
+    match args.get("action").and_then(Value::as_str).map(str::trim) {
+        Some(action) if !action.is_empty() => capability_access(&format!("{tool}.{action}")),
+        _ => CapabilityAccess::Write,
+    }
+}
+
+/// Maps roles to the capabilities they may trigger.
+///
+/// Configured under `tools.settings.roles`:
+/// - `default_role`: role for users without a row in `user_roles`. Defaults to
+///   `admin` so single-user installs keep working unchanged; a value that is
+///   not a role name means `readonly`, never `admin`.
+/// - `admin_only`: capabilities reserved for admins. Defaults to
+///   `["solana.transfer"]`.
+#[derive(Clone, Debug)]
+pub struct RolePolicy {
+    pub default_role: Role,
+    pub admin_only: HashSet<String>,
+}
+
+impl Default for RolePolicy {
+    fn default() -> Self {
+        Self {
+            default_role: Role::Admin,
+            admin_only: HashSet::from(["solana.transfer".to_string()]),
+        }
+    }
+}
+
+impl RolePolicy {
+    pub fn from_root_config(config: &serde_json::Value) -> Self {
+        let mut policy = Self::default();
+        let Some(settings) = config
+            .get("tools")
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("roles"))
+        else {
+            return policy;
+        };
+        if let Some(value) = settings.get("default_role") {
+            policy.default_role = value.as_str().and_then(Role::parse).unwrap_or_else(|| {
+                tracing::warn!(default_role = %value, "Unknown default role; using readonly");
+                Role::Readonly
+            });
+        }
+        if let Some(list) = settings
+            .get("admin_only")
+            .and_then(|value| value.as_array())
+        {
+            policy.admin_only = list
+                .iter()
+                .filter_map(|value| value.as_str())
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect();
+        }
+        policy
+    }
+
+    pub fn allows(&self, role: Role, capability: &str) -> bool {
+        match role {
+            Role::Admin => true,
+            Role::Member => !self.admin_only.contains(capability),
+            Role::Readonly => capability_access(capability) == CapabilityAccess::Read,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn readonly_is_limited_to_read_capabilities() {
+        let policy = RolePolicy::default();
+        assert!(policy.allows(Role::Readonly, "kv.sqlite.todo.list"));
+        assert!(policy.allows(Role::Readonly, "solana.balance"));
+        assert!(!policy.allows(Role::Readonly, "kv.sqlite.tasks.schedule"));
+        assert!(!policy.allows(Role::Readonly, "solana.transfer"));
+        assert!(!policy.allows(Role::Readonly, "http.request"));
+    }
+
+    #[test]
+    fn tool_calls_are_classified_by_action() {
+        use CapabilityAccess::{Read, Write};
+        let cases = [
+            ("todo", json!({"action": "list"}), Read),
+            ("solana", json!({"action": "balance"}), Read),
+            ("solana", json!({"action": "transfer"}), Write),
+            ("reminders", json!({"action": "create"}), Write),
+            ("http_call", json!({"url": "https://example.com"}), Write),
+            ("todo", json!({"action": " "}), Write),
+        ];
+        for (tool, args, access) in cases {
+            assert_eq!(tool_call_access(tool, &args), access, "{tool} {args}");
+        }
+    }
+
+    #[test]
+    fn members_are_blocked_from_admin_only_capabilities() {
+        let policy = RolePolicy::from_root_config(&json!({
+            "tools": {"settings": {"roles": {
+                "default_role": "member",
+                "admin_only": ["solana.transfer", "kv.sqlite.tasks.schedule"]
+            }}}
+        }));
+        assert_eq!(policy.default_role, Role::Member);
+        assert!(policy.allows(Role::Member, "kv.sqlite.todo.create"));
+        assert!(!policy.allows(Role::Member, "kv.sqlite.tasks.schedule"));
+        assert!(policy.allows(Role::Admin, "kv.sqlite.tasks.schedule"));
+    }
+
+    #[test]
+    fn unknown_default_role_fails_closed() {
+        for value in [json!("superuser"), json!("${env:ROLE}"), json!(1)] {
+            let policy = RolePolicy::from_root_config(&json!({
+                "tools": {"settings": {"roles": {"default_role": value}}}
+            }));
+            assert_eq!(policy.default_role, Role::Readonly, "{value}");
+        }
+    }
+}
@@ -0,0 +1,8 @@
+diesel::table! {
+    user_roles (user_id) {
+        user_id -> Text,
+        role -> Text,
+        granted_by -> Nullable<Text>,
+        updated_at -> BigInt,
+    }
+}
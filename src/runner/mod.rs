@@ -0,0 +1,372 @@
+//! Running build and test commands for the coding tool.
+//!
+//! `coding.run` lets the agent compile and test the code it writes. A
+//! command runs without a shell, from an allowlisted workspace (see
+//! [`Workspace`]), with a cleared environment, a timeout and a cap on the
+//! output kept. Only programs named in `tools.coding.run.programs` start.
+//! Unless `allow_network` is set the command gets no network: on Linux it
+//! runs in fresh user and network namespaces, and elsewhere it is refused
+//! because nothing can cut the network off.
+
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+
+use crate::error::ButterflyBotError;
+use crate::patch::Workspace;
+use crate::Result;
+
+/// Programs `coding.run` may start unless the config lists its own.
+pub const DEFAULT_PROGRAMS: &[&str] = &[
+    "cargo", "anchor", "make", "go", "python", "python3", "pytest", "npm", "node",
+];
+
+const DEFAULT_TIMEOUT_SECONDS: u64 = 120;
+const MAX_TIMEOUT_SECONDS: u64 = 1800;
+const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+const MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Variables passed through from the daemon's environment.
+const INHERITED_ENV: &[&str] = &[
+    "PATH",
+    "HOME",
+    "LANG",
+    "USER",
+    "TMPDIR",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunPolicy {
+    pub programs: Vec<String>,
+    pub timeout: Duration,
+    /// Per stream; the rest of the output is drained and dropped.
+    pub max_output_bytes: usize,
+    pub allow_network: bool,
+}
+
+impl Default for RunPolicy {
+    fn default() -> Self {
+        Self {
+            programs: DEFAULT_PROGRAMS.iter().map(|p| p.to_string()).collect(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
+            max_output_bytes: DEFAULT_MAX_OUTPUT_BYTES,
+            allow_network: false,
+        }
+    }
+}
+
+impl RunPolicy {
+    /// Reads the `run` section of the coding tool config.
+    pub fn from_tool_config(tool_cfg: Option<&Value>) -> Self {
+        let mut policy = Self::default();
+        let Some(section) = tool_cfg.and_then(|cfg| cfg.get("run")) else {
+            return policy;
+        };
+        if let Some(programs) = section.get("programs").and_then(Value::as_array) {
+            policy.programs = programs
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|program| !program.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(seconds) = section.get("timeout_seconds").and_then(Value::as_u64) {
+            policy.timeout = Duration::from_secs(seconds.clamp(1, MAX_TIMEOUT_SECONDS));
+        }
+        if let Some(bytes) = section.get("max_output_bytes").and_then(Value::as_u64) {
+            policy.max_output_bytes = (bytes as usize).clamp(1024, MAX_OUTPUT_BYTES);
+        }
+        policy.allow_network = section
+            .get("allow_network")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        policy
+    }
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct RunOutput {
+    pub command: Vec<String>,
+    pub cwd: String,
+    /// `None` when the command was killed.
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+    /// Either stream went past `max_output_bytes`.
+    pub truncated: bool,
+    pub network: bool,
+}
+
+/// Runs `program` with `args` from `cwd`, a directory relative to the
+/// workspace root. `timeout` may only shorten the policy's timeout.
+pub async fn run(
+    workspace: &Workspace,
+    cwd: Option<&str>,
+    program: &str,
+    args: &[String],
+    policy: &RunPolicy,
+    timeout: Option<Duration>,
+) -> Result<RunOutput> {
+    if program.contains(['/', '\\']) || !policy.programs.iter().any(|p| p == program) {
+        return Err(ButterflyBotError::Runtime(format!(
+            "Command {program} is not allowlisted for coding.run"
+        )));
+    }
+    let dir = match cwd
+        .map(str::trim)
+        .filter(|cwd| !cwd.is_empty() && *cwd != ".")
+    {
+        Some(cwd) => workspace.resolve(cwd)?,
+        None => workspace.root().to_path_buf(),
+    };
+    if !dir.is_dir() {
+        return Err(ButterflyBotError::Runtime(format!(
+            "Working directory {} does not exist",
+            dir.display()
+        )));
+    }
+
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .current_dir(&dir)
+        .env_clear()
+        .envs(
+            INHERITED_ENV
+                .iter()
+                .filter_map(|key| std::env::var_os(key).map(|value| (key.to_string(), value))),
+        )
+        .env("CARGO_TERM_COLOR", "never")
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if !policy.allow_network {
+        cmd.env("CARGO_NET_OFFLINE", "true")
+            .env("PIP_NO_INDEX", "1")
+            .env("npm_config_offline", "true");
+        isolate_network(&mut cmd)?;
+    }
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let started = Instant::now();
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| ButterflyBotError::Runtime(format!("Failed to start {program}: {e}")))?;
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+    let mut truncated = false;
+    let limit = timeout.map_or(policy.timeout, |timeout| timeout.min(policy.timeout));
+    let cap = policy.max_output_bytes;
+    let (stdout_pipe, stderr_pipe) = (child.stdout.take(), child.stderr.take());
+    let finished = tokio::time::timeout(limit, async {
+        let (out_truncated, err_truncated) = tokio::join!(
+            read_capped(stdout_pipe, cap, &mut stdout),
+            read_capped(stderr_pipe, cap, &mut stderr)
+        );
+        truncated = out_truncated || err_truncated;
+        child.wait().await
+    })
+    .await;
+
+    let (exit_code, timed_out) = match finished {
+        Ok(status) => {
+            let status = status.map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            (status.code(), false)
+        }
+        Err(_) => {
+            kill_group(&mut child).await;
+            (None, true)
+        }
+    };
+
+    let mut command = vec![program.to_string()];
+    command.extend(args.iter().cloned());
+    Ok(RunOutput {
+        command,
+        cwd: dir.to_string_lossy().to_string(),
+        exit_code,
+        success: exit_code == Some(0),
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+        stdout: String::from_utf8_lossy(&stdout).to_string(),
+        stderr: String::from_utf8_lossy(&stderr).to_string(),
+        truncated,
+        network: policy.allow_network,
+    })
+}
+
+/// Keeps the first `cap` bytes in `buf` and drains the rest so the child
+/// never blocks on a full pipe. Returns whether anything was dropped.
+async fn read_capped<R: AsyncRead + Unpin>(
+    reader: Option<R>,
+    cap: usize,
+    buf: &mut Vec<u8>,
+) -> bool {
+    let Some(mut reader) = reader else {
+        return false;
+    };
+    let mut truncated = false;
+    let mut chunk = [0u8; 8192];
+    while let Ok(read) = reader.read(&mut chunk).await {
+        if read == 0 {
+            break;
+        }
+        let room = cap.saturating_sub(buf.len());
+        buf.extend_from_slice(&chunk[..read.min(room)]);
+        truncated |= read > room;
+    }
+    truncated
+}
+
+#[cfg(target_os = "linux")]
+fn isolate_network(cmd: &mut Command) -> Result<()> {
+    // SAFETY: unshare only changes the namespaces of the forked child and is
+    // async-signal-safe.
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn isolate_network(_cmd: &mut Command) -> Result<()> {
+    Err(ButterflyBotError::Runtime(
+        "coding.run cannot cut off the network on this platform; set tools.coding.run.allow_network to run commands"
+            .to_string(),
+    ))
+}
+
+/// Kills the command and anything it started.
+async fn kill_group(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id().and_then(|pid| libc::pid_t::try_from(pid).ok()) {
+        // SAFETY: signals the process group created by `process_group(0)`.
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill().await;
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    use super::{run, RunPolicy};
+    use crate::patch::Workspace;
+
+    fn policy() -> RunPolicy {
+        RunPolicy {
+            programs: vec!["sh".to_string()],
+            allow_network: true,
+            ..RunPolicy::default()
+        }
+    }
+
+    fn args(script: &str) -> Vec<String> {
+        vec!["-c".to_string(), script.to_string()]
+    }
+
+    #[test]
+    fn policy_reads_run_section_and_clamps() {
+        let policy = RunPolicy::from_tool_config(Some(&json!({
+            "run": {"programs": ["cargo"], "timeout_seconds": 99999, "max_output_bytes": 1}
+        })));
+        assert_eq!(policy.programs, vec!["cargo".to_string()]);
+        assert_eq!(policy.timeout, Duration::from_secs(1800));
+        assert_eq!(policy.max_output_bytes, 1024);
+        assert!(!policy.allow_network);
+    }
+
+    #[tokio::test]
+    async fn runs_allowlisted_commands_in_the_workspace() {
+        let dir = tempdir().unwrap();
+        let workspace = Workspace::open(None, &[dir.path().to_string_lossy().to_string()]).unwrap();
+
+        let output = run(
+            &workspace,
+            None,
+            "sh",
+            &args("pwd; exit 3"),
+            &policy(),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(output.exit_code, Some(3));
+        assert!(!output.success);
+        assert!(output
+            .stdout
+            .trim()
+            .ends_with(dir.path().file_name().unwrap().to_string_lossy().as_ref()));
+
+        assert!(run(&workspace, None, "rm", &[], &policy(), None)
+            .await
+            .is_err());
+        assert!(run(
+            &workspace,
+            Some("../"),
+            "sh",
+            &args("true"),
+            &policy(),
+            None
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn caps_output_and_times_out() {
+        let dir = tempdir().unwrap();
+        let workspace = Workspace::open(None, &[dir.path().to_string_lossy().to_string()]).unwrap();
+        let mut policy = policy();
+        policy.max_output_bytes = 1024;
+
+        let output = run(
+            &workspace,
+            None,
+            "sh",
+            &args("yes | head -c 100000"),
+            &policy,
+            None,
+        )
+        .await
+        .unwrap();
+        assert!(output.truncated);
+        assert_eq!(output.stdout.len(), 1024);
+
+        let output = run(
+            &workspace,
+            None,
+            "sh",
+            &args("sleep 5"),
+            &policy,
+            Some(Duration::from_millis(200)),
+        )
+        .await
+        .unwrap();
+        assert!(output.timed_out);
+        assert_eq!(output.exit_code, None);
+    }
+}
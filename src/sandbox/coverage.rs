@@ -0,0 +1,385 @@
+//! Cross-checks the three places a capability has to be declared: the
+//! action mapping compiled into each wasm module, the sandbox allowlist, and
+//! the host handler in `ToolRegistry::run_capability_call`. A capability
+//! missing from any of them fails at call time with a confusing error, so
+//! the daemon checks at startup and `/doctor` fails on any mismatch.
+
+use std::collections::BTreeSet;
+
+use serde::Serialize;
+
+use super::SandboxSettings;
+use crate::plugins::registry::HOST_CAPABILITIES;
+
+/// Capabilities each `wasm-tool` module can emit, mirroring the
+/// `execute_<tool>` mappings in `wasm-tool/src/lib.rs`.
+pub const WASM_TOOL_CAPABILITIES: &[(&str, &[&str])] = &[
+    (
+        "todo",
+        &[
+            "kv.sqlite.todo.create",
+            "kv.sqlite.todo.create_many",
+            "kv.sqlite.todo.list",
+            "kv.sqlite.todo.complete",
+            "kv.sqlite.todo.reopen",
+            "kv.sqlite.todo.delete",
+            "kv.sqlite.todo.clear",
+            "kv.sqlite.todo.trash",
+            "kv.sqlite.todo.restore",
+            "kv.sqlite.todo.reorder",
+            "kv.sqlite.todo.create_checklist",
+            "kv.sqlite.todo.list_checklists",
+            "kv.sqlite.todo.reset_checklist",
+            "kv.sqlite.todo.checklist_history",
+            "kv.sqlite.todo.delete_checklist",
+            "kv.sqlite.todo.remind",
+            "kv.sqlite.todo.label",
+            "kv.sqlite.todo.project",
+            "kv.sqlite.todo.create_project",
+            "kv.sqlite.todo.list_projects",
+            "kv.sqlite.todo.update_project",
+            "kv.sqlite.todo.delete_project",
+            "chart.render",
+        ],
+    ),
+    (
+        "tasks",
+        &[
+            "kv.sqlite.tasks.schedule",
+            "kv.sqlite.tasks.list",
+            "kv.sqlite.tasks.enable",
+            "kv.sqlite.tasks.disable",
+            "kv.sqlite.tasks.delete",
+            "kv.sqlite.tasks.clear",
+            "kv.sqlite.tasks.trash",
+            "kv.sqlite.tasks.restore",
+            "kv.sqlite.tasks.project",
+        ],
+    ),
+    (
+        "reminders",
+        &[
+            "kv.sqlite.reminders.create",
+            "kv.sqlite.reminders.list",
+            "kv.sqlite.reminders.complete",
+            "kv.sqlite.reminders.delete",
+            "kv.sqlite.reminders.snooze",
+            "kv.sqlite.reminders.clear",
+            "kv.sqlite.reminders.trash",
+            "kv.sqlite.reminders.restore",
+            "kv.sqlite.reminders.set_delivery_window",
+            "kv.sqlite.reminders.set_priority",
+            "kv.sqlite.reminders.label",
+            "kv.sqlite.reminders.project",
+        ],
+    ),
+    (
+        "planning",
+        &[
+            "kv.sqlite.planning.create",
+            "kv.sqlite.planning.list",
+            "kv.sqlite.planning.get",
+            "kv.sqlite.planning.update",
+            "kv.sqlite.planning.delete",
+            "kv.sqlite.planning.clear",
+            "kv.sqlite.planning.trash",
+            "kv.sqlite.planning.restore",
+            "chart.render",
+            "kv.sqlite.planning.negotiate",
+            "kv.sqlite.planning.approve",
+            "kv.sqlite.planning.reject",
+            "kv.sqlite.planning.label",
+            "kv.sqlite.planning.project",
+            "kv.sqlite.planning.ask",
+            "kv.sqlite.planning.step_status",
+            "kv.sqlite.planning.note",
+            "kv.sqlite.planning.template_create",
+            "kv.sqlite.planning.template_list",
+            "kv.sqlite.planning.template_get",
+            "kv.sqlite.planning.template_update",
+            "kv.sqlite.planning.template_delete",
+            "kv.sqlite.planning.instantiate_template",
+        ],
+    ),
+    (
+        "wakeup",
+        &[
+            "kv.sqlite.wakeup.create",
+            "kv.sqlite.wakeup.list",
+            "kv.sqlite.wakeup.enable",
+            "kv.sqlite.wakeup.disable",
+            "kv.sqlite.wakeup.delete",
+        ],
+    ),
+    (
+        "coding",
+        &[
+            "coding.generate",
+            "coding.apply_patch",
+            "coding.run",
+            "git.status",
+            "git.diff",
+            "git.commit",
+            "git.branch",
+            "git.push",
+        ],
+    ),
+    ("http_call", &["http.request"]),
+    ("mcp", &["mcp.list_tools", "mcp.call"]),
+    ("github", &["github.list_tools", "github.call_tool"]),
+    (
+        "zapier",
+        &[
+            "zapier.list_tools",
+            "zapier.call_tool",
+            "zapier.list_zaps",
+            "zapier.trigger_zap",
+        ],
+    ),
+    (
+        "search_internet",
+        &["search.internet", "search.fetch_and_summarize"],
+    ),
+    (
+        "solana",
+        &[
+            "solana.wallet",
+            "solana.balance",
+            "solana.transfer",
+            "solana.simulate_transfer",
+            "solana.tx_status",
+            "solana.tx_history",
+            "chart.render",
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverageIssue {
+    /// The module emits it but the tool's allowlist rejects it.
+    NotAllowlisted,
+    /// Allowlisted (or emitted) but the host has no handler.
+    MissingHostHandler,
+    /// Allowlisted although the module never asks for it.
+    UnusedAllowlist,
+}
+
+impl CoverageIssue {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Self::NotAllowlisted => "emitted by the module but not allowlisted",
+            Self::MissingHostHandler => "no host handler",
+            Self::UnusedAllowlist => "allowlisted but never emitted by the module",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CapabilityCoverage {
+    pub capability: String,
+    /// `None` for host handlers no module currently reaches, such as
+    /// `clock.now_unix`.
+    pub tool: Option<String>,
+    pub emitted: bool,
+    pub allowlisted: bool,
+    pub host_handler: bool,
+    pub issues: Vec<CoverageIssue>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CoverageReport {
+    pub capabilities: Vec<CapabilityCoverage>,
+}
+
+impl CoverageReport {
+    /// Compares the module mappings, `settings` allowlists and host handlers.
+    pub fn build(settings: &SandboxSettings) -> Self {
+        let mut capabilities = Vec::new();
+        let mut reached = BTreeSet::new();
+        for (tool, emitted) in WASM_TOOL_CAPABILITIES {
+            let allow = settings.execution_plan(tool).tool_config.capabilities.allow;
+            let names = emitted
+                .iter()
+                .map(|name| name.to_string())
+                .chain(allow.iter().cloned())
+                .collect::<BTreeSet<_>>();
+            for capability in names {
+                let emitted = emitted.contains(&capability.as_str());
+                // Scoped secrets are served by the `secrets.get` handler.
+                let host_handler = HOST_CAPABILITIES.contains(&capability.as_str())
+                    || capability.starts_with("secrets.get.");
+                let allowlisted = allow.contains(&capability);
+                let mut issues = Vec::new();
+                if emitted && !allowlisted {
+                    issues.push(CoverageIssue::NotAllowlisted);
+                }
+                if !host_handler {
+                    issues.push(CoverageIssue::MissingHostHandler);
+                }
+                if allowlisted && !emitted && !is_host_service(&capability) {
+                    issues.push(CoverageIssue::UnusedAllowlist);
+                }
+                reached.insert(capability.clone());
+                capabilities.push(CapabilityCoverage {
+                    capability,
+                    tool: Some(tool.to_string()),
+                    emitted,
+                    allowlisted,
+                    host_handler,
+                    issues,
+                });
+            }
+        }
+        for capability in HOST_CAPABILITIES {
+            if !reached.contains(*capability) {
+                capabilities.push(CapabilityCoverage {
+                    capability: capability.to_string(),
+                    tool: None,
+                    emitted: false,
+                    allowlisted: false,
+                    host_handler: true,
+                    issues: Vec::new(),
+                });
+            }
+        }
+        Self { capabilities }
+    }
+
+    pub fn mismatches(&self) -> impl Iterator<Item = &CapabilityCoverage> {
+        self.capabilities
+            .iter()
+            .filter(|entry| !entry.issues.is_empty())
+    }
+
+    /// One line per mismatch, e.g. `todo: kv.sqlite.todo.remind (no host handler)`.
+    pub fn describe_mismatches(&self) -> Vec<String> {
+        self.mismatches()
+            .map(|entry| {
+                let issues = entry
+                    .issues
+                    .iter()
+                    .map(CoverageIssue::describe)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "{}: {} ({issues})",
+                    entry.tool.as_deref().unwrap_or("host"),
+                    entry.capability
+                )
+            })
+            .collect()
+    }
+}
+
+/// Host services a module may be granted without its mapping naming them
+/// (they're requested directly, not through an action).
+fn is_host_service(capability: &str) -> bool {
+    matches!(capability, "clock.now_unix" | "log.emit" | "secrets.get")
+        || capability.starts_with("secrets.get.")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use regex::Regex;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn defaults_are_consistent() {
+        let report = CoverageReport::build(&SandboxSettings::default());
+        assert_eq!(report.describe_mismatches(), Vec::<String>::new());
+        let remind = report
+            .capabilities
+            .iter()
+            .find(|entry| entry.capability == "kv.sqlite.todo.remind")
+            .unwrap();
+        assert!(remind.emitted && remind.allowlisted && remind.host_handler);
+        assert!(report
+            .capabilities
+            .iter()
+            .any(|entry| entry.capability == "clock.now_unix" && entry.tool.is_none()));
+    }
+
+    #[test]
+    fn narrowed_or_unknown_allowlists_are_reported() {
+        let settings = SandboxSettings::from_root_config(&json!({
+            "tools": {"settings": {"sandbox": {"tools": {
+                "todo": {"capabilities": {"allow": [
+                    "kv.sqlite.todo.list", "kv.sqlite.todo.archive", "clock.now_unix"
+                ]}}
+            }}}}
+        }));
+        let mismatches = CoverageReport::build(&settings).describe_mismatches();
+        assert!(mismatches.contains(
+            &"todo: kv.sqlite.todo.create (emitted by the module but not allowlisted)".to_string()
+        ));
+        assert!(mismatches.contains(
+            &"todo: kv.sqlite.todo.archive (no host handler, allowlisted but never emitted by the module)"
+                .to_string()
+        ));
+        assert!(!mismatches
+            .iter()
+            .any(|line| line.contains("clock.now_unix")));
+    }
+
+    /// Keeps `WASM_TOOL_CAPABILITIES` honest against the module source.
+    #[test]
+    fn wasm_mapping_matches_module_source() {
+        let source = include_str!("../../wasm-tool/src/lib.rs");
+        let name =
+            Regex::new(r#"(?m)(?:=>\s*|capability_call\(|^\s+)"([a-z_]+(?:\.[a-z_]+)+)""#).unwrap();
+        let mut from_source = BTreeMap::new();
+        for chunk in source.split("\nfn execute_").skip(1) {
+            let tool = chunk.split('(').next().unwrap().to_string();
+            let body = chunk.split("\n}\n").next().unwrap();
+            let capabilities = name
+                .captures_iter(body)
+                .map(|caps| caps[1].to_string())
+                .collect::<BTreeSet<_>>();
+            if !capabilities.is_empty() {
+                from_source.insert(tool, capabilities);
+            }
+        }
+        let declared = WASM_TOOL_CAPABILITIES
+            .iter()
+            .map(|(tool, caps)| {
+                (
+                    tool.to_string(),
+                    caps.iter().map(|c| c.to_string()).collect::<BTreeSet<_>>(),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(declared, from_source);
+    }
+
+    /// Keeps `HOST_CAPABILITIES` honest against the registry's match arms.
+    #[test]
+    fn host_list_matches_registry_arms() {
+        let source = include_str!("../plugins/registry.rs");
+        let body = source
+            .split("let response = match capability {")
+            .nth(1)
+            .and_then(|rest| rest.split("\n        };\n").next())
+            .unwrap();
+        let arm = Regex::new(r#"(?m)^            ((?:"[a-z_.]+"(?: \| )?)+) =>"#).unwrap();
+        let quoted = Regex::new(r#""([a-z_.]+)""#).unwrap();
+        let from_source = arm
+            .captures_iter(body)
+            .flat_map(|caps| {
+                quoted
+                    .captures_iter(&caps[1])
+                    .map(|name| name[1].to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<BTreeSet<_>>();
+        let declared = HOST_CAPABILITIES
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<BTreeSet<_>>();
+        assert_eq!(declared, from_source);
+    }
+}
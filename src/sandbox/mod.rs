@@ -8,11 +8,15 @@ use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use wasmtime::{Engine, Linker, Memory, Module, Store};
+use wasmtime::{Engine, Linker, Memory, Store, StoreLimits, StoreLimitsBuilder};
 
 use crate::error::ButterflyBotError;
 use crate::Result;
 
+pub mod coverage;
+pub mod module_cache;
+pub mod output;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ToolRuntime {
@@ -40,6 +44,25 @@ pub struct NetworkPolicy {
     pub default_deny: Option<bool>,
 }
 
+impl NetworkPolicy {
+    /// Whether `host` may be reached. Entries are exact hosts, `*.suffix`
+    /// or `*`; with no entries everything is allowed unless `default_deny`.
+    pub fn allows_host(&self, host: &str) -> bool {
+        if self.allow.is_empty() {
+            return !self.default_deny.unwrap_or(false);
+        }
+        let host = host.to_ascii_lowercase();
+        self.allow.iter().any(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            entry == "*"
+                || entry == host
+                || entry
+                    .strip_prefix("*.")
+                    .is_some_and(|suffix| host.ends_with(&format!(".{suffix}")))
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CapabilityPolicy {
     pub abi_version: Option<u32>,
@@ -53,6 +76,11 @@ pub struct WasmToolConfig {
     pub entrypoint: Option<String>,
     pub timeout_ms: Option<u64>,
     pub fuel: Option<u64>,
+    /// Cap on the guest's linear memory; growth past it traps.
+    pub max_memory_bytes: Option<u64>,
+    /// Cap on the serialized result; larger results are truncated (see
+    /// [`output`]).
+    pub max_output_bytes: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -120,7 +148,22 @@ impl SandboxSettings {
                 "kv.sqlite.todo.reopen",
                 "kv.sqlite.todo.delete",
                 "kv.sqlite.todo.clear",
+                "kv.sqlite.todo.trash",
+                "kv.sqlite.todo.restore",
                 "kv.sqlite.todo.reorder",
+                "kv.sqlite.todo.create_checklist",
+                "kv.sqlite.todo.list_checklists",
+                "kv.sqlite.todo.reset_checklist",
+                "kv.sqlite.todo.checklist_history",
+                "kv.sqlite.todo.delete_checklist",
+                "kv.sqlite.todo.remind",
+                "kv.sqlite.todo.label",
+                "kv.sqlite.todo.project",
+                "kv.sqlite.todo.create_project",
+                "kv.sqlite.todo.list_projects",
+                "kv.sqlite.todo.update_project",
+                "kv.sqlite.todo.delete_project",
+                "chart.render",
             ],
             "tasks" => vec![
                 "kv.sqlite.tasks.schedule",
@@ -129,6 +172,9 @@ impl SandboxSettings {
                 "kv.sqlite.tasks.disable",
                 "kv.sqlite.tasks.delete",
                 "kv.sqlite.tasks.clear",
+                "kv.sqlite.tasks.trash",
+                "kv.sqlite.tasks.restore",
+                "kv.sqlite.tasks.project",
             ],
             "reminders" => vec![
                 "kv.sqlite.reminders.create",
@@ -137,6 +183,12 @@ impl SandboxSettings {
                 "kv.sqlite.reminders.delete",
                 "kv.sqlite.reminders.snooze",
                 "kv.sqlite.reminders.clear",
+                "kv.sqlite.reminders.trash",
+                "kv.sqlite.reminders.restore",
+                "kv.sqlite.reminders.set_delivery_window",
+                "kv.sqlite.reminders.set_priority",
+                "kv.sqlite.reminders.label",
+                "kv.sqlite.reminders.project",
             ],
             "planning" => vec![
                 "kv.sqlite.planning.create",
@@ -145,6 +197,23 @@ impl SandboxSettings {
                 "kv.sqlite.planning.update",
                 "kv.sqlite.planning.delete",
                 "kv.sqlite.planning.clear",
+                "kv.sqlite.planning.trash",
+                "kv.sqlite.planning.restore",
+                "kv.sqlite.planning.negotiate",
+                "kv.sqlite.planning.approve",
+                "kv.sqlite.planning.reject",
+                "kv.sqlite.planning.label",
+                "kv.sqlite.planning.project",
+                "kv.sqlite.planning.ask",
+                "kv.sqlite.planning.step_status",
+                "kv.sqlite.planning.note",
+                "kv.sqlite.planning.template_create",
+                "kv.sqlite.planning.template_list",
+                "kv.sqlite.planning.template_get",
+                "kv.sqlite.planning.template_update",
+                "kv.sqlite.planning.template_delete",
+                "kv.sqlite.planning.instantiate_template",
+                "chart.render",
             ],
             "wakeup" => vec![
                 "kv.sqlite.wakeup.create",
@@ -153,12 +222,26 @@ impl SandboxSettings {
                 "kv.sqlite.wakeup.disable",
                 "kv.sqlite.wakeup.delete",
             ],
-            "coding" => vec!["coding.generate"],
+            "coding" => vec![
+                "coding.generate",
+                "coding.apply_patch",
+                "coding.run",
+                "git.status",
+                "git.diff",
+                "git.commit",
+                "git.branch",
+                "git.push",
+            ],
             "mcp" => vec!["mcp.list_tools", "mcp.call"],
             "http_call" => vec!["http.request"],
             "github" => vec!["github.list_tools", "github.call_tool"],
-            "zapier" => vec!["zapier.list_tools", "zapier.call_tool"],
-            "search_internet" => vec!["search.internet"],
+            "zapier" => vec![
+                "zapier.list_tools",
+                "zapier.call_tool",
+                "zapier.list_zaps",
+                "zapier.trigger_zap",
+            ],
+            "search_internet" => vec!["search.internet", "search.fetch_and_summarize"],
             "solana" => vec![
                 "solana.wallet",
                 "solana.balance",
@@ -166,6 +249,7 @@ impl SandboxSettings {
                 "solana.simulate_transfer",
                 "solana.tx_status",
                 "solana.tx_history",
+                "chart.render",
             ],
             _ => Vec::new(),
         }
@@ -179,6 +263,7 @@ impl SandboxSettings {
 mod tests {
     use serde_json::json;
     use std::fs;
+    use wasmtime::{Engine, Module, Store};
 
     use super::{SandboxSettings, ToolRuntime, ToolSandboxConfig, WasmRuntime};
 
@@ -206,6 +291,10 @@ mod tests {
             .execution_plan("reminders")
             .tool_config
             .is_capability_allowed("kv.sqlite.reminders.list"));
+        assert!(settings
+            .execution_plan("planning")
+            .tool_config
+            .is_capability_allowed("chart.render"));
     }
 
     #[test]
@@ -376,6 +465,61 @@ mod tests {
         assert_eq!(WasmRuntime::resolve_fuel_limit(&cfg), Some(1));
     }
 
+    #[test]
+    fn wasm_output_limit_defaults_and_floors() {
+        let mut cfg = ToolSandboxConfig::default();
+        assert_eq!(
+            WasmRuntime::resolve_max_output_bytes(&cfg),
+            super::output::DEFAULT_MAX_OUTPUT_BYTES
+        );
+        cfg.wasm.max_output_bytes = Some(0);
+        assert_eq!(
+            WasmRuntime::resolve_max_output_bytes(&cfg),
+            super::output::DEFAULT_MAX_OUTPUT_BYTES
+        );
+        cfg.wasm.max_output_bytes = Some(16);
+        assert_eq!(
+            WasmRuntime::resolve_max_output_bytes(&cfg),
+            super::output::MIN_MAX_OUTPUT_BYTES
+        );
+        cfg.wasm.max_output_bytes = Some(1_048_576);
+        assert_eq!(WasmRuntime::resolve_max_output_bytes(&cfg), 1_048_576);
+    }
+
+    #[test]
+    fn wasm_memory_limit_traps_on_growth_past_cap() {
+        let mut cfg = ToolSandboxConfig::default();
+        cfg.wasm.max_memory_bytes = Some(0);
+        assert_eq!(WasmRuntime::resolve_memory_limit(&cfg), None);
+
+        cfg.wasm.max_memory_bytes = Some(2 * 65_536);
+        let limit = WasmRuntime::resolve_memory_limit(&cfg);
+        let engine = Engine::default();
+        let module = Module::new(
+            &engine,
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "grow") (param i32) (result i32)
+                    local.get 0
+                    memory.grow))"#,
+        )
+        .unwrap();
+        let mut store = Store::new(&engine, WasmRuntime::store_limits(limit));
+        store.limiter(|limits| limits);
+        let instance = Linker::new(&engine)
+            .instantiate(&mut store, &module)
+            .unwrap();
+        let grow = instance
+            .get_typed_func::<i32, i32>(&mut store, "grow")
+            .unwrap();
+
+        assert_eq!(grow.call(&mut store, 1).unwrap(), 1);
+        let err = grow
+            .call(&mut store, 1)
+            .expect_err("third page is over the cap");
+        assert!(WasmRuntime::is_memory_limit_error(&format!("{err:#}")));
+    }
+
     #[test]
     fn wasm_module_validation_rejects_stub_marker() {
         let path = "./wasm/__test_stub_tool.wasm";
@@ -621,7 +765,7 @@ impl WasmRuntime {
         fallback.to_string_lossy().to_string()
     }
 
-    fn resolve_module_path(tool_name: &str, config: &ToolSandboxConfig) -> String {
+    pub(crate) fn resolve_module_path(tool_name: &str, config: &ToolSandboxConfig) -> String {
         let default_path = Self::default_module_path(tool_name);
         config
             .wasm
@@ -666,6 +810,39 @@ impl WasmRuntime {
         config.wasm.fuel.filter(|limit| *limit > 0)
     }
 
+    fn resolve_memory_limit(config: &ToolSandboxConfig) -> Option<usize> {
+        config
+            .wasm
+            .max_memory_bytes
+            .filter(|limit| *limit > 0)
+            .map(|limit| usize::try_from(limit).unwrap_or(usize::MAX))
+    }
+
+    fn resolve_max_output_bytes(config: &ToolSandboxConfig) -> usize {
+        config
+            .wasm
+            .max_output_bytes
+            .filter(|limit| *limit > 0)
+            .map(|limit| usize::try_from(limit).unwrap_or(usize::MAX))
+            .unwrap_or(output::DEFAULT_MAX_OUTPUT_BYTES)
+            .max(output::MIN_MAX_OUTPUT_BYTES)
+    }
+
+    fn store_limits(memory_limit: Option<usize>) -> StoreLimits {
+        match memory_limit {
+            Some(limit) => StoreLimitsBuilder::new()
+                .memory_size(limit)
+                .trap_on_grow_failure(true)
+                .build(),
+            None => StoreLimits::default(),
+        }
+    }
+
+    fn is_memory_limit_error(message: &str) -> bool {
+        let message = message.to_ascii_lowercase();
+        message.contains("growing memory") || message.contains("memory minimum size")
+    }
+
     pub fn validate_module_binary(tool_name: &str, config: &ToolSandboxConfig) -> Result<()> {
         let module_path = Self::resolve_module_path(tool_name, config);
         let path = Path::new(&module_path);
@@ -678,25 +855,25 @@ impl WasmRuntime {
 
         let mut file = File::open(path)
             .map_err(|e| ButterflyBotError::Runtime(format!("Failed to open wasm module: {e}")))?;
-        let mut header = [0u8; 4];
-        file.read_exact(&mut header).map_err(|e| {
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes).map_err(|e| {
             ButterflyBotError::Runtime(format!(
-                "Failed to read wasm module header for tool '{tool_name}' ({module_path}): {e}"
+                "Failed to read wasm module for tool '{tool_name}' ({module_path}): {e}"
             ))
         })?;
+        Self::validate_module_bytes(tool_name, &module_path, &bytes)
+    }
 
-        if header != Self::WASM_MAGIC {
+    /// Magic-header and placeholder-stub checks on a module's contents; also
+    /// run by `tools build` before anything is installed.
+    pub fn validate_module_bytes(tool_name: &str, module_path: &str, bytes: &[u8]) -> Result<()> {
+        if !bytes.starts_with(&Self::WASM_MAGIC) {
             return Err(ButterflyBotError::Runtime(format!(
                 "Invalid wasm module for tool '{tool_name}' at {module_path}: missing wasm magic header"
             )));
         }
 
-        let mut tail = Vec::new();
-        file.read_to_end(&mut tail).map_err(|e| {
-            ButterflyBotError::Runtime(format!(
-                "Failed to inspect wasm module body for tool '{tool_name}' ({module_path}): {e}"
-            ))
-        })?;
+        let tail = &bytes[4..];
         if tail
             .windows("stub responses".len())
             .any(|w| w == b"stub responses")
@@ -738,7 +915,7 @@ impl WasmRuntime {
         Ok((ptr, len))
     }
 
-    fn ensure_range(memory: &Memory, store: &Store<()>, ptr: i32, len: i32) -> Result<()> {
+    fn ensure_range(memory: &Memory, store: &Store<StoreLimits>, ptr: i32, len: i32) -> Result<()> {
         if ptr < 0 || len < 0 {
             return Err(ButterflyBotError::Runtime(
                 "Negative pointer/length from wasm".to_string(),
@@ -782,6 +959,7 @@ impl WasmRuntime {
         let entrypoint = Self::resolve_entrypoint(config);
         let timeout_ms = config.wasm.timeout_ms.unwrap_or(0);
         let fuel_limit = Self::resolve_fuel_limit(config);
+        let memory_limit = Self::resolve_memory_limit(config);
 
         let mut wasm_config = wasmtime::Config::new();
         if timeout_ms > 0 {
@@ -794,10 +972,19 @@ impl WasmRuntime {
         let engine = Engine::new(&wasm_config).map_err(|e| {
             ButterflyBotError::Runtime(format!("Failed to initialize wasm engine: {e}"))
         })?;
-        let module = Module::from_file(&engine, &module_path)
-            .map_err(|e| ButterflyBotError::Runtime(format!("Failed to load wasm module: {e}")))?;
+        let compile_started = std::time::Instant::now();
+        let (module, cache_outcome) =
+            module_cache::ModuleCache::new(module_cache::ModuleCache::default_dir())
+                .load(&engine, Path::new(&module_path))?;
+        tracing::debug!(
+            tool = %tool_name,
+            cache = cache_outcome.as_str(),
+            elapsed_ms = compile_started.elapsed().as_millis() as u64,
+            "Loaded wasm module"
+        );
         let linker = Linker::new(&engine);
-        let mut store = Store::new(&engine, ());
+        let mut store = Store::new(&engine, Self::store_limits(memory_limit));
+        store.limiter(|limits| limits);
 
         if let Some(limit) = fuel_limit {
             store.set_fuel(limit).map_err(|e| {
@@ -822,7 +1009,15 @@ impl WasmRuntime {
         };
 
         let instance = linker.instantiate(&mut store, &module).map_err(|e| {
-            ButterflyBotError::Runtime(format!("Failed to instantiate wasm module: {e}"))
+            let msg = format!("{e:#}");
+            match memory_limit {
+                Some(limit) if Self::is_memory_limit_error(&msg) => {
+                    ButterflyBotError::Runtime(format!(
+                        "WASM tool '{tool_name}' needs more than its {limit}-byte memory limit"
+                    ))
+                }
+                _ => ButterflyBotError::Runtime(format!("Failed to instantiate wasm module: {e}")),
+            }
         })?;
 
         let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| {
@@ -867,21 +1062,37 @@ impl WasmRuntime {
             ButterflyBotError::Runtime("WASM input too large to pass as i32 length".to_string())
         })?;
 
-        let input_ptr = alloc
-            .call(&mut store, input_len)
-            .map_err(|e| ButterflyBotError::Runtime(format!("WASM alloc failed: {e}")))?;
+        let input_ptr = alloc.call(&mut store, input_len).map_err(|e| {
+            let msg = format!("{e:#}");
+            match memory_limit {
+                Some(limit) if Self::is_memory_limit_error(&msg) => ButterflyBotError::Runtime(
+                    format!("WASM tool '{tool_name}' exceeded its memory limit of {limit} bytes"),
+                ),
+                _ => ButterflyBotError::Runtime(format!("WASM alloc failed: {e}")),
+            }
+        })?;
 
         Self::ensure_range(&memory, &store, input_ptr, input_len)?;
         memory
             .write(&mut store, input_ptr as usize, &input)
             .map_err(|e| ButterflyBotError::Runtime(format!("WASM memory write failed: {e}")))?;
 
-        let packed = exec.call(&mut store, (input_ptr, input_len)).map_err(|e| {
-            let msg = e.to_string();
+        let call_result = exec.call(&mut store, (input_ptr, input_len));
+        if let Some(limit) = fuel_limit {
+            if let Ok(remaining) = store.get_fuel() {
+                crate::metrics::record_wasm_fuel(tool_name, limit.saturating_sub(remaining));
+            }
+        }
+        let packed = call_result.map_err(|e| {
+            let msg = format!("{e:#}");
             if timeout_ms > 0 && msg.to_ascii_lowercase().contains("interrupt") {
                 ButterflyBotError::Runtime(format!(
                     "WASM tool '{tool_name}' timed out after {timeout_ms}ms"
                 ))
+            } else if let Some(limit) = memory_limit.filter(|_| Self::is_memory_limit_error(&msg)) {
+                ButterflyBotError::Runtime(format!(
+                    "WASM tool '{tool_name}' exceeded its memory limit of {limit} bytes"
+                ))
             } else {
                 ButterflyBotError::Runtime(format!("WASM tool execute failed: {msg}"))
             }
@@ -905,6 +1116,16 @@ impl WasmRuntime {
         let value: Value = serde_json::from_slice(&output).map_err(|e| {
             ButterflyBotError::Runtime(format!("WASM output must be valid JSON: {e}"))
         })?;
+        let max_output_bytes = Self::resolve_max_output_bytes(config);
+        let (value, truncated) = output::limit(value, max_output_bytes);
+        if truncated {
+            tracing::warn!(
+                tool = tool_name,
+                output_bytes = output.len(),
+                max_output_bytes,
+                "WASM tool output truncated"
+            );
+        }
         Ok(value)
     }
 
@@ -916,8 +1137,19 @@ impl WasmRuntime {
     ) -> Result<Value> {
         let tool_name = tool_name.to_string();
         let config = config.clone();
-        tokio::task::spawn_blocking(move || Self::execute_sync(&tool_name, &config, params))
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(format!("WASM task join error: {e}")))?
+        let started = std::time::Instant::now();
+        let result = tokio::task::spawn_blocking({
+            let tool_name = tool_name.clone();
+            move || Self::execute_sync(&tool_name, &config, params)
+        })
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(format!("WASM task join error: {e}")))
+        .and_then(|result| result);
+        crate::metrics::record_wasm_execution(
+            &tool_name,
+            started.elapsed(),
+            crate::metrics::Outcome::of(&result),
+        );
+        result
     }
 }
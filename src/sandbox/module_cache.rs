@@ -0,0 +1,241 @@
+//! On-disk cache of compiled WASM modules.
+//!
+//! Cranelift compilation dominates the cost of a tool call, and every call
+//! used to redo it. Compiled artifacts (`Module::serialize`) are written to
+//! `<app root>/wasm-cache` — or `BUTTERFLY_BOT_WASM_CACHE_DIR` — and reloaded
+//! with `Module::deserialize_file` on later calls.
+//!
+//! Entries are named `<stem>-<path>-<content>-<engine>.cwasm`: the module's
+//! file stem, a hash of its path, a hash of its bytes, and wasmtime's
+//! compatibility hash for the engine config (fuel, epochs, version). Editing
+//! or rebuilding a module changes the content hash, so the old entry simply
+//! stops matching and is pruned when the new one is written.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+use wasmtime::{Engine, Module};
+
+use crate::error::ButterflyBotError;
+use crate::Result;
+
+const EXTENSION: &str = "cwasm";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOutcome {
+    /// Loaded a previously compiled artifact.
+    Hit,
+    /// Compiled from source; the artifact was stored for next time.
+    Miss,
+    /// Compiled from source but the artifact could not be stored.
+    Uncached,
+}
+
+impl CacheOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheOutcome::Hit => "hit",
+            CacheOutcome::Miss => "miss",
+            CacheOutcome::Uncached => "uncached",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleCache {
+    dir: PathBuf,
+}
+
+impl ModuleCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn default_dir() -> PathBuf {
+        std::env::var("BUTTERFLY_BOT_WASM_CACHE_DIR")
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| crate::runtime_paths::app_root().join("wasm-cache"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the module at `module_path` compiled for `engine`, from the
+    /// cache when a matching artifact exists. Cache failures never fail the
+    /// load; they only cost a recompile.
+    pub fn load(&self, engine: &Engine, module_path: &Path) -> Result<(Module, CacheOutcome)> {
+        let bytes = fs::read(module_path).map_err(|e| {
+            ButterflyBotError::Runtime(format!(
+                "Failed to read wasm module {}: {e}",
+                module_path.display()
+            ))
+        })?;
+        let prefix = entry_prefix(module_path);
+        let engine_key = engine_key(engine);
+        let entry = self.dir.join(format!(
+            "{prefix}-{}-{engine_key}.{EXTENSION}",
+            short_hex(&Sha256::digest(&bytes))
+        ));
+
+        if entry.exists() {
+            // SAFETY: artifacts in the cache directory are only ever written
+            // by `store` below from `Module::serialize`, and wasmtime rejects
+            // artifacts built for a different engine configuration.
+            match unsafe { Module::deserialize_file(engine, &entry) } {
+                Ok(module) => return Ok((module, CacheOutcome::Hit)),
+                Err(err) => {
+                    tracing::warn!(entry = %entry.display(), error = %err, "Discarding unreadable wasm cache entry");
+                    let _ = fs::remove_file(&entry);
+                }
+            }
+        }
+
+        let module = Module::new(engine, &bytes)
+            .map_err(|e| ButterflyBotError::Runtime(format!("Failed to load wasm module: {e}")))?;
+        let outcome = match self.store(&module, &entry, &prefix, &engine_key) {
+            Ok(()) => CacheOutcome::Miss,
+            Err(err) => {
+                tracing::debug!(entry = %entry.display(), error = %err, "Could not cache compiled wasm module");
+                CacheOutcome::Uncached
+            }
+        };
+        Ok((module, outcome))
+    }
+
+    fn store(&self, module: &Module, entry: &Path, prefix: &str, engine_key: &str) -> Result<()> {
+        let serialized = module
+            .serialize()
+            .map_err(|e| ButterflyBotError::Runtime(format!("Failed to serialize module: {e}")))?;
+        fs::create_dir_all(&self.dir).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        // Written under a temporary name and renamed so a concurrent call
+        // never deserializes a half-written artifact.
+        let partial = entry.with_extension(format!("{EXTENSION}.{}.tmp", std::process::id()));
+        fs::write(&partial, serialized).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        fs::rename(&partial, entry).map_err(|e| {
+            let _ = fs::remove_file(&partial);
+            ButterflyBotError::Runtime(e.to_string())
+        })?;
+        self.prune_stale(entry, prefix, engine_key);
+        Ok(())
+    }
+
+    /// Removes artifacts of earlier builds of the same module for the same
+    /// engine configuration. Entries for other engine configs are kept, since
+    /// tools with different fuel or timeout settings share module files.
+    fn prune_stale(&self, keep: &Path, prefix: &str, engine_key: &str) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let suffix = format!("-{engine_key}.{EXTENSION}");
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if path != keep && name.starts_with(&format!("{prefix}-")) && name.ends_with(&suffix) {
+                let _ = fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+fn entry_prefix(module_path: &Path) -> String {
+    let stem: String = module_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("module")
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let canonical = fs::canonicalize(module_path).unwrap_or_else(|_| module_path.to_path_buf());
+    let path_hash = Sha256::digest(canonical.to_string_lossy().as_bytes());
+    format!("{stem}-{}", &short_hex(&path_hash)[..8])
+}
+
+fn engine_key(engine: &Engine) -> String {
+    let mut hasher = DefaultHasher::new();
+    engine.precompile_compatibility_hash().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn short_hex(digest: &[u8]) -> String {
+    digest[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADD_ONE: &str = r#"(module (func (export "run") (param i32) (result i32) local.get 0 i32.const 1 i32.add))"#;
+    const ADD_TWO: &str = r#"(module (func (export "run") (param i32) (result i32) local.get 0 i32.const 2 i32.add))"#;
+
+    fn cached_entries(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(EXTENSION))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn call_run(engine: &Engine, module: &Module, arg: i32) -> i32 {
+        let mut store = wasmtime::Store::new(engine, ());
+        let instance = wasmtime::Instance::new(&mut store, module, &[]).unwrap();
+        instance
+            .get_typed_func::<i32, i32>(&mut store, "run")
+            .unwrap()
+            .call(&mut store, arg)
+            .unwrap()
+    }
+
+    #[test]
+    fn module_cache_reuses_artifacts_and_invalidates_on_change() {
+        let temp = tempfile::tempdir().unwrap();
+        let module_path = temp.path().join("demo_tool.wat");
+        let cache = ModuleCache::new(temp.path().join("cache"));
+        let engine = Engine::default();
+
+        fs::write(&module_path, ADD_ONE).unwrap();
+        let (module, outcome) = cache.load(&engine, &module_path).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+        assert_eq!(call_run(&engine, &module, 1), 2);
+
+        let (module, outcome) = cache.load(&engine, &module_path).unwrap();
+        assert_eq!(outcome, CacheOutcome::Hit);
+        assert_eq!(call_run(&engine, &module, 1), 2);
+        assert_eq!(cached_entries(cache.dir()).len(), 1);
+
+        fs::write(&module_path, ADD_TWO).unwrap();
+        let (module, outcome) = cache.load(&engine, &module_path).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+        assert_eq!(call_run(&engine, &module, 1), 3);
+        assert_eq!(cached_entries(cache.dir()).len(), 1);
+
+        // A different engine config gets its own entry next to the first.
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let fuel_engine = Engine::new(&config).unwrap();
+        let (_, outcome) = cache.load(&fuel_engine, &module_path).unwrap();
+        assert_eq!(outcome, CacheOutcome::Miss);
+        assert_eq!(cached_entries(cache.dir()).len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! Caps the size of a WASM tool's result before it reaches the model.
+//!
+//! A result over the limit keeps its top-level `status` and `code`, gains
+//! `"truncated": true` and `original_bytes`, and has everything else cut
+//! down in order: long strings end in [`MARKER`], arrays keep their leading
+//! items plus a note of how many were dropped, and objects count the fields
+//! that no longer fit under `truncated_fields`.
+
+use serde_json::{Map, Value};
+
+pub const DEFAULT_MAX_OUTPUT_BYTES: usize = 64 * 1024;
+/// Smaller caps leave no room for anything but the markers.
+pub const MIN_MAX_OUTPUT_BYTES: usize = 1024;
+pub const MARKER: &str = "…[truncated]";
+
+/// Fields kept whole so callers can still tell success from failure.
+const PRESERVED: &[&str] = &["status", "code"];
+/// Below this much room a value is dropped rather than cut.
+const MIN_ROOM: usize = 32;
+
+/// `value` unchanged when it serializes to at most `max_bytes`, otherwise
+/// a truncated copy that does.
+pub fn limit(value: Value, max_bytes: usize) -> (Value, bool) {
+    let original_bytes = size(&value);
+    if original_bytes <= max_bytes {
+        return (value, false);
+    }
+
+    let mut out = Map::new();
+    let rest = match value {
+        Value::Object(mut fields) => {
+            for key in PRESERVED {
+                if let Some(field) = fields.remove(*key) {
+                    out.insert((*key).to_string(), field);
+                }
+            }
+            fields
+        }
+        other => Map::from_iter([("result".to_string(), other)]),
+    };
+    let markers = [
+        ("truncated".to_string(), Value::Bool(true)),
+        ("original_bytes".to_string(), Value::from(original_bytes)),
+    ];
+    out.extend(markers.clone());
+    // The shrunk fields share `out`'s braces and add one comma.
+    let room = (max_bytes + 1).saturating_sub(size(&Value::Object(out.clone())));
+    if let Value::Object(rest) = shrink(Value::Object(rest), room) {
+        out.extend(rest);
+    }
+    out.extend(markers);
+    (Value::Object(out), true)
+}
+
+fn size(value: &Value) -> usize {
+    serde_json::to_vec(value)
+        .map(|bytes| bytes.len())
+        .unwrap_or(0)
+}
+
+/// Cuts `value` to at most `budget` serialized bytes.
+fn shrink(value: Value, budget: usize) -> Value {
+    match value {
+        Value::String(text) => Value::String(cut_string(&text, budget)),
+        Value::Array(items) => {
+            let total = items.len();
+            let mut kept = Vec::new();
+            let mut used = 2;
+            for item in items {
+                let note = size(&dropped_note(total - kept.len())) + 1;
+                let room = budget.saturating_sub(used + 1 + note);
+                let item_size = size(&item);
+                if item_size <= room {
+                    used += item_size + 1;
+                    kept.push(item);
+                    continue;
+                }
+                if room >= MIN_ROOM {
+                    kept.push(shrink(item, room));
+                }
+                if kept.len() < total {
+                    kept.push(dropped_note(total - kept.len()));
+                }
+                break;
+            }
+            Value::Array(kept)
+        }
+        Value::Object(fields) => {
+            let note = r#","truncated_fields":"#.len() + 20;
+            let mut kept = Map::new();
+            let mut used = 2;
+            let mut dropped = 0;
+            for (key, field) in fields {
+                let entry = key.len() + 4;
+                let room = budget.saturating_sub(used + entry + note);
+                let field_size = size(&field);
+                if field_size <= room {
+                    used += entry + field_size;
+                    kept.insert(key, field);
+                } else if room >= MIN_ROOM {
+                    let field = shrink(field, room);
+                    used += entry + size(&field);
+                    kept.insert(key, field);
+                } else {
+                    dropped += 1;
+                }
+            }
+            if dropped > 0 {
+                kept.insert("truncated_fields".to_string(), Value::from(dropped));
+            }
+            Value::Object(kept)
+        }
+        // Scalars are small; the caller only gets here when nothing fits.
+        other => other,
+    }
+}
+
+fn dropped_note(count: usize) -> Value {
+    Value::String(format!("[{count} more items truncated]"))
+}
+
+fn cut_string(text: &str, budget: usize) -> String {
+    let mut keep = budget.saturating_sub(MARKER.len() + 2).min(text.len());
+    loop {
+        while !text.is_char_boundary(keep) {
+            keep -= 1;
+        }
+        let candidate = format!("{}{MARKER}", &text[..keep]);
+        let over = size(&Value::String(candidate.clone())).saturating_sub(budget);
+        if over == 0 || keep == 0 {
+            return candidate;
+        }
+        keep = keep.saturating_sub(over);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn oversized_results_keep_status_and_fit_the_cap() {
+        let small = json!({"status": "ok", "items": [1, 2, 3]});
+        assert_eq!(limit(small.clone(), 1024), (small, false));
+
+        let rows: Vec<Value> = (0..500)
+            .map(|i| json!({"id": i, "body": "ü".repeat(200)}))
+            .collect();
+        let big = json!({"status": "error", "code": "upstream", "rows": rows, "note": "x"});
+        let (limited, truncated) = limit(big.clone(), 2048);
+        assert!(truncated);
+        assert!(size(&limited) <= 2048, "{} bytes", size(&limited));
+        assert_eq!(limited["status"], "error");
+        assert_eq!(limited["code"], "upstream");
+        assert_eq!(limited["truncated"], true);
+        assert_eq!(limited["original_bytes"], size(&big));
+        let kept = limited["rows"].as_array().unwrap();
+        assert_eq!(kept[0]["id"], 0);
+        assert!(kept
+            .last()
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .ends_with("more items truncated]"));
+
+        let (text, _) = limit(Value::String("a".repeat(10_000)), 256);
+        assert!(size(&text) <= 256);
+        assert!(text["result"].as_str().unwrap().ends_with(MARKER));
+    }
+}
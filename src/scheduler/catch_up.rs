@@ -0,0 +1,216 @@
+//! What to do about runs that fell due while the daemon was down.
+//!
+//! Configured per job name under `tools.settings.scheduler`:
+//!
+//! ```json
+//! {"catch_up": {"default": "once", "scheduled_tasks": "all", "backup": "skip"},
+//!  "max_catch_up_runs": 24}
+//! ```
+//!
+//! `once` runs a job (or a due task or wakeup) a single time however many
+//! slots it missed, `skip` waits for the next slot on the original
+//! schedule, and `all` replays every missed slot up to
+//! `max_catch_up_runs`, dropping the oldest beyond that.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+pub const DEFAULT_MAX_RUNS: i64 = 24;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CatchUpPolicy {
+    #[default]
+    Once,
+    Skip,
+    All,
+}
+
+impl CatchUpPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "once" | "run_once" => Some(Self::Once),
+            "skip" => Some(Self::Skip),
+            "all" | "run_all" => Some(Self::All),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Once => "once",
+            Self::Skip => "skip",
+            Self::All => "all",
+        }
+    }
+
+    /// Plan for a job whose next run was due at `slot`: how many runs to
+    /// make right away and when the one after is due.
+    pub fn plan(self, slot: i64, now: i64, interval: i64, max_runs: i64) -> CatchUpPlan {
+        let interval = interval.max(1);
+        let missed = missed_slots(slot, now, interval);
+        if missed == 0 {
+            return CatchUpPlan {
+                missed,
+                runs: 0,
+                next_run_at: slot,
+            };
+        }
+        let (runs, next_run_at) = match self {
+            Self::Once => (1, now + interval),
+            Self::Skip if missed == 1 => (1, now + interval),
+            Self::Skip => (0, slot + missed * interval),
+            Self::All => (missed.min(max_runs.max(1)), now + interval),
+        };
+        CatchUpPlan {
+            missed,
+            runs,
+            next_run_at,
+        }
+    }
+
+    /// Plan for a due item that keeps its own schedule, such as a scheduled
+    /// task or a wakeup. Items run at most once per poll, so `all` moves the
+    /// item to its next missed slot and lets the following polls replay the
+    /// rest.
+    pub fn item_plan(self, slot: i64, now: i64, interval: i64, max_runs: i64) -> CatchUpPlan {
+        let interval = interval.max(1);
+        let missed = missed_slots(slot, now, interval);
+        let (runs, next_run_at) = match self {
+            _ if missed == 0 => (0, slot),
+            _ if missed == 1 => (1, now + interval),
+            Self::Once => (1, now + interval),
+            Self::Skip => (0, slot + missed * interval),
+            Self::All => {
+                let dropped = (missed - max_runs.max(1)).max(0);
+                (1, slot + (dropped + 1) * interval)
+            }
+        };
+        CatchUpPlan {
+            missed,
+            runs,
+            next_run_at,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CatchUpPlan {
+    /// Slots that came due by now; `1` is an ordinary on-time run.
+    pub missed: i64,
+    pub runs: i64,
+    pub next_run_at: i64,
+}
+
+fn missed_slots(slot: i64, now: i64, interval: i64) -> i64 {
+    if slot > now {
+        0
+    } else {
+        (now - slot) / interval + 1
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatchUpConfig {
+    pub default: CatchUpPolicy,
+    pub jobs: HashMap<String, CatchUpPolicy>,
+    pub max_runs: i64,
+}
+
+impl Default for CatchUpConfig {
+    fn default() -> Self {
+        Self {
+            default: CatchUpPolicy::Once,
+            jobs: HashMap::new(),
+            max_runs: DEFAULT_MAX_RUNS,
+        }
+    }
+}
+
+impl CatchUpConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let mut config = Self::default();
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("scheduler"))
+        else {
+            return config;
+        };
+        match section.get("catch_up") {
+            Some(Value::String(policy)) => {
+                if let Some(policy) = CatchUpPolicy::parse(policy) {
+                    config.default = policy;
+                }
+            }
+            Some(Value::Object(policies)) => {
+                for (job, policy) in policies {
+                    let Some(policy) = policy.as_str().and_then(CatchUpPolicy::parse) else {
+                        continue;
+                    };
+                    if job == "default" {
+                        config.default = policy;
+                    } else {
+                        config.jobs.insert(job.clone(), policy);
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(max_runs) = section.get("max_catch_up_runs").and_then(Value::as_i64) {
+            config.max_runs = max_runs.max(1);
+        }
+        config
+    }
+
+    pub fn policy_for(&self, job: &str) -> CatchUpPolicy {
+        self.jobs.get(job).copied().unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{CatchUpConfig, CatchUpPlan, CatchUpPolicy};
+
+    #[test]
+    fn policies_plan_missed_runs() {
+        // Due at 1_000 every 100s; down until 1_450, so five slots passed.
+        let plan = |policy: CatchUpPolicy| policy.plan(1_000, 1_450, 100, 3);
+        assert_eq!(
+            plan(CatchUpPolicy::Once),
+            CatchUpPlan {
+                missed: 5,
+                runs: 1,
+                next_run_at: 1_550
+            }
+        );
+        assert_eq!(plan(CatchUpPolicy::Skip).runs, 0);
+        assert_eq!(plan(CatchUpPolicy::Skip).next_run_at, 1_500);
+        assert_eq!(plan(CatchUpPolicy::All).runs, 3);
+        assert_eq!(CatchUpPolicy::Skip.plan(2_000, 1_450, 100, 3).runs, 0);
+
+        let item = |policy: CatchUpPolicy| policy.item_plan(1_000, 1_450, 100, 3);
+        assert_eq!(item(CatchUpPolicy::Once).next_run_at, 1_550);
+        assert_eq!(item(CatchUpPolicy::Skip).runs, 0);
+        // Five missed, three kept: this run stands in for 1_200, and 1_300
+        // and 1_400 follow on later polls.
+        assert_eq!(item(CatchUpPolicy::All).next_run_at, 1_300);
+        assert_eq!(CatchUpPolicy::Skip.item_plan(1_000, 1_020, 100, 3).runs, 1);
+    }
+
+    #[test]
+    fn config_reads_per_job_policies() {
+        let config = CatchUpConfig::from_tools(Some(&json!({"settings": {"scheduler": {
+            "catch_up": {"default": "skip", "scheduled_tasks": "all", "wakeup": "bogus"},
+            "max_catch_up_runs": 5
+        }}})));
+        assert_eq!(config.policy_for("scheduled_tasks"), CatchUpPolicy::All);
+        assert_eq!(config.policy_for("wakeup"), CatchUpPolicy::Skip);
+        assert_eq!(config.max_runs, 5);
+        assert_eq!(
+            CatchUpConfig::from_tools(None).policy_for("backup"),
+            CatchUpPolicy::Once
+        );
+    }
+}
@@ -3,13 +3,23 @@ use std::time::Duration;
 
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
 use crate::interfaces::scheduler::ScheduledJob;
 
+pub mod catch_up;
+mod schema;
+pub mod state;
+
+use catch_up::{CatchUpConfig, CatchUpPolicy};
+use state::JobStateStore;
+
 pub struct Scheduler {
     jobs: Vec<Arc<dyn ScheduledJob>>,
     handles: Vec<JoinHandle<()>>,
     stop: Option<watch::Sender<bool>>,
+    state: Option<Arc<JobStateStore>>,
+    catch_up: CatchUpConfig,
 }
 
 impl Scheduler {
@@ -18,9 +28,25 @@ impl Scheduler {
             jobs: Vec::new(),
             handles: Vec::new(),
             stop: None,
+            state: None,
+            catch_up: CatchUpConfig::default(),
         }
     }
 
+    /// Persists each job's last and next run, so a restart picks up the
+    /// schedule instead of running every job at once. Without a store jobs
+    /// run on start and then every interval.
+    pub fn with_state(mut self, state: Arc<JobStateStore>) -> Self {
+        self.state = Some(state);
+        self
+    }
+
+    /// How jobs that fell due while the daemon was down are caught up.
+    pub fn with_catch_up(mut self, catch_up: CatchUpConfig) -> Self {
+        self.catch_up = catch_up;
+        self
+    }
+
     pub fn register_job(&mut self, job: Arc<dyn ScheduledJob>) {
         self.jobs.push(job);
     }
@@ -35,16 +61,37 @@ impl Scheduler {
         }
         let (tx, rx) = watch::channel(false);
         self.stop = Some(tx);
+        let started_at = self.state.as_ref().map(|state| state.clock().now());
 
         for job in &self.jobs {
             let job = Arc::clone(job);
-            let mut tick = tokio::time::interval(job.interval());
+            let state = self.state.clone();
+            let policy = self.catch_up.policy_for(job.name());
+            let max_runs = self.catch_up.max_runs;
             let mut rx = rx.clone();
             let handle = tokio::spawn(async move {
+                let first = match (&state, started_at) {
+                    (Some(state), Some(started_at)) => {
+                        let name = job.name();
+                        if let Err(err) = state
+                            .register(name, interval_secs(job.as_ref()), started_at)
+                            .await
+                        {
+                            tracing::warn!(
+                                job = name,
+                                error = %err,
+                                "Failed to register scheduler job"
+                            );
+                        }
+                        catch_up(job.as_ref(), state, policy, max_runs, &rx).await
+                    }
+                    _ => Instant::now(),
+                };
+                let mut tick = tokio::time::interval_at(first, job.interval());
                 loop {
                     tokio::select! {
                         _ = tick.tick() => {
-                            let _ = job.run().await;
+                            run_job(job.as_ref(), state.as_deref()).await;
                         }
                         _ = rx.changed() => {
                             if *rx.borrow() {
@@ -58,6 +105,15 @@ impl Scheduler {
         }
     }
 
+    /// Runs every registered job once, in registration order, without the
+    /// interval timers. Paired with a `ManualClock`, this steps scheduled
+    /// behaviour deterministically.
+    pub async fn run_once(&self) {
+        for job in &self.jobs {
+            run_job(job.as_ref(), self.state.as_deref()).await;
+        }
+    }
+
     pub async fn stop(&mut self) {
         if let Some(tx) = self.stop.take() {
             let _ = tx.send(true);
@@ -78,3 +134,174 @@ impl Default for Scheduler {
 pub fn seconds(n: u64) -> Duration {
     Duration::from_secs(n)
 }
+
+fn interval_secs(job: &dyn ScheduledJob) -> i64 {
+    job.interval().as_secs().max(1) as i64
+}
+
+async fn run_job(job: &dyn ScheduledJob, state: Option<&JobStateStore>) {
+    let Some(state) = state else {
+        let _ = job.run().await;
+        return;
+    };
+    let run_at = state.clock().now();
+    let started = std::time::Instant::now();
+    let error = job.run().await.err().map(|err| err.to_string());
+    let duration_ms = started.elapsed().as_millis() as i64;
+    let next_run_at = run_at + interval_secs(job);
+    if let Err(err) = state
+        .record_run(
+            job.name(),
+            run_at,
+            next_run_at,
+            duration_ms,
+            error.as_deref(),
+        )
+        .await
+    {
+        tracing::warn!(job = job.name(), error = %err, "Failed to record scheduler run");
+    }
+}
+
+/// Makes the runs the policy asks for after downtime and returns when the
+/// first regular tick is due.
+async fn catch_up(
+    job: &dyn ScheduledJob,
+    state: &JobStateStore,
+    policy: CatchUpPolicy,
+    max_runs: i64,
+    stop: &watch::Receiver<bool>,
+) -> Instant {
+    let saved = match state.get(job.name()).await {
+        Ok(Some(saved)) => saved,
+        Ok(None) => return Instant::now(),
+        Err(err) => {
+            tracing::warn!(job = job.name(), error = %err, "Failed to load scheduler state");
+            return Instant::now();
+        }
+    };
+    let interval = interval_secs(job);
+    let plan = policy.plan(saved.next_run_at, state.clock().now(), interval, max_runs);
+    if plan.missed > 1 {
+        tracing::info!(
+            job = job.name(),
+            missed = plan.missed,
+            runs = plan.runs,
+            policy = policy.as_str(),
+            "Catching up missed scheduler runs"
+        );
+    }
+    for _ in 0..plan.runs {
+        if *stop.borrow() {
+            break;
+        }
+        run_job(job, Some(state)).await;
+    }
+    if plan.runs > 0 {
+        return Instant::now() + job.interval();
+    }
+    // A shortened interval since the last run should not leave the job
+    // waiting out the old one.
+    let wait = (plan.next_run_at - state.clock().now()).clamp(0, interval);
+    Instant::now() + Duration::from_secs(wait as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::error::Result;
+
+    struct CountJob {
+        count: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl ScheduledJob for CountJob {
+        fn name(&self) -> &str {
+            "count"
+        }
+
+        fn interval(&self) -> Duration {
+            Duration::from_secs(60)
+        }
+
+        async fn run(&self) -> Result<()> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    async fn restart(state: &Arc<JobStateStore>, policy: CatchUpPolicy) -> u32 {
+        let count = Arc::new(AtomicU32::new(0));
+        let mut scheduler =
+            Scheduler::new()
+                .with_state(state.clone())
+                .with_catch_up(CatchUpConfig {
+                    default: policy,
+                    jobs: HashMap::new(),
+                    max_runs: 3,
+                });
+        scheduler.register_job(Arc::new(CountJob {
+            count: count.clone(),
+        }));
+        scheduler.start();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        scheduler.stop().await;
+        count.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn restarts_catch_up_missed_runs_by_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scheduler.db");
+        let clock = ManualClock::new(10_000);
+        let state = Arc::new(
+            JobStateStore::new(path.to_string_lossy())
+                .await
+                .unwrap()
+                .with_clock(clock.clone()),
+        );
+
+        // Down since 9_060 with a 60s interval: sixteen slots missed.
+        state
+            .record_run("count", 9_000, 9_060, 5, None)
+            .await
+            .unwrap();
+        assert_eq!(restart(&state, CatchUpPolicy::All).await, 3);
+        let saved = state.get("count").await.unwrap().unwrap();
+        assert_eq!(saved.run_count, 4);
+        assert_eq!(saved.next_run_at, 10_060);
+        assert_eq!(saved.interval_seconds, 60);
+        assert_eq!(saved.registered_at, 10_000);
+        assert!(saved.last_duration_ms.is_some());
+        assert!(!saved.is_late(10_060));
+        assert!(saved.is_late(10_121));
+
+        state
+            .record_run("count", 9_000, 9_060, 5, None)
+            .await
+            .unwrap();
+        assert_eq!(restart(&state, CatchUpPolicy::Skip).await, 0);
+        assert_eq!(restart(&state, CatchUpPolicy::Once).await, 1);
+
+        // Not due yet: nothing runs on start, whatever the policy.
+        assert_eq!(restart(&state, CatchUpPolicy::Once).await, 0);
+
+        // Jobs a later start no longer registers drop out of the listing.
+        state.register("retired", 300, 9_000).await.unwrap();
+        let listed = state.list_registered().await.unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|job| job.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["count"]
+        );
+    }
+}
@@ -0,0 +1,13 @@
+diesel::table! {
+    scheduler_jobs (name) {
+        name -> Text,
+        last_run_at -> Nullable<BigInt>,
+        next_run_at -> BigInt,
+        last_error -> Nullable<Text>,
+        run_count -> BigInt,
+        updated_at -> BigInt,
+        interval_seconds -> BigInt,
+        last_duration_ms -> Nullable<BigInt>,
+        registered_at -> BigInt,
+    }
+}
@@ -0,0 +1,287 @@
+//! Last and next run of each scheduler job, kept across restarts so missed
+//! runs can be caught up (see [`super::catch_up`]) and so `/scheduler/jobs`
+//! can show what background work is doing.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+
+use super::schema::scheduler_jobs;
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const SCHEDULER_JOBS_UP_SQL: &str =
+    include_str!("../../migrations/20260329_create_scheduler_jobs/up.sql");
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct JobState {
+    pub name: String,
+    pub last_run_at: Option<i64>,
+    /// When the job is next due; in the past after downtime.
+    pub next_run_at: i64,
+    /// Error from the last run, if it failed.
+    pub last_error: Option<String>,
+    pub run_count: i64,
+    pub updated_at: i64,
+    /// `0` for rows saved before intervals were recorded.
+    pub interval_seconds: i64,
+    pub last_duration_ms: Option<i64>,
+    /// When the scheduler that owns the job last started.
+    pub registered_at: i64,
+}
+
+impl JobState {
+    /// A whole interval has passed since the job was due without it running.
+    pub fn is_late(&self, now: i64) -> bool {
+        self.interval_seconds > 0 && now > self.next_run_at + self.interval_seconds
+    }
+}
+
+#[derive(Queryable)]
+struct JobStateRow {
+    name: String,
+    last_run_at: Option<i64>,
+    next_run_at: i64,
+    last_error: Option<String>,
+    run_count: i64,
+    updated_at: i64,
+    interval_seconds: i64,
+    last_duration_ms: Option<i64>,
+    registered_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = scheduler_jobs)]
+struct NewJobState<'a> {
+    name: &'a str,
+    last_run_at: Option<i64>,
+    next_run_at: i64,
+    last_error: Option<&'a str>,
+    run_count: i64,
+    updated_at: i64,
+    interval_seconds: i64,
+    last_duration_ms: Option<i64>,
+    registered_at: i64,
+}
+
+pub struct JobStateStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl JobStateStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_scheduler_jobs_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for run timestamps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Option<JobState>> {
+        let mut conn = self.conn().await?;
+        let row: Option<JobStateRow> = scheduler_jobs::table
+            .filter(scheduler_jobs::name.eq(name))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    pub async fn list(&self) -> Result<Vec<JobState>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<JobStateRow> = scheduler_jobs::table
+            .order(scheduler_jobs::name.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Jobs registered by the most recent scheduler start, leaving out
+    /// names an older build ran that no longer exist.
+    pub async fn list_registered(&self) -> Result<Vec<JobState>> {
+        let jobs = self.list().await?;
+        let latest = jobs.iter().map(|job| job.registered_at).max();
+        Ok(jobs
+            .into_iter()
+            .filter(|job| Some(job.registered_at) == latest)
+            .collect())
+    }
+
+    /// Notes that a scheduler started at `registered_at` runs `name` every
+    /// `interval_seconds`. A job seen for the first time is due right away.
+    pub async fn register(
+        &self,
+        name: &str,
+        interval_seconds: i64,
+        registered_at: i64,
+    ) -> Result<()> {
+        let mut conn = self.write_conn().await?;
+        let row = NewJobState {
+            name,
+            last_run_at: None,
+            next_run_at: registered_at,
+            last_error: None,
+            run_count: 0,
+            updated_at: registered_at,
+            interval_seconds,
+            last_duration_ms: None,
+            registered_at,
+        };
+        diesel::insert_into(scheduler_jobs::table)
+            .values(&row)
+            .on_conflict(scheduler_jobs::name)
+            .do_update()
+            .set((
+                scheduler_jobs::interval_seconds.eq(interval_seconds),
+                scheduler_jobs::registered_at.eq(registered_at),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Records a run that started at `run_at` and took `duration_ms`;
+    /// `error` is `None` when it succeeded.
+    pub async fn record_run(
+        &self,
+        name: &str,
+        run_at: i64,
+        next_run_at: i64,
+        duration_ms: i64,
+        error: Option<&str>,
+    ) -> Result<JobState> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let row = NewJobState {
+            name,
+            last_run_at: Some(run_at),
+            next_run_at,
+            last_error: error,
+            run_count: 1,
+            updated_at: now,
+            interval_seconds: next_run_at - run_at,
+            last_duration_ms: Some(duration_ms),
+            registered_at: 0,
+        };
+        diesel::insert_into(scheduler_jobs::table)
+            .values(&row)
+            .on_conflict(scheduler_jobs::name)
+            .do_update()
+            .set((
+                scheduler_jobs::last_run_at.eq(Some(run_at)),
+                scheduler_jobs::next_run_at.eq(next_run_at),
+                scheduler_jobs::last_error.eq(error),
+                scheduler_jobs::run_count.eq(scheduler_jobs::run_count + 1),
+                scheduler_jobs::updated_at.eq(now),
+                scheduler_jobs::last_duration_ms.eq(Some(duration_ms)),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+        self.get(name).await?.ok_or_else(|| {
+            ButterflyBotError::Runtime(format!("Scheduler job '{name}' vanished after a run"))
+        })
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn map_row(row: JobStateRow) -> JobState {
+    JobState {
+        name: row.name,
+        last_run_at: row.last_run_at,
+        next_run_at: row.next_run_at,
+        last_error: row.last_error,
+        run_count: row.run_count,
+        updated_at: row.updated_at,
+        interval_seconds: row.interval_seconds,
+        last_duration_ms: row.last_duration_ms,
+        registered_at: row.registered_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_scheduler_jobs_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM scheduler_jobs LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    SCHEDULER_JOBS_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+
+        for statement in [
+            "ALTER TABLE scheduler_jobs ADD COLUMN interval_seconds BIGINT NOT NULL DEFAULT 0",
+            "ALTER TABLE scheduler_jobs ADD COLUMN last_duration_ms BIGINT",
+            "ALTER TABLE scheduler_jobs ADD COLUMN registered_at BIGINT NOT NULL DEFAULT 0",
+        ] {
+            if let Err(err) =
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, statement)
+            {
+                let message = err.to_string().to_ascii_lowercase();
+                if !message.contains("duplicate column name") {
+                    return Err(ButterflyBotError::Runtime(err.to_string()));
+                }
+            }
+        }
+
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Forward scheduling for the Gantt view.
+//!
+//! Each task lasts its PERT estimate, `(optimistic + 4 × likely +
+//! pessimistic) / 6`, using whichever of the three estimates it has. Open
+//! tasks start as soon as `now` and all of their dependencies allow; fixed
+//! tasks, such as finished work or calendar events, stay where they are. A backward pass from the end of the
+//! schedule, tightened by due dates, gives each task its slack; tasks
+//! without slack form the critical path. Dependency cycles are broken at
+//! the edge that closes them.
+
+use std::collections::HashMap;
+
+/// Used when a task has no estimate at all.
+pub const DEFAULT_MINUTES: i64 = 60;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Task {
+    pub id: String,
+    pub optimistic_minutes: Option<i32>,
+    pub likely_minutes: Option<i32>,
+    pub pessimistic_minutes: Option<i32>,
+    /// Ids of tasks that must finish first; unknown ids are ignored.
+    pub depends_on: Vec<String>,
+    pub due_at: Option<i64>,
+    /// Set for tasks that are finished or happen at a fixed time; they end
+    /// there and are never rescheduled.
+    pub fixed_end: Option<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledTask {
+    pub id: String,
+    pub start: i64,
+    pub end: i64,
+    /// How long the task can slip without delaying the schedule or missing
+    /// its due date; negative when it is already going to be late.
+    pub slack_seconds: i64,
+    pub critical: bool,
+    /// Finishes after its due date.
+    pub late: bool,
+}
+
+/// Expected duration from any mix of the three estimates.
+pub fn expected_minutes(
+    optimistic: Option<i32>,
+    likely: Option<i32>,
+    pessimistic: Option<i32>,
+) -> i64 {
+    let [optimistic, likely, pessimistic] =
+        [optimistic, likely, pessimistic].map(|estimate| estimate.map(|minutes| minutes.max(1)));
+    let likely = likely
+        .or_else(|| match (optimistic, pessimistic) {
+            (Some(low), Some(high)) => Some((low + high) / 2),
+            (low, high) => low.or(high),
+        })
+        .map(i64::from);
+    let Some(likely) = likely else {
+        return DEFAULT_MINUTES;
+    };
+    let optimistic = optimistic.map(i64::from).unwrap_or(likely);
+    let pessimistic = pessimistic.map(i64::from).unwrap_or(likely);
+    ((optimistic + 4 * likely + pessimistic + 3) / 6).max(1)
+}
+
+/// Schedules `tasks`, returned in the same order.
+pub fn schedule(tasks: &[Task], now: i64) -> Vec<ScheduledTask> {
+    let index: HashMap<&str, usize> = tasks
+        .iter()
+        .enumerate()
+        .map(|(position, task)| (task.id.as_str(), position))
+        .collect();
+    let order = topological_order(tasks, &index);
+    let mut rank = vec![0; tasks.len()];
+    for (position, &task) in order.iter().enumerate() {
+        rank[task] = position;
+    }
+    // Only edges that point backwards in the order survive; the rest
+    // closed a cycle.
+    let dependencies: Vec<Vec<usize>> = tasks
+        .iter()
+        .enumerate()
+        .map(|(task, item)| {
+            let mut deps = item
+                .depends_on
+                .iter()
+                .filter_map(|dep| index.get(dep.as_str()).copied())
+                .filter(|&dep| dep != task && rank[dep] < rank[task])
+                .collect::<Vec<_>>();
+            deps.sort_unstable();
+            deps.dedup();
+            deps
+        })
+        .collect();
+    let durations: Vec<i64> = tasks
+        .iter()
+        .map(|task| {
+            expected_minutes(
+                task.optimistic_minutes,
+                task.likely_minutes,
+                task.pessimistic_minutes,
+            ) * 60
+        })
+        .collect();
+
+    let mut start = vec![0; tasks.len()];
+    let mut end = vec![0; tasks.len()];
+    for &task in &order {
+        if let Some(fixed_end) = tasks[task].fixed_end {
+            end[task] = fixed_end;
+            start[task] = fixed_end - durations[task];
+            continue;
+        }
+        start[task] = dependencies[task]
+            .iter()
+            .map(|&dep| end[dep])
+            .fold(now, i64::max);
+        end[task] = start[task] + durations[task];
+    }
+
+    let open = |task: usize| tasks[task].fixed_end.is_none();
+    let finish = (0..tasks.len())
+        .filter(|&task| open(task))
+        .map(|task| end[task])
+        .max()
+        .unwrap_or(now);
+    let mut latest_end = vec![finish; tasks.len()];
+    for (task, item) in tasks.iter().enumerate() {
+        if let Some(due) = item.due_at {
+            latest_end[task] = latest_end[task].min(due);
+        }
+    }
+    for &task in order.iter().rev() {
+        let latest_start = latest_end[task] - durations[task];
+        for &dep in &dependencies[task] {
+            latest_end[dep] = latest_end[dep].min(latest_start);
+        }
+    }
+
+    tasks
+        .iter()
+        .enumerate()
+        .map(|(task, item)| {
+            let slack_seconds = if open(task) {
+                latest_end[task] - end[task]
+            } else {
+                0
+            };
+            ScheduledTask {
+                id: item.id.clone(),
+                start: start[task],
+                end: end[task],
+                slack_seconds,
+                critical: open(task) && slack_seconds <= 0,
+                late: item.due_at.is_some_and(|due| end[task] > due),
+            }
+        })
+        .collect()
+}
+
+/// Dependencies before dependents. When only cycles remain, the earliest
+/// remaining task in input order goes next regardless.
+fn topological_order(tasks: &[Task], index: &HashMap<&str, usize>) -> Vec<usize> {
+    let mut placed = vec![false; tasks.len()];
+    let mut order = Vec::with_capacity(tasks.len());
+    while order.len() < tasks.len() {
+        let ready = (0..tasks.len()).find(|&task| {
+            !placed[task]
+                && tasks[task].depends_on.iter().all(|dep| {
+                    index
+                        .get(dep.as_str())
+                        .is_none_or(|&dep| dep == task || placed[dep])
+                })
+        });
+        let next = ready.unwrap_or_else(|| {
+            (0..tasks.len())
+                .find(|&task| !placed[task])
+                .expect("an unplaced task remains")
+        });
+        placed[next] = true;
+        order.push(next);
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, likely: i32, depends_on: &[&str]) -> Task {
+        Task {
+            id: id.to_string(),
+            likely_minutes: Some(likely),
+            depends_on: depends_on.iter().map(|dep| dep.to_string()).collect(),
+            ..Task::default()
+        }
+    }
+
+    #[test]
+    fn schedules_after_dependencies_and_finds_the_critical_path() {
+        assert_eq!(expected_minutes(Some(30), Some(60), Some(150)), 70);
+        assert_eq!(expected_minutes(None, None, None), DEFAULT_MINUTES);
+        assert_eq!(expected_minutes(Some(20), None, Some(40)), 30);
+
+        let now = 1_700_000_000;
+        let tasks = vec![
+            task("design", 120, &[]),
+            task("build", 240, &["design"]),
+            task("docs", 60, &["design"]),
+            task("ship", 30, &["build", "docs"]),
+            Task {
+                due_at: Some(now + 1800),
+                ..task("call", 60, &[])
+            },
+            Task {
+                fixed_end: Some(now - 600),
+                ..task("kickoff", 30, &[])
+            },
+        ];
+        let plan = schedule(&tasks, now);
+        let by_id = |id: &str| plan.iter().find(|task| task.id == id).unwrap();
+
+        assert_eq!(by_id("design").start, now);
+        assert_eq!(by_id("build").start, now + 7200);
+        assert_eq!(by_id("ship").start, now + 7200 + 14_400);
+        assert!(by_id("design").critical && by_id("build").critical && by_id("ship").critical);
+        assert!(!by_id("docs").critical);
+        assert_eq!(by_id("docs").slack_seconds, 10_800);
+
+        let call = by_id("call");
+        assert!(call.late && call.critical);
+        assert_eq!(call.slack_seconds, -1800);
+        let kickoff = by_id("kickoff");
+        assert_eq!((kickoff.start, kickoff.end), (now - 2400, now - 600));
+        assert!(!kickoff.critical);
+    }
+
+    #[test]
+    fn cycles_do_not_stall_the_schedule() {
+        let now = 1_700_000_000;
+        let plan = schedule(&[task("a", 60, &["b"]), task("b", 60, &["a"])], now);
+        assert_eq!(plan[0].start, now);
+        assert_eq!(plan[1].start, now + 3600);
+    }
+}
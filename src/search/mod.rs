@@ -0,0 +1,687 @@
+//! Full-text search across todos, reminders, plans and chat history.
+//!
+//! The stores live in separate databases (and may be configured onto
+//! different files), so nothing can keep one index current with triggers.
+//! Instead each search first syncs the user's rows in the `work_search` FTS5
+//! table against what the stores hold, comparing content digests, and then
+//! queries it with bm25 ranking.
+//!
+//! Users with an encryption domain are never indexed: the index would be a
+//! readable copy of their sealed text. Their unlocked items are matched in
+//! memory instead, and any rows indexed before they enrolled are dropped.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer, Text};
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::db::{DbHandle, SqliteAsyncConn, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use crate::planning::PlanStore;
+use crate::reminders::{ReminderStatus, ReminderStore};
+use crate::security::user_domains::{self, DomainStatus};
+use crate::todo::{TodoStatus, TodoStore};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const WORK_SEARCH_UP_SQL: &str =
+    include_str!("../../migrations/20260313_create_work_search/up.sql");
+/// Per store; enough for a personal backlog without loading everything.
+const MAX_DOCUMENTS_PER_KIND: usize = 5000;
+const SNIPPET_TOKENS: i64 = 12;
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "for", "from", "in", "is", "it", "of", "on", "or", "that",
+    "the", "thing", "this", "to", "was", "what", "with",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchKind {
+    Todo,
+    Reminder,
+    Plan,
+    Chat,
+}
+
+impl SearchKind {
+    pub fn all() -> [SearchKind; 4] {
+        [
+            SearchKind::Todo,
+            SearchKind::Reminder,
+            SearchKind::Plan,
+            SearchKind::Chat,
+        ]
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SearchKind::Todo => "todo",
+            SearchKind::Reminder => "reminder",
+            SearchKind::Plan => "plan",
+            SearchKind::Chat => "chat",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "todo" | "todos" => Some(SearchKind::Todo),
+            "reminder" | "reminders" => Some(SearchKind::Reminder),
+            "plan" | "plans" => Some(SearchKind::Plan),
+            "chat" | "history" | "message" | "messages" => Some(SearchKind::Chat),
+            _ => None,
+        }
+    }
+
+    /// Parses a comma-separated list; empty or unrecognised input means all.
+    pub fn parse_list(value: Option<&str>) -> Vec<SearchKind> {
+        let kinds = value
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(SearchKind::parse)
+            .fold(Vec::new(), |mut kinds, kind| {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+                kinds
+            });
+        if kinds.is_empty() {
+            SearchKind::all().to_vec()
+        } else {
+            kinds
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchDocument {
+    pub kind: SearchKind,
+    pub item_id: i32,
+    pub title: String,
+    pub body: String,
+    pub updated_at: i64,
+}
+
+impl SearchDocument {
+    fn digest(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.title.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(self.body.as_bytes());
+        format!("{:x}", hasher.finalize())[..16].to_string()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchHit {
+    pub kind: SearchKind,
+    pub item_id: i32,
+    /// Inbox origin ref (`todo:3`, `plan:7`) or `chat:<message id>`.
+    pub origin_ref: String,
+    pub title: String,
+    /// Matching excerpt with hits wrapped in `[` `]`.
+    pub snippet: String,
+    pub updated_at: i64,
+    /// Lower is better (bm25).
+    pub rank: f64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SyncStats {
+    pub inserted: usize,
+    pub removed: usize,
+}
+
+/// Where the searchable stores live.
+#[derive(Clone, Debug)]
+pub struct SearchSources {
+    pub todo_db_path: String,
+    pub reminder_db_path: String,
+    pub plan_db_path: String,
+    pub memory_db_path: String,
+}
+
+#[derive(QueryableByName)]
+struct IndexedRow {
+    #[diesel(sql_type = Text)]
+    kind: String,
+    #[diesel(sql_type = Integer)]
+    item_id: i32,
+    #[diesel(sql_type = Text)]
+    digest: String,
+}
+
+#[derive(QueryableByName)]
+struct HitRow {
+    #[diesel(sql_type = Text)]
+    kind: String,
+    #[diesel(sql_type = Integer)]
+    item_id: i32,
+    #[diesel(sql_type = Text)]
+    title: String,
+    #[diesel(sql_type = Text)]
+    snippet: String,
+    #[diesel(sql_type = BigInt)]
+    updated_at: i64,
+    #[diesel(sql_type = Double)]
+    rank: f64,
+}
+
+#[derive(QueryableByName)]
+struct ResetRow {
+    #[diesel(sql_type = BigInt)]
+    reset_at: i64,
+}
+
+#[derive(QueryableByName)]
+struct MessageRow {
+    #[diesel(sql_type = Integer)]
+    id: i32,
+    #[diesel(sql_type = Text)]
+    role: String,
+    #[diesel(sql_type = Text)]
+    content: String,
+    #[diesel(sql_type = BigInt)]
+    timestamp: i64,
+}
+
+pub struct SearchIndex {
+    db: DbHandle,
+}
+
+impl SearchIndex {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_work_search_table(sqlite_path).await?;
+        Ok(Self { db })
+    }
+
+    /// Makes the user's indexed rows match `documents`: rows whose item is
+    /// gone or whose text changed are replaced.
+    pub async fn sync_user(
+        &self,
+        user_id: &str,
+        documents: &[SearchDocument],
+    ) -> Result<SyncStats> {
+        let mut conn = self.write_conn().await?;
+        let indexed: Vec<IndexedRow> = diesel::sql_query(
+            "SELECT kind, CAST(item_id AS INTEGER) AS item_id, digest \
+             FROM work_search WHERE user_id = ?",
+        )
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let mut indexed = indexed
+            .into_iter()
+            .map(|row| ((row.kind, row.item_id), row.digest))
+            .collect::<HashMap<_, _>>();
+
+        let mut stats = SyncStats::default();
+        for document in documents {
+            let key = (document.kind.as_str().to_string(), document.item_id);
+            let digest = document.digest();
+            match indexed.remove(&key) {
+                Some(existing) if existing == digest => continue,
+                Some(_) => {
+                    delete_row(&mut conn, user_id, &key.0, key.1).await?;
+                    stats.removed += 1;
+                }
+                None => {}
+            }
+            diesel::sql_query(
+                "INSERT INTO work_search (title, body, user_id, kind, item_id, updated_at, digest) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind::<Text, _>(&document.title)
+            .bind::<Text, _>(&document.body)
+            .bind::<Text, _>(user_id)
+            .bind::<Text, _>(document.kind.as_str())
+            .bind::<Integer, _>(document.item_id)
+            .bind::<BigInt, _>(document.updated_at)
+            .bind::<Text, _>(&digest)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            stats.inserted += 1;
+        }
+        for (kind, item_id) in indexed.into_keys() {
+            delete_row(&mut conn, user_id, &kind, item_id).await?;
+            stats.removed += 1;
+        }
+        Ok(stats)
+    }
+
+    pub async fn search(
+        &self,
+        user_id: &str,
+        query: &str,
+        kinds: &[SearchKind],
+        limit: usize,
+    ) -> Result<Vec<SearchHit>> {
+        let Some(query) = fts_query(query) else {
+            return Ok(Vec::new());
+        };
+        if kinds.is_empty() {
+            return Ok(Vec::new());
+        }
+        let kinds = kinds
+            .iter()
+            .map(|kind| format!("'{}'", kind.as_str()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut conn = self.conn().await?;
+        let rows: Vec<HitRow> = diesel::sql_query(format!(
+            "SELECT kind, CAST(item_id AS INTEGER) AS item_id, title, \
+             snippet(work_search, -1, '[', ']', '…', {SNIPPET_TOKENS}) AS snippet, \
+             CAST(updated_at AS INTEGER) AS updated_at, bm25(work_search, 4.0, 1.0) AS rank \
+             FROM work_search WHERE work_search MATCH ? AND user_id = ? AND kind IN ({kinds}) \
+             ORDER BY rank, updated_at DESC LIMIT ?"
+        ))
+        .bind::<Text, _>(query)
+        .bind::<Text, _>(user_id)
+        .bind::<BigInt, _>(limit.max(1) as i64)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let kind = SearchKind::parse(&row.kind)?;
+                Some(SearchHit {
+                    kind,
+                    item_id: row.item_id,
+                    origin_ref: format!("{}:{}", kind.as_str(), row.item_id),
+                    title: row.title,
+                    snippet: row.snippet,
+                    updated_at: row.updated_at,
+                    rank: row.rank,
+                })
+            })
+            .collect())
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::sql_query("DELETE FROM work_search WHERE user_id = ?")
+            .bind::<Text, _>(user_id)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SearchOutcome {
+    pub hits: Vec<SearchHit>,
+    /// False when the user's content is sealed and was matched in memory.
+    pub indexed: bool,
+}
+
+/// Syncs the index from `sources` and searches it, or falls back to an
+/// in-memory match for users with an encryption domain.
+pub async fn search_everything(
+    index: &SearchIndex,
+    sources: &SearchSources,
+    user_id: &str,
+    query: &str,
+    kinds: &[SearchKind],
+    limit: usize,
+) -> Result<SearchOutcome> {
+    match user_domains::status(user_id) {
+        DomainStatus::None => {
+            let documents = load_documents(sources, user_id).await?;
+            index.sync_user(user_id, &documents).await?;
+            Ok(SearchOutcome {
+                hits: index.search(user_id, query, kinds, limit).await?,
+                indexed: true,
+            })
+        }
+        DomainStatus::Locked => {
+            index.clear_user(user_id).await?;
+            Ok(SearchOutcome {
+                hits: Vec::new(),
+                indexed: false,
+            })
+        }
+        DomainStatus::Unlocked => {
+            index.clear_user(user_id).await?;
+            let documents = load_documents(sources, user_id).await?;
+            Ok(SearchOutcome {
+                hits: scan_documents(&documents, query, kinds, limit),
+                indexed: false,
+            })
+        }
+    }
+}
+
+/// Everything searchable for `user_id`, read through the stores so sealed
+/// columns come back opened.
+pub async fn load_documents(sources: &SearchSources, user_id: &str) -> Result<Vec<SearchDocument>> {
+    let mut documents = Vec::new();
+
+    let todos = TodoStore::new(&sources.todo_db_path)
+        .await?
+        .list_items(user_id, TodoStatus::All, MAX_DOCUMENTS_PER_KIND)
+        .await?;
+    documents.extend(todos.into_iter().map(|todo| SearchDocument {
+        kind: SearchKind::Todo,
+        item_id: todo.id,
+        title: todo.title,
+        body: todo.notes.unwrap_or_default(),
+        updated_at: todo.updated_at,
+    }));
+
+    let reminders = ReminderStore::new(&sources.reminder_db_path)
+        .await?
+        .list_reminders(user_id, ReminderStatus::All, MAX_DOCUMENTS_PER_KIND)
+        .await?;
+    documents.extend(reminders.into_iter().map(|reminder| SearchDocument {
+        kind: SearchKind::Reminder,
+        item_id: reminder.id,
+        title: reminder.title,
+        body: String::new(),
+        updated_at: reminder.completed_at.unwrap_or(reminder.created_at),
+    }));
+
+    let plans = PlanStore::new(&sources.plan_db_path)
+        .await?
+        .list_plans(user_id, MAX_DOCUMENTS_PER_KIND)
+        .await?;
+    documents.extend(plans.into_iter().map(|plan| {
+        let mut body = plan.goal;
+        for step in plan
+            .steps
+            .as_ref()
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(step_text)
+        {
+            body.push('\n');
+            body.push_str(&step);
+        }
+        SearchDocument {
+            kind: SearchKind::Plan,
+            item_id: plan.id,
+            title: plan.title,
+            body,
+            updated_at: plan.updated_at,
+        }
+    }));
+
+    documents.extend(load_chat_documents(&sources.memory_db_path, user_id).await?);
+    Ok(documents)
+}
+
+fn step_text(step: &Value) -> Option<String> {
+    if let Some(text) = step.as_str() {
+        return Some(text.trim().to_string()).filter(|text| !text.is_empty());
+    }
+    ["title", "description", "name", "text", "step"]
+        .iter()
+        .find_map(|key| step.get(*key).and_then(Value::as_str))
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
+
+/// User and assistant messages since the last history reset. A memory
+/// database that was never created simply has no chat to search.
+async fn load_chat_documents(memory_db_path: &str, user_id: &str) -> Result<Vec<SearchDocument>> {
+    if !Path::new(memory_db_path).exists() {
+        return Ok(Vec::new());
+    }
+    let database_url = memory_db_path.to_string();
+    let owner = user_id.to_string();
+    let rows = tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let reset_at = diesel::RunQueryDsl::load::<ResetRow>(
+            diesel::sql_query("SELECT reset_at FROM history_resets WHERE user_id = ?")
+                .bind::<Text, _>(&owner),
+            &mut conn,
+        )
+        .ok()
+        .and_then(|rows| rows.into_iter().next())
+        .map(|row| row.reset_at)
+        .unwrap_or(0);
+        let rows = diesel::RunQueryDsl::load::<MessageRow>(
+            diesel::sql_query(
+                "SELECT id, role, content, timestamp FROM messages \
+                 WHERE user_id = ? AND role IN ('user', 'assistant') AND timestamp > ? \
+                 ORDER BY id DESC LIMIT ?",
+            )
+            .bind::<Text, _>(&owner)
+            .bind::<BigInt, _>(reset_at)
+            .bind::<BigInt, _>(MAX_DOCUMENTS_PER_KIND as i64),
+            &mut conn,
+        );
+        match rows {
+            Ok(rows) => Ok(rows),
+            Err(err) if err.to_string().contains("no such table") => Ok(Vec::new()),
+            Err(err) => Err(ButterflyBotError::Runtime(err.to_string())),
+        }
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let content = user_domains::open(user_id, "message.content", row.content);
+            SearchDocument {
+                kind: SearchKind::Chat,
+                item_id: row.id,
+                title: format!(
+                    "{} message",
+                    if row.role == "user" {
+                        "You"
+                    } else {
+                        "Assistant"
+                    }
+                ),
+                body: content,
+                updated_at: row.timestamp,
+            }
+        })
+        .collect())
+}
+
+/// Search terms with stopwords dropped; empty when nothing is left.
+fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split(|ch: char| !ch.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|term| !term.is_empty() && !STOPWORDS.contains(&term.as_str()))
+        .collect()
+}
+
+/// Drops a plural ending so "taxes" and "tax" meet on the same prefix.
+fn stem(term: &str) -> &str {
+    term.strip_suffix("es")
+        .or_else(|| term.strip_suffix('s'))
+        .filter(|stem| stem.len() >= 3)
+        .unwrap_or(term)
+}
+
+/// Builds an FTS5 query that matches any term as a prefix, so "taxes" also
+/// finds "tax" and bm25 ranks items matching more terms first.
+pub fn fts_query(query: &str) -> Option<String> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return None;
+    }
+    Some(
+        terms
+            .iter()
+            .map(|term| format!("\"{}\"*", stem(term)))
+            .collect::<Vec<_>>()
+            .join(" OR "),
+    )
+}
+
+/// The in-memory counterpart of [`SearchIndex::search`] for sealed users:
+/// ranks by how many terms match, title matches counting double.
+pub fn scan_documents(
+    documents: &[SearchDocument],
+    query: &str,
+    kinds: &[SearchKind],
+    limit: usize,
+) -> Vec<SearchHit> {
+    let terms = query_terms(query);
+    if terms.is_empty() {
+        return Vec::new();
+    }
+    let mut hits = documents
+        .iter()
+        .filter(|document| kinds.contains(&document.kind))
+        .filter_map(|document| {
+            let title = document.title.to_lowercase();
+            let body = document.body.to_lowercase();
+            let score = terms
+                .iter()
+                .map(|term| {
+                    let stem = stem(term);
+                    2 * usize::from(title.contains(stem)) + usize::from(body.contains(stem))
+                })
+                .sum::<usize>();
+            (score > 0).then(|| SearchHit {
+                kind: document.kind,
+                item_id: document.item_id,
+                origin_ref: format!("{}:{}", document.kind.as_str(), document.item_id),
+                title: document.title.clone(),
+                snippet: document.body.chars().take(120).collect(),
+                updated_at: document.updated_at,
+                rank: -(score as f64),
+            })
+        })
+        .collect::<Vec<_>>();
+    hits.sort_by(|a, b| {
+        a.rank
+            .total_cmp(&b.rank)
+            .then(b.updated_at.cmp(&a.updated_at))
+    });
+    hits.truncate(limit.max(1));
+    hits
+}
+
+async fn delete_row(
+    conn: &mut SqliteAsyncConn,
+    user_id: &str,
+    kind: &str,
+    item_id: i32,
+) -> Result<()> {
+    diesel::sql_query("DELETE FROM work_search WHERE user_id = ? AND kind = ? AND item_id = ?")
+        .bind::<Text, _>(user_id)
+        .bind::<Text, _>(kind)
+        .bind::<Integer, _>(item_id)
+        .execute(conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(())
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_work_search_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM work_search LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, WORK_SEARCH_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(kind: SearchKind, item_id: i32, title: &str, body: &str) -> SearchDocument {
+        SearchDocument {
+            kind,
+            item_id,
+            title: title.to_string(),
+            body: body.to_string(),
+            updated_at: item_id as i64,
+        }
+    }
+
+    #[test]
+    fn queries_drop_stopwords_and_match_prefixes() {
+        assert_eq!(
+            fts_query("that thing about taxes").as_deref(),
+            Some("\"tax\"*")
+        );
+        assert_eq!(
+            fts_query("Dentist, invoices!").as_deref(),
+            Some("\"dentist\"* OR \"invoic\"*")
+        );
+        assert_eq!(fts_query("about the"), None);
+        assert_eq!(
+            SearchKind::parse_list(Some("plans, chat,bogus")),
+            vec![SearchKind::Plan, SearchKind::Chat]
+        );
+        assert_eq!(SearchKind::parse_list(None).len(), 4);
+    }
+
+    #[test]
+    fn scan_ranks_title_matches_first() {
+        let documents = vec![
+            doc(SearchKind::Todo, 1, "Buy milk", "also check the tax forms"),
+            doc(SearchKind::Plan, 2, "File taxes", "gather receipts"),
+            doc(SearchKind::Reminder, 3, "Call mum", ""),
+        ];
+        let hits = scan_documents(&documents, "that thing about taxes", &SearchKind::all(), 10);
+        assert_eq!(
+            hits.iter()
+                .map(|hit| hit.origin_ref.as_str())
+                .collect::<Vec<_>>(),
+            vec!["plan:2", "todo:1"]
+        );
+        let todos_only = scan_documents(&documents, "tax", &[SearchKind::Todo], 10);
+        assert_eq!(todos_only.len(), 1);
+    }
+}
@@ -7,5 +7,7 @@ pub mod secret_policy;
 pub mod signer_daemon;
 pub mod solana_rpc_policy;
 pub mod solana_signer;
+pub mod tokens;
 pub mod tpm_provider;
+pub mod user_domains;
 pub mod x402;
@@ -0,0 +1,20 @@
+use subtle::ConstantTimeEq;
+
+/// Compares a presented token with the expected secret in constant time,
+/// so a mismatch does not leak where the two differ.
+pub fn tokens_match(expected: &str, presented: &str) -> bool {
+    expected.as_bytes().ct_eq(presented.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tokens_match;
+
+    #[test]
+    fn only_identical_tokens_match() {
+        assert!(tokens_match("s3cret-token", "s3cret-token"));
+        assert!(!tokens_match("s3cret-token", "s3cret-tokeX"));
+        assert!(!tokens_match("s3cret-token", "s3cret"));
+        assert!(!tokens_match("s3cret-token", ""));
+    }
+}
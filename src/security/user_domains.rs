@@ -0,0 +1,456 @@
+//! Per-user encryption domains inside a shared database.
+//!
+//! SQLCipher protects the file as a whole, so anyone holding the database key
+//! can read every member's rows. A user who enrolls gets a random content key
+//! that is only stored wrapped under their passphrase; stores seal titles,
+//! notes and chat text with it before writing and open them on read. Until the
+//! user unlocks their domain in this process, their sealed columns read back as
+//! [`SEALED_PLACEHOLDER`] and writes of new content are refused rather than
+//! stored in the clear.
+//!
+//! Sealed values use a fresh nonce each time, so exact-match lookups and
+//! full-text search never match sealed columns.
+
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use cocoon::Cocoon;
+use diesel::sql_types::{BigInt, Binary, Text};
+use diesel::QueryableByName;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use rand::rngs::SysRng;
+use rand::TryRng;
+use serde::Serialize;
+use zeroize::Zeroizing;
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const USER_DOMAINS_UP_SQL: &str =
+    include_str!("../../migrations/20260310_create_user_encryption_domains/up.sql");
+
+/// Prefix of a sealed column value: `ud1:` + base64(nonce ‖ ciphertext).
+pub const SEALED_PREFIX: &str = "ud1:";
+/// What a sealed value reads as while its owner's domain is locked.
+pub const SEALED_PLACEHOLDER: &str = "[encrypted]";
+
+const NONCE_LEN: usize = 24;
+
+type ContentKey = Zeroizing<[u8; 32]>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DomainStatus {
+    /// The user never enrolled; their content is stored as-is.
+    None,
+    Locked,
+    Unlocked,
+}
+
+/// Enrolled users, with their content key while unlocked.
+fn keyring() -> &'static RwLock<HashMap<String, Option<ContentKey>>> {
+    static KEYRING: OnceLock<RwLock<HashMap<String, Option<ContentKey>>>> = OnceLock::new();
+    KEYRING.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+fn with_key<T>(user_id: &str, f: impl FnOnce(Option<Option<&ContentKey>>) -> T) -> T {
+    let guard = keyring()
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner());
+    f(guard.get(user_id).map(|key| key.as_ref()))
+}
+
+fn set_entry(user_id: &str, key: Option<ContentKey>) {
+    let mut guard = keyring()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    guard.insert(user_id.to_string(), key);
+}
+
+pub fn status(user_id: &str) -> DomainStatus {
+    with_key(user_id, |entry| match entry {
+        None => DomainStatus::None,
+        Some(None) => DomainStatus::Locked,
+        Some(Some(_)) => DomainStatus::Unlocked,
+    })
+}
+
+/// Forgets the user's content key; their sealed columns stay unreadable
+/// until they unlock again.
+pub fn lock(user_id: &str) {
+    let mut guard = keyring()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner());
+    if let Some(entry) = guard.get_mut(user_id) {
+        *entry = None;
+    }
+}
+
+pub fn is_sealed(value: &str) -> bool {
+    value.starts_with(SEALED_PREFIX)
+}
+
+/// `field` is bound into the ciphertext so a sealed title cannot be passed
+/// off as another user's note.
+fn aad(user_id: &str, field: &str) -> Vec<u8> {
+    format!("{user_id}\u{0}{field}").into_bytes()
+}
+
+/// Seals `plaintext` for storage in `field`. Users without a domain get the
+/// value back unchanged; a locked domain refuses the write.
+pub fn seal(user_id: &str, field: &str, plaintext: &str) -> Result<String> {
+    with_key(user_id, |entry| match entry {
+        None => Ok(plaintext.to_string()),
+        Some(None) => Err(locked_error(user_id)),
+        Some(Some(key)) => seal_with(key, user_id, field, plaintext),
+    })
+}
+
+pub fn seal_opt(user_id: &str, field: &str, plaintext: Option<&str>) -> Result<Option<String>> {
+    plaintext
+        .map(|value| seal(user_id, field, value))
+        .transpose()
+}
+
+/// Reverses [`seal`]. Plain values pass through; sealed ones read as
+/// [`SEALED_PLACEHOLDER`] when the domain is locked or the value does not
+/// authenticate.
+pub fn open(user_id: &str, field: &str, stored: String) -> String {
+    if !is_sealed(&stored) {
+        return stored;
+    }
+    with_key(user_id, |entry| match entry {
+        Some(Some(key)) => open_with(key, user_id, field, &stored),
+        _ => None,
+    })
+    .unwrap_or_else(|| SEALED_PLACEHOLDER.to_string())
+}
+
+pub fn open_opt(user_id: &str, field: &str, stored: Option<String>) -> Option<String> {
+    stored.map(|value| open(user_id, field, value))
+}
+
+fn locked_error(user_id: &str) -> ButterflyBotError {
+    ButterflyBotError::SecurityPolicy(format!(
+        "Encryption domain for '{user_id}' is locked; unlock it before adding content"
+    ))
+}
+
+fn seal_with(key: &ContentKey, user_id: &str, field: &str, plaintext: &str) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    let mut rng = SysRng;
+    rng.try_fill_bytes(&mut nonce)
+        .map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let aad = aad(user_id, field);
+    let ciphertext = cipher
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad: &aad,
+            },
+        )
+        .map_err(|_| ButterflyBotError::SecurityStorage("failed to seal field".to_string()))?;
+    let mut blob = nonce.to_vec();
+    blob.extend_from_slice(&ciphertext);
+    Ok(format!(
+        "{SEALED_PREFIX}{}",
+        general_purpose::STANDARD.encode(blob)
+    ))
+}
+
+fn open_with(key: &ContentKey, user_id: &str, field: &str, stored: &str) -> Option<String> {
+    let blob = general_purpose::STANDARD
+        .decode(stored.strip_prefix(SEALED_PREFIX)?)
+        .ok()?;
+    if blob.len() <= NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let aad = aad(user_id, field);
+    let plaintext = cipher
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: &aad,
+            },
+        )
+        .ok()?;
+    String::from_utf8(plaintext).ok()
+}
+
+#[derive(QueryableByName)]
+struct DomainRow {
+    #[diesel(sql_type = Text)]
+    user_id: String,
+    #[diesel(sql_type = Binary)]
+    wrapped_key: Vec<u8>,
+}
+
+/// Wrapped content keys, one per enrolled user.
+pub struct UserDomainStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl UserDomainStore {
+    /// Opens the store and registers every enrolled user as locked, so
+    /// their content is never written in the clear before they unlock.
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_user_domains_table(sqlite_path).await?;
+        let store = Self {
+            db,
+            clock: system_clock(),
+        };
+        for user_id in store.enrolled_users().await? {
+            if status(&user_id) == DomainStatus::None {
+                set_entry(&user_id, None);
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Creates the user's domain and leaves it unlocked. Content written
+    /// before enrolling stays readable as plaintext.
+    pub async fn enroll(&self, user_id: &str, passphrase: &str) -> Result<()> {
+        validate_passphrase(passphrase)?;
+        if self.load_wrapped(user_id).await?.is_some() {
+            return Err(ButterflyBotError::Config(format!(
+                "Encryption domain for '{user_id}' already exists"
+            )));
+        }
+        let mut key: ContentKey = Zeroizing::new([0u8; 32]);
+        let mut rng = SysRng;
+        rng.try_fill_bytes(key.as_mut_slice())
+            .map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+        let wrapped = wrap_key(&key, passphrase)?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::sql_query(
+            "INSERT INTO user_encryption_domains (user_id, wrapped_key, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?3)",
+        )
+        .bind::<Text, _>(user_id)
+        .bind::<Binary, _>(wrapped)
+        .bind::<BigInt, _>(now)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        set_entry(user_id, Some(key));
+        Ok(())
+    }
+
+    pub async fn unlock(&self, user_id: &str, passphrase: &str) -> Result<()> {
+        let wrapped = self.load_wrapped(user_id).await?.ok_or_else(|| {
+            ButterflyBotError::Config(format!("No encryption domain for '{user_id}'"))
+        })?;
+        let key = unwrap_key(&wrapped, passphrase)?;
+        set_entry(user_id, Some(key));
+        Ok(())
+    }
+
+    /// Re-wraps the same content key, so existing sealed rows stay readable.
+    pub async fn change_passphrase(&self, user_id: &str, current: &str, next: &str) -> Result<()> {
+        validate_passphrase(next)?;
+        let wrapped = self.load_wrapped(user_id).await?.ok_or_else(|| {
+            ButterflyBotError::Config(format!("No encryption domain for '{user_id}'"))
+        })?;
+        let key = unwrap_key(&wrapped, current)?;
+        let rewrapped = wrap_key(&key, next)?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::sql_query(
+            "UPDATE user_encryption_domains SET wrapped_key = ?2, updated_at = ?3
+             WHERE user_id = ?1",
+        )
+        .bind::<Text, _>(user_id)
+        .bind::<Binary, _>(rewrapped)
+        .bind::<BigInt, _>(now)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        set_entry(user_id, Some(key));
+        Ok(())
+    }
+
+    async fn enrolled_users(&self) -> Result<Vec<String>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<DomainRow> =
+            diesel::sql_query("SELECT user_id, wrapped_key FROM user_encryption_domains")
+                .load(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(|row| row.user_id).collect())
+    }
+
+    async fn load_wrapped(&self, user_id: &str) -> Result<Option<Vec<u8>>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<DomainRow> = diesel::sql_query(
+            "SELECT user_id, wrapped_key FROM user_encryption_domains WHERE user_id = ?1",
+        )
+        .bind::<Text, _>(user_id)
+        .load(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().next().map(|row| row.wrapped_key))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn validate_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < 8 {
+        return Err(ButterflyBotError::Config(
+            "Encryption passphrase must be at least 8 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn wrap_key(key: &ContentKey, passphrase: &str) -> Result<Vec<u8>> {
+    let mut cocoon = Cocoon::new(passphrase.as_bytes());
+    cocoon
+        .wrap(key.as_slice())
+        .map_err(|e| ButterflyBotError::SecurityStorage(format!("failed to wrap key: {e:?}")))
+}
+
+fn unwrap_key(wrapped: &[u8], passphrase: &str) -> Result<ContentKey> {
+    let cocoon = Cocoon::new(passphrase.as_bytes());
+    let raw = Zeroizing::new(cocoon.unwrap(wrapped).map_err(|_| {
+        ButterflyBotError::SecurityPolicy("Wrong encryption passphrase".to_string())
+    })?);
+    let bytes: [u8; 32] = raw.as_slice().try_into().map_err(|_| {
+        ButterflyBotError::SecurityStorage("wrapped key has the wrong length".to_string())
+    })?;
+    Ok(Zeroizing::new(bytes))
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_user_domains_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM user_encryption_domains LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, USER_DOMAINS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn sealed_fields_need_the_owners_passphrase() {
+        let temp = tempdir().unwrap();
+        let db_path = temp.path().join("domains.db");
+        let store = UserDomainStore::new(db_path.to_str().unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(seal("domain-plain", "todo.title", "hi").unwrap(), "hi");
+
+        store
+            .enroll("domain-alice", "correct horse battery")
+            .await
+            .unwrap();
+        let sealed = seal("domain-alice", "todo.title", "Dentist at 3").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("Dentist"));
+        assert_eq!(
+            open("domain-alice", "todo.title", sealed.clone()),
+            "Dentist at 3"
+        );
+        // Bound to user and field: neither can be swapped.
+        assert_eq!(
+            open("domain-alice", "todo.notes", sealed.clone()),
+            SEALED_PLACEHOLDER
+        );
+        assert_eq!(
+            open("domain-plain", "todo.title", sealed.clone()),
+            SEALED_PLACEHOLDER
+        );
+
+        lock("domain-alice");
+        assert_eq!(status("domain-alice"), DomainStatus::Locked);
+        assert_eq!(
+            open("domain-alice", "todo.title", sealed.clone()),
+            SEALED_PLACEHOLDER
+        );
+        assert!(seal("domain-alice", "todo.title", "new").is_err());
+
+        assert!(store
+            .unlock("domain-alice", "wrong passphrase")
+            .await
+            .is_err());
+        store
+            .change_passphrase("domain-alice", "correct horse battery", "staple forever")
+            .await
+            .unwrap();
+        lock("domain-alice");
+        store
+            .unlock("domain-alice", "staple forever")
+            .await
+            .unwrap();
+        assert_eq!(open("domain-alice", "todo.title", sealed), "Dentist at 3");
+    }
+}
@@ -0,0 +1,425 @@
+//! `service install|start|stop|status`: runs the daemon under the user's
+//! service manager — a systemd user unit on Linux, a launchd agent on
+//! macOS — so it stays up without the desktop UI. The UI finds a healthy
+//! daemon at the configured address and uses it instead of spawning its
+//! own.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{ButterflyBotError, Result};
+
+pub const SYSTEMD_UNIT: &str = "butterfly-botd.service";
+pub const LAUNCHD_LABEL: &str = "com.butterfly-bot.daemon";
+/// Forwarded into the service so it resolves data paths like this shell.
+const FORWARDED_ENV: [&str; 2] = ["BUTTERFLY_BOT_APP_ROOT", "BUTTERFLY_BOT_WASM_DIR"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceManager {
+    Systemd,
+    Launchd,
+}
+
+impl ServiceManager {
+    pub fn detect() -> Result<Self> {
+        if cfg!(target_os = "linux") {
+            Ok(Self::Systemd)
+        } else if cfg!(target_os = "macos") {
+            Ok(Self::Launchd)
+        } else {
+            Err(ButterflyBotError::Config(
+                "Service management needs systemd (Linux) or launchd (macOS)".to_string(),
+            ))
+        }
+    }
+
+    /// Where the unit or plist is written.
+    pub fn definition_path(self) -> Result<PathBuf> {
+        let home = std::env::var("HOME")
+            .ok()
+            .filter(|home| !home.trim().is_empty())
+            .map(PathBuf::from)
+            .ok_or_else(|| ButterflyBotError::Config("HOME is not set".to_string()))?;
+        Ok(match self {
+            Self::Systemd => std::env::var("XDG_CONFIG_HOME")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".config"))
+                .join("systemd")
+                .join("user")
+                .join(SYSTEMD_UNIT),
+            Self::Launchd => home
+                .join("Library")
+                .join("LaunchAgents")
+                .join(format!("{LAUNCHD_LABEL}.plist")),
+        })
+    }
+}
+
+/// How the service runs the daemon.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServiceSpec {
+    pub binary: PathBuf,
+    pub host: String,
+    pub port: u16,
+    pub db_path: PathBuf,
+    pub working_dir: PathBuf,
+    pub log_path: PathBuf,
+    pub env: Vec<(String, String)>,
+}
+
+impl ServiceSpec {
+    /// The daemon next to this executable, serving `daemon_url` from `db`.
+    /// Relative paths are resolved now, since the service starts elsewhere.
+    pub fn for_current_install(daemon_url: &str, db: &str) -> Result<Self> {
+        let binary = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join("butterfly-botd")))
+            .filter(|binary| binary.exists())
+            .ok_or_else(|| {
+                ButterflyBotError::Config(
+                    "butterfly-botd was not found next to butterfly-bot".to_string(),
+                )
+            })?;
+        let working_dir = std::env::current_dir()
+            .map_err(|e| ButterflyBotError::Runtime(format!("No working directory: {e}")))?;
+        let db_path = working_dir.join(db);
+        let log_path = db_path
+            .parent()
+            .unwrap_or(&working_dir)
+            .join("butterfly-botd.log");
+        let (host, port) = parse_daemon_url(daemon_url)?;
+        let env = FORWARDED_ENV
+            .iter()
+            .filter_map(|name| {
+                std::env::var(name)
+                    .ok()
+                    .filter(|value| !value.trim().is_empty())
+                    .map(|value| (name.to_string(), value))
+            })
+            .collect();
+        Ok(Self {
+            binary,
+            host,
+            port,
+            db_path,
+            working_dir,
+            log_path,
+            env,
+        })
+    }
+
+    fn args(&self) -> Vec<String> {
+        vec![
+            self.binary.to_string_lossy().to_string(),
+            "--host".to_string(),
+            self.host.clone(),
+            "--port".to_string(),
+            self.port.to_string(),
+            "--db".to_string(),
+            self.db_path.to_string_lossy().to_string(),
+        ]
+    }
+}
+
+fn parse_daemon_url(daemon_url: &str) -> Result<(String, u16)> {
+    let url = reqwest::Url::parse(daemon_url)
+        .map_err(|e| ButterflyBotError::Config(format!("Invalid daemon URL {daemon_url}: {e}")))?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| ButterflyBotError::Config(format!("Daemon URL {daemon_url} has no host")))?;
+    Ok((
+        host.to_string(),
+        url.port_or_known_default().unwrap_or(7878),
+    ))
+}
+
+/// A systemd user unit that restarts the daemon if it exits.
+pub fn systemd_unit(spec: &ServiceSpec) -> String {
+    let exec = spec
+        .args()
+        .iter()
+        .map(|arg| systemd_quote(arg))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut unit = format!(
+        "[Unit]\n\
+         Description=Butterfly Bot daemon\n\
+         After=network-online.target\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={exec}\n\
+         WorkingDirectory={}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n",
+        systemd_quote(&spec.working_dir.to_string_lossy())
+    );
+    for (name, value) in &spec.env {
+        unit.push_str(&format!(
+            "Environment={}\n",
+            systemd_quote(&format!("{name}={value}"))
+        ));
+    }
+    unit.push_str("\n[Install]\nWantedBy=default.target\n");
+    unit
+}
+
+fn systemd_quote(value: &str) -> String {
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "/._-:=+@".contains(c))
+    {
+        value.to_string()
+    } else {
+        format!(
+            "\"{}\"",
+            value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%")
+        )
+    }
+}
+
+/// A launchd agent started at login and kept alive.
+pub fn launchd_plist(spec: &ServiceSpec) -> String {
+    let arguments = spec
+        .args()
+        .iter()
+        .map(|arg| format!("        <string>{}</string>\n", xml_escape(arg)))
+        .collect::<String>();
+    let env = if spec.env.is_empty() {
+        String::new()
+    } else {
+        let entries = spec
+            .env
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    "        <key>{}</key>\n        <string>{}</string>\n",
+                    xml_escape(name),
+                    xml_escape(value)
+                )
+            })
+            .collect::<String>();
+        format!("    <key>EnvironmentVariables</key>\n    <dict>\n{entries}    </dict>\n")
+    };
+    let log = xml_escape(&spec.log_path.to_string_lossy());
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+         \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+         <plist version=\"1.0\">\n\
+         <dict>\n\
+         \x20   <key>Label</key>\n\
+         \x20   <string>{LAUNCHD_LABEL}</string>\n\
+         \x20   <key>ProgramArguments</key>\n\
+         \x20   <array>\n\
+         {arguments}\
+         \x20   </array>\n\
+         \x20   <key>WorkingDirectory</key>\n\
+         \x20   <string>{}</string>\n\
+         {env}\
+         \x20   <key>RunAtLoad</key>\n\
+         \x20   <true/>\n\
+         \x20   <key>KeepAlive</key>\n\
+         \x20   <dict>\n\
+         \x20       <key>SuccessfulExit</key>\n\
+         \x20       <false/>\n\
+         \x20   </dict>\n\
+         \x20   <key>StandardOutPath</key>\n\
+         \x20   <string>{log}</string>\n\
+         \x20   <key>StandardErrorPath</key>\n\
+         \x20   <string>{log}</string>\n\
+         </dict>\n\
+         </plist>\n",
+        xml_escape(&spec.working_dir.to_string_lossy())
+    )
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes the definition and registers it to start at login. Running it
+/// again rewrites the definition, e.g. after moving the install.
+pub fn install(manager: ServiceManager, spec: &ServiceSpec) -> Result<String> {
+    let path = manager.definition_path()?;
+    let contents = match manager {
+        ServiceManager::Systemd => systemd_unit(spec),
+        ServiceManager::Launchd => launchd_plist(spec),
+    };
+    write_definition(&path, &contents)?;
+    match manager {
+        ServiceManager::Systemd => {
+            run("systemctl", &["--user", "daemon-reload"])?;
+            run("systemctl", &["--user", "enable", SYSTEMD_UNIT])?;
+        }
+        ServiceManager::Launchd => {
+            // Replacing a loaded agent needs it unloaded first.
+            let _ = run("launchctl", &["bootout", &launchd_target()]);
+        }
+    }
+    Ok(format!(
+        "Installed {}; run `butterfly-bot service start` to start it now",
+        path.display()
+    ))
+}
+
+pub fn start(manager: ServiceManager) -> Result<String> {
+    match manager {
+        ServiceManager::Systemd => {
+            run("systemctl", &["--user", "start", SYSTEMD_UNIT])?;
+        }
+        ServiceManager::Launchd => {
+            let path = installed_definition(manager)?;
+            if run("launchctl", &["print", &launchd_target()]).is_err() {
+                run(
+                    "launchctl",
+                    &["bootstrap", &launchd_domain(), &path.to_string_lossy()],
+                )?;
+            }
+            run("launchctl", &["kickstart", &launchd_target()])?;
+        }
+    }
+    Ok("Daemon service started".to_string())
+}
+
+pub fn stop(manager: ServiceManager) -> Result<String> {
+    match manager {
+        ServiceManager::Systemd => {
+            run("systemctl", &["--user", "stop", SYSTEMD_UNIT])?;
+        }
+        // KeepAlive would restart a killed daemon, so the agent is unloaded;
+        // `start` loads it again.
+        ServiceManager::Launchd => {
+            run("launchctl", &["bootout", &launchd_target()])?;
+        }
+    }
+    Ok("Daemon service stopped".to_string())
+}
+
+/// The service manager's own report; an unloaded or stopped service is a
+/// status, not an error.
+pub fn status(manager: ServiceManager) -> Result<String> {
+    let path = manager.definition_path()?;
+    if !path.exists() {
+        return Ok("Daemon service is not installed".to_string());
+    }
+    let output = match manager {
+        ServiceManager::Systemd => Command::new("systemctl")
+            .args(["--user", "status", "--no-pager", SYSTEMD_UNIT])
+            .output(),
+        ServiceManager::Launchd => Command::new("launchctl")
+            .args(["print", &launchd_target()])
+            .output(),
+    }
+    .map_err(|e| ButterflyBotError::Runtime(format!("Could not query the service: {e}")))?;
+    let report = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if report.is_empty() {
+        return Ok(format!(
+            "Daemon service is installed at {} but not loaded",
+            path.display()
+        ));
+    }
+    Ok(report)
+}
+
+fn installed_definition(manager: ServiceManager) -> Result<PathBuf> {
+    let path = manager.definition_path()?;
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(ButterflyBotError::Config(
+            "Daemon service is not installed; run `butterfly-bot service install`".to_string(),
+        ))
+    }
+}
+
+fn write_definition(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", dir.display())))?;
+    }
+    std::fs::write(path, contents)
+        .map_err(|e| ButterflyBotError::Runtime(format!("{}: {e}", path.display())))
+}
+
+fn launchd_domain() -> String {
+    // SAFETY: getuid has no preconditions.
+    #[cfg(unix)]
+    let uid = unsafe { libc::getuid() };
+    #[cfg(not(unix))]
+    let uid = 0;
+    format!("gui/{uid}")
+}
+
+fn launchd_target() -> String {
+    format!("{}/{LAUNCHD_LABEL}", launchd_domain())
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| ButterflyBotError::Runtime(format!("Could not run {program}: {e}")))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    Err(ButterflyBotError::Runtime(format!(
+        "{program} {} failed: {}",
+        args.join(" "),
+        String::from_utf8_lossy(&output.stderr).trim()
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> ServiceSpec {
+        ServiceSpec {
+            binary: PathBuf::from("/opt/butterfly bot/butterfly-botd"),
+            host: "127.0.0.1".to_string(),
+            port: 7979,
+            db_path: PathBuf::from("/home/ada/data/butterfly-bot.db"),
+            working_dir: PathBuf::from("/home/ada"),
+            log_path: PathBuf::from("/home/ada/data/butterfly-botd.log"),
+            env: vec![(
+                "BUTTERFLY_BOT_APP_ROOT".to_string(),
+                "/home/ada".to_string(),
+            )],
+        }
+    }
+
+    #[test]
+    fn renders_unit_and_plist_for_the_daemon() {
+        let unit = systemd_unit(&spec());
+        assert!(unit.contains(
+            "ExecStart=\"/opt/butterfly bot/butterfly-botd\" --host 127.0.0.1 --port 7979 \
+             --db /home/ada/data/butterfly-bot.db\n"
+        ));
+        assert!(unit.contains("WorkingDirectory=/home/ada\n"));
+        assert!(unit.contains("Environment=BUTTERFLY_BOT_APP_ROOT=/home/ada\n"));
+        assert!(unit.ends_with("[Install]\nWantedBy=default.target\n"));
+
+        let plist = launchd_plist(&spec());
+        assert!(plist.contains("<string>com.butterfly-bot.daemon</string>"));
+        assert!(plist.contains("        <string>/opt/butterfly bot/butterfly-botd</string>\n"));
+        assert!(plist.contains("        <string>7979</string>\n"));
+        assert!(plist.contains("<key>BUTTERFLY_BOT_APP_ROOT</key>"));
+        assert!(plist.contains("<string>/home/ada/data/butterfly-botd.log</string>"));
+
+        assert_eq!(
+            parse_daemon_url("http://localhost:7979/").unwrap(),
+            ("localhost".to_string(), 7979)
+        );
+        assert!(parse_daemon_url("not a url").is_err());
+    }
+}
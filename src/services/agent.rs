@@ -9,9 +9,6 @@ use futures::StreamExt;
 use serde::Serialize;
 use serde_json::{json, Value};
 
-use once_cell::sync::Lazy;
-use regex::Regex;
-
 use tracing::{debug, error, info, warn};
 
 use crate::brain::manager::BrainManager;
@@ -21,10 +18,29 @@ use crate::factories::agent_factory::load_markdown_source;
 use crate::interfaces::brain::{BrainContext, BrainEvent};
 use crate::interfaces::providers::{LlmProvider, ToolCall};
 use crate::plugins::registry::ToolRegistry;
+use crate::redaction::{redact_json, redact_text, redact_value};
+use crate::roles::CapabilityAccess;
 use crate::security::x402::{canonicalize_payment_required, CanonicalX402Intent};
 use tokio::sync::broadcast;
 use tokio::sync::RwLock;
 
+tokio::task_local! {
+    /// Backend pinned to the conversation thread being served, if any.
+    static PINNED_LLM: Arc<dyn LlmProvider>;
+}
+
+/// Runs `fut` with chat generation routed to `provider` instead of the
+/// configured default. Audio transcription and speech stay on the default.
+pub async fn with_pinned_llm<F: std::future::Future>(
+    provider: Option<Arc<dyn LlmProvider>>,
+    fut: F,
+) -> F::Output {
+    match provider {
+        Some(provider) => PINNED_LLM.scope(provider, fut).await,
+        None => fut.await,
+    }
+}
+
 pub struct AgentService {
     llm_provider: Arc<dyn LlmProvider>,
     pub tool_registry: Arc<ToolRegistry>,
@@ -71,6 +87,12 @@ impl AgentService {
         }
     }
 
+    fn llm(&self) -> Arc<dyn LlmProvider> {
+        PINNED_LLM
+            .try_with(Arc::clone)
+            .unwrap_or_else(|_| self.llm_provider.clone())
+    }
+
     pub fn agent_name(&self) -> &str {
         &self.agent.name
     }
@@ -195,7 +217,13 @@ impl AgentService {
         }
     }
 
-    fn emit_tool_event(&self, user_id: &str, tool: &str, status: &str, payload: serde_json::Value) {
+    pub(crate) fn emit_tool_event(
+        &self,
+        user_id: &str,
+        tool: &str,
+        status: &str,
+        payload: serde_json::Value,
+    ) {
         let Some(sender) = &self.ui_event_tx else {
             return;
         };
@@ -425,7 +453,7 @@ impl AgentService {
 
         let tools = self.tool_registry.get_agent_tools(&self.agent.name).await;
         let output = if tools.is_empty() {
-            self.llm_provider
+            self.llm()
                 .generate_text(&full_prompt, &system_prompt, None)
                 .await?
         } else {
@@ -501,7 +529,8 @@ impl AgentService {
                 }
                 messages.push(json!({"role": "user", "content": full_prompt}));
 
-                let mut stream = self.llm_provider.chat_stream(messages, None);
+                let llm = self.llm();
+                let mut stream = llm.chat_stream(messages, None);
                 while let Some(event) = stream.next().await {
                     let event = event?;
                     if let Some(error) = event.error {
@@ -563,7 +592,7 @@ impl AgentService {
         self.append_runtime_identity_context(&mut full_prompt, user_id);
 
         let output = self
-            .llm_provider
+            .llm()
             .generate_text_with_images(&full_prompt, images, &system_prompt, detail, None)
             .await?;
         Ok(output)
@@ -599,7 +628,7 @@ impl AgentService {
         full_prompt.push_str(&format!("\n\nUSER IDENTIFIER: {}", user_id));
         self.append_runtime_identity_context(&mut full_prompt, user_id);
 
-        self.llm_provider
+        self.llm()
             .parse_structured_output(&full_prompt, &system_prompt, json_schema, None)
             .await
     }
@@ -876,7 +905,7 @@ impl AgentService {
 
         for _ in 0..20 {
             let response = self
-                .llm_provider
+                .llm()
                 .generate_with_tools(&prompt, system_prompt, tool_specs.clone())
                 .await?;
             if response.tool_calls.is_empty() && !response.text.is_empty() {
@@ -1102,7 +1131,7 @@ impl AgentService {
         x402_intent: &mut Option<CanonicalX402Intent>,
         x402_required: bool,
     ) -> Result<Vec<serde_json::Value>> {
-        let mut results = Vec::new();
+        let mut normalized = Vec::with_capacity(calls.len());
         for call in calls {
             let mut effective_name = normalize_tool_name(&call.name);
             let mut effective_args = call.arguments.clone();
@@ -1123,198 +1152,255 @@ impl AgentService {
 
             if effective_name == "solana" {
                 normalize_solana_action_and_aliases(&mut effective_args);
-                harden_solana_transfer_args(
-                    &mut effective_args,
-                    x402_intent.as_ref(),
-                    x402_required,
-                )?;
             }
+            normalized.push((effective_name, effective_args));
+        }
 
-            let tool = tools.iter().find(|t| t.name() == effective_name);
-            match tool {
-                Some(_tool) => {
-                    let redacted_args = redact_value(&effective_args);
-                    info!(
-                        tool = %effective_name,
-                        args = %serde_json::to_string(&redacted_args).unwrap_or_default(),
-                        "Tool call"
-                    );
-                    let mut args = effective_args.clone();
-                    if let serde_json::Value::Object(ref mut map) = args {
-                        if !map.contains_key("user_id") {
-                            map.insert(
-                                "user_id".to_string(),
-                                serde_json::Value::String(user_id.to_string()),
-                            );
-                        }
-                    }
-                    match self.tool_registry.execute_tool(&effective_name, args).await {
-                        Ok(result) => {
-                            let invalid_args_payload = result
-                                .get("status")
+        let parallel = self.tool_registry.parallel_tool_calls().await;
+        let mut results = Vec::new();
+        let mut pending = normalized.as_slice();
+        while !pending.is_empty() {
+            // A run of read-only calls executes together; anything that may
+            // write runs alone so later calls see its effects and the x402
+            // intent it captures.
+            let batch_len = pending
+                .iter()
+                .take_while(|(name, args)| {
+                    crate::roles::tool_call_access(name, args) == CapabilityAccess::Read
+                })
+                .count()
+                .max(1);
+            let (batch, rest) = pending.split_at(batch_len);
+            pending = rest;
+
+            let mut prepared = Vec::with_capacity(batch.len());
+            for (effective_name, effective_args) in batch {
+                let mut effective_args = effective_args.clone();
+                if effective_name == "solana" {
+                    harden_solana_transfer_args(
+                        &mut effective_args,
+                        x402_intent.as_ref(),
+                        x402_required,
+                    )?;
+                }
+                prepared.push((effective_name.clone(), effective_args));
+            }
+
+            let outcomes = futures::stream::iter(
+                prepared
+                    .iter()
+                    .map(|(name, args)| self.run_tool_call(name, args, tools, user_id)),
+            )
+            .buffered(parallel)
+            .collect::<Vec<_>>()
+            .await;
+
+            for ((effective_name, effective_args), outcome) in prepared.into_iter().zip(outcomes) {
+                let redacted_args = redact_value(&effective_args);
+                match outcome {
+                    Some(Ok(result)) => {
+                        let invalid_args_payload = result
+                            .get("status")
+                            .and_then(|v| v.as_str())
+                            .map(|v| v.eq_ignore_ascii_case("error"))
+                            .unwrap_or(false)
+                            && (result
+                                .get("code")
                                 .and_then(|v| v.as_str())
-                                .map(|v| v.eq_ignore_ascii_case("error"))
+                                .map(|v| v.eq_ignore_ascii_case("invalid_args"))
                                 .unwrap_or(false)
-                                && (result
-                                    .get("code")
-                                    .and_then(|v| v.as_str())
-                                    .map(|v| v.eq_ignore_ascii_case("invalid_args"))
-                                    .unwrap_or(false)
-                                    || result
-                                        .get("error")
-                                        .and_then(|v| v.as_str())
-                                        .map(|v| {
-                                            v.to_ascii_lowercase().contains("unsupported action")
-                                        })
-                                        .unwrap_or(false));
-
-                            if invalid_args_payload {
-                                let err_message = result
+                                || result
                                     .get("error")
                                     .and_then(|v| v.as_str())
-                                    .unwrap_or("invalid args")
-                                    .to_string();
-                                let _ = self
-                                    .tool_registry
-                                    .audit_tool_call(&effective_name, "skipped")
-                                    .await;
-                                info!(
-                                    tool = %effective_name,
-                                    status = "skipped",
-                                    error = %redact_string(&err_message),
-                                    "Tool result"
-                                );
-                                self.emit_tool_event(
-                                    user_id,
-                                    &effective_name,
-                                    "skipped",
-                                    serde_json::json!({ "args": redacted_args.clone(), "error": redact_string(&err_message) }),
-                                );
-                                results.push(serde_json::json!({
-                                    "tool": effective_name,
-                                    "status": "skipped",
-                                    "error": err_message,
-                                }));
-                                continue;
-                            }
-
-                            if effective_name == "http_call" {
-                                maybe_capture_x402_intent_from_http_result(
-                                    x402_intent,
-                                    &result,
-                                    user_id,
-                                );
-                            }
+                                    .map(|v| v.to_ascii_lowercase().contains("unsupported action"))
+                                    .unwrap_or(false));
 
+                        if invalid_args_payload {
+                            let err_message = result
+                                .get("error")
+                                .and_then(|v| v.as_str())
+                                .unwrap_or("invalid args")
+                                .to_string();
                             let _ = self
                                 .tool_registry
-                                .audit_tool_call(&effective_name, "success")
+                                .audit_tool_call(&effective_name, "skipped")
                                 .await;
-                            let result_clone = result.clone();
-                            let redacted_result = redact_value(&result_clone);
                             info!(
                                 tool = %effective_name,
-                                status = "success",
-                                result = %serde_json::to_string(&redacted_result).unwrap_or_default(),
+                                status = "skipped",
+                                error = %redact_text(&err_message),
                                 "Tool result"
                             );
                             self.emit_tool_event(
                                 user_id,
                                 &effective_name,
-                                "success",
-                                serde_json::json!({ "args": redacted_args.clone(), "result": redacted_result }),
+                                "skipped",
+                                serde_json::json!({
+                                    "args": redacted_args.clone(),
+                                    "error": redact_text(&err_message),
+                                }),
                             );
                             results.push(serde_json::json!({
                                 "tool": effective_name,
-                                "status": "success",
-                                "result": result,
+                                "status": "skipped",
+                                "error": redact_text(&err_message),
                             }));
+                            continue;
+                        }
+
+                        if effective_name == "http_call" {
+                            maybe_capture_x402_intent_from_http_result(
+                                x402_intent,
+                                &result,
+                                user_id,
+                            );
                         }
-                        Err(err) => {
-                            let err_message = err.to_string();
-                            let should_skip = matches!(err, ButterflyBotError::Runtime(_))
-                                && (err_message.contains("No MCP servers configured")
-                                    || err_message.contains("Unknown MCP server")
-                                    || err_message.contains("Missing GitHub PAT")
-                                    || err_message.contains("WASM module path does not exist")
-                                    || err_message.contains("returned a stub response")
-                                    || err_message.contains("WASM alloc failed")
-                                    || err_message.contains("WASM tool input too large")
-                                    || err_message.contains("WASM tool execute failed")
-                                    || err_message.contains("builder error for url")
-                                    || err_message.contains("relative URL without a base"));
-                            if should_skip {
-                                let _ = self
-                                    .tool_registry
-                                    .audit_tool_call(&effective_name, "skipped")
-                                    .await;
-                                info!(
-                                    tool = %effective_name,
-                                    status = "skipped",
-                                    error = %redact_string(&err_message),
-                                    "Tool result"
-                                );
-                                self.emit_tool_event(
-                                    user_id,
-                                    &effective_name,
-                                    "skipped",
-                                    serde_json::json!({ "args": redacted_args.clone(), "error": redact_string(&err_message) }),
-                                );
-                                results.push(serde_json::json!({
-                                    "tool": effective_name,
-                                    "status": "skipped",
-                                    "error": err_message,
-                                }));
-                                continue;
-                            }
 
+                        let _ = self
+                            .tool_registry
+                            .audit_tool_call(&effective_name, "success")
+                            .await;
+                        let result_clone = result.clone();
+                        let redacted_result = redact_value(&result_clone);
+                        info!(
+                            tool = %effective_name,
+                            status = "success",
+                            result = %serde_json::to_string(&redacted_result).unwrap_or_default(),
+                            "Tool result"
+                        );
+                        self.emit_tool_event(
+                            user_id,
+                            &effective_name,
+                            "success",
+                            serde_json::json!({
+                                "args": redacted_args.clone(),
+                                "result": redacted_result,
+                            }),
+                        );
+                        results.push(serde_json::json!({
+                            "tool": effective_name,
+                            "status": "success",
+                            "result": redact_json(&result),
+                        }));
+                    }
+                    Some(Err(err)) => {
+                        let err_message = err.to_string();
+                        let should_skip = matches!(err, ButterflyBotError::Runtime(_))
+                            && (err_message.contains("No MCP servers configured")
+                                || err_message.contains("Unknown MCP server")
+                                || err_message.contains("Missing GitHub PAT")
+                                || err_message.contains("WASM module path does not exist")
+                                || err_message.contains("returned a stub response")
+                                || err_message.contains("WASM alloc failed")
+                                || err_message.contains("WASM tool input too large")
+                                || err_message.contains("WASM tool execute failed")
+                                || err_message.contains("builder error for url")
+                                || err_message.contains("relative URL without a base"));
+                        if should_skip {
                             let _ = self
                                 .tool_registry
-                                .audit_tool_call(&effective_name, "error")
+                                .audit_tool_call(&effective_name, "skipped")
                                 .await;
                             info!(
                                 tool = %effective_name,
-                                status = "error",
-                                error = %redact_string(&err_message),
+                                status = "skipped",
+                                error = %redact_text(&err_message),
                                 "Tool result"
                             );
                             self.emit_tool_event(
                                 user_id,
                                 &effective_name,
-                                "error",
-                                serde_json::json!({ "args": redacted_args.clone(), "error": redact_string(&err.to_string()) }),
+                                "skipped",
+                                serde_json::json!({
+                                    "args": redacted_args.clone(),
+                                    "error": redact_text(&err_message),
+                                }),
                             );
-                            return Err(err);
+                            results.push(serde_json::json!({
+                                "tool": effective_name,
+                                "status": "skipped",
+                                "error": redact_text(&err_message),
+                            }));
+                            continue;
                         }
+
+                        let _ = self
+                            .tool_registry
+                            .audit_tool_call(&effective_name, "error")
+                            .await;
+                        info!(
+                            tool = %effective_name,
+                            status = "error",
+                            error = %redact_text(&err_message),
+                            "Tool result"
+                        );
+                        self.emit_tool_event(
+                            user_id,
+                            &effective_name,
+                            "error",
+                            serde_json::json!({
+                                "args": redacted_args.clone(),
+                                "error": redact_text(&err.to_string()),
+                            }),
+                        );
+                        return Err(err);
+                    }
+                    None => {
+                        let _ = self
+                            .tool_registry
+                            .audit_tool_call(&effective_name, "not_found")
+                            .await;
+                        info!(
+                            tool = %effective_name,
+                            status = "not_found",
+                            "Tool result"
+                        );
+                        self.emit_tool_event(
+                            user_id,
+                            &effective_name,
+                            "not_found",
+                            serde_json::json!({
+                                "args": redacted_args.clone(),
+                                "message": "Tool not found",
+                            }),
+                        );
+                        results.push(serde_json::json!({
+                            "tool": effective_name,
+                            "status": "error",
+                            "message": "Tool not found",
+                        }));
                     }
-                }
-                None => {
-                    let redacted_args = redact_value(&effective_args);
-                    let _ = self
-                        .tool_registry
-                        .audit_tool_call(&effective_name, "not_found")
-                        .await;
-                    info!(
-                        tool = %effective_name,
-                        status = "not_found",
-                        "Tool result"
-                    );
-                    self.emit_tool_event(
-                        user_id,
-                        &effective_name,
-                        "not_found",
-                        serde_json::json!({ "args": redacted_args.clone(), "message": "Tool not found" }),
-                    );
-                    results.push(serde_json::json!({
-                        "tool": effective_name,
-                        "status": "error",
-                        "message": "Tool not found",
-                    }));
                 }
             }
         }
         Ok(results)
     }
+
+    /// Runs one normalized call, or `None` when the agent has no such tool.
+    async fn run_tool_call(
+        &self,
+        effective_name: &str,
+        effective_args: &serde_json::Value,
+        tools: &[Arc<dyn crate::interfaces::plugins::Tool>],
+        user_id: &str,
+    ) -> Option<Result<serde_json::Value>> {
+        tools.iter().find(|t| t.name() == effective_name)?;
+        info!(
+            tool = %effective_name,
+            args = %serde_json::to_string(&redact_value(effective_args)).unwrap_or_default(),
+            "Tool call"
+        );
+        let mut args = effective_args.clone();
+        if let serde_json::Value::Object(ref mut map) = args {
+            if !map.contains_key("user_id") {
+                map.insert(
+                    "user_id".to_string(),
+                    serde_json::Value::String(user_id.to_string()),
+                );
+            }
+        }
+        Some(self.tool_registry.execute_tool(effective_name, args).await)
+    }
 }
 
 fn map_solana_alias_action(name: &str) -> Option<&'static str> {
@@ -1911,63 +1997,6 @@ fn x402_asset_is_native_sol(asset: &str) -> bool {
         || normalized.contains("solana")
 }
 
-static BEARER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\bbearer\s+[^\s\x22\x27]+").unwrap());
-static KEY_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"(?i)\b(sk-|xai-|github_pat_|ghp_|gho_|ghu_|ghs_|ghr_)[A-Za-z0-9_\-]+").unwrap()
-});
-
-fn redact_string(input: &str) -> String {
-    let mut out = BEARER_RE
-        .replace_all(input, "Bearer [REDACTED]")
-        .to_string();
-    out = KEY_RE.replace_all(&out, "$1[REDACTED]").to_string();
-    truncate_string(&out, 2000)
-}
-
-fn redact_value(value: &serde_json::Value) -> serde_json::Value {
-    match value {
-        serde_json::Value::Null => serde_json::Value::Null,
-        serde_json::Value::Bool(v) => serde_json::Value::Bool(*v),
-        serde_json::Value::Number(v) => serde_json::Value::Number(v.clone()),
-        serde_json::Value::String(v) => serde_json::Value::String(redact_string(v)),
-        serde_json::Value::Array(items) => serde_json::Value::Array(
-            items
-                .iter()
-                .map(redact_value)
-                .collect::<Vec<serde_json::Value>>(),
-        ),
-        serde_json::Value::Object(map) => {
-            let mut out = serde_json::Map::new();
-            for (key, value) in map {
-                if is_sensitive_key(key) {
-                    out.insert(
-                        key.clone(),
-                        serde_json::Value::String("[REDACTED]".to_string()),
-                    );
-                } else {
-                    out.insert(key.clone(), redact_value(value));
-                }
-            }
-            serde_json::Value::Object(out)
-        }
-    }
-}
-
-fn is_sensitive_key(key: &str) -> bool {
-    let lower = key.to_ascii_lowercase();
-    lower.contains("authorization")
-        || lower.contains("api_key")
-        || lower.contains("apikey")
-        || lower.contains("token")
-        || lower.contains("secret")
-        || lower.contains("password")
-        || lower.contains("pat")
-}
-
-fn truncate_string(value: &str, _max_len: usize) -> String {
-    value.to_string()
-}
-
 fn now_ts() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
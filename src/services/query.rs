@@ -8,12 +8,15 @@ use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 use md5::{Digest, Md5};
 
-use crate::error::Result;
-use crate::interfaces::providers::{ImageInput, MemoryProvider};
+use crate::error::{ButterflyBotError, Result};
+use crate::guardrails::data_scope::{DataCategory, DataScope};
+use crate::interfaces::providers::{ImageInput, MemoryHit, MemoryProvider};
+use crate::matrix::MatrixMirror;
+use crate::providers::retention::RetentionReport;
 use crate::reminders::ReminderStore;
 use crate::services::agent::AgentService;
 use crate::vault;
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(Debug, Clone)]
 pub enum UserInput {
@@ -51,6 +54,8 @@ pub struct QueryService {
     memory_provider: Option<Arc<dyn MemoryProvider>>,
     reminder_store: Option<Arc<ReminderStore>>,
     context_cache: tokio::sync::RwLock<Option<u64>>,
+    data_scope: DataScope,
+    matrix_mirror: Option<Arc<MatrixMirror>>,
 }
 
 impl QueryService {
@@ -64,9 +69,23 @@ impl QueryService {
             memory_provider,
             reminder_store,
             context_cache: tokio::sync::RwLock::new(None),
+            data_scope: DataScope::default(),
+            matrix_mirror: None,
         }
     }
 
+    /// Limits what context may be sent to the chat backends.
+    pub fn with_data_scope(mut self, data_scope: DataScope) -> Self {
+        self.data_scope = data_scope;
+        self
+    }
+
+    /// Mirrors chat and proactive messages into the users' Matrix rooms.
+    pub fn with_matrix_mirror(mut self, mirror: Option<Arc<MatrixMirror>>) -> Self {
+        self.matrix_mirror = mirror;
+        self
+    }
+
     async fn ensure_context_in_memory(&self, user_id: &str) -> Result<()> {
         let started = Instant::now();
         let Some(provider) = &self.memory_provider else {
@@ -136,14 +155,8 @@ impl QueryService {
             .try_handle_search_command(user_id, &processed_query)
             .await?
         {
-            if let Some(provider) = &self.memory_provider {
-                provider
-                    .append_message(user_id, "user", &processed_query)
-                    .await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &processed_query, Some(&response))
+                .await?;
             return Ok(response);
         }
 
@@ -151,14 +164,8 @@ impl QueryService {
             .try_handle_tasks_command(user_id, &processed_query)
             .await?
         {
-            if let Some(provider) = &self.memory_provider {
-                provider
-                    .append_message(user_id, "user", &processed_query)
-                    .await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &processed_query, Some(&response))
+                .await?;
             return Ok(response);
         }
 
@@ -166,14 +173,8 @@ impl QueryService {
             .try_handle_reminders_command(user_id, &processed_query)
             .await?
         {
-            if let Some(provider) = &self.memory_provider {
-                provider
-                    .append_message(user_id, "user", &processed_query)
-                    .await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &processed_query, Some(&response))
+                .await?;
             return Ok(response);
         }
 
@@ -181,14 +182,8 @@ impl QueryService {
             .try_handle_todo_command(user_id, &processed_query)
             .await?
         {
-            if let Some(provider) = &self.memory_provider {
-                provider
-                    .append_message(user_id, "user", &processed_query)
-                    .await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &processed_query, Some(&response))
+                .await?;
             return Ok(response);
         }
 
@@ -196,60 +191,20 @@ impl QueryService {
             .try_handle_plans_command(user_id, &processed_query)
             .await?
         {
-            if let Some(provider) = &self.memory_provider {
-                provider
-                    .append_message(user_id, "user", &processed_query)
-                    .await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &processed_query, Some(&response))
+                .await?;
             return Ok(response);
         }
 
-        let reminder_context = if let Some(store) = &self.reminder_store {
-            build_reminder_context(store, user_id).await
-        } else {
-            None
-        };
-        let mut memory_context = if let Some(provider) = &self.memory_provider {
-            let include_semantic = should_include_semantic_memory(&processed_query);
-            let history_future = provider.get_history(user_id, 12);
-            let semantic_future = async {
-                if include_semantic {
-                    provider.search(user_id, &processed_query, 5).await
-                } else {
-                    Ok(Vec::new())
-                }
-            };
-            let (history, semantic) = tokio::try_join!(history_future, semantic_future)?;
-            let history = history.join("\n");
-            build_memory_context(history, semantic, reminder_context)
-        } else {
-            reminder_context.unwrap_or_default()
-        };
-
-        if let Some(context_markdown) = self.context_for_autonomy(user_id, &processed_query).await {
-            if !memory_context.is_empty() {
-                memory_context.push_str("\n\n");
-            }
-            memory_context.push_str(&context_markdown);
-        }
+        let memory_context = self.assemble_context(user_id, &processed_query).await?;
 
         let response = self
             .agent_service
             .generate_response(user_id, &processed_query, &memory_context, prompt)
             .await?;
 
-        if let Some(provider) = &self.memory_provider {
-            if autonomy_tick {
-                return Ok(response);
-            }
-            provider
-                .append_message(user_id, "user", &processed_query)
-                .await?;
-            provider
-                .append_message(user_id, "assistant", &response)
+        if !autonomy_tick {
+            self.remember_exchange(user_id, &processed_query, Some(&response))
                 .await?;
         }
 
@@ -278,83 +233,36 @@ impl QueryService {
         self.ensure_context_in_memory(user_id).await?;
 
         if let Some(response) = self.try_handle_search_command(user_id, &text).await? {
-            if let Some(provider) = &self.memory_provider {
-                provider.append_message(user_id, "user", &text).await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &text, Some(&response))
+                .await?;
             return Ok(ProcessResult::Text(response));
         }
 
         if let Some(response) = self.try_handle_tasks_command(user_id, &text).await? {
-            if let Some(provider) = &self.memory_provider {
-                provider.append_message(user_id, "user", &text).await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &text, Some(&response))
+                .await?;
             return Ok(ProcessResult::Text(response));
         }
 
         if let Some(response) = self.try_handle_reminders_command(user_id, &text).await? {
-            if let Some(provider) = &self.memory_provider {
-                provider.append_message(user_id, "user", &text).await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &text, Some(&response))
+                .await?;
             return Ok(ProcessResult::Text(response));
         }
 
         if let Some(response) = self.try_handle_todo_command(user_id, &text).await? {
-            if let Some(provider) = &self.memory_provider {
-                provider.append_message(user_id, "user", &text).await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &text, Some(&response))
+                .await?;
             return Ok(ProcessResult::Text(response));
         }
 
         if let Some(response) = self.try_handle_plans_command(user_id, &text).await? {
-            if let Some(provider) = &self.memory_provider {
-                provider.append_message(user_id, "user", &text).await?;
-                provider
-                    .append_message(user_id, "assistant", &response)
-                    .await?;
-            }
+            self.remember_exchange(user_id, &text, Some(&response))
+                .await?;
             return Ok(ProcessResult::Text(response));
         }
 
-        let reminder_context = if let Some(store) = &self.reminder_store {
-            build_reminder_context(store, user_id).await
-        } else {
-            None
-        };
-        let mut memory_context = if let Some(provider) = &self.memory_provider {
-            let include_semantic = should_include_semantic_memory(&text);
-            let history_future = provider.get_history(user_id, 12);
-            let semantic_future = async {
-                if include_semantic {
-                    provider.search(user_id, &text, 5).await
-                } else {
-                    Ok(Vec::new())
-                }
-            };
-            let (history, semantic) = tokio::try_join!(history_future, semantic_future)?;
-            let history = history.join("\n");
-            build_memory_context(history, semantic, reminder_context)
-        } else {
-            reminder_context.unwrap_or_default()
-        };
-
-        if let Some(context_markdown) = self.context_for_autonomy(user_id, &text).await {
-            if !memory_context.is_empty() {
-                memory_context.push_str("\n\n");
-            }
-            memory_context.push_str(&context_markdown);
-        }
+        let memory_context = self.assemble_context(user_id, &text).await?;
 
         let result = if let Some(schema) = options.json_schema {
             let structured = self
@@ -400,16 +308,12 @@ impl QueryService {
             (other, _) => other,
         };
 
-        if let Some(provider) = &self.memory_provider {
-            if autonomy_tick {
-                return Ok(output);
-            }
-            provider.append_message(user_id, "user", &text).await?;
-            if let ProcessResult::Text(ref message) = output {
-                provider
-                    .append_message(user_id, "assistant", message)
-                    .await?;
-            }
+        if !autonomy_tick {
+            let message = match &output {
+                ProcessResult::Text(message) => Some(message.as_str()),
+                _ => None,
+            };
+            self.remember_exchange(user_id, &text, message).await?;
         }
 
         Ok(output)
@@ -428,78 +332,36 @@ impl QueryService {
             self.ensure_context_in_memory(user_id).await?;
 
             if let Some(response) = self.try_handle_search_command(user_id, &processed_query).await? {
-                if let Some(provider) = &self.memory_provider {
-                    provider.append_message(user_id, "user", &processed_query).await?;
-                    provider.append_message(user_id, "assistant", &response).await?;
-                }
+                self.remember_exchange(user_id, &processed_query, Some(&response)).await?;
                 yield response;
                 return;
             }
 
             if let Some(response) = self.try_handle_tasks_command(user_id, &processed_query).await? {
-                if let Some(provider) = &self.memory_provider {
-                    provider.append_message(user_id, "user", &processed_query).await?;
-                    provider.append_message(user_id, "assistant", &response).await?;
-                }
+                self.remember_exchange(user_id, &processed_query, Some(&response)).await?;
                 yield response;
                 return;
             }
 
             if let Some(response) = self.try_handle_reminders_command(user_id, &processed_query).await? {
-                if let Some(provider) = &self.memory_provider {
-                    provider.append_message(user_id, "user", &processed_query).await?;
-                    provider.append_message(user_id, "assistant", &response).await?;
-                }
+                self.remember_exchange(user_id, &processed_query, Some(&response)).await?;
                 yield response;
                 return;
             }
 
             if let Some(response) = self.try_handle_todo_command(user_id, &processed_query).await? {
-                if let Some(provider) = &self.memory_provider {
-                    provider.append_message(user_id, "user", &processed_query).await?;
-                    provider.append_message(user_id, "assistant", &response).await?;
-                }
+                self.remember_exchange(user_id, &processed_query, Some(&response)).await?;
                 yield response;
                 return;
             }
 
             if let Some(response) = self.try_handle_plans_command(user_id, &processed_query).await? {
-                if let Some(provider) = &self.memory_provider {
-                    provider.append_message(user_id, "user", &processed_query).await?;
-                    provider.append_message(user_id, "assistant", &response).await?;
-                }
+                self.remember_exchange(user_id, &processed_query, Some(&response)).await?;
                 yield response;
                 return;
             }
 
-            let reminder_context = if let Some(store) = &self.reminder_store {
-                build_reminder_context(store, user_id).await
-            } else {
-                None
-            };
-            let mut memory_context = if let Some(provider) = &self.memory_provider {
-                let include_semantic = should_include_semantic_memory(&processed_query);
-                let history_future = provider.get_history(user_id, 12);
-                let semantic_future = async {
-                    if include_semantic {
-                        provider.search(user_id, &processed_query, 5).await
-                    } else {
-                        Ok(Vec::new())
-                    }
-                };
-                let (history, semantic) = tokio::try_join!(history_future, semantic_future)?;
-                let history = history.join("\n");
-                build_memory_context(history, semantic, reminder_context)
-            } else {
-                reminder_context.unwrap_or_default()
-            };
-
-            if let Some(context_markdown) = self.context_for_autonomy(user_id, &processed_query).await {
-                if !memory_context.is_empty() {
-                    memory_context.push_str("\n\n");
-                }
-                memory_context.push_str(&context_markdown);
-            }
+            let memory_context = self.assemble_context(user_id, &processed_query).await?;
 
             let mut response_text = String::new();
             let mut stream = self.agent_service.generate_response_stream(
@@ -515,14 +377,8 @@ impl QueryService {
                 yield chunk;
             }
 
-            if let Some(provider) = &self.memory_provider {
-                if autonomy_tick {
-                    return;
-                }
-                provider.append_message(user_id, "user", &processed_query).await?;
-                if !response_text.is_empty() {
-                    provider.append_message(user_id, "assistant", &response_text).await?;
-                }
+            if !autonomy_tick {
+                self.remember_exchange(user_id, &processed_query, Some(&response_text)).await?;
             }
         })
     }
@@ -531,6 +387,90 @@ impl QueryService {
         self.agent_service.clone()
     }
 
+    /// Builds the context sent with a query: due reminders, recent history,
+    /// relevant memories and, for autonomy ticks, the context document.
+    /// Anything outside the data scope is dropped and reported.
+    async fn assemble_context(&self, user_id: &str, query: &str) -> Result<String> {
+        let mut violations = Vec::new();
+        let reminder_context = match &self.reminder_store {
+            Some(store) => build_reminder_context(store, user_id).await.map(|text| {
+                self.data_scope
+                    .filter("reminders", None, &text, &mut violations)
+            }),
+            None => None,
+        };
+        let mut memory_context = if let Some(provider) = &self.memory_provider {
+            let include_semantic = should_include_semantic_memory(query)
+                && self.data_scope.allows(DataCategory::Memories);
+            let history_future = provider.get_history(user_id, 12);
+            let semantic_future = async {
+                if include_semantic {
+                    provider.search(user_id, query, 5).await
+                } else {
+                    Ok(Vec::new())
+                }
+            };
+            let (history, semantic) = tokio::try_join!(history_future, semantic_future)?;
+            let history = self.data_scope.filter(
+                "history",
+                Some(DataCategory::Memories),
+                &history.join("\n"),
+                &mut violations,
+            );
+            let semantic = semantic
+                .into_iter()
+                .map(|item| {
+                    self.data_scope.filter(
+                        "memory",
+                        Some(DataCategory::Memories),
+                        &item,
+                        &mut violations,
+                    )
+                })
+                .filter(|item| !item.trim().is_empty())
+                .collect();
+            build_memory_context(history, semantic, reminder_context)
+        } else {
+            reminder_context.unwrap_or_default()
+        };
+
+        if let Some(context_markdown) = self.context_for_autonomy(user_id, query).await {
+            let context_markdown = self.data_scope.filter(
+                "context_doc",
+                Some(DataCategory::Memories),
+                &context_markdown,
+                &mut violations,
+            );
+            if !context_markdown.trim().is_empty() {
+                if !memory_context.is_empty() {
+                    memory_context.push_str("\n\n");
+                }
+                memory_context.push_str(&context_markdown);
+            }
+        }
+
+        if !violations.is_empty() {
+            warn!(
+                user_id,
+                target = self.data_scope.target(),
+                blocked = violations.len(),
+                "Kept context outside the data scope out of the prompt"
+            );
+            self.agent_service.emit_tool_event(
+                user_id,
+                "data_scope",
+                "blocked",
+                serde_json::json!({
+                    "target": self.data_scope.target(),
+                    "blocked": violations,
+                    "actor": "system",
+                    "severity": "warning",
+                }),
+            );
+        }
+        Ok(memory_context)
+    }
+
     async fn context_for_autonomy(&self, user_id: &str, query: &str) -> Option<String> {
         if user_id != "system" && !is_autonomy_tick(query) {
             return None;
@@ -562,6 +502,42 @@ impl QueryService {
         Ok(())
     }
 
+    /// Appends an unprompted assistant message, e.g. a scheduled digest.
+    pub async fn append_assistant_message(&self, user_id: &str, content: &str) -> Result<()> {
+        if let Some(provider) = &self.memory_provider {
+            provider
+                .append_message(user_id, "assistant", content)
+                .await?;
+        }
+        if let Some(mirror) = &self.matrix_mirror {
+            mirror.mirror_message(user_id, content);
+        }
+        Ok(())
+    }
+
+    /// Records a question and its answer in history and mirrors them to the
+    /// user's Matrix room. Empty answers aren't stored.
+    async fn remember_exchange(
+        &self,
+        user_id: &str,
+        query: &str,
+        response: Option<&str>,
+    ) -> Result<()> {
+        let response = response.filter(|response| !response.is_empty());
+        if let Some(provider) = &self.memory_provider {
+            provider.append_message(user_id, "user", query).await?;
+            if let Some(response) = response {
+                provider
+                    .append_message(user_id, "assistant", response)
+                    .await?;
+            }
+        }
+        if let Some(mirror) = &self.matrix_mirror {
+            mirror.mirror_exchange(user_id, query, response.unwrap_or_default());
+        }
+        Ok(())
+    }
+
     pub async fn get_user_history(&self, user_id: &str, limit: usize) -> Result<Vec<String>> {
         if let Some(provider) = &self.memory_provider {
             return provider.get_history(user_id, limit).await;
@@ -580,6 +556,34 @@ impl QueryService {
         }
         Ok(Vec::new())
     }
+
+    pub async fn semantic_search_memory(
+        &self,
+        user_id: &str,
+        query: &str,
+        limit: usize,
+        rerank: bool,
+    ) -> Result<Vec<MemoryHit>> {
+        if let Some(provider) = &self.memory_provider {
+            return provider
+                .semantic_search(user_id, query, limit, rerank)
+                .await;
+        }
+        Ok(Vec::new())
+    }
+
+    pub async fn enforce_memory_retention(
+        &self,
+        user_id: &str,
+        dry_run: bool,
+    ) -> Result<RetentionReport> {
+        match &self.memory_provider {
+            Some(provider) => provider.enforce_retention(user_id, dry_run).await,
+            None => Err(ButterflyBotError::Config(
+                "Memory is disabled; retention has nothing to act on".to_string(),
+            )),
+        }
+    }
 }
 
 fn is_autonomy_tick(query: &str) -> bool {
@@ -0,0 +1,356 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use rand::rngs::SysRng;
+use rand::TryRng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+mod schema;
+use schema::user_api_tokens;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const USER_TOKENS_UP_SQL: &str =
+    include_str!("../../migrations/20260301_create_user_api_tokens/up.sql");
+const TOKEN_PREFIX: &str = "bbu_";
+
+/// Metadata for a per-user API token. The secret itself is never stored; only
+/// its SHA-256 digest and a short display prefix are persisted.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserToken {
+    pub id: i32,
+    pub user_id: String,
+    pub label: Option<String>,
+    pub token_prefix: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+/// A freshly minted token. `token` is only available at issuance time.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedUserToken {
+    #[serde(flatten)]
+    pub meta: UserToken,
+    pub token: String,
+}
+
+#[derive(Queryable)]
+struct UserTokenRow {
+    id: i32,
+    user_id: String,
+    label: Option<String>,
+    #[allow(dead_code)]
+    token_hash: String,
+    token_prefix: String,
+    created_at: i64,
+    last_used_at: Option<i64>,
+    revoked_at: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = user_api_tokens)]
+struct NewUserToken<'a> {
+    user_id: &'a str,
+    label: Option<&'a str>,
+    token_hash: &'a str,
+    token_prefix: &'a str,
+    created_at: i64,
+}
+
+pub struct SessionStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl SessionStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_user_tokens_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    /// Replaces the clock used for timestamps.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn issue_token(&self, user_id: &str, label: Option<&str>) -> Result<IssuedUserToken> {
+        let user_id = user_id.trim();
+        if user_id.is_empty() {
+            return Err(ButterflyBotError::Runtime("Missing user_id".to_string()));
+        }
+        let label = label.map(str::trim).filter(|value| !value.is_empty());
+        let token = generate_token()?;
+        let token_hash = hash_token(&token);
+        let token_prefix: String = token.chars().take(TOKEN_PREFIX.len() + 6).collect();
+        let now = self.clock.now();
+
+        let mut conn = self.write_conn().await?;
+        let new_row = NewUserToken {
+            user_id,
+            label,
+            token_hash: &token_hash,
+            token_prefix: &token_prefix,
+            created_at: now,
+        };
+        diesel::insert_into(user_api_tokens::table)
+            .values(&new_row)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let row: UserTokenRow = user_api_tokens::table
+            .filter(user_api_tokens::token_hash.eq(&token_hash))
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        Ok(IssuedUserToken {
+            meta: map_row(row),
+            token,
+        })
+    }
+
+    /// Revokes `token_id` and issues a replacement with the same label.
+    pub async fn rotate_token(
+        &self,
+        user_id: &str,
+        token_id: i32,
+    ) -> Result<Option<IssuedUserToken>> {
+        let mut conn = self.conn().await?;
+        let existing: Option<UserTokenRow> = user_api_tokens::table
+            .filter(user_api_tokens::id.eq(token_id))
+            .filter(user_api_tokens::user_id.eq(user_id))
+            .filter(user_api_tokens::revoked_at.is_null())
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let Some(existing) = existing else {
+            return Ok(None);
+        };
+        self.revoke_token(user_id, token_id).await?;
+        let issued = self
+            .issue_token(&existing.user_id, existing.label.as_deref())
+            .await?;
+        Ok(Some(issued))
+    }
+
+    pub async fn revoke_token(&self, user_id: &str, token_id: i32) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            user_api_tokens::table
+                .filter(user_api_tokens::id.eq(token_id))
+                .filter(user_api_tokens::user_id.eq(user_id))
+                .filter(user_api_tokens::revoked_at.is_null()),
+        )
+        .set(user_api_tokens::revoked_at.eq(Some(self.clock.now())))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    pub async fn list_tokens(
+        &self,
+        user_id: &str,
+        include_revoked: bool,
+    ) -> Result<Vec<UserToken>> {
+        let mut conn = self.conn().await?;
+        let mut query = user_api_tokens::table
+            .filter(user_api_tokens::user_id.eq(user_id))
+            .into_boxed();
+        if !include_revoked {
+            query = query.filter(user_api_tokens::revoked_at.is_null());
+        }
+        let rows: Vec<UserTokenRow> = query
+            .order(user_api_tokens::created_at.desc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Returns the user that owns `token`, if it is a live per-user token.
+    pub async fn resolve_user(&self, token: &str) -> Result<Option<String>> {
+        let token = token.trim();
+        if !token.starts_with(TOKEN_PREFIX) {
+            return Ok(None);
+        }
+        let token_hash = hash_token(token);
+        let mut conn = self.write_conn().await?;
+        let found: Option<(i32, String)> = user_api_tokens::table
+            .filter(user_api_tokens::token_hash.eq(&token_hash))
+            .filter(user_api_tokens::revoked_at.is_null())
+            .select((user_api_tokens::id, user_api_tokens::user_id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let Some((id, user_id)) = found else {
+            return Ok(None);
+        };
+        diesel::update(user_api_tokens::table.filter(user_api_tokens::id.eq(id)))
+            .set(user_api_tokens::last_used_at.eq(Some(self.clock.now())))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(Some(user_id))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn map_row(row: UserTokenRow) -> UserToken {
+    UserToken {
+        id: row.id,
+        user_id: row.user_id,
+        label: row.label,
+        token_prefix: row.token_prefix,
+        created_at: row.created_at,
+        last_used_at: row.last_used_at,
+        revoked_at: row.revoked_at,
+    }
+}
+
+fn generate_token() -> Result<String> {
+    let mut bytes = [0u8; 32];
+    let mut rng = SysRng;
+    rng.try_fill_bytes(&mut bytes)
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(format!("{TOKEN_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+}
+
+fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_user_tokens_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM user_api_tokens LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                conn.run_pending_migrations(MIGRATIONS)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, USER_TOKENS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn issued_tokens_resolve_until_rotated_or_revoked() {
+        let temp = tempdir().unwrap();
+        let db_path = temp.path().join("sessions.db");
+        let clock = ManualClock::new(1_000);
+        let store = SessionStore::new(db_path.to_string_lossy())
+            .await
+            .unwrap()
+            .with_clock(clock.clone());
+
+        let issued = store.issue_token("alice", Some("laptop")).await.unwrap();
+        assert!(issued.token.starts_with(TOKEN_PREFIX));
+        assert_eq!(issued.meta.created_at, 1_000);
+        clock.advance(60);
+        assert_eq!(
+            store.resolve_user(&issued.token).await.unwrap().as_deref(),
+            Some("alice")
+        );
+        assert!(store
+            .resolve_user("bbu_not-a-token")
+            .await
+            .unwrap()
+            .is_none());
+
+        assert!(store
+            .rotate_token("bob", issued.meta.id)
+            .await
+            .unwrap()
+            .is_none());
+        let rotated = store
+            .rotate_token("alice", issued.meta.id)
+            .await
+            .unwrap()
+            .expect("rotated token");
+        assert_eq!(rotated.meta.label.as_deref(), Some("laptop"));
+        assert!(store.resolve_user(&issued.token).await.unwrap().is_none());
+        assert_eq!(
+            store.resolve_user(&rotated.token).await.unwrap().as_deref(),
+            Some("alice")
+        );
+
+        clock.advance(60);
+        assert!(store.revoke_token("alice", rotated.meta.id).await.unwrap());
+        assert!(store.resolve_user(&rotated.token).await.unwrap().is_none());
+        assert!(store.list_tokens("alice", false).await.unwrap().is_empty());
+        let tokens = store.list_tokens("alice", true).await.unwrap();
+        assert_eq!(tokens.len(), 2);
+        let rotated = tokens
+            .iter()
+            .find(|token| token.id == rotated.meta.id)
+            .unwrap();
+        assert_eq!(rotated.created_at, 1_060);
+        assert_eq!(rotated.last_used_at, Some(1_060));
+        assert_eq!(rotated.revoked_at, Some(1_120));
+    }
+}
@@ -0,0 +1,12 @@
+diesel::table! {
+    user_api_tokens (id) {
+        id -> Integer,
+        user_id -> Text,
+        label -> Nullable<Text>,
+        token_hash -> Text,
+        token_prefix -> Text,
+        created_at -> BigInt,
+        last_used_at -> Nullable<BigInt>,
+        revoked_at -> Nullable<BigInt>,
+    }
+}
@@ -0,0 +1,201 @@
+//! Coordinated daemon shutdown.
+//!
+//! SIGTERM, SIGINT, `POST /shutdown` and the UI's Stop button all end the
+//! same way: the daemon refuses new requests (`/health` still answers, so
+//! callers can wait for the process to exit), stops the scheduler, waits for
+//! in-flight requests, tool executions and wakeup runs, records the audit
+//! events still queued and exits. Draining is bounded by
+//! `tools.settings.shutdown.drain_seconds` (default 30); work still running
+//! at the deadline is logged by name and abandoned.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+pub const DEFAULT_DRAIN_SECONDS: u64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShutdownConfig {
+    /// How long in-flight work gets to finish once shutdown starts.
+    pub drain: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            drain: Duration::from_secs(DEFAULT_DRAIN_SECONDS),
+        }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let drain_seconds = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("shutdown"))
+            .and_then(|shutdown| shutdown.get("drain_seconds"))
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_DRAIN_SECONDS);
+        Self {
+            drain: Duration::from_secs(drain_seconds),
+        }
+    }
+}
+
+/// Work the daemon waits for before it exits.
+pub struct InFlight {
+    stopping: AtomicBool,
+    next_id: AtomicU64,
+    active: watch::Sender<HashMap<u64, String>>,
+}
+
+impl Default for InFlight {
+    fn default() -> Self {
+        Self {
+            stopping: AtomicBool::new(false),
+            next_id: AtomicU64::new(0),
+            active: watch::Sender::new(HashMap::new()),
+        }
+    }
+}
+
+impl InFlight {
+    /// Counts `label` as running until the returned guard drops.
+    pub fn begin(self: &Arc<Self>, label: impl Into<String>) -> WorkGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let label = label.into();
+        self.active.send_modify(|active| {
+            active.insert(id, label);
+        });
+        WorkGuard {
+            in_flight: Arc::clone(self),
+            id,
+        }
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// From now on the daemon refuses new requests.
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    /// Accepts requests again, for a daemon started a second time in the
+    /// same process.
+    pub fn resume(&self) {
+        self.stopping.store(false, Ordering::SeqCst);
+    }
+
+    /// Labels of the work running right now, sorted.
+    pub fn running(&self) -> Vec<String> {
+        let mut labels: Vec<String> = self.active.borrow().values().cloned().collect();
+        labels.sort();
+        labels
+    }
+
+    /// Waits until nothing is running or `deadline` passes, and returns the
+    /// labels of whatever is still running.
+    pub async fn drain(&self, deadline: Instant) -> Vec<String> {
+        let mut rx = self.active.subscribe();
+        let idle = tokio::time::timeout_at(deadline, async move {
+            rx.wait_for(HashMap::is_empty).await.map(|_| ())
+        })
+        .await;
+        match idle {
+            Ok(_) => Vec::new(),
+            Err(_) => self.running(),
+        }
+    }
+}
+
+pub struct WorkGuard {
+    in_flight: Arc<InFlight>,
+    id: u64,
+}
+
+impl Drop for WorkGuard {
+    fn drop(&mut self) {
+        self.in_flight.active.send_modify(|active| {
+            active.remove(&self.id);
+        });
+    }
+}
+
+/// The daemon's in-flight work.
+pub fn shared() -> Arc<InFlight> {
+    static IN_FLIGHT: OnceLock<Arc<InFlight>> = OnceLock::new();
+    IN_FLIGHT
+        .get_or_init(|| Arc::new(InFlight::default()))
+        .clone()
+}
+
+/// Resolves on SIGTERM or SIGINT (Ctrl-C where there is no SIGTERM).
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => tracing::info!("Received SIGTERM"),
+                    _ = tokio::signal::ctrl_c() => tracing::info!("Received SIGINT"),
+                }
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "Cannot listen for SIGTERM; only SIGINT stops");
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        tracing::info!("Received Ctrl-C");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_waits_for_work_until_the_deadline() {
+        let in_flight = Arc::new(InFlight::default());
+        let quick = in_flight.begin("tool:quick");
+        let stuck = in_flight.begin("tool:stuck");
+        assert_eq!(in_flight.running(), vec!["tool:quick", "tool:stuck"]);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(quick);
+        });
+        let left = in_flight
+            .drain(Instant::now() + Duration::from_millis(200))
+            .await;
+        assert_eq!(left, vec!["tool:stuck"]);
+
+        drop(stuck);
+        assert!(in_flight
+            .drain(Instant::now() + Duration::from_millis(10))
+            .await
+            .is_empty());
+
+        assert_eq!(
+            ShutdownConfig::from_tools(Some(
+                &json!({"settings": {"shutdown": {"drain_seconds": 5}}})
+            ))
+            .drain,
+            Duration::from_secs(5)
+        );
+        assert_eq!(ShutdownConfig::from_tools(None), ShutdownConfig::default());
+    }
+}
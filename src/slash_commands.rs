@@ -0,0 +1,167 @@
+//! Chat messages that start with `/` and name a known command, such as
+//! `/todo buy milk`, `/remind 3pm standup` or `/done 12`. The daemon runs
+//! these against the stores directly instead of sending them to the model.
+
+const DAY_SECS: i64 = 86_400;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SlashCommand {
+    Todo {
+        title: String,
+    },
+    /// `when` and `title` are still one string; see [`reminder_parts`].
+    Remind {
+        args: String,
+    },
+    /// An origin ref such as `reminder:3`; a bare number is a todo.
+    Done {
+        origin_ref: String,
+    },
+    Help,
+    /// A `/word` that is not a command, answered with the help text rather
+    /// than passed to the model.
+    Unknown {
+        name: String,
+    },
+}
+
+pub const HELP: &str = "Commands:\n\
+/todo <title> - add a todo\n\
+/remind <when> <title> - add a reminder, e.g. /remind tomorrow 9am call the bank\n\
+/done <id or ref> - mark an inbox item done, e.g. /done 12 or /done reminder:3\n\
+/help - show this list";
+
+/// The command in `text`, or `None` for an ordinary message. Only a single
+/// `/word` at the very start counts, so paths like `/etc/hosts` are left
+/// for the model; see [`unescape`] for `//word`.
+pub fn parse(text: &str) -> Option<SlashCommand> {
+    let text = text.trim();
+    let rest = text.strip_prefix('/')?;
+    let (name, args) = match rest.split_once(char::is_whitespace) {
+        Some((name, args)) => (name, args.trim()),
+        None => (rest, ""),
+    };
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let command = match name.to_ascii_lowercase().as_str() {
+        "todo" if !args.is_empty() => SlashCommand::Todo {
+            title: args.to_string(),
+        },
+        "remind" | "reminder" if !args.is_empty() => SlashCommand::Remind {
+            args: args.to_string(),
+        },
+        "done" if !args.is_empty() => {
+            let target = args.trim_start_matches('#');
+            let origin_ref = if target.chars().all(|c| c.is_ascii_digit()) {
+                format!("todo:{target}")
+            } else {
+                target.to_string()
+            };
+            SlashCommand::Done { origin_ref }
+        }
+        "todo" | "remind" | "reminder" | "done" | "help" => SlashCommand::Help,
+        _ => SlashCommand::Unknown {
+            name: name.to_string(),
+        },
+    };
+    Some(command)
+}
+
+/// `//text` with one slash removed, so a message can start with `/word`
+/// without running it as a command.
+pub fn unescape(text: &str) -> Option<&str> {
+    text.trim_start()
+        .strip_prefix('/')
+        .filter(|rest| rest.starts_with('/'))
+}
+
+/// Splits `/remind` arguments into a due time and a title, reading the time
+/// from the front (`3pm standup`) or, failing that, the end (`standup at
+/// 3pm`). A time of day already past today means the same time tomorrow.
+pub fn reminder_parts(user_id: &str, args: &str, now: i64) -> Option<(i64, String)> {
+    let words: Vec<&str> = args.split_whitespace().collect();
+    let parse = |phrase: &str| crate::timezones::parse_phrase(user_id, phrase, now);
+    let found = (1..words.len())
+        .rev()
+        .find_map(|n| Some((parse(&words[..n].join(" "))?, words[n..].to_vec())))
+        .or_else(|| {
+            (1..words.len()).find_map(|n| {
+                let mut title = words[..n].to_vec();
+                if title.len() > 1 && matches!(title.last(), Some(&("at" | "on" | "in"))) {
+                    title.pop();
+                }
+                Some((parse(&words[n..].join(" "))?, title))
+            })
+        });
+    let (due_at, mut title) = found?;
+    if title.len() > 1 && title[0].eq_ignore_ascii_case("to") {
+        title.remove(0);
+    }
+    let due_at = if due_at <= now && due_at > now - DAY_SECS {
+        due_at + DAY_SECS
+    } else {
+        due_at
+    };
+    Some((due_at, title.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_known_commands_and_leaves_other_text_alone() {
+        assert_eq!(
+            parse("/todo buy milk"),
+            Some(SlashCommand::Todo {
+                title: "buy milk".to_string()
+            })
+        );
+        assert_eq!(
+            parse("  /Remind 3pm standup "),
+            Some(SlashCommand::Remind {
+                args: "3pm standup".to_string()
+            })
+        );
+        assert_eq!(
+            parse("/done 12"),
+            Some(SlashCommand::Done {
+                origin_ref: "todo:12".to_string()
+            })
+        );
+        assert_eq!(
+            parse("/done reminder:3"),
+            Some(SlashCommand::Done {
+                origin_ref: "reminder:3".to_string()
+            })
+        );
+        assert_eq!(parse("/todo"), Some(SlashCommand::Help));
+        assert_eq!(
+            parse("/frobnicate now"),
+            Some(SlashCommand::Unknown {
+                name: "frobnicate".to_string()
+            })
+        );
+        assert_eq!(parse("/etc/hosts looks wrong"), None);
+        assert_eq!(parse("what does /todo do?"), None);
+        assert_eq!(parse("/"), None);
+        assert_eq!(parse("//todo is a command"), None);
+        assert_eq!(unescape("//todo is a command"), Some("/todo is a command"));
+        assert_eq!(unescape("/todo buy milk"), None);
+    }
+
+    #[test]
+    fn reminder_time_can_lead_or_trail_the_title() {
+        let now = 1_767_225_600; // 2026-01-01T00:00:00Z
+        let (due_at, title) = reminder_parts("u", "tomorrow 9am call the bank", now).unwrap();
+        assert_eq!(title, "call the bank");
+        assert!(due_at > now && due_at < now + 3 * DAY_SECS);
+
+        let (trailing, title) = reminder_parts("u", "call the bank tomorrow 9am", now).unwrap();
+        assert_eq!(title, "call the bank");
+        assert_eq!(trailing, due_at);
+
+        assert!(reminder_parts("u", "standup", now).is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::{Datelike, Duration, Local, NaiveTime, TimeZone, Utc};
+use serde::Serialize;
+
+/// Reminders created without a date are parked far in the future; anything due
+/// beyond this horizon is treated as undated.
+const SOMEDAY_HORIZON_SECONDS: i64 = 2 * 365 * 24 * 60 * 60;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmartList {
+    Overdue,
+    Today,
+    ThisWeek,
+    Later,
+    Someday,
+}
+
+impl SmartList {
+    pub fn all() -> [SmartList; 5] {
+        [
+            SmartList::Overdue,
+            SmartList::Today,
+            SmartList::ThisWeek,
+            SmartList::Later,
+            SmartList::Someday,
+        ]
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "overdue" | "late" => Some(SmartList::Overdue),
+            "today" => Some(SmartList::Today),
+            "this_week" | "this week" | "week" => Some(SmartList::ThisWeek),
+            "later" | "upcoming" => Some(SmartList::Later),
+            "someday" | "no_date" | "no date" | "undated" => Some(SmartList::Someday),
+            _ => None,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            SmartList::Overdue => "overdue",
+            SmartList::Today => "today",
+            SmartList::ThisWeek => "this_week",
+            SmartList::Later => "later",
+            SmartList::Someday => "someday",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SmartList::Overdue => "Overdue",
+            SmartList::Today => "Today",
+            SmartList::ThisWeek => "This Week",
+            SmartList::Later => "Later",
+            SmartList::Someday => "Someday",
+        }
+    }
+}
+
+/// Local-time boundaries used to bucket due dates.
+#[derive(Clone, Copy, Debug)]
+pub struct SmartListBounds {
+    pub now: i64,
+    pub tomorrow_start: i64,
+    pub next_week_start: i64,
+    pub someday_after: i64,
+}
+
+impl SmartListBounds {
+    pub fn at(now: i64) -> Self {
+        Self::at_in(&Local, now)
+    }
+
+    /// Same buckets, with day and week boundaries taken in `tz`.
+    pub fn at_in<Z: TimeZone>(tz: &Z, now: i64) -> Self {
+        let local_now = tz
+            .timestamp_opt(now, 0)
+            .single()
+            .unwrap_or_else(|| Utc::now().with_timezone(tz));
+        let today = local_now.date_naive();
+        let tomorrow = today + Duration::days(1);
+        let days_until_monday = 7 - i64::from(today.weekday().num_days_from_monday());
+        let next_monday = today + Duration::days(days_until_monday);
+
+        let local_midnight = |date: chrono::NaiveDate| {
+            tz.from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()
+                .map(|dt| dt.timestamp())
+        };
+
+        let tomorrow_start = local_midnight(tomorrow).unwrap_or(now + 24 * 60 * 60);
+        let next_week_start = local_midnight(next_monday)
+            .unwrap_or(tomorrow_start)
+            .max(tomorrow_start);
+
+        Self {
+            now,
+            tomorrow_start,
+            next_week_start,
+            someday_after: now + SOMEDAY_HORIZON_SECONDS,
+        }
+    }
+
+    pub fn classify(&self, due_at: Option<i64>) -> SmartList {
+        let Some(due_at) = due_at else {
+            return SmartList::Someday;
+        };
+        if due_at >= self.someday_after {
+            SmartList::Someday
+        } else if due_at < self.now {
+            SmartList::Overdue
+        } else if due_at < self.tomorrow_start {
+            SmartList::Today
+        } else if due_at < self.next_week_start {
+            SmartList::ThisWeek
+        } else {
+            SmartList::Later
+        }
+    }
+
+    /// Like `classify`, but "This Week" also covers today and overdue items so
+    /// that asking for the week never hides something more urgent.
+    pub fn matches(&self, list: SmartList, due_at: Option<i64>) -> bool {
+        let bucket = self.classify(due_at);
+        match list {
+            SmartList::ThisWeek => matches!(
+                bucket,
+                SmartList::Overdue | SmartList::Today | SmartList::ThisWeek
+            ),
+            other => bucket == other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_buckets_relative_to_local_boundaries() {
+        let now = 1_767_268_800; // 2026-01-01T12:00:00Z
+        let bounds = SmartListBounds::at(now);
+
+        assert_eq!(bounds.classify(None), SmartList::Someday);
+        assert_eq!(bounds.classify(Some(now - 60)), SmartList::Overdue);
+        assert_eq!(bounds.classify(Some(now + 1)), SmartList::Today);
+        assert_eq!(
+            bounds.classify(Some(bounds.tomorrow_start)),
+            if bounds.tomorrow_start < bounds.next_week_start {
+                SmartList::ThisWeek
+            } else {
+                SmartList::Later
+            }
+        );
+        assert_eq!(
+            bounds.classify(Some(bounds.next_week_start)),
+            SmartList::Later
+        );
+        assert_eq!(bounds.classify(Some(now + 315_360_000)), SmartList::Someday);
+    }
+
+    #[test]
+    fn this_week_includes_today_and_overdue() {
+        let now = 1_767_268_800;
+        let bounds = SmartListBounds::at(now);
+        assert!(bounds.matches(SmartList::ThisWeek, Some(now - 60)));
+        assert!(bounds.matches(SmartList::ThisWeek, Some(now + 1)));
+        assert!(!bounds.matches(SmartList::Today, Some(now - 60)));
+        assert!(!bounds.matches(SmartList::ThisWeek, None));
+    }
+
+    #[test]
+    fn parse_accepts_aliases() {
+        assert_eq!(SmartList::parse("Today"), Some(SmartList::Today));
+        assert_eq!(SmartList::parse("this week"), Some(SmartList::ThisWeek));
+        assert_eq!(SmartList::parse("no_date"), Some(SmartList::Someday));
+        assert_eq!(SmartList::parse("tomorrow"), None);
+    }
+}
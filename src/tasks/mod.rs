@@ -1,26 +1,23 @@
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use serde::Serialize;
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
+use crate::trash::{self, TrashBatch, TrashReceipt, TrashTable};
 
 mod schema;
 use schema::scheduled_tasks;
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const TASKS_UP_SQL: &str = include_str!("../../migrations/20260203_create_tasks/up.sql");
-
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
+const TASK_TRASH: TrashTable = TrashTable {
+    name: "scheduled_tasks",
+    columns: "id, user_id, name, prompt, run_at, interval_minutes, enabled, created_at, \
+              updated_at, last_run_at, next_run_at, project_id",
+};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct ScheduledTask {
@@ -85,22 +82,34 @@ impl TaskStatus {
 }
 
 pub struct TaskStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
 }
 
 impl TaskStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_tasks_table(sqlite_path).await?;
+        trash::ensure_trash_tables(sqlite_path, &[&TASK_TRASH]).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn create_task(
@@ -111,7 +120,7 @@ impl TaskStore {
         run_at: i64,
         interval_minutes: Option<i64>,
     ) -> Result<ScheduledTask> {
-        let now = now_ts();
+        let now = self.clock.now();
         let interval_minutes = interval_minutes.filter(|v| *v > 0);
         let next_run_at = run_at.max(now);
         let new = NewTask {
@@ -127,7 +136,7 @@ impl TaskStore {
             next_run_at,
         };
 
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         diesel::insert_into(scheduled_tasks::table)
             .values(&new)
             .execute(&mut conn)
@@ -170,8 +179,8 @@ impl TaskStore {
     }
 
     pub async fn set_enabled(&self, id: i32, enabled: bool) -> Result<ScheduledTask> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         diesel::update(scheduled_tasks::table.filter(scheduled_tasks::id.eq(id)))
             .set((
                 scheduled_tasks::enabled.eq(enabled),
@@ -190,7 +199,7 @@ impl TaskStore {
     }
 
     pub async fn delete_task(&self, id: i32) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let count = diesel::delete(scheduled_tasks::table.filter(scheduled_tasks::id.eq(id)))
             .execute(&mut conn)
             .await
@@ -199,32 +208,45 @@ impl TaskStore {
     }
 
     pub async fn clear_tasks(&self, user_id: &str, status: TaskStatus) -> Result<usize> {
-        let mut conn = self.conn().await?;
-        let deleted = match status {
-            TaskStatus::Enabled => diesel::delete(
-                scheduled_tasks::table
-                    .filter(scheduled_tasks::user_id.eq(user_id))
-                    .filter(scheduled_tasks::enabled.eq(true)),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
-            TaskStatus::Disabled => diesel::delete(
-                scheduled_tasks::table
-                    .filter(scheduled_tasks::user_id.eq(user_id))
-                    .filter(scheduled_tasks::enabled.eq(false)),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
-            TaskStatus::All => {
-                diesel::delete(scheduled_tasks::table.filter(scheduled_tasks::user_id.eq(user_id)))
-                    .execute(&mut conn)
-                    .await
-                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
-            }
+        Ok(self.clear_tasks_to_trash(user_id, status).await?.count)
+    }
+
+    /// Moves the matching tasks to the trash so the clear can be undone.
+    pub async fn clear_tasks_to_trash(
+        &self,
+        user_id: &str,
+        status: TaskStatus,
+    ) -> Result<TrashReceipt> {
+        let now = self.clock.now();
+        let batch_id = trash::new_batch_id(now)?;
+        let filter = match status {
+            TaskStatus::Enabled => " AND enabled = 1",
+            TaskStatus::Disabled => " AND enabled = 0",
+            TaskStatus::All => "",
         };
-        Ok(deleted)
+        let mut conn = self.write_conn().await?;
+        let count =
+            trash::move_rows(&mut conn, &TASK_TRASH, user_id, filter, &batch_id, now).await?;
+        Ok(TrashReceipt {
+            store: "tasks",
+            batch_id,
+            count,
+        })
+    }
+
+    pub async fn list_trash(&self, user_id: &str) -> Result<Vec<TrashBatch>> {
+        let mut conn = self.conn().await?;
+        trash::list_batches(&mut conn, &TASK_TRASH, "tasks", user_id).await
+    }
+
+    pub async fn restore_trash(&self, user_id: &str, batch_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::restore_rows(&mut conn, &TASK_TRASH, user_id, batch_id).await
+    }
+
+    pub async fn purge_trash(&self, cutoff: i64) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::purge_before(&mut conn, &TASK_TRASH, cutoff).await
     }
 
     pub async fn list_due(&self, now: i64, limit: usize) -> Result<Vec<ScheduledTask>> {
@@ -241,8 +263,8 @@ impl TaskStore {
     }
 
     pub async fn mark_run(&self, id: i32, last_run_at: i64, next_run_at: i64) -> Result<()> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         diesel::update(scheduled_tasks::table.filter(scheduled_tasks::id.eq(id)))
             .set((
                 scheduled_tasks::last_run_at.eq(Some(last_run_at)),
@@ -255,9 +277,24 @@ impl TaskStore {
         Ok(())
     }
 
+    /// Moves a skipped run to `next_run_at` without counting it as run.
+    pub async fn reschedule(&self, id: i32, next_run_at: i64) -> Result<()> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::update(scheduled_tasks::table.filter(scheduled_tasks::id.eq(id)))
+            .set((
+                scheduled_tasks::next_run_at.eq(next_run_at),
+                scheduled_tasks::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
     pub async fn complete_one_shot(&self, id: i32) -> Result<()> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         diesel::update(scheduled_tasks::table.filter(scheduled_tasks::id.eq(id)))
             .set((
                 scheduled_tasks::enabled.eq(false),
@@ -270,13 +307,11 @@ impl TaskStore {
     }
 
     async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 }
 
@@ -294,14 +329,6 @@ pub fn default_task_db_path() -> String {
     crate::runtime_paths::default_db_path()
 }
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-    }
-    Ok(())
-}
-
 async fn run_migrations(database_url: &str) -> Result<()> {
     let database_url = database_url.to_string();
     tokio::task::spawn_blocking(move || {
@@ -358,10 +385,3 @@ fn map_row(row: TaskRow) -> ScheduledTask {
         next_run_at: row.next_run_at,
     }
 }
-
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
-}
@@ -0,0 +1,535 @@
+//! Shareable `.butterfly-template.json` bundles.
+//!
+//! A bundle carries plans, checklists, rules (scheduled prompts the agent
+//! runs on an interval) and prompt templates. Importing is two steps: the
+//! preview lists every item the bundle will create together with the
+//! capabilities its rules need, and the import only goes ahead when given
+//! the digest of the bundle that was previewed.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::{ButterflyBotError, Result};
+use crate::labels::{self, LabelStore, LabelTarget};
+use crate::planning::PlanStore;
+use crate::sandbox::coverage::WASM_TOOL_CAPABILITIES;
+use crate::sandbox::SandboxSettings;
+use crate::tasks::TaskStore;
+use crate::todo::{ChecklistSchedule, TodoStore};
+
+mod prompts;
+mod schema;
+
+pub use prompts::{PromptTemplate, PromptTemplateStore};
+
+pub const TEMPLATE_FILE_SUFFIX: &str = ".butterfly-template.json";
+pub const TEMPLATE_FORMAT: &str = "butterfly-template";
+pub const TEMPLATE_VERSION: u32 = 1;
+const MAX_BUNDLE_BYTES: usize = 256 * 1024;
+const MAX_ITEMS_PER_SECTION: usize = 100;
+const MIN_RULE_INTERVAL_MINUTES: i64 = 5;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateBundle {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub plans: Vec<PlanTemplate>,
+    #[serde(default)]
+    pub checklists: Vec<ChecklistTemplate>,
+    #[serde(default)]
+    pub rules: Vec<RuleTemplate>,
+    #[serde(default)]
+    pub prompt_templates: Vec<PromptTemplateEntry>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlanTemplate {
+    pub title: String,
+    #[serde(default)]
+    pub goal: String,
+    #[serde(default)]
+    pub steps: Vec<Value>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChecklistTemplate {
+    pub name: String,
+    pub items: Vec<String>,
+    pub schedule: String,
+}
+
+/// A prompt the agent runs every `interval_minutes`, starting
+/// `start_in_minutes` after import. `tools` names the tools the prompt is
+/// written for; their sandbox allowlists are what the preview reports.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleTemplate {
+    pub name: String,
+    pub prompt: String,
+    pub interval_minutes: i64,
+    #[serde(default)]
+    pub start_in_minutes: i64,
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptTemplateEntry {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub body: String,
+}
+
+impl TemplateBundle {
+    /// Parses and validates a bundle file's contents.
+    pub fn parse(contents: &str) -> Result<Self> {
+        if contents.len() > MAX_BUNDLE_BYTES {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Template bundle is larger than {} KiB",
+                MAX_BUNDLE_BYTES / 1024
+            )));
+        }
+        let bundle: Self = serde_json::from_str(contents).map_err(|e| {
+            ButterflyBotError::Serialization(format!("Invalid template bundle: {e}"))
+        })?;
+        bundle.validate()?;
+        Ok(bundle)
+    }
+
+    pub fn from_value(value: &Value) -> Result<Self> {
+        Self::parse(&value.to_string())
+    }
+
+    fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(ButterflyBotError::Runtime(message));
+        if self.format != TEMPLATE_FORMAT {
+            return invalid(format!(
+                "Unsupported template format '{}' (expected '{TEMPLATE_FORMAT}')",
+                self.format
+            ));
+        }
+        if self.version != TEMPLATE_VERSION {
+            return invalid(format!(
+                "Unsupported template version {} (expected {TEMPLATE_VERSION})",
+                self.version
+            ));
+        }
+        if self.name.trim().is_empty() {
+            return invalid("Template bundle needs a name".to_string());
+        }
+        if self.item_count() == 0 {
+            return invalid("Template bundle is empty".to_string());
+        }
+        for (section, len) in [
+            ("plans", self.plans.len()),
+            ("checklists", self.checklists.len()),
+            ("rules", self.rules.len()),
+            ("prompt_templates", self.prompt_templates.len()),
+        ] {
+            if len > MAX_ITEMS_PER_SECTION {
+                return invalid(format!(
+                    "Template bundle has {len} {section}; at most {MAX_ITEMS_PER_SECTION} are allowed"
+                ));
+            }
+        }
+        for plan in &self.plans {
+            if plan.title.trim().is_empty() {
+                return invalid("Every plan needs a title".to_string());
+            }
+        }
+        for checklist in &self.checklists {
+            if checklist.name.trim().is_empty() {
+                return invalid("Every checklist needs a name".to_string());
+            }
+            if checklist.items.iter().all(|item| item.trim().is_empty()) {
+                return invalid(format!("Checklist '{}' has no items", checklist.name));
+            }
+            if ChecklistSchedule::parse(&checklist.schedule).is_none() {
+                return invalid(format!(
+                    "Checklist '{}' has unknown schedule '{}'",
+                    checklist.name, checklist.schedule
+                ));
+            }
+        }
+        for rule in &self.rules {
+            if rule.name.trim().is_empty() || rule.prompt.trim().is_empty() {
+                return invalid("Every rule needs a name and a prompt".to_string());
+            }
+            if rule.interval_minutes < MIN_RULE_INTERVAL_MINUTES {
+                return invalid(format!(
+                    "Rule '{}' runs more often than every {MIN_RULE_INTERVAL_MINUTES} minutes",
+                    rule.name
+                ));
+            }
+            if rule.start_in_minutes < 0 {
+                return invalid(format!("Rule '{}' has a negative start delay", rule.name));
+            }
+        }
+        let mut prompt_names = BTreeSet::new();
+        for prompt in &self.prompt_templates {
+            if prompt.name.trim().is_empty() || prompt.body.trim().is_empty() {
+                return invalid("Every prompt template needs a name and a body".to_string());
+            }
+            if !prompt_names.insert(prompt.name.trim()) {
+                return invalid(format!(
+                    "Prompt template '{}' appears twice",
+                    prompt.name.trim()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.plans.len() + self.checklists.len() + self.rules.len() + self.prompt_templates.len()
+    }
+
+    /// Hex SHA-256 of the parsed bundle; the import must quote it.
+    pub fn digest(&self) -> String {
+        let bytes = serde_json::to_vec(self).unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(&bytes);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateItemKind {
+    Plan,
+    Checklist,
+    Rule,
+    PromptTemplate,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PreviewEntry {
+    pub kind: TemplateItemKind,
+    pub name: String,
+    pub detail: String,
+}
+
+/// What one rule's tools are allowed to do under the current sandbox.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RuleCapabilities {
+    pub rule: String,
+    pub tool: String,
+    pub capabilities: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TemplatePreview {
+    pub name: String,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    pub digest: String,
+    pub creates: Vec<PreviewEntry>,
+    pub capabilities: Vec<RuleCapabilities>,
+    pub warnings: Vec<String>,
+}
+
+/// Lists what importing `bundle` would create. `existing_prompts` are the
+/// prompt template names the user already has; importing replaces them.
+pub fn preview(
+    bundle: &TemplateBundle,
+    settings: &SandboxSettings,
+    existing_prompts: &[String],
+) -> TemplatePreview {
+    let mut creates = Vec::new();
+    let mut capabilities = Vec::new();
+    let mut warnings = Vec::new();
+
+    for plan in &bundle.plans {
+        let mut detail = format!(
+            "Draft plan with {} step{}",
+            plan.steps.len(),
+            if plan.steps.len() == 1 { "" } else { "s" }
+        );
+        let plan_labels = labels::parse_labels(Some(&Value::from(plan.labels.clone())));
+        if !plan_labels.is_empty() {
+            detail.push_str(&format!(", labels: {}", plan_labels.join(", ")));
+        }
+        creates.push(PreviewEntry {
+            kind: TemplateItemKind::Plan,
+            name: plan.title.trim().to_string(),
+            detail,
+        });
+    }
+    for checklist in &bundle.checklists {
+        let items = checklist
+            .items
+            .iter()
+            .filter(|item| !item.trim().is_empty())
+            .count();
+        creates.push(PreviewEntry {
+            kind: TemplateItemKind::Checklist,
+            name: checklist.name.trim().to_string(),
+            detail: format!(
+                "{items} item{}, resets {}",
+                if items == 1 { "" } else { "s" },
+                ChecklistSchedule::parse(&checklist.schedule)
+                    .map(ChecklistSchedule::as_str)
+                    .unwrap_or("never")
+            ),
+        });
+    }
+    for rule in &bundle.rules {
+        creates.push(PreviewEntry {
+            kind: TemplateItemKind::Rule,
+            name: rule.name.trim().to_string(),
+            detail: format!(
+                "Runs every {} minutes: {}",
+                rule.interval_minutes,
+                rule.prompt.trim()
+            ),
+        });
+        if rule.tools.is_empty() {
+            warnings.push(format!(
+                "Rule '{}' does not say which tools it uses; it can reach any tool the agent has",
+                rule.name.trim()
+            ));
+        }
+        for tool in &rule.tools {
+            let tool = tool.trim();
+            if !WASM_TOOL_CAPABILITIES.iter().any(|(name, _)| *name == tool) {
+                warnings.push(format!(
+                    "Rule '{}' names unknown tool '{tool}'",
+                    rule.name.trim()
+                ));
+                continue;
+            }
+            capabilities.push(RuleCapabilities {
+                rule: rule.name.trim().to_string(),
+                tool: tool.to_string(),
+                capabilities: settings.execution_plan(tool).tool_config.capabilities.allow,
+            });
+        }
+    }
+    for prompt in &bundle.prompt_templates {
+        let name = prompt.name.trim();
+        let replaces = existing_prompts.iter().any(|existing| existing == name);
+        creates.push(PreviewEntry {
+            kind: TemplateItemKind::PromptTemplate,
+            name: name.to_string(),
+            detail: if replaces {
+                "Replaces your prompt template with this name".to_string()
+            } else {
+                prompt
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| "Prompt template".to_string())
+            },
+        });
+    }
+
+    TemplatePreview {
+        name: bundle.name.trim().to_string(),
+        description: bundle.description.clone(),
+        author: bundle.author.clone(),
+        digest: bundle.digest(),
+        creates,
+        capabilities,
+        warnings,
+    }
+}
+
+/// Databases the imported items are written to.
+#[derive(Clone, Debug)]
+pub struct ImportTargets {
+    pub plan_db_path: String,
+    pub todo_db_path: String,
+    pub task_db_path: String,
+    pub prompt_db_path: String,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub plan_ids: Vec<i32>,
+    pub checklist_ids: Vec<i32>,
+    pub task_ids: Vec<i32>,
+    pub prompt_templates: Vec<String>,
+}
+
+/// Creates everything in `bundle` for `user_id`. `digest` must match the
+/// bundle's digest so the user gets exactly what they reviewed.
+pub async fn import_bundle(
+    targets: &ImportTargets,
+    user_id: &str,
+    bundle: &TemplateBundle,
+    digest: &str,
+) -> Result<ImportSummary> {
+    if digest.trim() != bundle.digest() {
+        return Err(ButterflyBotError::Runtime(
+            "Template bundle changed since it was reviewed; preview it again".to_string(),
+        ));
+    }
+    let mut summary = ImportSummary::default();
+
+    if !bundle.plans.is_empty() {
+        let plan_store = PlanStore::new(&targets.plan_db_path).await?;
+        let label_store = LabelStore::new(&targets.plan_db_path).await?;
+        for plan in &bundle.plans {
+            let steps = Value::from(plan.steps.clone());
+            let created = plan_store
+                .create_plan(
+                    user_id,
+                    plan.title.trim(),
+                    plan.goal.trim(),
+                    Some(&steps),
+                    Some("draft"),
+                )
+                .await?;
+            let plan_labels = labels::parse_labels(Some(&Value::from(plan.labels.clone())));
+            if !plan_labels.is_empty() {
+                label_store
+                    .set_labels(user_id, LabelTarget::Plan, created.id, &plan_labels)
+                    .await?;
+            }
+            summary.plan_ids.push(created.id);
+        }
+    }
+
+    if !bundle.checklists.is_empty() {
+        let todo_store = TodoStore::new(&targets.todo_db_path).await?;
+        for checklist in &bundle.checklists {
+            let schedule = ChecklistSchedule::parse(&checklist.schedule).ok_or_else(|| {
+                ButterflyBotError::Runtime(format!("Unknown schedule '{}'", checklist.schedule))
+            })?;
+            let (created, _) = todo_store
+                .create_checklist(user_id, checklist.name.trim(), &checklist.items, schedule)
+                .await?;
+            summary.checklist_ids.push(created.id);
+        }
+    }
+
+    if !bundle.rules.is_empty() {
+        let task_store = TaskStore::new(&targets.task_db_path).await?;
+        let now = task_store.clock().now();
+        for rule in &bundle.rules {
+            let task = task_store
+                .create_task(
+                    user_id,
+                    rule.name.trim(),
+                    rule.prompt.trim(),
+                    now + rule.start_in_minutes * 60,
+                    Some(rule.interval_minutes),
+                )
+                .await?;
+            summary.task_ids.push(task.id);
+        }
+    }
+
+    if !bundle.prompt_templates.is_empty() {
+        let prompt_store = PromptTemplateStore::new(&targets.prompt_db_path).await?;
+        for prompt in &bundle.prompt_templates {
+            let saved = prompt_store
+                .upsert(
+                    user_id,
+                    prompt.name.trim(),
+                    prompt.description.as_deref(),
+                    prompt.body.trim(),
+                    Some(bundle.name.trim()),
+                )
+                .await?;
+            summary.prompt_templates.push(saved.name);
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bundle() -> Value {
+        json!({
+            "format": "butterfly-template",
+            "version": 1,
+            "name": "Weekly review",
+            "plans": [{"title": "Q3 launch", "goal": "ship", "steps": ["draft", "review"], "labels": ["Work"]}],
+            "checklists": [{"name": "Friday wrap-up", "items": ["inbox zero", ""], "schedule": "weekly"}],
+            "rules": [{"name": "Nudge", "prompt": "List overdue todos", "interval_minutes": 1440, "tools": ["todo", "teleport"]}],
+            "prompt_templates": [{"name": "standup", "body": "Summarize yesterday"}]
+        })
+    }
+
+    #[test]
+    fn preview_lists_items_and_rule_capabilities() {
+        let parsed = TemplateBundle::from_value(&bundle()).unwrap();
+        let preview = preview(
+            &parsed,
+            &SandboxSettings::default(),
+            &["standup".to_string()],
+        );
+
+        let kinds = preview
+            .creates
+            .iter()
+            .map(|entry| entry.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                TemplateItemKind::Plan,
+                TemplateItemKind::Checklist,
+                TemplateItemKind::Rule,
+                TemplateItemKind::PromptTemplate
+            ]
+        );
+        assert_eq!(
+            preview.creates[0].detail,
+            "Draft plan with 2 steps, labels: work"
+        );
+        assert_eq!(preview.creates[1].detail, "1 item, resets weekly");
+        assert!(preview.creates[3].detail.starts_with("Replaces"));
+        assert_eq!(preview.capabilities.len(), 1);
+        assert!(preview.capabilities[0]
+            .capabilities
+            .contains(&"kv.sqlite.todo.create".to_string()));
+        assert_eq!(
+            preview.warnings,
+            vec!["Rule 'Nudge' names unknown tool 'teleport'".to_string()]
+        );
+        assert_eq!(preview.digest, parsed.digest());
+    }
+
+    #[test]
+    fn invalid_bundles_are_rejected() {
+        let mut wrong_format = bundle();
+        wrong_format["format"] = json!("something-else");
+        assert!(TemplateBundle::from_value(&wrong_format).is_err());
+
+        let mut unknown_section = bundle();
+        unknown_section["webhooks"] = json!([]);
+        assert!(TemplateBundle::from_value(&unknown_section).is_err());
+
+        let mut too_frequent = bundle();
+        too_frequent["rules"][0]["interval_minutes"] = json!(1);
+        assert!(TemplateBundle::from_value(&too_frequent).is_err());
+
+        let mut bad_schedule = bundle();
+        bad_schedule["checklists"][0]["schedule"] = json!("hourly");
+        assert!(TemplateBundle::from_value(&bad_schedule).is_err());
+
+        let empty = json!({"format": "butterfly-template", "version": 1, "name": "x"});
+        assert!(TemplateBundle::from_value(&empty).is_err());
+    }
+}
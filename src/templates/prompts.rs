@@ -0,0 +1,232 @@
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::Serialize;
+
+use super::schema::prompt_templates;
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const PROMPT_TEMPLATES_UP_SQL: &str =
+    include_str!("../../migrations/20260312_create_prompt_templates/up.sql");
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PromptTemplate {
+    pub id: i32,
+    pub user_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub body: String,
+    /// Name of the template bundle it was imported from, if any.
+    pub source: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Queryable)]
+struct PromptTemplateRow {
+    id: i32,
+    user_id: String,
+    name: String,
+    description: Option<String>,
+    body: String,
+    source: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = prompt_templates)]
+struct NewPromptTemplate<'a> {
+    user_id: &'a str,
+    name: &'a str,
+    description: Option<&'a str>,
+    body: &'a str,
+    source: Option<&'a str>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+/// Reusable prompts, keyed by name per user.
+pub struct PromptTemplateStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl PromptTemplateStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_prompt_templates_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    /// Creates the template, or replaces the body of the one with the same
+    /// name.
+    pub async fn upsert(
+        &self,
+        user_id: &str,
+        name: &str,
+        description: Option<&str>,
+        body: &str,
+        source: Option<&str>,
+    ) -> Result<PromptTemplate> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let row = NewPromptTemplate {
+            user_id,
+            name,
+            description,
+            body,
+            source,
+            created_at: now,
+            updated_at: now,
+        };
+        diesel::insert_into(prompt_templates::table)
+            .values(&row)
+            .on_conflict((prompt_templates::user_id, prompt_templates::name))
+            .do_update()
+            .set((
+                prompt_templates::description.eq(description),
+                prompt_templates::body.eq(body),
+                prompt_templates::source.eq(source),
+                prompt_templates::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let row: PromptTemplateRow = prompt_templates::table
+            .filter(prompt_templates::user_id.eq(user_id))
+            .filter(prompt_templates::name.eq(name))
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(map_row(row))
+    }
+
+    pub async fn list(&self, user_id: &str) -> Result<Vec<PromptTemplate>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<PromptTemplateRow> = prompt_templates::table
+            .filter(prompt_templates::user_id.eq(user_id))
+            .order(prompt_templates::name.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Names among `names` the user already has a template for.
+    pub async fn existing_names(&self, user_id: &str, names: &[String]) -> Result<Vec<String>> {
+        if names.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.conn().await?;
+        prompt_templates::table
+            .filter(prompt_templates::user_id.eq(user_id))
+            .filter(prompt_templates::name.eq_any(names))
+            .select(prompt_templates::name)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    pub async fn delete(&self, user_id: &str, name: &str) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let deleted = diesel::delete(
+            prompt_templates::table
+                .filter(prompt_templates::user_id.eq(user_id))
+                .filter(prompt_templates::name.eq(name)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(deleted > 0)
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(prompt_templates::table.filter(prompt_templates::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+fn map_row(row: PromptTemplateRow) -> PromptTemplate {
+    PromptTemplate {
+        id: row.id,
+        user_id: row.user_id,
+        name: row.name,
+        description: row.description,
+        body: row.body,
+        source: row.source,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_prompt_templates_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM prompt_templates LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(
+                    &mut conn,
+                    PROMPT_TEMPLATES_UP_SQL,
+                )
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
@@ -0,0 +1,12 @@
+diesel::table! {
+    prompt_templates (id) {
+        id -> Integer,
+        user_id -> Text,
+        name -> Text,
+        description -> Nullable<Text>,
+        body -> Text,
+        source -> Nullable<Text>,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
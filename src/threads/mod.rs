@@ -0,0 +1,389 @@
+//! Per-thread chat settings.
+//!
+//! A thread may pin its own provider, model and temperature, e.g. a strong
+//! cloud model for planning and a local one for quick capture. Unpinned
+//! fields fall back to the global `llm` config. Chat requests that name no
+//! thread use [`DEFAULT_THREAD`].
+
+use std::sync::Arc;
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{system_clock, SharedClock};
+use crate::config::{Config, LlmProviderKind};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
+use crate::error::{ButterflyBotError, Result};
+use crate::llm::Provider;
+
+mod schema;
+use schema::chat_threads;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+const CHAT_THREADS_UP_SQL: &str =
+    include_str!("../../migrations/20260314_create_chat_threads/up.sql");
+
+pub const DEFAULT_THREAD: &str = "default";
+const MAX_THREAD_ID_LEN: usize = 64;
+const MAX_MODEL_LEN: usize = 128;
+const MAX_TEMPERATURE: f32 = 2.0;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChatThread {
+    pub user_id: String,
+    pub thread_id: String,
+    pub title: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl ChatThread {
+    pub fn is_pinned(&self) -> bool {
+        self.provider.is_some() || self.model.is_some() || self.temperature.is_some()
+    }
+
+    /// Chat backend for this thread, or `None` when nothing is pinned and the
+    /// global default applies.
+    pub fn pinned_provider(&self, config: &Config) -> Result<Option<Arc<dyn Provider>>> {
+        if !self.is_pinned() {
+            return Ok(None);
+        }
+        let provider = self.provider.as_deref().and_then(LlmProviderKind::parse);
+        crate::llm::build_pinned_provider(config, provider, self.model.clone(), self.temperature)
+            .map(Some)
+    }
+}
+
+/// Settings to pin on a thread. `None` fields follow the global default.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ThreadPin {
+    pub title: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+impl ThreadPin {
+    fn normalized(self) -> Result<Self> {
+        let provider = match non_empty(self.provider) {
+            Some(value) => Some(
+                LlmProviderKind::parse(&value)
+                    .ok_or_else(|| {
+                        ButterflyBotError::Config(format!("Unknown provider '{value}'"))
+                    })?
+                    .as_str()
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let model = non_empty(self.model);
+        if model
+            .as_ref()
+            .is_some_and(|model| model.len() > MAX_MODEL_LEN)
+        {
+            return Err(ButterflyBotError::Config(format!(
+                "Model name is longer than {MAX_MODEL_LEN} characters"
+            )));
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=MAX_TEMPERATURE).contains(&temperature) {
+                return Err(ButterflyBotError::Config(format!(
+                    "Temperature must be between 0 and {MAX_TEMPERATURE}"
+                )));
+            }
+        }
+        Ok(Self {
+            title: non_empty(self.title),
+            provider,
+            model,
+            temperature: self.temperature,
+        })
+    }
+}
+
+#[derive(Queryable)]
+struct ChatThreadRow {
+    #[allow(dead_code)]
+    id: i32,
+    user_id: String,
+    thread_id: String,
+    title: Option<String>,
+    provider: Option<String>,
+    model: Option<String>,
+    temperature: Option<f32>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = chat_threads)]
+struct NewChatThread<'a> {
+    user_id: &'a str,
+    thread_id: &'a str,
+    title: Option<&'a str>,
+    provider: Option<&'a str>,
+    model: Option<&'a str>,
+    temperature: Option<f32>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+pub struct ThreadStore {
+    db: DbHandle,
+    clock: SharedClock,
+}
+
+impl ThreadStore {
+    pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
+        run_migrations(sqlite_path).await?;
+        ensure_chat_threads_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
+    }
+
+    pub async fn get(&self, user_id: &str, thread_id: &str) -> Result<Option<ChatThread>> {
+        let mut conn = self.conn().await?;
+        let row: Option<ChatThreadRow> = chat_threads::table
+            .filter(chat_threads::user_id.eq(user_id))
+            .filter(chat_threads::thread_id.eq(thread_id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    pub async fn list(&self, user_id: &str) -> Result<Vec<ChatThread>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ChatThreadRow> = chat_threads::table
+            .filter(chat_threads::user_id.eq(user_id))
+            .order(chat_threads::updated_at.desc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_row).collect())
+    }
+
+    /// Replaces the thread's settings, creating the thread if needed.
+    pub async fn pin(&self, user_id: &str, thread_id: &str, pin: ThreadPin) -> Result<ChatThread> {
+        let thread_id = validate_thread_id(thread_id)?;
+        let pin = pin.normalized()?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let row = NewChatThread {
+            user_id,
+            thread_id,
+            title: pin.title.as_deref(),
+            provider: pin.provider.as_deref(),
+            model: pin.model.as_deref(),
+            temperature: pin.temperature,
+            created_at: now,
+            updated_at: now,
+        };
+        diesel::insert_into(chat_threads::table)
+            .values(&row)
+            .on_conflict((chat_threads::user_id, chat_threads::thread_id))
+            .do_update()
+            .set((
+                chat_threads::title.eq(pin.title.as_deref()),
+                chat_threads::provider.eq(pin.provider.as_deref()),
+                chat_threads::model.eq(pin.model.as_deref()),
+                chat_threads::temperature.eq(pin.temperature),
+                chat_threads::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        self.get(user_id, thread_id).await?.ok_or_else(|| {
+            ButterflyBotError::Runtime(format!("Thread '{thread_id}' vanished after pin"))
+        })
+    }
+
+    /// Drops the thread's provider, model and temperature so it follows the
+    /// global default again. The title is kept.
+    pub async fn unpin(&self, user_id: &str, thread_id: &str) -> Result<bool> {
+        let mut conn = self.write_conn().await?;
+        let updated = diesel::update(
+            chat_threads::table
+                .filter(chat_threads::user_id.eq(user_id))
+                .filter(chat_threads::thread_id.eq(thread_id)),
+        )
+        .set((
+            chat_threads::provider.eq(None::<String>),
+            chat_threads::model.eq(None::<String>),
+            chat_threads::temperature.eq(None::<f32>),
+            chat_threads::updated_at.eq(self.clock.now()),
+        ))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(updated > 0)
+    }
+
+    pub async fn clear_user(&self, user_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        diesel::delete(chat_threads::table.filter(chat_threads::user_id.eq(user_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
+    }
+}
+
+/// The thread a chat request belongs to; blank or missing means the default
+/// thread.
+pub fn thread_or_default(thread_id: Option<&str>) -> &str {
+    thread_id
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or(DEFAULT_THREAD)
+}
+
+fn validate_thread_id(thread_id: &str) -> Result<&str> {
+    let thread_id = thread_id.trim();
+    let valid = !thread_id.is_empty()
+        && thread_id.len() <= MAX_THREAD_ID_LEN
+        && thread_id
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.'));
+    if !valid {
+        return Err(ButterflyBotError::Config(format!(
+            "Thread ids are 1-{MAX_THREAD_ID_LEN} letters, digits, '-', '_' or '.'"
+        )));
+    }
+    Ok(thread_id)
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn map_row(row: ChatThreadRow) -> ChatThread {
+    ChatThread {
+        user_id: row.user_id,
+        thread_id: row.thread_id,
+        title: row.title,
+        provider: row.provider,
+        model: row.model,
+        temperature: row.temperature,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+    }
+}
+
+async fn run_migrations(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        conn.run_pending_migrations(MIGRATIONS)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+async fn ensure_chat_threads_table(database_url: &str) -> Result<()> {
+    let database_url = database_url.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        let check = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM chat_threads LIMIT 1",
+        );
+        if let Err(err) = check {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, CHAT_THREADS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pin_replaces_settings_and_unpin_keeps_title() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("threads.db");
+        let store = ThreadStore::new(path.to_str().unwrap()).await.unwrap();
+
+        let thread = store
+            .pin(
+                "user",
+                "planning",
+                ThreadPin {
+                    title: Some("Planning".to_string()),
+                    provider: Some("Anthropic".to_string()),
+                    model: Some("claude-sonnet-4".to_string()),
+                    temperature: Some(0.2),
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(thread.provider.as_deref(), Some("anthropic"));
+        assert!(thread.is_pinned());
+
+        let err = store
+            .pin(
+                "user",
+                "planning",
+                ThreadPin {
+                    temperature: Some(3.5),
+                    ..ThreadPin::default()
+                },
+            )
+            .await;
+        assert!(matches!(err, Err(ButterflyBotError::Config(_))));
+        assert!(store
+            .pin("user", "no spaces", ThreadPin::default())
+            .await
+            .is_err());
+
+        assert!(store.unpin("user", "planning").await.unwrap());
+        let thread = store.get("user", "planning").await.unwrap().unwrap();
+        assert!(!thread.is_pinned());
+        assert_eq!(thread.title.as_deref(), Some("Planning"));
+        assert!(store.get("other", "planning").await.unwrap().is_none());
+    }
+}
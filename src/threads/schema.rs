@@ -0,0 +1,13 @@
+diesel::table! {
+    chat_threads (id) {
+        id -> Integer,
+        user_id -> Text,
+        thread_id -> Text,
+        title -> Nullable<Text>,
+        provider -> Nullable<Text>,
+        model -> Nullable<Text>,
+        temperature -> Nullable<Float>,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+    }
+}
@@ -0,0 +1,200 @@
+//! Per-user time zones.
+//!
+//! Everything is stored as UTC epoch seconds; a zone only matters when a
+//! phrase like "tomorrow 9am" is resolved or when "today" has to end
+//! somewhere. Zones are IANA names under `tools.settings.timezones`:
+//! `{"default": "Europe/Berlin", "users": {"alice": "America/New_York"}}`.
+//! Users without an entry get `default`, and without that the host's zone.
+
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+use chrono::{Local, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+use crate::smart_lists::SmartListBounds;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UserTimezones {
+    pub default: Option<Tz>,
+    pub users: HashMap<String, Tz>,
+}
+
+impl UserTimezones {
+    /// Unknown zone names are dropped rather than failing the whole config.
+    pub fn from_root_config(config: &Value) -> Self {
+        let Some(section) = config
+            .get("tools")
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("timezones"))
+        else {
+            return Self::default();
+        };
+        let default = section.get("default").and_then(parse_zone);
+        let users = section
+            .get("users")
+            .and_then(|value| value.as_object())
+            .map(|users| {
+                users
+                    .iter()
+                    .filter_map(|(user_id, zone)| Some((user_id.clone(), parse_zone(zone)?)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { default, users }
+    }
+
+    pub fn zone_for(&self, user_id: &str) -> Option<Tz> {
+        self.users.get(user_id).copied().or(self.default)
+    }
+}
+
+fn parse_zone(value: &Value) -> Option<Tz> {
+    value.as_str()?.trim().parse::<Tz>().ok()
+}
+
+fn current() -> &'static RwLock<UserTimezones> {
+    static CURRENT: OnceLock<RwLock<UserTimezones>> = OnceLock::new();
+    CURRENT.get_or_init(|| RwLock::new(UserTimezones::default()))
+}
+
+pub fn configure(config: &Value) {
+    *current()
+        .write()
+        .unwrap_or_else(|poison| poison.into_inner()) = UserTimezones::from_root_config(config);
+}
+
+/// The configured zone for `user_id`; `None` means the host's local zone.
+pub fn zone_for(user_id: &str) -> Option<Tz> {
+    current()
+        .read()
+        .unwrap_or_else(|poison| poison.into_inner())
+        .zone_for(user_id)
+}
+
+/// Resolves a due-date phrase against `anchor_ts` in the user's zone.
+pub fn parse_phrase(user_id: &str, input: &str, anchor_ts: i64) -> Option<i64> {
+    match zone_for(user_id) {
+        Some(tz) => parse_phrase_in(&tz, input, anchor_ts),
+        None => parse_phrase_in(&Local, input, anchor_ts),
+    }
+}
+
+fn parse_phrase_in<Z>(tz: &Z, input: &str, anchor_ts: i64) -> Option<i64>
+where
+    Z: TimeZone,
+    Z::Offset: Copy,
+{
+    let anchor = tz
+        .timestamp_opt(anchor_ts, 0)
+        .single()
+        .unwrap_or_else(|| Utc::now().with_timezone(tz));
+    crate::date_phrases::parse_phrase(input, anchor).map(|dt| dt.timestamp())
+}
+
+/// Start of the user's calendar day containing `ts`.
+pub fn midnight(user_id: &str, ts: i64) -> i64 {
+    match zone_for(user_id) {
+        Some(tz) => midnight_in(&tz, ts),
+        None => midnight_in(&Local, ts),
+    }
+}
+
+fn midnight_in<Z: TimeZone>(tz: &Z, ts: i64) -> i64 {
+    let Some(local) = tz.timestamp_opt(ts, 0).single() else {
+        return ts;
+    };
+    tz.from_local_datetime(&local.date_naive().and_time(NaiveTime::MIN))
+        .earliest()
+        .map(|dt| dt.timestamp())
+        .unwrap_or(ts)
+}
+
+/// The user's calendar date at `ts` and the minutes since their midnight.
+pub fn local_day_minute(user_id: &str, ts: i64) -> Option<(NaiveDate, u32)> {
+    match zone_for(user_id) {
+        Some(tz) => day_minute_in(&tz, ts),
+        None => day_minute_in(&Local, ts),
+    }
+}
+
+fn day_minute_in<Z: TimeZone>(tz: &Z, ts: i64) -> Option<(NaiveDate, u32)> {
+    let local = tz.timestamp_opt(ts, 0).single()?.naive_local();
+    Some((local.date(), local.hour() * 60 + local.minute()))
+}
+
+/// Today/tomorrow/next-week boundaries as the user sees them.
+pub fn smart_list_bounds(user_id: &str, now: i64) -> SmartListBounds {
+    match zone_for(user_id) {
+        Some(tz) => SmartListBounds::at_in(&tz, now),
+        None => SmartListBounds::at(now),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::smart_lists::SmartList;
+    use serde_json::json;
+
+    #[test]
+    fn users_fall_back_to_the_default_zone() {
+        let zones = UserTimezones::from_root_config(&json!({
+            "tools": {"settings": {"timezones": {
+                "default": "Europe/Berlin",
+                "users": {"alice": "America/New_York", "bob": "Mars/Olympus"}
+            }}}
+        }));
+        assert_eq!(zones.zone_for("alice"), Some(chrono_tz::America::New_York));
+        assert_eq!(zones.zone_for("bob"), Some(chrono_tz::Europe::Berlin));
+        assert_eq!(zones.zone_for("carol"), Some(chrono_tz::Europe::Berlin));
+        assert_eq!(UserTimezones::default().zone_for("alice"), None);
+    }
+
+    #[test]
+    fn phrases_and_day_boundaries_follow_the_zone() {
+        let tokyo = chrono_tz::Asia::Tokyo;
+        let new_york = chrono_tz::America::New_York;
+        // 2026-03-04 23:30 UTC: already the 5th in Tokyo, still the 4th in New York.
+        let now = Utc
+            .with_ymd_and_hms(2026, 3, 4, 23, 30, 0)
+            .unwrap()
+            .timestamp();
+
+        let tokyo_tomorrow = parse_phrase_in(&tokyo, "tomorrow 9am", now).unwrap();
+        let new_york_tomorrow = parse_phrase_in(&new_york, "tomorrow 9am", now).unwrap();
+        assert_eq!(
+            tokyo_tomorrow,
+            Utc.with_ymd_and_hms(2026, 3, 6, 0, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+        assert_eq!(
+            new_york_tomorrow,
+            Utc.with_ymd_and_hms(2026, 3, 5, 14, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+
+        assert_eq!(
+            midnight_in(&tokyo, now),
+            Utc.with_ymd_and_hms(2026, 3, 4, 15, 0, 0)
+                .unwrap()
+                .timestamp()
+        );
+
+        let due = Utc
+            .with_ymd_and_hms(2026, 3, 5, 10, 0, 0)
+            .unwrap()
+            .timestamp();
+        assert_eq!(
+            SmartListBounds::at_in(&tokyo, now).classify(Some(due)),
+            SmartList::Today
+        );
+        assert_eq!(
+            SmartListBounds::at_in(&new_york, now).classify(Some(due)),
+            SmartList::ThisWeek
+        );
+    }
+}
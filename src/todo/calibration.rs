@@ -0,0 +1,340 @@
+//! Learning from how long todos really take.
+//!
+//! Completing a todo records its actual duration, created to done, next to
+//! the estimate it carried and the raw heuristic estimate. New todos scale
+//! the heuristic by the median `actual / heuristic` ratio of the user's
+//! recent todos in the same category, falling back to all of the user's
+//! todos while a category has too few samples. Explicit estimates are
+//! never scaled.
+
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use super::schema::todo_estimate_samples;
+use super::{infer_todo_sizing, TodoItem, TodoStore};
+use crate::error::{ButterflyBotError, Result};
+
+/// Samples a multiplier is computed from, newest first.
+const CALIBRATION_WINDOW: i64 = 20;
+/// Fewer samples than this and the estimate is left alone.
+const MIN_SAMPLES: usize = 3;
+const MIN_MULTIPLIER: f64 = 0.25;
+const MAX_MULTIPLIER: f64 = 4.0;
+/// Samples the accuracy report looks at.
+const ACCURACY_SAMPLES: i64 = 1000;
+
+const CATEGORIES: [(&str, &[&str]); 7] = [
+    ("migration", &["migration", "migrate"]),
+    ("security", &["security", "incident"]),
+    ("integration", &["integration", "cross-team"]),
+    ("refactor", &["refactor"]),
+    ("testing", &["test"]),
+    ("fix", &["fix", "bug"]),
+    ("cleanup", &["cleanup", "typo"]),
+];
+
+/// Calibration bucket for a todo: the first category whose keywords appear
+/// in it, otherwise `general`.
+pub fn estimate_category(title: &str, notes: Option<&str>) -> &'static str {
+    let text = format!("{title} {}", notes.unwrap_or_default()).to_ascii_lowercase();
+    CATEGORIES
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| text.contains(keyword)))
+        .map(|(category, _)| *category)
+        .unwrap_or("general")
+}
+
+/// How estimates have held up for one category, or `all` of them.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct EstimateAccuracy {
+    pub category: String,
+    pub samples: usize,
+    /// Median of actual over estimated minutes; above 1 means todos run long.
+    pub median_ratio: f64,
+    /// Mean of |actual − estimate| / estimate, as a percentage.
+    pub mean_absolute_error_pct: f64,
+    /// Share of todos that landed within 25% of their estimate.
+    pub within_25_pct: f64,
+    /// What the heuristic is multiplied by for new todos.
+    pub multiplier: f64,
+}
+
+#[derive(Queryable)]
+struct SampleRow {
+    category: String,
+    baseline_minutes: i32,
+    estimated_minutes: i32,
+    actual_minutes: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = todo_estimate_samples)]
+struct NewSample<'a> {
+    user_id: &'a str,
+    todo_id: i32,
+    category: &'a str,
+    baseline_minutes: i32,
+    estimated_minutes: i32,
+    actual_minutes: i32,
+    completed_at: i64,
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// Median `actual / heuristic` ratio, once there are enough samples.
+fn multiplier_from<'a>(samples: impl IntoIterator<Item = &'a SampleRow>) -> Option<f64> {
+    let ratios = samples
+        .into_iter()
+        .map(|sample| f64::from(sample.actual_minutes) / f64::from(sample.baseline_minutes))
+        .collect::<Vec<_>>();
+    if ratios.len() < MIN_SAMPLES {
+        return None;
+    }
+    median(ratios).map(|ratio| ratio.clamp(MIN_MULTIPLIER, MAX_MULTIPLIER))
+}
+
+fn accuracy_of(category: &str, samples: &[&SampleRow], multiplier: f64) -> EstimateAccuracy {
+    let ratios = samples
+        .iter()
+        .map(|sample| f64::from(sample.actual_minutes) / f64::from(sample.estimated_minutes))
+        .collect::<Vec<_>>();
+    let count = ratios.len().max(1) as f64;
+    EstimateAccuracy {
+        category: category.to_string(),
+        samples: samples.len(),
+        median_ratio: median(ratios.clone()).unwrap_or(1.0),
+        mean_absolute_error_pct: ratios.iter().map(|ratio| (ratio - 1.0).abs()).sum::<f64>()
+            / count
+            * 100.0,
+        within_25_pct: ratios
+            .iter()
+            .filter(|ratio| (*ratio - 1.0).abs() <= 0.25)
+            .count() as f64
+            / count,
+        multiplier,
+    }
+}
+
+impl TodoStore {
+    /// Factor for heuristic estimates of new `category` todos; 1.0 until
+    /// the user has completed enough todos.
+    pub async fn estimate_multiplier(&self, user_id: &str, category: &str) -> Result<f64> {
+        let by_category = self
+            .recent_samples(user_id, Some(category), CALIBRATION_WINDOW)
+            .await?;
+        if let Some(multiplier) = multiplier_from(&by_category) {
+            return Ok(multiplier);
+        }
+        let overall = self
+            .recent_samples(user_id, None, CALIBRATION_WINDOW)
+            .await?;
+        Ok(multiplier_from(&overall).unwrap_or(1.0))
+    }
+
+    /// Accuracy of the user's estimates overall (`all`, first) and per
+    /// category, busiest category first.
+    pub async fn estimate_accuracy(&self, user_id: &str) -> Result<Vec<EstimateAccuracy>> {
+        let samples = self.recent_samples(user_id, None, ACCURACY_SAMPLES).await?;
+        if samples.is_empty() {
+            return Ok(Vec::new());
+        }
+        let overall =
+            multiplier_from(samples.iter().take(CALIBRATION_WINDOW as usize)).unwrap_or(1.0);
+        let mut report = vec![accuracy_of(
+            "all",
+            &samples.iter().collect::<Vec<_>>(),
+            overall,
+        )];
+        let mut categories = samples
+            .iter()
+            .map(|sample| sample.category.as_str())
+            .collect::<Vec<_>>();
+        categories.sort_unstable();
+        categories.dedup();
+        for category in categories {
+            let in_category = samples
+                .iter()
+                .filter(|sample| sample.category == category)
+                .collect::<Vec<_>>();
+            let multiplier = multiplier_from(
+                in_category
+                    .iter()
+                    .copied()
+                    .take(CALIBRATION_WINDOW as usize),
+            )
+            .unwrap_or(overall);
+            report.push(accuracy_of(category, &in_category, multiplier));
+        }
+        report[1..].sort_by(|a, b| {
+            b.samples
+                .cmp(&a.samples)
+                .then_with(|| a.category.cmp(&b.category))
+        });
+        Ok(report)
+    }
+
+    /// Records how long a just-completed todo took. Todos without an
+    /// estimate are skipped; completing a todo again replaces its sample.
+    pub(super) async fn record_estimate_sample(&self, item: &TodoItem) -> Result<()> {
+        let (Some(completed_at), Some(estimated_minutes)) =
+            (item.completed_at, item.estimate_likely_minutes)
+        else {
+            return Ok(());
+        };
+        let notes = item.notes.as_deref();
+        let actual_minutes =
+            i32::try_from(((completed_at - item.created_at) / 60).max(1)).unwrap_or(i32::MAX);
+        let sample = NewSample {
+            user_id: &item.user_id,
+            todo_id: item.id,
+            category: estimate_category(&item.title, notes),
+            baseline_minutes: infer_todo_sizing(&item.title, notes).likely_minutes.max(1),
+            estimated_minutes: estimated_minutes.max(1),
+            actual_minutes,
+            completed_at,
+        };
+        let mut conn = self.conn().await?;
+        diesel::replace_into(todo_estimate_samples::table)
+            .values(&sample)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Forgets the sample of a reopened todo.
+    pub(super) async fn discard_estimate_sample(&self, user_id: &str, todo_id: i32) -> Result<()> {
+        let mut conn = self.conn().await?;
+        diesel::delete(
+            todo_estimate_samples::table
+                .filter(todo_estimate_samples::user_id.eq(user_id))
+                .filter(todo_estimate_samples::todo_id.eq(todo_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn recent_samples(
+        &self,
+        user_id: &str,
+        category: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<SampleRow>> {
+        let mut conn = self.conn().await?;
+        let mut query = todo_estimate_samples::table
+            .filter(todo_estimate_samples::user_id.eq(user_id))
+            .into_boxed();
+        if let Some(category) = category {
+            query = query.filter(todo_estimate_samples::category.eq(category));
+        }
+        query
+            .order((
+                todo_estimate_samples::completed_at.desc(),
+                todo_estimate_samples::id.desc(),
+            ))
+            .limit(limit)
+            .select((
+                todo_estimate_samples::category,
+                todo_estimate_samples::baseline_minutes,
+                todo_estimate_samples::estimated_minutes,
+                todo_estimate_samples::actual_minutes,
+            ))
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_category;
+    use crate::clock::ManualClock;
+    use crate::todo::TodoStore;
+
+    #[test]
+    fn categories_come_from_keywords() {
+        assert_eq!(
+            estimate_category("Plan the DB migration", None),
+            "migration"
+        );
+        assert_eq!(estimate_category("Fix login", Some("bug in form")), "fix");
+        assert_eq!(estimate_category("Buy milk", None), "general");
+    }
+
+    #[tokio::test]
+    async fn completions_calibrate_later_estimates() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("todo.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let clock = ManualClock::new(1_767_268_800);
+        let store = TodoStore::new(&db_path)
+            .await
+            .expect("store")
+            .with_clock(clock.clone());
+
+        let first = store
+            .create_item("u1", "Fix the login form", None, None)
+            .await
+            .expect("create");
+        let baseline = first.estimate_likely_minutes.expect("estimate");
+        assert_eq!(store.estimate_multiplier("u1", "fix").await.unwrap(), 1.0);
+
+        // Every fix takes twice the heuristic.
+        let mut ids = vec![first.id];
+        for title in ["Fix the signup form", "Fix the reset form"] {
+            ids.push(store.create_item("u1", title, None, None).await.unwrap().id);
+        }
+        clock.advance(i64::from(baseline) * 2 * 60);
+        for id in &ids {
+            store.set_completed(*id, true).await.expect("complete");
+        }
+        let multiplier = store.estimate_multiplier("u1", "fix").await.unwrap();
+        assert!((multiplier - 2.0).abs() < 0.01, "multiplier {multiplier}");
+        assert_eq!(
+            store.estimate_multiplier("u1", "cleanup").await.unwrap(),
+            multiplier
+        );
+        assert_eq!(store.estimate_multiplier("u2", "fix").await.unwrap(), 1.0);
+
+        let next = store
+            .create_item("u1", "Fix the search form", None, None)
+            .await
+            .expect("create calibrated");
+        assert_eq!(next.estimate_likely_minutes, Some(baseline * 2));
+        let explicit = store
+            .create_item(
+                "u1",
+                "Fix the footer",
+                Some("Time Estimate: 45 minutes"),
+                None,
+            )
+            .await
+            .expect("create explicit");
+        assert_eq!(explicit.estimate_likely_minutes, Some(45));
+
+        let report = store.estimate_accuracy("u1").await.expect("accuracy");
+        assert_eq!(report[0].category, "all");
+        assert_eq!(report[0].samples, 3);
+        assert!((report[0].median_ratio - 2.0).abs() < 0.01);
+        assert!((report[0].mean_absolute_error_pct - 100.0).abs() < 1.0);
+        assert_eq!(report[1].category, "fix");
+
+        store.set_completed(ids[0], false).await.expect("reopen");
+        let report = store.estimate_accuracy("u1").await.expect("accuracy");
+        assert_eq!(report[0].samples, 2);
+    }
+}
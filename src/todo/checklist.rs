@@ -0,0 +1,473 @@
+use chrono::{Duration, Local, Months, NaiveTime, TimeZone};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::Serialize;
+
+use super::schema::{todo_checklist_runs, todo_checklists, todo_items};
+use super::{map_row, now_ts, TodoItem, TodoRow, TodoStore};
+use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecklistSchedule {
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+}
+
+impl ChecklistSchedule {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "daily" | "day" | "every day" => Some(Self::Daily),
+            "weekly" | "week" | "every week" => Some(Self::Weekly),
+            "biweekly" | "fortnightly" | "every two weeks" => Some(Self::Biweekly),
+            "monthly" | "month" | "every month" => Some(Self::Monthly),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Daily => "daily",
+            Self::Weekly => "weekly",
+            Self::Biweekly => "biweekly",
+            Self::Monthly => "monthly",
+        }
+    }
+
+    /// Local midnight at the start of the next period after `ts`.
+    pub fn next_reset_after(self, ts: i64) -> i64 {
+        let Some(local) = Local.timestamp_opt(ts, 0).single() else {
+            return ts + 24 * 60 * 60;
+        };
+        let date = local.date_naive();
+        let next = match self {
+            Self::Daily => Some(date + Duration::days(1)),
+            Self::Weekly => Some(date + Duration::days(7)),
+            Self::Biweekly => Some(date + Duration::days(14)),
+            Self::Monthly => date.checked_add_months(Months::new(1)),
+        };
+        next.and_then(|date| {
+            Local
+                .from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()
+        })
+        .map(|dt| dt.timestamp())
+        .unwrap_or(ts + 24 * 60 * 60)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TodoChecklist {
+    pub id: i32,
+    pub user_id: String,
+    pub name: String,
+    pub items: Vec<String>,
+    pub schedule: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_reset_at: Option<i64>,
+    pub next_reset_at: i64,
+}
+
+/// One completed period of a checklist, captured when it was reset.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecklistRun {
+    pub id: i32,
+    pub checklist_id: i32,
+    pub user_id: String,
+    pub started_at: i64,
+    pub reset_at: i64,
+    pub total_items: i32,
+    pub completed_items: i32,
+    pub completed_titles: Vec<String>,
+}
+
+#[derive(Queryable)]
+struct ChecklistRow {
+    id: i32,
+    user_id: String,
+    name: String,
+    items_json: String,
+    schedule: String,
+    created_at: i64,
+    updated_at: i64,
+    last_reset_at: Option<i64>,
+    next_reset_at: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = todo_checklists)]
+struct NewChecklist<'a> {
+    user_id: &'a str,
+    name: &'a str,
+    items_json: &'a str,
+    schedule: &'a str,
+    created_at: i64,
+    updated_at: i64,
+    last_reset_at: Option<i64>,
+    next_reset_at: i64,
+}
+
+#[derive(Queryable)]
+struct ChecklistRunRow {
+    id: i32,
+    checklist_id: i32,
+    user_id: String,
+    started_at: i64,
+    reset_at: i64,
+    total_items: i32,
+    completed_items: i32,
+    completed_titles: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = todo_checklist_runs)]
+struct NewChecklistRun<'a> {
+    checklist_id: i32,
+    user_id: &'a str,
+    started_at: i64,
+    reset_at: i64,
+    total_items: i32,
+    completed_items: i32,
+    completed_titles: &'a str,
+}
+
+impl TodoStore {
+    /// Creates a recurring checklist and materializes its first round of todos.
+    pub async fn create_checklist(
+        &self,
+        user_id: &str,
+        name: &str,
+        items: &[String],
+        schedule: ChecklistSchedule,
+    ) -> Result<(TodoChecklist, Vec<TodoItem>)> {
+        let items = normalize_checklist_items(items);
+        if items.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "Checklist requires at least one item".to_string(),
+            ));
+        }
+        let items_json =
+            serde_json::to_string(&items).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let items_json = user_domains::seal(user_id, "todo_checklist.items", &items_json)?;
+        let now = now_ts();
+
+        let mut conn = self.conn().await?;
+        let new = NewChecklist {
+            user_id,
+            name,
+            items_json: &items_json,
+            schedule: schedule.as_str(),
+            created_at: now,
+            updated_at: now,
+            last_reset_at: Some(now),
+            next_reset_at: schedule.next_reset_after(now),
+        };
+        diesel::insert_into(todo_checklists::table)
+            .values(&new)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let row: ChecklistRow = todo_checklists::table
+            .filter(todo_checklists::user_id.eq(user_id))
+            .order(todo_checklists::id.desc())
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let checklist = map_checklist_row(row);
+        let todos = self.materialize_checklist(&checklist).await?;
+        Ok((checklist, todos))
+    }
+
+    pub async fn list_checklists(&self, user_id: &str) -> Result<Vec<TodoChecklist>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ChecklistRow> = todo_checklists::table
+            .filter(todo_checklists::user_id.eq(user_id))
+            .order(todo_checklists::name.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_checklist_row).collect())
+    }
+
+    pub async fn due_checklists(&self, now: i64, limit: usize) -> Result<Vec<TodoChecklist>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ChecklistRow> = todo_checklists::table
+            .filter(todo_checklists::next_reset_at.le(now))
+            .order(todo_checklists::next_reset_at.asc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_checklist_row).collect())
+    }
+
+    /// Records the current period in the checklist history, then replaces its
+    /// todos with a fresh, uncompleted copy of the template.
+    pub async fn reset_checklist(
+        &self,
+        user_id: &str,
+        checklist_id: i32,
+    ) -> Result<Option<(ChecklistRun, Vec<TodoItem>)>> {
+        let now = now_ts();
+        let mut conn = self.conn().await?;
+        let row: Option<ChecklistRow> = todo_checklists::table
+            .filter(todo_checklists::id.eq(checklist_id))
+            .filter(todo_checklists::user_id.eq(user_id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let checklist = map_checklist_row(row);
+
+        let current: Vec<TodoRow> = todo_items::table
+            .filter(todo_items::checklist_id.eq(checklist_id))
+            .order(todo_items::position.asc())
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let current: Vec<TodoItem> = current.into_iter().map(map_row).collect();
+        let completed_titles: Vec<String> = current
+            .iter()
+            .filter(|item| item.completed_at.is_some())
+            .map(|item| item.title.clone())
+            .collect();
+        let completed_titles_json = serde_json::to_string(&completed_titles)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let completed_titles_json = user_domains::seal(
+            user_id,
+            "todo_checklist_run.completed_titles",
+            &completed_titles_json,
+        )?;
+
+        let new_run = NewChecklistRun {
+            checklist_id,
+            user_id,
+            started_at: checklist.last_reset_at.unwrap_or(checklist.created_at),
+            reset_at: now,
+            total_items: current.len() as i32,
+            completed_items: completed_titles.len() as i32,
+            completed_titles: &completed_titles_json,
+        };
+        diesel::insert_into(todo_checklist_runs::table)
+            .values(&new_run)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        let run_row: ChecklistRunRow = todo_checklist_runs::table
+            .filter(todo_checklist_runs::checklist_id.eq(checklist_id))
+            .order(todo_checklist_runs::id.desc())
+            .first(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        diesel::delete(todo_items::table.filter(todo_items::checklist_id.eq(checklist_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
+        let schedule =
+            ChecklistSchedule::parse(&checklist.schedule).unwrap_or(ChecklistSchedule::Weekly);
+        diesel::update(todo_checklists::table.filter(todo_checklists::id.eq(checklist_id)))
+            .set((
+                todo_checklists::last_reset_at.eq(Some(now)),
+                todo_checklists::next_reset_at.eq(schedule.next_reset_after(now)),
+                todo_checklists::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        drop(conn);
+
+        let todos = self.materialize_checklist(&checklist).await?;
+        Ok(Some((map_run_row(run_row), todos)))
+    }
+
+    pub async fn checklist_history(
+        &self,
+        user_id: &str,
+        checklist_id: i32,
+        limit: usize,
+    ) -> Result<Vec<ChecklistRun>> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<ChecklistRunRow> = todo_checklist_runs::table
+            .filter(todo_checklist_runs::checklist_id.eq(checklist_id))
+            .filter(todo_checklist_runs::user_id.eq(user_id))
+            .order(todo_checklist_runs::reset_at.desc())
+            .limit(limit as i64)
+            .load(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(rows.into_iter().map(map_run_row).collect())
+    }
+
+    /// Deletes the checklist template along with its open todos and history.
+    pub async fn delete_checklist(&self, user_id: &str, checklist_id: i32) -> Result<bool> {
+        let mut conn = self.conn().await?;
+        let deleted = diesel::delete(
+            todo_checklists::table
+                .filter(todo_checklists::id.eq(checklist_id))
+                .filter(todo_checklists::user_id.eq(user_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        if deleted == 0 {
+            return Ok(false);
+        }
+        diesel::delete(todo_items::table.filter(todo_items::checklist_id.eq(checklist_id)))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        diesel::delete(
+            todo_checklist_runs::table.filter(todo_checklist_runs::checklist_id.eq(checklist_id)),
+        )
+        .execute(&mut conn)
+        .await
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(true)
+    }
+
+    async fn materialize_checklist(&self, checklist: &TodoChecklist) -> Result<Vec<TodoItem>> {
+        let notes = format!("Checklist: {}", checklist.name);
+        let mut todos = Vec::with_capacity(checklist.items.len());
+        for title in &checklist.items {
+            todos.push(
+                self.insert_item(
+                    &checklist.user_id,
+                    title,
+                    Some(notes.as_str()),
+                    None,
+                    Some(checklist.id),
+                    None,
+                )
+                .await?,
+            );
+        }
+        Ok(todos)
+    }
+}
+
+fn normalize_checklist_items(items: &[String]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| item.trim())
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn map_checklist_row(row: ChecklistRow) -> TodoChecklist {
+    let items_json = user_domains::open(&row.user_id, "todo_checklist.items", row.items_json);
+    TodoChecklist {
+        id: row.id,
+        user_id: row.user_id,
+        name: row.name,
+        items: serde_json::from_str(&items_json).unwrap_or_default(),
+        schedule: row.schedule,
+        created_at: row.created_at,
+        updated_at: row.updated_at,
+        last_reset_at: row.last_reset_at,
+        next_reset_at: row.next_reset_at,
+    }
+}
+
+fn map_run_row(row: ChecklistRunRow) -> ChecklistRun {
+    let completed_titles = user_domains::open(
+        &row.user_id,
+        "todo_checklist_run.completed_titles",
+        row.completed_titles,
+    );
+    ChecklistRun {
+        id: row.id,
+        checklist_id: row.checklist_id,
+        user_id: row.user_id,
+        started_at: row.started_at,
+        reset_at: row.reset_at,
+        total_items: row.total_items,
+        completed_items: row.completed_items,
+        completed_titles: serde_json::from_str(&completed_titles).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChecklistSchedule;
+    use crate::todo::TodoStore;
+
+    #[test]
+    fn schedule_next_reset_moves_forward() {
+        let now = 1_767_268_800;
+        for schedule in [
+            ChecklistSchedule::Daily,
+            ChecklistSchedule::Weekly,
+            ChecklistSchedule::Biweekly,
+            ChecklistSchedule::Monthly,
+        ] {
+            let next = schedule.next_reset_after(now);
+            assert!(next > now, "{} should move forward", schedule.as_str());
+        }
+        assert!(
+            ChecklistSchedule::Weekly.next_reset_after(now)
+                > ChecklistSchedule::Daily.next_reset_after(now)
+        );
+    }
+
+    #[tokio::test]
+    async fn reset_checklist_records_history_and_restores_items() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let db_path = dir.path().join("todo.db");
+        let db_path = db_path.to_string_lossy().to_string();
+        let store = TodoStore::new(&db_path).await.expect("store");
+
+        let (checklist, todos) = store
+            .create_checklist(
+                "u1",
+                "Groceries",
+                &["Milk".to_string(), "Eggs".to_string(), " ".to_string()],
+                ChecklistSchedule::Weekly,
+            )
+            .await
+            .expect("create checklist");
+        assert_eq!(todos.len(), 2);
+        assert!(todos
+            .iter()
+            .all(|todo| todo.checklist_id == Some(checklist.id)));
+
+        store
+            .set_completed(todos[0].id, true)
+            .await
+            .expect("complete milk");
+
+        let (run, fresh) = store
+            .reset_checklist("u1", checklist.id)
+            .await
+            .expect("reset")
+            .expect("checklist exists");
+        assert_eq!(run.total_items, 2);
+        assert_eq!(run.completed_items, 1);
+        assert_eq!(run.completed_titles, vec!["Milk".to_string()]);
+        assert_eq!(fresh.len(), 2);
+        assert!(fresh.iter().all(|todo| todo.completed_at.is_none()));
+
+        let history = store
+            .checklist_history("u1", checklist.id, 10)
+            .await
+            .expect("history");
+        assert_eq!(history.len(), 1);
+        assert!(store
+            .reset_checklist("u2", checklist.id)
+            .await
+            .expect("reset other user")
+            .is_none());
+    }
+}
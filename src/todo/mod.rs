@@ -1,29 +1,37 @@
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use diesel::dsl::max;
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use regex::Regex;
 use serde::Serialize;
 use std::sync::OnceLock;
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
+use crate::security::user_domains;
+use crate::trash::{self, TrashBatch, TrashReceipt, TrashTable};
 
+mod calibration;
+mod checklist;
 mod schema;
 use schema::todo_items;
 
+pub use calibration::{estimate_category, EstimateAccuracy};
+pub use checklist::{ChecklistRun, ChecklistSchedule, TodoChecklist};
+
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const TODO_UP_SQL: &str = include_str!("../../migrations/20260202_create_todos/up.sql");
-
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
+const CHECKLISTS_UP_SQL: &str =
+    include_str!("../../migrations/20260302_create_todo_checklists/up.sql");
+const ESTIMATE_SAMPLES_UP_SQL: &str =
+    include_str!("../../migrations/20260322_create_todo_estimate_samples/up.sql");
+const TODO_TRASH: TrashTable = TrashTable {
+    name: "todo_items",
+    columns: "id, user_id, title, notes, position, created_at, updated_at, completed_at, \
+              t_shirt_size, story_points, estimate_optimistic_minutes, estimate_likely_minutes, \
+              estimate_pessimistic_minutes, dependency_refs, checklist_id, origin_ref, project_id",
+};
 
 #[derive(Debug, Clone, Serialize)]
 pub struct TodoItem {
@@ -41,6 +49,9 @@ pub struct TodoItem {
     pub estimate_likely_minutes: Option<i32>,
     pub estimate_pessimistic_minutes: Option<i32>,
     pub dependency_refs: Vec<String>,
+    pub checklist_id: Option<i32>,
+    /// Where the item came from outside the bot, e.g. `email:<message-id>`.
+    pub origin_ref: Option<String>,
 }
 
 #[derive(Queryable)]
@@ -59,6 +70,8 @@ struct TodoRow {
     estimate_likely_minutes: Option<i32>,
     estimate_pessimistic_minutes: Option<i32>,
     dependency_refs: Option<String>,
+    checklist_id: Option<i32>,
+    origin_ref: Option<String>,
 }
 
 #[derive(Insertable)]
@@ -77,6 +90,8 @@ struct NewTodo<'a> {
     estimate_likely_minutes: Option<i32>,
     estimate_pessimistic_minutes: Option<i32>,
     dependency_refs: Option<&'a str>,
+    checklist_id: Option<i32>,
+    origin_ref: Option<&'a str>,
 }
 
 struct TodoSizingEstimate {
@@ -85,6 +100,26 @@ struct TodoSizingEstimate {
     optimistic_minutes: i32,
     likely_minutes: i32,
     pessimistic_minutes: i32,
+    /// Taken from the todo's own text rather than guessed.
+    explicit: bool,
+}
+
+impl TodoSizingEstimate {
+    /// Stretches a guessed estimate by the user's calibration multiplier.
+    fn calibrated(self, multiplier: f64) -> Self {
+        if self.explicit || multiplier == 1.0 {
+            return self;
+        }
+        let scale = |minutes: i32, floor: i32| {
+            ((f64::from(minutes) * multiplier).round() as i32).max(floor)
+        };
+        Self {
+            optimistic_minutes: scale(self.optimistic_minutes, 15),
+            likely_minutes: scale(self.likely_minutes, 30),
+            pessimistic_minutes: scale(self.pessimistic_minutes, 45),
+            ..self
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -105,22 +140,34 @@ impl TodoStatus {
 }
 
 pub struct TodoStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
 }
 
 impl TodoStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_todo_table(sqlite_path).await?;
+        trash::ensure_trash_tables(sqlite_path, &[&TODO_TRASH]).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn create_item(
@@ -130,9 +177,59 @@ impl TodoStore {
         notes: Option<&str>,
         dependency_refs: Option<&[String]>,
     ) -> Result<TodoItem> {
-        let now = now_ts();
-        let inferred = infer_todo_sizing(title, notes);
+        self.insert_item(user_id, title, notes, dependency_refs, None, None)
+            .await
+    }
+
+    /// Creates an item for something that arrived from outside, once.
+    /// Returns `None` when an item with `origin_ref` already exists, even
+    /// if it has since been completed.
+    pub async fn create_item_from_origin(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+        title: &str,
+        notes: Option<&str>,
+    ) -> Result<Option<TodoItem>> {
+        if self.find_by_origin(user_id, origin_ref).await?.is_some() {
+            return Ok(None);
+        }
+        self.insert_item(user_id, title, notes, None, None, Some(origin_ref))
+            .await
+            .map(Some)
+    }
+
+    pub async fn find_by_origin(
+        &self,
+        user_id: &str,
+        origin_ref: &str,
+    ) -> Result<Option<TodoItem>> {
         let mut conn = self.conn().await?;
+        let row: Option<TodoRow> = todo_items::table
+            .filter(todo_items::user_id.eq(user_id))
+            .filter(todo_items::origin_ref.eq(origin_ref))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
+    async fn insert_item(
+        &self,
+        user_id: &str,
+        title: &str,
+        notes: Option<&str>,
+        dependency_refs: Option<&[String]>,
+        checklist_id: Option<i32>,
+        origin_ref: Option<&str>,
+    ) -> Result<TodoItem> {
+        let now = self.clock.now();
+        let multiplier = self
+            .estimate_multiplier(user_id, estimate_category(title, notes))
+            .await?;
+        let inferred = infer_todo_sizing(title, notes).calibrated(multiplier);
+        let mut conn = self.write_conn().await?;
         let max_pos: Option<i32> = todo_items::table
             .filter(todo_items::user_id.eq(user_id))
             .select(max(todo_items::position))
@@ -140,6 +237,8 @@ impl TodoStore {
             .await
             .unwrap_or(None);
         let position = max_pos.unwrap_or(0) + 1;
+        let sealed_title = user_domains::seal(user_id, "todo.title", title)?;
+        let sealed_notes = user_domains::seal_opt(user_id, "todo.notes", notes)?;
         let dependency_refs_json = dependency_refs
             .map(normalize_dependency_refs)
             .filter(|refs| !refs.is_empty())
@@ -147,8 +246,8 @@ impl TodoStore {
 
         let new = NewTodo {
             user_id,
-            title,
-            notes,
+            title: &sealed_title,
+            notes: sealed_notes.as_deref(),
             position,
             created_at: now,
             updated_at: now,
@@ -159,6 +258,8 @@ impl TodoStore {
             estimate_likely_minutes: Some(inferred.likely_minutes),
             estimate_pessimistic_minutes: Some(inferred.pessimistic_minutes),
             dependency_refs: dependency_refs_json.as_deref(),
+            checklist_id,
+            origin_ref,
         };
 
         diesel::insert_into(todo_items::table)
@@ -206,10 +307,22 @@ impl TodoStore {
         Ok(rows.into_iter().map(map_row).collect())
     }
 
+    pub async fn get_item(&self, user_id: &str, id: i32) -> Result<Option<TodoItem>> {
+        let mut conn = self.conn().await?;
+        let row: Option<TodoRow> = todo_items::table
+            .filter(todo_items::user_id.eq(user_id))
+            .filter(todo_items::id.eq(id))
+            .first(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(row.map(map_row))
+    }
+
     pub async fn set_completed(&self, id: i32, completed: bool) -> Result<TodoItem> {
-        let now = now_ts();
+        let now = self.clock.now();
         let completed_at = if completed { Some(now) } else { None };
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         diesel::update(todo_items::table.filter(todo_items::id.eq(id)))
             .set((
                 todo_items::completed_at.eq(completed_at),
@@ -224,11 +337,21 @@ impl TodoStore {
             .first(&mut conn)
             .await
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(map_row(row))
+        drop(conn);
+        let item = map_row(row);
+        let recorded = if completed {
+            self.record_estimate_sample(&item).await
+        } else {
+            self.discard_estimate_sample(&item.user_id, item.id).await
+        };
+        if let Err(err) = recorded {
+            tracing::warn!(todo_id = item.id, error = %err, "Could not update the estimate sample");
+        }
+        Ok(item)
     }
 
     pub async fn delete_item(&self, id: i32) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let count = diesel::delete(todo_items::table.filter(todo_items::id.eq(id)))
             .execute(&mut conn)
             .await
@@ -237,37 +360,50 @@ impl TodoStore {
     }
 
     pub async fn clear_items(&self, user_id: &str, status: TodoStatus) -> Result<usize> {
-        let mut conn = self.conn().await?;
-        let deleted = match status {
-            TodoStatus::Open => diesel::delete(
-                todo_items::table
-                    .filter(todo_items::user_id.eq(user_id))
-                    .filter(todo_items::completed_at.is_null()),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
-            TodoStatus::Completed => diesel::delete(
-                todo_items::table
-                    .filter(todo_items::user_id.eq(user_id))
-                    .filter(todo_items::completed_at.is_not_null()),
-            )
-            .execute(&mut conn)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
-            TodoStatus::All => {
-                diesel::delete(todo_items::table.filter(todo_items::user_id.eq(user_id)))
-                    .execute(&mut conn)
-                    .await
-                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?
-            }
+        Ok(self.clear_items_to_trash(user_id, status).await?.count)
+    }
+
+    /// Moves the matching items to the trash so the clear can be undone.
+    pub async fn clear_items_to_trash(
+        &self,
+        user_id: &str,
+        status: TodoStatus,
+    ) -> Result<TrashReceipt> {
+        let now = self.clock.now();
+        let batch_id = trash::new_batch_id(now)?;
+        let filter = match status {
+            TodoStatus::Open => " AND completed_at IS NULL",
+            TodoStatus::Completed => " AND completed_at IS NOT NULL",
+            TodoStatus::All => "",
         };
-        Ok(deleted)
+        let mut conn = self.write_conn().await?;
+        let count =
+            trash::move_rows(&mut conn, &TODO_TRASH, user_id, filter, &batch_id, now).await?;
+        Ok(TrashReceipt {
+            store: "todo",
+            batch_id,
+            count,
+        })
     }
 
-    pub async fn reorder(&self, user_id: &str, ordered_ids: &[i32]) -> Result<()> {
-        let now = now_ts();
+    pub async fn list_trash(&self, user_id: &str) -> Result<Vec<TrashBatch>> {
         let mut conn = self.conn().await?;
+        trash::list_batches(&mut conn, &TODO_TRASH, "todo", user_id).await
+    }
+
+    pub async fn restore_trash(&self, user_id: &str, batch_id: &str) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::restore_rows(&mut conn, &TODO_TRASH, user_id, batch_id).await
+    }
+
+    pub async fn purge_trash(&self, cutoff: i64) -> Result<usize> {
+        let mut conn = self.write_conn().await?;
+        trash::purge_before(&mut conn, &TODO_TRASH, cutoff).await
+    }
+
+    pub async fn reorder(&self, user_id: &str, ordered_ids: &[i32]) -> Result<()> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         for (idx, id) in ordered_ids.iter().enumerate() {
             diesel::update(
                 todo_items::table
@@ -286,13 +422,11 @@ impl TodoStore {
     }
 
     async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 }
 
@@ -310,14 +444,6 @@ pub fn default_todo_db_path() -> String {
     crate::runtime_paths::default_db_path()
 }
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-    }
-    Ok(())
-}
-
 async fn run_migrations(database_url: &str) -> Result<()> {
     let database_url = database_url.to_string();
     tokio::task::spawn_blocking(move || {
@@ -359,6 +485,9 @@ async fn ensure_todo_table(database_url: &str) -> Result<()> {
             "ALTER TABLE todo_items ADD COLUMN estimate_likely_minutes INTEGER",
             "ALTER TABLE todo_items ADD COLUMN estimate_pessimistic_minutes INTEGER",
             "ALTER TABLE todo_items ADD COLUMN dependency_refs TEXT",
+            "ALTER TABLE todo_items ADD COLUMN checklist_id INTEGER",
+            "ALTER TABLE todo_items ADD COLUMN origin_ref TEXT",
+            "ALTER TABLE todo_items ADD COLUMN project_id INTEGER",
         ] {
             if let Err(err) =
                 diesel::connection::SimpleConnection::batch_execute(&mut conn, statement)
@@ -370,6 +499,22 @@ async fn ensure_todo_table(database_url: &str) -> Result<()> {
             }
         }
 
+        if let Err(err) = diesel::connection::SimpleConnection::batch_execute(
+            &mut conn,
+            "SELECT 1 FROM todo_checklists LIMIT 1",
+        ) {
+            let message = err.to_string();
+            if message.contains("no such table") {
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, CHECKLISTS_UP_SQL)
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+            } else {
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+
+        diesel::connection::SimpleConnection::batch_execute(&mut conn, ESTIMATE_SAMPLES_UP_SQL)
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+
         Ok::<_, ButterflyBotError>(())
     })
     .await
@@ -378,6 +523,8 @@ async fn ensure_todo_table(database_url: &str) -> Result<()> {
 }
 
 fn map_row(row: TodoRow) -> TodoItem {
+    let title = user_domains::open(&row.user_id, "todo.title", row.title);
+    let notes = user_domains::open_opt(&row.user_id, "todo.notes", row.notes);
     let mut dependency_refs = row
         .dependency_refs
         .as_deref()
@@ -385,7 +532,7 @@ fn map_row(row: TodoRow) -> TodoItem {
         .unwrap_or_default();
 
     if dependency_refs.is_empty() {
-        if let Some(notes) = row.notes.as_deref() {
+        if let Some(notes) = notes.as_deref() {
             dependency_refs = parse_dependency_refs_from_notes(notes);
         }
     }
@@ -393,8 +540,8 @@ fn map_row(row: TodoRow) -> TodoItem {
     TodoItem {
         id: row.id,
         user_id: row.user_id,
-        title: row.title,
-        notes: row.notes,
+        title,
+        notes,
         position: row.position,
         created_at: row.created_at,
         updated_at: row.updated_at,
@@ -405,6 +552,8 @@ fn map_row(row: TodoRow) -> TodoItem {
         estimate_likely_minutes: row.estimate_likely_minutes,
         estimate_pessimistic_minutes: row.estimate_pessimistic_minutes,
         dependency_refs,
+        checklist_id: row.checklist_id,
+        origin_ref: row.origin_ref,
     }
 }
 
@@ -526,6 +675,7 @@ fn infer_todo_sizing(title: &str, notes: Option<&str>) -> TodoSizingEstimate {
         optimistic_minutes: optimistic.max(15),
         likely_minutes: likely.max(30),
         pessimistic_minutes: pessimistic.max(45),
+        explicit: false,
     }
 }
 
@@ -603,12 +753,6 @@ fn parse_explicit_todo_sizing(text: &str) -> Option<TodoSizingEstimate> {
         optimistic_minutes: optimistic_minutes.max(15),
         likely_minutes,
         pessimistic_minutes: pessimistic_minutes.max(45),
+        explicit: true,
     })
 }
-
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
-}
@@ -14,5 +14,47 @@ diesel::table! {
         estimate_likely_minutes -> Nullable<Integer>,
         estimate_pessimistic_minutes -> Nullable<Integer>,
         dependency_refs -> Nullable<Text>,
+        checklist_id -> Nullable<Integer>,
+        origin_ref -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    todo_checklists (id) {
+        id -> Integer,
+        user_id -> Text,
+        name -> Text,
+        items_json -> Text,
+        schedule -> Text,
+        created_at -> BigInt,
+        updated_at -> BigInt,
+        last_reset_at -> Nullable<BigInt>,
+        next_reset_at -> BigInt,
+    }
+}
+
+diesel::table! {
+    todo_checklist_runs (id) {
+        id -> Integer,
+        checklist_id -> Integer,
+        user_id -> Text,
+        started_at -> BigInt,
+        reset_at -> BigInt,
+        total_items -> Integer,
+        completed_items -> Integer,
+        completed_titles -> Text,
+    }
+}
+
+diesel::table! {
+    todo_estimate_samples (id) {
+        id -> Integer,
+        user_id -> Text,
+        todo_id -> Integer,
+        category -> Text,
+        baseline_minutes -> Integer,
+        estimated_minutes -> Integer,
+        actual_minutes -> Integer,
+        completed_at -> BigInt,
     }
 }
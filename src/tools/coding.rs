@@ -1,11 +1,18 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
+use crate::git::{GitPolicy, Repository};
 use crate::interfaces::plugins::{Tool, ToolSecret};
 use crate::interfaces::providers::LlmProvider;
+use crate::patch::{self, Workspace};
 use crate::providers::openai::OpenAiProvider;
+use crate::runner::{self, RunPolicy};
+use crate::sandbox::{FilesystemPolicy, SandboxSettings};
 use crate::vault;
 
 #[derive(Clone, Debug)]
@@ -14,6 +21,11 @@ struct CodingConfig {
     model: String,
     base_url: String,
     system_prompt: String,
+    /// Where `apply_patch` may write, from the sandbox policy.
+    filesystem: FilesystemPolicy,
+    backup_dir: PathBuf,
+    run: RunPolicy,
+    git: GitPolicy,
 }
 
 impl Default for CodingConfig {
@@ -23,6 +35,12 @@ impl Default for CodingConfig {
             model: "gpt-5.2-codex".to_string(),
             base_url: "https://api.openai.com/v1".to_string(),
             system_prompt: "You are a senior coding agent. Focus on backend services (FastAPI/Python) and Solana smart contracts (Rust/Anchor). Provide precise, production-ready code changes with tests when applicable. Avoid UI and frontend work unless explicitly requested.".to_string(),
+            filesystem: FilesystemPolicy::default(),
+            backup_dir: crate::runtime_paths::app_root()
+                .join("data")
+                .join("patch-backups"),
+            run: RunPolicy::default(),
+            git: GitPolicy::default(),
         }
     }
 }
@@ -47,6 +65,138 @@ impl CodingTool {
     fn get_tool_config(config: &Value) -> Option<&Value> {
         config.get("tools").and_then(|tools| tools.get("coding"))
     }
+
+    fn apply_patch(config: &CodingConfig, params: &Value) -> Result<Value> {
+        let diff = params
+            .get("patch")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.trim().is_empty())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing patch".to_string()))?;
+        let dry_run = params
+            .get("dry_run")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        if !dry_run && config.filesystem.mode.as_deref() == Some("read_only") {
+            return Err(ButterflyBotError::Runtime(
+                "Coding tool filesystem policy is read_only; only dry runs are allowed".to_string(),
+            ));
+        }
+
+        let workspace = Workspace::open(
+            params.get("workspace").and_then(|v| v.as_str()),
+            &config.filesystem.allow,
+        )?;
+        let patches = patch::parse(diff)?;
+        let report = workspace.apply(&patches, dry_run, &config.backup_dir)?;
+        Ok(json!({"status": "ok", "action": "apply_patch", "report": report}))
+    }
+
+    async fn run_command(config: &CodingConfig, params: &Value) -> Result<Value> {
+        let command = params
+            .get("command")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing command".to_string()))?;
+        if config.filesystem.mode.as_deref() == Some("read_only") {
+            return Err(ButterflyBotError::Runtime(
+                "Coding tool filesystem policy is read_only; commands cannot run".to_string(),
+            ));
+        }
+        let args = params
+            .get("args")
+            .and_then(|v| v.as_array())
+            .map(|args| {
+                args.iter()
+                    .filter_map(|arg| arg.as_str().map(str::to_string))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let workspace = Workspace::open(
+            params.get("workspace").and_then(|v| v.as_str()),
+            &config.filesystem.allow,
+        )?;
+        let output = runner::run(
+            &workspace,
+            params.get("cwd").and_then(|v| v.as_str()),
+            command,
+            &args,
+            &config.run,
+            params
+                .get("timeout_seconds")
+                .and_then(|v| v.as_u64())
+                .map(Duration::from_secs),
+        )
+        .await?;
+        Ok(json!({"status": "ok", "action": "run", "result": output}))
+    }
+
+    async fn run_git(config: &CodingConfig, action: &str, params: &Value) -> Result<Value> {
+        let text = |key: &str| {
+            params
+                .get(key)
+                .and_then(|v| v.as_str())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let writes = matches!(action, "git_commit" | "git_push") || text("name").is_some();
+        if writes && config.filesystem.mode.as_deref() == Some("read_only") {
+            return Err(ButterflyBotError::Runtime(
+                "Coding tool filesystem policy is read_only; git can only be inspected".to_string(),
+            ));
+        }
+
+        let repo = Repository::open(text("workspace"), &config.filesystem.allow).await?;
+        let result = match action {
+            "git_status" => json!(repo.status().await?),
+            "git_diff" => {
+                let staged = params
+                    .get("staged")
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false);
+                json!(repo.diff(staged, text("path"), &config.git).await?)
+            }
+            "git_commit" => {
+                let message = text("message").ok_or_else(|| {
+                    ButterflyBotError::Runtime("Missing commit message".to_string())
+                })?;
+                let paths = params
+                    .get("paths")
+                    .and_then(|v| v.as_array())
+                    .map(|paths| {
+                        paths
+                            .iter()
+                            .filter_map(|path| path.as_str().map(str::to_string))
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+                json!(repo.commit(message, &paths, &config.git).await?)
+            }
+            "git_branch" => match text("name") {
+                Some(name) => {
+                    let create = params
+                        .get("create")
+                        .and_then(|v| v.as_bool())
+                        .unwrap_or(false);
+                    json!(repo.switch_branch(name, create).await?)
+                }
+                None => json!(repo.branches().await?),
+            },
+            "git_push" => {
+                let branch = text("branch")
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing branch".to_string()))?;
+                let remote = text("remote").unwrap_or("origin");
+                json!({"output": repo.push(remote, branch, &config.git).await?})
+            }
+            other => {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Unsupported coding action: {other}"
+                )))
+            }
+        };
+        Ok(json!({"status": "ok", "action": action, "result": result}))
+    }
 }
 
 #[async_trait]
@@ -56,17 +206,33 @@ impl Tool for CodingTool {
     }
 
     fn description(&self) -> &str {
-        "Use a dedicated coding model (Codex) for backend and smart contract work."
+        "Use a dedicated coding model (Codex) for backend and smart contract work. Action generate (default) returns code for a prompt; apply_patch applies a unified diff to an allowlisted workspace, with dry_run to check it first; run executes an allowlisted build or test command (command plus args) in that workspace and returns its exit code and output. git_status, git_diff, git_branch (list, or switch to name with create) and git_commit (message, optional paths; never on protected branches) work on a workspace that is a git repository; git_push waits for human approval."
     }
 
     fn parameters(&self) -> Value {
         json!({
             "type": "object",
             "properties": {
+                "action": { "type": "string", "enum": ["generate", "apply_patch", "run", "git_status", "git_diff", "git_commit", "git_branch", "git_push"] },
                 "prompt": { "type": "string", "description": "Coding task or request" },
-                "system_prompt": { "type": "string", "description": "Optional system prompt override" }
+                "system_prompt": { "type": "string", "description": "Optional system prompt override" },
+                "patch": { "type": "string", "description": "Unified diff for apply_patch; paths are relative to the workspace" },
+                "workspace": { "type": "string", "description": "Allowlisted directory to patch; optional when only one is allowed" },
+                "dry_run": { "type": "boolean", "description": "Check that the patch applies without writing anything" },
+                "command": { "type": "string", "description": "Program for run, such as cargo or pytest; no shell" },
+                "args": { "type": "array", "items": { "type": "string" }, "description": "Arguments for run" },
+                "cwd": { "type": "string", "description": "Directory for run, relative to the workspace" },
+                "timeout_seconds": { "type": "integer", "description": "Shorter timeout for run than the configured one" },
+                "path": { "type": "string", "description": "Limit git_diff to one path" },
+                "staged": { "type": "boolean", "description": "git_diff staged changes instead of unstaged ones" },
+                "message": { "type": "string", "description": "Commit message for git_commit" },
+                "paths": { "type": "array", "items": { "type": "string" }, "description": "Paths to commit; everything when omitted" },
+                "name": { "type": "string", "description": "Branch to switch to for git_branch" },
+                "create": { "type": "boolean", "description": "Create the git_branch branch" },
+                "remote": { "type": "string", "description": "Remote for git_push, default origin" },
+                "branch": { "type": "string", "description": "Branch for git_push" },
+                "user_id": { "type": "string", "description": "User asked to approve git_push" }
             },
-            "required": ["prompt"],
             "additionalProperties": false
         })
     }
@@ -111,7 +277,18 @@ impl Tool for CodingTool {
                     next.system_prompt = system_prompt.to_string();
                 }
             }
+            if let Some(backup_dir) = tool_cfg.get("backup_dir").and_then(|v| v.as_str()) {
+                if !backup_dir.trim().is_empty() {
+                    next.backup_dir = PathBuf::from(backup_dir);
+                }
+            }
         }
+        next.run = RunPolicy::from_tool_config(Self::get_tool_config(config));
+        next.git = GitPolicy::from_tool_config(Self::get_tool_config(config));
+        next.filesystem = SandboxSettings::from_root_config(config)
+            .execution_plan("coding")
+            .tool_config
+            .filesystem;
 
         let mut guard = self
             .config
@@ -122,6 +299,31 @@ impl Tool for CodingTool {
     }
 
     async fn execute(&self, params: Value) -> Result<Value> {
+        let action = params
+            .get("action")
+            .and_then(|v| v.as_str())
+            .unwrap_or("generate");
+        match action {
+            "generate" => {}
+            "apply_patch" => {
+                let config = self.config.read().await.clone();
+                return Self::apply_patch(&config, &params);
+            }
+            "run" => {
+                let config = self.config.read().await.clone();
+                return Self::run_command(&config, &params).await;
+            }
+            "git_status" | "git_diff" | "git_commit" | "git_branch" | "git_push" => {
+                let config = self.config.read().await.clone();
+                return Self::run_git(&config, action, &params).await;
+            }
+            other => {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "Unsupported coding action: {other}"
+                )))
+            }
+        }
+
         let prompt = params
             .get("prompt")
             .and_then(|v| v.as_str())
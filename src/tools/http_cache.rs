@@ -0,0 +1,266 @@
+//! Response cache for `http.request`.
+//!
+//! Agents tend to look the same thing up several times in one task. GET
+//! responses are kept in memory, keyed by URL and request headers, for as
+//! long as their `Cache-Control: max-age` allows, or for the server's
+//! `cache_ttl_seconds` when the config overrides it. Once stale, a response
+//! with an `ETag` or `Last-Modified` is revalidated with a conditional
+//! request, and a `304` serves the stored body again. `no-store` responses
+//! are never kept.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+
+use lru::LruCache;
+use reqwest::header::HeaderMap;
+
+pub const DEFAULT_MAX_ENTRIES: usize = 256;
+
+/// The directives of a `Cache-Control` header that matter here.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    pub no_store: bool,
+    pub no_cache: bool,
+    pub max_age: Option<u64>,
+}
+
+impl CacheControl {
+    pub fn parse(header: Option<&str>) -> Self {
+        let mut control = Self::default();
+        for directive in header.unwrap_or_default().split(',') {
+            let directive = directive.trim().to_ascii_lowercase();
+            match directive.split_once('=') {
+                Some(("max-age", seconds)) | Some(("s-maxage", seconds)) => {
+                    control.max_age = seconds.trim_matches('"').parse().ok();
+                }
+                _ if directive == "no-store" => control.no_store = true,
+                _ if directive == "no-cache" => control.no_cache = true,
+                _ => {}
+            }
+        }
+        control
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub text: String,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    response: CachedResponse,
+    stored_at: Instant,
+    fresh_for: Duration,
+}
+
+impl Entry {
+    fn validators(&self) -> Validators {
+        Validators {
+            etag: self.response.headers.get("etag").cloned(),
+            last_modified: self.response.headers.get("last-modified").cloned(),
+        }
+    }
+}
+
+/// What a stale entry can be revalidated with.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Lookup {
+    Fresh(CachedResponse),
+    Stale(Validators),
+    Miss,
+}
+
+pub struct HttpCache {
+    entries: LruCache<String, Entry>,
+}
+
+impl Default for HttpCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_ENTRIES)
+    }
+}
+
+impl HttpCache {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: LruCache::new(Self::capacity(max_entries)),
+        }
+    }
+
+    pub fn resize(&mut self, max_entries: usize) {
+        self.entries.resize(Self::capacity(max_entries));
+    }
+
+    fn capacity(max_entries: usize) -> NonZeroUsize {
+        NonZeroUsize::new(max_entries).unwrap_or(NonZeroUsize::MIN)
+    }
+
+    /// Requests only share an entry when they send the same headers, so a
+    /// response fetched with one credential is never served for another.
+    pub fn key(method: &str, url: &str, headers: &HeaderMap) -> String {
+        let mut pairs = headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_bytes()))
+            .collect::<Vec<_>>();
+        pairs.sort();
+        let mut hasher = DefaultHasher::new();
+        pairs.hash(&mut hasher);
+        format!("{method} {url} {:016x}", hasher.finish())
+    }
+
+    pub fn lookup(&mut self, key: &str, now: Instant) -> Lookup {
+        let Some(entry) = self.entries.get(key) else {
+            return Lookup::Miss;
+        };
+        if now.duration_since(entry.stored_at) < entry.fresh_for {
+            return Lookup::Fresh(entry.response.clone());
+        }
+        let validators = entry.validators();
+        if validators.etag.is_none() && validators.last_modified.is_none() {
+            self.entries.pop(key);
+            return Lookup::Miss;
+        }
+        Lookup::Stale(validators)
+    }
+
+    /// Keeps a `200` response if its headers allow it. Returns whether it
+    /// was stored.
+    pub fn store(
+        &mut self,
+        key: &str,
+        response: CachedResponse,
+        ttl: Option<Duration>,
+        now: Instant,
+    ) -> bool {
+        let control =
+            CacheControl::parse(response.headers.get("cache-control").map(String::as_str));
+        if response.status != 200 || control.no_store {
+            self.entries.pop(key);
+            return false;
+        }
+        let fresh_for = Self::freshness(control, ttl);
+        let entry = Entry {
+            response,
+            stored_at: now,
+            fresh_for,
+        };
+        let validators = entry.validators();
+        if fresh_for.is_zero() && validators.etag.is_none() && validators.last_modified.is_none() {
+            self.entries.pop(key);
+            return false;
+        }
+        self.entries.put(key.to_string(), entry);
+        true
+    }
+
+    /// Handles a `304` for `key`: refreshes the entry from the new headers
+    /// and returns the stored response.
+    pub fn revalidated(
+        &mut self,
+        key: &str,
+        headers: &HashMap<String, String>,
+        ttl: Option<Duration>,
+        now: Instant,
+    ) -> Option<CachedResponse> {
+        let entry = self.entries.get_mut(key)?;
+        for (name, value) in headers {
+            if matches!(
+                name.as_str(),
+                "cache-control" | "etag" | "last-modified" | "expires" | "date"
+            ) {
+                entry.response.headers.insert(name.clone(), value.clone());
+            }
+        }
+        let control = CacheControl::parse(
+            entry
+                .response
+                .headers
+                .get("cache-control")
+                .map(String::as_str),
+        );
+        entry.stored_at = now;
+        entry.fresh_for = Self::freshness(control, ttl);
+        Some(entry.response.clone())
+    }
+
+    fn freshness(control: CacheControl, ttl: Option<Duration>) -> Duration {
+        if control.no_cache {
+            return Duration::ZERO;
+        }
+        ttl.or(control.max_age.map(Duration::from_secs))
+            .unwrap_or(Duration::ZERO)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::{Duration, Instant};
+
+    use super::{CacheControl, CachedResponse, HttpCache, Lookup};
+
+    fn response(headers: &[(&str, &str)]) -> CachedResponse {
+        CachedResponse {
+            status: 200,
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            text: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_cache_control() {
+        let control = CacheControl::parse(Some("public, Max-Age=60"));
+        assert_eq!(control.max_age, Some(60));
+        assert!(!control.no_store);
+        assert!(CacheControl::parse(Some("no-store")).no_store);
+        assert!(CacheControl::parse(Some("private, no-cache")).no_cache);
+    }
+
+    #[test]
+    fn serves_fresh_entries_then_asks_for_revalidation() {
+        let mut cache = HttpCache::new(8);
+        let now = Instant::now();
+        let stored = response(&[("cache-control", "max-age=30"), ("etag", "\"v1\"")]);
+        assert!(cache.store("k", stored.clone(), None, now));
+
+        assert_eq!(cache.lookup("k", now), Lookup::Fresh(stored.clone()));
+        let later = now + Duration::from_secs(31);
+        let Lookup::Stale(validators) = cache.lookup("k", later) else {
+            panic!("expected a stale entry");
+        };
+        assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+
+        let refreshed = cache.revalidated("k", &HashMap::new(), None, later);
+        assert_eq!(refreshed, Some(stored.clone()));
+        assert_eq!(cache.lookup("k", later), Lookup::Fresh(stored));
+    }
+
+    #[test]
+    fn respects_no_store_and_ttl_overrides() {
+        let mut cache = HttpCache::new(8);
+        let now = Instant::now();
+        assert!(!cache.store("a", response(&[("cache-control", "no-store")]), None, now));
+        assert!(!cache.store("b", response(&[]), None, now));
+        assert!(cache.store("b", response(&[]), Some(Duration::from_secs(5)), now));
+        assert!(matches!(cache.lookup("b", now), Lookup::Fresh(_)));
+        assert_eq!(
+            cache.lookup("b", now + Duration::from_secs(6)),
+            Lookup::Miss
+        );
+    }
+}
@@ -1,30 +1,55 @@
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
-use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH,
+};
 use serde_json::{json, Value};
 use tokio::sync::RwLock;
 use tracing::info;
 
 use crate::error::{ButterflyBotError, Result};
-use crate::interfaces::plugins::Tool;
+use crate::interfaces::plugins::{Tool, ToolSecret};
+use crate::tools::http_cache::{CacheControl, CachedResponse, HttpCache, Lookup};
+use crate::tools::http_oauth::{OAuth2Config, TokenCache};
 
 #[derive(Clone, Debug, Default)]
 struct HttpCallServerConfig {
     name: String,
     url: String,
     headers: HashMap<String, String>,
+    /// Overrides how long this server's responses stay fresh.
+    cache_ttl_seconds: Option<u64>,
+    /// Fetches the `Authorization` header from a token endpoint.
+    oauth2: Option<OAuth2Config>,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug)]
 struct HttpCallConfig {
     servers: Vec<HttpCallServerConfig>,
     timeout_seconds: Option<u64>,
+    cache_enabled: bool,
+    cache_max_entries: usize,
+}
+
+impl Default for HttpCallConfig {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            timeout_seconds: None,
+            cache_enabled: true,
+            cache_max_entries: crate::tools::http_cache::DEFAULT_MAX_ENTRIES,
+        }
+    }
 }
 
 pub struct HttpCallTool {
     config: RwLock<HttpCallConfig>,
+    cache: Mutex<HttpCache>,
+    tokens: TokenCache,
 }
 
 impl Default for HttpCallTool {
@@ -37,9 +62,34 @@ impl HttpCallTool {
     pub fn new() -> Self {
         Self {
             config: RwLock::new(HttpCallConfig::default()),
+            cache: Mutex::new(HttpCache::default()),
+            tokens: TokenCache::default(),
         }
     }
 
+    fn with_cache<T>(&self, f: impl FnOnce(&mut HttpCache) -> T) -> T {
+        let mut cache = match self.cache.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        f(&mut cache)
+    }
+
+    async fn authorize(
+        &self,
+        request: &mut reqwest::Request,
+        server: &str,
+        oauth2: &OAuth2Config,
+        client: &reqwest::Client,
+    ) -> Result<()> {
+        let authorization = self.tokens.authorization(server, oauth2, client).await?;
+        let value = authorization
+            .parse()
+            .map_err(|_| ButterflyBotError::Http("Invalid OAuth2 token".to_string()))?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
     fn build_headers(
         default_headers: &HashMap<String, String>,
         headers: Option<&Value>,
@@ -170,10 +220,16 @@ impl HttpCallTool {
                         "HTTP call server entry requires name and url".to_string(),
                     ));
                 }
+                let oauth2 = server
+                    .get("oauth2")
+                    .map(|oauth2| OAuth2Config::from_value(&name, oauth2))
+                    .transpose()?;
                 parsed.push(HttpCallServerConfig {
                     name,
                     url,
                     headers: Self::parse_headers(server.get("headers")),
+                    cache_ttl_seconds: server.get("cache_ttl_seconds").and_then(|v| v.as_u64()),
+                    oauth2,
                 });
             }
             return Ok(parsed);
@@ -195,6 +251,8 @@ impl HttpCallTool {
                     name: format!("server_{}", index + 1),
                     url,
                     headers: shared_headers.clone(),
+                    cache_ttl_seconds: None,
+                    oauth2: None,
                 });
             }
             if !parsed.is_empty() {
@@ -209,6 +267,8 @@ impl HttpCallTool {
                     name: "default".to_string(),
                     url,
                     headers: shared_headers,
+                    cache_ttl_seconds: None,
+                    oauth2: None,
                 });
             }
         }
@@ -251,6 +311,22 @@ impl HttpCallTool {
             ))
         }
     }
+    fn response_json(
+        server: Option<&HttpCallServerConfig>,
+        response: CachedResponse,
+        cache: &str,
+    ) -> Value {
+        let json_value = serde_json::from_str::<Value>(&response.text).ok();
+        json!({
+            "status": "ok",
+            "server": server.map(|server| server.name.clone()),
+            "http_status": response.status,
+            "headers": response.headers,
+            "text": response.text,
+            "json": json_value,
+            "cache": cache
+        })
+    }
 }
 
 #[async_trait]
@@ -260,7 +336,7 @@ impl Tool for HttpCallTool {
     }
 
     fn description(&self) -> &str {
-        "Perform arbitrary HTTP requests with custom headers and optional JSON/body payloads."
+        "Perform arbitrary HTTP requests with custom headers and optional JSON/body payloads. GET responses are cached per Cache-Control/ETag; pass cache=false to force a fresh request."
     }
 
     fn parameters(&self) -> Value {
@@ -275,12 +351,35 @@ impl Tool for HttpCallTool {
                 "query": { "type": "object" },
                 "body": { "type": "string" },
                 "json": { "type": "object" },
-                "timeout_seconds": { "type": "integer" }
+                "timeout_seconds": { "type": "integer" },
+                "cache": { "type": "boolean", "description": "Use the response cache for GET requests (default true)" }
             },
             "required": ["method"]
         })
     }
 
+    fn required_secrets_for_config(&self, config: &Value) -> Vec<ToolSecret> {
+        let Some(cfg) = config.get("tools").and_then(|v| v.get("http_call")) else {
+            return Vec::new();
+        };
+        let Ok(servers) = Self::parse_servers(cfg) else {
+            return Vec::new();
+        };
+        let mut secrets = Vec::new();
+        for server in &servers {
+            let Some(oauth2) = &server.oauth2 else {
+                continue;
+            };
+            for name in oauth2.secret_names() {
+                secrets.push(ToolSecret::new(
+                    name,
+                    &format!("OAuth2 {} for HTTP server {}", name, server.name),
+                ));
+            }
+        }
+        secrets
+    }
+
     fn configure(&self, config: &Value) -> Result<()> {
         let tool_cfg = config.get("tools").and_then(|v| v.get("http_call"));
         let mut next = HttpCallConfig::default();
@@ -289,7 +388,17 @@ impl Tool for HttpCallTool {
             if let Some(timeout) = cfg.get("timeout_seconds").and_then(|v| v.as_u64()) {
                 next.timeout_seconds = Some(timeout);
             }
+            if let Some(cache) = cfg.get("cache") {
+                if let Some(enabled) = cache.get("enabled").and_then(|v| v.as_bool()) {
+                    next.cache_enabled = enabled;
+                }
+                if let Some(max_entries) = cache.get("max_entries").and_then(|v| v.as_u64()) {
+                    next.cache_max_entries = max_entries as usize;
+                }
+            }
         }
+        self.with_cache(|cache| cache.resize(next.cache_max_entries));
+        self.tokens.clear();
 
         let mut guard = self
             .config
@@ -351,6 +460,31 @@ impl Tool for HttpCallTool {
         );
 
         let redacted_headers = Self::redact_headers(&headers);
+        let ttl = selected_server
+            .and_then(|server| server.cache_ttl_seconds)
+            .map(Duration::from_secs);
+        let caller_control =
+            CacheControl::parse(headers.get(CACHE_CONTROL).and_then(|v| v.to_str().ok()));
+        let use_cache = cfg.cache_enabled
+            && method == "GET"
+            && params
+                .get("cache")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true)
+            && !caller_control.no_cache
+            && !caller_control.no_store;
+        let mut cache_headers = headers.clone();
+        // Injected tokens rotate, so entries are keyed by the OAuth2 client
+        // rather than by the token itself.
+        let oauth2 = selected_server
+            .and_then(|server| Some((server.name.as_str(), server.oauth2.as_ref()?)))
+            .filter(|_| !headers.contains_key(AUTHORIZATION));
+        if let Some((_, oauth2)) = oauth2 {
+            let client_key = format!("oauth2 {} {}", oauth2.client_id, oauth2.token_url);
+            if let Ok(value) = client_key.parse() {
+                cache_headers.insert(AUTHORIZATION, value);
+            }
+        }
         if !headers.is_empty() {
             req = req.headers(headers);
         }
@@ -366,6 +500,35 @@ impl Tool for HttpCallTool {
 
         let timeout = timeout_override.or(cfg.timeout_seconds).unwrap_or(60);
         req = req.timeout(Duration::from_secs(timeout));
+        let mut request = req
+            .build()
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if let Some((name, oauth2)) = oauth2 {
+            self.authorize(&mut request, name, oauth2, &client).await?;
+        }
+
+        let cache_key =
+            use_cache.then(|| HttpCache::key(&method, request.url().as_str(), &cache_headers));
+        let mut revalidating = false;
+        if let Some(key) = &cache_key {
+            match self.with_cache(|cache| cache.lookup(key, Instant::now())) {
+                Lookup::Fresh(cached) => {
+                    info!(method = %method, url = %url, "HTTP call served from cache");
+                    return Ok(Self::response_json(selected_server, cached, "hit"));
+                }
+                Lookup::Stale(validators) => {
+                    let headers = request.headers_mut();
+                    if let Some(etag) = validators.etag.and_then(|v| v.parse().ok()) {
+                        headers.insert(IF_NONE_MATCH, etag);
+                    }
+                    if let Some(since) = validators.last_modified.and_then(|v| v.parse().ok()) {
+                        headers.insert(IF_MODIFIED_SINCE, since);
+                    }
+                    revalidating = true;
+                }
+                Lookup::Miss => {}
+            }
+        }
 
         info!(
             method = %method,
@@ -374,10 +537,21 @@ impl Tool for HttpCallTool {
             "HTTP call request"
         );
 
-        let response = req
-            .send()
+        let retry = oauth2.and_then(|oauth2| Some((oauth2, request.try_clone()?)));
+        let mut response = client
+            .execute(request)
             .await
             .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        // A 401 usually means the token was revoked early; fetch a new one
+        // and try once more.
+        if let (Some(((name, oauth2), mut request)), 401) = (retry, response.status().as_u16()) {
+            self.tokens.invalidate(name);
+            self.authorize(&mut request, name, oauth2, &client).await?;
+            response = client
+                .execute(request)
+                .await
+                .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        }
 
         let status = response.status().as_u16();
         info!(
@@ -392,19 +566,31 @@ impl Tool for HttpCallTool {
             .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
             .collect::<HashMap<_, _>>();
 
+        if let (Some(key), true, 304) = (&cache_key, revalidating, status) {
+            let refreshed =
+                self.with_cache(|cache| cache.revalidated(key, &headers, ttl, Instant::now()));
+            if let Some(cached) = refreshed {
+                return Ok(Self::response_json(selected_server, cached, "revalidated"));
+            }
+        }
+
         let text = response
             .text()
             .await
             .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
-        let json_value = serde_json::from_str::<Value>(&text).ok();
+        let response = CachedResponse {
+            status,
+            headers,
+            text,
+        };
+        let cache_state = match &cache_key {
+            Some(key) => {
+                self.with_cache(|cache| cache.store(key, response.clone(), ttl, Instant::now()));
+                "miss"
+            }
+            None => "bypass",
+        };
 
-        Ok(json!({
-            "status": "ok",
-            "server": selected_server.map(|server| server.name.clone()),
-            "http_status": status,
-            "headers": headers,
-            "text": text,
-            "json": json_value
-        }))
+        Ok(Self::response_json(selected_server, response, cache_state))
     }
 }
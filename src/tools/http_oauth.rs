@@ -0,0 +1,324 @@
+//! OAuth2 tokens for `http_call` servers.
+//!
+//! A server with an `oauth2` block gets its `Authorization` header from a
+//! token endpoint instead of static config:
+//!
+//! ```json
+//! {"name": "crm", "url": "https://api.example.com",
+//!  "oauth2": {"token_url": "https://auth.example.com/token",
+//!             "grant": "client_credentials", "client_id": "bot",
+//!             "scope": "read write"}}
+//! ```
+//!
+//! The client secret lives in the vault as `http_call_<server>_client_secret`
+//! (or `client_secret_name`). With `"grant": "refresh_token"` the refresh
+//! token is read from `http_call_<server>_refresh_token` (or
+//! `refresh_token_name`), and a rotated one is written back. Access tokens
+//! are kept in memory until shortly before they expire, and dropped when the
+//! API answers `401`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::vault;
+
+/// Tokens are renewed this long before they expire.
+const EXPIRY_SKEW: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grant {
+    ClientCredentials,
+    RefreshToken,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// `client_id`/`client_secret` in the form body.
+    Body,
+    /// HTTP basic auth against the token endpoint.
+    Basic,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OAuth2Config {
+    pub token_url: String,
+    pub grant: Grant,
+    pub client_id: String,
+    pub scope: Option<String>,
+    pub audience: Option<String>,
+    pub client_auth: ClientAuth,
+    pub client_secret_name: String,
+    pub refresh_token_name: String,
+}
+
+impl OAuth2Config {
+    pub fn from_value(server: &str, value: &Value) -> Result<Self> {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        let token_url = text("token_url").ok_or_else(|| {
+            ButterflyBotError::Config(format!(
+                "HTTP call server '{server}' oauth2 needs token_url"
+            ))
+        })?;
+        let client_id = text("client_id").ok_or_else(|| {
+            ButterflyBotError::Config(format!(
+                "HTTP call server '{server}' oauth2 needs client_id"
+            ))
+        })?;
+        let grant = match text("grant").as_deref() {
+            None | Some("client_credentials") => Grant::ClientCredentials,
+            Some("refresh_token") => Grant::RefreshToken,
+            Some(other) => {
+                return Err(ButterflyBotError::Config(format!(
+                    "HTTP call server '{server}' has unsupported oauth2 grant '{other}'"
+                )))
+            }
+        };
+        let client_auth = match text("client_auth").as_deref() {
+            Some("basic") => ClientAuth::Basic,
+            _ => ClientAuth::Body,
+        };
+        Ok(Self {
+            token_url,
+            grant,
+            client_id,
+            scope: text("scope"),
+            audience: text("audience"),
+            client_auth,
+            client_secret_name: text("client_secret_name")
+                .unwrap_or_else(|| format!("http_call_{server}_client_secret")),
+            refresh_token_name: text("refresh_token_name")
+                .unwrap_or_else(|| format!("http_call_{server}_refresh_token")),
+        })
+    }
+
+    /// Vault entries this config reads, for the secrets prompt.
+    pub fn secret_names(&self) -> Vec<&str> {
+        let mut names = vec![self.client_secret_name.as_str()];
+        if self.grant == Grant::RefreshToken {
+            names.push(self.refresh_token_name.as_str());
+        }
+        names
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AccessToken {
+    value: String,
+    token_type: String,
+    expires_at: Option<Instant>,
+}
+
+impl AccessToken {
+    fn usable_at(&self, now: Instant) -> bool {
+        self.expires_at
+            .is_none_or(|expires_at| now + EXPIRY_SKEW < expires_at)
+    }
+
+    fn header_value(&self) -> String {
+        // Servers answer "bearer" as often as "Bearer"; APIs expect the latter.
+        let token_type = if self.token_type.eq_ignore_ascii_case("bearer") {
+            "Bearer"
+        } else {
+            self.token_type.as_str()
+        };
+        format!("{token_type} {}", self.value)
+    }
+}
+
+/// Access tokens per server name.
+#[derive(Default)]
+pub struct TokenCache {
+    tokens: Mutex<HashMap<String, AccessToken>>,
+}
+
+impl TokenCache {
+    fn with_tokens<T>(&self, f: impl FnOnce(&mut HashMap<String, AccessToken>) -> T) -> T {
+        let mut tokens = match self.tokens.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        f(&mut tokens)
+    }
+
+    /// The `Authorization` header value for `server`, fetching a new token
+    /// when there is none or it is about to expire.
+    pub async fn authorization(
+        &self,
+        server: &str,
+        config: &OAuth2Config,
+        client: &reqwest::Client,
+    ) -> Result<String> {
+        let now = Instant::now();
+        let cached = self.with_tokens(|tokens| {
+            tokens
+                .get(server)
+                .filter(|token| token.usable_at(now))
+                .map(AccessToken::header_value)
+        });
+        if let Some(header) = cached {
+            return Ok(header);
+        }
+        let token = fetch_token(config, client).await?;
+        let header = token.header_value();
+        self.with_tokens(|tokens| tokens.insert(server.to_string(), token));
+        Ok(header)
+    }
+
+    pub fn invalidate(&self, server: &str) {
+        self.with_tokens(|tokens| tokens.remove(server));
+    }
+
+    pub fn clear(&self) {
+        self.with_tokens(HashMap::clear);
+    }
+}
+
+async fn fetch_token(config: &OAuth2Config, client: &reqwest::Client) -> Result<AccessToken> {
+    let client_secret = vault::get_secret(&config.client_secret_name)?
+        .map(|secret| secret.trim().to_string())
+        .filter(|secret| !secret.is_empty());
+    let mut form = vec![];
+    match config.grant {
+        Grant::ClientCredentials => form.push(("grant_type", "client_credentials".to_string())),
+        Grant::RefreshToken => {
+            let refresh_token = vault::get_secret(&config.refresh_token_name)?
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty())
+                .ok_or_else(|| {
+                    ButterflyBotError::Runtime(format!(
+                        "Missing OAuth2 refresh token; store it as {}",
+                        config.refresh_token_name
+                    ))
+                })?;
+            form.push(("grant_type", "refresh_token".to_string()));
+            form.push(("refresh_token", refresh_token));
+        }
+    }
+    if let Some(scope) = &config.scope {
+        form.push(("scope", scope.clone()));
+    }
+    if let Some(audience) = &config.audience {
+        form.push(("audience", audience.clone()));
+    }
+
+    let mut request = client.post(&config.token_url);
+    match config.client_auth {
+        ClientAuth::Basic => {
+            request = request.basic_auth(&config.client_id, client_secret.as_deref());
+        }
+        ClientAuth::Body => {
+            form.push(("client_id", config.client_id.clone()));
+            if let Some(secret) = client_secret {
+                form.push(("client_secret", secret));
+            }
+        }
+    }
+
+    let response = request
+        .form(&form)
+        .timeout(Duration::from_secs(30))
+        .send()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    let status = response.status();
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    if !status.is_success() {
+        let reason = body
+            .get("error_description")
+            .or_else(|| body.get("error"))
+            .and_then(Value::as_str)
+            .unwrap_or("no reason given");
+        return Err(ButterflyBotError::Http(format!(
+            "OAuth2 token request failed with {status}: {reason}"
+        )));
+    }
+
+    let value = body
+        .get("access_token")
+        .and_then(Value::as_str)
+        .filter(|token| !token.is_empty())
+        .ok_or_else(|| {
+            ButterflyBotError::Http("OAuth2 token response has no access_token".to_string())
+        })?;
+    if let Some(rotated) = body.get("refresh_token").and_then(Value::as_str) {
+        if config.grant == Grant::RefreshToken && !rotated.is_empty() {
+            vault::set_secret(&config.refresh_token_name, rotated)?;
+        }
+    }
+    Ok(AccessToken {
+        value: value.to_string(),
+        token_type: body
+            .get("token_type")
+            .and_then(Value::as_str)
+            .unwrap_or("Bearer")
+            .to_string(),
+        expires_at: body
+            .get("expires_in")
+            .and_then(|expires_in| {
+                expires_in
+                    .as_u64()
+                    .or_else(|| expires_in.as_str()?.parse().ok())
+            })
+            .map(|seconds| Instant::now() + Duration::from_secs(seconds)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use serde_json::json;
+
+    use super::{AccessToken, Grant, OAuth2Config};
+
+    #[test]
+    fn config_defaults_secret_names_from_the_server() {
+        let config = OAuth2Config::from_value(
+            "crm",
+            &json!({"token_url": "https://auth/token", "client_id": "bot", "grant": "refresh_token"}),
+        )
+        .unwrap();
+        assert_eq!(config.grant, Grant::RefreshToken);
+        assert_eq!(
+            config.secret_names(),
+            vec!["http_call_crm_client_secret", "http_call_crm_refresh_token"]
+        );
+        assert!(OAuth2Config::from_value("crm", &json!({"client_id": "bot"})).is_err());
+        assert!(OAuth2Config::from_value(
+            "crm",
+            &json!({"token_url": "u", "client_id": "bot", "grant": "password"})
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn tokens_are_renewed_before_they_expire() {
+        let now = Instant::now();
+        let token = AccessToken {
+            value: "t".to_string(),
+            token_type: "bearer".to_string(),
+            expires_at: Some(now + Duration::from_secs(20)),
+        };
+        assert!(!token.usable_at(now));
+        assert_eq!(token.header_value(), "Bearer t");
+        let lasting = AccessToken {
+            expires_at: None,
+            ..token
+        };
+        assert!(lasting.usable_at(now));
+    }
+}
@@ -1,26 +1,113 @@
 use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use rmcp::model::{CallToolRequestParams, PaginatedRequestParams};
+use rmcp::service::{Peer, RunningService};
 use rmcp::transport::streamable_http_client::StreamableHttpClientTransportConfig;
 use rmcp::transport::StreamableHttpClientTransport;
-use rmcp::ServiceExt;
+use rmcp::{RoleClient, ServiceExt};
 use serde_json::{json, Value};
-use tokio::sync::RwLock;
+use tokio::process::{Child, Command};
+use tokio::sync::{Mutex, RwLock};
 
 use crate::error::{ButterflyBotError, Result};
 use crate::interfaces::plugins::Tool;
 
+/// Restarts allowed inside [`RESTART_WINDOW`] before a crashing stdio
+/// server is reported as broken instead of being spawned again.
+const MAX_RESTARTS: usize = 3;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// Splits a settings-form command line into program and arguments.
+/// Single and double quotes group words; there is no escaping or expansion.
+pub fn split_command_line(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut in_word = false;
+    for ch in line.chars() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some(_), ch) => current.push(ch),
+            (None, '"' | '\'') => {
+                quote = Some(ch);
+                in_word = true;
+            }
+            (None, ch) if ch.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, ch) => {
+                current.push(ch);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+/// Inverse of [`split_command_line`] for display.
+pub fn join_command_line<'a>(words: impl IntoIterator<Item = &'a str>) -> String {
+    words
+        .into_iter()
+        .map(|word| {
+            if word.is_empty() || word.chars().any(char::is_whitespace) {
+                if word.contains('"') {
+                    format!("'{word}'")
+                } else {
+                    format!("\"{word}\"")
+                }
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Clone, Debug)]
 struct McpServerConfig {
     name: String,
     url: String,
     headers: HashMap<String, String>,
+    /// Set for local servers spoken to over stdin/stdout; `url` is empty.
+    stdio: Option<McpStdioCommand>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct McpStdioCommand {
+    command: String,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    cwd: Option<String>,
+}
+
+/// A running stdio server. The child is killed when this is dropped, so
+/// reconfiguring or dropping the tool never leaves servers behind.
+struct StdioProcess {
+    child: Child,
+    service: RunningService<RoleClient, ()>,
+}
+
+#[derive(Default)]
+struct StdioSlot {
+    command: Option<McpStdioCommand>,
+    process: Option<StdioProcess>,
+    restarts: Vec<Instant>,
 }
 
 pub struct McpTool {
     servers: RwLock<Vec<McpServerConfig>>,
+    stdio: std::sync::Mutex<HashMap<String, Arc<Mutex<StdioSlot>>>>,
 }
 
 impl Default for McpTool {
@@ -33,6 +120,7 @@ impl McpTool {
     pub fn new() -> Self {
         Self {
             servers: RwLock::new(Vec::new()),
+            stdio: std::sync::Mutex::new(HashMap::new()),
         }
     }
 
@@ -60,11 +148,17 @@ impl McpTool {
                 .unwrap_or_default()
                 .trim()
                 .to_string();
-            if name.is_empty() || url.is_empty() {
+            let stdio = Self::parse_stdio(server)?;
+            if name.is_empty() || (url.is_empty() && stdio.is_none()) {
                 return Err(ButterflyBotError::Config(
-                    "MCP server entry requires name and url".to_string(),
+                    "MCP server entry requires name and url (or command for stdio)".to_string(),
                 ));
             }
+            if !url.is_empty() && stdio.is_some() {
+                return Err(ButterflyBotError::Config(format!(
+                    "MCP server '{name}' sets both url and command; pick one transport"
+                )));
+            }
             let headers = server
                 .get("headers")
                 .and_then(|v| v.as_object())
@@ -74,11 +168,73 @@ impl McpTool {
                         .collect::<HashMap<_, _>>()
                 })
                 .unwrap_or_default();
-            parsed.push(McpServerConfig { name, url, headers });
+            parsed.push(McpServerConfig {
+                name,
+                url,
+                headers,
+                stdio,
+            });
         }
         Ok(parsed)
     }
 
+    fn parse_stdio(server: &Value) -> Result<Option<McpStdioCommand>> {
+        let command = server
+            .get("command")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .unwrap_or_default();
+        let transport = server.get("transport").and_then(|v| v.as_str());
+        if command.is_empty() {
+            if transport == Some("stdio") {
+                return Err(ButterflyBotError::Config(
+                    "MCP stdio server entry requires command".to_string(),
+                ));
+            }
+            return Ok(None);
+        }
+        let args = match server.get("args") {
+            None | Some(Value::Null) => Vec::new(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| {
+                    item.as_str().map(str::to_string).ok_or_else(|| {
+                        ButterflyBotError::Config(
+                            "MCP server args must be an array of strings".to_string(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+            Some(_) => {
+                return Err(ButterflyBotError::Config(
+                    "MCP server args must be an array of strings".to_string(),
+                ))
+            }
+        };
+        let mut env = server
+            .get("env")
+            .and_then(|v| v.as_object())
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|s| (k.clone(), s.to_string())))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        env.sort();
+        let cwd = server
+            .get("cwd")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|cwd| !cwd.is_empty())
+            .map(str::to_string);
+        Ok(Some(McpStdioCommand {
+            command: command.to_string(),
+            args,
+            env,
+            cwd,
+        }))
+    }
+
     async fn find_server(&self, name: Option<&str>) -> Result<McpServerConfig> {
         let servers = self.servers.read().await;
         if servers.is_empty() {
@@ -127,8 +283,8 @@ impl McpTool {
 
     async fn with_client<T, F>(&self, server: &McpServerConfig, operation: F) -> Result<T>
     where
-        F: for<'a> FnOnce(
-            &'a rmcp::service::Peer<rmcp::RoleClient>,
+        F: for<'a> Fn(
+            &'a Peer<RoleClient>,
         ) -> std::pin::Pin<
             Box<
                 dyn std::future::Future<Output = std::result::Result<T, rmcp::ServiceError>>
@@ -137,6 +293,11 @@ impl McpTool {
             >,
         >,
     {
+        if let Some(command) = &server.stdio {
+            return self
+                .with_stdio_client(&server.name, command, operation)
+                .await;
+        }
         let client = Self::build_http_client(server)?;
         let mut transport_config =
             StreamableHttpClientTransportConfig::with_uri(server.url.clone());
@@ -160,6 +321,129 @@ impl McpTool {
         result
     }
 
+    /// Runs `operation` against the server's long-lived child process,
+    /// starting it on first use and again whenever it has exited. A call
+    /// that fails because the process died mid-request is retried once on
+    /// a fresh process.
+    async fn with_stdio_client<T, F>(
+        &self,
+        name: &str,
+        command: &McpStdioCommand,
+        operation: F,
+    ) -> Result<T>
+    where
+        F: for<'a> Fn(
+            &'a Peer<RoleClient>,
+        ) -> std::pin::Pin<
+            Box<
+                dyn std::future::Future<Output = std::result::Result<T, rmcp::ServiceError>>
+                    + Send
+                    + 'a,
+            >,
+        >,
+    {
+        let slot = {
+            let mut slots = self
+                .stdio
+                .lock()
+                .map_err(|_| ButterflyBotError::Runtime("MCP stdio lock poisoned".to_string()))?;
+            slots.entry(name.to_string()).or_default().clone()
+        };
+        let mut slot = slot.lock().await;
+        if slot.command.as_ref() != Some(command) {
+            slot.command = Some(command.clone());
+            slot.process = None;
+            slot.restarts.clear();
+        }
+
+        let mut retried = false;
+        loop {
+            let peer = Self::ensure_stdio_process(name, command, &mut *slot)
+                .await?
+                .service
+                .peer()
+                .clone();
+            match operation(&peer).await {
+                Ok(value) => return Ok(value),
+                Err(err) => {
+                    let exited = slot
+                        .process
+                        .as_mut()
+                        .is_none_or(|process| !matches!(process.child.try_wait(), Ok(None)));
+                    if !exited || retried {
+                        return Err(ButterflyBotError::Runtime(err.to_string()));
+                    }
+                    tracing::warn!(
+                        server = name,
+                        error = %err,
+                        "MCP stdio server exited during call; restarting"
+                    );
+                    slot.process = None;
+                    retried = true;
+                }
+            }
+        }
+    }
+
+    async fn ensure_stdio_process<'s>(
+        name: &str,
+        command: &McpStdioCommand,
+        slot: &'s mut StdioSlot,
+    ) -> Result<&'s mut StdioProcess> {
+        if let Some(process) = slot.process.as_mut() {
+            if let Ok(Some(status)) = process.child.try_wait() {
+                tracing::warn!(server = name, %status, "MCP stdio server exited; restarting");
+                slot.process = None;
+            }
+        }
+        if slot.process.is_none() {
+            let now = Instant::now();
+            slot.restarts
+                .retain(|started| now.duration_since(*started) < RESTART_WINDOW);
+            if slot.restarts.len() > MAX_RESTARTS {
+                return Err(ButterflyBotError::Runtime(format!(
+                    "MCP server '{name}' keeps exiting ({} starts in {}s); check its command",
+                    slot.restarts.len(),
+                    RESTART_WINDOW.as_secs()
+                )));
+            }
+            slot.restarts.push(now);
+            slot.process = Some(Self::spawn_stdio(name, command).await?);
+        }
+        slot.process
+            .as_mut()
+            .ok_or_else(|| ButterflyBotError::Runtime("MCP stdio server not running".to_string()))
+    }
+
+    async fn spawn_stdio(name: &str, command: &McpStdioCommand) -> Result<StdioProcess> {
+        let mut cmd = Command::new(&command.command);
+        cmd.args(&command.args)
+            .envs(command.env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true);
+        if let Some(cwd) = &command.cwd {
+            cmd.current_dir(cwd);
+        }
+        let mut child = cmd.spawn().map_err(|err| {
+            ButterflyBotError::Runtime(format!(
+                "Failed to start MCP server '{name}' ({}): {err}",
+                command.command
+            ))
+        })?;
+        let (Some(stdout), Some(stdin)) = (child.stdout.take(), child.stdin.take()) else {
+            return Err(ButterflyBotError::Runtime(format!(
+                "MCP server '{name}' started without stdio pipes"
+            )));
+        };
+        let service = ().serve((stdout, stdin)).await.map_err(|err| {
+            ButterflyBotError::Runtime(format!("MCP server '{name}' failed to initialize: {err}"))
+        })?;
+        tracing::info!(server = name, pid = child.id(), "Started MCP stdio server");
+        Ok(StdioProcess { child, service })
+    }
+
     async fn list_tools(&self, server: &McpServerConfig) -> Result<Value> {
         let list = self
             .with_client(server, |peer| {
@@ -185,6 +469,8 @@ impl McpTool {
         let tool_name = tool_name.to_string();
         let result = self
             .with_client(server, |peer| {
+                let tool_name = tool_name.clone();
+                let args_map = args_map.clone();
                 Box::pin(async move {
                     peer.call_tool(CallToolRequestParams {
                         name: tool_name.into(),
@@ -207,7 +493,7 @@ impl Tool for McpTool {
     }
 
     fn description(&self) -> &str {
-        "Call tools on configured MCP servers (streamable HTTP)."
+        "Call tools on configured MCP servers (streamable HTTP or local stdio commands)."
     }
 
     fn parameters(&self) -> Value {
@@ -228,6 +514,15 @@ impl Tool for McpTool {
 
     fn configure(&self, config: &Value) -> Result<()> {
         let servers = Self::parse_servers(config)?;
+        // Stop stdio servers that were removed; changed commands are
+        // respawned on next use.
+        if let Ok(mut slots) = self.stdio.lock() {
+            slots.retain(|name, _| {
+                servers
+                    .iter()
+                    .any(|server| &server.name == name && server.stdio.is_some())
+            });
+        }
         let mut guard = self
             .servers
             .try_write()
@@ -290,6 +585,79 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_servers_reads_stdio_commands() {
+        let servers = McpTool::parse_servers(&json!({
+            "tools": {"mcp": {"servers": [{
+                "name": "files",
+                "command": "npx",
+                "args": ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"],
+                "env": {"B": "2", "A": "1", "skip": false}
+            }]}}
+        }))
+        .expect("stdio server should parse");
+        assert_eq!(servers[0].url, "");
+        let stdio = servers[0].stdio.as_ref().expect("stdio command");
+        assert_eq!(stdio.command, "npx");
+        assert_eq!(stdio.args.len(), 3);
+        assert_eq!(
+            stdio.env,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string())
+            ]
+        );
+
+        for bad in [
+            json!({"name": "x", "transport": "stdio"}),
+            json!({"name": "x", "command": "srv", "args": "--flag"}),
+            json!({"name": "x", "command": "srv", "url": "http://localhost:1"}),
+        ] {
+            McpTool::parse_servers(&json!({"tools": {"mcp": {"servers": [bad]}}}))
+                .expect_err("invalid stdio entry should fail");
+        }
+    }
+
+    #[test]
+    fn command_lines_round_trip_through_the_settings_form() {
+        let words = split_command_line(r#"uvx  mcp-server-git --repository "/home/me/my repo" ''"#);
+        assert_eq!(
+            words,
+            vec![
+                "uvx",
+                "mcp-server-git",
+                "--repository",
+                "/home/me/my repo",
+                ""
+            ]
+        );
+        let line = join_command_line(words.iter().map(String::as_str));
+        assert_eq!(split_command_line(&line), words);
+    }
+
+    #[tokio::test]
+    async fn stdio_server_that_fails_to_start_reports_the_command() {
+        let tool = McpTool::new();
+        tool.configure(&json!({
+            "tools": {"mcp": {"servers": [
+                {"name": "ghost", "command": "/nonexistent/butterfly-mcp-server"}
+            ]}}
+        }))
+        .expect("configure stdio server");
+
+        let err = tool
+            .execute(json!({"action": "list_tools"}))
+            .await
+            .expect_err("missing binary should fail");
+        let message = runtime_message(err);
+        assert!(message.contains("Failed to start MCP server 'ghost'"));
+        assert!(message.contains("/nonexistent/butterfly-mcp-server"));
+
+        tool.configure(&json!({"tools": {"mcp": {"servers": []}}}))
+            .expect("reconfigure");
+        assert!(tool.stdio.lock().unwrap().is_empty());
+    }
+
     #[test]
     fn parse_servers_keeps_string_headers_only() {
         let servers = McpTool::parse_servers(&json!({
@@ -382,6 +750,7 @@ mod tests {
             name: "ok".to_string(),
             url: "http://localhost:3001".to_string(),
             headers: HashMap::from([("x-token".to_string(), "abc".to_string())]),
+            stdio: None,
         };
         McpTool::build_http_client(&valid_server).expect("valid headers should build client");
 
@@ -389,6 +758,7 @@ mod tests {
             name: "bad".to_string(),
             url: "http://localhost:3001".to_string(),
             headers: HashMap::from([("bad header".to_string(), "abc".to_string())]),
+            stdio: None,
         };
         let err = McpTool::build_http_client(&invalid_server).expect_err("invalid header");
         match err {
@@ -1,12 +1,16 @@
 pub mod coding;
 pub mod github;
+pub mod http_cache;
 pub mod http_call;
+pub mod http_oauth;
 pub mod mcp;
 pub mod planning;
 pub mod reminders;
 pub mod search_internet;
+pub mod search_providers;
 pub mod solana;
 pub mod tasks;
 pub mod todo;
 pub mod wakeup;
+pub mod web_page;
 pub mod zapier;
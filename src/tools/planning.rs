@@ -4,16 +4,35 @@ use std::collections::HashSet;
 use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
+use crate::inbox_state::{InboxStateStore, AUTHOR_AGENT};
 use crate::interfaces::plugins::Tool;
-use crate::planning::{default_plan_db_path, resolve_plan_db_path, PlanStore};
+use crate::labels::{self, LabelFilter, LabelStore, LabelTarget};
+use crate::planning::negotiation::{self, CapacityModel, PROPOSAL_STATUS};
+use crate::planning::templates::PlanTemplate;
+use crate::planning::{
+    default_plan_db_path, parse_step_status, resolve_plan_db_path, PlanItem, PlanStore,
+};
+use crate::projects::{ProjectStore, ProjectTarget};
+use crate::questions::QuestionStore;
+use crate::refs::{self, DependencyReport, RefResolver};
+use crate::reminders::ReminderStore;
 use crate::todo::{TodoStatus, TodoStore};
+use crate::trash;
 
 pub struct PlanningTool {
     sqlite_path: RwLock<Option<String>>,
     store: RwLock<Option<std::sync::Arc<PlanStore>>>,
     todo_store: RwLock<Option<std::sync::Arc<TodoStore>>>,
+    label_store: RwLock<Option<std::sync::Arc<LabelStore>>>,
+    project_store: RwLock<Option<std::sync::Arc<ProjectStore>>>,
+    question_store: RwLock<Option<std::sync::Arc<QuestionStore>>>,
+    capacity: RwLock<CapacityModel>,
 }
 
+/// Minutes before an unanswered question gets a reminder, unless the call
+/// sets `remind_in_minutes` (0 turns it off).
+const DEFAULT_QUESTION_REMINDER_MINUTES: i64 = 60;
+
 impl Default for PlanningTool {
     fn default() -> Self {
         Self::new()
@@ -26,6 +45,10 @@ impl PlanningTool {
             sqlite_path: RwLock::new(None),
             store: RwLock::new(None),
             todo_store: RwLock::new(None),
+            label_store: RwLock::new(None),
+            project_store: RwLock::new(None),
+            question_store: RwLock::new(None),
+            capacity: RwLock::new(CapacityModel::default()),
         }
     }
 
@@ -45,6 +68,148 @@ impl PlanningTool {
         Ok(store)
     }
 
+    async fn get_label_store(&self) -> Result<std::sync::Arc<LabelStore>> {
+        if let Some(store) = self.label_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let store = std::sync::Arc::new(LabelStore::new(path).await?);
+        let mut guard = self.label_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    async fn get_project_store(&self) -> Result<std::sync::Arc<ProjectStore>> {
+        if let Some(store) = self.project_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let store = std::sync::Arc::new(ProjectStore::new(path).await?);
+        let mut guard = self.project_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    /// Sets the plan's labels when the call carries a `labels` argument.
+    async fn apply_labels(
+        &self,
+        user_id: &str,
+        plan_id: i32,
+        params: &Value,
+    ) -> Result<Option<Vec<String>>> {
+        if params.get("labels").is_none_or(Value::is_null) {
+            return Ok(None);
+        }
+        let labels = self
+            .get_label_store()
+            .await?
+            .set_labels(
+                user_id,
+                LabelTarget::Plan,
+                plan_id,
+                &labels::parse_labels(params.get("labels")),
+            )
+            .await?;
+        Ok(Some(labels))
+    }
+
+    async fn get_question_store(&self) -> Result<std::sync::Arc<QuestionStore>> {
+        if let Some(store) = self.question_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let store = std::sync::Arc::new(QuestionStore::new(path).await?);
+        let mut guard = self.question_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    /// Files a blocking question for the human, with a reminder linked to it
+    /// so it doesn't sit unanswered.
+    async fn ask_question(&self, user_id: &str, params: &Value) -> Result<Value> {
+        let text = params
+            .get("question")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing question".to_string()))?;
+        let blocks_ref = params.get("blocks_ref").and_then(|v| v.as_str());
+        let question = self
+            .get_question_store()
+            .await?
+            .ask(user_id, text, blocks_ref)
+            .await?;
+
+        let remind_in = params
+            .get("remind_in_minutes")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(DEFAULT_QUESTION_REMINDER_MINUTES);
+        let reminder_id = if remind_in > 0 {
+            let path = self
+                .sqlite_path
+                .read()
+                .await
+                .clone()
+                .unwrap_or_else(default_plan_db_path);
+            let reminder = ReminderStore::new(path)
+                .await?
+                .create_linked_reminder(
+                    user_id,
+                    &format!("Answer: {}", question.question),
+                    question.asked_at + remind_in * 60,
+                    Some(&question.origin_ref()),
+                )
+                .await?;
+            Some(reminder.id)
+        } else {
+            None
+        };
+
+        Ok(json!({
+            "status": "ok",
+            "question": question,
+            "origin_ref": question.origin_ref(),
+            "reminder_id": reminder_id,
+            "note": "Ask the user this question in your reply. Their next message is recorded as the answer."
+        }))
+    }
+
+    /// Appends the agent's note to an inbox item's thread.
+    async fn add_note(&self, user_id: &str, params: &Value) -> Result<Value> {
+        let item_ref = params
+            .get("item_ref")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing item_ref".to_string()))?;
+        let text = params
+            .get("text")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing text".to_string()))?;
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let comment = InboxStateStore::new(path)
+            .await?
+            .add_comment(user_id, item_ref, AUTHOR_AGENT, text)
+            .await?;
+        Ok(json!({"status": "ok", "comment": comment}))
+    }
+
     async fn get_todo_store(&self) -> Result<std::sync::Arc<TodoStore>> {
         if let Some(store) = self.todo_store.read().await.as_ref() {
             return Ok(store.clone());
@@ -171,12 +336,8 @@ impl PlanningTool {
                     if trimmed.is_empty() {
                         return;
                     }
-                    let normalized = if trimmed.starts_with("plan_step:")
-                        || trimmed.starts_with("todo:")
-                        || trimmed.starts_with("task:")
-                        || trimmed.starts_with("reminder:")
-                    {
-                        trimmed.to_ascii_lowercase()
+                    let normalized = if refs::is_tracked(trimmed) {
+                        refs::canonical(trimmed)
                     } else if let Ok(step_index) = trimmed.parse::<usize>() {
                         format!("plan_step:{plan_id}:{step_index}")
                     } else {
@@ -539,6 +700,287 @@ impl PlanningTool {
 
         Ok(created)
     }
+
+    /// Persists a step's new status as its inbox state, which otherwise
+    /// keeps overriding the status stored on the step.
+    async fn sync_step_inbox(
+        &self,
+        user_id: &str,
+        step_ref: &str,
+        previous: Option<&str>,
+        status: &str,
+    ) -> Result<()> {
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let states = InboxStateStore::new(path).await?;
+        let from = match states.list_statuses(user_id, 5000).await?.remove(step_ref) {
+            Some(from) => from,
+            None => previous.unwrap_or("new").to_string(),
+        };
+        states.set_status(user_id, step_ref, status).await?;
+        if from != status {
+            states
+                .record_transition(user_id, step_ref, &from, status)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Completes (or reopens) the todo materialized from the step. Returns
+    /// whether the todo is now completed, or `None` when there is no todo.
+    async fn sync_step_todo(
+        &self,
+        user_id: &str,
+        step_ref: &str,
+        status: &str,
+    ) -> Result<Option<bool>> {
+        let todo_store = self.get_todo_store().await?;
+        let todo = todo_store
+            .list_items(user_id, TodoStatus::All, 5000)
+            .await?
+            .into_iter()
+            .find(|item| {
+                item.notes.as_deref().is_some_and(|notes| {
+                    notes.split('|').any(|part| {
+                        part.trim()
+                            .strip_prefix("PlanStepRef:")
+                            .is_some_and(|value| value.trim() == step_ref)
+                    })
+                })
+            });
+        let Some(todo) = todo else {
+            return Ok(None);
+        };
+        let completed = status == "done";
+        if todo.completed_at.is_some() != completed {
+            todo_store.set_completed(todo.id, completed).await?;
+        }
+        Ok(Some(completed))
+    }
+
+    /// Creates a plan with its project, labels, step todos and agenda check,
+    /// taking everything but the plan text from `params`.
+    async fn start_plan(
+        &self,
+        store: &PlanStore,
+        user_id: &str,
+        title: &str,
+        goal: &str,
+        steps: Option<&Value>,
+        params: &Value,
+    ) -> Result<Value> {
+        let normalized_steps = Self::normalize_steps_input(steps)?;
+        let status = params.get("status").and_then(|v| v.as_str());
+        let projects = self.get_project_store().await?;
+        let project = projects.resolve(user_id, params.get("project")).await?;
+        let plan = store
+            .create_plan(user_id, title, goal, normalized_steps.as_ref(), status)
+            .await?;
+        if let Some(project) = &project {
+            projects
+                .assign(user_id, ProjectTarget::Plan, plan.id, Some(project.id))
+                .await?;
+        }
+        let labels = self.apply_labels(user_id, plan.id, params).await?;
+        let todo_items_created = self
+            .materialize_steps_as_todos(user_id, plan.id, plan.steps.as_ref())
+            .await?;
+        let agenda_proposal = self.negotiate_agenda(store, user_id, &plan).await?;
+        let dependency_issues = self.dependency_issues(user_id, plan.id).await;
+        Ok(json!({
+            "status": "ok",
+            "plan": plan,
+            "labels": labels,
+            "project": project,
+            "todo_items_created": todo_items_created,
+            "agenda_proposal": agenda_proposal,
+            "dependency_issues": dependency_issues
+        }))
+    }
+
+    /// Broken dependency refs and dependency cycles among the plan's steps,
+    /// or `None` when there are none. A failed check is not worth failing
+    /// the plan write over, so it also yields `None`.
+    async fn dependency_issues(&self, user_id: &str, plan_id: i32) -> Option<DependencyReport> {
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_plan_db_path);
+        let resolver = RefResolver::load(&Value::Null, &path, user_id).await.ok()?;
+        let prefix = format!("plan_step:{plan_id}:");
+        let report = resolver.report_where(|item| item.origin_ref.starts_with(&prefix));
+        (!report.is_clean()).then_some(report)
+    }
+
+    /// Template steps are kept as written, placeholders included, and only
+    /// normalized once a plan is instantiated from them.
+    fn template_steps(steps: Option<&Value>) -> Result<Option<&Value>> {
+        match steps {
+            None | Some(Value::Null) => Ok(None),
+            Some(steps @ Value::Array(_)) => Ok(Some(steps)),
+            Some(_) => Err(ButterflyBotError::Runtime(
+                "Template steps must be an array".to_string(),
+            )),
+        }
+    }
+
+    /// The template named by `id`, or failing that by `name`.
+    async fn find_template(
+        store: &PlanStore,
+        user_id: &str,
+        params: &Value,
+    ) -> Result<PlanTemplate> {
+        let template = if let Some(id) = params.get("id").and_then(|v| v.as_i64()) {
+            store.get_template(user_id, id as i32).await?
+        } else if let Some(name) = params.get("name").and_then(|v| v.as_str()) {
+            store.find_template(user_id, name).await?
+        } else {
+            return Err(ButterflyBotError::Runtime(
+                "Missing template id or name".to_string(),
+            ));
+        };
+        template.ok_or_else(|| ButterflyBotError::Runtime("Template not found".to_string()))
+    }
+
+    /// Checks the plan's urgent steps against the capacity model and, when
+    /// they overcommit the horizon, files a trade-off proposal for approval.
+    /// Returns the pending proposal, if any.
+    async fn negotiate_agenda(
+        &self,
+        store: &PlanStore,
+        user_id: &str,
+        plan: &PlanItem,
+    ) -> Result<Option<PlanItem>> {
+        if !negotiation::is_active_plan(&plan.status) {
+            return Ok(None);
+        }
+        let incoming: Vec<_> = negotiation::plan_work_items(std::slice::from_ref(plan))
+            .into_iter()
+            .filter(|item| item.priority == "urgent")
+            .collect();
+        if incoming.is_empty() {
+            return Ok(None);
+        }
+
+        let plans = store.list_plans(user_id, 500).await?;
+        let trigger_refs: Vec<String> = incoming
+            .iter()
+            .map(|item| item.origin_ref.clone())
+            .collect();
+        if let Some(pending) = plans
+            .iter()
+            .find(|candidate| negotiation::is_pending_proposal_for(candidate, &trigger_refs))
+        {
+            return Ok(Some(pending.clone()));
+        }
+
+        let agenda = negotiation::plan_work_items(&plans);
+        let capacity = *self.capacity.read().await;
+        let Some(proposal) = negotiation::propose(&incoming, &agenda, capacity, now_ts()) else {
+            return Ok(None);
+        };
+        let steps = proposal.plan_steps();
+        let created = store
+            .create_plan(
+                user_id,
+                &proposal.plan_title(&plan.title),
+                &proposal.plan_goal(),
+                Some(&steps),
+                Some(PROPOSAL_STATUS),
+            )
+            .await?;
+        Ok(Some(created))
+    }
+
+    async fn pending_proposal(
+        &self,
+        store: &PlanStore,
+        user_id: &str,
+        id: i32,
+    ) -> Result<PlanItem> {
+        let plan = store.get_plan(id).await?;
+        if plan.user_id != user_id || plan.status != PROPOSAL_STATUS {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Plan {id} is not a pending agenda proposal"
+            )));
+        }
+        Ok(plan)
+    }
+
+    /// Applies each trade-off step of an approved proposal to the plan step
+    /// it targets. Returns how many steps were changed.
+    async fn apply_proposal(&self, store: &PlanStore, proposal: &PlanItem) -> Result<usize> {
+        let Some(steps) = proposal.steps.as_ref().and_then(|v| v.as_array()) else {
+            return Ok(0);
+        };
+        let mut applied = 0usize;
+        for step in steps {
+            let Some((plan_id, index)) = step
+                .get("target_ref")
+                .and_then(|v| v.as_str())
+                .and_then(parse_plan_step_ref)
+            else {
+                continue;
+            };
+            let target = store.get_plan(plan_id).await?;
+            if target.user_id != proposal.user_id {
+                continue;
+            }
+            let mut target_steps = target.steps.clone().unwrap_or_else(|| json!([]));
+            let Some(target_step) = target_steps
+                .as_array_mut()
+                .and_then(|items| items.get_mut(index))
+                .and_then(|item| item.as_object_mut())
+            else {
+                continue;
+            };
+            match step.get("action").and_then(|v| v.as_str()) {
+                Some("defer") => {
+                    let Some(until) = step.get("defer_until").and_then(|v| v.as_i64()) else {
+                        continue;
+                    };
+                    target_step.remove("due_date");
+                    target_step.insert("due_at".to_string(), json!(until));
+                }
+                Some("shrink") => {
+                    let Some(minutes) = step.get("target_minutes").and_then(|v| v.as_i64()) else {
+                        continue;
+                    };
+                    target_step.insert("estimate_likely_minutes".to_string(), json!(minutes));
+                    target_step.insert("estimate_optimistic_minutes".to_string(), json!(minutes));
+                    target_step.insert(
+                        "estimate_pessimistic_minutes".to_string(),
+                        json!(((minutes as f64) * 1.45).round() as i64),
+                    );
+                }
+                _ => continue,
+            }
+            store
+                .update_plan(plan_id, None, None, Some(&target_steps), None)
+                .await?;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+}
+
+fn parse_plan_step_ref(value: &str) -> Option<(i32, usize)> {
+    let rest = value.trim().strip_prefix("plan_step:")?;
+    let (plan_id, index) = rest.split_once(':')?;
+    Some((plan_id.parse().ok()?, index.parse().ok()?))
+}
+
+fn now_ts() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
 }
 
 #[async_trait]
@@ -548,7 +990,7 @@ impl Tool for PlanningTool {
     }
 
     fn description(&self) -> &str {
-        "Create and manage structured plans with goals and steps."
+        "Create and manage structured plans with goals and steps. Saving a plan with urgent steps that overcommit the agenda files a trade-off proposal (defer/shrink other work) for the user to approve or reject. action=chart renders a burndown or other chart inline. Plans can carry labels; list filters with labels_any / labels_all. Plans can belong to a project (see the todo tool); set it with project or on create, and pass project to list. When you cannot continue without the user, use action=ask with the question: it is tracked in their inbox with a reminder until they answer. Use action=step_status with id, step and status to mark a single step done, blocked or in progress. Use action=note with item_ref and text to add a timestamped note to an inbox item's thread (e.g. waiting on vendor reply); the user sees it on the item and it comes back as context when they reply about that item. Save repeated workflows with template_create (title, goal and steps may use {{placeholder}}s) and start them with instantiate_template, passing values for the placeholders. After create or update, dependency_issues lists step dependency refs that point nowhere and dependency cycles; fix them with update."
     }
 
     fn parameters(&self) -> Value {
@@ -557,10 +999,13 @@ impl Tool for PlanningTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["create", "list", "get", "update", "delete", "clear"]
+                    "enum": ["create", "list", "get", "update", "delete", "clear", "trash", "restore", "negotiate", "approve", "reject", "label", "project", "ask", "step_status", "note", "template_create", "template_list", "template_get", "template_update", "template_delete", "instantiate_template", "chart"]
                 },
                 "user_id": { "type": "string" },
-                "id": { "type": "integer" },
+                "id": { "type": "integer", "description": "Plan id, or template id for the template_* actions and instantiate_template" },
+                "name": { "type": "string", "description": "Template name (template_create, template_update); template_get and instantiate_template may use it instead of id" },
+                "values": { "type": "object", "description": "instantiate_template: value for each {{placeholder}} in the template" },
+                "batch_id": { "type": "string", "description": "Trash batch to restore; defaults to the latest clear" },
                 "title": { "type": "string" },
                 "goal": { "type": "string" },
                 "steps": {
@@ -593,8 +1038,22 @@ impl Tool for PlanningTool {
                         ]
                     }
                 },
-                "status": { "type": "string" },
-                "limit": { "type": "integer" }
+                "status": { "type": "string", "description": "Plan status; for step_status one of new, in_progress, blocked, done" },
+                "step": { "type": "integer", "description": "step_status: zero-based index of the step in the plan" },
+                "labels": { "type": "array", "items": { "type": "string" }, "description": "Labels to set (create, update, label)" },
+                "labels_any": { "type": "array", "items": { "type": "string" }, "description": "list: keep plans with at least one of these labels" },
+                "labels_all": { "type": "array", "items": { "type": "string" }, "description": "list: keep plans with every one of these labels" },
+                "project": {
+                    "type": ["string", "integer", "null"],
+                    "description": "Project name or id to put the plan in (create, project; null removes it) or to keep (list)"
+                },
+                "limit": { "type": "integer" },
+                "question": { "type": "string", "description": "ask: the question you are blocked on" },
+                "blocks_ref": { "type": "string", "description": "ask: origin ref of the work waiting on the answer, e.g. plan_step:4:2" },
+                "remind_in_minutes": { "type": "integer", "description": "ask: remind the user after this long (default 60, 0 for none)" },
+                "item_ref": { "type": "string", "description": "note: origin ref of the inbox item, e.g. todo:12 or plan_step:4:2" },
+                "text": { "type": "string", "description": "note: the note to add to the item's thread" },
+                "chart": crate::charts::chart_parameter_schema()
             },
             "required": ["action", "user_id"]
         })
@@ -607,6 +1066,11 @@ impl Tool for PlanningTool {
             .try_write()
             .map_err(|_| ButterflyBotError::Runtime("Planning tool lock busy".to_string()))?;
         *guard = path;
+        let mut capacity = self
+            .capacity
+            .try_write()
+            .map_err(|_| ButterflyBotError::Runtime("Planning tool lock busy".to_string()))?;
+        *capacity = CapacityModel::from_tools_config(config.get("tools"));
         Ok(())
     }
 
@@ -618,6 +1082,21 @@ impl Tool for PlanningTool {
             .to_string();
         let action = match action.as_str() {
             "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear",
+            "undo" | "undo_clear" | "untrash" => "restore",
+            "list_trash" => "trash",
+            "accept" => "approve",
+            "decline" => "reject",
+            "tag" | "set_labels" => "label",
+            "move" | "set_project" | "move_to_project" => "project",
+            "ask_human" | "question" => "ask",
+            "set_step_status" | "update_step" | "mark_step" => "step_status",
+            "comment" | "add_note" | "add_comment" => "note",
+            "create_template" | "save_template" => "template_create",
+            "list_templates" | "templates" => "template_list",
+            "get_template" => "template_get",
+            "update_template" => "template_update",
+            "delete_template" => "template_delete",
+            "instantiate" | "from_template" | "use_template" => "instantiate_template",
             other => other,
         };
         let user_id = params
@@ -638,20 +1117,145 @@ impl Tool for PlanningTool {
                     .get("goal")
                     .and_then(|v| v.as_str())
                     .ok_or_else(|| ButterflyBotError::Runtime("Missing goal".to_string()))?;
-                let normalized_steps = Self::normalize_steps_input(params.get("steps"))?;
-                let status = params.get("status").and_then(|v| v.as_str());
-                let plan = store
-                    .create_plan(user_id, title, goal, normalized_steps.as_ref(), status)
+                self.start_plan(&store, user_id, title, goal, params.get("steps"), &params)
+                    .await
+            }
+            "template_create" => {
+                let name = params
+                    .get("name")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing name".to_string()))?;
+                let title = params
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing title".to_string()))?;
+                let goal = params
+                    .get("goal")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing goal".to_string()))?;
+                let steps = Self::template_steps(params.get("steps"))?;
+                let template = store
+                    .create_template(user_id, name, title, goal, steps)
                     .await?;
-                let todo_items_created = self
-                    .materialize_steps_as_todos(user_id, plan.id, plan.steps.as_ref())
+                Ok(json!({"status": "ok", "template": template}))
+            }
+            "template_list" => {
+                let templates = store.list_templates(user_id, limit).await?;
+                Ok(json!({"status": "ok", "templates": templates}))
+            }
+            "template_get" => {
+                let template = Self::find_template(&store, user_id, &params).await?;
+                Ok(json!({"status": "ok", "template": template}))
+            }
+            "template_update" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let template = store
+                    .update_template(
+                        user_id,
+                        id,
+                        params.get("name").and_then(|v| v.as_str()),
+                        params.get("title").and_then(|v| v.as_str()),
+                        params.get("goal").and_then(|v| v.as_str()),
+                        Self::template_steps(params.get("steps"))?,
+                    )
+                    .await?
+                    .ok_or_else(|| {
+                        ButterflyBotError::Runtime(format!("Template {id} not found"))
+                    })?;
+                Ok(json!({"status": "ok", "template": template}))
+            }
+            "template_delete" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let deleted = store.delete_template(user_id, id).await?;
+                Ok(json!({"status": "ok", "deleted": deleted}))
+            }
+            "instantiate_template" => {
+                let template = Self::find_template(&store, user_id, &params).await?;
+                let empty = serde_json::Map::new();
+                let values = params
+                    .get("values")
+                    .and_then(|v| v.as_object())
+                    .unwrap_or(&empty);
+                let rendered = template.render(values)?;
+                let mut created = self
+                    .start_plan(
+                        &store,
+                        user_id,
+                        &rendered.title,
+                        &rendered.goal,
+                        rendered.steps.as_ref(),
+                        &params,
+                    )
                     .await?;
-                Ok(json!({"status": "ok", "plan": plan, "todo_items_created": todo_items_created}))
+                created["template"] = json!({"id": template.id, "name": template.name});
+                Ok(created)
             }
             "list" => {
-                let plans = store.list_plans(user_id, limit).await?;
+                let filter = LabelFilter::from_args(&params);
+                let scope = self
+                    .get_project_store()
+                    .await?
+                    .scope(user_id, ProjectTarget::Plan, params.get("project"))
+                    .await?;
+                let mut plans = store
+                    .list_plans(
+                        user_id,
+                        scope.scan_limit(labels::scan_limit(&filter, limit)),
+                    )
+                    .await?;
+                scope.retain(&mut plans, |plan| plan.id);
+                let mut plans = self
+                    .get_label_store()
+                    .await?
+                    .annotate(
+                        user_id,
+                        LabelTarget::Plan,
+                        plans,
+                        |plan| plan.id,
+                        &filter,
+                        limit,
+                    )
+                    .await?;
+                scope.attach(&mut plans);
                 Ok(json!({"status": "ok", "plans": plans}))
             }
+            "project" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let project = self
+                    .get_project_store()
+                    .await?
+                    .move_item(user_id, ProjectTarget::Plan, id, params.get("project"))
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "project": project}))
+            }
+            "label" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let plan = store.get_plan(id).await?;
+                if plan.user_id != user_id {
+                    return Err(ButterflyBotError::Runtime(format!("Plan {id} not found")));
+                }
+                let labels = self
+                    .apply_labels(user_id, id, &params)
+                    .await?
+                    .unwrap_or_default();
+                Ok(json!({"status": "ok", "id": id, "labels": labels}))
+            }
             "get" => {
                 let id = params
                     .get("id")
@@ -674,10 +1278,20 @@ impl Tool for PlanningTool {
                 let plan = store
                     .update_plan(id, title, goal, normalized_steps.as_ref(), status)
                     .await?;
+                let labels = self.apply_labels(user_id, plan.id, &params).await?;
                 let todo_items_created = self
                     .materialize_steps_as_todos(user_id, plan.id, plan.steps.as_ref())
                     .await?;
-                Ok(json!({"status": "ok", "plan": plan, "todo_items_created": todo_items_created}))
+                let agenda_proposal = self.negotiate_agenda(&store, user_id, &plan).await?;
+                let dependency_issues = self.dependency_issues(user_id, plan.id).await;
+                Ok(json!({
+                    "status": "ok",
+                    "plan": plan,
+                    "labels": labels,
+                    "todo_items_created": todo_items_created,
+                    "agenda_proposal": agenda_proposal,
+                    "dependency_issues": dependency_issues
+                }))
             }
             "delete" => {
                 let id = params
@@ -689,9 +1303,85 @@ impl Tool for PlanningTool {
                 Ok(json!({"status": "ok", "deleted": deleted}))
             }
             "clear" => {
-                let deleted = store.clear_plans(user_id).await?;
-                Ok(json!({"status": "ok", "deleted": deleted}))
+                let receipt = store.clear_plans_to_trash(user_id).await?;
+                Ok(trash::cleared_json(&receipt))
+            }
+            "trash" => Ok(json!({"status": "ok", "batches": store.list_trash(user_id).await?})),
+            "restore" => {
+                let batches = store.list_trash(user_id).await?;
+                let batch_id = trash::requested_batch(&params, &batches)?;
+                let restored = store.restore_trash(user_id, &batch_id).await?;
+                Ok(json!({"status": "ok", "restored": restored, "batch_id": batch_id}))
+            }
+            "negotiate" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let plan = store.get_plan(id).await?;
+                let agenda_proposal = self.negotiate_agenda(&store, user_id, &plan).await?;
+                Ok(json!({"status": "ok", "agenda_proposal": agenda_proposal}))
+            }
+            "approve" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let proposal = self.pending_proposal(&store, user_id, id).await?;
+                let applied = self.apply_proposal(&store, &proposal).await?;
+                let plan = store
+                    .update_plan(id, None, None, None, Some("done"))
+                    .await?;
+                Ok(json!({"status": "ok", "plan": plan, "applied": applied}))
+            }
+            "reject" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                self.pending_proposal(&store, user_id, id).await?;
+                let plan = store
+                    .update_plan(id, None, None, None, Some("rejected"))
+                    .await?;
+                Ok(json!({"status": "ok", "plan": plan}))
+            }
+            "step_status" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let index = params
+                    .get("step")
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing step".to_string()))?
+                    as usize;
+                let status = params
+                    .get("status")
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing status".to_string()))?;
+                let status = parse_step_status(status).ok_or_else(|| {
+                    ButterflyBotError::Runtime(format!("Unknown step status {status}"))
+                })?;
+                let (plan, previous) = store.set_step_status(user_id, id, index, status).await?;
+                let step_ref = format!("plan_step:{id}:{index}");
+                self.sync_step_inbox(user_id, &step_ref, previous.as_deref(), status)
+                    .await?;
+                let todo_completed = self.sync_step_todo(user_id, &step_ref, status).await?;
+                Ok(json!({
+                    "status": "ok",
+                    "plan": plan,
+                    "step_ref": step_ref,
+                    "step_status": status,
+                    "todo_completed": todo_completed
+                }))
             }
+            "ask" => self.ask_question(user_id, &params).await,
+            "note" => self.add_note(user_id, &params).await,
+            "chart" => crate::charts::execute_render(&params),
             _ => Err(ButterflyBotError::Runtime("Unsupported action".to_string())),
         }
     }
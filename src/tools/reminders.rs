@@ -6,13 +6,21 @@ use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
 use crate::interfaces::plugins::Tool;
+use crate::labels::{self, LabelFilter, LabelStore, LabelTarget};
+use crate::projects::{ProjectStore, ProjectTarget};
 use crate::reminders::{
-    default_reminder_db_path, resolve_reminder_db_path, ReminderStatus, ReminderStore,
+    default_reminder_db_path, parse_reminder_priority, resolve_reminder_db_path, DeliveryWindows,
+    ReminderStatus, ReminderStore,
 };
+use crate::smart_lists::SmartList;
+use crate::trash;
 
 pub struct RemindersTool {
     sqlite_path: RwLock<Option<String>>,
     store: RwLock<Option<std::sync::Arc<ReminderStore>>>,
+    label_store: RwLock<Option<std::sync::Arc<LabelStore>>>,
+    project_store: RwLock<Option<std::sync::Arc<ProjectStore>>>,
+    delivery_windows: RwLock<DeliveryWindows>,
 }
 
 impl Default for RemindersTool {
@@ -26,6 +34,9 @@ impl RemindersTool {
         Self {
             sqlite_path: RwLock::new(None),
             store: RwLock::new(None),
+            label_store: RwLock::new(None),
+            project_store: RwLock::new(None),
+            delivery_windows: RwLock::new(DeliveryWindows::default()),
         }
     }
 
@@ -45,6 +56,38 @@ impl RemindersTool {
         Ok(store)
     }
 
+    async fn get_label_store(&self) -> Result<std::sync::Arc<LabelStore>> {
+        if let Some(store) = self.label_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_reminder_db_path);
+        let store = std::sync::Arc::new(LabelStore::new(path).await?);
+        let mut guard = self.label_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    async fn get_project_store(&self) -> Result<std::sync::Arc<ProjectStore>> {
+        if let Some(store) = self.project_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_reminder_db_path);
+        let store = std::sync::Arc::new(ProjectStore::new(path).await?);
+        let mut guard = self.project_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
     fn parse_due_at_required(params: &Value) -> Result<i64> {
         if let Some(seconds) = params.get("delay_seconds").and_then(|v| v.as_i64()) {
             return Ok(now_ts() + seconds.max(0));
@@ -60,6 +103,25 @@ impl RemindersTool {
         ))
     }
 
+    /// Reads `delivery_window`, where `null` or an empty string clears it.
+    /// Anything else must name a configured window or parse as a literal one.
+    async fn parse_delivery_window(&self, params: &Value) -> Result<Option<String>> {
+        let Some(spec) = params
+            .get("delivery_window")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+        else {
+            return Ok(None);
+        };
+        if !self.delivery_windows.read().await.recognizes(spec) {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Unknown delivery window '{spec}'"
+            )));
+        }
+        Ok(Some(spec.to_string()))
+    }
+
     fn parse_due_at_optional(params: &Value) -> i64 {
         if let Some(seconds) = params.get("delay_seconds").and_then(|v| v.as_i64()) {
             return now_ts() + seconds.max(0);
@@ -81,7 +143,7 @@ impl Tool for RemindersTool {
     }
 
     fn description(&self) -> &str {
-        "Create, list, complete, delete, and snooze reminders (simple alarms/todos)."
+        "Create, list, complete, delete, and snooze reminders (simple alarms/todos). Reminders can carry a delivery window (e.g. '09:00-17:00 weekdays' or a configured name) so ones that fire outside it are held until it opens. Reminders can carry labels; list filters with labels_any / labels_all. Reminders can belong to a project (see the todo tool); set it with project or on create, and pass project to list. Reminders carry a priority (low, normal, high, urgent; set on create or with set_priority); high and urgent ones that go unacknowledged after firing are re-notified and then raised in chat."
     }
 
     fn parameters(&self) -> Value {
@@ -90,15 +152,37 @@ impl Tool for RemindersTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["create", "list", "complete", "delete", "snooze", "clear"]
+                    "enum": ["create", "list", "complete", "delete", "snooze", "clear", "trash", "restore", "set_delivery_window", "set_priority", "label", "project"]
                 },
                 "user_id": { "type": "string" },
                 "title": { "type": "string" },
                 "id": { "type": "integer" },
+                "batch_id": { "type": "string", "description": "Trash batch to restore; defaults to the latest clear" },
                 "due_at": { "type": "integer", "description": "Unix timestamp (seconds)" },
                 "delay_seconds": { "type": "integer", "description": "Delay from now in seconds" },
                 "in_seconds": { "type": "integer", "description": "Alias for delay_seconds" },
                 "status": { "type": "string", "enum": ["open", "completed", "all"] },
+                "priority": {
+                    "type": "string",
+                    "enum": ["low", "normal", "high", "urgent"],
+                    "description": "Priority to set (create, set_priority); defaults to a 'priority: high' tag in the title, else normal"
+                },
+                "delivery_window": {
+                    "type": ["string", "null"],
+                    "description": "Configured window name or spec like '09:00-17:00 mon-fri'; null clears it"
+                },
+                "list": {
+                    "type": "string",
+                    "enum": ["overdue", "today", "this_week", "later", "someday"],
+                    "description": "Smart list shortcut applied to open reminders"
+                },
+                "labels": { "type": "array", "items": { "type": "string" }, "description": "Labels to set (create, label)" },
+                "labels_any": { "type": "array", "items": { "type": "string" }, "description": "list: keep reminders with at least one of these labels" },
+                "labels_all": { "type": "array", "items": { "type": "string" }, "description": "list: keep reminders with every one of these labels" },
+                "project": {
+                    "type": ["string", "integer", "null"],
+                    "description": "Project name or id to put the reminder in (create, project; null removes it) or to keep (list)"
+                },
                 "limit": { "type": "integer" }
             },
             "required": ["action", "user_id"]
@@ -107,6 +191,12 @@ impl Tool for RemindersTool {
 
     fn configure(&self, config: &Value) -> Result<()> {
         let path = resolve_reminder_db_path(config);
+        let windows = DeliveryWindows::from_tools_config(config.get("tools"));
+        *self
+            .delivery_windows
+            .try_write()
+            .map_err(|_| ButterflyBotError::Runtime("Reminders tool lock busy".to_string()))? =
+            windows;
         let mut guard = self
             .sqlite_path
             .try_write()
@@ -127,6 +217,12 @@ impl Tool for RemindersTool {
             "done" | "finish" => "complete",
             "remove" | "erase" => "delete",
             "clear" | "clear_all" | "clear_reminders" => "clear",
+            "undo" | "undo_clear" | "untrash" => "restore",
+            "list_trash" => "trash",
+            "set_window" | "delivery_window" => "set_delivery_window",
+            "prioritize" | "priority" => "set_priority",
+            "tag" | "set_labels" => "label",
+            "move" | "set_project" | "move_to_project" => "project",
             other => other,
         };
         let user_id = params
@@ -144,7 +240,21 @@ impl Tool for RemindersTool {
                     .and_then(|v| v.as_str())
                     .ok_or_else(|| ButterflyBotError::Runtime("Missing title".to_string()))?;
                 let due_at = Self::parse_due_at_optional(&params);
-                let item = store.create_reminder(user_id, title, due_at).await?;
+                let delivery_window = self.parse_delivery_window(&params).await?;
+                let priority = parse_priority(&params)?;
+                let projects = self.get_project_store().await?;
+                let project = projects.resolve(user_id, params.get("project")).await?;
+                let mut item = store.create_reminder(user_id, title, due_at).await?;
+                if let Some(priority) = priority {
+                    store.set_priority(user_id, item.id, priority).await?;
+                    item.priority = priority.to_string();
+                }
+                if let Some(window) = delivery_window {
+                    store
+                        .set_delivery_window(user_id, item.id, Some(&window))
+                        .await?;
+                    item.delivery_window = Some(window);
+                }
                 if cfg!(debug_assertions) {
                     let path = self
                         .sqlite_path
@@ -157,14 +267,115 @@ impl Tool for RemindersTool {
                         item.id, user_id, item.due_at, path
                     );
                 }
-                Ok(json!({"status": "ok", "reminder": item}))
+                let mut response = json!({"status": "ok", "reminder": item});
+                if let Some(project) = project {
+                    projects
+                        .assign(user_id, ProjectTarget::Reminder, item.id, Some(project.id))
+                        .await?;
+                    response["project"] = json!(project);
+                }
+                let labels = labels::parse_labels(params.get("labels"));
+                if labels.is_empty() {
+                    return Ok(response);
+                }
+                let labels = self
+                    .get_label_store()
+                    .await?
+                    .set_labels(user_id, LabelTarget::Reminder, item.id, &labels)
+                    .await?;
+                response["labels"] = json!(labels);
+                Ok(response)
             }
             "list" => {
+                let filter = LabelFilter::from_args(&params);
+                let label_store = self.get_label_store().await?;
+                let scope = self
+                    .get_project_store()
+                    .await?
+                    .scope(user_id, ProjectTarget::Reminder, params.get("project"))
+                    .await?;
+                if let Some(raw_list) = params.get("list").and_then(|v| v.as_str()) {
+                    let list = SmartList::parse(raw_list).ok_or_else(|| {
+                        ButterflyBotError::Runtime(format!("Unknown smart list '{raw_list}'"))
+                    })?;
+                    let bounds = crate::timezones::smart_list_bounds(user_id, now_ts());
+                    let mut items = store
+                        .list_reminders(user_id, ReminderStatus::Open, 500)
+                        .await?;
+                    items.retain(|item| bounds.matches(list, Some(item.due_at)));
+                    scope.retain(&mut items, |item| item.id);
+                    let mut items = label_store
+                        .annotate(
+                            user_id,
+                            LabelTarget::Reminder,
+                            items,
+                            |item| item.id,
+                            &filter,
+                            limit,
+                        )
+                        .await?;
+                    scope.attach(&mut items);
+                    return Ok(json!({"status": "ok", "list": list.key(), "reminders": items}));
+                }
                 let status =
                     ReminderStatus::from_option(params.get("status").and_then(|v| v.as_str()));
-                let items = store.list_reminders(user_id, status, limit).await?;
+                let mut items = store
+                    .list_reminders(
+                        user_id,
+                        status,
+                        scope.scan_limit(labels::scan_limit(&filter, limit)),
+                    )
+                    .await?;
+                scope.retain(&mut items, |item| item.id);
+                let mut items = label_store
+                    .annotate(
+                        user_id,
+                        LabelTarget::Reminder,
+                        items,
+                        |item| item.id,
+                        &filter,
+                        limit,
+                    )
+                    .await?;
+                scope.attach(&mut items);
                 Ok(json!({"status": "ok", "reminders": items}))
             }
+            "label" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                if !store.owns_reminder(user_id, id).await? {
+                    return Err(ButterflyBotError::Runtime(format!(
+                        "Reminder {id} not found"
+                    )));
+                }
+                let labels = self
+                    .get_label_store()
+                    .await?
+                    .set_labels(
+                        user_id,
+                        LabelTarget::Reminder,
+                        id,
+                        &labels::parse_labels(params.get("labels")),
+                    )
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "labels": labels}))
+            }
+            "project" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let project = self
+                    .get_project_store()
+                    .await?
+                    .move_item(user_id, ProjectTarget::Reminder, id, params.get("project"))
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "project": project}))
+            }
             "complete" => {
                 let id = params
                     .get("id")
@@ -193,19 +404,62 @@ impl Tool for RemindersTool {
                 let updated = store.snooze_reminder(user_id, id, due_at).await?;
                 Ok(json!({"status": "ok", "snoozed": updated}))
             }
+            "set_delivery_window" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let delivery_window = self.parse_delivery_window(&params).await?;
+                let updated = store
+                    .set_delivery_window(user_id, id, delivery_window.as_deref())
+                    .await?;
+                Ok(json!({
+                    "status": "ok",
+                    "updated": updated,
+                    "delivery_window": delivery_window
+                }))
+            }
+            "set_priority" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let priority = parse_priority(&params)?
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing priority".to_string()))?;
+                let updated = store.set_priority(user_id, id, priority).await?;
+                Ok(json!({"status": "ok", "updated": updated, "priority": priority}))
+            }
             "clear" => {
                 let include_completed = matches!(
                     params.get("status").and_then(|v| v.as_str()),
                     Some("all") | Some("completed")
                 );
-                let deleted = store.delete_all(user_id, include_completed).await?;
-                Ok(json!({"status": "ok", "deleted": deleted}))
+                let receipt = store.clear_to_trash(user_id, include_completed).await?;
+                Ok(trash::cleared_json(&receipt))
+            }
+            "trash" => Ok(json!({"status": "ok", "batches": store.list_trash(user_id).await?})),
+            "restore" => {
+                let batches = store.list_trash(user_id).await?;
+                let batch_id = trash::requested_batch(&params, &batches)?;
+                let restored = store.restore_trash(user_id, &batch_id).await?;
+                Ok(json!({"status": "ok", "restored": restored, "batch_id": batch_id}))
             }
             _ => Err(ButterflyBotError::Runtime("Unsupported action".to_string())),
         }
     }
 }
 
+fn parse_priority(params: &Value) -> Result<Option<&'static str>> {
+    let Some(raw) = params.get("priority").and_then(|v| v.as_str()) else {
+        return Ok(None);
+    };
+    parse_reminder_priority(raw)
+        .map(Some)
+        .ok_or_else(|| ButterflyBotError::Runtime(format!("Unknown priority '{raw}'")))
+}
+
 fn now_ts() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
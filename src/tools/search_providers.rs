@@ -0,0 +1,417 @@
+//! Result-based backends for `search_internet`.
+//!
+//! Besides Grok, which answers in prose, the tool can query a search engine
+//! and hand back normalized `{title, url, snippet}` results:
+//!
+//! - `searxng`: a self-hosted SearXNG instance at `searxng_url`, through its
+//!   JSON API (`search.formats` must include `json` on the instance);
+//! - `brave`: the Brave Search API, with the key in the vault as
+//!   `search_internet_brave_api_key`;
+//! - `duckduckgo`: the DuckDuckGo HTML endpoint, no key needed.
+//!
+//! `brave_url` and `duckduckgo_url` override the public endpoints.
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use regex::Regex;
+use reqwest::{Client, Url};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::vault;
+
+pub const BRAVE_API_KEY: &str = "search_internet_brave_api_key";
+const BRAVE_URL: &str = "https://api.search.brave.com/res/v1/web/search";
+const DUCKDUCKGO_URL: &str = "https://html.duckduckgo.com/html/";
+/// DuckDuckGo answers clients without a browser-like agent with a captcha.
+pub(crate) const BROWSER_USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) butterfly-bot";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Endpoints and limits shared by the result-based providers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProviderSettings {
+    pub searxng_url: Option<String>,
+    pub brave_url: String,
+    pub duckduckgo_url: String,
+    pub max_results: usize,
+    pub timeout: u64,
+}
+
+impl Default for ProviderSettings {
+    fn default() -> Self {
+        Self {
+            searxng_url: None,
+            brave_url: BRAVE_URL.to_string(),
+            duckduckgo_url: DUCKDUCKGO_URL.to_string(),
+            max_results: 5,
+            timeout: 20,
+        }
+    }
+}
+
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The URL queried, for the network allowlist.
+    fn endpoint(&self) -> &str;
+
+    async fn search(&self, client: &Client, query: &str, limit: usize)
+        -> Result<Vec<SearchResult>>;
+}
+
+/// Names accepted in `provider` and `fallback` besides `grok`.
+pub const PROVIDERS: &[&str] = &["searxng", "brave", "duckduckgo"];
+
+pub fn provider_for(name: &str, settings: &ProviderSettings) -> Result<Box<dyn SearchProvider>> {
+    match name {
+        "searxng" => {
+            let base_url = settings.searxng_url.clone().ok_or_else(|| {
+                ButterflyBotError::Config(
+                    "tools.search_internet.searxng_url is required for the searxng provider"
+                        .to_string(),
+                )
+            })?;
+            Ok(Box::new(SearxngProvider { base_url }))
+        }
+        "brave" => Ok(Box::new(BraveProvider {
+            url: settings.brave_url.clone(),
+        })),
+        "duckduckgo" => Ok(Box::new(DuckDuckGoProvider {
+            url: settings.duckduckgo_url.clone(),
+        })),
+        other => Err(ButterflyBotError::Config(format!(
+            "Unknown search provider '{other}'"
+        ))),
+    }
+}
+
+pub struct SearxngProvider {
+    base_url: String,
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    fn name(&self) -> &'static str {
+        "searxng"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let url = format!("{}/search", self.base_url.trim_end_matches('/'));
+        let data = get_json(client.get(url).query(&[("q", query), ("format", "json")])).await?;
+        Ok(normalize(
+            data.get("results").and_then(Value::as_array),
+            "content",
+            limit,
+        ))
+    }
+}
+
+pub struct BraveProvider {
+    url: String,
+}
+
+#[async_trait]
+impl SearchProvider for BraveProvider {
+    fn name(&self) -> &'static str {
+        "brave"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let api_key = vault::get_secret(BRAVE_API_KEY)?
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| {
+                ButterflyBotError::Config(format!(
+                    "Brave Search API key not configured; store it as {BRAVE_API_KEY}"
+                ))
+            })?;
+        let count = limit.clamp(1, 20).to_string();
+        let data = get_json(
+            client
+                .get(&self.url)
+                .header("Accept", "application/json")
+                .header("X-Subscription-Token", api_key)
+                .query(&[("q", query), ("count", count.as_str())]),
+        )
+        .await?;
+        Ok(normalize(
+            data.get("web")
+                .and_then(|web| web.get("results"))
+                .and_then(Value::as_array),
+            "description",
+            limit,
+        ))
+    }
+}
+
+pub struct DuckDuckGoProvider {
+    url: String,
+}
+
+#[async_trait]
+impl SearchProvider for DuckDuckGoProvider {
+    fn name(&self) -> &'static str {
+        "duckduckgo"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    async fn search(
+        &self,
+        client: &Client,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let response = client
+            .post(&self.url)
+            .header("User-Agent", BROWSER_USER_AGENT)
+            .form(&[("q", query)])
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if !status.is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "DuckDuckGo answered {}",
+                status.as_u16()
+            )));
+        }
+        let mut results = parse_duckduckgo_html(&body);
+        results.truncate(limit);
+        Ok(results)
+    }
+}
+
+pub fn client(settings: &ProviderSettings) -> Result<Client> {
+    Client::builder()
+        .timeout(Duration::from_secs(settings.timeout))
+        .build()
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))
+}
+
+async fn get_json(request: reqwest::RequestBuilder) -> Result<Value> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(ButterflyBotError::Http(format!(
+            "Search request failed with {}: {}",
+            status.as_u16(),
+            text.chars().take(200).collect::<String>()
+        )));
+    }
+    response
+        .json::<Value>()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))
+}
+
+/// Maps provider JSON results onto [`SearchResult`], dropping entries
+/// without a URL and stripping the markup some APIs put in snippets.
+fn normalize(items: Option<&Vec<Value>>, snippet_key: &str, limit: usize) -> Vec<SearchResult> {
+    let text = |item: &Value, key: &str| {
+        item.get(key)
+            .and_then(Value::as_str)
+            .map(clean_html)
+            .unwrap_or_default()
+    };
+    items
+        .into_iter()
+        .flatten()
+        .filter_map(|item| {
+            let url = item.get("url").and_then(Value::as_str)?.trim().to_string();
+            if url.is_empty() {
+                return None;
+            }
+            Some(SearchResult {
+                title: text(item, "title"),
+                url,
+                snippet: text(item, snippet_key),
+            })
+        })
+        .take(limit)
+        .collect()
+}
+
+fn html_patterns() -> &'static (Regex, Regex, Regex) {
+    static PATTERNS: OnceLock<(Regex, Regex, Regex)> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        (
+            Regex::new(r#"(?s)<a[^>]*class="result__a"[^>]*href="([^"]*)"[^>]*>(.*?)</a>"#)
+                .expect("result link regex"),
+            Regex::new(r#"(?s)class="result__snippet"[^>]*>(.*?)</(?:a|div|td)>"#)
+                .expect("result snippet regex"),
+            Regex::new(r"(?s)<[^>]*>").expect("tag regex"),
+        )
+    })
+}
+
+/// Pulls results out of DuckDuckGo's HTML page. Links come wrapped in a
+/// `/l/?uddg=` redirect; ads, which link back to duckduckgo.com, are skipped.
+pub fn parse_duckduckgo_html(html: &str) -> Vec<SearchResult> {
+    let (link, snippet, _) = html_patterns();
+    let links = link.captures_iter(html).collect::<Vec<_>>();
+    links
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, caps)| {
+            let whole = caps.get(0)?;
+            let end = links
+                .get(idx + 1)
+                .and_then(|next| next.get(0))
+                .map(|next| next.start())
+                .unwrap_or(html.len());
+            let url = unwrap_redirect(&decode_entities(&caps[1]))?;
+            let snippet = snippet
+                .captures(&html[whole.end()..end])
+                .map(|caps| clean_html(&caps[1]))
+                .unwrap_or_default();
+            Some(SearchResult {
+                title: clean_html(&caps[2]),
+                url,
+                snippet,
+            })
+        })
+        .collect()
+}
+
+fn unwrap_redirect(href: &str) -> Option<String> {
+    let absolute = if href.starts_with("//") {
+        format!("https:{href}")
+    } else {
+        href.to_string()
+    };
+    let url = Url::parse(&absolute).ok()?;
+    let is_ddg = url
+        .host_str()
+        .is_some_and(|host| host == "duckduckgo.com" || host.ends_with(".duckduckgo.com"));
+    if !is_ddg {
+        return Some(absolute);
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "uddg")
+        .map(|(_, target)| target.into_owned())
+}
+
+pub(crate) fn clean_html(text: &str) -> String {
+    let (_, _, tag) = html_patterns();
+    let stripped = tag.replace_all(text, "");
+    decode_entities(&stripped)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Numbered plain-text listing for the model.
+pub fn format_results(results: &[SearchResult]) -> String {
+    results
+        .iter()
+        .enumerate()
+        .map(|(idx, result)| {
+            let mut entry = format!("[{}] {}\n{}", idx + 1, result.title, result.url);
+            if !result.snippet.is_empty() {
+                entry.push('\n');
+                entry.push_str(&result.snippet);
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{normalize, parse_duckduckgo_html, SearchResult};
+
+    #[test]
+    fn parses_duckduckgo_results_and_skips_ads() {
+        let html = r#"
+            <div class="result result--ad">
+              <a rel="nofollow" class="result__a" href="https://duckduckgo.com/y.js?ad_domain=x">Ad</a>
+            </div>
+            <div class="result">
+              <h2><a rel="nofollow" class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fwww.rust-lang.org%2F&amp;rut=abc">Rust <b>Programming</b> Language</a></h2>
+              <a class="result__snippet" href="x">A language empowering everyone &amp; fast.</a>
+            </div>
+            <div class="result">
+              <a class="result__a" href="https://doc.rust-lang.org/book/">The Book</a>
+            </div>"#;
+        assert_eq!(
+            parse_duckduckgo_html(html),
+            vec![
+                SearchResult {
+                    title: "Rust Programming Language".to_string(),
+                    url: "https://www.rust-lang.org/".to_string(),
+                    snippet: "A language empowering everyone & fast.".to_string(),
+                },
+                SearchResult {
+                    title: "The Book".to_string(),
+                    url: "https://doc.rust-lang.org/book/".to_string(),
+                    snippet: String::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn normalizes_json_results() {
+        let items = json!([
+            {"title": "<strong>Rust</strong> 1.80", "url": "https://blog.rust-lang.org", "description": "Released"},
+            {"title": "no url"},
+            {"title": "Third", "url": "https://example.com", "description": ""}
+        ]);
+        let results = normalize(items.as_array(), "description", 1);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Rust 1.80");
+        assert_eq!(results[0].snippet, "Released");
+    }
+}
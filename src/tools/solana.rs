@@ -178,7 +178,7 @@ impl Tool for SolanaTool {
     }
 
     fn description(&self) -> &str {
-        "Solana wallet operations: get wallet address, SOL balance, SPL-token balance by mint, simulate/submit SOL transfers (lamports), simulate/submit SPL-token transfers by mint+amount_atomic, and inspect transaction status/history."
+        "Solana wallet operations: get wallet address, SOL balance, SPL-token balance by mint, simulate/submit SOL transfers (lamports), simulate/submit SPL-token transfers by mint+amount_atomic, and inspect transaction status/history. action=chart renders balance history or spending over time as an inline chart."
     }
 
     fn parameters(&self) -> Value {
@@ -203,7 +203,8 @@ impl Tool for SolanaTool {
                         "status",
                         "signature_status",
                         "tx_history",
-                        "history"
+                        "history",
+                        "chart"
                     ]
                 },
                 "request_id": { "type": "string" },
@@ -219,7 +220,8 @@ impl Tool for SolanaTool {
                 "lamports": { "type": "integer" },
                 "amount_sol": { "type": "number", "description": "SOL amount (preferred over lamports when provided)" },
                 "signature": { "type": "string" },
-                "limit": { "type": "integer" }
+                "limit": { "type": "integer" },
+                "chart": crate::charts::chart_parameter_schema()
             },
             "required": ["action"]
         })
@@ -620,6 +622,7 @@ impl Tool for SolanaTool {
                     "entries": entries
                 }))
             }
+            "chart" => crate::charts::execute_render(&params),
             _ => Err(ButterflyBotError::Runtime("Unsupported action".to_string())),
         }
     }
@@ -4,11 +4,14 @@ use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
 use crate::interfaces::plugins::Tool;
+use crate::projects::{ProjectStore, ProjectTarget};
 use crate::tasks::{default_task_db_path, resolve_task_db_path, TaskStatus, TaskStore};
+use crate::trash;
 
 pub struct TasksTool {
     sqlite_path: RwLock<Option<String>>,
     store: RwLock<Option<std::sync::Arc<TaskStore>>>,
+    project_store: RwLock<Option<std::sync::Arc<ProjectStore>>>,
 }
 
 impl Default for TasksTool {
@@ -22,6 +25,7 @@ impl TasksTool {
         Self {
             sqlite_path: RwLock::new(None),
             store: RwLock::new(None),
+            project_store: RwLock::new(None),
         }
     }
 
@@ -40,6 +44,22 @@ impl TasksTool {
         *guard = Some(store.clone());
         Ok(store)
     }
+
+    async fn get_project_store(&self) -> Result<std::sync::Arc<ProjectStore>> {
+        if let Some(store) = self.project_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_task_db_path);
+        let store = std::sync::Arc::new(ProjectStore::new(path).await?);
+        let mut guard = self.project_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
 }
 
 #[async_trait]
@@ -49,7 +69,7 @@ impl Tool for TasksTool {
     }
 
     fn description(&self) -> &str {
-        "Schedule one-off or recurring tasks at specific times; cancelable."
+        "Schedule one-off or recurring tasks at specific times; cancelable. Tasks can belong to a project (see the todo tool); set it with project or on schedule, and pass project to list."
     }
 
     fn parameters(&self) -> Value {
@@ -58,7 +78,7 @@ impl Tool for TasksTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["schedule", "list", "cancel", "enable", "disable", "delete", "clear"]
+                    "enum": ["schedule", "list", "cancel", "enable", "disable", "delete", "clear", "trash", "restore", "project"]
                 },
                 "user_id": { "type": "string" },
                 "name": { "type": "string" },
@@ -67,7 +87,12 @@ impl Tool for TasksTool {
                 "interval_minutes": { "type": "integer", "description": "Recurring interval in minutes" },
                 "status": { "type": "string", "enum": ["enabled", "disabled", "all"] },
                 "limit": { "type": "integer" },
-                "id": { "type": "integer" }
+                "id": { "type": "integer" },
+                "batch_id": { "type": "string", "description": "Trash batch to restore; defaults to the latest clear" },
+                "project": {
+                    "type": ["string", "integer", "null"],
+                    "description": "Project name or id to put the task in (schedule, project; null removes it) or to keep (list)"
+                }
             },
             "required": ["action", "user_id"]
         })
@@ -91,6 +116,9 @@ impl Tool for TasksTool {
             .to_string();
         let action = match action.as_str() {
             "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear",
+            "undo" | "undo_clear" | "untrash" => "restore",
+            "list_trash" => "trash",
+            "move" | "set_project" | "move_to_project" => "project",
             other => other,
         };
         let user_id = params
@@ -116,16 +144,52 @@ impl Tool for TasksTool {
                     .and_then(|v| v.as_i64())
                     .ok_or_else(|| ButterflyBotError::Runtime("Missing run_at".to_string()))?;
                 let interval_minutes = params.get("interval_minutes").and_then(|v| v.as_i64());
+                let projects = self.get_project_store().await?;
+                let project = projects.resolve(user_id, params.get("project")).await?;
                 let task = store
                     .create_task(user_id, name, prompt, run_at, interval_minutes)
                     .await?;
-                Ok(json!({"status": "ok", "task": task}))
+                let Some(project) = project else {
+                    return Ok(json!({"status": "ok", "task": task}));
+                };
+                projects
+                    .assign(user_id, ProjectTarget::Task, task.id, Some(project.id))
+                    .await?;
+                Ok(json!({"status": "ok", "task": task, "project": project}))
             }
             "list" => {
                 let status = TaskStatus::from_option(params.get("status").and_then(|v| v.as_str()));
-                let tasks = store.list_tasks(user_id, status, limit).await?;
+                let scope = self
+                    .get_project_store()
+                    .await?
+                    .scope(user_id, ProjectTarget::Task, params.get("project"))
+                    .await?;
+                let mut tasks = store
+                    .list_tasks(user_id, status, scope.scan_limit(limit))
+                    .await?;
+                scope.retain(&mut tasks, |task| task.id);
+                tasks.truncate(limit);
+                let mut tasks = tasks
+                    .into_iter()
+                    .map(serde_json::to_value)
+                    .collect::<std::result::Result<Vec<_>, _>>()
+                    .map_err(|e| ButterflyBotError::Serialization(e.to_string()))?;
+                scope.attach(&mut tasks);
                 Ok(json!({"status": "ok", "tasks": tasks}))
             }
+            "project" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let project = self
+                    .get_project_store()
+                    .await?
+                    .move_item(user_id, ProjectTarget::Task, id, params.get("project"))
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "project": project}))
+            }
             "cancel" | "disable" => {
                 let id = params
                     .get("id")
@@ -155,8 +219,15 @@ impl Tool for TasksTool {
             }
             "clear" => {
                 let status = TaskStatus::from_option(params.get("status").and_then(|v| v.as_str()));
-                let deleted = store.clear_tasks(user_id, status).await?;
-                Ok(json!({"status": "ok", "deleted": deleted}))
+                let receipt = store.clear_tasks_to_trash(user_id, status).await?;
+                Ok(trash::cleared_json(&receipt))
+            }
+            "trash" => Ok(json!({"status": "ok", "batches": store.list_trash(user_id).await?})),
+            "restore" => {
+                let batches = store.list_trash(user_id).await?;
+                let batch_id = trash::requested_batch(&params, &batches)?;
+                let restored = store.restore_trash(user_id, &batch_id).await?;
+                Ok(json!({"status": "ok", "restored": restored, "batch_id": batch_id}))
             }
             _ => Err(ButterflyBotError::Runtime("Unsupported action".to_string())),
         }
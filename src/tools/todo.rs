@@ -1,14 +1,27 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
 use crate::interfaces::plugins::Tool;
-use crate::todo::{default_todo_db_path, resolve_todo_db_path, TodoStatus, TodoStore};
+use crate::labels::{self, LabelFilter, LabelStore, LabelTarget};
+use crate::projects::{ProjectStore, ProjectTarget};
+use crate::reminders::{default_reminder_db_path, resolve_reminder_db_path, ReminderStore};
+use crate::smart_lists::SmartList;
+use crate::todo::{
+    default_todo_db_path, resolve_todo_db_path, ChecklistSchedule, TodoStatus, TodoStore,
+};
+use crate::trash;
 
 pub struct TodoTool {
     sqlite_path: RwLock<Option<String>>,
     store: RwLock<Option<std::sync::Arc<TodoStore>>>,
+    label_store: RwLock<Option<std::sync::Arc<LabelStore>>>,
+    project_store: RwLock<Option<std::sync::Arc<ProjectStore>>>,
+    reminder_sqlite_path: RwLock<Option<String>>,
+    reminder_store: RwLock<Option<std::sync::Arc<ReminderStore>>>,
 }
 
 impl Default for TodoTool {
@@ -22,6 +35,10 @@ impl TodoTool {
         Self {
             sqlite_path: RwLock::new(None),
             store: RwLock::new(None),
+            label_store: RwLock::new(None),
+            project_store: RwLock::new(None),
+            reminder_sqlite_path: RwLock::new(None),
+            reminder_store: RwLock::new(None),
         }
     }
 
@@ -40,6 +57,88 @@ impl TodoTool {
         *guard = Some(store.clone());
         Ok(store)
     }
+
+    async fn get_label_store(&self) -> Result<std::sync::Arc<LabelStore>> {
+        if let Some(store) = self.label_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_todo_db_path);
+        let store = std::sync::Arc::new(LabelStore::new(path).await?);
+        let mut guard = self.label_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    async fn get_project_store(&self) -> Result<std::sync::Arc<ProjectStore>> {
+        if let Some(store) = self.project_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_todo_db_path);
+        let store = std::sync::Arc::new(ProjectStore::new(path).await?);
+        let mut guard = self.project_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    async fn get_reminder_store(&self) -> Result<std::sync::Arc<ReminderStore>> {
+        if let Some(store) = self.reminder_store.read().await.as_ref() {
+            return Ok(store.clone());
+        }
+        let path = self
+            .reminder_sqlite_path
+            .read()
+            .await
+            .clone()
+            .unwrap_or_else(default_reminder_db_path);
+        let store = std::sync::Arc::new(ReminderStore::new(path).await?);
+        let mut guard = self.reminder_store.write().await;
+        *guard = Some(store.clone());
+        Ok(store)
+    }
+
+    fn parse_remind_at(params: &Value, user_id: &str) -> Result<i64> {
+        if let Some(seconds) = params
+            .get("delay_seconds")
+            .or_else(|| params.get("in_seconds"))
+            .and_then(|v| v.as_i64())
+        {
+            return Ok(now_ts() + seconds.max(0));
+        }
+        if let Some(due_at) = params.get("due_at").and_then(|v| v.as_i64()) {
+            return Ok(if due_at >= 1_000_000_000_000 {
+                due_at / 1000
+            } else {
+                due_at
+            });
+        }
+        if let Some(when) = params.get("when").and_then(|v| v.as_str()) {
+            return crate::timezones::parse_phrase(user_id, when, now_ts()).ok_or_else(|| {
+                ButterflyBotError::Runtime(format!("Could not parse when '{when}'"))
+            });
+        }
+        Err(ButterflyBotError::Runtime(
+            "Missing due_at, delay_seconds or when".to_string(),
+        ))
+    }
+
+    fn checklist_id(params: &Value) -> Result<i32> {
+        params
+            .get("checklist_id")
+            .or_else(|| params.get("id"))
+            .and_then(|v| v.as_i64())
+            .map(|v| v as i32)
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing checklist_id".to_string()))
+    }
 }
 
 fn notes_with_explicit_sizing(
@@ -105,7 +204,7 @@ impl Tool for TodoTool {
     }
 
     fn description(&self) -> &str {
-        "Manage an ordered todo list (create, list, reorder, complete, delete, clear)."
+        "Manage an ordered todo list (create, list, reorder, complete, delete, clear, restore, remind, label, project, chart). Clears go to a trash and can be restored. Todos can carry labels such as 'work' or 'errand'; list filters with labels_any / labels_all. Projects group todos, plans, tasks and reminders: manage them with create_project, list_projects, update_project and delete_project, move a todo with project, and pass project to create or list."
     }
 
     fn parameters(&self) -> Value {
@@ -114,7 +213,12 @@ impl Tool for TodoTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["create", "list", "complete", "reopen", "delete", "clear", "reorder", "create_many"]
+                    "enum": [
+                        "create", "list", "complete", "reopen", "delete", "clear", "trash", "restore", "reorder", "create_many",
+                        "create_checklist", "list_checklists", "reset_checklist", "checklist_history", "delete_checklist",
+                        "remind", "label", "project", "create_project", "list_projects", "update_project",
+                        "delete_project", "chart"
+                    ]
                 },
                 "user_id": { "type": "string" },
                 "title": { "type": "string" },
@@ -125,6 +229,20 @@ impl Tool for TodoTool {
                 "estimate_likely_minutes": { "type": "integer" },
                 "estimate_pessimistic_minutes": { "type": "integer" },
                 "dependency_refs": { "type": "array", "items": { "type": "string" } },
+                "labels": { "type": "array", "items": { "type": "string" }, "description": "Labels to set (create, create_many items, label)" },
+                "labels_any": { "type": "array", "items": { "type": "string" }, "description": "list: keep todos with at least one of these labels" },
+                "labels_all": { "type": "array", "items": { "type": "string" }, "description": "list: keep todos with every one of these labels" },
+                "project": {
+                    "type": ["string", "integer"],
+                    "description": "Project name or id: the project to put a todo in (create, create_many items, project; null removes it), to keep (list), or to change (update_project, delete_project)"
+                },
+                "project_name": { "type": "string", "description": "Project name (create_project, update_project)" },
+                "description": { "type": "string", "description": "Project description (create_project, update_project)" },
+                "list": {
+                    "type": "string",
+                    "enum": ["overdue", "today", "this_week", "later", "someday"],
+                    "description": "Smart list shortcut; undated todos only appear in someday"
+                },
                 "items": {
                     "type": "array",
                     "items": {
@@ -138,15 +256,30 @@ impl Tool for TodoTool {
                                 "estimate_optimistic_minutes": { "type": "integer" },
                                 "estimate_likely_minutes": { "type": "integer" },
                                 "estimate_pessimistic_minutes": { "type": "integer" },
-                                "dependency_refs": { "type": "array", "items": { "type": "string" } }
+                                "dependency_refs": { "type": "array", "items": { "type": "string" } },
+                                "labels": { "type": "array", "items": { "type": "string" } },
+                                "project": { "type": ["string", "integer"] }
                             }}
                         ]
                     }
                 },
                 "status": { "type": "string", "enum": ["open", "completed", "all"] },
                 "limit": { "type": "integer" },
+                "chart": crate::charts::chart_parameter_schema(),
                 "id": { "type": "integer" },
-                "ordered_ids": { "type": "array", "items": { "type": "integer" } }
+                "batch_id": { "type": "string", "description": "Trash batch to restore; defaults to the latest clear" },
+                "ordered_ids": { "type": "array", "items": { "type": "integer" } },
+                "checklist_id": { "type": "integer" },
+                "due_at": { "type": "integer", "description": "Reminder time as a Unix timestamp (remind)" },
+                "delay_seconds": { "type": "integer", "description": "Reminder delay from now (remind)" },
+                "when": { "type": "string", "description": "Reminder time in plain English, e.g. 'friday 9am' (remind)" },
+                "reminder_title": { "type": "string", "description": "Reminder text; defaults to the todo title" },
+                "name": { "type": "string", "description": "Checklist name" },
+                "schedule": {
+                    "type": "string",
+                    "enum": ["daily", "weekly", "biweekly", "monthly"],
+                    "description": "How often a checklist resets to unchecked"
+                }
             },
             "required": ["action", "user_id"]
         })
@@ -159,6 +292,11 @@ impl Tool for TodoTool {
             .try_write()
             .map_err(|_| ButterflyBotError::Runtime("Todo tool lock busy".to_string()))?;
         *guard = path;
+        let mut reminder_guard = self
+            .reminder_sqlite_path
+            .try_write()
+            .map_err(|_| ButterflyBotError::Runtime("Todo tool lock busy".to_string()))?;
+        *reminder_guard = resolve_reminder_db_path(config);
         Ok(())
     }
 
@@ -174,6 +312,14 @@ impl Tool for TodoTool {
                 "create_many"
             }
             "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear",
+            "undo" | "undo_clear" | "untrash" => "restore",
+            "list_trash" => "trash",
+            "reset" | "reset_list" | "restart_checklist" => "reset_checklist",
+            "remind_me" | "set_reminder" | "add_reminder" => "remind",
+            "tag" | "set_labels" => "label",
+            "move" | "set_project" | "move_to_project" => "project",
+            "projects" => "list_projects",
+            "rename_project" => "update_project",
             other => other,
         };
         let user_id = params
@@ -212,6 +358,8 @@ impl Tool for TodoTool {
                         .map(|v| v as i32),
                 );
                 let dependency_refs = parse_dependency_refs(params.get("dependency_refs"));
+                let projects = self.get_project_store().await?;
+                let project = projects.resolve(user_id, params.get("project")).await?;
                 let item = store
                     .create_item(
                         user_id,
@@ -224,7 +372,26 @@ impl Tool for TodoTool {
                         },
                     )
                     .await?;
-                Ok(json!({"status": "ok", "item": item}))
+                if let Some(project) = &project {
+                    projects
+                        .assign(user_id, ProjectTarget::Todo, item.id, Some(project.id))
+                        .await?;
+                }
+                let labels = labels::parse_labels(params.get("labels"));
+                let mut response = json!({"status": "ok", "item": item});
+                if let Some(project) = project {
+                    response["project"] = json!(project);
+                }
+                if labels.is_empty() {
+                    return Ok(response);
+                }
+                let labels = self
+                    .get_label_store()
+                    .await?
+                    .set_labels(user_id, LabelTarget::Todo, item.id, &labels)
+                    .await?;
+                response["labels"] = json!(labels);
+                Ok(response)
             }
             "create_many" => {
                 let items = params
@@ -234,6 +401,7 @@ impl Tool for TodoTool {
                 if items.is_empty() {
                     return Err(ButterflyBotError::Runtime("items empty".to_string()));
                 }
+                let projects = self.get_project_store().await?;
                 let mut created = Vec::new();
                 for item in items {
                     match item {
@@ -265,6 +433,7 @@ impl Tool for TodoTool {
                                     .map(|v| v as i32),
                             );
                             let dependency_refs = parse_dependency_refs(map.get("dependency_refs"));
+                            let project = projects.resolve(user_id, map.get("project")).await?;
                             let created_item = store
                                 .create_item(
                                     user_id,
@@ -277,6 +446,28 @@ impl Tool for TodoTool {
                                     },
                                 )
                                 .await?;
+                            let labels = labels::parse_labels(map.get("labels"));
+                            if !labels.is_empty() {
+                                self.get_label_store()
+                                    .await?
+                                    .set_labels(
+                                        user_id,
+                                        LabelTarget::Todo,
+                                        created_item.id,
+                                        &labels,
+                                    )
+                                    .await?;
+                            }
+                            if let Some(project) = &project {
+                                projects
+                                    .assign(
+                                        user_id,
+                                        ProjectTarget::Todo,
+                                        created_item.id,
+                                        Some(project.id),
+                                    )
+                                    .await?;
+                            }
                             created.push(created_item);
                         }
                         _ => {
@@ -289,10 +480,140 @@ impl Tool for TodoTool {
                 Ok(json!({"status": "ok", "items": created}))
             }
             "list" => {
+                let filter = LabelFilter::from_args(&params);
+                let scope = self
+                    .get_project_store()
+                    .await?
+                    .scope(user_id, ProjectTarget::Todo, params.get("project"))
+                    .await?;
+                let scan = scope.scan_limit(labels::scan_limit(&filter, limit));
+                let label_store = self.get_label_store().await?;
+                if let Some(raw_list) = params.get("list").and_then(|v| v.as_str()) {
+                    let list = SmartList::parse(raw_list).ok_or_else(|| {
+                        ButterflyBotError::Runtime(format!("Unknown smart list '{raw_list}'"))
+                    })?;
+                    // Todos carry no due date, so they only ever land in Someday.
+                    let mut items = if list == SmartList::Someday {
+                        store.list_items(user_id, TodoStatus::Open, scan).await?
+                    } else {
+                        Vec::new()
+                    };
+                    scope.retain(&mut items, |item| item.id);
+                    let mut items = label_store
+                        .annotate(
+                            user_id,
+                            LabelTarget::Todo,
+                            items,
+                            |item| item.id,
+                            &filter,
+                            limit,
+                        )
+                        .await?;
+                    scope.attach(&mut items);
+                    return Ok(json!({"status": "ok", "list": list.key(), "items": items}));
+                }
                 let status = TodoStatus::from_option(params.get("status").and_then(|v| v.as_str()));
-                let items = store.list_items(user_id, status, limit).await?;
+                let mut items = store.list_items(user_id, status, scan).await?;
+                scope.retain(&mut items, |item| item.id);
+                let mut items = label_store
+                    .annotate(
+                        user_id,
+                        LabelTarget::Todo,
+                        items,
+                        |item| item.id,
+                        &filter,
+                        limit,
+                    )
+                    .await?;
+                scope.attach(&mut items);
                 Ok(json!({"status": "ok", "items": items}))
             }
+            "label" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                if store.get_item(user_id, id).await?.is_none() {
+                    return Err(ButterflyBotError::Runtime(format!("Todo {id} not found")));
+                }
+                let labels = self
+                    .get_label_store()
+                    .await?
+                    .set_labels(
+                        user_id,
+                        LabelTarget::Todo,
+                        id,
+                        &labels::parse_labels(params.get("labels")),
+                    )
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "labels": labels}))
+            }
+            "project" => {
+                let id = params
+                    .get("id")
+                    .and_then(|v| v.as_i64())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
+                    as i32;
+                let project = self
+                    .get_project_store()
+                    .await?
+                    .move_item(user_id, ProjectTarget::Todo, id, params.get("project"))
+                    .await?;
+                Ok(json!({"status": "ok", "id": id, "project": project}))
+            }
+            "create_project" => {
+                let name = params
+                    .get("project_name")
+                    .or_else(|| params.get("name"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| {
+                        ButterflyBotError::Runtime("Missing project_name".to_string())
+                    })?;
+                let project = self
+                    .get_project_store()
+                    .await?
+                    .create_project(
+                        user_id,
+                        name,
+                        params.get("description").and_then(|v| v.as_str()),
+                    )
+                    .await?;
+                Ok(json!({"status": "ok", "project": project}))
+            }
+            "list_projects" => {
+                let projects = self
+                    .get_project_store()
+                    .await?
+                    .list_projects(user_id)
+                    .await?;
+                Ok(json!({"status": "ok", "projects": projects}))
+            }
+            "update_project" => {
+                let projects = self.get_project_store().await?;
+                let project = projects
+                    .resolve(user_id, params.get("project"))
+                    .await?
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing project".to_string()))?;
+                let project = projects
+                    .update_project(
+                        user_id,
+                        project.id,
+                        params.get("project_name").and_then(|v| v.as_str()),
+                        params.get("description").and_then(|v| v.as_str()),
+                    )
+                    .await?;
+                Ok(json!({"status": "ok", "project": project}))
+            }
+            "delete_project" => {
+                let projects = self.get_project_store().await?;
+                let project = projects
+                    .resolve(user_id, params.get("project"))
+                    .await?
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing project".to_string()))?;
+                let detached = projects.delete_project(user_id, project.id).await?;
+                Ok(json!({"status": "ok", "deleted": project, "detached": detached}))
+            }
             "complete" => {
                 let id = params
                     .get("id")
@@ -300,7 +621,12 @@ impl Tool for TodoTool {
                     .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
                     as i32;
                 let item = store.set_completed(id, true).await?;
-                Ok(json!({"status": "ok", "item": item}))
+                let cleared = self
+                    .get_reminder_store()
+                    .await?
+                    .complete_linked_reminders(user_id, &format!("todo:{id}"))
+                    .await?;
+                Ok(json!({"status": "ok", "item": item, "cleared_reminders": cleared}))
             }
             "reopen" => {
                 let id = params
@@ -318,12 +644,25 @@ impl Tool for TodoTool {
                     .ok_or_else(|| ButterflyBotError::Runtime("Missing id".to_string()))?
                     as i32;
                 let deleted = store.delete_item(id).await?;
+                if deleted {
+                    self.get_reminder_store()
+                        .await?
+                        .complete_linked_reminders(user_id, &format!("todo:{id}"))
+                        .await?;
+                }
                 Ok(json!({"status": "ok", "deleted": deleted}))
             }
             "clear" => {
                 let status = TodoStatus::from_option(params.get("status").and_then(|v| v.as_str()));
-                let deleted = store.clear_items(user_id, status).await?;
-                Ok(json!({"status": "ok", "deleted": deleted}))
+                let receipt = store.clear_items_to_trash(user_id, status).await?;
+                Ok(trash::cleared_json(&receipt))
+            }
+            "trash" => Ok(json!({"status": "ok", "batches": store.list_trash(user_id).await?})),
+            "restore" => {
+                let batches = store.list_trash(user_id).await?;
+                let batch_id = trash::requested_batch(&params, &batches)?;
+                let restored = store.restore_trash(user_id, &batch_id).await?;
+                Ok(json!({"status": "ok", "restored": restored, "batch_id": batch_id}))
             }
             "reorder" => {
                 let ordered_ids = params
@@ -340,7 +679,121 @@ impl Tool for TodoTool {
                 store.reorder(user_id, &ids).await?;
                 Ok(json!({"status": "ok"}))
             }
+            "remind" => {
+                let remind_at = Self::parse_remind_at(&params, user_id)?;
+                let existing_id = params.get("id").and_then(|v| v.as_i64()).map(|v| v as i32);
+                let (item, created) = match existing_id {
+                    Some(id) => {
+                        let item = store.get_item(user_id, id).await?.ok_or_else(|| {
+                            ButterflyBotError::Runtime("Todo not found".to_string())
+                        })?;
+                        (item, false)
+                    }
+                    None => {
+                        let title =
+                            params
+                                .get("title")
+                                .and_then(|v| v.as_str())
+                                .ok_or_else(|| {
+                                    ButterflyBotError::Runtime("Missing id or title".to_string())
+                                })?;
+                        let notes = params.get("notes").and_then(|v| v.as_str());
+                        (store.create_item(user_id, title, notes, None).await?, true)
+                    }
+                };
+                let reminder_title = params
+                    .get("reminder_title")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(item.title.as_str());
+                let target_ref = format!("todo:{}", item.id);
+                let reminder = async {
+                    self.get_reminder_store()
+                        .await?
+                        .create_linked_reminder(
+                            user_id,
+                            reminder_title,
+                            remind_at,
+                            Some(&target_ref),
+                        )
+                        .await
+                }
+                .await;
+                // The todo and its reminder may live in different databases, so
+                // undo the todo by hand rather than leave half a pair behind.
+                let reminder = match reminder {
+                    Ok(reminder) => reminder,
+                    Err(err) => {
+                        if created {
+                            let _ = store.delete_item(item.id).await;
+                        }
+                        return Err(err);
+                    }
+                };
+                Ok(json!({"status": "ok", "item": item, "reminder": reminder}))
+            }
+            "create_checklist" => {
+                let name = params
+                    .get("name")
+                    .or_else(|| params.get("title"))
+                    .and_then(|v| v.as_str())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing name".to_string()))?;
+                let items: Vec<String> = params
+                    .get("items")
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| ButterflyBotError::Runtime("Missing items".to_string()))?
+                    .iter()
+                    .filter_map(|item| {
+                        item.as_str()
+                            .or_else(|| item.get("title").and_then(|v| v.as_str()))
+                            .map(str::to_string)
+                    })
+                    .collect();
+                let raw_schedule = params
+                    .get("schedule")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("weekly");
+                let schedule = ChecklistSchedule::parse(raw_schedule).ok_or_else(|| {
+                    ButterflyBotError::Runtime(format!("Unsupported schedule '{raw_schedule}'"))
+                })?;
+                let (checklist, items) = store
+                    .create_checklist(user_id, name, &items, schedule)
+                    .await?;
+                Ok(json!({"status": "ok", "checklist": checklist, "items": items}))
+            }
+            "list_checklists" => {
+                let checklists = store.list_checklists(user_id).await?;
+                Ok(json!({"status": "ok", "checklists": checklists}))
+            }
+            "reset_checklist" => {
+                let checklist_id = Self::checklist_id(&params)?;
+                match store.reset_checklist(user_id, checklist_id).await? {
+                    Some((run, items)) => Ok(json!({"status": "ok", "run": run, "items": items})),
+                    None => Err(ButterflyBotError::Runtime(
+                        "Checklist not found".to_string(),
+                    )),
+                }
+            }
+            "checklist_history" => {
+                let checklist_id = Self::checklist_id(&params)?;
+                let runs = store
+                    .checklist_history(user_id, checklist_id, limit)
+                    .await?;
+                Ok(json!({"status": "ok", "runs": runs}))
+            }
+            "delete_checklist" => {
+                let checklist_id = Self::checklist_id(&params)?;
+                let deleted = store.delete_checklist(user_id, checklist_id).await?;
+                Ok(json!({"status": "ok", "deleted": deleted}))
+            }
+            "chart" => crate::charts::execute_render(&params),
             _ => Err(ButterflyBotError::Runtime("Unsupported action".to_string())),
         }
     }
 }
+
+fn now_ts() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
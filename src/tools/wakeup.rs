@@ -4,7 +4,9 @@ use tokio::sync::RwLock;
 
 use crate::error::{ButterflyBotError, Result};
 use crate::interfaces::plugins::Tool;
-use crate::wakeup::{default_wakeup_db_path, resolve_wakeup_db_path, WakeupStatus, WakeupStore};
+use crate::wakeup::{
+    default_wakeup_db_path, resolve_wakeup_db_path, WakeupOptions, WakeupStatus, WakeupStore,
+};
 
 pub struct WakeupTool {
     sqlite_path: RwLock<Option<String>>,
@@ -64,6 +66,9 @@ impl Tool for WakeupTool {
                 "name": { "type": "string" },
                 "prompt": { "type": "string" },
                 "interval_minutes": { "type": "integer" },
+                "jitter_seconds": { "type": "integer", "description": "Random delay of up to this many seconds added to each next run" },
+                "max_concurrency": { "type": "integer", "description": "Runs allowed at once; due runs are skipped while this many are still executing (default 1)" },
+                "max_backoff_minutes": { "type": "integer", "description": "After consecutive failures, double the interval per failure up to this cap; 0 disables backoff" },
                 "status": { "type": "string", "enum": ["enabled", "disabled", "all"] },
                 "limit": { "type": "integer" },
                 "id": { "type": "integer" }
@@ -112,8 +117,18 @@ impl Tool for WakeupTool {
                     .ok_or_else(|| {
                         ButterflyBotError::Runtime("Missing interval_minutes".to_string())
                     })?;
+                let defaults = WakeupOptions::default();
+                let option = |key: &str| params.get(key).and_then(|v| v.as_i64());
+                let options = WakeupOptions {
+                    jitter_seconds: option("jitter_seconds").unwrap_or(defaults.jitter_seconds),
+                    max_concurrency: option("max_concurrency")
+                        .map(|value| value.clamp(1, i32::MAX as i64) as i32)
+                        .unwrap_or(defaults.max_concurrency),
+                    max_backoff_minutes: option("max_backoff_minutes")
+                        .unwrap_or(defaults.max_backoff_minutes),
+                };
                 let item = store
-                    .create_task(user_id, name, prompt, interval_minutes)
+                    .create_task_with_options(user_id, name, prompt, interval_minutes, options)
                     .await?;
                 Ok(json!({"status": "ok", "task": item}))
             }
@@ -0,0 +1,164 @@
+//! Readable text from a fetched web page, for `search.fetch_and_summarize`.
+//!
+//! Pages are read up to [`MAX_PAGE_BYTES`]; scripts, styles and page chrome
+//! (navigation, headers, footers, forms) are dropped, and `<article>` or
+//! `<main>` is preferred over the whole body when the page has one.
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+use reqwest::Client;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::tools::search_providers::{clean_html, BROWSER_USER_AGENT};
+
+pub const MAX_PAGE_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Page {
+    pub title: Option<String>,
+    pub text: String,
+    /// The body was cut at [`MAX_PAGE_BYTES`].
+    pub truncated: bool,
+}
+
+struct Patterns {
+    dropped: Vec<Regex>,
+    comment: Regex,
+    title: Regex,
+    main: Vec<Regex>,
+    block: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        // The regex crate has no backreferences, so one pattern per element.
+        let element = |tag: &str| {
+            Regex::new(&format!(r"(?is)<{tag}\b[^>]*>.*?</{tag}\s*>")).expect("element regex")
+        };
+        Patterns {
+            dropped: [
+                "script", "style", "noscript", "template", "svg", "iframe", "nav", "header",
+                "footer", "aside", "form",
+            ]
+            .into_iter()
+            .map(element)
+            .collect(),
+            comment: Regex::new(r"(?s)<!--.*?-->").expect("comment regex"),
+            title: Regex::new(r"(?is)<title\b[^>]*>(.*?)</title\s*>").expect("title regex"),
+            main: ["article", "main"].into_iter().map(element).collect(),
+            block: Regex::new(
+                r"(?i)</?(?:p|div|br|li|ul|ol|h[1-6]|tr|table|section|article|main|blockquote|pre)\b[^>]*>",
+            )
+            .expect("block regex"),
+        }
+    })
+}
+
+pub async fn fetch(client: &Client, url: &str) -> Result<Page> {
+    let mut response = client
+        .get(url)
+        .header("User-Agent", BROWSER_USER_AGENT)
+        .header("Accept", "text/html,text/plain;q=0.9,*/*;q=0.5")
+        .send()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(ButterflyBotError::Http(format!(
+            "{url} answered {}",
+            status.as_u16()
+        )));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("text/html")
+        .to_ascii_lowercase();
+    let is_html = content_type.contains("html");
+    if !is_html && !content_type.starts_with("text/") && !content_type.contains("json") {
+        return Err(ButterflyBotError::Runtime(format!(
+            "Cannot summarize {content_type} content"
+        )));
+    }
+
+    let mut body = Vec::new();
+    let mut truncated = false;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?
+    {
+        let room = MAX_PAGE_BYTES - body.len();
+        if chunk.len() >= room {
+            body.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let body = String::from_utf8_lossy(&body);
+
+    let (title, text) = if is_html {
+        readable_text(&body)
+    } else {
+        (None, body.trim().to_string())
+    };
+    Ok(Page {
+        title,
+        text,
+        truncated,
+    })
+}
+
+/// The page title and its readable text, one paragraph per line.
+pub fn readable_text(html: &str) -> (Option<String>, String) {
+    let patterns = patterns();
+    let title = patterns
+        .title
+        .captures(html)
+        .map(|caps| clean_html(&caps[1]))
+        .filter(|title| !title.is_empty());
+
+    let mut html = patterns.comment.replace_all(html, " ").into_owned();
+    for pattern in &patterns.dropped {
+        html = pattern.replace_all(&html, " ").into_owned();
+    }
+    if let Some(main) = patterns.main.iter().find_map(|pattern| pattern.find(&html)) {
+        html = main.as_str().to_string();
+    }
+
+    // Source line breaks mean nothing in HTML; block elements make the lines.
+    let html = html.replace(['\n', '\r'], " ");
+    let html = patterns.block.replace_all(&html, "\n");
+    let text = html
+        .lines()
+        .map(clean_html)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (title, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::readable_text;
+
+    #[test]
+    fn keeps_article_text_and_drops_chrome() {
+        let html = r#"<html><head><title>Rust 1.80 &amp; more</title>
+            <style>p { color: red }</style><script>track()</script></head>
+            <body><nav><a href="/">Home</a></nav>
+            <article><h1>Rust 1.80</h1><p>LazyCell is <b>stable</b>.</p><!-- ad -->
+            <p>Exclusive ranges in patterns.</p></article>
+            <footer>Copyright</footer></body></html>"#;
+        let (title, text) = readable_text(html);
+        assert_eq!(title.as_deref(), Some("Rust 1.80 & more"));
+        assert_eq!(
+            text,
+            "Rust 1.80\nLazyCell is stable.\nExclusive ranges in patterns."
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -9,11 +10,180 @@ use crate::interfaces::plugins::{Tool, ToolSecret};
 use crate::tools::mcp::McpTool;
 use crate::vault;
 
+/// JSON type a Zap payload field must have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldKind {
+    String,
+    Number,
+    Integer,
+    Boolean,
+    Object,
+    Array,
+}
+
+impl FieldKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "string" | "text" => Some(FieldKind::String),
+            "number" => Some(FieldKind::Number),
+            "integer" | "int" => Some(FieldKind::Integer),
+            "boolean" | "bool" => Some(FieldKind::Boolean),
+            "object" => Some(FieldKind::Object),
+            "array" | "list" => Some(FieldKind::Array),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FieldKind::String => "string",
+            FieldKind::Number => "number",
+            FieldKind::Integer => "integer",
+            FieldKind::Boolean => "boolean",
+            FieldKind::Object => "object",
+            FieldKind::Array => "array",
+        }
+    }
+
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            FieldKind::String => value.is_string(),
+            FieldKind::Number => value.is_number(),
+            FieldKind::Integer => value.is_i64() || value.is_u64(),
+            FieldKind::Boolean => value.is_boolean(),
+            FieldKind::Object => value.is_object(),
+            FieldKind::Array => value.is_array(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZapField {
+    pub name: String,
+    pub kind: FieldKind,
+    pub required: bool,
+}
+
+/// A Zap started by a "Catch Hook" trigger, from `tools.zapier.zaps`:
+///
+/// ```json
+/// {"name": "new_lead", "url": "https://hooks.zapier.com/hooks/catch/1/abc",
+///  "description": "Add a lead to the CRM",
+///  "fields": {"email": "string", "amount": "number?"}}
+/// ```
+///
+/// A `?` marks an optional field. With `fields` set, payloads are checked
+/// against them and unknown keys are refused; without, anything goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZapWebhook {
+    pub name: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub fields: Vec<ZapField>,
+}
+
+impl ZapWebhook {
+    /// Every outbound Zap in `tools.zapier.zaps`; entries without a name or
+    /// URL are inbound-only or incomplete and skipped.
+    pub fn from_root_config(config: &Value) -> Vec<Self> {
+        ZapierTool::get_tool_config(config)
+            .and_then(|tool_cfg| tool_cfg.get("zaps"))
+            .and_then(Value::as_array)
+            .map(|zaps| zaps.iter().filter_map(Self::from_value).collect())
+            .unwrap_or_default()
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        let text = |key: &str| {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        let fields = value
+            .get("fields")
+            .and_then(Value::as_object)
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|(name, kind)| {
+                        let kind = kind.as_str()?.trim();
+                        let (kind, required) = match kind.strip_suffix('?') {
+                            Some(kind) => (kind, false),
+                            None => (kind, true),
+                        };
+                        Some(ZapField {
+                            name: name.clone(),
+                            kind: FieldKind::parse(kind)?,
+                            required,
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(Self {
+            name: text("name")?,
+            url: text("url")?,
+            description: text("description"),
+            fields,
+        })
+    }
+
+    /// Problems with `payload`, empty when it fits the declared fields.
+    pub fn validate(&self, payload: &Value) -> Vec<String> {
+        let Some(payload) = payload.as_object() else {
+            return vec!["payload must be an object".to_string()];
+        };
+        if self.fields.is_empty() {
+            return Vec::new();
+        }
+        let mut problems = Vec::new();
+        for field in &self.fields {
+            match payload.get(&field.name) {
+                None | Some(Value::Null) if field.required => {
+                    problems.push(format!("missing {}", field.name))
+                }
+                None | Some(Value::Null) => {}
+                Some(value) if !field.kind.matches(value) => {
+                    problems.push(format!("{} must be a {}", field.name, field.kind.as_str()))
+                }
+                Some(_) => {}
+            }
+        }
+        for key in payload.keys() {
+            if !self.fields.iter().any(|field| &field.name == key) {
+                problems.push(format!("unknown field {key}"));
+            }
+        }
+        problems
+    }
+
+    /// What `list_zaps` shows; the hook URL acts as a credential and stays out.
+    fn summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "fields": self
+                .fields
+                .iter()
+                .map(|field| json!({
+                    "name": field.name,
+                    "type": field.kind.as_str(),
+                    "required": field.required,
+                }))
+                .collect::<Vec<_>>(),
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ZapierConfig {
     url: String,
     headers: HashMap<String, String>,
     token: Option<String>,
+    zaps: Vec<ZapWebhook>,
 }
 
 impl Default for ZapierConfig {
@@ -22,6 +192,7 @@ impl Default for ZapierConfig {
             url: "https://mcp.zapier.com/api/v1/connect".to_string(),
             headers: HashMap::new(),
             token: None,
+            zaps: Vec::new(),
         }
     }
 }
@@ -107,6 +278,54 @@ impl ZapierTool {
         format!("{url}{separator}token={token}")
     }
 
+    async fn trigger_zap(&self, zaps: &[ZapWebhook], params: &Value) -> Result<Value> {
+        let name = params
+            .get("zap")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ButterflyBotError::Runtime("Missing zap name".to_string()))?;
+        let zap = zaps.iter().find(|zap| zap.name == name).ok_or_else(|| {
+            ButterflyBotError::Runtime(format!("Unknown zap '{name}' (see list_zaps)"))
+        })?;
+        let payload = params
+            .get("payload")
+            .filter(|payload| !payload.is_null())
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+        let problems = zap.validate(&payload);
+        if !problems.is_empty() {
+            return Err(ButterflyBotError::Runtime(format!(
+                "Invalid payload for zap '{name}': {}",
+                problems.join("; ")
+            )));
+        }
+
+        let response = reqwest::Client::new()
+            .post(&zap.url)
+            .json(&payload)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        let status = response.status();
+        let text = response
+            .text()
+            .await
+            .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+        if !status.is_success() {
+            return Err(ButterflyBotError::Http(format!(
+                "Zap '{name}' webhook answered {}: {}",
+                status.as_u16(),
+                text.chars().take(200).collect::<String>()
+            )));
+        }
+        Ok(json!({
+            "status": "ok",
+            "zap": name,
+            "http_status": status.as_u16(),
+            "response": serde_json::from_str::<Value>(&text).unwrap_or(Value::String(text)),
+        }))
+    }
+
     fn build_mcp_config(&self, config: &ZapierConfig) -> Value {
         json!({
             "tools": {
@@ -131,7 +350,7 @@ impl Tool for ZapierTool {
     }
 
     fn description(&self) -> &str {
-        "Access Zapier MCP tools through a dedicated MCP endpoint wrapper."
+        "Access Zapier MCP tools through a dedicated MCP endpoint wrapper, and trigger configured Zap webhooks."
     }
 
     fn parameters(&self) -> Value {
@@ -140,10 +359,12 @@ impl Tool for ZapierTool {
             "properties": {
                 "action": {
                     "type": "string",
-                    "enum": ["list_tools", "call_tool"]
+                    "enum": ["list_tools", "call_tool", "list_zaps", "trigger_zap"]
                 },
                 "tool": { "type": "string", "description": "Zapier MCP tool name" },
-                "arguments": { "type": "object", "description": "Arguments for the Zapier MCP tool" }
+                "arguments": { "type": "object", "description": "Arguments for the Zapier MCP tool" },
+                "zap": { "type": "string", "description": "Configured Zap name for trigger_zap" },
+                "payload": { "type": "object", "description": "Fields sent to the Zap's webhook, as listed by list_zaps" }
             },
             "required": ["action"],
             "additionalProperties": false
@@ -203,6 +424,7 @@ impl Tool for ZapierTool {
                 next.headers = Self::parse_headers(headers);
             }
         }
+        next.zaps = ZapWebhook::from_root_config(config);
 
         if let Some(token) = next.token.clone() {
             next.url = Self::url_with_token(&next.url, &token);
@@ -242,6 +464,19 @@ impl Tool for ZapierTool {
 
         let mut config = self.config.read().await.clone();
 
+        // Zap webhooks carry their own credential in the URL, so they work
+        // without an MCP token.
+        match action.as_str() {
+            "list_zaps" => {
+                return Ok(json!({
+                    "status": "ok",
+                    "zaps": config.zaps.iter().map(ZapWebhook::summary).collect::<Vec<_>>(),
+                }))
+            }
+            "trigger_zap" => return self.trigger_zap(&config.zaps, &params).await,
+            _ => {}
+        }
+
         if !Self::has_valid_token_in_url(&config.url)
             && !Self::has_authorization_header(&config.headers)
         {
@@ -293,3 +528,42 @@ impl Tool for ZapierTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{FieldKind, ZapWebhook};
+
+    #[test]
+    fn zaps_parse_typed_fields_and_validate_payloads() {
+        let zaps = ZapWebhook::from_root_config(&json!({
+            "tools": {"zapier": {"zaps": [
+                {"name": "new_lead", "url": "https://hooks.zapier.com/hooks/catch/1/a",
+                 "fields": {"email": "string", "amount": "number?", "bogus": "date"}},
+                {"name": "inbound_only", "inbound": {"user": "alice"}}
+            ]}}
+        }));
+        assert_eq!(zaps.len(), 1);
+        let zap = &zaps[0];
+        assert_eq!(zap.fields.len(), 2);
+        assert!(zap.fields.iter().any(|field| field.name == "amount"
+            && field.kind == FieldKind::Number
+            && !field.required));
+
+        assert!(zap
+            .validate(&json!({"email": "a@b.c", "amount": 3.5}))
+            .is_empty());
+        let mut problems = zap.validate(&json!({"amount": "3", "extra": 1}));
+        problems.sort();
+        assert_eq!(
+            problems,
+            vec![
+                "amount must be a number",
+                "missing email",
+                "unknown field extra"
+            ]
+        );
+        assert_eq!(zap.validate(&json!([1])), vec!["payload must be an object"]);
+    }
+}
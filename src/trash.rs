@@ -0,0 +1,282 @@
+//! Soft-delete for bulk clears.
+//!
+//! Clearing todos, reminders, scheduled tasks or plans moves the rows into a
+//! `<table>_trash` tombstone table tagged with a batch id instead of deleting
+//! them. A batch can be restored as a whole, with its original ids, until the
+//! retention window passes and the daemon purges it.
+
+use diesel::sql_types::{BigInt, Text};
+use diesel::sqlite::SqliteConnection;
+use diesel::QueryableByName;
+use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
+use diesel_async::RunQueryDsl;
+use rand::rngs::SysRng;
+use rand::TryRng;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::error::{ButterflyBotError, Result};
+
+const TRASH_TABLES_UP_SQL: &str = include_str!("../migrations/20260308_create_trash_tables/up.sql");
+
+pub const DEFAULT_RETENTION_DAYS: u64 = 30;
+
+type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
+
+/// A table that clears move into `<name>_trash`. `columns` lists every
+/// column both tables share, in the same order.
+pub(crate) struct TrashTable {
+    pub name: &'static str,
+    pub columns: &'static str,
+}
+
+/// What a clear moved to the trash; `batch_id` is what undo needs.
+#[derive(Clone, Debug, Serialize)]
+pub struct TrashReceipt {
+    pub store: &'static str,
+    pub batch_id: String,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct TrashBatch {
+    pub store: String,
+    pub batch_id: String,
+    pub count: i64,
+    pub trashed_at: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrashConfig {
+    pub retention_days: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self {
+            retention_days: DEFAULT_RETENTION_DAYS,
+        }
+    }
+}
+
+impl TrashConfig {
+    /// Reads `tools.settings.trash.retention_days`.
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let retention_days = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("trash"))
+            .and_then(|trash| trash.get("retention_days"))
+            .and_then(|value| value.as_u64())
+            .unwrap_or(DEFAULT_RETENTION_DAYS);
+        Self { retention_days }
+    }
+
+    /// Batches trashed before this timestamp are due for purging.
+    pub fn purge_cutoff(&self, now: i64) -> i64 {
+        now.saturating_sub(self.retention_days.saturating_mul(86_400) as i64)
+    }
+}
+
+/// Tool response for a clear that went to the trash.
+pub fn cleared_json(receipt: &TrashReceipt) -> Value {
+    json!({
+        "status": "ok",
+        "deleted": receipt.count,
+        "batch_id": receipt.batch_id,
+        "undo": format!("action=restore with batch_id={} puts these back", receipt.batch_id),
+    })
+}
+
+/// The batch a `restore` call names, or the most recent one when it names
+/// none.
+pub fn requested_batch(params: &Value, batches: &[TrashBatch]) -> Result<String> {
+    match params.get("batch_id").and_then(|v| v.as_str()) {
+        Some(batch_id) if batches.iter().any(|batch| batch.batch_id == batch_id) => {
+            Ok(batch_id.to_string())
+        }
+        Some(batch_id) => Err(ButterflyBotError::Runtime(format!(
+            "No trashed batch '{batch_id}'"
+        ))),
+        None => batches
+            .first()
+            .map(|batch| batch.batch_id.clone())
+            .ok_or_else(|| ButterflyBotError::Runtime("Trash is empty".to_string())),
+    }
+}
+
+#[derive(QueryableByName)]
+struct BatchRow {
+    #[diesel(sql_type = Text)]
+    trash_batch: String,
+    #[diesel(sql_type = BigInt)]
+    count: i64,
+    #[diesel(sql_type = BigInt)]
+    trashed_at: i64,
+}
+
+pub(crate) fn new_batch_id(now: i64) -> Result<String> {
+    let mut bytes = [0u8; 6];
+    let mut rng = SysRng;
+    rng.try_fill_bytes(&mut bytes)
+        .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    let suffix = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    Ok(format!("{now}-{suffix}"))
+}
+
+/// Copies the user's rows matching `filter` (an SQL fragment starting with
+/// ` AND`, or empty) into the trash under `batch_id`, then deletes exactly
+/// the rows that were copied.
+pub(crate) async fn move_rows(
+    conn: &mut SqliteAsyncConn,
+    table: &TrashTable,
+    user_id: &str,
+    filter: &str,
+    batch_id: &str,
+    now: i64,
+) -> Result<usize> {
+    let TrashTable { name, columns } = table;
+    diesel::sql_query(format!(
+        "INSERT INTO {name}_trash ({columns}, trash_batch, trashed_at)
+         SELECT {columns}, ?2, ?3 FROM {name} WHERE user_id = ?1{filter}"
+    ))
+    .bind::<Text, _>(user_id)
+    .bind::<Text, _>(batch_id)
+    .bind::<BigInt, _>(now)
+    .execute(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    diesel::sql_query(format!(
+        "DELETE FROM {name} WHERE user_id = ?1
+           AND id IN (SELECT id FROM {name}_trash WHERE user_id = ?1 AND trash_batch = ?2)"
+    ))
+    .bind::<Text, _>(user_id)
+    .bind::<Text, _>(batch_id)
+    .execute(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+}
+
+/// Puts a batch back under its original ids. Rows whose id was taken in the
+/// meantime are skipped rather than overwriting newer data.
+pub(crate) async fn restore_rows(
+    conn: &mut SqliteAsyncConn,
+    table: &TrashTable,
+    user_id: &str,
+    batch_id: &str,
+) -> Result<usize> {
+    let TrashTable { name, columns } = table;
+    let restored = diesel::sql_query(format!(
+        "INSERT OR IGNORE INTO {name} ({columns})
+         SELECT {columns} FROM {name}_trash WHERE user_id = ?1 AND trash_batch = ?2"
+    ))
+    .bind::<Text, _>(user_id)
+    .bind::<Text, _>(batch_id)
+    .execute(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    diesel::sql_query(format!(
+        "DELETE FROM {name}_trash WHERE user_id = ?1 AND trash_batch = ?2"
+    ))
+    .bind::<Text, _>(user_id)
+    .bind::<Text, _>(batch_id)
+    .execute(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(restored)
+}
+
+/// The user's batches in `table`, newest first.
+pub(crate) async fn list_batches(
+    conn: &mut SqliteAsyncConn,
+    table: &TrashTable,
+    store: &str,
+    user_id: &str,
+) -> Result<Vec<TrashBatch>> {
+    let rows: Vec<BatchRow> = diesel::sql_query(format!(
+        "SELECT trash_batch, COUNT(*) AS count, MAX(trashed_at) AS trashed_at
+         FROM {}_trash WHERE user_id = ?1
+         GROUP BY trash_batch ORDER BY trashed_at DESC",
+        table.name
+    ))
+    .bind::<Text, _>(user_id)
+    .load(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| TrashBatch {
+            store: store.to_string(),
+            batch_id: row.trash_batch,
+            count: row.count,
+            trashed_at: row.trashed_at,
+        })
+        .collect())
+}
+
+/// Permanently drops everything trashed before `cutoff`.
+pub(crate) async fn purge_before(
+    conn: &mut SqliteAsyncConn,
+    table: &TrashTable,
+    cutoff: i64,
+) -> Result<usize> {
+    diesel::sql_query(format!(
+        "DELETE FROM {}_trash WHERE trashed_at < ?1",
+        table.name
+    ))
+    .bind::<BigInt, _>(cutoff)
+    .execute(conn)
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))
+}
+
+/// Creates any missing tombstone tables on databases whose migration history
+/// predates them.
+pub(crate) async fn ensure_trash_tables(database_url: &str, tables: &[&TrashTable]) -> Result<()> {
+    let database_url = database_url.to_string();
+    let names = tables
+        .iter()
+        .map(|table| format!("{}_trash", table.name))
+        .collect::<Vec<_>>();
+    tokio::task::spawn_blocking(move || {
+        let mut conn = crate::db::open_sqlcipher_connection_sync(&database_url)?;
+        for name in names {
+            let check = diesel::connection::SimpleConnection::batch_execute(
+                &mut conn,
+                &format!("SELECT 1 FROM {name} LIMIT 1"),
+            );
+            if let Err(err) = check {
+                let message = err.to_string();
+                if message.contains("no such table") {
+                    diesel::connection::SimpleConnection::batch_execute(
+                        &mut conn,
+                        TRASH_TABLES_UP_SQL,
+                    )
+                    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+                    break;
+                }
+                return Err(ButterflyBotError::Runtime(message));
+            }
+        }
+        Ok::<_, ButterflyBotError>(())
+    })
+    .await
+    .map_err(|e| ButterflyBotError::Runtime(e.to_string()))??;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retention_comes_from_settings_with_a_default() {
+        assert_eq!(TrashConfig::from_tools(None).retention_days, 30);
+        let tools = json!({"settings": {"trash": {"retention_days": 7}}});
+        let config = TrashConfig::from_tools(Some(&tools));
+        assert_eq!(config.retention_days, 7);
+        assert_eq!(config.purge_cutoff(10 * 86_400), 3 * 86_400);
+    }
+}
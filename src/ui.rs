@@ -3382,6 +3382,7 @@ fn app_view() -> Element {
                     context_embed_enabled: Some(false),
                     summary_threshold: None,
                     retention_days: None,
+                    retention: None,
                 });
                 memory_cfg.embedding_model = Some(openai_embedding_model_value);
                 memory_cfg.summary_model = Some(openai_summary_model_value);
@@ -2,12 +2,26 @@ use crate::error::{ButterflyBotError, Result};
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use rand::rngs::SysRng;
 use rand::TryRng;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 #[cfg(target_os = "macos")]
 use std::process::Command;
+use std::sync::Mutex;
 
 const SERVICE: &str = "butterfly-bot";
 const DAEMON_TOKEN_FILE: &str = "daemon_auth_token";
+const CATALOG_FILE: &str = "catalog.json";
+
+/// Entries the app manages itself; the named-secret API never touches them.
+const RESERVED_SECRETS: &[&str] = &[
+    "daemon_auth_token",
+    "db_encryption_key",
+    "app_config_json",
+    "context_md5",
+    "tpm_kek",
+    "compat_kek",
+    "platform_secure_binding",
+];
 
 fn daemon_auth_token_file() -> PathBuf {
     crate::runtime_paths::app_root()
@@ -174,7 +188,7 @@ fn macos_keychain_get_required(name: &str) -> Result<Option<String>> {
     Err(ButterflyBotError::SecurityStorage(message))
 }
 
-pub fn set_secret(name: &str, value: &str) -> Result<()> {
+fn store_secret(name: &str, value: &str) -> Result<()> {
     if keyring_disabled() {
         write_secret_fallback_file(name, value);
         return Ok(());
@@ -191,7 +205,7 @@ pub fn set_secret(name: &str, value: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn set_secret_required(name: &str, value: &str) -> Result<()> {
+fn store_secret_required(name: &str, value: &str) -> Result<()> {
     if keyring_disabled() {
         write_secret_fallback_file(name, value);
         return Ok(());
@@ -230,7 +244,7 @@ pub fn set_secret_required(name: &str, value: &str) -> Result<()> {
     }
 }
 
-pub fn get_secret_required(name: &str) -> Result<Option<String>> {
+fn load_secret_required(name: &str) -> Result<Option<String>> {
     if keyring_disabled() {
         return Ok(read_secret_fallback_file(name));
     }
@@ -274,7 +288,7 @@ pub fn get_secret_required(name: &str) -> Result<Option<String>> {
     }
 }
 
-pub fn get_secret(name: &str) -> Result<Option<String>> {
+fn load_secret(name: &str) -> Result<Option<String>> {
     if keyring_disabled() {
         return Ok(read_secret_fallback_file(name));
     }
@@ -292,8 +306,48 @@ pub fn get_secret(name: &str) -> Result<Option<String>> {
     }
 }
 
+/// Vault entries that hold app state rather than credentials.
+const NOT_CREDENTIALS: &[&str] = &["app_config_json", "context_md5"];
+
+// Every credential that passes through the vault is handed to the redactor,
+// so logs and audit events mask it from then on.
+fn remember(name: &str, value: &str) {
+    if !NOT_CREDENTIALS.contains(&name) {
+        crate::redaction::remember_secret(name, value);
+    }
+}
+
+pub fn set_secret(name: &str, value: &str) -> Result<()> {
+    store_secret(name, value)?;
+    remember(name, value);
+    Ok(())
+}
+
+pub fn set_secret_required(name: &str, value: &str) -> Result<()> {
+    store_secret_required(name, value)?;
+    remember(name, value);
+    Ok(())
+}
+
+pub fn get_secret_required(name: &str) -> Result<Option<String>> {
+    let value = load_secret_required(name)?;
+    if let Some(value) = &value {
+        remember(name, value);
+    }
+    Ok(value)
+}
+
+pub fn get_secret(name: &str) -> Result<Option<String>> {
+    let value = load_secret(name)?;
+    if let Some(value) = &value {
+        remember(name, value);
+    }
+    Ok(value)
+}
+
 pub fn ensure_daemon_auth_token() -> Result<String> {
     if let Some(token) = env_token() {
+        crate::redaction::remember_secret("daemon_auth_token", &token);
         return Ok(token);
     }
 
@@ -306,6 +360,7 @@ pub fn ensure_daemon_auth_token() -> Result<String> {
     }
 
     if let Some(token) = read_daemon_auth_token_file() {
+        crate::redaction::remember_secret("daemon_auth_token", &token);
         std::env::set_var("BUTTERFLY_BOT_TOKEN", &token);
         return Ok(token);
     }
@@ -320,3 +375,181 @@ pub fn ensure_daemon_auth_token() -> Result<String> {
     std::env::set_var("BUTTERFLY_BOT_TOKEN", &generated);
     Ok(generated)
 }
+
+/// What is known about a named secret without reading its value.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretMetadata {
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: i64,
+    pub rotated_at: i64,
+    /// Starts at 1 and goes up with each rotation.
+    pub version: u32,
+}
+
+// The keychain cannot enumerate entries, so named secrets are listed from a
+// catalog next to the fallback files. It holds metadata only.
+static CATALOG_LOCK: Mutex<()> = Mutex::new(());
+
+fn catalog_file() -> PathBuf {
+    crate::runtime_paths::app_root()
+        .join("secrets")
+        .join(CATALOG_FILE)
+}
+
+fn read_catalog() -> Result<Vec<SecretMetadata>> {
+    match std::fs::read_to_string(catalog_file()) {
+        Ok(raw) => serde_json::from_str(&raw)
+            .map_err(|e| ButterflyBotError::SecurityStorage(format!("Invalid catalog: {e}"))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(ButterflyBotError::SecurityStorage(err.to_string())),
+    }
+}
+
+fn write_catalog(entries: &[SecretMetadata]) -> Result<()> {
+    let path = catalog_file();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+    }
+    let raw = serde_json::to_string_pretty(entries)
+        .map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+    std::fs::write(&path, raw).map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+    }
+    Ok(())
+}
+
+fn with_catalog<T>(f: impl FnOnce(&mut Vec<SecretMetadata>) -> Result<T>) -> Result<T> {
+    let _guard = match CATALOG_LOCK.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let mut entries = read_catalog()?;
+    let out = f(&mut entries)?;
+    write_catalog(&entries)?;
+    Ok(out)
+}
+
+fn validate_secret_name(name: &str) -> Result<()> {
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '.'));
+    if name.is_empty() || name.len() > 64 || !valid_chars {
+        return Err(ButterflyBotError::Config(
+            "Secret names use 1-64 lowercase letters, digits, '_', '-' or '.'".to_string(),
+        ));
+    }
+    if RESERVED_SECRETS.contains(&name) {
+        return Err(ButterflyBotError::Config(format!(
+            "Secret '{name}' is managed by the app"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_secret_value(value: &str) -> Result<()> {
+    if value.trim().is_empty() {
+        return Err(ButterflyBotError::Config(
+            "Secret value must not be empty".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn delete_secret_value(name: &str) -> Result<()> {
+    let _ = std::fs::remove_file(secret_fallback_file(name));
+    if keyring_disabled() {
+        return Ok(());
+    }
+    let entry = keyring::Entry::new(SERVICE, name)
+        .map_err(|e| ButterflyBotError::SecurityStorage(e.to_string()))?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(err) if keyring_backend_unavailable(&err.to_string()) => Ok(()),
+        Err(err) => Err(ButterflyBotError::SecurityStorage(err.to_string())),
+    }
+}
+
+fn now_ts() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Reads every named secret once so it is redacted before any tool asks for
+/// it.
+pub fn remember_named_secrets() {
+    for entry in read_catalog().unwrap_or_default() {
+        let _ = get_secret(&entry.name);
+    }
+}
+
+/// Named secrets stored through [`create_secret`], sorted by name.
+pub fn list_secrets() -> Result<Vec<SecretMetadata>> {
+    let mut entries = read_catalog()?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+/// Stores a new named secret. Fails when the name is already in the vault,
+/// including entries written before the catalog existed.
+pub fn create_secret(name: &str, value: &str, description: Option<&str>) -> Result<SecretMetadata> {
+    validate_secret_name(name)?;
+    validate_secret_value(value)?;
+    with_catalog(|entries| {
+        if entries.iter().any(|entry| entry.name == name) || get_secret(name)?.is_some() {
+            return Err(ButterflyBotError::Config(format!(
+                "Secret '{name}' already exists"
+            )));
+        }
+        set_secret_required(name, value)?;
+        let now = now_ts();
+        let metadata = SecretMetadata {
+            name: name.to_string(),
+            description: description
+                .map(str::trim)
+                .filter(|description| !description.is_empty())
+                .map(str::to_string),
+            created_at: now,
+            rotated_at: now,
+            version: 1,
+        };
+        entries.push(metadata.clone());
+        Ok(metadata)
+    })
+}
+
+/// Replaces the value of a named secret.
+pub fn rotate_secret(name: &str, value: &str) -> Result<SecretMetadata> {
+    validate_secret_name(name)?;
+    validate_secret_value(value)?;
+    with_catalog(|entries| {
+        let entry = entries
+            .iter_mut()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| ButterflyBotError::Config(format!("Unknown secret '{name}'")))?;
+        set_secret_required(name, value)?;
+        entry.rotated_at = now_ts();
+        entry.version += 1;
+        Ok(entry.clone())
+    })
+}
+
+/// Removes a named secret and its catalog entry. Returns whether it existed.
+pub fn delete_secret(name: &str) -> Result<bool> {
+    validate_secret_name(name)?;
+    with_catalog(|entries| {
+        let Some(index) = entries.iter().position(|entry| entry.name == name) else {
+            return Ok(false);
+        };
+        delete_secret_value(name)?;
+        crate::redaction::forget_secret(name);
+        entries.remove(index);
+        Ok(true)
+    })
+}
@@ -0,0 +1,169 @@
+//! Microphone capture through a command-line recorder. The recorder writes
+//! a 16 kHz mono WAV file, which is what Whisper models expect, until it is
+//! told to stop.
+
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+
+/// Replaced by the output file in `record_command`.
+pub const PATH_PLACEHOLDER: &str = "{path}";
+/// How long a recorder gets to finish the file after being interrupted.
+const STOP_GRACE: Duration = Duration::from_secs(3);
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaptureConfig {
+    /// Program and arguments; `{path}` marks the output file.
+    pub command: Vec<String>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        let command: &[&str] = if cfg!(target_os = "linux") {
+            &[
+                "arecord", "-q", "-f", "S16_LE", "-r", "16000", "-c", "1", "-t", "wav", "{path}",
+            ]
+        } else {
+            &[
+                "sox", "-q", "-d", "-r", "16000", "-c", "1", "-b", "16", "{path}",
+            ]
+        };
+        Self {
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+impl CaptureConfig {
+    pub(crate) fn from_section(section: &Value) -> Self {
+        section
+            .get("record_command")
+            .and_then(Value::as_array)
+            .map(|args| {
+                args.iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|args| !args.is_empty())
+            .map(|command| Self { command })
+            .unwrap_or_default()
+    }
+}
+
+/// A recording in progress. Dropping it without [`Recording::stop`] kills
+/// the recorder and discards the audio.
+pub struct Recording {
+    child: Option<Child>,
+    path: PathBuf,
+}
+
+impl Recording {
+    pub fn start(config: &CaptureConfig) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "butterfly-voice-{}-{}.wav",
+            std::process::id(),
+            chrono::Utc::now().timestamp_millis()
+        ));
+        let (program, args) = config
+            .command
+            .split_first()
+            .ok_or_else(|| ButterflyBotError::Config("record_command is empty".to_string()))?;
+        let path_arg = path.to_string_lossy();
+        let child = Command::new(program)
+            .args(
+                args.iter()
+                    .map(|arg| arg.replace(PATH_PLACEHOLDER, &path_arg)),
+            )
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                ButterflyBotError::Runtime(format!("Could not start recorder {program}: {e}"))
+            })?;
+        Ok(Self {
+            child: Some(child),
+            path,
+        })
+    }
+
+    /// Stops the recorder and returns the WAV bytes. Blocks for up to a few
+    /// seconds while the recorder finishes the file.
+    pub fn stop(mut self) -> Result<Vec<u8>> {
+        if let Some(mut child) = self.child.take() {
+            interrupt(&child);
+            let deadline = Instant::now() + STOP_GRACE;
+            while child.try_wait().ok().flatten().is_none() {
+                if Instant::now() >= deadline {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        }
+        let bytes = std::fs::read(&self.path)
+            .map_err(|e| ButterflyBotError::Runtime(format!("Recorder produced no audio: {e}")))?;
+        if bytes.is_empty() {
+            return Err(ButterflyBotError::Runtime(
+                "Recorder produced no audio".to_string(),
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Asks the recorder to finish, the way Ctrl-C would, so it can write the
+/// WAV header. Elsewhere it is simply killed.
+#[cfg(unix)]
+fn interrupt(child: &Child) {
+    if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: signalling a child we spawned and have not yet reaped.
+        unsafe {
+            libc::kill(pid, libc::SIGINT);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn interrupt(_child: &Child) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn stop_interrupts_the_recorder_and_returns_its_file() {
+        let config = CaptureConfig::from_section(&json!({"record_command": [
+            "sh", "-c", "printf RIFF > \"$0\"; trap 'exit 0' INT; while :; do sleep 0.05; done",
+            "{path}"
+        ]}));
+        let recording = Recording::start(&config).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        let path = recording.path.clone();
+        assert_eq!(recording.stop().unwrap(), b"RIFF");
+        assert!(!path.exists());
+
+        assert!(!CaptureConfig::default().command.is_empty());
+        let missing = CaptureConfig {
+            command: vec!["butterfly-no-such-recorder".to_string()],
+        };
+        assert!(Recording::start(&missing).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+//! Talking with the bot: microphone capture on the desktop, transcription
+//! by a locally hosted Whisper model and replies read aloud by a local
+//! text-to-speech engine.
+//!
+//! Configured under `tools.settings.voice`:
+//!
+//! ```json
+//! {"transcription": {"engine": "whisper_cpp", "url": "http://127.0.0.1:8080",
+//!                    "language": "en"},
+//!  "record_command": ["arecord", "-q", "-f", "S16_LE", "-r", "16000", "-c", "1",
+//!                     "-t", "wav", "{path}"],
+//!  "speech": {"command": ["piper", "--model", "en_US-lessac-medium",
+//!                         "--output_file", "{path}"],
+//!             "play_command": ["aplay", "-q", "{path}"],
+//!             "tabs": ["chat", "inbox"]}}
+//! ```
+//!
+//! `whisper_cpp` talks to the whisper.cpp server's `/inference` endpoint.
+//! `openai_compatible` posts to `<url>/audio/transcriptions`, which local
+//! Whisper servers and Ollama front-ends such as speaches implement; it
+//! takes a `model` and an optional `api_key_secret` vault entry.
+//! `record_command` defaults to arecord on Linux and sox elsewhere.
+//! `speech.tabs` picks which desktop tabs speak: `chat` reads bot replies,
+//! `inbox` reads reminders and overdue nudges. Nothing is spoken by default.
+
+pub mod capture;
+pub mod speech;
+pub mod transcribe;
+
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::interfaces::providers::TranscriptionProvider;
+use capture::CaptureConfig;
+use speech::SpeechConfig;
+use transcribe::{OpenAiCompatibleTranscriber, WhisperCppTranscriber};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TranscriptionEngine {
+    WhisperCpp {
+        url: String,
+        language: Option<String>,
+    },
+    OpenAiCompatible {
+        url: String,
+        model: String,
+        language: Option<String>,
+        /// Vault entry holding the API key, for servers that want one.
+        api_key_secret: Option<String>,
+    },
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VoiceConfig {
+    /// `None` until a local engine is configured.
+    pub transcription: Option<TranscriptionEngine>,
+    pub capture: CaptureConfig,
+    pub speech: SpeechConfig,
+}
+
+impl VoiceConfig {
+    pub fn from_tools(tools: Option<&Value>) -> Self {
+        let Some(section) = tools
+            .and_then(|tools| tools.get("settings"))
+            .and_then(|settings| settings.get("voice"))
+        else {
+            return Self::default();
+        };
+        Self {
+            transcription: section.get("transcription").and_then(parse_engine),
+            capture: CaptureConfig::from_section(section),
+            speech: SpeechConfig::from_section(section),
+        }
+    }
+
+    pub fn transcriber(&self) -> Option<Arc<dyn TranscriptionProvider>> {
+        match self.transcription.clone()? {
+            TranscriptionEngine::WhisperCpp { url, language } => {
+                Some(Arc::new(WhisperCppTranscriber::new(url, language)))
+            }
+            TranscriptionEngine::OpenAiCompatible {
+                url,
+                model,
+                language,
+                api_key_secret,
+            } => Some(Arc::new(OpenAiCompatibleTranscriber::new(
+                url,
+                model,
+                language,
+                api_key_secret,
+            ))),
+        }
+    }
+}
+
+fn text(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn parse_engine(value: &Value) -> Option<TranscriptionEngine> {
+    let url = text(value, "url")?.trim_end_matches('/').to_string();
+    let language = text(value, "language");
+    match text(value, "engine").as_deref().unwrap_or("whisper_cpp") {
+        "whisper_cpp" | "whisper.cpp" => Some(TranscriptionEngine::WhisperCpp { url, language }),
+        "openai_compatible" | "openai" => Some(TranscriptionEngine::OpenAiCompatible {
+            url,
+            model: text(value, "model").unwrap_or_else(|| "whisper-1".to_string()),
+            language,
+            api_key_secret: text(value, "api_key_secret"),
+        }),
+        other => {
+            tracing::warn!(engine = other, "Ignoring unknown transcription engine");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_engines_and_defaults() {
+        assert_eq!(VoiceConfig::from_tools(None).transcription, None);
+
+        let tools = json!({"settings": {"voice": {
+            "transcription": {"url": "http://127.0.0.1:8080/", "language": "de"}
+        }}});
+        assert_eq!(
+            VoiceConfig::from_tools(Some(&tools)).transcription,
+            Some(TranscriptionEngine::WhisperCpp {
+                url: "http://127.0.0.1:8080".to_string(),
+                language: Some("de".to_string()),
+            })
+        );
+
+        let tools = json!({"settings": {"voice": {
+            "transcription": {"engine": "openai_compatible", "url": "http://localhost:8000/v1"}
+        }}});
+        let config = VoiceConfig::from_tools(Some(&tools));
+        assert!(matches!(
+            config.transcription,
+            Some(TranscriptionEngine::OpenAiCompatible { ref model, .. }) if model == "whisper-1"
+        ));
+        assert!(config.transcriber().is_some());
+
+        let unknown = json!({"settings": {"voice": {
+            "transcription": {"engine": "dictaphone", "url": "http://x"}
+        }}});
+        assert!(VoiceConfig::from_tools(Some(&unknown))
+            .transcriber()
+            .is_none());
+    }
+}
@@ -0,0 +1,229 @@
+//! Reading replies and reminders aloud through a local text-to-speech
+//! engine such as piper. Utterances go through one queue and are spoken one
+//! after another, so a reminder never talks over a chat reply.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+
+use serde_json::Value;
+
+use super::capture::PATH_PLACEHOLDER;
+use crate::error::{ButterflyBotError, Result};
+
+/// Utterances waiting beyond this are dropped rather than read out late.
+const MAX_QUEUED: usize = 8;
+/// Longer replies are cut off; the rest is on screen.
+const MAX_SPOKEN_CHARS: usize = 600;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeechConfig {
+    /// Reads the text on stdin and writes a WAV file to `{path}`.
+    pub synth_command: Vec<String>,
+    /// Plays `{path}` and exits when done.
+    pub play_command: Vec<String>,
+    /// UI tabs whose events are spoken: `chat` and `inbox`.
+    pub tabs: Vec<String>,
+}
+
+impl Default for SpeechConfig {
+    fn default() -> Self {
+        let play: &[&str] = if cfg!(target_os = "macos") {
+            &["afplay", "{path}"]
+        } else {
+            &["aplay", "-q", "{path}"]
+        };
+        Self {
+            synth_command: [
+                "piper",
+                "--model",
+                "en_US-lessac-medium",
+                "--output_file",
+                "{path}",
+            ]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect(),
+            play_command: play.iter().map(|arg| arg.to_string()).collect(),
+            tabs: Vec::new(),
+        }
+    }
+}
+
+impl SpeechConfig {
+    pub(crate) fn from_section(section: &Value) -> Self {
+        let Some(speech) = section.get("speech") else {
+            return Self::default();
+        };
+        let defaults = Self::default();
+        Self {
+            synth_command: strings(speech.get("command")).unwrap_or(defaults.synth_command),
+            play_command: strings(speech.get("play_command")).unwrap_or(defaults.play_command),
+            tabs: strings(speech.get("tabs")).unwrap_or_default(),
+        }
+    }
+
+    pub fn speaks(&self, tab: &str) -> bool {
+        self.tabs.iter().any(|enabled| enabled == tab)
+    }
+}
+
+fn strings(value: Option<&Value>) -> Option<Vec<String>> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .filter(|items| !items.is_empty())
+}
+
+/// Speaks queued text on a background thread. Dropping the speaker lets it
+/// finish what is already queued.
+pub struct Speaker {
+    queue: SyncSender<String>,
+}
+
+impl Speaker {
+    pub fn start(config: SpeechConfig) -> Self {
+        let (queue, pending) = sync_channel(MAX_QUEUED);
+        if let Err(err) = std::thread::Builder::new()
+            .name("butterfly-speech".to_string())
+            .spawn(move || speak_queued(&config, pending))
+        {
+            tracing::warn!(error = %err, "Could not start the speech thread");
+        }
+        Self { queue }
+    }
+
+    /// Queues `text`; returns false when there is nothing to say or the
+    /// queue is full.
+    pub fn say(&self, text: &str) -> bool {
+        let text = speakable(text);
+        if text.is_empty() {
+            return false;
+        }
+        match self.queue.try_send(text) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tracing::debug!("Speech queue full; dropping utterance");
+                false
+            }
+            Err(TrySendError::Disconnected(_)) => false,
+        }
+    }
+}
+
+fn speak_queued(config: &SpeechConfig, pending: Receiver<String>) {
+    for text in pending {
+        if let Err(err) = speak(config, &text) {
+            tracing::warn!(error = %err, "Text-to-speech failed");
+        }
+    }
+}
+
+fn speak(config: &SpeechConfig, text: &str) -> Result<()> {
+    let path = std::env::temp_dir().join(format!(
+        "butterfly-speech-{}-{}.wav",
+        std::process::id(),
+        chrono::Utc::now().timestamp_millis()
+    ));
+    let path_arg = path.to_string_lossy().to_string();
+    let result = run(&config.synth_command, &path_arg, Some(text))
+        .and_then(|()| run(&config.play_command, &path_arg, None));
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+fn run(command: &[String], path: &str, stdin: Option<&str>) -> Result<()> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| ButterflyBotError::Config("speech command is empty".to_string()))?;
+    let mut child = Command::new(program)
+        .args(args.iter().map(|arg| arg.replace(PATH_PLACEHOLDER, path)))
+        .stdin(if stdin.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| ButterflyBotError::Runtime(format!("Could not start {program}: {e}")))?;
+    if let (Some(text), Some(mut input)) = (stdin, child.stdin.take()) {
+        input
+            .write_all(text.as_bytes())
+            .map_err(|e| ButterflyBotError::Runtime(format!("{program}: {e}")))?;
+    }
+    let status = child
+        .wait()
+        .map_err(|e| ButterflyBotError::Runtime(format!("{program}: {e}")))?;
+    if !status.success() {
+        return Err(ButterflyBotError::Runtime(format!(
+            "{program} exited with {status}"
+        )));
+    }
+    Ok(())
+}
+
+/// Plain sentences from a markdown reply: code blocks are skipped, markup
+/// characters dropped and the result capped at a speakable length.
+pub fn speakable(text: &str) -> String {
+    let mut in_code = false;
+    let mut words = Vec::new();
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let plain = line.replace(['*', '_', '#', '`', '>', '|'], " ");
+        words.extend(plain.split_whitespace().map(str::to_string));
+    }
+    let joined = words.join(" ");
+    match joined.char_indices().nth(MAX_SPOKEN_CHARS) {
+        Some((cut, _)) => joined[..cut].to_string(),
+        None => joined,
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn speaks_queued_text_in_order() {
+        let log =
+            std::env::temp_dir().join(format!("butterfly-speech-test-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let log_arg = log.to_string_lossy().to_string();
+        let config = SpeechConfig::from_section(&json!({"speech": {
+            "command": ["sh", "-c", "cat > \"$0\"", "{path}"],
+            "play_command": ["sh", "-c", "sleep 0.1; cat \"$0\" >> \"$1\"; echo >> \"$1\"",
+                             "{path}", log_arg],
+            "tabs": ["chat"]
+        }}));
+        assert!(config.speaks("chat"));
+        assert!(!config.speaks("inbox"));
+
+        let speaker = Speaker::start(config);
+        assert!(speaker.say("**Done.** Your todo is saved"));
+        assert!(speaker.say("Reminder: call the bank"));
+        assert!(!speaker.say("```\nlet x = 1;\n```"));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let expected = "Done. Your todo is saved\nReminder: call the bank\n";
+        while std::fs::read_to_string(&log).unwrap_or_default() != expected {
+            assert!(Instant::now() < deadline, "speech did not finish in order");
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        let _ = std::fs::remove_file(&log);
+    }
+}
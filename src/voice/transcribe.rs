@@ -0,0 +1,207 @@
+//! Transcription over HTTP against locally hosted Whisper servers.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use crate::error::{ButterflyBotError, Result};
+use crate::interfaces::providers::TranscriptionProvider;
+
+/// Long recordings on CPU-only machines take a while.
+const TRANSCRIBE_TIMEOUT: Duration = Duration::from_secs(120);
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(TRANSCRIBE_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+/// The whisper.cpp example server (`whisper-server`).
+pub struct WhisperCppTranscriber {
+    url: String,
+    language: Option<String>,
+    client: reqwest::Client,
+}
+
+impl WhisperCppTranscriber {
+    pub fn new(url: String, language: Option<String>) -> Self {
+        Self {
+            url,
+            language,
+            client: http_client(),
+        }
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for WhisperCppTranscriber {
+    async fn transcribe(&self, audio_bytes: Vec<u8>, input_format: &str) -> Result<String> {
+        let mut fields = vec![("response_format", "json".to_string())];
+        if let Some(language) = &self.language {
+            fields.push(("language", language.clone()));
+        }
+        let (content_type, body) = multipart(&fields, &audio_bytes, input_format);
+        let request = self
+            .client
+            .post(format!("{}/inference", self.url))
+            .header("content-type", content_type)
+            .body(body);
+        send(request).await
+    }
+}
+
+/// Servers implementing OpenAI's `/audio/transcriptions`.
+pub struct OpenAiCompatibleTranscriber {
+    url: String,
+    model: String,
+    language: Option<String>,
+    api_key_secret: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiCompatibleTranscriber {
+    pub fn new(
+        url: String,
+        model: String,
+        language: Option<String>,
+        api_key_secret: Option<String>,
+    ) -> Self {
+        Self {
+            url,
+            model,
+            language,
+            api_key_secret,
+            client: http_client(),
+        }
+    }
+}
+
+#[async_trait]
+impl TranscriptionProvider for OpenAiCompatibleTranscriber {
+    async fn transcribe(&self, audio_bytes: Vec<u8>, input_format: &str) -> Result<String> {
+        let mut fields = vec![
+            ("model", self.model.clone()),
+            ("response_format", "json".to_string()),
+        ];
+        if let Some(language) = &self.language {
+            fields.push(("language", language.clone()));
+        }
+        let (content_type, body) = multipart(&fields, &audio_bytes, input_format);
+        let mut request = self
+            .client
+            .post(format!("{}/audio/transcriptions", self.url))
+            .header("content-type", content_type)
+            .body(body);
+        if let Some(name) = &self.api_key_secret {
+            let key = crate::vault::get_secret(name)?.ok_or_else(|| {
+                ButterflyBotError::Config(format!("Vault has no secret named {name}"))
+            })?;
+            request = request.bearer_auth(key.trim());
+        }
+        send(request).await
+    }
+}
+
+async fn send(request: reqwest::RequestBuilder) -> Result<String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| ButterflyBotError::Http(format!("Transcription request failed: {e}")))?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    if !status.is_success() {
+        return Err(ButterflyBotError::Http(format!(
+            "Transcription server returned {}: {}",
+            status.as_u16(),
+            body.chars().take(200).collect::<String>()
+        )));
+    }
+    let value: Value =
+        serde_json::from_str(&body).map_err(|e| ButterflyBotError::Http(e.to_string()))?;
+    value
+        .get("text")
+        .and_then(Value::as_str)
+        .map(|text| text.trim().to_string())
+        .ok_or_else(|| ButterflyBotError::Http("Transcription response has no text".to_string()))
+}
+
+/// A `multipart/form-data` body with `fields` and the audio as `file`.
+fn multipart(fields: &[(&str, String)], audio: &[u8], input_format: &str) -> (String, Vec<u8>) {
+    let boundary = format!(
+        "butterfly-{:x}",
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    let mut body = Vec::with_capacity(audio.len() + 512);
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{boundary}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; \
+             filename=\"audio.{input_format}\"\r\nContent-Type: audio/{input_format}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(audio);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+    (format!("multipart/form-data; boundary={boundary}"), body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::MockServer;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn whisper_cpp_posts_the_recording_and_reads_text() {
+        let server = MockServer::start_async().await;
+        let inference = server
+            .mock_async(|when, then| {
+                when.method("POST")
+                    .path("/inference")
+                    .body_includes("name=\"language\"\r\n\r\nen")
+                    .body_includes("filename=\"audio.wav\"")
+                    .body_includes("RIFF");
+                then.status(200)
+                    .json_body(json!({"text": " remind me to call mom \n"}));
+            })
+            .await;
+
+        let transcriber = WhisperCppTranscriber::new(server.base_url(), Some("en".to_string()));
+        let text = transcriber
+            .transcribe(b"RIFF....WAVE".to_vec(), "wav")
+            .await
+            .unwrap();
+        assert_eq!(text, "remind me to call mom");
+        inference.assert_async().await;
+
+        let failing = MockServer::start_async().await;
+        failing
+            .mock_async(|when, then| {
+                when.method("POST").path("/audio/transcriptions");
+                then.status(500).body("model not loaded");
+            })
+            .await;
+        let err = OpenAiCompatibleTranscriber::new(
+            failing.base_url(),
+            "whisper-1".to_string(),
+            None,
+            None,
+        )
+        .transcribe(Vec::new(), "wav")
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("model not loaded"));
+    }
+}
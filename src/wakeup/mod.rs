@@ -1,15 +1,15 @@
-use std::path::Path;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
-use diesel_async::pooled_connection::bb8::{Pool, PooledConnection};
-use diesel_async::pooled_connection::AsyncDieselConnectionManager;
-use diesel_async::sync_connection_wrapper::SyncConnectionWrapper;
 use diesel_async::RunQueryDsl;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use rand::rngs::SysRng;
+use rand::TryRng;
 use serde::Serialize;
 
+use crate::clock::{system_clock, SharedClock};
+use crate::db::{DbHandle, SqlitePooledConn, WriteConn};
 use crate::error::{ButterflyBotError, Result};
 
 mod schema;
@@ -18,10 +18,6 @@ use schema::wakeup_tasks;
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
 const WAKEUP_UP_SQL: &str = include_str!("../../migrations/20260202_create_wakeup/up.sql");
 
-type SqliteAsyncConn = SyncConnectionWrapper<SqliteConnection>;
-type SqlitePool = Pool<SqliteAsyncConn>;
-type SqlitePooledConn<'a> = PooledConnection<'a, SqliteAsyncConn>;
-
 #[derive(Debug, Clone, Serialize)]
 pub struct WakeupTask {
     pub id: i32,
@@ -34,6 +30,118 @@ pub struct WakeupTask {
     pub updated_at: i64,
     pub last_run_at: Option<i64>,
     pub next_run_at: i64,
+    pub jitter_seconds: i64,
+    pub max_concurrency: i32,
+    pub max_backoff_minutes: i64,
+    pub consecutive_failures: i32,
+}
+
+impl WakeupTask {
+    /// When the run starting at `run_at` schedules the next one: one
+    /// interval later, pushed back by up to `jitter_seconds`.
+    pub fn next_run_after(&self, run_at: i64) -> i64 {
+        run_at + self.interval_minutes.max(1) * 60 + jitter_roll(self.jitter_seconds)
+    }
+
+    /// Delay before retrying after `failures` consecutive failed runs: the
+    /// interval doubled per failure, capped at `max_backoff_minutes`. `None`
+    /// when backoff is off for this wakeup.
+    pub fn backoff_seconds(&self, failures: i32) -> Option<i64> {
+        if self.max_backoff_minutes <= 0 || failures <= 0 {
+            return None;
+        }
+        let interval = self.interval_minutes.max(1) * 60;
+        let cap = self.max_backoff_minutes * 60;
+        Some(
+            interval
+                .saturating_mul(1i64 << failures.min(30))
+                .min(cap.max(interval)),
+        )
+    }
+}
+
+/// Per-wakeup run options, fixed when the wakeup is created.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WakeupOptions {
+    /// Random delay of up to this many seconds added to each next run, so
+    /// wakeups created together do not fire together.
+    pub jitter_seconds: i64,
+    /// Runs of this wakeup allowed at once; a due run is skipped while
+    /// this many are still executing.
+    pub max_concurrency: i32,
+    /// Cap for exponential backoff after failed runs; `0` keeps the plain
+    /// interval.
+    pub max_backoff_minutes: i64,
+}
+
+impl Default for WakeupOptions {
+    fn default() -> Self {
+        Self {
+            jitter_seconds: 0,
+            max_concurrency: 1,
+            max_backoff_minutes: 0,
+        }
+    }
+}
+
+/// Wakeup runs still executing, so the scheduler can skip overlapping ones.
+#[derive(Clone, Default)]
+pub struct RunningWakeups {
+    running: Arc<Mutex<HashMap<i32, i32>>>,
+}
+
+impl RunningWakeups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claims a run slot for `task`, or `None` when `max_concurrency` runs
+    /// are already executing. The slot is released when the guard drops.
+    pub fn try_start(&self, task: &WakeupTask) -> Option<RunningGuard> {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        let count = running.entry(task.id).or_insert(0);
+        if *count >= task.max_concurrency.max(1) {
+            return None;
+        }
+        *count += 1;
+        Some(RunningGuard {
+            running: self.running.clone(),
+            id: task.id,
+        })
+    }
+
+    pub fn running(&self, id: i32) -> i32 {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        running.get(&id).copied().unwrap_or(0)
+    }
+}
+
+pub struct RunningGuard {
+    running: Arc<Mutex<HashMap<i32, i32>>>,
+    id: i32,
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        let mut running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = running.get_mut(&self.id) {
+            *count -= 1;
+            if *count <= 0 {
+                running.remove(&self.id);
+            }
+        }
+    }
+}
+
+fn jitter_roll(max_seconds: i64) -> i64 {
+    if max_seconds <= 0 {
+        return 0;
+    }
+    let mut bytes = [0u8; 8];
+    if SysRng.try_fill_bytes(&mut bytes).is_err() {
+        return 0;
+    }
+    (u64::from_le_bytes(bytes) % (max_seconds as u64 + 1)) as i64
 }
 
 #[derive(Queryable)]
@@ -48,6 +156,10 @@ struct WakeupRow {
     updated_at: i64,
     last_run_at: Option<i64>,
     next_run_at: i64,
+    jitter_seconds: i64,
+    max_concurrency: i32,
+    max_backoff_minutes: i64,
+    consecutive_failures: i32,
 }
 
 #[derive(Insertable)]
@@ -62,6 +174,9 @@ struct NewWakeup<'a> {
     updated_at: i64,
     last_run_at: Option<i64>,
     next_run_at: i64,
+    jitter_seconds: i64,
+    max_concurrency: i32,
+    max_backoff_minutes: i64,
 }
 
 #[derive(Clone, Copy)]
@@ -82,22 +197,33 @@ impl WakeupStatus {
 }
 
 pub struct WakeupStore {
-    pool: SqlitePool,
+    db: DbHandle,
+    clock: SharedClock,
 }
 
 impl WakeupStore {
     pub async fn new(sqlite_path: impl AsRef<str>) -> Result<Self> {
-        let sqlite_path = sqlite_path.as_ref();
-        ensure_parent_dir(sqlite_path)?;
+        Self::from_db(DbHandle::open(sqlite_path).await?).await
+    }
+
+    pub async fn from_db(db: DbHandle) -> Result<Self> {
+        let sqlite_path = db.path();
         run_migrations(sqlite_path).await?;
         ensure_wakeup_table(sqlite_path).await?;
+        Ok(Self {
+            db,
+            clock: system_clock(),
+        })
+    }
 
-        let manager = AsyncDieselConnectionManager::<SqliteAsyncConn>::new(sqlite_path);
-        let pool: SqlitePool = Pool::builder()
-            .build(manager)
-            .await
-            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        Ok(Self { pool })
+    /// Replaces the clock used for timestamps and due checks.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &SharedClock {
+        &self.clock
     }
 
     pub async fn create_task(
@@ -107,7 +233,25 @@ impl WakeupStore {
         prompt: &str,
         interval_minutes: i64,
     ) -> Result<WakeupTask> {
-        let now = now_ts();
+        self.create_task_with_options(
+            user_id,
+            name,
+            prompt,
+            interval_minutes,
+            WakeupOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn create_task_with_options(
+        &self,
+        user_id: &str,
+        name: &str,
+        prompt: &str,
+        interval_minutes: i64,
+        options: WakeupOptions,
+    ) -> Result<WakeupTask> {
+        let now = self.clock.now();
         let next_run_at = now + interval_minutes.max(1) * 60;
         let new = NewWakeup {
             user_id,
@@ -119,9 +263,12 @@ impl WakeupStore {
             updated_at: now,
             last_run_at: None,
             next_run_at,
+            jitter_seconds: options.jitter_seconds.max(0),
+            max_concurrency: options.max_concurrency.max(1),
+            max_backoff_minutes: options.max_backoff_minutes.max(0),
         };
 
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         diesel::insert_into(wakeup_tasks::table)
             .values(&new)
             .execute(&mut conn)
@@ -168,8 +315,8 @@ impl WakeupStore {
     }
 
     pub async fn set_enabled(&self, id: i32, enabled: bool) -> Result<WakeupTask> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         diesel::update(wakeup_tasks::table.filter(wakeup_tasks::id.eq(id)))
             .set((
                 wakeup_tasks::enabled.eq(enabled),
@@ -188,7 +335,7 @@ impl WakeupStore {
     }
 
     pub async fn delete_task(&self, id: i32) -> Result<bool> {
-        let mut conn = self.conn().await?;
+        let mut conn = self.write_conn().await?;
         let count = diesel::delete(wakeup_tasks::table.filter(wakeup_tasks::id.eq(id)))
             .execute(&mut conn)
             .await
@@ -210,8 +357,8 @@ impl WakeupStore {
     }
 
     pub async fn mark_run(&self, id: i32, last_run_at: i64, next_run_at: i64) -> Result<()> {
-        let now = now_ts();
-        let mut conn = self.conn().await?;
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
         diesel::update(wakeup_tasks::table.filter(wakeup_tasks::id.eq(id)))
             .set((
                 wakeup_tasks::last_run_at.eq(Some(last_run_at)),
@@ -224,14 +371,65 @@ impl WakeupStore {
         Ok(())
     }
 
-    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
-        let mut conn = self
-            .pool
-            .get()
+    /// Moves a skipped run to `next_run_at` without counting it as run.
+    pub async fn reschedule(&self, id: i32, next_run_at: i64) -> Result<()> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        diesel::update(wakeup_tasks::table.filter(wakeup_tasks::id.eq(id)))
+            .set((
+                wakeup_tasks::next_run_at.eq(next_run_at),
+                wakeup_tasks::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
             .await
             .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-        crate::db::apply_sqlcipher_key_async(&mut conn).await?;
-        Ok(conn)
+        Ok(())
+    }
+
+    /// Records how the run started at `run_at` ended. Failures count up and,
+    /// with backoff on, push the next run out; a success resets the count.
+    pub async fn record_outcome(&self, id: i32, run_at: i64, ok: bool) -> Result<WakeupTask> {
+        let now = self.clock.now();
+        let mut conn = self.write_conn().await?;
+        let task = map_row(
+            wakeup_tasks::table
+                .filter(wakeup_tasks::id.eq(id))
+                .first::<WakeupRow>(&mut conn)
+                .await
+                .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?,
+        );
+        let failures = if ok {
+            0
+        } else {
+            task.consecutive_failures.saturating_add(1)
+        };
+        let next_run_at = match task.backoff_seconds(failures) {
+            Some(delay) => task.next_run_at.max(run_at + delay),
+            None => task.next_run_at,
+        };
+        diesel::update(wakeup_tasks::table.filter(wakeup_tasks::id.eq(id)))
+            .set((
+                wakeup_tasks::consecutive_failures.eq(failures),
+                wakeup_tasks::next_run_at.eq(next_run_at),
+                wakeup_tasks::updated_at.eq(now),
+            ))
+            .execute(&mut conn)
+            .await
+            .map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
+        Ok(WakeupTask {
+            consecutive_failures: failures,
+            next_run_at,
+            updated_at: now,
+            ..task
+        })
+    }
+
+    async fn conn(&self) -> Result<SqlitePooledConn<'_>> {
+        self.db.conn().await
+    }
+
+    async fn write_conn(&self) -> Result<WriteConn<'_>> {
+        self.db.write_conn().await
     }
 }
 
@@ -249,14 +447,6 @@ pub fn default_wakeup_db_path() -> String {
     crate::runtime_paths::default_db_path()
 }
 
-fn ensure_parent_dir(path: &str) -> Result<()> {
-    let path = Path::new(path);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent).map_err(|e| ButterflyBotError::Runtime(e.to_string()))?;
-    }
-    Ok(())
-}
-
 async fn run_migrations(database_url: &str) -> Result<()> {
     let database_url = database_url.to_string();
     tokio::task::spawn_blocking(move || {
@@ -291,6 +481,22 @@ async fn ensure_wakeup_table(database_url: &str) -> Result<()> {
             }
         }
 
+        for statement in [
+            "ALTER TABLE wakeup_tasks ADD COLUMN jitter_seconds INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE wakeup_tasks ADD COLUMN max_concurrency INTEGER NOT NULL DEFAULT 1",
+            "ALTER TABLE wakeup_tasks ADD COLUMN max_backoff_minutes INTEGER NOT NULL DEFAULT 0",
+            "ALTER TABLE wakeup_tasks ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0",
+        ] {
+            if let Err(err) =
+                diesel::connection::SimpleConnection::batch_execute(&mut conn, statement)
+            {
+                let message = err.to_string().to_ascii_lowercase();
+                if !message.contains("duplicate column name") {
+                    return Err(ButterflyBotError::Runtime(err.to_string()));
+                }
+            }
+        }
+
         Ok::<_, ButterflyBotError>(())
     })
     .await
@@ -310,12 +516,72 @@ fn map_row(row: WakeupRow) -> WakeupTask {
         updated_at: row.updated_at,
         last_run_at: row.last_run_at,
         next_run_at: row.next_run_at,
+        jitter_seconds: row.jitter_seconds,
+        max_concurrency: row.max_concurrency,
+        max_backoff_minutes: row.max_backoff_minutes,
+        consecutive_failures: row.consecutive_failures,
     }
 }
 
-fn now_ts() -> i64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs() as i64
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(interval_minutes: i64, max_backoff_minutes: i64) -> WakeupTask {
+        WakeupTask {
+            id: 1,
+            user_id: "u".to_string(),
+            name: "digest".to_string(),
+            prompt: "summarize".to_string(),
+            interval_minutes,
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+            last_run_at: None,
+            next_run_at: 0,
+            jitter_seconds: 0,
+            max_concurrency: 1,
+            max_backoff_minutes,
+            consecutive_failures: 0,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_per_failure_up_to_the_cap() {
+        let capped = task(10, 60);
+        assert_eq!(capped.backoff_seconds(0), None);
+        assert_eq!(capped.backoff_seconds(1), Some(20 * 60));
+        assert_eq!(capped.backoff_seconds(2), Some(40 * 60));
+        assert_eq!(capped.backoff_seconds(3), Some(60 * 60));
+        assert_eq!(capped.backoff_seconds(40), Some(60 * 60));
+        assert_eq!(task(10, 0).backoff_seconds(3), None);
+
+        let jittered = WakeupTask {
+            jitter_seconds: 30,
+            ..capped
+        };
+        for _ in 0..20 {
+            let next = jittered.next_run_after(1_000);
+            assert!((1_600..=1_630).contains(&next), "{next}");
+        }
+    }
+
+    #[test]
+    fn running_wakeups_skip_past_max_concurrency() {
+        let running = RunningWakeups::new();
+        let single = task(10, 0);
+        let first = running.try_start(&single).expect("first run");
+        assert!(running.try_start(&single).is_none());
+        drop(first);
+        assert_eq!(running.running(single.id), 0);
+
+        let pair = WakeupTask {
+            max_concurrency: 2,
+            ..single
+        };
+        let _a = running.try_start(&pair).expect("first of two");
+        let _b = running.try_start(&pair).expect("second of two");
+        assert!(running.try_start(&pair).is_none());
+        assert_eq!(running.running(pair.id), 2);
+    }
 }
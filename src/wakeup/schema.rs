@@ -10,5 +10,9 @@ diesel::table! {
         updated_at -> BigInt,
         last_run_at -> Nullable<BigInt>,
         next_run_at -> BigInt,
+        jitter_seconds -> BigInt,
+        max_concurrency -> Integer,
+        max_backoff_minutes -> BigInt,
+        consecutive_failures -> Integer,
     }
 }
@@ -0,0 +1,324 @@
+//! `tools build`: compiles the per-tool WASM modules from `wasm-tool/`.
+//!
+//! Every module is built with its own `tool_<name>` feature, checked the
+//! same way the sandbox checks modules at startup, and staged. Only when
+//! all of them pass are they moved into the output directory, followed by a
+//! `manifest.json` with their SHA-256 hashes, so a failed build leaves the
+//! installed set untouched.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{ButterflyBotError, Result};
+use crate::sandbox::WasmRuntime;
+
+/// Tools with a `tool_<name>` feature in `wasm-tool/Cargo.toml`.
+pub const WASM_TOOLS: [&str; 12] = [
+    "coding",
+    "mcp",
+    "http_call",
+    "github",
+    "zapier",
+    "planning",
+    "reminders",
+    "search_internet",
+    "solana",
+    "tasks",
+    "todo",
+    "wakeup",
+];
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const WASM_TARGET: &str = "wasm32-unknown-unknown";
+const ARTIFACT: &str = "butterfly_bot_wasm_tool.wasm";
+
+#[derive(Clone, Debug)]
+pub struct BuildOptions {
+    /// Checkout root containing `wasm-tool/`.
+    pub repo_root: PathBuf,
+    pub out_dir: PathBuf,
+    /// Subset to build; empty means all of [`WASM_TOOLS`].
+    pub tools: Vec<String>,
+}
+
+impl BuildOptions {
+    pub fn for_repo(repo_root: impl Into<PathBuf>) -> Self {
+        let repo_root = repo_root.into();
+        Self {
+            out_dir: repo_root.join("wasm"),
+            repo_root,
+            tools: Vec::new(),
+        }
+    }
+
+    fn selected_tools(&self) -> Result<Vec<&'static str>> {
+        if self.tools.is_empty() {
+            return Ok(WASM_TOOLS.to_vec());
+        }
+        self.tools
+            .iter()
+            .map(|name| {
+                let name = name.trim().trim_end_matches("_tool");
+                WASM_TOOLS
+                    .iter()
+                    .copied()
+                    .find(|tool| *tool == name)
+                    .ok_or_else(|| {
+                        ButterflyBotError::Config(format!(
+                            "Unknown wasm tool '{name}'; expected one of: {}",
+                            WASM_TOOLS.join(", ")
+                        ))
+                    })
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub tool: String,
+    pub feature: String,
+    pub file: String,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildManifest {
+    pub built_at: i64,
+    pub modules: Vec<ManifestEntry>,
+}
+
+pub fn module_file_name(tool: &str) -> String {
+    format!("{tool}_tool.wasm")
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Builds with cargo; needs the `wasm32-unknown-unknown` target installed.
+pub fn build_tools(options: &BuildOptions) -> Result<BuildManifest> {
+    build_tools_with(options, |tool| compile_with_cargo(&options.repo_root, tool))
+}
+
+/// Like [`build_tools`], with `compile` producing each module's bytes.
+pub fn build_tools_with(
+    options: &BuildOptions,
+    mut compile: impl FnMut(&str) -> Result<Vec<u8>>,
+) -> Result<BuildManifest> {
+    let tools = options.selected_tools()?;
+    let staging = options
+        .out_dir
+        .join(format!(".staging-{}", std::process::id()));
+    create_dir(&staging)?;
+
+    let staged = stage_modules(&tools, &staging, &mut compile);
+    let result = staged.and_then(|modules| install(options, &staging, modules));
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+fn stage_modules(
+    tools: &[&str],
+    staging: &Path,
+    compile: &mut impl FnMut(&str) -> Result<Vec<u8>>,
+) -> Result<Vec<ManifestEntry>> {
+    let mut modules = Vec::with_capacity(tools.len());
+    for tool in tools {
+        let file = module_file_name(tool);
+        let bytes = compile(tool)?;
+        WasmRuntime::validate_module_bytes(tool, &file, &bytes)?;
+        write_file(&staging.join(&file), &bytes)?;
+        modules.push(ManifestEntry {
+            tool: tool.to_string(),
+            feature: format!("tool_{tool}"),
+            sha256: sha256_hex(&bytes),
+            bytes: bytes.len() as u64,
+            file,
+        });
+    }
+    Ok(modules)
+}
+
+fn install(
+    options: &BuildOptions,
+    staging: &Path,
+    modules: Vec<ManifestEntry>,
+) -> Result<BuildManifest> {
+    for module in &modules {
+        rename(
+            &staging.join(&module.file),
+            &options.out_dir.join(&module.file),
+        )?;
+    }
+
+    // Keep entries for modules this run didn't rebuild.
+    let mut merged = read_manifest(&options.out_dir)
+        .map(|manifest| manifest.modules)
+        .unwrap_or_default();
+    merged.retain(|entry| !modules.iter().any(|module| module.tool == entry.tool));
+    merged.extend(modules);
+    merged.sort_by_key(|entry| {
+        WASM_TOOLS
+            .iter()
+            .position(|tool| *tool == entry.tool)
+            .unwrap_or(usize::MAX)
+    });
+
+    let manifest = BuildManifest {
+        built_at: crate::clock::system_clock().now(),
+        modules: merged,
+    };
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| ButterflyBotError::Serialization(e.to_string()))?;
+    let tmp = staging.join(MANIFEST_FILE);
+    write_file(&tmp, &json)?;
+    rename(&tmp, &options.out_dir.join(MANIFEST_FILE))?;
+    Ok(manifest)
+}
+
+pub fn read_manifest(out_dir: &Path) -> Option<BuildManifest> {
+    let bytes = std::fs::read(out_dir.join(MANIFEST_FILE)).ok()?;
+    serde_json::from_slice(&bytes).ok()
+}
+
+fn compile_with_cargo(repo_root: &Path, tool: &str) -> Result<Vec<u8>> {
+    let crate_dir = repo_root.join("wasm-tool");
+    let status = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(crate_dir.join("Cargo.toml"))
+        .args([
+            "--target",
+            WASM_TARGET,
+            "--release",
+            "--no-default-features",
+        ])
+        .args(["--features", &format!("tool_{tool}")])
+        .status()
+        .map_err(|e| ButterflyBotError::Runtime(format!("Failed to run cargo: {e}")))?;
+    if !status.success() {
+        return Err(ButterflyBotError::Runtime(format!(
+            "cargo build for wasm tool '{tool}' failed ({status})"
+        )));
+    }
+    let artifact = crate_dir
+        .join("target")
+        .join(WASM_TARGET)
+        .join("release")
+        .join(ARTIFACT);
+    std::fs::read(&artifact).map_err(|e| {
+        ButterflyBotError::Runtime(format!(
+            "Failed to read built module {}: {e}",
+            artifact.to_string_lossy()
+        ))
+    })
+}
+
+fn create_dir(path: &Path) -> Result<()> {
+    std::fs::create_dir_all(path).map_err(|e| {
+        ButterflyBotError::Runtime(format!("Failed to create {}: {e}", path.to_string_lossy()))
+    })
+}
+
+fn write_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    std::fs::write(path, bytes).map_err(|e| {
+        ButterflyBotError::Runtime(format!("Failed to write {}: {e}", path.to_string_lossy()))
+    })
+}
+
+fn rename(from: &Path, to: &Path) -> Result<()> {
+    std::fs::rename(from, to).map_err(|e| {
+        ButterflyBotError::Runtime(format!("Failed to install {}: {e}", to.to_string_lossy()))
+    })
+}
+
+/// One line per module with a short hash, for the CLI.
+pub fn format_manifest(manifest: &BuildManifest, out_dir: &Path) -> String {
+    let mut lines = manifest
+        .modules
+        .iter()
+        .map(|module| {
+            format!(
+                "{:<26} {:>9} bytes  sha256:{}",
+                module.file,
+                module.bytes,
+                &module.sha256[..module.sha256.len().min(16)]
+            )
+        })
+        .collect::<Vec<_>>();
+    lines.push(format!(
+        "Installed to {}",
+        out_dir.join(MANIFEST_FILE).to_string_lossy()
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(body: &[u8]) -> Vec<u8> {
+        let mut bytes = vec![0x00, 0x61, 0x73, 0x6D];
+        bytes.extend_from_slice(body);
+        bytes
+    }
+
+    #[test]
+    fn installs_all_modules_with_a_manifest() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut options = BuildOptions::for_repo(temp.path());
+        options.tools = vec!["todo".to_string(), "reminders_tool".to_string()];
+
+        let manifest = build_tools_with(&options, |tool| Ok(module(tool.as_bytes()))).unwrap();
+        assert_eq!(
+            manifest
+                .modules
+                .iter()
+                .map(|m| m.file.as_str())
+                .collect::<Vec<_>>(),
+            vec!["reminders_tool.wasm", "todo_tool.wasm"]
+        );
+        let installed = std::fs::read(options.out_dir.join("todo_tool.wasm")).unwrap();
+        assert_eq!(manifest.modules[1].sha256, sha256_hex(&installed));
+        assert_eq!(read_manifest(&options.out_dir), Some(manifest));
+
+        options.tools = vec!["todo".to_string()];
+        let manifest = build_tools_with(&options, |_| Ok(module(b"v2"))).unwrap();
+        assert_eq!(manifest.modules.len(), 2);
+        assert_eq!(manifest.modules[1].bytes, 6);
+    }
+
+    #[test]
+    fn a_failing_module_leaves_the_installed_set_alone() {
+        let temp = tempfile::tempdir().unwrap();
+        let mut options = BuildOptions::for_repo(temp.path());
+        options.tools = vec!["todo".to_string(), "tasks".to_string()];
+
+        let err = build_tools_with(&options, |tool| {
+            Ok(if tool == "tasks" {
+                module(b"\"stub\":true")
+            } else {
+                module(b"ok")
+            })
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("placeholder stub"));
+        assert!(!options.out_dir.join("todo_tool.wasm").exists());
+        assert!(read_manifest(&options.out_dir).is_none());
+        assert_eq!(
+            std::fs::read_dir(&options.out_dir).unwrap().count(),
+            0,
+            "staging directory should be cleaned up"
+        );
+
+        options.tools = vec!["quantum".to_string()];
+        assert!(build_tools_with(&options, |_| Ok(module(b""))).is_err());
+    }
+}
@@ -44,6 +44,10 @@ const BUNDLED_WASM_MODULES: [(&str, &[u8]); 12] = [
     ),
 ];
 
+/// Set by `butterfly-botd --dev` to a directory of freshly built modules;
+/// they are used as-is instead of being replaced by the bundled copies.
+pub const DEV_WASM_DIR_ENV: &str = "BUTTERFLY_BOT_WASM_DEV_DIR";
+
 fn write_module_if_needed(root: &Path, file_name: &str, content: &[u8]) -> Result<()> {
     let path = root.join(file_name);
 
@@ -79,6 +83,13 @@ fn write_module_if_needed(root: &Path, file_name: &str, content: &[u8]) -> Resul
     })
 }
 
+/// Whether `bytes` are one of the modules shipped inside this binary.
+pub fn is_bundled_module(bytes: &[u8]) -> bool {
+    BUNDLED_WASM_MODULES
+        .iter()
+        .any(|(_, content)| *content == bytes)
+}
+
 fn provision_into_dir(wasm_dir: &Path) -> Result<()> {
     std::fs::create_dir_all(wasm_dir).map_err(|e| {
         ButterflyBotError::Runtime(format!(
@@ -96,6 +107,13 @@ fn provision_into_dir(wasm_dir: &Path) -> Result<()> {
 }
 
 pub fn ensure_bundled_wasm_tools() -> Result<PathBuf> {
+    if let Some(dev_dir) = std::env::var(DEV_WASM_DIR_ENV)
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+    {
+        return Ok(PathBuf::from(dev_dir.trim()));
+    }
+
     let mut tried = Vec::new();
     let mut last_err = None;
     let candidates = crate::runtime_paths::default_wasm_dir_candidates();
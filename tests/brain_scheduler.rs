@@ -107,3 +107,18 @@ async fn scheduler_runs_jobs() {
     let count = result.expect("scheduler test timed out");
     assert!(count >= 2);
 }
+
+#[tokio::test]
+async fn scheduler_run_once_steps_each_job_without_timers() {
+    let count = Arc::new(Mutex::new(0u32));
+    let mut scheduler = Scheduler::new();
+    scheduler.register_job(Arc::new(TickJob {
+        count: count.clone(),
+    }));
+
+    scheduler.run_once().await;
+    scheduler.run_once().await;
+
+    assert!(!scheduler.is_running());
+    assert_eq!(*count.lock().unwrap(), 2);
+}
@@ -32,6 +32,7 @@ async fn config_from_file_and_factory_errors() {
             model: None,
             base_url: Some("http://localhost:11434/v1".to_string()),
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
@@ -49,6 +50,7 @@ async fn config_from_file_and_factory_errors() {
             model: None,
             base_url: None,
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
@@ -69,6 +71,7 @@ async fn config_from_file_and_factory_errors() {
     let missing = Config {
         provider: None,
         openai: None,
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
@@ -2,21 +2,31 @@ use std::sync::{Arc, OnceLock};
 
 use axum::body::Body;
 use axum::http::{Request, StatusCode};
+use hmac::{Hmac, Mac};
 use http_body_util::BodyExt;
 use httpmock::Method::POST;
 use httpmock::MockServer;
 use serde_json::json;
+use sha2::Sha256;
 use tempfile::tempdir;
 use tokio::sync::{broadcast, RwLock};
 use tower::ServiceExt;
 
+use butterfly_bot::approvals::{ApprovalStore, ChainStep, TimeoutAction};
+use butterfly_bot::audit::AuditStore;
 use butterfly_bot::client::ButterflyBot;
 use butterfly_bot::config::{Config, MarkdownSource, OpenAiConfig};
 use butterfly_bot::config_store;
+use butterfly_bot::consent::ConsentStore;
 use butterfly_bot::daemon::{build_router, AppState};
 use butterfly_bot::inbox_state::InboxStateStore;
 use butterfly_bot::planning::PlanStore;
+use butterfly_bot::questions::QuestionStore;
 use butterfly_bot::reminders::ReminderStore;
+use butterfly_bot::roles::{Role, RolePolicy, RoleStore};
+use butterfly_bot::scheduler::state::JobStateStore;
+use butterfly_bot::services::agent::UiEvent;
+use butterfly_bot::sessions::SessionStore;
 use butterfly_bot::tasks::TaskStore;
 use butterfly_bot::todo::TodoStore;
 
@@ -57,6 +67,7 @@ async fn make_agent(server: &MockServer) -> ButterflyBot {
             model: Some("gpt-4o-mini".to_string()),
             base_url: Some(server.base_url()),
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
@@ -88,6 +99,9 @@ async fn daemon_health_and_auth() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -135,6 +149,9 @@ async fn daemon_empty_token_fails_closed() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -185,6 +202,9 @@ async fn daemon_process_text_and_memory_search() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -325,6 +345,9 @@ async fn daemon_inbox_and_actionable_count() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -451,65 +474,52 @@ async fn daemon_inbox_and_actionable_count() {
 }
 
 #[tokio::test]
-async fn daemon_plan_dependency_refs_are_relational_and_visible_in_inbox() {
+async fn daemon_inbox_smart_lists_bucket_by_due_date() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-plan-deps.db");
+    let db_file = temp.path().join("daemon-smart-lists.db");
     let db_path = db_file.to_string_lossy().to_string();
 
-    let plan_store = PlanStore::new(&db_path).await.unwrap();
-    let plan = plan_store
-        .create_plan(
-            "u",
-            "Dependency plan",
-            "Wire dependency graph",
-            Some(&json!([
-                {"id": "A1", "title": "Prepare schema", "owner": "human"},
-                {"id": "A2", "title": "Hook UI", "owner": "agent", "dependency_refs": ["A1"]}
-            ])),
-            Some("active"),
-        )
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let overdue = reminder_store
+        .create_reminder("u", "Renew passport", now - 3600)
+        .await
+        .unwrap();
+    let someday = reminder_store
+        .create_reminder("u", "Learn the cello", now + 315_360_000)
         .await
         .unwrap();
 
-    let dep_map = plan_store
-        .list_step_dependencies_for_plans(&[plan.id])
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let todo = todo_store
+        .create_item("u", "Sort photo library", None, None)
         .await
         .unwrap();
-    let dependent_step_ref = format!("plan_step:{}:{}", plan.id, 1);
-    let required_step_ref = format!("plan_step:{}:{}", plan.id, 0);
-    let relational_deps = dep_map
-        .get(&dependent_step_ref)
-        .cloned()
-        .unwrap_or_default();
-    assert!(
-        relational_deps
-            .iter()
-            .any(|dep| dep == &required_step_ref.to_ascii_lowercase()),
-        "expected relational dependency edge {} -> {}",
-        dependent_step_ref,
-        required_step_ref
-    );
 
-    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let (ui_event_tx, _) = broadcast::channel(16);
+    let (ui_event_tx, _) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
 
     let response = app
-        .clone()
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .uri("/inbox/smart_lists?user_id=u")
                 .header("authorization", "Bearer token")
                 .body(Body::empty())
                 .unwrap(),
@@ -520,62 +530,61 @@ async fn daemon_plan_dependency_refs_are_relational_and_visible_in_inbox() {
 
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
     let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let items = value
-        .get("items")
+    let lists = value
+        .get("lists")
         .and_then(|v| v.as_array())
         .cloned()
         .unwrap_or_default();
-
-    let dependent_step = items
+    let keys: Vec<&str> = lists
         .iter()
-        .find(|item| item.get("origin_ref").and_then(|v| v.as_str()) == Some(&dependent_step_ref))
-        .expect("expected dependent plan step in inbox");
-    let inbox_deps = dependent_step
-        .get("dependency_refs")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    assert!(
-        inbox_deps
-            .iter()
-            .any(|dep| dep.as_str() == Some(required_step_ref.as_str())),
-        "expected inbox dependency edge {} -> {}",
-        dependent_step_ref,
-        required_step_ref
+        .filter_map(|list| list.get("key").and_then(|v| v.as_str()))
+        .collect();
+    assert_eq!(
+        keys,
+        vec!["overdue", "today", "this_week", "later", "someday"]
     );
+
+    let list_refs = |key: &str| -> Vec<String> {
+        lists
+            .iter()
+            .find(|list| list.get("key").and_then(|v| v.as_str()) == Some(key))
+            .and_then(|list| list.get("items"))
+            .and_then(|v| v.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.get("origin_ref").and_then(|v| v.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    assert!(list_refs("overdue").contains(&format!("reminder:{}", overdue.id)));
+    let someday_refs = list_refs("someday");
+    assert!(someday_refs.contains(&format!("reminder:{}", someday.id)));
+    assert!(someday_refs.contains(&format!("todo:{}", todo.id)));
 }
 
 #[tokio::test]
-async fn daemon_plan_dependency_title_aliases_resolve_in_inbox() {
+async fn daemon_per_user_tokens_scope_requests_to_owner() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-plan-title-alias-deps.db");
+    let db_file = temp.path().join("daemon-user-tokens.db");
     let db_path = db_file.to_string_lossy().to_string();
-
-    let plan_store = PlanStore::new(&db_path).await.unwrap();
-    let plan = plan_store
-        .create_plan(
-            "u",
-            "Food delivery plan",
-            "Launch service",
-            Some(&json!([
-                {"title": "Conduct market research", "owner": "human"},
-                {"title": "Develop business plan", "owner": "human", "depends_on": "market research"}
-            ])),
-            Some("active"),
-        )
-        .await
-        .unwrap();
-
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let (ui_event_tx, _) = broadcast::channel(16);
+
+    let (ui_event_tx, _) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -584,71 +593,99 @@ async fn daemon_plan_dependency_title_aliases_resolve_in_inbox() {
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .method("POST")
+                .uri("/auth/tokens")
                 .header("authorization", "Bearer token")
-                .body(Body::empty())
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"user_id": "alice", "label": "laptop"}).to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let issued: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let alice_token = issued["token"].as_str().unwrap().to_string();
+    let token_id = issued["id"].as_i64().unwrap();
+
+    let inbox_status = |user: &str, token: &str| {
+        let app = app.clone();
+        let uri = format!("/inbox?user_id={user}");
+        let auth = format!("Bearer {token}");
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri(uri)
+                    .header("authorization", auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+        }
+    };
+
+    assert_eq!(inbox_status("alice", &alice_token).await, StatusCode::OK);
+    assert_eq!(
+        inbox_status("bob", &alice_token).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        inbox_status("alice", "bbu_bogus").await,
+        StatusCode::UNAUTHORIZED
+    );
 
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/auth/tokens/rotate")
+                .header("authorization", format!("Bearer {alice_token}"))
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"user_id": "alice", "token_id": token_id}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let items = value
-        .get("items")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
+    let rotated: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let rotated_token = rotated["token"].as_str().unwrap().to_string();
 
-    let dependent_ref = format!("plan_step:{}:{}", plan.id, 1);
-    let prerequisite_ref = format!("plan_step:{}:{}", plan.id, 0);
-    let dependent_item = items
-        .iter()
-        .find(|item| item.get("origin_ref").and_then(|v| v.as_str()) == Some(&dependent_ref))
-        .expect("expected dependent plan step in inbox");
-    let deps = dependent_item
-        .get("dependency_refs")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    assert!(
-        deps.iter()
-            .any(|value| value.as_str() == Some(prerequisite_ref.as_str())),
-        "expected '{}' to resolve to '{}'",
-        dependent_ref,
-        prerequisite_ref
+    assert_eq!(
+        inbox_status("alice", &alice_token).await,
+        StatusCode::UNAUTHORIZED
     );
+    assert_eq!(inbox_status("alice", &rotated_token).await, StatusCode::OK);
+    assert_eq!(inbox_status("bob", "token").await, StatusCode::OK);
 }
 
 #[tokio::test]
-async fn daemon_inbox_parses_todo_dependencies_from_notes_fallback() {
+async fn daemon_role_management_requires_admin() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-todo-notes-deps.db");
+    let db_file = temp.path().join("daemon-roles.db");
     let db_path = db_file.to_string_lossy().to_string();
-
-    let todo_store = TodoStore::new(&db_path).await.unwrap();
-    let prereq = todo_store
-        .create_item("u", "Prerequisite todo", Some("first"), None)
-        .await
-        .unwrap();
-    let dependent_notes = format!("Depends On: todo:{}", prereq.id);
-    let _dependent = todo_store
-        .create_item("u", "Dependent todo", Some(&dependent_notes), None)
-        .await
-        .unwrap();
-
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let (ui_event_tx, _) = broadcast::channel(16);
+
+    let (ui_event_tx, _) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -657,208 +694,4206 @@ async fn daemon_inbox_parses_todo_dependencies_from_notes_fallback() {
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .method("POST")
+                .uri("/auth/tokens")
                 .header("authorization", "Bearer token")
-                .body(Body::empty())
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"user_id": "carol"}).to_string()))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let items = value
-        .get("items")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
+    let issued: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let carol_token = issued["token"].as_str().unwrap().to_string();
+
+    let set_role = |auth: String, user: &str, role: &str| {
+        let app = app.clone();
+        let body = json!({"user_id": user, "role": role}).to_string();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/auth/roles")
+                    .header("authorization", auth)
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+        }
+    };
+    let list_roles = |auth: String| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/auth/roles")
+                    .header("authorization", auth)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
 
-    let dependent_item = items
-        .iter()
-        .find(|item| {
-            item.get("source_type").and_then(|v| v.as_str()) == Some("todo")
-                && item
-                    .get("title")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .contains("Dependent todo")
-        })
-        .expect("expected dependent todo item");
-    let deps = dependent_item
-        .get("dependency_refs")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    assert!(
-        deps.iter()
-            .any(|value| value.as_str() == Some(format!("todo:{}", prereq.id).as_str())),
-        "expected notes dependency to be visible in inbox"
+    assert_eq!(
+        set_role("Bearer token".to_string(), "carol", "member").await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        set_role("Bearer token".to_string(), "dave", "superuser").await,
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        set_role(format!("Bearer {carol_token}"), "carol", "admin").await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        list_roles(format!("Bearer {carol_token}")).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    assert_eq!(
+        set_role("Bearer token".to_string(), "carol", "admin").await,
+        StatusCode::OK
     );
+    let response = list_roles(format!("Bearer {carol_token}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let listed: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(listed["roles"][0]["user_id"], "carol");
+    assert_eq!(listed["roles"][0]["role"], "admin");
+    assert_eq!(listed["roles"][0]["granted_by"], "daemon");
 }
 
 #[tokio::test]
-async fn daemon_clear_user_data_endpoint() {
+async fn daemon_manages_named_secrets_for_admins() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-clear-user-data.db");
+    let db_file = temp.path().join("daemon-secrets.db");
     let db_path = db_file.to_string_lossy().to_string();
-
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    let reminder = reminder_store
-        .create_reminder("u", "Call dentist", now + 300)
-        .await
-        .unwrap();
-
-    let todo_store = TodoStore::new(&db_path).await.unwrap();
-    let _todo = todo_store
-        .create_item("u", "Pack bag", Some("for trip"), None)
-        .await
-        .unwrap();
-
-    let task_store = TaskStore::new(&db_path).await.unwrap();
-    let _task = task_store
-        .create_task("u", "Daily recap", "Write recap", now + 60, None)
-        .await
-        .unwrap();
-
-    let plan_store = PlanStore::new(&db_path).await.unwrap();
-    let _plan = plan_store
-        .create_plan("u", "Launch", "ship release", None, Some("active"))
-        .await
-        .unwrap();
 
-    let inbox_state_store = InboxStateStore::new(&db_path).await.unwrap();
-    inbox_state_store
-        .set_status("u", &format!("reminder:{}", reminder.id), "acknowledged")
-        .await
-        .unwrap();
-
-    let (ui_event_tx, _) = broadcast::channel(16);
+    let (ui_event_tx, _) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
-        db_path: db_path.clone(),
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
     };
     let app = build_router(state);
 
-    let response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("POST")
-                .uri("/clear_user_data")
+    let call = |method: &str, uri: &str, auth: String, body: serde_json::Value| {
+        let app = app.clone();
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", auth)
+            .header("content-type", "application/json")
+            .body(if method == "GET" {
+                Body::empty()
+            } else {
+                Body::from(body.to_string())
+            })
+            .unwrap();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let body = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default();
+            (status, body)
+        }
+    };
+    let admin = || "Bearer token".to_string();
+    let name = "daemon_test_crm_api_key";
+
+    let (status, created) = call(
+        "POST",
+        "/vault/secrets",
+        admin(),
+        json!({"name": name, "value": "v1", "description": "CRM"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(created["version"], 1);
+    assert_eq!(
+        butterfly_bot::vault::get_secret(name).unwrap().as_deref(),
+        Some("v1")
+    );
+    let (status, _) = call(
+        "POST",
+        "/vault/secrets",
+        admin(),
+        json!({"name": name, "value": "again"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = call(
+        "POST",
+        "/vault/secrets",
+        admin(),
+        json!({"name": "db_encryption_key", "value": "x"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, rotated) = call(
+        "POST",
+        "/vault/secrets/rotate",
+        admin(),
+        json!({"name": name, "value": "v2"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(rotated["version"], 2);
+    assert_eq!(
+        butterfly_bot::vault::get_secret(name).unwrap().as_deref(),
+        Some("v2")
+    );
+
+    let (status, listed) = call("GET", "/vault/secrets", admin(), json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let entry = listed["secrets"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["name"] == name)
+        .unwrap();
+    assert_eq!(entry["description"], "CRM");
+    assert!(entry.get("value").is_none());
+
+    let (_, issued) = call("POST", "/auth/tokens", admin(), json!({"user_id": "erin"})).await;
+    let erin = format!("Bearer {}", issued["token"].as_str().unwrap());
+    call(
+        "POST",
+        "/auth/roles",
+        admin(),
+        json!({"user_id": "erin", "role": "member"}),
+    )
+    .await;
+    let (status, _) = call("GET", "/vault/secrets", erin.clone(), json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = call("POST", "/vault/secrets/delete", erin, json!({"name": name})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, deleted) = call(
+        "POST",
+        "/vault/secrets/delete",
+        admin(),
+        json!({"name": name}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(deleted["deleted"], true);
+    assert_eq!(butterfly_bot::vault::get_secret(name).unwrap(), None);
+}
+
+#[tokio::test]
+async fn daemon_process_text_stream_emits_sse_events() {
+    let server = MockServer::start_async().await;
+    server
+        .mock_async(|when, then| {
+            when.method(POST).path("/chat/completions");
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-stream",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "streamed hello"},
+                    "finish_reason": "stop"
+                }]
+            }));
+        })
+        .await;
+
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-sse.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/process_text/stream")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"user_id":"u","text":"hello"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok()),
+        Some("text/event-stream")
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    let frames: Vec<&str> = body.split("\n\n").filter(|f| !f.is_empty()).collect();
+    let streamed: String = frames
+        .iter()
+        .filter_map(|frame| frame.strip_prefix("event: token\ndata: "))
+        .map(|data| {
+            let value: serde_json::Value = serde_json::from_str(data).unwrap();
+            value["text"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(streamed, "streamed hello");
+    assert_eq!(frames.last().copied(), Some("event: done\ndata: {}"));
+}
+
+#[tokio::test]
+async fn daemon_catch_up_summarizes_changes_since_timestamp() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-catch-up.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let since = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        - 60;
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    todo_store
+        .create_item("u", "Book flights", None, None)
+        .await
+        .unwrap();
+    let done = todo_store
+        .create_item("u", "Renew passport", None, None)
+        .await
+        .unwrap();
+    todo_store.set_completed(done.id, true).await.unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let reminder = reminder_store
+        .create_reminder("u", "Stretch", since)
+        .await
+        .unwrap();
+    assert!(reminder_store
+        .mark_fired_reminder("u", reminder.id, since + 30)
+        .await
+        .unwrap());
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/catch_up?user_id=u&since={since}"))
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+    let titles = |key: &str| {
+        value[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry["title"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+    assert!(titles("new_items").contains(&"Book flights".to_string()));
+    assert_eq!(
+        titles("completed_items"),
+        vec!["Renew passport".to_string()]
+    );
+    assert_eq!(titles("fired_reminders"), vec!["Stretch".to_string()]);
+    assert!(value["summary"]
+        .as_str()
+        .unwrap()
+        .contains("1 reminder fired"));
+    assert!(value["agent_context"]
+        .as_str()
+        .unwrap()
+        .contains("Renew passport"));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri(format!("/catch_up?user_id=u&since={}", since + 3_600))
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["summary"], json!(""));
+}
+
+#[tokio::test]
+async fn daemon_digest_preview_lists_due_reminders() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-digest.db")
+        .to_string_lossy()
+        .to_string();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    reminder_store
+        .create_reminder("u", "Water the plants", now - 60)
+        .await
+        .unwrap();
+    TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .create_item("u", "Someday: learn the cello", None, None)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/digest/preview?user_id=u")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/digest/preview?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["scheduled"], json!(false));
+    assert_eq!(value["counts"]["reminders"], json!(1));
+    assert_eq!(value["counts"]["blocked"], json!(0));
+    let text = value["text"].as_str().unwrap();
+    assert!(text.contains("- Water the plants"));
+    assert!(!text.contains("cello"));
+}
+
+#[tokio::test]
+async fn daemon_capabilities_report_lists_allowlist_and_host_coverage() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-capabilities.db")
+        .to_string_lossy()
+        .to_string();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/capabilities/report")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["consistent"], json!(true));
+    let capabilities = value["capabilities"].as_array().unwrap();
+    let transfer = capabilities
+        .iter()
+        .find(|entry| entry["capability"] == "solana.transfer")
+        .unwrap();
+    assert_eq!(transfer["tool"], json!("solana"));
+    assert_eq!(transfer["allowlisted"], json!(true));
+    assert_eq!(transfer["host_handler"], json!(true));
+}
+
+#[tokio::test]
+async fn daemon_capabilities_lists_tools_with_schema_and_allowlist() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-capability-discovery.db")
+        .to_string_lossy()
+        .to_string();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/capabilities")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/capabilities")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["abi_version"], json!(1));
+    let tools = value["tools"].as_array().unwrap();
+    let todo = tools
+        .iter()
+        .find(|tool| tool["name"] == "todo")
+        .expect("todo tool listed");
+    assert_eq!(todo["runtime"], json!("wasm"));
+    assert_eq!(todo["abi_version"], json!(1));
+    assert!(todo["capabilities"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("kv.sqlite.todo.create")));
+    assert_eq!(todo["input_schema"]["type"], json!("object"));
+}
+
+#[tokio::test]
+async fn daemon_tool_posture_labels_risk_and_last_use() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-tool-posture.db")
+        .to_string_lossy()
+        .to_string();
+    AuditStore::new(&db_path)
+        .await
+        .unwrap()
+        .record(&UiEvent {
+            event_type: "tool".to_string(),
+            user_id: "user".to_string(),
+            tool: "todo".to_string(),
+            status: "success".to_string(),
+            payload: json!({}),
+            timestamp: 1_700_000_000,
+        })
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/tools/posture")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let tools = value["tools"].as_array().unwrap();
+    let todo = tools
+        .iter()
+        .find(|tool| tool["name"] == "todo")
+        .expect("todo tool listed");
+    assert_eq!(todo["risk"], json!("low"));
+    assert_eq!(todo["areas"], json!([]));
+    assert_eq!(todo["last_used_at"], json!(1_700_000_000));
+    let reminders = tools
+        .iter()
+        .find(|tool| tool["name"] == "reminders")
+        .expect("reminders tool listed");
+    assert_eq!(reminders["last_used_at"], json!(null));
+}
+
+#[tokio::test]
+async fn daemon_backup_run_writes_a_snapshot_listed_in_status() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-backup.db")
+        .to_string_lossy()
+        .to_string();
+
+    let (ui_event_tx, mut ui_events) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/backups/run")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let run: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(run["bytes"].as_u64().unwrap() > 0);
+    assert_eq!(run["remote_key"], json!(null));
+    let event = ui_events.recv().await.unwrap();
+    assert_eq!(event.event_type, "backup");
+    assert_eq!(event.status, "success");
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/backups/status")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let status: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(status["enabled"], json!(true));
+    assert_eq!(status["keep"], json!(7));
+    let snapshots = status["snapshots"].as_array().unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0]["path"], run["snapshot"]);
+}
+
+#[tokio::test]
+async fn daemon_template_import_requires_reviewed_digest() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-templates.db")
+        .to_string_lossy()
+        .to_string();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+
+    let bundle = json!({
+        "format": "butterfly-template",
+        "version": 1,
+        "name": "Home admin",
+        "plans": [{"title": "Move house", "goal": "new flat", "steps": ["book van", "pack"]}],
+        "checklists": [{"name": "Sunday reset", "items": ["laundry", "bins"], "schedule": "weekly"}],
+        "rules": [{"name": "Bills", "prompt": "Remind me about unpaid bills", "interval_minutes": 1440, "tools": ["reminders"]}],
+        "prompt_templates": [{"name": "weekly-recap", "body": "Summarize my week"}]
+    });
+    let post = |uri: &'static str, body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/templates/preview",
+            json!({"user_id": "user", "bundle": bundle}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let preview: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(preview["creates"].as_array().unwrap().len(), 4);
+    assert_eq!(preview["capabilities"][0]["tool"], json!("reminders"));
+    assert!(preview["capabilities"][0]["capabilities"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("kv.sqlite.reminders.create")));
+    let digest = preview["digest"].as_str().unwrap().to_string();
+
+    let mut edited = bundle.clone();
+    edited["rules"][0]["prompt"] = json!("Pay every bill automatically");
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/templates/import",
+            json!({"user_id": "user", "bundle": edited, "digest": digest}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/templates/import",
+            json!({"user_id": "user", "bundle": bundle, "digest": digest}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let imported: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(
+        imported["imported"]["plan_ids"].as_array().unwrap().len(),
+        1
+    );
+    assert_eq!(
+        imported["imported"]["task_ids"].as_array().unwrap().len(),
+        1
+    );
+    assert_eq!(
+        imported["imported"]["prompt_templates"],
+        json!(["weekly-recap"])
+    );
+
+    let prompts = butterfly_bot::templates::PromptTemplateStore::new(&db_path)
+        .await
+        .unwrap()
+        .list("user")
+        .await
+        .unwrap();
+    assert_eq!(prompts[0].source.as_deref(), Some("Home admin"));
+}
+
+#[tokio::test]
+async fn daemon_search_finds_items_across_stores() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-search.db")
+        .to_string_lossy()
+        .to_string();
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let todo = todo_store
+        .create_item("user", "Send documents", Some("tax return for 2025"), None)
+        .await
+        .unwrap();
+    todo_store
+        .create_item("user", "Buy milk", None, None)
+        .await
+        .unwrap();
+    todo_store
+        .create_item("other", "My own taxes", None, None)
+        .await
+        .unwrap();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let reminder = reminder_store
+        .create_reminder("user", "Pay taxes", 4_102_444_800)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let get = |uri: &str| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(get("/search?user_id=user&q=that%20thing%20about%20taxes"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["indexed"], json!(true));
+    let refs = value["hits"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hit| hit["origin_ref"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        refs,
+        vec![
+            format!("reminder:{}", reminder.id),
+            format!("todo:{}", todo.id)
+        ]
+    );
+
+    let response = app
+        .clone()
+        .oneshot(get("/search?user_id=user&q=taxes&kinds=todo"))
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["hits"].as_array().unwrap().len(), 1);
+    assert!(value["hits"][0]["snippet"]
+        .as_str()
+        .unwrap()
+        .contains("[tax]"));
+
+    let response = app
+        .oneshot(get("/search?user_id=user&q=%20"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn daemon_thread_pin_routes_chat_to_pinned_model() {
+    let server = MockServer::start_async().await;
+    let completion = |content: &str| {
+        json!({
+            "id": "chatcmpl-test",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": content},
+                "finish_reason": "stop"
+            }]
+        })
+    };
+    let pinned_mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/chat/completions")
+                .body_includes("\"model\":\"pinned-model\"")
+                .body_includes("\"temperature\":0.25");
+            then.status(200).json_body(completion("from pinned"));
+        })
+        .await;
+    let default_mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/chat/completions")
+                .body_includes("\"model\":\"gpt-4o-mini\"");
+            then.status(200).json_body(completion("from default"));
+        })
+        .await;
+
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-threads.db")
+        .to_string_lossy()
+        .to_string();
+    let mut config = Config::convention_defaults(&db_path);
+    config.openai = Some(OpenAiConfig {
+        api_key: Some("key".to_string()),
+        model: Some("gpt-4o-mini".to_string()),
+        base_url: Some(server.base_url()),
+    });
+    config_store::save_config(&db_path, &config).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let post = |uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/threads/pin",
+            json!({"user_id": "user", "thread_id": "planning", "model": "pinned-model", "temperature": 0.25}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // No Anthropic key is configured, so this pin could never answer.
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/threads/pin",
+            json!({"user_id": "user", "thread_id": "planning", "provider": "anthropic"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/threads?user_id=user")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["default_model"], json!("openai:gpt-4o-mini"));
+    assert_eq!(value["threads"][0]["model"], json!("pinned-model"));
+
+    for (thread_id, expected) in [
+        (json!("planning"), "from pinned"),
+        (serde_json::Value::Null, "from default"),
+    ] {
+        let response = app
+            .clone()
+            .oneshot(post(
+                "/process_text",
+                json!({"user_id": "user", "text": "hello", "thread_id": thread_id}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(value["text"], json!(expected));
+    }
+    pinned_mock.assert_calls(1);
+    default_mock.assert_calls(1);
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/threads/unpin",
+            json!({"user_id": "user", "thread_id": "planning"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/process_text",
+            json!({"user_id": "user", "text": "hello again", "thread_id": "planning"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    default_mock.assert_calls(2);
+}
+
+#[tokio::test]
+async fn daemon_memory_search_requires_auth_and_query() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-memory-search.db")
+        .to_string_lossy()
+        .to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let unauthorized = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/memory/search")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"user_id":"u","query":"dentist"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+    let empty = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/memory/search")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"user_id":"u","query":"   ","rerank":true}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+    let bytes = empty.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["error"], "query is required");
+}
+
+#[tokio::test]
+async fn daemon_memory_retention_reports_when_memory_is_disabled() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-memory-retention.db")
+        .to_string_lossy()
+        .to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/memory/retention")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(r#"{"user_id":"u"}"#))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["error"].as_str().unwrap().contains("retention"));
+}
+
+#[tokio::test]
+async fn daemon_activity_heatmap_counts_work_transitions() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-activity-heatmap.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder = reminder_store
+        .create_reminder("u", "Heatmap reminder", now + 60)
+        .await
+        .unwrap();
+    let state_store = InboxStateStore::new(&db_path).await.unwrap();
+    state_store
+        .record_transition_at("u", "todo:1", "new", "done", now - 400 * 24 * 60 * 60)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    for action in ["acknowledge", "start"] {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/inbox/transition")
+                    .header("authorization", "Bearer token")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({
+                            "user_id": "u",
+                            "origin_ref": format!("reminder:{}", reminder.id),
+                            "action": action
+                        })
+                        .to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/insights/heatmap?user_id=u&days=30")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["total"].as_u64(), Some(1));
+    assert_eq!(value["window_days"].as_u64(), Some(30));
+    assert_eq!(value["cells"].as_array().map(|rows| rows.len()), Some(7));
+}
+
+#[tokio::test]
+async fn daemon_aggregate_insights_hide_titles_and_small_windows() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-aggregate-insights.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    for index in 0..6 {
+        reminder_store
+            .create_reminder("u", &format!("Secret errand {index}"), now + 600)
+            .await
+            .unwrap();
+    }
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let fetch = |user: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(format!("/insights/aggregate?user_id={user}&days=7"))
+                        .header("authorization", "Bearer token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        }
+    };
+
+    let body = fetch("u").await;
+    assert!(!body.contains("Secret errand"));
+    assert!(!body.contains("reminder:"));
+    let value: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(value["window_days"].as_u64(), Some(7));
+    let rate = value["overall"]["completion_rate"].as_f64().unwrap();
+    assert!((0.0..=1.0).contains(&rate));
+    assert_eq!(body, fetch("u").await);
+
+    let empty: serde_json::Value = serde_json::from_str(&fetch("nobody").await).unwrap();
+    assert!(empty["overall"].is_null());
+}
+
+#[tokio::test]
+async fn daemon_prompt_queue_status_requires_token_and_reports_limits() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-prompt-queue.db")
+        .to_string_lossy()
+        .to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/process_text/queue")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/process_text/queue")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["max_concurrent"].as_u64().unwrap() >= 1);
+    assert!(value["queued"].is_array());
+}
+
+#[tokio::test]
+async fn daemon_scheduler_jobs_lists_registered_jobs_and_flags_late_ones() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-scheduler-jobs.db")
+        .to_string_lossy()
+        .to_string();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let jobs = JobStateStore::new(&db_path).await.unwrap();
+    jobs.register("retired", 60, now - 86_400).await.unwrap();
+    jobs.register("backup", 3_600, now - 7_200).await.unwrap();
+    // Registered two hours ago with a one-minute interval and never run.
+    jobs.register("wakeup", 60, now - 7_200).await.unwrap();
+    jobs.record_run("backup", now - 600, now + 3_000, 1_250, Some("disk full"))
+        .await
+        .unwrap();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/scheduler/jobs")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/scheduler/jobs")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let listed = value["jobs"].as_array().unwrap();
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0]["name"], "backup");
+    assert_eq!(listed[0]["interval_seconds"], 3_600);
+    assert_eq!(listed[0]["last_duration_ms"], 1_250);
+    assert_eq!(listed[0]["last_error"], "disk full");
+    assert_eq!(listed[0]["late"], false);
+    assert_eq!(listed[1]["name"], "wakeup");
+    assert!(listed[1]["last_run_at"].is_null());
+    assert_eq!(listed[1]["late"], true);
+}
+
+#[tokio::test]
+async fn daemon_metrics_are_exported_in_prometheus_format() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-metrics.db")
+        .to_string_lossy()
+        .to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    butterfly_bot::metrics::record_tool_invocation(
+        "daemon_metrics_probe",
+        butterfly_bot::metrics::Outcome::Error,
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/metrics")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .get("content-type")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .starts_with("text/plain; version=0.0.4"));
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(body.contains("# TYPE butterfly_wasm_execution_seconds histogram"));
+    assert!(body.contains(
+        "butterfly_tool_invocations_total{tool=\"daemon_metrics_probe\",outcome=\"error\"} 1"
+    ));
+}
+
+#[tokio::test]
+async fn daemon_plan_dependency_refs_are_relational_and_visible_in_inbox() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-plan-deps.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let plan_store = PlanStore::new(&db_path).await.unwrap();
+    let plan = plan_store
+        .create_plan(
+            "u",
+            "Dependency plan",
+            "Wire dependency graph",
+            Some(&json!([
+                {"id": "A1", "title": "Prepare schema", "owner": "human"},
+                {"id": "A2", "title": "Hook UI", "owner": "agent", "dependency_refs": ["A1"]}
+            ])),
+            Some("active"),
+        )
+        .await
+        .unwrap();
+
+    let dep_map = plan_store
+        .list_step_dependencies_for_plans(&[plan.id])
+        .await
+        .unwrap();
+    let dependent_step_ref = format!("plan_step:{}:{}", plan.id, 1);
+    let required_step_ref = format!("plan_step:{}:{}", plan.id, 0);
+    let relational_deps = dep_map
+        .get(&dependent_step_ref)
+        .cloned()
+        .unwrap_or_default();
+    assert!(
+        relational_deps
+            .iter()
+            .any(|dep| dep == &required_step_ref.to_ascii_lowercase()),
+        "expected relational dependency edge {} -> {}",
+        dependent_step_ref,
+        required_step_ref
+    );
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let items = value
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let dependent_step = items
+        .iter()
+        .find(|item| item.get("origin_ref").and_then(|v| v.as_str()) == Some(&dependent_step_ref))
+        .expect("expected dependent plan step in inbox");
+    let inbox_deps = dependent_step
+        .get("dependency_refs")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert!(
+        inbox_deps
+            .iter()
+            .any(|dep| dep.as_str() == Some(required_step_ref.as_str())),
+        "expected inbox dependency edge {} -> {}",
+        dependent_step_ref,
+        required_step_ref
+    );
+}
+
+#[tokio::test]
+async fn daemon_plan_dependency_title_aliases_resolve_in_inbox() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-plan-title-alias-deps.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let plan_store = PlanStore::new(&db_path).await.unwrap();
+    let plan = plan_store
+        .create_plan(
+            "u",
+            "Food delivery plan",
+            "Launch service",
+            Some(&json!([
+                {"title": "Conduct market research", "owner": "human"},
+                {"title": "Develop business plan", "owner": "human", "depends_on": "market research"}
+            ])),
+            Some("active"),
+        )
+        .await
+        .unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let items = value
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let dependent_ref = format!("plan_step:{}:{}", plan.id, 1);
+    let prerequisite_ref = format!("plan_step:{}:{}", plan.id, 0);
+    let dependent_item = items
+        .iter()
+        .find(|item| item.get("origin_ref").and_then(|v| v.as_str()) == Some(&dependent_ref))
+        .expect("expected dependent plan step in inbox");
+    let deps = dependent_item
+        .get("dependency_refs")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert!(
+        deps.iter()
+            .any(|value| value.as_str() == Some(prerequisite_ref.as_str())),
+        "expected '{}' to resolve to '{}'",
+        dependent_ref,
+        prerequisite_ref
+    );
+}
+
+#[tokio::test]
+async fn daemon_inbox_parses_todo_dependencies_from_notes_fallback() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-todo-notes-deps.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let prereq = todo_store
+        .create_item("u", "Prerequisite todo", Some("first"), None)
+        .await
+        .unwrap();
+    let dependent_notes = format!("Depends On: todo:{}", prereq.id);
+    let _dependent = todo_store
+        .create_item("u", "Dependent todo", Some(&dependent_notes), None)
+        .await
+        .unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let items = value
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let dependent_item = items
+        .iter()
+        .find(|item| {
+            item.get("source_type").and_then(|v| v.as_str()) == Some("todo")
+                && item
+                    .get("title")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .contains("Dependent todo")
+        })
+        .expect("expected dependent todo item");
+    let deps = dependent_item
+        .get("dependency_refs")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert!(
+        deps.iter()
+            .any(|value| value.as_str() == Some(format!("todo:{}", prereq.id).as_str())),
+        "expected notes dependency to be visible in inbox"
+    );
+}
+
+#[tokio::test]
+async fn daemon_clear_user_data_endpoint() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-clear-user-data.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder = reminder_store
+        .create_reminder("u", "Call dentist", now + 300)
+        .await
+        .unwrap();
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let _todo = todo_store
+        .create_item("u", "Pack bag", Some("for trip"), None)
+        .await
+        .unwrap();
+
+    let task_store = TaskStore::new(&db_path).await.unwrap();
+    let _task = task_store
+        .create_task("u", "Daily recap", "Write recap", now + 60, None)
+        .await
+        .unwrap();
+
+    let plan_store = PlanStore::new(&db_path).await.unwrap();
+    let _plan = plan_store
+        .create_plan("u", "Launch", "ship release", None, Some("active"))
+        .await
+        .unwrap();
+
+    let inbox_state_store = InboxStateStore::new(&db_path).await.unwrap();
+    inbox_state_store
+        .set_status("u", &format!("reminder:{}", reminder.id), "acknowledged")
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/clear_user_data")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"user_id":"u"}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let cleared = value.get("cleared").cloned().unwrap_or_default();
+    assert!(
+        cleared
+            .get("reminders")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            >= 1
+    );
+    assert!(cleared.get("todos").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
+    assert!(cleared.get("tasks").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
+    assert!(cleared.get("plans").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
+    assert!(
+        cleared
+            .get("inbox_state_overrides")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0)
+            >= 1
+    );
+
+    let reminders = ReminderStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_reminders("u", butterfly_bot::reminders::ReminderStatus::All, 50)
+        .await
+        .unwrap();
+    assert!(reminders.is_empty());
+
+    let todos = TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_items("u", butterfly_bot::todo::TodoStatus::All, 50)
+        .await
+        .unwrap();
+    assert!(todos.is_empty());
+}
+
+#[tokio::test]
+async fn daemon_reminder_delivery_events_endpoint() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-reminder-events.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let audit_log_path = temp.path().join("reminders_audit.log");
+
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "reminders": {
+                "sqlite_path": db_path,
+                "audit_log_path": audit_log_path.to_string_lossy().to_string()
+            }
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let line_a = json!({
+        "timestamp": 1,
+        "user_id": "u",
+        "reminder_id": 11,
+        "status": "delivery_attempted",
+        "payload": {"title": "A"}
+    })
+    .to_string();
+    let line_b = json!({
+        "timestamp": 2,
+        "user_id": "u",
+        "reminder_id": 11,
+        "status": "delivered",
+        "payload": {"title": "A"}
+    })
+    .to_string();
+    std::fs::write(&audit_log_path, format!("{line_a}\n{line_b}\n")).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/reminders/delivery_events?user_id=u&limit=10")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let events = value
+        .get("events")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[1].get("status").and_then(|v| v.as_str()),
+        Some("delivered")
+    );
+}
+
+#[tokio::test]
+async fn daemon_dependency_check_reports_broken_refs_and_cycles() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-dependency-check.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let plan = PlanStore::new(&db_path)
+        .await
+        .unwrap()
+        .create_plan(
+            "u",
+            "Launch",
+            "Ship it",
+            Some(&json!([
+                {"title": "Write copy", "depends_on": [1]},
+                {"title": "Review copy", "depends_on": [0]}
+            ])),
+            Some("active"),
+        )
+        .await
+        .unwrap();
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let todo = todo_store
+        .create_item(
+            "u",
+            "Publish",
+            None,
+            Some(&[format!("Plan_Step:{}:1", plan.id), "todo:999".to_string()]),
+        )
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/dependencies/check?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["checked"], json!(4));
+    assert_eq!(
+        value["broken"],
+        json!([{"from": format!("todo:{}", todo.id), "to": "todo:999", "reason": "missing"}])
+    );
+    assert_eq!(
+        value["cycles"],
+        json!([[
+            format!("plan_step:{}:0", plan.id),
+            format!("plan_step:{}:1", plan.id)
+        ]])
+    );
+}
+
+#[tokio::test]
+async fn daemon_unblocks_items_once_their_dependencies_are_done() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-dependency-unblock.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let first = todo_store
+        .create_item("u", "Order parts", None, None)
+        .await
+        .unwrap();
+    let second = todo_store
+        .create_item("u", "Paint fence", None, None)
+        .await
+        .unwrap();
+    let dependent = todo_store
+        .create_item(
+            "u",
+            "Assemble",
+            None,
+            Some(&[format!("todo:{}", first.id), format!("todo:{}", second.id)]),
+        )
+        .await
+        .unwrap();
+    let dependent_ref = format!("todo:{}", dependent.id);
+    let states = InboxStateStore::new(&db_path).await.unwrap();
+    states
+        .set_status("u", &dependent_ref, "blocked")
+        .await
+        .unwrap();
+
+    let (ui_event_tx, mut ui_events) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(ReminderStore::new(&db_path).await.unwrap()),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let done = |origin_ref: String| {
+        Request::builder()
+            .method("POST")
+            .uri("/inbox/transition")
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"user_id": "u", "origin_ref": origin_ref, "action": "done"}).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(done(format!("todo:{}", first.id)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let statuses = states.list_statuses("u", 10).await.unwrap();
+    assert_eq!(
+        statuses.get(&dependent_ref).map(String::as_str),
+        Some("blocked")
+    );
+
+    let response = app
+        .oneshot(done(format!("todo:{}", second.id)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let statuses = states.list_statuses("u", 10).await.unwrap();
+    assert_eq!(
+        statuses.get(&dependent_ref).map(String::as_str),
+        Some("new")
+    );
+
+    let mut unblock_events = Vec::new();
+    while let Ok(event) = ui_events.try_recv() {
+        if event.payload["action"] == "unblock" {
+            unblock_events.push(event);
+        }
+    }
+    assert_eq!(unblock_events.len(), 1);
+    assert_eq!(
+        unblock_events[0].payload["origin_ref"],
+        json!(dependent_ref)
+    );
+    assert_eq!(unblock_events[0].payload["actor"], json!("system"));
+}
+
+#[tokio::test]
+async fn daemon_reminder_dead_letters_can_be_listed_redriven_and_discarded() {
+    use butterfly_bot::reminders::DeliveryChannel;
+
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-dead-letters.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let reminder = reminder_store
+        .create_reminder("u", "Call the dentist", 0)
+        .await
+        .unwrap();
+    reminder_store
+        .enqueue_deliveries(
+            "u",
+            reminder.id,
+            &reminder.title,
+            &[DeliveryChannel::Chat, DeliveryChannel::Push],
+        )
+        .await
+        .unwrap();
+    for delivery in reminder_store.due_deliveries(10).await.unwrap() {
+        reminder_store
+            .record_delivery_failure(&delivery, "relay down", 1)
+            .await
+            .unwrap();
+    }
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let store = state.reminder_store.clone();
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/reminders/dead_letters?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let dead_letters = value["dead_letters"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(dead_letters.len(), 2);
+    assert_eq!(dead_letters[0]["last_error"], "relay down");
+    let first = dead_letters[0]["id"].as_i64().unwrap();
+    let second = dead_letters[1]["id"].as_i64().unwrap();
+
+    let post = |path: &str, body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri(path)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/reminders/dead_letters/redrive",
+            json!({"user_id": "u", "id": first}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let queued = store.due_deliveries(10).await.unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].attempts, 0);
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/reminders/dead_letters/discard",
+            json!({"user_id": "u", "id": second}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(store.dead_letters("u", 10).await.unwrap().is_empty());
+
+    let response = app
+        .oneshot(post(
+            "/reminders/dead_letters/discard",
+            json!({"user_id": "u", "id": second}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn daemon_estimate_accuracy_compares_actual_durations_with_estimates() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-estimates.db")
+        .to_string_lossy()
+        .to_string();
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: None,
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let clock = butterfly_bot::clock::ManualClock::new(1_767_268_800);
+    let todo_store = TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .with_clock(clock.clone());
+    let mut todos = Vec::new();
+    for title in ["Tidy the garage", "Tidy the shed", "Tidy the attic"] {
+        todos.push(
+            todo_store
+                .create_item("u", title, None, None)
+                .await
+                .unwrap(),
+        );
+    }
+    let estimate = todos[0].estimate_likely_minutes.unwrap();
+    clock.advance(i64::from(estimate) * 60 / 2);
+    for todo in &todos {
+        todo_store.set_completed(todo.id, true).await.unwrap();
+    }
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/todos/estimate_accuracy?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let categories = value["categories"].as_array().cloned().unwrap_or_default();
+    assert_eq!(categories.len(), 2);
+    assert_eq!(categories[0]["category"], "all");
+    assert_eq!(categories[0]["samples"], 3);
+    assert_eq!(categories[1]["category"], "general");
+    let ratio = categories[0]["median_ratio"].as_f64().unwrap();
+    assert!((ratio - 0.5).abs() < 0.05, "median ratio {ratio}");
+    let multiplier = categories[0]["multiplier"].as_f64().unwrap();
+    assert!((multiplier - 0.5).abs() < 0.05, "multiplier {multiplier}");
+}
+
+#[tokio::test]
+async fn daemon_dashboard_serves_read_only_summary_when_enabled() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-dashboard.db")
+        .to_string_lossy()
+        .to_string();
+    let mut cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: None,
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    reminder_store
+        .create_reminder("u", "Call the dentist", now + 3600)
+        .await
+        .unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+    let get = |uri: &str, token: Option<&str>| {
+        let mut request = Request::builder().method("GET").uri(uri);
+        if let Some(token) = token {
+            request = request.header("authorization", format!("Bearer {token}"));
+        }
+        request.body(Body::empty()).unwrap()
+    };
+
+    let response = app.clone().oneshot(get("/dashboard", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    cfg.tools = Some(json!({"settings": {"dashboard": {"enabled": true}}}));
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let response = app.clone().oneshot(get("/dashboard", None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+
+    let response = app
+        .clone()
+        .oneshot(get("/dashboard/summary?user_id=u", None))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .oneshot(get("/dashboard/summary?user_id=u", Some("token")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["refresh_seconds"], 30);
+    assert_eq!(value["upcoming_reminders"][0]["title"], "Call the dentist");
+    assert!(value["inbox"]["open"].as_u64().unwrap() >= 1);
+    assert!(value["recent_events"].is_array());
+}
+
+#[tokio::test]
+async fn daemon_github_webhook_verifies_signature_and_fills_human_lane() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-github-webhook.db")
+        .to_string_lossy()
+        .to_string();
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "settings": {"github_webhook": {
+                "users": {"octocat": "u"},
+                "secret_name": "daemon_test_github_secret"
+            }}
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+    butterfly_bot::vault::set_secret("daemon_test_github_secret", "hook-secret").unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let deliver = |event: &'static str, body: serde_json::Value, secret: &'static str| {
+        let app = app.clone();
+        async move {
+            let body = body.to_string();
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+            mac.update(body.as_bytes());
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/webhooks/github")
+                    .header("x-github-event", event)
+                    .header(
+                        "x-hub-signature-256",
+                        format!("sha256={}", hex::encode(mac.finalize().into_bytes())),
+                    )
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let review_requested = json!({
+        "action": "review_requested",
+        "repository": {"full_name": "acme/app"},
+        "requested_reviewer": {"login": "octocat"},
+        "pull_request": {"number": 123, "title": "Add cache", "user": {"login": "hubot"},
+                         "html_url": "https://github.com/acme/app/pull/123"}
+    });
+
+    let forged = deliver("pull_request", review_requested.clone(), "wrong").await;
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+    let accepted = deliver("pull_request", review_requested.clone(), "hook-secret").await;
+    assert_eq!(accepted.status(), StatusCode::OK);
+    let redelivered = deliver("pull_request", review_requested, "hook-secret").await;
+    assert_eq!(redelivered.status(), StatusCode::OK);
+
+    let inbox = |app: axum::Router| async move {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/inbox?user_id=u&include_done=true")
+                    .header("authorization", "Bearer token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        value["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|item| item["origin_ref"] == "github:pr:acme/app:123")
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let items = inbox(app.clone()).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["owner"], "human");
+    assert_eq!(items[0]["status"], "new");
+    assert_eq!(items[0]["title"], "Review requested: Add cache");
+
+    let reviewed = deliver(
+        "pull_request_review",
+        json!({
+            "action": "submitted",
+            "repository": {"full_name": "acme/app"},
+            "review": {"user": {"login": "octocat"}},
+            "pull_request": {"number": 123}
+        }),
+        "hook-secret",
+    )
+    .await;
+    assert_eq!(reviewed.status(), StatusCode::OK);
+    assert_eq!(inbox(app.clone()).await[0]["status"], "done");
+}
+
+#[tokio::test]
+async fn daemon_zapier_webhook_checks_token_and_opens_inbox_items() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-zapier-webhook.db")
+        .to_string_lossy()
+        .to_string();
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "zapier": {"zaps": [{
+                "name": "tickets",
+                "inbound": {"user": "u", "secret_name": "daemon_test_zapier_secret"}
+            }]}
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+    butterfly_bot::vault::set_secret("daemon_test_zapier_secret", "zap-token").unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let deliver = |hook: &'static str, body: serde_json::Value, token: &'static str| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/webhooks/zapier/{hook}"))
+                    .header("authorization", format!("Bearer {token}"))
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+    let ticket = json!({"id": 7, "title": "Refund request", "priority": "high"});
+
+    let unknown = deliver("leads", ticket.clone(), "zap-token").await;
+    assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+    let forged = deliver("tickets", ticket.clone(), "wrong").await;
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+    let untitled = deliver("tickets", json!({"id": 8}), "zap-token").await;
+    assert_eq!(untitled.status(), StatusCode::BAD_REQUEST);
+    let accepted = deliver("tickets", ticket, "zap-token").await;
+    assert_eq!(accepted.status(), StatusCode::OK);
+
+    let inbox = |app: axum::Router| async move {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/inbox?user_id=u&include_done=true")
+                    .header("authorization", "Bearer token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        value["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|item| item["origin_ref"] == "zapier:tickets:7")
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let items = inbox(app.clone()).await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["status"], "new");
+    assert_eq!(items[0]["title"], "Refund request");
+
+    let closed = deliver(
+        "tickets",
+        json!({"id": "7", "status": "closed"}),
+        "zap-token",
+    )
+    .await;
+    assert_eq!(closed.status(), StatusCode::OK);
+    assert_eq!(inbox(app.clone()).await[0]["status"], "done");
+}
+
+#[tokio::test]
+async fn daemon_calendar_sync_imports_read_only_events_and_serves_reminder_feed() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let stamp = |ts: i64| {
+        chrono::DateTime::from_timestamp(ts, 0)
+            .unwrap()
+            .format("%Y%m%dT%H%M%SZ")
+            .to_string()
+    };
+    let ics = format!(
+        "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:offsite\r\nDTSTART:{}\r\nDTEND:{}\r\n\
+SUMMARY:Team offsite\r\nLOCATION:Berlin\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        stamp(now + 86_400),
+        stamp(now + 86_400 + 2 * 3600)
+    );
+    server
+        .mock_async(|when, then| {
+            when.method("GET").path("/work.ics");
+            then.status(200).body(ics);
+        })
+        .await;
+
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-calendar.db")
+        .to_string_lossy()
+        .to_string();
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "settings": {"calendar": {
+                "sources": [{"name": "work", "user_id": "u", "ics_url": server.url("/work.ics")}],
+                "feed": true
+            }}
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    reminder_store
+        .create_reminder("u", "Call the dentist", now + 3600)
+        .await
+        .unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let post = |uri: &'static str, body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post("/calendar/sync", json!({"user_id": "u"})))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["reports"][0]["events"], 1);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let event = value["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["origin_ref"] == "calendar:work:offsite")
+        .cloned()
+        .unwrap();
+    assert_eq!(event["source_type"], "calendar");
+    assert_eq!(event["requires_human_action"], false);
+    assert_eq!(event["estimate_likely_minutes"], 120);
+    assert_eq!(event["details"], "Berlin");
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/inbox/transition",
+            json!({"user_id": "u", "origin_ref": "calendar:work:offsite", "action": "done"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .clone()
+        .oneshot(post("/calendar/feed", json!({"user_id": "u"})))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let path = value["path"].as_str().unwrap().to_string();
+
+    let feed = |uri: String| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    };
+    let response = app.clone().oneshot(feed(path)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/calendar; charset=utf-8"
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let body = String::from_utf8(bytes.to_vec()).unwrap();
+    assert!(body.contains("SUMMARY:Call the dentist"));
+
+    let response = app
+        .oneshot(feed("/calendar/feed/u/not-the-token.ics".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+/// Serves `messages` (UID, flags, raw message) to every connection, the
+/// way a local IMAP bridge would.
+async fn fake_imap_server(messages: Vec<(u32, &'static str, String)>) -> u16 {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let messages = messages.clone();
+            tokio::spawn(async move {
+                let mut socket = BufReader::new(socket);
+                socket.write_all(b"* OK fake IMAP ready\r\n").await.unwrap();
+                let mut line = String::new();
+                while socket.read_line(&mut line).await.unwrap_or(0) > 0 {
+                    let (tag, command) = line.trim_end().split_once(' ').unwrap();
+                    let mut reply = Vec::new();
+                    if command.starts_with("UID SEARCH") {
+                        let uids: Vec<String> =
+                            messages.iter().map(|(uid, _, _)| uid.to_string()).collect();
+                        reply.extend(format!("* SEARCH {}\r\n", uids.join(" ")).into_bytes());
+                    } else if let Some(rest) = command.strip_prefix("UID FETCH ") {
+                        let (set, items) = rest.split_once(' ').unwrap();
+                        let wanted: Vec<u32> =
+                            set.split(',').map(|uid| uid.parse().unwrap()).collect();
+                        for (uid, flags, raw) in &messages {
+                            if !wanted.contains(uid) {
+                                continue;
+                            }
+                            let (data, item) = if items.contains("BODY.PEEK[HEADER]") {
+                                (
+                                    raw.split("\r\n\r\n").next().unwrap().to_string() + "\r\n\r\n",
+                                    "BODY[HEADER]",
+                                )
+                            } else {
+                                (raw.clone(), "BODY[]")
+                            };
+                            reply.extend(
+                                format!(
+                                    "* {uid} FETCH (UID {uid} FLAGS ({flags}) {item} {{{}}}\r\n",
+                                    data.len()
+                                )
+                                .into_bytes(),
+                            );
+                            reply.extend(data.into_bytes());
+                            reply.extend(b")\r\n");
+                        }
+                    } else if command == "LOGOUT" {
+                        reply.extend(b"* BYE\r\n");
+                    }
+                    reply.extend(format!("{tag} OK done\r\n").into_bytes());
+                    socket.write_all(&reply).await.unwrap();
+                    line.clear();
+                }
+            });
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn daemon_email_poll_turns_flagged_mail_into_todos_once() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let port = fake_imap_server(vec![
+        (
+            1,
+            "\\Seen \\Flagged",
+            "Message-ID: <plumber@mail.example>\r\nFrom: Alice <alice@example.com>\r\n\
+Subject: Pay the plumber\r\n\r\nInvoice attached, due Friday.\r\n"
+                .to_string(),
+        ),
+        (
+            2,
+            "\\Seen",
+            "Message-ID: <lunch@mail.example>\r\nSubject: Lunch?\r\n\r\nNoon?\r\n".to_string(),
+        ),
+    ])
+    .await;
+
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-email.db")
+        .to_string_lossy()
+        .to_string();
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "settings": {"email": {"accounts": [{
+                "name": "bridge", "user_id": "u", "host": "127.0.0.1", "port": port,
+                "tls": false, "username": "bot@example.com"
+            }]}}
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+    let poll = || {
+        Request::builder()
+            .method("POST")
+            .uri("/email/poll")
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({"user_id": "u"}).to_string()))
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(poll()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["reports"][0]["error"].is_null());
+    assert_eq!(value["reports"][0]["todo_ids"].as_array().unwrap().len(), 1);
+
+    let response = app.oneshot(poll()).await.unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["reports"][0]["todo_ids"], json!([]));
+
+    let todos = TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_items("u", butterfly_bot::todo::TodoStatus::All, 10)
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].title, "Pay the plumber");
+    assert_eq!(
+        todos[0].notes.as_deref(),
+        Some("From: Alice <alice@example.com>\n\nInvoice attached, due Friday.")
+    );
+    assert_eq!(
+        todos[0].origin_ref.as_deref(),
+        Some("email:plumber@mail.example")
+    );
+}
+
+#[tokio::test]
+async fn daemon_inbox_transition_persists_status() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-inbox-transition.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder = reminder_store
+        .create_reminder("u", "Stateful transition reminder", now + 60)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/inbox/transition")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({
+                        "user_id": "u",
+                        "origin_ref": format!("reminder:{}", reminder.id),
+                        "action": "acknowledge"
+                    })
+                    .to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let items = value
+        .get("items")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let expected_origin = format!("reminder:{}", reminder.id);
+    let transitioned = items
+        .iter()
+        .find(|item| {
+            item.get("origin_ref").and_then(|v| v.as_str()) == Some(expected_origin.as_str())
+        })
+        .cloned();
+    assert!(transitioned.is_some());
+    let transitioned = transitioned.unwrap();
+    assert_eq!(
+        transitioned.get("status").and_then(|v| v.as_str()),
+        Some("acknowledged")
+    );
+}
+
+#[tokio::test]
+async fn daemon_inbox_comments_thread_and_reach_the_chat_context() {
+    let server = MockServer::start_async().await;
+    let chat_mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/chat/completions")
+                .body_includes("waiting on vendor reply");
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-test",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "gpt-4o-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "noted"},
+                    "finish_reason": "stop"
+                }]
+            }));
+        })
+        .await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-inbox-comments.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder = reminder_store
+        .create_reminder("u", "Renew the support contract", now + 3600)
+        .await
+        .unwrap();
+    let origin_ref = format!("reminder:{}", reminder.id);
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+    let post = |uri: &str, body: serde_json::Value| {
+        Request::builder()
+            .method("POST")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+    let get = |uri: String| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/inbox/comments",
+            json!({"user_id": "u", "origin_ref": origin_ref, "body": "waiting on vendor reply"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let comment: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(comment["author"], json!("human"));
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/inbox/comments",
+            json!({"user_id": "u", "origin_ref": origin_ref, "body": "  "}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    InboxStateStore::new(&db_path)
+        .await
+        .unwrap()
+        .add_comment("u", &origin_ref, "agent", "Chased them by email")
+        .await
+        .unwrap();
+
+    let response = app
+        .clone()
+        .oneshot(get(format!(
+            "/inbox/comments?user_id=u&origin_ref={origin_ref}"
+        )))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let authors: Vec<&str> = value["comments"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|comment| comment["author"].as_str())
+        .collect();
+    assert_eq!(authors, vec!["human", "agent"]);
+
+    let response = app
+        .clone()
+        .oneshot(get("/inbox?user_id=u&limit=100".to_string()))
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let item = value["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["origin_ref"] == json!(origin_ref))
+        .cloned()
+        .unwrap();
+    assert_eq!(item["comments"][1]["body"], json!("Chased them by email"));
+
+    let response = app
+        .clone()
+        .oneshot(post(
+            "/process_text",
+            json!({"user_id": "u", "text": "Any news?", "reply_to": origin_ref}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    chat_mock.assert_calls(1);
+}
+
+#[tokio::test]
+async fn daemon_attachments_upload_download_and_show_on_items() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-attachments.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let reminder = reminder_store
+        .create_reminder("u", "File the expense report", now + 3600)
+        .await
+        .unwrap();
+    let origin_ref = format!("reminder:{}", reminder.id);
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+    let upload = |name: &str, bytes: &'static [u8]| {
+        Request::builder()
+            .method("POST")
+            .uri(format!(
+                "/attachments?user_id=u&origin_ref={origin_ref}&name={name}"
+            ))
+            .header("authorization", "Bearer token")
+            .header("content-type", "image/png")
+            .body(Body::from(bytes))
+            .unwrap()
+    };
+    let get = |uri: String| {
+        Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app
+        .clone()
+        .oneshot(upload("receipt.png", b"not really a png"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let attachment: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(attachment["name"], json!("receipt.png"));
+    assert_eq!(attachment["size_bytes"], json!(16));
+    let id = attachment["id"].as_i64().unwrap();
+
+    let response = app.clone().oneshot(upload("empty.png", b"")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Only the item's owner can attach to it.
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(format!(
+                    "/attachments?user_id=other&origin_ref={origin_ref}&name=x.png"
+                ))
+                .header("authorization", "Bearer token")
+                .body(Body::from(&b"bytes"[..]))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(get(format!("/attachments/{id}?user_id=u")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "image/png");
+    assert_eq!(
+        response.headers()["content-disposition"],
+        "attachment; filename=\"receipt.png\""
+    );
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(&bytes[..], b"not really a png");
+
+    // Another user's id is not found.
+    let response = app
+        .clone()
+        .oneshot(get(format!("/attachments/{id}?user_id=other")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app
+        .clone()
+        .oneshot(get("/inbox?user_id=u&limit=100".to_string()))
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let item = value["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["origin_ref"] == json!(origin_ref))
+        .cloned()
+        .unwrap();
+    assert_eq!(item["attachments"][0]["name"], json!("receipt.png"));
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/attachments/delete")
+                .header("authorization", "Bearer token")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({"user_id": "u", "id": id}).to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["deleted"], json!(true));
+
+    let response = app
+        .clone()
+        .oneshot(get(format!(
+            "/attachments?user_id=u&origin_ref={origin_ref}"
+        )))
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["attachments"].as_array().unwrap().is_empty());
+    let blobs = temp.path().join("attachments");
+    let leftover = walk_files(&blobs);
+    assert!(leftover.is_empty(), "blobs left behind: {leftover:?}");
+}
+
+fn walk_files(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                walk_files(&path)
+            } else {
+                vec![path]
+            }
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn daemon_slash_commands_skip_the_model() {
+    let server = MockServer::start_async().await;
+    let chat_mock = server
+        .mock_async(|when, then| {
+            when.method(POST).path("/chat/completions");
+            then.status(500);
+        })
+        .await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-slash.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
+    };
+    let app = build_router(state);
+    let say = |text: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/process_text")
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"user_id": "u", "text": text}).to_string(),
+            ))
+            .unwrap()
+    };
+    let reply = |response: axum::response::Response| async move {
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        value["text"].as_str().unwrap().to_string()
+    };
+
+    let text = reply(app.clone().oneshot(say("/todo buy milk")).await.unwrap()).await;
+    assert!(text.starts_with("Added todo"), "{text}");
+    let todos = TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_items("u", butterfly_bot::todo::TodoStatus::All, 10)
+        .await
+        .unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].title, "buy milk");
+
+    let done = format!("/done {}", todos[0].id);
+    let text = reply(app.clone().oneshot(say(&done)).await.unwrap()).await;
+    assert_eq!(text, "Marked done: buy milk");
+
+    let text = reply(
+        app.clone()
+            .oneshot(say("/remind tomorrow 9am standup"))
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(text.starts_with("Reminder"), "{text}");
+    let reminders = ReminderStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_reminders("u", butterfly_bot::reminders::ReminderStatus::Open, 10)
+        .await
+        .unwrap();
+    assert_eq!(reminders[0].title, "standup");
+
+    let text = reply(app.clone().oneshot(say("/nope")).await.unwrap()).await;
+    assert!(text.starts_with("Unknown command /nope"), "{text}");
+
+    // Commands go through the same role check as the tools.
+    RoleStore::new(&db_path)
+        .await
+        .unwrap()
+        .set_role("viewer", Role::Readonly, None)
+        .await
+        .unwrap();
+    let readonly = Request::builder()
+        .method("POST")
+        .uri("/process_text")
+        .header("authorization", "Bearer token")
+        .header("content-type", "application/json")
+        .body(Body::from(
+            json!({"user_id": "viewer", "text": "/todo sneak in"}).to_string(),
+        ))
+        .unwrap();
+    let text = reply(app.clone().oneshot(readonly).await.unwrap()).await;
+    assert!(text.contains("not permitted for role 'readonly'"), "{text}");
+    let todos = TodoStore::new(&db_path)
+        .await
+        .unwrap()
+        .list_items("viewer", butterfly_bot::todo::TodoStatus::All, 10)
+        .await
+        .unwrap();
+    assert!(todos.is_empty());
+
+    chat_mock.assert_calls(0);
+}
+
+#[tokio::test]
+async fn daemon_inbox_bulk_applies_each_item_and_reports_skips() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-inbox-bulk.db")
+        .to_string_lossy()
+        .to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let first = reminder_store
+        .create_reminder("u", "Bulk reminder one", now + 60)
+        .await
+        .unwrap();
+    let second = reminder_store
+        .create_reminder("u", "Bulk reminder two", now + 120)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let bulk = |action: &str, origin_refs: Vec<String>| {
+        Request::builder()
+            .method("POST")
+            .uri("/inbox/bulk")
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                json!({"user_id": "u", "origin_refs": origin_refs, "action": action}).to_string(),
+            ))
+            .unwrap()
+    };
+    let first_ref = format!("reminder:{}", first.id);
+    let second_ref = format!("reminder:{}", second.id);
+
+    let response = app
+        .clone()
+        .oneshot(bulk("block", vec![first_ref.clone()]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .clone()
+        .oneshot(bulk(
+            "done",
+            vec![
+                first_ref.clone(),
+                second_ref.clone(),
+                "reminder:999999".to_string(),
+                first_ref.clone(),
+            ],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["applied"].as_array().unwrap().len(), 2);
+    assert_eq!(value["applied"][1]["next_status"], "done");
+    assert_eq!(value["skipped"].as_array().unwrap().len(), 1);
+    assert_eq!(value["skipped"][0]["origin_ref"], "reminder:999999");
+
+    let response = app
+        .oneshot(bulk("snooze", vec![first_ref, second_ref]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(value["applied"], json!([]));
+    assert_eq!(value["skipped"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn daemon_inbox_applies_configured_priority_rules() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-inbox-rules.db")
+        .to_string_lossy()
+        .to_string();
+    let mut config = Config::convention_defaults(&db_path);
+    config.tools = Some(json!({"settings": {"inbox_rules": [
+        {"name": "Bills", "keywords": ["invoice"], "priority": "urgent"},
+        {"name": "Bot reminders", "source_types": ["reminder"], "keywords": ["backup"],
+         "owner": "agent", "requires_human_action": false}
+    ]}}));
+    config_store::save_config(&db_path, &config).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    for title in ["Pay the invoice", "Check the backup", "Water the plants"] {
+        reminder_store
+            .create_reminder("u", title, now + 3600)
+            .await
+            .unwrap();
+    }
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let response = build_router(state)
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let items = value["items"].as_array().unwrap();
+    let find = |title: &str| {
+        items
+            .iter()
+            .find(|item| item["title"] == title)
+            .unwrap()
+            .clone()
+    };
+
+    assert_eq!(items[0]["title"], "Pay the invoice");
+    assert_eq!(find("Pay the invoice")["priority"], "urgent");
+    let backup = find("Check the backup");
+    assert_eq!(backup["owner"], "agent");
+    assert_eq!(backup["requires_human_action"], false);
+    let plants = find("Water the plants");
+    assert_eq!(plants["priority"], "normal");
+    assert_eq!(plants["owner"], "human");
+}
+
+#[tokio::test]
+async fn daemon_audit_events_endpoint() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-audit-events.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let audit_log_path = temp.path().join("ui_events.log");
+
+    let cfg = Config {
+        provider: None,
+        openai: Some(OpenAiConfig {
+            api_key: Some("key".to_string()),
+            model: Some("gpt-4o-mini".to_string()),
+            base_url: Some(server.base_url()),
+        }),
+        llm: None,
+        heartbeat_source: MarkdownSource::default_heartbeat(),
+        prompt_source: MarkdownSource::default_prompt(),
+        memory: None,
+        tools: Some(json!({
+            "settings": {
+                "ui_event_log_path": audit_log_path.to_string_lossy().to_string()
+            }
+        })),
+        brains: None,
+    };
+    config_store::save_config(&db_path, &cfg).unwrap();
+
+    let line_a = json!({
+        "timestamp": 10,
+        "event_type": "inbox_transition",
+        "user_id": "u",
+        "tool": "inbox",
+        "status": "acknowledged"
+    })
+    .to_string();
+    let line_b = json!({
+        "timestamp": 11,
+        "event_type": "tasks",
+        "user_id": "u",
+        "tool": "tasks",
+        "status": "ok"
+    })
+    .to_string();
+    std::fs::write(&audit_log_path, format!("{line_a}\n{line_b}\n")).unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/audit/events?user_id=u&limit=10")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let events = value
+        .get("events")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0].get("event_type").and_then(|v| v.as_str()),
+        Some("inbox_transition")
+    );
+}
+
+#[tokio::test]
+async fn daemon_audit_events_filter_and_paginate_server_side() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-audit-store.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let audit = AuditStore::new(&db_path).await.unwrap();
+    for (ts, event_type, status, origin) in [
+        (10, "inbox_transition", "done", "todo:1"),
+        (20, "tool", "error", "todo:2"),
+        (30, "inbox_transition", "in_progress", "todo:2"),
+        (40, "inbox_transition", "done", "todo:3"),
+    ] {
+        audit
+            .record(&UiEvent {
+                event_type: event_type.to_string(),
+                user_id: "u".to_string(),
+                tool: "inbox".to_string(),
+                status: status.to_string(),
+                payload: json!({"origin_ref": origin}),
+                timestamp: ts,
+            })
+            .await
+            .unwrap();
+    }
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let get = |uri: String| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("GET")
+                        .uri(uri)
+                        .header("authorization", "Bearer token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            (status, value)
+        }
+    };
+    let stamps = |value: &serde_json::Value| {
+        value["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["timestamp"].as_i64().unwrap())
+            .collect::<Vec<_>>()
+    };
+
+    let (status, first) =
+        get("/audit/events?user_id=u&event_type=inbox_transition&limit=2".into()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(stamps(&first), vec![30, 40]);
+    let cursor = first["next_before_id"].as_i64().unwrap();
+
+    let (_, older) = get(format!(
+        "/audit/events?user_id=u&event_type=inbox_transition&limit=2&before_id={cursor}"
+    ))
+    .await;
+    assert_eq!(stamps(&older), vec![10]);
+    assert!(older["next_before_id"].is_null());
+
+    let (_, by_origin) = get("/audit/events?user_id=u&origin_ref=todo:2&since=25".into()).await;
+    assert_eq!(stamps(&by_origin), vec![30]);
+
+    let (_, errors) = get("/audit/events?user_id=u&severity=error".into()).await;
+    assert_eq!(stamps(&errors), vec![20]);
+    assert_eq!(errors["events"][0]["actor"], "agent");
+
+    let (status, _) = get("/audit/events?user_id=u&severity=loud".into()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn daemon_trash_lists_cleared_batches_and_restores_them() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-trash.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let item = todo_store
+        .create_item("u", "file taxes", None, None)
+        .await
+        .unwrap();
+    let plan_store = PlanStore::new(&db_path).await.unwrap();
+    let plan = plan_store
+        .create_plan("u", "Move", "Move flats", None, None)
+        .await
+        .unwrap();
+    let todo_receipt = todo_store
+        .clear_items_to_trash("u", butterfly_bot::todo::TodoStatus::All)
+        .await
+        .unwrap();
+    plan_store.clear_plans("u").await.unwrap();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/trash?user_id=u")
                 .header("authorization", "Bearer token")
-                .header("content-type", "application/json")
-                .body(Body::from(json!({"user_id":"u"}).to_string()))
+                .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let listed: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let mut stores = listed["batches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|batch| batch["store"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    stores.sort();
+    assert_eq!(stores, vec!["plans", "todo"]);
+    assert_eq!(listed["retention_days"], 30);
+
+    let restore = |store: &str, batch_id: &str| {
+        let app = app.clone();
+        let body = json!({"user_id": "u", "store": store, "batch_id": batch_id}).to_string();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/trash/restore")
+                    .header("authorization", "Bearer token")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
 
+    let response = restore("ledger", &todo_receipt.batch_id).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = restore("todo", &todo_receipt.batch_id).await;
+    assert_eq!(response.status(), StatusCode::OK);
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let cleared = value.get("cleared").cloned().unwrap_or_default();
-    assert!(
-        cleared
-            .get("reminders")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0)
-            >= 1
-    );
-    assert!(cleared.get("todos").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
-    assert!(cleared.get("tasks").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
-    assert!(cleared.get("plans").and_then(|v| v.as_u64()).unwrap_or(0) >= 1);
-    assert!(
-        cleared
-            .get("inbox_state_overrides")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0)
-            >= 1
-    );
+    let restored: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(restored["restored"], 1);
 
-    let reminders = ReminderStore::new(&db_path)
+    let items = todo_store
+        .list_items("u", butterfly_bot::todo::TodoStatus::All, 10)
         .await
-        .unwrap()
-        .list_reminders("u", butterfly_bot::reminders::ReminderStatus::All, 50)
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].id, item.id);
+    assert!(plan_store.get_plan(plan.id).await.is_err());
+}
+
+#[tokio::test]
+async fn daemon_parked_approvals_show_in_inbox_and_are_decided_once() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-approvals.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let approvals = ApprovalStore::new(&db_path).await.unwrap();
+    let parked = approvals
+        .park(
+            "u",
+            "solana",
+            "solana.transfer",
+            &json!({"user_id": "u", "to": "wallet-b", "lamports": 5000}),
+        )
         .await
         .unwrap();
-    assert!(reminders.is_empty());
 
-    let todos = TodoStore::new(&db_path)
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
         .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let inbox: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let item = inbox["items"]
+        .as_array()
         .unwrap()
-        .list_items("u", butterfly_bot::todo::TodoStatus::All, 50)
+        .iter()
+        .find(|item| item["source_type"] == "approval")
+        .cloned()
+        .expect("approval item in inbox");
+    assert_eq!(item["origin_ref"], format!("approval:{}", parked.id));
+    assert_eq!(item["requires_human_action"], true);
+    assert!(item["details"].as_str().unwrap().contains("to=wallet-b"));
+
+    let decide = |id: i32, decision: &str| {
+        let app = app.clone();
+        let body = json!({"user_id": "u", "id": id, "decision": decision}).to_string();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/approvals/decide")
+                    .header("authorization", "Bearer token")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    assert_eq!(
+        decide(parked.id, "maybe").await.status(),
+        StatusCode::BAD_REQUEST
+    );
+    assert_eq!(
+        decide(parked.id + 100, "reject").await.status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let response = decide(parked.id, "reject").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let decided: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(decided["status"], "rejected");
+    assert_eq!(decided["approval"]["status"], "rejected");
+
+    assert_eq!(
+        decide(parked.id, "approve").await.status(),
+        StatusCode::CONFLICT
+    );
+    assert!(approvals.list_pending("u", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn daemon_consent_requests_are_decided_from_the_inbox_and_logged() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-consent.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let consents = ConsentStore::new(&db_path).await.unwrap();
+    let asked = consents
+        .request("u", "fetch", "http.request", "api.example.com")
         .await
         .unwrap();
-    assert!(todos.is_empty());
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, mut events) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let call = |method: &str, uri: &str, body: Option<serde_json::Value>| {
+        let app = app.clone();
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", "Bearer token")
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap();
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let body = serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default();
+            (status, body)
+        }
+    };
+
+    let (status, inbox) = call("GET", "/inbox?user_id=u", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let item = inbox["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["source_type"] == "consent")
+        .cloned()
+        .expect("consent request in inbox");
+    assert_eq!(item["origin_ref"], format!("consent:{}", asked.id));
+    assert_eq!(item["title"], "Allow http.request for api.example.com?");
+
+    let decide = |decision: &str| json!({"user_id": "u", "id": asked.id, "decision": decision});
+    let (status, _) = call("POST", "/consent/decide", Some(decide("maybe"))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, granted) = call("POST", "/consent/decide", Some(decide("allow"))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(granted["status"], "granted");
+    assert_eq!(granted["active"], true);
+    assert!(granted["summary"]
+        .as_str()
+        .unwrap()
+        .starts_with("Allowed 'http.request' for api.example.com until "));
+    let event = events.recv().await.unwrap();
+    assert_eq!(event.event_type, "consent");
+    assert_eq!(event.status, "granted");
+    let (status, _) = call("POST", "/consent/decide", Some(decide("deny"))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, revoked) = call(
+        "POST",
+        "/consent/revoke",
+        Some(json!({"user_id": "u", "id": asked.id})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        revoked["summary"],
+        "Revoked 'http.request' for api.example.com"
+    );
+
+    let (status, log) = call("GET", "/consent?user_id=u", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(log["entries"].as_array().unwrap().len(), 1);
+    assert_eq!(log["entries"][0]["active"], false);
+    let (_, inbox) = call("GET", "/inbox?user_id=u", None).await;
+    assert!(!inbox["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|item| item["source_type"] == "consent"));
 }
 
 #[tokio::test]
-async fn daemon_reminder_delivery_events_endpoint() {
+async fn daemon_approval_chain_needs_each_step_from_its_own_approver() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-reminder-events.db");
+    let db_file = temp.path().join("daemon-approval-chain.db");
     let db_path = db_file.to_string_lossy().to_string();
-    let audit_log_path = temp.path().join("reminders_audit.log");
 
-    let cfg = Config {
-        provider: None,
-        openai: Some(OpenAiConfig {
-            api_key: Some("key".to_string()),
-            model: Some("gpt-4o-mini".to_string()),
-            base_url: Some(server.base_url()),
-        }),
-        heartbeat_source: MarkdownSource::default_heartbeat(),
-        prompt_source: MarkdownSource::default_prompt(),
-        memory: None,
-        tools: Some(json!({
-            "reminders": {
-                "sqlite_path": db_path,
-                "audit_log_path": audit_log_path.to_string_lossy().to_string()
-            }
-        })),
-        brains: None,
+    let step = |approver: &str| ChainStep {
+        approvers: vec![approver.to_string()],
+        timeout_secs: Some(3600),
+        on_timeout: TimeoutAction::Reject,
     };
-    config_store::save_config(&db_path, &cfg).unwrap();
-
-    let line_a = json!({
-        "timestamp": 1,
-        "user_id": "u",
-        "reminder_id": 11,
-        "status": "delivery_attempted",
-        "payload": {"title": "A"}
-    })
-    .to_string();
-    let line_b = json!({
-        "timestamp": 2,
-        "user_id": "u",
-        "reminder_id": 11,
-        "status": "delivered",
-        "payload": {"title": "A"}
-    })
-    .to_string();
-    std::fs::write(&audit_log_path, format!("{line_a}\n{line_b}\n")).unwrap();
+    let approvals = ApprovalStore::new(&db_path).await.unwrap();
+    let parked = approvals
+        .park_chain(
+            "u",
+            "solana",
+            "solana.transfer",
+            &json!({"user_id": "u", "to": "wallet-b", "lamports": 5000}),
+            &[step("partner"), step("parent")],
+        )
+        .await
+        .unwrap();
 
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let (ui_event_tx, _) = broadcast::channel(32);
+    let (ui_event_tx, mut events) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -868,47 +4903,205 @@ async fn daemon_reminder_delivery_events_endpoint() {
         .oneshot(
             Request::builder()
                 .method("GET")
-                .uri("/reminders/delivery_events?user_id=u&limit=10")
+                .uri("/inbox?user_id=partner")
                 .header("authorization", "Bearer token")
                 .body(Body::empty())
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-
     let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let events = value
-        .get("events")
-        .and_then(|v| v.as_array())
+    let inbox: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let item = inbox["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["source_type"] == "approval")
         .cloned()
-        .unwrap_or_default();
-    assert_eq!(events.len(), 2);
-    assert_eq!(
-        events[1].get("status").and_then(|v| v.as_str()),
-        Some("delivered")
-    );
+        .expect("approval waiting on the partner");
+    assert_eq!(item["title"], "Approve solana.transfer for u");
+    assert_eq!(item["requires_human_action"], true);
+
+    let decide = |user_id: &str, decision: &str| {
+        let app = app.clone();
+        let body = json!({"user_id": user_id, "id": parked.id, "decision": decision}).to_string();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/approvals/decide")
+                        .header("authorization", "Bearer token")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            (status, value)
+        }
+    };
+
+    assert_eq!(decide("u", "approve").await.0, StatusCode::FORBIDDEN);
+    assert_eq!(decide("stranger", "approve").await.0, StatusCode::NOT_FOUND);
+
+    let (status, decided) = decide("partner", "approve").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(decided["status"], "pending");
+    assert_eq!(decided["steps"][0]["status"], "approved");
+    assert_eq!(decided["steps"][0]["decided_by"], "partner");
+    assert_eq!(decided["steps"][1]["status"], "pending");
+    assert_eq!(decide("partner", "approve").await.0, StatusCode::FORBIDDEN);
+
+    let mut awaiting = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if event.event_type == "approval" && event.status == "awaiting" {
+            awaiting.push(event.user_id);
+        }
+    }
+    assert!(awaiting.contains(&"parent".to_string()));
+
+    let (status, decided) = decide("parent", "reject").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(decided["status"], "rejected");
+    assert_eq!(decided["approval"]["user_id"], "u");
+    assert_eq!(decided["steps"][1]["decided_by"], "parent");
+    assert!(approvals.list_pending("u", 10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn daemon_inbox_sweep_applies_only_accepted_proposals() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-inbox-sweep.db");
+    let db_path = db_file.to_string_lossy().to_string();
+
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    let first = reminder_store
+        .create_reminder("u", "Renew passport", now + 3600)
+        .await
+        .unwrap();
+    let second = reminder_store
+        .create_reminder("u", "Call the plumber", now + 7200)
+        .await
+        .unwrap();
+
+    let (ui_event_tx, _) = broadcast::channel(32);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let post = |uri: &'static str, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header("authorization", "Bearer token")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            (status, value)
+        }
+    };
+
+    // The mock model has no routes, so every proposal comes from the rules.
+    let (status, sweep) = post("/inbox/sweep", json!({"user_id": "u"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(sweep["status"], "proposed");
+    let proposals = sweep["approval"]["args"]["proposals"].as_array().unwrap();
+    assert_eq!(proposals.len(), 2);
+    assert!(proposals.iter().all(|p| p["proposed_by"] == "rule"));
+    let approval_id = sweep["approval"]["id"].as_i64().unwrap();
+
+    // A second run while the first is undecided returns the same batch.
+    let (_, again) = post("/inbox/sweep", json!({"user_id": "u"})).await;
+    assert_eq!(again["approval"]["id"].as_i64(), Some(approval_id));
+
+    let first_ref = format!("reminder:{}", first.id);
+    let second_ref = format!("reminder:{}", second.id);
+    let (status, decided) = post(
+        "/approvals/decide",
+        json!({
+            "user_id": "u",
+            "id": approval_id,
+            "decision": "approve",
+            "accepted": [first_ref],
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(decided["status"], "executed");
+    let outcome = &decided["approval"]["result"];
+    assert_eq!(outcome["applied"].as_array().unwrap().len(), 1);
+    assert_eq!(outcome["skipped"][0]["origin_ref"], second_ref);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("GET")
+                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let inbox: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    let status_of = |origin_ref: &str| {
+        inbox["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["origin_ref"] == origin_ref)
+            .map(|item| item["status"].clone())
+    };
+    assert_ne!(status_of(&first_ref), Some(json!("new")));
+    assert_eq!(status_of(&second_ref), Some(json!("new")));
 }
 
 #[tokio::test]
-async fn daemon_inbox_transition_persists_status() {
+async fn daemon_agent_question_gets_read_receipt_and_is_answered_from_chat() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-inbox-transition.db");
+    let db_file = temp.path().join("daemon-questions.db");
     let db_path = db_file.to_string_lossy().to_string();
 
-    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let now = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        .as_secs() as i64;
-    let reminder = reminder_store
-        .create_reminder("u", "Stateful transition reminder", now + 60)
+    let questions = QuestionStore::new(&db_path).await.unwrap();
+    let asked = questions
+        .ask("u", "Which venue should I book?", Some("plan_step:1:0"))
         .await
         .unwrap();
 
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
     let (ui_event_tx, _) = broadcast::channel(32);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
@@ -916,10 +5109,41 @@ async fn daemon_inbox_transition_persists_status() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
 
+    let question_item = |app: axum::Router| async move {
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("GET")
+                    .uri("/inbox?user_id=u&limit=100&include_done=true")
+                    .header("authorization", "Bearer token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = response.into_body().collect().await.unwrap().to_bytes();
+        let inbox: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        inbox["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["source_type"] == "question")
+            .cloned()
+            .expect("question item in inbox")
+    };
+
+    let item = question_item(app.clone()).await;
+    assert_eq!(item["origin_ref"], asked.origin_ref());
+    assert_eq!(item["status"], "new");
+    assert!(item["details"].as_str().unwrap().contains("(unread)"));
+
     let response = app
         .clone()
         .oneshot(
@@ -929,140 +5153,323 @@ async fn daemon_inbox_transition_persists_status() {
                 .header("authorization", "Bearer token")
                 .header("content-type", "application/json")
                 .body(Body::from(
-                    json!({
-                        "user_id": "u",
-                        "origin_ref": format!("reminder:{}", reminder.id),
-                        "action": "acknowledge"
-                    })
-                    .to_string(),
+                    json!({"user_id": "u", "origin_ref": asked.origin_ref(), "action": "acknowledge"})
+                        .to_string(),
                 ))
                 .unwrap(),
         )
         .await
         .unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    let seen = questions.get("u", asked.id).await.unwrap().unwrap();
+    assert!(seen.seen_at.is_some());
 
-    let response = app
+    // The reply is matched before the model runs, so the mock's lack of a
+    // completion route doesn't matter here.
+    let _ = app
         .clone()
         .oneshot(
             Request::builder()
-                .method("GET")
-                .uri("/inbox?user_id=u&limit=100&include_done=true")
+                .method("POST")
+                .uri("/process_text")
                 .header("authorization", "Bearer token")
-                .body(Body::empty())
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"user_id": "u", "text": "The old town hall"}).to_string(),
+                ))
                 .unwrap(),
         )
         .await
         .unwrap();
-    assert_eq!(response.status(), StatusCode::OK);
-    let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let items = value
-        .get("items")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    let expected_origin = format!("reminder:{}", reminder.id);
-    let transitioned = items
-        .iter()
-        .find(|item| {
-            item.get("origin_ref").and_then(|v| v.as_str()) == Some(expected_origin.as_str())
-        })
-        .cloned();
-    assert!(transitioned.is_some());
-    let transitioned = transitioned.unwrap();
-    assert_eq!(
-        transitioned.get("status").and_then(|v| v.as_str()),
-        Some("acknowledged")
-    );
+
+    let answered = questions.get("u", asked.id).await.unwrap().unwrap();
+    assert_eq!(answered.status, "answered");
+    assert_eq!(answered.answer.as_deref(), Some("The old town hall"));
+    let item = question_item(app.clone()).await;
+    assert_eq!(item["status"], "done");
 }
 
 #[tokio::test]
-async fn daemon_audit_events_endpoint() {
+async fn daemon_encryption_domain_seals_content_until_unlocked() {
     let server = MockServer::start_async().await;
     let agent = make_agent(&server).await;
     let temp = tempdir().unwrap();
-    let db_file = temp.path().join("daemon-audit-events.db");
+    let db_file = temp.path().join("daemon-domains.db");
     let db_path = db_file.to_string_lossy().to_string();
-    let audit_log_path = temp.path().join("ui_events.log");
+    let user = "domain-daemon-user";
 
-    let cfg = Config {
-        provider: None,
-        openai: Some(OpenAiConfig {
-            api_key: Some("key".to_string()),
-            model: Some("gpt-4o-mini".to_string()),
-            base_url: Some(server.base_url()),
-        }),
-        heartbeat_source: MarkdownSource::default_heartbeat(),
-        prompt_source: MarkdownSource::default_prompt(),
-        memory: None,
-        tools: Some(json!({
-            "settings": {
-                "ui_event_log_path": audit_log_path.to_string_lossy().to_string()
-            }
-        })),
-        brains: None,
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path: db_path.clone(),
     };
-    config_store::save_config(&db_path, &cfg).unwrap();
+    let app = build_router(state);
 
-    let line_a = json!({
-        "timestamp": 10,
-        "event_type": "inbox_transition",
-        "user_id": "u",
-        "tool": "inbox",
-        "status": "acknowledged"
-    })
-    .to_string();
-    let line_b = json!({
-        "timestamp": 11,
-        "event_type": "tasks",
-        "user_id": "u",
-        "tool": "tasks",
-        "status": "ok"
-    })
-    .to_string();
-    std::fs::write(&audit_log_path, format!("{line_a}\n{line_b}\n")).unwrap();
+    let post = |uri: &'static str, body: serde_json::Value| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri(uri)
+                        .header("authorization", "Bearer token")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body.to_string()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+            (status, body)
+        }
+    };
+
+    let (status, _) = post(
+        "/encryption/enroll",
+        json!({"user_id": user, "passphrase": "short"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, body) = post(
+        "/encryption/enroll",
+        json!({"user_id": user, "passphrase": "household secret"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "unlocked");
+
+    let todo_store = TodoStore::new(&db_path).await.unwrap();
+    let item = todo_store
+        .create_item(user, "Surprise party", Some("don't tell Sam"), None)
+        .await
+        .unwrap();
+    assert_eq!(item.title, "Surprise party");
+    let raw: String = {
+        use diesel::prelude::*;
+        use diesel::sql_types::Text;
+        #[derive(QueryableByName)]
+        struct Raw {
+            #[diesel(sql_type = Text)]
+            title: String,
+        }
+        let mut conn = butterfly_bot::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        diesel::sql_query("SELECT title FROM todo_items WHERE id = ?1")
+            .bind::<diesel::sql_types::Integer, _>(item.id)
+            .get_result::<Raw>(&mut conn)
+            .unwrap()
+            .title
+    };
+    assert!(raw.starts_with("ud1:"));
+    assert!(!raw.contains("Surprise"));
+
+    let inbox_state = InboxStateStore::new(&db_path).await.unwrap();
+    let origin_ref = format!("todo:{}", item.id);
+    inbox_state
+        .add_comment(user, &origin_ref, "human", "Sam already suspects")
+        .await
+        .unwrap();
+    let raw_comment: String = {
+        use diesel::prelude::*;
+        use diesel::sql_types::Text;
+        #[derive(QueryableByName)]
+        struct Raw {
+            #[diesel(sql_type = Text)]
+            body: String,
+        }
+        let mut conn = butterfly_bot::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        diesel::sql_query("SELECT body FROM inbox_comments WHERE origin_ref = ?1")
+            .bind::<Text, _>(&origin_ref)
+            .get_result::<Raw>(&mut conn)
+            .unwrap()
+            .body
+    };
+    assert!(raw_comment.starts_with("ud1:"));
+    assert!(!raw_comment.contains("Sam"));
+    let thread = inbox_state.comments(user, &origin_ref).await.unwrap();
+    assert_eq!(thread[0].body, "Sam already suspects");
+
+    let (status, body) = post("/encryption/lock", json!({"user_id": user})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "locked");
+    let locked = todo_store.get_item(user, item.id).await.unwrap().unwrap();
+    assert_eq!(locked.title, "[encrypted]");
+    assert!(todo_store
+        .create_item(user, "leak", None, None)
+        .await
+        .is_err());
+
+    let (status, _) = post(
+        "/encryption/unlock",
+        json!({"user_id": user, "passphrase": "not the secret"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = post(
+        "/encryption/unlock",
+        json!({"user_id": user, "passphrase": "household secret"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "unlocked");
+    let unlocked = todo_store.get_item(user, item.id).await.unwrap().unwrap();
+    assert_eq!(unlocked.title, "Surprise party");
+    assert_eq!(unlocked.notes.as_deref(), Some("don't tell Sam"));
+}
+
+#[tokio::test]
+async fn daemon_privacy_lock_tracks_ui_lock_state() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-privacy-lock.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let user = "privacy-lock-daemon-user";
 
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
-    let (ui_event_tx, _) = broadcast::channel(32);
+    let (ui_event_tx, _) = broadcast::channel(16);
     let state = AppState {
         agent: Arc::new(RwLock::new(Arc::new(agent))),
         reminder_store: Arc::new(reminder_store),
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
 
-    let response = app
-        .clone()
-        .oneshot(
-            Request::builder()
-                .method("GET")
-                .uri("/audit/events?user_id=u&limit=10")
-                .header("authorization", "Bearer token")
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+    let set_locked = |token: &'static str, locked: bool| {
+        let app = app.clone();
+        async move {
+            app.oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/privacy_lock")
+                    .header("authorization", format!("Bearer {token}"))
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        json!({"user_id": user, "locked": locked}).to_string(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+        }
+    };
+
+    let response = set_locked("wrong", true).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(!butterfly_bot::privacy_lock::is_locked(user));
+
+    let response = set_locked("token", true).await;
     assert_eq!(response.status(), StatusCode::OK);
+    assert!(butterfly_bot::privacy_lock::is_locked(user));
+    assert_eq!(
+        butterfly_bot::privacy_lock::reminder_notification_body(user, &["Pay rent"]),
+        "1 reminder due"
+    );
 
-    let bytes = response.into_body().collect().await.unwrap().to_bytes();
-    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
-    let events = value
-        .get("events")
-        .and_then(|v| v.as_array())
-        .cloned()
-        .unwrap_or_default();
-    assert_eq!(events.len(), 2);
+    let response = set_locked("token", false).await;
+    assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
-        events[0].get("event_type").and_then(|v| v.as_str()),
-        Some("inbox_transition")
+        butterfly_bot::privacy_lock::reminder_notification_body(user, &["Pay rent"]),
+        "Pay rent"
     );
 }
 
+#[tokio::test]
+async fn daemon_schema_doctor_reports_and_repairs_drift() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_path = temp
+        .path()
+        .join("daemon-schema-drift.db")
+        .to_string_lossy()
+        .to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    {
+        use diesel::connection::SimpleConnection;
+        let mut conn = butterfly_bot::db::open_sqlcipher_connection_sync(&db_path).unwrap();
+        conn.batch_execute("DROP INDEX idx_calendar_events_user_start")
+            .unwrap();
+    }
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+    let call = |method: &'static str, uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let response = app
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .header("authorization", "Bearer token")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            let bytes = response.into_body().collect().await.unwrap().to_bytes();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        }
+    };
+
+    let report = call("GET", "/doctor/schema").await;
+    let drift = report["databases"][0]["drift"].as_array().unwrap().clone();
+    assert_eq!(drift.len(), 1);
+    assert_eq!(drift[0]["kind"], "missing_index");
+    assert_eq!(drift[0]["name"], "idx_calendar_events_user_start");
+
+    let doctor = call("POST", "/doctor").await;
+    let schema_check = doctor["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|check| check["name"] == "schema_drift")
+        .cloned()
+        .unwrap();
+    assert_eq!(schema_check["status"], "warn");
+
+    let repaired = call("POST", "/doctor/schema/repair").await;
+    let entry = &repaired["databases"][0]["report"];
+    assert_eq!(entry["applied"].as_array().unwrap().len(), 1);
+    assert!(entry["remaining"].as_array().unwrap().is_empty());
+    let backup = entry["backup_path"].as_str().unwrap();
+    assert!(std::path::Path::new(backup).exists());
+
+    let report = call("GET", "/doctor/schema").await;
+    assert!(report["databases"][0]["drift"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
+
 #[tokio::test]
 async fn daemon_doctor_requires_auth_and_returns_checks() {
     let server = MockServer::start_async().await;
@@ -1078,6 +5485,9 @@ async fn daemon_doctor_requires_auth_and_returns_checks() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1132,6 +5542,62 @@ async fn daemon_doctor_requires_auth_and_returns_checks() {
     );
 }
 
+#[tokio::test]
+async fn daemon_storage_test_reports_missing_target() {
+    let server = MockServer::start_async().await;
+    let agent = make_agent(&server).await;
+    let temp = tempdir().unwrap();
+    let db_file = temp.path().join("daemon-storage-test.db");
+    let db_path = db_file.to_string_lossy().to_string();
+    let reminder_store = ReminderStore::new(&db_path).await.unwrap();
+    let (ui_event_tx, _) = broadcast::channel(16);
+    let state = AppState {
+        agent: Arc::new(RwLock::new(Arc::new(agent))),
+        reminder_store: Arc::new(reminder_store),
+        signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
+        token: "token".to_string(),
+        ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
+        db_path,
+    };
+    let app = build_router(state);
+
+    let unauthorized = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/storage/test")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/storage/test")
+                .header("authorization", "Bearer token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let value: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+    assert!(value["error"]
+        .as_str()
+        .unwrap_or_default()
+        .contains("No remote storage target"));
+}
+
 #[tokio::test]
 async fn daemon_security_audit_requires_auth_and_returns_findings() {
     let server = MockServer::start_async().await;
@@ -1147,6 +5613,9 @@ async fn daemon_security_audit_requires_auth_and_returns_findings() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1216,6 +5685,9 @@ async fn daemon_factory_reset_requires_auth_and_returns_default_config() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1286,6 +5758,9 @@ async fn daemon_signer_endpoints_enforce_auth_and_transitions() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1467,6 +5942,7 @@ async fn daemon_x_api_key_auth_and_reload_config_workflow() {
         base_url: Some(server.base_url()),
     });
     config_store::save_config(&db_path, &config).expect("save config for reload");
+    let store_path = db_path.clone();
 
     let reminder_store = ReminderStore::new(&db_path).await.unwrap();
     let (ui_event_tx, _) = broadcast::channel(16);
@@ -1476,6 +5952,9 @@ async fn daemon_x_api_key_auth_and_reload_config_workflow() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1520,6 +5999,23 @@ async fn daemon_x_api_key_auth_and_reload_config_workflow() {
         .await
         .unwrap();
     assert_eq!(doctor.status(), StatusCode::OK);
+
+    config.tools = Some(json!({"settings": {"roles": {
+        "default_role": "${env:BUTTERFLY_TEST_UNSET_DEFAULT_ROLE}"
+    }}}));
+    config_store::save_config(&store_path, &config).unwrap();
+    let broken = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/reload_config")
+                .header("x-api-key", "token")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(broken.status(), StatusCode::BAD_REQUEST);
 }
 
 #[tokio::test]
@@ -1541,6 +6037,9 @@ async fn daemon_preload_boot_emits_boot_events() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1593,6 +6092,9 @@ async fn daemon_x402_preview_enforces_auth_and_solana_only() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -1808,6 +6310,9 @@ async fn daemon_solana_api_surface_and_rpc_policy_wiring() {
         signer_service: butterfly_bot::security::signer_daemon::SignerService::default(),
         token: "token".to_string(),
         ui_event_tx,
+        session_store: Arc::new(SessionStore::new(&db_path).await.unwrap()),
+        role_store: Arc::new(RoleStore::new(&db_path).await.unwrap()),
+        role_policy: Arc::new(RwLock::new(RolePolicy::default())),
         db_path,
     };
     let app = build_router(state);
@@ -9,6 +9,7 @@ use butterfly_bot::error::Result;
 use butterfly_bot::interfaces::providers::{
     ChatEvent, ImageInput, LlmProvider, LlmResponse, MemoryProvider,
 };
+use butterfly_bot::providers::retention::RetentionPolicy;
 use butterfly_bot::providers::sqlite::{SqliteMemoryProvider, SqliteMemoryProviderConfig};
 
 fn setup_security_env() {
@@ -146,3 +147,82 @@ async fn summarization_inserts_memory() {
     let results = provider.search("u1", "ButterFly Bot", 5).await.unwrap();
     assert!(!results.is_empty());
 }
+
+fn two_days_from_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        + 2 * 24 * 60 * 60
+}
+
+#[tokio::test]
+async fn retention_summarizes_old_turns_before_deleting_them() {
+    setup_security_env();
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("retention.db");
+    let mut config = SqliteMemoryProviderConfig::new(db_path.to_str().unwrap());
+    config.summarizer = Some(Arc::new(SummarizerMock));
+    config.summary_threshold = Some(999);
+    config.retention = RetentionPolicy {
+        summarize_after_days: Some(1),
+        delete_after_days: Some(1),
+        ..Default::default()
+    };
+    let provider = SqliteMemoryProvider::new(config).await.unwrap();
+
+    provider
+        .append_message("u1", "user", "I like ButterFly Bot")
+        .await
+        .unwrap();
+    provider
+        .append_message("u1", "assistant", "Noted")
+        .await
+        .unwrap();
+
+    let later = two_days_from_now();
+    let dry = provider.retention_pass("u1", true, later).await.unwrap();
+    assert!(dry.dry_run);
+    assert_eq!(dry.turns_to_summarize, 2);
+    assert_eq!(dry.summary_batches, 1);
+    assert_eq!(dry.summaries_written, 0);
+    assert_eq!(dry.turns_to_delete, 2);
+    assert_eq!(provider.get_history("u1", 10).await.unwrap().len(), 2);
+
+    let applied = provider.retention_pass("u1", false, later).await.unwrap();
+    assert_eq!(applied.summaries_written, 1);
+    assert_eq!(applied.turns_to_delete, 2);
+    assert_eq!(applied.last_run_at, Some(later));
+    assert!(provider.get_history("u1", 10).await.unwrap().is_empty());
+    let results = provider.search("u1", "ButterFly Bot", 5).await.unwrap();
+    assert!(results
+        .iter()
+        .any(|entry| entry.contains("user likes ButterFly Bot")));
+}
+
+#[tokio::test]
+async fn retention_holds_turns_when_no_summarizer_is_configured() {
+    setup_security_env();
+    let dir = tempdir().unwrap();
+    let db_path = dir.path().join("retention-hold.db");
+    let mut config = SqliteMemoryProviderConfig::new(db_path.to_str().unwrap());
+    config.retention = RetentionPolicy {
+        summarize_after_days: Some(1),
+        delete_after_days: Some(1),
+        ..Default::default()
+    };
+    let provider = SqliteMemoryProvider::new(config).await.unwrap();
+    provider
+        .append_message("u1", "user", "keep me")
+        .await
+        .unwrap();
+
+    let report = provider
+        .retention_pass("u1", false, two_days_from_now())
+        .await
+        .unwrap();
+    assert!(!report.summarizer_available);
+    assert_eq!(report.turns_to_delete, 0);
+    assert_eq!(report.turns_awaiting_summary, 1);
+    assert_eq!(provider.get_history("u1", 10).await.unwrap().len(), 1);
+}
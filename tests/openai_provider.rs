@@ -677,6 +677,7 @@ async fn openai_provider_variants_and_agent_process() {
             model: Some("gpt-4o-mini".to_string()),
             base_url: Some(agent_server.base_url()),
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
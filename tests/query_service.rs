@@ -325,6 +325,7 @@ async fn query_service_and_client() {
             model: None,
             base_url: None,
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
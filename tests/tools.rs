@@ -1,6 +1,7 @@
 use std::sync::OnceLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use butterfly_bot::inbox_state::InboxStateStore;
 use butterfly_bot::interfaces::plugins::Tool;
 use butterfly_bot::tools::planning::PlanningTool;
 use butterfly_bot::tools::reminders::RemindersTool;
@@ -101,6 +102,274 @@ async fn todo_tool_supports_bulk_create_reorder_and_complete() {
     );
 }
 
+#[tokio::test]
+async fn todo_tool_clear_moves_items_to_trash_and_restore_keeps_ids() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("todo.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = TodoTool::new();
+    tool.configure(&json!({"tools": {"todo": {"sqlite_path": path}}}))
+        .expect("configure todo tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "add_many",
+            "user_id": "u1",
+            "items": ["pay rent", "book dentist"]
+        }))
+        .await
+        .expect("create many");
+    let mut ids: Vec<i64> = created["items"]
+        .as_array()
+        .expect("items")
+        .iter()
+        .map(|item| item["id"].as_i64().expect("item id"))
+        .collect();
+    ids.sort();
+
+    let cleared = tool
+        .execute(json!({"action": "wipe", "user_id": "u1", "status": "all"}))
+        .await
+        .expect("clear todos");
+    assert_eq!(cleared["deleted"], json!(2));
+    let batch_id = cleared["batch_id"].as_str().expect("batch id").to_string();
+
+    let listed = tool
+        .execute(json!({"action": "list", "user_id": "u1", "status": "all"}))
+        .await
+        .expect("list after clear");
+    assert!(listed["items"].as_array().expect("items").is_empty());
+
+    let trash = tool
+        .execute(json!({"action": "list_trash", "user_id": "u1"}))
+        .await
+        .expect("list trash");
+    assert_eq!(trash["batches"][0]["batch_id"], json!(batch_id));
+    assert_eq!(trash["batches"][0]["count"], json!(2));
+
+    let missing = tool
+        .execute(json!({"action": "restore", "user_id": "u1", "batch_id": "nope"}))
+        .await;
+    assert!(missing.is_err());
+
+    let restored = tool
+        .execute(json!({"action": "undo", "user_id": "u1"}))
+        .await
+        .expect("restore latest batch");
+    assert_eq!(restored["restored"], json!(2));
+
+    let listed = tool
+        .execute(json!({"action": "list", "user_id": "u1", "status": "all"}))
+        .await
+        .expect("list after restore");
+    let mut restored_ids: Vec<i64> = listed["items"]
+        .as_array()
+        .expect("items")
+        .iter()
+        .map(|item| item["id"].as_i64().expect("item id"))
+        .collect();
+    restored_ids.sort();
+    assert_eq!(restored_ids, ids);
+
+    let trash = tool
+        .execute(json!({"action": "trash", "user_id": "u1"}))
+        .await
+        .expect("list trash after restore");
+    assert!(trash["batches"].as_array().expect("batches").is_empty());
+}
+
+#[tokio::test]
+async fn todo_tool_remind_links_reminder_and_completion_clears_it() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("todo-remind.db");
+    let path = db_path.to_string_lossy().to_string();
+    let config = json!({"tools": {
+        "todo": {"sqlite_path": path.clone()},
+        "reminders": {"sqlite_path": path}
+    }});
+
+    let todo_tool = TodoTool::new();
+    todo_tool.configure(&config).expect("configure todo tool");
+    let reminders_tool = RemindersTool::new();
+    reminders_tool
+        .configure(&config)
+        .expect("configure reminders tool");
+
+    let paired = todo_tool
+        .execute(json!({
+            "action": "remind_me",
+            "user_id": "u1",
+            "title": "file expense report",
+            "delay_seconds": 3600
+        }))
+        .await
+        .expect("remind");
+    let todo_id = paired["item"]["id"].as_i64().expect("todo id");
+    assert_eq!(
+        paired["reminder"]["target_ref"].as_str(),
+        Some(format!("todo:{todo_id}").as_str())
+    );
+
+    let completed = todo_tool
+        .execute(json!({"action": "complete", "user_id": "u1", "id": todo_id}))
+        .await
+        .expect("complete todo");
+    assert_eq!(completed["cleared_reminders"].as_u64(), Some(1));
+
+    let open = reminders_tool
+        .execute(json!({"action": "list", "user_id": "u1", "status": "open"}))
+        .await
+        .expect("list reminders");
+    assert!(open["reminders"].as_array().expect("reminders").is_empty());
+
+    let missing_time = todo_tool
+        .execute(json!({"action": "remind", "user_id": "u1", "id": todo_id}))
+        .await;
+    assert!(missing_time.is_err());
+}
+
+#[tokio::test]
+async fn todo_tool_labels_filter_lists() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("todo-labels.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = TodoTool::new();
+    tool.configure(&json!({"tools": {"todo": {"sqlite_path": path}}}))
+        .expect("configure todo tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "buy stamps",
+            "labels": ["Errand", "#work"]
+        }))
+        .await
+        .expect("create");
+    assert_eq!(created["labels"], json!(["errand", "work"]));
+    let stamps = created["item"]["id"].as_i64().expect("id");
+
+    let other = tool
+        .execute(json!({"action": "create", "user_id": "u1", "title": "write report"}))
+        .await
+        .expect("create other");
+    let report = other["item"]["id"].as_i64().expect("id");
+    tool.execute(json!({
+        "action": "tag",
+        "user_id": "u1",
+        "id": report,
+        "labels": "work"
+    }))
+    .await
+    .expect("label");
+
+    let listed_ids = |value: serde_json::Value| -> Vec<i64> {
+        value["items"]
+            .as_array()
+            .expect("items")
+            .iter()
+            .map(|item| item["id"].as_i64().expect("id"))
+            .collect()
+    };
+
+    let any = tool
+        .execute(json!({"action": "list", "user_id": "u1", "labels_any": ["work"]}))
+        .await
+        .expect("list any");
+    assert_eq!(listed_ids(any.clone()).len(), 2);
+    assert!(listed_ids(any).contains(&report));
+
+    let all = tool
+        .execute(json!({"action": "list", "user_id": "u1", "labels_all": ["work", "errand"]}))
+        .await
+        .expect("list all");
+    assert_eq!(listed_ids(all), vec![stamps]);
+
+    let foreign = tool
+        .execute(json!({"action": "label", "user_id": "u2", "id": stamps, "labels": ["x"]}))
+        .await;
+    assert!(foreign.is_err());
+}
+
+#[tokio::test]
+async fn todo_tool_projects_group_and_filter_lists() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("todo-projects.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = TodoTool::new();
+    tool.configure(&json!({"tools": {"todo": {"sqlite_path": path}}}))
+        .expect("configure todo tool");
+
+    let launch = tool
+        .execute(json!({"action": "create_project", "user_id": "u1", "name": "Launch"}))
+        .await
+        .expect("create project");
+    let launch_id = launch["project"]["id"].as_i64().expect("project id");
+    let duplicate = tool
+        .execute(json!({"action": "create_project", "user_id": "u1", "name": "launch"}))
+        .await;
+    assert!(duplicate.is_err());
+
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "write press release",
+            "project": "Launch"
+        }))
+        .await
+        .expect("create in project");
+    assert_eq!(created["project"]["id"].as_i64(), Some(launch_id));
+    let release = created["item"]["id"].as_i64().expect("id");
+
+    let other = tool
+        .execute(json!({"action": "create", "user_id": "u1", "title": "water plants"}))
+        .await
+        .expect("create other");
+    let plants = other["item"]["id"].as_i64().expect("id");
+
+    let unknown = tool
+        .execute(json!({"action": "create", "user_id": "u1", "title": "x", "project": "Nope"}))
+        .await;
+    assert!(unknown.is_err());
+
+    let filtered = tool
+        .execute(json!({"action": "list", "user_id": "u1", "project": launch_id}))
+        .await
+        .expect("list project");
+    let items = filtered["items"].as_array().expect("items");
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["id"].as_i64(), Some(release));
+    assert_eq!(items[0]["project_id"].as_i64(), Some(launch_id));
+
+    tool.execute(json!({"action": "move", "user_id": "u1", "id": plants, "project": "launch"}))
+        .await
+        .expect("move into project");
+    let listed = tool
+        .execute(json!({"action": "projects", "user_id": "u1"}))
+        .await
+        .expect("list projects");
+    assert_eq!(listed["projects"][0]["todos"].as_i64(), Some(2));
+
+    let deleted = tool
+        .execute(json!({"action": "delete_project", "user_id": "u1", "project": launch_id}))
+        .await
+        .expect("delete project");
+    assert_eq!(deleted["detached"].as_i64(), Some(2));
+    let all = tool
+        .execute(json!({"action": "list", "user_id": "u1"}))
+        .await
+        .expect("list all");
+    assert_eq!(all["items"].as_array().expect("items").len(), 2);
+}
+
 #[tokio::test]
 async fn tasks_tool_schedules_and_toggles_task() {
     setup_security_env();
@@ -211,6 +480,331 @@ async fn planning_tool_crud_flow_works() {
     assert_eq!(deleted["deleted"], json!(true));
 }
 
+#[tokio::test]
+async fn planning_tool_updates_a_single_step_status() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("plans.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = PlanningTool::new();
+    tool.configure(&json!({"tools": {"planning": {"sqlite_path": path}}}))
+        .expect("configure planning tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "Ship v1",
+            "goal": "Release with docs",
+            "steps": ["freeze scope", "publish changelog"]
+        }))
+        .await
+        .expect("create plan");
+    let id = created["plan"]["id"].as_i64().expect("plan id");
+    assert_eq!(created["todo_items_created"], json!(2));
+
+    let marked = tool
+        .execute(json!({
+            "action": "mark_step",
+            "user_id": "u1",
+            "id": id,
+            "step": 0,
+            "status": "completed"
+        }))
+        .await
+        .expect("mark step done");
+    assert_eq!(marked["step_ref"], json!(format!("plan_step:{id}:0")));
+    assert_eq!(marked["step_status"], json!("done"));
+    assert_eq!(marked["todo_completed"], json!(true));
+    assert_eq!(marked["plan"]["steps"][0]["status"], json!("done"));
+    assert!(marked["plan"]["steps"][1]["status"].is_null());
+
+    let states = InboxStateStore::new(&path).await.expect("inbox states");
+    let statuses = states.list_statuses("u1", 10).await.expect("statuses");
+    assert_eq!(
+        statuses
+            .get(&format!("plan_step:{id}:0"))
+            .map(String::as_str),
+        Some("done")
+    );
+
+    let reopened = tool
+        .execute(json!({
+            "action": "step_status",
+            "user_id": "u1",
+            "id": id,
+            "step": 0,
+            "status": "blocked"
+        }))
+        .await
+        .expect("block step");
+    assert_eq!(reopened["todo_completed"], json!(false));
+
+    assert!(tool
+        .execute(json!({
+            "action": "step_status",
+            "user_id": "u1",
+            "id": id,
+            "step": 5,
+            "status": "done"
+        }))
+        .await
+        .is_err());
+    assert!(tool
+        .execute(json!({
+            "action": "step_status",
+            "user_id": "u2",
+            "id": id,
+            "step": 0,
+            "status": "done"
+        }))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn planning_tool_notes_land_in_the_item_thread() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("plans.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = PlanningTool::new();
+    tool.configure(&json!({"tools": {"planning": {"sqlite_path": path}}}))
+        .expect("configure planning tool");
+
+    let states = InboxStateStore::new(&path).await.expect("inbox states");
+    states
+        .add_comment("u1", "todo:3", "human", "Asked the vendor on Monday")
+        .await
+        .expect("human note");
+    let noted = tool
+        .execute(json!({
+            "action": "comment",
+            "user_id": "u1",
+            "item_ref": "todo:3",
+            "text": "  waiting on vendor reply  "
+        }))
+        .await
+        .expect("agent note");
+    assert_eq!(noted["comment"]["author"], json!("agent"));
+    assert_eq!(noted["comment"]["body"], json!("waiting on vendor reply"));
+
+    let thread = states.comments("u1", "todo:3").await.expect("thread");
+    assert_eq!(
+        thread
+            .iter()
+            .map(|comment| (comment.author.as_str(), comment.body.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            ("human", "Asked the vendor on Monday"),
+            ("agent", "waiting on vendor reply"),
+        ]
+    );
+    assert!(states.comments("u2", "todo:3").await.unwrap().is_empty());
+
+    for params in [
+        json!({"action": "note", "user_id": "u1", "item_ref": "todo:3", "text": " "}),
+        json!({"action": "note", "user_id": "u1", "text": "no item"}),
+    ] {
+        assert!(tool.execute(params).await.is_err());
+    }
+    assert!(states
+        .add_comment("u1", "todo:3", "someone", "hi")
+        .await
+        .is_err());
+
+    states.clear_statuses("u1").await.expect("clear");
+    assert!(states.comments("u1", "todo:3").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn planning_tool_instantiates_plans_from_templates() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("plans.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = PlanningTool::new();
+    tool.configure(&json!({"tools": {"planning": {"sqlite_path": path}}}))
+        .expect("configure planning tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "template_create",
+            "user_id": "u1",
+            "name": "Onboard new client",
+            "title": "Onboard {{client}}",
+            "goal": "Get {{client}} live",
+            "steps": ["Kickoff call with {{client}}", {"title": "Send {{client}} the contract"}]
+        }))
+        .await
+        .expect("create template");
+    let template_id = created["template"]["id"].as_i64().expect("template id");
+    assert_eq!(created["template"]["placeholders"], json!(["client"]));
+
+    let duplicate = tool
+        .execute(json!({
+            "action": "template_create",
+            "user_id": "u1",
+            "name": "onboard  new client",
+            "title": "Again",
+            "goal": "Again"
+        }))
+        .await;
+    assert!(duplicate.is_err());
+
+    let missing = tool
+        .execute(json!({
+            "action": "instantiate_template",
+            "user_id": "u1",
+            "name": "Onboard new client"
+        }))
+        .await;
+    assert!(missing.is_err());
+
+    let plan = tool
+        .execute(json!({
+            "action": "use_template",
+            "user_id": "u1",
+            "name": "Onboard new client",
+            "values": {"client": "Acme"}
+        }))
+        .await
+        .expect("instantiate template");
+    assert_eq!(plan["template"]["id"], json!(template_id));
+    assert_eq!(plan["plan"]["title"], json!("Onboard Acme"));
+    assert_eq!(plan["plan"]["goal"], json!("Get Acme live"));
+    assert_eq!(
+        plan["plan"]["steps"][1]["title"],
+        json!("Send Acme the contract")
+    );
+    assert_eq!(plan["todo_items_created"], json!(2));
+
+    let updated = tool
+        .execute(json!({
+            "action": "template_update",
+            "user_id": "u1",
+            "id": template_id,
+            "goal": "Get {{client}} live by {{date}}"
+        }))
+        .await
+        .expect("update template");
+    assert_eq!(
+        updated["template"]["placeholders"],
+        json!(["client", "date"])
+    );
+    assert_eq!(updated["template"]["title"], json!("Onboard {{client}}"));
+
+    let other_user = tool
+        .execute(json!({"action": "template_list", "user_id": "u2"}))
+        .await
+        .expect("list other user's templates");
+    assert!(other_user["templates"]
+        .as_array()
+        .expect("templates")
+        .is_empty());
+
+    let deleted = tool
+        .execute(json!({"action": "template_delete", "user_id": "u1", "id": template_id}))
+        .await
+        .expect("delete template");
+    assert_eq!(deleted["deleted"], json!(true));
+    let listed = tool
+        .execute(json!({"action": "template_list", "user_id": "u1"}))
+        .await
+        .expect("list templates");
+    assert!(listed["templates"]
+        .as_array()
+        .expect("templates")
+        .is_empty());
+}
+
+#[tokio::test]
+async fn planning_tool_proposes_trade_off_when_urgent_work_overcommits() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("plans.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = PlanningTool::new();
+    tool.configure(&json!({"tools": {"planning": {
+        "sqlite_path": path,
+        "capacity": {"minutes_per_day": 180, "horizon_days": 1}
+    }}}))
+    .expect("configure planning tool");
+
+    let soon = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+        + 3600;
+    let existing = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "Quarterly report",
+            "goal": "Send the report",
+            "steps": [
+                {"title": "Draft report", "priority": "high", "due_at": soon,
+                 "estimate_optimistic_minutes": 60, "estimate_likely_minutes": 120},
+                {"title": "Tidy slides", "priority": "low", "due_at": soon,
+                 "estimate_likely_minutes": 60}
+            ]
+        }))
+        .await
+        .expect("create existing plan");
+    assert!(existing["agenda_proposal"].is_null());
+    let existing_id = existing["plan"]["id"].as_i64().expect("plan id");
+
+    let urgent = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "Production outage",
+            "goal": "Restore service",
+            "steps": [{"title": "Fix outage", "priority": "urgent", "estimate_likely_minutes": 90}]
+        }))
+        .await
+        .expect("create urgent plan");
+    let proposal = &urgent["agenda_proposal"];
+    assert_eq!(proposal["status"], json!("proposed"));
+    let steps = proposal["steps"].as_array().expect("proposal steps");
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0]["action"], json!("defer"));
+    assert_eq!(steps[1]["action"], json!("shrink"));
+    let proposal_id = proposal["id"].as_i64().expect("proposal id");
+
+    let again = tool
+        .execute(json!({"action": "negotiate", "user_id": "u1", "id": urgent["plan"]["id"]}))
+        .await
+        .expect("negotiate again");
+    assert_eq!(again["agenda_proposal"]["id"], json!(proposal_id));
+
+    let approved = tool
+        .execute(json!({"action": "approve", "user_id": "u1", "id": proposal_id}))
+        .await
+        .expect("approve proposal");
+    assert_eq!(approved["applied"], json!(2));
+    assert_eq!(approved["plan"]["status"], json!("done"));
+
+    let updated = tool
+        .execute(json!({"action": "get", "user_id": "u1", "id": existing_id}))
+        .await
+        .expect("get updated plan");
+    assert_eq!(
+        updated["plan"]["steps"][0]["estimate_likely_minutes"],
+        json!(60)
+    );
+    assert!(updated["plan"]["steps"][1]["due_at"].as_i64().unwrap() > soon);
+
+    let rejected = tool
+        .execute(json!({"action": "reject", "user_id": "u1", "id": proposal_id}))
+        .await;
+    assert!(rejected.is_err());
+}
+
 #[tokio::test]
 async fn reminders_tool_handles_aliases_and_lifecycle() {
     setup_security_env();
@@ -263,6 +857,103 @@ async fn reminders_tool_handles_aliases_and_lifecycle() {
     assert_eq!(cleared["deleted"], json!(1));
 }
 
+#[tokio::test]
+async fn reminders_tool_validates_and_sets_delivery_windows() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("reminders.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = RemindersTool::new();
+    tool.configure(&json!({"tools": {"reminders": {
+        "sqlite_path": path,
+        "delivery_windows": {"work": "09:00-17:00 weekdays"}
+    }}}))
+    .expect("configure reminders tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "Send report",
+            "in_seconds": 60,
+            "delivery_window": "work"
+        }))
+        .await
+        .expect("create reminder with window");
+    assert_eq!(created["reminder"]["delivery_window"], json!("work"));
+    let id = created["reminder"]["id"].as_i64().expect("reminder id");
+
+    let rejected = tool
+        .execute(json!({
+            "action": "set_window",
+            "user_id": "u1",
+            "id": id,
+            "delivery_window": "lunchtime"
+        }))
+        .await;
+    assert!(rejected.is_err());
+
+    let cleared = tool
+        .execute(json!({
+            "action": "set_delivery_window",
+            "user_id": "u1",
+            "id": id,
+            "delivery_window": null
+        }))
+        .await
+        .expect("clear window");
+    assert_eq!(cleared["updated"], json!(true));
+
+    let listed = tool
+        .execute(json!({"action": "list", "user_id": "u1"}))
+        .await
+        .expect("list reminders");
+    assert_eq!(listed["reminders"][0]["delivery_window"], json!(null));
+}
+
+#[tokio::test]
+async fn reminders_tool_sets_priority() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let db_path = dir.path().join("reminders.db");
+    let path = db_path.to_string_lossy().to_string();
+
+    let tool = RemindersTool::new();
+    tool.configure(&json!({"tools": {"reminders": {"sqlite_path": path}}}))
+        .expect("configure reminders tool");
+
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "title": "Renew passport",
+            "in_seconds": 60,
+            "priority": "high"
+        }))
+        .await
+        .expect("create reminder with priority");
+    assert_eq!(created["reminder"]["priority"], json!("high"));
+    let id = created["reminder"]["id"].as_i64().expect("reminder id");
+
+    let rejected = tool
+        .execute(json!({"action": "set_priority", "user_id": "u1", "id": id, "priority": "asap"}))
+        .await;
+    assert!(rejected.is_err());
+
+    let lowered = tool
+        .execute(json!({"action": "prioritize", "user_id": "u1", "id": id, "priority": "low"}))
+        .await
+        .expect("set priority");
+    assert_eq!(lowered["updated"], json!(true));
+
+    let listed = tool
+        .execute(json!({"action": "list", "user_id": "u1"}))
+        .await
+        .expect("list reminders");
+    assert_eq!(listed["reminders"][0]["priority"], json!("low"));
+}
+
 #[tokio::test]
 async fn wakeup_tool_create_toggle_and_delete() {
     setup_security_env();
@@ -310,3 +1001,64 @@ async fn wakeup_tool_create_toggle_and_delete() {
         .expect("delete wakeup task");
     assert_eq!(deleted["deleted"], json!(true));
 }
+
+#[tokio::test]
+async fn wakeup_options_are_stored_and_failures_back_off() {
+    setup_security_env();
+    let dir = tempdir().expect("temp dir");
+    let path = dir.path().join("wakeup.db").to_string_lossy().to_string();
+
+    let tool = WakeupTool::new();
+    tool.configure(&json!({"tools": {"wakeup": {"sqlite_path": path.clone()}}}))
+        .expect("configure wakeup tool");
+    let created = tool
+        .execute(json!({
+            "action": "create",
+            "user_id": "u1",
+            "name": "Inbox sweep",
+            "prompt": "triage new items",
+            "interval_minutes": 10,
+            "jitter_seconds": 90,
+            "max_concurrency": 2,
+            "max_backoff_minutes": 60
+        }))
+        .await
+        .expect("create wakeup task with options");
+    assert_eq!(created["task"]["jitter_seconds"], json!(90));
+    assert_eq!(created["task"]["max_concurrency"], json!(2));
+    assert_eq!(created["task"]["max_backoff_minutes"], json!(60));
+    assert_eq!(created["task"]["consecutive_failures"], json!(0));
+    let id = created["task"]["id"].as_i64().expect("wakeup id") as i32;
+
+    let clock = butterfly_bot::clock::ManualClock::new(1_767_268_800);
+    let store = butterfly_bot::wakeup::WakeupStore::new(&path)
+        .await
+        .expect("wakeup store")
+        .with_clock(clock.clone());
+    let run_at = clock.advance(600);
+    store
+        .mark_run(id, run_at, run_at + 600)
+        .await
+        .expect("mark run");
+    let failed = store
+        .record_outcome(id, run_at, false)
+        .await
+        .expect("first failure");
+    assert_eq!(failed.consecutive_failures, 1);
+    assert_eq!(failed.next_run_at, run_at + 20 * 60);
+    let failed = store
+        .record_outcome(id, run_at, false)
+        .await
+        .expect("second failure");
+    assert_eq!(failed.next_run_at, run_at + 40 * 60);
+    let failed = store
+        .record_outcome(id, run_at, false)
+        .await
+        .expect("capped failure");
+    assert_eq!(failed.next_run_at, run_at + 60 * 60);
+    let recovered = store
+        .record_outcome(id, run_at, true)
+        .await
+        .expect("success resets failures");
+    assert_eq!(recovered.consecutive_failures, 0);
+}
@@ -98,6 +98,130 @@ async fn http_call_tool_uses_base_url_headers_query_and_parses_json() {
     request_mock.assert_calls(1);
 }
 
+#[tokio::test]
+async fn http_call_tool_caches_get_responses_and_revalidates_with_etag() {
+    setup_security_env();
+    let server = MockServer::start_async().await;
+    let fresh_mock = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/rates");
+            then.status(200)
+                .header("cache-control", "max-age=60")
+                .json_body(json!({"usd": 1}));
+        })
+        .await;
+    let first_mock = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/status")
+                .header_missing("if-none-match");
+            then.status(200)
+                .header("cache-control", "no-cache")
+                .header("etag", "\"v1\"")
+                .json_body(json!({"up": true}));
+        })
+        .await;
+    let revalidate_mock = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/status")
+                .header("if-none-match", "\"v1\"");
+            then.status(304);
+        })
+        .await;
+
+    let tool = HttpCallTool::new();
+    tool.configure(&json!({
+        "tools": {"http_call": {"servers": [{"name": "api", "url": server.base_url()}]}}
+    }))
+    .expect("configure http_call");
+
+    let first = tool
+        .execute(json!({"method": "GET", "endpoint": "rates"}))
+        .await
+        .expect("first rates call");
+    let second = tool
+        .execute(json!({"method": "GET", "endpoint": "rates"}))
+        .await
+        .expect("second rates call");
+    assert_eq!(first["cache"], json!("miss"));
+    assert_eq!(second["cache"], json!("hit"));
+    assert_eq!(second["json"]["usd"], json!(1));
+    fresh_mock.assert_calls(1);
+
+    let bypass = tool
+        .execute(json!({"method": "GET", "endpoint": "rates", "cache": false}))
+        .await
+        .expect("uncached rates call");
+    assert_eq!(bypass["cache"], json!("bypass"));
+    fresh_mock.assert_calls(2);
+
+    tool.execute(json!({"method": "GET", "endpoint": "status"}))
+        .await
+        .expect("first status call");
+    let revalidated = tool
+        .execute(json!({"method": "GET", "endpoint": "status"}))
+        .await
+        .expect("revalidated status call");
+    assert_eq!(revalidated["cache"], json!("revalidated"));
+    assert_eq!(revalidated["http_status"], json!(200));
+    assert_eq!(revalidated["json"]["up"], json!(true));
+    first_mock.assert_calls(1);
+    revalidate_mock.assert_calls(1);
+}
+
+#[tokio::test]
+async fn http_call_tool_fetches_and_reuses_oauth2_tokens() {
+    setup_security_env();
+    disable_keyring_for_test_process();
+    butterfly_bot::vault::set_secret("http_call_crm_client_secret", "shh")
+        .expect("store client secret");
+    let server = MockServer::start_async().await;
+    let token_mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/oauth/token")
+                .body_includes("grant_type=client_credentials")
+                .body_includes("client_id=bot")
+                .body_includes("client_secret=shh");
+            then.status(200).json_body(
+                json!({"access_token": "t1", "expires_in": 3600, "token_type": "bearer"}),
+            );
+        })
+        .await;
+    let api_mock = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/contacts")
+                .header("authorization", "Bearer t1");
+            then.status(201).json_body(json!({"ok": true}));
+        })
+        .await;
+
+    let tool = HttpCallTool::new();
+    let config = json!({
+        "tools": {"http_call": {"servers": [{
+            "name": "crm",
+            "url": server.base_url(),
+            "oauth2": {"token_url": server.url("/oauth/token"), "client_id": "bot"}
+        }]}}
+    });
+    let secrets = tool.required_secrets_for_config(&config);
+    assert_eq!(secrets.len(), 1);
+    assert_eq!(secrets[0].name, "http_call_crm_client_secret");
+    tool.configure(&config).expect("configure http_call");
+
+    for _ in 0..2 {
+        let result = tool
+            .execute(json!({"method": "POST", "endpoint": "contacts", "json": {"name": "Ada"}}))
+            .await
+            .expect("oauth2 call");
+        assert_eq!(result["http_status"], json!(201));
+    }
+    token_mock.assert_calls(1);
+    api_mock.assert_calls(2);
+}
+
 #[tokio::test]
 async fn http_call_tool_infers_json_from_string_body() {
     setup_security_env();
@@ -358,6 +482,56 @@ async fn coding_tool_requires_prompt_before_provider_call() {
     assert_runtime_err_contains(err, "Missing prompt");
 }
 
+#[tokio::test]
+async fn coding_tool_applies_patches_inside_allowlisted_workspace() {
+    setup_security_env();
+    let temp = tempfile::tempdir().unwrap();
+    let workspace = temp.path().join("service");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(workspace.join("app.py"), "def ping():\n    return 'pong'\n").unwrap();
+    let backups = temp.path().join("backups");
+
+    let tool = CodingTool::new();
+    tool.configure(&json!({
+        "tools": {
+            "coding": {"backup_dir": backups.to_string_lossy()},
+            "settings": {"sandbox": {"tools": {"coding": {
+                "filesystem": {"allow": [workspace.to_string_lossy()]}
+            }}}}
+        }
+    }))
+    .expect("configure coding tool with workspace");
+
+    let patch = "--- a/app.py\n+++ b/app.py\n@@ -1,2 +1,2 @@\n def ping():\n-    return 'pong'\n+    return 'PONG'\n";
+    let dry = tool
+        .execute(json!({"action": "apply_patch", "patch": patch, "dry_run": true}))
+        .await
+        .expect("dry run");
+    assert_eq!(dry["report"]["files"][0]["change"], json!("modified"));
+    assert!(std::fs::read_to_string(workspace.join("app.py"))
+        .unwrap()
+        .contains("'pong'"));
+
+    let applied = tool
+        .execute(json!({"action": "apply_patch", "patch": patch}))
+        .await
+        .expect("apply patch");
+    assert_eq!(applied["report"]["dry_run"], json!(false));
+    assert!(std::fs::read_to_string(workspace.join("app.py"))
+        .unwrap()
+        .contains("'PONG'"));
+    let backup = applied["report"]["files"][0]["backup"].as_str().unwrap();
+    assert!(std::fs::read_to_string(backup).unwrap().contains("'pong'"));
+
+    let escape = "--- /dev/null\n+++ b/../escape.py\n@@ -0,0 +1 @@\n+print('out')\n";
+    let err = tool
+        .execute(json!({"action": "apply_patch", "patch": escape}))
+        .await
+        .expect_err("paths outside the workspace are refused");
+    assert_runtime_err_contains(err, "must stay inside the workspace");
+    assert!(!temp.path().join("escape.py").exists());
+}
+
 #[tokio::test]
 async fn mcp_tool_validates_config_and_reports_routing_errors() {
     setup_security_env();
@@ -606,6 +780,67 @@ async fn zapier_tool_header_auth_and_action_validation() {
     assert_runtime_err_contains(missing_tool_err, "Missing tool name");
 }
 
+#[tokio::test]
+async fn zapier_tool_lists_and_triggers_typed_zaps() {
+    setup_security_env();
+    disable_keyring_for_test_process();
+    let server = MockServer::start_async().await;
+    let hook = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/hooks/catch/1/lead")
+                .json_body(json!({"email": "ada@example.com", "amount": 12.5}));
+            then.status(200)
+                .header("content-type", "application/json")
+                .json_body(json!({"status": "success", "id": "zap-run-1"}));
+        })
+        .await;
+
+    let tool = ZapierTool::new();
+    tool.configure(&json!({
+        "tools": {"zapier": {"zaps": [
+            {
+                "name": "new_lead",
+                "url": server.url("/hooks/catch/1/lead"),
+                "description": "Add a lead to the CRM",
+                "fields": {"email": "string", "amount": "number?"}
+            },
+            {"name": "tickets", "inbound": {"user": "alice"}}
+        ]}}
+    }))
+    .expect("configure zapier zaps");
+
+    let listed = tool
+        .execute(json!({"action": "list_zaps"}))
+        .await
+        .expect("list zaps without an MCP token");
+    assert_eq!(listed["zaps"].as_array().map(Vec::len), Some(1));
+    assert_eq!(listed["zaps"][0]["name"], json!("new_lead"));
+    assert!(!listed.to_string().contains("/hooks/catch"));
+
+    let invalid = tool
+        .execute(json!({
+            "action": "trigger_zap",
+            "zap": "new_lead",
+            "payload": {"amount": "12"}
+        }))
+        .await
+        .expect_err("payload must match the declared fields");
+    assert_runtime_err_contains(invalid, "missing email");
+
+    let fired = tool
+        .execute(json!({
+            "action": "trigger_zap",
+            "zap": "new_lead",
+            "payload": {"email": "ada@example.com", "amount": 12.5}
+        }))
+        .await
+        .expect("trigger zap");
+    assert_eq!(fired["status"], json!("ok"));
+    assert_eq!(fired["response"]["id"], json!("zap-run-1"));
+    hook.assert_calls(1);
+}
+
 #[tokio::test]
 async fn search_internet_tool_honors_network_policy_and_query_validation() {
     setup_security_env();
@@ -643,6 +878,167 @@ async fn search_internet_tool_honors_network_policy_and_query_validation() {
         .contains("Network access denied"));
 }
 
+#[tokio::test]
+async fn search_internet_falls_back_across_result_providers() {
+    setup_security_env();
+    let server = MockServer::start_async().await;
+    let searxng = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/searx/search")
+                .query_param("q", "rust release")
+                .query_param("format", "json");
+            then.status(503);
+        })
+        .await;
+    let duckduckgo = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/ddg/html/")
+                .body_includes("q=rust+release");
+            then.status(200).body(
+                r#"<div class="result"><a class="result__a" href="//duckduckgo.com/l/?uddg=https%3A%2F%2Fblog.rust-lang.org%2F&amp;rut=x">Rust <b>Blog</b></a>
+                <a class="result__snippet" href="x">Rust 1.80 is out.</a></div>"#,
+            );
+        })
+        .await;
+    let brave = server
+        .mock_async(|when, then| {
+            when.method(GET)
+                .path("/brave")
+                .header("x-subscription-token", "brave-test-key")
+                .query_param("q", "rust release");
+            then.status(200).json_body(json!({
+                "web": {"results": [
+                    {"title": "Announcing <strong>Rust</strong>", "url": "https://blog.rust-lang.org/1", "description": "Stable"}
+                ]}
+            }));
+        })
+        .await;
+
+    let tool = SearchInternetTool::new();
+    tool.configure(&json!({
+        "tools": {
+            "search_internet": {
+                "provider": "searxng",
+                "fallback": ["duckduckgo"],
+                "searxng_url": format!("{}/searx", server.base_url()),
+                "duckduckgo_url": format!("{}/ddg/html/", server.base_url())
+            }
+        }
+    }))
+    .expect("configure search_internet");
+    assert!(tool
+        .required_secrets_for_config(
+            &json!({"tools": {"search_internet": {"provider": "searxng"}}})
+        )
+        .is_empty());
+
+    let response = tool
+        .execute(json!({"query": "rust release"}))
+        .await
+        .expect("search response");
+    assert_eq!(response["status"], json!("success"));
+    assert_eq!(response["provider"], json!("duckduckgo"));
+    assert_eq!(response["fallback_from"][0]["provider"], json!("searxng"));
+    assert_eq!(
+        response["results"],
+        json!([{"title": "Rust Blog", "url": "https://blog.rust-lang.org/", "snippet": "Rust 1.80 is out."}])
+    );
+    searxng.assert_calls(1);
+    duckduckgo.assert_calls(1);
+
+    butterfly_bot::vault::set_secret("search_internet_brave_api_key", "brave-test-key")
+        .expect("store brave key");
+    tool.configure(&json!({
+        "tools": {
+            "search_internet": {
+                "provider": "brave",
+                "brave_url": format!("{}/brave", server.base_url())
+            }
+        }
+    }))
+    .expect("configure brave");
+    let response = tool
+        .execute(json!({"query": "rust release"}))
+        .await
+        .expect("brave response");
+    assert_eq!(response["provider"], json!("brave"));
+    assert_eq!(response["results"][0]["title"], json!("Announcing Rust"));
+    assert_eq!(response["results"][0]["snippet"], json!("Stable"));
+    brave.assert_calls(1);
+}
+
+#[tokio::test]
+async fn search_internet_fetches_and_summarizes_a_page() {
+    setup_security_env();
+    let server = MockServer::start_async().await;
+    let page = server
+        .mock_async(|when, then| {
+            when.method(GET).path("/post");
+            then.status(200)
+                .header("content-type", "text/html; charset=utf-8")
+                .body(
+                    "<html><head><title>Rust 1.80</title><script>x()</script></head><body>\
+                     <nav>Home</nav><article><p>LazyCell and LazyLock are stable.</p></article></body></html>",
+                );
+        })
+        .await;
+    let summary = server
+        .mock_async(|when, then| {
+            when.method(POST)
+                .path("/v1/chat/completions")
+                .body_includes("\"model\":\"summary-mini\"")
+                .body_includes("LazyCell and LazyLock are stable.");
+            then.status(200).json_body(json!({
+                "id": "chatcmpl-summary",
+                "object": "chat.completion",
+                "created": 1,
+                "model": "summary-mini",
+                "choices": [{
+                    "index": 0,
+                    "message": {"role": "assistant", "content": "Rust 1.80 stabilizes LazyCell and LazyLock."},
+                    "finish_reason": "stop"
+                }]
+            }));
+        })
+        .await;
+
+    let tool = SearchInternetTool::new();
+    tool.configure(&json!({
+        "memory": {
+            "summary_model": "summary-mini",
+            "openai": {"api_key": "summary-key", "base_url": format!("{}/v1", server.base_url())}
+        },
+        "tools": {"search_internet": {"provider": "duckduckgo"}}
+    }))
+    .expect("configure search_internet");
+
+    let response = tool
+        .execute(json!({
+            "action": "fetch_and_summarize",
+            "url": format!("{}/post", server.base_url()),
+            "query": "what is new?"
+        }))
+        .await
+        .expect("summary response");
+    assert_eq!(response["status"], json!("success"));
+    assert_eq!(response["title"], json!("Rust 1.80"));
+    assert_eq!(response["summarized"], json!(true));
+    assert_eq!(
+        response["result"],
+        json!("Rust 1.80 stabilizes LazyCell and LazyLock.")
+    );
+    page.assert_calls(1);
+    summary.assert_calls(1);
+
+    let missing_url = tool
+        .execute(json!({"action": "fetch_and_summarize", "query": "x"}))
+        .await
+        .expect("missing url response");
+    assert_eq!(missing_url["message"], json!("url is required"));
+}
+
 #[tokio::test]
 async fn solana_tool_requires_rpc_endpoint_for_network_actions() {
     setup_security_env();
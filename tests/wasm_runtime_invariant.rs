@@ -18,6 +18,7 @@ async fn all_registered_tools_resolve_to_wasm_runtime() {
             model: Some("gpt-4o-mini".to_string()),
             base_url: Some("http://localhost:11434/v1".to_string()),
         }),
+        llm: None,
         heartbeat_source: MarkdownSource::default_heartbeat(),
         prompt_source: MarkdownSource::default_prompt(),
         memory: None,
@@ -96,6 +96,21 @@ fn require_i64(args: &Map<String, Value>, key: &str) -> Result<(), Value> {
     }
 }
 
+fn require_chart(args: &Map<String, Value>) -> Result<(), Value> {
+    let has_series = args
+        .get("chart")
+        .and_then(|chart| chart.get("series"))
+        .or_else(|| args.get("series"))
+        .and_then(|value| value.as_array())
+        .map(|series| !series.is_empty())
+        .unwrap_or(false);
+    if has_series {
+        Ok(())
+    } else {
+        Err(invalid_args("Missing chart series"))
+    }
+}
+
 fn execute_todo(input: &Value) -> Value {
     let mut args = match input_object(input) {
         Ok(args) => args,
@@ -117,12 +132,65 @@ fn execute_todo(input: &Value) -> Value {
             "create_many"
         }
         "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear",
+        "undo" | "undo_clear" | "untrash" => "restore",
+        "list_trash" => "trash",
+        "reset" | "reset_list" | "restart_checklist" => "reset_checklist",
+        "remind_me" | "set_reminder" | "add_reminder" => "remind",
+        "tag" | "set_labels" => "label",
+        "move" | "set_project" | "move_to_project" => "project",
+        "projects" => "list_projects",
+        "rename_project" => "update_project",
         other => other,
     };
     args.insert("action".to_string(), Value::String(action.to_string()));
 
+    if action == "create_project" && !args.contains_key("project_name") {
+        if let Some(name) = args.get("name").cloned() {
+            args.insert("project_name".to_string(), name);
+        }
+    }
+
+    if matches!(
+        action,
+        "reset_checklist" | "checklist_history" | "delete_checklist"
+    ) && !args.contains_key("checklist_id")
+    {
+        if let Some(id) = args.get("id").cloned() {
+            args.insert("checklist_id".to_string(), id);
+        }
+    }
+
     let valid = match action {
         "create" => require_string(&args, "title"),
+        "create_checklist" => {
+            let has_items = args
+                .get("items")
+                .and_then(|value| value.as_array())
+                .map(|items| !items.is_empty())
+                .unwrap_or(false);
+            if !has_items {
+                Err(invalid_args("Missing items"))
+            } else {
+                require_string(&args, "name")
+            }
+        }
+        "reset_checklist" | "checklist_history" | "delete_checklist" => {
+            require_i64(&args, "checklist_id")
+        }
+        "remind" => {
+            let has_target =
+                require_i64(&args, "id").is_ok() || require_string(&args, "title").is_ok();
+            let has_time = ["due_at", "delay_seconds", "in_seconds", "when"]
+                .iter()
+                .any(|key| args.get(*key).map(|value| !value.is_null()).unwrap_or(false));
+            if !has_target {
+                Err(invalid_args("Missing id or title"))
+            } else if !has_time {
+                Err(invalid_args("Missing due_at, delay_seconds or when"))
+            } else {
+                Ok(())
+            }
+        }
         "create_many" => {
             let has_items = args
                 .get("items")
@@ -135,7 +203,15 @@ fn execute_todo(input: &Value) -> Value {
                 Err(invalid_args("Missing items"))
             }
         }
-        "complete" | "reopen" | "delete" => require_i64(&args, "id"),
+        "complete" | "reopen" | "delete" | "label" | "project" => require_i64(&args, "id"),
+        "create_project" => require_string(&args, "project_name"),
+        "update_project" | "delete_project" => {
+            if args.get("project").is_some_and(|value| !value.is_null()) {
+                Ok(())
+            } else {
+                Err(invalid_args("Missing project"))
+            }
+        }
         "reorder" => {
             let has_ids = args
                 .get("ordered_ids")
@@ -148,7 +224,8 @@ fn execute_todo(input: &Value) -> Value {
                 Err(invalid_args("Missing ordered_ids"))
             }
         }
-        "list" | "clear" => Ok(()),
+        "list" | "clear" | "list_checklists" | "trash" | "restore" | "list_projects" => Ok(()),
+        "chart" => require_chart(&args),
         _ => Err(invalid_args("Unsupported action")),
     };
 
@@ -164,7 +241,22 @@ fn execute_todo(input: &Value) -> Value {
         "reopen" => "kv.sqlite.todo.reopen",
         "delete" => "kv.sqlite.todo.delete",
         "clear" => "kv.sqlite.todo.clear",
+        "trash" => "kv.sqlite.todo.trash",
+        "restore" => "kv.sqlite.todo.restore",
         "reorder" => "kv.sqlite.todo.reorder",
+        "create_checklist" => "kv.sqlite.todo.create_checklist",
+        "list_checklists" => "kv.sqlite.todo.list_checklists",
+        "reset_checklist" => "kv.sqlite.todo.reset_checklist",
+        "checklist_history" => "kv.sqlite.todo.checklist_history",
+        "delete_checklist" => "kv.sqlite.todo.delete_checklist",
+        "remind" => "kv.sqlite.todo.remind",
+        "label" => "kv.sqlite.todo.label",
+        "project" => "kv.sqlite.todo.project",
+        "create_project" => "kv.sqlite.todo.create_project",
+        "list_projects" => "kv.sqlite.todo.list_projects",
+        "update_project" => "kv.sqlite.todo.update_project",
+        "delete_project" => "kv.sqlite.todo.delete_project",
+        "chart" => "chart.render",
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -189,6 +281,9 @@ fn execute_tasks(input: &Value) -> Value {
     let action = match action.as_str() {
         "cancel" => "disable".to_string(),
         "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear".to_string(),
+        "undo" | "undo_clear" | "untrash" => "restore".to_string(),
+        "list_trash" => "trash".to_string(),
+        "move" | "set_project" | "move_to_project" => "project".to_string(),
         other => other.to_string(),
     };
     args.insert("action".to_string(), Value::String(action.clone()));
@@ -199,8 +294,8 @@ fn execute_tasks(input: &Value) -> Value {
                 .and_then(|_| require_string(&args, "prompt"))
                 .and_then(|_| require_i64(&args, "run_at"))
         }
-        "cancel" | "disable" | "enable" | "delete" => require_i64(&args, "id"),
-        "list" | "clear" => Ok(()),
+        "cancel" | "disable" | "enable" | "delete" | "project" => require_i64(&args, "id"),
+        "list" | "clear" | "trash" | "restore" => Ok(()),
         _ => Err(invalid_args("Unsupported action")),
     };
 
@@ -215,6 +310,9 @@ fn execute_tasks(input: &Value) -> Value {
         "disable" => "kv.sqlite.tasks.disable",
         "delete" => "kv.sqlite.tasks.delete",
         "clear" => "kv.sqlite.tasks.clear",
+        "trash" => "kv.sqlite.tasks.trash",
+        "restore" => "kv.sqlite.tasks.restore",
+        "project" => "kv.sqlite.tasks.project",
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -242,13 +340,24 @@ fn execute_reminders(input: &Value) -> Value {
         "done" | "finish" => "complete",
         "remove" | "erase" => "delete",
         "clear" | "clear_all" | "clear_reminders" => "clear",
+        "undo" | "undo_clear" | "untrash" => "restore",
+        "list_trash" => "trash",
+        "set_window" | "delivery_window" => "set_delivery_window",
+        "prioritize" | "priority" => "set_priority",
+        "tag" | "set_labels" => "label",
+        "move" | "set_project" | "move_to_project" => "project",
         other => other,
     };
     args.insert("action".to_string(), Value::String(action.to_string()));
 
     let valid = match action {
         "create" => require_string(&args, "title"),
-        "complete" | "delete" => require_i64(&args, "id"),
+        "complete" | "delete" | "set_delivery_window" | "label" | "project" => {
+            require_i64(&args, "id")
+        }
+        "set_priority" => {
+            require_i64(&args, "id").and_then(|_| require_string(&args, "priority"))
+        }
         "snooze" => {
             require_i64(&args, "id").and_then(|_| {
                 let has_due = args
@@ -270,7 +379,7 @@ fn execute_reminders(input: &Value) -> Value {
                 }
             })
         }
-        "list" | "clear" => Ok(()),
+        "list" | "clear" | "trash" | "restore" => Ok(()),
         _ => Err(invalid_args("Unsupported action")),
     };
 
@@ -285,6 +394,12 @@ fn execute_reminders(input: &Value) -> Value {
         "delete" => "kv.sqlite.reminders.delete",
         "snooze" => "kv.sqlite.reminders.snooze",
         "clear" => "kv.sqlite.reminders.clear",
+        "trash" => "kv.sqlite.reminders.trash",
+        "restore" => "kv.sqlite.reminders.restore",
+        "set_delivery_window" => "kv.sqlite.reminders.set_delivery_window",
+        "set_priority" => "kv.sqlite.reminders.set_priority",
+        "label" => "kv.sqlite.reminders.label",
+        "project" => "kv.sqlite.reminders.project",
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -308,6 +423,21 @@ fn execute_planning(input: &Value) -> Value {
         .to_string();
     let action = match raw_action.as_str() {
         "clear_all" | "delete_all" | "remove_all" | "wipe" | "clean" => "clear".to_string(),
+        "undo" | "undo_clear" | "untrash" => "restore".to_string(),
+        "list_trash" => "trash".to_string(),
+        "accept" => "approve".to_string(),
+        "decline" => "reject".to_string(),
+        "tag" | "set_labels" => "label".to_string(),
+        "move" | "set_project" | "move_to_project" => "project".to_string(),
+        "ask_human" | "question" => "ask".to_string(),
+        "set_step_status" | "update_step" | "mark_step" => "step_status".to_string(),
+        "comment" | "add_note" | "add_comment" => "note".to_string(),
+        "create_template" | "save_template" => "template_create".to_string(),
+        "list_templates" | "templates" => "template_list".to_string(),
+        "get_template" => "template_get".to_string(),
+        "update_template" => "template_update".to_string(),
+        "delete_template" => "template_delete".to_string(),
+        "instantiate" | "from_template" | "use_template" => "instantiate_template".to_string(),
         other => other.to_string(),
     };
     let mut args = args;
@@ -315,8 +445,24 @@ fn execute_planning(input: &Value) -> Value {
 
     let valid = match action.as_str() {
         "create" => require_string(&args, "title").and_then(|_| require_string(&args, "goal")),
-        "get" | "update" | "delete" => require_i64(&args, "id"),
-        "list" | "clear" => Ok(()),
+        "step_status" => require_i64(&args, "id")
+            .and_then(|_| require_i64(&args, "step"))
+            .and_then(|_| require_string(&args, "status")),
+        "template_create" => require_string(&args, "name")
+            .and_then(|_| require_string(&args, "title"))
+            .and_then(|_| require_string(&args, "goal")),
+        "template_update" | "template_delete" => require_i64(&args, "id"),
+        "template_get" | "instantiate_template" => {
+            require_i64(&args, "id").or_else(|_| require_string(&args, "name"))
+        }
+        "template_list" => Ok(()),
+        "ask" => require_string(&args, "question"),
+        "note" => require_string(&args, "item_ref").and_then(|_| require_string(&args, "text")),
+        "get" | "update" | "delete" | "negotiate" | "approve" | "reject" | "label" | "project" => {
+            require_i64(&args, "id")
+        }
+        "list" | "clear" | "trash" | "restore" => Ok(()),
+        "chart" => require_chart(&args),
         _ => Err(invalid_args("Unsupported action")),
     };
 
@@ -331,6 +477,23 @@ fn execute_planning(input: &Value) -> Value {
         "update" => "kv.sqlite.planning.update",
         "delete" => "kv.sqlite.planning.delete",
         "clear" => "kv.sqlite.planning.clear",
+        "trash" => "kv.sqlite.planning.trash",
+        "restore" => "kv.sqlite.planning.restore",
+        "chart" => "chart.render",
+        "negotiate" => "kv.sqlite.planning.negotiate",
+        "approve" => "kv.sqlite.planning.approve",
+        "reject" => "kv.sqlite.planning.reject",
+        "label" => "kv.sqlite.planning.label",
+        "project" => "kv.sqlite.planning.project",
+        "ask" => "kv.sqlite.planning.ask",
+        "step_status" => "kv.sqlite.planning.step_status",
+        "note" => "kv.sqlite.planning.note",
+        "template_create" => "kv.sqlite.planning.template_create",
+        "template_list" => "kv.sqlite.planning.template_list",
+        "template_get" => "kv.sqlite.planning.template_get",
+        "template_update" => "kv.sqlite.planning.template_update",
+        "template_delete" => "kv.sqlite.planning.template_delete",
+        "instantiate_template" => "kv.sqlite.planning.instantiate_template",
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -386,11 +549,30 @@ fn execute_coding(input: &Value) -> Value {
         Err(err) => return err,
     };
 
-    if let Err(err) = require_string(&args, "prompt") {
-        return err;
+    let action = args
+        .get("action")
+        .and_then(|value| value.as_str())
+        .unwrap_or("generate")
+        .to_string();
+
+    let (capability, required) = match action.as_str() {
+        "generate" => ("coding.generate", Some("prompt")),
+        "apply_patch" => ("coding.apply_patch", Some("patch")),
+        "run" => ("coding.run", Some("command")),
+        "git_status" => ("git.status", None),
+        "git_diff" => ("git.diff", None),
+        "git_branch" => ("git.branch", None),
+        "git_commit" => ("git.commit", Some("message")),
+        "git_push" => ("git.push", Some("branch")),
+        _ => return invalid_args("Unsupported action"),
+    };
+    if let Some(required) = required {
+        if let Err(err) = require_string(&args, required) {
+            return err;
+        }
     }
 
-    capability_call("coding.generate", Value::Object(args))
+    capability_call(capability, Value::Object(args))
 }
 
 fn execute_http_call(input: &Value) -> Value {
@@ -478,6 +660,13 @@ fn execute_zapier(input: &Value) -> Value {
             }
             "zapier.call_tool"
         }
+        "list_zaps" => "zapier.list_zaps",
+        "trigger_zap" => {
+            if let Err(err) = require_string(&args, "zap") {
+                return err;
+            }
+            "zapier.trigger_zap"
+        }
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -490,11 +679,23 @@ fn execute_search_internet(input: &Value) -> Value {
         Err(err) => return err,
     };
 
-    if let Err(err) = require_string(&args, "query") {
-        return err;
+    let capability = match args.get("action").and_then(|v| v.as_str()) {
+        None | Some("search") => "search.internet",
+        Some("fetch_and_summarize") => {
+            if let Err(err) = require_string(&args, "url") {
+                return err;
+            }
+            "search.fetch_and_summarize"
+        }
+        Some(_) => return invalid_args("Unsupported action"),
+    };
+    if capability == "search.internet" {
+        if let Err(err) = require_string(&args, "query") {
+            return err;
+        }
     }
 
-    capability_call("search.internet", Value::Object(args))
+    capability_call(capability, Value::Object(args))
 }
 
 fn sol_to_lamports(amount_sol: f64) -> Option<u64> {
@@ -748,6 +949,7 @@ fn execute_solana(input: &Value) -> Value {
                 })
         }
         "tx_status" => require_string(&args, "signature"),
+        "chart" => require_chart(&args),
         "tx_history" => {
             let has_address = args
                 .get("address")
@@ -779,6 +981,7 @@ fn execute_solana(input: &Value) -> Value {
         "simulate_transfer" => "solana.simulate_transfer",
         "tx_status" => "solana.tx_status",
         "tx_history" => "solana.tx_history",
+        "chart" => "chart.render",
         _ => return invalid_args("Unsupported action"),
     };
 
@@ -860,6 +1063,77 @@ mod tests {
         );
     }
 
+    #[test]
+    fn label_actions_map_per_store() {
+        for (tool, capability) in [
+            ("todo", "kv.sqlite.todo.label"),
+            ("reminders", "kv.sqlite.reminders.label"),
+            ("planning", "kv.sqlite.planning.label"),
+        ] {
+            let output = execute_for_tool(
+                tool,
+                &json!({"action":"tag","user_id":"u1","id":3,"labels":["work"]}),
+            );
+            assert_eq!(output["capability_call"]["name"].as_str(), Some(capability));
+            assert_eq!(output["capability_call"]["args"]["action"].as_str(), Some("label"));
+        }
+        let missing_id = execute_for_tool(
+            "todo",
+            &json!({"action":"label","user_id":"u1","labels":["work"]}),
+        );
+        assert_eq!(missing_id["status"].as_str(), Some("error"));
+    }
+
+    #[test]
+    fn project_actions_map_per_store() {
+        for (tool, capability) in [
+            ("todo", "kv.sqlite.todo.project"),
+            ("tasks", "kv.sqlite.tasks.project"),
+            ("reminders", "kv.sqlite.reminders.project"),
+            ("planning", "kv.sqlite.planning.project"),
+        ] {
+            let output = execute_for_tool(
+                tool,
+                &json!({"action":"move","user_id":"u1","id":3,"project":"Launch"}),
+            );
+            assert_eq!(output["capability_call"]["name"].as_str(), Some(capability));
+            assert_eq!(output["capability_call"]["args"]["action"].as_str(), Some("project"));
+        }
+
+        let create = execute_for_tool(
+            "todo",
+            &json!({"action":"create_project","user_id":"u1","name":"Launch"}),
+        );
+        assert_eq!(
+            create["capability_call"]["name"].as_str(),
+            Some("kv.sqlite.todo.create_project")
+        );
+        assert_eq!(
+            create["capability_call"]["args"]["project_name"].as_str(),
+            Some("Launch")
+        );
+        let listed = execute_for_tool("todo", &json!({"action":"projects","user_id":"u1"}));
+        assert_eq!(
+            listed["capability_call"]["name"].as_str(),
+            Some("kv.sqlite.todo.list_projects")
+        );
+        let missing = execute_for_tool("todo", &json!({"action":"delete_project","user_id":"u1"}));
+        assert_eq!(missing["status"].as_str(), Some("error"));
+    }
+
+    #[test]
+    fn chart_action_maps_to_chart_render_capability() {
+        let output = execute_for_tool(
+            "planning",
+            &json!({"action":"chart","user_id":"u1","chart":{"kind":"burndown","series":[{"points":[{"x":"d1","y":3}]}]}}),
+        );
+        assert_eq!(output["status"].as_str(), Some("capability_call"));
+        assert_eq!(output["capability_call"]["name"].as_str(), Some("chart.render"));
+
+        let output = execute_for_tool("solana", &json!({"action":"chart","user_id":"u1"}));
+        assert_eq!(output["status"].as_str(), Some("error"));
+    }
+
     #[test]
     fn solana_transfer_uses_capability_call() {
         let output = execute_for_tool(
@@ -996,6 +1270,20 @@ mod tests {
         let coding = execute_for_tool("coding", &json!({"prompt":"hi"}));
         assert_eq!(coding["status"].as_str(), Some("capability_call"));
         assert_eq!(coding["capability_call"]["name"], "coding.generate");
+        let patch = execute_for_tool(
+            "coding",
+            &json!({"action":"apply_patch","patch":"--- a/x\n+++ b/x\n","dry_run":true}),
+        );
+        assert_eq!(patch["capability_call"]["name"], "coding.apply_patch");
+        let run = execute_for_tool(
+            "coding",
+            &json!({"action":"run","command":"cargo","args":["test"]}),
+        );
+        assert_eq!(run["capability_call"]["name"], "coding.run");
+        let push = execute_for_tool("coding", &json!({"action":"git_push"}));
+        assert_eq!(push["status"].as_str(), Some("error"));
+        let status = execute_for_tool("coding", &json!({"action":"git_status"}));
+        assert_eq!(status["capability_call"]["name"], "git.status");
 
         let github = execute_for_tool("github", &json!({"action":"list_tools"}));
         assert_eq!(github["status"].as_str(), Some("capability_call"));
@@ -1004,10 +1292,22 @@ mod tests {
         let zapier = execute_for_tool("zapier", &json!({"action":"list_tools"}));
         assert_eq!(zapier["status"].as_str(), Some("capability_call"));
         assert_eq!(zapier["capability_call"]["name"], "zapier.list_tools");
+        let trigger = execute_for_tool(
+            "zapier",
+            &json!({"action":"trigger_zap","zap":"new_lead","payload":{"email":"a@b.c"}}),
+        );
+        assert_eq!(trigger["capability_call"]["name"], "zapier.trigger_zap");
+        let missing_zap = execute_for_tool("zapier", &json!({"action":"trigger_zap"}));
+        assert_eq!(missing_zap["code"].as_str(), Some("invalid_args"));
 
         let search = execute_for_tool("search_internet", &json!({"query":"rust"}));
         assert_eq!(search["status"].as_str(), Some("capability_call"));
         assert_eq!(search["capability_call"]["name"], "search.internet");
+        let fetch = execute_for_tool(
+            "search_internet",
+            &json!({"action":"fetch_and_summarize","url":"https://example.com/a"}),
+        );
+        assert_eq!(fetch["capability_call"]["name"], "search.fetch_and_summarize");
     }
 
     #[test]